@@ -0,0 +1,242 @@
+//! Python bindings for the Token ACL preflight and decoder utilities
+//!
+//! Compliance teams script their audits in Python. The `token_acl` module
+//! runs the suite's own Rust code rather than a port of it, so a script and
+//! the suite cannot disagree about an address or a decision:
+//!
+//! - the sRFC 37 discriminators and the PDAs gates and the FAMP derive
+//! - `holder_status`, the [`HolderStatus`] preflight over accounts the
+//!   script fetched itself
+//! - `decode_transaction`, the client's [`Decoder`] over a `getTransaction`
+//!   response
+//!
+//! Addresses cross the boundary as base58 strings and account data as
+//! `bytes`; malformed input raises `ValueError`. Build and install the
+//! module into the current environment with
+//!
+//! ```sh
+//! pip install ./bindings/python
+//! ```
+//!
+//! `tests/test_parity.py` checks the module against cases the Rust
+//! `python_parity` test records.
+
+use std::{collections::HashMap, str::FromStr};
+
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyBytes, PyDict},
+};
+use solana_program::pubkey::Pubkey;
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use token_acl_client::{
+    decoder::{Decoder, ProgramKind},
+    instructions,
+    status::{Gates, HolderStatus, StatusError, StatusSource},
+};
+use token_acl_interface as interface;
+
+/// An address and its bump, as Python sees a PDA
+type Pda = (String, u8);
+
+fn parse_pubkey(address: &str) -> PyResult<Pubkey> {
+    Pubkey::from_str(address)
+        .map_err(|e| PyValueError::new_err(format!("Invalid address '{}': {}", address, e)))
+}
+
+fn to_pda((address, bump): (Pubkey, u8)) -> Pda {
+    (address.to_string(), bump)
+}
+
+/// Thaw extra-account-metas PDA of `mint` under `gate_program_id`
+#[pyfunction]
+fn find_thaw_extra_account_metas_address(mint: &str, gate_program_id: &str) -> PyResult<Pda> {
+    Ok(to_pda(interface::find_thaw_extra_account_metas_address(
+        &parse_pubkey(mint)?,
+        &parse_pubkey(gate_program_id)?,
+    )))
+}
+
+/// Freeze extra-account-metas PDA of `mint` under `gate_program_id`
+#[pyfunction]
+fn find_freeze_extra_account_metas_address(mint: &str, gate_program_id: &str) -> PyResult<Pda> {
+    Ok(to_pda(interface::find_freeze_extra_account_metas_address(
+        &parse_pubkey(mint)?,
+        &parse_pubkey(gate_program_id)?,
+    )))
+}
+
+/// Missing record policy PDA of `mint` under `gate_program_id`
+#[pyfunction]
+fn find_missing_record_policy_address(mint: &str, gate_program_id: &str) -> PyResult<Pda> {
+    Ok(to_pda(interface::find_missing_record_policy_address(
+        &parse_pubkey(mint)?,
+        &parse_pubkey(gate_program_id)?,
+    )))
+}
+
+/// FAMP mint config PDA of `mint`
+#[pyfunction]
+fn find_mint_config_address(mint: &str, famp_program_id: &str) -> PyResult<Pda> {
+    Ok(to_pda(instructions::find_mint_config_address(
+        &parse_pubkey(mint)?,
+        &parse_pubkey(famp_program_id)?,
+    )))
+}
+
+/// `owner`'s allow list record PDA for `mint` under `gate_program_id`
+#[pyfunction]
+fn allow_list_address(gate_program_id: &str, mint: &str, owner: &str) -> PyResult<Pda> {
+    let pda = gate_view::allow_list_address(
+        &parse_pubkey(gate_program_id)?,
+        &parse_pubkey(mint)?,
+        &parse_pubkey(owner)?,
+    );
+    Ok(to_pda((pda.address(), pda.bump())))
+}
+
+/// `owner`'s block list record PDA for `mint` under `gate_program_id`
+#[pyfunction]
+fn block_list_address(gate_program_id: &str, mint: &str, owner: &str) -> PyResult<Pda> {
+    let pda = gate_view::block_list_address(
+        &parse_pubkey(gate_program_id)?,
+        &parse_pubkey(mint)?,
+        &parse_pubkey(owner)?,
+    );
+    Ok(to_pda((pda.address(), pda.bump())))
+}
+
+/// The accounts a script fetched, served from memory
+struct FetchedAccounts {
+    accounts: HashMap<Pubkey, (Pubkey, Vec<u8>)>,
+    token_accounts: Vec<(Pubkey, Vec<u8>)>,
+}
+
+impl StatusSource for FetchedAccounts {
+    async fn account(&self, address: &Pubkey) -> Result<Option<(Pubkey, Vec<u8>)>, StatusError> {
+        Ok(self.accounts.get(address).cloned())
+    }
+
+    async fn token_accounts(
+        &self,
+        _mint: &Pubkey,
+        _owner: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Vec<u8>)>, StatusError> {
+        // `HolderStatus::query` drops accounts of other mints and owners
+        Ok(self.token_accounts.clone())
+    }
+}
+
+/// The status of `owner` for `mint` at `unix_timestamp`
+///
+/// `accounts` maps addresses to their owning program and data, and must
+/// hold the owner's record PDAs where they exist; `token_accounts` pairs
+/// addresses with the data of the owner's Token-2022 accounts. Returns a
+/// dict with `allow_listed` and `blocked` (`None` without that gate),
+/// `token_accounts` and `render`, the text `token-acl status` prints.
+#[pyfunction]
+#[pyo3(signature = (mint, owner, accounts, token_accounts, unix_timestamp, allow_list=None, block_list=None))]
+#[allow(clippy::too_many_arguments)]
+fn holder_status<'py>(
+    py: Python<'py>,
+    mint: &str,
+    owner: &str,
+    accounts: HashMap<String, (String, Vec<u8>)>,
+    token_accounts: Vec<(String, Vec<u8>)>,
+    unix_timestamp: i64,
+    allow_list: Option<&str>,
+    block_list: Option<&str>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let source = FetchedAccounts {
+        accounts: accounts
+            .into_iter()
+            .map(|(address, (program, data))| {
+                Ok((parse_pubkey(&address)?, (parse_pubkey(&program)?, data)))
+            })
+            .collect::<PyResult<_>>()?,
+        token_accounts: token_accounts
+            .into_iter()
+            .map(|(address, data)| Ok((parse_pubkey(&address)?, data)))
+            .collect::<PyResult<_>>()?,
+    };
+    let gates = Gates {
+        allow_list: allow_list.map(parse_pubkey).transpose()?,
+        block_list: block_list.map(parse_pubkey).transpose()?,
+    };
+    let status = futures::executor::block_on(HolderStatus::query(
+        &source,
+        gates,
+        &parse_pubkey(mint)?,
+        &parse_pubkey(owner)?,
+        unix_timestamp,
+    ))
+    .map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+    let result = PyDict::new(py);
+    result.set_item("mint", status.mint.to_string())?;
+    result.set_item("owner", status.owner.to_string())?;
+    result.set_item("allow_listed", status.allow_listed())?;
+    result.set_item("blocked", status.blocked())?;
+    let token_accounts = status
+        .token_accounts
+        .iter()
+        .map(|account| {
+            let entry = PyDict::new(py);
+            entry.set_item("address", account.address.to_string())?;
+            entry.set_item("amount", account.amount)?;
+            entry.set_item("frozen", account.frozen)?;
+            Ok(entry)
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    result.set_item("token_accounts", token_accounts)?;
+    result.set_item("unix_timestamp", status.unix_timestamp)?;
+    result.set_item("render", status.render())?;
+    Ok(result)
+}
+
+/// Decode `transaction`, the JSON `getTransaction` returns with the
+/// `base64` encoding, into the decoder's JSON
+///
+/// `programs` maps the program ids of this repository's gates to their
+/// kind, as `ProgramKind` serializes it (`"block_list"`,
+/// `"production_allow_list"`, ...).
+#[pyfunction]
+fn decode_transaction(transaction: &str, programs: HashMap<String, String>) -> PyResult<String> {
+    let mut decoder = Decoder::new();
+    for (program_id, kind) in programs {
+        let kind: ProgramKind = serde_json::from_value(serde_json::Value::String(kind.clone()))
+            .map_err(|_| PyValueError::new_err(format!("Unknown program kind '{}'", kind)))?;
+        decoder.register(parse_pubkey(&program_id)?, kind);
+    }
+    let confirmed: EncodedConfirmedTransactionWithStatusMeta = serde_json::from_str(transaction)
+        .map_err(|e| PyValueError::new_err(format!("Invalid transaction: {}", e)))?;
+    let decoded = decoder
+        .decode_confirmed(&confirmed)
+        .map_err(PyValueError::new_err)?;
+    Ok(decoded.to_json().to_string())
+}
+
+#[pymodule]
+fn token_acl(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add(
+        "CAN_THAW_PERMISSIONLESS_DISCRIMINATOR",
+        PyBytes::new(m.py(), &interface::CAN_THAW_PERMISSIONLESS_DISCRIMINATOR),
+    )?;
+    m.add(
+        "CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR",
+        PyBytes::new(m.py(), &interface::CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR),
+    )?;
+    m.add_function(wrap_pyfunction!(find_thaw_extra_account_metas_address, m)?)?;
+    m.add_function(wrap_pyfunction!(
+        find_freeze_extra_account_metas_address,
+        m
+    )?)?;
+    m.add_function(wrap_pyfunction!(find_missing_record_policy_address, m)?)?;
+    m.add_function(wrap_pyfunction!(find_mint_config_address, m)?)?;
+    m.add_function(wrap_pyfunction!(allow_list_address, m)?)?;
+    m.add_function(wrap_pyfunction!(block_list_address, m)?)?;
+    m.add_function(wrap_pyfunction!(holder_status, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_transaction, m)?)?;
+    Ok(())
+}
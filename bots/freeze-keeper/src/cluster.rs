@@ -0,0 +1,193 @@
+//! What the keeper reads from and submits to
+//!
+//! [`Cluster`] is the handful of queries a scan needs. [`RpcCluster`] serves
+//! them over JSON-RPC; the localnet tests serve them from a program-test
+//! bank.
+
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    client_error::ClientError,
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{
+    account::from_account, clock::Clock, commitment_config::CommitmentConfig, hash::Hash,
+    pubkey::Pubkey, signature::Signature, sysvar, transaction::Transaction,
+    transaction::TransactionError,
+};
+
+use crate::KeeperError;
+
+/// Sizes of a block list record, written before blocks could expire,
+/// without and with an expiry, as `gate_view` lays it out
+const BLOCK_LIST_RECORD_LENS: [u64; 3] = [
+    gate_view::BLOCK_LIST_LEGACY_RECORD_LEN as u64,
+    gate_view::BLOCK_LIST_RECORD_LEN as u64,
+    gate_view::BLOCK_LIST_RECORD_WITH_EXPIRY_LEN as u64,
+];
+
+/// Offsets of the mint and owner in a token account
+const TOKEN_ACCOUNT_MINT_OFFSET: usize = 0;
+const TOKEN_ACCOUNT_OWNER_OFFSET: usize = 32;
+
+#[allow(async_fn_in_trait)]
+pub trait Cluster {
+    /// Address and data of every block list record `gate` holds for `mint`
+    async fn block_list_records(
+        &self,
+        gate: &Pubkey,
+        mint: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Vec<u8>)>, KeeperError>;
+
+    /// Address and data of every Token-2022 account of `mint` owned by
+    /// `owner`
+    async fn token_accounts(
+        &self,
+        mint: &Pubkey,
+        owner: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Vec<u8>)>, KeeperError>;
+
+    /// Data of the account at `address`, or `None` if there is none
+    async fn account_data(&self, address: &Pubkey) -> Result<Option<Vec<u8>>, KeeperError>;
+
+    /// The cluster's clock, which block expiries are judged by
+    async fn unix_timestamp(&self) -> Result<i64, KeeperError>;
+
+    async fn latest_blockhash(&self) -> Result<Hash, KeeperError>;
+
+    /// Submit `transaction` and wait for it to be confirmed
+    async fn send_and_confirm(&self, transaction: &Transaction) -> Result<Signature, KeeperError>;
+}
+
+/// Classify a failed submission: a transaction the cluster executed and
+/// rejected fails the same way again, while a dropped request or an expired
+/// blockhash may land on a retry
+pub fn submission_error(message: String, rejected: Option<&TransactionError>) -> KeeperError {
+    let retryable = matches!(rejected, None | Some(TransactionError::BlockhashNotFound));
+    KeeperError::Transaction { message, retryable }
+}
+
+/// A cluster reached over JSON-RPC
+pub struct RpcCluster {
+    rpc: RpcClient,
+}
+
+impl RpcCluster {
+    /// Connect to `url` at `confirmed` commitment
+    pub fn new(url: &str) -> Self {
+        Self::from_client(RpcClient::new_with_commitment(
+            url.to_string(),
+            CommitmentConfig::confirmed(),
+        ))
+    }
+
+    pub fn from_client(rpc: RpcClient) -> Self {
+        Self { rpc }
+    }
+
+    async fn program_accounts(
+        &self,
+        program: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Vec<u8>)>, KeeperError> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(filters),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(self.rpc.commitment()),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+        let accounts = self
+            .rpc
+            .get_program_accounts_with_config(program, config)
+            .await
+            .map_err(rpc_error)?;
+        Ok(accounts
+            .into_iter()
+            .map(|(address, account)| (address, account.data))
+            .collect())
+    }
+}
+
+impl Cluster for RpcCluster {
+    async fn block_list_records(
+        &self,
+        gate: &Pubkey,
+        mint: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Vec<u8>)>, KeeperError> {
+        // A filter matches one size, so each record layout is its own query
+        let mut records = Vec::new();
+        for len in BLOCK_LIST_RECORD_LENS {
+            records.extend(
+                self.program_accounts(
+                    gate,
+                    vec![
+                        RpcFilterType::DataSize(len),
+                        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, mint.as_ref())),
+                    ],
+                )
+                .await?,
+            );
+        }
+        Ok(records)
+    }
+
+    async fn token_accounts(
+        &self,
+        mint: &Pubkey,
+        owner: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Vec<u8>)>, KeeperError> {
+        self.program_accounts(
+            &spl_token_2022::id(),
+            vec![
+                RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                    TOKEN_ACCOUNT_MINT_OFFSET,
+                    mint.as_ref(),
+                )),
+                RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                    TOKEN_ACCOUNT_OWNER_OFFSET,
+                    owner.as_ref(),
+                )),
+            ],
+        )
+        .await
+    }
+
+    async fn account_data(&self, address: &Pubkey) -> Result<Option<Vec<u8>>, KeeperError> {
+        let response = self
+            .rpc
+            .get_account_with_commitment(address, self.rpc.commitment())
+            .await
+            .map_err(rpc_error)?;
+        Ok(response.value.map(|account| account.data))
+    }
+
+    async fn unix_timestamp(&self) -> Result<i64, KeeperError> {
+        let account = self
+            .rpc
+            .get_account(&sysvar::clock::id())
+            .await
+            .map_err(rpc_error)?;
+        let clock: Clock = from_account(&account)
+            .ok_or_else(|| KeeperError::Rpc("Malformed clock sysvar".to_string()))?;
+        Ok(clock.unix_timestamp)
+    }
+
+    async fn latest_blockhash(&self) -> Result<Hash, KeeperError> {
+        self.rpc.get_latest_blockhash().await.map_err(rpc_error)
+    }
+
+    async fn send_and_confirm(&self, transaction: &Transaction) -> Result<Signature, KeeperError> {
+        self.rpc
+            .send_and_confirm_transaction(transaction)
+            .await
+            .map_err(|e| submission_error(e.to_string(), e.get_transaction_error().as_ref()))
+    }
+}
+
+fn rpc_error(error: ClientError) -> KeeperError {
+    KeeperError::Rpc(error.to_string())
+}
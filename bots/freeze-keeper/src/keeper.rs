@@ -0,0 +1,288 @@
+//! The scan-and-freeze loop
+
+use std::{collections::BTreeSet, time::Duration};
+
+use gate_view::BlockListView;
+use solana_sdk::{
+    instruction::Instruction,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use spl_tlv_account_resolution::state::AccountFetchError;
+use spl_token_2022::{
+    extension::StateWithExtensions,
+    state::{Account as TokenAccount, AccountState},
+};
+use token_acl_client::instructions::{self, FreezeAuthority};
+
+use crate::{cluster::Cluster, metrics::KeeperMetrics, KeeperError};
+
+/// What the keeper watches and how it submits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeeperConfig {
+    /// Block-list gate of the mint
+    pub gate: Pubkey,
+    pub mint: Pubkey,
+    /// Check-and-freeze pairs per transaction; 8 fit in a packet
+    pub freezes_per_transaction: usize,
+    /// Submissions of one batch before giving up on it
+    pub max_attempts: u32,
+    /// Wait before the first resubmission, doubled for each one after
+    pub retry_backoff: Duration,
+}
+
+impl KeeperConfig {
+    pub fn new(gate: Pubkey, mint: Pubkey) -> Self {
+        Self {
+            gate,
+            mint,
+            freezes_per_transaction: 8,
+            max_attempts: 5,
+            retry_backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+/// What freezing one blocked holder took
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffenderReport {
+    pub offender: Pubkey,
+    /// Token accounts of the mint the holder owns
+    pub token_accounts: usize,
+    /// Of those, frozen before the keeper got to them
+    pub already_frozen: usize,
+    /// Of those, frozen by the keeper
+    pub frozen: usize,
+    /// Freeze transactions confirmed
+    pub transactions: usize,
+}
+
+/// The outcome of one scan
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    /// Newly blocked holders whose accounts are now all frozen
+    pub frozen: Vec<OffenderReport>,
+    /// Newly blocked holders the scan could not finish, retried next scan
+    pub failed: Vec<(Pubkey, KeeperError)>,
+}
+
+pub struct FreezeKeeper<C> {
+    cluster: C,
+    config: KeeperConfig,
+    /// Pays for and calls the freeze checks
+    payer: Keypair,
+    /// Signs the freezes in place of the FAMP PDA
+    freeze_authority: Keypair,
+    /// Blocked holders with every account frozen
+    handled: BTreeSet<Pubkey>,
+    metrics: KeeperMetrics,
+}
+
+impl<C: Cluster> FreezeKeeper<C> {
+    pub fn new(
+        cluster: C,
+        config: KeeperConfig,
+        payer: Keypair,
+        freeze_authority: Keypair,
+    ) -> Self {
+        Self {
+            cluster,
+            config,
+            payer,
+            freeze_authority,
+            handled: BTreeSet::new(),
+            metrics: KeeperMetrics::default(),
+        }
+    }
+
+    pub fn config(&self) -> &KeeperConfig {
+        &self.config
+    }
+
+    pub fn cluster(&self) -> &C {
+        &self.cluster
+    }
+
+    pub fn metrics(&self) -> &KeeperMetrics {
+        &self.metrics
+    }
+
+    /// Blocked holders not yet handled, in address order
+    ///
+    /// Only canonical records of the configured mint count: anything else
+    /// the gate owns at a record size is ignored. So are blocks that have
+    /// lapsed, which the gate no longer freezes for.
+    pub async fn scan(&mut self) -> Result<Vec<Pubkey>, KeeperError> {
+        let records = self
+            .cluster
+            .block_list_records(&self.config.gate, &self.config.mint)
+            .await?;
+        self.metrics.scans += 1;
+        let views: Vec<BlockListView> = records
+            .iter()
+            .filter_map(|(address, data)| {
+                let view = BlockListView::unpack(data).ok()?;
+                let canonical =
+                    gate_view::block_list_address(&self.config.gate, &view.mint, &view.owner);
+                (view.blocked && view.mint == self.config.mint && canonical.address() == *address)
+                    .then_some(view)
+            })
+            .collect();
+        // Only blocks that carry an expiry need the clock
+        let now = if views.iter().any(|view| view.expiry_timestamp.is_some()) {
+            self.cluster.unix_timestamp().await?
+        } else {
+            i64::MIN
+        };
+        let offenders: BTreeSet<Pubkey> = views
+            .iter()
+            .filter(|view| view.is_active(now))
+            .map(|view| view.owner)
+            .filter(|owner| !self.handled.contains(owner))
+            .collect();
+        self.metrics.offenders_detected += offenders.len() as u64;
+        Ok(offenders.into_iter().collect())
+    }
+
+    /// Freeze every thawed token account `offender` holds of the mint
+    pub async fn freeze_offender(
+        &mut self,
+        offender: &Pubkey,
+    ) -> Result<OffenderReport, KeeperError> {
+        let accounts = self
+            .cluster
+            .token_accounts(&self.config.mint, offender)
+            .await?;
+        let thawed: Vec<Pubkey> = accounts
+            .iter()
+            .filter(|(_, data)| is_thawed(data))
+            .map(|(address, _)| *address)
+            .collect();
+        self.metrics.token_accounts_found += accounts.len() as u64;
+        self.metrics.already_frozen += (accounts.len() - thawed.len()) as u64;
+
+        let mut transactions = 0;
+        for batch in thawed.chunks(self.config.freezes_per_transaction.max(1)) {
+            transactions += self.freeze_batch(batch).await?;
+        }
+
+        self.handled.insert(*offender);
+        self.metrics.offenders_frozen += 1;
+        self.metrics.accounts_frozen += thawed.len() as u64;
+        Ok(OffenderReport {
+            offender: *offender,
+            token_accounts: accounts.len(),
+            already_frozen: accounts.len() - thawed.len(),
+            frozen: thawed.len(),
+            transactions,
+        })
+    }
+
+    /// Scan, then freeze the accounts of every newly blocked holder
+    pub async fn run_once(&mut self) -> Result<ScanReport, KeeperError> {
+        let mut report = ScanReport::default();
+        for offender in self.scan().await? {
+            match self.freeze_offender(&offender).await {
+                Ok(frozen) => report.frozen.push(frozen),
+                Err(e) => report.failed.push((offender, e)),
+            }
+        }
+        Ok(report)
+    }
+
+    /// Submit the freezes of `batch` until they land; returns the
+    /// transactions confirmed (0 if every account froze meanwhile)
+    async fn freeze_batch(&mut self, batch: &[Pubkey]) -> Result<usize, KeeperError> {
+        let mut pending = batch.to_vec();
+        let mut attempt = 1;
+        loop {
+            let result = self.submit(&pending).await;
+            match result {
+                Ok(()) => {
+                    self.metrics.transactions_landed += 1;
+                    return Ok(1);
+                }
+                Err(e) if e.is_retryable() && attempt < self.config.max_attempts => {
+                    tokio::time::sleep(self.config.retry_backoff * 2u32.pow(attempt - 1)).await;
+                    attempt += 1;
+                    self.metrics.retries += 1;
+                    // A submission that landed unconfirmed must not be
+                    // frozen again: Token-2022 rejects freezing a frozen
+                    // account
+                    pending = self.still_thawed(&pending).await?;
+                    if pending.is_empty() {
+                        return Ok(0);
+                    }
+                }
+                Err(e) => {
+                    self.metrics.failed_batches += 1;
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    async fn submit(&mut self, batch: &[Pubkey]) -> Result<(), KeeperError> {
+        let mut instructions = Vec::with_capacity(batch.len() * 2);
+        for token_account in batch {
+            instructions.extend(self.freeze_instructions(token_account).await?);
+        }
+        let blockhash = self.cluster.latest_blockhash().await?;
+        let transaction = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&self.payer.pubkey()),
+            &[&self.payer, &self.freeze_authority],
+            blockhash,
+        );
+        self.metrics.transactions_sent += 1;
+        self.cluster.send_and_confirm(&transaction).await?;
+        Ok(())
+    }
+
+    /// `can-freeze-permissionless` for `token_account`, resolved from the
+    /// gate's freeze extra-account-metas, followed by the freeze it
+    /// authorizes
+    async fn freeze_instructions(
+        &self,
+        token_account: &Pubkey,
+    ) -> Result<[Instruction; 2], KeeperError> {
+        let KeeperConfig { gate, mint, .. } = self.config;
+        let check = instructions::resolve_extra_accounts(
+            instructions::permissionless_freeze(&mint, token_account, &self.payer.pubkey(), &gate),
+            |address| async move {
+                self.cluster
+                    .account_data(&address)
+                    .await
+                    .map_err(|e| Box::new(e) as AccountFetchError)
+            },
+        )
+        .await
+        .map_err(|e| KeeperError::Resolve(format!("Freeze check of {}: {}", token_account, e)))?;
+        instructions::authorized(check, &FreezeAuthority::Key(self.freeze_authority.pubkey()))
+            .map_err(|e| KeeperError::Resolve(e.to_string()))
+    }
+
+    async fn still_thawed(&self, accounts: &[Pubkey]) -> Result<Vec<Pubkey>, KeeperError> {
+        let mut thawed = Vec::new();
+        for address in accounts {
+            if let Some(data) = self.cluster.account_data(address).await? {
+                if is_thawed(&data) {
+                    thawed.push(*address);
+                }
+            }
+        }
+        Ok(thawed)
+    }
+}
+
+/// Whether `data` is an initialized token account that is not frozen
+fn is_thawed(data: &[u8]) -> bool {
+    let state = if data.len() == TokenAccount::LEN {
+        TokenAccount::unpack(data).map(|account| account.state)
+    } else {
+        StateWithExtensions::<TokenAccount>::unpack(data).map(|account| account.base.state)
+    };
+    state == Ok(AccountState::Initialized)
+}
@@ -0,0 +1,70 @@
+//! Freeze keeper
+//!
+//! Reference keeper bot for block-list gates. Each scan reads the gate's
+//! block list records for one mint, and for every holder newly listed as
+//! blocked it enumerates their token accounts of the mint and freezes each
+//! one still thawed. Every freeze is preceded by the gate's
+//! `can-freeze-permissionless` check, resolved from the gate's freeze
+//! extra-account-metas, in the same transaction, so a freeze only lands if
+//! the gate authorizes it at that moment.
+//!
+//! Freezes are batched [`KeeperConfig::freezes_per_transaction`] to a
+//! transaction. A batch whose submission fails in a way resubmitting could
+//! fix (a dropped request, an expired blockhash) is retried with backoff,
+//! first dropping any account that froze meanwhile, so a submission that
+//! landed without being confirmed is never frozen twice. A holder counts as
+//! handled once all their accounts are frozen; one that failed is picked up
+//! again by the next scan.
+//!
+//! The FAMP program is not part of this suite, so the freeze itself is a
+//! Token-2022 `FreezeAccount` signed by the configured freeze authority,
+//! standing in for the FAMP's PDA as in the suite's tests.
+
+pub mod cluster;
+pub mod keeper;
+pub mod metrics;
+
+pub use cluster::{Cluster, RpcCluster};
+pub use keeper::{FreezeKeeper, KeeperConfig, OffenderReport, ScanReport};
+pub use metrics::KeeperMetrics;
+
+/// Why a keeper operation failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeeperError {
+    /// Reading from the cluster failed
+    Rpc(String),
+    /// The freeze check could not be resolved from the gate's
+    /// extra-account-metas
+    Resolve(String),
+    /// A transaction did not land; `retryable` if resubmitting it could
+    Transaction { message: String, retryable: bool },
+}
+
+impl KeeperError {
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            KeeperError::Transaction {
+                retryable: true,
+                ..
+            }
+        )
+    }
+}
+
+impl std::fmt::Display for KeeperError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeeperError::Rpc(message) => write!(f, "RPC error: {}", message),
+            KeeperError::Resolve(message) => write!(f, "Resolve error: {}", message),
+            KeeperError::Transaction { message, retryable } => write!(
+                f,
+                "Transaction error{}: {}",
+                if *retryable { " (retryable)" } else { "" },
+                message
+            ),
+        }
+    }
+}
+
+impl std::error::Error for KeeperError {}
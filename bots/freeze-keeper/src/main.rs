@@ -0,0 +1,142 @@
+//! freeze-keeper
+//!
+//! ```text
+//! freeze-keeper --rpc-url <URL> --gate <PUBKEY> --mint <PUBKEY>
+//!               --payer <KEYPAIR> --freeze-authority <KEYPAIR>
+//!               [--interval-secs <N>] [--batch-size <N>] [--metrics <PATH>] [--once]
+//! ```
+//!
+//! Scans every `--interval-secs` (default 10) until killed, or once with
+//! `--once`. With `--metrics`, the counters are written to `<PATH>` after
+//! every scan.
+
+use std::{process::ExitCode, str::FromStr, time::Duration};
+
+use freeze_keeper::{FreezeKeeper, KeeperConfig, RpcCluster};
+use solana_sdk::{pubkey::Pubkey, signature::read_keypair_file};
+
+struct Args {
+    rpc_url: String,
+    gate: Pubkey,
+    mint: Pubkey,
+    payer: String,
+    freeze_authority: String,
+    interval: Duration,
+    batch_size: Option<usize>,
+    metrics: Option<String>,
+    once: bool,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut rpc_url = None;
+    let mut gate = None;
+    let mut mint = None;
+    let mut payer = None;
+    let mut freeze_authority = None;
+    let mut interval = Duration::from_secs(10);
+    let mut batch_size = None;
+    let mut metrics = None;
+    let mut once = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        if flag == "--once" {
+            once = true;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", flag))?;
+        match flag.as_str() {
+            "--rpc-url" => rpc_url = Some(value),
+            "--gate" => gate = Some(parse(&flag, &value)?),
+            "--mint" => mint = Some(parse(&flag, &value)?),
+            "--payer" => payer = Some(value),
+            "--freeze-authority" => freeze_authority = Some(value),
+            "--interval-secs" => interval = Duration::from_secs(parse(&flag, &value)?),
+            "--batch-size" => batch_size = Some(parse(&flag, &value)?),
+            "--metrics" => metrics = Some(value),
+            _ => return Err(format!("Unknown flag {}", flag)),
+        }
+    }
+
+    Ok(Args {
+        rpc_url: required(rpc_url, "--rpc-url")?,
+        gate: required(gate, "--gate")?,
+        mint: required(mint, "--mint")?,
+        payer: required(payer, "--payer")?,
+        freeze_authority: required(freeze_authority, "--freeze-authority")?,
+        interval,
+        batch_size,
+        metrics,
+        once,
+    })
+}
+
+fn required<T>(value: Option<T>, flag: &str) -> Result<T, String> {
+    value.ok_or_else(|| format!("{} is required", flag))
+}
+
+fn parse<T: FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid {} value: {}", flag, value))
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let keypair = |path: &str| {
+        read_keypair_file(path).map_err(|e| format!("Reading keypair {} failed: {}", path, e))
+    };
+    let (payer, freeze_authority) = match (keypair(&args.payer), keypair(&args.freeze_authority)) {
+        (Ok(payer), Ok(freeze_authority)) => (payer, freeze_authority),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut config = KeeperConfig::new(args.gate, args.mint);
+    if let Some(batch_size) = args.batch_size {
+        config.freezes_per_transaction = batch_size;
+    }
+    let mut keeper = FreezeKeeper::new(
+        RpcCluster::new(&args.rpc_url),
+        config,
+        payer,
+        freeze_authority,
+    );
+
+    loop {
+        match keeper.run_once().await {
+            Ok(report) => {
+                for frozen in &report.frozen {
+                    println!(
+                        "Froze {} of {} token accounts of {} in {} transactions",
+                        frozen.frozen, frozen.token_accounts, frozen.offender, frozen.transactions
+                    );
+                }
+                for (offender, e) in &report.failed {
+                    eprintln!("Freezing {} failed: {}", offender, e);
+                }
+            }
+            Err(e) => eprintln!("Scan failed: {}", e),
+        }
+        if let Some(path) = &args.metrics {
+            if let Err(e) = std::fs::write(path, keeper.metrics().render_prometheus()) {
+                eprintln!("Writing metrics to {} failed: {}", path, e);
+            }
+        }
+        if args.once {
+            return ExitCode::SUCCESS;
+        }
+        tokio::time::sleep(args.interval).await;
+    }
+}
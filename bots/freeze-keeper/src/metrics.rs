@@ -0,0 +1,96 @@
+//! Keeper counters, rendered in the Prometheus text format
+//!
+//! The binary writes them to a file after every scan, for a node exporter's
+//! textfile collector to pick up.
+
+/// Totals since the keeper started
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeeperMetrics {
+    /// Scans of the block list
+    pub scans: u64,
+    /// Newly blocked holders found by scans
+    pub offenders_detected: u64,
+    /// Blocked holders with every token account frozen
+    pub offenders_frozen: u64,
+    /// Token accounts of blocked holders found
+    pub token_accounts_found: u64,
+    /// Token accounts that were already frozen when found
+    pub already_frozen: u64,
+    /// Token accounts the keeper froze
+    pub accounts_frozen: u64,
+    /// Freeze transactions submitted, retries included
+    pub transactions_sent: u64,
+    /// Freeze transactions confirmed
+    pub transactions_landed: u64,
+    /// Resubmissions after a retryable failure
+    pub retries: u64,
+    /// Batches given up on
+    pub failed_batches: u64,
+}
+
+impl KeeperMetrics {
+    fn counters(&self) -> [(&'static str, &'static str, u64); 10] {
+        [
+            ("scans", "Scans of the block list", self.scans),
+            (
+                "offenders_detected",
+                "Newly blocked holders found",
+                self.offenders_detected,
+            ),
+            (
+                "offenders_frozen",
+                "Blocked holders with every token account frozen",
+                self.offenders_frozen,
+            ),
+            (
+                "token_accounts_found",
+                "Token accounts of blocked holders found",
+                self.token_accounts_found,
+            ),
+            (
+                "already_frozen",
+                "Token accounts already frozen when found",
+                self.already_frozen,
+            ),
+            (
+                "accounts_frozen",
+                "Token accounts the keeper froze",
+                self.accounts_frozen,
+            ),
+            (
+                "transactions_sent",
+                "Freeze transactions submitted, retries included",
+                self.transactions_sent,
+            ),
+            (
+                "transactions_landed",
+                "Freeze transactions confirmed",
+                self.transactions_landed,
+            ),
+            (
+                "retries",
+                "Resubmissions after a retryable failure",
+                self.retries,
+            ),
+            (
+                "failed_batches",
+                "Freeze batches given up on",
+                self.failed_batches,
+            ),
+        ]
+    }
+
+    /// Every counter as `freeze_keeper_<name>_total`, with its help and type
+    /// lines
+    pub fn render_prometheus(&self) -> String {
+        let mut text = String::new();
+        for (name, help, value) in self.counters() {
+            text.push_str(&format!(
+                "# HELP freeze_keeper_{name}_total {help}\n\
+                 # TYPE freeze_keeper_{name}_total counter\n\
+                 freeze_keeper_{name}_total {value}\n"
+            ));
+        }
+        text
+    }
+}
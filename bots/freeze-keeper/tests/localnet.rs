@@ -0,0 +1,479 @@
+//! Freeze keeper against a local bank
+//!
+//! [`BanksCluster`] serves the keeper's queries from a program-test bank
+//! running the block list example. The bank has no account index, so the
+//! fixture registers every record and token account it creates and the
+//! program-account queries filter those, the way the RPC filters do. Sends
+//! can be made to fail before or after the bank processes them, to exercise
+//! the keeper's retries.
+
+use std::sync::{Arc, Mutex};
+
+use borsh::BorshSerialize;
+use example_block_list::{
+    block_list_address, create_block_list_record, initialize_freeze_extra_account_metas,
+    BlockReason,
+};
+use freeze_keeper::{cluster::submission_error, Cluster, FreezeKeeper, KeeperConfig, KeeperError};
+use solana_program_test::{
+    processor, BanksClient, BanksClientError, ProgramTest, ProgramTestContext,
+};
+use solana_sdk::{
+    clock::Clock,
+    hash::Hash,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    transaction::Transaction,
+};
+use spl_token_2022::state::{Account as TokenAccount, AccountState};
+use token_acl_integration_tests::program_test::{mint_account, rent_exempt_account, token_account};
+
+/// A program-test bank behind the keeper's [`Cluster`]
+struct BanksCluster {
+    banks_client: BanksClient,
+    /// Every account the fixture created, standing in for the RPC index
+    registry: Arc<Mutex<Vec<Pubkey>>>,
+    /// Sends to fail before the bank sees them
+    dropped_sends: Mutex<usize>,
+    /// Sends to fail after the bank processed them
+    lost_confirmations: Mutex<usize>,
+    /// Transactions the bank processed
+    processed: Mutex<usize>,
+}
+
+impl BanksCluster {
+    async fn registered(&self, program: &Pubkey) -> Vec<(Pubkey, Vec<u8>)> {
+        let addresses = self.registry.lock().unwrap().clone();
+        let mut accounts = Vec::new();
+        for address in addresses {
+            let account = self.banks_client.clone().get_account(address).await;
+            if let Ok(Some(account)) = account {
+                if account.owner == *program {
+                    accounts.push((address, account.data));
+                }
+            }
+        }
+        accounts
+    }
+
+    /// Take one of the failures queued in `counter`, if any
+    fn take(counter: &Mutex<usize>) -> bool {
+        let mut remaining = counter.lock().unwrap();
+        let take = *remaining > 0;
+        *remaining = remaining.saturating_sub(1);
+        take
+    }
+}
+
+impl Cluster for BanksCluster {
+    async fn block_list_records(
+        &self,
+        gate: &Pubkey,
+        mint: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Vec<u8>)>, KeeperError> {
+        Ok(self
+            .registered(gate)
+            .await
+            .into_iter()
+            .filter(|(_, data)| {
+                [
+                    gate_view::BLOCK_LIST_LEGACY_RECORD_LEN,
+                    gate_view::BLOCK_LIST_RECORD_LEN,
+                    gate_view::BLOCK_LIST_RECORD_WITH_EXPIRY_LEN,
+                ]
+                .contains(&data.len())
+                    && data[..32] == mint.to_bytes()
+            })
+            .collect())
+    }
+
+    async fn token_accounts(
+        &self,
+        mint: &Pubkey,
+        owner: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Vec<u8>)>, KeeperError> {
+        Ok(self
+            .registered(&spl_token_2022::id())
+            .await
+            .into_iter()
+            .filter(|(_, data)| data[..32] == mint.to_bytes() && data[32..64] == owner.to_bytes())
+            .collect())
+    }
+
+    async fn account_data(&self, address: &Pubkey) -> Result<Option<Vec<u8>>, KeeperError> {
+        self.banks_client
+            .clone()
+            .get_account(*address)
+            .await
+            .map(|account| account.map(|a| a.data))
+            .map_err(|e| KeeperError::Rpc(e.to_string()))
+    }
+
+    async fn unix_timestamp(&self) -> Result<i64, KeeperError> {
+        self.banks_client
+            .clone()
+            .get_sysvar::<Clock>()
+            .await
+            .map(|clock| clock.unix_timestamp)
+            .map_err(|e| KeeperError::Rpc(e.to_string()))
+    }
+
+    async fn latest_blockhash(&self) -> Result<Hash, KeeperError> {
+        self.banks_client
+            .clone()
+            .get_latest_blockhash()
+            .await
+            .map_err(|e| KeeperError::Rpc(e.to_string()))
+    }
+
+    async fn send_and_confirm(&self, transaction: &Transaction) -> Result<Signature, KeeperError> {
+        if Self::take(&self.dropped_sends) {
+            return Err(submission_error("Send dropped".to_string(), None));
+        }
+        self.banks_client
+            .clone()
+            .process_transaction(transaction.clone())
+            .await
+            .map_err(|e| {
+                let rejected = match &e {
+                    BanksClientError::TransactionError(err)
+                    | BanksClientError::SimulationError { err, .. } => Some(err.clone()),
+                    _ => None,
+                };
+                submission_error(e.to_string(), rejected.as_ref())
+            })?;
+        *self.processed.lock().unwrap() += 1;
+        if Self::take(&self.lost_confirmations) {
+            return Err(submission_error("Confirmation timed out".to_string(), None));
+        }
+        Ok(transaction.signatures[0])
+    }
+}
+
+struct KeeperFixture {
+    context: ProgramTestContext,
+    gate: Pubkey,
+    mint: Pubkey,
+    issuer: Keypair,
+    registry: Arc<Mutex<Vec<Pubkey>>>,
+}
+
+impl KeeperFixture {
+    async fn new() -> Self {
+        let gate = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let issuer = Keypair::new();
+
+        let mut program_test = ProgramTest::new(
+            "example_block_list",
+            gate,
+            processor!(example_block_list::process_instruction),
+        );
+        program_test.add_account(mint, mint_account(&issuer.pubkey(), 0));
+        let mut context = program_test.start_with_context().await;
+
+        let init = initialize_freeze_extra_account_metas(&gate, &context.payer.pubkey(), &mint);
+        let transaction = Transaction::new_signed_with_payer(
+            &[init],
+            Some(&context.payer.pubkey()),
+            &[&context.payer],
+            context.last_blockhash,
+        );
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .expect("freeze extra-account-metas should initialize");
+
+        Self {
+            context,
+            gate,
+            mint,
+            issuer,
+            registry: Arc::default(),
+        }
+    }
+
+    /// A new holder with `thawed` thawed and `frozen` frozen token accounts
+    fn new_holder(&mut self, thawed: usize, frozen: usize) -> (Pubkey, Vec<Pubkey>) {
+        let holder = Pubkey::new_unique();
+        let states = std::iter::repeat_n(AccountState::Initialized, thawed)
+            .chain(std::iter::repeat_n(AccountState::Frozen, frozen));
+        let accounts: Vec<Pubkey> = states
+            .map(|state| {
+                let address = Pubkey::new_unique();
+                self.context.set_account(
+                    &address,
+                    &token_account(&self.mint, &holder, 1_000, state).into(),
+                );
+                address
+            })
+            .collect();
+        self.registry.lock().unwrap().extend(&accounts);
+        (holder, accounts)
+    }
+
+    /// Put `holder` on the block list
+    fn list(&mut self, holder: &Pubkey) {
+        self.list_until(holder, None);
+    }
+
+    /// Put `holder` on the block list until `expiry_timestamp`, if any
+    fn list_until(&mut self, holder: &Pubkey, expiry_timestamp: Option<i64>) {
+        let mut record = create_block_list_record(&self.mint, holder, BlockReason::Sanctions, 0);
+        record.expiry_timestamp = expiry_timestamp;
+        let address = block_list_address(&self.mint, holder, &self.gate).address();
+        let account = rent_exempt_account(record.try_to_vec().unwrap(), self.gate);
+        self.context.set_account(&address, &account.into());
+        self.registry.lock().unwrap().push(address);
+    }
+
+    fn cluster(&self) -> BanksCluster {
+        BanksCluster {
+            banks_client: self.context.banks_client.clone(),
+            registry: self.registry.clone(),
+            dropped_sends: Mutex::new(0),
+            lost_confirmations: Mutex::new(0),
+            processed: Mutex::new(0),
+        }
+    }
+
+    fn keeper(&self, freeze_authority: &Keypair) -> FreezeKeeper<BanksCluster> {
+        let mut config = KeeperConfig::new(self.gate, self.mint);
+        config.retry_backoff = std::time::Duration::ZERO;
+        FreezeKeeper::new(
+            self.cluster(),
+            config,
+            self.context.payer.insecure_clone(),
+            freeze_authority.insecure_clone(),
+        )
+    }
+
+    async fn states(&mut self, accounts: &[Pubkey]) -> Vec<AccountState> {
+        let mut states = Vec::new();
+        for address in accounts {
+            let account = self
+                .context
+                .banks_client
+                .get_account(*address)
+                .await
+                .unwrap()
+                .unwrap();
+            states.push(TokenAccount::unpack(&account.data).unwrap().state);
+        }
+        states
+    }
+}
+
+#[tokio::test]
+async fn freezes_every_account_of_a_blocked_holder_in_batches() {
+    let mut fixture = KeeperFixture::new().await;
+    let (offender, offender_accounts) = fixture.new_holder(20, 0);
+    let (_, innocent_accounts) = fixture.new_holder(3, 0);
+    fixture.list(&offender);
+
+    let mut keeper = fixture.keeper(&fixture.issuer);
+    let report = keeper.run_once().await.unwrap();
+
+    assert!(report.failed.is_empty(), "{:?}", report.failed);
+    assert_eq!(report.frozen.len(), 1);
+    let frozen = &report.frozen[0];
+    assert_eq!(frozen.offender, offender);
+    assert_eq!(frozen.token_accounts, 20);
+    assert_eq!(frozen.already_frozen, 0);
+    assert_eq!(frozen.frozen, 20);
+    // 8 check-and-freeze pairs to a transaction
+    assert_eq!(frozen.transactions, 3);
+    assert!(fixture
+        .states(&offender_accounts)
+        .await
+        .iter()
+        .all(|state| *state == AccountState::Frozen));
+    assert!(fixture
+        .states(&innocent_accounts)
+        .await
+        .iter()
+        .all(|state| *state == AccountState::Initialized));
+}
+
+#[tokio::test]
+async fn later_scans_skip_handled_holders_and_pick_up_new_ones() {
+    let mut fixture = KeeperFixture::new().await;
+    let (first, _) = fixture.new_holder(2, 0);
+    fixture.list(&first);
+    let mut keeper = fixture.keeper(&fixture.issuer);
+    assert_eq!(keeper.run_once().await.unwrap().frozen.len(), 1);
+    assert!(keeper.scan().await.unwrap().is_empty());
+
+    let (second, second_accounts) = fixture.new_holder(2, 0);
+    fixture.list(&second);
+    let report = keeper.run_once().await.unwrap();
+
+    assert_eq!(report.frozen.len(), 1);
+    assert_eq!(report.frozen[0].offender, second);
+    assert!(fixture
+        .states(&second_accounts)
+        .await
+        .iter()
+        .all(|state| *state == AccountState::Frozen));
+    assert_eq!(keeper.metrics().scans, 3);
+    assert_eq!(keeper.metrics().offenders_frozen, 2);
+}
+
+#[tokio::test]
+async fn lapsed_blocks_are_not_frozen() {
+    let mut fixture = KeeperFixture::new().await;
+    let now = fixture
+        .context
+        .banks_client
+        .get_sysvar::<Clock>()
+        .await
+        .unwrap()
+        .unix_timestamp;
+    let (lapsed, lapsed_accounts) = fixture.new_holder(2, 0);
+    fixture.list_until(&lapsed, Some(now - 1));
+    let (held, held_accounts) = fixture.new_holder(2, 0);
+    fixture.list_until(&held, Some(now + 3600));
+
+    let mut keeper = fixture.keeper(&fixture.issuer);
+    let report = keeper.run_once().await.unwrap();
+
+    assert!(report.failed.is_empty(), "{:?}", report.failed);
+    assert_eq!(report.frozen.len(), 1);
+    assert_eq!(report.frozen[0].offender, held);
+    assert!(fixture
+        .states(&held_accounts)
+        .await
+        .iter()
+        .all(|state| *state == AccountState::Frozen));
+    assert!(fixture
+        .states(&lapsed_accounts)
+        .await
+        .iter()
+        .all(|state| *state == AccountState::Initialized));
+}
+
+#[tokio::test]
+async fn accounts_already_frozen_are_skipped() {
+    let mut fixture = KeeperFixture::new().await;
+    let (offender, accounts) = fixture.new_holder(3, 2);
+    fixture.list(&offender);
+
+    let mut keeper = fixture.keeper(&fixture.issuer);
+    let report = keeper.run_once().await.unwrap();
+
+    // Freezing a frozen account fails, so any of the two in a batch would
+    // have failed the whole offender
+    assert!(report.failed.is_empty(), "{:?}", report.failed);
+    assert_eq!(report.frozen[0].token_accounts, 5);
+    assert_eq!(report.frozen[0].already_frozen, 2);
+    assert_eq!(report.frozen[0].frozen, 3);
+    assert_eq!(report.frozen[0].transactions, 1);
+    assert!(fixture
+        .states(&accounts)
+        .await
+        .iter()
+        .all(|state| *state == AccountState::Frozen));
+}
+
+#[tokio::test]
+async fn dropped_sends_are_resubmitted() {
+    let mut fixture = KeeperFixture::new().await;
+    let (offender, accounts) = fixture.new_holder(10, 0);
+    fixture.list(&offender);
+
+    let mut keeper = fixture.keeper(&fixture.issuer);
+    *keeper.cluster().dropped_sends.lock().unwrap() = 2;
+    let report = keeper.run_once().await.unwrap();
+
+    assert!(report.failed.is_empty(), "{:?}", report.failed);
+    assert_eq!(report.frozen[0].frozen, 10);
+    assert_eq!(report.frozen[0].transactions, 2);
+    assert_eq!(keeper.metrics().retries, 2);
+    assert_eq!(keeper.metrics().transactions_sent, 4);
+    assert_eq!(*keeper.cluster().processed.lock().unwrap(), 2);
+    assert!(fixture
+        .states(&accounts)
+        .await
+        .iter()
+        .all(|state| *state == AccountState::Frozen));
+}
+
+#[tokio::test]
+async fn a_landed_but_unconfirmed_batch_is_not_frozen_twice() {
+    let mut fixture = KeeperFixture::new().await;
+    let (offender, accounts) = fixture.new_holder(10, 0);
+    fixture.list(&offender);
+
+    let mut keeper = fixture.keeper(&fixture.issuer);
+    *keeper.cluster().lost_confirmations.lock().unwrap() = 1;
+    let report = keeper.run_once().await.unwrap();
+
+    // The first batch landed unconfirmed: the retry finds it frozen and sends
+    // nothing, where resubmitting it would have failed on frozen accounts
+    assert!(report.failed.is_empty(), "{:?}", report.failed);
+    assert_eq!(report.frozen[0].frozen, 10);
+    assert_eq!(report.frozen[0].transactions, 1);
+    assert_eq!(keeper.metrics().retries, 1);
+    assert_eq!(keeper.metrics().transactions_sent, 2);
+    assert_eq!(*keeper.cluster().processed.lock().unwrap(), 2);
+    assert!(fixture
+        .states(&accounts)
+        .await
+        .iter()
+        .all(|state| *state == AccountState::Frozen));
+}
+
+#[tokio::test]
+async fn a_rejected_freeze_is_not_retried_and_the_holder_stays_pending() {
+    let mut fixture = KeeperFixture::new().await;
+    let (offender, accounts) = fixture.new_holder(3, 0);
+    fixture.list(&offender);
+
+    let mut keeper = fixture.keeper(&Keypair::new());
+    let report = keeper.run_once().await.unwrap();
+
+    assert!(report.frozen.is_empty());
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].0, offender);
+    assert!(!report.failed[0].1.is_retryable());
+    assert_eq!(keeper.metrics().retries, 0);
+    assert_eq!(keeper.metrics().failed_batches, 1);
+    assert!(fixture
+        .states(&accounts)
+        .await
+        .iter()
+        .all(|state| *state == AccountState::Initialized));
+    assert_eq!(keeper.scan().await.unwrap(), vec![offender]);
+}
+
+#[tokio::test]
+async fn metrics_render_as_prometheus_counters() {
+    let mut fixture = KeeperFixture::new().await;
+    let (offender, _) = fixture.new_holder(9, 1);
+    fixture.list(&offender);
+
+    let mut keeper = fixture.keeper(&fixture.issuer);
+    keeper.run_once().await.unwrap();
+    let text = keeper.metrics().render_prometheus();
+
+    for line in [
+        "freeze_keeper_scans_total 1",
+        "freeze_keeper_offenders_detected_total 1",
+        "freeze_keeper_offenders_frozen_total 1",
+        "freeze_keeper_token_accounts_found_total 10",
+        "freeze_keeper_already_frozen_total 1",
+        "freeze_keeper_accounts_frozen_total 9",
+        "freeze_keeper_transactions_sent_total 2",
+        "freeze_keeper_transactions_landed_total 2",
+        "# TYPE freeze_keeper_retries_total counter",
+    ] {
+        assert!(
+            text.lines().any(|l| l == line),
+            "missing {:?} in\n{}",
+            line,
+            text
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! What the concierge reads from and submits to
+//!
+//! [`Cluster`] is the handful of queries a scan needs. [`RpcCluster`] serves
+//! them over JSON-RPC; the localnet tests serve them from a program-test
+//! bank.
+
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    client_error::ClientError,
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig, hash::Hash, message::Message, pubkey::Pubkey,
+    signature::Signature, transaction::Transaction,
+};
+
+use crate::ConciergeError;
+
+/// Offset of the mint in a token account
+const TOKEN_ACCOUNT_MINT_OFFSET: usize = 0;
+
+#[allow(async_fn_in_trait)]
+pub trait Cluster {
+    /// Address and data of every Token-2022 account of `mint`
+    async fn token_accounts(&self, mint: &Pubkey)
+        -> Result<Vec<(Pubkey, Vec<u8>)>, ConciergeError>;
+
+    async fn latest_blockhash(&self) -> Result<Hash, ConciergeError>;
+
+    /// Fee the cluster charges for `message`
+    async fn fee_for_message(&self, message: &Message) -> Result<u64, ConciergeError>;
+
+    /// Simulate `transaction`: `Ok(Err(reason))` if it would fail
+    async fn simulate(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<Result<(), String>, ConciergeError>;
+
+    /// Submit `transaction` and wait for it to be confirmed
+    async fn send_and_confirm(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<Signature, ConciergeError>;
+}
+
+/// A cluster reached over JSON-RPC
+pub struct RpcCluster {
+    rpc: RpcClient,
+}
+
+impl RpcCluster {
+    /// Connect to `url` at `confirmed` commitment
+    pub fn new(url: &str) -> Self {
+        Self::from_client(RpcClient::new_with_commitment(
+            url.to_string(),
+            CommitmentConfig::confirmed(),
+        ))
+    }
+
+    pub fn from_client(rpc: RpcClient) -> Self {
+        Self { rpc }
+    }
+}
+
+impl Cluster for RpcCluster {
+    async fn token_accounts(
+        &self,
+        mint: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Vec<u8>)>, ConciergeError> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                TOKEN_ACCOUNT_MINT_OFFSET,
+                mint.as_ref(),
+            ))]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(self.rpc.commitment()),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+        let accounts = self
+            .rpc
+            .get_program_accounts_with_config(&spl_token_2022::id(), config)
+            .await
+            .map_err(rpc_error)?;
+        Ok(accounts
+            .into_iter()
+            .map(|(address, account)| (address, account.data))
+            .collect())
+    }
+
+    async fn latest_blockhash(&self) -> Result<Hash, ConciergeError> {
+        self.rpc.get_latest_blockhash().await.map_err(rpc_error)
+    }
+
+    async fn fee_for_message(&self, message: &Message) -> Result<u64, ConciergeError> {
+        self.rpc
+            .get_fee_for_message(message)
+            .await
+            .map_err(rpc_error)
+    }
+
+    async fn simulate(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<Result<(), String>, ConciergeError> {
+        let response = self
+            .rpc
+            .simulate_transaction(transaction)
+            .await
+            .map_err(rpc_error)?;
+        Ok(match response.value.err {
+            None => Ok(()),
+            Some(e) => Err(e.to_string()),
+        })
+    }
+
+    async fn send_and_confirm(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<Signature, ConciergeError> {
+        self.rpc
+            .send_and_confirm_transaction(transaction)
+            .await
+            .map_err(|e| ConciergeError::Transaction(e.to_string()))
+    }
+}
+
+fn rpc_error(error: ClientError) -> ConciergeError {
+    ConciergeError::Rpc(error.to_string())
+}
@@ -0,0 +1,246 @@
+//! The watch-and-thaw loop
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use solana_sdk::{
+    hash::Hash,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_token_2022::{
+    extension::StateWithExtensions,
+    state::{Account as TokenAccount, AccountState},
+};
+use token_acl_client::instructions::{self, FreezeAuthority};
+use token_acl_interface as interface;
+
+use crate::{
+    cluster::Cluster,
+    fees::{FeeLedger, Funding},
+    ConciergeError,
+};
+
+/// What the concierge watches and for whom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConciergeConfig {
+    /// Allow-list gate of the mint
+    pub gate: Pubkey,
+    pub mint: Pubkey,
+    /// Owners who opted in to the service; `None` serves every holder
+    pub opted_in: Option<BTreeSet<Pubkey>>,
+    /// Check-and-thaw pairs per transaction
+    pub thaws_per_transaction: usize,
+}
+
+impl ConciergeConfig {
+    pub fn new(gate: Pubkey, mint: Pubkey) -> Self {
+        Self {
+            gate,
+            mint,
+            opted_in: None,
+            thaws_per_transaction: 8,
+        }
+    }
+}
+
+/// Why a new frozen account is not served
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Skipped {
+    /// Not the owner's associated token account for the mint
+    NotAssociated,
+    /// The owner did not opt in
+    NotOptedIn,
+}
+
+/// The outcome of one scan
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    /// Token accounts thawed
+    pub thawed: Vec<Pubkey>,
+    /// Pending accounts whose thaw the gate denied, with its reason; checked
+    /// again next scan
+    pub denied: Vec<(Pubkey, String)>,
+    /// New frozen accounts the concierge will not serve
+    pub skipped: Vec<(Pubkey, Skipped)>,
+    /// Approved accounts whose thaw did not land; retried next scan
+    pub failed: Vec<(Pubkey, ConciergeError)>,
+}
+
+pub struct ThawConcierge<C> {
+    cluster: C,
+    config: ConciergeConfig,
+    /// Signs the thaws in place of the FAMP PDA
+    issuer: Keypair,
+    funding: Funding,
+    /// Every token account of the mint seen so far
+    known: BTreeSet<Pubkey>,
+    /// New frozen accounts still to thaw, with their owners
+    pending: BTreeMap<Pubkey, Pubkey>,
+    ledger: FeeLedger,
+}
+
+impl<C: Cluster> ThawConcierge<C> {
+    pub fn new(cluster: C, config: ConciergeConfig, issuer: Keypair, funding: Funding) -> Self {
+        let payer = match &funding {
+            Funding::Issuer => issuer.pubkey(),
+            Funding::Provider(provider) => provider.pubkey(),
+        };
+        Self {
+            cluster,
+            config,
+            issuer,
+            funding,
+            known: BTreeSet::new(),
+            pending: BTreeMap::new(),
+            ledger: FeeLedger::new(payer),
+        }
+    }
+
+    pub fn cluster(&self) -> &C {
+        &self.cluster
+    }
+
+    pub fn config(&self) -> &ConciergeConfig {
+        &self.config
+    }
+
+    pub fn ledger(&self) -> &FeeLedger {
+        &self.ledger
+    }
+
+    /// Token accounts waiting for their thaw to be approved or to land
+    pub fn pending(&self) -> impl Iterator<Item = &Pubkey> {
+        self.pending.keys()
+    }
+
+    /// Pick up new frozen accounts, then thaw every pending one the gate
+    /// approves
+    pub async fn run_once(&mut self) -> Result<ScanReport, ConciergeError> {
+        let mut report = ScanReport::default();
+        self.watch(&mut report).await?;
+        if self.pending.is_empty() {
+            return Ok(report);
+        }
+
+        let blockhash = self.cluster.latest_blockhash().await?;
+        let mut approved = Vec::new();
+        for (token_account, owner) in &self.pending {
+            let instructions = self.thaw_instructions(token_account, owner)?;
+            match self
+                .cluster
+                .simulate(&self.transaction(&instructions, blockhash))
+                .await?
+            {
+                Ok(()) => approved.push((*token_account, instructions)),
+                Err(reason) => report.denied.push((*token_account, reason)),
+            }
+        }
+
+        for batch in approved.chunks(self.config.thaws_per_transaction.max(1)) {
+            let instructions: Vec<Instruction> = batch
+                .iter()
+                .flat_map(|(_, instructions)| instructions.iter().cloned())
+                .collect();
+            let transaction = self.transaction(&instructions, blockhash);
+            let fee = self.cluster.fee_for_message(&transaction.message).await?;
+            match self.cluster.send_and_confirm(&transaction).await {
+                Ok(_) => {
+                    self.ledger.record(batch.len(), fee);
+                    for (token_account, _) in batch {
+                        self.pending.remove(token_account);
+                        report.thawed.push(*token_account);
+                    }
+                }
+                Err(e) => {
+                    report.failed.extend(
+                        batch
+                            .iter()
+                            .map(|(token_account, _)| (*token_account, e.clone())),
+                    );
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Record every token account of the mint, queueing new frozen ones the
+    /// concierge serves, and drop pending accounts that are no longer frozen
+    async fn watch(&mut self, report: &mut ScanReport) -> Result<(), ConciergeError> {
+        for (address, data) in self.cluster.token_accounts(&self.config.mint).await? {
+            let Ok(account) = StateWithExtensions::<TokenAccount>::unpack(&data) else {
+                continue;
+            };
+            let frozen = account.base.state == AccountState::Frozen;
+            if !self.known.insert(address) {
+                // A thaw that landed unconfirmed, or someone else's
+                if !frozen {
+                    self.pending.remove(&address);
+                }
+                continue;
+            }
+            if !frozen {
+                continue;
+            }
+            let owner = account.base.owner;
+            let associated = get_associated_token_address_with_program_id(
+                &owner,
+                &self.config.mint,
+                &spl_token_2022::id(),
+            );
+            if address != associated {
+                report.skipped.push((address, Skipped::NotAssociated));
+            } else if self
+                .config
+                .opted_in
+                .as_ref()
+                .is_some_and(|opted_in| !opted_in.contains(&owner))
+            {
+                report.skipped.push((address, Skipped::NotOptedIn));
+            } else {
+                self.pending.insert(address, owner);
+            }
+        }
+        Ok(())
+    }
+
+    /// `can-thaw-permissionless` for `token_account`, with the allow list's
+    /// extra accounts (owner, record, missing-record policy), followed by the
+    /// thaw it authorizes
+    fn thaw_instructions(
+        &self,
+        token_account: &Pubkey,
+        owner: &Pubkey,
+    ) -> Result<[Instruction; 2], ConciergeError> {
+        let ConciergeConfig { gate, mint, .. } = self.config;
+        let check = instructions::with_extra_accounts(
+            instructions::permissionless_thaw(&mint, token_account, &self.ledger.payer, &gate),
+            &[
+                *owner,
+                gate_view::allow_list_address(&gate, &mint, owner).address(),
+                interface::find_missing_record_policy_address(&mint, &gate).0,
+            ],
+        );
+        instructions::authorized(check, &FreezeAuthority::Key(self.issuer.pubkey()))
+            .map_err(|e| ConciergeError::Transaction(e.to_string()))
+    }
+
+    fn transaction(&self, instructions: &[Instruction], blockhash: Hash) -> Transaction {
+        match &self.funding {
+            Funding::Issuer => Transaction::new_signed_with_payer(
+                instructions,
+                Some(&self.issuer.pubkey()),
+                &[&self.issuer],
+                blockhash,
+            ),
+            Funding::Provider(provider) => Transaction::new_signed_with_payer(
+                instructions,
+                Some(&provider.pubkey()),
+                &[provider.as_ref(), &self.issuer],
+                blockhash,
+            ),
+        }
+    }
+}
@@ -0,0 +1,49 @@
+//! Who pays for thaws, and what they paid
+
+use solana_sdk::{pubkey::Pubkey, signature::Keypair};
+
+/// Who pays the fees of the concierge's transactions
+pub enum Funding {
+    /// The issuer, who signs the thaws anyway
+    Issuer,
+    /// A service provider, signing as fee payer alongside the issuer
+    Provider(Box<Keypair>),
+}
+
+/// Fees paid by one funder since the concierge started
+///
+/// Only confirmed transactions are counted: a thaw the gate denies is
+/// caught by simulation and never submitted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeLedger {
+    pub payer: Pubkey,
+    /// Thaw transactions confirmed
+    pub transactions: u64,
+    /// Token accounts thawed by those transactions
+    pub thaws: u64,
+    /// Fees charged for those transactions
+    pub lamports: u64,
+}
+
+impl FeeLedger {
+    pub fn new(payer: Pubkey) -> Self {
+        Self {
+            payer,
+            transactions: 0,
+            thaws: 0,
+            lamports: 0,
+        }
+    }
+
+    /// Record a confirmed transaction thawing `thaws` accounts for `fee`
+    pub fn record(&mut self, thaws: usize, fee: u64) {
+        self.transactions += 1;
+        self.thaws += thaws as u64;
+        self.lamports += fee;
+    }
+
+    /// Average fee per account thawed, `None` before the first thaw
+    pub fn lamports_per_thaw(&self) -> Option<u64> {
+        self.lamports.checked_div(self.thaws)
+    }
+}
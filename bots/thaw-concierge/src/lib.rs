@@ -0,0 +1,50 @@
+//! Thaw concierge
+//!
+//! Reference service for allow-list gates, for holders whose wallets do not
+//! speak sRFC 37 and so never send the permissionless thaw themselves. Each
+//! scan reads every Token-2022 account of one mint. Token accounts the
+//! concierge has not seen before that are frozen (new accounts of a mint
+//! with a frozen default state) are thawed on their owner's behalf, if the
+//! account is the owner's associated token account and the owner passes the
+//! opt-in filter.
+//!
+//! Every thaw follows the gate's `can-thaw-permissionless` check in the same
+//! transaction. The check is simulated first, and only thaws the gate
+//! approves are submitted, so a holder the gate denies costs the funder
+//! nothing. Denied accounts stay pending and are checked again on every
+//! scan, so a holder who is allow-listed after creating their account is
+//! thawed on the next scan. Accounts the concierge saw thawed and that were
+//! frozen later are not new, and are left alone.
+//!
+//! Fees are paid by the issuer or by a service provider, see [`Funding`],
+//! and accounted for in a [`FeeLedger`]. The FAMP program is not part of
+//! this suite, so the thaw itself is a Token-2022 `ThawAccount` signed by
+//! the issuer, standing in for the FAMP's PDA as in the suite's tests.
+
+pub mod cluster;
+pub mod concierge;
+pub mod fees;
+
+pub use cluster::{Cluster, RpcCluster};
+pub use concierge::{ConciergeConfig, ScanReport, Skipped, ThawConcierge};
+pub use fees::{FeeLedger, Funding};
+
+/// Why a concierge operation failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConciergeError {
+    /// Reading from the cluster failed
+    Rpc(String),
+    /// A transaction was rejected or did not land
+    Transaction(String),
+}
+
+impl std::fmt::Display for ConciergeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConciergeError::Rpc(message) => write!(f, "RPC error: {}", message),
+            ConciergeError::Transaction(message) => write!(f, "Transaction error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConciergeError {}
@@ -0,0 +1,158 @@
+//! thaw-concierge
+//!
+//! ```text
+//! thaw-concierge --rpc-url <URL> --gate <PUBKEY> --mint <PUBKEY> --issuer <KEYPAIR>
+//!                [--provider <KEYPAIR>] [--opt-in <PATH>]
+//!                [--interval-secs <N>] [--batch-size <N>] [--once]
+//! ```
+//!
+//! Fees are paid by `--provider` if given, by the issuer otherwise.
+//! `--opt-in` names a file with one owner address per line; without it every
+//! holder is served. Scans every `--interval-secs` (default 10) until
+//! killed, or once with `--once`.
+
+use std::{collections::BTreeSet, process::ExitCode, str::FromStr, time::Duration};
+
+use solana_sdk::{pubkey::Pubkey, signature::read_keypair_file, signature::Keypair};
+use thaw_concierge::{ConciergeConfig, Funding, RpcCluster, ThawConcierge};
+
+struct Args {
+    rpc_url: String,
+    gate: Pubkey,
+    mint: Pubkey,
+    issuer: String,
+    provider: Option<String>,
+    opt_in: Option<String>,
+    interval: Duration,
+    batch_size: Option<usize>,
+    once: bool,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut rpc_url = None;
+    let mut gate = None;
+    let mut mint = None;
+    let mut issuer = None;
+    let mut provider = None;
+    let mut opt_in = None;
+    let mut interval = Duration::from_secs(10);
+    let mut batch_size = None;
+    let mut once = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        if flag == "--once" {
+            once = true;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", flag))?;
+        match flag.as_str() {
+            "--rpc-url" => rpc_url = Some(value),
+            "--gate" => gate = Some(parse(&flag, &value)?),
+            "--mint" => mint = Some(parse(&flag, &value)?),
+            "--issuer" => issuer = Some(value),
+            "--provider" => provider = Some(value),
+            "--opt-in" => opt_in = Some(value),
+            "--interval-secs" => interval = Duration::from_secs(parse(&flag, &value)?),
+            "--batch-size" => batch_size = Some(parse(&flag, &value)?),
+            _ => return Err(format!("Unknown flag {}", flag)),
+        }
+    }
+
+    Ok(Args {
+        rpc_url: required(rpc_url, "--rpc-url")?,
+        gate: required(gate, "--gate")?,
+        mint: required(mint, "--mint")?,
+        issuer: required(issuer, "--issuer")?,
+        provider,
+        opt_in,
+        interval,
+        batch_size,
+        once,
+    })
+}
+
+fn required<T>(value: Option<T>, flag: &str) -> Result<T, String> {
+    value.ok_or_else(|| format!("{} is required", flag))
+}
+
+fn parse<T: FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid {} value: {}", flag, value))
+}
+
+fn keypair(path: &str) -> Result<Keypair, String> {
+    read_keypair_file(path).map_err(|e| format!("Reading keypair {} failed: {}", path, e))
+}
+
+/// Owner addresses in `path`, one per line; blank lines and `#` comments
+/// are ignored
+fn read_opt_in(path: &str) -> Result<BTreeSet<Pubkey>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Reading opt-in list {} failed: {}", path, e))?;
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| parse("--opt-in", line))
+        .collect()
+}
+
+async fn run(args: Args) -> Result<(), String> {
+    let issuer = keypair(&args.issuer)?;
+    let funding = match &args.provider {
+        Some(path) => Funding::Provider(Box::new(keypair(path)?)),
+        None => Funding::Issuer,
+    };
+    let mut config = ConciergeConfig::new(args.gate, args.mint);
+    if let Some(path) = &args.opt_in {
+        config.opted_in = Some(read_opt_in(path)?);
+    }
+    if let Some(batch_size) = args.batch_size {
+        config.thaws_per_transaction = batch_size;
+    }
+    let mut concierge = ThawConcierge::new(RpcCluster::new(&args.rpc_url), config, issuer, funding);
+
+    loop {
+        match concierge.run_once().await {
+            Ok(report) => {
+                for token_account in &report.thawed {
+                    println!("Thawed {}", token_account);
+                }
+                for (token_account, reason) in &report.denied {
+                    println!("Gate denied {}: {}", token_account, reason);
+                }
+                for (token_account, e) in &report.failed {
+                    eprintln!("Thawing {} failed: {}", token_account, e);
+                }
+                let ledger = concierge.ledger();
+                println!(
+                    "{} paid {} lamports for {} thaws in {} transactions",
+                    ledger.payer, ledger.lamports, ledger.thaws, ledger.transactions
+                );
+            }
+            Err(e) => eprintln!("Scan failed: {}", e),
+        }
+        if args.once {
+            return Ok(());
+        }
+        tokio::time::sleep(args.interval).await;
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let result = match parse_args() {
+        Ok(args) => run(args).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,423 @@
+//! Thaw concierge against a local bank
+//!
+//! [`BanksCluster`] serves the concierge's queries from a program-test bank
+//! running the production allow list. The bank has no account index, so the
+//! fixture registers every token account it creates and the program-account
+//! query filters those, the way the RPC filter does. New token accounts are
+//! written frozen, as a mint with a frozen default account state creates
+//! them.
+
+use std::sync::{Arc, Mutex};
+
+use production_allow_list::{allow_list_address, config_address, ADD_TO_ALLOW_LIST, INITIALIZE};
+use solana_program_test::{processor, BanksClient, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    system_program,
+    transaction::Transaction,
+};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_token_2022::state::{Account as TokenAccount, AccountState};
+use thaw_concierge::{Cluster, ConciergeConfig, ConciergeError, Funding, Skipped, ThawConcierge};
+use token_acl_integration_tests::program_test::{mint_account, token_account};
+
+/// Lamports given to the issuer and the provider
+const FUNDER_LAMPORTS: u64 = 1_000_000_000;
+
+/// A program-test bank behind the concierge's [`Cluster`]
+struct BanksCluster {
+    banks_client: BanksClient,
+    /// Every token account the fixture created, standing in for the RPC
+    /// index
+    registry: Arc<Mutex<Vec<Pubkey>>>,
+}
+
+impl Cluster for BanksCluster {
+    async fn token_accounts(
+        &self,
+        mint: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Vec<u8>)>, ConciergeError> {
+        let addresses = self.registry.lock().unwrap().clone();
+        let mut accounts = Vec::new();
+        for address in addresses {
+            let account = self.banks_client.clone().get_account(address).await;
+            if let Ok(Some(account)) = account {
+                if account.owner == spl_token_2022::id() && account.data[..32] == mint.to_bytes() {
+                    accounts.push((address, account.data));
+                }
+            }
+        }
+        Ok(accounts)
+    }
+
+    async fn latest_blockhash(&self) -> Result<Hash, ConciergeError> {
+        self.banks_client
+            .clone()
+            .get_latest_blockhash()
+            .await
+            .map_err(|e| ConciergeError::Rpc(e.to_string()))
+    }
+
+    async fn fee_for_message(&self, message: &Message) -> Result<u64, ConciergeError> {
+        self.banks_client
+            .clone()
+            .get_fee_for_message(message.clone())
+            .await
+            .map_err(|e| ConciergeError::Rpc(e.to_string()))?
+            .ok_or_else(|| ConciergeError::Rpc("Blockhash expired".to_string()))
+    }
+
+    async fn simulate(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<Result<(), String>, ConciergeError> {
+        let simulation = self
+            .banks_client
+            .clone()
+            .simulate_transaction(transaction.clone())
+            .await
+            .map_err(|e| ConciergeError::Rpc(e.to_string()))?;
+        Ok(match simulation.result {
+            Some(Ok(())) => Ok(()),
+            Some(Err(e)) => Err(e.to_string()),
+            None => Err("Simulation did not run".to_string()),
+        })
+    }
+
+    async fn send_and_confirm(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<Signature, ConciergeError> {
+        self.banks_client
+            .clone()
+            .process_transaction(transaction.clone())
+            .await
+            .map_err(|e| ConciergeError::Transaction(e.to_string()))?;
+        Ok(transaction.signatures[0])
+    }
+}
+
+struct ConciergeFixture {
+    context: ProgramTestContext,
+    gate: Pubkey,
+    mint: Pubkey,
+    issuer: Keypair,
+    provider: Keypair,
+    registry: Arc<Mutex<Vec<Pubkey>>>,
+}
+
+impl ConciergeFixture {
+    async fn new() -> Self {
+        let gate = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let issuer = Keypair::new();
+        let provider = Keypair::new();
+
+        let mut program_test = ProgramTest::new(
+            "production_allow_list",
+            gate,
+            processor!(production_allow_list::process_instruction),
+        );
+        program_test.add_account(mint, mint_account(&issuer.pubkey(), 0));
+        for funder in [&issuer, &provider] {
+            program_test.add_account(
+                funder.pubkey(),
+                Account::new(FUNDER_LAMPORTS, 0, &system_program::id()),
+            );
+        }
+        let context = program_test.start_with_context().await;
+
+        let mut fixture = Self {
+            context,
+            gate,
+            mint,
+            issuer,
+            provider,
+            registry: Arc::default(),
+        };
+        let initialize = Instruction::new_with_bytes(
+            gate,
+            &[INITIALIZE],
+            vec![
+                AccountMeta::new(config_address(&mint, &gate).address(), false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(fixture.issuer.pubkey(), true),
+                AccountMeta::new(fixture.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        fixture.send(initialize).await;
+        fixture
+    }
+
+    async fn send(&mut self, instruction: Instruction) {
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&self.context.payer.pubkey()),
+            &[&self.context.payer, &self.issuer],
+            self.context
+                .banks_client
+                .get_latest_blockhash()
+                .await
+                .unwrap(),
+        );
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .expect("allow list admin transaction should succeed");
+    }
+
+    async fn allow_list(&mut self, user: &Pubkey) {
+        let add = Instruction::new_with_bytes(
+            self.gate,
+            &[ADD_TO_ALLOW_LIST],
+            vec![
+                AccountMeta::new_readonly(config_address(&self.mint, &self.gate).address(), false),
+                AccountMeta::new(
+                    allow_list_address(&self.mint, user, &self.gate).address(),
+                    false,
+                ),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(*user, false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        self.send(add).await;
+    }
+
+    /// Create `owner`'s associated token account in `state`
+    fn create_associated(&mut self, owner: &Pubkey, state: AccountState) -> Pubkey {
+        let address =
+            get_associated_token_address_with_program_id(owner, &self.mint, &spl_token_2022::id());
+        self.create_at(address, owner, state);
+        address
+    }
+
+    fn create_at(&mut self, address: Pubkey, owner: &Pubkey, state: AccountState) {
+        self.context
+            .set_account(&address, &token_account(&self.mint, owner, 0, state).into());
+        self.registry.lock().unwrap().push(address);
+    }
+
+    fn concierge(&self, config: ConciergeConfig, funding: Funding) -> ThawConcierge<BanksCluster> {
+        ThawConcierge::new(
+            BanksCluster {
+                banks_client: self.context.banks_client.clone(),
+                registry: self.registry.clone(),
+            },
+            config,
+            self.issuer.insecure_clone(),
+            funding,
+        )
+    }
+
+    fn config(&self) -> ConciergeConfig {
+        ConciergeConfig::new(self.gate, self.mint)
+    }
+
+    async fn state(&mut self, address: &Pubkey) -> AccountState {
+        let account = self
+            .context
+            .banks_client
+            .get_account(*address)
+            .await
+            .unwrap()
+            .unwrap();
+        TokenAccount::unpack(&account.data).unwrap().state
+    }
+
+    async fn balance(&mut self, address: &Pubkey) -> u64 {
+        self.context
+            .banks_client
+            .get_balance(*address)
+            .await
+            .unwrap()
+    }
+
+    async fn lamports_per_signature(&mut self) -> u64 {
+        let message = Message::new_with_blockhash(
+            &[],
+            Some(&self.issuer.pubkey()),
+            &self.context.last_blockhash,
+        );
+        self.context
+            .banks_client
+            .get_fee_for_message(message)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+}
+
+#[tokio::test]
+async fn thaws_new_frozen_accounts_of_allow_listed_holders() {
+    let mut fixture = ConciergeFixture::new().await;
+    let holders: Vec<Pubkey> = (0..10).map(|_| Pubkey::new_unique()).collect();
+    let mut accounts = Vec::new();
+    for holder in &holders {
+        fixture.allow_list(holder).await;
+        accounts.push(fixture.create_associated(holder, AccountState::Frozen));
+    }
+
+    let mut concierge = fixture.concierge(fixture.config(), Funding::Issuer);
+    let report = concierge.run_once().await.unwrap();
+
+    assert_eq!(report.thawed.len(), 10);
+    assert!(report.denied.is_empty(), "{:?}", report.denied);
+    assert!(report.failed.is_empty(), "{:?}", report.failed);
+    for account in &accounts {
+        assert_eq!(fixture.state(account).await, AccountState::Initialized);
+    }
+    assert_eq!(concierge.pending().count(), 0);
+    // 8 check-and-thaw pairs to a transaction
+    assert_eq!(concierge.ledger().transactions, 2);
+}
+
+#[tokio::test]
+async fn denied_holders_cost_nothing_and_are_thawed_once_allow_listed() {
+    let mut fixture = ConciergeFixture::new().await;
+    let holder = Pubkey::new_unique();
+    let account = fixture.create_associated(&holder, AccountState::Frozen);
+    let issuer = fixture.issuer.pubkey();
+    let before = fixture.balance(&issuer).await;
+
+    let mut concierge = fixture.concierge(fixture.config(), Funding::Issuer);
+    let report = concierge.run_once().await.unwrap();
+
+    assert!(report.thawed.is_empty());
+    assert_eq!(report.denied.len(), 1);
+    assert_eq!(report.denied[0].0, account);
+    assert_eq!(fixture.state(&account).await, AccountState::Frozen);
+    assert_eq!(fixture.balance(&issuer).await, before);
+    assert_eq!(concierge.ledger().lamports, 0);
+    assert_eq!(concierge.pending().collect::<Vec<_>>(), vec![&account]);
+
+    fixture.allow_list(&holder).await;
+    let report = concierge.run_once().await.unwrap();
+
+    assert_eq!(report.thawed, vec![account]);
+    assert_eq!(fixture.state(&account).await, AccountState::Initialized);
+}
+
+#[tokio::test]
+async fn accounts_outside_the_filters_are_skipped() {
+    let mut fixture = ConciergeFixture::new().await;
+    let opted_in = Pubkey::new_unique();
+    let not_opted_in = Pubkey::new_unique();
+    for holder in [&opted_in, &not_opted_in] {
+        fixture.allow_list(holder).await;
+    }
+    let served = fixture.create_associated(&opted_in, AccountState::Frozen);
+    let unserved = fixture.create_associated(&not_opted_in, AccountState::Frozen);
+    let auxiliary = Pubkey::new_unique();
+    fixture.create_at(auxiliary, &opted_in, AccountState::Frozen);
+
+    let mut config = fixture.config();
+    config.opted_in = Some([opted_in].into());
+    let mut concierge = fixture.concierge(config, Funding::Issuer);
+    let mut report = concierge.run_once().await.unwrap();
+    report.skipped.sort();
+
+    assert_eq!(report.thawed, vec![served]);
+    let mut expected = vec![
+        (unserved, Skipped::NotOptedIn),
+        (auxiliary, Skipped::NotAssociated),
+    ];
+    expected.sort();
+    assert_eq!(report.skipped, expected);
+    assert_eq!(fixture.state(&unserved).await, AccountState::Frozen);
+    assert_eq!(fixture.state(&auxiliary).await, AccountState::Frozen);
+
+    // Skipped accounts are not reconsidered
+    let report = concierge.run_once().await.unwrap();
+    assert!(report.skipped.is_empty());
+    assert!(report.thawed.is_empty());
+}
+
+#[tokio::test]
+async fn accounts_frozen_after_being_seen_thawed_are_left_alone() {
+    let mut fixture = ConciergeFixture::new().await;
+    let holder = Pubkey::new_unique();
+    fixture.allow_list(&holder).await;
+    let account = fixture.create_associated(&holder, AccountState::Initialized);
+
+    let mut concierge = fixture.concierge(fixture.config(), Funding::Issuer);
+    assert!(concierge.run_once().await.unwrap().thawed.is_empty());
+
+    // The issuer froze it on purpose
+    fixture.create_at(account, &holder, AccountState::Frozen);
+    let report = concierge.run_once().await.unwrap();
+
+    assert!(report.thawed.is_empty());
+    assert!(report.denied.is_empty());
+    assert_eq!(fixture.state(&account).await, AccountState::Frozen);
+}
+
+#[tokio::test]
+async fn issuer_funded_fees_are_accounted_to_the_issuer() {
+    let mut fixture = ConciergeFixture::new().await;
+    for _ in 0..3 {
+        let holder = Pubkey::new_unique();
+        fixture.allow_list(&holder).await;
+        fixture.create_associated(&holder, AccountState::Frozen);
+    }
+    let issuer = fixture.issuer.pubkey();
+    let lamports_per_signature = fixture.lamports_per_signature().await;
+    let before = fixture.balance(&issuer).await;
+
+    let mut concierge = fixture.concierge(fixture.config(), Funding::Issuer);
+    concierge.run_once().await.unwrap();
+    let ledger = concierge.ledger().clone();
+
+    assert_eq!(ledger.payer, issuer);
+    assert_eq!(ledger.transactions, 1);
+    assert_eq!(ledger.thaws, 3);
+    // The issuer is the only signer
+    assert_eq!(ledger.lamports, lamports_per_signature);
+    assert_eq!(before - fixture.balance(&issuer).await, ledger.lamports);
+    assert_eq!(ledger.lamports_per_thaw(), Some(lamports_per_signature / 3));
+}
+
+#[tokio::test]
+async fn provider_funded_fees_are_accounted_to_the_provider() {
+    let mut fixture = ConciergeFixture::new().await;
+    for _ in 0..10 {
+        let holder = Pubkey::new_unique();
+        fixture.allow_list(&holder).await;
+        fixture.create_associated(&holder, AccountState::Frozen);
+    }
+    let denied = Pubkey::new_unique();
+    fixture.create_associated(&denied, AccountState::Frozen);
+    let issuer = fixture.issuer.pubkey();
+    let provider = fixture.provider.pubkey();
+    let lamports_per_signature = fixture.lamports_per_signature().await;
+    let issuer_before = fixture.balance(&issuer).await;
+    let provider_before = fixture.balance(&provider).await;
+
+    let funding = Funding::Provider(Box::new(fixture.provider.insecure_clone()));
+    let mut concierge = fixture.concierge(fixture.config(), funding);
+    let report = concierge.run_once().await.unwrap();
+    let ledger = concierge.ledger().clone();
+
+    assert_eq!(report.thawed.len(), 10);
+    assert_eq!(report.denied.len(), 1);
+    assert_eq!(ledger.payer, provider);
+    assert_eq!(ledger.transactions, 2);
+    assert_eq!(ledger.thaws, 10);
+    // The provider pays and the issuer co-signs every transaction
+    assert_eq!(ledger.lamports, 2 * 2 * lamports_per_signature);
+    assert_eq!(
+        provider_before - fixture.balance(&provider).await,
+        ledger.lamports
+    );
+    assert_eq!(fixture.balance(&issuer).await, issuer_before);
+}
@@ -1,3 +1,6 @@
+// `entrypoint!` expands to cfgs only known to the BPF toolchain.
+#![allow(unexpected_cfgs)]
+
 use borsh::{BorshDeserialize, BorshSerialize};
 /// Example Allow List Gate Program
 ///
@@ -5,24 +8,47 @@ use borsh::{BorshDeserialize, BorshSerialize};
 /// This allow list program:
 /// - Implements can-thaw-permissionless: Returns success if user is in allow list
 /// - Optionally implements can-freeze-permissionless: Not supported (returns error)
-/// - Creates and manages extra-account-metas PDAs
+/// - Creates the thaw extra-account-metas PDA, resolving the allow list record
+///   after the owner the caller supplies
+use extra_account_metas::ListKind;
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
-    entrypoint,
     entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
     msg,
     program_error::ProgramError,
     pubkey::Pubkey,
+    system_program,
+};
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, seeds::Seed};
+use token_acl_interface::{
+    find_thaw_extra_account_metas_address, CanonicalPda, GateInterfaceVersion,
 };
 
 // Discriminators from sRFC 37
 const CAN_THAW_PERMISSIONLESS_DISCRIMINATOR: [u8; 8] = [8, 175, 169, 129, 137, 74, 61, 241];
 const CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR: [u8; 8] = [214, 141, 109, 75, 248, 1, 45, 29];
 
+// SPL Token account layout: mint (0..32), owner (32..64), ...
+const TOKEN_ACCOUNT_OWNER_OFFSET: usize = 32;
+
 // Seeds
-const ALLOW_LIST_SEED: &[u8] = b"allow-list";
+pub const ALLOW_LIST_SEED: &[u8] = b"allow-list";
 
-entrypoint!(process_instruction);
+// Instruction discriminator (single byte, outside the sRFC 37 interface)
+pub const INITIALIZE_THAW_EXTRA_ACCOUNT_METAS: u8 = 0;
+
+/// The gate interface this allow list speaks: the original layout, owner
+/// then record
+pub const GATE_INTERFACE_VERSION: GateInterfaceVersion = GateInterfaceVersion::LEGACY;
+
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
+
+/// Allow list record PDA of `user` for `mint`
+pub fn allow_list_address(mint: &Pubkey, user: &Pubkey, program_id: &Pubkey) -> CanonicalPda {
+    CanonicalPda::find(&[ALLOW_LIST_SEED, mint.as_ref(), user.as_ref()], program_id)
+}
 
 /// Allow List record for a user
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
@@ -38,6 +64,10 @@ pub fn process_instruction(
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
+    if instruction_data == [INITIALIZE_THAW_EXTRA_ACCOUNT_METAS] {
+        return process_initialize_thaw_extra_account_metas(program_id, accounts);
+    }
+
     if instruction_data.len() < 8 {
         return Err(ProgramError::InvalidInstructionData);
     }
@@ -60,6 +90,105 @@ pub fn process_instruction(
     }
 }
 
+/// Extra accounts of `can-thaw-permissionless` after the owner, which the
+/// caller supplies: the owner's allow list record,
+/// `[ALLOW_LIST_SEED, mint, owner]`, with the owner read from bytes 32..64 of
+/// the token account
+pub fn thaw_extra_account_metas() -> Result<Vec<ExtraAccountMeta>, ProgramError> {
+    Ok(vec![ExtraAccountMeta::new_with_seeds(
+        &[
+            Seed::Literal {
+                bytes: ALLOW_LIST_SEED.to_vec(),
+            },
+            Seed::AccountKey { index: 2 },
+            Seed::AccountData {
+                account_index: 1,
+                data_index: 32,
+                length: 32,
+            },
+        ],
+        false,
+        false,
+    )?])
+}
+
+/// Build an `INITIALIZE_THAW_EXTRA_ACCOUNT_METAS` instruction
+pub fn initialize_thaw_extra_account_metas(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    let (extra_account_metas, _) = find_thaw_extra_account_metas_address(mint, program_id);
+    Instruction::new_with_bytes(
+        *program_id,
+        &[INITIALIZE_THAW_EXTRA_ACCOUNT_METAS],
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(extra_account_metas, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Create the thaw extra-account-metas PDA of a mint
+///
+/// The list is [`thaw_extra_account_metas`], so anyone may pay to create it.
+/// Accounts:
+/// 0. payer (signer, writable)
+/// 1. thaw extra-account-metas (writable)
+/// 2. mint
+/// 3. system program
+fn process_initialize_thaw_extra_account_metas(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let payer = next_account_info(account_info_iter)?;
+    let extra_account_metas = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    extra_account_metas::create_list(
+        ListKind::Thaw,
+        program_id,
+        &GATE_INTERFACE_VERSION,
+        &thaw_extra_account_metas()?,
+        payer,
+        extra_account_metas,
+        mint,
+        system_program,
+    )?;
+
+    msg!(
+        "Thaw extra-account-metas initialized for mint: {}",
+        mint.key
+    );
+    Ok(())
+}
+
+/// Fail unless `token_account` holds `mint` and belongs to `owner`
+fn check_token_account_owner(
+    token_account: &AccountInfo,
+    mint: &Pubkey,
+    owner: &Pubkey,
+) -> ProgramResult {
+    let data = token_account.try_borrow_data()?;
+    let bound = data.len() >= TOKEN_ACCOUNT_OWNER_OFFSET + 32
+        && data[..32] == mint.to_bytes()
+        && data[TOKEN_ACCOUNT_OWNER_OFFSET..TOKEN_ACCOUNT_OWNER_OFFSET + 32] == owner.to_bytes();
+    if !bound {
+        msg!(
+            "Token account {} is not owned by {} for this mint",
+            token_account.key,
+            owner
+        );
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
 fn process_can_thaw_permissionless(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
 
@@ -73,23 +202,20 @@ fn process_can_thaw_permissionless(program_id: &Pubkey, accounts: &[AccountInfo]
     // 5. allow list PDA
 
     let _caller = next_account_info(account_info_iter)?;
-    let _token_account = next_account_info(account_info_iter)?;
+    let token_account = next_account_info(account_info_iter)?;
     let mint = next_account_info(account_info_iter)?;
     let _extra_account_metas = next_account_info(account_info_iter)?;
     let token_account_owner = next_account_info(account_info_iter)?;
     let allow_list_pda = next_account_info(account_info_iter)?;
 
+    // The record is looked up by the supplied owner, so it must be the
+    // owner of the account being thawed
+    check_token_account_owner(token_account, mint.key, token_account_owner.key)?;
+
     // Verify allow list PDA derivation
-    let (expected_pda, _bump) = Pubkey::find_program_address(
-        &[
-            ALLOW_LIST_SEED,
-            mint.key.as_ref(),
-            token_account_owner.key.as_ref(),
-        ],
-        program_id,
-    );
+    let expected_pda = allow_list_address(mint.key, token_account_owner.key, program_id);
 
-    if *allow_list_pda.key != expected_pda {
+    if *allow_list_pda.key != expected_pda.address() {
         msg!("Invalid allow list PDA");
         return Err(ProgramError::InvalidAccountData);
     }
@@ -100,7 +226,7 @@ fn process_can_thaw_permissionless(program_id: &Pubkey, accounts: &[AccountInfo]
         return Err(ProgramError::InvalidAccountData);
     }
 
-    let record = AllowListRecord::try_from_slice(&allow_list_pda.data.borrow())?;
+    let record = AllowListRecord::try_from_slice(&allow_list_pda.try_borrow_data()?)?;
 
     if !record.allowed {
         msg!("User {} is not allowed", token_account_owner.key);
@@ -1,3 +1,6 @@
+// `entrypoint!` expands to cfgs only known to the BPF toolchain.
+#![allow(unexpected_cfgs)]
+
 use borsh::{BorshDeserialize, BorshSerialize};
 /// Example Block List Gate Program
 ///
@@ -5,24 +8,106 @@ use borsh::{BorshDeserialize, BorshSerialize};
 /// This block list program:
 /// - Implements can-freeze-permissionless: Returns success if user is in block list
 /// - Implements can-thaw-permissionless: Returns success if user is NOT in block list
-/// - Creates and manages extra-account-metas PDAs
+/// - Creates and manages the freeze extra-account-metas PDA, so a keeper bot
+///   holding only a token account can resolve the block list record to freeze it.
+///   The mint authority can replace the list (e.g. to add an oracle account);
+///   every update bumps its version, and freezes resolved against an older
+///   version are rejected
+/// - Creates the thaw extra-account-metas PDA, resolving the record and the
+///   missing-record policy PDA after the owner the caller supplies
+/// - Lets the mint authority invert the missing-record policy, so a holder
+///   without a record is denied a permissionless thaw instead of allowed
+/// - Keeps the block list on chain: the mint authority initializes a config
+///   naming the list's authority, who blocks and unblocks users and can
+///   propose a new authority, which takes over once it accepts
+/// - Lets a block carry an expiry: once the clock passes it the block lapses
+///   on its own, freezes are refused and thaws permitted as if unblocked
+use extra_account_metas::ListKind;
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
-    entrypoint,
+    clock::Clock,
     entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
     msg,
+    program::{invoke, invoke_signed},
     program_error::ProgramError,
     pubkey::Pubkey,
+    rent::Rent,
+    system_instruction, system_program,
+    sysvar::Sysvar,
+};
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, seeds::Seed};
+use token_acl_interface::{
+    check_system_program, find_freeze_extra_account_metas_address,
+    find_missing_record_policy_address, find_thaw_extra_account_metas_address, store_account_data,
+    CanonicalPda, GateInterfaceVersion, MissingRecordPolicy,
+    CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR, CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+    FEATURE_MISSING_RECORD_POLICY, FEATURE_VERSIONED_EXTRA_ACCOUNT_METAS,
+    MISSING_RECORD_POLICY_SEED,
 };
-
-// Discriminators from sRFC 37
-const CAN_THAW_PERMISSIONLESS_DISCRIMINATOR: [u8; 8] = [8, 175, 169, 129, 137, 74, 61, 241];
-const CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR: [u8; 8] = [214, 141, 109, 75, 248, 1, 45, 29];
 
 // Seeds
-const BLOCK_LIST_SEED: &[u8] = b"block-list";
+pub const BLOCK_LIST_SEED: &[u8] = b"block-list";
+pub const CONFIG_SEED: &[u8] = b"config";
+pub const PENDING_AUTHORITY_SEED: &[u8] = b"pending-authority";
+
+/// Block list record PDA of `user` for `mint`
+pub fn block_list_address(mint: &Pubkey, user: &Pubkey, program_id: &Pubkey) -> CanonicalPda {
+    CanonicalPda::find(&[BLOCK_LIST_SEED, mint.as_ref(), user.as_ref()], program_id)
+}
+
+/// Config PDA of `mint`
+pub fn config_address(mint: &Pubkey, program_id: &Pubkey) -> CanonicalPda {
+    CanonicalPda::find(&[CONFIG_SEED, mint.as_ref()], program_id)
+}
 
-entrypoint!(process_instruction);
+/// Pending-authority PDA of `mint`
+pub fn pending_authority_address(mint: &Pubkey, program_id: &Pubkey) -> CanonicalPda {
+    CanonicalPda::find(&[PENDING_AUTHORITY_SEED, mint.as_ref()], program_id)
+}
+
+// Instruction discriminators (single byte, outside the sRFC 37 interface)
+pub const INITIALIZE_FREEZE_EXTRA_ACCOUNT_METAS: u8 = 0;
+pub const UPDATE_FREEZE_EXTRA_ACCOUNT_METAS: u8 = 1;
+pub const SET_MISSING_RECORD_POLICY: u8 = 2;
+pub const INITIALIZE_THAW_EXTRA_ACCOUNT_METAS: u8 = 3;
+pub const INITIALIZE: u8 = 4;
+pub const BLOCK_USER: u8 = 5;
+pub const UNBLOCK_USER: u8 = 6;
+pub const UPDATE_AUTHORITY: u8 = 7;
+// 8 is the first byte of the can-thaw-permissionless discriminator
+pub const ACCEPT_AUTHORITY: u8 = 9;
+pub const CANCEL_AUTHORITY_TRANSFER: u8 = 10;
+
+/// A block list thaws everyone it has not blocked, unless the issuer says
+/// otherwise
+pub const DEFAULT_MISSING_RECORD_POLICY: MissingRecordPolicy = MissingRecordPolicy::Allow;
+
+// SPL Token mint layout: mint authority as a `COption<Pubkey>` (0..36), ...
+const MINT_AUTHORITY_END: usize = 36;
+
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
+
+/// Who manages a mint's block list
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct Config {
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    pub bump: u8,
+}
+
+/// Authority `UPDATE_AUTHORITY` proposed for a mint's block list, waiting
+/// for the proposed key to run `ACCEPT_AUTHORITY`
+///
+/// Until it does, the config keeps its current authority, so a mistyped
+/// key never takes control.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct PendingAuthority {
+    pub mint: Pubkey,
+    pub proposed: Pubkey,
+    pub bump: u8,
+}
 
 /// Block List record for a user (e.g., sanctions list)
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
@@ -32,9 +117,94 @@ pub struct BlockListRecord {
     pub blocked: bool,
     pub reason: BlockReason,
     pub added_timestamp: i64,
+    /// When the block lapses; `None` blocks until the user is unblocked
+    pub expiry_timestamp: Option<i64>,
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+impl BlockListRecord {
+    /// Size of a record written before records could expire: it ends at
+    /// `added_timestamp`, with no expiry at all
+    pub const LEGACY_LEN: usize = 74;
+
+    /// Decode a record, reading one written before records could expire as
+    /// a block that lasts until the user is unblocked
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() == Self::LEGACY_LEN {
+            let mut upgraded = data.to_vec();
+            upgraded.push(0);
+            return Ok(Self::try_from_slice(&upgraded)?);
+        }
+        Ok(Self::try_from_slice(data)?)
+    }
+
+    /// Encode the record for `record_account`; a legacy record without an
+    /// expiry keeps its layout, so it is never resized
+    fn pack_for(&self, record_account: &AccountInfo) -> Result<Vec<u8>, ProgramError> {
+        let mut data = self.try_to_vec()?;
+        if record_account.data_len() == Self::LEGACY_LEN && self.expiry_timestamp.is_none() {
+            data.truncate(Self::LEGACY_LEN);
+        }
+        Ok(data)
+    }
+
+    pub fn is_expired(&self, current_timestamp: i64) -> bool {
+        self.expiry_timestamp
+            .is_some_and(|expiry| current_timestamp > expiry)
+    }
+
+    /// Whether the block is in force at `current_timestamp`
+    pub fn is_active(&self, current_timestamp: i64) -> bool {
+        self.blocked && !self.is_expired(current_timestamp)
+    }
+
+    /// Whether the block is in force now, for programs judging it on
+    /// chain; only records that carry an expiry read the clock
+    pub fn is_active_now(&self) -> Result<bool, ProgramError> {
+        if !self.blocked || self.expiry_timestamp.is_none() {
+            return Ok(self.blocked);
+        }
+        Ok(self.is_active(Clock::get()?.unix_timestamp))
+    }
+}
+
+/// Instruction data of `BLOCK_USER`, after the discriminator
+///
+/// Data holding only the reason blocks until the user is unblocked.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct BlockUserArgs {
+    pub reason: BlockReason,
+    pub expiry_timestamp: Option<i64>,
+}
+
+impl BlockUserArgs {
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        let args = match data {
+            [reason] => BlockReason::try_from_slice(&[*reason]).map(|reason| Self {
+                reason,
+                expiry_timestamp: None,
+            }),
+            _ => Self::try_from_slice(data),
+        };
+        args.map_err(|_| ProgramError::InvalidInstructionData)
+    }
+
+    /// Reject a block that would have lapsed by `current_timestamp`
+    pub fn validate(&self, current_timestamp: i64) -> ProgramResult {
+        if let Some(expiry) = self.expiry_timestamp {
+            if expiry <= current_timestamp {
+                msg!(
+                    "Expiry {} is not after the current time {}",
+                    expiry,
+                    current_timestamp
+                );
+                return Err(ProgramError::InvalidArgument);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
 pub enum BlockReason {
     Sanctions,
     Compliance,
@@ -47,13 +217,25 @@ pub fn process_instruction(
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
-    if instruction_data.len() < 8 {
-        return Err(ProgramError::InvalidInstructionData);
-    }
-
-    let discriminator = &instruction_data[0..8];
-
-    match discriminator {
+    match instruction_data {
+        [INITIALIZE_FREEZE_EXTRA_ACCOUNT_METAS] => {
+            process_initialize_freeze_extra_account_metas(program_id, accounts)
+        }
+        [UPDATE_FREEZE_EXTRA_ACCOUNT_METAS, data @ ..] => {
+            process_update_freeze_extra_account_metas(program_id, accounts, data)
+        }
+        [SET_MISSING_RECORD_POLICY, data @ ..] => {
+            process_set_missing_record_policy(program_id, accounts, data)
+        }
+        [INITIALIZE_THAW_EXTRA_ACCOUNT_METAS] => {
+            process_initialize_thaw_extra_account_metas(program_id, accounts)
+        }
+        [INITIALIZE] => process_initialize(program_id, accounts),
+        [BLOCK_USER, data @ ..] => process_block_user(program_id, accounts, data),
+        [UNBLOCK_USER] => process_unblock_user(program_id, accounts),
+        [UPDATE_AUTHORITY] => process_update_authority(program_id, accounts),
+        [ACCEPT_AUTHORITY] => process_accept_authority(program_id, accounts),
+        [CANCEL_AUTHORITY_TRANSFER] => process_cancel_authority_transfer(program_id, accounts),
         d if d == CAN_THAW_PERMISSIONLESS_DISCRIMINATOR => {
             process_can_thaw_permissionless(program_id, accounts)
         }
@@ -67,34 +249,1017 @@ pub fn process_instruction(
     }
 }
 
+/// The block list record of the token account's owner,
+/// `[BLOCK_LIST_SEED, mint, owner]`, with the owner read from bytes 32..64 of
+/// the token account
+fn block_list_record_meta() -> Result<ExtraAccountMeta, ProgramError> {
+    ExtraAccountMeta::new_with_seeds(
+        &[
+            Seed::Literal {
+                bytes: BLOCK_LIST_SEED.to_vec(),
+            },
+            Seed::AccountKey { index: 2 },
+            Seed::AccountData {
+                account_index: 1,
+                data_index: 32,
+                length: 32,
+            },
+        ],
+        false,
+        false,
+    )
+}
+
+/// Extra accounts of `can-freeze-permissionless`: the block list record of
+/// the token account's owner
+pub fn freeze_extra_account_metas() -> Result<Vec<ExtraAccountMeta>, ProgramError> {
+    Ok(vec![block_list_record_meta()?])
+}
+
+/// Extra accounts of `can-thaw-permissionless` after the owner, which the
+/// caller supplies: the owner's block list record and the missing-record
+/// policy PDA, `[MISSING_RECORD_POLICY_SEED, mint]`
+pub fn thaw_extra_account_metas() -> Result<Vec<ExtraAccountMeta>, ProgramError> {
+    Ok(vec![
+        block_list_record_meta()?,
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: MISSING_RECORD_POLICY_SEED.to_vec(),
+                },
+                Seed::AccountKey { index: 2 },
+            ],
+            false,
+            false,
+        )?,
+    ])
+}
+
+/// The gate interface this block list speaks: its thaw check takes the
+/// missing-record policy PDA, and its freeze list is versioned
+pub const GATE_INTERFACE_VERSION: GateInterfaceVersion = GateInterfaceVersion {
+    version: 1,
+    features: FEATURE_MISSING_RECORD_POLICY | FEATURE_VERSIONED_EXTRA_ACCOUNT_METAS,
+};
+
+/// Account size of a freeze extra-account-metas list with `num_metas` entries
+pub fn freeze_extra_account_metas_size(num_metas: usize) -> Result<usize, ProgramError> {
+    extra_account_metas::list_size(num_metas)
+}
+
+/// Write a versioned freeze extra-account-metas list into zeroed `data`,
+/// advertising [`GATE_INTERFACE_VERSION`]
+pub fn pack_freeze_extra_account_metas(
+    data: &mut [u8],
+    version: u64,
+    metas: &[ExtraAccountMeta],
+) -> ProgramResult {
+    extra_account_metas::pack_list(
+        ListKind::Freeze,
+        data,
+        version,
+        &GATE_INTERFACE_VERSION,
+        metas,
+    )
+}
+
+/// Version of a freeze extra-account-metas list
+pub fn freeze_extra_account_metas_version(data: &[u8]) -> Result<u64, ProgramError> {
+    extra_account_metas::list_version(data)
+}
+
+/// Build an `INITIALIZE_FREEZE_EXTRA_ACCOUNT_METAS` instruction
+pub fn initialize_freeze_extra_account_metas(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    let (extra_account_metas, _) = find_freeze_extra_account_metas_address(mint, program_id);
+    Instruction::new_with_bytes(
+        *program_id,
+        &[INITIALIZE_FREEZE_EXTRA_ACCOUNT_METAS],
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(extra_account_metas, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Build an `INITIALIZE_THAW_EXTRA_ACCOUNT_METAS` instruction
+pub fn initialize_thaw_extra_account_metas(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    let (extra_account_metas, _) = find_thaw_extra_account_metas_address(mint, program_id);
+    Instruction::new_with_bytes(
+        *program_id,
+        &[INITIALIZE_THAW_EXTRA_ACCOUNT_METAS],
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(extra_account_metas, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Build an `UPDATE_FREEZE_EXTRA_ACCOUNT_METAS` instruction replacing the
+/// list at `expected_version` with `metas`
+///
+/// Data: discriminator, `expected_version` (u64 LE), then `metas` as a
+/// `PodSlice<ExtraAccountMeta>`.
+pub fn update_freeze_extra_account_metas(
+    program_id: &Pubkey,
+    mint_authority: &Pubkey,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    expected_version: u64,
+    metas: &[ExtraAccountMeta],
+) -> Result<Instruction, ProgramError> {
+    let mut data = vec![UPDATE_FREEZE_EXTRA_ACCOUNT_METAS];
+    data.extend_from_slice(&expected_version.to_le_bytes());
+    data.extend(extra_account_metas::encode_metas(metas)?);
+
+    let (extra_account_metas, _) = find_freeze_extra_account_metas_address(mint, program_id);
+    Ok(Instruction::new_with_bytes(
+        *program_id,
+        &data,
+        vec![
+            AccountMeta::new_readonly(*mint_authority, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(extra_account_metas, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    ))
+}
+
+/// Build a `SET_MISSING_RECORD_POLICY` instruction
+pub fn set_missing_record_policy(
+    program_id: &Pubkey,
+    mint_authority: &Pubkey,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    policy: MissingRecordPolicy,
+) -> Instruction {
+    let (policy_address, _) = find_missing_record_policy_address(mint, program_id);
+    Instruction::new_with_bytes(
+        *program_id,
+        &[SET_MISSING_RECORD_POLICY, policy as u8],
+        vec![
+            AccountMeta::new_readonly(*mint_authority, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(policy_address, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Build an `INITIALIZE` instruction making `authority`, which must be the
+/// mint authority, the block list's authority
+pub fn initialize(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &[INITIALIZE],
+        vec![
+            AccountMeta::new(config_address(mint, program_id).address(), false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Build a `BLOCK_USER` instruction blocking `user` for `reason`, until
+/// `expiry_timestamp` if one is given
+pub fn block_user(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    user: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+    reason: BlockReason,
+    expiry_timestamp: Option<i64>,
+) -> Instruction {
+    let args = BlockUserArgs {
+        reason,
+        expiry_timestamp,
+    };
+    let mut data = vec![BLOCK_USER];
+    data.extend(args.try_to_vec().expect("args always serialize"));
+    Instruction::new_with_bytes(
+        *program_id,
+        &data,
+        vec![
+            AccountMeta::new_readonly(config_address(mint, program_id).address(), false),
+            AccountMeta::new(block_list_address(mint, user, program_id).address(), false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(*user, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Build an `UNBLOCK_USER` instruction
+pub fn unblock_user(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    user: &Pubkey,
+    authority: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &[UNBLOCK_USER],
+        vec![
+            AccountMeta::new_readonly(config_address(mint, program_id).address(), false),
+            AccountMeta::new(block_list_address(mint, user, program_id).address(), false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+    )
+}
+
+/// Build an `UPDATE_AUTHORITY` instruction proposing `proposed_authority`,
+/// with `payer` paying for the pending-authority PDA
+pub fn update_authority(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    current_authority: &Pubkey,
+    proposed_authority: &Pubkey,
+    payer: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &[UPDATE_AUTHORITY],
+        vec![
+            AccountMeta::new_readonly(config_address(mint, program_id).address(), false),
+            AccountMeta::new_readonly(*current_authority, true),
+            AccountMeta::new_readonly(*proposed_authority, false),
+            AccountMeta::new(pending_authority_address(mint, program_id).address(), false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Build an `ACCEPT_AUTHORITY` instruction, signed by the proposed
+/// authority, refunding the pending-authority PDA's rent to `recipient`
+pub fn accept_authority(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    proposed_authority: &Pubkey,
+    recipient: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &[ACCEPT_AUTHORITY],
+        vec![
+            AccountMeta::new(config_address(mint, program_id).address(), false),
+            AccountMeta::new(pending_authority_address(mint, program_id).address(), false),
+            AccountMeta::new_readonly(*proposed_authority, true),
+            AccountMeta::new(*recipient, false),
+        ],
+    )
+}
+
+/// Build a `CANCEL_AUTHORITY_TRANSFER` instruction, signed by the current
+/// authority, refunding the pending-authority PDA's rent to `recipient`
+pub fn cancel_authority_transfer(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    authority: &Pubkey,
+    recipient: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &[CANCEL_AUTHORITY_TRANSFER],
+        vec![
+            AccountMeta::new_readonly(config_address(mint, program_id).address(), false),
+            AccountMeta::new(pending_authority_address(mint, program_id).address(), false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*recipient, false),
+        ],
+    )
+}
+
+/// Create the freeze extra-account-metas PDA of a mint
+///
+/// The initial list (version 1) is [`freeze_extra_account_metas`], so anyone
+/// may pay to create it. Accounts:
+/// 0. payer (signer, writable)
+/// 1. freeze extra-account-metas (writable)
+/// 2. mint
+/// 3. system program
+fn process_initialize_freeze_extra_account_metas(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let payer = next_account_info(account_info_iter)?;
+    let extra_account_metas = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    extra_account_metas::create_list(
+        ListKind::Freeze,
+        program_id,
+        &GATE_INTERFACE_VERSION,
+        &freeze_extra_account_metas()?,
+        payer,
+        extra_account_metas,
+        mint,
+        system_program,
+    )?;
+
+    msg!(
+        "Freeze extra-account-metas initialized for mint: {}",
+        mint.key
+    );
+    Ok(())
+}
+
+/// Create the thaw extra-account-metas PDA of a mint
+///
+/// The list is [`thaw_extra_account_metas`], so anyone may pay to create
+/// it. It never changes, so the thaw check does not verify its accounts
+/// against it. Accounts:
+/// 0. payer (signer, writable)
+/// 1. thaw extra-account-metas (writable)
+/// 2. mint
+/// 3. system program
+fn process_initialize_thaw_extra_account_metas(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let payer = next_account_info(account_info_iter)?;
+    let extra_account_metas = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    extra_account_metas::create_list(
+        ListKind::Thaw,
+        program_id,
+        &GATE_INTERFACE_VERSION,
+        &thaw_extra_account_metas()?,
+        payer,
+        extra_account_metas,
+        mint,
+        system_program,
+    )?;
+
+    msg!(
+        "Thaw extra-account-metas initialized for mint: {}",
+        mint.key
+    );
+    Ok(())
+}
+
+/// Replace a mint's freeze extra-account-metas, e.g. to add an oracle or
+/// registry account the gate has started reading
+///
+/// Only the mint authority may update, and only the version it read
+/// (`expected_version`), so two concurrent updates cannot silently clobber
+/// each other. The block list record must stay the first extra account.
+/// Accounts:
+/// 0. mint authority (signer)
+/// 1. payer (signer, writable), tops up rent if the list grew
+/// 2. freeze extra-account-metas (writable)
+/// 3. mint
+/// 4. system program
+fn process_update_freeze_extra_account_metas(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let authority = next_account_info(account_info_iter)?;
+    let payer = next_account_info(account_info_iter)?;
+    let extra_account_metas = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if data.len() < 8 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let (expected_version, metas) = data.split_at(8);
+    let expected_version = u64::from_le_bytes(
+        expected_version
+            .try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+    let metas = extra_account_metas::decode_metas(metas)?;
+    if metas.first() != freeze_extra_account_metas()?.first() {
+        msg!("The block list record must stay the first extra account");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    extra_account_metas::verify_list_address(
+        ListKind::Freeze,
+        program_id,
+        extra_account_metas,
+        mint.key,
+    )?;
+
+    if extra_account_metas.owner != program_id || extra_account_metas.data_is_empty() {
+        msg!("Freeze extra-account-metas not initialized");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    if mint_authority(mint)? != Some(*authority.key) {
+        msg!("{} is not the mint authority", authority.key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let version = extra_account_metas::update_list(
+        ListKind::Freeze,
+        program_id,
+        &GATE_INTERFACE_VERSION,
+        expected_version,
+        &metas,
+        payer,
+        extra_account_metas,
+        mint,
+        system_program,
+    )?;
+
+    msg!(
+        "Freeze extra-account-metas for mint {} updated to version {}",
+        mint.key,
+        version
+    );
+    Ok(())
+}
+
+/// Set how `can-thaw-permissionless` treats holders without a record
+///
+/// Data: the policy byte (0 = deny, 1 = allow). Only the mint authority may
+/// set it; until it does, holders without a record are thawed.
+/// Accounts:
+/// 0. mint authority (signer)
+/// 1. payer (signer, writable)
+/// 2. missing-record policy PDA (writable)
+/// 3. mint
+/// 4. system program
+fn process_set_missing_record_policy(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let authority = next_account_info(account_info_iter)?;
+    let payer = next_account_info(account_info_iter)?;
+    let policy_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    let policy =
+        MissingRecordPolicy::unpack(data).map_err(|_| ProgramError::InvalidInstructionData)?;
+    // There is no review queue here to decide on a held holder
+    if policy == MissingRecordPolicy::Review {
+        msg!("The block list does not review holders");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    if mint_authority(mint)? != Some(*authority.key) {
+        msg!("{} is not the mint authority", authority.key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    policy.store(program_id, policy_account, mint.key, payer, system_program)?;
+
+    msg!(
+        "Missing-record policy for mint {} set to {:?}",
+        mint.key,
+        policy
+    );
+    Ok(())
+}
+
+/// Create a mint's config, making the mint authority the block list's
+/// authority
+///
+/// Accounts:
+/// 0. config PDA (writable)
+/// 1. mint
+/// 2. mint authority (signer)
+/// 3. payer (signer, writable)
+/// 4. system program
+fn process_initialize(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+    let payer = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    check_system_program(system_program)?;
+
+    let config_pda = config_address(mint.key, program_id);
+    config_pda.verify(config_account.key)?;
+
+    if mint_authority(mint)? != Some(*authority.key) {
+        msg!("{} is not the mint authority", authority.key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !config_account.data_is_empty() {
+        msg!("Block list for mint {} already initialized", mint.key);
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let config = Config {
+        authority: *authority.key,
+        mint: *mint.key,
+        bump: config_pda.bump(),
+    };
+    let config_data = config.try_to_vec()?;
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            config_account.key,
+            Rent::get()?.minimum_balance(config_data.len()),
+            config_data.len() as u64,
+            program_id,
+        ),
+        &[
+            payer.clone(),
+            config_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[CONFIG_SEED, mint.key.as_ref(), &[config_pda.bump()]]],
+    )?;
+    store_account_data(config_account, &config_data)?;
+
+    msg!("Block list initialized for mint: {}", mint.key);
+    Ok(())
+}
+
+/// The config in `config_account`, which `authority` must have signed for
+fn load_config(
+    program_id: &Pubkey,
+    config_account: &AccountInfo,
+    authority: &AccountInfo,
+) -> Result<Config, ProgramError> {
+    if config_account.owner != program_id {
+        msg!("Config must be owned by this program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let config = Config::try_from_slice(&config_account.try_borrow_data()?)?;
+    // Ownership alone would accept a config at a non-canonical address
+    config_address(&config.mint, program_id).verify(config_account.key)?;
+
+    if *authority.key != config.authority {
+        msg!("Invalid authority");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    Ok(config)
+}
+
+/// The block list record in `record_account`, which must be the canonical
+/// record of a user for the config's mint
+fn load_record(
+    program_id: &Pubkey,
+    config: &Config,
+    record_account: &AccountInfo,
+) -> Result<BlockListRecord, ProgramError> {
+    if record_account.owner != program_id || record_account.data_is_empty() {
+        msg!("No block list record at {}", record_account.key);
+        return Err(ProgramError::UninitializedAccount);
+    }
+    let record = BlockListRecord::unpack(&record_account.try_borrow_data()?)?;
+    if record.mint != config.mint {
+        msg!("Block list record belongs to a different mint");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    block_list_address(&record.mint, &record.user, program_id).verify(record_account.key)?;
+    Ok(record)
+}
+
+/// Block a user, creating their record or re-blocking one whose block was
+/// lifted or has lapsed
+///
+/// Data: [`BlockUserArgs`].
+/// Accounts:
+/// 0. config
+/// 1. block list PDA (writable)
+/// 2. mint
+/// 3. user
+/// 4. authority (signer)
+/// 5. payer (signer, writable)
+/// 6. system program
+fn process_block_user(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let record_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let user = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+    let payer = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    check_system_program(system_program)?;
+
+    let args = BlockUserArgs::unpack(data)?;
+    let added_timestamp = Clock::get()?.unix_timestamp;
+    args.validate(added_timestamp)?;
+    let config = load_config(program_id, config_account, authority)?;
+    if *mint.key != config.mint {
+        msg!("Config belongs to a different mint");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let record_pda = block_list_address(mint.key, user.key, program_id);
+    record_pda.verify(record_account.key)?;
+
+    if !record_account.data_is_empty() {
+        let mut record = load_record(program_id, &config, record_account)?;
+        if record.is_active(added_timestamp) {
+            msg!("User {} is already blocked", user.key);
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        record.blocked = true;
+        record.reason = args.reason;
+        record.added_timestamp = added_timestamp;
+        record.expiry_timestamp = args.expiry_timestamp;
+        let record_data = record.pack_for(record_account)?;
+        if record_data.len() != record_account.data_len() {
+            // Gaining or dropping an expiry changes the record's size
+            let shortfall = Rent::get()?
+                .minimum_balance(record_data.len())
+                .saturating_sub(record_account.lamports());
+            if shortfall > 0 {
+                invoke(
+                    &system_instruction::transfer(payer.key, record_account.key, shortfall),
+                    &[
+                        payer.clone(),
+                        record_account.clone(),
+                        system_program.clone(),
+                    ],
+                )?;
+            }
+            record_account.realloc(record_data.len(), false)?;
+        }
+        store_account_data(record_account, &record_data)?;
+        msg!("User {} blocked again for mint {}", user.key, mint.key);
+        return Ok(());
+    }
+
+    let mut record = create_block_list_record(mint.key, user.key, args.reason, added_timestamp);
+    record.expiry_timestamp = args.expiry_timestamp;
+    let record_data = record.try_to_vec()?;
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            record_account.key,
+            Rent::get()?.minimum_balance(record_data.len()),
+            record_data.len() as u64,
+            program_id,
+        ),
+        &[
+            payer.clone(),
+            record_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            BLOCK_LIST_SEED,
+            mint.key.as_ref(),
+            user.key.as_ref(),
+            &[record_pda.bump()],
+        ]],
+    )?;
+    store_account_data(record_account, &record_data)?;
+
+    msg!("User {} blocked for mint {}", user.key, mint.key);
+    Ok(())
+}
+
+/// Unblock a user
+///
+/// The record stays, with `blocked = false`, as the history of the block;
+/// the user thaws like one who was never blocked.
+/// Accounts:
+/// 0. config
+/// 1. block list PDA (writable)
+/// 2. authority (signer)
+fn process_unblock_user(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let record_account = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+
+    let config = load_config(program_id, config_account, authority)?;
+    let mut record = load_record(program_id, &config, record_account)?;
+    if !record.blocked {
+        msg!("User {} is not blocked", record.user);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    record.blocked = false;
+    store_account_data(record_account, &record.pack_for(record_account)?)?;
+
+    msg!("User {} unblocked for mint {}", record.user, record.mint);
+    Ok(())
+}
+
+/// Propose a new authority for the block list
+///
+/// Nothing changes hands yet: the proposal is stored in the mint's
+/// pending-authority PDA and the current authority keeps the list until the
+/// proposed key signs `ACCEPT_AUTHORITY`. A later proposal replaces an
+/// earlier one, and `CANCEL_AUTHORITY_TRANSFER` withdraws it.
+/// Accounts:
+/// 0. config
+/// 1. current authority (signer)
+/// 2. proposed authority
+/// 3. pending-authority PDA (writable)
+/// 4. payer (signer, writable)
+/// 5. system program
+fn process_update_authority(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let current_authority = next_account_info(account_info_iter)?;
+    let proposed_authority = next_account_info(account_info_iter)?;
+    let pending_account = next_account_info(account_info_iter)?;
+    let payer = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    check_system_program(system_program)?;
+
+    let config = load_config(program_id, config_account, current_authority)?;
+    let pending_pda = pending_authority_address(&config.mint, program_id);
+    pending_pda.verify(pending_account.key)?;
+
+    let pending_data = PendingAuthority {
+        mint: config.mint,
+        proposed: *proposed_authority.key,
+        bump: pending_pda.bump(),
+    }
+    .try_to_vec()?;
+    if pending_account.data_is_empty() {
+        invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                pending_account.key,
+                Rent::get()?.minimum_balance(pending_data.len()),
+                pending_data.len() as u64,
+                program_id,
+            ),
+            &[
+                payer.clone(),
+                pending_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                PENDING_AUTHORITY_SEED,
+                config.mint.as_ref(),
+                &[pending_pda.bump()],
+            ]],
+        )?;
+    } else if pending_account.owner != program_id {
+        msg!("Pending authority must be owned by this program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    store_account_data(pending_account, &pending_data)?;
+
+    msg!(
+        "Block list authority for mint {} proposed to {}",
+        config.mint,
+        proposed_authority.key
+    );
+    Ok(())
+}
+
+/// The pending authority in `pending_account`, which must be the canonical
+/// pending-authority PDA of the config's mint
+fn load_pending_authority(
+    program_id: &Pubkey,
+    config: &Config,
+    pending_account: &AccountInfo,
+) -> Result<PendingAuthority, ProgramError> {
+    if pending_account.owner != program_id || pending_account.data_is_empty() {
+        msg!("No authority transfer is pending");
+        return Err(ProgramError::UninitializedAccount);
+    }
+    let pending = PendingAuthority::try_from_slice(&pending_account.try_borrow_data()?)?;
+    if pending.mint != config.mint {
+        msg!("Pending authority belongs to a different mint");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    pending_authority_address(&config.mint, program_id).verify(pending_account.key)?;
+    Ok(pending)
+}
+
+/// Zero the pending-authority account, hand it back to the system program
+/// and refund its lamports to `recipient`
+fn close_pending_authority(
+    pending_account: &AccountInfo,
+    recipient: &AccountInfo,
+) -> ProgramResult {
+    if recipient.key == pending_account.key {
+        msg!("Rent recipient cannot be the pending authority being closed");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let refund = pending_account.lamports();
+    **recipient.try_borrow_mut_lamports()? = recipient
+        .lamports()
+        .checked_add(refund)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **pending_account.try_borrow_mut_lamports()? = 0;
+
+    pending_account.try_borrow_mut_data()?.fill(0);
+    pending_account.realloc(0, false)?;
+    pending_account.assign(&system_program::id());
+    Ok(())
+}
+
+/// Take over the block list as the proposed authority
+///
+/// The proposed key must sign, which proves it is held by someone. The
+/// pending-authority PDA is closed and its rent refunded to the recipient.
+/// Accounts:
+/// 0. config (writable)
+/// 1. pending-authority PDA (writable)
+/// 2. proposed authority (signer)
+/// 3. rent recipient (writable)
+fn process_accept_authority(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let pending_account = next_account_info(account_info_iter)?;
+    let proposed_authority = next_account_info(account_info_iter)?;
+    let recipient = next_account_info(account_info_iter)?;
+
+    if config_account.owner != program_id {
+        msg!("Config must be owned by this program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let mut config = Config::try_from_slice(&config_account.try_borrow_data()?)?;
+    config_address(&config.mint, program_id).verify(config_account.key)?;
+
+    let pending = load_pending_authority(program_id, &config, pending_account)?;
+    if *proposed_authority.key != pending.proposed {
+        msg!("{} is not the proposed authority", proposed_authority.key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !proposed_authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    close_pending_authority(pending_account, recipient)?;
+    config.authority = pending.proposed;
+    store_account_data(config_account, &config.try_to_vec()?)?;
+
+    msg!(
+        "Block list authority for mint {} handed to {}",
+        config.mint,
+        proposed_authority.key
+    );
+    Ok(())
+}
+
+/// Withdraw a proposed authority transfer
+///
+/// Only the current authority may cancel. The pending-authority PDA is
+/// closed and its rent refunded to the recipient.
+/// Accounts:
+/// 0. config
+/// 1. pending-authority PDA (writable)
+/// 2. authority (signer)
+/// 3. rent recipient (writable)
+fn process_cancel_authority_transfer(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let pending_account = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+    let recipient = next_account_info(account_info_iter)?;
+
+    let config = load_config(program_id, config_account, authority)?;
+    let pending = load_pending_authority(program_id, &config, pending_account)?;
+    close_pending_authority(pending_account, recipient)?;
+
+    msg!("Authority transfer to {} cancelled", pending.proposed);
+    Ok(())
+}
+
+/// Owner of an SPL Token / Token-2022 account, which must hold `mint`
+///
+/// Both programs lay out the mint in bytes 0..32 and the owner in 32..64.
+fn token_account_owner(
+    token_account: &AccountInfo,
+    mint: &AccountInfo,
+) -> Result<Pubkey, ProgramError> {
+    let data = token_account.try_borrow_data()?;
+    if data.len() < 64 || data[..32] != mint.key.to_bytes() {
+        msg!("Token account does not hold mint {}", mint.key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Pubkey::try_from(&data[32..64]).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+/// Mint authority of an SPL Token / Token-2022 mint, if it has one
+fn mint_authority(mint: &AccountInfo) -> Result<Option<Pubkey>, ProgramError> {
+    let data = mint.try_borrow_data()?;
+    if data.len() < MINT_AUTHORITY_END {
+        msg!("Invalid mint account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    match data[..4] {
+        [0, 0, 0, 0] => Ok(None),
+        [1, 0, 0, 0] => Pubkey::try_from(&data[4..MINT_AUTHORITY_END])
+            .map(Some)
+            .map_err(|_| ProgramError::InvalidAccountData),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
 fn process_can_thaw_permissionless(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
 
-    // Accounts as per sRFC 37 interface
+    // Accounts as per sRFC 37 interface, then the owner, their block list
+    // record and the missing-record policy PDA
     let _caller = next_account_info(account_info_iter)?;
-    let _token_account = next_account_info(account_info_iter)?;
+    let token_account = next_account_info(account_info_iter)?;
     let mint = next_account_info(account_info_iter)?;
     let _extra_account_metas = next_account_info(account_info_iter)?;
     let token_account_owner = next_account_info(account_info_iter)?;
     let block_list_pda = next_account_info(account_info_iter)?;
+    let policy_account = next_account_info(account_info_iter)?;
+
+    // The record is looked up by the supplied owner, so a blocked holder
+    // could otherwise name a clean owner and be thawed on their record
+    let owner = self::token_account_owner(token_account, mint)?;
+    if owner != *token_account_owner.key {
+        msg!(
+            "Token account {} is not owned by {} for this mint",
+            token_account.key,
+            token_account_owner.key
+        );
+        return Err(ProgramError::InvalidAccountData);
+    }
 
     // Verify block list PDA derivation
-    let (expected_pda, _bump) = Pubkey::find_program_address(
-        &[
-            BLOCK_LIST_SEED,
-            mint.key.as_ref(),
-            token_account_owner.key.as_ref(),
-        ],
-        program_id,
-    );
+    let expected_pda = block_list_address(mint.key, token_account_owner.key, program_id);
 
-    if *block_list_pda.key != expected_pda {
+    if *block_list_pda.key != expected_pda.address() {
         msg!("Invalid block list PDA");
         return Err(ProgramError::InvalidAccountData);
     }
 
-    // For thaw: Allow if user is NOT in block list
+    // For thaw: Allow if user is NOT in block list, unless the issuer
+    // requires a record
     if block_list_pda.data_is_empty() {
+        let policy = MissingRecordPolicy::load(
+            program_id,
+            policy_account,
+            mint.key,
+            DEFAULT_MISSING_RECORD_POLICY,
+        )?;
+        if !policy.permits_thaw() {
+            msg!(
+                "❌ User {} has no block list record and missing records are denied - permissionless thaw denied",
+                token_account_owner.key
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
         msg!(
             "✅ User {} not in block list - permissionless thaw authorized",
             token_account_owner.key
@@ -102,9 +1267,9 @@ fn process_can_thaw_permissionless(program_id: &Pubkey, accounts: &[AccountInfo]
         return Ok(());
     }
 
-    let record = BlockListRecord::try_from_slice(&block_list_pda.data.borrow())?;
+    let record = BlockListRecord::unpack(&block_list_pda.try_borrow_data()?)?;
 
-    if record.blocked {
+    if record.is_active_now()? {
         msg!(
             "❌ User {} is blocked (reason: {:?}) - permissionless thaw denied",
             token_account_owner.key,
@@ -126,25 +1291,33 @@ fn process_can_freeze_permissionless(
 ) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
 
-    // Accounts as per sRFC 37 interface
+    // Accounts as per sRFC 37 interface, then the extra accounts resolved
+    // from the freeze extra-account-metas (see `freeze_extra_account_metas`)
     let _caller = next_account_info(account_info_iter)?;
-    let _token_account = next_account_info(account_info_iter)?;
+    let token_account = next_account_info(account_info_iter)?;
     let mint = next_account_info(account_info_iter)?;
-    let _extra_account_metas = next_account_info(account_info_iter)?;
-    let token_account_owner = next_account_info(account_info_iter)?;
+    let extra_account_metas = next_account_info(account_info_iter)?;
     let block_list_pda = next_account_info(account_info_iter)?;
 
-    // Verify block list PDA derivation
-    let (expected_pda, _bump) = Pubkey::find_program_address(
-        &[
-            BLOCK_LIST_SEED,
-            mint.key.as_ref(),
-            token_account_owner.key.as_ref(),
-        ],
+    extra_account_metas::verify_list_address(
+        ListKind::Freeze,
         program_id,
-    );
+        extra_account_metas,
+        mint.key,
+    )?;
+    extra_account_metas::check_extra_accounts(
+        ListKind::Freeze,
+        program_id,
+        accounts,
+        0,
+        extra_account_metas,
+    )?;
+    let token_account_owner = token_account_owner(token_account, mint)?;
 
-    if *block_list_pda.key != expected_pda {
+    // Verify block list PDA derivation
+    let expected_pda = block_list_address(mint.key, &token_account_owner, program_id);
+
+    if *block_list_pda.key != expected_pda.address() {
         msg!("Invalid block list PDA");
         return Err(ProgramError::InvalidAccountData);
     }
@@ -153,24 +1326,32 @@ fn process_can_freeze_permissionless(
     if block_list_pda.data_is_empty() {
         msg!(
             "❌ User {} not in block list - permissionless freeze denied",
-            token_account_owner.key
+            token_account_owner
         );
         return Err(ProgramError::InvalidAccountData);
     }
 
-    let record = BlockListRecord::try_from_slice(&block_list_pda.data.borrow())?;
+    let record = BlockListRecord::unpack(&block_list_pda.try_borrow_data()?)?;
 
     if !record.blocked {
         msg!(
             "❌ User {} not blocked - permissionless freeze denied",
-            token_account_owner.key
+            token_account_owner
+        );
+        return Err(ProgramError::InvalidAccountData);
+    }
+    // A lapsed block freezes nobody, even before the authority unblocks
+    if !record.is_active_now()? {
+        msg!(
+            "❌ Block on user {} has expired - permissionless freeze denied",
+            token_account_owner
         );
         return Err(ProgramError::InvalidAccountData);
     }
 
     msg!(
         "✅ User {} is blocked (reason: {:?}) - permissionless freeze authorized",
-        token_account_owner.key,
+        token_account_owner,
         record.reason
     );
     Ok(())
@@ -189,6 +1370,7 @@ pub fn create_block_list_record(
         blocked: true,
         reason,
         added_timestamp: timestamp,
+        expiry_timestamp: None,
     }
 }
 
@@ -211,6 +1393,188 @@ mod tests {
         assert_eq!(deserialized.reason, BlockReason::Sanctions);
     }
 
+    #[test]
+    fn test_block_list_record_expiry() {
+        let mut record = create_block_list_record(
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            BlockReason::Compliance,
+            0,
+        );
+        assert!(record.is_active(i64::MAX));
+
+        record.expiry_timestamp = Some(2000);
+        assert!(record.is_active(2000)); // In force through the expiry
+        assert!(!record.is_active(2001)); // Lapsed
+        assert!(record.is_expired(2001));
+
+        record.blocked = false;
+        assert!(!record.is_active(1000));
+    }
+
+    #[test]
+    fn test_legacy_block_list_record() {
+        let mint = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let mut record =
+            create_block_list_record(&mint, &user, BlockReason::RiskAssessment, 1234567890);
+
+        // Written before records could expire: no expiry byte at all
+        let mut legacy = record.try_to_vec().unwrap();
+        legacy.pop();
+        assert_eq!(legacy.len(), BlockListRecord::LEGACY_LEN);
+        assert!(BlockListRecord::try_from_slice(&legacy).is_err());
+        let decoded = BlockListRecord::unpack(&legacy).unwrap();
+        assert_eq!(decoded.user, user);
+        assert_eq!(decoded.added_timestamp, 1234567890);
+        assert_eq!(decoded.expiry_timestamp, None);
+        assert!(decoded.is_active(i64::MAX));
+
+        record.expiry_timestamp = Some(2000);
+        let current = record.try_to_vec().unwrap();
+        assert_eq!(
+            BlockListRecord::unpack(&current).unwrap().expiry_timestamp,
+            Some(2000)
+        );
+        assert!(BlockListRecord::unpack(&current[..current.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_block_user_args() {
+        // A bare reason byte blocks indefinitely
+        assert_eq!(
+            BlockUserArgs::unpack(&[BlockReason::Other as u8]).unwrap(),
+            BlockUserArgs {
+                reason: BlockReason::Other,
+                expiry_timestamp: None,
+            }
+        );
+        let args = BlockUserArgs {
+            reason: BlockReason::Compliance,
+            expiry_timestamp: Some(2000),
+        };
+        assert_eq!(
+            BlockUserArgs::unpack(&args.try_to_vec().unwrap()).unwrap(),
+            args
+        );
+        // No reason, a reason out of range, a truncated expiry
+        for data in [&[][..], &[9], &[1, 1, 0]] {
+            assert_eq!(
+                BlockUserArgs::unpack(data),
+                Err(ProgramError::InvalidInstructionData)
+            );
+        }
+
+        assert!(args.validate(1999).is_ok());
+        assert_eq!(args.validate(2000), Err(ProgramError::InvalidArgument));
+    }
+
+    #[test]
+    fn test_admin_discriminators_leave_interface_reachable() {
+        for admin in [
+            INITIALIZE_FREEZE_EXTRA_ACCOUNT_METAS,
+            UPDATE_FREEZE_EXTRA_ACCOUNT_METAS,
+            SET_MISSING_RECORD_POLICY,
+            INITIALIZE_THAW_EXTRA_ACCOUNT_METAS,
+            INITIALIZE,
+            BLOCK_USER,
+            UNBLOCK_USER,
+            UPDATE_AUTHORITY,
+            ACCEPT_AUTHORITY,
+            CANCEL_AUTHORITY_TRANSFER,
+        ] {
+            assert_ne!(admin, CAN_THAW_PERMISSIONLESS_DISCRIMINATOR[0]);
+            assert_ne!(admin, CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR[0]);
+        }
+    }
+
+    #[test]
+    fn test_freeze_extra_account_metas_resolve_block_list_pda() {
+        let program_id = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mut token_data = [0; 165];
+        token_data[..32].copy_from_slice(mint.as_ref());
+        token_data[32..64].copy_from_slice(owner.as_ref());
+
+        let metas = freeze_extra_account_metas().unwrap();
+        assert_eq!(metas.len(), 1);
+        let resolved = metas[0]
+            .resolve(
+                &CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR,
+                &program_id,
+                |index| match index {
+                    1 => Some((&token_account, Some(&token_data[..]))),
+                    2 => Some((&mint, None)),
+                    _ => None,
+                },
+            )
+            .unwrap();
+
+        let (expected, _) = Pubkey::find_program_address(
+            &[BLOCK_LIST_SEED, mint.as_ref(), owner.as_ref()],
+            &program_id,
+        );
+        assert_eq!(resolved.pubkey, expected);
+        assert!(!resolved.is_signer);
+        assert!(!resolved.is_writable);
+    }
+
+    #[test]
+    fn test_versioned_freeze_extra_account_metas() {
+        let metas = freeze_extra_account_metas().unwrap();
+        let mut data = vec![0; freeze_extra_account_metas_size(metas.len()).unwrap()];
+        pack_freeze_extra_account_metas(&mut data, 7, &metas).unwrap();
+
+        assert_eq!(freeze_extra_account_metas_version(&data).unwrap(), 7);
+        assert_eq!(
+            GateInterfaceVersion::from_extra_account_metas(&data).unwrap(),
+            GATE_INTERFACE_VERSION
+        );
+        assert_eq!(
+            extra_account_metas::list_metas(ListKind::Freeze, &data).unwrap(),
+            metas
+        );
+    }
+
+    #[test]
+    fn test_thaw_extra_account_metas_resolve_record_and_policy() {
+        let program_id = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mut token_data = [0; 165];
+        token_data[..32].copy_from_slice(mint.as_ref());
+        token_data[32..64].copy_from_slice(owner.as_ref());
+
+        let resolved: Vec<Pubkey> = thaw_extra_account_metas()
+            .unwrap()
+            .iter()
+            .map(|meta| {
+                meta.resolve(
+                    &CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+                    &program_id,
+                    |index| match index {
+                        1 => Some((&token_account, Some(&token_data[..]))),
+                        2 => Some((&mint, None)),
+                        _ => None,
+                    },
+                )
+                .unwrap()
+                .pubkey
+            })
+            .collect();
+
+        assert_eq!(
+            resolved,
+            [
+                block_list_address(&mint, &owner, &program_id).address(),
+                find_missing_record_policy_address(&mint, &program_id).0,
+            ]
+        );
+    }
+
     #[test]
     fn test_discriminators() {
         // Verify discriminators match sRFC 37 spec
@@ -0,0 +1,283 @@
+// `entrypoint!` expands to cfgs only known to the BPF toolchain.
+#![allow(unexpected_cfgs)]
+
+use borsh::{BorshDeserialize, BorshSerialize};
+/// Example Lending Consumer Program
+///
+/// A program that is not a gate but wants the gates' answer: a lending
+/// market that only lends to holders the issuer has allow-listed and not
+/// blocked. It reads the records through `gate_view`, from accounts the
+/// borrower passes in, without a CPI into either gate.
+///
+/// - The market pins the allow list and block list programs it trusts when
+///   it is created; records are only read under those programs
+/// - Check borrower: succeeds if the borrower has an active allow list
+///   record and is not blocked. A real market would go on to lend
+use gate_view::{is_allow_listed, is_blocked};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction, system_program,
+    sysvar::Sysvar,
+};
+use token_acl_interface::{check_system_program, CanonicalPda};
+
+// Instruction discriminators
+pub const INITIALIZE_MARKET: u8 = 0;
+pub const CHECK_BORROWER: u8 = 1;
+
+// Seeds
+pub const MARKET_SEED: &[u8] = b"market";
+
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
+
+/// A lending market for one mint, and the gates it trusts
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct Market {
+    pub mint: Pubkey,
+    pub allow_list_program: Pubkey,
+    pub block_list_program: Pubkey,
+    pub bump: u8,
+}
+
+/// Why a borrower was turned away
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorrowerError {
+    /// Blocked for the mint; the allow list is not consulted
+    Blocked = 0,
+    /// Without an active allow list record
+    NotAllowListed = 1,
+}
+
+impl From<BorrowerError> for ProgramError {
+    fn from(error: BorrowerError) -> Self {
+        ProgramError::Custom(error as u32)
+    }
+}
+
+/// Market PDA of `mint`
+pub fn market_address(mint: &Pubkey, program_id: &Pubkey) -> CanonicalPda {
+    CanonicalPda::find(&[MARKET_SEED, mint.as_ref()], program_id)
+}
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    match instruction_data {
+        [INITIALIZE_MARKET, data @ ..] => process_initialize_market(program_id, accounts, data),
+        [CHECK_BORROWER] => process_check_borrower(program_id, accounts),
+        _ => {
+            msg!("Unknown instruction");
+            Err(ProgramError::InvalidInstructionData)
+        }
+    }
+}
+
+/// Build an `InitializeMarket` instruction trusting the given gates
+///
+/// Accounts:
+/// 0. market PDA (writable)
+/// 1. mint
+/// 2. payer (writable, signer)
+/// 3. system program
+pub fn initialize_market(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    allow_list_program: &Pubkey,
+    block_list_program: &Pubkey,
+) -> Instruction {
+    let mut data = vec![INITIALIZE_MARKET];
+    data.extend_from_slice(allow_list_program.as_ref());
+    data.extend_from_slice(block_list_program.as_ref());
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(market_address(mint, program_id).address(), false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data,
+    }
+}
+
+/// Build a `CheckBorrower` instruction for `borrower`
+///
+/// Accounts:
+/// 0. market PDA
+/// 1. borrower (signer)
+/// 2. borrower's allow list record, which may be empty
+/// 3. borrower's block list record, which may be empty
+pub fn check_borrower(program_id: &Pubkey, market: &Market, borrower: &Pubkey) -> Instruction {
+    let allow_list_record =
+        gate_view::allow_list_address(&market.allow_list_program, &market.mint, borrower);
+    let block_list_record =
+        gate_view::block_list_address(&market.block_list_program, &market.mint, borrower);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(market_address(&market.mint, program_id).address(), false),
+            AccountMeta::new_readonly(*borrower, true),
+            AccountMeta::new_readonly(allow_list_record.address(), false),
+            AccountMeta::new_readonly(block_list_record.address(), false),
+        ],
+        data: vec![CHECK_BORROWER],
+    }
+}
+
+/// Create the market of a mint
+///
+/// Data: the allow list program id, then the block list program id.
+fn process_initialize_market(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let market_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let payer = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    check_system_program(system_program)?;
+
+    if data.len() != 64 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let key = |i: usize| {
+        Pubkey::try_from(&data[i * 32..(i + 1) * 32])
+            .map_err(|_| ProgramError::InvalidInstructionData)
+    };
+
+    let market_pda = market_address(mint.key, program_id);
+    market_pda.verify(market_account.key)?;
+    if !market_account.data_is_empty() {
+        msg!("Market already initialized");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let market = Market {
+        mint: *mint.key,
+        allow_list_program: key(0)?,
+        block_list_program: key(1)?,
+        bump: market_pda.bump(),
+    };
+
+    let market_data = market.try_to_vec()?;
+    let rent = Rent::get()?;
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            market_account.key,
+            rent.minimum_balance(market_data.len()),
+            market_data.len() as u64,
+            program_id,
+        ),
+        &[
+            payer.clone(),
+            market_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[MARKET_SEED, mint.key.as_ref(), &[market.bump]]],
+    )?;
+
+    market_account
+        .data
+        .borrow_mut()
+        .copy_from_slice(&market_data);
+
+    msg!("Lending market initialized for mint: {}", mint.key);
+    Ok(())
+}
+
+/// Let a borrower in if the gates say so
+fn process_check_borrower(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let market_account = next_account_info(account_info_iter)?;
+    let borrower = next_account_info(account_info_iter)?;
+    let allow_list_record = next_account_info(account_info_iter)?;
+    let block_list_record = next_account_info(account_info_iter)?;
+
+    if market_account.owner != program_id || market_account.data_is_empty() {
+        msg!("Invalid market");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let market = Market::try_from_slice(&market_account.data.borrow())?;
+    market_address(&market.mint, program_id).verify(market_account.key)?;
+
+    if !borrower.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // The records are only trusted under the programs the market pinned;
+    // gate_view rejects anything else at the record addresses
+    let now = Clock::get()?.unix_timestamp;
+    if is_blocked(
+        &market.block_list_program,
+        block_list_record,
+        &market.mint,
+        borrower.key,
+        now,
+    )? {
+        msg!("❌ Borrower {} is blocked", borrower.key);
+        return Err(BorrowerError::Blocked.into());
+    }
+    if !is_allow_listed(
+        &market.allow_list_program,
+        allow_list_record,
+        &market.mint,
+        borrower.key,
+        now,
+    )? {
+        msg!("❌ Borrower {} is not allow-listed", borrower.key);
+        return Err(BorrowerError::NotAllowListed.into());
+    }
+
+    msg!("✅ Borrower {} may borrow", borrower.key);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_borrower_accounts() {
+        let program_id = Pubkey::new_unique();
+        let market = Market {
+            mint: Pubkey::new_unique(),
+            allow_list_program: Pubkey::new_unique(),
+            block_list_program: Pubkey::new_unique(),
+            bump: 255,
+        };
+        let borrower = Pubkey::new_unique();
+        let instruction = check_borrower(&program_id, &market, &borrower);
+
+        assert_eq!(instruction.data, vec![CHECK_BORROWER]);
+        assert!(instruction.accounts[1].is_signer);
+        assert_eq!(
+            instruction.accounts[2].pubkey,
+            gate_view::allow_list_address(&market.allow_list_program, &market.mint, &borrower)
+                .address()
+        );
+        assert_eq!(
+            instruction.accounts[3].pubkey,
+            gate_view::block_list_address(&market.block_list_program, &market.mint, &borrower)
+                .address()
+        );
+        assert!(instruction.accounts.iter().all(|meta| !meta.is_writable));
+    }
+}
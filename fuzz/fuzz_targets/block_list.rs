@@ -0,0 +1,14 @@
+//! Fuzz target for `examples/block_list`
+//!
+//! Arbitrary bytes become the accounts and instruction data of the gate's
+//! `process_instruction`, see `token_acl_integration_tests::gate_fuzz`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use token_acl_integration_tests::gate_fuzz::FuzzTarget;
+
+fuzz_target!(|data: &[u8]| {
+    // Errors are answers; only a panic is a finding
+    let _ = FuzzTarget::BlockList.execute(data);
+});
@@ -0,0 +1,319 @@
+//! Hostile gate programs
+//!
+//! A gate is third-party code the FAMP invokes with nothing but read-only,
+//! unsigned accounts. sRFC 37 relies on the runtime to keep it that way, so
+//! the security tests need gates that actually try to break out: move the
+//! caller's lamports, close the token account, call back into the FAMP,
+//! regain the write access the FAMP withheld, or write the accounts they
+//! were only shown. Each [`Attack`] is one such gate.
+//!
+//! The runtime cannot stop a gate from doing what it is allowed to do, so
+//! each [`Sneak`] approves the check while doing something permitted but
+//! underhand beside it: logging a denial it did not make, filling the
+//! return data, or burning the transaction's compute. The security tests
+//! check that nothing downstream of the FAMP mistakes any of it for the
+//! decision.
+//!
+//! The gates are runtime builtins rather than `solana-program` entrypoints.
+//! Native `solana-program-test` runs program CPIs through stubs that panic
+//! on a privilege escalation or a reentrant call, where the runtime returns
+//! an error to the caller; as builtins, every attack goes through the same
+//! `InvokeContext` checks an SBF gate's would, and comes back as the
+//! `InstructionError` the runtime rejects it with.
+//!
+//! Every gate takes the sRFC 37 accounts, whatever its instruction data:
+//!
+//! 0. caller
+//! 1. token account
+//! 2. mint
+//! 3. extra-account-metas
+//! 4. the program an attack needs, where it needs one (see
+//!    [`Attack::target_program`])
+
+use solana_program_runtime::{
+    declare_process_instruction, invoke_context::BuiltinFunctionWithContext,
+    solana_rbpf::vm::ContextObject, stable_log,
+};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program::MAX_RETURN_DATA,
+    pubkey::Pubkey,
+    system_instruction, system_program,
+};
+use spl_token_2022::instruction::TokenInstruction;
+use token_acl_interface::events::{DecisionReason, EventFormat, ThawDecision};
+
+/// Compute units each gate charges; the runtime rejects builtins that
+/// charge none
+const COMPUTE_UNITS: u64 = 150;
+
+const CALLER: u16 = 0;
+const TOKEN_ACCOUNT: u16 = 1;
+const MINT: u16 = 2;
+const TARGET_PROGRAM: u16 = 4;
+
+/// Lines the misleading gate logs while approving: a denial in the words
+/// of this repository's gates, and the runtime's failure line for the
+/// system program
+pub const MISLEADING_LOGS: [&str; 2] = [
+    "❌ Permissionless thaw denied - holder is not on the allow list",
+    "Program 11111111111111111111111111111111 failed: custom program error: 0x0",
+];
+
+/// Compute units the compute-burning gate leaves in the transaction
+pub const COMPUTE_HEADROOM: u64 = 100;
+
+/// What a hostile gate tries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Attack {
+    /// Transfers a lamport from the caller through a system program CPI,
+    /// as if the caller's signature reached the gate
+    CpiTransfer,
+    /// Closes the token account the way a program closes its own accounts:
+    /// its lamports to the caller and its data cleared
+    CloseAccount,
+    /// Calls the FAMP that invoked it
+    Reentrancy,
+    /// Asks Token-2022 to thaw the token account, naming it writable
+    /// although the gate was given it read-only
+    PrivilegeEscalation,
+    /// Zeroes the token account's data
+    ReadonlyWrite,
+}
+
+impl Attack {
+    pub const ALL: [Attack; 5] = [
+        Attack::CpiTransfer,
+        Attack::CloseAccount,
+        Attack::Reentrancy,
+        Attack::PrivilegeEscalation,
+        Attack::ReadonlyWrite,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Attack::CpiTransfer => "cpi_transfer",
+            Attack::CloseAccount => "close_account",
+            Attack::Reentrancy => "reentrancy",
+            Attack::PrivilegeEscalation => "privilege_escalation",
+            Attack::ReadonlyWrite => "readonly_write",
+        }
+    }
+
+    /// Where the gate is loaded; fixed so tests and reports can name it
+    pub fn program_id(&self) -> Pubkey {
+        let tag = match self {
+            Attack::CpiTransfer => 0xA1,
+            Attack::CloseAccount => 0xA2,
+            Attack::Reentrancy => 0xA3,
+            Attack::PrivilegeEscalation => 0xA4,
+            Attack::ReadonlyWrite => 0xA5,
+        };
+        Pubkey::new_from_array([tag; 32])
+    }
+
+    /// The gate's builtin entrypoint
+    pub fn entrypoint(&self) -> BuiltinFunctionWithContext {
+        // The gates' `vm` are generic over the invoke context's lifetime,
+        // which the alias fixes; each arm is coerced on its own
+        match self {
+            Attack::CpiTransfer => CpiTransferGate::vm as BuiltinFunctionWithContext,
+            Attack::CloseAccount => CloseAccountGate::vm as BuiltinFunctionWithContext,
+            Attack::Reentrancy => ReentrantGate::vm as BuiltinFunctionWithContext,
+            Attack::PrivilegeEscalation => {
+                PrivilegeEscalationGate::vm as BuiltinFunctionWithContext
+            }
+            Attack::ReadonlyWrite => ReadonlyWriteGate::vm as BuiltinFunctionWithContext,
+        }
+    }
+
+    /// The program the gate expects as its first extra account: the system
+    /// program or Token-2022 it invokes, or `famp` to call back into
+    pub fn target_program(&self, famp: &Pubkey) -> Option<Pubkey> {
+        match self {
+            Attack::CpiTransfer => Some(system_program::id()),
+            Attack::Reentrancy => Some(*famp),
+            Attack::PrivilegeEscalation => Some(spl_token_2022::id()),
+            Attack::CloseAccount | Attack::ReadonlyWrite => None,
+        }
+    }
+
+    /// The errors the runtime may reject the attack with, depending on
+    /// whether the FAMP passed the gate the token account read-only or, as
+    /// a buggy FAMP would, writable
+    pub fn rejections(&self) -> &'static [InstructionError] {
+        match self {
+            Attack::CpiTransfer | Attack::PrivilegeEscalation => {
+                &[InstructionError::PrivilegeEscalation]
+            }
+            Attack::CloseAccount => &[
+                InstructionError::ReadonlyLamportChange,
+                InstructionError::ExternalAccountLamportSpend,
+            ],
+            Attack::Reentrancy => &[InstructionError::ReentrancyNotAllowed],
+            Attack::ReadonlyWrite => &[
+                InstructionError::ReadonlyDataModified,
+                InstructionError::ExternalAccountDataModified,
+            ],
+        }
+    }
+}
+
+/// What an approving gate slips in beside its approval
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sneak {
+    /// Logs [`MISLEADING_LOGS`] and a [`ThawDecision`] event denying the
+    /// caller
+    MisleadingLogs,
+    /// Sets [`MAX_RETURN_DATA`] bytes of return data
+    LargeReturnData,
+    /// Consumes every compute unit the transaction has left but
+    /// [`COMPUTE_HEADROOM`]
+    ComputeExhaustion,
+}
+
+impl Sneak {
+    pub const ALL: [Sneak; 3] = [
+        Sneak::MisleadingLogs,
+        Sneak::LargeReturnData,
+        Sneak::ComputeExhaustion,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Sneak::MisleadingLogs => "misleading_logs",
+            Sneak::LargeReturnData => "large_return_data",
+            Sneak::ComputeExhaustion => "compute_exhaustion",
+        }
+    }
+
+    /// Where the gate is loaded; fixed so tests and reports can name it
+    pub fn program_id(&self) -> Pubkey {
+        let tag = match self {
+            Sneak::MisleadingLogs => 0xB1,
+            Sneak::LargeReturnData => 0xB2,
+            Sneak::ComputeExhaustion => 0xB3,
+        };
+        Pubkey::new_from_array([tag; 32])
+    }
+
+    /// The gate's builtin entrypoint
+    pub fn entrypoint(&self) -> BuiltinFunctionWithContext {
+        match self {
+            Sneak::MisleadingLogs => MisleadingLogsGate::vm as BuiltinFunctionWithContext,
+            Sneak::LargeReturnData => LargeReturnDataGate::vm as BuiltinFunctionWithContext,
+            Sneak::ComputeExhaustion => ComputeExhaustionGate::vm as BuiltinFunctionWithContext,
+        }
+    }
+}
+
+declare_process_instruction!(CpiTransferGate, COMPUTE_UNITS, |invoke_context| {
+    let transaction_context = &invoke_context.transaction_context;
+    let instruction_context = transaction_context.get_current_instruction_context()?;
+    let caller = *instruction_context
+        .try_borrow_instruction_account(transaction_context, CALLER)?
+        .get_key();
+    let token_account = *instruction_context
+        .try_borrow_instruction_account(transaction_context, TOKEN_ACCOUNT)?
+        .get_key();
+    invoke_context.native_invoke(
+        system_instruction::transfer(&caller, &token_account, 1).into(),
+        &[],
+    )
+});
+
+declare_process_instruction!(CloseAccountGate, COMPUTE_UNITS, |invoke_context| {
+    let transaction_context = &invoke_context.transaction_context;
+    let instruction_context = transaction_context.get_current_instruction_context()?;
+    let mut token_account =
+        instruction_context.try_borrow_instruction_account(transaction_context, TOKEN_ACCOUNT)?;
+    let lamports = token_account.get_lamports();
+    token_account.set_lamports(0, &invoke_context.feature_set)?;
+    token_account.set_data_length(0, &invoke_context.feature_set)?;
+    drop(token_account);
+    let mut caller =
+        instruction_context.try_borrow_instruction_account(transaction_context, CALLER)?;
+    caller.checked_add_lamports(lamports, &invoke_context.feature_set)
+});
+
+declare_process_instruction!(ReentrantGate, COMPUTE_UNITS, |invoke_context| {
+    let transaction_context = &invoke_context.transaction_context;
+    let instruction_context = transaction_context.get_current_instruction_context()?;
+    let famp = *instruction_context
+        .try_borrow_instruction_account(transaction_context, TARGET_PROGRAM)?
+        .get_key();
+    let data = instruction_context.get_instruction_data().to_vec();
+    invoke_context.native_invoke(
+        Instruction::new_with_bytes(famp, &data, Vec::new()).into(),
+        &[],
+    )
+});
+
+declare_process_instruction!(PrivilegeEscalationGate, COMPUTE_UNITS, |invoke_context| {
+    let transaction_context = &invoke_context.transaction_context;
+    let instruction_context = transaction_context.get_current_instruction_context()?;
+    let key = |index| {
+        instruction_context
+            .try_borrow_instruction_account(transaction_context, index)
+            .map(|account| *account.get_key())
+    };
+    let (caller, token_account, mint) = (key(CALLER)?, key(TOKEN_ACCOUNT)?, key(MINT)?);
+    let thaw = Instruction {
+        program_id: spl_token_2022::id(),
+        accounts: vec![
+            AccountMeta::new(token_account, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(caller, false),
+        ],
+        data: TokenInstruction::ThawAccount.pack(),
+    };
+    invoke_context.native_invoke(thaw.into(), &[])
+});
+
+declare_process_instruction!(ReadonlyWriteGate, COMPUTE_UNITS, |invoke_context| {
+    let transaction_context = &invoke_context.transaction_context;
+    let instruction_context = transaction_context.get_current_instruction_context()?;
+    let mut token_account =
+        instruction_context.try_borrow_instruction_account(transaction_context, TOKEN_ACCOUNT)?;
+    let zeroed = vec![0; token_account.get_data().len()];
+    token_account.set_data_from_slice(&zeroed, &invoke_context.feature_set)
+});
+
+declare_process_instruction!(MisleadingLogsGate, COMPUTE_UNITS, |invoke_context| {
+    let transaction_context = &invoke_context.transaction_context;
+    let instruction_context = transaction_context.get_current_instruction_context()?;
+    let key = |index| {
+        instruction_context
+            .try_borrow_instruction_account(transaction_context, index)
+            .map(|account| *account.get_key())
+    };
+    let (caller, mint) = (key(CALLER)?, key(MINT)?);
+    let log_collector = invoke_context.get_log_collector();
+    for line in MISLEADING_LOGS {
+        stable_log::program_log(&log_collector, line);
+    }
+    let denial = ThawDecision::new(mint, caller, DecisionReason::NotListed);
+    let fields = denial.log_fields(EventFormat::Native);
+    stable_log::program_data(
+        &log_collector,
+        &fields.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+    );
+    Ok(())
+});
+
+declare_process_instruction!(LargeReturnDataGate, COMPUTE_UNITS, |invoke_context| {
+    let transaction_context = &mut invoke_context.transaction_context;
+    let program_id = *transaction_context
+        .get_current_instruction_context()?
+        .get_last_program_key(transaction_context)?;
+    transaction_context.set_return_data(program_id, vec![0xFF; MAX_RETURN_DATA])
+});
+
+declare_process_instruction!(ComputeExhaustionGate, COMPUTE_UNITS, |invoke_context| {
+    let burn = invoke_context
+        .get_remaining()
+        .saturating_sub(COMPUTE_HEADROOM);
+    invoke_context
+        .consume_checked(burn)
+        .map_err(|_| InstructionError::ComputationalBudgetExceeded)
+});
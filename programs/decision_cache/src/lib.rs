@@ -0,0 +1,478 @@
+// `entrypoint!` expands to cfgs only known to the BPF toolchain.
+#![allow(unexpected_cfgs)]
+
+use borsh::{BorshDeserialize, BorshSerialize};
+/**
+ * Gate Decision Cache Program (prototype)
+ *
+ * A wallet wants to show "this account can be thawed" without simulating
+ * the gate. This program keeps the last positive decision of a gate per
+ * (mint, owner) in a PDA the wallet reads in one call. The FAMP, or anyone,
+ * writes it with `RecordDecision`, which runs the gate's
+ * `can-thaw-permissionless` itself, so an entry exists only if the gate
+ * really approved; a denial fails the instruction and writes nothing.
+ *
+ * An entry records which extra accounts the gate read and a hash of what
+ * they held. Any list update (deactivation, revocation, a closed record, a
+ * new missing-record policy) changes that hash, and the entry reads as
+ * stale from then on; `Invalidate` lets anyone close such an entry.
+ * Changes the hash cannot see, like a record's expiry passing or an
+ * upgraded gate, are covered by a maximum age.
+ *
+ * The cache is advisory only. Nothing thaws because of an entry: the FAMP
+ * still runs the gate on every thaw, so a stale entry can mislead a wallet's
+ * display but never grant a thaw.
+ *
+ * Instructions:
+ * - RECORD_DECISION: run the gate's thaw check and cache the approval
+ * - INVALIDATE: close an entry whose accounts changed since
+ */
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    hash::Hasher,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction, system_program,
+    sysvar::Sysvar,
+};
+use token_acl_interface::{
+    self as interface, check_system_program, store_account_data, CanonicalPda,
+};
+
+// Instruction discriminators
+pub const RECORD_DECISION: u8 = 0;
+pub const INVALIDATE: u8 = 1;
+
+// Seeds
+pub const DECISION_SEED: &[u8] = b"decision";
+
+/// Age after which a wallet should treat an entry as stale even if its
+/// accounts are unchanged, since an expiry may have passed in between
+pub const MAX_DECISION_AGE: i64 = 3_600;
+
+// SPL Token account layout: mint (0..32), owner (32..64), ...
+const TOKEN_ACCOUNT_OWNER_OFFSET: usize = 32;
+
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
+
+/// The last approval of `gate_program` for `owner`'s account of `mint`
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DecisionEntry {
+    pub gate_program: Pubkey,
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    /// Paid the entry's rent; `Invalidate` refunds it here
+    pub payer: Pubkey,
+    /// Hash of the extra accounts' addresses, in the order the gate read them
+    pub accounts_hash: [u8; 32],
+    /// Hash of their owners and data when the gate approved
+    pub state_hash: [u8; 32],
+    pub decided_slot: u64,
+    pub decided_timestamp: i64,
+    pub bump: u8,
+}
+
+impl DecisionEntry {
+    pub const LEN: usize = 32 * 6 + 8 + 8 + 1;
+
+    /// Whether the entry still describes what the gate would decide, given
+    /// the extra accounts as they are now and the cluster time `now`
+    pub fn freshness(
+        &self,
+        extra_accounts: &[ObservedAccount],
+        now: i64,
+        max_age: i64,
+    ) -> Freshness {
+        if accounts_hash(extra_accounts) != self.accounts_hash {
+            Freshness::OtherAccounts
+        } else if state_hash(extra_accounts) != self.state_hash {
+            Freshness::ListChanged
+        } else if now.saturating_sub(self.decided_timestamp) > max_age {
+            Freshness::TooOld
+        } else {
+            Freshness::Fresh
+        }
+    }
+}
+
+/// What a reader can conclude from an entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Freshness {
+    /// Nothing the gate read has changed: the thaw should succeed
+    Fresh,
+    /// The extra accounts are not the ones the gate read, so the entry says
+    /// nothing about them
+    OtherAccounts,
+    /// An account the gate read has changed since it approved
+    ListChanged,
+    /// Unchanged, but older than the reader accepts
+    TooOld,
+}
+
+impl Freshness {
+    pub fn is_fresh(self) -> bool {
+        self == Freshness::Fresh
+    }
+}
+
+/// An extra account as the gate saw it
+///
+/// An account that does not exist is owned by the system program and has
+/// no data, which is how a reader should describe it too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObservedAccount<'a> {
+    pub key: &'a Pubkey,
+    pub owner: &'a Pubkey,
+    pub data: &'a [u8],
+}
+
+impl<'a> ObservedAccount<'a> {
+    pub fn new(key: &'a Pubkey, owner: &'a Pubkey, data: &'a [u8]) -> Self {
+        Self { key, owner, data }
+    }
+
+    /// An account that does not exist
+    pub fn missing(key: &'a Pubkey) -> Self {
+        Self {
+            key,
+            owner: &system_program::ID,
+            data: &[],
+        }
+    }
+}
+
+/// Hash of the accounts' addresses, in order
+pub fn accounts_hash(accounts: &[ObservedAccount]) -> [u8; 32] {
+    let mut hasher = Hasher::default();
+    for account in accounts {
+        hasher.hash(account.key.as_ref());
+    }
+    hasher.result().to_bytes()
+}
+
+/// Hash of the accounts' owners and data, in order
+///
+/// Lamports are left out: topping up a record does not change a decision.
+pub fn state_hash(accounts: &[ObservedAccount]) -> [u8; 32] {
+    let mut hasher = Hasher::default();
+    for account in accounts {
+        hasher.hash(account.owner.as_ref());
+        hasher.hash(&(account.data.len() as u64).to_le_bytes());
+        hasher.hash(account.data);
+    }
+    hasher.result().to_bytes()
+}
+
+/// Decision entry PDA of `owner` for `mint` under `gate_program`
+///
+/// The gate is part of the address, so an entry written through some other
+/// gate can never pass for the mint's own gate's.
+pub fn decision_address(
+    gate_program: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    program_id: &Pubkey,
+) -> CanonicalPda {
+    CanonicalPda::find(
+        &[
+            DECISION_SEED,
+            gate_program.as_ref(),
+            mint.as_ref(),
+            owner.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    match instruction_data {
+        [RECORD_DECISION] => process_record_decision(program_id, accounts),
+        [INVALIDATE] => process_invalidate(program_id, accounts),
+        _ => {
+            msg!("Unknown instruction");
+            Err(ProgramError::InvalidInstructionData)
+        }
+    }
+}
+
+/// Hashes of the accounts as they are now
+fn observe(accounts: &[AccountInfo]) -> Result<([u8; 32], [u8; 32]), ProgramError> {
+    let data = accounts
+        .iter()
+        .map(|account| account.try_borrow_data())
+        .collect::<Result<Vec<_>, _>>()?;
+    let observed: Vec<ObservedAccount> = accounts
+        .iter()
+        .zip(&data)
+        .map(|(account, data)| ObservedAccount::new(account.key, account.owner, data))
+        .collect();
+    Ok((accounts_hash(&observed), state_hash(&observed)))
+}
+
+/// Run the gate's thaw check and cache its approval
+///
+/// The gate is called through the interface's CPI helper, with every account
+/// read-only. An existing entry for the same gate, mint and owner is
+/// overwritten with the new decision.
+///
+/// Accounts:
+/// 0. decision entry PDA (writable)
+/// 1. payer (writable, signer)
+/// 2. system program
+/// 3. gate program
+/// 4. caller
+/// 5. token account
+/// 6. mint
+/// 7. thaw extra-account-metas
+/// 8. ... the gate's extra accounts
+fn process_record_decision(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let entry_account = next_account_info(account_info_iter)?;
+    let payer = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let gate_program = next_account_info(account_info_iter)?;
+    let caller = next_account_info(account_info_iter)?;
+    let token_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let extra_account_metas = next_account_info(account_info_iter)?;
+    let extra_accounts = account_info_iter.as_slice();
+    check_system_program(system_program)?;
+
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // The entry is keyed by the account's owner, so the account must exist
+    // and belong to the mint
+    let owner = {
+        let data = token_account.try_borrow_data()?;
+        if data.len() < TOKEN_ACCOUNT_OWNER_OFFSET + 32 || data[..32] != mint.key.to_bytes() {
+            msg!(
+                "{} is not a token account of mint {}",
+                token_account.key,
+                mint.key
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Pubkey::try_from(&data[TOKEN_ACCOUNT_OWNER_OFFSET..TOKEN_ACCOUNT_OWNER_OFFSET + 32])
+            .map_err(|_| ProgramError::InvalidAccountData)?
+    };
+
+    let entry_pda = decision_address(gate_program.key, mint.key, &owner, program_id);
+    if *entry_account.key != entry_pda.address() {
+        msg!("Invalid decision entry PDA");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // A denial ends the instruction here, before anything is written
+    interface::invoke_can_thaw_permissionless(
+        gate_program,
+        caller,
+        token_account,
+        mint,
+        extra_account_metas,
+        &interface::readonly_accounts(extra_accounts),
+    )?;
+
+    let (accounts_hash, state_hash) = observe(extra_accounts)?;
+    let clock = Clock::get()?;
+    let entry = DecisionEntry {
+        gate_program: *gate_program.key,
+        mint: *mint.key,
+        owner,
+        payer: *payer.key,
+        accounts_hash,
+        state_hash,
+        decided_slot: clock.slot,
+        decided_timestamp: clock.unix_timestamp,
+        bump: entry_pda.bump(),
+    };
+    let entry_data = entry.try_to_vec()?;
+
+    if entry_account.data_is_empty() {
+        let rent = Rent::get()?;
+        invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                entry_account.key,
+                rent.minimum_balance(entry_data.len()),
+                entry_data.len() as u64,
+                program_id,
+            ),
+            &[payer.clone(), entry_account.clone(), system_program.clone()],
+            &[&[
+                DECISION_SEED,
+                gate_program.key.as_ref(),
+                mint.key.as_ref(),
+                owner.as_ref(),
+                &[entry.bump],
+            ]],
+        )?;
+    } else if entry_account.owner != program_id {
+        msg!("Decision entry must be owned by this program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    store_account_data(entry_account, &entry_data)?;
+
+    msg!(
+        "Decision of gate {} for owner {} of mint {} cached at slot {}",
+        gate_program.key,
+        owner,
+        mint.key,
+        clock.slot
+    );
+    Ok(())
+}
+
+/// Close an entry whose extra accounts changed since the gate approved
+///
+/// Permissionless, so a keeper (or the issuer, right after a list update)
+/// can clear stale entries. The extra accounts must be the ones the gate
+/// read, in the same order, or anyone could close a fresh entry by passing
+/// others. The rent goes back to whoever paid it.
+///
+/// Accounts:
+/// 0. decision entry PDA (writable)
+/// 1. the entry's payer (writable)
+/// 2. ... the gate's extra accounts, as recorded
+fn process_invalidate(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let entry_account = next_account_info(account_info_iter)?;
+    let recipient = next_account_info(account_info_iter)?;
+    let extra_accounts = account_info_iter.as_slice();
+
+    if entry_account.owner != program_id {
+        msg!("Decision entry must be owned by this program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let entry = DecisionEntry::try_from_slice(&entry_account.try_borrow_data()?)?;
+    decision_address(&entry.gate_program, &entry.mint, &entry.owner, program_id)
+        .verify(entry_account.key)?;
+
+    if *recipient.key != entry.payer {
+        msg!("Rent goes back to the entry's payer {}", entry.payer);
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let (accounts_hash, state_hash) = observe(extra_accounts)?;
+    if accounts_hash != entry.accounts_hash {
+        msg!("Extra accounts are not the ones the gate read");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if state_hash == entry.state_hash {
+        msg!("Decision for owner {} is still fresh", entry.owner);
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let refund = entry_account.lamports();
+    **recipient.try_borrow_mut_lamports()? = recipient
+        .lamports()
+        .checked_add(refund)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **entry_account.try_borrow_mut_lamports()? = 0;
+
+    entry_account.try_borrow_mut_data()?.fill(0);
+    entry_account.realloc(0, false)?;
+    entry_account.assign(&system_program::id());
+
+    msg!(
+        "Stale decision for owner {} of mint {} invalidated",
+        entry.owner,
+        entry.mint
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(extra_accounts: &[ObservedAccount], decided_timestamp: i64) -> DecisionEntry {
+        DecisionEntry {
+            gate_program: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            payer: Pubkey::new_unique(),
+            accounts_hash: accounts_hash(extra_accounts),
+            state_hash: state_hash(extra_accounts),
+            decided_slot: 1,
+            decided_timestamp,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn test_entry_len() {
+        let entry = entry(&[], 0);
+        assert_eq!(entry.try_to_vec().unwrap().len(), DecisionEntry::LEN);
+    }
+
+    #[test]
+    fn test_freshness() {
+        let gate = Pubkey::new_unique();
+        let (record, policy) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let before = [
+            ObservedAccount::new(&record, &gate, &[1, 1]),
+            ObservedAccount::missing(&policy),
+        ];
+        let entry = entry(&before, 1_000);
+
+        assert_eq!(entry.freshness(&before, 1_000, 60), Freshness::Fresh);
+        assert_eq!(entry.freshness(&before, 1_060, 60), Freshness::Fresh);
+        assert_eq!(entry.freshness(&before, 1_061, 60), Freshness::TooOld);
+
+        // The record's data, its owner (closed), or the policy changed
+        for after in [
+            [ObservedAccount::new(&record, &gate, &[1, 0]), before[1]],
+            [ObservedAccount::missing(&record), before[1]],
+            [before[0], ObservedAccount::new(&policy, &gate, &[1])],
+        ] {
+            assert_eq!(entry.freshness(&after, 1_000, 60), Freshness::ListChanged);
+        }
+
+        // Same states under other addresses, or in another order
+        let other = Pubkey::new_unique();
+        assert_eq!(
+            entry.freshness(
+                &[ObservedAccount::new(&other, &gate, &[1, 1]), before[1]],
+                1_000,
+                60
+            ),
+            Freshness::OtherAccounts
+        );
+        assert_eq!(
+            entry.freshness(&[before[1], before[0]], 1_000, 60),
+            Freshness::OtherAccounts
+        );
+    }
+
+    #[test]
+    fn test_state_hash_separates_accounts() {
+        let owner = Pubkey::new_unique();
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        // Moving a byte from one account's data to the next changes the hash
+        assert_ne!(
+            state_hash(&[
+                ObservedAccount::new(&a, &owner, &[1, 2]),
+                ObservedAccount::new(&b, &owner, &[3]),
+            ]),
+            state_hash(&[
+                ObservedAccount::new(&a, &owner, &[1]),
+                ObservedAccount::new(&b, &owner, &[2, 3]),
+            ])
+        );
+    }
+}
@@ -0,0 +1,537 @@
+//! Thaw and freeze extra-account-metas lists for gate programs
+//!
+//! A gate publishes, per mint, which extra accounts its
+//! `can-thaw-permissionless` and `can-freeze-permissionless` checks take:
+//! the `thaw-extra-account-metas` and `freeze-extra-account-metas` PDAs
+//! (`[seed, mint]` under the gate). Each holds a TLV state with three
+//! entries:
+//!
+//! - the list's version ([`ExtraAccountMetasVersion`], u64 LE), 1 when
+//!   created and bumped by every update;
+//! - the [`GateInterfaceVersion`] the gate speaks;
+//! - the `ExtraAccountMetaList` of `spl-tlv-account-resolution`, keyed by
+//!   the check's discriminator.
+//!
+//! Gate programs create, update and check their lists with [`create_list`],
+//! [`update_list`] and [`check_extra_accounts`]. Off-chain callers read a
+//! list with [`ListState::unpack`] and complete a check with
+//! [`resolve_extra_accounts`].
+//!
+//! Account resolution can only derive PDAs and fixed keys. A thaw check
+//! that takes the token account's owner (as the example gates' do) has the
+//! caller supply it ahead of the listed accounts: the list then resolves
+//! everything after it, and gates count it as a leading account in
+//! [`check_extra_accounts`].
+
+use std::future::Future;
+
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::Instruction,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+use spl_discriminator::{ArrayDiscriminator, SplDiscriminate};
+use spl_pod::slice::{PodSlice, PodSliceMut};
+use spl_tlv_account_resolution::{
+    account::ExtraAccountMeta,
+    error::AccountResolutionError,
+    state::{AccountDataResult, ExtraAccountMetaList},
+};
+use spl_type_length_value::state::{TlvState, TlvStateBorrowed, TlvStateMut};
+use token_acl_interface::{
+    self as interface, CanFreezePermissionless, CanThawPermissionless, GateInterfaceVersion,
+    CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR, CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+    FREEZE_EXTRA_ACCOUNT_METAS_SEED, THAW_EXTRA_ACCOUNT_METAS_SEED,
+};
+
+/// Accounts every permissionless check takes before its extra accounts:
+/// caller, token account, mint and the extra-account-metas list
+pub const INTERFACE_ACCOUNTS: usize = 4;
+
+/// Which check a list belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListKind {
+    Thaw,
+    Freeze,
+}
+
+impl ListKind {
+    pub fn seed(self) -> &'static [u8] {
+        match self {
+            ListKind::Thaw => THAW_EXTRA_ACCOUNT_METAS_SEED,
+            ListKind::Freeze => FREEZE_EXTRA_ACCOUNT_METAS_SEED,
+        }
+    }
+
+    /// Discriminator of the check, which also keys the list's TLV entry
+    pub fn discriminator(self) -> [u8; 8] {
+        match self {
+            ListKind::Thaw => CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+            ListKind::Freeze => CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR,
+        }
+    }
+
+    /// The check `instruction_data` starts with, if it is one
+    pub fn of_instruction(instruction_data: &[u8]) -> Option<Self> {
+        match instruction_data.get(..8) {
+            Some(d) if d == CAN_THAW_PERMISSIONLESS_DISCRIMINATOR => Some(ListKind::Thaw),
+            Some(d) if d == CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR => Some(ListKind::Freeze),
+            _ => None,
+        }
+    }
+
+    /// `mint`'s list of this kind under `gate_program_id`
+    pub fn find_address(self, mint: &Pubkey, gate_program_id: &Pubkey) -> (Pubkey, u8) {
+        match self {
+            ListKind::Thaw => {
+                interface::find_thaw_extra_account_metas_address(mint, gate_program_id)
+            }
+            ListKind::Freeze => {
+                interface::find_freeze_extra_account_metas_address(mint, gate_program_id)
+            }
+        }
+    }
+}
+
+/// TLV entry holding the version of an extra-account-metas list
+pub struct ExtraAccountMetasVersion;
+
+impl SplDiscriminate for ExtraAccountMetasVersion {
+    const SPL_DISCRIMINATOR: ArrayDiscriminator =
+        ArrayDiscriminator::new([172, 48, 167, 12, 158, 213, 162, 167]);
+}
+
+/// Account size of a list with `num_metas` entries
+pub fn list_size(num_metas: usize) -> Result<usize, ProgramError> {
+    let version_entry = TlvStateBorrowed::get_base_len() + std::mem::size_of::<u64>();
+    let interface_entry = TlvStateBorrowed::get_base_len() + GateInterfaceVersion::LEN;
+    Ok(version_entry + interface_entry + ExtraAccountMetaList::size_of(num_metas)?)
+}
+
+/// Write a `kind` list into zeroed `data`, sized with [`list_size`]
+pub fn pack_list(
+    kind: ListKind,
+    data: &mut [u8],
+    version: u64,
+    interface_version: &GateInterfaceVersion,
+    metas: &[ExtraAccountMeta],
+) -> ProgramResult {
+    {
+        let mut state = TlvStateMut::unpack(data)?;
+        let (value, _) = state.alloc::<ExtraAccountMetasVersion>(8, false)?;
+        value.copy_from_slice(&version.to_le_bytes());
+        let (value, _) = state.alloc::<GateInterfaceVersion>(GateInterfaceVersion::LEN, false)?;
+        value.copy_from_slice(&interface_version.pack());
+    }
+    match kind {
+        ListKind::Thaw => ExtraAccountMetaList::init::<CanThawPermissionless>(data, metas),
+        ListKind::Freeze => ExtraAccountMetaList::init::<CanFreezePermissionless>(data, metas),
+    }
+}
+
+/// Version of the list in `data`
+pub fn list_version(data: &[u8]) -> Result<u64, ProgramError> {
+    let state = TlvStateBorrowed::unpack(data)?;
+    let value = state.get_first_bytes::<ExtraAccountMetasVersion>()?;
+    let value: [u8; 8] = value
+        .try_into()
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    Ok(u64::from_le_bytes(value))
+}
+
+/// The extra account metas of the `kind` list in `data`
+pub fn list_metas(kind: ListKind, data: &[u8]) -> Result<Vec<ExtraAccountMeta>, ProgramError> {
+    let state = TlvStateBorrowed::unpack(data)?;
+    let metas = match kind {
+        ListKind::Thaw => {
+            ExtraAccountMetaList::unpack_with_tlv_state::<CanThawPermissionless>(&state)?
+        }
+        ListKind::Freeze => {
+            ExtraAccountMetaList::unpack_with_tlv_state::<CanFreezePermissionless>(&state)?
+        }
+    };
+    Ok(metas.data().to_vec())
+}
+
+/// A list as an off-chain caller reads it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListState {
+    pub version: u64,
+    pub interface_version: GateInterfaceVersion,
+    pub metas: Vec<ExtraAccountMeta>,
+}
+
+impl ListState {
+    pub fn unpack(kind: ListKind, data: &[u8]) -> Result<Self, ProgramError> {
+        Ok(Self {
+            version: list_version(data)?,
+            interface_version: GateInterfaceVersion::from_extra_account_metas(data)?,
+            metas: list_metas(kind, data)?,
+        })
+    }
+}
+
+/// `metas` as instruction data: a `PodSlice<ExtraAccountMeta>`
+pub fn encode_metas(metas: &[ExtraAccountMeta]) -> Result<Vec<u8>, ProgramError> {
+    let mut data = vec![0; PodSlice::<ExtraAccountMeta>::size_of(metas.len())?];
+    let mut slice = PodSliceMut::init(&mut data)?;
+    for meta in metas {
+        slice.push(*meta)?;
+    }
+    Ok(data)
+}
+
+/// Metas encoded with [`encode_metas`]
+///
+/// Fails with `InvalidInstructionData` unless the length prefix accounts for
+/// exactly the metas that follow it.
+pub fn decode_metas(data: &[u8]) -> Result<Vec<ExtraAccountMeta>, ProgramError> {
+    let slice = PodSlice::<ExtraAccountMeta>::unpack(data)?;
+    // `PodSlice::data` slices by the prefix without checking it
+    let length = data
+        .get(..4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_le_bytes)
+        .ok_or(ProgramError::InvalidInstructionData)? as usize;
+    if PodSlice::<ExtraAccountMeta>::size_of(length)? != data.len() {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    Ok(slice.data().to_vec())
+}
+
+/// Check that `list_account` is `mint`'s `kind` list under `program_id`,
+/// returning its bump
+pub fn verify_list_address(
+    kind: ListKind,
+    program_id: &Pubkey,
+    list_account: &AccountInfo,
+    mint: &Pubkey,
+) -> Result<u8, ProgramError> {
+    let (expected, bump) = kind.find_address(mint, program_id);
+    if *list_account.key != expected {
+        msg!("Invalid {:?} extra-account-metas PDA", kind);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok(bump)
+}
+
+fn check_initialized(program_id: &Pubkey, list_account: &AccountInfo) -> ProgramResult {
+    if list_account.owner != program_id || list_account.data_is_empty() {
+        msg!("Extra-account-metas not initialized");
+        return Err(ProgramError::UninitializedAccount);
+    }
+    Ok(())
+}
+
+/// Create `mint`'s `kind` list at version 1, paid by `payer`
+///
+/// Fails with `InvalidSeeds` if `list_account` is not the list's PDA and
+/// with `AccountAlreadyInitialized` if it exists. Who may create a list is
+/// the gate's decision; check it first.
+#[allow(clippy::too_many_arguments)]
+pub fn create_list<'a>(
+    kind: ListKind,
+    program_id: &Pubkey,
+    interface_version: &GateInterfaceVersion,
+    metas: &[ExtraAccountMeta],
+    payer: &AccountInfo<'a>,
+    list_account: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+) -> ProgramResult {
+    interface::check_system_program(system_program)?;
+    let bump = verify_list_address(kind, program_id, list_account, mint.key)?;
+
+    if !list_account.data_is_empty() {
+        msg!("{:?} extra-account-metas already initialized", kind);
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let size = list_size(metas.len())?;
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            list_account.key,
+            Rent::get()?.minimum_balance(size),
+            size as u64,
+            program_id,
+        ),
+        &[payer.clone(), list_account.clone(), system_program.clone()],
+        &[&[kind.seed(), mint.key.as_ref(), &[bump]]],
+    )?;
+
+    pack_list(
+        kind,
+        &mut list_account.try_borrow_mut_data()?,
+        1,
+        interface_version,
+        metas,
+    )
+}
+
+/// Replace `mint`'s `kind` list at `expected_version` with `metas`, bumping
+/// its version; `payer` tops up the rent if it grew
+///
+/// Only the version the updater read is replaced, so two concurrent updates
+/// cannot silently clobber each other: any other fails with
+/// `InvalidArgument`. Returns the new version. Who may update a list is the
+/// gate's decision; check it first.
+#[allow(clippy::too_many_arguments)]
+pub fn update_list<'a>(
+    kind: ListKind,
+    program_id: &Pubkey,
+    interface_version: &GateInterfaceVersion,
+    expected_version: u64,
+    metas: &[ExtraAccountMeta],
+    payer: &AccountInfo<'a>,
+    list_account: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+) -> Result<u64, ProgramError> {
+    interface::check_system_program(system_program)?;
+    verify_list_address(kind, program_id, list_account, mint.key)?;
+    check_initialized(program_id, list_account)?;
+
+    let version = list_version(&list_account.try_borrow_data()?)?;
+    if version != expected_version {
+        msg!(
+            "{:?} extra-account-metas are at version {}, not {}",
+            kind,
+            version,
+            expected_version
+        );
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let size = list_size(metas.len())?;
+    if size > list_account.data_len() {
+        let shortfall = Rent::get()?
+            .minimum_balance(size)
+            .saturating_sub(list_account.lamports());
+        if shortfall > 0 {
+            invoke(
+                &system_instruction::transfer(payer.key, list_account.key, shortfall),
+                &[payer.clone(), list_account.clone(), system_program.clone()],
+            )?;
+        }
+    }
+    list_account.realloc(size, false)?;
+
+    let mut data = list_account.try_borrow_mut_data()?;
+    data.fill(0);
+    pack_list(kind, &mut data, version + 1, interface_version, metas)?;
+    Ok(version + 1)
+}
+
+/// Check the accounts of a check against `mint`'s current `kind` list
+///
+/// `accounts` are the check's accounts: the interface accounts, then
+/// `leading` accounts the caller supplies, then the listed ones. A
+/// transaction resolved against an older version of the list fails with
+/// `IncorrectAccount`, instead of the gate deciding on stale accounts.
+pub fn check_extra_accounts(
+    kind: ListKind,
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    leading: usize,
+    list_account: &AccountInfo,
+) -> ProgramResult {
+    check_initialized(program_id, list_account)?;
+
+    let data = list_account.try_borrow_data()?;
+    let version = list_version(&data)?;
+    let expected = list_metas(kind, &data)?.len();
+    let discriminator = kind.discriminator();
+    // `check_account_infos` assumes every listed account was provided
+    let stale =
+        accounts.len() != INTERFACE_ACCOUNTS + leading + expected
+            || match kind {
+                ListKind::Thaw => {
+                    ExtraAccountMetaList::check_account_infos::<CanThawPermissionless>(
+                        accounts,
+                        &discriminator,
+                        program_id,
+                        &data,
+                    )
+                }
+                ListKind::Freeze => ExtraAccountMetaList::check_account_infos::<
+                    CanFreezePermissionless,
+                >(accounts, &discriminator, program_id, &data),
+            }
+            .is_err();
+    if stale {
+        msg!(
+            "Extra accounts do not match {:?} extra-account-metas version {}",
+            kind,
+            version
+        );
+        return Err(AccountResolutionError::IncorrectAccount.into());
+    }
+    Ok(())
+}
+
+/// `check` with `leading` appended, then the accounts the gate's list
+/// resolves to, all read-only and non-signer
+///
+/// Fails as [`interface::resolve_extra_account_metas`] does when the gate
+/// has no list for the mint or speaks another interface version.
+pub async fn resolve_extra_accounts<F, Fut>(
+    mut check: Instruction,
+    leading: &[Pubkey],
+    fetch_account_data: F,
+) -> Result<Instruction, ProgramError>
+where
+    F: Fn(Pubkey) -> Fut,
+    Fut: Future<Output = AccountDataResult>,
+{
+    check.accounts.extend(
+        leading
+            .iter()
+            .map(|address| interface::ReadonlyMeta::new(*address).into()),
+    );
+    interface::resolve_extra_account_metas(&mut check, fetch_account_data).await?;
+    Ok(check)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spl_tlv_account_resolution::seeds::Seed;
+    use std::collections::HashMap;
+    use token_acl_interface::FEATURE_MISSING_RECORD_POLICY;
+
+    const INTERFACE: GateInterfaceVersion = GateInterfaceVersion {
+        version: 1,
+        features: FEATURE_MISSING_RECORD_POLICY,
+    };
+
+    /// A record `[b"record", mint, owner]`, with the owner read from the
+    /// token account, and a fixed oracle
+    fn metas(oracle: &Pubkey) -> Vec<ExtraAccountMeta> {
+        vec![
+            ExtraAccountMeta::new_with_seeds(
+                &[
+                    Seed::Literal {
+                        bytes: b"record".to_vec(),
+                    },
+                    Seed::AccountKey { index: 2 },
+                    Seed::AccountData {
+                        account_index: 1,
+                        data_index: 32,
+                        length: 32,
+                    },
+                ],
+                false,
+                false,
+            )
+            .unwrap(),
+            ExtraAccountMeta::new_with_pubkey(oracle, false, true).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_list_round_trip() {
+        let metas = metas(&Pubkey::new_unique());
+        for kind in [ListKind::Thaw, ListKind::Freeze] {
+            let mut data = vec![0; list_size(metas.len()).unwrap()];
+            pack_list(kind, &mut data, 3, &INTERFACE, &metas).unwrap();
+
+            let state = ListState::unpack(kind, &data).unwrap();
+            assert_eq!(state.version, 3);
+            assert_eq!(state.interface_version, INTERFACE);
+            assert_eq!(state.metas, metas);
+        }
+    }
+
+    #[test]
+    fn test_list_kinds_do_not_mix() {
+        let metas = metas(&Pubkey::new_unique());
+        let mut data = vec![0; list_size(metas.len()).unwrap()];
+        pack_list(ListKind::Thaw, &mut data, 1, &INTERFACE, &metas).unwrap();
+        assert!(list_metas(ListKind::Freeze, &data).is_err());
+
+        let (mint, gate) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert_ne!(
+            ListKind::Thaw.find_address(&mint, &gate),
+            ListKind::Freeze.find_address(&mint, &gate)
+        );
+        assert_eq!(
+            ListKind::of_instruction(&CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR),
+            Some(ListKind::Freeze)
+        );
+        assert_eq!(ListKind::of_instruction(&[0; 8]), None);
+    }
+
+    #[test]
+    fn test_encoded_metas_round_trip() {
+        let metas = metas(&Pubkey::new_unique());
+        assert_eq!(decode_metas(&encode_metas(&metas).unwrap()).unwrap(), metas);
+        assert_eq!(decode_metas(&encode_metas(&[]).unwrap()).unwrap(), []);
+
+        // A prefix claiming more (or fewer) metas than follow is rejected
+        let mut overrun = encode_metas(&metas).unwrap();
+        overrun[..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let mut short = encode_metas(&metas).unwrap();
+        short[..4].copy_from_slice(&1u32.to_le_bytes());
+        for data in [overrun, short] {
+            assert_eq!(
+                decode_metas(&data),
+                Err(ProgramError::InvalidInstructionData)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_after_leading_accounts() {
+        let (gate, mint, token_account, owner, caller, oracle) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let (list_address, _) = ListKind::Thaw.find_address(&mint, &gate);
+        let metas = metas(&oracle);
+        let mut list = vec![0; list_size(metas.len()).unwrap()];
+        pack_list(ListKind::Thaw, &mut list, 1, &INTERFACE, &metas).unwrap();
+        let mut token_data = vec![0; 165];
+        token_data[..32].copy_from_slice(mint.as_ref());
+        token_data[32..64].copy_from_slice(owner.as_ref());
+        let accounts = HashMap::from([(list_address, list), (token_account, token_data)]);
+
+        let check = interface::can_thaw_permissionless(
+            &gate,
+            caller,
+            token_account,
+            mint,
+            list_address,
+            &[],
+        );
+        let check = resolve_extra_accounts(check, &[owner], |address| {
+            let data = accounts.get(&address).cloned();
+            async move { Ok(data) }
+        })
+        .await
+        .unwrap();
+
+        let (record, _) =
+            Pubkey::find_program_address(&[b"record", mint.as_ref(), owner.as_ref()], &gate);
+        let resolved: Vec<Pubkey> = check.accounts[INTERFACE_ACCOUNTS..]
+            .iter()
+            .map(|meta| meta.pubkey)
+            .collect();
+        assert_eq!(resolved, [owner, record, oracle]);
+        assert!(check
+            .accounts
+            .iter()
+            .all(|meta| !meta.is_signer && !meta.is_writable));
+    }
+}
@@ -0,0 +1,397 @@
+//! Read-only views of gate records
+//!
+//! Other on-chain programs (a lending protocol asking "is this owner
+//! allow-listed?") can read a gate's records straight from the accounts
+//! passed to them, without a CPI into the gate. This crate is the layout
+//! those reads rely on, and the checks every reader needs: a record must be
+//! the canonical PDA for the mint and owner under the gate the consumer
+//! trusts, and owned by that gate, or anyone could pass in a look-alike
+//! account.
+//!
+//! It depends on neither gate crate, so consumers don't build gate programs
+//! into their own. The layouts below are part of the gates' public
+//! interface: fields are never reordered, resized or reinterpreted, and
+//! `tests/gate_view.rs` reads records written by the gates themselves to
+//! keep the two in step.
+//!
+//! # Allow list record (`production_allow_list`)
+//!
+//! PDA `[b"allow-list", mint, owner]` under the allow list program, Borsh
+//! encoded. The expiry is optional, so the last two fields move with it:
+//!
+//! | Offset | Size | Field |
+//! |--------|------|-------|
+//! | 0 | 32 | mint |
+//! | 32 | 32 | owner |
+//! | 64 | 1 | allowed (0 or 1) |
+//! | 65 | 1 | access level (0 none, 1 basic, 2 enhanced, 3 institutional) |
+//! | 66 | 8 | added timestamp, `i64` LE |
+//! | 74 | 1 or 9 | expiry: 0, or 1 followed by the timestamp as `i64` LE |
+//! | 75 or 83 | 1 | PDA bump |
+//! | 76 or 84 | 1 | revoked (0 or 1) |
+//!
+//! # Block list record (`example_block_list`)
+//!
+//! PDA `[b"block-list", mint, owner]` under the block list program, Borsh
+//! encoded. A block past its expiry has lapsed, blocked flag or not:
+//!
+//! | Offset | Size | Field |
+//! |--------|------|-------|
+//! | 0 | 32 | mint |
+//! | 32 | 32 | owner |
+//! | 64 | 1 | blocked (0 or 1) |
+//! | 65 | 1 | reason (0 sanctions, 1 compliance, 2 risk assessment, 3 other) |
+//! | 66 | 8 | added timestamp, `i64` LE |
+//! | 74 | 1 or 9 | expiry: 0, or 1 followed by the timestamp as `i64` LE |
+//!
+//! Records written before blocks could expire end at offset 74, with no
+//! expiry byte; they read as blocks without an expiry.
+//!
+//! # Missing records
+//!
+//! An empty account at the canonical address means the gate has no record
+//! for the owner: not allow-listed, and not blocked. The gate's
+//! [`MissingRecordPolicy`](token_acl_interface::MissingRecordPolicy) governs
+//! permissionless thaws only; a consumer deciding whether to trust an owner
+//! should not read a missing record as membership.
+
+use solana_program::{account_info::AccountInfo, msg, program_error::ProgramError, pubkey::Pubkey};
+use token_acl_interface::CanonicalPda;
+
+/// Allow list record seed of `production_allow_list`
+pub const ALLOW_LIST_SEED: &[u8] = b"allow-list";
+/// Block list record seed of `example_block_list`
+pub const BLOCK_LIST_SEED: &[u8] = b"block-list";
+
+/// Size of an allow list record without an expiry
+pub const ALLOW_LIST_RECORD_LEN: usize = 77;
+/// Size of an allow list record with an expiry
+pub const ALLOW_LIST_RECORD_WITH_EXPIRY_LEN: usize = 85;
+/// Size of a block list record written before blocks could expire
+pub const BLOCK_LIST_LEGACY_RECORD_LEN: usize = 74;
+/// Size of a block list record without an expiry
+pub const BLOCK_LIST_RECORD_LEN: usize = 75;
+/// Size of a block list record with an expiry
+pub const BLOCK_LIST_RECORD_WITH_EXPIRY_LEN: usize = 83;
+
+/// Allow list record PDA of `owner` for `mint` under `gate_program_id`
+pub fn allow_list_address(gate_program_id: &Pubkey, mint: &Pubkey, owner: &Pubkey) -> CanonicalPda {
+    CanonicalPda::find(
+        &[ALLOW_LIST_SEED, mint.as_ref(), owner.as_ref()],
+        gate_program_id,
+    )
+}
+
+/// Block list record PDA of `owner` for `mint` under `gate_program_id`
+pub fn block_list_address(gate_program_id: &Pubkey, mint: &Pubkey, owner: &Pubkey) -> CanonicalPda {
+    CanonicalPda::find(
+        &[BLOCK_LIST_SEED, mint.as_ref(), owner.as_ref()],
+        gate_program_id,
+    )
+}
+
+/// An allow list record, as laid out in the crate docs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllowListView {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub allowed: bool,
+    /// Kept as the raw byte so a level added later still reads
+    pub access_level: u8,
+    pub added_timestamp: i64,
+    pub expiry_timestamp: Option<i64>,
+    pub revoked: bool,
+}
+
+impl AllowListView {
+    /// Decode a record, failing with `InvalidAccountData` on anything that
+    /// is not exactly the documented layout
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        let expiry_timestamp = match data.get(74) {
+            Some(0) => None,
+            Some(1) => Some(read_i64(data, 75)?),
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let tail = if expiry_timestamp.is_some() { 83 } else { 75 };
+        if data.len() != tail + 2 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(Self {
+            mint: read_pubkey(data, 0)?,
+            owner: read_pubkey(data, 32)?,
+            allowed: read_bool(data, 64)?,
+            access_level: data[65],
+            added_timestamp: read_i64(data, 66)?,
+            expiry_timestamp,
+            revoked: read_bool(data, tail + 1)?,
+        })
+    }
+
+    /// Whether the record lets the owner in at `current_timestamp`: allowed,
+    /// not revoked and not expired, the same rule the gate thaws by
+    pub fn is_active(&self, current_timestamp: i64) -> bool {
+        let expired = self
+            .expiry_timestamp
+            .is_some_and(|expiry| current_timestamp > expiry);
+        self.allowed && !self.revoked && !expired
+    }
+
+    /// The record of `owner` for `mint` kept by `gate_program_id`, or `None`
+    /// if the gate has none
+    ///
+    /// Fails with `InvalidAccountData` unless `record` is the canonical PDA,
+    /// and with `IllegalOwner` if it holds data but isn't owned by the gate.
+    pub fn load(
+        gate_program_id: &Pubkey,
+        record: &AccountInfo,
+        mint: &Pubkey,
+        owner: &Pubkey,
+    ) -> Result<Option<Self>, ProgramError> {
+        let expected = allow_list_address(gate_program_id, mint, owner);
+        let Some(data) = record_data(gate_program_id, record, &expected)? else {
+            return Ok(None);
+        };
+        let view = Self::unpack(&data)?;
+        if view.mint != *mint || view.owner != *owner {
+            msg!("Allow list record belongs to another mint or owner");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(Some(view))
+    }
+}
+
+/// A block list record, as laid out in the crate docs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockListView {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub blocked: bool,
+    /// Kept as the raw byte so a reason added later still reads
+    pub reason: u8,
+    pub added_timestamp: i64,
+    pub expiry_timestamp: Option<i64>,
+}
+
+impl BlockListView {
+    /// Decode a record, failing with `InvalidAccountData` on anything that
+    /// is not exactly the documented layout
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        let (expiry_timestamp, len) = match data.get(74) {
+            None => (None, BLOCK_LIST_LEGACY_RECORD_LEN),
+            Some(0) => (None, BLOCK_LIST_RECORD_LEN),
+            Some(1) => (Some(read_i64(data, 75)?), BLOCK_LIST_RECORD_WITH_EXPIRY_LEN),
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        if data.len() != len {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(Self {
+            mint: read_pubkey(data, 0)?,
+            owner: read_pubkey(data, 32)?,
+            blocked: read_bool(data, 64)?,
+            reason: data[65],
+            added_timestamp: read_i64(data, 66)?,
+            expiry_timestamp,
+        })
+    }
+
+    /// Whether the block is in force at `current_timestamp`: blocked and
+    /// not expired, the same rule the gate freezes by
+    pub fn is_active(&self, current_timestamp: i64) -> bool {
+        let expired = self
+            .expiry_timestamp
+            .is_some_and(|expiry| current_timestamp > expiry);
+        self.blocked && !expired
+    }
+
+    /// The record of `owner` for `mint` kept by `gate_program_id`, or `None`
+    /// if the gate has none
+    ///
+    /// Fails like [`AllowListView::load`].
+    pub fn load(
+        gate_program_id: &Pubkey,
+        record: &AccountInfo,
+        mint: &Pubkey,
+        owner: &Pubkey,
+    ) -> Result<Option<Self>, ProgramError> {
+        let expected = block_list_address(gate_program_id, mint, owner);
+        let Some(data) = record_data(gate_program_id, record, &expected)? else {
+            return Ok(None);
+        };
+        let view = Self::unpack(&data)?;
+        if view.mint != *mint || view.owner != *owner {
+            msg!("Block list record belongs to another mint or owner");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(Some(view))
+    }
+}
+
+/// Whether `owner` has an active allow list record for `mint` at
+/// `current_timestamp`; a missing record means no
+pub fn is_allow_listed(
+    gate_program_id: &Pubkey,
+    record: &AccountInfo,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    current_timestamp: i64,
+) -> Result<bool, ProgramError> {
+    Ok(AllowListView::load(gate_program_id, record, mint, owner)?
+        .is_some_and(|view| view.is_active(current_timestamp)))
+}
+
+/// Whether `owner` has a block in force for `mint` at `current_timestamp`;
+/// a missing record means no
+pub fn is_blocked(
+    gate_program_id: &Pubkey,
+    record: &AccountInfo,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    current_timestamp: i64,
+) -> Result<bool, ProgramError> {
+    Ok(BlockListView::load(gate_program_id, record, mint, owner)?
+        .is_some_and(|view| view.is_active(current_timestamp)))
+}
+
+/// The data of the record at `expected`, or `None` while it is empty
+fn record_data(
+    gate_program_id: &Pubkey,
+    record: &AccountInfo,
+    expected: &CanonicalPda,
+) -> Result<Option<Vec<u8>>, ProgramError> {
+    if *record.key != expected.address() {
+        msg!("{} is not the gate's record PDA", record.key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    // Anyone can fund the address, but only the gate can give it data
+    if record.data_is_empty() {
+        return Ok(None);
+    }
+    if record.owner != gate_program_id {
+        msg!("Record {} is not owned by the gate", record.key);
+        return Err(ProgramError::IllegalOwner);
+    }
+    Ok(Some(record.try_borrow_data()?.to_vec()))
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Result<Pubkey, ProgramError> {
+    data.get(offset..offset + 32)
+        .and_then(|bytes| Pubkey::try_from(bytes).ok())
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+fn read_i64(data: &[u8], offset: usize) -> Result<i64, ProgramError> {
+    data.get(offset..offset + 8)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(i64::from_le_bytes)
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+fn read_bool(data: &[u8], offset: usize) -> Result<bool, ProgramError> {
+    match data.get(offset) {
+        Some(0) => Ok(false),
+        Some(1) => Ok(true),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allow_list_record(expiry: Option<i64>) -> Vec<u8> {
+        let mut data = vec![7; 32];
+        data.extend([8; 32]);
+        data.extend([1, 2]);
+        data.extend(100i64.to_le_bytes());
+        match expiry {
+            Some(expiry) => {
+                data.push(1);
+                data.extend(expiry.to_le_bytes());
+            }
+            None => data.push(0),
+        }
+        data.extend([254, 0]);
+        data
+    }
+
+    #[test]
+    fn test_allow_list_layout() {
+        let data = allow_list_record(None);
+        assert_eq!(data.len(), ALLOW_LIST_RECORD_LEN);
+        let view = AllowListView::unpack(&data).unwrap();
+        assert_eq!(view.mint, Pubkey::new_from_array([7; 32]));
+        assert_eq!(view.owner, Pubkey::new_from_array([8; 32]));
+        assert!(view.allowed && !view.revoked);
+        assert_eq!(view.access_level, 2);
+        assert_eq!(view.added_timestamp, 100);
+        assert_eq!(view.expiry_timestamp, None);
+        assert!(view.is_active(i64::MAX));
+
+        let data = allow_list_record(Some(500));
+        assert_eq!(data.len(), ALLOW_LIST_RECORD_WITH_EXPIRY_LEN);
+        let view = AllowListView::unpack(&data).unwrap();
+        assert_eq!(view.expiry_timestamp, Some(500));
+        assert!(view.is_active(500));
+        assert!(!view.is_active(501));
+
+        // The revoked flag is the last byte wherever the expiry puts it
+        let mut data = allow_list_record(Some(500));
+        *data.last_mut().unwrap() = 1;
+        assert!(!AllowListView::unpack(&data).unwrap().is_active(0));
+    }
+
+    #[test]
+    fn test_malformed_records_rejected() {
+        let mut short = allow_list_record(None);
+        short.pop();
+        let mut long = allow_list_record(None);
+        long.push(0);
+        let mut bad_flag = allow_list_record(None);
+        bad_flag[64] = 2;
+        let mut bad_option = allow_list_record(None);
+        bad_option[74] = 2;
+        for data in [short, long, bad_flag, bad_option, vec![]] {
+            assert_eq!(
+                AllowListView::unpack(&data),
+                Err(ProgramError::InvalidAccountData)
+            );
+        }
+
+        let mut block = vec![0; BLOCK_LIST_RECORD_LEN];
+        block[64] = 1;
+        assert!(BlockListView::unpack(&block).unwrap().blocked);
+        block.push(0);
+        assert_eq!(
+            BlockListView::unpack(&block),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn test_block_list_layout() {
+        let mut data = vec![0; BLOCK_LIST_RECORD_LEN];
+        data[64] = 1;
+        let view = BlockListView::unpack(&data).unwrap();
+        assert_eq!(view.expiry_timestamp, None);
+        assert!(view.is_active(i64::MAX));
+
+        data[74] = 1;
+        data.extend(500i64.to_le_bytes());
+        assert_eq!(data.len(), BLOCK_LIST_RECORD_WITH_EXPIRY_LEN);
+        let view = BlockListView::unpack(&data).unwrap();
+        assert_eq!(view.expiry_timestamp, Some(500));
+        assert!(view.is_active(500));
+        assert!(!view.is_active(501));
+
+        data.truncate(BLOCK_LIST_LEGACY_RECORD_LEN);
+        let view = BlockListView::unpack(&data).unwrap();
+        assert_eq!(view.expiry_timestamp, None);
+        assert!(view.is_active(i64::MAX));
+        data.pop();
+        assert_eq!(
+            BlockListView::unpack(&data),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+}
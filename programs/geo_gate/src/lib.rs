@@ -0,0 +1,560 @@
+// `entrypoint!` expands to cfgs only known to the BPF toolchain.
+#![allow(unexpected_cfgs)]
+
+use borsh::{BorshDeserialize, BorshSerialize};
+/**
+ * Geo-blocking Gate Program
+ *
+ * Gates permissionless thaws on the holder's jurisdiction following the
+ * sRFC 37 interface specification. The holder's jurisdiction is the one a
+ * jurisdiction registry attests in their holder record, so the registry
+ * authority assigns codes to wallets once for every mint sharing it. Each
+ * mint's config names the registry and lists the jurisdictions the mint
+ * may be held in, which is usually narrower than what the registry allows:
+ * a security token offered in the US and Germany only lists `US` and `DE`.
+ *
+ * A thaw needs the holder's jurisdiction to be on the mint's list and not
+ * embargoed in the registry; an embargo always wins, so blocking a code in
+ * the registry denies it on every mint at once. A holder without a record,
+ * or in a code the registry does not know, is denied. A freeze is
+ * authorized for a holder the registry places somewhere the mint does not
+ * allow, so keepers can freeze holders who moved or were embargoed.
+ *
+ * Instructions:
+ * - INITIALIZE: create the config of a mint with its registry and list
+ * - SET_ALLOWED_JURISDICTIONS: replace the mint's list
+ * - can-thaw-permissionless: succeeds for a holder in an allowed jurisdiction
+ * - can-freeze-permissionless: succeeds for a holder in any other
+ *   jurisdiction the registry knows
+ */
+use jurisdiction_registry::{holder_verdict, JurisdictionCode, JurisdictionVerdict};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+use token_acl_interface::{
+    check_system_program, store_account_data, CanonicalPda,
+    CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR, CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+};
+
+// Instruction discriminators
+pub const INITIALIZE: u8 = 0;
+pub const SET_ALLOWED_JURISDICTIONS: u8 = 1;
+
+// Seeds
+pub const CONFIG_SEED: &[u8] = b"geo-config";
+
+/// Jurisdictions a mint can allow; its config is allocated for this many
+pub const MAX_ALLOWED_JURISDICTIONS: usize = 32;
+
+// SPL Token account layout: mint (0..32), owner (32..64), ...
+const TOKEN_ACCOUNT_OWNER_OFFSET: usize = 32;
+
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
+
+/// Per-mint configuration: which registry to read and where the mint may be
+/// held
+///
+/// The account is allocated for [`MAX_ALLOWED_JURISDICTIONS`] codes up
+/// front, so changing the list never reallocates; the unused tail is zero.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GeoConfig {
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    /// Program owning the registry and its holder records
+    /// (`jurisdiction_registry`)
+    pub registry_program: Pubkey,
+    pub registry: Pubkey,
+    pub bump: u8,
+    pub allowed: Vec<JurisdictionCode>,
+}
+
+impl GeoConfig {
+    /// Account size for [`MAX_ALLOWED_JURISDICTIONS`] codes
+    pub const LEN: usize = 32 * 4 + 1 + 4 + MAX_ALLOWED_JURISDICTIONS * 2;
+
+    pub fn allows(&self, code: &JurisdictionCode) -> bool {
+        self.allowed.contains(code)
+    }
+
+    pub fn validate(&self) -> Result<(), ProgramError> {
+        if self.allowed.len() > MAX_ALLOWED_JURISDICTIONS {
+            msg!(
+                "{} jurisdictions is more than the {} a config holds",
+                self.allowed.len(),
+                MAX_ALLOWED_JURISDICTIONS
+            );
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        for (i, code) in self.allowed.iter().enumerate() {
+            if !code.iter().all(u8::is_ascii_uppercase) {
+                msg!("{:?} is not an ISO 3166-1 alpha-2 code", code);
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            if self.allowed[..i].contains(code) {
+                msg!("Jurisdiction {:?} is listed twice", code);
+                return Err(ProgramError::InvalidInstructionData);
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a config from its zero-padded account data
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        Self::deserialize(&mut &data[..]).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// The config laid out in an account of [`Self::LEN`] bytes
+    pub fn pack(&self) -> Result<Vec<u8>, ProgramError> {
+        let mut data = vec![0; Self::LEN];
+        self.serialize(&mut &mut data[..])?;
+        Ok(data)
+    }
+}
+
+/// Why a permissionless thaw is denied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeoDenial {
+    /// The holder has no record, or the registry does not know their code
+    Unregistered,
+    /// The registry blocks the holder's jurisdiction
+    Embargoed(JurisdictionCode),
+    /// The registry allows the jurisdiction, but the mint does not
+    NotAllowed(JurisdictionCode),
+}
+
+/// The geo-blocking thaw rule: an embargo overrides the mint's list
+pub fn check_thaw(
+    config: &GeoConfig,
+    verdict: JurisdictionVerdict,
+) -> Result<JurisdictionCode, GeoDenial> {
+    match verdict {
+        JurisdictionVerdict::Unknown => Err(GeoDenial::Unregistered),
+        JurisdictionVerdict::Blocked(code) => Err(GeoDenial::Embargoed(code)),
+        JurisdictionVerdict::Permitted(code) if config.allows(&code) => Ok(code),
+        JurisdictionVerdict::Permitted(code) => Err(GeoDenial::NotAllowed(code)),
+    }
+}
+
+/// The geo-blocking freeze rule: a holder the registry places outside the
+/// mint's jurisdictions may be frozen; an unregistered one may not
+pub fn permits_freeze(config: &GeoConfig, verdict: JurisdictionVerdict) -> bool {
+    matches!(
+        check_thaw(config, verdict),
+        Err(GeoDenial::Embargoed(_) | GeoDenial::NotAllowed(_))
+    )
+}
+
+/// Config PDA of `mint`
+pub fn config_address(mint: &Pubkey, program_id: &Pubkey) -> CanonicalPda {
+    CanonicalPda::find(&[CONFIG_SEED, mint.as_ref()], program_id)
+}
+
+/// Jurisdiction codes packed two bytes each
+fn unpack_codes(data: &[u8]) -> Result<Vec<JurisdictionCode>, ProgramError> {
+    let codes = data.chunks_exact(2);
+    if !codes.remainder().is_empty() {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    Ok(codes.map(|code| [code[0], code[1]]).collect())
+}
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    match instruction_data {
+        [INITIALIZE, data @ ..] => process_initialize(program_id, accounts, data),
+        [SET_ALLOWED_JURISDICTIONS, codes @ ..] => {
+            process_set_allowed_jurisdictions(program_id, accounts, unpack_codes(codes)?)
+        }
+        d if d == CAN_THAW_PERMISSIONLESS_DISCRIMINATOR => {
+            process_can_thaw_permissionless(program_id, accounts)
+        }
+        d if d == CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR => {
+            process_can_freeze_permissionless(program_id, accounts)
+        }
+        _ => {
+            msg!("Unknown instruction");
+            Err(ProgramError::InvalidInstructionData)
+        }
+    }
+}
+
+/// Initialize the config of a mint
+///
+/// Data: the registry program id and the registry address, then the
+/// allowed jurisdiction codes, two bytes each.
+///
+/// Accounts:
+/// 0. config PDA (writable)
+/// 1. mint
+/// 2. authority (signer)
+/// 3. payer (writable, signer)
+/// 4. system program
+fn process_initialize(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+    let payer = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    check_system_program(system_program)?;
+
+    if data.len() < 64 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let key = |i: usize| {
+        Pubkey::try_from(&data[i * 32..(i + 1) * 32])
+            .map_err(|_| ProgramError::InvalidInstructionData)
+    };
+
+    let config_pda = config_address(mint.key, program_id);
+    if *config_account.key != config_pda.address() {
+        msg!("Invalid config PDA");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let config = GeoConfig {
+        authority: *authority.key,
+        mint: *mint.key,
+        registry_program: key(0)?,
+        registry: key(1)?,
+        bump: config_pda.bump(),
+        allowed: unpack_codes(&data[64..])?,
+    };
+    config.validate()?;
+
+    let rent = Rent::get()?;
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            config_account.key,
+            rent.minimum_balance(GeoConfig::LEN),
+            GeoConfig::LEN as u64,
+            program_id,
+        ),
+        &[
+            payer.clone(),
+            config_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[CONFIG_SEED, mint.key.as_ref(), &[config.bump]]],
+    )?;
+
+    store_account_data(config_account, &config.pack()?)?;
+
+    msg!(
+        "Geo gate initialized for mint {}: {} allowed jurisdictions",
+        mint.key,
+        config.allowed.len()
+    );
+    Ok(())
+}
+
+/// The config in `config_account`, checked to be the PDA of `mint` and
+/// owned by this program
+fn load_config(
+    program_id: &Pubkey,
+    config_account: &AccountInfo,
+    mint: &Pubkey,
+) -> Result<GeoConfig, ProgramError> {
+    if *config_account.key != config_address(mint, program_id).address()
+        || config_account.owner != program_id
+        || config_account.data_is_empty()
+    {
+        msg!("Invalid geo gate config");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    GeoConfig::unpack(&config_account.try_borrow_data()?)
+}
+
+/// Replace the mint's allowed jurisdictions
+///
+/// Accounts:
+/// 0. config (writable)
+/// 1. mint
+/// 2. authority (signer)
+fn process_set_allowed_jurisdictions(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    allowed: Vec<JurisdictionCode>,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+
+    let mut config = load_config(program_id, config_account, mint.key)?;
+    if *authority.key != config.authority {
+        msg!("Invalid authority");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    config.allowed = allowed;
+    config.validate()?;
+    store_account_data(config_account, &config.pack()?)?;
+
+    msg!(
+        "Mint {} allows {} jurisdictions",
+        mint.key,
+        config.allowed.len()
+    );
+    Ok(())
+}
+
+/// What the configured registry says about `user`, reading the registry and
+/// the holder record from the next two accounts
+fn jurisdiction_verdict<'a, 'b: 'a>(
+    config: &GeoConfig,
+    account_info_iter: &mut impl Iterator<Item = &'a AccountInfo<'b>>,
+    user: &Pubkey,
+) -> Result<JurisdictionVerdict, ProgramError> {
+    let registry = next_account_info(account_info_iter)?;
+    let holder_record = next_account_info(account_info_iter)?;
+    if *registry.key != config.registry {
+        msg!("Not the configured jurisdiction registry");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    holder_verdict(&config.registry_program, registry, holder_record, user)
+}
+
+fn code_name(code: &JurisdictionCode) -> String {
+    String::from_utf8_lossy(code).into_owned()
+}
+
+/// sRFC 37 Interface: Can thaw permissionless
+fn process_can_thaw_permissionless(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    // Accounts as per sRFC 37 interface:
+    // 0. caller
+    // 1. token account
+    // 2. mint
+    // 3. extra-account-metas
+    // Extra accounts:
+    // 4. token account owner
+    // 5. geo config PDA
+    // 6. jurisdiction registry
+    // 7. holder jurisdiction PDA
+
+    let _caller = next_account_info(account_info_iter)?;
+    let token_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let _extra_account_metas = next_account_info(account_info_iter)?;
+    let token_account_owner = next_account_info(account_info_iter)?;
+    let config_account = next_account_info(account_info_iter)?;
+
+    // The holder record is looked up by the supplied owner, so it must be
+    // the account's current owner
+    if !token_account.data_is_empty() {
+        let data = token_account.try_borrow_data()?;
+        let bound = data.len() >= TOKEN_ACCOUNT_OWNER_OFFSET + 32
+            && data[..32] == mint.key.to_bytes()
+            && data[TOKEN_ACCOUNT_OWNER_OFFSET..TOKEN_ACCOUNT_OWNER_OFFSET + 32]
+                == token_account_owner.key.to_bytes();
+        if !bound {
+            msg!(
+                "Token account {} is not owned by {} for this mint",
+                token_account.key,
+                token_account_owner.key
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+
+    let config = load_config(program_id, config_account, mint.key)?;
+    let user = token_account_owner.key;
+    let verdict = jurisdiction_verdict(&config, account_info_iter, user)?;
+
+    match check_thaw(&config, verdict) {
+        Ok(code) => {
+            msg!(
+                "✅ User {} is in allowed jurisdiction {} - permissionless thaw authorized",
+                user,
+                code_name(&code)
+            );
+            Ok(())
+        }
+        Err(GeoDenial::Unregistered) => {
+            msg!(
+                "❌ User {} has no recognized jurisdiction - permissionless thaw denied",
+                user
+            );
+            Err(ProgramError::InvalidAccountData)
+        }
+        Err(GeoDenial::Embargoed(code)) => {
+            msg!(
+                "❌ User {} is in embargoed jurisdiction {} - permissionless thaw denied",
+                user,
+                code_name(&code)
+            );
+            Err(ProgramError::InvalidAccountData)
+        }
+        Err(GeoDenial::NotAllowed(code)) => {
+            msg!(
+                "❌ User {} is in jurisdiction {}, which mint {} does not allow - permissionless thaw denied",
+                user,
+                code_name(&code),
+                mint.key
+            );
+            Err(ProgramError::InvalidAccountData)
+        }
+    }
+}
+
+/// sRFC 37 Interface: Can freeze permissionless
+fn process_can_freeze_permissionless(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    // Accounts as per sRFC 37 interface:
+    // 0. caller
+    // 1. token account
+    // 2. mint
+    // 3. extra-account-metas
+    // Extra accounts:
+    // 4. geo config PDA
+    // 5. jurisdiction registry
+    // 6. holder jurisdiction PDA of the token account's owner
+
+    let _caller = next_account_info(account_info_iter)?;
+    let token_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let _extra_account_metas = next_account_info(account_info_iter)?;
+    let config_account = next_account_info(account_info_iter)?;
+
+    let owner = {
+        let data = token_account.try_borrow_data()?;
+        if data.len() < TOKEN_ACCOUNT_OWNER_OFFSET + 32 || data[..32] != mint.key.to_bytes() {
+            msg!("Token account does not hold mint {}", mint.key);
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Pubkey::try_from(&data[TOKEN_ACCOUNT_OWNER_OFFSET..TOKEN_ACCOUNT_OWNER_OFFSET + 32])
+            .map_err(|_| ProgramError::InvalidAccountData)?
+    };
+
+    let config = load_config(program_id, config_account, mint.key)?;
+    let verdict = jurisdiction_verdict(&config, account_info_iter, &owner)?;
+    if !permits_freeze(&config, verdict) {
+        msg!(
+            "❌ User {} is not outside the mint's jurisdictions - permissionless freeze denied",
+            owner
+        );
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    msg!(
+        "✅ User {} is outside the mint's jurisdictions - permissionless freeze authorized",
+        owner
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(allowed: &[&[u8; 2]]) -> GeoConfig {
+        GeoConfig {
+            authority: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            registry_program: Pubkey::new_unique(),
+            registry: Pubkey::new_unique(),
+            bump: 255,
+            allowed: allowed.iter().map(|code| **code).collect(),
+        }
+    }
+
+    #[test]
+    fn test_embargo_overrides_the_mint_list() {
+        let config = config(&[b"US", b"DE"]);
+        use JurisdictionVerdict::*;
+        assert_eq!(check_thaw(&config, Permitted(*b"US")), Ok(*b"US"));
+        assert_eq!(
+            check_thaw(&config, Blocked(*b"US")),
+            Err(GeoDenial::Embargoed(*b"US"))
+        );
+        assert_eq!(
+            check_thaw(&config, Permitted(*b"FR")),
+            Err(GeoDenial::NotAllowed(*b"FR"))
+        );
+        assert_eq!(check_thaw(&config, Unknown), Err(GeoDenial::Unregistered));
+    }
+
+    #[test]
+    fn test_freeze_needs_a_known_jurisdiction_outside_the_list() {
+        let config = config(&[b"US"]);
+        use JurisdictionVerdict::*;
+        assert!(permits_freeze(&config, Blocked(*b"US")));
+        assert!(permits_freeze(&config, Permitted(*b"FR")));
+        assert!(!permits_freeze(&config, Permitted(*b"US")));
+        assert!(!permits_freeze(&config, Unknown));
+    }
+
+    #[test]
+    fn test_config_validation() {
+        assert_eq!(config(&[]).validate(), Ok(()));
+        assert_eq!(config(&[b"US", b"DE"]).validate(), Ok(()));
+        let full: Vec<[u8; 2]> = (0..MAX_ALLOWED_JURISDICTIONS)
+            .map(|i| [b'A' + (i / 26) as u8, b'A' + (i % 26) as u8])
+            .collect();
+        let mut too_many = config(&[]);
+        too_many.allowed = full;
+        assert_eq!(too_many.validate(), Ok(()));
+        too_many.allowed.push(*b"ZZ");
+        for invalid in [config(&[b"US", b"US"]), config(&[b"us"]), too_many] {
+            assert_eq!(
+                invalid.validate(),
+                Err(ProgramError::InvalidInstructionData),
+                "{:?}",
+                invalid.allowed
+            );
+        }
+    }
+
+    #[test]
+    fn test_full_config_fits_its_account() {
+        let mut full = config(&[]);
+        full.allowed = (0..MAX_ALLOWED_JURISDICTIONS)
+            .map(|i| [b'A' + (i / 26) as u8, b'A' + (i % 26) as u8])
+            .collect();
+        assert_eq!(full.try_to_vec().unwrap().len(), GeoConfig::LEN);
+
+        // A shorter list leaves a zero tail the config still reads from
+        let short = config(&[b"US"]);
+        let data = short.pack().unwrap();
+        assert_eq!(data.len(), GeoConfig::LEN);
+        assert_eq!(GeoConfig::unpack(&data).unwrap(), short);
+    }
+
+    #[test]
+    fn test_codes_unpack_in_pairs() {
+        assert_eq!(unpack_codes(b"USDE"), Ok(vec![*b"US", *b"DE"]));
+        assert_eq!(unpack_codes(b""), Ok(vec![]));
+        assert_eq!(
+            unpack_codes(b"USD"),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
+}
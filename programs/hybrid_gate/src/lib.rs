@@ -0,0 +1,640 @@
+// `entrypoint!` expands to cfgs only known to the BPF toolchain.
+#![allow(unexpected_cfgs)]
+
+use borsh::{BorshDeserialize, BorshSerialize};
+/**
+ * Hybrid Gate Program
+ *
+ * Combines an allow list (KYC) with a sanctions block list following the
+ * sRFC 37 interface specification. Neither list is kept here: the config
+ * names the production allow list and block list programs whose records
+ * decide, and the gate reads them directly.
+ *
+ * The config can also name a shared jurisdiction registry, which the gate
+ * queries through two more extra accounts; an embargo is then one write to
+ * the registry instead of an update per holder.
+ *
+ * An optional cooling-off period delays thaws: an allow list record only
+ * counts once it has existed that long, so a compromised allow list
+ * authority cannot add a holder and release their account in one go. The
+ * period is set by the gate's own authority with SET_COOLING_OFF.
+ *
+ * In an emergency the same authority can pause the gate with SET_PAUSED:
+ * every permissionless thaw of the mint is then denied, whoever the holder,
+ * until it is unpaused. Freezes are not paused.
+ *
+ * Sanctions always take precedence, then the jurisdiction, then the allow
+ * list, then the cooling-off period:
+ * - can-thaw-permissionless: succeeds only for a user who is allow-listed,
+ *   NOT sanctioned and, with a registry, in an allowed jurisdiction; a
+ *   sanctioned or embargoed user is denied even with an active allow list
+ *   record
+ * - can-freeze-permissionless: succeeds for any sanctioned or embargoed
+ *   user, whatever the allow list says, so a keeper freeze always wins over
+ *   a thaw that is still pending
+ */
+use example_block_list::{block_list_address, BlockListRecord};
+use jurisdiction_registry::{holder_verdict, JurisdictionVerdict};
+use production_allow_list::{allow_list_address, AllowListRecord};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+use token_acl_interface::{
+    check_system_program, store_account_data, CanonicalPda,
+    CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR, CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+};
+
+// Instruction discriminators
+pub const INITIALIZE: u8 = 0;
+pub const SET_COOLING_OFF: u8 = 1;
+pub const SET_PAUSED: u8 = 2;
+
+// Seeds
+pub const CONFIG_SEED: &[u8] = b"hybrid-config";
+
+// SPL Token account layout: mint (0..32), owner (32..64), ...
+const TOKEN_ACCOUNT_OWNER_OFFSET: usize = 32;
+
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
+
+/// Per-mint configuration: which programs hold the two lists
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct HybridConfig {
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    /// Program owning the allow list records (`production_allow_list`)
+    pub allow_list_program: Pubkey,
+    /// Program owning the sanctions records (`example_block_list`)
+    pub block_list_program: Pubkey,
+    /// Jurisdiction registry to consult, if any
+    pub jurisdictions: Option<JurisdictionSource>,
+    /// Seconds an allow list record must exist before it permits a thaw;
+    /// 0 turns the cooling-off period off
+    pub cooling_off_seconds: u32,
+    /// Kill switch: while set, no permissionless thaw is authorized
+    pub paused: bool,
+    pub bump: u8,
+}
+
+/// A jurisdiction registry and the program that owns it
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct JurisdictionSource {
+    pub program: Pubkey,
+    pub registry: Pubkey,
+}
+
+/// Whether an allow list record added at `added_timestamp` has cooled off by
+/// `current_timestamp`; a thaw is permitted from the second the period ends
+pub fn cooled_off(added_timestamp: i64, cooling_off_seconds: u32, current_timestamp: i64) -> bool {
+    current_timestamp >= added_timestamp.saturating_add(i64::from(cooling_off_seconds))
+}
+
+/// Why a permissionless thaw is denied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThawDenial {
+    /// On the sanctions list; the allow list is not consulted
+    Sanctioned,
+    /// In a jurisdiction the registry does not allow
+    Jurisdiction,
+    /// Not sanctioned, but without an active allow list record
+    NotAllowed,
+}
+
+/// The hybrid thaw rule: sanctions override the jurisdiction, which
+/// overrides the allow list
+///
+/// `jurisdiction_permitted` is `true` for a mint without a registry.
+pub fn check_thaw(
+    sanctioned: bool,
+    jurisdiction_permitted: bool,
+    allowed: bool,
+) -> Result<(), ThawDenial> {
+    if sanctioned {
+        return Err(ThawDenial::Sanctioned);
+    }
+    if !jurisdiction_permitted {
+        return Err(ThawDenial::Jurisdiction);
+    }
+    if !allowed {
+        return Err(ThawDenial::NotAllowed);
+    }
+    Ok(())
+}
+
+/// The hybrid freeze rule: every sanctioned or embargoed user may be frozen,
+/// allow-listed or not
+pub fn permits_freeze(sanctioned: bool, embargoed: bool) -> bool {
+    sanctioned || embargoed
+}
+
+/// Config PDA of `mint`
+pub fn config_address(mint: &Pubkey, program_id: &Pubkey) -> CanonicalPda {
+    CanonicalPda::find(&[CONFIG_SEED, mint.as_ref()], program_id)
+}
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    match instruction_data {
+        [INITIALIZE, data @ ..] => process_initialize(program_id, accounts, data),
+        [SET_COOLING_OFF, a, b, c, d] => {
+            process_set_cooling_off(program_id, accounts, u32::from_le_bytes([*a, *b, *c, *d]))
+        }
+        [SET_PAUSED, paused @ (0 | 1)] => process_set_paused(program_id, accounts, *paused == 1),
+        d if d == CAN_THAW_PERMISSIONLESS_DISCRIMINATOR => {
+            process_can_thaw_permissionless(program_id, accounts)
+        }
+        d if d == CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR => {
+            process_can_freeze_permissionless(program_id, accounts)
+        }
+        _ => {
+            msg!("Unknown instruction");
+            Err(ProgramError::InvalidInstructionData)
+        }
+    }
+}
+
+/// Initialize the config of a mint
+///
+/// Data: the allow list program id and the block list program id, then
+/// optionally the jurisdiction registry program id and registry address.
+fn process_initialize(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+    let payer = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    check_system_program(system_program)?;
+
+    let key = |i: usize| {
+        Pubkey::try_from(&data[i * 32..(i + 1) * 32])
+            .map_err(|_| ProgramError::InvalidInstructionData)
+    };
+    let jurisdictions = match data.len() {
+        64 => None,
+        128 => Some(JurisdictionSource {
+            program: key(2)?,
+            registry: key(3)?,
+        }),
+        _ => return Err(ProgramError::InvalidInstructionData),
+    };
+    let allow_list_program = key(0)?;
+    let block_list_program = key(1)?;
+
+    let config_pda = config_address(mint.key, program_id);
+    if *config_account.key != config_pda.address() {
+        msg!("Invalid config PDA");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let config = HybridConfig {
+        authority: *authority.key,
+        mint: *mint.key,
+        allow_list_program,
+        block_list_program,
+        jurisdictions,
+        cooling_off_seconds: 0,
+        paused: false,
+        bump: config_pda.bump(),
+    };
+
+    let config_data = config.try_to_vec()?;
+    let rent = Rent::get()?;
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            config_account.key,
+            rent.minimum_balance(config_data.len()),
+            config_data.len() as u64,
+            program_id,
+        ),
+        &[
+            payer.clone(),
+            config_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[CONFIG_SEED, mint.key.as_ref(), &[config.bump]]],
+    )?;
+
+    store_account_data(config_account, &config_data)?;
+
+    msg!("Hybrid gate initialized for mint: {}", mint.key);
+    Ok(())
+}
+
+/// Set the cooling-off period of a mint
+///
+/// Data: the period in seconds (u32, little-endian), 0 to turn it off. It
+/// applies to every thaw from now on, including for records added before.
+///
+/// Accounts:
+/// 0. config (writable)
+/// 1. mint
+/// 2. authority (signer)
+fn process_set_cooling_off(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    cooling_off_seconds: u32,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+
+    let mut config = load_authorized_config(program_id, config_account, mint, authority)?;
+    config.cooling_off_seconds = cooling_off_seconds;
+    store_account_data(config_account, &config.try_to_vec()?)?;
+
+    msg!(
+        "Cooling-off period for mint {} set to {} seconds",
+        mint.key,
+        cooling_off_seconds
+    );
+    Ok(())
+}
+
+/// Pause or unpause the permissionless thaws of a mint
+///
+/// Data: 1 to pause, 0 to unpause. Thaws already landed stay thawed, and
+/// freezes go on as before.
+///
+/// Accounts:
+/// 0. config (writable)
+/// 1. mint
+/// 2. authority (signer)
+fn process_set_paused(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    paused: bool,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+
+    let mut config = load_authorized_config(program_id, config_account, mint, authority)?;
+    config.paused = paused;
+    store_account_data(config_account, &config.try_to_vec()?)?;
+
+    msg!(
+        "Permissionless thaws of mint {} {}",
+        mint.key,
+        if paused { "paused" } else { "unpaused" }
+    );
+    Ok(())
+}
+
+/// The config of `mint`, for an instruction only its authority may sign
+fn load_authorized_config(
+    program_id: &Pubkey,
+    config_account: &AccountInfo,
+    mint: &AccountInfo,
+    authority: &AccountInfo,
+) -> Result<HybridConfig, ProgramError> {
+    let config = load_config(program_id, config_account, mint)?;
+    if *authority.key != config.authority {
+        msg!("Invalid authority");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    Ok(config)
+}
+
+/// The config of `mint`, checked to be the canonical PDA owned by this
+/// program
+fn load_config(
+    program_id: &Pubkey,
+    config_account: &AccountInfo,
+    mint: &AccountInfo,
+) -> Result<HybridConfig, ProgramError> {
+    if *config_account.key != config_address(mint.key, program_id).address()
+        || config_account.owner != program_id
+        || config_account.data_is_empty()
+    {
+        msg!("Invalid hybrid gate config");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(HybridConfig::try_from_slice(
+        &config_account.try_borrow_data()?,
+    )?)
+}
+
+/// Whether `user` is on the sanctions list, with a block that has not
+/// lapsed
+///
+/// A missing record means not sanctioned; a record must be the user's PDA
+/// under the configured block list program and owned by it.
+fn is_sanctioned(
+    config: &HybridConfig,
+    block_list_pda: &AccountInfo,
+    user: &Pubkey,
+) -> Result<bool, ProgramError> {
+    let expected = block_list_address(&config.mint, user, &config.block_list_program);
+    if *block_list_pda.key != expected.address() {
+        msg!("Invalid block list PDA");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if block_list_pda.data_is_empty() {
+        return Ok(false);
+    }
+    if *block_list_pda.owner != config.block_list_program {
+        msg!("Block list record not owned by the block list program");
+        return Err(ProgramError::IllegalOwner);
+    }
+    let record = BlockListRecord::unpack(&block_list_pda.try_borrow_data()?)?;
+    record.is_active_now()
+}
+
+/// What the configured registry says about `user`, reading the registry and
+/// the holder record from the next two accounts; `None` without a registry
+fn jurisdiction_verdict<'a, 'b: 'a>(
+    config: &HybridConfig,
+    account_info_iter: &mut impl Iterator<Item = &'a AccountInfo<'b>>,
+    user: &Pubkey,
+) -> Result<Option<JurisdictionVerdict>, ProgramError> {
+    let Some(source) = config.jurisdictions else {
+        return Ok(None);
+    };
+    let registry = next_account_info(account_info_iter)?;
+    let holder_record = next_account_info(account_info_iter)?;
+    if *registry.key != source.registry {
+        msg!("Not the configured jurisdiction registry");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    holder_verdict(&source.program, registry, holder_record, user).map(Some)
+}
+
+/// `user`'s allow list record, if they have one
+fn allow_list_record(
+    config: &HybridConfig,
+    allow_list_pda: &AccountInfo,
+    user: &Pubkey,
+) -> Result<Option<AllowListRecord>, ProgramError> {
+    let expected = allow_list_address(&config.mint, user, &config.allow_list_program);
+    if *allow_list_pda.key != expected.address() {
+        msg!("Invalid allow list PDA");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if allow_list_pda.data_is_empty() {
+        return Ok(None);
+    }
+    if *allow_list_pda.owner != config.allow_list_program {
+        msg!("Allow list record not owned by the allow list program");
+        return Err(ProgramError::IllegalOwner);
+    }
+    let record = AllowListRecord::try_from_slice(&allow_list_pda.try_borrow_data()?)?;
+    Ok(Some(record))
+}
+
+/// sRFC 37 Interface: Can thaw permissionless
+fn process_can_thaw_permissionless(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    // Accounts as per sRFC 37 interface:
+    // 0. caller
+    // 1. token account
+    // 2. mint
+    // 3. extra-account-metas
+    // Extra accounts:
+    // 4. token account owner
+    // 5. hybrid config PDA
+    // 6. allow list PDA
+    // 7. block list PDA
+    // With a jurisdiction registry configured:
+    // 8. jurisdiction registry
+    // 9. holder jurisdiction PDA
+
+    let _caller = next_account_info(account_info_iter)?;
+    let token_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let _extra_account_metas = next_account_info(account_info_iter)?;
+    let token_account_owner = next_account_info(account_info_iter)?;
+    let config_account = next_account_info(account_info_iter)?;
+    let allow_list_pda = next_account_info(account_info_iter)?;
+    let block_list_pda = next_account_info(account_info_iter)?;
+
+    // Both records are looked up by the supplied owner, so it must be the
+    // account's current owner
+    if !token_account.data_is_empty() {
+        let data = token_account.try_borrow_data()?;
+        let bound = data.len() >= TOKEN_ACCOUNT_OWNER_OFFSET + 32
+            && data[..32] == mint.key.to_bytes()
+            && data[TOKEN_ACCOUNT_OWNER_OFFSET..TOKEN_ACCOUNT_OWNER_OFFSET + 32]
+                == token_account_owner.key.to_bytes();
+        if !bound {
+            msg!(
+                "Token account {} is not owned by {} for this mint",
+                token_account.key,
+                token_account_owner.key
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+
+    let config = load_config(program_id, config_account, mint)?;
+    let user = token_account_owner.key;
+    if config.paused {
+        msg!(
+            "❌ Permissionless thaws of mint {} are paused - permissionless thaw denied",
+            mint.key
+        );
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let current_timestamp = Clock::get()?.unix_timestamp;
+
+    // The sanctions list is read first, and the allow list only if the
+    // sanctions list and the jurisdiction clear
+    let sanctioned = is_sanctioned(&config, block_list_pda, user)?;
+    let jurisdiction = jurisdiction_verdict(&config, account_info_iter, user)?;
+    let jurisdiction_permitted = jurisdiction.is_none_or(|verdict| verdict.permits_thaw());
+    let record = if !sanctioned && jurisdiction_permitted {
+        allow_list_record(&config, allow_list_pda, user)?
+    } else {
+        None
+    };
+    let allowed = record
+        .as_ref()
+        .is_some_and(|record| record.permits_thaw(current_timestamp));
+
+    match check_thaw(sanctioned, jurisdiction_permitted, allowed) {
+        Ok(()) => {
+            let added_timestamp = record.map_or(0, |record| record.added_timestamp);
+            if !cooled_off(
+                added_timestamp,
+                config.cooling_off_seconds,
+                current_timestamp,
+            ) {
+                msg!(
+                    "❌ User {} was allow-listed at {}, cooling off for {} seconds - permissionless thaw denied",
+                    user,
+                    added_timestamp,
+                    config.cooling_off_seconds
+                );
+                return Err(ProgramError::InvalidAccountData);
+            }
+            msg!(
+                "✅ User {} is allow-listed and not sanctioned - permissionless thaw authorized",
+                user
+            );
+            Ok(())
+        }
+        Err(ThawDenial::Sanctioned) => {
+            msg!(
+                "❌ User {} is sanctioned - sanctions override the allow list, permissionless thaw denied",
+                user
+            );
+            Err(ProgramError::InvalidAccountData)
+        }
+        Err(ThawDenial::Jurisdiction) => {
+            msg!(
+                "❌ User {} is not in an allowed jurisdiction ({:?}) - permissionless thaw denied",
+                user,
+                jurisdiction
+            );
+            Err(ProgramError::InvalidAccountData)
+        }
+        Err(ThawDenial::NotAllowed) => {
+            msg!(
+                "❌ User {} is not allow-listed - permissionless thaw denied",
+                user
+            );
+            Err(ProgramError::InvalidAccountData)
+        }
+    }
+}
+
+/// sRFC 37 Interface: Can freeze permissionless
+fn process_can_freeze_permissionless(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    // Accounts as per sRFC 37 interface:
+    // 0. caller
+    // 1. token account
+    // 2. mint
+    // 3. extra-account-metas
+    // Extra accounts:
+    // 4. hybrid config PDA
+    // 5. block list PDA of the token account's owner
+    // With a jurisdiction registry configured:
+    // 6. jurisdiction registry
+    // 7. holder jurisdiction PDA of the token account's owner
+
+    let _caller = next_account_info(account_info_iter)?;
+    let token_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let _extra_account_metas = next_account_info(account_info_iter)?;
+    let config_account = next_account_info(account_info_iter)?;
+    let block_list_pda = next_account_info(account_info_iter)?;
+
+    let owner = {
+        let data = token_account.try_borrow_data()?;
+        if data.len() < TOKEN_ACCOUNT_OWNER_OFFSET + 32 || data[..32] != mint.key.to_bytes() {
+            msg!("Token account does not hold mint {}", mint.key);
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Pubkey::try_from(&data[TOKEN_ACCOUNT_OWNER_OFFSET..TOKEN_ACCOUNT_OWNER_OFFSET + 32])
+            .map_err(|_| ProgramError::InvalidAccountData)?
+    };
+
+    let config = load_config(program_id, config_account, mint)?;
+
+    // The allow list is not consulted: being allow-listed never shields a
+    // sanctioned or embargoed user from a freeze
+    let sanctioned = is_sanctioned(&config, block_list_pda, &owner)?;
+    let embargoed = jurisdiction_verdict(&config, account_info_iter, &owner)?
+        .is_some_and(|verdict| verdict.is_embargoed());
+    if !permits_freeze(sanctioned, embargoed) {
+        msg!(
+            "❌ User {} is neither sanctioned nor embargoed - permissionless freeze denied",
+            owner
+        );
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    msg!(
+        "✅ User {} is {} - permissionless freeze authorized",
+        owner,
+        if sanctioned {
+            "sanctioned"
+        } else {
+            "embargoed"
+        }
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanctions_take_precedence() {
+        assert_eq!(check_thaw(false, true, true), Ok(()));
+        assert_eq!(check_thaw(true, true, true), Err(ThawDenial::Sanctioned));
+        assert_eq!(check_thaw(true, false, false), Err(ThawDenial::Sanctioned));
+        assert_eq!(
+            check_thaw(false, false, true),
+            Err(ThawDenial::Jurisdiction)
+        );
+        assert_eq!(check_thaw(false, true, false), Err(ThawDenial::NotAllowed));
+    }
+
+    #[test]
+    fn test_freeze_follows_sanctions_and_embargoes_only() {
+        assert!(permits_freeze(true, false));
+        assert!(permits_freeze(false, true));
+        assert!(!permits_freeze(false, false));
+    }
+
+    #[test]
+    fn test_cooling_off_boundary() {
+        assert!(cooled_off(1_000, 0, 1_000));
+        assert!(!cooled_off(1_000, 60, 1_059));
+        assert!(cooled_off(1_000, 60, 1_060));
+        assert!(!cooled_off(i64::MAX - 1, u32::MAX, i64::MAX - 1));
+    }
+
+    #[test]
+    fn test_config_roundtrip() {
+        let config = HybridConfig {
+            authority: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            allow_list_program: Pubkey::new_unique(),
+            block_list_program: Pubkey::new_unique(),
+            jurisdictions: Some(JurisdictionSource {
+                program: Pubkey::new_unique(),
+                registry: Pubkey::new_unique(),
+            }),
+            cooling_off_seconds: 3_600,
+            paused: true,
+            bump: 254,
+        };
+        let data = config.try_to_vec().unwrap();
+        assert_eq!(HybridConfig::try_from_slice(&data).unwrap(), config);
+    }
+}
@@ -0,0 +1,476 @@
+// `entrypoint!` expands to cfgs only known to the BPF toolchain.
+#![allow(unexpected_cfgs)]
+
+use borsh::{BorshDeserialize, BorshSerialize};
+/**
+ * Jurisdiction Registry Program
+ *
+ * One registry account maps jurisdiction codes (ISO 3166-1 alpha-2) to
+ * allowed or blocked, and a holder record per holder attests which
+ * jurisdiction the holder is in. Gates (the hybrid gate, and any geo gate)
+ * take both accounts as extra accounts and read them with
+ * [`holder_verdict`], so an embargo is a single write to the registry that
+ * every gate and mint sharing it sees at once, instead of an update per
+ * holder.
+ *
+ * Instructions:
+ * - INITIALIZE_REGISTRY: create the registry of an authority
+ * - SET_JURISDICTION: mark a code allowed or blocked (bumps the version)
+ * - SET_HOLDER_JURISDICTION: create or update a holder's record
+ */
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+use token_acl_interface::{check_system_program, store_account_data, CanonicalPda};
+
+// Instruction discriminators
+pub const INITIALIZE_REGISTRY: u8 = 0;
+pub const SET_JURISDICTION: u8 = 1;
+pub const SET_HOLDER_JURISDICTION: u8 = 2;
+
+// Seeds
+pub const REGISTRY_SEED: &[u8] = b"jurisdiction-registry";
+pub const HOLDER_SEED: &[u8] = b"holder-jurisdiction";
+
+/// Codes a registry can hold; its account is allocated for this many
+pub const MAX_JURISDICTIONS: usize = 64;
+
+/// ISO 3166-1 alpha-2 country code, e.g. `*b"US"`
+pub type JurisdictionCode = [u8; 2];
+
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JurisdictionStatus {
+    Allowed = 0,
+    Blocked = 1,
+}
+
+impl JurisdictionStatus {
+    pub fn unpack(byte: u8) -> Result<Self, ProgramError> {
+        match byte {
+            0 => Ok(JurisdictionStatus::Allowed),
+            1 => Ok(JurisdictionStatus::Blocked),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JurisdictionEntry {
+    pub code: JurisdictionCode,
+    pub status: JurisdictionStatus,
+}
+
+/// The shared code -> status table
+///
+/// The account is allocated for [`MAX_JURISDICTIONS`] entries up front, so
+/// adding a code never reallocates; the unused tail is zero.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct JurisdictionRegistry {
+    pub authority: Pubkey,
+    /// Bumped by every `SetJurisdiction`
+    pub version: u64,
+    pub bump: u8,
+    pub entries: Vec<JurisdictionEntry>,
+}
+
+/// Which jurisdiction a holder is in, as attested by the registry authority
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct HolderJurisdiction {
+    pub registry: Pubkey,
+    pub holder: Pubkey,
+    pub code: JurisdictionCode,
+    pub bump: u8,
+}
+
+/// What a registry says about a holder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JurisdictionVerdict {
+    /// The holder's jurisdiction is allowed
+    Permitted(JurisdictionCode),
+    /// The holder's jurisdiction is blocked (embargoed)
+    Blocked(JurisdictionCode),
+    /// The holder has no record, or their code is not in the registry
+    Unknown,
+}
+
+impl JurisdictionVerdict {
+    /// Thaws need a positively allowed jurisdiction
+    pub fn permits_thaw(&self) -> bool {
+        matches!(self, JurisdictionVerdict::Permitted(_))
+    }
+
+    /// Freezes need a positively blocked one; an unknown holder is not
+    /// embargoed
+    pub fn is_embargoed(&self) -> bool {
+        matches!(self, JurisdictionVerdict::Blocked(_))
+    }
+}
+
+impl JurisdictionRegistry {
+    /// Account size for [`MAX_JURISDICTIONS`] entries
+    pub const LEN: usize = 32 + 8 + 1 + 4 + MAX_JURISDICTIONS * 3;
+
+    pub fn status(&self, code: &JurisdictionCode) -> Option<JurisdictionStatus> {
+        self.entries
+            .iter()
+            .find(|entry| entry.code == *code)
+            .map(|entry| entry.status)
+    }
+
+    /// The verdict for a holder in `code`
+    pub fn verdict(&self, code: &JurisdictionCode) -> JurisdictionVerdict {
+        match self.status(code) {
+            Some(JurisdictionStatus::Allowed) => JurisdictionVerdict::Permitted(*code),
+            Some(JurisdictionStatus::Blocked) => JurisdictionVerdict::Blocked(*code),
+            None => JurisdictionVerdict::Unknown,
+        }
+    }
+
+    /// Set `code` to `status` and bump the version
+    pub fn set(
+        &mut self,
+        code: JurisdictionCode,
+        status: JurisdictionStatus,
+    ) -> Result<(), ProgramError> {
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.code == code) {
+            entry.status = status;
+        } else if self.entries.len() < MAX_JURISDICTIONS {
+            self.entries.push(JurisdictionEntry { code, status });
+        } else {
+            msg!("Registry is full ({} jurisdictions)", MAX_JURISDICTIONS);
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+        self.version = self
+            .version
+            .checked_add(1)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    /// Read a registry from its zero-padded account data
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        Self::deserialize(&mut &data[..]).map_err(|_| ProgramError::InvalidAccountData)
+    }
+}
+
+/// Registry PDA of `authority`
+pub fn registry_address(authority: &Pubkey, program_id: &Pubkey) -> CanonicalPda {
+    CanonicalPda::find(&[REGISTRY_SEED, authority.as_ref()], program_id)
+}
+
+/// Record PDA of `holder` in `registry`
+pub fn holder_address(registry: &Pubkey, holder: &Pubkey, program_id: &Pubkey) -> CanonicalPda {
+    CanonicalPda::find(
+        &[HOLDER_SEED, registry.as_ref(), holder.as_ref()],
+        program_id,
+    )
+}
+
+/// Read what `registry` says about `holder`, for gates
+///
+/// Both accounts must be owned by `registry_program`, and the holder record
+/// must be `holder`'s PDA in `registry`. A holder without a record is
+/// [`JurisdictionVerdict::Unknown`].
+pub fn holder_verdict(
+    registry_program: &Pubkey,
+    registry: &AccountInfo,
+    holder_record: &AccountInfo,
+    holder: &Pubkey,
+) -> Result<JurisdictionVerdict, ProgramError> {
+    if registry.owner != registry_program || registry.data_is_empty() {
+        msg!("Jurisdiction registry not owned by the registry program");
+        return Err(ProgramError::IllegalOwner);
+    }
+    if *holder_record.key != holder_address(registry.key, holder, registry_program).address() {
+        msg!("Invalid holder jurisdiction PDA");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if holder_record.data_is_empty() {
+        return Ok(JurisdictionVerdict::Unknown);
+    }
+    if holder_record.owner != registry_program {
+        msg!("Holder jurisdiction not owned by the registry program");
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let record = HolderJurisdiction::try_from_slice(&holder_record.try_borrow_data()?)?;
+    let registry = JurisdictionRegistry::unpack(&registry.try_borrow_data()?)?;
+    Ok(registry.verdict(&record.code))
+}
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    match instruction_data {
+        [INITIALIZE_REGISTRY] => process_initialize_registry(program_id, accounts),
+        [SET_JURISDICTION, a, b, status] => process_set_jurisdiction(
+            program_id,
+            accounts,
+            [*a, *b],
+            JurisdictionStatus::unpack(*status)?,
+        ),
+        [SET_HOLDER_JURISDICTION, a, b] => {
+            process_set_holder_jurisdiction(program_id, accounts, [*a, *b])
+        }
+        _ => {
+            msg!("Unknown instruction");
+            Err(ProgramError::InvalidInstructionData)
+        }
+    }
+}
+
+/// Create the registry of the signing authority
+///
+/// Accounts:
+/// 0. registry PDA (writable)
+/// 1. authority (signer)
+/// 2. payer (writable, signer)
+/// 3. system program
+fn process_initialize_registry(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let registry_account = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+    let payer = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    check_system_program(system_program)?;
+
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let registry_pda = registry_address(authority.key, program_id);
+    if *registry_account.key != registry_pda.address() {
+        msg!("Invalid registry PDA");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let registry = JurisdictionRegistry {
+        authority: *authority.key,
+        version: 0,
+        bump: registry_pda.bump(),
+        entries: Vec::new(),
+    };
+
+    let rent = Rent::get()?;
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            registry_account.key,
+            rent.minimum_balance(JurisdictionRegistry::LEN),
+            JurisdictionRegistry::LEN as u64,
+            program_id,
+        ),
+        &[
+            payer.clone(),
+            registry_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[REGISTRY_SEED, authority.key.as_ref(), &[registry.bump]]],
+    )?;
+
+    registry.serialize(&mut &mut registry_account.try_borrow_mut_data()?[..])?;
+
+    msg!("Jurisdiction registry created for {}", authority.key);
+    Ok(())
+}
+
+/// The registry in `registry_account`, checked to be this program's and
+/// managed by the signing `authority`
+fn load_registry(
+    program_id: &Pubkey,
+    registry_account: &AccountInfo,
+    authority: &AccountInfo,
+) -> Result<JurisdictionRegistry, ProgramError> {
+    if registry_account.owner != program_id {
+        msg!("Registry must be owned by this program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let registry = JurisdictionRegistry::unpack(&registry_account.try_borrow_data()?)?;
+    if *authority.key != registry.authority {
+        msg!("Invalid authority");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    Ok(registry)
+}
+
+/// Mark `code` allowed or blocked
+///
+/// Accounts:
+/// 0. registry (writable)
+/// 1. authority (signer)
+fn process_set_jurisdiction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    code: JurisdictionCode,
+    status: JurisdictionStatus,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let registry_account = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+
+    let mut registry = load_registry(program_id, registry_account, authority)?;
+    registry.set(code, status)?;
+    registry.serialize(&mut &mut registry_account.try_borrow_mut_data()?[..])?;
+
+    msg!(
+        "Jurisdiction {} set to {:?} (registry version {})",
+        String::from_utf8_lossy(&code),
+        status,
+        registry.version
+    );
+    Ok(())
+}
+
+/// Create or update the record of which jurisdiction a holder is in
+///
+/// Accounts:
+/// 0. registry
+/// 1. holder record PDA (writable)
+/// 2. holder
+/// 3. authority (signer)
+/// 4. payer (writable, signer)
+/// 5. system program
+fn process_set_holder_jurisdiction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    code: JurisdictionCode,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let registry_account = next_account_info(account_info_iter)?;
+    let holder_account = next_account_info(account_info_iter)?;
+    let holder = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+    let payer = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    check_system_program(system_program)?;
+
+    load_registry(program_id, registry_account, authority)?;
+
+    let holder_pda = holder_address(registry_account.key, holder.key, program_id);
+    if *holder_account.key != holder_pda.address() {
+        msg!("Invalid holder jurisdiction PDA");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let record = HolderJurisdiction {
+        registry: *registry_account.key,
+        holder: *holder.key,
+        code,
+        bump: holder_pda.bump(),
+    };
+    let record_data = record.try_to_vec()?;
+
+    if holder_account.data_is_empty() {
+        let rent = Rent::get()?;
+        invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                holder_account.key,
+                rent.minimum_balance(record_data.len()),
+                record_data.len() as u64,
+                program_id,
+            ),
+            &[
+                payer.clone(),
+                holder_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                HOLDER_SEED,
+                registry_account.key.as_ref(),
+                holder.key.as_ref(),
+                &[record.bump],
+            ]],
+        )?;
+    } else if holder_account.owner != program_id {
+        msg!("Holder jurisdiction must be owned by this program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    store_account_data(holder_account, &record_data)?;
+
+    msg!(
+        "Holder {} set to jurisdiction {}",
+        holder.key,
+        String::from_utf8_lossy(&code)
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> JurisdictionRegistry {
+        JurisdictionRegistry {
+            authority: Pubkey::new_unique(),
+            version: 0,
+            bump: 255,
+            entries: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_set_updates_in_place_and_bumps_version() {
+        let mut registry = registry();
+        registry.set(*b"US", JurisdictionStatus::Allowed).unwrap();
+        registry.set(*b"US", JurisdictionStatus::Blocked).unwrap();
+
+        assert_eq!(registry.entries.len(), 1);
+        assert_eq!(registry.version, 2);
+        assert_eq!(
+            registry.verdict(b"US"),
+            JurisdictionVerdict::Blocked(*b"US")
+        );
+        assert_eq!(registry.verdict(b"FR"), JurisdictionVerdict::Unknown);
+    }
+
+    #[test]
+    fn test_full_registry_fits_its_account() {
+        let mut registry = registry();
+        for i in 0..MAX_JURISDICTIONS {
+            registry
+                .set(
+                    [b'A' + (i / 26) as u8, b'A' + (i % 26) as u8],
+                    JurisdictionStatus::Allowed,
+                )
+                .unwrap();
+        }
+        assert_eq!(
+            registry.set(*b"ZZ", JurisdictionStatus::Allowed),
+            Err(ProgramError::AccountDataTooSmall)
+        );
+
+        let mut data = vec![0; JurisdictionRegistry::LEN];
+        registry.serialize(&mut &mut data[..]).unwrap();
+        assert_eq!(JurisdictionRegistry::unpack(&data).unwrap(), registry);
+    }
+
+    #[test]
+    fn test_verdicts() {
+        assert!(JurisdictionVerdict::Permitted(*b"US").permits_thaw());
+        assert!(!JurisdictionVerdict::Blocked(*b"US").permits_thaw());
+        assert!(!JurisdictionVerdict::Unknown.permits_thaw());
+        assert!(JurisdictionVerdict::Blocked(*b"US").is_embargoed());
+        assert!(!JurisdictionVerdict::Unknown.is_embargoed());
+    }
+}
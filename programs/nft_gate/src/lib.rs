@@ -0,0 +1,926 @@
+// `entrypoint!` expands to cfgs only known to the BPF toolchain.
+#![allow(unexpected_cfgs)]
+
+use borsh::{BorshDeserialize, BorshSerialize};
+/**
+ * NFT-gated Thaw Gate Program
+ *
+ * Gates permissionless thaws on membership of an NFT collection following
+ * the sRFC 37 interface specification, so an NFT works as a membership
+ * pass for a permissioned token: whoever holds a pass may thaw their token
+ * account, and passing the NFT on passes the right to thaw with it. Each
+ * mint's config names the collection, by the mint of its collection NFT.
+ *
+ * Membership is what Metaplex Token Metadata attests: the NFT's metadata
+ * account must name the collection, and the collection's authority must
+ * have verified it, since anyone can mint an NFT that merely claims a
+ * collection. The holder must own the NFT's token account and hold the
+ * NFT in it, under SPL Token or Token-2022, and the NFT's mint must have
+ * issued exactly one indivisible token.
+ *
+ * The holder picks which of their NFTs to show, so the caller supplies the
+ * owner, the NFT account, the NFT's mint and its metadata; the mint's
+ * thaw extra-account-metas list resolves the config after them.
+ *
+ * Instructions:
+ * - INITIALIZE: create the config of a mint with its collection
+ * - SET_COLLECTION: replace the mint's collection
+ * - INITIALIZE_THAW_EXTRA_ACCOUNT_METAS: create the mint's thaw
+ *   extra-account-metas list
+ * - can-thaw-permissionless: succeeds for a holder of an NFT verified to
+ *   be in the collection
+ * - can-freeze-permissionless: not supported; one NFT account cannot show
+ *   that its owner holds no other NFT of the collection
+ */
+use extra_account_metas::ListKind;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction, system_program,
+    sysvar::Sysvar,
+};
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, seeds::Seed};
+use token_acl_interface::{
+    check_system_program, find_thaw_extra_account_metas_address, store_account_data, CanonicalPda,
+    GateInterfaceVersion, CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR,
+    CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+};
+
+// Instruction discriminators
+pub const INITIALIZE: u8 = 0;
+pub const SET_COLLECTION: u8 = 1;
+pub const INITIALIZE_THAW_EXTRA_ACCOUNT_METAS: u8 = 2;
+
+/// The gate interface the NFT gate speaks: the original layout, with the
+/// accounts the caller supplies ahead of the listed config
+pub const GATE_INTERFACE_VERSION: GateInterfaceVersion = GateInterfaceVersion::LEGACY;
+
+// Seeds
+pub const CONFIG_SEED: &[u8] = b"nft-config";
+
+/// Metaplex Token Metadata, which owns every NFT's metadata account
+pub const METADATA_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+/// Seed prefix of a metadata account: `[b"metadata", program, mint]`
+pub const METADATA_SEED: &[u8] = b"metadata";
+
+/// `Key::MetadataV1`, the first byte of a metadata account
+pub const METADATA_V1_KEY: u8 = 4;
+
+/// SPL Token, which most NFTs are minted with
+pub const SPL_TOKEN_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+/// Token-2022
+pub const TOKEN_2022_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+// SPL Token account layout: mint (0..32), owner (32..64), amount (64..72),
+// delegate (72..108), state (108), ...
+const TOKEN_ACCOUNT_OWNER_OFFSET: usize = 32;
+const TOKEN_ACCOUNT_AMOUNT_OFFSET: usize = 64;
+const TOKEN_ACCOUNT_STATE_OFFSET: usize = 108;
+const TOKEN_ACCOUNT_LEN: usize = 165;
+
+// SPL Token mint layout: mint authority (0..36), supply (36..44),
+// decimals (44), is_initialized (45), freeze authority (46..82)
+const MINT_SUPPLY_OFFSET: usize = 36;
+const MINT_DECIMALS_OFFSET: usize = 44;
+const MINT_INITIALIZED_OFFSET: usize = 45;
+const MINT_LEN: usize = 82;
+
+// Metaplex `TokenStandard`s of fungible tokens
+const FUNGIBLE_ASSET: u8 = 1;
+const FUNGIBLE: u8 = 2;
+
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
+
+/// Per-mint configuration: the collection whose NFTs are passes
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct NftConfig {
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    /// Mint of the collection NFT, which verified members name
+    pub collection: Pubkey,
+    pub bump: u8,
+}
+
+impl NftConfig {
+    pub const LEN: usize = 32 * 3 + 1;
+}
+
+/// A Metaplex creator
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Creator {
+    pub address: Pubkey,
+    pub verified: bool,
+    pub share: u8,
+}
+
+/// The `Data` of a Metaplex metadata account
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MetadataData {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub seller_fee_basis_points: u16,
+    pub creators: Option<Vec<Creator>>,
+}
+
+/// The collection a Metaplex NFT names, and whether the collection's
+/// authority verified it
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Collection {
+    pub verified: bool,
+    /// Mint of the collection NFT
+    pub key: Pubkey,
+}
+
+/// A Metaplex metadata account, up to and including its collection
+///
+/// The fields Metaplex lays out after the collection are not read, and the
+/// account's zero padding is ignored.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Metadata {
+    /// [`METADATA_V1_KEY`]
+    pub key: u8,
+    pub update_authority: Pubkey,
+    pub mint: Pubkey,
+    pub data: MetadataData,
+    pub primary_sale_happened: bool,
+    pub is_mutable: bool,
+    pub edition_nonce: Option<u8>,
+    /// Metaplex `TokenStandard`, e.g. 0 for `NonFungible`
+    pub token_standard: Option<u8>,
+    pub collection: Option<Collection>,
+}
+
+impl Metadata {
+    /// Read the metadata from the start of a metadata account
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        let metadata =
+            Self::deserialize(&mut &data[..]).map_err(|_| ProgramError::InvalidAccountData)?;
+        if metadata.key != METADATA_V1_KEY {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(metadata)
+    }
+}
+
+/// The fields of a token account the gate reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NftHolding {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+impl NftHolding {
+    /// Read an initialized SPL Token or Token-2022 account, with or without
+    /// extensions
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() < TOKEN_ACCOUNT_LEN || data[TOKEN_ACCOUNT_STATE_OFFSET] == 0 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let key = |offset: usize| {
+            Pubkey::try_from(&data[offset..offset + 32])
+                .map_err(|_| ProgramError::InvalidAccountData)
+        };
+        let mut amount = [0; 8];
+        amount.copy_from_slice(&data[TOKEN_ACCOUNT_AMOUNT_OFFSET..TOKEN_ACCOUNT_AMOUNT_OFFSET + 8]);
+        Ok(Self {
+            mint: key(0)?,
+            owner: key(TOKEN_ACCOUNT_OWNER_OFFSET)?,
+            amount: u64::from_le_bytes(amount),
+        })
+    }
+}
+
+/// The fields of an NFT's mint the gate reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NftMint {
+    pub supply: u64,
+    pub decimals: u8,
+}
+
+impl NftMint {
+    /// Read an initialized SPL Token or Token-2022 mint, with or without
+    /// extensions
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() < MINT_LEN || data[MINT_INITIALIZED_OFFSET] == 0 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let mut supply = [0; 8];
+        supply.copy_from_slice(&data[MINT_SUPPLY_OFFSET..MINT_SUPPLY_OFFSET + 8]);
+        Ok(Self {
+            supply: u64::from_le_bytes(supply),
+            decimals: data[MINT_DECIMALS_OFFSET],
+        })
+    }
+
+    /// Whether the mint issued exactly one token that cannot be split
+    pub fn is_unique(&self) -> bool {
+        self.supply == 1 && self.decimals == 0
+    }
+}
+
+/// Why a permissionless thaw is denied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NftDenial {
+    /// The NFT account is someone else's, or empty
+    NotHeld,
+    /// The token is fungible, by its metadata or because its mint issued
+    /// more than one token or divides it, so its supply can be split among
+    /// any number of holders
+    Fungible,
+    /// The NFT is in no collection, or in another one
+    OutsideCollection,
+    /// The NFT names the collection, but its authority has not verified it
+    Unverified,
+}
+
+/// The membership rule: `holder` holds an NFT verified to be in the
+/// configured collection
+///
+/// Metadata written before Metaplex recorded token standards has none, so
+/// whether the token is unique is read from its mint as well.
+pub fn check_membership(
+    config: &NftConfig,
+    holder: &Pubkey,
+    holding: &NftHolding,
+    nft_mint: &NftMint,
+    metadata: &Metadata,
+) -> Result<(), NftDenial> {
+    if holding.owner != *holder || holding.amount == 0 {
+        return Err(NftDenial::NotHeld);
+    }
+    if !nft_mint.is_unique() || matches!(metadata.token_standard, Some(FUNGIBLE_ASSET | FUNGIBLE)) {
+        return Err(NftDenial::Fungible);
+    }
+    match &metadata.collection {
+        Some(collection) if collection.key != config.collection => {
+            Err(NftDenial::OutsideCollection)
+        }
+        Some(collection) if collection.verified => Ok(()),
+        Some(_) => Err(NftDenial::Unverified),
+        None => Err(NftDenial::OutsideCollection),
+    }
+}
+
+/// Config PDA of `mint`
+pub fn config_address(mint: &Pubkey, program_id: &Pubkey) -> CanonicalPda {
+    CanonicalPda::find(&[CONFIG_SEED, mint.as_ref()], program_id)
+}
+
+/// Metaplex metadata account of `nft_mint`
+pub fn metadata_address(nft_mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            METADATA_SEED,
+            METADATA_PROGRAM_ID.as_ref(),
+            nft_mint.as_ref(),
+        ],
+        &METADATA_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Extra accounts of `can-thaw-permissionless` after the four the caller
+/// supplies (owner, NFT account, NFT mint and metadata): the mint's config,
+/// `[CONFIG_SEED, mint]`
+pub fn thaw_extra_account_metas() -> Result<Vec<ExtraAccountMeta>, ProgramError> {
+    Ok(vec![ExtraAccountMeta::new_with_seeds(
+        &[
+            Seed::Literal {
+                bytes: CONFIG_SEED.to_vec(),
+            },
+            Seed::AccountKey { index: 2 },
+        ],
+        false,
+        false,
+    )?])
+}
+
+/// Build an `INITIALIZE_THAW_EXTRA_ACCOUNT_METAS` instruction
+pub fn initialize_thaw_extra_account_metas(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    let (extra_account_metas, _) = find_thaw_extra_account_metas_address(mint, program_id);
+    Instruction::new_with_bytes(
+        *program_id,
+        &[INITIALIZE_THAW_EXTRA_ACCOUNT_METAS],
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(extra_account_metas, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// A collection mint packed as instruction data
+fn unpack_collection(data: &[u8]) -> Result<Pubkey, ProgramError> {
+    Pubkey::try_from(data).map_err(|_| ProgramError::InvalidInstructionData)
+}
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    match instruction_data {
+        [INITIALIZE, collection @ ..] => {
+            process_initialize(program_id, accounts, unpack_collection(collection)?)
+        }
+        [SET_COLLECTION, collection @ ..] => {
+            process_set_collection(program_id, accounts, unpack_collection(collection)?)
+        }
+        [INITIALIZE_THAW_EXTRA_ACCOUNT_METAS] => {
+            process_initialize_thaw_extra_account_metas(program_id, accounts)
+        }
+        d if d == CAN_THAW_PERMISSIONLESS_DISCRIMINATOR => {
+            process_can_thaw_permissionless(program_id, accounts)
+        }
+        d if d == CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR => {
+            // An owner without this NFT may still hold another member
+            msg!("Permissionless freeze not supported by the NFT gate");
+            Err(ProgramError::InvalidInstructionData)
+        }
+        _ => {
+            msg!("Unknown instruction");
+            Err(ProgramError::InvalidInstructionData)
+        }
+    }
+}
+
+/// Initialize the config of a mint
+///
+/// Data: the mint of the collection NFT.
+///
+/// Accounts:
+/// 0. config PDA (writable)
+/// 1. mint
+/// 2. authority (signer)
+/// 3. payer (writable, signer)
+/// 4. system program
+fn process_initialize(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    collection: Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+    let payer = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    check_system_program(system_program)?;
+
+    let config_pda = config_address(mint.key, program_id);
+    if *config_account.key != config_pda.address() {
+        msg!("Invalid config PDA");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let config = NftConfig {
+        authority: *authority.key,
+        mint: *mint.key,
+        collection,
+        bump: config_pda.bump(),
+    };
+
+    let rent = Rent::get()?;
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            config_account.key,
+            rent.minimum_balance(NftConfig::LEN),
+            NftConfig::LEN as u64,
+            program_id,
+        ),
+        &[
+            payer.clone(),
+            config_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[CONFIG_SEED, mint.key.as_ref(), &[config.bump]]],
+    )?;
+
+    store_account_data(config_account, &config.try_to_vec()?)?;
+
+    msg!(
+        "NFT gate initialized for mint {}: collection {}",
+        mint.key,
+        collection
+    );
+    Ok(())
+}
+
+/// The config in `config_account`, checked to be the PDA of `mint` and
+/// owned by this program
+fn load_config(
+    program_id: &Pubkey,
+    config_account: &AccountInfo,
+    mint: &Pubkey,
+) -> Result<NftConfig, ProgramError> {
+    if *config_account.key != config_address(mint, program_id).address()
+        || config_account.owner != program_id
+        || config_account.data_is_empty()
+    {
+        msg!("Invalid NFT gate config");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    NftConfig::try_from_slice(&config_account.try_borrow_data()?)
+        .map_err(|_| ProgramError::InvalidAccountData)
+}
+
+/// Replace the mint's collection
+///
+/// Holders of the previous collection's NFTs can no longer thaw; accounts
+/// they already thawed stay thawed.
+///
+/// Accounts:
+/// 0. config (writable)
+/// 1. mint
+/// 2. authority (signer)
+fn process_set_collection(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    collection: Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+
+    let mut config = load_config(program_id, config_account, mint.key)?;
+    if *authority.key != config.authority {
+        msg!("Invalid authority");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    config.collection = collection;
+    store_account_data(config_account, &config.try_to_vec()?)?;
+
+    msg!("Mint {} is gated on collection {}", mint.key, collection);
+    Ok(())
+}
+
+/// Create the thaw extra-account-metas PDA of a mint
+///
+/// The list is [`thaw_extra_account_metas`], so anyone may pay to create it.
+/// Accounts:
+/// 0. payer (signer, writable)
+/// 1. thaw extra-account-metas (writable)
+/// 2. mint
+/// 3. system program
+fn process_initialize_thaw_extra_account_metas(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let payer = next_account_info(account_info_iter)?;
+    let extra_account_metas = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    extra_account_metas::create_list(
+        ListKind::Thaw,
+        program_id,
+        &GATE_INTERFACE_VERSION,
+        &thaw_extra_account_metas()?,
+        payer,
+        extra_account_metas,
+        mint,
+        system_program,
+    )?;
+
+    msg!(
+        "Thaw extra-account-metas initialized for mint: {}",
+        mint.key
+    );
+    Ok(())
+}
+
+/// The NFT in `nft_account`, checked to be a token account, its mint,
+/// checked to be the NFT's under the same token program, and its metadata,
+/// checked to be the one Metaplex keeps for the NFT's mint
+fn load_nft(
+    nft_account: &AccountInfo,
+    nft_mint_account: &AccountInfo,
+    metadata_account: &AccountInfo,
+) -> Result<(NftHolding, NftMint, Metadata), ProgramError> {
+    if *nft_account.owner != SPL_TOKEN_PROGRAM_ID && *nft_account.owner != TOKEN_2022_PROGRAM_ID {
+        msg!("NFT account {} is not a token account", nft_account.key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let holding = NftHolding::unpack(&nft_account.try_borrow_data()?)?;
+
+    if *nft_mint_account.key != holding.mint || nft_mint_account.owner != nft_account.owner {
+        msg!("Not the mint of NFT {}", holding.mint);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let nft_mint = NftMint::unpack(&nft_mint_account.try_borrow_data()?)?;
+
+    if *metadata_account.key != metadata_address(&holding.mint)
+        || *metadata_account.owner != METADATA_PROGRAM_ID
+    {
+        msg!("Not the Metaplex metadata of NFT {}", holding.mint);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let metadata = Metadata::unpack(&metadata_account.try_borrow_data()?)?;
+    if metadata.mint != holding.mint {
+        msg!("Metadata is not for NFT {}", holding.mint);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok((holding, nft_mint, metadata))
+}
+
+/// sRFC 37 Interface: Can thaw permissionless
+fn process_can_thaw_permissionless(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    // Accounts as per sRFC 37 interface:
+    // 0. caller
+    // 1. token account
+    // 2. mint
+    // 3. extra-account-metas
+    // Extra accounts the caller supplies:
+    // 4. token account owner
+    // 5. the owner's token account of an NFT
+    // 6. the NFT's mint
+    // 7. the NFT's Metaplex metadata PDA
+    // Extra accounts (from extra-account-metas):
+    // 8. NFT gate config PDA
+
+    let _caller = next_account_info(account_info_iter)?;
+    let token_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let _extra_account_metas = next_account_info(account_info_iter)?;
+    let token_account_owner = next_account_info(account_info_iter)?;
+    let nft_account = next_account_info(account_info_iter)?;
+    let nft_mint_account = next_account_info(account_info_iter)?;
+    let metadata_account = next_account_info(account_info_iter)?;
+    let config_account = next_account_info(account_info_iter)?;
+
+    // The NFT is matched against the supplied owner, so it must be the
+    // account's current owner
+    if !token_account.data_is_empty() {
+        let data = token_account.try_borrow_data()?;
+        let bound = data.len() >= TOKEN_ACCOUNT_OWNER_OFFSET + 32
+            && data[..32] == mint.key.to_bytes()
+            && data[TOKEN_ACCOUNT_OWNER_OFFSET..TOKEN_ACCOUNT_OWNER_OFFSET + 32]
+                == token_account_owner.key.to_bytes();
+        if !bound {
+            msg!(
+                "Token account {} is not owned by {} for this mint",
+                token_account.key,
+                token_account_owner.key
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+
+    let config = load_config(program_id, config_account, mint.key)?;
+    let (holding, nft_mint, metadata) = load_nft(nft_account, nft_mint_account, metadata_account)?;
+    let user = token_account_owner.key;
+
+    match check_membership(&config, user, &holding, &nft_mint, &metadata) {
+        Ok(()) => {
+            msg!(
+                "✅ User {} holds NFT {} of collection {} - permissionless thaw authorized",
+                user,
+                holding.mint,
+                config.collection
+            );
+            Ok(())
+        }
+        Err(NftDenial::NotHeld) => {
+            msg!(
+                "❌ User {} does not hold NFT {} - permissionless thaw denied",
+                user,
+                holding.mint
+            );
+            Err(ProgramError::InvalidAccountData)
+        }
+        Err(NftDenial::Fungible) => {
+            msg!(
+                "❌ User {} holds fungible token {}, not an NFT - permissionless thaw denied",
+                user,
+                holding.mint
+            );
+            Err(ProgramError::InvalidAccountData)
+        }
+        Err(NftDenial::OutsideCollection) => {
+            msg!(
+                "❌ User {} holds NFT {}, which is not in collection {} - permissionless thaw denied",
+                user,
+                holding.mint,
+                config.collection
+            );
+            Err(ProgramError::InvalidAccountData)
+        }
+        Err(NftDenial::Unverified) => {
+            msg!(
+                "❌ User {} holds NFT {}, which is not verified in collection {} - permissionless thaw denied",
+                user,
+                holding.mint,
+                config.collection
+            );
+            Err(ProgramError::InvalidAccountData)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The mint of an NFT: one token, without decimals
+    const NFT: NftMint = NftMint {
+        supply: 1,
+        decimals: 0,
+    };
+
+    fn config() -> NftConfig {
+        NftConfig {
+            authority: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            collection: Pubkey::new_unique(),
+            bump: 255,
+        }
+    }
+
+    fn metadata(mint: Pubkey, collection: Option<Collection>) -> Metadata {
+        Metadata {
+            key: METADATA_V1_KEY,
+            update_authority: Pubkey::new_unique(),
+            mint,
+            data: MetadataData {
+                name: "Pass #1".to_string(),
+                symbol: "PASS".to_string(),
+                uri: "https://example.com/pass/1.json".to_string(),
+                seller_fee_basis_points: 500,
+                creators: Some(vec![Creator {
+                    address: Pubkey::new_unique(),
+                    verified: true,
+                    share: 100,
+                }]),
+            },
+            primary_sale_happened: false,
+            is_mutable: true,
+            edition_nonce: Some(254),
+            token_standard: Some(0),
+            collection,
+        }
+    }
+
+    #[test]
+    fn test_membership_needs_a_verified_collection() {
+        let config = config();
+        let holder = Pubkey::new_unique();
+        let holding = NftHolding {
+            mint: Pubkey::new_unique(),
+            owner: holder,
+            amount: 1,
+        };
+        let member = |verified, key| metadata(holding.mint, Some(Collection { verified, key }));
+
+        let verified = member(true, config.collection);
+        assert_eq!(
+            check_membership(&config, &holder, &holding, &NFT, &verified),
+            Ok(())
+        );
+        assert_eq!(
+            check_membership(
+                &config,
+                &holder,
+                &holding,
+                &NFT,
+                &member(false, config.collection)
+            ),
+            Err(NftDenial::Unverified)
+        );
+        assert_eq!(
+            check_membership(
+                &config,
+                &holder,
+                &holding,
+                &NFT,
+                &member(true, Pubkey::new_unique())
+            ),
+            Err(NftDenial::OutsideCollection)
+        );
+        assert_eq!(
+            check_membership(
+                &config,
+                &holder,
+                &holding,
+                &NFT,
+                &metadata(holding.mint, None)
+            ),
+            Err(NftDenial::OutsideCollection)
+        );
+
+        let mut fungible = verified.clone();
+        fungible.token_standard = Some(FUNGIBLE);
+        assert_eq!(
+            check_membership(&config, &holder, &holding, &NFT, &fungible),
+            Err(NftDenial::Fungible)
+        );
+    }
+
+    #[test]
+    fn test_membership_needs_a_unique_token() {
+        let config = config();
+        let holder = Pubkey::new_unique();
+        let holding = NftHolding {
+            mint: Pubkey::new_unique(),
+            owner: holder,
+            amount: 1,
+        };
+        let member = metadata(
+            holding.mint,
+            Some(Collection {
+                verified: true,
+                key: config.collection,
+            }),
+        );
+        // Metadata that predates token standards records none
+        let mut legacy = member.clone();
+        legacy.token_standard = None;
+        assert_eq!(
+            check_membership(&config, &holder, &holding, &NFT, &legacy),
+            Ok(())
+        );
+
+        for mint in [
+            NftMint {
+                supply: 1_000,
+                decimals: 0,
+            },
+            NftMint {
+                supply: 1,
+                decimals: 6,
+            },
+            NftMint {
+                supply: 0,
+                decimals: 0,
+            },
+        ] {
+            for metadata in [&member, &legacy] {
+                assert_eq!(
+                    check_membership(&config, &holder, &holding, &mint, metadata),
+                    Err(NftDenial::Fungible),
+                    "{:?} with token standard {:?}",
+                    mint,
+                    metadata.token_standard
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_membership_needs_the_nft_held() {
+        let config = config();
+        let holder = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let member = metadata(
+            mint,
+            Some(Collection {
+                verified: true,
+                key: config.collection,
+            }),
+        );
+        let holding = |owner, amount| NftHolding {
+            mint,
+            owner,
+            amount,
+        };
+
+        assert_eq!(
+            check_membership(&config, &holder, &holding(holder, 1), &NFT, &member),
+            Ok(())
+        );
+        assert_eq!(
+            check_membership(&config, &holder, &holding(holder, 0), &NFT, &member),
+            Err(NftDenial::NotHeld)
+        );
+        assert_eq!(
+            check_membership(
+                &config,
+                &holder,
+                &holding(Pubkey::new_unique(), 1),
+                &NFT,
+                &member
+            ),
+            Err(NftDenial::NotHeld)
+        );
+    }
+
+    #[test]
+    fn test_metadata_reads_past_its_padding() {
+        let mint = Pubkey::new_unique();
+        let member = metadata(
+            mint,
+            Some(Collection {
+                verified: true,
+                key: Pubkey::new_unique(),
+            }),
+        );
+        // Metaplex allocates metadata accounts at a fixed size and appends
+        // fields after the collection
+        let mut data = member.try_to_vec().unwrap();
+        data.resize(679, 0);
+        assert_eq!(Metadata::unpack(&data), Ok(member.clone()));
+
+        let mut edition = data.clone();
+        edition[0] = 6;
+        assert_eq!(
+            Metadata::unpack(&edition),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            Metadata::unpack(&data[..40]),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn test_holding_reads_token_accounts() {
+        let mint = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mut data = vec![0; TOKEN_ACCOUNT_LEN];
+        data[..32].copy_from_slice(mint.as_ref());
+        data[32..64].copy_from_slice(owner.as_ref());
+        data[64..72].copy_from_slice(&1u64.to_le_bytes());
+        data[TOKEN_ACCOUNT_STATE_OFFSET] = 1;
+        let holding = NftHolding {
+            mint,
+            owner,
+            amount: 1,
+        };
+        assert_eq!(NftHolding::unpack(&data), Ok(holding));
+
+        // Token-2022 extensions follow the base account
+        let mut extended = data.clone();
+        extended.resize(TOKEN_ACCOUNT_LEN + 8, 2);
+        assert_eq!(NftHolding::unpack(&extended), Ok(holding));
+
+        let mut uninitialized = data.clone();
+        uninitialized[TOKEN_ACCOUNT_STATE_OFFSET] = 0;
+        for invalid in [&uninitialized[..], &data[..TOKEN_ACCOUNT_LEN - 1]] {
+            assert_eq!(
+                NftHolding::unpack(invalid),
+                Err(ProgramError::InvalidAccountData)
+            );
+        }
+    }
+
+    #[test]
+    fn test_mint_reads_supply_and_decimals() {
+        let mut data = vec![0; MINT_LEN];
+        data[MINT_SUPPLY_OFFSET..MINT_SUPPLY_OFFSET + 8].copy_from_slice(&1u64.to_le_bytes());
+        data[MINT_INITIALIZED_OFFSET] = 1;
+        assert_eq!(NftMint::unpack(&data), Ok(NFT));
+
+        // Token-2022 extensions follow the base mint
+        let mut extended = data.clone();
+        extended.resize(TOKEN_ACCOUNT_LEN + 8, 2);
+        assert_eq!(NftMint::unpack(&extended), Ok(NFT));
+
+        let mut uninitialized = data.clone();
+        uninitialized[MINT_INITIALIZED_OFFSET] = 0;
+        for invalid in [&uninitialized[..], &data[..MINT_LEN - 1]] {
+            assert_eq!(
+                NftMint::unpack(invalid),
+                Err(ProgramError::InvalidAccountData)
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_fits_its_account() {
+        assert_eq!(config().try_to_vec().unwrap().len(), NftConfig::LEN);
+    }
+}
@@ -7,41 +7,100 @@ use borsh::{BorshDeserialize, BorshSerialize};
  *
  * Features:
  * - Implements can-thaw-permissionless interface
+ * - Creates the thaw extra-account-metas PDA, resolving the allow list record
+ *   and missing-record policy after the owner the caller supplies
  * - Admin controls for managing allow list
  * - Tiered access levels
  * - Expiry handling
- * - Upgrade authority
+ * - Two-step authority handover: proposed, then accepted by the new key
+ * - Initialize and add fail clearly on an existing account, or leave it as is
+ * - Permanent revocation ("never thaw again")
+ * - Records close with their rent refunded
+ * - Per-mint missing-record policy (default: deny holders without a record)
+ * - Snapshot import: holders materialize their own record from a Merkle proof
+ * - Manual review: under the `Review` missing-record policy, holders without
+ *   a record queue a review request and wait for the authority's decision
+ * - Thaw decisions logged as events, natively or in Anchor's layout
  * - Comprehensive error handling
  */
+use extra_account_metas::ListKind;
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
-    entrypoint,
+    clock::Clock,
     entrypoint::ProgramResult,
+    hash::hashv,
+    instruction::{AccountMeta, Instruction},
     msg,
     program::invoke_signed,
     program_error::ProgramError,
     pubkey::Pubkey,
     rent::Rent,
-    system_instruction,
+    system_instruction, system_program,
     sysvar::Sysvar,
 };
-
-// Discriminators from sRFC 37
-const CAN_THAW_PERMISSIONLESS_DISCRIMINATOR: [u8; 8] = [8, 175, 169, 129, 137, 74, 61, 241];
-const CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR: [u8; 8] = [214, 141, 109, 75, 248, 1, 45, 29];
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, seeds::Seed};
+use token_acl_interface::{
+    check_system_program,
+    events::{DecisionReason, EventFormat, ThawDecision},
+    find_missing_record_policy_address, find_thaw_extra_account_metas_address, store_account_data,
+    CanonicalPda, GateInterfaceVersion, MissingRecordPolicy,
+    CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR, CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+    FEATURE_MISSING_RECORD_POLICY, MISSING_RECORD_POLICY_SEED,
+};
 
 // Instruction discriminators
-const INITIALIZE: u8 = 0;
-const ADD_TO_ALLOW_LIST: u8 = 1;
-const REMOVE_FROM_ALLOW_LIST: u8 = 2;
-const UPDATE_AUTHORITY: u8 = 3;
+pub const INITIALIZE: u8 = 0;
+pub const ADD_TO_ALLOW_LIST: u8 = 1;
+pub const REMOVE_FROM_ALLOW_LIST: u8 = 2;
+pub const UPDATE_AUTHORITY: u8 = 3;
+pub const REVOKE: u8 = 4;
+pub const LIFT_REVOCATION: u8 = 5;
+pub const SET_MISSING_RECORD_POLICY: u8 = 6;
+pub const SET_SNAPSHOT_ROOT: u8 = 7;
+// 8 would shadow the can-thaw-permissionless discriminator, which starts with it
+pub const MATERIALIZE_FROM_SNAPSHOT: u8 = 9;
+pub const CLOSE_RECORD: u8 = 10;
+pub const ACCEPT_AUTHORITY: u8 = 11;
+pub const CANCEL_AUTHORITY_TRANSFER: u8 = 12;
+pub const INITIALIZE_THAW_EXTRA_ACCOUNT_METAS: u8 = 13;
+pub const REQUEST_REVIEW: u8 = 14;
+pub const APPROVE_REVIEW: u8 = 15;
+pub const REJECT_REVIEW: u8 = 16;
+
+/// Layout of the thaw decisions the gate logs; the `anchor-events` feature
+/// selects Anchor's
+pub const EVENT_FORMAT: EventFormat = if cfg!(feature = "anchor-events") {
+    EventFormat::Anchor
+} else {
+    EventFormat::Native
+};
+
+/// An allow list only thaws holders it knows, unless the issuer says otherwise
+pub const DEFAULT_MISSING_RECORD_POLICY: MissingRecordPolicy = MissingRecordPolicy::Deny;
+
+/// The gate interface this allow list speaks: its thaw check takes the
+/// missing-record policy PDA
+pub const GATE_INTERFACE_VERSION: GateInterfaceVersion = GateInterfaceVersion {
+    version: 1,
+    features: FEATURE_MISSING_RECORD_POLICY,
+};
 
 // Seeds
-const ALLOW_LIST_SEED: &[u8] = b"allow-list";
-const CONFIG_SEED: &[u8] = b"config";
+pub const ALLOW_LIST_SEED: &[u8] = b"allow-list";
+pub const CONFIG_SEED: &[u8] = b"config";
+pub const SNAPSHOT_ROOT_SEED: &[u8] = b"snapshot-root";
+pub const PENDING_AUTHORITY_SEED: &[u8] = b"pending-authority";
+pub const REVIEW_REQUEST_SEED: &[u8] = b"review-request";
+
+// Domain prefixes keep a leaf from passing as an inner node and vice versa
+const SNAPSHOT_LEAF_PREFIX: &[u8] = &[0];
+const SNAPSHOT_NODE_PREFIX: &[u8] = &[1];
+
+// SPL Token account layout: mint (0..32), owner (32..64), ...
+const TOKEN_ACCOUNT_OWNER_OFFSET: usize = 32;
 
 #[cfg(not(feature = "no-entrypoint"))]
-entrypoint!(process_instruction);
+solana_program::entrypoint!(process_instruction);
 
 /// Program configuration
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
@@ -51,6 +110,32 @@ pub struct Config {
     pub bump: u8,
 }
 
+/// Authority `UpdateAuthority` proposed for a mint's config, waiting for
+/// the proposed key to run `AcceptAuthority`
+///
+/// Until it does, the config keeps its current authority, so a mistyped
+/// key never takes control.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct PendingAuthority {
+    pub mint: Pubkey,
+    pub proposed: Pubkey,
+    pub bump: u8,
+}
+
+/// A holder's request to be reviewed, queued with `RequestReview` while
+/// the mint's missing-record policy is `Review`
+///
+/// `ApproveReview` and `RejectReview` take it off the queue, refunding its
+/// rent to the holder.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct ReviewRequest {
+    pub mint: Pubkey,
+    pub user: Pubkey,
+    /// Cluster time the holder asked for review
+    pub requested_timestamp: i64,
+    pub bump: u8,
+}
+
 /// Access levels for tiered permissions
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
 pub enum AccessLevel {
@@ -67,9 +152,342 @@ pub struct AllowListRecord {
     pub user: Pubkey,
     pub allowed: bool,
     pub access_level: AccessLevel,
+    /// Cluster time `AddToAllowList` created the record; gates with a
+    /// cooling-off period count from it
     pub added_timestamp: i64,
     pub expiry_timestamp: Option<i64>,
     pub bump: u8,
+    /// Set by `Revoke`: the user can never thaw, whatever `allowed` and the
+    /// expiry say, until the authority runs `LiftRevocation`
+    pub revoked: bool,
+}
+
+/// How `RemoveFromAllowList` disposes of a record
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum RemoveMode {
+    /// Keep the record but mark it as not allowed
+    Deactivate = 0,
+    /// Close the record and refund its rent to a recipient account
+    Close = 1,
+}
+
+impl RemoveMode {
+    /// Parse the mode from instruction data, defaulting to `Deactivate`
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        match data.first() {
+            None | Some(0) => Ok(RemoveMode::Deactivate),
+            Some(1) => Ok(RemoveMode::Close),
+            Some(_) => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+/// What `Initialize` and `AddToAllowList` do when their account exists
+///
+/// The account exists once an earlier instruction created it, including one
+/// that landed first in a race between two admins or two retries.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum InitMode {
+    /// Fail with `AccountAlreadyInitialized`
+    Create = 0,
+    /// Succeed without changing the account, provided it is what the
+    /// instruction would have created: a config for the same mint and
+    /// authority, or a record for the same mint and user. Like Anchor's
+    /// `init_if_needed`, but an existing record keeps its state, so a
+    /// deactivated or revoked user is not let back in.
+    IfNeeded = 1,
+}
+
+impl InitMode {
+    /// Parse the mode from instruction data, defaulting to `Create`
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        match data.first() {
+            None | Some(0) => Ok(InitMode::Create),
+            Some(1) => Ok(InitMode::IfNeeded),
+            Some(_) => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+/// Instruction data of `AddToAllowList`, after the discriminator and the
+/// `InitMode` byte
+///
+/// Data that stops at the mode byte, or has none, adds the user with the
+/// default: enhanced access that never expires.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct AddToAllowListArgs {
+    pub access_level: AccessLevel,
+    pub expiry_timestamp: Option<i64>,
+}
+
+impl Default for AddToAllowListArgs {
+    fn default() -> Self {
+        Self {
+            access_level: AccessLevel::Enhanced,
+            expiry_timestamp: None,
+        }
+    }
+}
+
+impl AddToAllowListArgs {
+    /// Parse the args that follow the `InitMode` byte, defaulting when
+    /// there are none
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.is_empty() {
+            return Ok(Self::default());
+        }
+        Self::try_from_slice(data).map_err(|_| ProgramError::InvalidInstructionData)
+    }
+
+    /// Reject a record that could never thaw anything: one with
+    /// `AccessLevel::None`, or one that expires by `current_timestamp`
+    pub fn validate(&self, current_timestamp: i64) -> ProgramResult {
+        if self.access_level == AccessLevel::None {
+            msg!("A record must grant an access level above None");
+            return Err(ProgramError::InvalidArgument);
+        }
+        if let Some(expiry) = self.expiry_timestamp {
+            if expiry <= current_timestamp {
+                msg!(
+                    "Expiry {} is not after the current time {}",
+                    expiry,
+                    current_timestamp
+                );
+                return Err(ProgramError::InvalidArgument);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Merkle root of an allow list snapshot the authority published for a mint
+///
+/// Holders in the snapshot create their own record with
+/// `MaterializeFromSnapshot`, paying its rent, instead of the authority
+/// adding each of them.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct SnapshotRoot {
+    pub mint: Pubkey,
+    pub root: [u8; 32],
+    pub bump: u8,
+}
+
+/// One holder's entry in an allow list snapshot
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct SnapshotEntry {
+    pub user: Pubkey,
+    pub access_level: AccessLevel,
+    pub expiry_timestamp: Option<i64>,
+}
+
+impl SnapshotEntry {
+    /// Leaf hash of the entry in `mint`'s snapshot
+    ///
+    /// The mint is part of the leaf, so a proof for one mint's snapshot
+    /// never materializes a record for another.
+    pub fn leaf(&self, mint: &Pubkey) -> [u8; 32] {
+        let expiry = self.expiry_timestamp.map_or([0; 9], |expiry| {
+            let mut bytes = [1; 9];
+            bytes[1..].copy_from_slice(&expiry.to_le_bytes());
+            bytes
+        });
+        hashv(&[
+            SNAPSHOT_LEAF_PREFIX,
+            mint.as_ref(),
+            self.user.as_ref(),
+            &[self.access_level as u8],
+            &expiry,
+        ])
+        .to_bytes()
+    }
+}
+
+/// Parent of two snapshot nodes
+///
+/// The pair is hashed in sorted order, so a proof is just the siblings from
+/// leaf to root, without their positions.
+pub fn snapshot_node(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (low, high) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[SNAPSHOT_NODE_PREFIX, low, high]).to_bytes()
+}
+
+/// Whether `proof` leads from `leaf` to `root`
+pub fn verify_snapshot_proof(root: &[u8; 32], leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+    proof
+        .iter()
+        .fold(leaf, |node, sibling| snapshot_node(&node, sibling))
+        == *root
+}
+
+/// Instruction data of `MaterializeFromSnapshot`, after the discriminator
+///
+/// The user of the entry is the signer, so a holder can only materialize
+/// their own record.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct MaterializeArgs {
+    pub access_level: AccessLevel,
+    pub expiry_timestamp: Option<i64>,
+    pub proof: Vec<[u8; 32]>,
+}
+
+/// Config PDA of `mint`
+pub fn config_address(mint: &Pubkey, program_id: &Pubkey) -> CanonicalPda {
+    CanonicalPda::find(&[CONFIG_SEED, mint.as_ref()], program_id)
+}
+
+/// Allow list record PDA of `user` for `mint`
+pub fn allow_list_address(mint: &Pubkey, user: &Pubkey, program_id: &Pubkey) -> CanonicalPda {
+    CanonicalPda::find(&[ALLOW_LIST_SEED, mint.as_ref(), user.as_ref()], program_id)
+}
+
+/// Snapshot root PDA of `mint`
+pub fn snapshot_root_address(mint: &Pubkey, program_id: &Pubkey) -> CanonicalPda {
+    CanonicalPda::find(&[SNAPSHOT_ROOT_SEED, mint.as_ref()], program_id)
+}
+
+/// Pending-authority PDA of `mint`
+pub fn pending_authority_address(mint: &Pubkey, program_id: &Pubkey) -> CanonicalPda {
+    CanonicalPda::find(&[PENDING_AUTHORITY_SEED, mint.as_ref()], program_id)
+}
+
+/// Review request PDA of `user` for `mint`
+pub fn review_request_address(mint: &Pubkey, user: &Pubkey, program_id: &Pubkey) -> CanonicalPda {
+    CanonicalPda::find(
+        &[REVIEW_REQUEST_SEED, mint.as_ref(), user.as_ref()],
+        program_id,
+    )
+}
+
+/// Extra accounts of `can-thaw-permissionless` after the owner, which the
+/// caller supplies: the owner's allow list record,
+/// `[ALLOW_LIST_SEED, mint, owner]`, with the owner read from bytes 32..64 of
+/// the token account, and the missing-record policy PDA,
+/// `[MISSING_RECORD_POLICY_SEED, mint]`
+pub fn thaw_extra_account_metas() -> Result<Vec<ExtraAccountMeta>, ProgramError> {
+    Ok(vec![
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: ALLOW_LIST_SEED.to_vec(),
+                },
+                Seed::AccountKey { index: 2 },
+                Seed::AccountData {
+                    account_index: 1,
+                    data_index: TOKEN_ACCOUNT_OWNER_OFFSET as u8,
+                    length: 32,
+                },
+            ],
+            false,
+            false,
+        )?,
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: MISSING_RECORD_POLICY_SEED.to_vec(),
+                },
+                Seed::AccountKey { index: 2 },
+            ],
+            false,
+            false,
+        )?,
+    ])
+}
+
+/// Build an `INITIALIZE_THAW_EXTRA_ACCOUNT_METAS` instruction
+pub fn initialize_thaw_extra_account_metas(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    let (extra_account_metas, _) = find_thaw_extra_account_metas_address(mint, program_id);
+    Instruction::new_with_bytes(
+        *program_id,
+        &[INITIALIZE_THAW_EXTRA_ACCOUNT_METAS],
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(extra_account_metas, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Build a `REQUEST_REVIEW` instruction, signed and paid for by `user`
+pub fn request_review(program_id: &Pubkey, mint: &Pubkey, user: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &[REQUEST_REVIEW],
+        vec![
+            AccountMeta::new_readonly(config_address(mint, program_id).address(), false),
+            AccountMeta::new_readonly(
+                find_missing_record_policy_address(mint, program_id).0,
+                false,
+            ),
+            AccountMeta::new_readonly(allow_list_address(mint, user, program_id).address(), false),
+            AccountMeta::new(
+                review_request_address(mint, user, program_id).address(),
+                false,
+            ),
+            AccountMeta::new(*user, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Build an `APPROVE_REVIEW` instruction adding `user` with `args`, signed by
+/// `authority`; `payer` pays for the record
+pub fn approve_review(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    user: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+    args: &AddToAllowListArgs,
+) -> Instruction {
+    let mut data = vec![APPROVE_REVIEW];
+    data.extend_from_slice(&args.try_to_vec().expect("args always serialize"));
+    Instruction::new_with_bytes(
+        *program_id,
+        &data,
+        review_decision_accounts(program_id, mint, user, authority, payer),
+    )
+}
+
+/// Build a `REJECT_REVIEW` instruction, signed by `authority`; `payer` pays
+/// for the deactivated record
+pub fn reject_review(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    user: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &[REJECT_REVIEW],
+        review_decision_accounts(program_id, mint, user, authority, payer),
+    )
+}
+
+fn review_decision_accounts(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    user: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new_readonly(config_address(mint, program_id).address(), false),
+        AccountMeta::new(
+            review_request_address(mint, user, program_id).address(),
+            false,
+        ),
+        AccountMeta::new(allow_list_address(mint, user, program_id).address(), false),
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(*user, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ]
 }
 
 impl AllowListRecord {
@@ -80,6 +498,11 @@ impl AllowListRecord {
             false
         }
     }
+
+    /// Whether a permissionless thaw may go ahead at `current_timestamp`
+    pub fn permits_thaw(&self, current_timestamp: i64) -> bool {
+        !self.revoked && self.allowed && !self.is_expired(current_timestamp)
+    }
 }
 
 pub fn process_instruction(
@@ -98,8 +521,32 @@ pub fn process_instruction(
         ADD_TO_ALLOW_LIST => {
             process_add_to_allow_list(program_id, accounts, &instruction_data[1..])
         }
-        REMOVE_FROM_ALLOW_LIST => process_remove_from_allow_list(program_id, accounts),
+        REMOVE_FROM_ALLOW_LIST => {
+            process_remove_from_allow_list(program_id, accounts, &instruction_data[1..])
+        }
         UPDATE_AUTHORITY => process_update_authority(program_id, accounts, &instruction_data[1..]),
+        REVOKE => process_revoke(program_id, accounts),
+        LIFT_REVOCATION => process_lift_revocation(program_id, accounts),
+        SET_MISSING_RECORD_POLICY => {
+            process_set_missing_record_policy(program_id, accounts, &instruction_data[1..])
+        }
+        SET_SNAPSHOT_ROOT => {
+            process_set_snapshot_root(program_id, accounts, &instruction_data[1..])
+        }
+        MATERIALIZE_FROM_SNAPSHOT => {
+            process_materialize_from_snapshot(program_id, accounts, &instruction_data[1..])
+        }
+        CLOSE_RECORD => process_close_record(program_id, accounts),
+        ACCEPT_AUTHORITY => process_accept_authority(program_id, accounts),
+        CANCEL_AUTHORITY_TRANSFER => process_cancel_authority_transfer(program_id, accounts),
+        INITIALIZE_THAW_EXTRA_ACCOUNT_METAS => {
+            process_initialize_thaw_extra_account_metas(program_id, accounts)
+        }
+        REQUEST_REVIEW => process_request_review(program_id, accounts),
+        APPROVE_REVIEW => {
+            process_review_decision(program_id, accounts, Some(&instruction_data[1..]))
+        }
+        REJECT_REVIEW => process_review_decision(program_id, accounts, None),
         _ => {
             // Check for sRFC 37 interface discriminators
             if instruction_data.len() >= 8 {
@@ -118,11 +565,11 @@ pub fn process_instruction(
 }
 
 /// Initialize the program configuration
-fn process_initialize(
-    program_id: &Pubkey,
-    accounts: &[AccountInfo],
-    _data: &[u8],
-) -> ProgramResult {
+///
+/// Instruction data (after the discriminator) is an optional `InitMode`
+/// byte, `Create` by default.
+fn process_initialize(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let mode = InitMode::unpack(data)?;
     let account_info_iter = &mut accounts.iter();
 
     let config_account = next_account_info(account_info_iter)?;
@@ -130,12 +577,13 @@ fn process_initialize(
     let authority = next_account_info(account_info_iter)?;
     let payer = next_account_info(account_info_iter)?;
     let system_program = next_account_info(account_info_iter)?;
+    check_system_program(system_program)?;
 
     // Verify config PDA
-    let (config_pda, bump) =
-        Pubkey::find_program_address(&[CONFIG_SEED, mint.key.as_ref()], program_id);
+    let config_pda = config_address(mint.key, program_id);
+    let bump = config_pda.bump();
 
-    if *config_account.key != config_pda {
+    if *config_account.key != config_pda.address() {
         msg!("Invalid config PDA");
         return Err(ProgramError::InvalidAccountData);
     }
@@ -144,6 +592,30 @@ fn process_initialize(
         return Err(ProgramError::MissingRequiredSignature);
     }
 
+    if !config_account.data_is_empty() {
+        if config_account.owner != program_id {
+            msg!("Config must be owned by this program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let existing = Config::try_from_slice(&config_account.try_borrow_data()?)?;
+        if mode == InitMode::IfNeeded
+            && existing.mint == *mint.key
+            && existing.authority == *authority.key
+        {
+            msg!(
+                "Allow list program already initialized for mint: {}",
+                mint.key
+            );
+            return Ok(());
+        }
+        msg!(
+            "Config for mint {} already initialized with authority {}",
+            mint.key,
+            existing.authority
+        );
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
     // Create config account
     let config = Config {
         authority: *authority.key,
@@ -171,21 +643,23 @@ fn process_initialize(
         &[&[CONFIG_SEED, mint.key.as_ref(), &[bump]]],
     )?;
 
-    config_account
-        .data
-        .borrow_mut()
-        .copy_from_slice(&config_data);
+    store_account_data(config_account, &config_data)?;
 
     msg!("Allow list program initialized for mint: {}", mint.key);
     Ok(())
 }
 
 /// Add user to allow list
+///
+/// Instruction data (after the discriminator) is an optional `InitMode`
+/// byte, `Create` by default, then optional `AddToAllowListArgs`.
 fn process_add_to_allow_list(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
-    _data: &[u8],
+    data: &[u8],
 ) -> ProgramResult {
+    let mode = InitMode::unpack(data)?;
+    let args = AddToAllowListArgs::unpack(data.get(1..).unwrap_or_default())?;
     let account_info_iter = &mut accounts.iter();
 
     let config_account = next_account_info(account_info_iter)?;
@@ -195,75 +669,81 @@ fn process_add_to_allow_list(
     let authority = next_account_info(account_info_iter)?;
     let payer = next_account_info(account_info_iter)?;
     let system_program = next_account_info(account_info_iter)?;
+    check_system_program(system_program)?;
+
+    if config_account.owner != program_id {
+        msg!("Config must be owned by this program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
 
     // Verify authority
-    let config = Config::try_from_slice(&config_account.data.borrow())?;
+    let config = Config::try_from_slice(&config_account.try_borrow_data()?)?;
     if *authority.key != config.authority {
         msg!("Invalid authority");
         return Err(ProgramError::InvalidAccountData);
     }
 
+    if *mint.key != config.mint {
+        msg!("Config belongs to a different mint");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Ownership alone would accept a config at a non-canonical address
+    config_address(mint.key, program_id).verify(config_account.key)?;
+
     if !authority.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    // Parse parameters (access_level, expiry)
-    // Simplified - in production, parse from data properly
-    let access_level = AccessLevel::Enhanced;
-    let expiry_timestamp = None;
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    args.validate(current_timestamp)?;
 
     // Verify allow list PDA
-    let (allow_list_pda, bump) = Pubkey::find_program_address(
-        &[ALLOW_LIST_SEED, mint.key.as_ref(), user.key.as_ref()],
-        program_id,
-    );
+    let allow_list_pda = allow_list_address(mint.key, user.key, program_id);
+    let bump = allow_list_pda.bump();
 
-    if *allow_list_account.key != allow_list_pda {
+    if *allow_list_account.key != allow_list_pda.address() {
         msg!("Invalid allow list PDA");
         return Err(ProgramError::InvalidAccountData);
     }
 
+    if !allow_list_account.data_is_empty() {
+        if allow_list_account.owner != program_id {
+            msg!("Allow list record must be owned by this program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let existing = AllowListRecord::try_from_slice(&allow_list_account.try_borrow_data()?)?;
+        if mode == InitMode::IfNeeded && existing.mint == *mint.key && existing.user == *user.key {
+            msg!(
+                "User {} already has an allow list record, left as is",
+                user.key
+            );
+            return Ok(());
+        }
+        msg!("User {} already has an allow list record", user.key);
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
     // Create allow list record
     let record = AllowListRecord {
         mint: *mint.key,
         user: *user.key,
         allowed: true,
-        access_level,
-        added_timestamp: 0, // Use Clock sysvar in production
-        expiry_timestamp,
+        access_level: args.access_level,
+        added_timestamp: current_timestamp,
+        expiry_timestamp: args.expiry_timestamp,
         bump,
+        revoked: false,
     };
 
-    let record_data = record.try_to_vec()?;
-    let rent = Rent::get()?;
-    let required_lamports = rent.minimum_balance(record_data.len());
-
-    invoke_signed(
-        &system_instruction::create_account(
-            payer.key,
-            allow_list_account.key,
-            required_lamports,
-            record_data.len() as u64,
-            program_id,
-        ),
-        &[
-            payer.clone(),
-            allow_list_account.clone(),
-            system_program.clone(),
-        ],
-        &[&[
-            ALLOW_LIST_SEED,
-            mint.key.as_ref(),
-            user.key.as_ref(),
-            &[bump],
-        ]],
+    create_record(
+        program_id,
+        allow_list_account,
+        payer,
+        system_program,
+        &record,
     )?;
 
-    allow_list_account
-        .data
-        .borrow_mut()
-        .copy_from_slice(&record_data);
-
     msg!(
         "User {} added to allow list for mint {}",
         user.key,
@@ -273,150 +753,1100 @@ fn process_add_to_allow_list(
 }
 
 /// Remove user from allow list
-fn process_remove_from_allow_list(_program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+///
+/// Instruction data (after the discriminator) is an optional `RemoveMode` byte.
+/// `Deactivate` (the default) keeps the record with `allowed = false`; `Close`
+/// zeroes the record, hands it back to the system program and refunds its
+/// lamports to the recipient account.
+///
+/// Accounts:
+/// 0. config
+/// 1. allow list PDA
+/// 2. authority (signer)
+/// 3. rent recipient (writable, `Close` only)
+fn process_remove_from_allow_list(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let mode = RemoveMode::unpack(data)?;
     let account_info_iter = &mut accounts.iter();
 
     let config_account = next_account_info(account_info_iter)?;
     let allow_list_account = next_account_info(account_info_iter)?;
     let authority = next_account_info(account_info_iter)?;
 
-    // Verify authority
-    let config = Config::try_from_slice(&config_account.data.borrow())?;
-    if *authority.key != config.authority {
-        return Err(ProgramError::InvalidAccountData);
-    }
+    let mut record =
+        load_managed_record(program_id, config_account, allow_list_account, authority)?;
 
-    if !authority.is_signer {
-        return Err(ProgramError::MissingRequiredSignature);
+    if mode == RemoveMode::Close {
+        let recipient = next_account_info(account_info_iter)?;
+        return close_record(allow_list_account, recipient, &record);
     }
 
-    // Mark as not allowed (or close account)
-    let mut record = AllowListRecord::try_from_slice(&allow_list_account.data.borrow())?;
+    // Mark as not allowed
     record.allowed = false;
-
-    let serialized_record = record.try_to_vec()?;
-    allow_list_account
-        .data
-        .borrow_mut()
-        .copy_from_slice(&serialized_record);
+    store_record(allow_list_account, &record)?;
 
     msg!("User {} removed from allow list", record.user);
     Ok(())
 }
 
-/// Update program authority
-fn process_update_authority(
-    _program_id: &Pubkey,
-    accounts: &[AccountInfo],
-    _data: &[u8],
-) -> ProgramResult {
-    let account_info_iter = &mut accounts.iter();
+/// Load a record the config authority is managing
+///
+/// Checks that the config and record are this program's canonical accounts
+/// for the same mint and that `authority` is the config authority and signed.
+fn load_managed_record(
+    program_id: &Pubkey,
+    config_account: &AccountInfo,
+    allow_list_account: &AccountInfo,
+    authority: &AccountInfo,
+) -> Result<AllowListRecord, ProgramError> {
+    if allow_list_account.owner != program_id {
+        msg!("Allow list record must be owned by this program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
 
-    let config_account = next_account_info(account_info_iter)?;
-    let current_authority = next_account_info(account_info_iter)?;
-    let new_authority = next_account_info(account_info_iter)?;
+    let config = load_authorized_config(program_id, config_account, authority)?;
 
-    let mut config = Config::try_from_slice(&config_account.data.borrow())?;
+    let record = AllowListRecord::try_from_slice(&allow_list_account.try_borrow_data()?)?;
+    if record.mint != config.mint {
+        msg!("Allow list record belongs to a different mint");
+        return Err(ProgramError::InvalidAccountData);
+    }
 
-    if *current_authority.key != config.authority {
+    allow_list_address(&record.mint, &record.user, program_id).verify(allow_list_account.key)?;
+    Ok(record)
+}
+
+/// Load the canonical config, checking that `authority` is its authority and
+/// signed
+fn load_authorized_config(
+    program_id: &Pubkey,
+    config_account: &AccountInfo,
+    authority: &AccountInfo,
+) -> Result<Config, ProgramError> {
+    let config = load_config(program_id, config_account)?;
+
+    // Verify authority
+    if *authority.key != config.authority {
         return Err(ProgramError::InvalidAccountData);
     }
 
-    if !current_authority.is_signer {
+    if !authority.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    config.authority = *new_authority.key;
-    let serialized_config = config.try_to_vec()?;
-    config_account
-        .data
-        .borrow_mut()
-        .copy_from_slice(&serialized_config);
+    Ok(config)
+}
+
+/// Load the canonical config of whichever mint it belongs to
+fn load_config(program_id: &Pubkey, config_account: &AccountInfo) -> Result<Config, ProgramError> {
+    if config_account.owner != program_id {
+        msg!("Config must be owned by this program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
 
-    msg!("Authority updated to: {}", new_authority.key);
-    Ok(())
+    let config = Config::try_from_slice(&config_account.try_borrow_data()?)?;
+    config_address(&config.mint, program_id).verify(config_account.key)?;
+    Ok(config)
 }
 
-/// sRFC 37 Interface: Can thaw permissionless
-fn process_can_thaw_permissionless(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+/// Close a user's record and reclaim its rent
+///
+/// Works on a record in any state but revoked, so the rent of users who
+/// were only deactivated can be reclaimed later. The record is zeroed,
+/// handed back to the system program and its lamports refunded to the
+/// recipient; the gate then treats the user as having no record.
+///
+/// Accounts:
+/// 0. config
+/// 1. allow list PDA (writable)
+/// 2. authority (signer)
+/// 3. rent recipient (writable)
+fn process_close_record(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
 
-    // Accounts as per sRFC 37 interface:
-    // 0. caller
-    // 1. token account
-    // 2. mint
-    // 3. extra-account-metas
-    // Extra accounts:
-    // 4. token account owner
-    // 5. allow list PDA
-
-    let _caller = next_account_info(account_info_iter)?;
-    let _token_account = next_account_info(account_info_iter)?;
-    let mint = next_account_info(account_info_iter)?;
-    let _extra_account_metas = next_account_info(account_info_iter)?;
-    let token_account_owner = next_account_info(account_info_iter)?;
-    let allow_list_pda = next_account_info(account_info_iter)?;
+    let config_account = next_account_info(account_info_iter)?;
+    let allow_list_account = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+    let recipient = next_account_info(account_info_iter)?;
 
-    // Verify allow list PDA derivation
-    let (expected_pda, _bump) = Pubkey::find_program_address(
-        &[
-            ALLOW_LIST_SEED,
-            mint.key.as_ref(),
-            token_account_owner.key.as_ref(),
-        ],
-        program_id,
-    );
+    let record = load_managed_record(program_id, config_account, allow_list_account, authority)?;
+    close_record(allow_list_account, recipient, &record)
+}
 
-    if *allow_list_pda.key != expected_pda {
-        msg!("Invalid allow list PDA");
+/// Zero `record`'s account, reassign it to the system program and refund its
+/// lamports to `recipient`
+fn close_record(
+    allow_list_account: &AccountInfo,
+    recipient: &AccountInfo,
+    record: &AllowListRecord,
+) -> ProgramResult {
+    // Closing and re-adding would otherwise lift a revocation
+    if record.revoked {
+        msg!("User {} is revoked; lift the revocation first", record.user);
         return Err(ProgramError::InvalidAccountData);
     }
 
-    // Check if allow list record exists
-    if allow_list_pda.data_is_empty() {
-        msg!("User {} not in allow list", token_account_owner.key);
-        return Err(ProgramError::InvalidAccountData);
+    if recipient.key == allow_list_account.key {
+        msg!("Rent recipient cannot be the record being closed");
+        return Err(ProgramError::InvalidArgument);
     }
 
-    let record = AllowListRecord::try_from_slice(&allow_list_pda.data.borrow())?;
-
-    // Verify user is allowed
-    if !record.allowed {
-        msg!("User {} is not allowed", token_account_owner.key);
-        return Err(ProgramError::InvalidAccountData);
-    }
+    let refund = allow_list_account.lamports();
+    **recipient.try_borrow_mut_lamports()? = recipient
+        .lamports()
+        .checked_add(refund)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **allow_list_account.try_borrow_mut_lamports()? = 0;
 
-    // Check expiry
-    let current_timestamp = 0; // Use Clock sysvar in production
-    if record.is_expired(current_timestamp) {
-        msg!("User {}'s access has expired", token_account_owner.key);
-        return Err(ProgramError::InvalidAccountData);
-    }
+    allow_list_account.try_borrow_mut_data()?.fill(0);
+    allow_list_account.realloc(0, false)?;
+    allow_list_account.assign(&system_program::id());
 
     msg!(
-        "✅ User {} is in allow list (level: {:?}) - permissionless thaw authorized",
-        token_account_owner.key,
-        record.access_level
+        "Allow list record of user {} closed, {} lamports refunded to {}",
+        record.user,
+        refund,
+        recipient.key
     );
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Create `record`'s account at its canonical PDA, paid for by `payer`
+fn create_record<'a>(
+    program_id: &Pubkey,
+    allow_list_account: &AccountInfo<'a>,
+    payer: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    record: &AllowListRecord,
+) -> ProgramResult {
+    let record_data = record.try_to_vec()?;
+    let rent = Rent::get()?;
+    let required_lamports = rent.minimum_balance(record_data.len());
 
-    #[test]
-    fn test_discriminators() {
-        assert_eq!(
-            CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
-            [8, 175, 169, 129, 137, 74, 61, 241]
-        );
-        assert_eq!(
-            CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR,
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            allow_list_account.key,
+            required_lamports,
+            record_data.len() as u64,
+            program_id,
+        ),
+        &[
+            payer.clone(),
+            allow_list_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            ALLOW_LIST_SEED,
+            record.mint.as_ref(),
+            record.user.as_ref(),
+            &[record.bump],
+        ]],
+    )?;
+
+    store_account_data(allow_list_account, &record_data)
+}
+
+fn store_record(allow_list_account: &AccountInfo, record: &AllowListRecord) -> ProgramResult {
+    store_account_data(allow_list_account, &record.try_to_vec()?)
+}
+
+/// Permanently revoke a user
+///
+/// The record stays, marked revoked and not allowed, so permissionless thaws
+/// are denied whatever `allowed` and the expiry say. Only `LiftRevocation`
+/// clears it; the record cannot be closed while revoked.
+///
+/// Accounts:
+/// 0. config
+/// 1. allow list PDA (writable)
+/// 2. authority (signer)
+fn process_revoke(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let allow_list_account = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+
+    let mut record =
+        load_managed_record(program_id, config_account, allow_list_account, authority)?;
+    record.revoked = true;
+    record.allowed = false;
+    store_record(allow_list_account, &record)?;
+
+    msg!("User {} revoked", record.user);
+    Ok(())
+}
+
+/// Lift a user's revocation
+///
+/// The record goes back to deactivated (`allowed = false`); allowing the user
+/// again is a separate decision.
+///
+/// Accounts:
+/// 0. config
+/// 1. allow list PDA (writable)
+/// 2. authority (signer)
+fn process_lift_revocation(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let allow_list_account = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+
+    let mut record =
+        load_managed_record(program_id, config_account, allow_list_account, authority)?;
+    if !record.revoked {
+        msg!("User {} is not revoked", record.user);
+        return Err(ProgramError::InvalidArgument);
+    }
+    record.revoked = false;
+    store_record(allow_list_account, &record)?;
+
+    msg!("Revocation of user {} lifted", record.user);
+    Ok(())
+}
+
+/// Set how `can-thaw-permissionless` treats holders without a record
+///
+/// Data: the policy byte (0 = deny, 1 = allow). The policy PDA is created on
+/// first use; until then the gate denies them.
+///
+/// Accounts:
+/// 0. config
+/// 1. missing-record policy PDA (writable)
+/// 2. authority (signer)
+/// 3. payer (signer, writable)
+/// 4. system program
+fn process_set_missing_record_policy(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let policy_account = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+    let payer = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    let policy =
+        MissingRecordPolicy::unpack(data).map_err(|_| ProgramError::InvalidInstructionData)?;
+    let config = load_authorized_config(program_id, config_account, authority)?;
+    policy.store(
+        program_id,
+        policy_account,
+        &config.mint,
+        payer,
+        system_program,
+    )?;
+
+    msg!(
+        "Missing-record policy for mint {} set to {:?}",
+        config.mint,
+        policy
+    );
+    Ok(())
+}
+
+/// Publish the Merkle root of an allow list snapshot
+///
+/// Data: the 32-byte root. The root PDA is created on first use and
+/// overwritten after that, so publishing a new snapshot retires every proof
+/// against the old one; records already materialized stay. Publishing
+/// `[0; 32]` stops materialization.
+///
+/// Accounts:
+/// 0. config
+/// 1. snapshot root PDA (writable)
+/// 2. authority (signer)
+/// 3. payer (signer, writable)
+/// 4. system program
+fn process_set_snapshot_root(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let root_account = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+    let payer = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    check_system_program(system_program)?;
+
+    let root: [u8; 32] = data
+        .try_into()
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let config = load_authorized_config(program_id, config_account, authority)?;
+
+    let root_pda = snapshot_root_address(&config.mint, program_id);
+    root_pda.verify(root_account.key)?;
+
+    let snapshot_root = SnapshotRoot {
+        mint: config.mint,
+        root,
+        bump: root_pda.bump(),
+    };
+    let root_data = snapshot_root.try_to_vec()?;
+
+    if root_account.data_is_empty() {
+        let rent = Rent::get()?;
+        invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                root_account.key,
+                rent.minimum_balance(root_data.len()),
+                root_data.len() as u64,
+                program_id,
+            ),
+            &[payer.clone(), root_account.clone(), system_program.clone()],
+            &[&[SNAPSHOT_ROOT_SEED, config.mint.as_ref(), &[root_pda.bump()]]],
+        )?;
+    } else if root_account.owner != program_id {
+        msg!("Snapshot root must be owned by this program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    store_account_data(root_account, &root_data)?;
+
+    msg!("Snapshot root for mint {} published", config.mint);
+    Ok(())
+}
+
+/// Create the signer's allow list record from the published snapshot
+///
+/// Data: `MaterializeArgs`. The entry's leaf, with the signer as its user,
+/// must prove into the published root. The signer pays the record's rent.
+/// A record that already exists is never overwritten, whether an earlier
+/// materialization or `AddToAllowList` created it, so a deactivated or
+/// revoked holder cannot restore themselves from the snapshot. A record
+/// closed with `RemoveFromAllowList` can be materialized again until the
+/// authority publishes a snapshot without the holder.
+///
+/// Accounts:
+/// 0. snapshot root PDA
+/// 1. allow list PDA (writable)
+/// 2. mint
+/// 3. user (signer, writable)
+/// 4. system program
+fn process_materialize_from_snapshot(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let root_account = next_account_info(account_info_iter)?;
+    let allow_list_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let user = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    check_system_program(system_program)?;
+
+    let args =
+        MaterializeArgs::try_from_slice(data).map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    if !user.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if root_account.owner != program_id {
+        msg!("Snapshot root must be owned by this program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let snapshot_root = SnapshotRoot::try_from_slice(&root_account.try_borrow_data()?)?;
+    if snapshot_root.mint != *mint.key {
+        msg!("Snapshot root belongs to a different mint");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    snapshot_root_address(mint.key, program_id).verify(root_account.key)?;
+
+    let allow_list_pda = allow_list_address(mint.key, user.key, program_id);
+    if *allow_list_account.key != allow_list_pda.address() {
+        msg!("Invalid allow list PDA");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !allow_list_account.data_is_empty() {
+        msg!("User {} already has an allow list record", user.key);
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let entry = SnapshotEntry {
+        user: *user.key,
+        access_level: args.access_level,
+        expiry_timestamp: args.expiry_timestamp,
+    };
+    if !verify_snapshot_proof(&snapshot_root.root, entry.leaf(mint.key), &args.proof) {
+        msg!("User {} is not in the snapshot", user.key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let bump = allow_list_pda.bump();
+    let record = AllowListRecord {
+        mint: *mint.key,
+        user: *user.key,
+        allowed: true,
+        access_level: entry.access_level,
+        added_timestamp: Clock::get()?.unix_timestamp,
+        expiry_timestamp: entry.expiry_timestamp,
+        bump,
+        revoked: false,
+    };
+
+    let record_data = record.try_to_vec()?;
+    let rent = Rent::get()?;
+
+    invoke_signed(
+        &system_instruction::create_account(
+            user.key,
+            allow_list_account.key,
+            rent.minimum_balance(record_data.len()),
+            record_data.len() as u64,
+            program_id,
+        ),
+        &[
+            user.clone(),
+            allow_list_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            ALLOW_LIST_SEED,
+            mint.key.as_ref(),
+            user.key.as_ref(),
+            &[bump],
+        ]],
+    )?;
+
+    store_account_data(allow_list_account, &record_data)?;
+
+    msg!(
+        "User {} materialized from snapshot for mint {}",
+        user.key,
+        mint.key
+    );
+    Ok(())
+}
+
+/// Propose a new config authority
+///
+/// Nothing changes hands yet: the proposal is stored in the mint's
+/// pending-authority PDA and the current authority keeps control until the
+/// proposed key signs `AcceptAuthority`. A later proposal replaces an
+/// earlier one, and `CancelAuthorityTransfer` withdraws it.
+///
+/// Accounts:
+/// 0. config
+/// 1. current authority (signer)
+/// 2. proposed authority
+/// 3. pending-authority PDA (writable)
+/// 4. payer (signer, writable)
+/// 5. system program
+fn process_update_authority(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let current_authority = next_account_info(account_info_iter)?;
+    let new_authority = next_account_info(account_info_iter)?;
+    let pending_account = next_account_info(account_info_iter)?;
+    let payer = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if config_account.owner != program_id {
+        msg!("Config must be owned by this program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let config = Config::try_from_slice(&config_account.try_borrow_data()?)?;
+    config_address(&config.mint, program_id).verify(config_account.key)?;
+
+    if *current_authority.key != config.authority {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !current_authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    check_system_program(system_program)?;
+    let pending_pda = pending_authority_address(&config.mint, program_id);
+    pending_pda.verify(pending_account.key)?;
+
+    let pending = PendingAuthority {
+        mint: config.mint,
+        proposed: *new_authority.key,
+        bump: pending_pda.bump(),
+    };
+    let pending_data = pending.try_to_vec()?;
+
+    if pending_account.data_is_empty() {
+        let rent = Rent::get()?;
+        invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                pending_account.key,
+                rent.minimum_balance(pending_data.len()),
+                pending_data.len() as u64,
+                program_id,
+            ),
+            &[
+                payer.clone(),
+                pending_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                PENDING_AUTHORITY_SEED,
+                config.mint.as_ref(),
+                &[pending_pda.bump()],
+            ]],
+        )?;
+    } else if pending_account.owner != program_id {
+        msg!("Pending authority must be owned by this program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    store_account_data(pending_account, &pending_data)?;
+
+    msg!("Authority transfer to {} proposed", new_authority.key);
+    Ok(())
+}
+
+/// Load the mint's pending authority, checking it is this program's
+/// canonical account for the config's mint
+fn load_pending_authority(
+    program_id: &Pubkey,
+    config: &Config,
+    pending_account: &AccountInfo,
+) -> Result<PendingAuthority, ProgramError> {
+    if pending_account.owner != program_id || pending_account.data_is_empty() {
+        msg!("No authority transfer is pending");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    let pending = PendingAuthority::try_from_slice(&pending_account.try_borrow_data()?)?;
+    if pending.mint != config.mint {
+        msg!("Pending authority belongs to a different mint");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    pending_authority_address(&config.mint, program_id).verify(pending_account.key)?;
+    Ok(pending)
+}
+
+/// Zero the pending-authority account, reassign it to the system program
+/// and refund its lamports to `recipient`
+fn close_pending_authority(
+    pending_account: &AccountInfo,
+    recipient: &AccountInfo,
+) -> ProgramResult {
+    if recipient.key == pending_account.key {
+        msg!("Rent recipient cannot be the pending authority being closed");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let refund = pending_account.lamports();
+    **recipient.try_borrow_mut_lamports()? = recipient
+        .lamports()
+        .checked_add(refund)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **pending_account.try_borrow_mut_lamports()? = 0;
+
+    pending_account.try_borrow_mut_data()?.fill(0);
+    pending_account.realloc(0, false)?;
+    pending_account.assign(&system_program::id());
+    Ok(())
+}
+
+/// Take over the config as the proposed authority
+///
+/// The proposed key must sign, which proves it is held by someone. The
+/// pending-authority PDA is closed and its rent refunded to the recipient.
+///
+/// Accounts:
+/// 0. config (writable)
+/// 1. pending-authority PDA (writable)
+/// 2. proposed authority (signer)
+/// 3. rent recipient (writable)
+fn process_accept_authority(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let pending_account = next_account_info(account_info_iter)?;
+    let new_authority = next_account_info(account_info_iter)?;
+    let recipient = next_account_info(account_info_iter)?;
+
+    if config_account.owner != program_id {
+        msg!("Config must be owned by this program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut config = Config::try_from_slice(&config_account.try_borrow_data()?)?;
+    config_address(&config.mint, program_id).verify(config_account.key)?;
+
+    let pending = load_pending_authority(program_id, &config, pending_account)?;
+    if *new_authority.key != pending.proposed {
+        msg!("{} is not the proposed authority", new_authority.key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !new_authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    close_pending_authority(pending_account, recipient)?;
+
+    config.authority = pending.proposed;
+    store_account_data(config_account, &config.try_to_vec()?)?;
+
+    msg!("Authority updated to: {}", new_authority.key);
+    Ok(())
+}
+
+/// Withdraw a proposed authority transfer
+///
+/// Only the current authority may cancel. The pending-authority PDA is
+/// closed and its rent refunded to the recipient.
+///
+/// Accounts:
+/// 0. config
+/// 1. pending-authority PDA (writable)
+/// 2. authority (signer)
+/// 3. rent recipient (writable)
+fn process_cancel_authority_transfer(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let pending_account = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+    let recipient = next_account_info(account_info_iter)?;
+
+    let config = load_authorized_config(program_id, config_account, authority)?;
+    let pending = load_pending_authority(program_id, &config, pending_account)?;
+    close_pending_authority(pending_account, recipient)?;
+
+    msg!("Authority transfer to {} cancelled", pending.proposed);
+    Ok(())
+}
+
+/// Create the thaw extra-account-metas PDA of a mint
+///
+/// The list is [`thaw_extra_account_metas`], the same for every mint, so
+/// anyone may pay to create it.
+///
+/// Accounts:
+/// 0. payer (signer, writable)
+/// 1. thaw extra-account-metas (writable)
+/// 2. mint
+/// 3. system program
+fn process_initialize_thaw_extra_account_metas(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let payer = next_account_info(account_info_iter)?;
+    let extra_account_metas = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    extra_account_metas::create_list(
+        ListKind::Thaw,
+        program_id,
+        &GATE_INTERFACE_VERSION,
+        &thaw_extra_account_metas()?,
+        payer,
+        extra_account_metas,
+        mint,
+        system_program,
+    )?;
+
+    msg!(
+        "Thaw extra-account-metas initialized for mint: {}",
+        mint.key
+    );
+    Ok(())
+}
+
+/// Queue the signer for review
+///
+/// Only while the mint's missing-record policy is `Review`, and only for a
+/// holder without a record. The holder pays the request's rent and gets it
+/// back when the authority decides.
+///
+/// Accounts:
+/// 0. config
+/// 1. missing-record policy PDA
+/// 2. allow list PDA
+/// 3. review request PDA (writable)
+/// 4. user (signer, writable)
+/// 5. system program
+fn process_request_review(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let policy_account = next_account_info(account_info_iter)?;
+    let allow_list_account = next_account_info(account_info_iter)?;
+    let request_account = next_account_info(account_info_iter)?;
+    let user = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    check_system_program(system_program)?;
+
+    if !user.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let config = load_config(program_id, config_account)?;
+    let policy = MissingRecordPolicy::load(
+        program_id,
+        policy_account,
+        &config.mint,
+        DEFAULT_MISSING_RECORD_POLICY,
+    )?;
+    if policy != MissingRecordPolicy::Review {
+        msg!("Mint {} does not review holders", config.mint);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    allow_list_address(&config.mint, user.key, program_id).verify(allow_list_account.key)?;
+    if !allow_list_account.data_is_empty() {
+        msg!("User {} already has an allow list record", user.key);
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let request_pda = review_request_address(&config.mint, user.key, program_id);
+    request_pda.verify(request_account.key)?;
+    if !request_account.data_is_empty() {
+        msg!("User {} is already waiting for review", user.key);
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let request = ReviewRequest {
+        mint: config.mint,
+        user: *user.key,
+        requested_timestamp: Clock::get()?.unix_timestamp,
+        bump: request_pda.bump(),
+    };
+    let request_data = request.try_to_vec()?;
+    invoke_signed(
+        &system_instruction::create_account(
+            user.key,
+            request_account.key,
+            Rent::get()?.minimum_balance(request_data.len()),
+            request_data.len() as u64,
+            program_id,
+        ),
+        &[
+            user.clone(),
+            request_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            REVIEW_REQUEST_SEED,
+            config.mint.as_ref(),
+            user.key.as_ref(),
+            &[request_pda.bump()],
+        ]],
+    )?;
+    store_account_data(request_account, &request_data)?;
+
+    msg!(
+        "Review of user {} requested at {}",
+        user.key,
+        request.requested_timestamp
+    );
+    Ok(())
+}
+
+/// Decide on a queued review request
+///
+/// `ApproveReview` (data: optional `AddToAllowListArgs`) adds the holder
+/// as `AddToAllowList` would; `RejectReview` creates their record
+/// deactivated, so the gate denies them as not allowed and they cannot ask
+/// again until the authority closes it. A holder the authority added some
+/// other way in the meantime keeps the record they have. Either way the
+/// request is closed and its rent refunded to the holder.
+///
+/// Accounts:
+/// 0. config
+/// 1. review request PDA (writable)
+/// 2. allow list PDA (writable)
+/// 3. authority (signer)
+/// 4. payer (signer, writable)
+/// 5. user (writable)
+/// 6. system program
+fn process_review_decision(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    approve: Option<&[u8]>,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let request_account = next_account_info(account_info_iter)?;
+    let allow_list_account = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+    let payer = next_account_info(account_info_iter)?;
+    let user = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    check_system_program(system_program)?;
+
+    let args = approve.map(AddToAllowListArgs::unpack).transpose()?;
+    let config = load_authorized_config(program_id, config_account, authority)?;
+
+    if request_account.owner != program_id || request_account.data_is_empty() {
+        msg!("No review request is queued");
+        return Err(ProgramError::UninitializedAccount);
+    }
+    let request = ReviewRequest::try_from_slice(&request_account.try_borrow_data()?)?;
+    if request.mint != config.mint || request.user != *user.key {
+        msg!("Review request belongs to a different mint or user");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    review_request_address(&request.mint, &request.user, program_id).verify(request_account.key)?;
+
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    if let Some(args) = &args {
+        args.validate(current_timestamp)?;
+    }
+
+    let allow_list_pda = allow_list_address(&config.mint, user.key, program_id);
+    allow_list_pda.verify(allow_list_account.key)?;
+    if allow_list_account.data_is_empty() {
+        let record = AllowListRecord {
+            mint: config.mint,
+            user: *user.key,
+            allowed: args.is_some(),
+            access_level: args.map_or(AccessLevel::None, |args| args.access_level),
+            added_timestamp: current_timestamp,
+            expiry_timestamp: args.and_then(|args| args.expiry_timestamp),
+            bump: allow_list_pda.bump(),
+            revoked: false,
+        };
+        create_record(
+            program_id,
+            allow_list_account,
+            payer,
+            system_program,
+            &record,
+        )?;
+    } else {
+        msg!(
+            "User {} already has an allow list record, left as is",
+            user.key
+        );
+    }
+
+    close_review_request(request_account, user)?;
+
+    msg!(
+        "Review of user {} {} after {}s",
+        user.key,
+        if args.is_some() {
+            "approved"
+        } else {
+            "rejected"
+        },
+        current_timestamp.saturating_sub(request.requested_timestamp)
+    );
+    Ok(())
+}
+
+/// Zero the review request, reassign it to the system program and refund
+/// its lamports to the holder who paid for it
+fn close_review_request(request_account: &AccountInfo, user: &AccountInfo) -> ProgramResult {
+    let refund = request_account.lamports();
+    **user.try_borrow_mut_lamports()? = user
+        .lamports()
+        .checked_add(refund)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **request_account.try_borrow_mut_lamports()? = 0;
+
+    request_account.try_borrow_mut_data()?.fill(0);
+    request_account.realloc(0, false)?;
+    request_account.assign(&system_program::id());
+    Ok(())
+}
+
+/// sRFC 37 Interface: Can thaw permissionless
+fn process_can_thaw_permissionless(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    // Accounts as per sRFC 37 interface:
+    // 0. caller
+    // 1. token account
+    // 2. mint
+    // 3. extra-account-metas
+    // Extra accounts:
+    // 4. token account owner
+    // 5. allow list PDA
+    // 6. missing-record policy PDA
+
+    let _caller = next_account_info(account_info_iter)?;
+    let token_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let _extra_account_metas = next_account_info(account_info_iter)?;
+    let token_account_owner = next_account_info(account_info_iter)?;
+    let allow_list_pda = next_account_info(account_info_iter)?;
+    let policy_account = next_account_info(account_info_iter)?;
+
+    // The record is looked up by the supplied owner, so it must be the
+    // account's current owner; otherwise a stale record for a previous owner
+    // could vouch for an account recreated at the same address. An account
+    // that does not exist cannot be thawed, so there is nothing to bind.
+    if !token_account.data_is_empty() {
+        let data = token_account.try_borrow_data()?;
+        let bound = data.len() >= TOKEN_ACCOUNT_OWNER_OFFSET + 32
+            && data[..32] == mint.key.to_bytes()
+            && data[TOKEN_ACCOUNT_OWNER_OFFSET..TOKEN_ACCOUNT_OWNER_OFFSET + 32]
+                == token_account_owner.key.to_bytes();
+        if !bound {
+            msg!(
+                "Token account {} is not owned by {} for this mint",
+                token_account.key,
+                token_account_owner.key
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+
+    // Verify allow list PDA derivation
+    let expected_pda = allow_list_address(mint.key, token_account_owner.key, program_id);
+
+    if *allow_list_pda.key != expected_pda.address() {
+        msg!("Invalid allow list PDA");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Check if allow list record exists
+    if allow_list_pda.data_is_empty() {
+        let policy = MissingRecordPolicy::load(
+            program_id,
+            policy_account,
+            mint.key,
+            DEFAULT_MISSING_RECORD_POLICY,
+        )?;
+        if policy == MissingRecordPolicy::Review {
+            emit_decision(
+                mint.key,
+                token_account_owner.key,
+                DecisionReason::PendingReview,
+            );
+            msg!("User {} is pending review", token_account_owner.key);
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if !policy.permits_thaw() {
+            emit_decision(mint.key, token_account_owner.key, DecisionReason::NotListed);
+            msg!("User {} not in allow list", token_account_owner.key);
+            return Err(ProgramError::InvalidAccountData);
+        }
+        emit_decision(
+            mint.key,
+            token_account_owner.key,
+            DecisionReason::MissingRecordAllowed,
+        );
+        msg!(
+            "✅ User {} not in allow list, missing records allowed - permissionless thaw authorized",
+            token_account_owner.key
+        );
+        return Ok(());
+    }
+
+    let record = AllowListRecord::try_from_slice(&allow_list_pda.try_borrow_data()?)?;
+
+    // A revocation overrides `allowed` and the expiry
+    if record.revoked {
+        emit_decision(mint.key, token_account_owner.key, DecisionReason::Revoked);
+        msg!("User {} is revoked", token_account_owner.key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Verify user is allowed
+    if !record.allowed {
+        emit_decision(
+            mint.key,
+            token_account_owner.key,
+            DecisionReason::NotAllowed,
+        );
+        msg!("User {} is not allowed", token_account_owner.key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Check expiry; only records that carry one need the clock
+    if record.expiry_timestamp.is_some() && record.is_expired(Clock::get()?.unix_timestamp) {
+        emit_decision(mint.key, token_account_owner.key, DecisionReason::Expired);
+        msg!("User {}'s access has expired", token_account_owner.key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    emit_decision(mint.key, token_account_owner.key, DecisionReason::Listed);
+    msg!(
+        "✅ User {} is in allow list (level: {:?}) - permissionless thaw authorized",
+        token_account_owner.key,
+        record.access_level
+    );
+    Ok(())
+}
+
+/// Log the decision about `owner` as a [`ThawDecision`] in [`EVENT_FORMAT`]
+fn emit_decision(mint: &Pubkey, owner: &Pubkey, reason: DecisionReason) {
+    ThawDecision::new(*mint, *owner, reason).emit(EVENT_FORMAT);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discriminators() {
+        assert_eq!(
+            CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+            [8, 175, 169, 129, 137, 74, 61, 241]
+        );
+        assert_eq!(
+            CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR,
             [214, 141, 109, 75, 248, 1, 45, 29]
         );
     }
 
+    #[test]
+    fn test_admin_discriminators_leave_interface_reachable() {
+        for admin in [
+            INITIALIZE,
+            ADD_TO_ALLOW_LIST,
+            REMOVE_FROM_ALLOW_LIST,
+            UPDATE_AUTHORITY,
+            REVOKE,
+            LIFT_REVOCATION,
+            SET_MISSING_RECORD_POLICY,
+            SET_SNAPSHOT_ROOT,
+            MATERIALIZE_FROM_SNAPSHOT,
+            CLOSE_RECORD,
+            ACCEPT_AUTHORITY,
+            CANCEL_AUTHORITY_TRANSFER,
+            INITIALIZE_THAW_EXTRA_ACCOUNT_METAS,
+            REQUEST_REVIEW,
+            APPROVE_REVIEW,
+            REJECT_REVIEW,
+        ] {
+            assert_ne!(admin, CAN_THAW_PERMISSIONLESS_DISCRIMINATOR[0]);
+            assert_ne!(admin, CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR[0]);
+        }
+    }
+
     #[test]
     fn test_access_level() {
         let level = AccessLevel::Enhanced;
@@ -437,9 +1867,145 @@ mod tests {
             added_timestamp: 1000,
             expiry_timestamp: Some(2000),
             bump: 255,
+            revoked: false,
         };
 
         assert!(!record.is_expired(1500)); // Not expired
         assert!(record.is_expired(2500)); // Expired
     }
+
+    #[test]
+    fn test_revoked_record_never_permits_thaw() {
+        let mut record = AllowListRecord {
+            mint: Pubkey::new_unique(),
+            user: Pubkey::new_unique(),
+            allowed: true,
+            access_level: AccessLevel::Basic,
+            added_timestamp: 1000,
+            expiry_timestamp: None,
+            bump: 255,
+            revoked: false,
+        };
+        assert!(record.permits_thaw(1500));
+
+        record.revoked = true;
+        assert!(!record.permits_thaw(1500));
+
+        record.expiry_timestamp = Some(i64::MAX);
+        assert!(!record.permits_thaw(1500));
+    }
+
+    #[test]
+    fn test_snapshot_proof() {
+        let mint = Pubkey::new_unique();
+        let entries: Vec<SnapshotEntry> = (0..3)
+            .map(|i| SnapshotEntry {
+                user: Pubkey::new_unique(),
+                access_level: AccessLevel::Basic,
+                expiry_timestamp: (i == 1).then_some(2000),
+            })
+            .collect();
+        let leaves: Vec<[u8; 32]> = entries.iter().map(|entry| entry.leaf(&mint)).collect();
+        let pair = snapshot_node(&leaves[0], &leaves[1]);
+        let root = snapshot_node(&pair, &leaves[2]);
+
+        assert!(verify_snapshot_proof(
+            &root,
+            leaves[0],
+            &[leaves[1], leaves[2]]
+        ));
+        assert!(verify_snapshot_proof(
+            &root,
+            leaves[1],
+            &[leaves[0], leaves[2]]
+        ));
+        assert!(verify_snapshot_proof(&root, leaves[2], &[pair]));
+
+        // The leaf commits to the mint and every field of the entry
+        assert!(!verify_snapshot_proof(
+            &root,
+            entries[0].leaf(&Pubkey::new_unique()),
+            &[leaves[1], leaves[2]]
+        ));
+        let mut upgraded = entries[0];
+        upgraded.access_level = AccessLevel::Institutional;
+        assert!(!verify_snapshot_proof(
+            &root,
+            upgraded.leaf(&mint),
+            &[leaves[1], leaves[2]]
+        ));
+        let mut unexpiring = entries[1];
+        unexpiring.expiry_timestamp = None;
+        assert!(!verify_snapshot_proof(
+            &root,
+            unexpiring.leaf(&mint),
+            &[leaves[0], leaves[2]]
+        ));
+
+        // An inner node is not a leaf
+        assert!(!verify_snapshot_proof(&root, pair, &[leaves[2], leaves[2]]));
+    }
+
+    #[test]
+    fn test_init_mode_unpack() {
+        assert_eq!(InitMode::unpack(&[]).unwrap(), InitMode::Create);
+        assert_eq!(InitMode::unpack(&[0]).unwrap(), InitMode::Create);
+        assert_eq!(InitMode::unpack(&[1]).unwrap(), InitMode::IfNeeded);
+        assert_eq!(
+            InitMode::unpack(&[2]),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
+
+    #[test]
+    fn test_add_to_allow_list_args_unpack() {
+        assert_eq!(
+            AddToAllowListArgs::unpack(&[]).unwrap(),
+            AddToAllowListArgs::default()
+        );
+        let args = AddToAllowListArgs {
+            access_level: AccessLevel::Institutional,
+            expiry_timestamp: Some(2000),
+        };
+        assert_eq!(
+            AddToAllowListArgs::unpack(&args.try_to_vec().unwrap()).unwrap(),
+            args
+        );
+        // An access level out of range, a truncated expiry, trailing bytes
+        for data in [&[4, 0][..], &[1, 1, 0xD0, 0x07], &[1, 0, 0]] {
+            assert_eq!(
+                AddToAllowListArgs::unpack(data),
+                Err(ProgramError::InvalidInstructionData)
+            );
+        }
+    }
+
+    #[test]
+    fn test_add_to_allow_list_args_validate() {
+        let args = |access_level, expiry_timestamp| AddToAllowListArgs {
+            access_level,
+            expiry_timestamp,
+        };
+        assert!(args(AccessLevel::Basic, None).validate(1500).is_ok());
+        assert!(args(AccessLevel::Basic, Some(1501)).validate(1500).is_ok());
+        assert_eq!(
+            args(AccessLevel::Basic, Some(1500)).validate(1500),
+            Err(ProgramError::InvalidArgument)
+        );
+        assert_eq!(
+            args(AccessLevel::None, None).validate(1500),
+            Err(ProgramError::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn test_remove_mode_unpack() {
+        assert_eq!(RemoveMode::unpack(&[]).unwrap(), RemoveMode::Deactivate);
+        assert_eq!(RemoveMode::unpack(&[0]).unwrap(), RemoveMode::Deactivate);
+        assert_eq!(RemoveMode::unpack(&[1]).unwrap(), RemoveMode::Close);
+        assert_eq!(
+            RemoveMode::unpack(&[2]),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
 }
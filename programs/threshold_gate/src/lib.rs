@@ -0,0 +1,572 @@
+// `entrypoint!` expands to cfgs only known to the BPF toolchain.
+#![allow(unexpected_cfgs)]
+
+use borsh::{BorshDeserialize, BorshSerialize};
+/**
+ * Threshold Gate Program
+ *
+ * Dual-control compliance approval following the sRFC 37 interface
+ * specification. The per-mint config names N approvers and a threshold M;
+ * each approver posts an approval PDA for a (mint, user) pair once they have
+ * signed off on the user off-chain, and a permissionless thaw needs M of
+ * those approvals to be standing. A single compromised approver key can
+ * therefore never thaw an account on its own.
+ *
+ * Instructions:
+ * - INITIALIZE: create the config of a mint with its approvers and threshold
+ * - POST_APPROVAL: an approver approves a user
+ * - REVOKE_APPROVAL: an approver withdraws their approval, closing its PDA
+ * - can-thaw-permissionless: succeeds once the threshold of approvals stands
+ * - can-freeze-permissionless: not supported
+ */
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction, system_program,
+    sysvar::Sysvar,
+};
+use token_acl_interface::{
+    check_system_program, store_account_data, CanonicalPda,
+    CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR, CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+};
+
+// Instruction discriminators
+pub const INITIALIZE: u8 = 0;
+pub const POST_APPROVAL: u8 = 1;
+pub const REVOKE_APPROVAL: u8 = 2;
+
+// Seeds
+pub const CONFIG_SEED: &[u8] = b"threshold-config";
+pub const APPROVAL_SEED: &[u8] = b"approval";
+
+/// Approvers a config can name; every one is an extra account of a thaw
+pub const MAX_APPROVERS: usize = 8;
+
+// SPL Token account layout: mint (0..32), owner (32..64), ...
+const TOKEN_ACCOUNT_OWNER_OFFSET: usize = 32;
+
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
+
+/// Per-mint configuration: who approves, and how many approvals a thaw needs
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct ThresholdConfig {
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    /// Approvals a thaw needs, at least 1 and at most `approvers.len()`
+    pub threshold: u8,
+    /// In the order their approval PDAs are passed to a thaw
+    pub approvers: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl ThresholdConfig {
+    /// Check the threshold can be met and cannot be met by one approver
+    /// counted twice
+    pub fn validate(&self) -> Result<(), ProgramError> {
+        if self.approvers.is_empty() || self.approvers.len() > MAX_APPROVERS {
+            msg!(
+                "A config names 1 to {} approvers, not {}",
+                MAX_APPROVERS,
+                self.approvers.len()
+            );
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        if self.threshold == 0 || self.threshold as usize > self.approvers.len() {
+            msg!(
+                "Threshold {} is not between 1 and {}",
+                self.threshold,
+                self.approvers.len()
+            );
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        for (i, approver) in self.approvers.iter().enumerate() {
+            if self.approvers[..i].contains(approver) {
+                msg!("Approver {} is listed twice", approver);
+                return Err(ProgramError::InvalidInstructionData);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_approver(&self, key: &Pubkey) -> bool {
+        self.approvers.contains(key)
+    }
+}
+
+/// One approver's standing approval of a user for a mint
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Approval {
+    pub mint: Pubkey,
+    pub user: Pubkey,
+    pub approver: Pubkey,
+    pub bump: u8,
+}
+
+impl Approval {
+    pub const LEN: usize = 32 + 32 + 32 + 1;
+}
+
+/// The threshold rule: a thaw needs at least `threshold` standing approvals
+pub fn meets_threshold(approvals: usize, threshold: u8) -> bool {
+    threshold > 0 && approvals >= threshold as usize
+}
+
+/// Config PDA of `mint`
+pub fn config_address(mint: &Pubkey, program_id: &Pubkey) -> CanonicalPda {
+    CanonicalPda::find(&[CONFIG_SEED, mint.as_ref()], program_id)
+}
+
+/// Approval PDA of `approver` for `user` on `mint`
+pub fn approval_address(
+    mint: &Pubkey,
+    user: &Pubkey,
+    approver: &Pubkey,
+    program_id: &Pubkey,
+) -> CanonicalPda {
+    CanonicalPda::find(
+        &[
+            APPROVAL_SEED,
+            mint.as_ref(),
+            user.as_ref(),
+            approver.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    match instruction_data {
+        [INITIALIZE, threshold, approvers @ ..] => {
+            process_initialize(program_id, accounts, *threshold, approvers)
+        }
+        [POST_APPROVAL] => process_post_approval(program_id, accounts),
+        [REVOKE_APPROVAL] => process_revoke_approval(program_id, accounts),
+        d if d == CAN_THAW_PERMISSIONLESS_DISCRIMINATOR => {
+            process_can_thaw_permissionless(program_id, accounts)
+        }
+        d if d == CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR => {
+            // Approvals only ever release accounts
+            msg!("Permissionless freeze not supported by the threshold gate");
+            Err(ProgramError::InvalidInstructionData)
+        }
+        _ => {
+            msg!("Unknown instruction");
+            Err(ProgramError::InvalidInstructionData)
+        }
+    }
+}
+
+/// Initialize the config of a mint
+///
+/// Data: the threshold, then the approvers' keys.
+///
+/// Accounts:
+/// 0. config PDA (writable)
+/// 1. mint
+/// 2. authority (signer)
+/// 3. payer (writable, signer)
+/// 4. system program
+fn process_initialize(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    threshold: u8,
+    approvers: &[u8],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+    let payer = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    check_system_program(system_program)?;
+
+    let keys = approvers.chunks_exact(32);
+    if !keys.remainder().is_empty() {
+        msg!("Approver keys must be 32 bytes each");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let approvers = keys
+        .map(|key| Pubkey::try_from(key).map_err(|_| ProgramError::InvalidInstructionData))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let config_pda = config_address(mint.key, program_id);
+    if *config_account.key != config_pda.address() {
+        msg!("Invalid config PDA");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let config = ThresholdConfig {
+        authority: *authority.key,
+        mint: *mint.key,
+        threshold,
+        approvers,
+        bump: config_pda.bump(),
+    };
+    config.validate()?;
+
+    let config_data = config.try_to_vec()?;
+    let rent = Rent::get()?;
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            config_account.key,
+            rent.minimum_balance(config_data.len()),
+            config_data.len() as u64,
+            program_id,
+        ),
+        &[
+            payer.clone(),
+            config_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[CONFIG_SEED, mint.key.as_ref(), &[config.bump]]],
+    )?;
+
+    store_account_data(config_account, &config_data)?;
+
+    msg!(
+        "Threshold gate initialized for mint {}: {} of {} approvals",
+        mint.key,
+        config.threshold,
+        config.approvers.len()
+    );
+    Ok(())
+}
+
+/// The config of `mint`, checked to be the canonical PDA owned by this
+/// program
+fn load_config(
+    program_id: &Pubkey,
+    config_account: &AccountInfo,
+    mint: &Pubkey,
+) -> Result<ThresholdConfig, ProgramError> {
+    if *config_account.key != config_address(mint, program_id).address()
+        || config_account.owner != program_id
+        || config_account.data_is_empty()
+    {
+        msg!("Invalid threshold gate config");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(ThresholdConfig::try_from_slice(
+        &config_account.try_borrow_data()?,
+    )?)
+}
+
+/// The config of `mint`, with `approver` a signing approver listed in it
+fn load_config_for_approver(
+    program_id: &Pubkey,
+    config_account: &AccountInfo,
+    mint: &Pubkey,
+    approver: &AccountInfo,
+) -> Result<ThresholdConfig, ProgramError> {
+    let config = load_config(program_id, config_account, mint)?;
+    if !approver.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !config.is_approver(approver.key) {
+        msg!("{} is not an approver for mint {}", approver.key, mint);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(config)
+}
+
+/// Approve a user, as one of the mint's approvers
+///
+/// Accounts:
+/// 0. config
+/// 1. approval PDA (writable)
+/// 2. mint
+/// 3. user
+/// 4. approver (signer)
+/// 5. payer (writable, signer)
+/// 6. system program
+fn process_post_approval(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let approval_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let user = next_account_info(account_info_iter)?;
+    let approver = next_account_info(account_info_iter)?;
+    let payer = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    check_system_program(system_program)?;
+
+    load_config_for_approver(program_id, config_account, mint.key, approver)?;
+
+    let approval_pda = approval_address(mint.key, user.key, approver.key, program_id);
+    if *approval_account.key != approval_pda.address() {
+        msg!("Invalid approval PDA");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !approval_account.data_is_empty() {
+        msg!("{} has already approved {}", approver.key, user.key);
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let approval = Approval {
+        mint: *mint.key,
+        user: *user.key,
+        approver: *approver.key,
+        bump: approval_pda.bump(),
+    };
+    let approval_data = approval.try_to_vec()?;
+    let rent = Rent::get()?;
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            approval_account.key,
+            rent.minimum_balance(approval_data.len()),
+            approval_data.len() as u64,
+            program_id,
+        ),
+        &[
+            payer.clone(),
+            approval_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            APPROVAL_SEED,
+            mint.key.as_ref(),
+            user.key.as_ref(),
+            approver.key.as_ref(),
+            &[approval.bump],
+        ]],
+    )?;
+
+    store_account_data(approval_account, &approval_data)?;
+
+    msg!(
+        "Approver {} approved user {} for mint {}",
+        approver.key,
+        user.key,
+        mint.key
+    );
+    Ok(())
+}
+
+/// Withdraw an approval: the approval PDA is zeroed, handed back to the
+/// system program and its lamports refunded to the recipient
+///
+/// Accounts:
+/// 0. config
+/// 1. approval PDA (writable)
+/// 2. mint
+/// 3. user
+/// 4. approver (signer)
+/// 5. rent recipient (writable)
+fn process_revoke_approval(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let approval_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let user = next_account_info(account_info_iter)?;
+    let approver = next_account_info(account_info_iter)?;
+    let recipient = next_account_info(account_info_iter)?;
+
+    load_config_for_approver(program_id, config_account, mint.key, approver)?;
+
+    let approval_pda = approval_address(mint.key, user.key, approver.key, program_id);
+    if *approval_account.key != approval_pda.address() {
+        msg!("Invalid approval PDA");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if approval_account.data_is_empty() || approval_account.owner != program_id {
+        msg!("{} has no approval of {} to revoke", approver.key, user.key);
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if recipient.key == approval_account.key {
+        msg!("Rent recipient cannot be the approval being closed");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let refund = approval_account.lamports();
+    **recipient.try_borrow_mut_lamports()? = recipient
+        .lamports()
+        .checked_add(refund)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **approval_account.try_borrow_mut_lamports()? = 0;
+
+    approval_account.try_borrow_mut_data()?.fill(0);
+    approval_account.realloc(0, false)?;
+    approval_account.assign(&system_program::id());
+
+    msg!(
+        "Approver {} revoked their approval of user {} for mint {}",
+        approver.key,
+        user.key,
+        mint.key
+    );
+    Ok(())
+}
+
+/// Whether `approval_pda` is `approver`'s standing approval of `user`
+///
+/// A missing approval is not standing; an account at another address is
+/// rejected so one approval cannot be passed in place of another's.
+fn is_approved(
+    program_id: &Pubkey,
+    approval_pda: &AccountInfo,
+    mint: &Pubkey,
+    user: &Pubkey,
+    approver: &Pubkey,
+) -> Result<bool, ProgramError> {
+    let expected = approval_address(mint, user, approver, program_id);
+    if *approval_pda.key != expected.address() {
+        msg!("Invalid approval PDA for approver {}", approver);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if approval_pda.data_is_empty() {
+        return Ok(false);
+    }
+    if approval_pda.owner != program_id {
+        msg!("Approval not owned by the threshold gate");
+        return Err(ProgramError::IllegalOwner);
+    }
+    let approval = Approval::try_from_slice(&approval_pda.try_borrow_data()?)?;
+    Ok(approval.mint == *mint && approval.user == *user && approval.approver == *approver)
+}
+
+/// sRFC 37 Interface: Can thaw permissionless
+fn process_can_thaw_permissionless(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    // Accounts as per sRFC 37 interface:
+    // 0. caller
+    // 1. token account
+    // 2. mint
+    // 3. extra-account-metas
+    // Extra accounts:
+    // 4. token account owner
+    // 5. threshold config PDA
+    // 6.. one approval PDA per approver, in the config's order
+
+    let _caller = next_account_info(account_info_iter)?;
+    let token_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let _extra_account_metas = next_account_info(account_info_iter)?;
+    let token_account_owner = next_account_info(account_info_iter)?;
+    let config_account = next_account_info(account_info_iter)?;
+
+    // Approvals are looked up by the supplied owner, so it must be the
+    // account's current owner
+    if !token_account.data_is_empty() {
+        let data = token_account.try_borrow_data()?;
+        let bound = data.len() >= TOKEN_ACCOUNT_OWNER_OFFSET + 32
+            && data[..32] == mint.key.to_bytes()
+            && data[TOKEN_ACCOUNT_OWNER_OFFSET..TOKEN_ACCOUNT_OWNER_OFFSET + 32]
+                == token_account_owner.key.to_bytes();
+        if !bound {
+            msg!(
+                "Token account {} is not owned by {} for this mint",
+                token_account.key,
+                token_account_owner.key
+            );
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+
+    let config = load_config(program_id, config_account, mint.key)?;
+    let user = token_account_owner.key;
+
+    let mut approvals = 0;
+    for approver in &config.approvers {
+        let approval_pda = next_account_info(account_info_iter)?;
+        if is_approved(program_id, approval_pda, mint.key, user, approver)? {
+            approvals += 1;
+        }
+    }
+
+    if !meets_threshold(approvals, config.threshold) {
+        msg!(
+            "❌ User {} has {} of {} required approvals - permissionless thaw denied",
+            user,
+            approvals,
+            config.threshold
+        );
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    msg!(
+        "✅ User {} has {} of {} required approvals - permissionless thaw authorized",
+        user,
+        approvals,
+        config.threshold
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(threshold: u8, approvers: Vec<Pubkey>) -> ThresholdConfig {
+        ThresholdConfig {
+            authority: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            threshold,
+            approvers,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn test_threshold_rule() {
+        assert!(meets_threshold(2, 2));
+        assert!(meets_threshold(3, 2));
+        assert!(!meets_threshold(1, 2));
+        assert!(!meets_threshold(0, 0));
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let approvers: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        assert_eq!(config(2, approvers.clone()).validate(), Ok(()));
+        assert_eq!(config(3, approvers.clone()).validate(), Ok(()));
+
+        let invalid = [
+            config(0, approvers.clone()),
+            config(4, approvers.clone()),
+            config(1, Vec::new()),
+            config(2, vec![approvers[0], approvers[0]]),
+            config(1, vec![Pubkey::new_unique(); MAX_APPROVERS + 1]),
+        ];
+        for config in invalid {
+            assert_eq!(
+                config.validate(),
+                Err(ProgramError::InvalidInstructionData),
+                "{:?}",
+                config
+            );
+        }
+    }
+
+    #[test]
+    fn test_approval_roundtrip() {
+        let approval = Approval {
+            mint: Pubkey::new_unique(),
+            user: Pubkey::new_unique(),
+            approver: Pubkey::new_unique(),
+            bump: 254,
+        };
+        let data = approval.try_to_vec().unwrap();
+        assert_eq!(data.len(), Approval::LEN);
+        assert_eq!(Approval::try_from_slice(&data).unwrap(), approval);
+    }
+}
@@ -0,0 +1,911 @@
+//! Decoding Token ACL transactions
+//!
+//! Explorers show a gate's `can-thaw-permissionless` as opaque bytes sent to
+//! an unknown program. [`Decoder`] names what a transaction did instead:
+//! each instruction's program and instruction, the role of every account it
+//! passed, and for permissionless checks the gate's decision with the reason
+//! it logged, and the [`ThawDecision`] event where the gate logged one. The
+//! result serializes to JSON for explorers and dashboards.
+//!
+//! The sRFC 37 checks are recognized by discriminator whatever program they
+//! are sent to, with their four interface accounts named. The FAMP program
+//! is not part of this repository; the Token-2022 thaws and freezes the
+//! issuer signs in its place are decoded with the rest of Token-2022, the
+//! system program, compute budget and associated token account
+//! instructions. The admin instructions and extra accounts of this
+//! repository's gates are decoded once their program ids are registered
+//! with [`Decoder::register`], since each deployment has its own.
+//!
+//! Transactions come from a [`TransactionTrace`]
+//! ([`Decoder::decode_trace`], [`Decoder::decode_transaction`]) or from RPC
+//! ([`Decoder::decode_confirmed`]), including v0 transactions whose
+//! accounts were loaded from lookup tables.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize, Serializer};
+use solana_sdk::{
+    borsh1::try_from_slice_unchecked,
+    compute_budget::{self, ComputeBudgetInstruction},
+    message::{v0::LoadedMessage, VersionedMessage},
+    program_utils::limited_deserialize,
+    pubkey::Pubkey,
+    system_instruction::SystemInstruction,
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, EncodedConfirmedTransactionWithStatusMeta,
+};
+use spl_token_2022::instruction::TokenInstruction;
+use std::collections::BTreeMap;
+use token_acl_interface::{
+    events::{EventFormat, ThawDecision},
+    CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR, CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+};
+
+use crate::trace::{self, AccountUse, Event, Invocation, Outcome, TransactionTrace};
+
+/// Accounts every sRFC 37 check starts with
+const INTERFACE_ROLES: [&str; 4] = ["caller", "token_account", "mint", "extra_account_metas"];
+
+/// A program of this repository whose instructions the decoder knows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgramKind {
+    /// `examples/allow_list`
+    AllowList,
+    /// `examples/block_list`
+    BlockList,
+    /// `programs/production_allow_list`
+    ProductionAllowList,
+    /// `programs/hybrid_gate`
+    HybridGate,
+    /// `programs/jurisdiction_registry`
+    JurisdictionRegistry,
+    /// `programs/threshold_gate`
+    ThresholdGate,
+    /// `programs/velocity_gate`
+    VelocityGate,
+    /// `programs/geo_gate`
+    GeoGate,
+    /// `programs/nft_gate`
+    NftGate,
+    /// `examples/lending_consumer`
+    LendingConsumer,
+}
+
+/// What kind of instruction was decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstructionCategory {
+    /// `can-thaw-permissionless` or `can-freeze-permissionless`
+    PermissionlessCheck,
+    /// A gate's or consumer's own instruction, e.g. adding to a list
+    Admin,
+    Token,
+    System,
+    ComputeBudget,
+    AssociatedToken,
+    Unknown,
+}
+
+/// An account as one instruction uses it, with its role there
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DecodedAccount {
+    pub role: String,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub address: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+/// A gate's answer to a permissionless check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Decision {
+    pub approved: bool,
+    /// The error the gate denied with
+    pub error: Option<String>,
+    /// The last line the gate logged, which says why for this repository's
+    /// gates
+    pub reason: Option<String>,
+    /// The decision event the gate logged, if it logs them
+    pub event: Option<DecisionEvent>,
+}
+
+/// A [`ThawDecision`] a gate logged with `sol_log_data`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DecisionEvent {
+    /// `native` or `anchor`
+    pub format: &'static str,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub owner: Pubkey,
+    pub approved: bool,
+    /// See [`token_acl_interface::events::DecisionReason::name`]
+    pub reason: &'static str,
+}
+
+impl DecisionEvent {
+    fn new(format: EventFormat, decision: &ThawDecision) -> Self {
+        Self {
+            format: match format {
+                EventFormat::Native => "native",
+                EventFormat::Anchor => "anchor",
+            },
+            owner: decision.owner,
+            approved: decision.approved,
+            reason: decision.reason.name(),
+        }
+    }
+}
+
+/// One top-level instruction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DecodedInstruction {
+    pub index: usize,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub program: Pubkey,
+    /// The program's well-known name, see [`trace::program_name`]
+    pub program_name: String,
+    pub program_kind: Option<ProgramKind>,
+    pub category: InstructionCategory,
+    /// `snake_case` name, e.g. `can_thaw_permissionless` or `thaw_account`
+    pub instruction: String,
+    /// The bytes that selected the instruction, hex
+    pub discriminator: String,
+    pub accounts: Vec<DecodedAccount>,
+    /// `success`, `failed: <error>`, `unfinished`, or `not executed` for
+    /// instructions after the one that failed
+    pub outcome: String,
+    /// For permissionless checks that ran
+    pub decision: Option<Decision>,
+}
+
+/// A transaction, decoded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DecodedTransaction {
+    pub signature: String,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub fee_payer: Pubkey,
+    pub error: Option<String>,
+    pub instructions: Vec<DecodedInstruction>,
+}
+
+impl DecodedTransaction {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("decoded transactions always serialize")
+    }
+
+    pub fn to_json_pretty(&self) -> String {
+        serde_json::to_string_pretty(self).expect("decoded transactions always serialize")
+    }
+
+    /// The permissionless checks, in order
+    pub fn checks(&self) -> impl Iterator<Item = &DecodedInstruction> {
+        self.instructions
+            .iter()
+            .filter(|instruction| instruction.category == InstructionCategory::PermissionlessCheck)
+    }
+}
+
+fn serialize_pubkey<S: Serializer>(address: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&address.to_string())
+}
+
+/// An instruction's name and the roles of its accounts, before it ran
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstructionShape {
+    pub category: InstructionCategory,
+    pub name: String,
+    pub discriminator: Vec<u8>,
+    /// Roles of the leading accounts; the rest are `account_<n>`
+    pub roles: Vec<&'static str>,
+    /// Role of every account after `roles`, e.g. one approval per approver
+    pub repeated: Option<&'static str>,
+}
+
+impl InstructionShape {
+    fn new(
+        category: InstructionCategory,
+        name: &str,
+        discriminator: &[u8],
+        roles: &[&'static str],
+    ) -> Self {
+        Self {
+            category,
+            name: name.to_string(),
+            discriminator: discriminator.to_vec(),
+            roles: roles.to_vec(),
+            repeated: None,
+        }
+    }
+
+    fn unknown(data: &[u8]) -> Self {
+        Self::new(
+            InstructionCategory::Unknown,
+            "unknown",
+            &data[..data.len().min(8)],
+            &[],
+        )
+    }
+
+    /// The role of the account at `index`
+    pub fn role(&self, index: usize) -> String {
+        match (self.roles.get(index), self.repeated) {
+            (Some(role), _) => role.to_string(),
+            (None, Some(role)) => role.to_string(),
+            (None, None) => format!("account_{}", index),
+        }
+    }
+}
+
+/// Names programs and instructions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Decoder {
+    programs: BTreeMap<Pubkey, ProgramKind>,
+}
+
+impl Decoder {
+    /// A decoder that knows the sRFC 37 checks and the well-known programs
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decode `program_id`'s instructions as `kind`'s
+    pub fn register(&mut self, program_id: Pubkey, kind: ProgramKind) -> &mut Self {
+        self.programs.insert(program_id, kind);
+        self
+    }
+
+    pub fn kind(&self, program_id: &Pubkey) -> Option<ProgramKind> {
+        self.programs.get(program_id).copied()
+    }
+
+    /// What `data` asks of `program`
+    pub fn shape(&self, program: &Pubkey, data: &[u8]) -> InstructionShape {
+        if let Some(shape) = check_shape(self.kind(program), data) {
+            return shape;
+        }
+        if let Some(kind) = self.kind(program) {
+            return admin_shape(kind, data).unwrap_or_else(|| InstructionShape::unknown(data));
+        }
+        if *program == spl_token_2022::id() {
+            token_shape(data)
+        } else if *program == system_program::id() {
+            system_shape(data)
+        } else if *program == compute_budget::id() {
+            compute_budget_shape(data)
+        } else if *program == spl_associated_token_account::id() {
+            associated_token_shape(data)
+        } else {
+            InstructionShape::unknown(data)
+        }
+    }
+
+    /// Decode a recorded transaction
+    pub fn decode_trace(&self, trace: &TransactionTrace) -> DecodedTransaction {
+        let instructions = trace
+            .instructions
+            .iter()
+            .enumerate()
+            .map(|(index, instruction)| {
+                let shape = self.shape(&instruction.program, &instruction.data);
+                let accounts = instruction
+                    .accounts
+                    .iter()
+                    .enumerate()
+                    .map(|(position, account)| decoded_account(&shape, position, account))
+                    .collect();
+                let decision = (shape.category == InstructionCategory::PermissionlessCheck)
+                    .then(|| instruction.invocation.as_ref().and_then(decision))
+                    .flatten();
+                DecodedInstruction {
+                    index,
+                    program: instruction.program,
+                    program_name: trace::program_name(&instruction.program),
+                    program_kind: self.kind(&instruction.program),
+                    category: shape.category,
+                    instruction: shape.name.clone(),
+                    discriminator: hex::encode(&shape.discriminator),
+                    accounts,
+                    outcome: instruction
+                        .invocation
+                        .as_ref()
+                        .map_or("not executed".to_string(), |invocation| {
+                            invocation.outcome.to_string()
+                        }),
+                    decision,
+                }
+            })
+            .collect();
+        DecodedTransaction {
+            signature: trace.signature.to_string(),
+            fee_payer: trace.fee_payer,
+            error: trace.error.clone(),
+            instructions,
+        }
+    }
+
+    /// Decode `transaction` from its result and the logs it produced
+    pub fn decode_transaction(
+        &self,
+        transaction: &Transaction,
+        result: &Result<(), TransactionError>,
+        logs: &[String],
+    ) -> DecodedTransaction {
+        self.decode_trace(&TransactionTrace::new(transaction, result, logs, None))
+    }
+
+    /// Decode a transaction as `getTransaction` returns it with the
+    /// `base64` encoding
+    pub fn decode_confirmed(
+        &self,
+        confirmed: &EncodedConfirmedTransactionWithStatusMeta,
+    ) -> Result<DecodedTransaction, String> {
+        let transaction = confirmed
+            .transaction
+            .transaction
+            .decode()
+            .ok_or("Transaction is not binary encoded")?;
+        let meta = confirmed.transaction.meta.as_ref();
+        let logs: Vec<String> = match meta.map(|meta| &meta.log_messages) {
+            Some(OptionSerializer::Some(logs)) => logs.clone(),
+            _ => Vec::new(),
+        };
+        let result = match meta.and_then(|meta| meta.err.clone()) {
+            Some(error) => Err(error),
+            None => Ok(()),
+        };
+        let compute_units =
+            meta.and_then(|meta| Option::<u64>::from(meta.compute_units_consumed.clone()));
+
+        let trace = match &transaction.message {
+            VersionedMessage::Legacy(message) => TransactionTrace::new(
+                &Transaction {
+                    signatures: transaction.signatures.clone(),
+                    message: message.clone(),
+                },
+                &result,
+                &logs,
+                compute_units,
+            ),
+            VersionedMessage::V0(message) => {
+                let loaded = match meta.map(|meta| &meta.loaded_addresses) {
+                    Some(OptionSerializer::Some(loaded)) => loaded_addresses(loaded)?,
+                    _ if message.address_table_lookups.is_empty() => Default::default(),
+                    _ => return Err("Lookup table accounts were not returned".to_string()),
+                };
+                let loaded = LoadedMessage::new(message.clone(), loaded);
+                TransactionTrace::from_loaded(
+                    transaction.signatures.first().copied().unwrap_or_default(),
+                    &loaded,
+                    &result,
+                    &logs,
+                    compute_units,
+                )
+            }
+        };
+        Ok(self.decode_trace(&trace))
+    }
+}
+
+fn loaded_addresses(
+    loaded: &solana_transaction_status::UiLoadedAddresses,
+) -> Result<solana_sdk::message::v0::LoadedAddresses, String> {
+    let parse = |addresses: &[String]| -> Result<Vec<Pubkey>, String> {
+        addresses
+            .iter()
+            .map(|address| {
+                address
+                    .parse()
+                    .map_err(|_| format!("Invalid address {}", address))
+            })
+            .collect()
+    };
+    Ok(solana_sdk::message::v0::LoadedAddresses {
+        writable: parse(&loaded.writable)?,
+        readonly: parse(&loaded.readonly)?,
+    })
+}
+
+fn decoded_account(
+    shape: &InstructionShape,
+    position: usize,
+    account: &AccountUse,
+) -> DecodedAccount {
+    DecodedAccount {
+        role: shape.role(position),
+        address: account.address,
+        is_signer: account.is_signer,
+        is_writable: account.is_writable,
+    }
+}
+
+/// The gate's decision, from how its check ran
+fn decision(invocation: &Invocation) -> Option<Decision> {
+    let reason = invocation
+        .events
+        .iter()
+        .rev()
+        .find_map(|event| match event {
+            Event::Log(line) => line.strip_prefix("Program log: ").map(str::to_string),
+            Event::Cpi(_) => None,
+        });
+    let event = invocation.events.iter().find_map(|event| match event {
+        Event::Log(line) => {
+            thaw_decision(line).map(|(format, decision)| DecisionEvent::new(format, &decision))
+        }
+        Event::Cpi(_) => None,
+    });
+    match &invocation.outcome {
+        Outcome::Success => Some(Decision {
+            approved: true,
+            error: None,
+            reason,
+            event,
+        }),
+        Outcome::Failed(error) => Some(Decision {
+            approved: false,
+            error: Some(error.clone()),
+            reason,
+            event,
+        }),
+        Outcome::Unfinished => None,
+    }
+}
+
+/// The thaw decision a `Program data:` log line carries, in either format
+pub fn thaw_decision(line: &str) -> Option<(EventFormat, ThawDecision)> {
+    let fields = line
+        .strip_prefix("Program data: ")?
+        .split(' ')
+        .map(|field| BASE64.decode(field))
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    ThawDecision::from_log_fields(&fields)
+}
+
+/// Every thaw decision in `logs`, in the order they were logged
+pub fn thaw_decisions(logs: &[String]) -> Vec<(EventFormat, ThawDecision)> {
+    logs.iter().filter_map(|line| thaw_decision(line)).collect()
+}
+
+/// The extra accounts of `kind`'s checks, after the interface accounts
+fn check_extras(kind: ProgramKind, thaw: bool) -> (&'static [&'static str], Option<&'static str>) {
+    match (kind, thaw) {
+        (ProgramKind::AllowList, true) => (&["owner", "allow_list_record"], None),
+        (ProgramKind::BlockList, true) => (
+            &["owner", "block_list_record", "missing_record_policy"],
+            None,
+        ),
+        (ProgramKind::BlockList, false) => (&["block_list_record"], None),
+        (ProgramKind::ProductionAllowList, true) => (
+            &["owner", "allow_list_record", "missing_record_policy"],
+            None,
+        ),
+        (ProgramKind::HybridGate, true) => (
+            &[
+                "owner",
+                "config",
+                "allow_list_record",
+                "block_list_record",
+                "jurisdiction_registry",
+                "holder_jurisdiction",
+            ],
+            None,
+        ),
+        (ProgramKind::HybridGate, false) => (
+            &[
+                "config",
+                "block_list_record",
+                "jurisdiction_registry",
+                "holder_jurisdiction",
+            ],
+            None,
+        ),
+        (ProgramKind::ThresholdGate, true) => (&["owner", "config"], Some("approval")),
+        (ProgramKind::VelocityGate, true) => {
+            (&["owner", "config", "ledger", "instructions_sysvar"], None)
+        }
+        (ProgramKind::GeoGate, true) => (
+            &[
+                "owner",
+                "config",
+                "jurisdiction_registry",
+                "holder_jurisdiction",
+            ],
+            None,
+        ),
+        (ProgramKind::GeoGate, false) => (
+            &["config", "jurisdiction_registry", "holder_jurisdiction"],
+            None,
+        ),
+        (ProgramKind::NftGate, true) => (
+            &["owner", "nft_account", "nft_mint", "nft_metadata", "config"],
+            None,
+        ),
+        _ => (&[], None),
+    }
+}
+
+fn check_shape(kind: Option<ProgramKind>, data: &[u8]) -> Option<InstructionShape> {
+    let (name, thaw) = if data == CAN_THAW_PERMISSIONLESS_DISCRIMINATOR {
+        ("can_thaw_permissionless", true)
+    } else if data == CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR {
+        ("can_freeze_permissionless", false)
+    } else {
+        return None;
+    };
+    let (extras, repeated) = kind.map_or((&[][..], None), |kind| check_extras(kind, thaw));
+    let mut shape = InstructionShape::new(
+        InstructionCategory::PermissionlessCheck,
+        name,
+        data,
+        &INTERFACE_ROLES,
+    );
+    shape.roles.extend_from_slice(extras);
+    shape.repeated = repeated;
+    Some(shape)
+}
+
+/// `kind`'s own instructions, selected by their first byte
+fn admin_shape(kind: ProgramKind, data: &[u8]) -> Option<InstructionShape> {
+    use ProgramKind::*;
+
+    const CREATE_LIST: &[&str] = &["payer", "extra_account_metas", "mint", "system_program"];
+    const INITIALIZE_CONFIG: &[&str] = &["config", "mint", "authority", "payer", "system_program"];
+    const REVIEW_DECISION: &[&str] = &[
+        "config",
+        "review_request",
+        "allow_list_record",
+        "authority",
+        "payer",
+        "user",
+        "system_program",
+    ];
+
+    let first = *data.first()?;
+    // Instructions that take no arguments are exactly their discriminator
+    let bare = data.len() == 1;
+    let (name, roles): (&str, &[&'static str]) = match (kind, first) {
+        (AllowList, 0) if bare => ("initialize_thaw_extra_account_metas", CREATE_LIST),
+        (BlockList, 0) if bare => ("initialize_freeze_extra_account_metas", CREATE_LIST),
+        (BlockList, 1) => (
+            "update_freeze_extra_account_metas",
+            &[
+                "authority",
+                "payer",
+                "extra_account_metas",
+                "mint",
+                "system_program",
+            ],
+        ),
+        (BlockList, 2) => (
+            "set_missing_record_policy",
+            &[
+                "authority",
+                "payer",
+                "missing_record_policy",
+                "mint",
+                "system_program",
+            ],
+        ),
+        (BlockList, 3) if bare => ("initialize_thaw_extra_account_metas", CREATE_LIST),
+        (BlockList, 4) if bare => ("initialize", INITIALIZE_CONFIG),
+        (BlockList, 5) => (
+            "block_user",
+            &[
+                "config",
+                "block_list_record",
+                "mint",
+                "user",
+                "authority",
+                "payer",
+                "system_program",
+            ],
+        ),
+        (BlockList, 6) if bare => (
+            "unblock_user",
+            &["config", "block_list_record", "authority"],
+        ),
+        (BlockList, 7) if bare => (
+            "update_authority",
+            &[
+                "config",
+                "current_authority",
+                "new_authority",
+                "pending_authority",
+                "payer",
+                "system_program",
+            ],
+        ),
+        (BlockList, 9) if bare => (
+            "accept_authority",
+            &["config", "pending_authority", "new_authority", "recipient"],
+        ),
+        (BlockList, 10) if bare => (
+            "cancel_authority_transfer",
+            &["config", "pending_authority", "authority", "recipient"],
+        ),
+        (ProductionAllowList, 0) => ("initialize", INITIALIZE_CONFIG),
+        (ProductionAllowList, 1) => (
+            "add_to_allow_list",
+            &[
+                "config",
+                "allow_list_record",
+                "mint",
+                "user",
+                "authority",
+                "payer",
+                "system_program",
+            ],
+        ),
+        (ProductionAllowList, 2) => (
+            "remove_from_allow_list",
+            &["config", "allow_list_record", "authority", "recipient"],
+        ),
+        (ProductionAllowList, 3) => (
+            "update_authority",
+            &[
+                "config",
+                "current_authority",
+                "new_authority",
+                "pending_authority",
+                "payer",
+                "system_program",
+            ],
+        ),
+        (ProductionAllowList, 4) => ("revoke", &["config", "allow_list_record", "authority"]),
+        (ProductionAllowList, 5) => (
+            "lift_revocation",
+            &["config", "allow_list_record", "authority"],
+        ),
+        (ProductionAllowList, 6) => (
+            "set_missing_record_policy",
+            &[
+                "config",
+                "missing_record_policy",
+                "authority",
+                "payer",
+                "system_program",
+            ],
+        ),
+        (ProductionAllowList, 7) => (
+            "set_snapshot_root",
+            &[
+                "config",
+                "snapshot_root",
+                "authority",
+                "payer",
+                "system_program",
+            ],
+        ),
+        (ProductionAllowList, 9) => (
+            "materialize_from_snapshot",
+            &[
+                "snapshot_root",
+                "allow_list_record",
+                "mint",
+                "user",
+                "system_program",
+            ],
+        ),
+        (ProductionAllowList, 10) => (
+            "close_record",
+            &["config", "allow_list_record", "authority", "recipient"],
+        ),
+        (ProductionAllowList, 11) => (
+            "accept_authority",
+            &["config", "pending_authority", "new_authority", "recipient"],
+        ),
+        (ProductionAllowList, 12) => (
+            "cancel_authority_transfer",
+            &["config", "pending_authority", "authority", "recipient"],
+        ),
+        (ProductionAllowList, 13) if bare => ("initialize_thaw_extra_account_metas", CREATE_LIST),
+        (ProductionAllowList, 14) if bare => (
+            "request_review",
+            &[
+                "config",
+                "missing_record_policy",
+                "allow_list_record",
+                "review_request",
+                "user",
+                "system_program",
+            ],
+        ),
+        (ProductionAllowList, 15) => ("approve_review", REVIEW_DECISION),
+        (ProductionAllowList, 16) if bare => ("reject_review", REVIEW_DECISION),
+        (HybridGate | ThresholdGate | VelocityGate | GeoGate | NftGate, 0) => {
+            ("initialize", INITIALIZE_CONFIG)
+        }
+        (HybridGate, 1) => ("set_cooling_off", &["config", "mint", "authority"]),
+        (HybridGate, 2) => ("set_paused", &["config", "mint", "authority"]),
+        (JurisdictionRegistry, 0) if bare => (
+            "initialize_registry",
+            &["registry", "authority", "payer", "system_program"],
+        ),
+        (JurisdictionRegistry, 1) => ("set_jurisdiction", &["registry", "authority"]),
+        (JurisdictionRegistry, 2) => (
+            "set_holder_jurisdiction",
+            &[
+                "registry",
+                "holder_jurisdiction",
+                "holder",
+                "authority",
+                "payer",
+                "system_program",
+            ],
+        ),
+        (ThresholdGate, 1) if bare => (
+            "post_approval",
+            &[
+                "config",
+                "approval",
+                "mint",
+                "user",
+                "approver",
+                "payer",
+                "system_program",
+            ],
+        ),
+        (ThresholdGate, 2) if bare => (
+            "revoke_approval",
+            &[
+                "config",
+                "approval",
+                "mint",
+                "user",
+                "approver",
+                "recipient",
+            ],
+        ),
+        (VelocityGate, 1) if bare => (
+            "record_thaw",
+            &[
+                "config",
+                "ledger",
+                "mint",
+                "token_account",
+                "owner",
+                "payer",
+                "system_program",
+            ],
+        ),
+        (GeoGate, 1) => (
+            "set_allowed_jurisdictions",
+            &["config", "mint", "authority"],
+        ),
+        (NftGate, 1) => ("set_collection", &["config", "mint", "authority"]),
+        (NftGate, 2) if bare => ("initialize_thaw_extra_account_metas", CREATE_LIST),
+        (LendingConsumer, 0) => (
+            "initialize_market",
+            &["market", "mint", "payer", "system_program"],
+        ),
+        (LendingConsumer, 1) if bare => (
+            "check_borrower",
+            &[
+                "market",
+                "borrower",
+                "allow_list_record",
+                "block_list_record",
+            ],
+        ),
+        _ => return None,
+    };
+    Some(InstructionShape::new(
+        InstructionCategory::Admin,
+        name,
+        &[first],
+        roles,
+    ))
+}
+
+// Plain transfers are deprecated for Token-2022 mints but still decoded
+#[allow(deprecated)]
+fn token_shape(data: &[u8]) -> InstructionShape {
+    let Ok(instruction) = TokenInstruction::unpack(data) else {
+        return InstructionShape::unknown(data);
+    };
+    let roles: &[&'static str] = match instruction {
+        TokenInstruction::InitializeMint { .. } => &["mint", "rent_sysvar"],
+        TokenInstruction::InitializeMint2 { .. } => &["mint"],
+        TokenInstruction::InitializeAccount => &["account", "mint", "owner", "rent_sysvar"],
+        TokenInstruction::InitializeAccount2 { .. } => &["account", "mint", "rent_sysvar"],
+        TokenInstruction::InitializeAccount3 { .. } => &["account", "mint"],
+        TokenInstruction::Transfer { .. } => &["source", "destination", "authority"],
+        TokenInstruction::TransferChecked { .. } => &["source", "mint", "destination", "authority"],
+        TokenInstruction::MintTo { .. } | TokenInstruction::MintToChecked { .. } => {
+            &["mint", "account", "authority"]
+        }
+        TokenInstruction::Burn { .. } | TokenInstruction::BurnChecked { .. } => {
+            &["account", "mint", "authority"]
+        }
+        TokenInstruction::CloseAccount => &["account", "destination", "authority"],
+        // The FAMP's thaws and freezes, signed by the freeze authority
+        TokenInstruction::FreezeAccount | TokenInstruction::ThawAccount => {
+            &["account", "mint", "freeze_authority"]
+        }
+        _ => &[],
+    };
+    InstructionShape::new(
+        InstructionCategory::Token,
+        &snake_case(&variant_name(&instruction)),
+        &data[..1],
+        roles,
+    )
+}
+
+fn system_shape(data: &[u8]) -> InstructionShape {
+    let Ok(instruction) = limited_deserialize::<SystemInstruction>(data) else {
+        return InstructionShape::unknown(data);
+    };
+    let roles: &[&'static str] = match instruction {
+        SystemInstruction::CreateAccount { .. } => &["funder", "new_account"],
+        SystemInstruction::Transfer { .. } => &["from", "to"],
+        SystemInstruction::Assign { .. } => &["account"],
+        SystemInstruction::Allocate { .. } => &["account"],
+        _ => &[],
+    };
+    InstructionShape::new(
+        InstructionCategory::System,
+        &snake_case(&variant_name(&instruction)),
+        &data[..4],
+        roles,
+    )
+}
+
+fn compute_budget_shape(data: &[u8]) -> InstructionShape {
+    match try_from_slice_unchecked::<ComputeBudgetInstruction>(data) {
+        Ok(instruction) => InstructionShape::new(
+            InstructionCategory::ComputeBudget,
+            &snake_case(&variant_name(&instruction)),
+            &data[..1],
+            &[],
+        ),
+        Err(_) => InstructionShape::unknown(data),
+    }
+}
+
+fn associated_token_shape(data: &[u8]) -> InstructionShape {
+    const CREATE: &[&str] = &[
+        "funder",
+        "associated_account",
+        "wallet",
+        "mint",
+        "system_program",
+        "token_program",
+    ];
+    let (name, roles): (&str, &[&'static str]) = match data {
+        [] | [0] => ("create", CREATE),
+        [1] => ("create_idempotent", CREATE),
+        [2] => ("recover_nested", &[]),
+        _ => return InstructionShape::unknown(data),
+    };
+    InstructionShape::new(InstructionCategory::AssociatedToken, name, data, roles)
+}
+
+/// `Variant { .. }` or `Variant(..)` as `Variant`
+fn variant_name(value: &impl std::fmt::Debug) -> String {
+    let debug = format!("{:?}", value);
+    debug
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// `ThawAccount` as `thaw_account`
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (index, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if index > 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
@@ -0,0 +1,331 @@
+//! Permissionless thaw and freeze instructions
+//!
+//! A permissionless thaw takes two instructions: the gate's
+//! `can-thaw-permissionless` check, which only decides, and the Token-2022
+//! thaw the freeze authority makes once the check passes. A freeze works the
+//! same way.
+//!
+//! [`permissionless_thaw`] / [`permissionless_freeze`] build the check. They
+//! encode the sRFC 37 discriminator and derive the mint's extra-account-metas
+//! PDA under the gate. The gate's extra accounts come after that, either ones
+//! the caller already knows ([`with_extra_accounts`]) or ones read from the
+//! gate's list ([`resolve_extra_accounts`]). [`authorized`] pairs a finished
+//! check with the freeze or thaw it authorizes.
+//!
+//! On a mint managed by a FAMP, the freeze authority is the FAMP's MintConfig
+//! PDA ([`find_mint_config_address`]), which signs through the FAMP. Before
+//! the issuer hands the authority over, and in tests, it holds the authority
+//! with its own key.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # use solana_program::pubkey::Pubkey;
+//! # use token_acl_client::instructions::{self, FreezeAuthority};
+//! # let (gate, mint, token_account, owner, record) = (
+//! #     Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(),
+//! #     Pubkey::new_unique(), Pubkey::new_unique(),
+//! # );
+//! let check = instructions::permissionless_thaw(&mint, &token_account, &owner, &gate);
+//! let check = instructions::with_extra_accounts(check, &[owner, record]);
+//! let [check, thaw] = instructions::authorized(check, &FreezeAuthority::Key(owner)).unwrap();
+//! assert_eq!(thaw.program_id, spl_token_2022::id());
+//! ```
+
+use solana_program::{instruction::Instruction, program_error::ProgramError, pubkey::Pubkey};
+use spl_tlv_account_resolution::state::AccountDataResult;
+use spl_token_2022::instruction as token_instruction;
+use std::future::Future;
+use token_acl_interface::{
+    self as interface, CanonicalPda, ReadonlyMeta, CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR,
+    CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+};
+
+/// Seed of a mint's MintConfig PDA under the FAMP program
+pub const MINT_CONFIG_SEED: &[u8] = b"MINT_CFG";
+
+/// MintConfig PDA of `mint` under `famp_program_id`
+pub fn find_mint_config_address(mint: &Pubkey, famp_program_id: &Pubkey) -> (Pubkey, u8) {
+    CanonicalPda::find(&[MINT_CONFIG_SEED, mint.as_ref()], famp_program_id).into()
+}
+
+/// Who holds a mint's freeze authority
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FreezeAuthority {
+    /// The mint's MintConfig PDA, which signs through the FAMP program
+    MintConfig { famp_program_id: Pubkey },
+    /// A key that signs for itself, e.g. the issuer's
+    Key(Pubkey),
+}
+
+impl FreezeAuthority {
+    /// The address that signs `mint`'s freezes and thaws
+    pub fn address(&self, mint: &Pubkey) -> Pubkey {
+        match self {
+            FreezeAuthority::MintConfig { famp_program_id } => {
+                find_mint_config_address(mint, famp_program_id).0
+            }
+            FreezeAuthority::Key(key) => *key,
+        }
+    }
+}
+
+/// `gate_program`'s `can-thaw-permissionless` check of `token_account`,
+/// requested by `caller`, without the gate's extra accounts
+pub fn permissionless_thaw(
+    mint: &Pubkey,
+    token_account: &Pubkey,
+    caller: &Pubkey,
+    gate_program: &Pubkey,
+) -> Instruction {
+    let (extra_account_metas, _) =
+        interface::find_thaw_extra_account_metas_address(mint, gate_program);
+    interface::can_thaw_permissionless(
+        gate_program,
+        caller,
+        token_account,
+        mint,
+        extra_account_metas,
+        &[],
+    )
+}
+
+/// `gate_program`'s `can-freeze-permissionless` check of `token_account`,
+/// requested by `caller`, without the gate's extra accounts
+pub fn permissionless_freeze(
+    mint: &Pubkey,
+    token_account: &Pubkey,
+    caller: &Pubkey,
+    gate_program: &Pubkey,
+) -> Instruction {
+    let (extra_account_metas, _) =
+        interface::find_freeze_extra_account_metas_address(mint, gate_program);
+    interface::can_freeze_permissionless(
+        gate_program,
+        caller,
+        token_account,
+        mint,
+        extra_account_metas,
+        &[],
+    )
+}
+
+/// `check` with `extra_accounts` appended read-only and non-signer, in the
+/// order the gate's extra-account-metas resolve them
+pub fn with_extra_accounts(mut check: Instruction, extra_accounts: &[Pubkey]) -> Instruction {
+    check.accounts.extend(
+        extra_accounts
+            .iter()
+            .map(|address| ReadonlyMeta::new(*address).into()),
+    );
+    check
+}
+
+/// `check` with the extra accounts the gate's extra-account-metas list
+/// resolves to appended
+///
+/// `fetch_account_data` returns an account's data, or `None` if it does not
+/// exist. Fails as [`interface::resolve_extra_account_metas`] does when the
+/// gate has no list for the mint or speaks another interface version.
+pub async fn resolve_extra_accounts<F, Fut>(
+    mut check: Instruction,
+    fetch_account_data: F,
+) -> Result<Instruction, ProgramError>
+where
+    F: Fn(Pubkey) -> Fut,
+    Fut: Future<Output = AccountDataResult>,
+{
+    interface::resolve_extra_account_metas(&mut check, fetch_account_data).await?;
+    Ok(check)
+}
+
+/// `check`, then the Token-2022 thaw or freeze it authorizes, signed by
+/// `authority`
+///
+/// Fails with `InvalidInstructionData` if `check` is not a permissionless
+/// check and with `NotEnoughAccountKeys` if it lacks the token account or
+/// mint.
+pub fn authorized(
+    check: Instruction,
+    authority: &FreezeAuthority,
+) -> Result<[Instruction; 2], ProgramError> {
+    let (token_account, mint) = match check.accounts.get(1..3) {
+        Some([token_account, mint]) => (token_account.pubkey, mint.pubkey),
+        _ => return Err(ProgramError::NotEnoughAccountKeys),
+    };
+    let authority = authority.address(&mint);
+    let operation = match check.data.get(..8) {
+        Some(d) if d == CAN_THAW_PERMISSIONLESS_DISCRIMINATOR => token_instruction::thaw_account(
+            &spl_token_2022::id(),
+            &token_account,
+            &mint,
+            &authority,
+            &[],
+        )?,
+        Some(d) if d == CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR => {
+            token_instruction::freeze_account(
+                &spl_token_2022::id(),
+                &token_account,
+                &mint,
+                &authority,
+                &[],
+            )?
+        }
+        _ => return Err(ProgramError::InvalidInstructionData),
+    };
+    Ok([check, operation])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_de_escalated(instruction: &Instruction) {
+        for meta in &instruction.accounts {
+            assert!(!meta.is_signer, "{} is a signer", meta.pubkey);
+            assert!(!meta.is_writable, "{} is writable", meta.pubkey);
+        }
+    }
+
+    #[test]
+    fn test_permissionless_thaw_layout() {
+        let (mint, token_account, caller, gate) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let check = permissionless_thaw(&mint, &token_account, &caller, &gate);
+        let (extra_metas, _) = Pubkey::find_program_address(
+            &[interface::THAW_EXTRA_ACCOUNT_METAS_SEED, mint.as_ref()],
+            &gate,
+        );
+
+        assert_eq!(check.program_id, gate);
+        assert_eq!(check.data, CAN_THAW_PERMISSIONLESS_DISCRIMINATOR);
+        let accounts: Vec<Pubkey> = check.accounts.iter().map(|meta| meta.pubkey).collect();
+        assert_eq!(accounts, [caller, token_account, mint, extra_metas]);
+        assert_de_escalated(&check);
+    }
+
+    #[test]
+    fn test_permissionless_freeze_layout() {
+        let (mint, token_account, caller, gate) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let check = permissionless_freeze(&mint, &token_account, &caller, &gate);
+        let (extra_metas, _) = interface::find_freeze_extra_account_metas_address(&mint, &gate);
+
+        assert_eq!(check.data, CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR);
+        assert_eq!(check.accounts[3].pubkey, extra_metas);
+        assert_de_escalated(&check);
+    }
+
+    #[test]
+    fn test_with_extra_accounts_appends_in_order() {
+        let extras = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let check = permissionless_thaw(
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+        );
+        let check = with_extra_accounts(check, &extras);
+
+        assert_eq!(check.accounts.len(), 6);
+        assert_eq!(check.accounts[4].pubkey, extras[0]);
+        assert_eq!(check.accounts[5].pubkey, extras[1]);
+        assert_de_escalated(&check);
+    }
+
+    #[test]
+    fn test_authorized_pairs_check_with_operation() {
+        let (mint, token_account, gate, issuer, famp) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+
+        let check = permissionless_thaw(&mint, &token_account, &issuer, &gate);
+        let [_, thaw] = authorized(check, &FreezeAuthority::Key(issuer)).unwrap();
+        assert_eq!(
+            thaw,
+            token_instruction::thaw_account(
+                &spl_token_2022::id(),
+                &token_account,
+                &mint,
+                &issuer,
+                &[]
+            )
+            .unwrap()
+        );
+
+        let check = permissionless_freeze(&mint, &token_account, &issuer, &gate);
+        let famp_authority = FreezeAuthority::MintConfig {
+            famp_program_id: famp,
+        };
+        let [_, freeze] = authorized(check, &famp_authority).unwrap();
+        let (mint_config, _) =
+            Pubkey::find_program_address(&[MINT_CONFIG_SEED, mint.as_ref()], &famp);
+        assert_eq!(
+            freeze,
+            token_instruction::freeze_account(
+                &spl_token_2022::id(),
+                &token_account,
+                &mint,
+                &mint_config,
+                &[]
+            )
+            .unwrap()
+        );
+
+        let mut other = permissionless_thaw(&mint, &token_account, &issuer, &gate);
+        other.data = vec![0; 8];
+        assert_eq!(
+            authorized(other, &famp_authority),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
+
+    // tokio has no wasm32 runtime; tests/wasm.rs covers that build
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_resolve_extra_accounts() {
+        use spl_tlv_account_resolution::{account::ExtraAccountMeta, state::ExtraAccountMetaList};
+        use token_acl_interface::CanThawPermissionless;
+
+        let (mint, token_account, caller, gate) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let listed = Pubkey::new_unique();
+        let metas = [ExtraAccountMeta::new_with_pubkey(&listed, true, true).unwrap()];
+        let mut list = vec![0; ExtraAccountMetaList::size_of(metas.len()).unwrap()];
+        ExtraAccountMetaList::init::<CanThawPermissionless>(&mut list, &metas).unwrap();
+        let (extra_metas, _) = interface::find_thaw_extra_account_metas_address(&mint, &gate);
+
+        let check = permissionless_thaw(&mint, &token_account, &caller, &gate);
+        let resolved = resolve_extra_accounts(check, |address| {
+            let data = (address == extra_metas).then(|| list.clone());
+            async move { Ok(data) }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(resolved.accounts.len(), 5);
+        assert_eq!(resolved.accounts[4].pubkey, listed);
+        // Declared writable and signer in the list, passed de-escalated
+        assert_de_escalated(&resolved);
+
+        let unlisted = permissionless_thaw(&mint, &token_account, &caller, &Pubkey::new_unique());
+        let result = resolve_extra_accounts(unlisted, |_| async { Ok(None) }).await;
+        assert_eq!(result, Err(ProgramError::UninitializedAccount));
+    }
+}
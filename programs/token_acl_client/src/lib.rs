@@ -0,0 +1,31 @@
+//! Off-chain client for Token ACL
+//!
+//! Wallets, keeper bots and tests build permissionless thaws and freezes
+//! through [`instructions`] instead of assembling account lists and
+//! discriminators by hand. The builders sit on top of
+//! [`token_acl_interface`], so the accounts they hand a gate are always
+//! de-escalated and every PDA is derived with its canonical bump.
+//!
+//! [`snapshot`] builds the Merkle snapshots holders materialize their own
+//! production allow list records from, and [`status`] tells a holder where
+//! they stand with a mint's gates, as the `token-acl status` command prints
+//! it.
+//!
+//! With the `decoder` feature, [`trace`] rebuilds what a transaction did
+//! from its logs and [`decoder`] names it, for explorers, dashboards and
+//! the Python bindings.
+//!
+//! Everything but the JSON-RPC status source, the `token-acl` command and
+//! the decoder builds for `wasm32-unknown-unknown` with
+//! `default-features = false`, so a browser wallet runs the same builders
+//! and status checks as a keeper; `tests/wasm.rs` runs them under
+//! wasm-bindgen. The `rpc` feature, on by default, adds the status source
+//! and the command.
+
+#[cfg(feature = "decoder")]
+pub mod decoder;
+pub mod instructions;
+pub mod snapshot;
+pub mod status;
+#[cfg(feature = "decoder")]
+pub mod trace;
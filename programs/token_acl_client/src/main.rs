@@ -0,0 +1,91 @@
+//! token-acl
+//!
+//! ```text
+//! token-acl status --rpc-url <URL> --mint <PUBKEY> --owner <PUBKEY>
+//!                  [--allow-list <GATE>] [--block-list <GATE>]
+//! ```
+//!
+//! `status` prints whether the owner is on the allow list kept by the
+//! `--allow-list` gate (with their tier and expiry) and on the block list
+//! kept by the `--block-list` gate, and which of their token accounts of the
+//! mint are frozen. A list whose gate is not given is not checked.
+
+use std::{process::ExitCode, str::FromStr};
+
+use solana_program::pubkey::Pubkey;
+use token_acl_client::status::{Gates, HolderStatus, RpcStatusSource};
+
+struct StatusArgs {
+    rpc_url: String,
+    mint: Pubkey,
+    owner: Pubkey,
+    gates: Gates,
+}
+
+fn parse_status_args(mut args: impl Iterator<Item = String>) -> Result<StatusArgs, String> {
+    let mut rpc_url = None;
+    let mut mint = None;
+    let mut owner = None;
+    let mut gates = Gates::default();
+
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", flag))?;
+        match flag.as_str() {
+            "--rpc-url" => rpc_url = Some(value),
+            "--mint" => mint = Some(parse(&flag, &value)?),
+            "--owner" => owner = Some(parse(&flag, &value)?),
+            "--allow-list" => gates.allow_list = Some(parse(&flag, &value)?),
+            "--block-list" => gates.block_list = Some(parse(&flag, &value)?),
+            _ => return Err(format!("Unknown flag {}", flag)),
+        }
+    }
+
+    Ok(StatusArgs {
+        rpc_url: required(rpc_url, "--rpc-url")?,
+        mint: required(mint, "--mint")?,
+        owner: required(owner, "--owner")?,
+        gates,
+    })
+}
+
+fn required<T>(value: Option<T>, flag: &str) -> Result<T, String> {
+    value.ok_or_else(|| format!("{} is required", flag))
+}
+
+fn parse<T: FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid {} value: {}", flag, value))
+}
+
+async fn status(args: StatusArgs) -> Result<(), String> {
+    let source = RpcStatusSource::new(&args.rpc_url);
+    let now = source.unix_timestamp().await.map_err(|e| e.to_string())?;
+    let status = HolderStatus::query(&source, args.gates, &args.mint, &args.owner, now)
+        .await
+        .map_err(|e| e.to_string())?;
+    print!("{}", status.render());
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let result = match args.next().as_deref() {
+        Some("status") => match parse_status_args(args) {
+            Ok(args) => status(args).await,
+            Err(e) => Err(e),
+        },
+        Some(command) => Err(format!("Unknown command {}", command)),
+        None => Err("Usage: token-acl status --rpc-url <URL> --mint <PUBKEY> --owner <PUBKEY> [--allow-list <GATE>] [--block-list <GATE>]".to_string()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,315 @@
+//! Allow list snapshots for the production allow list
+//!
+//! Instead of adding every holder itself, the allow list authority can
+//! publish the Merkle root of a snapshot of the list ([`set_root`]). Each
+//! holder in it then creates their own record, paying its rent, with a proof
+//! against that root ([`materialize`]). The authority keeps the snapshot off
+//! chain and hands holders their proofs.
+//!
+//! Leaves and inner nodes are hashed by `production_allow_list`
+//! ([`SnapshotEntry::leaf`], [`production_allow_list::snapshot_node`]), so
+//! the proofs built here are the ones the program checks.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # use solana_program::pubkey::Pubkey;
+//! # use production_allow_list::{AccessLevel, SnapshotEntry};
+//! # use token_acl_client::snapshot::{self, AllowListSnapshot};
+//! # let (gate, mint, authority, holder) = (
+//! #     Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(),
+//! #     Pubkey::new_unique(),
+//! # );
+//! let entries = [SnapshotEntry {
+//!     user: holder,
+//!     access_level: AccessLevel::Basic,
+//!     expiry_timestamp: None,
+//! }];
+//! let snapshot = AllowListSnapshot::new(mint, entries).unwrap();
+//! let publish = snapshot::set_root(&gate, &mint, &authority, &authority, snapshot.root());
+//! let materialize = snapshot.materialize(&gate, &holder).unwrap();
+//! assert_eq!(materialize.accounts[3].pubkey, holder);
+//! ```
+
+use borsh::BorshSerialize;
+use production_allow_list::{
+    allow_list_address, config_address, snapshot_node, snapshot_root_address, MaterializeArgs,
+    SnapshotEntry, MATERIALIZE_FROM_SNAPSHOT, SET_SNAPSHOT_ROOT,
+};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program,
+};
+use std::collections::HashMap;
+
+/// Why a snapshot could not be built
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// A snapshot needs at least one entry
+    Empty,
+    /// The user has more than one entry; only one could ever be materialized
+    DuplicateUser(Pubkey),
+}
+
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::Empty => write!(f, "a snapshot needs at least one entry"),
+            SnapshotError::DuplicateUser(user) => {
+                write!(f, "user {} has more than one snapshot entry", user)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+/// The Merkle tree of one mint's allow list snapshot
+#[derive(Debug, Clone)]
+pub struct AllowListSnapshot {
+    mint: Pubkey,
+    entries: Vec<SnapshotEntry>,
+    positions: HashMap<Pubkey, usize>,
+    /// Leaves first, root last; a level with an odd node out carries it up
+    /// unhashed
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl AllowListSnapshot {
+    /// Build `mint`'s snapshot of `entries`
+    pub fn new(
+        mint: Pubkey,
+        entries: impl IntoIterator<Item = SnapshotEntry>,
+    ) -> Result<Self, SnapshotError> {
+        let entries: Vec<SnapshotEntry> = entries.into_iter().collect();
+        if entries.is_empty() {
+            return Err(SnapshotError::Empty);
+        }
+
+        let mut positions = HashMap::with_capacity(entries.len());
+        for (position, entry) in entries.iter().enumerate() {
+            if positions.insert(entry.user, position).is_some() {
+                return Err(SnapshotError::DuplicateUser(entry.user));
+            }
+        }
+
+        let mut levels = vec![entries
+            .iter()
+            .map(|entry| entry.leaf(&mint))
+            .collect::<Vec<_>>()];
+        while levels[levels.len() - 1].len() > 1 {
+            let next = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => snapshot_node(a, b),
+                    [odd] => *odd,
+                    _ => unreachable!("chunks of two"),
+                })
+                .collect();
+            levels.push(next);
+        }
+
+        Ok(Self {
+            mint,
+            entries,
+            positions,
+            levels,
+        })
+    }
+
+    pub fn mint(&self) -> &Pubkey {
+        &self.mint
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The root the authority publishes
+    pub fn root(&self) -> [u8; 32] {
+        self.levels[self.levels.len() - 1][0]
+    }
+
+    /// `user`'s entry, if they are in the snapshot
+    pub fn entry(&self, user: &Pubkey) -> Option<&SnapshotEntry> {
+        self.positions
+            .get(user)
+            .map(|&position| &self.entries[position])
+    }
+
+    /// The siblings from `user`'s leaf up to the root
+    pub fn proof(&self, user: &Pubkey) -> Option<Vec<[u8; 32]>> {
+        let mut position = *self.positions.get(user)?;
+        let mut proof = Vec::with_capacity(self.levels.len() - 1);
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(position ^ 1) {
+                proof.push(*sibling);
+            }
+            position /= 2;
+        }
+        Some(proof)
+    }
+
+    /// `user`'s `MaterializeFromSnapshot` under `program_id`, if they are in
+    /// the snapshot
+    pub fn materialize(&self, program_id: &Pubkey, user: &Pubkey) -> Option<Instruction> {
+        let entry = self.entry(user)?;
+        let proof = self.proof(user)?;
+        Some(materialize(program_id, &self.mint, entry, proof))
+    }
+}
+
+/// Publish `root` as `mint`'s snapshot, signed by the config `authority`
+pub fn set_root(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+    root: [u8; 32],
+) -> Instruction {
+    let mut data = vec![SET_SNAPSHOT_ROOT];
+    data.extend_from_slice(&root);
+    Instruction::new_with_bytes(
+        *program_id,
+        &data,
+        vec![
+            AccountMeta::new_readonly(config_address(mint, program_id).address(), false),
+            AccountMeta::new(snapshot_root_address(mint, program_id).address(), false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Create `entry`'s record from `proof`, signed and paid for by its user
+pub fn materialize(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    entry: &SnapshotEntry,
+    proof: Vec<[u8; 32]>,
+) -> Instruction {
+    let args = MaterializeArgs {
+        access_level: entry.access_level,
+        expiry_timestamp: entry.expiry_timestamp,
+        proof,
+    };
+    let mut data = vec![MATERIALIZE_FROM_SNAPSHOT];
+    data.extend(
+        args.try_to_vec()
+            .expect("materialize args always serialize"),
+    );
+    Instruction::new_with_bytes(
+        *program_id,
+        &data,
+        vec![
+            AccountMeta::new_readonly(snapshot_root_address(mint, program_id).address(), false),
+            AccountMeta::new(
+                allow_list_address(mint, &entry.user, program_id).address(),
+                false,
+            ),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(entry.user, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use production_allow_list::{verify_snapshot_proof, AccessLevel};
+
+    fn entries(count: usize) -> Vec<SnapshotEntry> {
+        (0..count)
+            .map(|i| SnapshotEntry {
+                user: Pubkey::new_unique(),
+                access_level: AccessLevel::Basic,
+                expiry_timestamp: (i % 3 == 0).then_some(i as i64),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_every_proof_verifies() {
+        let mint = Pubkey::new_unique();
+        for count in [1, 2, 3, 5, 8, 13, 100] {
+            let entries = entries(count);
+            let snapshot = AllowListSnapshot::new(mint, entries.clone()).unwrap();
+            for entry in &entries {
+                let proof = snapshot.proof(&entry.user).unwrap();
+                assert!(
+                    proof.len() <= 7,
+                    "{} siblings for {} entries",
+                    proof.len(),
+                    count
+                );
+                assert!(
+                    verify_snapshot_proof(&snapshot.root(), entry.leaf(&mint), &proof),
+                    "proof of entry {} of {} does not verify",
+                    entry.user,
+                    count
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_proofs_do_not_transfer() {
+        let mint = Pubkey::new_unique();
+        let entries = entries(4);
+        let snapshot = AllowListSnapshot::new(mint, entries.clone()).unwrap();
+
+        assert!(snapshot.proof(&Pubkey::new_unique()).is_none());
+        let proof = snapshot.proof(&entries[0].user).unwrap();
+        assert!(!verify_snapshot_proof(
+            &snapshot.root(),
+            entries[1].leaf(&mint),
+            &proof
+        ));
+
+        let other_mint = AllowListSnapshot::new(Pubkey::new_unique(), entries.clone()).unwrap();
+        assert_ne!(other_mint.root(), snapshot.root());
+    }
+
+    #[test]
+    fn test_rejects_empty_and_duplicate_snapshots() {
+        let mint = Pubkey::new_unique();
+        assert_eq!(
+            AllowListSnapshot::new(mint, []).unwrap_err(),
+            SnapshotError::Empty
+        );
+
+        let mut entries = entries(3);
+        entries.push(SnapshotEntry {
+            access_level: AccessLevel::Institutional,
+            ..entries[1]
+        });
+        assert_eq!(
+            AllowListSnapshot::new(mint, entries.clone()).unwrap_err(),
+            SnapshotError::DuplicateUser(entries[1].user)
+        );
+    }
+
+    #[test]
+    fn test_materialize_layout() {
+        let (program_id, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let entries = entries(3);
+        let snapshot = AllowListSnapshot::new(mint, entries.clone()).unwrap();
+        let instruction = snapshot.materialize(&program_id, &entries[2].user).unwrap();
+
+        assert_eq!(instruction.data[0], MATERIALIZE_FROM_SNAPSHOT);
+        let user = &instruction.accounts[3];
+        assert_eq!(user.pubkey, entries[2].user);
+        assert!(user.is_signer && user.is_writable);
+        assert_eq!(
+            instruction.accounts[1].pubkey,
+            allow_list_address(&mint, &entries[2].user, &program_id).address()
+        );
+    }
+}
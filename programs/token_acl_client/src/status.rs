@@ -0,0 +1,412 @@
+//! Holder self-service status
+//!
+//! A holder whose transfer failed wants to know why without asking the
+//! issuer: are they on the allow list, at which tier and until when, are
+//! they blocked, and which of their token accounts are frozen.
+//! [`HolderStatus::query`] answers from the gates' records, decoded with
+//! [`gate_view`] so a record is only believed at its canonical PDA and owned
+//! by the gate, and from the owner's Token-2022 accounts of the mint.
+//! [`HolderStatus::render`] prints the answer the way the `token-acl status`
+//! command shows it.
+//!
+//! The queries go through a [`StatusSource`]: `RpcStatusSource` over
+//! JSON-RPC, or anything else that can serve accounts, as the tests do from
+//! an in-process bank. A wallet built for the browser, without the `rpc`
+//! feature, serves the accounts it already fetched.
+
+use std::fmt;
+
+use gate_view::{allow_list_address, block_list_address, AllowListView, BlockListView};
+#[cfg(feature = "rpc")]
+use solana_account_decoder::UiAccountEncoding;
+#[cfg(feature = "rpc")]
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_program::pubkey::Pubkey;
+#[cfg(feature = "rpc")]
+use solana_program::{clock::Clock, sysvar};
+#[cfg(feature = "rpc")]
+use solana_sdk::account::from_account;
+use spl_token_2022::{
+    extension::StateWithExtensions,
+    state::{Account as TokenAccount, AccountState},
+};
+
+/// Offset of the mint in a token account
+#[cfg(feature = "rpc")]
+const TOKEN_ACCOUNT_MINT_OFFSET: usize = 0;
+/// Offset of the owner in a token account
+#[cfg(feature = "rpc")]
+const TOKEN_ACCOUNT_OWNER_OFFSET: usize = 32;
+
+/// Why a status query failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatusError {
+    /// Reading from the cluster failed
+    Rpc(String),
+    /// The account at a record PDA is not a record of the owner for the
+    /// mint, or is not owned by the gate
+    InvalidRecord(Pubkey),
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatusError::Rpc(message) => write!(f, "RPC error: {}", message),
+            StatusError::InvalidRecord(address) => {
+                write!(f, "{} does not hold a record the gate wrote", address)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+/// What a status query reads
+#[allow(async_fn_in_trait)]
+pub trait StatusSource {
+    /// Owning program and data of the account at `address`, or `None` if
+    /// there is none
+    async fn account(&self, address: &Pubkey) -> Result<Option<(Pubkey, Vec<u8>)>, StatusError>;
+
+    /// Address and data of every Token-2022 account of `mint` owned by
+    /// `owner`
+    async fn token_accounts(
+        &self,
+        mint: &Pubkey,
+        owner: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Vec<u8>)>, StatusError>;
+}
+
+/// `getProgramAccounts` filters selecting the token accounts of `mint`
+/// owned by `owner`
+///
+/// Token-2022 accounts have no fixed size once they carry extensions, so
+/// there is no data size filter; whatever does not decode is skipped.
+#[cfg(feature = "rpc")]
+pub fn token_account_filters(mint: &Pubkey, owner: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            TOKEN_ACCOUNT_MINT_OFFSET,
+            mint.as_ref(),
+        )),
+        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            TOKEN_ACCOUNT_OWNER_OFFSET,
+            owner.as_ref(),
+        )),
+    ]
+}
+
+/// A cluster reached over JSON-RPC
+#[cfg(feature = "rpc")]
+pub struct RpcStatusSource {
+    rpc: RpcClient,
+}
+
+#[cfg(feature = "rpc")]
+impl RpcStatusSource {
+    pub fn new(url: &str) -> Self {
+        Self::from_client(RpcClient::new(url.to_string()))
+    }
+
+    pub fn from_client(rpc: RpcClient) -> Self {
+        Self { rpc }
+    }
+
+    /// The cluster's clock, which record expiries are judged by
+    pub async fn unix_timestamp(&self) -> Result<i64, StatusError> {
+        let account = self
+            .rpc
+            .get_account(&sysvar::clock::id())
+            .await
+            .map_err(rpc_error)?;
+        let clock: Clock = from_account(&account)
+            .ok_or_else(|| StatusError::Rpc("Malformed clock sysvar".to_string()))?;
+        Ok(clock.unix_timestamp)
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl StatusSource for RpcStatusSource {
+    async fn account(&self, address: &Pubkey) -> Result<Option<(Pubkey, Vec<u8>)>, StatusError> {
+        let response = self
+            .rpc
+            .get_account_with_commitment(address, self.rpc.commitment())
+            .await
+            .map_err(rpc_error)?;
+        Ok(response.value.map(|account| (account.owner, account.data)))
+    }
+
+    async fn token_accounts(
+        &self,
+        mint: &Pubkey,
+        owner: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Vec<u8>)>, StatusError> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(token_account_filters(mint, owner)),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(self.rpc.commitment()),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+        let accounts = self
+            .rpc
+            .get_program_accounts_with_config(&spl_token_2022::id(), config)
+            .await
+            .map_err(rpc_error)?;
+        Ok(accounts
+            .into_iter()
+            .map(|(address, account)| (address, account.data))
+            .collect())
+    }
+}
+
+#[cfg(feature = "rpc")]
+fn rpc_error(e: impl fmt::Display) -> StatusError {
+    StatusError::Rpc(e.to_string())
+}
+
+/// The gates a mint is guarded by; either may be absent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Gates {
+    /// A `production_allow_list` gate
+    pub allow_list: Option<Pubkey>,
+    /// An `example_block_list` gate
+    pub block_list: Option<Pubkey>,
+}
+
+/// One of the owner's token accounts of the mint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenAccountStatus {
+    pub address: Pubkey,
+    pub amount: u64,
+    pub frozen: bool,
+}
+
+/// Everything a holder can find out about themselves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HolderStatus {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub gates: Gates,
+    /// The owner's allow list record; `None` without one, or without an
+    /// allow list gate
+    pub allow_list: Option<AllowListView>,
+    /// The owner's block list record; `None` without one, or without a
+    /// block list gate
+    pub block_list: Option<BlockListView>,
+    pub token_accounts: Vec<TokenAccountStatus>,
+    /// Cluster time the records' expiries are judged at
+    pub unix_timestamp: i64,
+}
+
+impl HolderStatus {
+    /// Read the status of `owner` for `mint` at `unix_timestamp`
+    pub async fn query<S: StatusSource>(
+        source: &S,
+        gates: Gates,
+        mint: &Pubkey,
+        owner: &Pubkey,
+        unix_timestamp: i64,
+    ) -> Result<Self, StatusError> {
+        let allow_list = match gates.allow_list {
+            Some(gate) => {
+                let address = allow_list_address(&gate, mint, owner).address();
+                read_record(source, &gate, &address)
+                    .await?
+                    .map(|data| {
+                        AllowListView::unpack(&data)
+                            .ok()
+                            .filter(|view| view.mint == *mint && view.owner == *owner)
+                            .ok_or(StatusError::InvalidRecord(address))
+                    })
+                    .transpose()?
+            }
+            None => None,
+        };
+        let block_list = match gates.block_list {
+            Some(gate) => {
+                let address = block_list_address(&gate, mint, owner).address();
+                read_record(source, &gate, &address)
+                    .await?
+                    .map(|data| {
+                        BlockListView::unpack(&data)
+                            .ok()
+                            .filter(|view| view.mint == *mint && view.owner == *owner)
+                            .ok_or(StatusError::InvalidRecord(address))
+                    })
+                    .transpose()?
+            }
+            None => None,
+        };
+
+        let mut token_accounts: Vec<TokenAccountStatus> = source
+            .token_accounts(mint, owner)
+            .await?
+            .into_iter()
+            .filter_map(|(address, data)| {
+                let account = StateWithExtensions::<TokenAccount>::unpack(&data)
+                    .ok()?
+                    .base;
+                (account.mint == *mint && account.owner == *owner).then_some(TokenAccountStatus {
+                    address,
+                    amount: account.amount,
+                    frozen: account.state == AccountState::Frozen,
+                })
+            })
+            .collect();
+        token_accounts.sort_by_key(|account| account.address);
+
+        Ok(Self {
+            mint: *mint,
+            owner: *owner,
+            gates,
+            allow_list,
+            block_list,
+            token_accounts,
+            unix_timestamp,
+        })
+    }
+
+    /// Whether the allow list lets the owner in now; `None` without an
+    /// allow list gate
+    pub fn allow_listed(&self) -> Option<bool> {
+        self.gates.allow_list.map(|_| {
+            self.allow_list
+                .is_some_and(|view| view.is_active(self.unix_timestamp))
+        })
+    }
+
+    /// Whether a block is in force against the owner now; `None` without a
+    /// block list gate
+    pub fn blocked(&self) -> Option<bool> {
+        self.gates.block_list.map(|_| {
+            self.block_list
+                .is_some_and(|view| view.is_active(self.unix_timestamp))
+        })
+    }
+
+    /// The status as `token-acl status` prints it
+    pub fn render(&self) -> String {
+        let mut out = format!("Mint:  {}\nOwner: {}\n", self.mint, self.owner);
+        out.push_str(&format!("Allow list: {}\n", self.render_allow_list()));
+        out.push_str(&format!("Block list: {}\n", self.render_block_list()));
+        if self.token_accounts.is_empty() {
+            out.push_str("Token accounts: none\n");
+        } else {
+            out.push_str(&format!(
+                "Token accounts ({}):\n",
+                self.token_accounts.len()
+            ));
+            for account in &self.token_accounts {
+                out.push_str(&format!(
+                    "  {}  {}  {}\n",
+                    account.address,
+                    if account.frozen { "frozen" } else { "active" },
+                    account.amount
+                ));
+            }
+        }
+        out
+    }
+
+    fn render_allow_list(&self) -> String {
+        if self.gates.allow_list.is_none() {
+            return "not checked".to_string();
+        }
+        let Some(view) = self.allow_list else {
+            return "not listed".to_string();
+        };
+        let state = if view.revoked {
+            "revoked"
+        } else if !view.allowed {
+            "listed, not allowed"
+        } else if view.is_active(self.unix_timestamp) {
+            "allowed"
+        } else {
+            "expired"
+        };
+        let expiry = match view.expiry_timestamp {
+            Some(expiry) => format!("expires at {}", expiry),
+            None => "no expiry".to_string(),
+        };
+        format!(
+            "{} (tier {}, {})",
+            state,
+            tier_name(view.access_level),
+            expiry
+        )
+    }
+
+    fn render_block_list(&self) -> String {
+        if self.gates.block_list.is_none() {
+            return "not checked".to_string();
+        }
+        let Some(view) = self.block_list else {
+            return "not listed".to_string();
+        };
+        if !view.blocked {
+            return "not blocked".to_string();
+        }
+        let state = if view.is_active(self.unix_timestamp) {
+            "blocked"
+        } else {
+            "block expired"
+        };
+        match view.expiry_timestamp {
+            Some(expiry) => format!(
+                "{} ({}, expires at {})",
+                state,
+                reason_name(view.reason),
+                expiry
+            ),
+            None => format!("{} ({})", state, reason_name(view.reason)),
+        }
+    }
+}
+
+/// The data of the gate's record at `address`, or `None` while it is empty
+async fn read_record<S: StatusSource>(
+    source: &S,
+    gate: &Pubkey,
+    address: &Pubkey,
+) -> Result<Option<Vec<u8>>, StatusError> {
+    let Some((program, data)) = source.account(address).await? else {
+        return Ok(None);
+    };
+    // Anyone can fund the address, but only the gate can give it data
+    if data.is_empty() {
+        return Ok(None);
+    }
+    if program != *gate {
+        return Err(StatusError::InvalidRecord(*address));
+    }
+    Ok(Some(data))
+}
+
+/// Name of an allow list access level
+pub fn tier_name(access_level: u8) -> String {
+    match access_level {
+        0 => "none".to_string(),
+        1 => "basic".to_string(),
+        2 => "enhanced".to_string(),
+        3 => "institutional".to_string(),
+        level => format!("level {}", level),
+    }
+}
+
+/// Name of a block list reason
+pub fn reason_name(reason: u8) -> String {
+    match reason {
+        0 => "sanctions".to_string(),
+        1 => "compliance".to_string(),
+        2 => "risk assessment".to_string(),
+        3 => "other".to_string(),
+        reason => format!("reason {}", reason),
+    }
+}
@@ -0,0 +1,322 @@
+//! Transactions as they executed
+//!
+//! A transaction's logs are a flat list of `invoke`, `consumed` and `failed`
+//! lines that leave it to the reader to work out which instruction and which
+//! CPI did what. [`TransactionTrace`] rebuilds the tree: each instruction
+//! with its accounts and their signer/writable flags, and under it the
+//! program's logs, compute units and the CPIs it made, for
+//! [`crate::decoder`] to name.
+
+use solana_sdk::{
+    compute_budget,
+    instruction::CompiledInstruction,
+    message::v0::LoadedMessage,
+    pubkey::Pubkey,
+    signature::Signature,
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+use std::fmt;
+
+/// How a program invocation ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Success,
+    Failed(String),
+    /// The logs stop before the invocation ends, e.g. when truncated
+    Unfinished,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Success => write!(f, "success"),
+            Outcome::Failed(error) => write!(f, "failed: {}", error),
+            Outcome::Unfinished => write!(f, "unfinished"),
+        }
+    }
+}
+
+/// What happened inside an invocation, in order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A line the program logged, e.g. `Program log: Instruction: Add`
+    Log(String),
+    Cpi(Invocation),
+}
+
+/// One program invocation, with the logs and CPIs it made
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    pub program: Pubkey,
+    pub outcome: Outcome,
+    /// Units consumed and the limit they were consumed against
+    pub compute_units: Option<(u64, u64)>,
+    pub events: Vec<Event>,
+}
+
+impl Invocation {
+    fn new(program: Pubkey) -> Self {
+        Self {
+            program,
+            outcome: Outcome::Unfinished,
+            compute_units: None,
+            events: Vec::new(),
+        }
+    }
+
+    /// The CPIs this invocation made, in order
+    pub fn cpis(&self) -> impl Iterator<Item = &Invocation> {
+        self.events.iter().filter_map(|event| match event {
+            Event::Cpi(invocation) => Some(invocation),
+            Event::Log(_) => None,
+        })
+    }
+}
+
+/// An account as one instruction uses it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountUse {
+    pub address: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+/// One top-level instruction and, if it ran, its invocation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstructionTrace {
+    pub program: Pubkey,
+    pub accounts: Vec<AccountUse>,
+    pub data: Vec<u8>,
+    /// `None` for instructions after the one that failed
+    pub invocation: Option<Invocation>,
+}
+
+/// A transaction as it executed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionTrace {
+    pub signature: Signature,
+    pub fee_payer: Pubkey,
+    pub error: Option<String>,
+    /// Units the whole transaction consumed, if the bank reported them
+    pub compute_units: Option<u64>,
+    pub instructions: Vec<InstructionTrace>,
+    /// Log lines outside any invocation
+    pub logs: Vec<String>,
+}
+
+impl TransactionTrace {
+    /// Trace `transaction` from its result and the logs it produced
+    pub fn new(
+        transaction: &Transaction,
+        result: &Result<(), TransactionError>,
+        logs: &[String],
+        compute_units: Option<u64>,
+    ) -> Self {
+        let message = &transaction.message;
+        Self::from_compiled(
+            transaction.signatures.first().copied().unwrap_or_default(),
+            &message.account_keys,
+            &message.instructions,
+            |index| message.is_signer(index),
+            |index| message.is_writable(index),
+            result,
+            logs,
+            compute_units,
+        )
+    }
+
+    /// Trace a v0 transaction whose lookup table accounts were loaded
+    pub fn from_loaded(
+        signature: Signature,
+        message: &LoadedMessage,
+        result: &Result<(), TransactionError>,
+        logs: &[String],
+        compute_units: Option<u64>,
+    ) -> Self {
+        let account_keys: Vec<Pubkey> = message.account_keys().iter().copied().collect();
+        Self::from_compiled(
+            signature,
+            &account_keys,
+            &message.message.instructions,
+            |index| message.is_signer(index),
+            |index| message.is_writable(index),
+            result,
+            logs,
+            compute_units,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn from_compiled(
+        signature: Signature,
+        account_keys: &[Pubkey],
+        instructions: &[CompiledInstruction],
+        is_signer: impl Fn(usize) -> bool,
+        is_writable: impl Fn(usize) -> bool,
+        result: &Result<(), TransactionError>,
+        logs: &[String],
+        compute_units: Option<u64>,
+    ) -> Self {
+        let (mut invocations, stray) = parse_invocations(logs);
+        invocations.reverse();
+        let instructions = instructions
+            .iter()
+            .map(|instruction| InstructionTrace {
+                program: account_keys[usize::from(instruction.program_id_index)],
+                accounts: instruction
+                    .accounts
+                    .iter()
+                    .map(|index| {
+                        let index = usize::from(*index);
+                        AccountUse {
+                            address: account_keys[index],
+                            is_signer: is_signer(index),
+                            is_writable: is_writable(index),
+                        }
+                    })
+                    .collect(),
+                data: instruction.data.clone(),
+                invocation: invocations.pop(),
+            })
+            .collect();
+        Self {
+            signature,
+            fee_payer: account_keys.first().copied().unwrap_or_default(),
+            error: result.as_ref().err().map(ToString::to_string),
+            compute_units,
+            instructions,
+            logs: stray,
+        }
+    }
+
+    pub fn failed(&self) -> bool {
+        self.error.is_some()
+    }
+}
+
+/// The top-level invocations `logs` describe, one per instruction that ran,
+/// in order
+pub fn invocations(logs: &[String]) -> Vec<Invocation> {
+    parse_invocations(logs).0
+}
+
+/// Split `logs` into the top-level invocations they describe and the lines
+/// outside any invocation
+///
+/// Builtin programs under `solana-program-test` may log their `invoke` twice,
+/// the second time at a depth already on the stack, and their `success` or
+/// `failed` up to as many times; the repeats are folded into one invocation.
+fn parse_invocations(logs: &[String]) -> (Vec<Invocation>, Vec<String>) {
+    let mut roots = Vec::new();
+    let mut stray = Vec::new();
+    // Open invocations, with how many times each repeated its `invoke`
+    let mut stack: Vec<(Invocation, usize)> = Vec::new();
+    // The program closed last, and how many repeats of its closing line may
+    // still follow
+    let mut closed: Option<(Pubkey, usize)> = None;
+
+    for line in logs {
+        if let Some((program, event)) = program_event(line) {
+            if let Some(depth) = invoke_depth(event) {
+                let open = stack.len();
+                match stack.last_mut() {
+                    Some((top, repeats)) if top.program == program && depth <= open => {
+                        *repeats += 1
+                    }
+                    _ => stack.push((Invocation::new(program), 0)),
+                }
+                continue;
+            }
+            let outcome = if event == "success" {
+                Some(Outcome::Success)
+            } else {
+                event
+                    .strip_prefix("failed: ")
+                    .map(|error| Outcome::Failed(error.to_string()))
+            };
+            if let Some(outcome) = outcome {
+                let top = stack.last().map(|(top, _)| top.program);
+                match &mut closed {
+                    Some((last, repeats))
+                        if *last == program && *repeats > 0 && top != Some(program) =>
+                    {
+                        *repeats -= 1
+                    }
+                    _ => {
+                        closed = close(&mut stack, &mut roots, outcome);
+                    }
+                }
+                continue;
+            }
+            if let (Some(units), Some((top, _))) = (consumed(event), stack.last_mut()) {
+                top.compute_units = Some(units);
+                continue;
+            }
+        }
+        match stack.last_mut() {
+            Some((top, _)) => top.events.push(Event::Log(line.clone())),
+            None => stray.push(line.clone()),
+        }
+    }
+    while !stack.is_empty() {
+        close(&mut stack, &mut roots, Outcome::Unfinished);
+    }
+    (roots, stray)
+}
+
+/// End the innermost open invocation with `outcome`, returning its program
+/// and how many times it repeated its `invoke`
+fn close(
+    stack: &mut Vec<(Invocation, usize)>,
+    roots: &mut Vec<Invocation>,
+    outcome: Outcome,
+) -> Option<(Pubkey, usize)> {
+    let (mut invocation, repeats) = stack.pop()?;
+    invocation.outcome = outcome;
+    let program = invocation.program;
+    match stack.last_mut() {
+        Some((parent, _)) => parent.events.push(Event::Cpi(invocation)),
+        None => roots.push(invocation),
+    }
+    Some((program, repeats))
+}
+
+/// `invoke [<depth>]`
+fn invoke_depth(event: &str) -> Option<usize> {
+    event
+        .strip_prefix("invoke [")?
+        .strip_suffix(']')?
+        .parse()
+        .ok()
+}
+
+/// `Program <id> <event>` split into the program and the event
+fn program_event(line: &str) -> Option<(Pubkey, &str)> {
+    let (program, event) = line.strip_prefix("Program ")?.split_once(' ')?;
+    Some((program.parse().ok()?, event))
+}
+
+/// `consumed <units> of <limit> compute units`
+fn consumed(event: &str) -> Option<(u64, u64)> {
+    let (units, limit) = event
+        .strip_prefix("consumed ")?
+        .strip_suffix(" compute units")?
+        .split_once(" of ")?;
+    Some((units.parse().ok()?, limit.parse().ok()?))
+}
+
+/// The name of a well-known program, or the program's address
+pub fn program_name(program: &Pubkey) -> String {
+    if *program == system_program::id() {
+        "system_program".to_string()
+    } else if *program == spl_token_2022::id() {
+        "token_2022".to_string()
+    } else if *program == spl_associated_token_account::id() {
+        "associated_token".to_string()
+    } else if *program == compute_budget::id() {
+        "compute_budget".to_string()
+    } else {
+        program.to_string()
+    }
+}
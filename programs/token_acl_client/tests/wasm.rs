@@ -0,0 +1,129 @@
+//! Smoke test of the client in a browser-like WebAssembly runtime
+//!
+//! Wallets reuse the client's PDA derivation, discriminators and status
+//! preflight in the page instead of reimplementing them in JavaScript. This
+//! test builds the client without its `rpc` feature for
+//! `wasm32-unknown-unknown` and runs those paths under wasm-bindgen:
+//!
+//! ```sh
+//! cargo install wasm-bindgen-cli --version <wasm-bindgen version in Cargo.lock>
+//! CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER=wasm-bindgen-test-runner \
+//!     cargo test -p token_acl_client --no-default-features \
+//!     --target wasm32-unknown-unknown --test wasm
+//! ```
+
+#![cfg(target_arch = "wasm32")]
+
+use borsh::BorshSerialize;
+use production_allow_list::{AccessLevel, AllowListRecord};
+use solana_program::pubkey::Pubkey;
+use spl_discriminator::{ArrayDiscriminator, SplDiscriminate};
+use token_acl_client::{
+    instructions::{self, FreezeAuthority},
+    status::{Gates, HolderStatus, StatusError, StatusSource},
+};
+use token_acl_interface::{
+    self as interface, CanThawPermissionless, CanonicalPda,
+    CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR, CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+/// The accounts a wallet fetched for the holder, served from memory
+struct FetchedAccounts(Vec<(Pubkey, Pubkey, Vec<u8>)>);
+
+impl StatusSource for FetchedAccounts {
+    async fn account(&self, address: &Pubkey) -> Result<Option<(Pubkey, Vec<u8>)>, StatusError> {
+        Ok(self
+            .0
+            .iter()
+            .find(|(key, _, _)| key == address)
+            .map(|(_, owner, data)| (*owner, data.clone())))
+    }
+
+    async fn token_accounts(
+        &self,
+        _mint: &Pubkey,
+        _owner: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Vec<u8>)>, StatusError> {
+        Ok(Vec::new())
+    }
+}
+
+#[wasm_bindgen_test]
+fn discriminators_match_the_spec() {
+    assert_eq!(
+        CanThawPermissionless::SPL_DISCRIMINATOR,
+        ArrayDiscriminator::new(CAN_THAW_PERMISSIONLESS_DISCRIMINATOR)
+    );
+    assert_ne!(
+        CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+        CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR
+    );
+}
+
+#[wasm_bindgen_test]
+fn pdas_derive_canonically() {
+    let (gate, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (address, bump) = interface::find_thaw_extra_account_metas_address(&mint, &gate);
+    let pda = CanonicalPda::find(
+        &[interface::THAW_EXTRA_ACCOUNT_METAS_SEED, mint.as_ref()],
+        &gate,
+    );
+    assert_eq!((pda.address(), pda.bump()), (address, bump));
+    assert!(pda.verify(&address).is_ok());
+    assert!(!address.is_on_curve());
+}
+
+#[wasm_bindgen_test]
+fn thaw_builds_with_its_check() {
+    let (gate, mint, token_account, owner) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let check = instructions::permissionless_thaw(&mint, &token_account, &owner, &gate);
+    assert_eq!(check.data, CAN_THAW_PERMISSIONLESS_DISCRIMINATOR);
+    let check = instructions::with_extra_accounts(check, &[owner]);
+    let [check, thaw] = instructions::authorized(check, &FreezeAuthority::Key(owner)).unwrap();
+    assert_eq!(check.program_id, gate);
+    assert_eq!(thaw.program_id, spl_token_2022::id());
+}
+
+#[wasm_bindgen_test]
+async fn status_preflight_reads_records() {
+    let (gate, mint, owner) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let address = production_allow_list::allow_list_address(&mint, &owner, &gate);
+    let record = AllowListRecord {
+        mint,
+        user: owner,
+        allowed: true,
+        access_level: AccessLevel::Basic,
+        added_timestamp: 0,
+        expiry_timestamp: Some(1_000),
+        bump: address.bump(),
+        revoked: false,
+    };
+    let source = FetchedAccounts(vec![(
+        address.address(),
+        gate,
+        record.try_to_vec().unwrap(),
+    )]);
+    let gates = Gates {
+        allow_list: Some(gate),
+        block_list: None,
+    };
+
+    let at_expiry = HolderStatus::query(&source, gates, &mint, &owner, 1_000)
+        .await
+        .unwrap();
+    assert_eq!(at_expiry.allow_listed(), Some(true));
+    let after_expiry = HolderStatus::query(&source, gates, &mint, &owner, 1_001)
+        .await
+        .unwrap();
+    assert_eq!(after_expiry.allow_listed(), Some(false));
+}
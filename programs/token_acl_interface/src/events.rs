@@ -0,0 +1,214 @@
+//! Gate decision events
+//!
+//! A gate's `msg!` lines say why it decided as it did, but only to a reader
+//! who parses this repository's English. A gate that also logs its
+//! `can-thaw-permissionless` decision as a [`ThawDecision`] with
+//! `sol_log_data` gives indexers a structured record instead: the runtime
+//! writes it to the transaction's logs as a `Program data:` line of base64
+//! fields, which is kept whether the check approved or denied.
+//!
+//! The event is laid out as borsh serializes it: mint and owner (32 bytes
+//! each), `approved` (one byte), then the [`DecisionReason`] (one byte).
+//! It is logged in one of two formats:
+//!
+//! - [`EventFormat::Native`]: two fields, [`THAW_DECISION_EVENT_TAG`] then
+//!   the event
+//! - [`EventFormat::Anchor`]: one field, [`ThawDecision::ANCHOR_DISCRIMINATOR`]
+//!   followed by the event. This is what Anchor's `emit!` logs for an
+//!   `#[event] pub struct ThawDecision` with the same fields, so Anchor's
+//!   event parser and IDL clients decode it as one of their own events.
+//!
+//! Anchor's other convention, `emit_cpi!`, carries the event in a CPI the
+//! program makes to itself, signed by an event authority PDA. A gate only
+//! gets the accounts its extra-account-metas list names, so it is not
+//! offered.
+
+use solana_program::{log::sol_log_data, pubkey::Pubkey};
+
+/// First field of a [`EventFormat::Native`] thaw decision
+pub const THAW_DECISION_EVENT_TAG: &[u8] = b"token-acl:thaw-decision";
+
+/// How a gate lays out the events it logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventFormat {
+    /// Tagged with [`THAW_DECISION_EVENT_TAG`] in a field of its own
+    Native,
+    /// Prefixed with the discriminator Anchor gives the event
+    Anchor,
+}
+
+/// Why a gate decided a `can-thaw-permissionless` check as it did
+///
+/// Stored as a single byte.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DecisionReason {
+    /// The owner's record lets them in
+    Listed = 0,
+    /// The owner has no record, and the mint's missing-record policy thaws
+    /// them anyway
+    MissingRecordAllowed = 1,
+    /// The owner has no record, and the policy denies them
+    NotListed = 2,
+    /// The owner's record is revoked
+    Revoked = 3,
+    /// The owner's record does not allow them
+    NotAllowed = 4,
+    /// The owner's record has expired
+    Expired = 5,
+    /// The owner has no record, and the mint holds holders without one for
+    /// a reviewer's decision
+    PendingReview = 6,
+}
+
+impl DecisionReason {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Listed),
+            1 => Some(Self::MissingRecordAllowed),
+            2 => Some(Self::NotListed),
+            3 => Some(Self::Revoked),
+            4 => Some(Self::NotAllowed),
+            5 => Some(Self::Expired),
+            6 => Some(Self::PendingReview),
+            _ => None,
+        }
+    }
+
+    /// Whether the check passes for this reason
+    pub fn approves(&self) -> bool {
+        matches!(self, Self::Listed | Self::MissingRecordAllowed)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Listed => "listed",
+            Self::MissingRecordAllowed => "missing_record_allowed",
+            Self::NotListed => "not_listed",
+            Self::Revoked => "revoked",
+            Self::NotAllowed => "not_allowed",
+            Self::Expired => "expired",
+            Self::PendingReview => "pending_review",
+        }
+    }
+}
+
+/// A gate's `can-thaw-permissionless` decision about a token account owner
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThawDecision {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub approved: bool,
+    pub reason: DecisionReason,
+}
+
+impl ThawDecision {
+    pub const LEN: usize = 32 + 32 + 1 + 1;
+
+    /// First 8 bytes of `sha256("event:ThawDecision")`, the discriminator
+    /// Anchor derives for the event
+    pub const ANCHOR_DISCRIMINATOR: [u8; 8] = [10, 132, 222, 245, 6, 51, 252, 22];
+
+    pub fn new(mint: Pubkey, owner: Pubkey, reason: DecisionReason) -> Self {
+        Self {
+            mint,
+            owner,
+            approved: reason.approves(),
+            reason,
+        }
+    }
+
+    pub fn pack(&self) -> [u8; Self::LEN] {
+        let mut data = [0; Self::LEN];
+        data[..32].copy_from_slice(self.mint.as_ref());
+        data[32..64].copy_from_slice(self.owner.as_ref());
+        data[64] = self.approved as u8;
+        data[65] = self.reason as u8;
+        data
+    }
+
+    /// The event in `data`, which must be exactly [`ThawDecision::LEN`]
+    /// bytes
+    pub fn unpack(data: &[u8]) -> Option<Self> {
+        if data.len() != Self::LEN {
+            return None;
+        }
+        let approved = match data[64] {
+            0 => false,
+            1 => true,
+            _ => return None,
+        };
+        Some(Self {
+            mint: Pubkey::try_from(&data[..32]).ok()?,
+            owner: Pubkey::try_from(&data[32..64]).ok()?,
+            approved,
+            reason: DecisionReason::from_u8(data[65])?,
+        })
+    }
+
+    /// The fields the event is logged as in `format`
+    pub fn log_fields(&self, format: EventFormat) -> Vec<Vec<u8>> {
+        match format {
+            EventFormat::Native => vec![THAW_DECISION_EVENT_TAG.to_vec(), self.pack().to_vec()],
+            EventFormat::Anchor => vec![[
+                Self::ANCHOR_DISCRIMINATOR.as_slice(),
+                self.pack().as_slice(),
+            ]
+            .concat()],
+        }
+    }
+
+    /// Log the event in `format` with `sol_log_data`
+    pub fn emit(&self, format: EventFormat) {
+        let fields = self.log_fields(format);
+        let fields: Vec<&[u8]> = fields.iter().map(Vec::as_slice).collect();
+        sol_log_data(&fields);
+    }
+
+    /// The event logged as `fields`, in whichever format it was logged;
+    /// `None` for anything else a program logged with `sol_log_data`
+    pub fn from_log_fields<T: AsRef<[u8]>>(fields: &[T]) -> Option<(EventFormat, Self)> {
+        match fields {
+            [tag, event] if tag.as_ref() == THAW_DECISION_EVENT_TAG => {
+                Some((EventFormat::Native, Self::unpack(event.as_ref())?))
+            }
+            [data] => {
+                let event = data.as_ref().strip_prefix(&Self::ANCHOR_DISCRIMINATOR)?;
+                Some((EventFormat::Anchor, Self::unpack(event)?))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::hash::hash;
+
+    #[test]
+    fn test_anchor_discriminator() {
+        assert_eq!(
+            ThawDecision::ANCHOR_DISCRIMINATOR,
+            hash(b"event:ThawDecision").to_bytes()[..8]
+        );
+    }
+
+    #[test]
+    fn test_decision_round_trip() {
+        let decision = ThawDecision::new(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            DecisionReason::Revoked,
+        );
+        assert!(!decision.approved);
+        for format in [EventFormat::Native, EventFormat::Anchor] {
+            assert_eq!(
+                ThawDecision::from_log_fields(&decision.log_fields(format)),
+                Some((format, decision))
+            );
+        }
+        assert_eq!(ThawDecision::unpack(&decision.pack()[1..]), None);
+        assert_eq!(ThawDecision::from_log_fields(&[b"other".as_slice()]), None);
+    }
+}
@@ -0,0 +1,1028 @@
+//! sRFC 37 gate program interface
+//!
+//! Discriminators, seeds and CPI helpers shared by everything that calls a
+//! gate program: FAMP implementations invoking `can-thaw-permissionless` /
+//! `can-freeze-permissionless`, and composite gates fanning out to member
+//! gates.
+//!
+//! The helpers are the one place the de-escalated account metas are built.
+//! Every account is passed to the gate read-only and without the signer flag,
+//! whatever privileges it carries in the calling instruction, so a gate can
+//! only approve or deny; it can never move funds or write state on behalf of
+//! the caller. The helpers only accept [`ReadonlyMeta`] / [`ReadonlyAccount`],
+//! so passing an escalated account is a compile error rather than a review
+//! finding.
+//!
+//! Off-chain callers (keeper bots, wallets) complete an instruction with
+//! [`resolve_extra_account_metas`], which reads the gate's extra-account-metas
+//! list and appends the accounts it resolves to, de-escalated the same way.
+//! The crate depends on nothing beyond `solana-program` and the SPL TLV
+//! crates, so it also builds for `wasm32-unknown-unknown` and browser
+//! wallets derive the same PDAs and discriminators as the programs.
+//!
+//! Gate PDAs (configs, records, extra-account-metas) are only ever derived
+//! through [`CanonicalPda`], so every address these helpers hand out uses the
+//! canonical bump, and programs can reject any other bump with
+//! [`CanonicalPda::verify`].
+//!
+//! # Missing records
+//!
+//! A gate decides on the record it keeps for the token account's owner, and
+//! a holder it has never heard of has no record. What that means is the
+//! mint's [`MissingRecordPolicy`], stored in the gate's policy PDA
+//! (`[MISSING_RECORD_POLICY_SEED, mint]`) and passed to
+//! `can-thaw-permissionless` after the gate's own extra accounts:
+//!
+//! - [`MissingRecordPolicy::Deny`]: a holder without a record cannot be
+//!   thawed permissionlessly. The default of allow lists.
+//! - [`MissingRecordPolicy::Allow`]: a holder without a record is thawed. The
+//!   default of block lists.
+//! - [`MissingRecordPolicy::Review`]: a holder without a record is denied
+//!   until a reviewer decides on them, for gates that keep a review queue.
+//!   The holder asks for review through the gate, and the reviewer's
+//!   decision creates their record.
+//!
+//! A gate whose policy PDA was never written uses its default, so issuers
+//! only pay for the account when they invert it. The policy is only ever
+//! consulted for a missing record: a record that exists decides on its own
+//! contents, and `can-freeze-permissionless` is never authorized by the
+//! absence of a record, whatever the policy.
+//!
+//! # Interface versions
+//!
+//! A gate that changes what it expects from callers (which extra accounts,
+//! in which order) advertises a [`GateInterfaceVersion`] entry in its
+//! extra-account-metas account: an interface version and a bitmap of the
+//! features a caller must understand. The CPI helpers and
+//! [`resolve_extra_account_metas`] check it before calling or resolving
+//! anything, so a caller built against another version fails with a
+//! [`GateVersionError`] instead of handing the gate accounts it would
+//! misread. A gate without the entry (or without an extra-account-metas
+//! account) speaks [`GateInterfaceVersion::LEGACY`].
+//!
+//! # Decision events
+//!
+//! Gates that log their decisions for indexers do so as the events of
+//! [`events`], in this crate's own layout or in Anchor's.
+
+use std::future::Future;
+
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction, system_program,
+    sysvar::{Sysvar, SysvarId},
+};
+use spl_discriminator::{ArrayDiscriminator, SplDiscriminate};
+use spl_tlv_account_resolution::state::{AccountDataResult, ExtraAccountMetaList};
+use spl_type_length_value::state::{TlvState, TlvStateBorrowed};
+
+pub mod events;
+
+/// `can-thaw-permissionless` instruction discriminator
+pub const CAN_THAW_PERMISSIONLESS_DISCRIMINATOR: [u8; 8] = [8, 175, 169, 129, 137, 74, 61, 241];
+
+/// `can-freeze-permissionless` instruction discriminator
+pub const CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR: [u8; 8] = [214, 141, 109, 75, 248, 1, 45, 29];
+
+/// Seed of the thaw extra-account-metas PDA, followed by the mint
+pub const THAW_EXTRA_ACCOUNT_METAS_SEED: &[u8] = b"thaw-extra-account-metas";
+
+/// Seed of the freeze extra-account-metas PDA, followed by the mint
+pub const FREEZE_EXTRA_ACCOUNT_METAS_SEED: &[u8] = b"freeze-extra-account-metas";
+
+/// Seed of a gate's missing-record policy PDA, followed by the mint
+pub const MISSING_RECORD_POLICY_SEED: &[u8] = b"missing-record-policy";
+
+/// TLV entry type of the `can-thaw-permissionless` extra-account-metas list
+pub struct CanThawPermissionless;
+
+impl SplDiscriminate for CanThawPermissionless {
+    const SPL_DISCRIMINATOR: ArrayDiscriminator =
+        ArrayDiscriminator::new(CAN_THAW_PERMISSIONLESS_DISCRIMINATOR);
+}
+
+/// TLV entry type of the `can-freeze-permissionless` extra-account-metas list
+pub struct CanFreezePermissionless;
+
+impl SplDiscriminate for CanFreezePermissionless {
+    const SPL_DISCRIMINATOR: ArrayDiscriminator =
+        ArrayDiscriminator::new(CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR);
+}
+
+/// A program-derived address with its canonical bump
+///
+/// Only [`CanonicalPda::find`] builds one, so the address is always the one
+/// `find_program_address` returns: the highest bump that lands off the curve.
+/// The same seeds with a lower bump can also produce a valid PDA, which would
+/// let a second account stand in for the first; programs close that door by
+/// checking incoming accounts with [`CanonicalPda::verify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CanonicalPda {
+    address: Pubkey,
+    bump: u8,
+}
+
+impl CanonicalPda {
+    pub fn find(seeds: &[&[u8]], program_id: &Pubkey) -> Self {
+        let (address, bump) = Pubkey::find_program_address(seeds, program_id);
+        Self { address, bump }
+    }
+
+    pub fn address(&self) -> Pubkey {
+        self.address
+    }
+
+    pub fn bump(&self) -> u8 {
+        self.bump
+    }
+
+    /// Fail with `InvalidSeeds` unless `key` is this canonical address
+    pub fn verify(&self, key: &Pubkey) -> ProgramResult {
+        if *key != self.address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(())
+    }
+}
+
+impl From<CanonicalPda> for (Pubkey, u8) {
+    fn from(pda: CanonicalPda) -> Self {
+        (pda.address, pda.bump)
+    }
+}
+
+/// Thaw extra-account-metas PDA of `mint` under `gate_program_id`
+pub fn find_thaw_extra_account_metas_address(
+    mint: &Pubkey,
+    gate_program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    CanonicalPda::find(
+        &[THAW_EXTRA_ACCOUNT_METAS_SEED, mint.as_ref()],
+        gate_program_id,
+    )
+    .into()
+}
+
+/// Freeze extra-account-metas PDA of `mint` under `gate_program_id`
+pub fn find_freeze_extra_account_metas_address(
+    mint: &Pubkey,
+    gate_program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    CanonicalPda::find(
+        &[FREEZE_EXTRA_ACCOUNT_METAS_SEED, mint.as_ref()],
+        gate_program_id,
+    )
+    .into()
+}
+
+/// Missing-record policy PDA of `mint` under `gate_program_id`
+pub fn find_missing_record_policy_address(mint: &Pubkey, gate_program_id: &Pubkey) -> (Pubkey, u8) {
+    CanonicalPda::find(
+        &[MISSING_RECORD_POLICY_SEED, mint.as_ref()],
+        gate_program_id,
+    )
+    .into()
+}
+
+/// How a gate treats a holder it has no record for
+///
+/// See the [crate docs](crate#missing-records). Stored as a single byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MissingRecordPolicy {
+    Deny = 0,
+    Allow = 1,
+    Review = 2,
+}
+
+impl MissingRecordPolicy {
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        match data {
+            [0] => Ok(Self::Deny),
+            [1] => Ok(Self::Allow),
+            [2] => Ok(Self::Review),
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+
+    /// Whether a holder without a record may be thawed
+    pub fn permits_thaw(self) -> bool {
+        self == Self::Allow
+    }
+
+    /// The policy of `mint` held in `policy_account`, or `default` if the
+    /// issuer never set one
+    ///
+    /// The account must be the canonical policy PDA, and once written it must
+    /// be owned by the gate.
+    pub fn load(
+        gate_program_id: &Pubkey,
+        policy_account: &AccountInfo,
+        mint: &Pubkey,
+        default: Self,
+    ) -> Result<Self, ProgramError> {
+        CanonicalPda::find(
+            &[MISSING_RECORD_POLICY_SEED, mint.as_ref()],
+            gate_program_id,
+        )
+        .verify(policy_account.key)?;
+        if policy_account.data_is_empty() {
+            return Ok(default);
+        }
+        if policy_account.owner != gate_program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        Self::unpack(&policy_account.try_borrow_data()?)
+    }
+
+    /// Write this policy into the policy PDA of `mint`, creating it if needed
+    ///
+    /// For the gate's own admin instruction, which has already checked that
+    /// the signer may set the mint's policy.
+    pub fn store<'a>(
+        self,
+        gate_program_id: &Pubkey,
+        policy_account: &AccountInfo<'a>,
+        mint: &Pubkey,
+        payer: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        check_system_program(system_program)?;
+        let pda = CanonicalPda::find(
+            &[MISSING_RECORD_POLICY_SEED, mint.as_ref()],
+            gate_program_id,
+        );
+        pda.verify(policy_account.key)?;
+
+        if policy_account.data_is_empty() {
+            invoke_signed(
+                &system_instruction::create_account(
+                    payer.key,
+                    policy_account.key,
+                    Rent::get()?.minimum_balance(1),
+                    1,
+                    gate_program_id,
+                ),
+                &[
+                    payer.clone(),
+                    policy_account.clone(),
+                    system_program.clone(),
+                ],
+                &[&[MISSING_RECORD_POLICY_SEED, mint.as_ref(), &[pda.bump()]]],
+            )?;
+        } else if policy_account.owner != gate_program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        policy_account.try_borrow_mut_data()?[0] = self as u8;
+        Ok(())
+    }
+}
+
+/// Feature bit: `can-thaw-permissionless` takes the missing-record policy
+/// PDA after the gate's own extra accounts
+pub const FEATURE_MISSING_RECORD_POLICY: u32 = 1 << 0;
+
+/// Feature bit: the extra-account-metas list is versioned and a call resolved
+/// against a stale list fails with `IncorrectAccount`; callers re-resolve
+pub const FEATURE_VERSIONED_EXTRA_ACCOUNT_METAS: u32 = 1 << 1;
+
+/// Interface version and features a gate requires of its callers
+///
+/// Stored as a TLV entry in the gate's extra-account-metas account: the
+/// version byte, then the feature bitmap (u32 LE).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GateInterfaceVersion {
+    pub version: u8,
+    pub features: u32,
+}
+
+impl SplDiscriminate for GateInterfaceVersion {
+    const SPL_DISCRIMINATOR: ArrayDiscriminator =
+        ArrayDiscriminator::new([104, 221, 162, 226, 176, 155, 166, 194]);
+}
+
+impl GateInterfaceVersion {
+    pub const LEN: usize = 5;
+
+    /// What a gate that advertises no version speaks: the original sRFC 37
+    /// layout
+    pub const LEGACY: Self = Self {
+        version: 1,
+        features: 0,
+    };
+
+    /// What this crate speaks
+    pub const CURRENT: Self = Self {
+        version: 1,
+        features: FEATURE_MISSING_RECORD_POLICY | FEATURE_VERSIONED_EXTRA_ACCOUNT_METAS,
+    };
+
+    pub fn pack(&self) -> [u8; Self::LEN] {
+        let mut data = [0; Self::LEN];
+        data[0] = self.version;
+        data[1..].copy_from_slice(&self.features.to_le_bytes());
+        data
+    }
+
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        let data: [u8; Self::LEN] = data
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        let mut features = [0; 4];
+        features.copy_from_slice(&data[1..]);
+        Ok(Self {
+            version: data[0],
+            features: u32::from_le_bytes(features),
+        })
+    }
+
+    /// The version advertised in extra-account-metas `data`, or
+    /// [`Self::LEGACY`] if there is no entry
+    pub fn from_extra_account_metas(data: &[u8]) -> Result<Self, ProgramError> {
+        let state = TlvStateBorrowed::unpack(data)?;
+        if !state
+            .get_discriminators()?
+            .contains(&Self::SPL_DISCRIMINATOR)
+        {
+            return Ok(Self::LEGACY);
+        }
+        Self::unpack(state.get_first_bytes::<Self>()?)
+    }
+
+    /// The version `gate_program_id` advertises through `extra_account_metas`
+    ///
+    /// An account the gate does not own (typically one that does not exist)
+    /// advertises nothing, so the gate is [`Self::LEGACY`].
+    pub fn read(
+        gate_program_id: &Pubkey,
+        extra_account_metas: &AccountInfo,
+    ) -> Result<Self, ProgramError> {
+        if extra_account_metas.owner != gate_program_id || extra_account_metas.data_is_empty() {
+            return Ok(Self::LEGACY);
+        }
+        Self::from_extra_account_metas(&extra_account_metas.try_borrow_data()?)
+    }
+
+    /// Whether a caller speaking `self` may call a gate advertising `gate`
+    ///
+    /// Versions must match exactly: each version fixes the account layout,
+    /// so an older or newer gate would misread the caller's accounts. Every
+    /// feature the gate requires must be one the caller knows.
+    pub fn check_gate(&self, gate: &Self) -> Result<(), GateVersionError> {
+        if gate.version > self.version {
+            return Err(GateVersionError::GateTooNew);
+        }
+        if gate.version < self.version {
+            return Err(GateVersionError::GateTooOld);
+        }
+        if gate.features & !self.features != 0 {
+            return Err(GateVersionError::UnsupportedFeatures);
+        }
+        Ok(())
+    }
+}
+
+/// Why a caller refused to call a gate
+///
+/// Surfaced as `ProgramError::Custom` with the discriminant as the code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum GateVersionError {
+    /// The gate speaks a newer interface version than the caller
+    GateTooNew = 0x7E50_0001,
+    /// The gate speaks an older interface version than the caller
+    GateTooOld = 0x7E50_0002,
+    /// The gate requires a feature the caller does not know
+    UnsupportedFeatures = 0x7E50_0003,
+}
+
+impl From<GateVersionError> for ProgramError {
+    fn from(error: GateVersionError) -> Self {
+        ProgramError::Custom(error as u32)
+    }
+}
+
+/// Fail unless a caller speaking `caller` may call `gate_program` through
+/// `extra_account_metas`
+///
+/// The CPI helpers run this with [`GateInterfaceVersion::CURRENT`]; FAMP
+/// implementations that build their own CPI call it first.
+pub fn check_gate_interface_version(
+    caller: &GateInterfaceVersion,
+    gate_program: &AccountInfo,
+    extra_account_metas: &AccountInfo,
+) -> ProgramResult {
+    let gate = GateInterfaceVersion::read(gate_program.key, extra_account_metas)?;
+    caller.check_gate(&gate).map_err(|e| {
+        msg!(
+            "Gate {} speaks interface version {} (features {:#x}), caller speaks {} (features {:#x}): {:?}",
+            gate_program.key,
+            gate.version,
+            gate.features,
+            caller.version,
+            caller.features,
+            e
+        );
+        e.into()
+    })
+}
+
+/// An account passed to a gate program: read-only and never a signer
+///
+/// There is deliberately no way to build one from an [`AccountMeta`] or to
+/// set its flags, so gate instructions cannot be given escalated accounts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReadonlyMeta(Pubkey);
+
+impl ReadonlyMeta {
+    pub fn new(pubkey: Pubkey) -> Self {
+        Self(pubkey)
+    }
+
+    pub fn pubkey(&self) -> &Pubkey {
+        &self.0
+    }
+}
+
+impl From<Pubkey> for ReadonlyMeta {
+    fn from(pubkey: Pubkey) -> Self {
+        Self(pubkey)
+    }
+}
+
+impl From<&Pubkey> for ReadonlyMeta {
+    fn from(pubkey: &Pubkey) -> Self {
+        Self(*pubkey)
+    }
+}
+
+impl From<ReadonlyMeta> for AccountMeta {
+    fn from(meta: ReadonlyMeta) -> Self {
+        AccountMeta::new_readonly(meta.0, false)
+    }
+}
+
+/// An account handed to a gate program in a CPI
+///
+/// Wraps a copy of the caller's [`AccountInfo`] with the signer and writable
+/// flags cleared, so the gate sees the account exactly as the CPI passes it.
+#[derive(Debug, Clone)]
+pub struct ReadonlyAccount<'a>(AccountInfo<'a>);
+
+impl<'a> ReadonlyAccount<'a> {
+    pub fn new(account: &AccountInfo<'a>) -> Self {
+        let mut account = account.clone();
+        account.is_signer = false;
+        account.is_writable = false;
+        Self(account)
+    }
+
+    pub fn meta(&self) -> ReadonlyMeta {
+        ReadonlyMeta(*self.0.key)
+    }
+
+    pub fn info(&self) -> &AccountInfo<'a> {
+        &self.0
+    }
+}
+
+impl<'a> From<&AccountInfo<'a>> for ReadonlyAccount<'a> {
+    fn from(account: &AccountInfo<'a>) -> Self {
+        Self::new(account)
+    }
+}
+
+/// De-escalate a slice of accounts, e.g. the extra accounts a FAMP resolved
+pub fn readonly_accounts<'a>(accounts: &[AccountInfo<'a>]) -> Vec<ReadonlyAccount<'a>> {
+    accounts.iter().map(ReadonlyAccount::new).collect()
+}
+
+/// Fail with `IncorrectProgramId` unless `account` is the system program
+///
+/// Admin instructions take the system program to create and grow accounts;
+/// checking it up front turns a spoofed program into a clear error instead
+/// of a CPI that fails deep in the runtime.
+pub fn check_system_program(account: &AccountInfo) -> ProgramResult {
+    if !system_program::check_id(account.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(())
+}
+
+/// Fail with `InvalidArgument` unless `account` is the sysvar `S`
+///
+/// For instructions that take `Rent` or `Clock` as an account rather than
+/// reading them through `Sysvar::get`, which cannot be spoofed.
+pub fn check_sysvar<S: SysvarId>(account: &AccountInfo) -> ProgramResult {
+    if !S::check_id(account.key) {
+        return Err(ProgramError::InvalidArgument);
+    }
+    Ok(())
+}
+
+/// Overwrite `account`'s data with `data`, which must be exactly as long
+///
+/// A gate writing a record into an account of another size, e.g. one
+/// allocated for an earlier layout, fails with `InvalidAccountData` instead
+/// of panicking in `copy_from_slice`, and an account already borrowed fails
+/// with `AccountBorrowFailed`.
+pub fn store_account_data(account: &AccountInfo, data: &[u8]) -> ProgramResult {
+    let mut account_data = account.try_borrow_mut_data()?;
+    if account_data.len() != data.len() {
+        msg!(
+            "Account {} holds {} bytes, expected {}",
+            account.key,
+            account_data.len(),
+            data.len()
+        );
+        return Err(ProgramError::InvalidAccountData);
+    }
+    account_data.copy_from_slice(data);
+    Ok(())
+}
+
+/// Build a `can-thaw-permissionless` instruction
+///
+/// Accounts:
+/// 0. caller
+/// 1. token account
+/// 2. mint
+/// 3. thaw extra-account-metas
+/// 4. ... extra accounts, in the order the extra-account-metas resolve them
+pub fn can_thaw_permissionless(
+    gate_program_id: &Pubkey,
+    caller: impl Into<ReadonlyMeta>,
+    token_account: impl Into<ReadonlyMeta>,
+    mint: impl Into<ReadonlyMeta>,
+    extra_account_metas: impl Into<ReadonlyMeta>,
+    extra_accounts: &[ReadonlyMeta],
+) -> Instruction {
+    permissionless_instruction(
+        gate_program_id,
+        CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+        [
+            caller.into(),
+            token_account.into(),
+            mint.into(),
+            extra_account_metas.into(),
+        ],
+        extra_accounts,
+    )
+}
+
+/// Build a `can-freeze-permissionless` instruction
+///
+/// Same account layout as [`can_thaw_permissionless`], with the freeze
+/// extra-account-metas.
+pub fn can_freeze_permissionless(
+    gate_program_id: &Pubkey,
+    caller: impl Into<ReadonlyMeta>,
+    token_account: impl Into<ReadonlyMeta>,
+    mint: impl Into<ReadonlyMeta>,
+    extra_account_metas: impl Into<ReadonlyMeta>,
+    extra_accounts: &[ReadonlyMeta],
+) -> Instruction {
+    permissionless_instruction(
+        gate_program_id,
+        CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR,
+        [
+            caller.into(),
+            token_account.into(),
+            mint.into(),
+            extra_account_metas.into(),
+        ],
+        extra_accounts,
+    )
+}
+
+/// CPI into `gate_program`'s `can-thaw-permissionless`
+///
+/// The gate sees every account de-escalated, even if `caller` signed the
+/// outer transaction or `token_account` is writable in it. Returns the gate's
+/// error when it denies the thaw, and a [`GateVersionError`] without calling
+/// it when the gate speaks another interface version.
+pub fn invoke_can_thaw_permissionless<'a>(
+    gate_program: &AccountInfo<'a>,
+    caller: impl Into<ReadonlyAccount<'a>>,
+    token_account: impl Into<ReadonlyAccount<'a>>,
+    mint: impl Into<ReadonlyAccount<'a>>,
+    extra_account_metas: impl Into<ReadonlyAccount<'a>>,
+    extra_accounts: &[ReadonlyAccount<'a>],
+) -> ProgramResult {
+    invoke_permissionless(
+        gate_program,
+        CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+        [
+            caller.into(),
+            token_account.into(),
+            mint.into(),
+            extra_account_metas.into(),
+        ],
+        extra_accounts,
+    )
+}
+
+/// CPI into `gate_program`'s `can-freeze-permissionless`
+///
+/// See [`invoke_can_thaw_permissionless`].
+pub fn invoke_can_freeze_permissionless<'a>(
+    gate_program: &AccountInfo<'a>,
+    caller: impl Into<ReadonlyAccount<'a>>,
+    token_account: impl Into<ReadonlyAccount<'a>>,
+    mint: impl Into<ReadonlyAccount<'a>>,
+    extra_account_metas: impl Into<ReadonlyAccount<'a>>,
+    extra_accounts: &[ReadonlyAccount<'a>],
+) -> ProgramResult {
+    invoke_permissionless(
+        gate_program,
+        CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR,
+        [
+            caller.into(),
+            token_account.into(),
+            mint.into(),
+            extra_account_metas.into(),
+        ],
+        extra_accounts,
+    )
+}
+
+/// Append the extra accounts a gate's extra-account-metas list resolves to
+///
+/// `instruction` is a `can-thaw-permissionless` or `can-freeze-permissionless`
+/// instruction without extra accounts, as built by [`can_thaw_permissionless`]
+/// / [`can_freeze_permissionless`]. `fetch_account_data` returns an account's
+/// data, or `None` if it does not exist; it is called for the
+/// extra-account-metas account and for every account a seed reads from.
+///
+/// Resolved accounts are appended read-only and non-signer, whatever the list
+/// declares. Fails with `UninitializedAccount` if the gate has no list for
+/// this mint, and with a [`GateVersionError`] if the list advertises another
+/// interface version.
+pub async fn resolve_extra_account_metas<F, Fut>(
+    instruction: &mut Instruction,
+    fetch_account_data: F,
+) -> Result<(), ProgramError>
+where
+    F: Fn(Pubkey) -> Fut,
+    Fut: Future<Output = AccountDataResult>,
+{
+    let extra_account_metas = instruction
+        .accounts
+        .get(3)
+        .ok_or(ProgramError::NotEnoughAccountKeys)?
+        .pubkey;
+    let list = fetch_account_data(extra_account_metas)
+        .await
+        .map_err(|_| ProgramError::UninitializedAccount)?
+        .ok_or(ProgramError::UninitializedAccount)?;
+    let gate = GateInterfaceVersion::from_extra_account_metas(&list)?;
+    GateInterfaceVersion::CURRENT.check_gate(&gate)?;
+
+    let base_len = instruction.accounts.len();
+    match instruction.data.get(..8) {
+        Some(d) if d == CAN_THAW_PERMISSIONLESS_DISCRIMINATOR => {
+            ExtraAccountMetaList::add_to_instruction::<CanThawPermissionless, _, _>(
+                instruction,
+                fetch_account_data,
+                &list,
+            )
+            .await?
+        }
+        Some(d) if d == CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR => {
+            ExtraAccountMetaList::add_to_instruction::<CanFreezePermissionless, _, _>(
+                instruction,
+                fetch_account_data,
+                &list,
+            )
+            .await?
+        }
+        _ => return Err(ProgramError::InvalidInstructionData),
+    }
+
+    for meta in &mut instruction.accounts[base_len..] {
+        *meta = ReadonlyMeta::new(meta.pubkey).into();
+    }
+    Ok(())
+}
+
+fn permissionless_instruction(
+    gate_program_id: &Pubkey,
+    discriminator: [u8; 8],
+    base_accounts: [ReadonlyMeta; 4],
+    extra_accounts: &[ReadonlyMeta],
+) -> Instruction {
+    let accounts = base_accounts
+        .into_iter()
+        .chain(extra_accounts.iter().copied())
+        .map(AccountMeta::from)
+        .collect();
+    Instruction::new_with_bytes(*gate_program_id, &discriminator, accounts)
+}
+
+fn invoke_permissionless<'a>(
+    gate_program: &AccountInfo<'a>,
+    discriminator: [u8; 8],
+    base_accounts: [ReadonlyAccount<'a>; 4],
+    extra_accounts: &[ReadonlyAccount<'a>],
+) -> ProgramResult {
+    check_gate_interface_version(
+        &GateInterfaceVersion::CURRENT,
+        gate_program,
+        base_accounts[3].info(),
+    )?;
+    let accounts: Vec<&ReadonlyAccount<'a>> = base_accounts.iter().chain(extra_accounts).collect();
+    let metas: Vec<ReadonlyMeta> = accounts.iter().map(|account| account.meta()).collect();
+    let instruction = permissionless_instruction(
+        gate_program.key,
+        discriminator,
+        [metas[0], metas[1], metas[2], metas[3]],
+        &metas[4..],
+    );
+    let infos: Vec<AccountInfo<'a>> = accounts
+        .into_iter()
+        .map(|account| account.info().clone())
+        .chain([gate_program.clone()])
+        .collect();
+    invoke(&instruction, &infos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_de_escalated(instruction: &Instruction) {
+        for meta in &instruction.accounts {
+            assert!(!meta.is_signer, "{} is a signer", meta.pubkey);
+            assert!(!meta.is_writable, "{} is writable", meta.pubkey);
+        }
+    }
+
+    #[test]
+    fn test_tlv_entry_types() {
+        assert_eq!(
+            CanThawPermissionless::SPL_DISCRIMINATOR_SLICE,
+            CAN_THAW_PERMISSIONLESS_DISCRIMINATOR
+        );
+        assert_eq!(
+            CanFreezePermissionless::SPL_DISCRIMINATOR_SLICE,
+            CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR
+        );
+    }
+
+    #[test]
+    fn test_thaw_instruction_layout() {
+        let gate = Pubkey::new_unique();
+        let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+        let extras: Vec<ReadonlyMeta> = keys[4..].iter().map(ReadonlyMeta::from).collect();
+        let instruction =
+            can_thaw_permissionless(&gate, keys[0], keys[1], keys[2], keys[3], &extras);
+
+        assert_eq!(instruction.program_id, gate);
+        assert_eq!(instruction.data, CAN_THAW_PERMISSIONLESS_DISCRIMINATOR);
+        let order: Vec<Pubkey> = instruction.accounts.iter().map(|m| m.pubkey).collect();
+        assert_eq!(order, keys);
+        assert_de_escalated(&instruction);
+    }
+
+    #[test]
+    fn test_freeze_instruction_layout() {
+        let gate = Pubkey::new_unique();
+        let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let instruction = can_freeze_permissionless(&gate, keys[0], keys[1], keys[2], keys[3], &[]);
+
+        assert_eq!(instruction.data, CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR);
+        assert_eq!(instruction.accounts.len(), 4);
+        assert_de_escalated(&instruction);
+    }
+
+    #[test]
+    fn test_readonly_account_clears_flags() {
+        let key = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = [];
+        let account =
+            AccountInfo::new(&key, true, true, &mut lamports, &mut data, &owner, false, 0);
+
+        let readonly = ReadonlyAccount::from(&account);
+        assert!(!readonly.info().is_signer);
+        assert!(!readonly.info().is_writable);
+        assert_eq!(readonly.meta().pubkey(), &key);
+        assert_eq!(
+            AccountMeta::from(readonly.meta()),
+            AccountMeta::new_readonly(key, false)
+        );
+    }
+
+    #[test]
+    fn test_extra_account_metas_addresses() {
+        let gate = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let (thaw, _) = find_thaw_extra_account_metas_address(&mint, &gate);
+        let (freeze, _) = find_freeze_extra_account_metas_address(&mint, &gate);
+
+        assert_eq!(
+            thaw,
+            Pubkey::find_program_address(&[b"thaw-extra-account-metas", mint.as_ref()], &gate).0
+        );
+        assert_ne!(thaw, freeze);
+    }
+
+    #[test]
+    fn test_canonical_pda_rejects_lower_bumps() {
+        let gate = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let seeds: &[&[u8]] = &[THAW_EXTRA_ACCOUNT_METAS_SEED, mint.as_ref()];
+        let pda = CanonicalPda::find(seeds, &gate);
+
+        assert_eq!(
+            (pda.address(), pda.bump()),
+            find_thaw_extra_account_metas_address(&mint, &gate)
+        );
+        assert!(pda.verify(&pda.address()).is_ok());
+
+        // Every other valid bump derives a different address, and none verify
+        let mut lower_bumps = 0;
+        for bump in 0..pda.bump() {
+            if let Ok(address) =
+                Pubkey::create_program_address(&[seeds[0], seeds[1], &[bump]], &gate)
+            {
+                lower_bumps += 1;
+                assert_eq!(pda.verify(&address), Err(ProgramError::InvalidSeeds));
+            }
+        }
+        assert!(lower_bumps > 0, "expected at least one non-canonical bump");
+    }
+
+    #[test]
+    fn test_system_program_and_sysvar_checks() {
+        use solana_program::{clock::Clock, rent::Rent, sysvar};
+
+        let system = system_program::id();
+        let rent = sysvar::rent::id();
+        let clock = sysvar::clock::id();
+        let spoofed = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = [];
+        let mut account = AccountInfo::new(
+            &system,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &owner,
+            false,
+            0,
+        );
+
+        assert!(check_system_program(&account).is_ok());
+        account.key = &spoofed;
+        assert_eq!(
+            check_system_program(&account),
+            Err(ProgramError::IncorrectProgramId)
+        );
+
+        account.key = &rent;
+        assert!(check_sysvar::<Rent>(&account).is_ok());
+        assert_eq!(
+            check_sysvar::<Clock>(&account),
+            Err(ProgramError::InvalidArgument)
+        );
+        account.key = &clock;
+        assert!(check_sysvar::<Clock>(&account).is_ok());
+        for key in [&spoofed, &clock, &system] {
+            account.key = key;
+            assert_eq!(
+                check_sysvar::<Rent>(&account),
+                Err(ProgramError::InvalidArgument)
+            );
+        }
+    }
+
+    #[test]
+    fn test_missing_record_policy_load() {
+        let gate = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let (address, _) = find_missing_record_policy_address(&mint, &gate);
+        let mut lamports = 0;
+        let mut data = vec![];
+        let mut account = AccountInfo::new(
+            &address,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &gate,
+            false,
+            0,
+        );
+
+        // Never written: the gate's default
+        for default in [MissingRecordPolicy::Deny, MissingRecordPolicy::Allow] {
+            assert_eq!(
+                MissingRecordPolicy::load(&gate, &account, &mint, default),
+                Ok(default)
+            );
+        }
+
+        let mut written = [MissingRecordPolicy::Allow as u8];
+        account.data = std::rc::Rc::new(std::cell::RefCell::new(&mut written[..]));
+        assert_eq!(
+            MissingRecordPolicy::load(&gate, &account, &mint, MissingRecordPolicy::Deny),
+            Ok(MissingRecordPolicy::Allow)
+        );
+
+        let impostor = Pubkey::new_unique();
+        account.owner = &impostor;
+        assert_eq!(
+            MissingRecordPolicy::load(&gate, &account, &mint, MissingRecordPolicy::Deny),
+            Err(ProgramError::IncorrectProgramId)
+        );
+
+        let other_mint = Pubkey::new_unique();
+        assert_eq!(
+            MissingRecordPolicy::load(&gate, &account, &other_mint, MissingRecordPolicy::Deny),
+            Err(ProgramError::InvalidSeeds)
+        );
+        assert_eq!(
+            MissingRecordPolicy::unpack(&[2]),
+            Ok(MissingRecordPolicy::Review)
+        );
+        assert!(!MissingRecordPolicy::Review.permits_thaw());
+        assert_eq!(
+            MissingRecordPolicy::unpack(&[3]),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn test_gate_interface_version_negotiation() {
+        let current = GateInterfaceVersion::CURRENT;
+        assert_eq!(
+            GateInterfaceVersion::unpack(&current.pack()).unwrap(),
+            current
+        );
+        assert!(current.check_gate(&current).is_ok());
+        assert!(current.check_gate(&GateInterfaceVersion::LEGACY).is_ok());
+
+        let newer = GateInterfaceVersion {
+            version: current.version + 1,
+            features: 0,
+        };
+        assert_eq!(
+            current.check_gate(&newer),
+            Err(GateVersionError::GateTooNew)
+        );
+        assert_eq!(
+            newer.check_gate(&current),
+            Err(GateVersionError::GateTooOld)
+        );
+
+        // A legacy caller cannot call a gate that needs the policy account
+        assert_eq!(
+            GateInterfaceVersion::LEGACY.check_gate(&current),
+            Err(GateVersionError::UnsupportedFeatures)
+        );
+        assert_eq!(
+            ProgramError::from(GateVersionError::GateTooNew),
+            ProgramError::Custom(0x7E50_0001)
+        );
+    }
+
+    #[test]
+    fn test_gate_interface_version_in_extra_account_metas() {
+        let metas = [];
+        let list_size = ExtraAccountMetaList::size_of(0).unwrap();
+        let mut legacy = vec![0; list_size];
+        ExtraAccountMetaList::init::<CanThawPermissionless>(&mut legacy, &metas).unwrap();
+        assert_eq!(
+            GateInterfaceVersion::from_extra_account_metas(&legacy).unwrap(),
+            GateInterfaceVersion::LEGACY
+        );
+
+        let entry_size = TlvStateBorrowed::get_base_len() + GateInterfaceVersion::LEN;
+        let mut versioned = vec![0; entry_size + list_size];
+        {
+            let mut state =
+                spl_type_length_value::state::TlvStateMut::unpack(&mut versioned).unwrap();
+            let (value, _) = state
+                .alloc::<GateInterfaceVersion>(GateInterfaceVersion::LEN, false)
+                .unwrap();
+            value.copy_from_slice(&GateInterfaceVersion::CURRENT.pack());
+        }
+        ExtraAccountMetaList::init::<CanThawPermissionless>(&mut versioned, &metas).unwrap();
+        assert_eq!(
+            GateInterfaceVersion::from_extra_account_metas(&versioned).unwrap(),
+            GateInterfaceVersion::CURRENT
+        );
+    }
+}
@@ -0,0 +1,608 @@
+// `entrypoint!` expands to cfgs only known to the BPF toolchain.
+#![allow(unexpected_cfgs)]
+
+use borsh::{BorshDeserialize, BorshSerialize};
+/**
+ * Velocity Gate Program
+ *
+ * Rate-limits permissionless thaws per holder following the sRFC 37
+ * interface specification. The per-mint config allows at most `max_thaws`
+ * thaws per holder in any `window_seconds`, across all of the holder's
+ * token accounts, so a leaked or abused thaw path releases a bounded number
+ * of accounts a day instead of all of them.
+ *
+ * A gate only ever sees its accounts read-only, so the thaw check cannot
+ * count thaws itself. Each thaw is instead preceded by RECORD_THAW, which
+ * the holder signs: it appends the thaw to the holder's ledger, a ring
+ * buffer PDA of recent thaw timestamps, and fails once the window is full.
+ * The thaw check reads the instructions sysvar and only succeeds directly
+ * after a RECORD_THAW for the same token account, so every thaw is counted
+ * exactly once. Two transactions recording for one holder both write the
+ * ledger and are therefore never executed in parallel.
+ *
+ * Instructions:
+ * - INITIALIZE: create the config of a mint with its limit and window
+ * - RECORD_THAW: count a thaw against the holder's limit
+ * - can-thaw-permissionless: succeeds directly after a RECORD_THAW for the
+ *   token account
+ * - can-freeze-permissionless: not supported
+ */
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::{instructions, Sysvar},
+};
+use token_acl_interface::{
+    check_system_program, store_account_data, CanonicalPda,
+    CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR, CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+};
+
+// Instruction discriminators
+pub const INITIALIZE: u8 = 0;
+pub const RECORD_THAW: u8 = 1;
+
+// Seeds
+pub const CONFIG_SEED: &[u8] = b"velocity-config";
+pub const LEDGER_SEED: &[u8] = b"thaw-ledger";
+
+/// Thaws a ledger remembers, and so the highest limit a config can set
+pub const LEDGER_CAPACITY: usize = 16;
+
+/// Position of the token account among RECORD_THAW's accounts
+const RECORD_THAW_TOKEN_ACCOUNT_INDEX: usize = 3;
+
+// SPL Token account layout: mint (0..32), owner (32..64), ...
+const TOKEN_ACCOUNT_OWNER_OFFSET: usize = 32;
+
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
+
+/// Per-mint configuration: how many thaws a holder gets per window
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct VelocityConfig {
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    /// Thaws a holder may make in any window, 1 to `LEDGER_CAPACITY`
+    pub max_thaws: u8,
+    /// Length of the sliding window in seconds, at least 1
+    pub window_seconds: u32,
+    pub bump: u8,
+}
+
+impl VelocityConfig {
+    pub fn validate(&self) -> Result<(), ProgramError> {
+        if self.max_thaws == 0 || self.max_thaws as usize > LEDGER_CAPACITY {
+            msg!(
+                "A limit of {} thaws is not between 1 and {}",
+                self.max_thaws,
+                LEDGER_CAPACITY
+            );
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        if self.window_seconds == 0 {
+            msg!("The window must be at least one second");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(())
+    }
+}
+
+/// A holder's recent thaws of one mint, as a ring buffer of timestamps
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct ThawLedger {
+    pub mint: Pubkey,
+    pub user: Pubkey,
+    /// Slot the next thaw is written to, overwriting the oldest once full
+    pub next: u8,
+    /// Slots filled so far, up to `LEDGER_CAPACITY`
+    pub len: u8,
+    pub timestamps: [i64; LEDGER_CAPACITY],
+    pub bump: u8,
+}
+
+impl ThawLedger {
+    pub const LEN: usize = 32 + 32 + 1 + 1 + 8 * LEDGER_CAPACITY + 1;
+
+    pub fn new(mint: Pubkey, user: Pubkey, bump: u8) -> Self {
+        Self {
+            mint,
+            user,
+            next: 0,
+            len: 0,
+            timestamps: [0; LEDGER_CAPACITY],
+            bump,
+        }
+    }
+
+    /// Thaws recorded less than `window_seconds` before `now`
+    pub fn thaws_within(&self, now: i64, window_seconds: u32) -> usize {
+        let cutoff = now.saturating_sub(i64::from(window_seconds));
+        self.timestamps[..self.len as usize]
+            .iter()
+            .filter(|&&timestamp| timestamp > cutoff)
+            .count()
+    }
+
+    /// Record a thaw at `now` if the holder has one left in the window
+    ///
+    /// The ledger keeps the last `LEDGER_CAPACITY` thaws, which always
+    /// include the last `max_thaws`, so overwriting the oldest never loses
+    /// one that still counts.
+    pub fn record(&mut self, now: i64, config: &VelocityConfig) -> Result<(), VelocityDenial> {
+        let recent = self.thaws_within(now, config.window_seconds);
+        if recent >= config.max_thaws as usize {
+            return Err(VelocityDenial { recent });
+        }
+        let slot = self.next as usize % LEDGER_CAPACITY;
+        self.timestamps[slot] = now;
+        self.next = ((slot + 1) % LEDGER_CAPACITY) as u8;
+        self.len = (self.len as usize + 1).min(LEDGER_CAPACITY) as u8;
+        Ok(())
+    }
+}
+
+/// Why a thaw was not recorded: the holder already thawed `recent` times in
+/// the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VelocityDenial {
+    pub recent: usize,
+}
+
+/// Config PDA of `mint`
+pub fn config_address(mint: &Pubkey, program_id: &Pubkey) -> CanonicalPda {
+    CanonicalPda::find(&[CONFIG_SEED, mint.as_ref()], program_id)
+}
+
+/// Thaw ledger PDA of `user` for `mint`
+pub fn ledger_address(mint: &Pubkey, user: &Pubkey, program_id: &Pubkey) -> CanonicalPda {
+    CanonicalPda::find(&[LEDGER_SEED, mint.as_ref(), user.as_ref()], program_id)
+}
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    match instruction_data {
+        [INITIALIZE, max_thaws, window @ ..] => {
+            let window_seconds = window
+                .try_into()
+                .map(u32::from_le_bytes)
+                .map_err(|_| ProgramError::InvalidInstructionData)?;
+            process_initialize(program_id, accounts, *max_thaws, window_seconds)
+        }
+        [RECORD_THAW] => process_record_thaw(program_id, accounts),
+        d if d == CAN_THAW_PERMISSIONLESS_DISCRIMINATOR => {
+            process_can_thaw_permissionless(program_id, accounts)
+        }
+        d if d == CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR => {
+            // Only thaws are rate-limited
+            msg!("Permissionless freeze not supported by the velocity gate");
+            Err(ProgramError::InvalidInstructionData)
+        }
+        _ => {
+            msg!("Unknown instruction");
+            Err(ProgramError::InvalidInstructionData)
+        }
+    }
+}
+
+/// Initialize the config of a mint
+///
+/// Data: the thaw limit (u8), then the window in seconds (u32,
+/// little-endian).
+///
+/// Accounts:
+/// 0. config PDA (writable)
+/// 1. mint
+/// 2. authority (signer)
+/// 3. payer (writable, signer)
+/// 4. system program
+fn process_initialize(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    max_thaws: u8,
+    window_seconds: u32,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let authority = next_account_info(account_info_iter)?;
+    let payer = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    check_system_program(system_program)?;
+
+    let config_pda = config_address(mint.key, program_id);
+    if *config_account.key != config_pda.address() {
+        msg!("Invalid config PDA");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let config = VelocityConfig {
+        authority: *authority.key,
+        mint: *mint.key,
+        max_thaws,
+        window_seconds,
+        bump: config_pda.bump(),
+    };
+    config.validate()?;
+
+    let config_data = config.try_to_vec()?;
+    let rent = Rent::get()?;
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            config_account.key,
+            rent.minimum_balance(config_data.len()),
+            config_data.len() as u64,
+            program_id,
+        ),
+        &[
+            payer.clone(),
+            config_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[CONFIG_SEED, mint.key.as_ref(), &[config.bump]]],
+    )?;
+
+    store_account_data(config_account, &config_data)?;
+
+    msg!(
+        "Velocity gate initialized for mint {}: {} thaws per {} seconds",
+        mint.key,
+        config.max_thaws,
+        config.window_seconds
+    );
+    Ok(())
+}
+
+/// The config of `mint`, checked to be the canonical PDA owned by this
+/// program
+fn load_config(
+    program_id: &Pubkey,
+    config_account: &AccountInfo,
+    mint: &Pubkey,
+) -> Result<VelocityConfig, ProgramError> {
+    if *config_account.key != config_address(mint, program_id).address()
+        || config_account.owner != program_id
+        || config_account.data_is_empty()
+    {
+        msg!("Invalid velocity gate config");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(VelocityConfig::try_from_slice(
+        &config_account.try_borrow_data()?,
+    )?)
+}
+
+/// Fail unless `token_account` holds `mint` and belongs to `owner`
+fn check_token_account_owner(
+    token_account: &AccountInfo,
+    mint: &Pubkey,
+    owner: &Pubkey,
+) -> ProgramResult {
+    let data = token_account.try_borrow_data()?;
+    let bound = data.len() >= TOKEN_ACCOUNT_OWNER_OFFSET + 32
+        && data[..32] == mint.to_bytes()
+        && data[TOKEN_ACCOUNT_OWNER_OFFSET..TOKEN_ACCOUNT_OWNER_OFFSET + 32] == owner.to_bytes();
+    if !bound {
+        msg!(
+            "Token account {} is not owned by {} for this mint",
+            token_account.key,
+            owner
+        );
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+/// Count a thaw of `token_account` against its owner's limit, creating the
+/// owner's ledger on their first thaw
+///
+/// The owner signs, so no one else can use up their thaws.
+///
+/// Accounts:
+/// 0. config
+/// 1. ledger PDA (writable)
+/// 2. mint
+/// 3. token account
+/// 4. token account owner (signer)
+/// 5. payer (writable, signer)
+/// 6. system program
+fn process_record_thaw(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let config_account = next_account_info(account_info_iter)?;
+    let ledger_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let token_account = next_account_info(account_info_iter)?;
+    let owner = next_account_info(account_info_iter)?;
+    let payer = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    check_system_program(system_program)?;
+
+    let config = load_config(program_id, config_account, mint.key)?;
+    check_token_account_owner(token_account, mint.key, owner.key)?;
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let ledger_pda = ledger_address(mint.key, owner.key, program_id);
+    if *ledger_account.key != ledger_pda.address() {
+        msg!("Invalid thaw ledger PDA");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let mut ledger = if ledger_account.data_is_empty() {
+        let rent = Rent::get()?;
+        invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                ledger_account.key,
+                rent.minimum_balance(ThawLedger::LEN),
+                ThawLedger::LEN as u64,
+                program_id,
+            ),
+            &[
+                payer.clone(),
+                ledger_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                LEDGER_SEED,
+                mint.key.as_ref(),
+                owner.key.as_ref(),
+                &[ledger_pda.bump()],
+            ]],
+        )?;
+        ThawLedger::new(*mint.key, *owner.key, ledger_pda.bump())
+    } else {
+        if ledger_account.owner != program_id {
+            msg!("Thaw ledger not owned by the velocity gate");
+            return Err(ProgramError::IllegalOwner);
+        }
+        ThawLedger::try_from_slice(&ledger_account.try_borrow_data()?)?
+    };
+
+    let now = Clock::get()?.unix_timestamp;
+    if let Err(denial) = ledger.record(now, &config) {
+        msg!(
+            "❌ User {} thawed {} times in the last {} seconds, the limit is {} - thaw not recorded",
+            owner.key,
+            denial.recent,
+            config.window_seconds,
+            config.max_thaws
+        );
+        return Err(ProgramError::InvalidAccountData);
+    }
+    store_account_data(ledger_account, &ledger.try_to_vec()?)?;
+
+    msg!(
+        "Thaw of {} recorded for user {}: {} of {} in the window",
+        token_account.key,
+        owner.key,
+        ledger.thaws_within(now, config.window_seconds),
+        config.max_thaws
+    );
+    Ok(())
+}
+
+/// Whether the top-level instruction before the current one recorded a thaw
+/// of `token_account` in `ledger` with this program
+///
+/// Only the directly preceding instruction counts, so one RECORD_THAW can
+/// never authorize two thaws.
+fn thaw_recorded(
+    program_id: &Pubkey,
+    instructions_sysvar: &AccountInfo,
+    ledger: &Pubkey,
+    token_account: &Pubkey,
+) -> Result<bool, ProgramError> {
+    let current = instructions::load_current_index_checked(instructions_sysvar)?;
+    let Some(previous) = current.checked_sub(1) else {
+        return Ok(false);
+    };
+    let record = instructions::load_instruction_at_checked(previous as usize, instructions_sysvar)?;
+    Ok(record.program_id == *program_id
+        && record.data == [RECORD_THAW]
+        && record.accounts.get(1).map(|meta| meta.pubkey) == Some(*ledger)
+        && record
+            .accounts
+            .get(RECORD_THAW_TOKEN_ACCOUNT_INDEX)
+            .map(|meta| meta.pubkey)
+            == Some(*token_account))
+}
+
+/// sRFC 37 Interface: Can thaw permissionless
+fn process_can_thaw_permissionless(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    // Accounts as per sRFC 37 interface:
+    // 0. caller
+    // 1. token account
+    // 2. mint
+    // 3. extra-account-metas
+    // Extra accounts:
+    // 4. token account owner
+    // 5. velocity config PDA
+    // 6. owner's thaw ledger PDA
+    // 7. instructions sysvar
+
+    let _caller = next_account_info(account_info_iter)?;
+    let token_account = next_account_info(account_info_iter)?;
+    let mint = next_account_info(account_info_iter)?;
+    let _extra_account_metas = next_account_info(account_info_iter)?;
+    let token_account_owner = next_account_info(account_info_iter)?;
+    let config_account = next_account_info(account_info_iter)?;
+    let ledger_account = next_account_info(account_info_iter)?;
+    let instructions_sysvar = next_account_info(account_info_iter)?;
+
+    check_token_account_owner(token_account, mint.key, token_account_owner.key)?;
+    load_config(program_id, config_account, mint.key)?;
+
+    let user = token_account_owner.key;
+    if *ledger_account.key != ledger_address(mint.key, user, program_id).address() {
+        msg!("Invalid thaw ledger PDA");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if !thaw_recorded(
+        program_id,
+        instructions_sysvar,
+        ledger_account.key,
+        token_account.key,
+    )? {
+        msg!(
+            "❌ No thaw of {} recorded for user {} directly before - permissionless thaw denied",
+            token_account.key,
+            user
+        );
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    msg!(
+        "✅ Thaw of {} recorded within user {}'s limit - permissionless thaw authorized",
+        token_account.key,
+        user
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_thaws: u8, window_seconds: u32) -> VelocityConfig {
+        VelocityConfig {
+            authority: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            max_thaws,
+            window_seconds,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn test_limit_within_window() {
+        let config = config(3, 100);
+        let mut ledger = ThawLedger::new(config.mint, Pubkey::new_unique(), 254);
+
+        for now in [1_000, 1_010, 1_020] {
+            assert_eq!(ledger.record(now, &config), Ok(()));
+        }
+        assert_eq!(
+            ledger.record(1_099, &config),
+            Err(VelocityDenial { recent: 3 })
+        );
+
+        // The first thaw leaves the window after exactly 100 seconds
+        assert_eq!(ledger.thaws_within(1_100, 100), 2);
+        assert_eq!(ledger.record(1_100, &config), Ok(()));
+        assert_eq!(
+            ledger.record(1_100, &config),
+            Err(VelocityDenial { recent: 3 })
+        );
+    }
+
+    #[test]
+    fn test_ring_buffer_wraps() {
+        let config = config(LEDGER_CAPACITY as u8, 10);
+        let mut ledger = ThawLedger::new(config.mint, Pubkey::new_unique(), 254);
+
+        // Well over the capacity, each thaw in a window of its own
+        for i in 0..(3 * LEDGER_CAPACITY as i64) {
+            assert_eq!(ledger.record(i * 10, &config), Ok(()));
+        }
+        assert_eq!(ledger.len as usize, LEDGER_CAPACITY);
+        assert_eq!(ledger.next, 0);
+
+        let now = 1_000;
+        for _ in 0..LEDGER_CAPACITY {
+            assert_eq!(ledger.record(now, &config), Ok(()));
+        }
+        assert_eq!(
+            ledger.record(now, &config),
+            Err(VelocityDenial {
+                recent: LEDGER_CAPACITY
+            })
+        );
+    }
+
+    #[test]
+    fn test_config_validation() {
+        assert_eq!(config(1, 1).validate(), Ok(()));
+        assert_eq!(config(LEDGER_CAPACITY as u8, 86_400).validate(), Ok(()));
+        for invalid in [
+            config(0, 86_400),
+            config(LEDGER_CAPACITY as u8 + 1, 86_400),
+            config(3, 0),
+        ] {
+            assert_eq!(
+                invalid.validate(),
+                Err(ProgramError::InvalidInstructionData),
+                "{:?}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_ledger_roundtrip() {
+        let mut ledger = ThawLedger::new(Pubkey::new_unique(), Pubkey::new_unique(), 253);
+        ledger.record(42, &config(3, 60)).unwrap();
+        let data = ledger.try_to_vec().unwrap();
+        assert_eq!(data.len(), ThawLedger::LEN);
+        assert_eq!(ThawLedger::try_from_slice(&data).unwrap(), ledger);
+    }
+
+    #[test]
+    fn test_token_account_owner_binding() {
+        let (key, mint, owner) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let token_program = Pubkey::new_unique();
+        let check = |data: &mut [u8], owner: &Pubkey| {
+            let mut lamports = 0;
+            let account = AccountInfo::new(
+                &key,
+                false,
+                false,
+                &mut lamports,
+                data,
+                &token_program,
+                false,
+                0,
+            );
+            check_token_account_owner(&account, &mint, owner)
+        };
+
+        let mut data = [0; 165];
+        data[..32].copy_from_slice(mint.as_ref());
+        data[32..64].copy_from_slice(owner.as_ref());
+        assert_eq!(check(&mut data, &owner), Ok(()));
+        assert_eq!(
+            check(&mut data, &Pubkey::new_unique()),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        // A closed or never-created token account binds no one
+        assert_eq!(
+            check(&mut [], &owner),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+}
@@ -134,6 +134,90 @@ else
 fi
 cd ../..
 
+# Build jurisdiction registry program
+echo ""
+print_status "Building jurisdiction registry program..."
+cd programs/jurisdiction_registry
+if cargo build $BUILD_MODE $CARGO_FLAGS; then
+    print_success "Jurisdiction registry program built successfully"
+else
+    print_error "Jurisdiction registry program build failed"
+    exit 1
+fi
+cd ../..
+
+# Build hybrid gate program
+echo ""
+print_status "Building hybrid gate program..."
+cd programs/hybrid_gate
+if cargo build $BUILD_MODE $CARGO_FLAGS; then
+    print_success "Hybrid gate program built successfully"
+else
+    print_error "Hybrid gate program build failed"
+    exit 1
+fi
+cd ../..
+
+# Build threshold gate program
+echo ""
+print_status "Building threshold gate program..."
+cd programs/threshold_gate
+if cargo build $BUILD_MODE $CARGO_FLAGS; then
+    print_success "Threshold gate program built successfully"
+else
+    print_error "Threshold gate program build failed"
+    exit 1
+fi
+cd ../..
+
+# Build velocity gate program
+echo ""
+print_status "Building velocity gate program..."
+cd programs/velocity_gate
+if cargo build $BUILD_MODE $CARGO_FLAGS; then
+    print_success "Velocity gate program built successfully"
+else
+    print_error "Velocity gate program build failed"
+    exit 1
+fi
+cd ../..
+
+# Build geo-blocking gate program
+echo ""
+print_status "Building geo-blocking gate program..."
+cd programs/geo_gate
+if cargo build $BUILD_MODE $CARGO_FLAGS; then
+    print_success "Geo-blocking gate program built successfully"
+else
+    print_error "Geo-blocking gate program build failed"
+    exit 1
+fi
+cd ../..
+
+# Build NFT gate program
+echo ""
+print_status "Building NFT gate program..."
+cd programs/nft_gate
+if cargo build $BUILD_MODE $CARGO_FLAGS; then
+    print_success "NFT gate program built successfully"
+else
+    print_error "NFT gate program build failed"
+    exit 1
+fi
+cd ../..
+
+# Build example lending consumer program
+echo ""
+print_status "Building example lending consumer program..."
+cd examples/lending_consumer
+if cargo build $BUILD_MODE $CARGO_FLAGS; then
+    print_success "Example lending consumer program built successfully"
+else
+    print_error "Example lending consumer program build failed"
+    exit 1
+fi
+cd ../..
+
 echo ""
 echo "╔═══════════════════════════════════════════════════════════════════╗"
 echo "║                    BUILD COMPLETE! ✓                             ║"
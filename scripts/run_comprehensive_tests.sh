@@ -18,6 +18,35 @@ NC='\033[0m' # No Color
 SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
 PROJECT_ROOT="$(dirname "$SCRIPT_DIR")"
 
+# Suite configuration flags, forwarded to the test binaries (see suite.toml)
+SUITE_ARGS=()
+while [ $# -gt 0 ]; do
+    case "$1" in
+        --config)
+            export TOKEN_ACL_SUITE_CONFIG="$(cd "$(dirname "$2")" && pwd)/$(basename "$2")"
+            shift 2
+            ;;
+        --enable|--disable|--only|--deactivate-feature)
+            SUITE_ARGS+=("$1" "$2")
+            shift 2
+            ;;
+        --enable=*|--disable=*|--only=*|--deactivate-feature=*)
+            SUITE_ARGS+=("$1")
+            shift
+            ;;
+        --keep-state)
+            SUITE_ARGS+=("$1")
+            shift
+            ;;
+        *)
+            echo "Usage: $0 [--config FILE] [--enable CATEGORY] [--disable CATEGORY] [--only CATEGORY,...] [--keep-state] [--deactivate-feature FEATURE_ID,...]"
+            echo "Categories: load_tests, fuzz_smoke, devnet, long_scenarios, self_test, experiments, all"
+            exit 1
+            ;;
+    esac
+done
+export TOKEN_ACL_SUITE_ARGS="${SUITE_ARGS[*]}"
+
 echo -e "${PURPLE}╔═══════════════════════════════════════════════════════════════════╗${NC}"
 echo -e "${PURPLE}║   sRFC 37: Token ACL - Comprehensive Testing Suite              ║${NC}"
 echo -e "${PURPLE}║   Enhanced with Performance, Coverage & Logging                 ║${NC}"
@@ -65,6 +94,10 @@ print_info "Setting up structured logging..."
 export RUST_LOG=info
 export TOKEN_ACL_LOG_LEVEL=info
 
+if [ -n "$TOKEN_ACL_SUITE_ARGS" ]; then
+    print_info "Suite flags: $TOKEN_ACL_SUITE_ARGS"
+fi
+
 # Check Rust version
 print_info "Checking Rust toolchain..."
 rustc --version
@@ -108,7 +141,7 @@ else
 fi
 
 print_info "Building production programs..."
-if cargo build --package production_allow_list; then
+if cargo build --package production_allow_list --package hybrid_gate --package jurisdiction_registry --package threshold_gate --package velocity_gate --package geo_gate --package nft_gate --package example_lending_consumer; then
     print_success "Production programs built successfully"
 else
     print_error "Failed to build production programs"
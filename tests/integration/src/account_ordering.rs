@@ -0,0 +1,231 @@
+//! Account orderings a FAMP might send a permissionless check
+//!
+//! sRFC 37 fixes the first four accounts of `can-thaw-permissionless` and
+//! `can-freeze-permissionless`: the caller, the token account, the mint and
+//! the extra-account-metas PDA. The rest are the gate's extra accounts,
+//! which the FAMP resolves from the extra-account-metas. A FAMP that
+//! resolves them slightly differently may leave one out, pass them in
+//! another order, or append accounts of its own. None of that may turn a
+//! denial into an approval.
+//!
+//! [`arrangements`] takes a well-formed check and returns rearrangements of
+//! its accounts to send in its place:
+//!
+//! - each account left out, the owner included
+//! - the mint and the token account swapped
+//! - the extra accounts reversed, rotated, swapped pairwise, repeated or
+//!   left out
+//! - decoys, e.g. another holder's records, appended after the extra
+//!   accounts
+//!
+//! `tests/account_ordering.rs` sends them, and random orderings, to every
+//! gate in the repository, as [`workspace_gates`] finds them. The
+//! conformance battery sends them to the gates it certifies (see
+//! [`crate::conformance`]).
+
+use std::{fs, path::Path};
+
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+/// Accounts sRFC 37 fixes before a check's extra accounts
+pub const FIXED_ACCOUNTS: usize = 4;
+
+/// The workspace manifest, whose members include every gate
+pub const WORKSPACE_MANIFEST: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../Cargo.toml");
+
+/// A workspace member implementing a permissionless check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceGate {
+    /// The member's package name, e.g. "geo_gate"
+    pub package: String,
+    pub can_thaw: bool,
+    pub can_freeze: bool,
+}
+
+/// The members of the workspace at `manifest` whose library processes
+/// `can-thaw-permissionless` or `can-freeze-permissionless`, in member order
+pub fn workspace_gates(manifest: &Path) -> Result<Vec<WorkspaceGate>, String> {
+    let read = |path: &Path| {
+        fs::read_to_string(path).map_err(|e| format!("Reading {}: {}", path.display(), e))
+    };
+    let parse = |path: &Path| -> Result<toml::Value, String> {
+        read(path)?
+            .parse()
+            .map_err(|e| format!("Parsing {}: {}", path.display(), e))
+    };
+    let root = manifest.parent().unwrap_or_else(|| Path::new("."));
+    let workspace = parse(manifest)?;
+    let members = workspace
+        .get("workspace")
+        .and_then(|workspace| workspace.get("members"))
+        .and_then(toml::Value::as_array)
+        .ok_or_else(|| format!("{} lists no workspace members", manifest.display()))?;
+
+    let mut gates = Vec::new();
+    for member in members.iter().filter_map(toml::Value::as_str) {
+        let source = root.join(member).join("src/lib.rs");
+        if !source.exists() {
+            continue;
+        }
+        let source_text = read(&source)?;
+        let can_thaw = source_text.contains("fn process_can_thaw_permissionless(");
+        let can_freeze = source_text.contains("fn process_can_freeze_permissionless(");
+        if !can_thaw && !can_freeze {
+            continue;
+        }
+        let package_manifest = root.join(member).join("Cargo.toml");
+        let package = parse(&package_manifest)?
+            .get("package")
+            .and_then(|package| package.get("name"))
+            .and_then(toml::Value::as_str)
+            .ok_or_else(|| format!("{} names no package", package_manifest.display()))?
+            .to_string();
+        gates.push(WorkspaceGate {
+            package,
+            can_thaw,
+            can_freeze,
+        });
+    }
+    Ok(gates)
+}
+
+/// An account appended to a check, named for reports
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoy {
+    pub label: String,
+    pub meta: AccountMeta,
+}
+
+impl Decoy {
+    /// `address`, read-only and not signing, as a FAMP passes extras
+    pub fn new(label: impl Into<String>, address: Pubkey) -> Self {
+        Self {
+            label: label.into(),
+            meta: AccountMeta::new_readonly(address, false),
+        }
+    }
+}
+
+/// A check's accounts, rearranged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Arrangement {
+    /// What was done to the accounts, e.g. "mint and token account swapped"
+    pub name: String,
+    pub accounts: Vec<AccountMeta>,
+}
+
+impl Arrangement {
+    /// `check` with this arrangement's accounts
+    pub fn apply(&self, check: &Instruction) -> Instruction {
+        Instruction {
+            program_id: check.program_id,
+            accounts: self.accounts.clone(),
+            data: check.data.clone(),
+        }
+    }
+}
+
+/// How reports name the account at `index` of a check
+fn role(index: usize, owner_index: Option<usize>) -> String {
+    match index {
+        0 => "caller".to_string(),
+        1 => "token account".to_string(),
+        2 => "mint".to_string(),
+        3 => "extra-account-metas".to_string(),
+        _ if Some(index) == owner_index => "owner".to_string(),
+        _ => format!("extra account {}", index - FIXED_ACCOUNTS + 1),
+    }
+}
+
+/// Rearrangements of `check`'s accounts, each different from the others
+/// and from `check`'s own
+///
+/// `owner` is the token account's owner when the check takes it, so
+/// reports can name the arrangement leaving it out.
+pub fn arrangements(
+    check: &Instruction,
+    owner: Option<&Pubkey>,
+    decoys: &[Decoy],
+) -> Vec<Arrangement> {
+    let accounts = &check.accounts;
+    let split = FIXED_ACCOUNTS.min(accounts.len());
+    let (fixed, extras) = accounts.split_at(split);
+    let owner_index = owner.and_then(|owner| {
+        extras
+            .iter()
+            .position(|meta| meta.pubkey == *owner)
+            .map(|index| index + FIXED_ACCOUNTS)
+    });
+    let with_extras = |extras: Vec<AccountMeta>| -> Vec<AccountMeta> {
+        fixed.iter().cloned().chain(extras).collect()
+    };
+
+    let mut candidates: Vec<(String, Vec<AccountMeta>)> = Vec::new();
+    for index in 0..accounts.len() {
+        let mut rest = accounts.clone();
+        rest.remove(index);
+        candidates.push((format!("{} missing", role(index, owner_index)), rest));
+    }
+    if accounts.len() > 2 {
+        let mut swapped = accounts.clone();
+        swapped.swap(1, 2);
+        candidates.push(("mint and token account swapped".to_string(), swapped));
+    }
+    if !extras.is_empty() {
+        candidates.push(("no extra accounts".to_string(), fixed.to_vec()));
+        candidates.push((
+            "extra accounts repeated".to_string(),
+            with_extras(extras.iter().chain(extras).cloned().collect()),
+        ));
+    }
+    if extras.len() > 1 {
+        let mut reversed = extras.to_vec();
+        reversed.reverse();
+        candidates.push(("extra accounts reversed".to_string(), with_extras(reversed)));
+        for shift in 1..extras.len() {
+            let mut rotated = extras.to_vec();
+            rotated.rotate_left(shift);
+            candidates.push((
+                format!("extra accounts rotated by {}", shift),
+                with_extras(rotated),
+            ));
+        }
+        for index in 0..extras.len() - 1 {
+            let mut swapped = extras.to_vec();
+            swapped.swap(index, index + 1);
+            candidates.push((
+                format!(
+                    "{} and {} swapped",
+                    role(index + FIXED_ACCOUNTS, owner_index),
+                    role(index + FIXED_ACCOUNTS + 1, owner_index)
+                ),
+                with_extras(swapped),
+            ));
+        }
+    }
+    for decoy in decoys {
+        let mut appended = accounts.clone();
+        appended.push(decoy.meta.clone());
+        candidates.push((format!("{} appended", decoy.label), appended));
+    }
+    if decoys.len() > 1 {
+        let mut appended = accounts.clone();
+        appended.extend(decoys.iter().map(|decoy| decoy.meta.clone()));
+        candidates.push(("every decoy appended".to_string(), appended));
+    }
+
+    let mut arrangements: Vec<Arrangement> = Vec::new();
+    for (name, accounts) in candidates {
+        if accounts != check.accounts
+            && !arrangements
+                .iter()
+                .any(|arrangement| arrangement.accounts == accounts)
+        {
+            arrangements.push(Arrangement { name, accounts });
+        }
+    }
+    arrangements
+}
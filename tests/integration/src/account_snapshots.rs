@@ -0,0 +1,169 @@
+//! Account-state snapshots and the differ between them
+//!
+//! Scenario fixtures capture every account a transaction references before
+//! and after it runs. [`AccountSnapshot::diff`] reduces the pair to the
+//! accounts that actually changed, which is what the post-condition checks in
+//! [`crate::invariants`] look at. Diffs display accounts by the names tests
+//! give them in [`crate::labels`].
+
+use std::{collections::BTreeMap, fmt};
+
+use solana_program_test::{BanksClient, BanksClientError};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+
+use crate::{labels, units};
+
+/// The parts of an account a step can change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountState {
+    pub lamports: u64,
+    pub owner: Pubkey,
+    pub data: Vec<u8>,
+}
+
+impl From<Account> for AccountState {
+    fn from(account: Account) -> Self {
+        Self {
+            lamports: account.lamports,
+            owner: account.owner,
+            data: account.data,
+        }
+    }
+}
+
+/// The state of a set of accounts at one point in a scenario
+///
+/// Accounts that do not exist are recorded as `None`, so creating and closing
+/// an account both show up in a diff.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AccountSnapshot {
+    accounts: BTreeMap<Pubkey, Option<AccountState>>,
+}
+
+impl AccountSnapshot {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fetch `addresses` from the bank
+    pub async fn capture(
+        banks_client: &mut BanksClient,
+        addresses: &[Pubkey],
+    ) -> Result<Self, BanksClientError> {
+        let mut snapshot = Self::new();
+        for address in addresses {
+            let account = banks_client.get_account(*address).await?;
+            snapshot.insert(*address, account.map(AccountState::from));
+        }
+        Ok(snapshot)
+    }
+
+    pub fn insert(&mut self, address: Pubkey, state: Option<AccountState>) {
+        self.accounts.insert(address, state);
+    }
+
+    pub fn get(&self, address: &Pubkey) -> Option<&AccountState> {
+        self.accounts.get(address).and_then(Option::as_ref)
+    }
+
+    pub fn addresses(&self) -> impl Iterator<Item = &Pubkey> {
+        self.accounts.keys()
+    }
+
+    /// Accounts whose state differs between `self` and `after`
+    ///
+    /// An address missing from one side counts as a missing account there.
+    pub fn diff(&self, after: &AccountSnapshot) -> AccountDiff {
+        let mut addresses: Vec<&Pubkey> = self.addresses().chain(after.addresses()).collect();
+        addresses.sort();
+        addresses.dedup();
+
+        let changes = addresses
+            .into_iter()
+            .filter_map(|address| {
+                let before = self.get(address);
+                let after = after.get(address);
+                (before != after).then(|| AccountChange {
+                    address: *address,
+                    before: before.cloned(),
+                    after: after.cloned(),
+                })
+            })
+            .collect();
+        AccountDiff { changes }
+    }
+}
+
+/// One account's state before and after a step
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountChange {
+    pub address: Pubkey,
+    pub before: Option<AccountState>,
+    pub after: Option<AccountState>,
+}
+
+impl AccountChange {
+    pub fn lamports_before(&self) -> u64 {
+        self.before.as_ref().map_or(0, |state| state.lamports)
+    }
+
+    pub fn lamports_after(&self) -> u64 {
+        self.after.as_ref().map_or(0, |state| state.lamports)
+    }
+
+    /// Lamports gained (positive) or lost (negative) during the step
+    pub fn lamport_delta(&self) -> i128 {
+        i128::from(self.lamports_after()) - i128::from(self.lamports_before())
+    }
+
+    /// Whether the account existed before and is gone, or emptied, after
+    pub fn is_closed(&self) -> bool {
+        self.before.is_some() && self.lamports_after() == 0
+    }
+}
+
+impl fmt::Display for AccountChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lifecycle = match (&self.before, &self.after) {
+            (None, Some(_)) => " (created)",
+            (Some(_), None) => " (closed)",
+            _ => "",
+        };
+        write!(
+            f,
+            "{}: {} -> {}{}",
+            labels::name(&self.address),
+            units::group_digits(self.lamports_before()),
+            units::lamports(self.lamports_after()),
+            lifecycle
+        )
+    }
+}
+
+/// The accounts a step changed, in address order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AccountDiff {
+    pub changes: Vec<AccountChange>,
+}
+
+impl AccountDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn get(&self, address: &Pubkey) -> Option<&AccountChange> {
+        self.changes
+            .iter()
+            .find(|change| change.address == *address)
+    }
+}
+
+/// One changed account per line
+impl fmt::Display for AccountDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "  {}", change)?;
+        }
+        Ok(())
+    }
+}
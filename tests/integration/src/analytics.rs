@@ -0,0 +1,423 @@
+//! Gate decision analytics
+//!
+//! This module turns the program logs produced by gate decisions into
+//! aggregate compliance statistics: approval/denial counts, denial reasons,
+//! and per-tier breakdowns. [`ReviewQueue`] does the same for the
+//! production allow list's manual review queue, measuring how long holders
+//! wait for a decision. Logs can come from a test run
+//! (`BanksTransactionResultWithMetadata::metadata`) or from a live
+//! `logsSubscribe` notification, which carries the same log lines and error.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # use token_acl_integration_tests::analytics::{DecisionAnalytics, GateOperation};
+//! let mut analytics = DecisionAnalytics::new();
+//! analytics.ingest_logs(
+//!     GateOperation::Thaw,
+//!     &["Program log: User 11111111111111111111111111111111 not in allow list".to_string()],
+//!     false,
+//! );
+//!
+//! let summary = analytics.summary();
+//! assert_eq!(summary.denied, 1);
+//! assert_eq!(summary.denial_reasons.get("not_listed"), Some(&1));
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::units;
+
+/// Tier label used when the logs do not reveal the user's access level
+pub const UNKNOWN_TIER: &str = "unknown";
+
+/// Permissionless operation a gate decided on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GateOperation {
+    Thaw,
+    Freeze,
+}
+
+/// A single gate decision extracted from program logs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DecisionEvent {
+    pub operation: GateOperation,
+    /// Token account owner the decision was about, if logged
+    pub user: Option<String>,
+    /// Access level reported by the gate, if logged
+    pub tier: Option<String>,
+    pub approved: bool,
+    /// Normalized denial reason; `None` for approvals
+    pub denial_reason: Option<String>,
+}
+
+impl DecisionEvent {
+    /// Build a decision event from one gate invocation's logs
+    ///
+    /// `succeeded` is the transaction status and is authoritative for the
+    /// outcome; the logs only supply the user, tier, and denial reason.
+    pub fn from_logs(operation: GateOperation, logs: &[String], succeeded: bool) -> Self {
+        let messages: Vec<&str> = logs
+            .iter()
+            .map(|line| line.strip_prefix("Program log: ").unwrap_or(line))
+            .collect();
+
+        let user = messages.iter().find_map(|message| parse_user(message));
+        let tier = messages.iter().find_map(|message| parse_tier(message));
+        let denial_reason = if succeeded {
+            None
+        } else {
+            Some(
+                messages
+                    .iter()
+                    .find_map(|message| classify_denial(message))
+                    .unwrap_or("other")
+                    .to_string(),
+            )
+        };
+
+        Self {
+            operation,
+            user,
+            tier,
+            approved: succeeded,
+            denial_reason,
+        }
+    }
+}
+
+/// Approval/denial counts for one bucket
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DecisionCounts {
+    pub approved: usize,
+    pub denied: usize,
+}
+
+impl DecisionCounts {
+    fn record(&mut self, approved: bool) {
+        if approved {
+            self.approved += 1;
+        } else {
+            self.denied += 1;
+        }
+    }
+}
+
+/// Aggregated view of all recorded decisions
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DecisionSummary {
+    pub total: usize,
+    pub approved: usize,
+    pub denied: usize,
+    /// Percentage of decisions that were approvals, to one decimal
+    pub approval_rate: f64,
+    pub denial_reasons: BTreeMap<String, usize>,
+    pub by_tier: BTreeMap<String, DecisionCounts>,
+    pub by_operation: BTreeMap<String, DecisionCounts>,
+}
+
+/// Accumulates decision events and produces compliance artifacts
+#[derive(Debug, Clone, Default)]
+pub struct DecisionAnalytics {
+    events: Vec<DecisionEvent>,
+}
+
+impl DecisionAnalytics {
+    /// Create an empty aggregator
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an already-parsed decision
+    pub fn record(&mut self, event: DecisionEvent) {
+        self.events.push(event);
+    }
+
+    /// Parse and record one gate invocation's logs
+    pub fn ingest_logs(&mut self, operation: GateOperation, logs: &[String], succeeded: bool) {
+        self.record(DecisionEvent::from_logs(operation, logs, succeeded));
+    }
+
+    /// All recorded decisions, in ingestion order
+    pub fn events(&self) -> &[DecisionEvent] {
+        &self.events
+    }
+
+    /// Aggregate the recorded decisions
+    pub fn summary(&self) -> DecisionSummary {
+        let mut summary = DecisionSummary {
+            total: self.events.len(),
+            ..DecisionSummary::default()
+        };
+
+        for event in &self.events {
+            if event.approved {
+                summary.approved += 1;
+            } else {
+                summary.denied += 1;
+            }
+            if let Some(reason) = &event.denial_reason {
+                *summary.denial_reasons.entry(reason.clone()).or_default() += 1;
+            }
+
+            let tier = event.tier.as_deref().unwrap_or(UNKNOWN_TIER);
+            summary
+                .by_tier
+                .entry(tier.to_string())
+                .or_default()
+                .record(event.approved);
+            summary
+                .by_operation
+                .entry(operation_label(event.operation).to_string())
+                .or_default()
+                .record(event.approved);
+        }
+
+        summary.approval_rate =
+            units::rounded_ratio(summary.approved as u64, summary.total as u64).unwrap_or(0.0);
+        summary
+    }
+
+    /// Render the summary as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(&self.summary())
+    }
+
+    /// Render the summary as CSV with one row per bucket
+    ///
+    /// Columns are `dimension,key,approved,denied`. Denial reasons only
+    /// populate the `denied` column.
+    pub fn to_csv(&self) -> String {
+        let summary = self.summary();
+        let mut csv = String::from("dimension,key,approved,denied\n");
+
+        csv.push_str(&format!(
+            "overall,all,{},{}\n",
+            summary.approved, summary.denied
+        ));
+        for (operation, counts) in &summary.by_operation {
+            csv.push_str(&format!(
+                "operation,{},{},{}\n",
+                operation, counts.approved, counts.denied
+            ));
+        }
+        for (tier, counts) in &summary.by_tier {
+            csv.push_str(&format!(
+                "tier,{},{},{}\n",
+                tier, counts.approved, counts.denied
+            ));
+        }
+        for (reason, count) in &summary.denial_reasons {
+            csv.push_str(&format!("denial_reason,{},0,{}\n", reason, count));
+        }
+        csv
+    }
+
+    /// Write the JSON and CSV artifacts, creating parent directories as needed
+    pub fn write_artifacts(
+        &self,
+        json_path: impl AsRef<Path>,
+        csv_path: impl AsRef<Path>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        for path in [json_path.as_ref(), csv_path.as_ref()] {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        fs::write(json_path, self.to_json()?)?;
+        fs::write(csv_path, self.to_csv())?;
+        Ok(())
+    }
+}
+
+/// How a reviewer decided a queued review request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewOutcome {
+    Approved,
+    Rejected,
+}
+
+/// A review request that left the queue
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReviewDecision {
+    pub user: String,
+    pub outcome: ReviewOutcome,
+    /// Seconds from the request to the decision, by the cluster clock
+    pub latency_seconds: i64,
+}
+
+/// How the review queue is doing
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReviewQueueSummary {
+    pub requested: usize,
+    pub approved: usize,
+    pub rejected: usize,
+    /// Requests still waiting for a decision
+    pub pending: usize,
+    /// Mean seconds from request to decision, to one decimal
+    pub mean_latency_seconds: Option<f64>,
+    pub max_latency_seconds: Option<i64>,
+    /// Seconds the longest-waiting pending request has been queued
+    pub oldest_pending_seconds: Option<i64>,
+}
+
+/// Follows the production allow list's review queue through the logs of
+/// `RequestReview`, `ApproveReview` and `RejectReview`
+///
+/// # Examples
+///
+/// ```rust
+/// # use token_acl_integration_tests::analytics::ReviewQueue;
+/// let user = "11111111111111111111111111111111";
+/// let mut queue = ReviewQueue::new();
+/// queue.ingest_logs(&[format!("Program log: Review of user {} requested at 100", user)]);
+/// assert_eq!(queue.summary(160).oldest_pending_seconds, Some(60));
+///
+/// queue.ingest_logs(&[format!("Program log: Review of user {} approved after 90s", user)]);
+/// let summary = queue.summary(200);
+/// assert_eq!((summary.approved, summary.pending), (1, 0));
+/// assert_eq!(summary.max_latency_seconds, Some(90));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ReviewQueue {
+    /// Users waiting for a decision, and when they asked
+    pending: BTreeMap<String, i64>,
+    requested: usize,
+    decisions: Vec<ReviewDecision>,
+}
+
+impl ReviewQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Follow the queue through one transaction's logs
+    ///
+    /// Only logs of transactions that succeeded belong here: a failed
+    /// request or decision changed nothing.
+    pub fn ingest_logs(&mut self, logs: &[String]) {
+        for message in logs
+            .iter()
+            .map(|line| line.strip_prefix("Program log: ").unwrap_or(line))
+        {
+            let Some(rest) = message.strip_prefix("Review of user ") else {
+                continue;
+            };
+            let Some((user, event)) = rest.split_once(' ') else {
+                continue;
+            };
+            if let Some(timestamp) = event.strip_prefix("requested at ") {
+                if let Ok(timestamp) = timestamp.parse() {
+                    self.requested += 1;
+                    self.pending.insert(user.to_string(), timestamp);
+                }
+                continue;
+            }
+            let (outcome, latency) = if let Some(latency) = event.strip_prefix("approved after ") {
+                (ReviewOutcome::Approved, latency)
+            } else if let Some(latency) = event.strip_prefix("rejected after ") {
+                (ReviewOutcome::Rejected, latency)
+            } else {
+                continue;
+            };
+            if let Some(Ok(latency_seconds)) = latency.strip_suffix('s').map(str::parse) {
+                self.pending.remove(user);
+                self.decisions.push(ReviewDecision {
+                    user: user.to_string(),
+                    outcome,
+                    latency_seconds,
+                });
+            }
+        }
+    }
+
+    /// Decided requests, in the order they were decided
+    pub fn decisions(&self) -> &[ReviewDecision] {
+        &self.decisions
+    }
+
+    /// The queue as of `unix_timestamp`
+    pub fn summary(&self, unix_timestamp: i64) -> ReviewQueueSummary {
+        let latencies: Vec<i64> = self
+            .decisions
+            .iter()
+            .map(|decision| decision.latency_seconds)
+            .collect();
+        let count = |outcome| {
+            self.decisions
+                .iter()
+                .filter(|decision| decision.outcome == outcome)
+                .count()
+        };
+        ReviewQueueSummary {
+            requested: self.requested,
+            approved: count(ReviewOutcome::Approved),
+            rejected: count(ReviewOutcome::Rejected),
+            pending: self.pending.len(),
+            mean_latency_seconds: (!latencies.is_empty()).then(|| {
+                let mean = latencies.iter().sum::<i64>() as f64 / latencies.len() as f64;
+                (mean * 10.0).round() / 10.0
+            }),
+            max_latency_seconds: latencies.iter().copied().max(),
+            oldest_pending_seconds: self
+                .pending
+                .values()
+                .min()
+                .map(|requested| unix_timestamp.saturating_sub(*requested)),
+        }
+    }
+}
+
+fn operation_label(operation: GateOperation) -> &'static str {
+    match operation {
+        GateOperation::Thaw => "thaw",
+        GateOperation::Freeze => "freeze",
+    }
+}
+
+/// Extract the pubkey following "User " in a gate log message
+fn parse_user(message: &str) -> Option<String> {
+    let rest = &message[message.find("User ")? + "User ".len()..];
+    let user: String = rest
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .collect();
+    user.parse::<solana_sdk::pubkey::Pubkey>().ok()?;
+    Some(user)
+}
+
+/// Extract the access level from "(level: X)"
+fn parse_tier(message: &str) -> Option<String> {
+    let rest = &message[message.find("(level: ")? + "(level: ".len()..];
+    Some(rest[..rest.find(')')?].to_string())
+}
+
+/// Map a gate's denial log message to a stable reason key
+fn classify_denial(message: &str) -> Option<&'static str> {
+    if message.contains("is pending review") {
+        Some("pending_review")
+    } else if message.contains("not in allow list") {
+        Some("not_listed")
+    } else if message.contains("is not allowed") {
+        Some("revoked")
+    } else if message.contains("access has expired") {
+        Some("expired")
+    } else if message.contains("is blocked") {
+        Some("blocked")
+    } else if message.contains("not blocked") || message.contains("not in block list") {
+        Some("not_blocked")
+    } else if message.contains("is not owned by") {
+        Some("owner_mismatch")
+    } else if message.contains("Invalid") && message.contains("PDA") {
+        Some("invalid_pda")
+    } else if message.contains("not supported") {
+        Some("unsupported")
+    } else {
+        None
+    }
+}
@@ -0,0 +1,329 @@
+//! Lint for assertions that cannot fail
+//!
+//! Suite tests count their assertions (`assertions += 1;`) and return a
+//! failure report when a condition does not hold. A condition that only
+//! reads literals, directly or through `let reentrancy_prevented = true;`,
+//! holds on every run whatever the code under test does. This lint scans
+//! the test sources for such assertions; a test whose assertions are all on
+//! literals fails the coverage gate until it is converted to an executable
+//! check.
+//!
+//! The scan is line-based rather than a full parse: it splits each counted
+//! test function into assertion blocks at the counter increments and reads
+//! the `if` and `assert!` conditions in each block.
+
+use std::{collections::BTreeSet, fs, path::Path};
+
+/// Directory of the suite's test sources
+pub const TESTS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests");
+
+/// An assertion whose conditions only read literals
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiteralAssertion {
+    /// Line of the assertion counter, 1-based
+    pub line: usize,
+    pub condition: String,
+}
+
+/// The assertions of one counted test function
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestLint {
+    pub file: String,
+    pub function: String,
+    /// Line of the function signature, 1-based
+    pub line: usize,
+    pub assertions: usize,
+    pub literal_assertions: Vec<LiteralAssertion>,
+}
+
+impl TestLint {
+    /// Every assertion the function counts is on literals, so it can never
+    /// fail
+    pub fn is_literal_only(&self) -> bool {
+        self.assertions > 0 && self.literal_assertions.len() == self.assertions
+    }
+
+    /// `file::function`, as reports name it
+    pub fn location(&self) -> String {
+        format!("{}::{}", self.file, self.function)
+    }
+}
+
+/// Lint every counted test function in `source`
+pub fn lint_source(file: &str, source: &str) -> Vec<TestLint> {
+    let lines: Vec<String> = source.lines().map(strip_literals).collect();
+    let mut lints = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let Some(function) = function_name(&lines[index]) else {
+            index += 1;
+            continue;
+        };
+        let end = body_end(&lines, index);
+        if let Some(lint) = lint_function(file, function, index, &lines[index..=end]) {
+            lints.push(lint);
+        }
+        index = end + 1;
+    }
+    lints
+}
+
+/// Lint every `.rs` file directly in `dir`, in file name order
+pub fn lint_test_sources(dir: &Path) -> Result<Vec<TestLint>, String> {
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rs"))
+        .collect();
+    paths.sort();
+    let mut lints = Vec::new();
+    for path in paths {
+        let source = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let file = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        lints.extend(lint_source(&file, &source));
+    }
+    Ok(lints)
+}
+
+/// The tests in `lints` that can never fail
+pub fn literal_only(lints: &[TestLint]) -> Vec<&TestLint> {
+    lints.iter().filter(|lint| lint.is_literal_only()).collect()
+}
+
+/// Markdown report of a lint run
+pub fn render_lint_report(lints: &[TestLint]) -> String {
+    let flagged = literal_only(lints);
+    let literal: usize = lints.iter().map(|lint| lint.literal_assertions.len()).sum();
+    let mut report = String::from("## Literal Assertion Lint\n\n");
+    report.push_str(&format!(
+        "- **Counted tests scanned**: {}\n- **Assertions on literals**: {}\n- **Tests asserting only literals**: {}\n\n",
+        lints.len(),
+        literal,
+        flagged.len()
+    ));
+    if literal == 0 {
+        return report;
+    }
+    report.push_str("| Test | Line | Condition | Literal-Only Test |\n");
+    report.push_str("|------|------|-----------|-------------------|\n");
+    for lint in lints {
+        for assertion in &lint.literal_assertions {
+            report.push_str(&format!(
+                "| {} | {} | `{}` | {} |\n",
+                lint.location(),
+                assertion.line,
+                assertion.condition.replace('|', "\\|"),
+                if lint.is_literal_only() { "❌" } else { "-" }
+            ));
+        }
+    }
+    report.push('\n');
+    report
+}
+
+/// The name of the function a line declares, if it starts one
+fn function_name(line: &str) -> Option<&str> {
+    let mut rest = line.trim_start();
+    for qualifier in ["pub(crate) ", "pub ", "async "] {
+        rest = rest.strip_prefix(qualifier).unwrap_or(rest);
+    }
+    let rest = rest.strip_prefix("fn ")?;
+    let end = rest.find(|c: char| !is_identifier_char(c))?;
+    Some(&rest[..end]).filter(|name| !name.is_empty())
+}
+
+/// Index of the line closing the function whose signature is at `start`
+fn body_end(lines: &[String], start: usize) -> usize {
+    let mut depth = 0i64;
+    let mut opened = false;
+    for (index, line) in lines.iter().enumerate().skip(start) {
+        for c in line.chars() {
+            match c {
+                '{' => {
+                    depth += 1;
+                    opened = true;
+                }
+                '}' => depth -= 1,
+                ';' if !opened && depth == 0 => return index,
+                _ => {}
+            }
+        }
+        if opened && depth <= 0 {
+            return index;
+        }
+    }
+    lines.len() - 1
+}
+
+fn lint_function(file: &str, function: &str, start: usize, body: &[String]) -> Option<TestLint> {
+    let markers: Vec<usize> = body
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| is_assertion_counter(line))
+        .map(|(index, _)| index)
+        .collect();
+    if markers.is_empty() {
+        return None;
+    }
+    let literal_bindings: BTreeSet<&str> = body.iter().filter_map(|l| literal_binding(l)).collect();
+
+    let mut literal_assertions = Vec::new();
+    for (position, &marker) in markers.iter().enumerate() {
+        let end = markers.get(position + 1).copied().unwrap_or(body.len());
+        let conditions = conditions(&body[marker..end]);
+        if !conditions.is_empty()
+            && conditions
+                .iter()
+                .all(|condition| reads_only_literals(condition, &literal_bindings))
+        {
+            literal_assertions.push(LiteralAssertion {
+                line: start + marker + 1,
+                condition: conditions.join(" / "),
+            });
+        }
+    }
+    Some(TestLint {
+        file: file.to_string(),
+        function: function.to_string(),
+        line: start + 1,
+        assertions: markers.len(),
+        literal_assertions,
+    })
+}
+
+/// `assertions += 1;`, `assertion_count += 1;` and the like
+fn is_assertion_counter(line: &str) -> bool {
+    line.trim()
+        .strip_suffix(" += 1;")
+        .is_some_and(|name| name.contains("assertion") && name.chars().all(is_identifier_char))
+}
+
+/// The name bound by `let name = <literal expression>;`
+fn literal_binding(line: &str) -> Option<&str> {
+    let rest = line.trim().strip_prefix("let ")?;
+    let rest = rest.strip_prefix("mut ").unwrap_or(rest);
+    let (pattern, value) = rest.split_once(" = ")?;
+    let name = pattern.split(':').next()?.trim();
+    let value = value.trim().strip_suffix(';')?;
+    let is_name = !name.is_empty() && name.chars().all(is_identifier_char);
+    (is_name && reads_only_literals(value, &BTreeSet::new())).then_some(name)
+}
+
+/// Conditions of the `if` expressions and assertion macros in `block`
+fn conditions(block: &[String]) -> Vec<String> {
+    let mut conditions = Vec::new();
+    let mut index = 0;
+    while index < block.len() {
+        let line = block[index].trim();
+        if let Some(start) = if_condition_start(line) {
+            let mut condition = line[start..].to_string();
+            while !condition.trim_end().ends_with('{') && index + 1 < block.len() {
+                index += 1;
+                condition.push(' ');
+                condition.push_str(block[index].trim());
+            }
+            let condition = condition.trim_end().trim_end_matches('{').trim();
+            conditions.push(condition.to_string());
+        } else if let Some(start) = ["assert!(", "assert_eq!(", "assert_ne!("]
+            .iter()
+            .find_map(|mac| line.find(mac).map(|at| at + mac.len()))
+        {
+            let mut condition = line[start..].to_string();
+            while !condition.trim_end().ends_with(';') && index + 1 < block.len() {
+                index += 1;
+                condition.push(' ');
+                condition.push_str(block[index].trim());
+            }
+            let condition = condition
+                .trim_end()
+                .trim_end_matches(';')
+                .trim_end_matches(')');
+            // The message arguments after the condition are not read
+            let condition = condition.split(", \"").next().unwrap_or(condition).trim();
+            conditions.push(condition.to_string());
+        }
+        index += 1;
+    }
+    conditions
+}
+
+/// Where the condition of an `if` on this line begins
+fn if_condition_start(line: &str) -> Option<usize> {
+    if line.starts_with("if ") {
+        return Some(3);
+    }
+    line.find(" if ")
+        .filter(|_| line.starts_with("} else") || line.starts_with("else"))
+        .map(|at| at + 4)
+}
+
+/// Whether every name `expression` reads is a literal or bound to one
+fn reads_only_literals(expression: &str, literal_bindings: &BTreeSet<&str>) -> bool {
+    let mut read_anything = false;
+    for token in expression.split(|c: char| !is_identifier_char(c)) {
+        if token.is_empty() {
+            continue;
+        }
+        read_anything = true;
+        let literal = token == "true"
+            || token == "false"
+            || token.starts_with(|c: char| c.is_ascii_digit())
+            || token == STRING_PLACEHOLDER;
+        if !literal && !literal_bindings.contains(token) {
+            return false;
+        }
+    }
+    read_anything
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// What string and char literals are replaced with, so their contents are
+/// not read as names
+const STRING_PLACEHOLDER: &str = "__literal__";
+
+/// `line` with comments dropped and string and char literals replaced
+fn strip_literals(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'/') => break,
+            '"' => {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+                out.push_str(STRING_PLACEHOLDER);
+            }
+            '\'' => {
+                // A char literal is 'x' or '\x'; anything else is a lifetime
+                let mut lookahead = chars.clone();
+                let length = match lookahead.next() {
+                    Some('\\') if lookahead.nth(1) == Some('\'') => Some(3),
+                    Some(_) if lookahead.next() == Some('\'') => Some(2),
+                    _ => None,
+                };
+                if let Some(length) = length {
+                    chars.nth(length - 1);
+                    out.push_str(STRING_PLACEHOLDER);
+                } else {
+                    out.push(c);
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
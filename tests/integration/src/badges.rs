@@ -0,0 +1,157 @@
+//! Status badges
+//!
+//! Repositories embedding the suite can show its live status with
+//! shields.io endpoint badges instead of parsing the reports. Report
+//! generation writes one endpoint file per badge to [`BADGES_DIR`]:
+//!
+//! - `tests.json`: pass rate over every test report generated into the
+//!   reports directory, skipped tests left out
+//! - `coverage.json`: basic blocks of the gate programs executed by the
+//!   execution coverage run
+//! - `thaw_compute_units.json`: compute units of a permissionless thaw
+//!   through the reference gate, from the compute budget guidance
+//!
+//! Each test binary writes its own report, so the pass rate is kept as a
+//! tally per report in `tests_tally.json` and the badge recomputed from all
+//! of them whenever one is written.
+
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path, sync::Mutex};
+
+use crate::{
+    bpf_coverage::ProgramCoverage, budget_guidance::OperationUnits, units, TestResultReport,
+};
+
+/// The suite's reports; only reports written here count toward the pass rate
+pub const REPORTS_DIR: &str = "../../tests/reports";
+
+/// Where the endpoint files are written
+pub const BADGES_DIR: &str = "../../tests/reports/badges";
+
+/// The gate whose thaw is shown on the compute unit badge
+pub const REFERENCE_GATE: &str = "example_allow_list";
+
+const TALLY_FILE: &str = "tests_tally.json";
+
+/// Serializes updates of the pass-rate tally within a test binary
+static TALLY_LOCK: Mutex<()> = Mutex::new(());
+
+/// A shields.io endpoint badge
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Badge {
+    /// Always 1
+    pub schema_version: u8,
+    pub label: String,
+    pub message: String,
+    pub color: String,
+}
+
+impl Badge {
+    pub fn new(label: &str, message: &str, color: &str) -> Self {
+        Self {
+            schema_version: 1,
+            label: label.to_string(),
+            message: message.to_string(),
+            color: color.to_string(),
+        }
+    }
+
+    /// Share of the tests that ran which passed
+    pub fn pass_rate(passed: usize, failed: usize) -> Self {
+        let ran = passed + failed;
+        if ran == 0 {
+            return Self::new("tests", "none run", "lightgrey");
+        }
+        let color = if failed == 0 { "brightgreen" } else { "red" };
+        let rate = units::percent(passed as u64, ran as u64);
+        Self::new("tests", &format!("{} ({}/{})", rate, passed, ran), color)
+    }
+
+    /// Basic blocks executed over the built programs in `coverage`
+    pub fn coverage(coverage: &[ProgramCoverage]) -> Self {
+        let built: Vec<_> = coverage.iter().filter(|p| p.is_built()).collect();
+        let total: usize = built.iter().map(|p| p.total_blocks).sum();
+        if total == 0 {
+            return Self::new("coverage", "not traced", "lightgrey");
+        }
+        let executed: usize = built.iter().map(|p| p.executed_blocks.len()).sum();
+        let percentage = executed as f64 / total as f64 * 100.0;
+        let message = units::percent(executed as u64, total as u64);
+        let color = match percentage {
+            p if p >= 90.0 => "brightgreen",
+            p if p >= 75.0 => "green",
+            p if p >= 50.0 => "yellow",
+            _ => "red",
+        };
+        Self::new("coverage", &message, color)
+    }
+
+    /// The most expensive sample of `operation`; unmetered samples are not
+    /// compute units and are shown as such
+    pub fn compute_units(label: &str, operation: &OperationUnits) -> Self {
+        if !operation.metered {
+            return Self::new(label, "not metered", "lightgrey");
+        }
+        Self::new(label, &units::compute_units(operation.max()), "blue")
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+/// Passed and failed tests of one report
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tally {
+    pub passed: usize,
+    pub failed: usize,
+}
+
+impl Tally {
+    pub fn of(results: &[TestResultReport]) -> Self {
+        let ran = results.iter().filter(|r| r.skipped.is_none());
+        let (passed, failed) = ran.fold((0, 0), |(passed, failed), r| {
+            if r.passed {
+                (passed + 1, failed)
+            } else {
+                (passed, failed + 1)
+            }
+        });
+        Self { passed, failed }
+    }
+}
+
+/// Write `badge` to `<dir>/<name>.json`
+pub fn write_badge(dir: &Path, name: &str, badge: &Badge) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let json = badge.to_json().map_err(std::io::Error::other)?;
+    fs::write(dir.join(format!("{}.json", name)), json)
+}
+
+/// Record `results` as the tally of `report` in `dir` and rewrite the
+/// pass-rate badge from every report's tally
+pub fn record_results(
+    dir: &Path,
+    report: &str,
+    results: &[TestResultReport],
+) -> std::io::Result<Badge> {
+    let _guard = TALLY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let path = dir.join(TALLY_FILE);
+    // A missing or unreadable tally starts over
+    let mut tallies: BTreeMap<String, Tally> = fs::read(&path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default();
+    tallies.insert(report.to_string(), Tally::of(results));
+
+    let (passed, failed) = tallies
+        .values()
+        .fold((0, 0), |(p, f), t| (p + t.passed, f + t.failed));
+    let badge = Badge::pass_rate(passed, failed);
+    fs::create_dir_all(dir)?;
+    let tally = serde_json::to_vec_pretty(&tallies).map_err(std::io::Error::other)?;
+    fs::write(&path, tally)?;
+    write_badge(dir, "tests", &badge)?;
+    Ok(badge)
+}
@@ -6,6 +6,8 @@
 use solana_sdk::pubkey::Pubkey;
 use std::time::{Duration, Instant};
 
+use crate::fixtures::test_data;
+
 /// Benchmark result with timing information
 #[derive(Debug, Clone)]
 pub struct BenchmarkResult {
@@ -57,6 +59,51 @@ impl BenchmarkResult {
     }
 }
 
+/// Latency percentiles over repeated runs of one end-to-end operation
+#[derive(Debug, Clone, Default)]
+pub struct LatencyDistribution {
+    /// Sorted, fastest first
+    samples: Vec<Duration>,
+}
+
+impl LatencyDistribution {
+    pub fn new(mut samples: Vec<Duration>) -> Self {
+        samples.sort();
+        Self { samples }
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Nearest-rank percentile (`0.0..=100.0`); zero without samples
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * self.samples.len() as f64).ceil();
+        let index = (rank as usize)
+            .saturating_sub(1)
+            .min(self.samples.len() - 1);
+        self.samples[index]
+    }
+
+    pub fn max(&self) -> Duration {
+        self.samples.last().copied().unwrap_or_default()
+    }
+
+    pub fn mean(&self) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        self.samples.iter().sum::<Duration>() / self.samples.len() as u32
+    }
+}
+
 /// Benchmark runner for measuring operation performance
 pub struct BenchmarkRunner {
     name: String,
@@ -159,8 +206,8 @@ pub mod performance_benchmarks {
             .iterations(50000)
             .warmup_iterations(5000)
             .run(|| {
-                let thaw_discriminator = [8, 175, 169, 129, 137, 74, 61, 241];
-                let freeze_discriminator = [214, 141, 109, 75, 248, 1, 45, 29];
+                let thaw_discriminator = test_data::THAW_DISCRIMINATOR;
+                let freeze_discriminator = test_data::FREEZE_DISCRIMINATOR;
 
                 if thaw_discriminator.len() != 8 || freeze_discriminator.len() != 8 {
                     return Err("Invalid discriminator length".to_string());
@@ -218,20 +265,21 @@ pub mod performance_benchmarks {
             .iterations(20000)
             .warmup_iterations(2000)
             .run(|| {
-                let account = Keypair::new();
-                let authority = Keypair::new();
+                // Key generation is not part of validation, so use cheap unique keys
+                let account = Pubkey::new_unique();
+                let authority = Pubkey::new_unique();
 
                 // Simulate account validation logic
-                if account.pubkey() == Pubkey::default() {
+                if account == Pubkey::default() {
                     return Err("Invalid account".to_string());
                 }
 
-                if authority.pubkey() == Pubkey::default() {
+                if authority == Pubkey::default() {
                     return Err("Invalid authority".to_string());
                 }
 
                 // Simulate permission check
-                let is_authorized = account.pubkey() == authority.pubkey();
+                let is_authorized = account == authority;
                 if !is_authorized {
                     // This is expected in most cases, so we'll just continue
                 }
@@ -254,6 +302,7 @@ pub mod performance_benchmarks {
 /// Performance analysis and reporting
 pub mod performance_analysis {
     use super::*;
+    use crate::{time, units};
     use std::fs;
 
     /// Generate performance report
@@ -261,14 +310,20 @@ pub mod performance_analysis {
         results: &[BenchmarkResult],
         output_path: &str,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        let report = render_performance_report(results);
+        fs::create_dir_all("../../tests/reports").ok();
+        fs::write(output_path, report)?;
+
+        Ok(())
+    }
+
+    /// Render a performance report as markdown
+    pub fn render_performance_report(results: &[BenchmarkResult]) -> String {
         let mut report = String::new();
 
         // Header
         report.push_str("# Token ACL Performance Benchmarks\n\n");
-        report.push_str(&format!(
-            "**Generated**: {}\n\n",
-            chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC")
-        ));
+        report.push_str(&format!("**Generated**: {}\n\n", time::report_timestamp()));
 
         // Summary
         let total_benchmarks = results.len();
@@ -301,10 +356,10 @@ pub mod performance_analysis {
             } else {
                 "❌ FAIL"
             };
-            let avg_time = format!("{:.2}μs", result.avg_duration.as_micros());
-            let min_time = format!("{:.2}μs", result.min_duration.as_micros());
-            let max_time = format!("{:.2}μs", result.max_duration.as_micros());
-            let total_time = format!("{:.2}ms", result.duration.as_millis());
+            let avg_time = units::duration(result.avg_duration);
+            let min_time = units::duration(result.min_duration);
+            let max_time = units::duration(result.max_duration);
+            let total_time = units::duration(result.duration);
 
             report.push_str(&format!(
                 "| {} | {} | {} | {} | {} | {} | {} |\n",
@@ -321,9 +376,9 @@ pub mod performance_analysis {
             .min_by_key(|r| r.avg_duration)
         {
             report.push_str(&format!(
-                "**Fastest Operation**: {} (avg: {:.2}μs)\n\n",
+                "**Fastest Operation**: {} (avg: {})\n\n",
                 fastest.name,
-                fastest.avg_duration.as_micros()
+                units::duration(fastest.avg_duration)
             ));
         }
 
@@ -333,9 +388,9 @@ pub mod performance_analysis {
             .max_by_key(|r| r.avg_duration)
         {
             report.push_str(&format!(
-                "**Slowest Operation**: {} (avg: {:.2}μs)\n\n",
+                "**Slowest Operation**: {} (avg: {})\n\n",
                 slowest.name,
-                slowest.avg_duration.as_micros()
+                units::duration(slowest.avg_duration)
             ));
         }
 
@@ -343,19 +398,379 @@ pub mod performance_analysis {
         report.push_str("## Optimization Recommendations\n\n");
 
         for result in results.iter().filter(|r| r.success) {
-            if result.avg_duration.as_micros() > 1000 {
+            if result.avg_duration > Duration::from_millis(1) {
                 report.push_str(&format!(
-                    "- **{}**: Consider optimization (avg: {:.2}μs)\n",
+                    "- **{}**: Consider optimization (avg: {})\n",
                     result.name,
-                    result.avg_duration.as_micros()
+                    units::duration(result.avg_duration)
                 ));
             }
         }
 
-        // Write to file
-        fs::create_dir_all("../../tests/reports").ok();
-        fs::write(output_path, &report)?;
+        report
+    }
+}
 
-        Ok(())
+/// Compute units measured by executing instructions
+///
+/// [`ComputeProfiler`](compute_profile::ComputeProfiler) sends each
+/// transaction through the [`SvmHarness`](crate::svm::SvmHarness) and reads
+/// every instruction's units from its `Program <id> consumed N of M compute
+/// units` log, so the numbers are what the programs' builds consume rather
+/// than estimates. A program the harness runs as a native processor logs
+/// units that say nothing about its SBF build; a profile that invoked one is
+/// unmetered, left out of the test's metrics and of the baselines.
+///
+/// Baselines are committed in `tests/baselines/compute_units.json`. A metered
+/// profile that consumes more than the tolerance above its baseline is a
+/// regression. After reviewing a change, accept its numbers by rerunning with
+/// `UPDATE_CU_BASELINES=1`.
+pub mod compute_profile {
+    use super::*;
+    use crate::{
+        metrics::TestMetrics,
+        suite_config::SuiteCategory,
+        svm::SvmHarness,
+        trace::{self, Invocation},
+        units,
+    };
+    use solana_sdk::{instruction::Instruction, signature::Keypair, transaction::TransactionError};
+    use std::{collections::BTreeMap, fs, path::Path};
+
+    /// The committed baselines
+    pub const BASELINE_PATH: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/baselines/compute_units.json"
+    );
+
+    /// Environment variable that makes checks overwrite the baselines
+    pub const UPDATE_ENV: &str = "UPDATE_CU_BASELINES";
+
+    /// How far above its baseline, in percent, a profile may go
+    pub const DEFAULT_TOLERANCE_PERCENT: u64 = 5;
+
+    /// One top-level instruction of a profiled transaction
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct InstructionUnits {
+        pub program: Pubkey,
+        /// Units the instruction consumed, its CPIs included; `None` when
+        /// the program logged none
+        pub compute_units: Option<u64>,
+        /// Whether the program and every program it invoked ran from an
+        /// SBF build
+        pub metered: bool,
+    }
+
+    /// The compute units of one transaction
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct ComputeProfile {
+        pub name: String,
+        pub instructions: Vec<InstructionUnits>,
+        /// Units the whole transaction consumed, as the bank reported them
+        pub compute_units: u64,
+    }
+
+    impl ComputeProfile {
+        /// Profile `name` from the logs of a transaction that consumed
+        /// `compute_units`, with `is_native` telling which programs ran as
+        /// native processors
+        pub fn from_logs(
+            name: &str,
+            logs: &[String],
+            compute_units: u64,
+            is_native: impl Fn(&Pubkey) -> bool,
+        ) -> Self {
+            let instructions = trace::invocations(logs)
+                .iter()
+                .map(|invocation| InstructionUnits {
+                    program: invocation.program,
+                    compute_units: invocation.compute_units.map(|(consumed, _)| consumed),
+                    metered: !invoked_natively(invocation, &is_native),
+                })
+                .collect();
+            Self {
+                name: name.to_string(),
+                instructions,
+                compute_units,
+            }
+        }
+
+        /// Whether every instruction ran from SBF builds only
+        pub fn is_metered(&self) -> bool {
+            self.instructions
+                .iter()
+                .all(|instruction| instruction.metered)
+        }
+
+        /// Units the instructions of `program` consumed
+        pub fn units_of(&self, program: &Pubkey) -> u64 {
+            self.instructions
+                .iter()
+                .filter(|instruction| instruction.program == *program)
+                .filter_map(|instruction| instruction.compute_units)
+                .sum()
+        }
+    }
+
+    fn invoked_natively(invocation: &Invocation, is_native: &impl Fn(&Pubkey) -> bool) -> bool {
+        is_native(&invocation.program)
+            || invocation
+                .cpis()
+                .any(|cpi| invoked_natively(cpi, is_native))
+    }
+
+    /// Executes transactions on a harness and keeps their profiles and the
+    /// metrics of the test running them
+    #[derive(Debug, Clone, Default)]
+    pub struct ComputeProfiler {
+        profiles: Vec<ComputeProfile>,
+        metrics: TestMetrics,
+    }
+
+    impl ComputeProfiler {
+        pub fn new(category: Option<SuiteCategory>) -> Self {
+            Self {
+                profiles: Vec::new(),
+                metrics: TestMetrics::new(category),
+            }
+        }
+
+        /// Execute `instructions` on `svm`, signed by its payer and
+        /// `signers`, and profile them as `name`
+        ///
+        /// The transaction's fee is recorded in the metrics, and its units
+        /// too when it is metered.
+        pub fn profile(
+            &mut self,
+            svm: &mut SvmHarness,
+            name: &str,
+            instructions: &[Instruction],
+            signers: &[&Keypair],
+        ) -> Result<&ComputeProfile, TransactionError> {
+            let executed = svm.send(instructions, signers)?;
+            let profile = ComputeProfile::from_logs(
+                name,
+                &executed.logs,
+                executed.compute_units,
+                |program| svm.is_native(program),
+            );
+            self.metrics.record_fee(svm.fee(signers.len() + 1));
+            if profile.is_metered() {
+                self.metrics.record_compute_units(profile.compute_units);
+            }
+            self.profiles.push(profile);
+            Ok(self.profiles.last().expect("a profile was just added"))
+        }
+
+        pub fn profiles(&self) -> &[ComputeProfile] {
+            &self.profiles
+        }
+
+        pub fn metrics(&self) -> &TestMetrics {
+            &self.metrics
+        }
+
+        /// The metrics to attach to the test's report
+        pub fn into_metrics(self) -> TestMetrics {
+            self.metrics
+        }
+    }
+
+    /// How a profile compares with its baseline
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum BaselineCheck {
+        /// It invoked a native processor, so there is nothing to compare
+        Unmetered,
+        /// No baseline is recorded for it
+        New,
+        Within {
+            baseline: u64,
+        },
+        /// Below the baseline by more than the tolerance; the baseline can
+        /// be lowered
+        Improved {
+            baseline: u64,
+        },
+        Regressed {
+            baseline: u64,
+        },
+    }
+
+    impl BaselineCheck {
+        pub fn is_regression(&self) -> bool {
+            matches!(self, BaselineCheck::Regressed { .. })
+        }
+
+        pub fn label(&self) -> &'static str {
+            match self {
+                BaselineCheck::Unmetered => "not metered",
+                BaselineCheck::New => "🆕 new",
+                BaselineCheck::Within { .. } => "✅ within",
+                BaselineCheck::Improved { .. } => "⬇️ improved",
+                BaselineCheck::Regressed { .. } => "❌ regressed",
+            }
+        }
+
+        pub fn baseline(&self) -> Option<u64> {
+            match self {
+                BaselineCheck::Within { baseline }
+                | BaselineCheck::Improved { baseline }
+                | BaselineCheck::Regressed { baseline } => Some(*baseline),
+                BaselineCheck::Unmetered | BaselineCheck::New => None,
+            }
+        }
+    }
+
+    /// Compute units per profile name
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub struct ComputeBaselines {
+        units: BTreeMap<String, u64>,
+    }
+
+    impl ComputeBaselines {
+        /// The baselines in `path`, none if the file does not exist
+        pub fn load(path: &Path) -> Result<Self, String> {
+            let json = match fs::read_to_string(path) {
+                Ok(json) => json,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+                Err(e) => return Err(format!("Reading {} failed: {}", path.display(), e)),
+            };
+            let units = serde_json::from_str(&json)
+                .map_err(|e| format!("{} is not a baseline file: {}", path.display(), e))?;
+            Ok(Self { units })
+        }
+
+        pub fn save(&self, path: &Path) -> Result<(), String> {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            let mut json = serde_json::to_string_pretty(&self.units).map_err(|e| e.to_string())?;
+            json.push('\n');
+            fs::write(path, json).map_err(|e| format!("Writing {} failed: {}", path.display(), e))
+        }
+
+        pub fn get(&self, name: &str) -> Option<u64> {
+            self.units.get(name).copied()
+        }
+
+        pub fn set(&mut self, name: &str, units: u64) {
+            self.units.insert(name.to_string(), units);
+        }
+
+        /// Compare `profile` with its baseline, allowing `tolerance_percent`
+        /// either way
+        pub fn check(&self, profile: &ComputeProfile, tolerance_percent: u64) -> BaselineCheck {
+            if !profile.is_metered() {
+                return BaselineCheck::Unmetered;
+            }
+            let Some(baseline) = self.get(&profile.name) else {
+                return BaselineCheck::New;
+            };
+            let tolerance = baseline * tolerance_percent / 100;
+            if profile.compute_units > baseline + tolerance {
+                BaselineCheck::Regressed { baseline }
+            } else if profile.compute_units + tolerance < baseline {
+                BaselineCheck::Improved { baseline }
+            } else {
+                BaselineCheck::Within { baseline }
+            }
+        }
+    }
+
+    /// Check `profiles` against the committed baselines, or make them the
+    /// baselines when [`UPDATE_ENV`] is set
+    pub fn check_baselines(
+        profiles: &[ComputeProfile],
+        tolerance_percent: u64,
+    ) -> Result<Vec<BaselineCheck>, String> {
+        let update = std::env::var(UPDATE_ENV).is_ok_and(|value| value != "0");
+        check_baselines_in(
+            Path::new(BASELINE_PATH),
+            profiles,
+            tolerance_percent,
+            update,
+        )
+    }
+
+    /// Check `profiles` against the baselines in `path`, recording every
+    /// metered one there first when `update` is set
+    ///
+    /// An error names every profile that regressed.
+    pub fn check_baselines_in(
+        path: &Path,
+        profiles: &[ComputeProfile],
+        tolerance_percent: u64,
+        update: bool,
+    ) -> Result<Vec<BaselineCheck>, String> {
+        let mut baselines = ComputeBaselines::load(path)?;
+        if update {
+            for profile in profiles.iter().filter(|profile| profile.is_metered()) {
+                baselines.set(&profile.name, profile.compute_units);
+            }
+            baselines.save(path)?;
+        }
+
+        let checks: Vec<BaselineCheck> = profiles
+            .iter()
+            .map(|profile| baselines.check(profile, tolerance_percent))
+            .collect();
+        let regressions: Vec<String> = profiles
+            .iter()
+            .zip(&checks)
+            .filter_map(|(profile, check)| match check {
+                BaselineCheck::Regressed { baseline } => Some(format!(
+                    "{} consumed {}, over its baseline of {}",
+                    profile.name,
+                    units::compute_units(profile.compute_units),
+                    units::compute_units(*baseline)
+                )),
+                _ => None,
+            })
+            .collect();
+        if !regressions.is_empty() {
+            return Err(format!(
+                "Compute units regressed beyond {}%: {}; rerun with {}=1 to accept",
+                tolerance_percent,
+                regressions.join("; "),
+                UPDATE_ENV
+            ));
+        }
+        Ok(checks)
+    }
+
+    /// Render `profiles` and their baseline checks as markdown
+    pub fn render_compute_profile(profiles: &[ComputeProfile], checks: &[BaselineCheck]) -> String {
+        let mut report = String::from("## Compute Profile\n\n");
+        report.push_str("| Profile | Instructions | Compute Units | Baseline | Status |\n");
+        report.push_str("|---------|--------------|---------------|----------|--------|\n");
+        for (profile, check) in profiles.iter().zip(checks) {
+            let instructions: Vec<String> = profile
+                .instructions
+                .iter()
+                .map(|instruction| {
+                    let units = instruction
+                        .compute_units
+                        .map_or_else(|| "-".to_string(), units::compute_units);
+                    format!("{} ({})", trace::program_name(&instruction.program), units)
+                })
+                .collect();
+            let compute_units = if profile.is_metered() {
+                units::compute_units(profile.compute_units)
+            } else {
+                "not metered".to_string()
+            };
+            report.push_str(&format!(
+                "| {} | {} | {} | {} | {} |\n",
+                profile.name,
+                instructions.join(", "),
+                compute_units,
+                check
+                    .baseline()
+                    .map_or_else(|| "-".to_string(), units::compute_units),
+                check.label()
+            ));
+        }
+        report.push('\n');
+        report
     }
 }
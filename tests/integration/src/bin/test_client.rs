@@ -0,0 +1,129 @@
+//! test-client
+//!
+//! ```text
+//! test-client --gate <PROGRAM_ID> [--rpc-url <URL>] [--keypair <PATH>] [SCENARIO...]
+//! test-client --list
+//! ```
+//!
+//! Runs the suite's narrative scenarios, see
+//! `token_acl_integration_tests::scenarios`, against a live cluster: devnet
+//! unless `--rpc-url` says otherwise, with the production allow list
+//! deployed at `--gate`. The keypair (`~/.config/solana/id.json` by default)
+//! pays for everything and acts as the issuer of the mints the scenarios
+//! create. Without scenario names, every scenario runs.
+
+use std::{path::PathBuf, process::ExitCode};
+
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig, pubkey::Pubkey, signature::read_keypair_file,
+};
+use token_acl_integration_tests::{rpc::DEVNET_URL, scenarios::Scenario};
+
+struct ClientArgs {
+    rpc_url: String,
+    keypair: PathBuf,
+    gate: Pubkey,
+    scenarios: Vec<Scenario>,
+}
+
+fn default_keypair() -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_default();
+    PathBuf::from(home).join(".config/solana/id.json")
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<ClientArgs, String> {
+    let mut rpc_url = DEVNET_URL.to_string();
+    let mut keypair = default_keypair();
+    let mut gate = None;
+    let mut scenarios = Vec::new();
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--rpc-url" => rpc_url = value()?,
+            "--keypair" => keypair = PathBuf::from(value()?),
+            "--gate" => {
+                let value = value()?;
+                gate = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("Invalid --gate value: {}", value))?,
+                );
+            }
+            name if !name.starts_with("--") => scenarios
+                .push(Scenario::parse(name).ok_or_else(|| format!("Unknown scenario {}", name))?),
+            _ => return Err(format!("Unknown flag {}", arg)),
+        }
+    }
+    if scenarios.is_empty() {
+        scenarios = Scenario::ALL.to_vec();
+    }
+
+    Ok(ClientArgs {
+        rpc_url,
+        keypair,
+        gate: gate.ok_or("--gate is required")?,
+        scenarios,
+    })
+}
+
+/// Run every scenario, reporting each; whether all of them passed
+async fn run(args: ClientArgs) -> Result<bool, String> {
+    let payer = read_keypair_file(&args.keypair)
+        .map_err(|e| format!("Failed to read {}: {}", args.keypair.display(), e))?;
+    let rpc = RpcClient::new_with_commitment(args.rpc_url.clone(), CommitmentConfig::confirmed());
+    println!("Running against {} with gate {}", args.rpc_url, args.gate);
+
+    let mut passed = true;
+    for scenario in args.scenarios {
+        println!("\n▶ {} ({})", scenario.title(), scenario.name());
+        match scenario.run(&rpc, &payer, args.gate).await {
+            Ok(outcome) => {
+                for step in &outcome.steps {
+                    println!("  • {}", step);
+                }
+                println!(
+                    "✅ {}: {} checks passed on mint {}",
+                    scenario.title(),
+                    outcome.checks,
+                    outcome.mint
+                );
+            }
+            Err(e) => {
+                println!("❌ {}: {}", scenario.title(), e);
+                passed = false;
+            }
+        }
+    }
+    Ok(passed)
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1).peekable();
+    if args.peek().map(String::as_str) == Some("--list") {
+        for scenario in Scenario::ALL {
+            println!("{:<18} {}", scenario.name(), scenario.title());
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    match parse_args(args) {
+        Ok(args) => match run(args).await {
+            Ok(true) => ExitCode::SUCCESS,
+            Ok(false) => ExitCode::FAILURE,
+            Err(e) => {
+                eprintln!("{}", e);
+                ExitCode::FAILURE
+            }
+        },
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!(
+                "Usage: test-client --gate <PROGRAM_ID> [--rpc-url <URL>] [--keypair <PATH>] [SCENARIO...]"
+            );
+            ExitCode::from(2)
+        }
+    }
+}
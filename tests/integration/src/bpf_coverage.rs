@@ -0,0 +1,370 @@
+//! Execution coverage of SBF programs
+//!
+//! Runs instructions against a program's SBF build with instruction tracing
+//! on, the way Mollusk does: a mock invoke context holding the accounts the
+//! instruction names, with the BPF loader and the system program as the
+//! only builtins. Every traced program counter is mapped onto the basic
+//! blocks of the program's control-flow graph, so coverage counts on-chain
+//! code executed rather than tests named after it.
+//!
+//! Gate programs only exist as SBF builds after `cargo build-sbf`, or under
+//! `cargo test-sbf`; [`find_sbf_program`] looks where those put them.
+//! Token-2022 is always available from the builds program-test bundles.
+
+use solana_bpf_loader_program::{
+    load_program_from_bytes, syscalls::create_program_runtime_environment_v1,
+};
+use solana_program_runtime::{
+    loaded_programs::{
+        LoadProgramMetrics, LoadedProgram, LoadedProgramType, DELAY_VISIBILITY_SLOT_OFFSET,
+    },
+    solana_rbpf::static_analysis::Analysis,
+    timings::ExecuteTimings,
+    with_mock_invoke_context,
+};
+use solana_program_test::BanksClient;
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    bpf_loader, bpf_loader_upgradeable,
+    bpf_loader_upgradeable::UpgradeableLoaderState,
+    clock::Clock,
+    instruction::{Instruction, InstructionError},
+    native_loader,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_program, sysvar,
+    transaction_context::InstructionAccount,
+};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+/// Where SBF builds of a program named `name` may be, in search order
+fn sbf_search_paths(name: &str) -> Vec<PathBuf> {
+    let file = format!("{}.so", name);
+    let mut paths: Vec<PathBuf> = ["SBF_OUT_DIR", "BPF_OUT_DIR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .map(|dir| PathBuf::from(dir).join(&file))
+        .collect();
+    paths.push(PathBuf::from("../../target/deploy").join(&file));
+    paths
+}
+
+/// The SBF build of the program named `name`, if one has been built
+pub fn find_sbf_program(name: &str) -> Option<Vec<u8>> {
+    sbf_search_paths(name)
+        .into_iter()
+        .find_map(|path| std::fs::read(path).ok())
+}
+
+/// Name Token-2022's coverage is recorded under
+pub const SPL_TOKEN_2022: &str = "spl_token_2022";
+
+/// The Token-2022 build program-test runs
+pub fn spl_token_2022_elf() -> Vec<u8> {
+    let (programdata, _) = Pubkey::find_program_address(
+        &[spl_token_2022::id().as_ref()],
+        &bpf_loader_upgradeable::id(),
+    );
+    solana_program_test::programs::spl_programs(&Rent::default())
+        .into_iter()
+        .find(|(address, _)| *address == programdata)
+        .map(|(_, account)| {
+            Account::from(account).data[UpgradeableLoaderState::size_of_programdata_metadata()..]
+                .to_vec()
+        })
+        .expect("program-test bundles Token-2022")
+}
+
+/// Every account `instruction` names, as the bank holds them now; accounts
+/// that do not exist are passed empty, as the runtime would
+pub async fn instruction_accounts(
+    banks_client: &mut BanksClient,
+    instruction: &Instruction,
+) -> Result<Vec<(Pubkey, Account)>, String> {
+    let mut accounts = Vec::new();
+    for meta in &instruction.accounts {
+        if accounts.iter().any(|(address, _)| *address == meta.pubkey) {
+            continue;
+        }
+        let account = banks_client
+            .get_account(meta.pubkey)
+            .await
+            .map_err(|e| e.to_string())?
+            .unwrap_or_default();
+        accounts.push((meta.pubkey, account));
+    }
+    Ok(accounts)
+}
+
+/// One traced execution
+#[derive(Debug, Clone)]
+pub struct ExecutionTrace {
+    pub result: Result<(), InstructionError>,
+    /// Program counters executed, in instruction slots, in order
+    pub program_counters: Vec<usize>,
+    pub logs: Vec<String>,
+}
+
+/// A program's SBF build and the basic blocks of its text section
+pub struct SbfProgram {
+    pub name: String,
+    pub program_id: Pubkey,
+    elf: Vec<u8>,
+    /// Start of every basic block
+    blocks: BTreeSet<usize>,
+}
+
+impl SbfProgram {
+    /// Verify `elf` and build its control-flow graph
+    pub fn from_elf(name: &str, program_id: Pubkey, elf: Vec<u8>) -> Result<Self, String> {
+        let loaded = load_traced(&elf)?;
+        let blocks = match &loaded.program {
+            LoadedProgramType::LegacyV1(executable) => Analysis::from_executable(executable)
+                .map_err(|e| format!("{} does not analyze: {}", name, e))?
+                .cfg_nodes
+                .keys()
+                .copied()
+                .collect(),
+            _ => return Err(format!("{} did not load as an SBF program", name)),
+        };
+        Ok(Self {
+            name: name.to_string(),
+            program_id,
+            elf,
+            blocks,
+        })
+    }
+
+    pub fn total_blocks(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Coverage of this program with nothing executed yet
+    pub fn coverage(&self) -> ProgramCoverage {
+        ProgramCoverage {
+            program: self.name.clone(),
+            total_blocks: self.blocks.len(),
+            executed_blocks: BTreeSet::new(),
+            instructions_traced: 0,
+        }
+    }
+
+    /// The basic block `pc` is in
+    fn block_of(&self, pc: usize) -> Option<usize> {
+        self.blocks.range(..=pc).next_back().copied()
+    }
+
+    /// Run `instruction` with `accounts` and trace it; an instruction the
+    /// program rejects is still a trace
+    pub fn trace(
+        &self,
+        instruction: &Instruction,
+        accounts: &[(Pubkey, Account)],
+    ) -> Result<ExecutionTrace, String> {
+        if instruction.program_id != self.program_id {
+            return Err(format!(
+                "Instruction is for {}, not {}",
+                instruction.program_id, self.name
+            ));
+        }
+        let mut transaction_accounts: Vec<(Pubkey, AccountSharedData)> = accounts
+            .iter()
+            .map(|(address, account)| (*address, account.clone().into()))
+            .collect();
+        let mut add = |address: Pubkey, account: Account| {
+            if !transaction_accounts.iter().any(|(key, _)| *key == address) {
+                transaction_accounts.push((address, account.into()));
+            }
+        };
+        add(
+            sysvar::clock::id(),
+            Account::new_data(1, &Clock::default(), &sysvar::id()).unwrap(),
+        );
+        add(
+            sysvar::rent::id(),
+            Account::new_data(1, &Rent::default(), &sysvar::id()).unwrap(),
+        );
+        add(system_program::id(), native_program());
+        add(
+            self.program_id,
+            Account {
+                lamports: 1,
+                data: self.elf.clone(),
+                owner: bpf_loader::id(),
+                executable: true,
+                rent_epoch: 0,
+            },
+        );
+        add(bpf_loader::id(), native_program());
+
+        let index_of = |address: &Pubkey| {
+            transaction_accounts
+                .iter()
+                .position(|(key, _)| key == address)
+                .unwrap() as u16
+        };
+        let instruction_accounts: Vec<InstructionAccount> = instruction
+            .accounts
+            .iter()
+            .enumerate()
+            .map(|(position, meta)| {
+                let index_in_transaction = index_of(&meta.pubkey);
+                InstructionAccount {
+                    index_in_transaction,
+                    index_in_caller: index_in_transaction,
+                    index_in_callee: instruction.accounts[..position]
+                        .iter()
+                        .position(|earlier| earlier.pubkey == meta.pubkey)
+                        .unwrap_or(position) as u16,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                }
+            })
+            .collect();
+        let program_indices = [index_of(&bpf_loader::id()), index_of(&self.program_id)];
+        let program = load_traced(&self.elf)?;
+
+        with_mock_invoke_context!(invoke_context, transaction_context, transaction_accounts);
+        let mut builtins = LoadedProgramsForTxBatch::default();
+        builtins.replenish(
+            bpf_loader::id(),
+            Arc::new(LoadedProgram::new_builtin(
+                0,
+                0,
+                solana_bpf_loader_program::Entrypoint::vm,
+            )),
+        );
+        builtins.replenish(
+            system_program::id(),
+            Arc::new(LoadedProgram::new_builtin(
+                0,
+                0,
+                solana_system_program::system_processor::Entrypoint::vm,
+            )),
+        );
+        invoke_context.programs_loaded_for_tx_batch = &builtins;
+        invoke_context
+            .programs_modified_by_tx
+            .set_slot_for_tests(DELAY_VISIBILITY_SLOT_OFFSET);
+        invoke_context
+            .programs_modified_by_tx
+            .replenish(self.program_id, Arc::new(program));
+
+        let result = invoke_context.process_instruction(
+            &instruction.data,
+            &instruction_accounts,
+            &program_indices,
+            &mut 0,
+            &mut ExecuteTimings::default(),
+        );
+        let program_counters = invoke_context
+            .get_traces()
+            .iter()
+            .flatten()
+            .map(|registers| registers[11] as usize)
+            .collect();
+        let logs = invoke_context
+            .get_log_collector()
+            .map(|collector| collector.borrow().get_recorded_content().to_vec())
+            .unwrap_or_default();
+        Ok(ExecutionTrace {
+            result,
+            program_counters,
+            logs,
+        })
+    }
+
+    /// Trace `instruction` and count it against `coverage`
+    pub fn trace_into(
+        &self,
+        coverage: &mut ProgramCoverage,
+        instruction: &Instruction,
+        accounts: &[(Pubkey, Account)],
+    ) -> Result<ExecutionTrace, String> {
+        let trace = self.trace(instruction, accounts)?;
+        coverage.instructions_traced += 1;
+        coverage.executed_blocks.extend(
+            trace
+                .program_counters
+                .iter()
+                .filter_map(|&pc| self.block_of(pc)),
+        );
+        Ok(trace)
+    }
+}
+
+/// A builtin's program account
+fn native_program() -> Account {
+    Account {
+        lamports: 1,
+        data: Vec::new(),
+        owner: native_loader::id(),
+        executable: true,
+        rent_epoch: 0,
+    }
+}
+
+/// Load `elf` into a runtime environment with instruction tracing on
+fn load_traced(elf: &[u8]) -> Result<LoadedProgram, String> {
+    let environment = create_program_runtime_environment_v1(
+        &solana_sdk::feature_set::FeatureSet::all_enabled(),
+        &solana_program_runtime::compute_budget::ComputeBudget::default(),
+        false,
+        true,
+    )
+    .map_err(|e| e.to_string())?;
+    load_program_from_bytes(
+        None,
+        &mut LoadProgramMetrics::default(),
+        elf,
+        &bpf_loader::id(),
+        elf.len(),
+        0,
+        std::sync::Arc::new(environment),
+        false,
+    )
+    .map_err(|e| format!("Program does not load: {}", e))
+}
+
+/// Basic blocks of one program executed by the suite
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgramCoverage {
+    pub program: String,
+    pub total_blocks: usize,
+    /// Start of every basic block executed at least once
+    pub executed_blocks: BTreeSet<usize>,
+    pub instructions_traced: usize,
+}
+
+impl ProgramCoverage {
+    /// Coverage of a program that has no SBF build to trace
+    pub fn not_built(program: &str) -> Self {
+        Self {
+            program: program.to_string(),
+            total_blocks: 0,
+            executed_blocks: BTreeSet::new(),
+            instructions_traced: 0,
+        }
+    }
+
+    pub fn is_built(&self) -> bool {
+        self.total_blocks > 0
+    }
+
+    /// Share of the program's basic blocks executed
+    pub fn percentage(&self) -> f64 {
+        if self.total_blocks == 0 {
+            return 0.0;
+        }
+        self.executed_blocks.len() as f64 / self.total_blocks as f64 * 100.0
+    }
+
+    /// Add what `other` executed of the same program
+    pub fn merge(&mut self, other: &ProgramCoverage) {
+        self.total_blocks = self.total_blocks.max(other.total_blocks);
+        self.executed_blocks
+            .extend(other.executed_blocks.iter().copied());
+        self.instructions_traced += other.instructions_traced;
+    }
+}
@@ -0,0 +1,172 @@
+//! Compute budget guidance
+//!
+//! Integrators set a compute-unit limit on every Token ACL transaction; too
+//! low and it fails, too high and priority fees are paid on units never
+//! used. [`BudgetGuidance`] collects the units each operation consumed on
+//! the bank and recommends a limit with a safety margin, written out as the
+//! compute budget guidance artifact on every benchmark run.
+//!
+//! program-test runs the gate programs natively unless their SBF builds
+//! are loaded (`cargo test-sbf`), and native execution is not metered, so
+//! every sample records whether it was. Operations with unmetered samples
+//! get no recommendation until they are measured under SBF.
+
+use serde::Serialize;
+use solana_program_runtime::compute_budget_processor::MAX_COMPUTE_UNIT_LIMIT;
+use std::{fs, path::Path};
+
+use crate::{badges, time, units};
+
+/// Headroom added over the most expensive sample, in percent
+pub const SAFETY_MARGIN_PERCENT: u64 = 20;
+
+/// Recommended limits are rounded up to a multiple of this
+pub const LIMIT_GRANULARITY: u64 = 1_000;
+
+/// Whether program-test loads SBF builds instead of native processors
+///
+/// `cargo test-sbf` sets `SBF_OUT_DIR`, which is also what makes
+/// program-test prefer the SBF builds.
+pub fn programs_metered() -> bool {
+    std::env::var("SBF_OUT_DIR").is_ok() || std::env::var("BPF_OUT_DIR").is_ok()
+}
+
+/// Compute units one operation consumed over its samples
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OperationUnits {
+    pub operation: String,
+    pub samples: Vec<u64>,
+    /// Every sample ran only metered programs
+    pub metered: bool,
+}
+
+impl OperationUnits {
+    pub fn max(&self) -> u64 {
+        self.samples.iter().copied().max().unwrap_or_default()
+    }
+
+    /// The limit to request: the most expensive sample plus
+    /// [`SAFETY_MARGIN_PERCENT`], rounded up to [`LIMIT_GRANULARITY`];
+    /// `None` while any sample was unmetered
+    pub fn recommended_limit(&self) -> Option<u32> {
+        if !self.metered || self.samples.is_empty() {
+            return None;
+        }
+        Some(limit_with_margin(self.max()))
+    }
+}
+
+/// The limit to request for a transaction consuming `units`: plus
+/// [`SAFETY_MARGIN_PERCENT`], rounded up to [`LIMIT_GRANULARITY`] and capped
+/// at the transaction maximum
+pub fn limit_with_margin(units: u64) -> u32 {
+    let with_margin = (units * (100 + SAFETY_MARGIN_PERCENT)).div_ceil(100);
+    let rounded = with_margin.div_ceil(LIMIT_GRANULARITY) * LIMIT_GRANULARITY;
+    rounded.min(u64::from(MAX_COMPUTE_UNIT_LIMIT)) as u32
+}
+
+/// Per-operation compute units and the limits they imply
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BudgetGuidance {
+    pub operations: Vec<OperationUnits>,
+}
+
+impl BudgetGuidance {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record one run of `operation`; `metered` is false if any program it
+    /// executed ran natively
+    pub fn record(&mut self, operation: &str, units: u64, metered: bool) {
+        match self
+            .operations
+            .iter_mut()
+            .find(|entry| entry.operation == operation)
+        {
+            Some(entry) => {
+                entry.samples.push(units);
+                entry.metered &= metered;
+            }
+            None => self.operations.push(OperationUnits {
+                operation: operation.to_string(),
+                samples: vec![units],
+                metered,
+            }),
+        }
+    }
+
+    pub fn get(&self, operation: &str) -> Option<&OperationUnits> {
+        self.operations
+            .iter()
+            .find(|entry| entry.operation == operation)
+    }
+
+    /// Render the guidance as markdown
+    pub fn to_markdown(&self) -> String {
+        let mut report = String::from("# Compute Budget Guidance\n\n");
+        report.push_str(&format!("**Generated**: {}\n\n", time::report_timestamp()));
+        report.push_str(&format!(
+            "Recommended limits are the most expensive run plus {}%, rounded up to {}.\n\n",
+            SAFETY_MARGIN_PERCENT,
+            units::compute_units(LIMIT_GRANULARITY)
+        ));
+        report.push_str("| Operation | Runs | Max | Recommended Limit |\n");
+        report.push_str("|-----------|------|-----|-------------------|\n");
+        for entry in &self.operations {
+            let limit = match entry.recommended_limit() {
+                Some(limit) => units::compute_units(u64::from(limit)),
+                None => "not metered; run `cargo test-sbf`".to_string(),
+            };
+            report.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                entry.operation,
+                entry.samples.len(),
+                units::compute_units(entry.max()),
+                limit
+            ));
+        }
+        report
+    }
+
+    /// The guidance as JSON, recommended limits included
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        #[derive(Serialize)]
+        struct Entry<'a> {
+            #[serde(flatten)]
+            units: &'a OperationUnits,
+            recommended_limit: Option<u32>,
+        }
+        let entries: Vec<_> = self
+            .operations
+            .iter()
+            .map(|units| Entry {
+                units,
+                recommended_limit: units.recommended_limit(),
+            })
+            .collect();
+        serde_json::to_string_pretty(&entries)
+    }
+}
+
+/// Write `guidance` as markdown to `markdown_path` and as JSON to `json_path`,
+/// and the reference gate's thaw to its badge
+pub fn generate_budget_guidance(
+    guidance: &BudgetGuidance,
+    markdown_path: &str,
+    json_path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all("../../tests/reports").ok();
+    fs::write(markdown_path, guidance.to_markdown())?;
+    fs::write(json_path, guidance.to_json()?)?;
+
+    let thaw = format!("Permissionless thaw ({})", badges::REFERENCE_GATE);
+    if let Some(units) = guidance.get(&thaw) {
+        badges::write_badge(
+            Path::new(badges::BADGES_DIR),
+            "thaw_compute_units",
+            &badges::Badge::compute_units("thaw", units),
+        )?;
+    }
+    Ok(())
+}
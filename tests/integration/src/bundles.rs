@@ -0,0 +1,195 @@
+//! Ordered multi-transaction bundles
+//!
+//! Some flows do not fit in one transaction, e.g. creating an account,
+//! thawing it through a gate with many extra accounts, and making the first
+//! transfer. This module splits such a flow into named, ordered steps and
+//! emits one transaction per step.
+//!
+//! Submitted through a bundle engine (such as Jito's block engine), the
+//! transactions land all-or-nothing. Submitted one by one, which is also what
+//! happens when a bundle is dropped and the wallet falls back to plain RPC,
+//! a failure at any seam leaves the earlier steps landed and the later ones
+//! skipped. [`submit_sequential`] reports exactly that partial state, so tests
+//! can document what a wallet sees at every seam.
+
+use crate::onboarding;
+use solana_program_test::BanksClient;
+use solana_sdk::{
+    hash::Hash,
+    instruction::Instruction,
+    message::Message,
+    packet::PACKET_DATA_SIZE,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+
+/// Most transactions a Jito bundle may carry
+pub const MAX_BUNDLE_TRANSACTIONS: usize = 5;
+
+/// One named transaction of a bundle
+#[derive(Debug, Clone)]
+pub struct BundleTransaction {
+    pub step: String,
+    pub transaction: Transaction,
+}
+
+/// Ordered steps of a flow that spans several transactions
+#[derive(Debug, Clone)]
+pub struct TransactionBundle {
+    payer: Pubkey,
+    steps: Vec<(String, Vec<Instruction>)>,
+    tip: Option<(Pubkey, u64)>,
+}
+
+impl TransactionBundle {
+    /// Start an empty bundle whose fees are paid by `payer`
+    pub fn new(payer: Pubkey) -> Self {
+        Self {
+            payer,
+            steps: Vec::new(),
+            tip: None,
+        }
+    }
+
+    /// Append a step executed as its own transaction
+    pub fn step(mut self, name: &str, instructions: Vec<Instruction>) -> Self {
+        self.steps.push((name.to_string(), instructions));
+        self
+    }
+
+    /// Pay a bundle engine tip from the payer in the last transaction, so the
+    /// tip is only paid if every step lands
+    pub fn with_tip(mut self, tip_account: Pubkey, lamports: u64) -> Self {
+        self.tip = Some((tip_account, lamports));
+        self
+    }
+
+    /// Names of the steps, in order
+    pub fn step_names(&self) -> Vec<String> {
+        self.steps.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    /// Build the unsigned transactions, in order
+    ///
+    /// Fails if the bundle is empty, has more than
+    /// [`MAX_BUNDLE_TRANSACTIONS`] steps, or any step exceeds the packet size
+    /// limit.
+    pub fn build(&self, recent_blockhash: Hash) -> Result<Vec<BundleTransaction>, String> {
+        if self.steps.is_empty() {
+            return Err("Bundle has no steps".to_string());
+        }
+        if self.steps.len() > MAX_BUNDLE_TRANSACTIONS {
+            return Err(format!(
+                "Bundle has {} steps, above the {} transaction limit",
+                self.steps.len(),
+                MAX_BUNDLE_TRANSACTIONS
+            ));
+        }
+
+        let last = self.steps.len() - 1;
+        self.steps
+            .iter()
+            .enumerate()
+            .map(|(index, (step, instructions))| {
+                let mut instructions = instructions.clone();
+                if let (true, Some((tip_account, lamports))) = (index == last, self.tip) {
+                    instructions.push(system_instruction::transfer(
+                        &self.payer,
+                        &tip_account,
+                        lamports,
+                    ));
+                }
+
+                let message = Message::new_with_blockhash(
+                    &instructions,
+                    Some(&self.payer),
+                    &recent_blockhash,
+                );
+                let transaction = Transaction::new_unsigned(message);
+                let size = onboarding::signed_size(&transaction);
+                if size > PACKET_DATA_SIZE {
+                    return Err(format!(
+                        "Step '{}' is {} bytes, above the {} byte limit",
+                        step, size, PACKET_DATA_SIZE
+                    ));
+                }
+                Ok(BundleTransaction {
+                    step: step.clone(),
+                    transaction,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Sign every transaction with whichever of `signers` it requires
+///
+/// Fails if any transaction is left without a required signature.
+pub fn sign_bundle(
+    bundle: &mut [BundleTransaction],
+    signers: &[&Keypair],
+    recent_blockhash: Hash,
+) -> Result<(), String> {
+    for entry in bundle.iter_mut() {
+        let message = &entry.transaction.message;
+        let required = &message.account_keys[..message.header.num_required_signatures as usize];
+        let relevant: Vec<&Keypair> = signers
+            .iter()
+            .copied()
+            .filter(|signer| required.contains(&signer.pubkey()))
+            .collect();
+        entry
+            .transaction
+            .try_partial_sign(&relevant, recent_blockhash)
+            .map_err(|e| format!("Signing step '{}' failed: {}", entry.step, e))?;
+        if !entry.transaction.is_signed() {
+            return Err(format!("Step '{}' is missing a signature", entry.step));
+        }
+    }
+    Ok(())
+}
+
+/// What a sequential submission left behind
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BundleOutcome {
+    /// Steps that landed, in order
+    pub landed: Vec<String>,
+    /// The step that failed and its error
+    pub failed: Option<(String, String)>,
+    /// Steps never submitted because an earlier one failed
+    pub skipped: Vec<String>,
+}
+
+impl BundleOutcome {
+    /// Every step landed
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_none()
+    }
+
+    /// Some, but not all, steps landed: the state a bundle engine would have
+    /// rolled back
+    pub fn is_partial(&self) -> bool {
+        self.failed.is_some() && !self.landed.is_empty()
+    }
+}
+
+/// Submit the transactions one by one, stopping at the first failure
+pub async fn submit_sequential(
+    banks_client: &mut BanksClient,
+    bundle: Vec<BundleTransaction>,
+) -> BundleOutcome {
+    let mut outcome = BundleOutcome::default();
+    for entry in bundle {
+        if outcome.failed.is_some() {
+            outcome.skipped.push(entry.step);
+            continue;
+        }
+        match banks_client.process_transaction(entry.transaction).await {
+            Ok(()) => outcome.landed.push(entry.step),
+            Err(e) => outcome.failed = Some((entry.step, e.to_string())),
+        }
+    }
+    outcome
+}
@@ -8,7 +8,10 @@
 //! - **Test Result Reporting**: Standardized test result structure with detailed reporting
 //! - **Common Utilities**: Helper functions for PDA derivation, keypair generation, and validation
 //! - **Assertion Helpers**: Reusable assertion functions with detailed error messages
-//! - **Report Generation**: Comprehensive test report generation with markdown output
+//! - **Report Generation**: Comprehensive test report generation with markdown and HTML output
+//! - **Durable Nonces**: Building, offline signing, and submitting nonce-anchored transactions
+//! - **Program Test Harness**: The gate programs deployed in `solana-program-test`, driven by real transactions
+//! - **SVM Harness**: The same programs executed synchronously on an in-process bank, LiteSVM style
 //!
 //! # Examples
 //!
@@ -24,9 +27,40 @@
 //! assert!(!result.passed);
 //! ```
 
-use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{clock::Slot, pubkey::Pubkey, signature::Signature};
 use std::fmt;
 
+use crate::{
+    metrics::TestMetrics,
+    suite_config::SuiteCategory,
+    trace::{self, TransactionTrace},
+};
+
+/// A transaction a test submitted, kept so failures can be inspected in an
+/// explorer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionRecord {
+    /// What the transaction did in the test, e.g. "add holder"
+    pub label: String,
+    pub signature: Signature,
+    /// Slot it was processed in, if known
+    pub slot: Option<Slot>,
+    /// How it executed, for failed transactions traced by the suite
+    pub trace: Option<TransactionTrace>,
+}
+
+impl TransactionRecord {
+    /// The record of a failed transaction, carrying its trace
+    pub fn failed(trace: TransactionTrace) -> Self {
+        Self {
+            label: "failed transaction".to_string(),
+            signature: trace.signature,
+            slot: None,
+            trace: Some(trace),
+        }
+    }
+}
+
 /// Standardized test result reporting structure
 ///
 /// This structure provides a consistent way to report test results across
@@ -39,10 +73,15 @@ use std::fmt;
 /// - `passed`: Whether the test passed or failed
 /// - `error`: Optional error message if the test failed
 /// - `assertions_run`: Number of assertions that were executed during the test
+/// - `transactions`: Transactions the test submitted, in order, then the
+///   last failed transaction's trace on a failure
+/// - `skipped`: The disabled category, if the test did not run
+/// - `metrics`: Lamports the test spent, if it tracked them
 ///
 /// # Examples
 ///
 /// ```rust
+/// # use token_acl_integration_tests::TestResultReport;
 /// let result = TestResultReport::success("PDA Derivation Test", 3);
 /// println!("Test result: {}", result);
 /// ```
@@ -56,6 +95,13 @@ pub struct TestResultReport {
     pub error: Option<String>,
     /// Number of assertions that were executed during the test
     pub assertions_run: usize,
+    /// Transactions the test submitted, in order, then the last failed
+    /// transaction's trace on a failure
+    pub transactions: Vec<TransactionRecord>,
+    /// Set when the test did not run because its category is disabled
+    pub skipped: Option<SuiteCategory>,
+    /// Fees and rent the test spent; empty if it did not track them
+    pub metrics: TestMetrics,
 }
 
 impl TestResultReport {
@@ -73,16 +119,23 @@ impl TestResultReport {
     /// # Examples
     ///
     /// ```rust
+    /// # use token_acl_integration_tests::TestResultReport;
     /// let result = TestResultReport::success("PDA Derivation Test", 3);
     /// assert!(result.passed);
     /// assert_eq!(result.assertions_run, 3);
     /// ```
     pub fn success(name: &str, assertions: usize) -> Self {
+        // Transactions a passing test expected to fail stay out of the
+        // next test's report
+        trace::take_failure();
         Self {
             name: name.to_string(),
             passed: true,
             error: None,
             assertions_run: assertions,
+            transactions: Vec::new(),
+            skipped: None,
+            metrics: TestMetrics::default(),
         }
     }
 
@@ -95,11 +148,14 @@ impl TestResultReport {
     ///
     /// # Returns
     ///
-    /// A `TestResultReport` with `passed` set to `false` and the provided error message.
+    /// A `TestResultReport` with `passed` set to `false` and the provided error message,
+    /// carrying the trace of the last transaction that failed on this thread (see
+    /// [`crate::trace`]).
     ///
     /// # Examples
     ///
     /// ```rust
+    /// # use token_acl_integration_tests::TestResultReport;
     /// let result = TestResultReport::failure("PDA Test", "Invalid seeds provided".to_string());
     /// assert!(!result.passed);
     /// assert!(result.error.is_some());
@@ -110,22 +166,106 @@ impl TestResultReport {
             passed: false,
             error: Some(error),
             assertions_run: 0,
+            transactions: trace::take_failure()
+                .map(TransactionRecord::failed)
+                .into_iter()
+                .collect(),
+            skipped: None,
+            metrics: TestMetrics::default(),
+        }
+    }
+
+    /// Create a result for a test that did not run because `category` is
+    /// disabled
+    ///
+    /// Skipped tests count as passed so their `#[test]` wrappers succeed, but
+    /// reports show them separately.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use token_acl_integration_tests::{suite_config::SuiteCategory, TestResultReport};
+    /// let result = TestResultReport::skipped("Load Test", SuiteCategory::LoadTests);
+    /// assert!(result.passed);
+    /// assert_eq!(result.status(), "SKIP");
+    /// ```
+    pub fn skipped(name: &str, category: SuiteCategory) -> Self {
+        Self {
+            name: name.to_string(),
+            passed: true,
+            error: None,
+            assertions_run: 0,
+            transactions: Vec::new(),
+            skipped: Some(category),
+            metrics: TestMetrics::new(Some(category)),
         }
     }
 
+    /// Attach the transactions the test submitted, ahead of any traced
+    /// failure
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use solana_sdk::signature::Signature;
+    /// # use token_acl_integration_tests::{TestResultReport, TransactionRecord};
+    /// let record = TransactionRecord {
+    ///     label: "add holder".to_string(),
+    ///     signature: Signature::default(),
+    ///     slot: Some(42),
+    ///     trace: None,
+    /// };
+    /// let result = TestResultReport::success("Add Test", 1).with_transactions(vec![record]);
+    /// assert_eq!(result.transactions.len(), 1);
+    /// ```
+    pub fn with_transactions(mut self, transactions: Vec<TransactionRecord>) -> Self {
+        self.transactions.splice(0..0, transactions);
+        self
+    }
+
+    /// Attach the fees and rent the test spent
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use token_acl_integration_tests::{metrics::TestMetrics, TestResultReport};
+    /// let mut metrics = TestMetrics::default();
+    /// metrics.record_fee(5_000);
+    /// let result = TestResultReport::success("Add Test", 1).with_metrics(metrics);
+    /// assert_eq!(result.metrics.cost.fees, 5_000);
+    /// ```
+    pub fn with_metrics(mut self, metrics: TestMetrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
     /// Get a human-readable status string
     ///
     /// # Returns
     ///
-    /// Returns "PASS" if the test passed, "FAIL" if it failed.
+    /// Returns "SKIP" if the test did not run, "PASS" if it passed, "FAIL"
+    /// if it failed.
     pub fn status(&self) -> &'static str {
-        if self.passed {
+        if self.skipped.is_some() {
+            "SKIP"
+        } else if self.passed {
             "PASS"
         } else {
             "FAIL"
         }
     }
 
+    /// Status with its report icon, e.g. "✅ PASS"
+    pub fn status_label(&self) -> &'static str {
+        if self.skipped.is_some() {
+            "⏭️ SKIP"
+        } else if self.passed {
+            "✅ PASS"
+        } else {
+            "❌ FAIL"
+        }
+    }
+
     /// Check if the test passed
     ///
     /// # Returns
@@ -147,12 +287,14 @@ impl TestResultReport {
 
 impl fmt::Display for TestResultReport {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let status = if self.passed { "✅ PASS" } else { "❌ FAIL" };
         let error = self.error.as_deref().unwrap_or("-");
         write!(
             f,
             "{} | {} | {} | {}",
-            self.name, status, self.assertions_run, error
+            self.name,
+            self.status_label(),
+            self.assertions_run,
+            error
         )
     }
 }
@@ -163,7 +305,7 @@ impl fmt::Display for TestResultReport {
 /// for keypair generation, PDA derivation, and validation operations.
 pub mod utils {
     use super::*;
-    use solana_sdk::signature::Keypair;
+    use solana_sdk::signature::{keypair_from_seed, Keypair};
 
     /// Generate a test keypair with a deterministic seed
     ///
@@ -183,6 +325,8 @@ pub mod utils {
     /// # Examples
     ///
     /// ```rust
+    /// # use solana_sdk::signature::Signer;
+    /// # use token_acl_integration_tests::utils;
     /// let keypair = utils::create_test_keypair(b"test_seed");
     /// assert!(!keypair.pubkey().to_bytes().iter().all(|&b| b == 0));
     /// ```
@@ -190,7 +334,7 @@ pub mod utils {
         let mut seed_bytes = [0u8; 32];
         let copy_len = seed.len().min(32);
         seed_bytes[..copy_len].copy_from_slice(&seed[..copy_len]);
-        Keypair::from_bytes(&seed_bytes)
+        keypair_from_seed(&seed_bytes)
             .expect("Failed to create keypair from seed - this should not happen with valid seeds")
     }
 
@@ -209,10 +353,12 @@ pub mod utils {
     /// # Examples
     ///
     /// ```rust
+    /// # use solana_sdk::pubkey::Pubkey;
+    /// # use token_acl_integration_tests::utils;
     /// let program_id = Pubkey::new_unique();
-    /// let seeds = [b"test_seed"];
-    /// let (expected_pda, _) = Pubkey::find_program_address(&seeds, &program_id);
-    /// assert!(utils::verify_pda_derivation(&seeds, &program_id, &expected_pda));
+    /// let seeds: &[&[u8]] = &[b"test_seed"];
+    /// let (expected_pda, _) = Pubkey::find_program_address(seeds, &program_id);
+    /// assert!(utils::verify_pda_derivation(seeds, &program_id, &expected_pda));
     /// ```
     pub fn verify_pda_derivation(
         seeds: &[&[u8]],
@@ -236,6 +382,8 @@ pub mod utils {
     /// # Examples
     ///
     /// ```rust
+    /// # use solana_sdk::pubkey::Pubkey;
+    /// # use token_acl_integration_tests::utils;
     /// let default_pubkey = Pubkey::default();
     /// assert!(utils::is_default_pubkey(&default_pubkey));
     ///
@@ -259,6 +407,7 @@ pub mod utils {
     /// # Examples
     ///
     /// ```rust
+    /// # use token_acl_integration_tests::utils;
     /// let valid_discriminator = [1, 2, 3, 4, 5, 6, 7, 8];
     /// assert!(utils::is_valid_discriminator(&valid_discriminator));
     ///
@@ -285,6 +434,7 @@ pub mod utils {
     /// # Examples
     ///
     /// ```rust
+    /// # use token_acl_integration_tests::utils;
     /// let program_id = utils::create_test_program_id("my_test_program");
     /// assert!(!utils::is_default_pubkey(&program_id));
     /// ```
@@ -300,93 +450,274 @@ pub mod utils {
 /// Test result aggregation and reporting
 pub mod reporting {
     use super::*;
-    use std::fs;
+    use crate::explorer::{self, Cluster};
+    use crate::{badges, compatibility, labels, metrics, suite_config, time, units};
+    use std::{fs, path::Path};
 
-    /// Generate a comprehensive test report
+    /// Generate a comprehensive test report, listing the categories and
+    /// runtime features the current suite configuration disables; reports
+    /// in the suite's reports directory are also folded into the pass-rate
+    /// badge
     pub fn generate_test_report(
         results: &[TestResultReport],
         title: &str,
         output_path: &str,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        let disabled = suite_config::current().disabled();
+        let mut report = render_test_report(results, title, &disabled);
+        report.push_str(&render_deactivated_features(
+            &suite_config::current().deactivated_features(),
+        ));
+        fs::create_dir_all("../../tests/reports").ok();
+        fs::write(output_path, report)?;
+
+        // Reports written elsewhere, e.g. into a bundle, are not the suite's
+        let path = Path::new(output_path);
+        if path.parent() == Some(Path::new(badges::REPORTS_DIR)) {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            badges::record_results(Path::new(badges::BADGES_DIR), &name, results)?;
+        }
+
+        Ok(())
+    }
+
+    /// A `## Deactivated Runtime Features` section, or nothing when the
+    /// banks ran with every feature active
+    pub fn render_deactivated_features(features: &[Pubkey]) -> String {
+        if features.is_empty() {
+            return String::new();
+        }
+        let mut section = String::from("## Deactivated Runtime Features\n\n");
+        for feature in features {
+            section.push_str(&format!(
+                "- `{}` - {}\n",
+                feature,
+                compatibility::describe_feature(feature)
+            ));
+        }
+        section.push('\n');
+        section
+    }
+
+    /// Render a test report as markdown, naming the accounts the test
+    /// labeled (see [`crate::labels`]) in its errors
+    pub fn render_test_report(
+        results: &[TestResultReport],
+        title: &str,
+        disabled: &[SuiteCategory],
+    ) -> String {
         let mut report = String::new();
 
         // Header
         report.push_str(&format!("# {}\n\n", title));
-        report.push_str(&format!(
-            "**Generated**: {}\n\n",
-            chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC")
-        ));
+        report.push_str(&format!("**Generated**: {}\n\n", time::report_timestamp()));
 
         // Summary
         let total = results.len();
-        let passed = results.iter().filter(|r| r.passed).count();
-        let failed = total - passed;
+        let skipped = results.iter().filter(|r| r.skipped.is_some()).count();
+        let passed = results.iter().filter(|r| r.passed).count() - skipped;
+        let failed = total - passed - skipped;
         let total_assertions: usize = results.iter().map(|r| r.assertions_run).sum();
 
         report.push_str("## Summary\n\n");
         report.push_str(&format!("- **Total Tests**: {}\n", total));
         report.push_str(&format!(
-            "- **Passed**: {} ({}%)\n",
+            "- **Passed**: {} ({})\n",
             passed,
-            (passed * 100) / total
+            units::percent(passed as u64, total as u64)
         ));
         report.push_str(&format!("- **Failed**: {}\n", failed));
+        if skipped > 0 {
+            report.push_str(&format!("- **Skipped**: {}\n", skipped));
+        }
         report.push_str(&format!("- **Total Assertions**: {}\n\n", total_assertions));
 
-        if passed == total {
+        if failed == 0 {
             report.push_str("✅ **ALL TESTS PASSED!**\n\n");
         } else {
             report.push_str("❌ **SOME TESTS FAILED**\n\n");
         }
 
+        // Disabled categories
+        if !disabled.is_empty() {
+            report.push_str("## Disabled Categories\n\n");
+            for category in disabled {
+                report.push_str(&format!(
+                    "- `{}` - {}\n",
+                    category.key(),
+                    category.description()
+                ));
+            }
+            report.push('\n');
+        }
+
         // Results table
         report.push_str("## Test Results\n\n");
         report.push_str("| Test | Status | Assertions | Details |\n");
         report.push_str("|------|--------|------------|----------|\n");
 
         for result in results {
-            let status = if result.passed {
-                "✅ PASS"
-            } else {
-                "❌ FAIL"
+            let details = match (&result.error, result.skipped) {
+                (Some(error), _) => labels::annotate(error),
+                (None, Some(category)) => format!("`{}` disabled", category),
+                (None, None) => "-".to_string(),
             };
-            let error = result.error.as_deref().unwrap_or("-");
             report.push_str(&format!(
                 "| {} | {} | {} | {} |\n",
-                result.name, status, result.assertions_run, error
+                result.name,
+                result.status_label(),
+                result.assertions_run,
+                details
             ));
         }
 
-        report.push_str("\n## Details\n\n");
+        report.push('\n');
+        report.push_str(&metrics::render_cost_section(results));
+        report.push_str(&metrics::render_compute_section(results));
+
+        report.push_str("## Details\n\n");
         for result in results {
-            report.push_str(&format!(
-                "### {} - {}\n\n",
-                if result.passed { "✅" } else { "❌" },
-                result.name
-            ));
-            report.push_str(&format!(
-                "- **Status**: {}\n",
-                if result.passed { "PASS" } else { "FAIL" }
-            ));
+            let (icon, _) = result.status_label().split_once(' ').unwrap_or_default();
+            report.push_str(&format!("### {} - {}\n\n", icon, result.name));
+            report.push_str(&format!("- **Status**: {}\n", result.status()));
             report.push_str(&format!(
                 "- **Assertions Run**: {}\n",
                 result.assertions_run
             ));
             if let Some(error) = &result.error {
-                report.push_str(&format!("- **Error**: {}\n", error));
+                report.push_str(&format!("- **Error**: {}\n", labels::annotate(error)));
+            }
+            if !result.transactions.is_empty() {
+                report.push_str("- **Transactions**:\n");
+                for record in &result.transactions {
+                    let slot = record
+                        .slot
+                        .map(|slot| slot.to_string())
+                        .unwrap_or_else(|| "unknown".to_string());
+                    report.push_str(&format!(
+                        "  - {}: `{}` (slot {})\n",
+                        record.label, record.signature, slot
+                    ));
+                    if let Some(trace) = &record.trace {
+                        report.push_str("\n    ```text\n");
+                        for line in trace::render(trace).lines() {
+                            report.push_str(&format!("    {}\n", line));
+                        }
+                        report.push_str("    ```\n");
+                    }
+                }
             }
             report.push('\n');
         }
 
-        // Write to file
+        let errors: Vec<&str> = results.iter().filter_map(|r| r.error.as_deref()).collect();
+        report.push_str(&labels::with_labels(|labels| {
+            labels.render_legend(&errors.join("\n"))
+        }));
+
+        report
+    }
+
+    /// Generate an HTML test report whose transactions link to Solana
+    /// Explorer on `cluster`
+    pub fn generate_html_report(
+        results: &[TestResultReport],
+        title: &str,
+        output_path: &str,
+        cluster: &Cluster,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let report = render_html_report(results, title, cluster);
         fs::create_dir_all("../../tests/reports").ok();
-        fs::write(output_path, &report)?;
+        fs::write(output_path, report)?;
 
         Ok(())
     }
+
+    /// Render a test report as HTML
+    pub fn render_html_report(
+        results: &[TestResultReport],
+        title: &str,
+        cluster: &Cluster,
+    ) -> String {
+        let passed = results.iter().filter(|r| r.passed).count();
+        let mut report = String::new();
+
+        report.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        report.push_str(&format!(
+            "<title>{}</title>\n</head>\n<body>\n",
+            escape_html(title)
+        ));
+        report.push_str(&format!("<h1>{}</h1>\n", escape_html(title)));
+        report.push_str(&format!(
+            "<p><strong>Generated</strong>: {}</p>\n",
+            time::report_timestamp()
+        ));
+        report.push_str(&format!(
+            "<p><strong>Passed</strong>: {} of {} ({})</p>\n",
+            passed,
+            results.len(),
+            units::percent(passed as u64, results.len() as u64)
+        ));
+
+        report.push_str("<table>\n<tr><th>Test</th><th>Status</th><th>Assertions</th><th>Details</th><th>Transactions</th></tr>\n");
+        for result in results {
+            let transactions: Vec<String> = result
+                .transactions
+                .iter()
+                .map(|record| {
+                    let block = match record.slot {
+                        Some(slot) => format!(
+                            " (<a href=\"{}\">slot {}</a>)",
+                            explorer::block_url(slot, cluster),
+                            slot
+                        ),
+                        None => String::new(),
+                    };
+                    let trace = record
+                        .trace
+                        .as_ref()
+                        .map(|trace| {
+                            format!(
+                                "<details><summary>trace</summary><pre>{}</pre></details>",
+                                escape_html(&trace::render(trace))
+                            )
+                        })
+                        .unwrap_or_default();
+                    format!(
+                        "<a href=\"{}\">{}</a>{}{}",
+                        explorer::transaction_url(&record.signature, cluster),
+                        escape_html(&record.label),
+                        block,
+                        trace
+                    )
+                })
+                .collect();
+            report.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape_html(&result.name),
+                result.status(),
+                result.assertions_run,
+                escape_html(&labels::annotate(result.error.as_deref().unwrap_or("-"))),
+                transactions.join("<br>")
+            ));
+        }
+        report.push_str("</table>\n</body>\n</html>\n");
+
+        report
+    }
+
+    fn escape_html(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
 }
 
 /// Common test assertions
+///
+/// They fail with the report itself, which tests return as is
+#[allow(clippy::result_large_err)]
 pub mod assertions {
     use super::*;
 
@@ -442,3 +773,1114 @@ pub mod assertions {
         }
     }
 }
+
+/// Durable nonce transaction helpers
+///
+/// Admin batch operations are often signed offline (e.g. by a compliance
+/// multisig), long after a recent blockhash would have expired. These helpers
+/// build transactions anchored to a durable nonce instead, let each signer
+/// add their signature independently, and submit them to a program-test bank.
+pub mod nonce {
+    use solana_program_test::BanksClient;
+    use solana_sdk::{
+        account_utils::StateMut,
+        hash::Hash,
+        instruction::Instruction,
+        nonce::{state::Versions, State},
+        pubkey::Pubkey,
+        rent::Rent,
+        signature::Keypair,
+        system_instruction,
+        transaction::Transaction,
+    };
+
+    /// Instructions that create and initialize a rent-exempt nonce account
+    ///
+    /// The `nonce_account` keypair must sign the transaction carrying these.
+    pub fn create_nonce_account_instructions(
+        payer: &Pubkey,
+        nonce_account: &Pubkey,
+        authority: &Pubkey,
+    ) -> Vec<Instruction> {
+        let lamports = Rent::default().minimum_balance(State::size());
+        system_instruction::create_nonce_account(payer, nonce_account, authority, lamports)
+    }
+
+    /// Fetch the durable nonce currently stored in `nonce_account`
+    ///
+    /// Returns `None` if the account does not exist or is not an initialized
+    /// nonce account.
+    pub async fn fetch_nonce(
+        banks_client: &mut BanksClient,
+        nonce_account: &Pubkey,
+    ) -> Option<Hash> {
+        let account = banks_client.get_account(*nonce_account).await.ok()??;
+        let versions: Versions = account.state().ok()?;
+        match versions.state() {
+            State::Initialized(data) => Some(data.blockhash()),
+            State::Uninitialized => None,
+        }
+    }
+
+    /// Build an unsigned transaction anchored to a durable nonce
+    ///
+    /// An `AdvanceNonceAccount` instruction is prepended, as the runtime
+    /// requires, so that the nonce is consumed when the transaction lands.
+    /// Signers then call [`sign_offline`] independently, in any order.
+    pub fn build_nonce_transaction(
+        instructions: &[Instruction],
+        fee_payer: &Pubkey,
+        nonce_account: &Pubkey,
+        nonce_authority: &Pubkey,
+        nonce: Hash,
+    ) -> Transaction {
+        let mut all_instructions = vec![system_instruction::advance_nonce_account(
+            nonce_account,
+            nonce_authority,
+        )];
+        all_instructions.extend_from_slice(instructions);
+
+        let mut transaction = Transaction::new_with_payer(&all_instructions, Some(fee_payer));
+        transaction.message.recent_blockhash = nonce;
+        transaction
+    }
+
+    /// Add one signer's signature without requiring the others to be present
+    pub fn sign_offline(transaction: &mut Transaction, signer: &Keypair) {
+        let nonce = transaction.message.recent_blockhash;
+        transaction.partial_sign(&[signer], nonce);
+    }
+
+    /// Submit a (possibly nonce-anchored) transaction to a program-test bank,
+    /// tracing it if it fails
+    ///
+    /// `BanksClient::process_transaction` looks up the transaction's blockhash
+    /// in the recent blockhash queue and panics for durable nonces, so this
+    /// executes directly against the working bank instead.
+    pub async fn submit(
+        banks_client: &mut BanksClient,
+        transaction: Transaction,
+    ) -> Result<(), String> {
+        if !transaction.is_signed() {
+            return Err("Transaction is missing signatures".to_string());
+        }
+        let outcome = banks_client
+            .process_transaction_with_metadata(transaction.clone())
+            .await
+            .map_err(|e| e.to_string())?;
+        crate::trace::record(&transaction, &outcome);
+        outcome.result.map_err(|e| e.to_string())
+    }
+}
+
+/// Gate programs executed in `solana-program-test`
+///
+/// [`GateHarness`] starts a bank of its own with the allow list and block
+/// list examples and the production allow list deployed, a Token-2022 mint
+/// whose freeze authority the issuer holds, and the production allow list
+/// initialized for that mint. Tests send real transactions through it and
+/// assert on the accounts the bank holds afterwards.
+///
+/// There is no FAMP program in the suite, so a permissionless operation is
+/// the gate's check followed, in the same transaction, by the freeze or thaw
+/// the FAMP would make on its approval, with the issuer standing in for the
+/// FAMP's freeze authority PDA. A denial fails the whole transaction, as it
+/// would through the FAMP.
+pub mod program_test {
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use example_allow_list::create_allow_list_record;
+    use example_block_list::{create_block_list_record, BlockReason};
+    use production_allow_list::{
+        AllowListRecord, RemoveMode, ACCEPT_AUTHORITY, ADD_TO_ALLOW_LIST,
+        CANCEL_AUTHORITY_TRANSFER, INITIALIZE, REMOVE_FROM_ALLOW_LIST, UPDATE_AUTHORITY,
+    };
+    use solana_program_test::{processor, BanksClientError, ProgramTest};
+    use solana_sdk::{
+        account::Account,
+        instruction::{AccountMeta, Instruction, InstructionError},
+        program_option::COption,
+        program_pack::Pack,
+        pubkey::Pubkey,
+        rent::Rent,
+        signature::{Keypair, Signer},
+        system_program,
+        transaction::{Transaction, TransactionError},
+    };
+    use spl_token_2022::{
+        extension::{
+            default_account_state::DefaultAccountState, ExtensionType, StateWithExtensionsMut,
+        },
+        instruction as token_instruction,
+        state::{Account as TokenAccount, AccountState, Mint},
+    };
+    use token_acl_client::instructions::{self, FreezeAuthority};
+    use token_acl_interface::find_missing_record_policy_address;
+
+    use crate::context::TestContext;
+    use crate::decoder::{self, ProgramKind};
+
+    /// A gate program the harness deploys
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Gate {
+        /// `examples/allow_list`: thaws listed holders, never freezes
+        AllowList,
+        /// `examples/block_list`: thaws holders it has not blocked and
+        /// freezes the ones it has
+        BlockList,
+        /// `programs/production_allow_list`: the allow list with an admin
+        /// authority, revocations and a missing-record policy
+        ProductionAllowList,
+    }
+
+    /// An account holding `data`, rent-exempt and owned by `owner`
+    pub fn rent_exempt_account(data: Vec<u8>, owner: Pubkey) -> Account {
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    /// A Token-2022 mint with six decimals and `supply`, whose mint and
+    /// freeze authority is `authority`
+    pub fn mint_account(authority: &Pubkey, supply: u64) -> Account {
+        let mut data = vec![0; Mint::LEN];
+        Mint::pack(
+            Mint {
+                mint_authority: COption::Some(*authority),
+                supply,
+                decimals: 6,
+                is_initialized: true,
+                freeze_authority: COption::Some(*authority),
+            },
+            &mut data,
+        )
+        .expect("a mint always packs into its own length");
+        rent_exempt_account(data, spl_token_2022::id())
+    }
+
+    /// A mint like [`mint_account`] whose new token accounts start in
+    /// `state`, through the DefaultAccountState extension
+    pub fn mint_account_with_default_state(
+        authority: &Pubkey,
+        supply: u64,
+        state: AccountState,
+    ) -> Account {
+        let len =
+            ExtensionType::try_calculate_account_len::<Mint>(&[ExtensionType::DefaultAccountState])
+                .expect("the extension has a fixed length");
+        let mut data = vec![0; len];
+        let mut mint = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data)
+            .expect("the buffer is sized for the mint");
+        mint.init_extension::<DefaultAccountState>(true)
+            .expect("the buffer is sized for the extension")
+            .state = state.into();
+        mint.base = Mint {
+            mint_authority: COption::Some(*authority),
+            supply,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::Some(*authority),
+        };
+        mint.pack_base();
+        mint.init_account_type()
+            .expect("the buffer holds an account type");
+        rent_exempt_account(data, spl_token_2022::id())
+    }
+
+    /// A Token-2022 account of `mint` held by `owner` with `amount`, in
+    /// `state`
+    pub fn token_account(
+        mint: &Pubkey,
+        owner: &Pubkey,
+        amount: u64,
+        state: AccountState,
+    ) -> Account {
+        let mut data = vec![0; TokenAccount::LEN];
+        TokenAccount::pack(
+            TokenAccount {
+                mint: *mint,
+                owner: *owner,
+                amount,
+                delegate: COption::None,
+                state,
+                is_native: COption::None,
+                delegated_amount: 0,
+                close_authority: COption::None,
+            },
+            &mut data,
+        )
+        .expect("a token account always packs into its own length");
+        rent_exempt_account(data, spl_token_2022::id())
+    }
+
+    /// An error a sent transaction fails with, from a bank or from
+    /// [`crate::SvmHarness`]
+    pub trait TransactionFailure {
+        /// The transaction's error, if the transaction ran and failed
+        fn transaction_error(&self) -> Option<&TransactionError>;
+    }
+
+    impl TransactionFailure for TransactionError {
+        fn transaction_error(&self) -> Option<&TransactionError> {
+            Some(self)
+        }
+    }
+
+    impl TransactionFailure for BanksClientError {
+        fn transaction_error(&self) -> Option<&TransactionError> {
+            match self {
+                BanksClientError::TransactionError(error) => Some(error),
+                _ => None,
+            }
+        }
+    }
+
+    /// Whether `result` failed instruction `index` with `expected`
+    pub fn failed_at<T, E: TransactionFailure>(
+        result: &Result<T, E>,
+        index: u8,
+        expected: InstructionError,
+    ) -> bool {
+        matches!(
+            result.as_ref().err().and_then(TransactionFailure::transaction_error),
+            Some(TransactionError::InstructionError(i, error))
+                if *i == index && *error == expected
+        )
+    }
+
+    /// A bank with the gate programs and one mint
+    pub struct GateHarness {
+        pub context: TestContext,
+        pub allow_list: Pubkey,
+        pub block_list: Pubkey,
+        pub production_allow_list: Pubkey,
+        /// Freeze authority of the mint and authority of the production
+        /// allow list
+        pub issuer: Keypair,
+        pub mint: Pubkey,
+    }
+
+    impl GateHarness {
+        /// Start a bank for `test_name` with the programs deployed, the
+        /// production allow list initialized for the mint and the block
+        /// list's freeze extra-account-metas created
+        pub async fn start(test_name: &str) -> Result<Self, String> {
+            let allow_list = Pubkey::new_unique();
+            let block_list = Pubkey::new_unique();
+            let production_allow_list = Pubkey::new_unique();
+            let issuer = Keypair::new();
+            let mint = Pubkey::new_unique();
+            decoder::register(allow_list, ProgramKind::AllowList);
+            decoder::register(block_list, ProgramKind::BlockList);
+            decoder::register(production_allow_list, ProgramKind::ProductionAllowList);
+
+            let mut program_test = ProgramTest::new(
+                "example_allow_list",
+                allow_list,
+                processor!(example_allow_list::process_instruction),
+            );
+            program_test.add_program(
+                "example_block_list",
+                block_list,
+                processor!(example_block_list::process_instruction),
+            );
+            program_test.add_program(
+                "production_allow_list",
+                production_allow_list,
+                processor!(production_allow_list::process_instruction),
+            );
+
+            program_test.add_account(mint, mint_account(&issuer.pubkey(), 0));
+
+            let context = TestContext::start(test_name, program_test).await?;
+            let mut harness = Self {
+                context,
+                allow_list,
+                block_list,
+                production_allow_list,
+                issuer,
+                mint,
+            };
+
+            let payer = harness.context.payer.pubkey();
+            let initialize = Instruction::new_with_bytes(
+                production_allow_list,
+                &[INITIALIZE],
+                vec![
+                    AccountMeta::new(harness.production_config(), false),
+                    AccountMeta::new_readonly(mint, false),
+                    AccountMeta::new_readonly(harness.issuer.pubkey(), true),
+                    AccountMeta::new(payer, true),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            );
+            let freeze_extra_metas = example_block_list::initialize_freeze_extra_account_metas(
+                &block_list,
+                &payer,
+                &mint,
+            );
+            harness
+                .send(&[initialize, freeze_extra_metas], &[])
+                .await
+                .map_err(|e| format!("Harness setup failed: {:?}", e))?;
+            Ok(harness)
+        }
+
+        /// Program id of `gate`
+        pub fn program_id(&self, gate: Gate) -> Pubkey {
+            match gate {
+                Gate::AllowList => self.allow_list,
+                Gate::BlockList => self.block_list,
+                Gate::ProductionAllowList => self.production_allow_list,
+            }
+        }
+
+        /// Config PDA of the production allow list for the mint
+        pub fn production_config(&self) -> Pubkey {
+            production_allow_list::config_address(&self.mint, &self.production_allow_list).address()
+        }
+
+        /// `user`'s record PDA under `gate`
+        pub fn record_address(&self, gate: Gate, user: &Pubkey) -> Pubkey {
+            let program_id = self.program_id(gate);
+            match gate {
+                Gate::AllowList => {
+                    example_allow_list::allow_list_address(&self.mint, user, &program_id)
+                }
+                Gate::BlockList => {
+                    example_block_list::block_list_address(&self.mint, user, &program_id)
+                }
+                Gate::ProductionAllowList => {
+                    production_allow_list::allow_list_address(&self.mint, user, &program_id)
+                }
+            }
+            .address()
+        }
+
+        /// Create a token account of the mint for `owner` in `state`
+        pub fn create_token_account(&mut self, owner: &Pubkey, state: AccountState) -> Pubkey {
+            let address = Pubkey::new_unique();
+            self.context.set_account(
+                &address,
+                &token_account(&self.mint, owner, 1_000, state).into(),
+            );
+            address
+        }
+
+        /// List `user` on the allow list example, or delist them
+        ///
+        /// The example has no admin instructions, so the record is written
+        /// directly, owned by the allow list program.
+        pub fn set_listed(&mut self, user: &Pubkey, allowed: bool) {
+            let mut record = create_allow_list_record(&self.mint, user, 0);
+            record.allowed = allowed;
+            let data = record.try_to_vec().expect("records always serialize");
+            self.write_record(Gate::AllowList, user, data);
+        }
+
+        /// Put `user` on the block list example, or take them off it
+        pub fn set_blocked(&mut self, user: &Pubkey, blocked: bool) {
+            let mut record = create_block_list_record(&self.mint, user, BlockReason::Sanctions, 0);
+            record.blocked = blocked;
+            let data = record.try_to_vec().expect("records always serialize");
+            self.write_record(Gate::BlockList, user, data);
+        }
+
+        fn write_record(&mut self, gate: Gate, user: &Pubkey, data: Vec<u8>) {
+            let account = rent_exempt_account(data, self.program_id(gate));
+            let address = self.record_address(gate, user);
+            self.context.set_account(&address, &account.into());
+        }
+
+        /// Add `user` to the production allow list, naming `authority` as
+        /// the signer it claims to be
+        pub fn add_to_allow_list(
+            &self,
+            user: &Pubkey,
+            authority: &Pubkey,
+            authority_signs: bool,
+        ) -> Instruction {
+            Instruction::new_with_bytes(
+                self.production_allow_list,
+                &[ADD_TO_ALLOW_LIST],
+                vec![
+                    AccountMeta::new_readonly(self.production_config(), false),
+                    AccountMeta::new(self.record_address(Gate::ProductionAllowList, user), false),
+                    AccountMeta::new_readonly(self.mint, false),
+                    AccountMeta::new_readonly(*user, false),
+                    AccountMeta::new_readonly(*authority, authority_signs),
+                    AccountMeta::new(self.context.payer.pubkey(), true),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            )
+        }
+
+        /// Deactivate `user`'s production allow list record, or close it
+        /// into `recipient`
+        pub fn remove_from_allow_list(
+            &self,
+            user: &Pubkey,
+            authority: &Pubkey,
+            recipient: Option<&Pubkey>,
+        ) -> Instruction {
+            let mut metas = vec![
+                AccountMeta::new_readonly(self.production_config(), false),
+                AccountMeta::new(self.record_address(Gate::ProductionAllowList, user), false),
+                AccountMeta::new_readonly(*authority, true),
+            ];
+            let mode = match recipient {
+                Some(recipient) => {
+                    metas.push(AccountMeta::new(*recipient, false));
+                    RemoveMode::Close
+                }
+                None => RemoveMode::Deactivate,
+            };
+            Instruction::new_with_bytes(
+                self.production_allow_list,
+                &[REMOVE_FROM_ALLOW_LIST, mode as u8],
+                metas,
+            )
+        }
+
+        /// Pending-authority PDA of the production allow list for the mint
+        pub fn pending_authority(&self) -> Pubkey {
+            production_allow_list::pending_authority_address(
+                &self.mint,
+                &self.production_allow_list,
+            )
+            .address()
+        }
+
+        /// Propose handing the production allow list `config` from
+        /// `current` to `new`; `new` takes over once it accepts
+        pub fn update_authority(
+            &self,
+            config: &Pubkey,
+            current: &Pubkey,
+            new: &Pubkey,
+        ) -> Instruction {
+            Instruction::new_with_bytes(
+                self.production_allow_list,
+                &[UPDATE_AUTHORITY],
+                vec![
+                    AccountMeta::new_readonly(*config, false),
+                    AccountMeta::new_readonly(*current, true),
+                    AccountMeta::new_readonly(*new, false),
+                    AccountMeta::new(self.pending_authority(), false),
+                    AccountMeta::new(self.context.payer.pubkey(), true),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            )
+        }
+
+        /// Accept the proposed authority of the production allow list
+        /// `config`, signed by `new`, refunding the proposal's rent to the
+        /// payer
+        pub fn accept_authority(&self, config: &Pubkey, new: &Pubkey) -> Instruction {
+            Instruction::new_with_bytes(
+                self.production_allow_list,
+                &[ACCEPT_AUTHORITY],
+                vec![
+                    AccountMeta::new(*config, false),
+                    AccountMeta::new(self.pending_authority(), false),
+                    AccountMeta::new_readonly(*new, true),
+                    AccountMeta::new(self.context.payer.pubkey(), false),
+                ],
+            )
+        }
+
+        /// Withdraw the proposed authority of the production allow list
+        /// `config`, signed by `authority`, refunding the proposal's rent to
+        /// the payer
+        pub fn cancel_authority_transfer(
+            &self,
+            config: &Pubkey,
+            authority: &Pubkey,
+        ) -> Instruction {
+            Instruction::new_with_bytes(
+                self.production_allow_list,
+                &[CANCEL_AUTHORITY_TRANSFER],
+                vec![
+                    AccountMeta::new_readonly(*config, false),
+                    AccountMeta::new(self.pending_authority(), false),
+                    AccountMeta::new_readonly(*authority, true),
+                    AccountMeta::new(self.context.payer.pubkey(), false),
+                ],
+            )
+        }
+
+        /// `gate`'s `can-thaw-permissionless` check of `owner`'s
+        /// `token_account`, requested by `caller`, with the extra accounts
+        /// the gate resolves
+        pub fn can_thaw(
+            &self,
+            gate: Gate,
+            caller: &Pubkey,
+            token_account: &Pubkey,
+            owner: &Pubkey,
+        ) -> Instruction {
+            let program_id = self.program_id(gate);
+            let mut extras = vec![*owner, self.record_address(gate, owner)];
+            if gate != Gate::AllowList {
+                extras.push(find_missing_record_policy_address(&self.mint, &program_id).0);
+            }
+            instructions::with_extra_accounts(
+                instructions::permissionless_thaw(&self.mint, token_account, caller, &program_id),
+                &extras,
+            )
+        }
+
+        /// `gate`'s `can-freeze-permissionless` check of `owner`'s
+        /// `token_account`, requested by `caller`
+        pub fn can_freeze(
+            &self,
+            gate: Gate,
+            caller: &Pubkey,
+            token_account: &Pubkey,
+            owner: &Pubkey,
+        ) -> Instruction {
+            let program_id = self.program_id(gate);
+            instructions::with_extra_accounts(
+                instructions::permissionless_freeze(&self.mint, token_account, caller, &program_id),
+                &[self.record_address(gate, owner)],
+            )
+        }
+
+        /// A Token-2022 freeze of `token_account` signed by `authority`
+        pub fn freeze(&self, token_account: &Pubkey, authority: &Pubkey) -> Instruction {
+            token_instruction::freeze_account(
+                &spl_token_2022::id(),
+                token_account,
+                &self.mint,
+                authority,
+                &[],
+            )
+            .expect("the Token-2022 program id is always valid")
+        }
+
+        /// A Token-2022 thaw of `token_account` signed by `authority`
+        pub fn thaw(&self, token_account: &Pubkey, authority: &Pubkey) -> Instruction {
+            token_instruction::thaw_account(
+                &spl_token_2022::id(),
+                token_account,
+                &self.mint,
+                authority,
+                &[],
+            )
+            .expect("the Token-2022 program id is always valid")
+        }
+
+        /// A permissionless thaw through `gate`: its check, then the thaw the
+        /// FAMP would make on its approval
+        pub fn permissionless_thaw(
+            &self,
+            gate: Gate,
+            caller: &Pubkey,
+            token_account: &Pubkey,
+            owner: &Pubkey,
+        ) -> Vec<Instruction> {
+            let check = self.can_thaw(gate, caller, token_account, owner);
+            instructions::authorized(check, &FreezeAuthority::Key(self.issuer.pubkey()))
+                .expect("a thaw check authorizes a thaw")
+                .to_vec()
+        }
+
+        /// A permissionless freeze through `gate`: its check, then the
+        /// freeze the FAMP would make on its approval
+        pub fn permissionless_freeze(
+            &self,
+            gate: Gate,
+            caller: &Pubkey,
+            token_account: &Pubkey,
+            owner: &Pubkey,
+        ) -> Vec<Instruction> {
+            let check = self.can_freeze(gate, caller, token_account, owner);
+            instructions::authorized(check, &FreezeAuthority::Key(self.issuer.pubkey()))
+                .expect("a freeze check authorizes a freeze")
+                .to_vec()
+        }
+
+        /// Send `instructions` paid by the bank's payer and signed by
+        /// `signers`, and by the issuer if an instruction needs it; traces
+        /// the transaction if it fails
+        pub async fn send(
+            &mut self,
+            instructions: &[Instruction],
+            signers: &[&Keypair],
+        ) -> Result<(), TransactionError> {
+            // A fresh blockhash keeps retried instructions from being
+            // deduplicated
+            let blockhash = self
+                .context
+                .get_new_latest_blockhash()
+                .await
+                .expect("the bank should hand out a blockhash");
+            let mut transaction =
+                Transaction::new_with_payer(instructions, Some(&self.context.payer.pubkey()));
+            let mut all_signers = vec![&self.context.payer];
+            if transaction
+                .message
+                .signer_keys()
+                .contains(&&self.issuer.pubkey())
+            {
+                all_signers.push(&self.issuer);
+            }
+            all_signers.extend_from_slice(signers);
+            transaction.sign(&all_signers, blockhash);
+            let outcome = self
+                .context
+                .banks_client
+                .process_transaction_with_metadata(transaction.clone())
+                .await
+                .expect("the bank should process the transaction");
+            crate::trace::record(&transaction, &outcome);
+            outcome.result
+        }
+
+        pub async fn account(&mut self, address: &Pubkey) -> Option<Account> {
+            self.context
+                .banks_client
+                .get_account(*address)
+                .await
+                .ok()
+                .flatten()
+        }
+
+        /// State of `token_account`, if it is a token account
+        pub async fn token_state(&mut self, token_account: &Pubkey) -> Option<AccountState> {
+            let account = self.account(token_account).await?;
+            TokenAccount::unpack(&account.data)
+                .ok()
+                .map(|account| account.state)
+        }
+
+        /// `user`'s production allow list record
+        pub async fn production_record(&mut self, user: &Pubkey) -> Option<AllowListRecord> {
+            let address = self.record_address(Gate::ProductionAllowList, user);
+            let account = self.account(&address).await?;
+            AllowListRecord::try_from_slice(&account.data).ok()
+        }
+    }
+}
+
+pub use svm::SvmHarness;
+
+/// Programs executed in-process on a bare bank, the way LiteSVM does
+///
+/// [`SvmHarness`] owns a single `solana-runtime` bank and no banks server,
+/// POH service or async client: transactions execute synchronously on the
+/// calling thread, so a test that sends a few dozen of them finishes in well
+/// under a second. Token-2022 runs from the SBF build program-test bundles.
+/// Gate programs run from their SBF builds when `cargo build-sbf` has put
+/// them where [`find_sbf_program`](crate::bpf_coverage::find_sbf_program)
+/// looks, and from their native processors otherwise.
+///
+/// As in [`program_test`], there is no FAMP program: a permissionless thaw
+/// is the gate's check followed by the thaw, signed by the freeze authority.
+pub mod svm {
+    use solana_banks_interface::TransactionMetadata;
+    use solana_client::rpc_filter::RpcFilterType;
+    use solana_program_runtime::{
+        invoke_context::BuiltinFunctionWithContext, loaded_programs::LoadedProgram,
+    };
+    use solana_program_test::{BanksTransactionResultWithMetadata, ProgramTest};
+    use solana_runtime::{
+        bank::Bank, bank_forks::BankForks, genesis_utils::create_genesis_config_with_leader_ex,
+        runtime_config::RuntimeConfig,
+    };
+    use solana_sdk::{
+        account::{Account, AccountSharedData},
+        bpf_loader,
+        clock::Clock,
+        fee_calculator::{FeeRateGovernor, DEFAULT_TARGET_LAMPORTS_PER_SIGNATURE},
+        genesis_config::ClusterType,
+        instruction::Instruction,
+        native_token::sol_to_lamports,
+        pubkey::Pubkey,
+        rent::Rent,
+        signature::{Keypair, Signer},
+        system_instruction,
+        transaction::{Transaction, TransactionError},
+    };
+    use spl_token_2022::{
+        extension::{
+            default_account_state, BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+        },
+        instruction as token_instruction,
+        state::{Account as TokenAccount, AccountState, Mint},
+    };
+    use std::{
+        collections::HashSet,
+        sync::{Arc, Once, RwLock},
+    };
+
+    use crate::{bpf_coverage::find_sbf_program, trace};
+
+    /// Native processors reach the runtime (CPIs, sysvars, logs) through
+    /// the syscall stubs program-test installs the first time one of its
+    /// banks starts; start one, once, on a thread of its own so this also
+    /// works from inside a test's async runtime
+    fn install_syscall_stubs() {
+        static STUBS: Once = Once::new();
+        STUBS.call_once(|| {
+            std::thread::spawn(|| {
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .expect("runtime for program-test's syscall stubs")
+                    .block_on(ProgramTest::default().start());
+            })
+            .join()
+            .expect("program-test should install its syscall stubs");
+        });
+    }
+
+    /// Logs and compute units of a transaction that executed successfully
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Executed {
+        pub logs: Vec<String>,
+        pub compute_units: u64,
+    }
+
+    /// One in-process bank with Token-2022 and whichever programs a test
+    /// adds
+    pub struct SvmHarness {
+        /// Holds every bank the harness made; the program cache needs it
+        /// to tell which slots a program is visible in
+        bank_forks: Arc<RwLock<BankForks>>,
+        /// The working bank, at the highest slot
+        bank: Arc<Bank>,
+        /// Funded at genesis; pays for and signs every transaction
+        pub payer: Keypair,
+        /// Programs added as native processors, whose compute units are
+        /// not the ones an SBF build would consume
+        native: HashSet<Pubkey>,
+    }
+
+    impl Default for SvmHarness {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl SvmHarness {
+        /// A bank with every feature active, default rent and fees, and the
+        /// SPL programs program-test bundles
+        pub fn new() -> Self {
+            let payer = Keypair::new();
+            let rent = Rent::default();
+            let genesis_config = create_genesis_config_with_leader_ex(
+                sol_to_lamports(1_000_000.0),
+                &payer.pubkey(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                sol_to_lamports(1_000_000.0),
+                42,
+                FeeRateGovernor {
+                    lamports_per_signature: DEFAULT_TARGET_LAMPORTS_PER_SIGNATURE / 2,
+                    ..FeeRateGovernor::default()
+                },
+                rent.clone(),
+                ClusterType::Development,
+                vec![],
+            );
+            let bank = Bank::new_with_paths(
+                &genesis_config,
+                Arc::new(RuntimeConfig::default()),
+                Vec::default(),
+                None,
+                None,
+                Default::default(),
+                Default::default(),
+                false,
+                None,
+                None,
+                None,
+                Arc::default(),
+            );
+            for (address, account) in solana_program_test::programs::spl_programs(&rent) {
+                bank.store_account(&address, &account);
+            }
+            let bank_forks = BankForks::new_rw_arc(bank);
+            let bank = bank_forks.read().unwrap().working_bank();
+            let mut harness = Self {
+                bank_forks,
+                bank,
+                payer,
+                native: HashSet::new(),
+            };
+            harness.next_slot(|_| {});
+            harness
+        }
+
+        /// Move to a new bank at the next slot, set up by `configure` before
+        /// anyone else sees it; programs deployed in a slot only run from
+        /// the next one
+        fn next_slot(&mut self, configure: impl FnOnce(&mut Bank)) {
+            self.bank.fill_bank_with_ticks_for_tests();
+            let mut bank = Bank::new_from_parent(
+                self.bank.clone(),
+                self.bank.collector_id(),
+                self.bank.slot() + 1,
+            );
+            configure(&mut bank);
+            self.bank = self
+                .bank_forks
+                .write()
+                .unwrap()
+                .insert(bank)
+                .clone_without_scheduler();
+        }
+
+        /// Add the program `name` at `program_id`: its SBF build if there is
+        /// one, else `builtin`, e.g. `processor!(gate::process_instruction)`
+        ///
+        /// Returns whether the SBF build was loaded.
+        pub fn add_program(
+            &mut self,
+            name: &str,
+            program_id: Pubkey,
+            builtin: Option<BuiltinFunctionWithContext>,
+        ) -> Result<bool, String> {
+            if let Some(elf) = find_sbf_program(name) {
+                self.load_program(program_id, elf);
+                return Ok(true);
+            }
+            let builtin =
+                builtin.ok_or_else(|| format!("No SBF build or processor for {}", name))?;
+            self.add_builtin(name, program_id, builtin);
+            Ok(false)
+        }
+
+        /// Add `builtin` as the native processor of the program `name` at
+        /// `program_id`, whether or not it has an SBF build
+        pub fn add_builtin(
+            &mut self,
+            name: &str,
+            program_id: Pubkey,
+            builtin: BuiltinFunctionWithContext,
+        ) {
+            install_syscall_stubs();
+            // Only a bank that is not shared yet takes builtins
+            self.next_slot(|bank| {
+                bank.add_builtin(
+                    program_id,
+                    name.to_string(),
+                    LoadedProgram::new_builtin(0, name.len(), builtin),
+                )
+            });
+            self.native.insert(program_id);
+        }
+
+        /// Deploy the SBF program `elf` at `program_id`
+        pub fn load_program(&mut self, program_id: Pubkey, elf: Vec<u8>) {
+            self.native.remove(&program_id);
+            self.set_account(
+                program_id,
+                Account {
+                    lamports: Rent::default().minimum_balance(elf.len()).max(1),
+                    data: elf,
+                    owner: bpf_loader::id(),
+                    executable: true,
+                    rent_epoch: 0,
+                },
+            );
+            self.next_slot(|_| {});
+        }
+
+        /// Whether `program_id` runs as a native processor rather than
+        /// from an SBF build
+        pub fn is_native(&self, program_id: &Pubkey) -> bool {
+            self.native.contains(program_id)
+        }
+
+        /// What a transaction with `signatures` signatures costs
+        pub fn fee(&self, signatures: usize) -> u64 {
+            self.bank.get_lamports_per_signature() * signatures as u64
+        }
+
+        pub fn set_account(&mut self, address: Pubkey, account: Account) {
+            self.bank
+                .store_account(&address, &AccountSharedData::from(account));
+        }
+
+        pub fn account(&self, address: &Pubkey) -> Option<Account> {
+            self.bank.get_account(address).map(Account::from)
+        }
+
+        /// Every account `program_id` owns that passes all of `filters`, as
+        /// `getProgramAccounts` would return them
+        pub fn program_accounts(
+            &self,
+            program_id: &Pubkey,
+            filters: &[RpcFilterType],
+        ) -> Result<Vec<(Pubkey, Account)>, String> {
+            let accounts = self
+                .bank
+                .get_filtered_program_accounts(
+                    program_id,
+                    |account| filters.iter().all(|filter| filter.allows(account)),
+                    &Default::default(),
+                )
+                .map_err(|e| e.to_string())?;
+            Ok(accounts
+                .into_iter()
+                .map(|(address, account)| (address, Account::from(account)))
+                .collect())
+        }
+
+        /// Transfer `lamports` from the payer to `to`
+        pub fn airdrop(&mut self, to: &Pubkey, lamports: u64) -> Result<(), TransactionError> {
+            let transfer = system_instruction::transfer(&self.payer.pubkey(), to, lamports);
+            self.send(&[transfer], &[]).map(|_| ())
+        }
+
+        pub fn clock(&self) -> Clock {
+            self.bank.clock()
+        }
+
+        /// Set the cluster time, e.g. to expire a record
+        pub fn set_unix_timestamp(&mut self, unix_timestamp: i64) {
+            let clock = Clock {
+                unix_timestamp,
+                ..self.bank.clock()
+            };
+            self.bank.set_sysvar_for_tests(&clock);
+        }
+
+        /// Create a Token-2022 mint whose new token accounts start in
+        /// `default_state`, with `authority` as its mint and freeze
+        /// authority
+        pub fn create_mint(
+            &mut self,
+            authority: &Pubkey,
+            default_state: AccountState,
+        ) -> Result<Pubkey, TransactionError> {
+            let mint = Keypair::new();
+            let space = ExtensionType::try_calculate_account_len::<Mint>(&[
+                ExtensionType::DefaultAccountState,
+            ])
+            .expect("mint length with one extension");
+            let instructions = [
+                system_instruction::create_account(
+                    &self.payer.pubkey(),
+                    &mint.pubkey(),
+                    self.bank.get_minimum_balance_for_rent_exemption(space),
+                    space as u64,
+                    &spl_token_2022::id(),
+                ),
+                default_account_state::instruction::initialize_default_account_state(
+                    &spl_token_2022::id(),
+                    &mint.pubkey(),
+                    &default_state,
+                )
+                .expect("default account state instruction"),
+                token_instruction::initialize_mint2(
+                    &spl_token_2022::id(),
+                    &mint.pubkey(),
+                    authority,
+                    Some(authority),
+                    6,
+                )
+                .expect("initialize mint instruction"),
+            ];
+            self.send(&instructions, &[&mint])?;
+            Ok(mint.pubkey())
+        }
+
+        /// Create a token account of `mint` for `owner`, initialized by
+        /// Token-2022 and so in the mint's default state
+        pub fn create_token_account(
+            &mut self,
+            mint: &Pubkey,
+            owner: &Pubkey,
+        ) -> Result<Pubkey, TransactionError> {
+            let token_account = Keypair::new();
+            let mint_extensions = self
+                .account(mint)
+                .and_then(|account| {
+                    StateWithExtensions::<Mint>::unpack(&account.data)
+                        .and_then(|mint| mint.get_extension_types())
+                        .ok()
+                })
+                .unwrap_or_default();
+            let space = ExtensionType::try_calculate_account_len::<TokenAccount>(
+                &ExtensionType::get_required_init_account_extensions(&mint_extensions),
+            )
+            .expect("token account length");
+            let instructions = [
+                system_instruction::create_account(
+                    &self.payer.pubkey(),
+                    &token_account.pubkey(),
+                    self.bank.get_minimum_balance_for_rent_exemption(space),
+                    space as u64,
+                    &spl_token_2022::id(),
+                ),
+                token_instruction::initialize_account3(
+                    &spl_token_2022::id(),
+                    &token_account.pubkey(),
+                    mint,
+                    owner,
+                )
+                .expect("initialize account instruction"),
+            ];
+            self.send(&instructions, &[&token_account])?;
+            Ok(token_account.pubkey())
+        }
+
+        /// State of a Token-2022 account, with or without extensions
+        pub fn token_state(&self, token_account: &Pubkey) -> Option<AccountState> {
+            let account = self.account(token_account)?;
+            StateWithExtensions::<TokenAccount>::unpack(&account.data)
+                .ok()
+                .map(|account| account.base.state)
+        }
+
+        /// Execute `instructions` as one transaction signed by the payer
+        /// and `signers`
+        ///
+        /// Every transaction gets a fresh blockhash, so sending the same
+        /// instructions twice executes them twice. A failed transaction is
+        /// recorded for the test's trace.
+        pub fn send(
+            &mut self,
+            instructions: &[Instruction],
+            signers: &[&Keypair],
+        ) -> Result<Executed, TransactionError> {
+            self.bank.register_unique_recent_blockhash_for_test();
+            let mut all_signers = vec![&self.payer];
+            all_signers.extend_from_slice(signers);
+            let transaction = Transaction::new_signed_with_payer(
+                instructions,
+                Some(&self.payer.pubkey()),
+                &all_signers,
+                self.bank.last_blockhash(),
+            );
+            let executed = self
+                .bank
+                .process_transaction_with_metadata(transaction.clone());
+            let outcome = BanksTransactionResultWithMetadata {
+                result: executed.flattened_result(),
+                metadata: executed.details().map(|details| TransactionMetadata {
+                    compute_units_consumed: details.executed_units,
+                    log_messages: details.log_messages.clone().unwrap_or_default(),
+                    return_data: details.return_data.clone(),
+                }),
+            };
+            trace::record(&transaction, &outcome);
+            outcome.result.map(|()| {
+                let metadata = outcome
+                    .metadata
+                    .expect("an executed transaction has metadata");
+                Executed {
+                    logs: metadata.log_messages,
+                    compute_units: metadata.compute_units_consumed,
+                }
+            })
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Runtime compatibility matrix
+//!
+//! Freeze and CPI semantics occasionally change between runtime releases,
+//! and each change ships behind a feature gate that clusters activate on
+//! their own schedule. A [`FeatureProfile`] pins a runtime by the gates it
+//! has not activated yet; `solana-program-test` starts with every gate it
+//! knows active, so the baseline profile is the newest runtime.
+//!
+//! Tests run the same scenarios once per profile and record what each
+//! observed, as named facts, in a [`CompatibilityMatrix`]. The matrix reports
+//! every fact that differs from the baseline, which is what a gate program
+//! must be checked against before a cluster it runs on upgrades.
+
+use solana_program_test::ProgramTest;
+use solana_sdk::{feature_set::FEATURE_NAMES, pubkey::Pubkey};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A runtime pinned by the feature gates it has not activated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeatureProfile {
+    name: String,
+    deactivated: BTreeSet<Pubkey>,
+}
+
+impl FeatureProfile {
+    /// The newest runtime: every gate the bank knows is active
+    pub fn all_active(name: &str) -> Self {
+        Self::without(name, &[])
+    }
+
+    /// A runtime from before `features` were activated
+    pub fn without(name: &str, features: &[Pubkey]) -> Self {
+        Self {
+            name: name.to_string(),
+            deactivated: features.iter().copied().collect(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn deactivated(&self) -> impl Iterator<Item = &Pubkey> {
+        self.deactivated.iter()
+    }
+
+    /// Start `program_test` on this runtime
+    pub fn apply(&self, program_test: &mut ProgramTest) {
+        for feature in &self.deactivated {
+            program_test.deactivate_feature(*feature);
+        }
+    }
+
+    /// What the deactivated gates change, by the runtime's own descriptions
+    pub fn describe(&self) -> Vec<String> {
+        self.deactivated.iter().map(describe_feature).collect()
+    }
+}
+
+/// The runtime's own description of the feature gate `feature`
+pub fn describe_feature(feature: &Pubkey) -> String {
+    FEATURE_NAMES
+        .get(feature)
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| format!("unknown feature {}", feature))
+}
+
+/// What one scenario observed on one runtime, as named facts
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Observation {
+    facts: BTreeMap<String, String>,
+}
+
+impl Observation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the fact `name`, e.g. `("result", "ok")`
+    pub fn with(mut self, name: &str, value: impl ToString) -> Self {
+        self.facts.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.facts.get(name).map(String::as_str)
+    }
+}
+
+/// A fact a profile observed differently from the baseline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    pub scenario: String,
+    pub profile: String,
+    pub fact: String,
+    /// `None` when only one side observed the fact
+    pub baseline: Option<String>,
+    pub observed: Option<String>,
+}
+
+/// Scenario observations across runtime profiles
+#[derive(Debug, Clone)]
+pub struct CompatibilityMatrix {
+    profiles: Vec<FeatureProfile>,
+    /// Observations by scenario, then by profile name
+    observations: BTreeMap<String, BTreeMap<String, Observation>>,
+}
+
+impl CompatibilityMatrix {
+    /// A matrix compared against `baseline`
+    pub fn new(baseline: FeatureProfile) -> Self {
+        Self {
+            profiles: vec![baseline],
+            observations: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_profile(&mut self, profile: FeatureProfile) -> &mut Self {
+        self.profiles.push(profile);
+        self
+    }
+
+    /// The baseline, then the other profiles in the order they were added
+    pub fn profiles(&self) -> &[FeatureProfile] {
+        &self.profiles
+    }
+
+    pub fn baseline(&self) -> &FeatureProfile {
+        &self.profiles[0]
+    }
+
+    pub fn record(&mut self, profile: &str, scenario: &str, observation: Observation) {
+        self.observations
+            .entry(scenario.to_string())
+            .or_default()
+            .insert(profile.to_string(), observation);
+    }
+
+    pub fn observation(&self, profile: &str, scenario: &str) -> Option<&Observation> {
+        self.observations.get(scenario)?.get(profile)
+    }
+
+    /// Every fact a profile observed differently from the baseline, by
+    /// scenario, then profile order, then fact
+    pub fn differences(&self) -> Vec<Difference> {
+        let baseline = self.baseline().name();
+        let mut differences = Vec::new();
+        for (scenario, by_profile) in &self.observations {
+            let expected = by_profile.get(baseline).cloned().unwrap_or_default();
+            for profile in &self.profiles[1..] {
+                let Some(observed) = by_profile.get(profile.name()) else {
+                    continue;
+                };
+                let facts: BTreeSet<&String> =
+                    expected.facts.keys().chain(observed.facts.keys()).collect();
+                for fact in facts {
+                    let (before, after) = (expected.facts.get(fact), observed.facts.get(fact));
+                    if before != after {
+                        differences.push(Difference {
+                            scenario: scenario.clone(),
+                            profile: profile.name().to_string(),
+                            fact: fact.clone(),
+                            baseline: before.cloned(),
+                            observed: after.cloned(),
+                        });
+                    }
+                }
+            }
+        }
+        differences
+    }
+
+    /// A `## Runtime Compatibility` section: the profiles, then the
+    /// behavioral differences against the baseline
+    pub fn render_markdown(&self) -> String {
+        let mut section = String::from("## Runtime Compatibility\n\n");
+        section
+            .push_str("| Profile | Deactivated features |\n|---------|----------------------|\n");
+        for profile in &self.profiles {
+            let features = profile.describe();
+            let features = if features.is_empty() {
+                "none (baseline)".to_string()
+            } else {
+                features.join("; ")
+            };
+            section.push_str(&format!("| {} | {} |\n", profile.name(), features));
+        }
+        section.push('\n');
+
+        let differences = self.differences();
+        if differences.is_empty() {
+            section.push_str("No behavioral differences from the baseline.\n\n");
+            return section;
+        }
+        section.push_str(&format!(
+            "### Differences from {}\n\n| Scenario | Profile | Fact | Baseline | Observed |\n|----------|---------|------|----------|----------|\n",
+            self.baseline().name()
+        ));
+        for difference in &differences {
+            section.push_str(&format!(
+                "| {} | {} | {} | {} | {} |\n",
+                difference.scenario,
+                difference.profile,
+                difference.fact,
+                difference.baseline.as_deref().unwrap_or("-"),
+                difference.observed.as_deref().unwrap_or("-")
+            ));
+        }
+        section.push('\n');
+        section
+    }
+}
@@ -0,0 +1,1028 @@
+//! Conformance checks for third-party gate programs
+//!
+//! Before an issuer points a mint at a gate someone else wrote, they need to
+//! know it answers sRFC 37 checks the way a FAMP will ask them. A
+//! [`GateConfig`] describes the gate to the suite: where its SBF build is,
+//! which extra accounts its checks take, how to set it up for a mint, and a
+//! few holders with the records the gate should find and the decisions it
+//! should reach. [`ConformanceRunner`] loads the build into an
+//! [`SvmHarness`] and runs a fixed battery against it:
+//!
+//! - **Discriminators**: every holder's `can-thaw-permissionless` and
+//!   `can-freeze-permissionless` reach the configured decision, and a gate
+//!   configured without permissionless freeze rejects it for everyone.
+//! - **Unknown instructions**: empty data, a truncated discriminator,
+//!   near-misses of both discriminators and arbitrary bytes are rejected.
+//! - **PDA validation**: a denial must not turn into an approval when the
+//!   check is given another holder's record, a copy of that record at an
+//!   address that is not the holder's PDA, or (for gates taking the owner)
+//!   another owner than the token account's.
+//! - **De-escalation**: checks run with every account read-only and no
+//!   signer, as a FAMP calls them, and reach the same decisions as with the
+//!   caller signing and the token account writable.
+//! - **Account ordering**: no rearrangement of a denied holder's accounts
+//!   (see [`arrangements`]), with an approved holder's accounts appended as
+//!   decoys, is approved.
+//!
+//! The [`CertificationReport`] lists every check with its outcome; a gate is
+//! certified when none failed. `tests/conformance.rs` certifies the configs
+//! in `tests/integration/tests/conformance/`, and the gate named by
+//! `TOKEN_ACL_CONFORMANCE_CONFIG` when it is set.
+//!
+//! # Configuration
+//!
+//! Accounts are named by role: `caller`, `token_account`, `mint`, `owner`,
+//! `record`, `thaw_metas`, `freeze_metas` (the gate's extra-account-metas
+//! PDAs), `payer`, `system_program`, a base58 address, or
+//! `pda:<seed>/<seed>/...`, derived under the gate. Seeds, there and in
+//! `record_seeds`, are `{mint}`, `{owner}`, or UTF-8 literals. Record data is
+//! a list of segments: `{mint}`, `{owner}`, `{bump}` (the record's canonical
+//! bump), or hex bytes.
+//!
+//! ```toml
+//! name = "Example Block List"
+//! program = "../../../../target/deploy/example_block_list.so"
+//! record_seeds = ["block-list", "{mint}", "{owner}"]
+//!
+//! [thaw]
+//! extra_accounts = ["owner", "record", "pda:missing-record-policy/{mint}"]
+//!
+//! [freeze]
+//! extra_accounts = ["record"]
+//!
+//! [[setup]]
+//! data = "00"
+//! accounts = ["payer", "freeze_metas", "mint", "system_program"]
+//! writable = ["payer", "freeze_metas"]
+//!
+//! [[holders]]
+//! label = "blocked"
+//! record = ["{mint}", "{owner}", "01", "00", "0000000000000000", "00"]
+//! thaw = "deny"
+//! freeze = "allow"
+//! ```
+//!
+//! The payer signs every setup instruction and is the mint's authority, so
+//! it can stand in for a gate's admin.
+
+use serde::Deserialize;
+use solana_program_runtime::invoke_context::BuiltinFunctionWithContext;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::TransactionError,
+};
+use spl_token_2022::state::AccountState;
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use token_acl_interface::{
+    self as interface, ReadonlyMeta, CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR,
+    CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+};
+
+use crate::{
+    account_ordering::{arrangements, Decoy},
+    common::program_test::rent_exempt_account,
+    SvmHarness,
+};
+
+/// Environment variable naming a gate config to certify
+pub const CONFORMANCE_CONFIG_ENV: &str = "TOKEN_ACL_CONFORMANCE_CONFIG";
+
+/// Instruction data no gate may accept
+fn unknown_instructions() -> Vec<(&'static str, Vec<u8>)> {
+    let flip_last = |discriminator: [u8; 8]| {
+        let mut data = discriminator.to_vec();
+        data[7] ^= 0xff;
+        data
+    };
+    let mut reversed = CAN_THAW_PERMISSIONLESS_DISCRIMINATOR;
+    reversed.reverse();
+    vec![
+        ("empty data", Vec::new()),
+        (
+            "truncated can-thaw discriminator",
+            CAN_THAW_PERMISSIONLESS_DISCRIMINATOR[..7].to_vec(),
+        ),
+        (
+            "can-thaw discriminator with its last byte flipped",
+            flip_last(CAN_THAW_PERMISSIONLESS_DISCRIMINATOR),
+        ),
+        (
+            "can-freeze discriminator with its last byte flipped",
+            flip_last(CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR),
+        ),
+        ("reversed can-thaw discriminator", reversed.to_vec()),
+        ("all ones", vec![0xff; 8]),
+    ]
+}
+
+/// What a gate should decide for a holder
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Decision {
+    Allow,
+    #[default]
+    Deny,
+}
+
+/// A gate, as a config file describes it
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GateConfig {
+    pub name: String,
+    /// SBF build of the gate; relative paths are resolved against the
+    /// config file's directory by [`GateConfig::load`]
+    pub program: PathBuf,
+    /// Seeds of a holder's record PDA under the gate
+    pub record_seeds: Vec<String>,
+    pub thaw: CheckConfig,
+    /// Absent for gates that never approve a permissionless freeze
+    #[serde(default)]
+    pub freeze: Option<CheckConfig>,
+    /// Instructions run once, before any holder exists
+    #[serde(default)]
+    pub setup: Vec<SetupInstruction>,
+    pub holders: Vec<HolderConfig>,
+}
+
+/// The extra accounts of one check, after the four interface accounts
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CheckConfig {
+    pub extra_accounts: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SetupInstruction {
+    /// Instruction data, hex
+    pub data: String,
+    pub accounts: Vec<String>,
+    /// Accounts passed writable; the payer always is
+    #[serde(default)]
+    pub writable: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HolderConfig {
+    pub label: String,
+    /// Data of the holder's record, owned by the gate; no record if absent
+    #[serde(default)]
+    pub record: Option<Vec<String>>,
+    pub thaw: Decision,
+    #[serde(default)]
+    pub freeze: Decision,
+}
+
+impl GateConfig {
+    /// Parse and validate a config
+    pub fn parse(config: &str) -> Result<Self, String> {
+        let config: Self = toml::from_str(config).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Read the config at `path`, resolving `program` against its directory
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        let mut config =
+            Self::parse(&text).map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+        if config.program.is_relative() {
+            if let Some(dir) = path.parent() {
+                config.program = dir.join(&config.program);
+            }
+        }
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        parse_seeds(&self.record_seeds)?;
+        for check in std::iter::once(&self.thaw).chain(&self.freeze) {
+            parse_refs(&check.extra_accounts)?;
+        }
+        for setup in &self.setup {
+            hex::decode(&setup.data).map_err(|e| format!("Setup data: {}", e))?;
+            for account in parse_refs(&setup.accounts)?
+                .iter()
+                .chain(&parse_refs(&setup.writable)?)
+            {
+                if account.needs_holder() {
+                    return Err(format!(
+                        "Setup cannot name {:?}, which is per holder",
+                        account
+                    ));
+                }
+            }
+        }
+        if self.holders.is_empty() {
+            return Err("At least one holder is needed".to_string());
+        }
+        for holder in &self.holders {
+            if let Some(record) = &holder.record {
+                for segment in record {
+                    DataSegment::from_str(segment)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A PDA seed in a config
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SeedTemplate {
+    Mint,
+    Owner,
+    Literal(Vec<u8>),
+}
+
+impl FromStr for SeedTemplate {
+    type Err = String;
+
+    fn from_str(seed: &str) -> Result<Self, String> {
+        Ok(match seed {
+            "{mint}" => Self::Mint,
+            "{owner}" => Self::Owner,
+            _ if seed.starts_with('{') => return Err(format!("Unknown seed {}", seed)),
+            _ => Self::Literal(seed.as_bytes().to_vec()),
+        })
+    }
+}
+
+fn parse_seeds<S: AsRef<str>>(seeds: &[S]) -> Result<Vec<SeedTemplate>, String> {
+    seeds.iter().map(|seed| seed.as_ref().parse()).collect()
+}
+
+/// An account in a config, by role
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum AccountRef {
+    Payer,
+    Caller,
+    TokenAccount,
+    Mint,
+    Owner,
+    Record,
+    ThawMetas,
+    FreezeMetas,
+    SystemProgram,
+    Pda(Vec<SeedTemplate>),
+    Address(Pubkey),
+}
+
+impl AccountRef {
+    /// Whether the account differs between holders
+    fn needs_holder(&self) -> bool {
+        match self {
+            Self::TokenAccount | Self::Owner | Self::Record => true,
+            Self::Pda(seeds) => seeds.contains(&SeedTemplate::Owner),
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for AccountRef {
+    type Err = String;
+
+    fn from_str(account: &str) -> Result<Self, String> {
+        Ok(match account {
+            "payer" => Self::Payer,
+            "caller" => Self::Caller,
+            "token_account" => Self::TokenAccount,
+            "mint" => Self::Mint,
+            "owner" => Self::Owner,
+            "record" => Self::Record,
+            "thaw_metas" => Self::ThawMetas,
+            "freeze_metas" => Self::FreezeMetas,
+            "system_program" => Self::SystemProgram,
+            _ => match account.strip_prefix("pda:") {
+                Some(seeds) => Self::Pda(parse_seeds(&seeds.split('/').collect::<Vec<_>>())?),
+                None => Self::Address(
+                    account
+                        .parse()
+                        .map_err(|_| format!("Unknown account {}", account))?,
+                ),
+            },
+        })
+    }
+}
+
+fn parse_refs(accounts: &[String]) -> Result<Vec<AccountRef>, String> {
+    accounts.iter().map(|account| account.parse()).collect()
+}
+
+/// A segment of record data in a config
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DataSegment {
+    Mint,
+    Owner,
+    Bump,
+    Bytes(Vec<u8>),
+}
+
+impl FromStr for DataSegment {
+    type Err = String;
+
+    fn from_str(segment: &str) -> Result<Self, String> {
+        Ok(match segment {
+            "{mint}" => Self::Mint,
+            "{owner}" => Self::Owner,
+            "{bump}" => Self::Bump,
+            _ => Self::Bytes(
+                hex::decode(segment).map_err(|e| format!("Record data {}: {}", segment, e))?,
+            ),
+        })
+    }
+}
+
+/// Which permissionless check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckKind {
+    Thaw,
+    Freeze,
+}
+
+impl fmt::Display for CheckKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckKind::Thaw => write!(f, "can-thaw"),
+            CheckKind::Freeze => write!(f, "can-freeze"),
+        }
+    }
+}
+
+/// The battery's groups of checks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CheckCategory {
+    Discriminators,
+    UnknownInstructions,
+    PdaValidation,
+    Deescalation,
+    AccountOrdering,
+}
+
+impl fmt::Display for CheckCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckCategory::Discriminators => write!(f, "Discriminators"),
+            CheckCategory::UnknownInstructions => write!(f, "Unknown instructions"),
+            CheckCategory::PdaValidation => write!(f, "PDA validation"),
+            CheckCategory::Deescalation => write!(f, "De-escalation"),
+            CheckCategory::AccountOrdering => write!(f, "Account ordering"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckOutcome {
+    Passed,
+    Failed(String),
+    /// The config has no holders the check needs
+    NotApplicable(String),
+}
+
+/// One check of the battery
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConformanceCheck {
+    pub category: CheckCategory,
+    pub name: String,
+    pub outcome: CheckOutcome,
+}
+
+/// Every check run against one gate
+#[derive(Debug, Clone)]
+pub struct CertificationReport {
+    pub gate: String,
+    pub program_id: Pubkey,
+    /// Whether the gate ran from its SBF build rather than a native
+    /// processor
+    pub sbf: bool,
+    pub checks: Vec<ConformanceCheck>,
+}
+
+impl CertificationReport {
+    /// Whether no check failed
+    pub fn certified(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &ConformanceCheck> {
+        self.checks
+            .iter()
+            .filter(|check| matches!(check.outcome, CheckOutcome::Failed(_)))
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut report = format!("# Conformance Report: {}\n\n", self.gate);
+        report.push_str(&format!(
+            "**Verdict**: {}\n\n",
+            if self.certified() {
+                "✅ Certified"
+            } else {
+                "❌ Not certified"
+            }
+        ));
+        report.push_str(&format!(
+            "Ran from its {} at `{}`.\n\n",
+            if self.sbf {
+                "SBF build"
+            } else {
+                "native processor"
+            },
+            self.program_id
+        ));
+        report.push_str("| Category | Check | Result |\n|---|---|---|\n");
+        for check in &self.checks {
+            let result = match &check.outcome {
+                CheckOutcome::Passed => "✅ Passed".to_string(),
+                CheckOutcome::Failed(reason) => format!("❌ {}", reason),
+                CheckOutcome::NotApplicable(reason) => format!("➖ {}", reason),
+            };
+            report.push_str(&format!(
+                "| {} | {} | {} |\n",
+                check.category, check.name, result
+            ));
+        }
+        report
+    }
+
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_markdown())
+    }
+}
+
+/// A holder's accounts
+#[derive(Debug, Clone, Copy)]
+struct Subject {
+    owner: Pubkey,
+    token_account: Pubkey,
+    record: Pubkey,
+}
+
+struct Holder {
+    label: String,
+    subject: Subject,
+    /// Data of the record the holder was given, if any
+    record: Option<Vec<u8>>,
+    thaw: Decision,
+    freeze: Decision,
+}
+
+impl Holder {
+    fn decision(&self, kind: CheckKind) -> Decision {
+        match kind {
+            CheckKind::Thaw => self.thaw,
+            CheckKind::Freeze => self.freeze,
+        }
+    }
+}
+
+/// A gate loaded into its own bank with a mint and the config's holders
+pub struct ConformanceRunner {
+    svm: SvmHarness,
+    name: String,
+    gate: Pubkey,
+    mint: Pubkey,
+    caller: Keypair,
+    sbf: bool,
+    record_seeds: Vec<SeedTemplate>,
+    thaw: Vec<AccountRef>,
+    freeze: Option<Vec<AccountRef>>,
+    holders: Vec<Holder>,
+}
+
+impl ConformanceRunner {
+    /// Load the gate's SBF build if it has been built, and `builtin`, its
+    /// native processor, otherwise
+    pub fn start(
+        config: &GateConfig,
+        builtin: Option<BuiltinFunctionWithContext>,
+    ) -> Result<Self, String> {
+        config.validate()?;
+        let mut svm = SvmHarness::new();
+        let gate = Pubkey::new_unique();
+        let sbf = match (fs::read(&config.program), builtin) {
+            (Ok(elf), _) => {
+                svm.load_program(gate, elf);
+                true
+            }
+            (Err(_), Some(builtin)) => {
+                svm.add_builtin(&config.name, gate, builtin);
+                false
+            }
+            (Err(e), None) => {
+                return Err(format!("Cannot read {}: {}", config.program.display(), e))
+            }
+        };
+        let authority = svm.payer.pubkey();
+        let mint = svm
+            .create_mint(&authority, AccountState::Initialized)
+            .map_err(|e| format!("Mint creation failed: {:?}", e))?;
+
+        let mut runner = Self {
+            svm,
+            name: config.name.clone(),
+            gate,
+            mint,
+            caller: Keypair::new(),
+            sbf,
+            record_seeds: parse_seeds(&config.record_seeds)?,
+            thaw: parse_refs(&config.thaw.extra_accounts)?,
+            freeze: config
+                .freeze
+                .as_ref()
+                .map(|check| parse_refs(&check.extra_accounts))
+                .transpose()?,
+            holders: Vec::new(),
+        };
+
+        for (index, setup) in config.setup.iter().enumerate() {
+            let instruction = runner.setup_instruction(setup)?;
+            runner
+                .svm
+                .send(&[instruction], &[])
+                .map_err(|e| format!("Setup instruction {} failed: {:?}", index, e))?;
+        }
+
+        for holder in &config.holders {
+            let owner = Pubkey::new_unique();
+            let token_account = runner
+                .svm
+                .create_token_account(&mint, &owner)
+                .map_err(|e| format!("Token account of {} failed: {:?}", holder.label, e))?;
+            let (record, bump) = runner.pda(&runner.record_seeds, &owner);
+            let data = match &holder.record {
+                Some(segments) => {
+                    let mut data = Vec::new();
+                    for segment in segments {
+                        match segment.parse()? {
+                            DataSegment::Mint => data.extend_from_slice(mint.as_ref()),
+                            DataSegment::Owner => data.extend_from_slice(owner.as_ref()),
+                            DataSegment::Bump => data.push(bump),
+                            DataSegment::Bytes(bytes) => data.extend(bytes),
+                        }
+                    }
+                    runner.plant(record, data.clone());
+                    Some(data)
+                }
+                None => None,
+            };
+            runner.holders.push(Holder {
+                label: holder.label.clone(),
+                subject: Subject {
+                    owner,
+                    token_account,
+                    record,
+                },
+                record: data,
+                thaw: holder.thaw,
+                freeze: holder.freeze,
+            });
+        }
+        Ok(runner)
+    }
+
+    pub fn program_id(&self) -> Pubkey {
+        self.gate
+    }
+
+    /// Run the battery
+    pub fn run(&mut self) -> CertificationReport {
+        let mut checks = Vec::new();
+        for kind in [CheckKind::Thaw, CheckKind::Freeze] {
+            checks.push(self.check_decisions(kind));
+        }
+        checks.extend(self.check_unknown_instructions());
+        for kind in [CheckKind::Thaw, CheckKind::Freeze] {
+            if self.extras(kind).is_none() {
+                continue;
+            }
+            checks.push(self.check_record_substitution(kind));
+            checks.push(self.check_foreign_record(kind));
+            checks.push(self.check_owner_binding(kind));
+        }
+        for kind in [CheckKind::Thaw, CheckKind::Freeze] {
+            checks.push(self.check_deescalation(kind));
+        }
+        for kind in [CheckKind::Thaw, CheckKind::Freeze] {
+            if self.extras(kind).is_some() {
+                checks.push(self.check_account_ordering(kind));
+            }
+        }
+        CertificationReport {
+            gate: self.name.clone(),
+            program_id: self.gate,
+            sbf: self.sbf,
+            checks,
+        }
+    }
+
+    /// Discriminators: each holder's check reaches its configured decision
+    fn check_decisions(&mut self, kind: CheckKind) -> ConformanceCheck {
+        let name = format!("{} decisions", kind);
+        let mut mismatches = Vec::new();
+        for index in 0..self.holders.len() {
+            let holder = &self.holders[index];
+            let (label, expected) = (holder.label.clone(), holder.decision(kind));
+            let check = self.check_instruction(kind, &holder.subject, false);
+            let decided = self.decide(check, false);
+            if decided.0 != expected {
+                mismatches.push(format!("{} {}", label, describe(&decided)));
+            }
+        }
+        let outcome = if mismatches.is_empty() {
+            CheckOutcome::Passed
+        } else {
+            CheckOutcome::Failed(format!(
+                "Expected decisions differ: {}",
+                mismatches.join("; ")
+            ))
+        };
+        ConformanceCheck {
+            category: CheckCategory::Discriminators,
+            name,
+            outcome,
+        }
+    }
+
+    /// Unknown instructions: each is rejected, sent with the accounts of a
+    /// holder's thaw check
+    fn check_unknown_instructions(&mut self) -> Vec<ConformanceCheck> {
+        let subject = self.holders[0].subject;
+        unknown_instructions()
+            .into_iter()
+            .map(|(name, data)| {
+                let mut instruction = self.check_instruction(CheckKind::Thaw, &subject, false);
+                instruction.data = data;
+                let outcome = match self.svm.send(&[instruction], &[]) {
+                    Ok(_) => CheckOutcome::Failed("Accepted".to_string()),
+                    Err(_) => CheckOutcome::Passed,
+                };
+                ConformanceCheck {
+                    category: CheckCategory::UnknownInstructions,
+                    name: name.to_string(),
+                    outcome,
+                }
+            })
+            .collect()
+    }
+
+    /// A holder the gate approves for `kind` with a record, and one it
+    /// denies
+    fn approved_and_denied(&self, kind: CheckKind) -> Option<(usize, usize)> {
+        let approved = self
+            .holders
+            .iter()
+            .position(|h| h.decision(kind) == Decision::Allow && h.record.is_some())?;
+        let denied = self
+            .holders
+            .iter()
+            .position(|h| h.decision(kind) == Decision::Deny)?;
+        Some((approved, denied))
+    }
+
+    /// PDA validation: run the denied holder's check with accounts from
+    /// `substitute`, which must not be approved
+    fn check_substitution(
+        &mut self,
+        kind: CheckKind,
+        name: &str,
+        substitute: impl FnOnce(&mut Self, &Holder, &Holder) -> Option<Subject>,
+    ) -> ConformanceCheck {
+        let name = format!("{}: {}", kind, name);
+        let outcome = match self.approved_and_denied(kind) {
+            None => CheckOutcome::NotApplicable(format!(
+                "Needs a holder approved with a record and one denied for {}",
+                kind
+            )),
+            Some((approved, denied)) => {
+                let (approved, denied) = (self.holder_view(approved), self.holder_view(denied));
+                match substitute(self, &approved, &denied) {
+                    None => CheckOutcome::NotApplicable(format!(
+                        "The {} check does not take the owner",
+                        kind
+                    )),
+                    Some(subject) => {
+                        let check = self.check_instruction(kind, &subject, false);
+                        match self.decide(check, false) {
+                            (Decision::Deny, _) => CheckOutcome::Passed,
+                            (Decision::Allow, _) => CheckOutcome::Failed(format!(
+                                "Approved {} with {}'s accounts",
+                                denied.label, approved.label
+                            )),
+                        }
+                    }
+                }
+            }
+        };
+        ConformanceCheck {
+            category: CheckCategory::PdaValidation,
+            name,
+            outcome,
+        }
+    }
+
+    /// Another holder's record in place of the denied holder's
+    fn check_record_substitution(&mut self, kind: CheckKind) -> ConformanceCheck {
+        self.check_substitution(kind, "another holder's record", |_, approved, denied| {
+            Some(Subject {
+                record: approved.subject.record,
+                ..denied.subject
+            })
+        })
+    }
+
+    /// A gate-owned copy of another holder's record at an address that is
+    /// not the denied holder's PDA
+    fn check_foreign_record(&mut self, kind: CheckKind) -> ConformanceCheck {
+        self.check_substitution(
+            kind,
+            "record copy at a non-PDA address",
+            |runner, approved, denied| {
+                let copy = Pubkey::new_unique();
+                runner.plant(copy, approved.record.clone().unwrap_or_default());
+                Some(Subject {
+                    record: copy,
+                    ..denied.subject
+                })
+            },
+        )
+    }
+
+    /// The denied holder's token account with another owner and everything
+    /// derived from them
+    fn check_owner_binding(&mut self, kind: CheckKind) -> ConformanceCheck {
+        let takes_owner = self
+            .extras(kind)
+            .is_some_and(|extras| extras.contains(&AccountRef::Owner));
+        self.check_substitution(
+            kind,
+            "token account of another owner",
+            |_, approved, denied| {
+                takes_owner.then_some(Subject {
+                    token_account: denied.subject.token_account,
+                    ..approved.subject
+                })
+            },
+        )
+    }
+
+    /// De-escalation: decisions with every account read-only and no signer
+    /// match the ones with the caller signing and the token account
+    /// writable
+    fn check_deescalation(&mut self, kind: CheckKind) -> ConformanceCheck {
+        let mut differences = Vec::new();
+        for index in 0..self.holders.len() {
+            let subject = self.holders[index].subject;
+            let label = self.holders[index].label.clone();
+            let readonly = self.check_instruction(kind, &subject, false);
+            let readonly = self.decide(readonly, false);
+            let escalated = self.check_instruction(kind, &subject, true);
+            let escalated = self.decide(escalated, true);
+            if readonly.0 != escalated.0 {
+                differences.push(format!(
+                    "{} {} read-only but {} escalated",
+                    label,
+                    describe(&readonly),
+                    describe(&escalated)
+                ));
+            }
+        }
+        let outcome = if differences.is_empty() {
+            CheckOutcome::Passed
+        } else {
+            CheckOutcome::Failed(differences.join("; "))
+        };
+        ConformanceCheck {
+            category: CheckCategory::Deescalation,
+            name: format!("{} under de-escalated permissions", kind),
+            outcome,
+        }
+    }
+
+    /// Account ordering: every denied holder's check stays denied however
+    /// its accounts are rearranged, with the first approved holder's token
+    /// account, record and owner, and an unknown account, as decoys
+    fn check_account_ordering(&mut self, kind: CheckKind) -> ConformanceCheck {
+        let takes_owner = self
+            .extras(kind)
+            .is_some_and(|extras| extras.contains(&AccountRef::Owner));
+        let mut decoys = Vec::new();
+        if let Some(approved) = self
+            .holders
+            .iter()
+            .find(|holder| holder.decision(kind) == Decision::Allow)
+        {
+            let subject = approved.subject;
+            decoys.push(Decoy::new(
+                format!("{}'s token account", approved.label),
+                subject.token_account,
+            ));
+            decoys.push(Decoy::new(
+                format!("{}'s record", approved.label),
+                subject.record,
+            ));
+            decoys.push(Decoy::new(
+                format!("{}'s owner", approved.label),
+                subject.owner,
+            ));
+        }
+        decoys.push(Decoy::new("an unknown account", Pubkey::new_unique()));
+
+        let mut denied = 0;
+        let mut approvals = Vec::new();
+        for index in 0..self.holders.len() {
+            if self.holders[index].decision(kind) != Decision::Deny {
+                continue;
+            }
+            denied += 1;
+            let subject = self.holders[index].subject;
+            let label = self.holders[index].label.clone();
+            let check = self.check_instruction(kind, &subject, false);
+            let owner = takes_owner.then_some(subject.owner);
+            for arrangement in arrangements(&check, owner.as_ref(), &decoys) {
+                let decision = self.decide(arrangement.apply(&check), false);
+                if decision.0 == Decision::Allow {
+                    approvals.push(format!("{} with {}", label, arrangement.name));
+                }
+            }
+        }
+        let outcome = if denied == 0 {
+            CheckOutcome::NotApplicable(format!("Needs a holder denied for {}", kind))
+        } else if approvals.is_empty() {
+            CheckOutcome::Passed
+        } else {
+            CheckOutcome::Failed(format!("Approved {}", approvals.join("; ")))
+        };
+        ConformanceCheck {
+            category: CheckCategory::AccountOrdering,
+            name: format!("{} with rearranged accounts", kind),
+            outcome,
+        }
+    }
+
+    /// The gate's decision on `check`, with the error it denied with
+    fn decide(
+        &mut self,
+        check: Instruction,
+        caller_signs: bool,
+    ) -> (Decision, Option<TransactionError>) {
+        let caller = self.caller.insecure_clone();
+        let signers: &[&Keypair] = if caller_signs { &[&caller] } else { &[] };
+        match self.svm.send(&[check], signers) {
+            Ok(_) => (Decision::Allow, None),
+            Err(e) => (Decision::Deny, Some(e)),
+        }
+    }
+
+    fn extras(&self, kind: CheckKind) -> Option<&Vec<AccountRef>> {
+        match kind {
+            CheckKind::Thaw => Some(&self.thaw),
+            CheckKind::Freeze => self.freeze.as_ref(),
+        }
+    }
+
+    /// `kind` for `subject`, every account read-only and none signing unless
+    /// `escalated`
+    ///
+    /// A gate without a freeze config gets a freeze check with no extra
+    /// accounts, which it must reject.
+    fn check_instruction(
+        &self,
+        kind: CheckKind,
+        subject: &Subject,
+        escalated: bool,
+    ) -> Instruction {
+        let extras: Vec<ReadonlyMeta> = self
+            .extras(kind)
+            .map(|extras| {
+                extras
+                    .iter()
+                    .map(|account| self.resolve(account, Some(subject)).into())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let (caller, token_account) = (self.caller.pubkey(), subject.token_account);
+        let mut check = match kind {
+            CheckKind::Thaw => interface::can_thaw_permissionless(
+                &self.gate,
+                caller,
+                token_account,
+                self.mint,
+                self.resolve(&AccountRef::ThawMetas, None),
+                &extras,
+            ),
+            CheckKind::Freeze => interface::can_freeze_permissionless(
+                &self.gate,
+                caller,
+                token_account,
+                self.mint,
+                self.resolve(&AccountRef::FreezeMetas, None),
+                &extras,
+            ),
+        };
+        if escalated {
+            check.accounts[0] = AccountMeta::new_readonly(self.caller.pubkey(), true);
+            check.accounts[1] = AccountMeta::new(subject.token_account, false);
+        }
+        check
+    }
+
+    fn setup_instruction(&self, setup: &SetupInstruction) -> Result<Instruction, String> {
+        let data = hex::decode(&setup.data).map_err(|e| format!("Setup data: {}", e))?;
+        let writable = parse_refs(&setup.writable)?;
+        let accounts = parse_refs(&setup.accounts)?
+            .iter()
+            .map(|account| {
+                let address = self.resolve(account, None);
+                let signer = *account == AccountRef::Payer;
+                if signer || writable.contains(account) {
+                    AccountMeta::new(address, signer)
+                } else {
+                    AccountMeta::new_readonly(address, false)
+                }
+            })
+            .collect();
+        Ok(Instruction::new_with_bytes(self.gate, &data, accounts))
+    }
+
+    /// The address `account` names; per-holder accounts need `subject`,
+    /// which [`GateConfig::validate`] ensures setup never lacks
+    fn resolve(&self, account: &AccountRef, subject: Option<&Subject>) -> Pubkey {
+        let holder = || subject.expect("per-holder accounts are only named in checks");
+        match account {
+            AccountRef::Payer => self.svm.payer.pubkey(),
+            AccountRef::Caller => self.caller.pubkey(),
+            AccountRef::TokenAccount => holder().token_account,
+            AccountRef::Mint => self.mint,
+            AccountRef::Owner => holder().owner,
+            AccountRef::Record => holder().record,
+            AccountRef::ThawMetas => {
+                interface::find_thaw_extra_account_metas_address(&self.mint, &self.gate).0
+            }
+            AccountRef::FreezeMetas => {
+                interface::find_freeze_extra_account_metas_address(&self.mint, &self.gate).0
+            }
+            AccountRef::SystemProgram => system_program::id(),
+            AccountRef::Pda(seeds) => {
+                let owner = subject.map(|subject| subject.owner).unwrap_or_default();
+                self.pda(seeds, &owner).0
+            }
+            AccountRef::Address(address) => *address,
+        }
+    }
+
+    fn pda(&self, seeds: &[SeedTemplate], owner: &Pubkey) -> (Pubkey, u8) {
+        let seeds: Vec<&[u8]> = seeds
+            .iter()
+            .map(|seed| match seed {
+                SeedTemplate::Mint => self.mint.as_ref(),
+                SeedTemplate::Owner => owner.as_ref(),
+                SeedTemplate::Literal(bytes) => bytes.as_slice(),
+            })
+            .collect();
+        Pubkey::find_program_address(&seeds, &self.gate)
+    }
+
+    /// Store `data` at `address`, rent-exempt and owned by the gate
+    fn plant(&mut self, address: Pubkey, data: Vec<u8>) {
+        self.svm
+            .set_account(address, rent_exempt_account(data, self.gate));
+    }
+
+    /// A copy of the holder at `index`, so checks can borrow the runner
+    fn holder_view(&self, index: usize) -> Holder {
+        let holder = &self.holders[index];
+        Holder {
+            label: holder.label.clone(),
+            subject: holder.subject,
+            record: holder.record.clone(),
+            thaw: holder.thaw,
+            freeze: holder.freeze,
+        }
+    }
+}
+
+fn describe((decision, error): &(Decision, Option<TransactionError>)) -> String {
+    match (decision, error) {
+        (Decision::Allow, _) => "was approved".to_string(),
+        (Decision::Deny, Some(error)) => format!("was denied ({:?})", error),
+        (Decision::Deny, None) => "was denied".to_string(),
+    }
+}
+
+/// Certify the gate described by the config at `path`, from its SBF build
+pub fn certify(path: &Path) -> Result<CertificationReport, String> {
+    let config = GateConfig::load(path)?;
+    Ok(ConformanceRunner::start(&config, None)?.run())
+}
@@ -0,0 +1,187 @@
+//! Write-lock contention analysis
+//!
+//! Solana schedules transactions in parallel unless they write-lock the same
+//! account. A gate that writes one globally shared account on every check
+//! (a counter PDA, a global config, a fee vault) serializes every thaw on the
+//! cluster behind that account.
+//!
+//! [`ContentionAnalyzer`] records the accounts each operation write-locks and
+//! flags "hot" accounts that too many independent operations write. Fee
+//! payers are excluded by default: the submitter picks them, not the gate.
+
+use serde::Serialize;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, transaction::Transaction};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Accounts write-locked by one recorded operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OperationLocks {
+    pub operation: String,
+    pub writable: BTreeSet<Pubkey>,
+}
+
+/// An account written by more operations than the analyzer allows
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HotAccount {
+    pub address: Pubkey,
+    pub label: Option<String>,
+    /// Number of recorded operations that write-lock the account
+    pub write_count: usize,
+    /// Distinct operation names that write-lock the account
+    pub operations: BTreeSet<String>,
+}
+
+/// Collects per-operation write locks and flags hot accounts
+#[derive(Debug, Clone)]
+pub struct ContentionAnalyzer {
+    operations: Vec<OperationLocks>,
+    labels: BTreeMap<Pubkey, String>,
+    ignored: BTreeSet<Pubkey>,
+    include_fee_payers: bool,
+    max_writers: usize,
+}
+
+impl Default for ContentionAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ContentionAnalyzer {
+    /// Flag any account write-locked by more than one operation
+    pub fn new() -> Self {
+        Self {
+            operations: Vec::new(),
+            labels: BTreeMap::new(),
+            ignored: BTreeSet::new(),
+            include_fee_payers: false,
+            max_writers: 1,
+        }
+    }
+
+    /// Allow up to `max_writers` operations to share a writable account
+    pub fn with_max_writers(mut self, max_writers: usize) -> Self {
+        self.max_writers = max_writers.max(1);
+        self
+    }
+
+    /// Also count writable signers (fee payers, rent payers)
+    pub fn include_fee_payers(mut self) -> Self {
+        self.include_fee_payers = true;
+        self
+    }
+
+    /// Never flag `address`, e.g. a sponsor the caller deliberately shares
+    pub fn ignore(&mut self, address: Pubkey) -> &mut Self {
+        self.ignored.insert(address);
+        self
+    }
+
+    /// Name `address` in reports
+    pub fn label(&mut self, address: Pubkey, label: impl Into<String>) -> &mut Self {
+        self.labels.insert(address, label.into());
+        self
+    }
+
+    /// Record the write locks of one operation made of `instructions`
+    pub fn record_instructions(&mut self, operation: &str, instructions: &[Instruction]) {
+        let writable = instructions
+            .iter()
+            .flat_map(|instruction| &instruction.accounts)
+            .filter(|meta| meta.is_writable && (self.include_fee_payers || !meta.is_signer))
+            .map(|meta| meta.pubkey)
+            .collect();
+        self.push(operation, writable);
+    }
+
+    /// Record the write locks of one compiled transaction
+    pub fn record_transaction(&mut self, operation: &str, transaction: &Transaction) {
+        let message = &transaction.message;
+        let writable = message
+            .account_keys
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| {
+                message.is_writable(*index)
+                    && (self.include_fee_payers || !message.is_signer(*index))
+            })
+            .map(|(_, key)| *key)
+            .collect();
+        self.push(operation, writable);
+    }
+
+    fn push(&mut self, operation: &str, writable: BTreeSet<Pubkey>) {
+        self.operations.push(OperationLocks {
+            operation: operation.to_string(),
+            writable,
+        });
+    }
+
+    /// All recorded operations, in recording order
+    pub fn operations(&self) -> &[OperationLocks] {
+        &self.operations
+    }
+
+    /// Accounts written by more than the allowed number of operations,
+    /// most contended first
+    pub fn hot_accounts(&self) -> Vec<HotAccount> {
+        let mut writers: BTreeMap<Pubkey, (usize, BTreeSet<String>)> = BTreeMap::new();
+        for locks in &self.operations {
+            for address in &locks.writable {
+                let (count, names) = writers.entry(*address).or_default();
+                *count += 1;
+                names.insert(locks.operation.clone());
+            }
+        }
+
+        let mut hot: Vec<HotAccount> = writers
+            .into_iter()
+            .filter(|(address, (count, _))| {
+                *count > self.max_writers && !self.ignored.contains(address)
+            })
+            .map(|(address, (write_count, operations))| HotAccount {
+                address,
+                label: self.labels.get(&address).cloned(),
+                write_count,
+                operations,
+            })
+            .collect();
+        hot.sort_by_key(|account| std::cmp::Reverse(account.write_count));
+        hot
+    }
+
+    /// Render the analysis as a markdown report
+    pub fn to_markdown(&self) -> String {
+        let hot = self.hot_accounts();
+        let mut report = String::from("# Write-Lock Contention Analysis\n\n");
+        report.push_str(&format!(
+            "Operations analyzed: {}\nAllowed writers per account: {}\nHot accounts: {}\n\n",
+            self.operations.len(),
+            self.max_writers,
+            hot.len()
+        ));
+
+        if hot.is_empty() {
+            report.push_str("No globally shared writable accounts found.\n");
+            return report;
+        }
+
+        report.push_str("| Account | Label | Writers | Operations |\n");
+        report.push_str("|---------|-------|---------|------------|\n");
+        for account in &hot {
+            report.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                account.address,
+                account.label.as_deref().unwrap_or("-"),
+                account.write_count,
+                account
+                    .operations
+                    .iter()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        report
+    }
+}
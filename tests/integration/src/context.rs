@@ -0,0 +1,251 @@
+//! Per-test ephemeral contexts
+//!
+//! Each test starts its own bank through [`TestContext::start`] instead of
+//! sharing one across tests, so an account one test creates or a record it
+//! revokes is never seen by another, however many stateful tests run in
+//! parallel. The context also prices a signature and reads the rent once,
+//! which fee and rent assertions need in nearly every test.
+//!
+//! Tests that run against a shared validator cannot get a fresh bank. They
+//! derive their keys from a [`Namespace`] instead: a mint, issuer or holder
+//! named by label is private to the test and the run, so two tests, or two
+//! runs of the same test, never initialize the same config or write the same
+//! record. The keys are named by their labels in [`crate::labels`], so logs
+//! and reports read the same from run to run.
+//!
+//! A bank starts with every runtime feature gate active, less any the suite
+//! configuration deactivates (see [`crate::suite_config`]). A test can then
+//! activate a deactivated gate mid-run with
+//! [`TestContext::activate_feature`], the way a cluster upgrade lands at an
+//! epoch boundary, to check a gate before and after the same upgrade.
+
+use sha2::{Digest, Sha256};
+use solana_program_test::{BanksClient, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    clock::Slot,
+    feature::{self, Feature},
+    message::Message,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{keypair_from_seed, Keypair, Signer},
+};
+use std::ops::{Deref, DerefMut};
+
+use crate::{labels, suite_config};
+
+/// Keys private to one test in one run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Namespace {
+    test_name: String,
+    seed: [u8; 32],
+}
+
+impl Namespace {
+    /// A namespace for `test_name` that no other run shares
+    pub fn new(test_name: &str) -> Self {
+        Self::with_run_seed(test_name, &Keypair::new().pubkey().to_bytes())
+    }
+
+    /// The namespace of `test_name` in the run identified by `run_seed`;
+    /// the same seed derives the same keys, for replaying a run
+    pub fn with_run_seed(test_name: &str, run_seed: &[u8]) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(run_seed);
+        hasher.update(test_name.as_bytes());
+        Self {
+            test_name: test_name.to_string(),
+            seed: hasher.finalize().into(),
+        }
+    }
+
+    pub fn test_name(&self) -> &str {
+        &self.test_name
+    }
+
+    /// The keypair named `label` in this namespace, the same on every call,
+    /// labeled `label` for the test on this thread
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use solana_sdk::signature::Signer;
+    /// # use token_acl_integration_tests::context::Namespace;
+    /// let first = Namespace::new("Permissionless Thaw");
+    /// let second = Namespace::new("Permissionless Thaw");
+    /// assert_eq!(first.keypair("mint").pubkey(), first.address("mint"));
+    /// assert_ne!(first.address("mint"), second.address("mint"));
+    /// ```
+    pub fn keypair(&self, label: &str) -> Keypair {
+        let mut hasher = Sha256::new();
+        hasher.update(self.seed);
+        hasher.update(label.as_bytes());
+        let seed: [u8; 32] = hasher.finalize().into();
+        let keypair =
+            keypair_from_seed(&seed).expect("a 32-byte seed is always a valid keypair seed");
+        labels::label(keypair.pubkey(), label);
+        keypair
+    }
+
+    /// The address of [`Namespace::keypair`] for `label`
+    pub fn address(&self, label: &str) -> Pubkey {
+        self.keypair(label).pubkey()
+    }
+}
+
+/// A bank of its own for one test
+///
+/// Dereferences to the `ProgramTestContext`, so `banks_client`, `payer` and
+/// `last_blockhash` read as they do on a bare context.
+pub struct TestContext {
+    context: ProgramTestContext,
+    namespace: Namespace,
+    /// Rent of the test's bank
+    pub rent: Rent,
+    /// What one signature costs on the test's bank
+    pub lamports_per_signature: u64,
+}
+
+impl TestContext {
+    /// Start a fresh bank for `test_name` with the programs and accounts of
+    /// `program_test`, without the feature gates the suite deactivates
+    pub async fn start(test_name: &str, mut program_test: ProgramTest) -> Result<Self, String> {
+        for feature in suite_config::current().deactivated_features() {
+            program_test.deactivate_feature(feature);
+        }
+        let mut context = program_test.start_with_context().await;
+        let rent = context
+            .banks_client
+            .get_rent()
+            .await
+            .map_err(|e| format!("Failed to read rent: {}", e))?;
+        let message = Message::new_with_blockhash(
+            &[],
+            Some(&context.payer.pubkey()),
+            &context.last_blockhash,
+        );
+        let lamports_per_signature = context
+            .banks_client
+            .get_fee_for_message(message)
+            .await
+            .map_err(|e| format!("Failed to price a signature: {}", e))?
+            .unwrap_or_default();
+        labels::label(context.payer.pubkey(), "payer");
+        Ok(Self {
+            context,
+            namespace: Namespace::new(test_name),
+            rent,
+            lamports_per_signature,
+        })
+    }
+
+    pub fn test_name(&self) -> &str {
+        self.namespace.test_name()
+    }
+
+    /// Keys private to this test
+    pub fn namespace(&self) -> &Namespace {
+        &self.namespace
+    }
+
+    /// A handle on the test's bank, for code that takes its own client
+    ///
+    /// The bank stops registering new blockhashes when the context is
+    /// dropped, so a fixture keeps the context for as long as it uses the
+    /// client.
+    pub fn client(&self) -> BanksClient {
+        self.context.banks_client.clone()
+    }
+
+    /// The funded payer of the test's bank
+    pub fn payer(&self) -> Keypair {
+        self.context.payer.insecure_clone()
+    }
+
+    /// What a transaction with `signatures` signatures costs
+    pub fn fee(&self, signatures: usize) -> u64 {
+        self.lamports_per_signature * signatures as u64
+    }
+
+    /// Whether the runtime feature gate `feature` is active on the bank
+    pub async fn is_feature_active(&mut self, feature: &Pubkey) -> Result<bool, String> {
+        let account = self
+            .context
+            .banks_client
+            .get_account(*feature)
+            .await
+            .map_err(|e| format!("Failed to read feature {}: {}", feature, e))?;
+        Ok(account
+            .and_then(|account| feature::from_account(&account))
+            .is_some_and(|feature| feature.activated_at.is_some()))
+    }
+
+    /// Activate the deactivated feature gate `feature` at the next epoch
+    /// boundary, as a cluster upgrade would, and warp the bank there;
+    /// returns the activation slot
+    ///
+    /// The payer funds the pending feature account, as `solana feature
+    /// activate` would, which keeps the bank's capitalization intact.
+    pub async fn activate_feature(&mut self, feature: Pubkey) -> Result<Slot, String> {
+        if self.is_feature_active(&feature).await? {
+            return Err(format!("Feature {} is already active", feature));
+        }
+        let lamports = self.rent.minimum_balance(Feature::size_of());
+        let payer = self.context.payer.pubkey();
+        let mut funding = self
+            .context
+            .banks_client
+            .get_account(payer)
+            .await
+            .map_err(|e| format!("Failed to read the payer: {}", e))?
+            .ok_or("The payer has no account")?;
+        funding.lamports = funding
+            .lamports
+            .checked_sub(lamports)
+            .ok_or("The payer cannot fund a feature account")?;
+        self.context.set_account(&payer, &funding.into());
+        self.context.set_account(
+            &feature,
+            &feature::create_account(&Feature::default(), lamports),
+        );
+
+        let epoch_schedule = self.context.genesis_config().epoch_schedule.clone();
+        let slot = self
+            .context
+            .banks_client
+            .get_root_slot()
+            .await
+            .map_err(|e| format!("Failed to read the slot: {}", e))?;
+        let boundary = epoch_schedule.get_first_slot_in_epoch(epoch_schedule.get_epoch(slot) + 1);
+        self.context
+            .warp_to_slot(boundary)
+            .map_err(|e| format!("Failed to warp to slot {}: {:?}", boundary, e))?;
+        self.context.last_blockhash = self
+            .context
+            .banks_client
+            .get_latest_blockhash()
+            .await
+            .map_err(|e| format!("Failed to read the blockhash: {}", e))?;
+
+        if !self.is_feature_active(&feature).await? {
+            return Err(format!(
+                "Feature {} did not activate at slot {}",
+                feature, boundary
+            ));
+        }
+        Ok(boundary)
+    }
+}
+
+impl Deref for TestContext {
+    type Target = ProgramTestContext;
+
+    fn deref(&self) -> &ProgramTestContext {
+        &self.context
+    }
+}
+
+impl DerefMut for TestContext {
+    fn deref_mut(&mut self) -> &mut ProgramTestContext {
+        &mut self.context
+    }
+}
@@ -0,0 +1,230 @@
+//! Cost accounting for macro-benchmarks
+//!
+//! An issuer sizing an onboarding campaign wants three numbers: how long it
+//! takes, what it costs in fees, and how much SOL ends up locked as rent.
+//! [`CampaignCost`] collects them per phase (batch allow list adds, holder
+//! self-thaws, ...) from what the bank actually charged, and renders the
+//! totals and the per-user cost as a markdown cost report.
+//!
+//! A gate designer wants a different number: how far a thaw check is from
+//! the compute, transaction size and account lock ceilings as the gate's
+//! extra accounts grow. [`GateCheckCost`] records one point of that curve.
+
+use serde::Serialize;
+use solana_program_runtime::compute_budget_processor::DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT;
+use solana_sdk::packet::PACKET_DATA_SIZE;
+use std::{fs, time::Duration};
+
+use crate::{time, units};
+
+/// What one phase of a campaign consumed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PhaseCost {
+    pub name: String,
+    pub transactions: usize,
+    /// Transaction fees paid, by whoever paid them
+    pub fees: u64,
+    /// Lamports locked in accounts the phase created
+    pub rent: u64,
+    pub compute_units: u64,
+    pub elapsed: Duration,
+}
+
+impl PhaseCost {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Count one landed transaction
+    pub fn record_transaction(&mut self, fee: u64, compute_units: u64) {
+        self.transactions += 1;
+        self.fees += fee;
+        self.compute_units += compute_units;
+    }
+}
+
+/// Wall-clock, fees and rent of a campaign onboarding `users` holders
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CampaignCost {
+    pub name: String,
+    pub users: usize,
+    pub phases: Vec<PhaseCost>,
+}
+
+impl CampaignCost {
+    pub fn new(name: &str, users: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            users,
+            phases: Vec::new(),
+        }
+    }
+
+    pub fn add_phase(&mut self, phase: PhaseCost) {
+        self.phases.push(phase);
+    }
+
+    pub fn transactions(&self) -> usize {
+        self.phases.iter().map(|phase| phase.transactions).sum()
+    }
+
+    pub fn fees(&self) -> u64 {
+        self.phases.iter().map(|phase| phase.fees).sum()
+    }
+
+    pub fn rent(&self) -> u64 {
+        self.phases.iter().map(|phase| phase.rent).sum()
+    }
+
+    pub fn compute_units(&self) -> u64 {
+        self.phases.iter().map(|phase| phase.compute_units).sum()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.phases.iter().map(|phase| phase.elapsed).sum()
+    }
+
+    /// Fees and rent per onboarded user, in lamports
+    pub fn per_user_lamports(&self) -> u64 {
+        (self.fees() + self.rent())
+            .checked_div(self.users as u64)
+            .unwrap_or_default()
+    }
+
+    /// Render the cost report as markdown
+    pub fn to_markdown(&self) -> String {
+        let mut report = format!("## {}\n\n", self.name);
+        report.push_str(&format!("- **Users**: {}\n", self.users));
+        report.push_str(&format!("- **Transactions**: {}\n", self.transactions()));
+        report.push_str(&format!(
+            "- **Wall-clock**: {}\n",
+            units::duration(self.elapsed())
+        ));
+        report.push_str(&format!(
+            "- **Total fees**: {}\n",
+            units::sol_and_lamports(self.fees())
+        ));
+        report.push_str(&format!(
+            "- **Total rent**: {}\n",
+            units::sol_and_lamports(self.rent())
+        ));
+        report.push_str(&format!(
+            "- **Per user**: {}\n\n",
+            units::sol_and_lamports(self.per_user_lamports())
+        ));
+
+        report.push_str("| Phase | Transactions | Fees | Rent | Compute Units | Wall-clock |\n");
+        report.push_str("|-------|--------------|------|------|---------------|------------|\n");
+        for phase in &self.phases {
+            report.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} |\n",
+                phase.name,
+                phase.transactions,
+                units::sol_and_lamports(phase.fees),
+                units::sol_and_lamports(phase.rent),
+                units::compute_units(phase.compute_units),
+                units::duration(phase.elapsed)
+            ));
+        }
+        report.push('\n');
+        report
+    }
+}
+
+/// Accounts a transaction may lock while `increase_tx_account_lock_limit`
+/// is inactive on mainnet
+pub const ACCOUNT_LOCK_LIMIT: usize = 64;
+
+/// One thaw check through the FAMP, with `extra_accounts` resolved from the
+/// gate's extra-account-metas
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GateCheckCost {
+    /// How the extra accounts are derived, e.g. "literal" or "dynamic"
+    pub seeds: String,
+    pub extra_accounts: usize,
+    /// Accounts the thaw transaction locks
+    pub transaction_accounts: usize,
+    /// Signed transaction size in bytes
+    pub transaction_size: usize,
+    /// Accounts a client fetched to resolve the extra accounts
+    pub account_fetches: usize,
+    /// Compute units of the check, in the FAMP and the gate
+    pub compute_units: u64,
+}
+
+impl GateCheckCost {
+    /// Compute units left under the default per-instruction limit
+    pub fn compute_headroom(&self) -> u64 {
+        u64::from(DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT).saturating_sub(self.compute_units)
+    }
+
+    /// Bytes left before the transaction no longer fits in a packet
+    pub fn size_headroom(&self) -> usize {
+        PACKET_DATA_SIZE.saturating_sub(self.transaction_size)
+    }
+
+    /// Accounts left under [`ACCOUNT_LOCK_LIMIT`]
+    pub fn account_headroom(&self) -> usize {
+        ACCOUNT_LOCK_LIMIT.saturating_sub(self.transaction_accounts)
+    }
+}
+
+/// Render gate check scaling as a markdown section
+fn gate_checks_to_markdown(gate_checks: &[GateCheckCost]) -> String {
+    let mut report = String::from("## Gate Check Scaling\n\n");
+    report.push_str(&format!(
+        "Headroom against {}, {} bytes and {} account locks.\n\n",
+        units::compute_units(u64::from(DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT)),
+        PACKET_DATA_SIZE,
+        ACCOUNT_LOCK_LIMIT
+    ));
+    report.push_str(
+        "| Seeds | Extra Accounts | Compute Units | CU Headroom | Size | Size Headroom | Accounts | Account Headroom | Fetches |\n",
+    );
+    report.push_str(
+        "|-------|----------------|---------------|-------------|------|---------------|----------|------------------|---------|\n",
+    );
+    for check in gate_checks {
+        report.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} | {} | {} | {} |\n",
+            check.seeds,
+            check.extra_accounts,
+            units::compute_units(check.compute_units),
+            units::compute_units(check.compute_headroom()),
+            check.transaction_size,
+            check.size_headroom(),
+            check.transaction_accounts,
+            check.account_headroom(),
+            check.account_fetches
+        ));
+    }
+    report.push('\n');
+    report
+}
+
+/// Write the cost report of `campaigns` and `gate_checks` to `output_path`
+pub fn generate_cost_report(
+    campaigns: &[CampaignCost],
+    gate_checks: &[GateCheckCost],
+    output_path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all("../../tests/reports").ok();
+    fs::write(output_path, render_cost_report(campaigns, gate_checks))?;
+    Ok(())
+}
+
+/// Render the cost report of `campaigns` and `gate_checks` as markdown
+pub fn render_cost_report(campaigns: &[CampaignCost], gate_checks: &[GateCheckCost]) -> String {
+    let mut report = String::from("# Token ACL Cost Report\n\n");
+    report.push_str(&format!("**Generated**: {}\n\n", time::report_timestamp()));
+    for campaign in campaigns {
+        report.push_str(&campaign.to_markdown());
+    }
+    if !gate_checks.is_empty() {
+        report.push_str(&gate_checks_to_markdown(gate_checks));
+    }
+    report
+}
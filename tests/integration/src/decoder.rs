@@ -0,0 +1,28 @@
+//! Decoding Token ACL transactions in tests
+//!
+//! The [`Decoder`] itself lives in the client's
+//! [`token_acl_client::decoder`], so explorers and the Python bindings
+//! decode with the same tables the suite checks. Tests deploy this
+//! repository's gates at fresh addresses, so [`crate::trace`] prints the
+//! admin instructions and extra accounts of the programs registered on the
+//! test's thread with [`register`].
+
+use solana_sdk::pubkey::Pubkey;
+use std::cell::RefCell;
+pub use token_acl_client::decoder::*;
+
+thread_local! {
+    static REGISTRY: RefCell<Decoder> = RefCell::new(Decoder::new());
+}
+
+/// Decode `program_id` as `kind` in traces printed on this thread
+pub fn register(program_id: Pubkey, kind: ProgramKind) {
+    REGISTRY.with(|registry| {
+        registry.borrow_mut().register(program_id, kind);
+    });
+}
+
+/// Run `f` with the decoder of this thread's registrations
+pub fn with_registry<R>(f: impl FnOnce(&Decoder) -> R) -> R {
+    REGISTRY.with(|registry| f(&registry.borrow()))
+}
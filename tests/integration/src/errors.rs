@@ -0,0 +1,181 @@
+//! Retryable error classification
+//!
+//! Integrators submitting Token ACL transactions need to know which failures
+//! to retry. This module maps transaction, banks, and RPC client errors into
+//! three categories:
+//!
+//! - [`ErrorClass::Retryable`]: transient; rebuild (fresh blockhash) and resend
+//! - [`ErrorClass::NeedsUserAction`]: will keep failing until someone acts,
+//!   e.g. funds the fee payer, signs, or gets the holder onto the allow list
+//! - [`ErrorClass::Fatal`]: the transaction itself is wrong; do not resend
+//!
+//! Gate denials surface as generic instruction errors, so the gate's logs are
+//! used to tell them apart from malformed transactions when available.
+
+use crate::analytics::{DecisionEvent, GateOperation};
+use serde::Serialize;
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_request::{RpcError, RpcResponseErrorData},
+};
+use solana_program_test::BanksClientError;
+use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+/// JSON-RPC error code returned by an unhealthy node
+const NODE_UNHEALTHY: i64 = -32005;
+
+/// What an integrator should do about a failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    Retryable,
+    Fatal,
+    NeedsUserAction,
+}
+
+/// A classified failure with a short machine-readable reason
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Classification {
+    pub class: ErrorClass,
+    pub reason: String,
+}
+
+impl Classification {
+    fn new(class: ErrorClass, reason: &str) -> Self {
+        Self {
+            class,
+            reason: reason.to_string(),
+        }
+    }
+
+    /// Whether resending (after rebuilding) may succeed without other changes
+    pub fn should_retry(&self) -> bool {
+        self.class == ErrorClass::Retryable
+    }
+}
+
+/// Classify a transaction error on its own
+pub fn classify_transaction_error(error: &TransactionError) -> Classification {
+    use ErrorClass::*;
+    match error {
+        TransactionError::BlockhashNotFound => Classification::new(Retryable, "blockhash_expired"),
+        TransactionError::AccountInUse
+        | TransactionError::WouldExceedMaxBlockCostLimit
+        | TransactionError::WouldExceedMaxAccountCostLimit
+        | TransactionError::WouldExceedMaxVoteCostLimit
+        | TransactionError::WouldExceedAccountDataBlockLimit
+        | TransactionError::WouldExceedAccountDataTotalLimit
+        | TransactionError::ClusterMaintenance => Classification::new(Retryable, "congestion"),
+        TransactionError::AlreadyProcessed => Classification::new(Fatal, "already_processed"),
+        TransactionError::AccountNotFound
+        | TransactionError::InsufficientFundsForFee
+        | TransactionError::InsufficientFundsForRent { .. } => {
+            Classification::new(NeedsUserAction, "insufficient_funds")
+        }
+        TransactionError::InstructionError(_, instruction_error) => {
+            classify_instruction_error(instruction_error)
+        }
+        _ => Classification::new(Fatal, "invalid_transaction"),
+    }
+}
+
+fn classify_instruction_error(error: &InstructionError) -> Classification {
+    use ErrorClass::*;
+    match error {
+        InstructionError::InsufficientFunds => {
+            Classification::new(NeedsUserAction, "insufficient_funds")
+        }
+        InstructionError::MissingRequiredSignature => {
+            Classification::new(NeedsUserAction, "missing_signature")
+        }
+        InstructionError::ComputationalBudgetExceeded => {
+            Classification::new(Fatal, "compute_budget_exceeded")
+        }
+        _ => Classification::new(Fatal, "instruction_failed"),
+    }
+}
+
+/// Classify a transaction error using the program logs it produced
+///
+/// A gate denial the holder can resolve (not listed, revoked, expired,
+/// blocked, pending review) needs user action; a malformed check (wrong
+/// PDA or owner) is fatal.
+pub fn classify_with_logs(error: &TransactionError, logs: &[String]) -> Classification {
+    if let TransactionError::InstructionError(_, InstructionError::InvalidAccountData) = error {
+        let decision = DecisionEvent::from_logs(GateOperation::Thaw, logs, false);
+        match decision.denial_reason.as_deref() {
+            Some(
+                reason @ ("not_listed" | "revoked" | "expired" | "blocked" | "not_blocked"
+                | "pending_review"),
+            ) => {
+                return Classification::new(
+                    ErrorClass::NeedsUserAction,
+                    &format!("gate_denied:{}", reason),
+                )
+            }
+            Some(reason @ ("invalid_pda" | "owner_mismatch" | "unsupported")) => {
+                return Classification::new(ErrorClass::Fatal, &format!("gate_rejected:{}", reason))
+            }
+            _ => {}
+        }
+    }
+    classify_transaction_error(error)
+}
+
+/// Classify a `BanksClient` error from a test run
+pub fn classify_banks_error(error: &BanksClientError) -> Classification {
+    match error {
+        BanksClientError::TransactionError(error) => classify_transaction_error(error),
+        BanksClientError::SimulationError { err, logs, .. } => classify_with_logs(err, logs),
+        BanksClientError::Io(_) | BanksClientError::RpcError(_) => {
+            Classification::new(ErrorClass::Retryable, "transport")
+        }
+        BanksClientError::ClientError(_) => Classification::new(ErrorClass::Fatal, "client"),
+    }
+}
+
+/// Classify an RPC client error from a live cluster
+pub fn classify_client_error(error: &ClientError) -> Classification {
+    use ErrorClass::*;
+    match &error.kind {
+        ClientErrorKind::Io(_) => Classification::new(Retryable, "transport"),
+        ClientErrorKind::Reqwest(error) => match error.status() {
+            Some(status) if status.as_u16() == 429 => {
+                Classification::new(Retryable, "rate_limited")
+            }
+            Some(status) if status.is_server_error() => {
+                Classification::new(Retryable, "server_error")
+            }
+            Some(_) => Classification::new(Fatal, "http_error"),
+            None => Classification::new(Retryable, "transport"),
+        },
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, data, .. }) => match data {
+            RpcResponseErrorData::SendTransactionPreflightFailure(simulation) => {
+                match &simulation.err {
+                    Some(error) => {
+                        classify_with_logs(error, simulation.logs.as_deref().unwrap_or_default())
+                    }
+                    None => Classification::new(Fatal, "preflight_failed"),
+                }
+            }
+            RpcResponseErrorData::NodeUnhealthy { .. } => {
+                Classification::new(Retryable, "node_unhealthy")
+            }
+            RpcResponseErrorData::Empty if *code == NODE_UNHEALTHY => {
+                Classification::new(Retryable, "node_unhealthy")
+            }
+            RpcResponseErrorData::Empty => Classification::new(Fatal, "rpc_error"),
+        },
+        ClientErrorKind::RpcError(RpcError::RpcRequestError(_)) => {
+            Classification::new(Retryable, "transport")
+        }
+        ClientErrorKind::RpcError(_) | ClientErrorKind::SerdeJson(_) => {
+            Classification::new(Fatal, "rpc_error")
+        }
+        ClientErrorKind::SigningError(_) => {
+            Classification::new(NeedsUserAction, "missing_signature")
+        }
+        ClientErrorKind::TransactionError(error) => classify_transaction_error(error),
+        ClientErrorKind::Custom(_) => Classification::new(Fatal, "client"),
+    }
+}
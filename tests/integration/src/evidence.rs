@@ -0,0 +1,252 @@
+//! Compliance evidence bundle export
+//!
+//! This module packages suite output (test reports, audit logs, coverage
+//! analysis, benchmark reports, and allow/block list snapshots) into a single
+//! `.tar.gz` archive that issuers can attach to regulatory filings or vendor
+//! due-diligence questionnaires.
+//!
+//! Every bundle carries a `manifest.json` listing each file with its SHA-256
+//! digest, and a `manifest.sig` holding an ed25519 signature over the
+//! manifest bytes by the exporting key. [`verify_bundle`] checks both.
+
+use crate::time;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// Archive path of the manifest
+pub const MANIFEST_NAME: &str = "manifest.json";
+/// Archive path of the manifest signature
+pub const SIGNATURE_NAME: &str = "manifest.sig";
+
+/// Category of a bundled artifact, which also selects its archive directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvidenceKind {
+    TestReport,
+    AuditLog,
+    Coverage,
+    Benchmarks,
+    ListSnapshot,
+}
+
+impl EvidenceKind {
+    /// Directory the artifact is stored under inside the archive
+    pub fn directory(&self) -> &'static str {
+        match self {
+            EvidenceKind::TestReport => "reports",
+            EvidenceKind::AuditLog => "audit",
+            EvidenceKind::Coverage => "coverage",
+            EvidenceKind::Benchmarks => "benchmarks",
+            EvidenceKind::ListSnapshot => "snapshots",
+        }
+    }
+}
+
+/// Manifest entry for one bundled file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvidenceFile {
+    pub kind: EvidenceKind,
+    /// Path inside the archive
+    pub path: String,
+    pub size: u64,
+    /// Hex-encoded SHA-256 of the file contents
+    pub sha256: String,
+}
+
+/// Signed description of a bundle's contents
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvidenceManifest {
+    pub suite: String,
+    pub suite_version: String,
+    pub created_at: String,
+    /// Base58 pubkey of the key that signed the manifest
+    pub signer: String,
+    pub files: Vec<EvidenceFile>,
+}
+
+/// Collects artifacts and exports them as a signed archive
+#[derive(Debug, Clone, Default)]
+pub struct EvidenceBundle {
+    files: BTreeMap<String, (EvidenceKind, Vec<u8>)>,
+}
+
+impl EvidenceBundle {
+    /// Create an empty bundle
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add in-memory contents under `<kind directory>/<name>`
+    ///
+    /// Adding the same path twice replaces the earlier contents.
+    pub fn add_bytes(&mut self, kind: EvidenceKind, name: &str, contents: Vec<u8>) -> &mut Self {
+        let path = format!("{}/{}", kind.directory(), name);
+        self.files.insert(path, (kind, contents));
+        self
+    }
+
+    /// Add a file from disk, keeping its file name
+    pub fn add_file(
+        &mut self,
+        kind: EvidenceKind,
+        path: impl AsRef<Path>,
+    ) -> Result<&mut Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| format!("Invalid evidence file path: {}", path.display()))?;
+        let contents = fs::read(path)?;
+        Ok(self.add_bytes(kind, name, contents))
+    }
+
+    /// Add a list snapshot serialized as pretty-printed JSON
+    pub fn add_list_snapshot<T: Serialize>(
+        &mut self,
+        name: &str,
+        snapshot: &T,
+    ) -> Result<&mut Self, serde_json::Error> {
+        let contents = serde_json::to_vec_pretty(snapshot)?;
+        Ok(self.add_bytes(
+            EvidenceKind::ListSnapshot,
+            &format!("{}.json", name),
+            contents,
+        ))
+    }
+
+    /// Build the manifest describing the current contents
+    pub fn manifest(&self, signer: &Pubkey) -> EvidenceManifest {
+        let files = self
+            .files
+            .iter()
+            .map(|(path, (kind, contents))| EvidenceFile {
+                kind: *kind,
+                path: path.clone(),
+                size: contents.len() as u64,
+                sha256: hex::encode(Sha256::digest(contents)),
+            })
+            .collect();
+
+        EvidenceManifest {
+            suite: "token-acl-testing-suite".to_string(),
+            suite_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: time::format_rfc3339(time::now()),
+            signer: signer.to_string(),
+            files,
+        }
+    }
+
+    /// Write the signed archive to `output_path` and return its manifest
+    pub fn export(
+        &self,
+        output_path: impl AsRef<Path>,
+        signer: &Keypair,
+    ) -> Result<EvidenceManifest, Box<dyn std::error::Error>> {
+        if self.files.is_empty() {
+            return Err("Evidence bundle is empty".into());
+        }
+
+        let manifest = self.manifest(&signer.pubkey());
+        let manifest_bytes = serde_json::to_vec_pretty(&manifest)?;
+        let signature = signer.sign_message(&manifest_bytes);
+
+        let output_path = output_path.as_ref();
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let encoder = flate2::write::GzEncoder::new(
+            fs::File::create(output_path)?,
+            flate2::Compression::default(),
+        );
+        let mut archive = tar::Builder::new(encoder);
+
+        append(&mut archive, MANIFEST_NAME, &manifest_bytes)?;
+        append(
+            &mut archive,
+            SIGNATURE_NAME,
+            signature.to_string().as_bytes(),
+        )?;
+        for (path, (_, contents)) in &self.files {
+            append(&mut archive, path, contents)?;
+        }
+        archive.into_inner()?.finish()?;
+
+        Ok(manifest)
+    }
+}
+
+/// Verify an exported bundle and return its manifest
+///
+/// Fails if the manifest signature does not match the manifest's signer, or
+/// if any listed file is missing, altered, or unlisted.
+pub fn verify_bundle(path: impl AsRef<Path>) -> Result<EvidenceManifest, String> {
+    let file = fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+
+    let mut contents = BTreeMap::new();
+    for entry in archive.entries().map_err(|e| e.to_string())? {
+        let mut entry = entry.map_err(|e| e.to_string())?;
+        let path = entry
+            .path()
+            .map_err(|e| e.to_string())?
+            .to_string_lossy()
+            .into_owned();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).map_err(|e| e.to_string())?;
+        contents.insert(path, data);
+    }
+
+    let manifest_bytes = contents
+        .remove(MANIFEST_NAME)
+        .ok_or("Bundle has no manifest")?;
+    let signature_text = contents
+        .remove(SIGNATURE_NAME)
+        .ok_or("Bundle has no manifest signature")?;
+    let manifest: EvidenceManifest =
+        serde_json::from_slice(&manifest_bytes).map_err(|e| e.to_string())?;
+
+    let signer: Pubkey = manifest
+        .signer
+        .parse()
+        .map_err(|_| "Manifest signer is not a valid pubkey".to_string())?;
+    let signature: Signature = String::from_utf8_lossy(&signature_text)
+        .parse()
+        .map_err(|_| "Manifest signature is malformed".to_string())?;
+    if !signature.verify(signer.as_ref(), &manifest_bytes) {
+        return Err("Manifest signature does not match signer".to_string());
+    }
+
+    for file in &manifest.files {
+        let data = contents
+            .remove(&file.path)
+            .ok_or_else(|| format!("Bundle is missing {}", file.path))?;
+        if hex::encode(Sha256::digest(&data)) != file.sha256 {
+            return Err(format!("Digest mismatch for {}", file.path));
+        }
+    }
+    if let Some(extra) = contents.keys().next() {
+        return Err(format!("Bundle contains unlisted file {}", extra));
+    }
+
+    Ok(manifest)
+}
+
+fn append<W: std::io::Write>(
+    archive: &mut tar::Builder<W>,
+    path: &str,
+    contents: &[u8],
+) -> std::io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    archive.append_data(&mut header, path, contents)
+}
@@ -0,0 +1,140 @@
+//! Transaction recording and explorer deep links
+//!
+//! Live-cluster runs record every transaction a test submits, with the slot
+//! it landed in, so a failing test can be inspected in Solana Explorer. The
+//! records travel on [`TestResultReport`](crate::TestResultReport) into the
+//! markdown and HTML reports.
+
+use crate::{rpc::RateLimitedRpc, TransactionRecord};
+use solana_client::client_error::Result as ClientResult;
+use solana_program_test::{BanksClient, BanksClientError};
+use solana_sdk::{clock::Slot, pubkey::Pubkey, signature::Signature, transaction::Transaction};
+
+const EXPLORER_URL: &str = "https://explorer.solana.com";
+
+/// Cluster the links point at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cluster {
+    MainnetBeta,
+    Devnet,
+    Testnet,
+    /// Any other RPC endpoint, e.g. a local validator
+    Custom(String),
+}
+
+impl Cluster {
+    /// Recognise the public clusters by RPC URL
+    pub fn from_rpc_url(url: &str) -> Self {
+        if url.contains("api.mainnet-beta.solana.com") {
+            Cluster::MainnetBeta
+        } else if url.contains("api.devnet.solana.com") {
+            Cluster::Devnet
+        } else if url.contains("api.testnet.solana.com") {
+            Cluster::Testnet
+        } else {
+            Cluster::Custom(url.to_string())
+        }
+    }
+
+    fn query(&self) -> String {
+        match self {
+            Cluster::MainnetBeta => String::new(),
+            Cluster::Devnet => "?cluster=devnet".to_string(),
+            Cluster::Testnet => "?cluster=testnet".to_string(),
+            Cluster::Custom(url) => format!("?cluster=custom&customUrl={}", encode_component(url)),
+        }
+    }
+}
+
+/// Explorer page for a transaction
+pub fn transaction_url(signature: &Signature, cluster: &Cluster) -> String {
+    format!("{}/tx/{}{}", EXPLORER_URL, signature, cluster.query())
+}
+
+/// Explorer page for an account
+pub fn account_url(address: &Pubkey, cluster: &Cluster) -> String {
+    format!("{}/address/{}{}", EXPLORER_URL, address, cluster.query())
+}
+
+/// Explorer page for a block
+pub fn block_url(slot: Slot, cluster: &Cluster) -> String {
+    format!("{}/block/{}{}", EXPLORER_URL, slot, cluster.query())
+}
+
+/// Percent-encode everything but unreserved URL characters
+fn encode_component(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Collects the transactions one test submits
+#[derive(Debug, Clone, Default)]
+pub struct TransactionRecorder {
+    records: Vec<TransactionRecord>,
+}
+
+impl TransactionRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, label: &str, signature: Signature, slot: Option<Slot>) {
+        self.records.push(TransactionRecord {
+            label: label.to_string(),
+            signature,
+            slot,
+            trace: None,
+        });
+    }
+
+    /// Process through a program-test bank, recording the transaction
+    /// whether or not it succeeds
+    pub async fn process(
+        &mut self,
+        banks_client: &mut BanksClient,
+        label: &str,
+        transaction: Transaction,
+    ) -> Result<(), BanksClientError> {
+        let signature = transaction.signatures[0];
+        let result = banks_client.process_transaction(transaction).await;
+        let slot = banks_client.get_root_slot().await.ok();
+        self.record(label, signature, slot);
+        result
+    }
+
+    /// Send to a live cluster and record the signature; the slot is filled
+    /// in by [`Self::resolve_slots`] once the cluster has processed it
+    pub async fn send(
+        &mut self,
+        rpc: &RateLimitedRpc,
+        label: &str,
+        transaction: &Transaction,
+    ) -> ClientResult<Signature> {
+        let result = rpc.send_transaction(transaction).await;
+        self.record(label, transaction.signatures[0], None);
+        result
+    }
+
+    /// Look up the slot of every record that does not have one yet
+    pub async fn resolve_slots(&mut self, rpc: &RateLimitedRpc) -> ClientResult<()> {
+        for record in self.records.iter_mut().filter(|r| r.slot.is_none()) {
+            record.slot = rpc.get_signature_slot(&record.signature).await?;
+        }
+        Ok(())
+    }
+
+    pub fn records(&self) -> &[TransactionRecord] {
+        &self.records
+    }
+
+    pub fn into_records(self) -> Vec<TransactionRecord> {
+        self.records
+    }
+}
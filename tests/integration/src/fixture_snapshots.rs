@@ -0,0 +1,442 @@
+//! Deterministic fixture snapshots
+//!
+//! The heavy scenario suites all start from the same state: a Token-2022
+//! mint whose freeze authority the issuer holds, the production allow list's
+//! config for it, an allow list record per holder, and the mint's thaw
+//! extra-account-metas list. Building that with real transactions costs a
+//! suite a transaction per handful of holders before its first assertion.
+//!
+//! [`FixtureSnapshot::build`] builds it once, on a bank whose clock is
+//! pinned to [`FIXTURE_UNIX_TIMESTAMP`] and with every key derived from the
+//! snapshot's name, so two builds hold byte-identical accounts.
+//! [`FixtureSnapshot::to_json`] renders them as an artifact that is
+//! committed under [`FIXTURE_SNAPSHOT_DIR`] and reviewed like code: one
+//! entry per account, named, with its data in hex. [`FixtureSnapshot::boot`]
+//! starts a bank with the accounts already in place.
+//!
+//! A committed snapshot goes stale when the programs change what they
+//! write. [`FixtureSnapshot::committed`] then builds a fresh one, and
+//! `UPDATE_FIXTURE_SNAPSHOTS=1` writes it back over the artifact.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use borsh::BorshDeserialize;
+use production_allow_list::{AllowListRecord, ADD_TO_ALLOW_LIST, INITIALIZE};
+use serde::{Deserialize, Serialize};
+use solana_program_test::{processor, ProgramTest};
+use solana_sdk::{
+    account::Account,
+    clock::Clock,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::Transaction,
+};
+use spl_token_2022::state::AccountState;
+use token_acl_interface::{
+    find_missing_record_policy_address, find_thaw_extra_account_metas_address,
+};
+
+use crate::{
+    common::program_test::{mint_account, token_account},
+    context::{Namespace, TestContext},
+    labels,
+};
+
+/// Where committed snapshots live
+pub const FIXTURE_SNAPSHOT_DIR: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixture_snapshots");
+
+/// Bumped whenever [`FixtureSnapshot::build`] creates something else; a
+/// snapshot of another version is stale
+pub const FIXTURE_SNAPSHOT_VERSION: u32 = 1;
+
+/// Cluster time of the bank a snapshot is built and booted on
+pub const FIXTURE_UNIX_TIMESTAMP: i64 = 1_729_071_000;
+
+/// The allow list fixture the heavy suites share
+pub const ALLOW_LIST_FIXTURE: &str = "allow_list_100";
+
+/// Holders of [`ALLOW_LIST_FIXTURE`]
+pub const ALLOW_LIST_FIXTURE_HOLDERS: usize = 100;
+
+/// Keys of a snapshot derive from its name under this run seed
+const FIXTURE_RUN_SEED: &[u8] = b"fixture-snapshot";
+
+/// `ADD_TO_ALLOW_LIST` instructions per build transaction; well inside the
+/// packet size and the compute budget
+const ADDS_PER_TRANSACTION: usize = 8;
+
+/// One account of a snapshot
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixtureAccount {
+    /// `mint`, `config`, `thaw_extra_account_metas` or `holder-NNN`
+    pub label: String,
+    pub address: Pubkey,
+    pub account: Account,
+}
+
+/// The accounts of a fully initialized allow list, see the
+/// [module docs](self)
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixtureSnapshot {
+    pub name: String,
+    pub version: u32,
+    pub unix_timestamp: i64,
+    pub gate_program_id: Pubkey,
+    /// Mint, config, thaw list, then the records in holder order
+    pub accounts: Vec<FixtureAccount>,
+}
+
+/// The committed file's layout
+#[derive(Serialize, Deserialize)]
+struct SnapshotFile {
+    name: String,
+    version: u32,
+    unix_timestamp: i64,
+    gate_program_id: String,
+    accounts: Vec<SnapshotFileAccount>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotFileAccount {
+    label: String,
+    address: String,
+    owner: String,
+    lamports: u64,
+    rent_epoch: u64,
+    executable: bool,
+    /// Hex
+    data: String,
+}
+
+fn parse_pubkey(value: &str, what: &str) -> Result<Pubkey, String> {
+    value
+        .parse()
+        .map_err(|e| format!("Invalid {} {}: {}", what, value, e))
+}
+
+/// Label of holder `index` in a snapshot
+fn holder_label(index: usize) -> String {
+    format!("holder-{:03}", index)
+}
+
+impl FixtureSnapshot {
+    /// Keys of the snapshot called `name`
+    fn namespace(name: &str) -> Namespace {
+        Namespace::with_run_seed(name, FIXTURE_RUN_SEED)
+    }
+
+    /// Build the snapshot called `name` with `holders` allow list records,
+    /// through real transactions on a bank of its own
+    pub async fn build(name: &str, holders: usize) -> Result<Self, String> {
+        let namespace = Self::namespace(name);
+        let gate_program_id = namespace.address("gate");
+        let authority = namespace.keypair("authority");
+        let mint = namespace.address("mint");
+
+        let mut program_test = ProgramTest::new(
+            "production_allow_list",
+            gate_program_id,
+            processor!(production_allow_list::process_instruction),
+        );
+        program_test.add_account(mint, mint_account(&authority.pubkey(), 0));
+
+        let mut context = TestContext::start(name, program_test).await?;
+        pin_clock(&mut context).await?;
+
+        let config = production_allow_list::config_address(&mint, &gate_program_id).address();
+        let payer = context.payer.pubkey();
+        let initialize = Instruction::new_with_bytes(
+            gate_program_id,
+            &[INITIALIZE],
+            vec![
+                AccountMeta::new(config, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(authority.pubkey(), true),
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        let initialize_list = production_allow_list::initialize_thaw_extra_account_metas(
+            &gate_program_id,
+            &payer,
+            &mint,
+        );
+        send(&mut context, &[initialize, initialize_list], &authority).await?;
+
+        let holder_addresses: Vec<Pubkey> = (0..holders)
+            .map(|index| namespace.address(&holder_label(index)))
+            .collect();
+        for batch in holder_addresses.chunks(ADDS_PER_TRANSACTION) {
+            let adds: Vec<Instruction> = batch
+                .iter()
+                .map(|holder| {
+                    let record =
+                        production_allow_list::allow_list_address(&mint, holder, &gate_program_id)
+                            .address();
+                    Instruction::new_with_bytes(
+                        gate_program_id,
+                        &[ADD_TO_ALLOW_LIST],
+                        vec![
+                            AccountMeta::new_readonly(config, false),
+                            AccountMeta::new(record, false),
+                            AccountMeta::new_readonly(mint, false),
+                            AccountMeta::new_readonly(*holder, false),
+                            AccountMeta::new_readonly(authority.pubkey(), true),
+                            AccountMeta::new(payer, true),
+                            AccountMeta::new_readonly(system_program::id(), false),
+                        ],
+                    )
+                })
+                .collect();
+            send(&mut context, &adds, &authority).await?;
+        }
+
+        let (list_address, _) = find_thaw_extra_account_metas_address(&mint, &gate_program_id);
+        let mut addresses = vec![
+            ("mint".to_string(), mint),
+            ("config".to_string(), config),
+            ("thaw_extra_account_metas".to_string(), list_address),
+        ];
+        addresses.extend(holder_addresses.iter().enumerate().map(|(index, holder)| {
+            let record = production_allow_list::allow_list_address(&mint, holder, &gate_program_id)
+                .address();
+            (holder_label(index), record)
+        }));
+
+        let mut accounts = Vec::with_capacity(addresses.len());
+        for (label, address) in addresses {
+            let account = context
+                .banks_client
+                .get_account(address)
+                .await
+                .map_err(|e| format!("Failed to read {}: {}", label, e))?
+                .ok_or_else(|| format!("The build did not create {}", label))?;
+            accounts.push(FixtureAccount {
+                label,
+                address,
+                account,
+            });
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            version: FIXTURE_SNAPSHOT_VERSION,
+            unix_timestamp: FIXTURE_UNIX_TIMESTAMP,
+            gate_program_id,
+            accounts,
+        })
+    }
+
+    /// Path of the committed snapshot called `name`
+    pub fn path(name: &str) -> PathBuf {
+        Path::new(FIXTURE_SNAPSHOT_DIR).join(format!("{}.json", name))
+    }
+
+    /// The committed snapshot called `name`, or a fresh build with
+    /// `holders` records if it is missing, stale or does not have them;
+    /// with `UPDATE_FIXTURE_SNAPSHOTS=1`, a fresh build is written back
+    pub async fn committed(name: &str, holders: usize) -> Result<Self, String> {
+        let path = Self::path(name);
+        let update = std::env::var("UPDATE_FIXTURE_SNAPSHOTS").is_ok_and(|value| value == "1");
+        if !update {
+            if let Ok(snapshot) = Self::load(&path) {
+                if snapshot.version == FIXTURE_SNAPSHOT_VERSION
+                    && snapshot.holders().len() == holders
+                {
+                    return Ok(snapshot);
+                }
+            }
+        }
+        let snapshot = Self::build(name, holders).await?;
+        if update {
+            snapshot.save(&path)?;
+        } else {
+            println!(
+                "⚠️  Fixture snapshot {} is missing or stale; rebuilt it. Run with UPDATE_FIXTURE_SNAPSHOTS=1 to commit it.",
+                name
+            );
+        }
+        Ok(snapshot)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::from_json(&json)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        fs::write(path, self.to_json()?)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        let file = SnapshotFile {
+            name: self.name.clone(),
+            version: self.version,
+            unix_timestamp: self.unix_timestamp,
+            gate_program_id: self.gate_program_id.to_string(),
+            accounts: self
+                .accounts
+                .iter()
+                .map(|entry| SnapshotFileAccount {
+                    label: entry.label.clone(),
+                    address: entry.address.to_string(),
+                    owner: entry.account.owner.to_string(),
+                    lamports: entry.account.lamports,
+                    rent_epoch: entry.account.rent_epoch,
+                    executable: entry.account.executable,
+                    data: hex::encode(&entry.account.data),
+                })
+                .collect(),
+        };
+        let mut json = serde_json::to_string_pretty(&file)
+            .map_err(|e| format!("Failed to render snapshot {}: {}", self.name, e))?;
+        json.push('\n');
+        Ok(json)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let file: SnapshotFile =
+            serde_json::from_str(json).map_err(|e| format!("Invalid fixture snapshot: {}", e))?;
+        let accounts = file
+            .accounts
+            .into_iter()
+            .map(|entry| {
+                Ok(FixtureAccount {
+                    address: parse_pubkey(&entry.address, "address")?,
+                    account: Account {
+                        lamports: entry.lamports,
+                        data: hex::decode(&entry.data)
+                            .map_err(|e| format!("Invalid data of {}: {}", entry.label, e))?,
+                        owner: parse_pubkey(&entry.owner, "owner")?,
+                        executable: entry.executable,
+                        rent_epoch: entry.rent_epoch,
+                    },
+                    label: entry.label,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self {
+            gate_program_id: parse_pubkey(&file.gate_program_id, "gate program id")?,
+            name: file.name,
+            version: file.version,
+            unix_timestamp: file.unix_timestamp,
+            accounts,
+        })
+    }
+
+    /// Start a bank for `test_name` with the gate deployed at the
+    /// snapshot's program id, every account of the snapshot in place and
+    /// the clock at the snapshot's time
+    pub async fn boot(&self, test_name: &str) -> Result<TestContext, String> {
+        let mut program_test = ProgramTest::new(
+            "production_allow_list",
+            self.gate_program_id,
+            processor!(production_allow_list::process_instruction),
+        );
+        for entry in &self.accounts {
+            program_test.add_account(entry.address, entry.account.clone());
+        }
+        let mut context = TestContext::start(test_name, program_test).await?;
+        pin_clock(&mut context).await?;
+
+        labels::label(self.gate_program_id, "gate");
+        for entry in &self.accounts {
+            labels::label(entry.address, &entry.label);
+        }
+        Ok(context)
+    }
+
+    /// The account labeled `label`
+    pub fn account(&self, label: &str) -> Option<&FixtureAccount> {
+        self.accounts.iter().find(|entry| entry.label == label)
+    }
+
+    pub fn mint(&self) -> Pubkey {
+        Self::namespace(&self.name).address("mint")
+    }
+
+    /// Config authority and the mint's freeze authority
+    pub fn authority(&self) -> Keypair {
+        Self::namespace(&self.name).keypair("authority")
+    }
+
+    pub fn config(&self) -> Pubkey {
+        production_allow_list::config_address(&self.mint(), &self.gate_program_id).address()
+    }
+
+    /// The mint's missing-record policy PDA; the snapshot does not create
+    /// it, so the gate's default applies
+    pub fn missing_record_policy(&self) -> Pubkey {
+        find_missing_record_policy_address(&self.mint(), &self.gate_program_id).0
+    }
+
+    /// Create a frozen token account of the snapshot's mint for `owner` on
+    /// a booted bank
+    pub fn create_token_account(&self, context: &mut TestContext, owner: &Pubkey) -> Pubkey {
+        let address = Pubkey::new_unique();
+        context.set_account(
+            &address,
+            &token_account(&self.mint(), owner, 0, AccountState::Frozen).into(),
+        );
+        address
+    }
+
+    /// The holders with a record, read from the records themselves
+    pub fn holders(&self) -> Vec<Pubkey> {
+        self.accounts
+            .iter()
+            .filter(|entry| entry.label.starts_with("holder-"))
+            .filter_map(|entry| AllowListRecord::try_from_slice(&entry.account.data).ok())
+            .map(|record| record.user)
+            .collect()
+    }
+}
+
+/// Set the bank's clock to [`FIXTURE_UNIX_TIMESTAMP`]
+async fn pin_clock(context: &mut TestContext) -> Result<(), String> {
+    let clock: Clock = context
+        .banks_client
+        .get_sysvar()
+        .await
+        .map_err(|e| format!("Failed to read the clock: {}", e))?;
+    context.set_sysvar(&Clock {
+        unix_timestamp: FIXTURE_UNIX_TIMESTAMP,
+        ..clock
+    });
+    Ok(())
+}
+
+async fn send(
+    context: &mut TestContext,
+    instructions: &[Instruction],
+    authority: &Keypair,
+) -> Result<(), String> {
+    let blockhash = context
+        .get_new_latest_blockhash()
+        .await
+        .map_err(|e| format!("Failed to get a blockhash: {}", e))?;
+    let payer = context.payer();
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&payer.pubkey()),
+        &[&payer, authority],
+        blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| format!("Fixture build transaction failed: {}", e))
+}
@@ -0,0 +1,284 @@
+//! Fuzzing the gates' instruction processors
+//!
+//! The cargo-fuzz targets in `fuzz/` hand arbitrary bytes to
+//! [`FuzzTarget::execute`], which turns them into an account list and
+//! instruction data and calls the gate's `process_instruction` directly,
+//! without a bank in between. A gate has to answer anything with an error:
+//! a short discriminator, a record of the wrong length, Borsh that does not
+//! decode, an account passed twice. A panic, including an out-of-bounds
+//! slice, is a finding.
+//!
+//! libFuzzer keeps the inputs that panicked under `fuzz/artifacts/<target>/`.
+//! [`replay`] runs those, the hand-written seeds in `fuzz/seeds/<target>/`
+//! and any corpus a local fuzzing run grew in `fuzz/corpus/<target>/`, and
+//! the coverage report lists every input that still panics (see
+//! `CoverageAnalysis::record_fuzz_replays`).
+//!
+//! # Input layout
+//!
+//! The first byte is the number of accounts (up to [`MAX_ACCOUNTS`]). Each
+//! account takes three bytes and then its data:
+//!
+//! - flags: bit 0 signer, bit 1 writable, bits 2-3 owner (the gate, the
+//!   system program, Token-2022, another program), bits 4-5 key (fresh, the
+//!   gate, the system program, the rent sysvar), bit 6 a duplicate of an
+//!   earlier account
+//! - data length, little-endian, modulo [`MAX_ACCOUNT_DATA`]; for a
+//!   duplicate, the low byte picks the account it repeats
+//!
+//! then the data, padded with zeros if the input runs out. Whatever is left
+//! is the instruction data.
+
+use std::{
+    fs, io,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+};
+
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey, system_program, sysvar,
+};
+
+/// Directory of the cargo-fuzz crate
+pub const FUZZ_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../fuzz");
+
+/// Most accounts an input passes; more than any gate instruction takes
+pub const MAX_ACCOUNTS: usize = 10;
+
+/// Account data lengths wrap at this; longer than any gate record
+pub const MAX_ACCOUNT_DATA: usize = 512;
+
+/// Lamports every account starts with
+const ACCOUNT_LAMPORTS: u64 = 1_000_000_000;
+
+/// A gate program with a fuzz target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FuzzTarget {
+    /// `examples/allow_list`
+    AllowList,
+    /// `programs/production_allow_list`
+    ProductionAllowList,
+    /// `examples/block_list`
+    BlockList,
+}
+
+impl FuzzTarget {
+    pub const ALL: [FuzzTarget; 3] = [
+        FuzzTarget::AllowList,
+        FuzzTarget::ProductionAllowList,
+        FuzzTarget::BlockList,
+    ];
+
+    /// The fuzz target's name, as `cargo fuzz run` takes it
+    pub fn name(&self) -> &'static str {
+        match self {
+            FuzzTarget::AllowList => "allow_list",
+            FuzzTarget::ProductionAllowList => "production_allow_list",
+            FuzzTarget::BlockList => "block_list",
+        }
+    }
+
+    /// Where the gate runs; fixed, so a saved input replays the same way
+    pub fn program_id(&self) -> Pubkey {
+        let tag = match self {
+            FuzzTarget::AllowList => 0xF1,
+            FuzzTarget::ProductionAllowList => 0xF2,
+            FuzzTarget::BlockList => 0xF3,
+        };
+        Pubkey::new_from_array([tag; 32])
+    }
+
+    fn processor(&self) -> fn(&Pubkey, &[AccountInfo], &[u8]) -> ProgramResult {
+        match self {
+            FuzzTarget::AllowList => example_allow_list::process_instruction,
+            FuzzTarget::ProductionAllowList => production_allow_list::process_instruction,
+            FuzzTarget::BlockList => example_block_list::process_instruction,
+        }
+    }
+
+    /// Run the gate on the input `bytes` describe; panics where the gate
+    /// does, which is what libFuzzer watches for
+    pub fn execute(&self, bytes: &[u8]) -> ProgramResult {
+        let program_id = self.program_id();
+        let input = FuzzInput::parse(&program_id, bytes);
+        let mut lamports: Vec<u64> = input.accounts.iter().map(|_| ACCOUNT_LAMPORTS).collect();
+        let mut data: Vec<Vec<u8>> = input
+            .accounts
+            .iter()
+            .map(|account| account.data.clone())
+            .collect();
+
+        let mut infos: Vec<AccountInfo> = Vec::with_capacity(input.accounts.len());
+        for ((account, lamports), data) in input
+            .accounts
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+        {
+            let info = match account.duplicate_of {
+                // A duplicate shares the earlier account's lamports and
+                // data, as the runtime passes it
+                Some(index) => infos[index].clone(),
+                None => AccountInfo::new(
+                    &account.key,
+                    account.is_signer,
+                    account.is_writable,
+                    lamports,
+                    data,
+                    &account.owner,
+                    false,
+                    0,
+                ),
+            };
+            infos.push(info);
+        }
+        (self.processor())(&program_id, &infos, &input.instruction_data)
+    }
+
+    /// [`FuzzTarget::execute`], with a panic caught as
+    /// [`FuzzOutcome::Panic`]
+    pub fn run(&self, bytes: &[u8]) -> FuzzOutcome {
+        match panic::catch_unwind(AssertUnwindSafe(|| self.execute(bytes))) {
+            Ok(Ok(())) => FuzzOutcome::Ok,
+            Ok(Err(error)) => FuzzOutcome::Err(error),
+            Err(payload) => FuzzOutcome::Panic(panic_message(payload.as_ref())),
+        }
+    }
+}
+
+/// How a gate answered an input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FuzzOutcome {
+    Ok,
+    Err(ProgramError),
+    /// The gate panicked, with the panic's message
+    Panic(String),
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "non-string panic payload".to_string()
+    }
+}
+
+/// One account of a [`FuzzInput`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzAccount {
+    pub key: Pubkey,
+    pub owner: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+    pub data: Vec<u8>,
+    /// Passed again as this earlier account rather than as itself
+    pub duplicate_of: Option<usize>,
+}
+
+/// The accounts and instruction data fuzz bytes describe, see the
+/// [module docs](self#input-layout)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzInput {
+    pub accounts: Vec<FuzzAccount>,
+    pub instruction_data: Vec<u8>,
+}
+
+impl FuzzInput {
+    /// Read the input `bytes` describe for the gate at `program_id`; every
+    /// byte string is an input
+    pub fn parse(program_id: &Pubkey, bytes: &[u8]) -> Self {
+        let mut bytes = bytes.iter().copied();
+        let count = bytes.next().unwrap_or(0) as usize % (MAX_ACCOUNTS + 1);
+        let mut accounts = Vec::with_capacity(count);
+        for index in 0..count {
+            let flags = bytes.next().unwrap_or(0);
+            let length = u16::from_le_bytes([bytes.next().unwrap_or(0), bytes.next().unwrap_or(0)]);
+            let duplicate_of = (flags & 0x40 != 0 && index > 0).then(|| length as usize % index);
+            let data = match duplicate_of {
+                Some(_) => Vec::new(),
+                None => (0..length as usize % MAX_ACCOUNT_DATA)
+                    .map(|_| bytes.next().unwrap_or(0))
+                    .collect(),
+            };
+            let owner = match (flags >> 2) & 0b11 {
+                0 => *program_id,
+                1 => system_program::id(),
+                2 => spl_token_2022::id(),
+                _ => Pubkey::new_from_array([0xEE; 32]),
+            };
+            let key = match (flags >> 4) & 0b11 {
+                0 => Pubkey::new_from_array([index as u8 + 1; 32]),
+                1 => *program_id,
+                2 => system_program::id(),
+                _ => sysvar::rent::id(),
+            };
+            accounts.push(FuzzAccount {
+                key,
+                owner,
+                is_signer: flags & 0x01 != 0,
+                is_writable: flags & 0x02 != 0,
+                data,
+                duplicate_of,
+            });
+        }
+        Self {
+            accounts,
+            instruction_data: bytes.collect(),
+        }
+    }
+}
+
+/// One saved input, replayed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzReplay {
+    pub target: FuzzTarget,
+    /// Path of the input under [`FUZZ_DIR`], e.g.
+    /// `artifacts/allow_list/crash-…`
+    pub input: String,
+    pub outcome: FuzzOutcome,
+}
+
+impl FuzzReplay {
+    /// The input still panics the gate
+    pub fn is_finding(&self) -> bool {
+        matches!(self.outcome, FuzzOutcome::Panic(_))
+    }
+}
+
+/// Where saved inputs live, under [`FUZZ_DIR`]
+const INPUT_DIRS: [&str; 3] = ["seeds", "corpus", "artifacts"];
+
+/// Replay every saved input of every target under `fuzz_dir`: seeds, the
+/// local corpus and libFuzzer's artifacts, in that order. Missing
+/// directories hold no inputs.
+pub fn replay(fuzz_dir: &Path) -> io::Result<Vec<FuzzReplay>> {
+    let mut replays = Vec::new();
+    for target in FuzzTarget::ALL {
+        for dir in INPUT_DIRS {
+            let relative = Path::new(dir).join(target.name());
+            let entries = match fs::read_dir(fuzz_dir.join(&relative)) {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            let mut files: Vec<_> = entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<io::Result<_>>()?;
+            files.retain(|path| path.is_file());
+            files.sort();
+            for path in files {
+                let bytes = fs::read(&path)?;
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                replays.push(FuzzReplay {
+                    target,
+                    input: relative.join(name.as_ref()).display().to_string(),
+                    outcome: target.run(&bytes),
+                });
+            }
+        }
+    }
+    Ok(replays)
+}
@@ -0,0 +1,284 @@
+//! Freeze/thaw history reconstruction
+//!
+//! This module rebuilds the freeze/thaw timeline of a single token account
+//! from recorded transaction history, so an auditor can answer questions such
+//! as "why was this account thawed at slot X?". Each entry records who
+//! triggered the change, whether it went through the issuer's freeze
+//! authority (permissioned) or a gate check (permissionless), and the gate's
+//! decision.
+//!
+//! Recorded transactions are expected to list inner (CPI) instructions
+//! alongside top-level ones, as returned by `getTransaction` with
+//! `innerInstructions` flattened. A transaction containing an sRFC 37 gate
+//! check for the account is treated as one permissionless attempt, even if it
+//! also carries the token program's thaw or freeze CPI.
+
+use crate::analytics::{DecisionEvent, GateOperation};
+use crate::fixtures::test_data;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{pubkey::Pubkey, transaction::Transaction};
+use spl_token_2022::instruction::TokenInstruction;
+
+/// Legacy SPL Token program, whose freeze/thaw layout matches Token-2022
+pub const SPL_TOKEN_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+/// A single instruction as it appeared in a transaction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedInstruction {
+    pub program_id: Pubkey,
+    pub accounts: Vec<Pubkey>,
+    pub data: Vec<u8>,
+}
+
+/// A landed (or failed) transaction as retrieved from history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedTransaction {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub signers: Vec<Pubkey>,
+    /// Top-level and inner instructions, in execution order
+    pub instructions: Vec<RecordedInstruction>,
+    pub logs: Vec<String>,
+    pub succeeded: bool,
+}
+
+impl RecordedTransaction {
+    /// Record a transaction submitted during a test run
+    pub fn from_transaction(
+        transaction: &Transaction,
+        slot: u64,
+        block_time: Option<i64>,
+        logs: Vec<String>,
+        succeeded: bool,
+    ) -> Self {
+        let message = &transaction.message;
+        let signer_count = message.header.num_required_signatures as usize;
+        let instructions = message
+            .instructions
+            .iter()
+            .map(|instruction| RecordedInstruction {
+                program_id: message.account_keys[instruction.program_id_index as usize],
+                accounts: instruction
+                    .accounts
+                    .iter()
+                    .map(|index| message.account_keys[*index as usize])
+                    .collect(),
+                data: instruction.data.clone(),
+            })
+            .collect();
+
+        Self {
+            signature: transaction.signatures[0].to_string(),
+            slot,
+            block_time,
+            signers: message.account_keys[..signer_count].to_vec(),
+            instructions,
+            logs,
+            succeeded,
+        }
+    }
+}
+
+/// Direction of a freeze state change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FreezeAction {
+    Freeze,
+    Thaw,
+}
+
+/// How a freeze state change was authorized
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthorizationMode {
+    /// Signed by the freeze authority directly
+    Permissioned,
+    /// Authorized by a gate program decision
+    Permissionless,
+}
+
+/// One freeze/thaw attempt on the account
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimelineEntry {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub action: FreezeAction,
+    pub mode: AuthorizationMode,
+    /// Freeze authority for permissioned changes, sRFC 37 caller otherwise
+    pub triggered_by: Pubkey,
+    /// Whether the transaction landed and changed the account state
+    pub applied: bool,
+    /// Gate decision, for permissionless attempts only
+    pub gate_decision: Option<DecisionEvent>,
+}
+
+/// Programs whose instructions are interpreted during reconstruction
+#[derive(Debug, Clone)]
+pub struct HistoryConfig {
+    pub token_programs: Vec<Pubkey>,
+    pub gate_program: Pubkey,
+}
+
+impl HistoryConfig {
+    /// Interpret SPL Token and Token-2022 instructions plus `gate_program` checks
+    pub fn new(gate_program: Pubkey) -> Self {
+        Self {
+            token_programs: vec![spl_token_2022::id(), SPL_TOKEN_PROGRAM_ID],
+            gate_program,
+        }
+    }
+}
+
+/// Chronological freeze/thaw history of one token account
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountTimeline {
+    pub token_account: Pubkey,
+    pub entries: Vec<TimelineEntry>,
+}
+
+impl AccountTimeline {
+    /// Rebuild the timeline of `token_account` from transaction history
+    ///
+    /// History may be supplied in any order; entries are sorted by slot,
+    /// keeping the input order for transactions in the same slot.
+    pub fn reconstruct(
+        token_account: &Pubkey,
+        history: &[RecordedTransaction],
+        config: &HistoryConfig,
+    ) -> Self {
+        let mut entries: Vec<TimelineEntry> = history
+            .iter()
+            .filter_map(|transaction| entry_for(token_account, transaction, config))
+            .collect();
+        entries.sort_by_key(|entry| entry.slot);
+
+        Self {
+            token_account: *token_account,
+            entries,
+        }
+    }
+
+    /// Entries that actually changed the account state
+    pub fn applied(&self) -> impl Iterator<Item = &TimelineEntry> {
+        self.entries.iter().filter(|entry| entry.applied)
+    }
+
+    /// The last applied change at or before `slot`, if any
+    pub fn last_change_at(&self, slot: u64) -> Option<&TimelineEntry> {
+        self.applied().filter(|entry| entry.slot <= slot).last()
+    }
+
+    /// Whether the account was frozen at `slot`, if the history covers it
+    pub fn is_frozen_at(&self, slot: u64) -> Option<bool> {
+        self.last_change_at(slot)
+            .map(|entry| entry.action == FreezeAction::Freeze)
+    }
+
+    /// Explain why the account was thawed at `block_time`
+    ///
+    /// Returns the applied thaw in effect at that time, or `None` if the
+    /// account was frozen (or its state unknown) at that moment.
+    pub fn explain_thaw_at(&self, block_time: i64) -> Option<&TimelineEntry> {
+        self.applied()
+            .filter(|entry| entry.block_time.is_some_and(|time| time <= block_time))
+            .last()
+            .filter(|entry| entry.action == FreezeAction::Thaw)
+    }
+
+    /// Render the timeline as a markdown table for audit reports
+    pub fn to_markdown(&self) -> String {
+        let mut report = format!("# Freeze/Thaw History for {}\n\n", self.token_account);
+        report.push_str("| Slot | Action | Mode | Triggered By | Applied | Gate Decision |\n");
+        report.push_str("|------|--------|------|--------------|---------|---------------|\n");
+
+        for entry in &self.entries {
+            let decision = match &entry.gate_decision {
+                Some(decision) if decision.approved => "approved".to_string(),
+                Some(decision) => format!(
+                    "denied ({})",
+                    decision.denial_reason.as_deref().unwrap_or("other")
+                ),
+                None => "-".to_string(),
+            };
+            report.push_str(&format!(
+                "| {} | {:?} | {:?} | {} | {} | {} |\n",
+                entry.slot,
+                entry.action,
+                entry.mode,
+                entry.triggered_by,
+                if entry.applied { "yes" } else { "no" },
+                decision
+            ));
+        }
+        report
+    }
+}
+
+fn entry_for(
+    token_account: &Pubkey,
+    transaction: &RecordedTransaction,
+    config: &HistoryConfig,
+) -> Option<TimelineEntry> {
+    let entry = |action, mode, triggered_by, gate_decision| TimelineEntry {
+        signature: transaction.signature.clone(),
+        slot: transaction.slot,
+        block_time: transaction.block_time,
+        action,
+        mode,
+        triggered_by,
+        applied: transaction.succeeded,
+        gate_decision,
+    };
+
+    // sRFC 37 gate accounts: caller, token account, mint, extra metas, ...
+    let gate_check = transaction.instructions.iter().find_map(|instruction| {
+        if instruction.program_id != config.gate_program
+            || instruction.accounts.get(1) != Some(token_account)
+        {
+            return None;
+        }
+        let (action, operation) = match instruction.data.get(..8)? {
+            data if data == test_data::THAW_DISCRIMINATOR => {
+                (FreezeAction::Thaw, GateOperation::Thaw)
+            }
+            data if data == test_data::FREEZE_DISCRIMINATOR => {
+                (FreezeAction::Freeze, GateOperation::Freeze)
+            }
+            _ => return None,
+        };
+        Some((action, operation, *instruction.accounts.first()?))
+    });
+    if let Some((action, operation, caller)) = gate_check {
+        let decision =
+            DecisionEvent::from_logs(operation, &transaction.logs, transaction.succeeded);
+        return Some(entry(
+            action,
+            AuthorizationMode::Permissionless,
+            caller,
+            Some(decision),
+        ));
+    }
+
+    // Token program accounts: token account, mint, freeze authority
+    transaction.instructions.iter().find_map(|instruction| {
+        if !config.token_programs.contains(&instruction.program_id)
+            || instruction.accounts.first() != Some(token_account)
+        {
+            return None;
+        }
+        let action = match TokenInstruction::unpack(&instruction.data).ok()? {
+            TokenInstruction::FreezeAccount => FreezeAction::Freeze,
+            TokenInstruction::ThawAccount => FreezeAction::Thaw,
+            _ => return None,
+        };
+        Some(entry(
+            action,
+            AuthorizationMode::Permissioned,
+            *instruction.accounts.get(2)?,
+            None,
+        ))
+    })
+}
@@ -0,0 +1,262 @@
+//! Holder enumeration for emergency operations
+//!
+//! A mass freeze, a drill or a holder analytics run starts from the list of
+//! every token account of a mint. [`enumerate_holders`] builds it the way an
+//! RPC node can serve it at 10k+ holders: one `getProgramAccounts` on
+//! Token-2022 filtered to the mint that returns addresses only, then the
+//! accounts themselves in pages of `getMultipleAccounts`, so no single
+//! response carries every holder's data and a page can be retried alone.
+//!
+//! Token-2022 accounts have no fixed size once they carry extensions, so the
+//! mint at bytes 0..32 is the only filter; whatever then does not decode as
+//! a token account of the mint (it changed hands between the two calls, or
+//! was closed) is counted as skipped rather than failing the run.
+
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+    rpc_request::MAX_MULTIPLE_ACCOUNTS,
+};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use spl_token_2022::{
+    extension::StateWithExtensions,
+    state::{Account as TokenAccount, AccountState},
+};
+use std::time::{Duration, Instant};
+
+use crate::{svm::SvmHarness, units};
+
+/// Where the mint sits in a token account
+pub const TOKEN_ACCOUNT_MINT_OFFSET: usize = 0;
+
+/// Reads the token accounts to enumerate
+#[allow(async_fn_in_trait)]
+pub trait HolderSource {
+    /// Addresses of the accounts `program_id` owns that pass every filter
+    async fn program_account_addresses(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<Pubkey>, String>;
+
+    /// The accounts at `addresses`, at most [`MAX_MULTIPLE_ACCOUNTS`] of
+    /// them, `None` for any that does not exist
+    async fn accounts(&self, addresses: &[Pubkey]) -> Result<Vec<Option<Account>>, String>;
+}
+
+impl HolderSource for RpcClient {
+    async fn program_account_addresses(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<Pubkey>, String> {
+        // An empty data slice: the node returns addresses without the data
+        let config = RpcProgramAccountsConfig {
+            filters: Some(filters),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                data_slice: Some(UiDataSliceConfig {
+                    offset: 0,
+                    length: 0,
+                }),
+                commitment: Some(self.commitment()),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+        let accounts = self
+            .get_program_accounts_with_config(program_id, config)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(accounts.into_iter().map(|(address, _)| address).collect())
+    }
+
+    async fn accounts(&self, addresses: &[Pubkey]) -> Result<Vec<Option<Account>>, String> {
+        self.get_multiple_accounts(addresses)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl HolderSource for SvmHarness {
+    async fn program_account_addresses(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<Pubkey>, String> {
+        Ok(self
+            .program_accounts(program_id, &filters)?
+            .into_iter()
+            .map(|(address, _)| address)
+            .collect())
+    }
+
+    async fn accounts(&self, addresses: &[Pubkey]) -> Result<Vec<Option<Account>>, String> {
+        Ok(addresses
+            .iter()
+            .map(|address| self.account(address))
+            .collect())
+    }
+}
+
+/// One token account of the enumerated mint
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenHolder {
+    pub address: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub state: AccountState,
+}
+
+impl TokenHolder {
+    pub fn is_frozen(&self) -> bool {
+        self.state == AccountState::Frozen
+    }
+}
+
+/// What an enumeration cost
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnumerationStats {
+    /// Accounts the address scan returned
+    pub candidates: usize,
+    /// `getMultipleAccounts` calls made
+    pub pages: usize,
+    /// Candidates gone or no longer token accounts of the mint by the time
+    /// their page was read
+    pub skipped: usize,
+    pub elapsed: Duration,
+}
+
+impl EnumerationStats {
+    /// Holders read per second, over the whole enumeration
+    pub fn holders_per_second(&self, holders: usize) -> f64 {
+        holders as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Every token account of a mint, ordered by address
+#[derive(Debug, Clone, PartialEq)]
+pub struct HolderEnumeration {
+    pub mint: Pubkey,
+    pub holders: Vec<TokenHolder>,
+    pub stats: EnumerationStats,
+}
+
+impl HolderEnumeration {
+    /// The `count` largest balances, largest first; equal balances keep
+    /// address order, so the same state always gives the same list
+    pub fn top_by_balance(&self, count: usize) -> Vec<TokenHolder> {
+        let mut ranked = self.holders.clone();
+        ranked.sort_by_key(|holder| std::cmp::Reverse(holder.amount));
+        ranked.truncate(count);
+        ranked
+    }
+
+    pub fn frozen(&self) -> impl Iterator<Item = &TokenHolder> {
+        self.holders.iter().filter(|holder| holder.is_frozen())
+    }
+
+    /// Tokens held across every account
+    pub fn total_amount(&self) -> u64 {
+        self.holders.iter().map(|holder| holder.amount).sum()
+    }
+
+    /// Render the enumeration's size and cost as markdown
+    pub fn to_markdown(&self) -> String {
+        let mut report = format!("## Holders of `{}`\n\n", self.mint);
+        report.push_str(&format!(
+            "- **Token accounts**: {}\n",
+            units::group_digits(self.holders.len() as u64)
+        ));
+        report.push_str(&format!(
+            "- **Frozen**: {}\n",
+            units::group_digits(self.frozen().count() as u64)
+        ));
+        report.push_str(&format!(
+            "- **Total held**: {}\n",
+            units::group_digits(self.total_amount())
+        ));
+        report.push_str(&format!(
+            "- **Pages**: {} ({} candidates, {} skipped)\n",
+            self.stats.pages, self.stats.candidates, self.stats.skipped
+        ));
+        report.push_str(&format!(
+            "- **Wall-clock**: {} ({:.0} holders/s)\n\n",
+            units::duration(self.stats.elapsed),
+            self.stats.holders_per_second(self.holders.len())
+        ));
+        report
+    }
+}
+
+/// The `getProgramAccounts` filter selecting the token accounts of `mint`
+pub fn mint_filter(mint: &Pubkey) -> RpcFilterType {
+    RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+        TOKEN_ACCOUNT_MINT_OFFSET,
+        mint.to_bytes().to_vec(),
+    ))
+}
+
+/// `account` as a holder of `mint`, if it is a Token-2022 account of it
+pub fn decode_holder(address: Pubkey, account: &Account, mint: &Pubkey) -> Option<TokenHolder> {
+    if account.owner != spl_token_2022::id() {
+        return None;
+    }
+    let state = StateWithExtensions::<TokenAccount>::unpack(&account.data).ok()?;
+    (state.base.mint == *mint).then_some(TokenHolder {
+        address,
+        owner: state.base.owner,
+        amount: state.base.amount,
+        state: state.base.state,
+    })
+}
+
+/// Every token account of `mint`, read `page_size` accounts per call
+///
+/// `page_size` is capped at [`MAX_MULTIPLE_ACCOUNTS`], the most an RPC node
+/// serves in one `getMultipleAccounts`.
+pub async fn enumerate_holders<S: HolderSource>(
+    source: &S,
+    mint: &Pubkey,
+    page_size: usize,
+) -> Result<HolderEnumeration, String> {
+    let started = Instant::now();
+    let mut addresses = source
+        .program_account_addresses(&spl_token_2022::id(), vec![mint_filter(mint)])
+        .await
+        .map_err(|e| format!("Scanning the token accounts of {} failed: {}", mint, e))?;
+    // RPC nodes return program accounts in no particular order
+    addresses.sort();
+    addresses.dedup();
+
+    let mut stats = EnumerationStats {
+        candidates: addresses.len(),
+        ..EnumerationStats::default()
+    };
+    let mut holders = Vec::with_capacity(addresses.len());
+    for (page, chunk) in addresses
+        .chunks(page_size.clamp(1, MAX_MULTIPLE_ACCOUNTS))
+        .enumerate()
+    {
+        let accounts = source
+            .accounts(chunk)
+            .await
+            .map_err(|e| format!("Reading page {} failed: {}", page, e))?;
+        stats.pages += 1;
+        for (address, account) in chunk.iter().zip(accounts) {
+            match account.and_then(|account| decode_holder(*address, &account, mint)) {
+                Some(holder) => holders.push(holder),
+                None => stats.skipped += 1,
+            }
+        }
+    }
+    stats.elapsed = started.elapsed();
+
+    Ok(HolderEnumeration {
+        mint: *mint,
+        holders,
+        stats,
+    })
+}
@@ -0,0 +1,170 @@
+//! The published sRFC 37 table, machine-readable
+//!
+//! `programs/token_acl_interface/spec/srfc37.toml` lists the interface's
+//! instruction discriminators, TLV entry types, PDA seeds and feature bits.
+//! [`InterfaceSpec::published`] reads the copy compiled into the suite, and
+//! `tests/interface_spec.rs` checks the interface crate and the suite's own
+//! copies against it, so a revision of the spec is one file change.
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use solana_sdk::pubkey::Pubkey;
+
+/// The spec file as checked in
+pub const SPEC_TOML: &str = include_str!("../../../programs/token_acl_interface/spec/srfc37.toml");
+
+/// The whole table
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct InterfaceSpec {
+    /// Interface version a gate speaking this table advertises
+    pub version: u8,
+    pub instructions: Vec<SpecInstruction>,
+    pub tlv_entries: Vec<SpecTlvEntry>,
+    pub seeds: Vec<SpecSeed>,
+    pub features: Vec<SpecFeature>,
+}
+
+/// A permissionless check a FAMP calls on a gate
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SpecInstruction {
+    pub name: String,
+    /// What the discriminator hashes
+    pub preimage: String,
+    /// Hex of the 8 discriminator bytes
+    pub discriminator: String,
+    /// Roles of the interface's accounts, before the gate's extra accounts
+    pub accounts: Vec<String>,
+    /// Name of the seed of the extra-account-metas PDA holding the list
+    pub extra_account_metas: String,
+}
+
+/// A TLV entry type a gate stores
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SpecTlvEntry {
+    pub name: String,
+    pub preimage: String,
+    pub discriminator: String,
+    /// Length of the entry's value
+    pub len: usize,
+}
+
+/// A PDA seed, followed by the accounts named in `followed_by`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SpecSeed {
+    pub name: String,
+    pub seed: String,
+    pub followed_by: Vec<String>,
+}
+
+/// A bit of the gate's feature bitmap
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SpecFeature {
+    pub name: String,
+    pub bit: u32,
+}
+
+/// First 8 bytes of the SHA-256 of `preimage`, as SPL discriminators are
+/// made
+pub fn hashed_discriminator(preimage: &str) -> [u8; 8] {
+    let digest = Sha256::digest(preimage.as_bytes());
+    let mut discriminator = [0; 8];
+    discriminator.copy_from_slice(&digest[..8]);
+    discriminator
+}
+
+/// Decode a hex discriminator, `None` unless it is exactly 8 bytes
+fn parse_discriminator(hex_bytes: &str) -> Option<[u8; 8]> {
+    hex::decode(hex_bytes).ok()?.try_into().ok()
+}
+
+impl InterfaceSpec {
+    pub fn parse(toml_text: &str) -> Result<Self, String> {
+        let spec: Self = toml::from_str(toml_text).map_err(|e| e.to_string())?;
+        let discriminators = spec
+            .instructions
+            .iter()
+            .map(|i| (&i.name, &i.discriminator))
+            .chain(spec.tlv_entries.iter().map(|e| (&e.name, &e.discriminator)));
+        for (name, discriminator) in discriminators {
+            if parse_discriminator(discriminator).is_none() {
+                return Err(format!(
+                    "{}: discriminator {:?} is not 8 hex bytes",
+                    name, discriminator
+                ));
+            }
+        }
+        Ok(spec)
+    }
+
+    /// The table compiled into the suite
+    pub fn published() -> Self {
+        Self::parse(SPEC_TOML).expect("the checked-in sRFC 37 spec parses")
+    }
+
+    pub fn instruction(&self, name: &str) -> Option<&SpecInstruction> {
+        self.instructions.iter().find(|i| i.name == name)
+    }
+
+    pub fn tlv_entry(&self, name: &str) -> Option<&SpecTlvEntry> {
+        self.tlv_entries.iter().find(|e| e.name == name)
+    }
+
+    pub fn seed(&self, name: &str) -> Option<&SpecSeed> {
+        self.seeds.iter().find(|s| s.name == name)
+    }
+
+    pub fn feature(&self, name: &str) -> Option<&SpecFeature> {
+        self.features.iter().find(|f| f.name == name)
+    }
+
+    /// The interface instruction `data` calls, if any: its data is exactly
+    /// the discriminator
+    pub fn classify(&self, data: &[u8]) -> Option<&SpecInstruction> {
+        self.instructions
+            .iter()
+            .find(|i| data == i.discriminator_bytes())
+    }
+
+    /// Every discriminator in the table: the instructions', then the TLV
+    /// entries'
+    pub fn discriminators(&self) -> Vec<(&str, [u8; 8])> {
+        self.instructions
+            .iter()
+            .map(|i| (i.name.as_str(), i.discriminator_bytes()))
+            .chain(
+                self.tlv_entries
+                    .iter()
+                    .map(|e| (e.name.as_str(), e.discriminator_bytes())),
+            )
+            .collect()
+    }
+}
+
+impl SpecInstruction {
+    pub fn discriminator_bytes(&self) -> [u8; 8] {
+        parse_discriminator(&self.discriminator).expect("validated when parsed")
+    }
+}
+
+impl SpecTlvEntry {
+    pub fn discriminator_bytes(&self) -> [u8; 8] {
+        parse_discriminator(&self.discriminator).expect("validated when parsed")
+    }
+}
+
+impl SpecSeed {
+    /// The PDA for `mint` under `program_id`; every seed in the table is
+    /// followed by the mint alone
+    pub fn mint_address(&self, mint: &Pubkey, program_id: &Pubkey) -> Option<(Pubkey, u8)> {
+        (self.followed_by == ["mint"]).then(|| {
+            Pubkey::find_program_address(&[self.seed.as_bytes(), mint.as_ref()], program_id)
+        })
+    }
+}
+
+impl SpecFeature {
+    /// The bit as a mask, `None` if it is outside the u32 bitmap
+    pub fn mask(&self) -> Option<u32> {
+        1u32.checked_shl(self.bit)
+    }
+}
@@ -0,0 +1,589 @@
+//! Post-conditions checked after every scenario step
+//!
+//! Scenario fixtures register their invariants with an [`InvariantEngine`],
+//! which snapshots the accounts each step references (see
+//! [`crate::account_snapshots`]) plus any the invariants ask to watch, and
+//! checks every registered invariant against the resulting [`Step`]. A
+//! narrative scenario test thereby also asserts, after each transaction:
+//! - [`LamportInvariants`]: gate-owned accounts stay rent-exempt, and
+//!   lamports only leave them by closing to a declared recipient,
+//! - [`ConfigAuthorityUnchanged`]: a config's authority only changes in a
+//!   step that ran the program's handover instruction,
+//! - [`FreezeStateConsistency`]: a token account only changes freeze state
+//!   through the gate when the owner's record says it may.
+//!
+//! Failed transactions are checked too; the runtime rolls them back, so only
+//! the fee should move.
+
+use std::{collections::BTreeSet, fmt};
+
+use borsh::BorshDeserialize;
+use solana_program_test::{BanksClient, BanksClientError};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    rent::Rent,
+    transaction::Transaction,
+};
+use spl_token_2022::{
+    extension::StateWithExtensions,
+    state::{Account as TokenAccount, AccountState as TokenAccountState},
+};
+use token_acl_interface::CanonicalPda;
+
+use crate::{
+    account_snapshots::{AccountChange, AccountDiff, AccountSnapshot, AccountState},
+    labels,
+};
+
+/// A post-condition a step broke
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvariantViolation {
+    pub invariant: &'static str,
+    pub address: Pubkey,
+    pub detail: String,
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] {}: {}",
+            self.invariant,
+            labels::name(&self.address),
+            labels::annotate(&self.detail)
+        )
+    }
+}
+
+/// Rent-exemption and lamport-flow checks for the accounts of gate programs
+#[derive(Debug, Clone)]
+pub struct LamportInvariants {
+    programs: BTreeSet<Pubkey>,
+    rent: Rent,
+    refund_recipients: BTreeSet<Pubkey>,
+}
+
+impl LamportInvariants {
+    /// Check the accounts owned by `programs` against `rent`
+    pub fn new(programs: &[Pubkey], rent: Rent) -> Self {
+        Self {
+            programs: programs.iter().copied().collect(),
+            rent,
+            refund_recipients: BTreeSet::new(),
+        }
+    }
+
+    /// Accept `recipient` as the destination of a closed account's lamports
+    pub fn allow_refunds_to(&mut self, recipient: Pubkey) {
+        self.refund_recipients.insert(recipient);
+    }
+
+    fn gate_owned(&self, state: Option<&AccountState>) -> bool {
+        state.is_some_and(|state| self.programs.contains(&state.owner))
+    }
+
+    /// Every violation in `diff`, in address order per invariant
+    pub fn check(&self, diff: &AccountDiff) -> Vec<InvariantViolation> {
+        let mut violations = Vec::new();
+
+        for change in &diff.changes {
+            if let Some(after) = &change.after {
+                let minimum = self.rent.minimum_balance(after.data.len());
+                if self.programs.contains(&after.owner) && after.lamports < minimum {
+                    violations.push(InvariantViolation {
+                        invariant: "rent-exemption",
+                        address: change.address,
+                        detail: format!(
+                            "{} lamports for {} bytes, rent exemption needs {}",
+                            after.lamports,
+                            after.data.len(),
+                            minimum
+                        ),
+                    });
+                }
+            }
+
+            if self.gate_owned(change.before.as_ref())
+                && change.lamport_delta() < 0
+                && !change.is_closed()
+            {
+                violations.push(InvariantViolation {
+                    invariant: "lamport-drain",
+                    address: change.address,
+                    detail: format!(
+                        "lost {} lamports without being closed",
+                        -change.lamport_delta()
+                    ),
+                });
+            }
+        }
+
+        let released: i128 = diff
+            .changes
+            .iter()
+            .filter(|change| self.gate_owned(change.before.as_ref()) && change.lamport_delta() < 0)
+            .map(|change| -change.lamport_delta())
+            .sum();
+        if released > 0 {
+            violations.extend(
+                diff.changes
+                    .iter()
+                    .filter(|change| self.is_unexpected_recipient(change))
+                    .map(|change| InvariantViolation {
+                        invariant: "lamport-leak",
+                        address: change.address,
+                        detail: format!(
+                            "received {} lamports released by gate accounts",
+                            change.lamport_delta()
+                        ),
+                    }),
+            );
+        }
+
+        let total = |lamports: fn(&AccountChange) -> u64| -> u128 {
+            diff.changes
+                .iter()
+                .map(|change| u128::from(lamports(change)))
+                .sum()
+        };
+        let (before, after) = (
+            total(AccountChange::lamports_before),
+            total(AccountChange::lamports_after),
+        );
+        if after > before {
+            violations.push(InvariantViolation {
+                invariant: "lamport-creation",
+                address: Pubkey::default(),
+                detail: format!("step ended with {} more lamports", after - before),
+            });
+        }
+
+        violations
+    }
+
+    /// A non-gate account that gained lamports without being declared
+    fn is_unexpected_recipient(&self, change: &AccountChange) -> bool {
+        change.lamport_delta() > 0
+            && !self.gate_owned(change.after.as_ref())
+            && !self.refund_recipients.contains(&change.address)
+    }
+
+    /// Panic with every violation in `diff`, naming the `step`
+    pub fn assert_holds(&self, step: &str, diff: &AccountDiff) {
+        panic_on_violations(step, diff, &self.check(diff));
+    }
+}
+
+fn panic_on_violations(step: &str, diff: &AccountDiff, violations: &[InvariantViolation]) {
+    if !violations.is_empty() {
+        let list: Vec<String> = violations.iter().map(ToString::to_string).collect();
+        panic!(
+            "Invariants violated by {}:\n  {}\nAccounts changed:\n{}",
+            step,
+            list.join("\n  "),
+            diff
+        );
+    }
+}
+
+/// One scenario step as the invariants see it
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub name: String,
+    pub instructions: Vec<Instruction>,
+    pub before: AccountSnapshot,
+    pub after: AccountSnapshot,
+    pub diff: AccountDiff,
+}
+
+impl Step {
+    pub fn new(
+        name: impl Into<String>,
+        instructions: Vec<Instruction>,
+        before: AccountSnapshot,
+        after: AccountSnapshot,
+    ) -> Self {
+        let diff = before.diff(&after);
+        Self {
+            name: name.into(),
+            instructions,
+            before,
+            after,
+            diff,
+        }
+    }
+
+    /// The step that ran `transactions` between the two snapshots
+    pub fn from_transactions(
+        name: impl Into<String>,
+        transactions: &[&Transaction],
+        before: AccountSnapshot,
+        after: AccountSnapshot,
+    ) -> Self {
+        let instructions = transactions
+            .iter()
+            .flat_map(|transaction| instructions_of(transaction))
+            .collect();
+        Self::new(name, instructions, before, after)
+    }
+
+    /// Whether any instruction in the step targets `program`
+    pub fn invokes(&self, program: &Pubkey) -> bool {
+        self.instructions
+            .iter()
+            .any(|instruction| instruction.program_id == *program)
+    }
+
+    /// Whether the step ran `program`'s instruction with this discriminator
+    pub fn invokes_instruction(&self, program: &Pubkey, discriminator: u8) -> bool {
+        self.instructions.iter().any(|instruction| {
+            instruction.program_id == *program && instruction.data.first() == Some(&discriminator)
+        })
+    }
+}
+
+/// The top-level instructions of a transaction, decompiled from its message
+pub fn instructions_of(transaction: &Transaction) -> Vec<Instruction> {
+    let message = &transaction.message;
+    message
+        .instructions
+        .iter()
+        .map(|compiled| Instruction {
+            program_id: message.account_keys[usize::from(compiled.program_id_index)],
+            accounts: compiled
+                .accounts
+                .iter()
+                .map(|&index| {
+                    let index = usize::from(index);
+                    AccountMeta {
+                        pubkey: message.account_keys[index],
+                        is_signer: message.is_signer(index),
+                        is_writable: message.is_writable(index),
+                    }
+                })
+                .collect(),
+            data: compiled.data.clone(),
+        })
+        .collect()
+}
+
+/// A post-condition checked after every step
+pub trait Invariant {
+    fn name(&self) -> &'static str;
+
+    /// Accounts to snapshot besides the ones the step references, given the
+    /// referenced accounts as they are before the step
+    fn watched_accounts(&self, _referenced: &AccountSnapshot) -> Vec<Pubkey> {
+        Vec::new()
+    }
+
+    /// Accept `recipient` as the destination of a closed account's lamports;
+    /// invariants that do not track lamport flow ignore it
+    fn allow_refunds_to(&mut self, _recipient: Pubkey) {}
+
+    fn check(&self, step: &Step) -> Vec<InvariantViolation>;
+}
+
+impl Invariant for LamportInvariants {
+    fn name(&self) -> &'static str {
+        "lamports"
+    }
+
+    fn allow_refunds_to(&mut self, recipient: Pubkey) {
+        LamportInvariants::allow_refunds_to(self, recipient);
+    }
+
+    fn check(&self, step: &Step) -> Vec<InvariantViolation> {
+        LamportInvariants::check(self, &step.diff)
+    }
+}
+
+/// A config account's authority only changes when the step ran the
+/// program's handover instruction: the one that actually installs the new
+/// authority, which for the production allow list and the example block
+/// list is their `ACCEPT_AUTHORITY`
+///
+/// The authority is the first field of the config, so it is read from the
+/// first 32 bytes of its data.
+#[derive(Debug, Clone)]
+pub struct ConfigAuthorityUnchanged {
+    program: Pubkey,
+    config: Pubkey,
+    handover: u8,
+}
+
+impl ConfigAuthorityUnchanged {
+    pub fn new(program: Pubkey, config: Pubkey, handover: u8) -> Self {
+        Self {
+            program,
+            config,
+            handover,
+        }
+    }
+
+    /// The production allow list's config for `mint`
+    pub fn allow_list(mint: &Pubkey, program: &Pubkey) -> Self {
+        Self::new(
+            *program,
+            production_allow_list::config_address(mint, program).address(),
+            production_allow_list::ACCEPT_AUTHORITY,
+        )
+    }
+
+    /// The example block list's config for `mint`
+    pub fn block_list(mint: &Pubkey, program: &Pubkey) -> Self {
+        Self::new(
+            *program,
+            example_block_list::config_address(mint, program).address(),
+            example_block_list::ACCEPT_AUTHORITY,
+        )
+    }
+}
+
+fn authority_of(state: Option<&AccountState>) -> Option<&[u8]> {
+    state.and_then(|state| state.data.get(..32))
+}
+
+impl Invariant for ConfigAuthorityUnchanged {
+    fn name(&self) -> &'static str {
+        "config-authority"
+    }
+
+    fn watched_accounts(&self, _referenced: &AccountSnapshot) -> Vec<Pubkey> {
+        vec![self.config]
+    }
+
+    fn check(&self, step: &Step) -> Vec<InvariantViolation> {
+        let Some(change) = step.diff.get(&self.config) else {
+            return Vec::new();
+        };
+        let (before, after) = (
+            authority_of(change.before.as_ref()),
+            authority_of(change.after.as_ref()),
+        );
+        let changed = before.is_some() && before != after;
+        if !changed || step.invokes_instruction(&self.program, self.handover) {
+            return Vec::new();
+        }
+        vec![InvariantViolation {
+            invariant: self.name(),
+            address: self.config,
+            detail: format!(
+                "authority changed from {} to {} without a handover instruction",
+                describe_authority(before),
+                describe_authority(after)
+            ),
+        }]
+    }
+}
+
+fn describe_authority(authority: Option<&[u8]>) -> String {
+    authority
+        .and_then(|bytes| Pubkey::try_from(bytes).ok())
+        .map_or_else(|| "none".to_string(), |authority| authority.to_string())
+}
+
+/// The owner's record at its canonical address for a gate
+type RecordAddress = fn(&Pubkey, &Pubkey, &Pubkey) -> CanonicalPda;
+
+/// Whether the owner's record, if any, lets the gate approve a transition
+type RecordPermits = fn(Option<&AccountState>) -> bool;
+
+/// Freeze-state changes that involve the gate agree with the owner's record
+///
+/// A step that invokes the gate and thaws (or freezes) one of the mint's
+/// token accounts must leave the owner's record in a state the gate would
+/// approve that transition for. Freeze-state changes in steps that do not
+/// invoke the gate are the issuer's own permissioned freezes and thaws, and
+/// are not checked.
+#[derive(Debug, Clone)]
+pub struct FreezeStateConsistency {
+    mint: Pubkey,
+    gate: Pubkey,
+    record_address: RecordAddress,
+    thaw_permitted: RecordPermits,
+    freeze_permitted: Option<RecordPermits>,
+}
+
+impl FreezeStateConsistency {
+    /// Thaws need an allowed, unrevoked production allow list record
+    pub fn allow_list(mint: Pubkey, gate: Pubkey) -> Self {
+        Self {
+            mint,
+            gate,
+            record_address: production_allow_list::allow_list_address,
+            thaw_permitted: |record| {
+                record
+                    .and_then(|record| {
+                        production_allow_list::AllowListRecord::deserialize(&mut &record.data[..])
+                            .ok()
+                    })
+                    .is_some_and(|record| record.allowed && !record.revoked)
+            },
+            freeze_permitted: None,
+        }
+    }
+
+    /// Thaws need no blocking record, freezes need one
+    ///
+    /// A step doesn't record the clock, so a block with an expiry may have
+    /// lapsed: thaws past one are let through, freezes still need the flag.
+    pub fn block_list(mint: Pubkey, gate: Pubkey) -> Self {
+        Self {
+            mint,
+            gate,
+            record_address: example_block_list::block_list_address,
+            thaw_permitted: |record| {
+                block_list_record(record)
+                    .is_none_or(|record| !record.blocked || record.expiry_timestamp.is_some())
+            },
+            freeze_permitted: Some(|record| {
+                block_list_record(record).is_some_and(|record| record.blocked)
+            }),
+        }
+    }
+
+    fn token_account(&self, state: Option<&AccountState>) -> Option<(Pubkey, bool)> {
+        let state = state?;
+        spl_token_2022::check_spl_token_program_account(&state.owner).ok()?;
+        let account = StateWithExtensions::<TokenAccount>::unpack(&state.data).ok()?;
+        (account.base.mint == self.mint).then_some((
+            account.base.owner,
+            account.base.state == TokenAccountState::Frozen,
+        ))
+    }
+
+    fn record_for(&self, owner: &Pubkey) -> Pubkey {
+        (self.record_address)(&self.mint, owner, &self.gate).address()
+    }
+}
+
+fn block_list_record(record: Option<&AccountState>) -> Option<example_block_list::BlockListRecord> {
+    record.and_then(|record| example_block_list::BlockListRecord::unpack(&record.data).ok())
+}
+
+impl Invariant for FreezeStateConsistency {
+    fn name(&self) -> &'static str {
+        "freeze-state"
+    }
+
+    fn watched_accounts(&self, referenced: &AccountSnapshot) -> Vec<Pubkey> {
+        referenced
+            .addresses()
+            .filter_map(|address| self.token_account(referenced.get(address)))
+            .map(|(owner, _)| self.record_for(&owner))
+            .collect()
+    }
+
+    fn check(&self, step: &Step) -> Vec<InvariantViolation> {
+        if !step.invokes(&self.gate) {
+            return Vec::new();
+        }
+
+        let mut violations = Vec::new();
+        for change in &step.diff.changes {
+            let (Some((owner, was_frozen)), Some((_, frozen))) = (
+                self.token_account(change.before.as_ref()),
+                self.token_account(change.after.as_ref()),
+            ) else {
+                continue;
+            };
+            let (transition, permitted) = match (was_frozen, frozen) {
+                (true, false) => ("thawed", Some(self.thaw_permitted)),
+                (false, true) => ("frozen", self.freeze_permitted),
+                _ => continue,
+            };
+            let Some(permitted) = permitted else {
+                continue;
+            };
+            let record = self.record_for(&owner);
+            if !permitted(step.after.get(&record)) {
+                violations.push(InvariantViolation {
+                    invariant: self.name(),
+                    address: change.address,
+                    detail: format!(
+                        "{} through the gate, but record {} for owner {} does not permit it",
+                        transition, record, owner
+                    ),
+                });
+            }
+        }
+        violations
+    }
+}
+
+/// The invariants a scenario checks after every step
+#[derive(Default)]
+pub struct InvariantEngine {
+    invariants: Vec<Box<dyn Invariant>>,
+}
+
+impl InvariantEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, invariant: impl Invariant + 'static) -> &mut Self {
+        self.invariants.push(Box::new(invariant));
+        self
+    }
+
+    /// Names of the registered invariants, in registration order
+    pub fn names(&self) -> Vec<&'static str> {
+        self.invariants
+            .iter()
+            .map(|invariant| invariant.name())
+            .collect()
+    }
+
+    /// Accept `recipient` as the destination of a closed account's lamports
+    pub fn allow_refunds_to(&mut self, recipient: Pubkey) {
+        for invariant in &mut self.invariants {
+            invariant.allow_refunds_to(recipient);
+        }
+    }
+
+    /// Snapshot `addresses` and every account the invariants watch for them
+    pub async fn snapshot(
+        &self,
+        banks_client: &mut BanksClient,
+        addresses: &[Pubkey],
+    ) -> Result<AccountSnapshot, BanksClientError> {
+        let mut snapshot = AccountSnapshot::capture(banks_client, addresses).await?;
+        let watched: Vec<Pubkey> = self
+            .invariants
+            .iter()
+            .flat_map(|invariant| invariant.watched_accounts(&snapshot))
+            .filter(|address| !addresses.contains(address))
+            .collect();
+        let extra = AccountSnapshot::capture(banks_client, &watched).await?;
+        for address in &watched {
+            snapshot.insert(*address, extra.get(address).cloned());
+        }
+        Ok(snapshot)
+    }
+
+    /// Snapshot the accounts in `before` again after the step ran
+    pub async fn snapshot_after(
+        &self,
+        banks_client: &mut BanksClient,
+        before: &AccountSnapshot,
+    ) -> Result<AccountSnapshot, BanksClientError> {
+        let addresses: Vec<Pubkey> = before.addresses().copied().collect();
+        AccountSnapshot::capture(banks_client, &addresses).await
+    }
+
+    /// Every violation of every registered invariant, in registration order
+    pub fn check(&self, step: &Step) -> Vec<InvariantViolation> {
+        self.invariants
+            .iter()
+            .flat_map(|invariant| invariant.check(step))
+            .collect()
+    }
+
+    /// Panic with every violation in `step`
+    pub fn assert_holds(&self, step: &Step) {
+        panic_on_violations(&step.name, &step.diff, &self.check(step));
+    }
+}
@@ -0,0 +1,152 @@
+//! Human names for the accounts a test uses
+//!
+//! A failure that involves an issuer, three investors and their records is
+//! unreadable as a wall of base58. Tests name their accounts as they create
+//! them, e.g. `issuer`, `investor_3` or `allow_pda(investor_3)`, and log
+//! entries, account diffs, invariant violations and rendered reports show
+//! those names instead of the addresses.
+//!
+//! Like the logger in [`crate::logging`], the registry is per thread, so each
+//! test sees only its own names. Names depend only on what a test labels, in
+//! the order it labels them, never on the addresses themselves, so a run
+//! against fresh keys reads the same as the last one.
+
+use solana_sdk::pubkey::Pubkey;
+use std::{cell::RefCell, collections::BTreeMap};
+
+/// Names for addresses, each unique among them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AddressLabels {
+    names: BTreeMap<Pubkey, String>,
+}
+
+impl AddressLabels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Name `address`; an address keeps the first name it was given, and a
+    /// name already taken by another address gets a `#2`, `#3`, ... suffix
+    pub fn label(&mut self, address: Pubkey, name: &str) -> Pubkey {
+        if self.names.contains_key(&address) {
+            return address;
+        }
+        let mut unique = name.to_string();
+        let mut suffix = 1;
+        while self.names.values().any(|taken| *taken == unique) {
+            suffix += 1;
+            unique = format!("{}#{}", name, suffix);
+        }
+        self.names.insert(address, unique);
+        address
+    }
+
+    /// Name an address derived from `base` after what it is, e.g.
+    /// `allow_pda(investor_3)`
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use solana_sdk::pubkey::Pubkey;
+    /// # use token_acl_integration_tests::labels::AddressLabels;
+    /// let (investor, record) = (Pubkey::new_unique(), Pubkey::new_unique());
+    /// let mut labels = AddressLabels::new();
+    /// labels.label(investor, "investor_3");
+    /// labels.label_derived(record, "allow_pda", &investor);
+    /// assert_eq!(labels.name(&record), "allow_pda(investor_3)");
+    /// ```
+    pub fn label_derived(&mut self, address: Pubkey, kind: &str, base: &Pubkey) -> Pubkey {
+        let name = format!("{}({})", kind, self.name(base));
+        self.label(address, &name)
+    }
+
+    pub fn get(&self, address: &Pubkey) -> Option<&str> {
+        self.names.get(address).map(String::as_str)
+    }
+
+    /// The name of `address`, or the address itself if it has none
+    pub fn name(&self, address: &Pubkey) -> String {
+        self.get(address)
+            .map(str::to_string)
+            .unwrap_or_else(|| address.to_string())
+    }
+
+    /// `text` with every named address in it replaced by its name
+    pub fn annotate(&self, text: &str) -> String {
+        self.names
+            .iter()
+            .fold(text.to_string(), |text, (address, name)| {
+                text.replace(&address.to_string(), name)
+            })
+    }
+
+    /// The named addresses `text` mentions, in name order
+    pub fn mentioned(&self, text: &str) -> Vec<(&str, &Pubkey)> {
+        let mut mentioned: Vec<(&str, &Pubkey)> = self
+            .names
+            .iter()
+            .filter(|(address, _)| text.contains(&address.to_string()))
+            .map(|(address, name)| (name.as_str(), address))
+            .collect();
+        mentioned.sort();
+        mentioned
+    }
+
+    /// A markdown table of the named addresses `text` mentions, so a report
+    /// that shows names still leads back to the accounts; empty if it
+    /// mentions none
+    pub fn render_legend(&self, text: &str) -> String {
+        let mentioned = self.mentioned(text);
+        if mentioned.is_empty() {
+            return String::new();
+        }
+        let mut legend = String::from("## Accounts\n\n| Name | Address |\n|------|---------|\n");
+        for (name, address) in mentioned {
+            legend.push_str(&format!("| {} | `{}` |\n", name, address));
+        }
+        legend.push('\n');
+        legend
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+thread_local! {
+    static TEST_LABELS: RefCell<AddressLabels> = RefCell::new(AddressLabels::new());
+}
+
+/// Run `f` with the names of the test running on this thread
+pub fn with_labels<R>(f: impl FnOnce(&mut AddressLabels) -> R) -> R {
+    TEST_LABELS.with(|labels| f(&mut labels.borrow_mut()))
+}
+
+/// Name `address` for the test on this thread; see [`AddressLabels::label`]
+pub fn label(address: Pubkey, name: &str) -> Pubkey {
+    with_labels(|labels| labels.label(address, name))
+}
+
+/// Name an address derived from `base`; see [`AddressLabels::label_derived`]
+pub fn label_derived(address: Pubkey, kind: &str, base: &Pubkey) -> Pubkey {
+    with_labels(|labels| labels.label_derived(address, kind, base))
+}
+
+/// The name of `address` in the test on this thread, or the address itself
+pub fn name(address: &Pubkey) -> String {
+    with_labels(|labels| labels.name(address))
+}
+
+/// `text` with the named addresses of the test on this thread replaced
+pub fn annotate(text: &str) -> String {
+    with_labels(|labels| labels.annotate(text))
+}
+
+/// Forget every name of the test on this thread
+pub fn clear() {
+    with_labels(|labels| *labels = AddressLabels::new());
+}
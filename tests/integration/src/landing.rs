@@ -0,0 +1,337 @@
+//! Transaction landing strategy comparison
+//!
+//! An onboarding campaign sizes its send rate and budget on how many
+//! permissionless thaws land under load, and how fast. That depends on how
+//! the transactions are sent as much as on the gate: through a plain RPC
+//! node, with a priority fee, through a staked connection, or as a Jito
+//! transaction with a tip. This module applies each strategy to the same
+//! thaw transactions, sends a burst of them at once, and watches which land
+//! before their blockhash expires.
+//!
+//! The staked and Jito strategies are only compared when their endpoints
+//! are configured:
+//!
+//! - `TOKEN_ACL_LANDING_PRIORITY_FEES`: micro-lamports per compute unit,
+//!   comma separated (default `1000,10000,100000`)
+//! - `TOKEN_ACL_STAKED_RPC_URL`: an RPC endpoint with staked connections
+//! - `TOKEN_ACL_JITO_URL`, `TOKEN_ACL_JITO_TIP_ACCOUNT` and optionally
+//!   `TOKEN_ACL_JITO_TIP_LAMPORTS` (default 10,000): a block engine's
+//!   transaction endpoint and the tip it requires
+//!
+//! The experiment itself runs against a cluster where a production allow
+//! list gate is deployed, in the `experiments` suite category:
+//!
+//! - `TOKEN_ACL_LANDING_RPC_URL`: the experiment's RPC endpoint (default
+//!   devnet)
+//! - `TOKEN_ACL_LANDING_KEYPAIR`: a funded keypair file; it acts as issuer
+//!   of a fresh mint and funds the holders
+//! - `TOKEN_ACL_LANDING_GATE`: the gate's program id
+//! - `TOKEN_ACL_LANDING_BURST`: thaws sent at once per strategy (default 25)
+//!
+//! Statuses are always read from the experiment's own RPC endpoint, so every
+//! strategy is timed against the same view of the cluster.
+
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction, instruction::Instruction, pubkey::Pubkey,
+    signature::Signature, system_instruction, transaction::Transaction,
+};
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use crate::{benchmarks::LatencyDistribution, submission::SubmissionCluster, units};
+
+pub const RPC_URL_ENV: &str = "TOKEN_ACL_LANDING_RPC_URL";
+pub const KEYPAIR_ENV: &str = "TOKEN_ACL_LANDING_KEYPAIR";
+pub const GATE_ENV: &str = "TOKEN_ACL_LANDING_GATE";
+pub const BURST_ENV: &str = "TOKEN_ACL_LANDING_BURST";
+pub const PRIORITY_FEES_ENV: &str = "TOKEN_ACL_LANDING_PRIORITY_FEES";
+pub const STAKED_RPC_ENV: &str = "TOKEN_ACL_STAKED_RPC_URL";
+pub const JITO_URL_ENV: &str = "TOKEN_ACL_JITO_URL";
+pub const JITO_TIP_ACCOUNT_ENV: &str = "TOKEN_ACL_JITO_TIP_ACCOUNT";
+pub const JITO_TIP_ENV: &str = "TOKEN_ACL_JITO_TIP_LAMPORTS";
+
+/// Priority fee levels compared by default, in micro-lamports per CU
+pub const DEFAULT_PRIORITY_FEES: [u64; 3] = [1_000, 10_000, 100_000];
+
+/// Thaws per strategy when `TOKEN_ACL_LANDING_BURST` is unset
+pub const DEFAULT_BURST: usize = 25;
+
+/// Jito tip when `TOKEN_ACL_JITO_TIP_LAMPORTS` is unset
+pub const DEFAULT_JITO_TIP: u64 = 10_000;
+
+/// How a transaction is sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LandingStrategy {
+    /// The experiment's RPC endpoint, no fee beyond the base fee
+    PlainRpc,
+    /// The experiment's RPC endpoint, with a compute unit price
+    PriorityFee { micro_lamports: u64 },
+    /// An endpoint forwarding over staked connections
+    Staked { url: String },
+    /// A block engine, paid by a tip transfer in the transaction
+    Jito {
+        url: String,
+        tip_account: Pubkey,
+        tip_lamports: u64,
+    },
+}
+
+impl LandingStrategy {
+    /// Name used in reports
+    pub fn name(&self) -> String {
+        match self {
+            LandingStrategy::PlainRpc => "Plain RPC".to_string(),
+            LandingStrategy::PriorityFee { micro_lamports } => {
+                format!("Priority fee {} µlamports/CU", micro_lamports)
+            }
+            LandingStrategy::Staked { .. } => "Staked RPC".to_string(),
+            LandingStrategy::Jito { tip_lamports, .. } => {
+                format!("Jito, {} lamport tip", tip_lamports)
+            }
+        }
+    }
+
+    /// The endpoint transactions are sent to, `None` for the experiment's
+    pub fn endpoint(&self) -> Option<&str> {
+        match self {
+            LandingStrategy::PlainRpc | LandingStrategy::PriorityFee { .. } => None,
+            LandingStrategy::Staked { url } | LandingStrategy::Jito { url, .. } => Some(url),
+        }
+    }
+
+    /// `instructions` as this strategy sends them: a compute unit price
+    /// first, or a tip from `payer` last
+    pub fn apply(&self, instructions: &[Instruction], payer: &Pubkey) -> Vec<Instruction> {
+        match self {
+            LandingStrategy::PlainRpc | LandingStrategy::Staked { .. } => instructions.to_vec(),
+            LandingStrategy::PriorityFee { micro_lamports } => std::iter::once(
+                ComputeBudgetInstruction::set_compute_unit_price(*micro_lamports),
+            )
+            .chain(instructions.iter().cloned())
+            .collect(),
+            LandingStrategy::Jito {
+                tip_account,
+                tip_lamports,
+                ..
+            } => instructions
+                .iter()
+                .cloned()
+                .chain(std::iter::once(system_instruction::transfer(
+                    payer,
+                    tip_account,
+                    *tip_lamports,
+                )))
+                .collect(),
+        }
+    }
+
+    /// Lamports paid on top of the base fee by a transaction requesting
+    /// `compute_unit_limit` units
+    pub fn premium(&self, compute_unit_limit: u32) -> u64 {
+        match self {
+            LandingStrategy::PlainRpc | LandingStrategy::Staked { .. } => 0,
+            LandingStrategy::PriorityFee { micro_lamports } => {
+                (u128::from(*micro_lamports) * u128::from(compute_unit_limit)).div_ceil(1_000_000)
+                    as u64
+            }
+            LandingStrategy::Jito { tip_lamports, .. } => *tip_lamports,
+        }
+    }
+}
+
+/// The strategies an experiment compares
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LandingConfig {
+    pub priority_fees: Vec<u64>,
+    pub staked_url: Option<String>,
+    /// Block engine URL, tip account and tip
+    pub jito: Option<(String, Pubkey, u64)>,
+}
+
+impl Default for LandingConfig {
+    fn default() -> Self {
+        Self {
+            priority_fees: DEFAULT_PRIORITY_FEES.to_vec(),
+            staked_url: None,
+            jito: None,
+        }
+    }
+}
+
+impl LandingConfig {
+    /// Read the configuration from the environment; unset variables keep
+    /// their defaults
+    pub fn from_env() -> Result<Self, String> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let mut config = Self::default();
+        if let Some(levels) = var(PRIORITY_FEES_ENV) {
+            config.priority_fees = levels
+                .split(',')
+                .map(|level| parse(PRIORITY_FEES_ENV, level))
+                .collect::<Result<_, _>>()?;
+        }
+        config.staked_url = var(STAKED_RPC_ENV);
+        if let Some(url) = var(JITO_URL_ENV) {
+            let tip_account = var(JITO_TIP_ACCOUNT_ENV)
+                .ok_or_else(|| format!("{} needs {}", JITO_URL_ENV, JITO_TIP_ACCOUNT_ENV))?;
+            let tip = match var(JITO_TIP_ENV) {
+                Some(tip) => parse(JITO_TIP_ENV, &tip)?,
+                None => DEFAULT_JITO_TIP,
+            };
+            config.jito = Some((url, parse(JITO_TIP_ACCOUNT_ENV, &tip_account)?, tip));
+        }
+        Ok(config)
+    }
+
+    /// Plain RPC, each priority fee level, then the configured endpoints
+    pub fn strategies(&self) -> Vec<LandingStrategy> {
+        let mut strategies = vec![LandingStrategy::PlainRpc];
+        strategies.extend(
+            self.priority_fees
+                .iter()
+                .map(|&micro_lamports| LandingStrategy::PriorityFee { micro_lamports }),
+        );
+        if let Some(url) = &self.staked_url {
+            strategies.push(LandingStrategy::Staked { url: url.clone() });
+        }
+        if let Some((url, tip_account, tip_lamports)) = &self.jito {
+            strategies.push(LandingStrategy::Jito {
+                url: url.clone(),
+                tip_account: *tip_account,
+                tip_lamports: *tip_lamports,
+            });
+        }
+        strategies
+    }
+}
+
+fn parse<T: FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid {} value: {}", name, value))
+}
+
+/// How one strategy's burst fared
+#[derive(Debug, Clone)]
+pub struct StrategyResult {
+    pub strategy: LandingStrategy,
+    pub sent: usize,
+    /// Landed and succeeded
+    pub landed: usize,
+    /// Landed and failed, e.g. denied by the gate
+    pub failed: usize,
+    /// Send to first status showing it landed, for the landed transactions
+    pub latency: LatencyDistribution,
+    /// Lamports each transaction paid beyond the base fee
+    pub premium: u64,
+}
+
+impl StrategyResult {
+    /// Share of the transactions sent that landed and succeeded
+    pub fn landed_rate(&self) -> f64 {
+        if self.sent == 0 {
+            return 0.0;
+        }
+        self.landed as f64 / self.sent as f64
+    }
+}
+
+/// Send `transactions` through `sender` all at once, then poll `observer`
+/// until each has landed or `deadline` has passed
+///
+/// `premium` is what each transaction pays beyond the base fee, for the
+/// report.
+pub async fn run_strategy<S: SubmissionCluster, O: SubmissionCluster>(
+    strategy: LandingStrategy,
+    sender: &S,
+    observer: &O,
+    transactions: Vec<Transaction>,
+    deadline: Duration,
+    poll_interval: Duration,
+    premium: u64,
+) -> StrategyResult {
+    let started = Instant::now();
+    let mut unresolved: Vec<(Signature, Instant)> = Vec::with_capacity(transactions.len());
+    for transaction in &transactions {
+        // A failed send is counted as not landing, not retried: rebroadcasting
+        // is part of what a strategy does or does not do
+        let sent_at = Instant::now();
+        let _ = sender.send(transaction).await;
+        unresolved.push((transaction.signatures[0], sent_at));
+    }
+
+    let mut landed = Vec::new();
+    let mut failed = 0;
+    while !unresolved.is_empty() && started.elapsed() < deadline {
+        tokio::time::sleep(poll_interval).await;
+        let mut pending = Vec::with_capacity(unresolved.len());
+        for (signature, sent_at) in unresolved {
+            match observer.signature_status(&signature).await {
+                Ok(Some(Ok(()))) => landed.push(sent_at.elapsed()),
+                Ok(Some(Err(_))) => failed += 1,
+                Ok(None) | Err(_) => pending.push((signature, sent_at)),
+            }
+        }
+        unresolved = pending;
+    }
+
+    StrategyResult {
+        strategy,
+        sent: transactions.len(),
+        landed: landed.len(),
+        failed,
+        latency: LatencyDistribution::new(landed),
+        premium,
+    }
+}
+
+/// Render the comparison of `results`, each a burst of `burst` thaws sent
+/// to `cluster`, as markdown
+pub fn render_landing_report(results: &[StrategyResult], burst: usize, cluster: &str) -> String {
+    let mut report = String::from("## Transaction Landing Strategies\n\n");
+    report.push_str(&format!(
+        "{} permissionless thaws sent at once per strategy to {}. Latency runs from send to the first status showing the transaction landed.\n\n",
+        burst, cluster
+    ));
+    report.push_str(
+        "| Strategy | Sent | Landed | Failed | Landed rate | p50 | p90 | p99 | Premium per tx |\n",
+    );
+    report.push_str(
+        "|----------|------|--------|--------|-------------|-----|-----|-----|----------------|\n",
+    );
+    for result in results {
+        let latency = &result.latency;
+        let percentile = |p: f64| {
+            if latency.is_empty() {
+                "-".to_string()
+            } else {
+                units::duration(latency.percentile(p))
+            }
+        };
+        report.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} | {} | {} | {} |\n",
+            result.strategy.name(),
+            result.sent,
+            result.landed,
+            result.failed,
+            units::percent(result.landed as u64, result.sent as u64),
+            percentile(50.0),
+            percentile(90.0),
+            percentile(99.0),
+            units::lamports(result.premium)
+        ));
+    }
+    if !results
+        .iter()
+        .any(|result| result.strategy.endpoint().is_some())
+    {
+        report.push_str(&format!(
+            "\nStaked and Jito endpoints were not compared; set `{}` or `{}` to include them.\n",
+            STAKED_RPC_ENV, JITO_URL_ENV
+        ));
+    }
+    report
+}
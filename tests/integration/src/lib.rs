@@ -3,11 +3,56 @@
 //! This module provides shared utilities and common functionality for testing
 //! the sRFC 37 Token ACL implementation.
 
+pub mod account_ordering;
+pub mod account_snapshots;
+pub mod analytics;
+pub mod assertion_lint;
+pub mod badges;
 pub mod benchmarks;
+pub mod bpf_coverage;
+pub mod budget_guidance;
+pub mod bundles;
 pub mod common;
+pub mod compatibility;
+pub mod conformance;
+pub mod contention;
+pub mod context;
+pub mod costs;
 pub mod coverage;
+pub mod decoder;
+pub mod errors;
+pub mod evidence;
+pub mod explorer;
+pub mod fixture_snapshots;
 pub mod fixtures;
+pub mod gate_fuzz;
+pub mod history;
+pub mod holders;
+pub mod interface_spec;
+pub mod invariants;
+pub mod labels;
+pub mod landing;
+pub mod lifecycle;
 pub mod logging;
+pub mod lookups;
+pub mod metrics;
+pub mod mirror;
+pub mod notifier;
+pub mod onboarding;
+#[cfg(feature = "query-service")]
+pub mod query_service;
+pub mod rpc;
+pub mod scenarios;
+pub mod self_test;
+pub mod sequences;
+pub mod simulation;
+pub mod snapshots;
+pub mod submission;
+pub mod suite_config;
+pub mod teardown;
+pub mod time;
+pub mod trace;
+pub mod units;
 
 pub use benchmarks::*;
 /// Re-export commonly used types and functions
@@ -0,0 +1,104 @@
+//! The freeze lifecycle of a holder's token account as a state machine
+//!
+//! A holder on the allow list starts [`HolderState::Active`]. The issuer can
+//! freeze an active account, the holder can thaw a frozen one through the
+//! gate, and revoking marks the holder's record revoked and freezes the
+//! account for good:
+//!
+//! ```text
+//!            Freeze               Revoke
+//!   Active ---------> Frozen ---------> Revoked <-+
+//!     ^  <---------              Revoke  ^   |    | Revoke
+//!     |     Thaw                         |   +----+
+//!     +----------------------------------+
+//! ```
+//!
+//! Every other transition is rejected and leaves the state unchanged.
+
+use std::collections::BTreeSet;
+
+/// Where a holder's account is in the lifecycle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HolderState {
+    /// On the allow list and thawed
+    #[default]
+    Active,
+    /// On the allow list and frozen, so it can be thawed again
+    Frozen,
+    /// Off the allow list and frozen, for good
+    Revoked,
+}
+
+/// An operation that moves a holder through the lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Transition {
+    /// The issuer freezes the account
+    Freeze,
+    /// The holder thaws the account through the gate
+    Thaw,
+    /// The issuer revokes the holder on the allow list and freezes the
+    /// account
+    Revoke,
+}
+
+impl Transition {
+    pub const ALL: [Transition; 3] = [Transition::Freeze, Transition::Thaw, Transition::Revoke];
+}
+
+impl HolderState {
+    pub const ALL: [HolderState; 3] = [
+        HolderState::Active,
+        HolderState::Frozen,
+        HolderState::Revoked,
+    ];
+
+    /// The state after `transition`, or `None` if it is rejected
+    pub fn transition(self, transition: Transition) -> Option<Self> {
+        match (self, transition) {
+            (HolderState::Active, Transition::Freeze) => Some(HolderState::Frozen),
+            (HolderState::Frozen, Transition::Thaw) => Some(HolderState::Active),
+            (_, Transition::Revoke) => Some(HolderState::Revoked),
+            _ => None,
+        }
+    }
+
+    pub fn accepts(self, transition: Transition) -> bool {
+        self.transition(transition).is_some()
+    }
+
+    /// The state an account is in, from its freeze state and whether the
+    /// holder's allow list record is active; `None` for a thawed account of
+    /// a revoked holder, which the lifecycle never reaches
+    pub fn observe(frozen: bool, allowed: bool) -> Option<Self> {
+        match (frozen, allowed) {
+            (false, true) => Some(HolderState::Active),
+            (true, true) => Some(HolderState::Frozen),
+            (true, false) => Some(HolderState::Revoked),
+            (false, false) => None,
+        }
+    }
+
+    pub fn is_frozen(self) -> bool {
+        self != HolderState::Active
+    }
+
+    pub fn is_allowed(self) -> bool {
+        self != HolderState::Revoked
+    }
+}
+
+/// Every state reachable from `initial` through accepted transitions
+pub fn reachable_states(initial: HolderState) -> BTreeSet<HolderState> {
+    let mut reachable = BTreeSet::from([initial]);
+    let mut frontier = vec![initial];
+    while let Some(state) = frontier.pop() {
+        for transition in Transition::ALL {
+            if let Some(next) = state.transition(transition) {
+                if reachable.insert(next) {
+                    frontier.push(next);
+                }
+            }
+        }
+    }
+    reachable
+}
@@ -2,9 +2,13 @@
 //!
 //! This module provides structured logging, error handling, and debugging
 //! capabilities for the Token ACL testing suite.
+//!
+//! The logger behind [`with_logger`] is per thread, like the mocked clock in
+//! [`crate::time`]: each `#[tokio::test]` runs on its own thread, so one
+//! test's entries never end up in another's.
 
-use std::fmt;
-use std::time::{SystemTime, UNIX_EPOCH};
+use crate::{labels, time};
+use std::{cell::RefCell, fmt};
 
 /// Log levels for structured logging
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
@@ -39,18 +43,15 @@ pub struct LogEntry {
 }
 
 impl LogEntry {
-    /// Create a new log entry
+    /// Create a new log entry, naming the accounts the test labeled
     pub fn new(level: LogLevel, module: &str, message: &str) -> Self {
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+        let timestamp = time::now().max(0) as u64;
 
         Self {
             timestamp,
             level,
             module: module.to_string(),
-            message: message.to_string(),
+            message: labels::annotate(message),
             context: None,
         }
     }
@@ -161,10 +162,7 @@ impl Logger {
 
         let mut content = String::new();
         content.push_str("# Token ACL Test Logs\n\n");
-        content.push_str(&format!(
-            "**Generated**: {}\n\n",
-            chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC")
-        ));
+        content.push_str(&format!("**Generated**: {}\n\n", time::report_timestamp()));
 
         for entry in &self.entries {
             content.push_str(&format!("{}\n", entry.format_console()));
@@ -177,22 +175,25 @@ impl Logger {
     }
 }
 
-/// Global logger instance
-static mut GLOBAL_LOGGER: Option<Logger> = None;
+/// Level of a test's logger when it was not initialized
+pub const DEFAULT_LOG_LEVEL: LogLevel = LogLevel::Info;
+
+thread_local! {
+    static TEST_LOGGER: RefCell<Option<Logger>> = const { RefCell::new(None) };
+}
 
-/// Initialize the global logger
+/// Start a fresh logger for the test running on this thread
 pub fn init_logger(level: LogLevel) {
-    unsafe {
-        GLOBAL_LOGGER = Some(Logger::new(level));
-    }
+    TEST_LOGGER.with(|logger| *logger.borrow_mut() = Some(Logger::new(level)));
 }
 
-/// Get a reference to the global logger
-pub fn get_logger() -> &'static mut Logger {
-    // SAFETY: This is safe because we initialize the logger once and then only read/write to it
-    // in a controlled manner. The logger is designed to be thread-safe for our use case.
-    #[allow(static_mut_refs)]
-    unsafe { GLOBAL_LOGGER.as_mut().expect("Logger not initialized") }
+/// Run `f` with this thread's logger, started at [`DEFAULT_LOG_LEVEL`] if
+/// the test did not initialize one
+pub fn with_logger<R>(f: impl FnOnce(&mut Logger) -> R) -> R {
+    TEST_LOGGER.with(|logger| {
+        let mut logger = logger.borrow_mut();
+        f(logger.get_or_insert_with(|| Logger::new(DEFAULT_LOG_LEVEL)))
+    })
 }
 
 /// Enhanced error types for better error handling
@@ -236,8 +237,7 @@ pub mod error_handling {
 
     /// Handle test errors with logging
     pub fn handle_test_error(error: TestError, module: &str) -> crate::TestResultReport {
-        let logger = get_logger();
-        logger.error(module, &format!("{}", error));
+        with_logger(|logger| logger.error(module, &format!("{}", error)));
 
         crate::TestResultReport::failure(module, error.to_string())
     }
@@ -249,8 +249,7 @@ pub mod error_handling {
 
     /// Log and return error
     pub fn log_and_return_error<T>(error: TestError, module: &str) -> TestResult<T> {
-        let logger = get_logger();
-        logger.error(module, &format!("{}", error));
+        with_logger(|logger| logger.error(module, &format!("{}", error)));
         Err(error)
     }
 }
@@ -262,19 +261,21 @@ pub mod debugging {
 
     /// Debug account information
     pub fn debug_account(account: &Pubkey, module: &str) {
-        let logger = get_logger();
         let context = serde_json::json!({
             "account": account.to_string(),
+            "label": labels::with_labels(|labels| labels.get(account).map(str::to_string)),
             "is_default": *account == Pubkey::default(),
             "is_on_curve": account.is_on_curve()
         });
 
-        logger.log_with_context(
-            LogLevel::Debug,
-            module,
-            "Account debug information",
-            context,
-        );
+        with_logger(|logger| {
+            logger.log_with_context(
+                LogLevel::Debug,
+                module,
+                "Account debug information",
+                context,
+            )
+        });
     }
 
     /// Debug PDA derivation
@@ -285,7 +286,6 @@ pub mod debugging {
         bump: u8,
         module: &str,
     ) {
-        let logger = get_logger();
         let context = serde_json::json!({
             "seeds": seeds.iter().map(hex::encode).collect::<Vec<_>>(),
             "program_id": program_id.to_string(),
@@ -294,12 +294,14 @@ pub mod debugging {
             "is_on_curve": pda.is_on_curve()
         });
 
-        logger.log_with_context(
-            LogLevel::Debug,
-            module,
-            "PDA derivation debug information",
-            context,
-        );
+        with_logger(|logger| {
+            logger.log_with_context(
+                LogLevel::Debug,
+                module,
+                "PDA derivation debug information",
+                context,
+            )
+        });
     }
 
     /// Debug test execution timing
@@ -307,10 +309,9 @@ pub mod debugging {
     where
         F: FnOnce() -> R,
     {
-        let logger = get_logger();
         let start = std::time::Instant::now();
 
-        logger.debug(module, &format!("Starting {}", operation));
+        with_logger(|logger| logger.debug(module, &format!("Starting {}", operation)));
 
         let result = f();
 
@@ -321,12 +322,14 @@ pub mod debugging {
             "duration_us": duration.as_micros()
         });
 
-        logger.log_with_context(
-            LogLevel::Debug,
-            module,
-            &format!("Completed {}", operation),
-            context,
-        );
+        with_logger(|logger| {
+            logger.log_with_context(
+                LogLevel::Debug,
+                module,
+                &format!("Completed {}", operation),
+                context,
+            )
+        });
 
         result
     }
@@ -0,0 +1,457 @@
+//! Membership lookup strategies for gate records
+//!
+//! A gate answers "is this wallet on the list?" and the production gates do
+//! it with one record PDA per user. At millions of users the alternatives
+//! start to pay off: a Merkle root with a proof per check, a bloom filter
+//! bitmap, or a concurrent Merkle tree of compressed accounts. Each trades
+//! compute, check size, rent and update cost differently; this module
+//! prices them per list size and renders the decision matrix.
+//!
+//! Compute units are priced from the runtime's syscall costs (PDA
+//! derivations, SHA-256 and CPI); everything else a gate does is the same
+//! for every strategy. [`MerkleTree`] and [`BloomFilter`] are working
+//! implementations so benchmarks can check the models against real proofs
+//! and false-positive rates at sizes that fit in memory.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use solana_program_runtime::compute_budget::ComputeBudget;
+use solana_sdk::{packet::PACKET_DATA_SIZE, rent::Rent, system_instruction};
+
+use crate::{costs::ACCOUNT_LOCK_LIMIT, units};
+
+/// Fee of a single-signature transaction
+const SIGNATURE_FEE: u64 = 5_000;
+
+/// Signed size of a thaw check through the FAMP with no extra accounts, as
+/// the account scaling benchmark measures it
+pub const THAW_TRANSACTION_BASE_SIZE: usize = 302;
+
+/// Accounts a thaw check through the FAMP locks before any extra account
+pub const THAW_TRANSACTION_BASE_ACCOUNTS: usize = 6;
+
+/// Proof nodes a compressed-account check passes as accounts; deeper levels
+/// are served from the tree's canopy
+pub const COMPRESSED_PROOF_ACCOUNTS: u32 = 10;
+
+/// Changelog entries of the concurrent Merkle tree
+pub const COMPRESSED_MAX_BUFFER_SIZE: u64 = 64;
+
+/// Root account of a Merkle allow list: the root and a sequence number
+const MERKLE_ROOT_ACCOUNT_SIZE: usize = 32 + 8;
+
+/// How a gate looks up a wallet in its list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum LookupStrategy {
+    /// One record PDA per user, derived and read by the gate
+    UserPda,
+    /// A Merkle root account; the check carries the user's proof
+    MerkleProof,
+    /// A bloom filter bitmap in one account
+    BloomFilter,
+    /// A leaf in a concurrent Merkle tree, verified against the canopy
+    CompressedAccount,
+}
+
+impl LookupStrategy {
+    pub const ALL: [LookupStrategy; 4] = [
+        LookupStrategy::UserPda,
+        LookupStrategy::MerkleProof,
+        LookupStrategy::BloomFilter,
+        LookupStrategy::CompressedAccount,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LookupStrategy::UserPda => "Per-user PDA",
+            LookupStrategy::MerkleProof => "Merkle proof",
+            LookupStrategy::BloomFilter => "Bloom filter",
+            LookupStrategy::CompressedAccount => "Compressed account",
+        }
+    }
+}
+
+/// What one strategy costs for a list of `list_size` wallets
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LookupCost {
+    pub strategy: LookupStrategy,
+    pub list_size: u64,
+    /// Compute units of one membership check
+    pub compute_units: u64,
+    /// Bytes a check adds to the thaw transaction: proof data and account keys
+    pub proof_bytes: usize,
+    /// Accounts a check adds to the thaw transaction
+    pub check_accounts: usize,
+    /// Lamports the whole list holds as rent
+    pub rent: u64,
+    /// Fees and rent of adding one wallet
+    pub update_lamports: u64,
+    /// Chance a wallet not on the list passes the check
+    pub false_positive_rate: f64,
+    /// The ceiling the strategy hits at this size, if any
+    pub limit: Option<String>,
+}
+
+impl LookupCost {
+    /// Answers are exact and no ceiling is hit
+    pub fn is_viable(&self) -> bool {
+        self.false_positive_rate == 0.0 && self.limit.is_none()
+    }
+
+    fn check_limits(mut self) -> Self {
+        if THAW_TRANSACTION_BASE_SIZE + self.proof_bytes > PACKET_DATA_SIZE {
+            self.limit = Some(format!(
+                "thaw transaction of {} bytes exceeds {}",
+                THAW_TRANSACTION_BASE_SIZE + self.proof_bytes,
+                PACKET_DATA_SIZE
+            ));
+        } else if THAW_TRANSACTION_BASE_ACCOUNTS + self.check_accounts > ACCOUNT_LOCK_LIMIT {
+            self.limit = Some(format!(
+                "thaw transaction locks more than {} accounts",
+                ACCOUNT_LOCK_LIMIT
+            ));
+        }
+        self
+    }
+}
+
+/// Levels of a Merkle tree over `leaves` leaves
+pub fn merkle_depth(leaves: u64) -> u32 {
+    leaves.max(1).next_power_of_two().trailing_zeros()
+}
+
+/// Compute units of hashing slices of `slice_lens` bytes
+fn sha256_units(budget: &ComputeBudget, slice_lens: &[u64]) -> u64 {
+    budget.sha256_base_cost
+        + slice_lens
+            .iter()
+            .map(|len| {
+                budget
+                    .mem_op_base_cost
+                    .max(budget.sha256_byte_cost * (len / 2))
+            })
+            .sum::<u64>()
+}
+
+/// Compute units of hashing a wallet into a leaf and walking `depth` levels
+fn merkle_verify_units(budget: &ComputeBudget, depth: u32) -> u64 {
+    sha256_units(budget, &[32]) + u64::from(depth) * sha256_units(budget, &[32, 32])
+}
+
+/// Per-user PDA: the gate derives and reads the wallet's `record_size`
+/// byte record
+///
+/// A derivation tries two bumps on average.
+pub fn user_pda_cost(list_size: u64, record_size: usize) -> LookupCost {
+    let budget = ComputeBudget::default();
+    let record_rent = Rent::default().minimum_balance(record_size);
+    LookupCost {
+        strategy: LookupStrategy::UserPda,
+        list_size,
+        compute_units: 2 * budget.create_program_address_units,
+        proof_bytes: 32,
+        check_accounts: 1,
+        rent: record_rent * list_size,
+        update_lamports: SIGNATURE_FEE + record_rent,
+        false_positive_rate: 0.0,
+        limit: None,
+    }
+    .check_limits()
+}
+
+/// Merkle proof: the check carries one sibling per level as instruction
+/// data and reads the root account
+///
+/// Adding a wallet rewrites the root, which also invalidates every
+/// holder's proof.
+pub fn merkle_proof_cost(list_size: u64) -> LookupCost {
+    let budget = ComputeBudget::default();
+    let depth = merkle_depth(list_size);
+    LookupCost {
+        strategy: LookupStrategy::MerkleProof,
+        list_size,
+        compute_units: merkle_verify_units(&budget, depth),
+        proof_bytes: 32 * depth as usize + 32,
+        check_accounts: 1,
+        rent: Rent::default().minimum_balance(MERKLE_ROOT_ACCOUNT_SIZE),
+        update_lamports: SIGNATURE_FEE,
+        false_positive_rate: 0.0,
+        limit: None,
+    }
+    .check_limits()
+}
+
+/// Bloom filter sized for `list_size` wallets at `false_positive_rate`
+///
+/// The check hashes the wallet once per hash function. Wallets can be
+/// added but never removed.
+pub fn bloom_filter_cost(list_size: u64, false_positive_rate: f64) -> LookupCost {
+    let budget = ComputeBudget::default();
+    let (bits, hashes) = BloomFilter::dimensions(list_size, false_positive_rate);
+    let bytes = bits.div_ceil(8);
+    let limit = (bytes > system_instruction::MAX_PERMITTED_DATA_LENGTH).then(|| {
+        format!(
+            "{} byte bitmap exceeds the {} byte account limit",
+            bytes,
+            system_instruction::MAX_PERMITTED_DATA_LENGTH
+        )
+    });
+    LookupCost {
+        strategy: LookupStrategy::BloomFilter,
+        list_size,
+        compute_units: u64::from(hashes) * sha256_units(&budget, &[32, 1]),
+        proof_bytes: 32,
+        check_accounts: 1,
+        rent: Rent::default().minimum_balance(bytes as usize),
+        update_lamports: SIGNATURE_FEE,
+        false_positive_rate,
+        limit,
+    }
+    .check_limits()
+}
+
+/// Account size of a concurrent Merkle tree of `depth` levels with a
+/// canopy of `canopy_depth` levels, after spl-account-compression's layout
+pub fn concurrent_merkle_tree_size(depth: u32, canopy_depth: u32) -> usize {
+    let header = 56;
+    let path = 32 * depth as u64 + 32 + 4 + 4;
+    let tree = 3 * 8 + COMPRESSED_MAX_BUFFER_SIZE * path + path;
+    let canopy = 32 * ((1u64 << (canopy_depth + 1)) - 2);
+    (header + tree + canopy) as usize
+}
+
+/// Compressed account: the gate CPIs into the compression program, which
+/// verifies the leaf against the tree and its canopy
+///
+/// Proof nodes below the canopy travel as accounts, so the canopy grows
+/// with the list to keep the check within [`COMPRESSED_PROOF_ACCOUNTS`].
+pub fn compressed_account_cost(list_size: u64) -> LookupCost {
+    let budget = ComputeBudget::default();
+    let depth = merkle_depth(list_size);
+    let proof_accounts = depth.min(COMPRESSED_PROOF_ACCOUNTS);
+    let canopy_depth = depth - proof_accounts;
+    // The tree, the compression program and the proof nodes
+    let check_accounts = 2 + proof_accounts as usize;
+    LookupCost {
+        strategy: LookupStrategy::CompressedAccount,
+        list_size,
+        compute_units: merkle_verify_units(&budget, depth) + budget.invoke_units,
+        proof_bytes: 32 * check_accounts,
+        check_accounts,
+        rent: Rent::default().minimum_balance(concurrent_merkle_tree_size(depth, canopy_depth)),
+        update_lamports: SIGNATURE_FEE,
+        false_positive_rate: 0.0,
+        limit: None,
+    }
+    .check_limits()
+}
+
+/// Every strategy for a list of `list_size` wallets
+pub fn lookup_costs(
+    list_size: u64,
+    record_size: usize,
+    false_positive_rate: f64,
+) -> Vec<LookupCost> {
+    vec![
+        user_pda_cost(list_size, record_size),
+        merkle_proof_cost(list_size),
+        bloom_filter_cost(list_size, false_positive_rate),
+        compressed_account_cost(list_size),
+    ]
+}
+
+/// SHA-256 of the concatenated `parts`
+fn hash(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// The leaf of `wallet`
+pub fn merkle_leaf(wallet: &[u8]) -> [u8; 32] {
+    hash(&[wallet])
+}
+
+/// A Merkle tree over wallet leaves, with sorted-pair hashing so proofs
+/// carry no direction bits
+///
+/// Short levels are padded by carrying the last node up unchanged.
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+    /// Leaves first, root last
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: Vec<[u8; 32]>) -> Self {
+        let mut levels = vec![leaves];
+        while levels.last().map_or(0, Vec::len) > 1 {
+            let level = levels.last().unwrap();
+            let next = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_pair(left, right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+        Self { levels }
+    }
+
+    /// The root, or zero for an empty tree
+    pub fn root(&self) -> [u8; 32] {
+        self.levels
+            .last()
+            .and_then(|level| level.first())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Siblings from the leaf at `index` up to the root
+    pub fn proof(&self, mut index: usize) -> Vec<[u8; 32]> {
+        let mut proof = Vec::new();
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        proof
+    }
+
+    /// Whether `leaf` and `proof` hash up to `root`
+    pub fn verify(root: &[u8; 32], leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+        proof
+            .iter()
+            .fold(leaf, |node, sibling| hash_pair(&node, sibling))
+            == *root
+    }
+}
+
+fn hash_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    if a <= b {
+        hash(&[a, b])
+    } else {
+        hash(&[b, a])
+    }
+}
+
+/// A bloom filter over wallets, one SHA-256 per hash function
+#[derive(Debug, Clone)]
+pub struct BloomFilter {
+    bits: Vec<u8>,
+    hashes: u32,
+}
+
+impl BloomFilter {
+    /// Bits and hash functions for `items` at `false_positive_rate`
+    pub fn dimensions(items: u64, false_positive_rate: f64) -> (u64, u32) {
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-(items.max(1) as f64) * false_positive_rate.ln() / (ln2 * ln2)).ceil();
+        let hashes = (bits / items.max(1) as f64 * ln2).round().max(1.0);
+        (bits as u64, hashes as u32)
+    }
+
+    pub fn with_capacity(items: u64, false_positive_rate: f64) -> Self {
+        let (bits, hashes) = Self::dimensions(items, false_positive_rate);
+        Self {
+            bits: vec![0; bits.div_ceil(8) as usize],
+            hashes,
+        }
+    }
+
+    pub fn hashes(&self) -> u32 {
+        self.hashes
+    }
+
+    pub fn size(&self) -> usize {
+        self.bits.len()
+    }
+
+    fn positions<'a>(&'a self, wallet: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+        let bits = self.bits.len() as u64 * 8;
+        (0..self.hashes).map(move |i| {
+            let digest = hash(&[wallet, &[i as u8]]);
+            let mut head = [0; 8];
+            head.copy_from_slice(&digest[..8]);
+            (u64::from_le_bytes(head) % bits) as usize
+        })
+    }
+
+    pub fn insert(&mut self, wallet: &[u8]) {
+        let positions: Vec<_> = self.positions(wallet).collect();
+        for position in positions {
+            self.bits[position / 8] |= 1 << (position % 8);
+        }
+    }
+
+    /// Never false for an inserted wallet; true for others at the filter's
+    /// false-positive rate
+    pub fn contains(&self, wallet: &[u8]) -> bool {
+        self.positions(wallet)
+            .all(|position| self.bits[position / 8] & (1 << (position % 8)) != 0)
+    }
+}
+
+/// The viable strategy minimizing `key`, by name
+fn winner<K: Ord>(costs: &[LookupCost], key: impl Fn(&LookupCost) -> K) -> &'static str {
+    costs
+        .iter()
+        .filter(|cost| cost.is_viable())
+        .min_by_key(|cost| key(cost))
+        .map_or("none", |cost| cost.strategy.name())
+}
+
+/// Render every strategy at every list size, then the decision matrix
+///
+/// `costs` holds one [`lookup_costs`] result per list size. The matrix only
+/// considers strategies that answer exactly and hit no ceiling.
+pub fn render_lookup_report(costs: &[Vec<LookupCost>]) -> String {
+    let mut report = String::from("## Record Lookup Strategies\n\n");
+    report.push_str(
+        "| List Size | Strategy | Compute Units | Check Bytes | Check Accounts | Rent | Add One Wallet | False Positives | Limit |\n",
+    );
+    report.push_str(
+        "|-----------|----------|---------------|-------------|----------------|------|----------------|-----------------|-------|\n",
+    );
+    for cost in costs.iter().flatten() {
+        report.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} | {} | {} | {} |\n",
+            cost.list_size,
+            cost.strategy.name(),
+            units::compute_units(cost.compute_units),
+            cost.proof_bytes,
+            cost.check_accounts,
+            units::sol_and_lamports(cost.rent),
+            units::sol_and_lamports(cost.update_lamports),
+            cost.false_positive_rate,
+            cost.limit.as_deref().unwrap_or("-")
+        ));
+    }
+
+    report.push_str("\n## Decision Matrix\n\n");
+    report.push_str("| List Size | Fewest CU | Smallest Check | Least Rent | Cheapest Add |\n");
+    report.push_str("|-----------|-----------|----------------|------------|--------------|\n");
+    for size_costs in costs {
+        let Some(first) = size_costs.first() else {
+            continue;
+        };
+        report.push_str(&format!(
+            "| {} | {} | {} | {} | {} |\n",
+            first.list_size,
+            winner(size_costs, |cost| cost.compute_units),
+            winner(size_costs, |cost| cost.proof_bytes),
+            winner(size_costs, |cost| cost.rent),
+            winner(size_costs, |cost| cost.update_lamports)
+        ));
+    }
+    report.push_str(
+        "\nBloom filters are never exact and are left out of the matrix; a false positive thaws a wallet that is not on an allow list. \
+         Adding to a Merkle list invalidates every holder's proof; per-user PDAs and compressed accounts update in place.\n",
+    );
+    report
+}
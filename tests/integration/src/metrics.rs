@@ -0,0 +1,299 @@
+//! Lamport cost accounting per test
+//!
+//! A live run pays for every transaction it sends and every account it
+//! creates. [`TestMetrics`] keeps what one test spent: transaction fees, rent
+//! locked in the accounts it created, and rent it got back by closing
+//! accounts. Fees per signature and rent exemption are the same on a local
+//! bank as on devnet, so the cost section [`render_cost_section`] adds to
+//! each report, per test and per category, is what the same tests cost on a
+//! live cluster.
+//!
+//! Tests that execute on a metered SBF build also record the compute units
+//! their transactions consumed, measured rather than estimated by
+//! [`crate::benchmarks::compute_profile`]; [`render_compute_section`] lists
+//! them.
+//!
+//! Live tests also check what they spent against the budget `suite.toml`
+//! sets for their category with [`over_budget`], so a change that makes a
+//! devnet run expensive fails the run instead of draining the keypair.
+
+use solana_sdk::transaction::Transaction;
+use std::{collections::BTreeMap, ops::AddAssign};
+
+use crate::{
+    account_snapshots::AccountDiff,
+    suite_config::{self, SuiteCategory},
+    units, TestResultReport,
+};
+
+/// Category label of tests that run in every configuration
+pub const CORE_CATEGORY: &str = "core";
+
+/// Lamports spent by a test, or by every test in a category
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LamportCost {
+    /// Transaction fees paid
+    pub fees: u64,
+    /// Lamports locked in accounts created with data
+    pub rent: u64,
+    /// Lamports released by closing accounts with data
+    pub reclaimed: u64,
+}
+
+impl LamportCost {
+    /// Fees plus rent minus what was reclaimed; negative when a test closed
+    /// accounts it did not pay for
+    pub fn net(&self) -> i128 {
+        i128::from(self.fees) + i128::from(self.rent) - i128::from(self.reclaimed)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl AddAssign for LamportCost {
+    fn add_assign(&mut self, other: Self) {
+        self.fees += other.fees;
+        self.rent += other.rent;
+        self.reclaimed += other.reclaimed;
+    }
+}
+
+/// What one test consumed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TestMetrics {
+    /// Category the test belongs to, `None` for tests that always run
+    pub category: Option<SuiteCategory>,
+    /// Transactions charged a fee
+    pub transactions: u32,
+    pub cost: LamportCost,
+    /// Compute units consumed by transactions that ran on SBF builds
+    pub compute_units: u64,
+}
+
+impl TestMetrics {
+    pub fn new(category: Option<SuiteCategory>) -> Self {
+        Self {
+            category,
+            ..Self::default()
+        }
+    }
+
+    /// Count one transaction that paid `fee`
+    pub fn record_fee(&mut self, fee: u64) {
+        self.transactions += 1;
+        self.cost.fees += fee;
+    }
+
+    /// Add `units` compute units consumed by one transaction
+    pub fn record_compute_units(&mut self, units: u64) {
+        self.compute_units += units;
+    }
+
+    /// Count a step that ran `transactions` and changed `diff`
+    ///
+    /// A transaction pays `lamports_per_signature` per signature when its fee
+    /// payer's balance dropped in the step; one rejected before execution
+    /// pays nothing. Accounts the step created with data count as rent and
+    /// accounts with data it closed as reclaimed. Lamports moved between
+    /// accounts that exist before and after, such as funding a wallet, are
+    /// neither.
+    pub fn record_step(
+        &mut self,
+        transactions: &[&Transaction],
+        lamports_per_signature: u64,
+        diff: &AccountDiff,
+    ) {
+        for transaction in transactions {
+            let Some(fee_payer) = transaction.message.account_keys.first() else {
+                continue;
+            };
+            if diff
+                .get(fee_payer)
+                .is_some_and(|change| change.lamport_delta() < 0)
+            {
+                self.record_fee(lamports_per_signature * transaction.signatures.len() as u64);
+            }
+        }
+
+        for change in &diff.changes {
+            let had_data = change.before.as_ref().is_some_and(|s| !s.data.is_empty());
+            let has_data = change.after.as_ref().is_some_and(|s| !s.data.is_empty());
+            if change.lamports_before() == 0 && has_data {
+                self.cost.rent += change.lamports_after();
+            } else if change.is_closed() && had_data {
+                self.cost.reclaimed += change.lamports_before();
+            }
+        }
+    }
+
+    /// Whether anything was recorded
+    pub fn is_empty(&self) -> bool {
+        self.transactions == 0 && self.cost.is_zero() && self.compute_units == 0
+    }
+
+    /// Label of the category for reports
+    pub fn category_label(&self) -> &'static str {
+        self.category.map_or(CORE_CATEGORY, SuiteCategory::key)
+    }
+
+    /// An error if the test's net spend exceeds `budget` lamports
+    pub fn check_budget(&self, budget: u64) -> Result<(), String> {
+        if self.cost.net() > i128::from(budget) {
+            return Err(format!(
+                "Spent {} net ({} fees, {} rent, {} reclaimed), over the {} budget of {}",
+                signed_lamports(self.cost.net()),
+                units::lamports(self.cost.fees),
+                units::lamports(self.cost.rent),
+                units::lamports(self.cost.reclaimed),
+                self.category_label(),
+                units::lamports(budget)
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A failed report for `test_name` if `metrics` exceeds the budget the
+/// current suite configuration sets for its category
+///
+/// Categories without a budget, and tests without one, are not checked.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use token_acl_integration_tests::{metrics::{self, TestMetrics}, TestResultReport};
+/// fn finish_devnet_test(test_name: &str, metrics: TestMetrics, assertions: usize) -> TestResultReport {
+///     if let Some(failure) = metrics::over_budget(test_name, &metrics) {
+///         return failure;
+///     }
+///     TestResultReport::success(test_name, assertions).with_metrics(metrics)
+/// }
+/// ```
+pub fn over_budget(test_name: &str, metrics: &TestMetrics) -> Option<TestResultReport> {
+    let budget = suite_config::current().budget(metrics.category?)?;
+    let error = metrics.check_budget(budget).err()?;
+    Some(TestResultReport::failure(test_name, error).with_metrics(metrics.clone()))
+}
+
+/// Costs summed per category label, in report order with core first
+pub fn costs_by_category(results: &[TestResultReport]) -> Vec<(&'static str, usize, LamportCost)> {
+    let mut totals: BTreeMap<Option<SuiteCategory>, (usize, LamportCost)> = BTreeMap::new();
+    for result in results.iter().filter(|r| !r.metrics.is_empty()) {
+        let (tests, cost) = totals.entry(result.metrics.category).or_default();
+        *tests += 1;
+        *cost += result.metrics.cost;
+    }
+    totals
+        .into_iter()
+        .map(|(category, (tests, cost))| {
+            let label = category.map_or(CORE_CATEGORY, SuiteCategory::key);
+            (label, tests, cost)
+        })
+        .collect()
+}
+
+/// The markdown cost section for `results`; empty when no test recorded a
+/// cost
+pub fn render_cost_section(results: &[TestResultReport]) -> String {
+    let measured: Vec<&TestResultReport> =
+        results.iter().filter(|r| !r.metrics.is_empty()).collect();
+    if measured.is_empty() {
+        return String::new();
+    }
+
+    let mut total = LamportCost::default();
+    for result in &measured {
+        total += result.metrics.cost;
+    }
+
+    let mut section = String::from("## Cost\n\n");
+    section.push_str(&format!(
+        "A run of the {} tests below spends {} net: {} in fees and {} in rent, {} of it reclaimed.\n\n",
+        measured.len(),
+        signed_sol(total.net()),
+        units::lamports(total.fees),
+        units::lamports(total.rent),
+        units::lamports(total.reclaimed)
+    ));
+
+    section.push_str("| Test | Category | Transactions | Fees | Rent | Reclaimed | Net |\n");
+    section.push_str("|------|----------|--------------|------|------|-----------|-----|\n");
+    for result in &measured {
+        let metrics = &result.metrics;
+        section.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} | {} |\n",
+            result.name,
+            metrics.category_label(),
+            metrics.transactions,
+            units::lamports(metrics.cost.fees),
+            units::lamports(metrics.cost.rent),
+            units::lamports(metrics.cost.reclaimed),
+            signed_lamports(metrics.cost.net())
+        ));
+    }
+
+    section.push_str("\n### By Category\n\n");
+    section.push_str("| Category | Tests | Fees | Rent | Reclaimed | Net |\n");
+    section.push_str("|----------|-------|------|------|-----------|-----|\n");
+    for (label, tests, cost) in costs_by_category(results) {
+        section.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} |\n",
+            label,
+            tests,
+            units::lamports(cost.fees),
+            units::lamports(cost.rent),
+            units::lamports(cost.reclaimed),
+            signed_lamports(cost.net())
+        ));
+    }
+    section.push('\n');
+
+    section
+}
+
+/// The markdown compute section for `results`; empty when no test measured
+/// compute units
+pub fn render_compute_section(results: &[TestResultReport]) -> String {
+    let measured: Vec<&TestResultReport> = results
+        .iter()
+        .filter(|r| r.metrics.compute_units > 0)
+        .collect();
+    if measured.is_empty() {
+        return String::new();
+    }
+
+    let mut section = String::from("## Compute Units\n\n");
+    section.push_str("| Test | Category | Compute Units |\n");
+    section.push_str("|------|----------|---------------|\n");
+    for result in &measured {
+        section.push_str(&format!(
+            "| {} | {} | {} |\n",
+            result.name,
+            result.metrics.category_label(),
+            units::compute_units(result.metrics.compute_units)
+        ));
+    }
+    section.push('\n');
+
+    section
+}
+
+fn signed_lamports(lamports: i128) -> String {
+    let magnitude = units::lamports(lamports.unsigned_abs() as u64);
+    if lamports < 0 {
+        format!("-{}", magnitude)
+    } else {
+        magnitude
+    }
+}
+
+fn signed_sol(lamports: i128) -> String {
+    let magnitude = units::sol_and_lamports(lamports.unsigned_abs() as u64);
+    if lamports < 0 {
+        format!("-{}", magnitude)
+    } else {
+        magnitude
+    }
+}
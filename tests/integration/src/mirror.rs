@@ -0,0 +1,532 @@
+//! Off-chain mirror of a mint's list records
+//!
+//! Dashboards and reconciliation jobs ask the same membership questions over
+//! and over ("is this owner allow-listed?", "who is blocked?"), and
+//! answering each from RPC is slow and rate limited. [`ListMirror`] keeps a
+//! local copy of every allow list and block list record of one mint in a
+//! sled tree and answers from it.
+//!
+//! It is kept current incrementally from [`AccountUpdate`]s, which come from
+//! `programSubscribe` notifications ([`ListMirror::follow`]) or any other
+//! source ([`ListMirror::sync`]). Notifications can arrive late, twice or out
+//! of order, so every address remembers the slot it was last written at and
+//! older updates are dropped. Closing an account reassigns it to the system
+//! program, which a program subscription never reports, so a periodic
+//! [`ListMirror::reconcile`] against a full snapshot removes records that
+//! disappeared without a notification; [`ListMirror::bootstrap`] is the same
+//! reconciliation against `getProgramAccounts`.
+//!
+//! Records are decoded with `gate_view`, so the mirror holds exactly what an
+//! on-chain consumer would read.
+
+use std::collections::BTreeSet;
+
+use futures::{stream, Stream, StreamExt};
+use gate_view::{AllowListView, BlockListView};
+use solana_client::{
+    nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient},
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+};
+use solana_sdk::{
+    account::Account, clock::Slot, commitment_config::CommitmentConfig, pubkey::Pubkey,
+};
+
+/// Key prefixes within a mint's tree
+const ADDRESS_PREFIX: u8 = b'a';
+const RECORD_PREFIX: u8 = b'r';
+const SLOT_KEY: &[u8] = b"slot";
+
+/// Which list a record belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ListKind {
+    AllowList = 1,
+    BlockList = 2,
+}
+
+impl ListKind {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            1 => Some(ListKind::AllowList),
+            2 => Some(ListKind::BlockList),
+            _ => None,
+        }
+    }
+}
+
+/// The state of one account at a slot, as a subscription or snapshot
+/// reports it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountUpdate {
+    pub address: Pubkey,
+    pub slot: Slot,
+    /// The program owning the account at `slot`
+    pub owner: Pubkey,
+    pub data: Vec<u8>,
+}
+
+impl AccountUpdate {
+    pub fn from_account(address: Pubkey, slot: Slot, account: &Account) -> Self {
+        Self {
+            address,
+            slot,
+            owner: account.owner,
+            data: account.data.clone(),
+        }
+    }
+
+    /// An account that no longer exists at `slot`
+    pub fn closed(address: Pubkey, slot: Slot) -> Self {
+        Self {
+            address,
+            slot,
+            owner: solana_sdk::system_program::id(),
+            data: Vec::new(),
+        }
+    }
+}
+
+/// What applying an update did to the mirror
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MirrorChange {
+    /// A record the mirror did not hold
+    Inserted(ListKind, Pubkey),
+    /// A new version of a record the mirror held
+    Updated(ListKind, Pubkey),
+    /// A record that no longer exists
+    Removed(ListKind, Pubkey),
+    /// Older than what the mirror holds for the address
+    Stale,
+    /// Not a record of this mint
+    Ignored,
+}
+
+#[derive(Debug)]
+pub enum MirrorError {
+    Storage(sled::Error),
+    Rpc(String),
+    /// A stored entry that does not decode; the tree was written by
+    /// something else
+    Corrupt(String),
+}
+
+impl std::fmt::Display for MirrorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MirrorError::Storage(e) => write!(f, "mirror storage error: {}", e),
+            MirrorError::Rpc(e) => write!(f, "mirror RPC error: {}", e),
+            MirrorError::Corrupt(e) => write!(f, "corrupt mirror entry: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MirrorError {}
+
+impl From<sled::Error> for MirrorError {
+    fn from(error: sled::Error) -> Self {
+        MirrorError::Storage(error)
+    }
+}
+
+/// The gate programs whose records a mirror follows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MirroredGates {
+    pub allow_list_program: Option<Pubkey>,
+    pub block_list_program: Option<Pubkey>,
+}
+
+impl MirroredGates {
+    fn programs(&self) -> impl Iterator<Item = Pubkey> {
+        self.allow_list_program
+            .into_iter()
+            .chain(self.block_list_program)
+    }
+}
+
+/// Local copy of one mint's list records
+///
+/// Each mint gets its own tree in the database, named after the mint, so one
+/// database can hold mirrors of many mints.
+pub struct ListMirror {
+    tree: sled::Tree,
+    mint: Pubkey,
+    gates: MirroredGates,
+}
+
+impl ListMirror {
+    /// Open (or create) the mirror of `mint` in `db`
+    pub fn open(db: &sled::Db, mint: Pubkey, gates: MirroredGates) -> Result<Self, MirrorError> {
+        Ok(Self {
+            tree: db.open_tree(mint.to_bytes())?,
+            mint,
+            gates,
+        })
+    }
+
+    pub fn mint(&self) -> Pubkey {
+        self.mint
+    }
+
+    /// The highest slot applied so far
+    pub fn slot(&self) -> Result<Slot, MirrorError> {
+        Ok(self
+            .tree
+            .get(SLOT_KEY)?
+            .map(|bytes| read_slot(&bytes))
+            .transpose()?
+            .unwrap_or(0))
+    }
+
+    /// Apply one update, atomically
+    pub fn apply(&self, update: &AccountUpdate) -> Result<MirrorChange, MirrorError> {
+        let address_key = address_key(&update.address);
+        let previous = self
+            .tree
+            .get(&address_key)?
+            .map(|bytes| decode_address_entry(&bytes))
+            .transpose()?;
+        if previous.is_some_and(|(slot, _)| slot > update.slot) {
+            return Ok(MirrorChange::Stale);
+        }
+        let previous_record = previous.and_then(|(_, record)| record);
+        let record = self.classify(update);
+
+        // An address that never held a record is only remembered once it is
+        // closed, so a late notification from before the close can't bring
+        // it back
+        if record.is_none() && previous_record.is_none() && !update.data.is_empty() {
+            return Ok(MirrorChange::Ignored);
+        }
+
+        let mut batch = sled::Batch::default();
+        if let Some((kind, owner)) = previous_record {
+            batch.remove(record_key(kind, &owner));
+        }
+        if let Some((kind, owner)) = record {
+            let mut value = update.slot.to_le_bytes().to_vec();
+            value.extend_from_slice(&update.data);
+            batch.insert(record_key(kind, &owner), value);
+        }
+        batch.insert(address_key, encode_address_entry(update.slot, record));
+        if update.slot > self.slot()? {
+            batch.insert(SLOT_KEY, &update.slot.to_le_bytes());
+        }
+        self.tree.apply_batch(batch)?;
+
+        Ok(match (previous_record, record) {
+            (_, Some((kind, owner))) if previous_record == record => {
+                MirrorChange::Updated(kind, owner)
+            }
+            (_, Some((kind, owner))) => MirrorChange::Inserted(kind, owner),
+            (Some((kind, owner)), None) => MirrorChange::Removed(kind, owner),
+            (None, None) => MirrorChange::Ignored,
+        })
+    }
+
+    /// Apply every update from `updates` until the stream ends, returning
+    /// the changes made
+    pub async fn sync<S>(&self, updates: S) -> Result<Vec<MirrorChange>, MirrorError>
+    where
+        S: Stream<Item = AccountUpdate>,
+    {
+        let mut updates = std::pin::pin!(updates);
+        let mut changes = Vec::new();
+        while let Some(update) = updates.next().await {
+            match self.apply(&update)? {
+                MirrorChange::Stale | MirrorChange::Ignored => {}
+                change => changes.push(change),
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Bring the mirror in line with a full snapshot of the gates' accounts
+    /// taken at `slot`
+    ///
+    /// Every record in the snapshot is applied, and every record the mirror
+    /// holds that the snapshot lacks is removed as closed at `slot`. Entries
+    /// newer than `slot` are kept either way.
+    pub fn reconcile(
+        &self,
+        slot: Slot,
+        snapshot: &[(Pubkey, Account)],
+    ) -> Result<Vec<MirrorChange>, MirrorError> {
+        let mut changes = Vec::new();
+        let mut present = BTreeSet::new();
+        for (address, account) in snapshot {
+            present.insert(*address);
+            match self.apply(&AccountUpdate::from_account(*address, slot, account))? {
+                MirrorChange::Stale | MirrorChange::Ignored => {}
+                change => changes.push(change),
+            }
+        }
+
+        let mut vanished = Vec::new();
+        for entry in self.tree.scan_prefix([ADDRESS_PREFIX]) {
+            let (key, value) = entry?;
+            let (_, record) = decode_address_entry(&value)?;
+            let address = read_pubkey(&key[1..])?;
+            if record.is_some() && !present.contains(&address) {
+                vanished.push(address);
+            }
+        }
+        for address in vanished {
+            match self.apply(&AccountUpdate::closed(address, slot))? {
+                MirrorChange::Stale | MirrorChange::Ignored => {}
+                change => changes.push(change),
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Reconcile against every account the gates own, read over RPC
+    pub async fn bootstrap(&self, rpc: &RpcClient) -> Result<Vec<MirrorChange>, MirrorError> {
+        // Taken first, so anything written while the accounts are fetched
+        // carries a later slot and wins
+        let slot = rpc
+            .get_slot()
+            .await
+            .map_err(|e| MirrorError::Rpc(e.to_string()))?;
+        let mut snapshot = Vec::new();
+        for program in self.gates.programs() {
+            snapshot.extend(
+                rpc.get_program_accounts(&program)
+                    .await
+                    .map_err(|e| MirrorError::Rpc(e.to_string()))?,
+            );
+        }
+        self.reconcile(slot, &snapshot)
+    }
+
+    /// Follow the gates' accounts over `programSubscribe` until a
+    /// subscription ends
+    ///
+    /// Closures are not notified; reconcile periodically alongside.
+    pub async fn follow(&self, pubsub: &PubsubClient) -> Result<(), MirrorError> {
+        let config = RpcProgramAccountsConfig {
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(solana_account_decoder::UiAccountEncoding::Base64),
+                commitment: Some(CommitmentConfig::confirmed()),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+
+        let mut subscriptions = Vec::new();
+        for program in self.gates.programs() {
+            let (notifications, _unsubscribe) = pubsub
+                .program_subscribe(&program, Some(config.clone()))
+                .await
+                .map_err(|e| MirrorError::Rpc(e.to_string()))?;
+            subscriptions.push(notifications);
+        }
+
+        let updates = stream::select_all(subscriptions).filter_map(|response| async move {
+            let address = response.value.pubkey.parse().ok()?;
+            let account: Account = response.value.account.decode()?;
+            Some(AccountUpdate::from_account(
+                address,
+                response.context.slot,
+                &account,
+            ))
+        });
+        self.sync(updates).await.map(|_| ())
+    }
+
+    /// The allow list record of `owner`
+    pub fn allow_list_record(&self, owner: &Pubkey) -> Result<Option<AllowListView>, MirrorError> {
+        self.record_data(ListKind::AllowList, owner)?
+            .map(|data| {
+                AllowListView::unpack(&data).map_err(|e| MirrorError::Corrupt(e.to_string()))
+            })
+            .transpose()
+    }
+
+    /// The block list record of `owner`
+    pub fn block_list_record(&self, owner: &Pubkey) -> Result<Option<BlockListView>, MirrorError> {
+        self.record_data(ListKind::BlockList, owner)?
+            .map(|data| {
+                BlockListView::unpack(&data).map_err(|e| MirrorError::Corrupt(e.to_string()))
+            })
+            .transpose()
+    }
+
+    /// Whether `owner` has an active allow list record at `now`
+    pub fn is_allow_listed(&self, owner: &Pubkey, now: i64) -> Result<bool, MirrorError> {
+        Ok(self
+            .allow_list_record(owner)?
+            .is_some_and(|record| record.is_active(now)))
+    }
+
+    /// Whether `owner` has a block in force at `now`
+    pub fn is_blocked(&self, owner: &Pubkey, now: i64) -> Result<bool, MirrorError> {
+        Ok(self
+            .block_list_record(owner)?
+            .is_some_and(|record| record.is_active(now)))
+    }
+
+    /// Every allow list record, by owner
+    pub fn allow_list_records(&self) -> Result<Vec<AllowListView>, MirrorError> {
+        self.records(ListKind::AllowList)?
+            .iter()
+            .map(|data| {
+                AllowListView::unpack(data).map_err(|e| MirrorError::Corrupt(e.to_string()))
+            })
+            .collect()
+    }
+
+    /// Every block list record, by owner
+    pub fn block_list_records(&self) -> Result<Vec<BlockListView>, MirrorError> {
+        self.records(ListKind::BlockList)?
+            .iter()
+            .map(|data| {
+                BlockListView::unpack(data).map_err(|e| MirrorError::Corrupt(e.to_string()))
+            })
+            .collect()
+    }
+
+    /// Owners with an active allow list record at `now`
+    pub fn allow_listed(&self, now: i64) -> Result<Vec<Pubkey>, MirrorError> {
+        Ok(self
+            .allow_list_records()?
+            .into_iter()
+            .filter(|record| record.is_active(now))
+            .map(|record| record.owner)
+            .collect())
+    }
+
+    /// Owners with a block in force at `now`
+    pub fn blocked(&self, now: i64) -> Result<Vec<Pubkey>, MirrorError> {
+        Ok(self
+            .block_list_records()?
+            .into_iter()
+            .filter(|record| record.is_active(now))
+            .map(|record| record.owner)
+            .collect())
+    }
+
+    /// The list and owner `update` is a record of, if it is one of this
+    /// mint's records at its canonical address
+    fn classify(&self, update: &AccountUpdate) -> Option<(ListKind, Pubkey)> {
+        if Some(update.owner) == self.gates.allow_list_program {
+            let view = AllowListView::unpack(&update.data).ok()?;
+            let expected = gate_view::allow_list_address(&update.owner, &self.mint, &view.owner);
+            (view.mint == self.mint && expected.address() == update.address)
+                .then_some((ListKind::AllowList, view.owner))
+        } else if Some(update.owner) == self.gates.block_list_program {
+            let view = BlockListView::unpack(&update.data).ok()?;
+            let expected = gate_view::block_list_address(&update.owner, &self.mint, &view.owner);
+            (view.mint == self.mint && expected.address() == update.address)
+                .then_some((ListKind::BlockList, view.owner))
+        } else {
+            None
+        }
+    }
+
+    fn record_data(&self, kind: ListKind, owner: &Pubkey) -> Result<Option<Vec<u8>>, MirrorError> {
+        Ok(self
+            .tree
+            .get(record_key(kind, owner))?
+            .map(|value| value[8..].to_vec()))
+    }
+
+    fn records(&self, kind: ListKind) -> Result<Vec<Vec<u8>>, MirrorError> {
+        self.tree
+            .scan_prefix([RECORD_PREFIX, kind as u8])
+            .map(|entry| Ok(entry?.1[8..].to_vec()))
+            .collect()
+    }
+}
+
+fn address_key(address: &Pubkey) -> Vec<u8> {
+    let mut key = vec![ADDRESS_PREFIX];
+    key.extend_from_slice(address.as_ref());
+    key
+}
+
+fn record_key(kind: ListKind, owner: &Pubkey) -> Vec<u8> {
+    let mut key = vec![RECORD_PREFIX, kind as u8];
+    key.extend_from_slice(owner.as_ref());
+    key
+}
+
+/// Slot, then the record's list (0 for none) and owner
+fn encode_address_entry(slot: Slot, record: Option<(ListKind, Pubkey)>) -> Vec<u8> {
+    let mut value = slot.to_le_bytes().to_vec();
+    match record {
+        Some((kind, owner)) => {
+            value.push(kind as u8);
+            value.extend_from_slice(owner.as_ref());
+        }
+        None => value.push(0),
+    }
+    value
+}
+
+fn decode_address_entry(value: &[u8]) -> Result<(Slot, Option<(ListKind, Pubkey)>), MirrorError> {
+    let slot = read_slot(value)?;
+    match value.get(8) {
+        Some(0) => Ok((slot, None)),
+        Some(&byte) => {
+            let kind = ListKind::from_byte(byte)
+                .ok_or_else(|| MirrorError::Corrupt(format!("list kind {}", byte)))?;
+            Ok((slot, Some((kind, read_pubkey(&value[9..])?))))
+        }
+        None => Err(MirrorError::Corrupt("truncated address entry".to_string())),
+    }
+}
+
+fn read_slot(bytes: &[u8]) -> Result<Slot, MirrorError> {
+    bytes
+        .get(..8)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(Slot::from_le_bytes)
+        .ok_or_else(|| MirrorError::Corrupt("truncated slot".to_string()))
+}
+
+fn read_pubkey(bytes: &[u8]) -> Result<Pubkey, MirrorError> {
+    Pubkey::try_from(bytes).map_err(|_| MirrorError::Corrupt("bad public key".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temporary_db() -> sled::Db {
+        sled::Config::new().temporary(true).open().unwrap()
+    }
+
+    #[test]
+    fn test_address_entry_roundtrip() {
+        let owner = Pubkey::new_unique();
+        for record in [
+            None,
+            Some((ListKind::AllowList, owner)),
+            Some((ListKind::BlockList, owner)),
+        ] {
+            let value = encode_address_entry(42, record);
+            assert_eq!(decode_address_entry(&value).unwrap(), (42, record));
+        }
+        assert!(decode_address_entry(&[0; 8]).is_err());
+    }
+
+    #[test]
+    fn test_foreign_accounts_ignored() {
+        let gate = Pubkey::new_unique();
+        let gates = MirroredGates {
+            allow_list_program: Some(gate),
+            block_list_program: None,
+        };
+        let mirror = ListMirror::open(&temporary_db(), Pubkey::new_unique(), gates).unwrap();
+
+        // Owned by the gate but not a record (a config, say)
+        let config = AccountUpdate {
+            address: Pubkey::new_unique(),
+            slot: 1,
+            owner: gate,
+            data: vec![0; 65],
+        };
+        assert_eq!(mirror.apply(&config).unwrap(), MirrorChange::Ignored);
+        assert_eq!(mirror.slot().unwrap(), 0);
+        assert!(mirror.allow_list_records().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,423 @@
+//! Webhook notifications for gate decisions and freezes
+//!
+//! Compliance teams want to hear about a freeze or a denied thaw when it
+//! happens, not at the next dashboard refresh. [`WebhookNotifier`] POSTs
+//! every [`GateEvent`] as a JSON [`Notification`] to each configured
+//! [`Webhook`]; a [`QueryService`](crate::query_service::QueryService) given
+//! its [`NotificationSender`] raises the events as its feeds report them.
+//!
+//! Every request carries two headers:
+//!
+//! - `X-Token-Acl-Timestamp`: unix seconds when the request was signed
+//! - `X-Token-Acl-Signature`: `sha256=` and the hex HMAC-SHA256 of
+//!   `"{timestamp}.{body}"` under the webhook's secret
+//!
+//! Receivers check both with [`verify_signature`] and should reject stale
+//! timestamps, so a captured request cannot be replayed later.
+//!
+//! Connection failures, timeouts, `429` and `5xx` responses are retried with
+//! exponential backoff ([`RetryPolicy`]); any other status is final. A retry
+//! resends the same notification `id`, so receivers can drop duplicates.
+//! Events are delivered one at a time in the order they were raised.
+
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use solana_sdk::{clock::Slot, pubkey::Pubkey};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::analytics::{DecisionEvent, GateOperation};
+
+pub const TIMESTAMP_HEADER: &str = "X-Token-Acl-Timestamp";
+pub const SIGNATURE_HEADER: &str = "X-Token-Acl-Signature";
+
+/// Delivery reports kept between flushes; older ones are dropped
+const MAX_PENDING_REPORTS: usize = 1024;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Something a compliance team wants to hear about
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GateEvent {
+    /// A token account that was thawed is now frozen
+    AccountFrozen {
+        mint: String,
+        token_account: String,
+        owner: String,
+        slot: Slot,
+    },
+    /// A gate refused a permissionless thaw
+    ThawDenied {
+        mint: String,
+        /// The holder, if the gate logged them
+        owner: Option<String>,
+        /// Normalized denial reason, as in the decision analytics
+        reason: String,
+    },
+}
+
+impl GateEvent {
+    pub fn account_frozen(
+        mint: &Pubkey,
+        token_account: &Pubkey,
+        owner: &Pubkey,
+        slot: Slot,
+    ) -> Self {
+        GateEvent::AccountFrozen {
+            mint: mint.to_string(),
+            token_account: token_account.to_string(),
+            owner: owner.to_string(),
+            slot,
+        }
+    }
+
+    /// The event a gate decision raises: only denied thaws do
+    pub fn from_decision(mint: &Pubkey, decision: &DecisionEvent) -> Option<Self> {
+        (decision.operation == GateOperation::Thaw && !decision.approved).then(|| {
+            GateEvent::ThawDenied {
+                mint: mint.to_string(),
+                owner: decision.user.clone(),
+                reason: decision
+                    .denial_reason
+                    .clone()
+                    .unwrap_or_else(|| "other".to_string()),
+            }
+        })
+    }
+}
+
+/// The body POSTed to a webhook
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Notification {
+    /// Stable across retries of the same event
+    pub id: String,
+    /// Unix seconds when the event was raised
+    pub created_at: i64,
+    pub event: GateEvent,
+}
+
+impl Notification {
+    pub fn new(event: GateEvent) -> Self {
+        let elapsed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut hasher = Sha256::new();
+        hasher.update(elapsed.as_nanos().to_le_bytes());
+        hasher.update(serde_json::to_vec(&event).unwrap_or_default());
+        Self {
+            id: hex::encode(&hasher.finalize()[..16]),
+            created_at: elapsed.as_secs() as i64,
+            event,
+        }
+    }
+}
+
+/// One receiver of notifications
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    pub url: String,
+    /// HMAC key shared with the receiver
+    pub secret: Vec<u8>,
+}
+
+impl Webhook {
+    pub fn new(url: impl Into<String>, secret: impl Into<Vec<u8>>) -> Self {
+        Self {
+            url: url.into(),
+            secret: secret.into(),
+        }
+    }
+}
+
+/// How hard to try a webhook that is not answering
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first
+    pub max_attempts: u32,
+    /// Wait before the first retry; doubled for each later one
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Per-request timeout
+    pub timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before attempt `attempt` (1-based); none before the first
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        if attempt <= 1 {
+            return Duration::ZERO;
+        }
+        let factor = 2u32.saturating_pow(attempt - 2);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// How a delivery ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeliveryOutcome {
+    /// A `2xx` response
+    Delivered(u16),
+    /// A status that retrying will not change
+    Rejected(u16),
+    /// Every attempt failed; the last failure
+    GaveUp(String),
+}
+
+/// The delivery of one notification to one webhook
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeliveryReport {
+    pub notification_id: String,
+    pub url: String,
+    pub attempts: u32,
+    pub outcome: DeliveryOutcome,
+}
+
+/// The signature header value of `body` signed at `timestamp`
+pub fn sign(secret: &[u8], timestamp: i64, body: &[u8]) -> String {
+    format!(
+        "sha256={}",
+        hex::encode(mac(secret, timestamp, body).finalize().into_bytes())
+    )
+}
+
+/// Whether `signature` is `body` signed at `timestamp` under `secret`
+///
+/// Compares in constant time.
+pub fn verify_signature(secret: &[u8], timestamp: i64, body: &[u8], signature: &str) -> bool {
+    let Some(digest) = signature
+        .strip_prefix("sha256=")
+        .and_then(|digest| hex::decode(digest).ok())
+    else {
+        return false;
+    };
+    mac(secret, timestamp, body).verify_slice(&digest).is_ok()
+}
+
+fn mac(secret: &[u8], timestamp: i64, body: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC takes keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+/// POSTs notifications to webhooks, signed and retried
+#[derive(Debug, Clone)]
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    webhooks: Vec<Webhook>,
+    retry: RetryPolicy,
+}
+
+impl WebhookNotifier {
+    pub fn new(webhooks: Vec<Webhook>, retry: RetryPolicy) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            webhooks,
+            retry,
+        }
+    }
+
+    /// Deliver `notification` to every webhook, retrying each as needed
+    pub async fn deliver(&self, notification: &Notification) -> Vec<DeliveryReport> {
+        let body = serde_json::to_vec(notification).expect("notifications serialize");
+        let mut reports = Vec::with_capacity(self.webhooks.len());
+        for webhook in &self.webhooks {
+            reports.push(self.deliver_to(webhook, &notification.id, &body).await);
+        }
+        reports
+    }
+
+    async fn deliver_to(&self, webhook: &Webhook, id: &str, body: &[u8]) -> DeliveryReport {
+        let mut attempts = 0;
+        let outcome = loop {
+            attempts += 1;
+            tokio::time::sleep(self.retry.backoff(attempts)).await;
+
+            // Signed afresh on every attempt, so a retry is not stale
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs() as i64);
+            let response = self
+                .client
+                .post(&webhook.url)
+                .timeout(self.retry.timeout)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(TIMESTAMP_HEADER, timestamp.to_string())
+                .header(SIGNATURE_HEADER, sign(&webhook.secret, timestamp, body))
+                .body(body.to_vec())
+                .send()
+                .await;
+
+            let failure = match response {
+                Ok(response) => {
+                    let status = response.status();
+                    if status.is_success() {
+                        break DeliveryOutcome::Delivered(status.as_u16());
+                    }
+                    if !(status.is_server_error() || status.as_u16() == 429) {
+                        break DeliveryOutcome::Rejected(status.as_u16());
+                    }
+                    format!("HTTP {}", status.as_u16())
+                }
+                Err(e) => e.to_string(),
+            };
+            if attempts >= self.retry.max_attempts {
+                break DeliveryOutcome::GaveUp(failure);
+            }
+        };
+        DeliveryReport {
+            notification_id: id.to_string(),
+            url: webhook.url.clone(),
+            attempts,
+            outcome,
+        }
+    }
+
+    /// Deliver in the background, in the order events are sent
+    ///
+    /// The task ends once every sender is dropped.
+    pub fn spawn(self) -> NotificationSender {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<Message>();
+        tokio::spawn(async move {
+            let mut reports = VecDeque::new();
+            while let Some(message) = receiver.recv().await {
+                match message {
+                    Message::Event(event) => {
+                        for report in self.deliver(&Notification::new(event)).await {
+                            if reports.len() == MAX_PENDING_REPORTS {
+                                reports.pop_front();
+                            }
+                            reports.push_back(report);
+                        }
+                    }
+                    Message::Flush(reply) => {
+                        reply.send(reports.drain(..).collect()).ok();
+                    }
+                }
+            }
+        });
+        NotificationSender { sender }
+    }
+}
+
+enum Message {
+    Event(GateEvent),
+    Flush(oneshot::Sender<Vec<DeliveryReport>>),
+}
+
+impl std::fmt::Debug for Message {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Message::Event(event) => f.debug_tuple("Event").field(event).finish(),
+            Message::Flush(_) => f.write_str("Flush"),
+        }
+    }
+}
+
+/// Queues events for a spawned [`WebhookNotifier`]
+#[derive(Debug, Clone)]
+pub struct NotificationSender {
+    sender: mpsc::UnboundedSender<Message>,
+}
+
+impl NotificationSender {
+    /// Queue `event`; false if the notifier has stopped
+    pub fn send(&self, event: GateEvent) -> bool {
+        self.sender.send(Message::Event(event)).is_ok()
+    }
+
+    /// Wait for everything queued so far to be delivered, and take the
+    /// reports of deliveries since the last flush
+    pub async fn flush(&self) -> Vec<DeliveryReport> {
+        let (reply, reports) = oneshot::channel();
+        if self.sender.send(Message::Flush(reply)).is_err() {
+            return Vec::new();
+        }
+        reports.await.unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_round_trip() {
+        let body = br#"{"id":"1"}"#;
+        let signature = sign(b"secret", 1_700_000_000, body);
+
+        assert!(signature.starts_with("sha256="));
+        assert!(verify_signature(b"secret", 1_700_000_000, body, &signature));
+        assert!(!verify_signature(b"other", 1_700_000_000, body, &signature));
+        assert!(!verify_signature(
+            b"secret",
+            1_700_000_001,
+            body,
+            &signature
+        ));
+        assert!(!verify_signature(
+            b"secret",
+            1_700_000_000,
+            b"{}",
+            &signature
+        ));
+        assert!(!verify_signature(b"secret", 1_700_000_000, body, "md5=00"));
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_cap() {
+        let retry = RetryPolicy {
+            max_attempts: 6,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(350),
+            timeout: Duration::from_secs(1),
+        };
+
+        let waits: Vec<_> = (1..=5).map(|attempt| retry.backoff(attempt)).collect();
+        assert_eq!(
+            waits,
+            [0, 100, 200, 350, 350].map(Duration::from_millis).to_vec()
+        );
+    }
+
+    #[test]
+    fn test_only_denied_thaws_raise_events() {
+        let mint = Pubkey::new_unique();
+        let decision = |operation, approved| DecisionEvent {
+            operation,
+            user: Some("holder".to_string()),
+            tier: None,
+            approved,
+            denial_reason: (!approved).then(|| "blocked".to_string()),
+        };
+
+        assert_eq!(
+            GateEvent::from_decision(&mint, &decision(GateOperation::Thaw, false)),
+            Some(GateEvent::ThawDenied {
+                mint: mint.to_string(),
+                owner: Some("holder".to_string()),
+                reason: "blocked".to_string(),
+            })
+        );
+        assert!(GateEvent::from_decision(&mint, &decision(GateOperation::Thaw, true)).is_none());
+        assert!(GateEvent::from_decision(&mint, &decision(GateOperation::Freeze, false)).is_none());
+    }
+}
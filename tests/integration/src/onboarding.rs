@@ -0,0 +1,176 @@
+//! Composed onboarding transactions
+//!
+//! The smoothest onboarding for a permissioned token is one transaction that
+//! creates the holder's associated token account and immediately thaws it
+//! through the gate. Because it is a single transaction, a gate denial rolls
+//! back the account creation as well: the holder either ends up with a
+//! usable account or with nothing.
+//!
+//! [`CreateAndThawBuilder`] composes that transaction:
+//! 1. an optional compute unit limit,
+//! 2. an idempotent associated token account creation,
+//! 3. the gate's sRFC 37 thaw check with the holder as caller,
+//! 4. any follow-up instructions, typically the Token ACL permissionless thaw.
+//!
+//! [`CreateAndThawBuilder::build_simulated`] sizes the limit by simulating
+//! the composition first, see [`crate::simulation`].
+
+use crate::{
+    fixtures::test_data,
+    simulation::{self, SimulatedBudget, TransactionSimulator},
+};
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    packet::PACKET_DATA_SIZE,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::Transaction,
+};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account_idempotent,
+};
+
+/// Builds the single create-and-thaw onboarding transaction
+#[derive(Debug, Clone)]
+pub struct CreateAndThawBuilder {
+    payer: Pubkey,
+    owner: Pubkey,
+    mint: Pubkey,
+    token_program: Pubkey,
+    gate_program: Pubkey,
+    extra_accounts: Vec<AccountMeta>,
+    follow_up: Vec<Instruction>,
+    compute_unit_limit: Option<u32>,
+}
+
+impl CreateAndThawBuilder {
+    /// Onboard `owner` to a Token-2022 `mint` gated by `gate_program`,
+    /// with `payer` covering fees and rent
+    pub fn new(payer: Pubkey, owner: Pubkey, mint: Pubkey, gate_program: Pubkey) -> Self {
+        Self {
+            payer,
+            owner,
+            mint,
+            token_program: spl_token_2022::id(),
+            gate_program,
+            extra_accounts: Vec::new(),
+            follow_up: Vec::new(),
+            compute_unit_limit: None,
+        }
+    }
+
+    /// Use a different token program (e.g. legacy SPL Token)
+    pub fn token_program(mut self, token_program: Pubkey) -> Self {
+        self.token_program = token_program;
+        self
+    }
+
+    /// Append an extra account resolved from the gate's extra-account-metas
+    pub fn extra_account(mut self, meta: AccountMeta) -> Self {
+        self.extra_accounts.push(meta);
+        self
+    }
+
+    /// Append an instruction that runs after the gate check
+    pub fn then(mut self, instruction: Instruction) -> Self {
+        self.follow_up.push(instruction);
+        self
+    }
+
+    /// Request a compute unit limit for the whole transaction
+    pub fn compute_unit_limit(mut self, units: u32) -> Self {
+        self.compute_unit_limit = Some(units);
+        self
+    }
+
+    /// Address of the associated token account being created
+    pub fn associated_token_address(&self) -> Pubkey {
+        get_associated_token_address_with_program_id(&self.owner, &self.mint, &self.token_program)
+    }
+
+    /// The gate's thaw check for the new account
+    pub fn thaw_check_instruction(&self) -> Instruction {
+        let (extra_metas, _) = Pubkey::find_program_address(
+            &[test_data::THAW_EXTRA_ACCOUNT_METAS_SEED, self.mint.as_ref()],
+            &self.gate_program,
+        );
+        let mut accounts = vec![
+            AccountMeta::new_readonly(self.owner, true),
+            AccountMeta::new_readonly(self.associated_token_address(), false),
+            AccountMeta::new_readonly(self.mint, false),
+            AccountMeta::new_readonly(extra_metas, false),
+        ];
+        accounts.extend(self.extra_accounts.iter().cloned());
+        Instruction::new_with_bytes(self.gate_program, &test_data::THAW_DISCRIMINATOR, accounts)
+    }
+
+    /// All instructions of the composed transaction, in order
+    pub fn instructions(&self) -> Vec<Instruction> {
+        let mut instructions = Vec::new();
+        if let Some(units) = self.compute_unit_limit {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(units));
+        }
+        instructions.push(create_associated_token_account_idempotent(
+            &self.payer,
+            &self.owner,
+            &self.mint,
+            &self.token_program,
+        ));
+        instructions.push(self.thaw_check_instruction());
+        instructions.extend(self.follow_up.iter().cloned());
+        instructions
+    }
+
+    /// Build the unsigned transaction, refusing compositions that exceed the
+    /// packet size limit
+    pub fn build(&self, recent_blockhash: Hash) -> Result<Transaction, String> {
+        let message =
+            Message::new_with_blockhash(&self.instructions(), Some(&self.payer), &recent_blockhash);
+        let transaction = Transaction::new_unsigned(message);
+
+        let size = signed_size(&transaction);
+        if size > PACKET_DATA_SIZE {
+            return Err(format!(
+                "Onboarding transaction is {} bytes, above the {} byte limit",
+                size, PACKET_DATA_SIZE
+            ));
+        }
+        Ok(transaction)
+    }
+
+    /// Simulate the composition, then build it with a compute unit limit
+    /// sized from the units it consumed, in place of any limit requested
+    /// with [`Self::compute_unit_limit`]
+    ///
+    /// Fails without building if the simulation fails, e.g. because the gate
+    /// denies the holder.
+    pub async fn build_simulated<S: TransactionSimulator>(
+        &self,
+        simulator: &S,
+        recent_blockhash: Hash,
+    ) -> Result<(Transaction, SimulatedBudget), String> {
+        let budget = simulation::simulate_budget(
+            simulator,
+            &self.instructions(),
+            &self.payer,
+            recent_blockhash,
+        )
+        .await?;
+        let transaction = self
+            .clone()
+            .compute_unit_limit(budget.compute_unit_limit)
+            .build(recent_blockhash)?;
+        Ok((transaction, budget))
+    }
+}
+
+/// Serialized size of `transaction` once every required signature is present
+pub fn signed_size(transaction: &Transaction) -> usize {
+    let signatures = transaction.message.header.num_required_signatures as usize;
+    // Compact-u16 signature count (one byte below 128), then the signatures
+    1 + signatures * std::mem::size_of::<Signature>() + transaction.message.serialize().len()
+}
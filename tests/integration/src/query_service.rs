@@ -0,0 +1,689 @@
+//! HTTP query service for compliance dashboards
+//!
+//! A compliance dashboard asks three things of each mint: who is on its
+//! lists, how its gates have been deciding, and which of its token accounts
+//! are frozen. [`QueryService`] answers all three as read-only JSON over
+//! HTTP, from a [`ListMirror`] per mint, the [`DecisionAnalytics`] fed from
+//! that mint's gate logs, and a [`FreezeIndex`] of its token accounts.
+//! Nothing is read from RPC per request; whatever keeps the mirror current
+//! (`ListMirror::follow`, a `logsSubscribe` feed, a token account
+//! subscription) keeps the service current. Given a
+//! [`NotificationSender`], it also raises webhook alerts as the feeds report
+//! freezes and denied thaws (see [`crate::notifier`]).
+//!
+//! Built with the `query-service` feature, which is on by default.
+//!
+//! | Route | Response |
+//! |-------|----------|
+//! | `GET /mints` | [`MintOverview`] of every served mint |
+//! | `GET /mints/{mint}/allow-list` | [`AllowListEntry`]s; `?active=true` keeps active ones |
+//! | `GET /mints/{mint}/block-list` | [`BlockListEntry`]s; `?blocked=true` keeps blocked ones, `?active=true` blocks in force |
+//! | `GET /mints/{mint}/members/{owner}` | [`Membership`] of one owner |
+//! | `GET /mints/{mint}/analytics` | [`DecisionSummary`] of the mint's gate decisions |
+//! | `GET /mints/{mint}/freeze-states` | [`FreezeStates`]; `?frozen=true\|false` filters |
+//!
+//! Allow list and block list activity is judged at `?at=<unix timestamp>`,
+//! defaulting to the service's clock. A malformed public key is a 400 and a mint the
+//! service does not serve a 404, both with an [`ErrorBody`].
+
+use std::collections::BTreeMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use gate_view::{AllowListView, BlockListView};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{clock::Slot, program_pack::Pack, pubkey::Pubkey};
+use spl_token_2022::{
+    extension::StateWithExtensions,
+    state::{Account as TokenAccount, AccountState},
+};
+use tokio::{sync::oneshot, task::JoinHandle};
+
+use crate::analytics::{DecisionAnalytics, DecisionEvent, DecisionSummary, GateOperation};
+use crate::mirror::{AccountUpdate, ListMirror, MirrorError};
+use crate::notifier::{GateEvent, NotificationSender};
+
+/// Freeze state of one token account, as of the slot it was last seen at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenAccountState {
+    pub owner: Pubkey,
+    pub frozen: bool,
+    pub slot: Slot,
+}
+
+/// Freeze states of one mint's Token-2022 accounts
+///
+/// Fed the same [`AccountUpdate`]s as a [`ListMirror`], with the same
+/// ordering rule: an update older than what the index holds for its address
+/// is dropped, and an address that stopped being one of the mint's token
+/// accounts is remembered as gone at its slot.
+#[derive(Debug, Clone)]
+pub struct FreezeIndex {
+    mint: Pubkey,
+    accounts: BTreeMap<Pubkey, TokenAccountState>,
+    removed: BTreeMap<Pubkey, Slot>,
+}
+
+impl FreezeIndex {
+    pub fn new(mint: Pubkey) -> Self {
+        Self {
+            mint,
+            accounts: BTreeMap::new(),
+            removed: BTreeMap::new(),
+        }
+    }
+
+    pub fn mint(&self) -> Pubkey {
+        self.mint
+    }
+
+    /// Apply one update; whether the index changed
+    pub fn apply(&mut self, update: &AccountUpdate) -> bool {
+        let last_slot = self
+            .accounts
+            .get(&update.address)
+            .map(|state| state.slot)
+            .or_else(|| self.removed.get(&update.address).copied());
+        if last_slot.is_some_and(|slot| update.slot < slot) {
+            return false;
+        }
+
+        match self.decode(update) {
+            Some(state) => {
+                self.removed.remove(&update.address);
+                self.accounts.insert(update.address, state) != Some(state)
+            }
+            None => {
+                let known = self.accounts.remove(&update.address).is_some();
+                if known || self.removed.contains_key(&update.address) {
+                    self.removed.insert(update.address, update.slot);
+                }
+                known
+            }
+        }
+    }
+
+    /// The state of the token account at `address`
+    pub fn get(&self, address: &Pubkey) -> Option<&TokenAccountState> {
+        self.accounts.get(address)
+    }
+
+    /// Every token account of the mint, by address
+    pub fn accounts(&self) -> impl Iterator<Item = (&Pubkey, &TokenAccountState)> {
+        self.accounts.iter()
+    }
+
+    /// Number of frozen token accounts
+    pub fn frozen_count(&self) -> usize {
+        self.accounts.values().filter(|state| state.frozen).count()
+    }
+
+    /// Whether `update` holds a token account, of any mint
+    fn is_token_account(update: &AccountUpdate) -> bool {
+        update.owner == spl_token_2022::id() && update.data.len() >= TokenAccount::LEN
+    }
+
+    fn decode(&self, update: &AccountUpdate) -> Option<TokenAccountState> {
+        if !Self::is_token_account(update) {
+            return None;
+        }
+        let account = StateWithExtensions::<TokenAccount>::unpack(&update.data).ok()?;
+        (account.base.mint == self.mint && account.base.state != AccountState::Uninitialized)
+            .then_some(TokenAccountState {
+                owner: account.base.owner,
+                frozen: account.base.state == AccountState::Frozen,
+                slot: update.slot,
+            })
+    }
+}
+
+/// One allow list record
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AllowListEntry {
+    pub owner: String,
+    pub allowed: bool,
+    pub access_level: u8,
+    pub added_timestamp: i64,
+    pub expiry_timestamp: Option<i64>,
+    pub revoked: bool,
+    /// Whether the record lets its owner through at the queried time
+    pub active: bool,
+}
+
+impl AllowListEntry {
+    fn new(view: &AllowListView, now: i64) -> Self {
+        Self {
+            owner: view.owner.to_string(),
+            allowed: view.allowed,
+            access_level: view.access_level,
+            added_timestamp: view.added_timestamp,
+            expiry_timestamp: view.expiry_timestamp,
+            revoked: view.revoked,
+            active: view.is_active(now),
+        }
+    }
+}
+
+/// One block list record
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockListEntry {
+    pub owner: String,
+    pub blocked: bool,
+    pub reason: u8,
+    pub added_timestamp: i64,
+    pub expiry_timestamp: Option<i64>,
+    /// Whether the block is in force at the queried time
+    pub active: bool,
+}
+
+impl BlockListEntry {
+    fn new(view: &BlockListView, now: i64) -> Self {
+        Self {
+            owner: view.owner.to_string(),
+            blocked: view.blocked,
+            reason: view.reason,
+            added_timestamp: view.added_timestamp,
+            expiry_timestamp: view.expiry_timestamp,
+            active: view.is_active(now),
+        }
+    }
+}
+
+/// Both records of one owner
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Membership {
+    pub mint: String,
+    pub owner: String,
+    pub allow_listed: bool,
+    pub blocked: bool,
+    pub allow_list: Option<AllowListEntry>,
+    pub block_list: Option<BlockListEntry>,
+}
+
+/// One token account's freeze state
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FreezeStateEntry {
+    pub address: String,
+    pub owner: String,
+    pub frozen: bool,
+    pub slot: Slot,
+}
+
+/// The token accounts of a mint, with totals over all of them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FreezeStates {
+    pub mint: String,
+    pub token_accounts: usize,
+    pub frozen: usize,
+    pub accounts: Vec<FreezeStateEntry>,
+}
+
+/// Headline numbers of one served mint
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MintOverview {
+    pub mint: String,
+    /// Highest slot the mirror has seen
+    pub slot: Slot,
+    pub allow_listed: usize,
+    pub blocked: usize,
+    pub token_accounts: usize,
+    pub frozen: usize,
+    pub decisions: usize,
+}
+
+/// Body of every error response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorBody {
+    pub error: String,
+}
+
+/// Why a query failed
+#[derive(Debug)]
+pub enum QueryError {
+    InvalidPubkey(String),
+    UnknownMint(Pubkey),
+    Mirror(MirrorError),
+}
+
+impl From<MirrorError> for QueryError {
+    fn from(error: MirrorError) -> Self {
+        QueryError::Mirror(error)
+    }
+}
+
+impl IntoResponse for QueryError {
+    fn into_response(self) -> Response {
+        let (status, error) = match self {
+            QueryError::InvalidPubkey(key) => (
+                StatusCode::BAD_REQUEST,
+                format!("invalid public key: {}", key),
+            ),
+            QueryError::UnknownMint(mint) => {
+                (StatusCode::NOT_FOUND, format!("mint not served: {}", mint))
+            }
+            QueryError::Mirror(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        };
+        (status, Json(ErrorBody { error })).into_response()
+    }
+}
+
+/// Everything the service holds for one mint
+struct MintState {
+    mirror: ListMirror,
+    analytics: DecisionAnalytics,
+    freeze_index: FreezeIndex,
+}
+
+/// Per-mint list membership, decision analytics and freeze states, served
+/// over HTTP
+///
+/// Cloning is cheap and every clone shares the same state, so the feeds can
+/// keep one while the server holds another.
+#[derive(Clone, Default)]
+pub struct QueryService {
+    mints: Arc<RwLock<BTreeMap<Pubkey, MintState>>>,
+    notifier: Arc<RwLock<Option<NotificationSender>>>,
+}
+
+impl QueryService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve the mint `mirror` holds, replacing anything served for it
+    pub fn serve_mint(&self, mirror: ListMirror) {
+        let mint = mirror.mint();
+        self.mints.write().unwrap().insert(
+            mint,
+            MintState {
+                mirror,
+                analytics: DecisionAnalytics::new(),
+                freeze_index: FreezeIndex::new(mint),
+            },
+        );
+    }
+
+    /// Raise a [`GateEvent`] through `notifier` for every denied thaw
+    /// recorded and every thawed token account seen frozen from now on
+    pub fn notify(&self, notifier: NotificationSender) {
+        *self.notifier.write().unwrap() = Some(notifier);
+    }
+
+    /// The mints being served
+    pub fn mints(&self) -> Vec<Pubkey> {
+        self.mints.read().unwrap().keys().copied().collect()
+    }
+
+    /// Record a gate decision about `mint`; false if the mint is not served
+    pub fn record_decision(&self, mint: &Pubkey, event: DecisionEvent) -> bool {
+        let alert = {
+            let mut mints = self.mints.write().unwrap();
+            let Some(state) = mints.get_mut(mint) else {
+                return false;
+            };
+            let alert = GateEvent::from_decision(mint, &event);
+            state.analytics.record(event);
+            alert
+        };
+        if let Some(alert) = alert {
+            self.raise(alert);
+        }
+        true
+    }
+
+    /// Record the decision in one gate invocation's logs
+    pub fn ingest_logs(
+        &self,
+        mint: &Pubkey,
+        operation: GateOperation,
+        logs: &[String],
+        succeeded: bool,
+    ) -> bool {
+        self.record_decision(mint, DecisionEvent::from_logs(operation, logs, succeeded))
+    }
+
+    /// Apply an account update to the freeze states of every served mint;
+    /// whether any changed
+    ///
+    /// A token account only ever belongs to one mint, but a closed one no
+    /// longer says which, so the update goes to every index.
+    pub fn apply_token_account(&self, update: &AccountUpdate) -> bool {
+        let mut alerts = Vec::new();
+        let mut changed = false;
+        for (mint, state) in self.mints.write().unwrap().iter_mut() {
+            let index = &mut state.freeze_index;
+            let was_thawed = index
+                .get(&update.address)
+                .is_some_and(|state| !state.frozen);
+            changed |= index.apply(update);
+            // A new account frozen by default is not news; a thawed one
+            // being frozen is
+            if let Some(now) = index.get(&update.address).filter(|state| state.frozen) {
+                if was_thawed {
+                    alerts.push(GateEvent::account_frozen(
+                        mint,
+                        &update.address,
+                        &now.owner,
+                        now.slot,
+                    ));
+                }
+            }
+        }
+        for alert in alerts {
+            self.raise(alert);
+        }
+        changed
+    }
+
+    fn raise(&self, event: GateEvent) {
+        if let Some(notifier) = self.notifier.read().unwrap().as_ref() {
+            notifier.send(event);
+        }
+    }
+
+    /// The routes of the service, over this service's state
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/mints", get(list_mints))
+            .route("/mints/:mint/allow-list", get(allow_list))
+            .route("/mints/:mint/block-list", get(block_list))
+            .route("/mints/:mint/members/:owner", get(membership))
+            .route("/mints/:mint/analytics", get(analytics))
+            .route("/mints/:mint/freeze-states", get(freeze_states))
+            .with_state(self.clone())
+    }
+
+    /// Serve on `addr` until the returned handle is shut down
+    ///
+    /// Port 0 picks a free port; [`RunningQueryService::local_addr`] tells
+    /// which.
+    pub fn spawn(&self, addr: SocketAddr) -> io::Result<RunningQueryService> {
+        let listener = std::net::TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let server = axum::Server::from_tcp(listener)
+            .map_err(io::Error::other)?
+            .serve(self.router().into_make_service());
+        let (shutdown, signal) = oneshot::channel::<()>();
+        let task = tokio::spawn(async move {
+            server
+                .with_graceful_shutdown(async {
+                    signal.await.ok();
+                })
+                .await
+                .map_err(io::Error::other)
+        });
+        Ok(RunningQueryService {
+            local_addr,
+            shutdown,
+            task,
+        })
+    }
+
+    /// Run `query` on the state of `mint`
+    fn with_mint<T>(
+        &self,
+        mint: &str,
+        query: impl FnOnce(&MintState) -> Result<T, QueryError>,
+    ) -> Result<Json<T>, QueryError> {
+        let mint = parse_pubkey(mint)?;
+        let mints = self.mints.read().unwrap();
+        let state = mints.get(&mint).ok_or(QueryError::UnknownMint(mint))?;
+        query(state).map(Json)
+    }
+}
+
+/// A query service serving in the background
+pub struct RunningQueryService {
+    local_addr: SocketAddr,
+    shutdown: oneshot::Sender<()>,
+    task: JoinHandle<io::Result<()>>,
+}
+
+impl RunningQueryService {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// `http://<addr>`, for building request URLs
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.local_addr)
+    }
+
+    /// Stop accepting connections and wait for in-flight requests
+    pub async fn shutdown(self) -> io::Result<()> {
+        self.shutdown.send(()).ok();
+        self.task.await.map_err(io::Error::other)?
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ListParams {
+    at: Option<i64>,
+    active: Option<bool>,
+    blocked: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct FreezeParams {
+    frozen: Option<bool>,
+}
+
+fn parse_pubkey(key: &str) -> Result<Pubkey, QueryError> {
+    key.parse()
+        .map_err(|_| QueryError::InvalidPubkey(key.to_string()))
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64)
+}
+
+async fn list_mints(
+    State(service): State<QueryService>,
+) -> Result<Json<Vec<MintOverview>>, QueryError> {
+    let now = now();
+    let mints = service.mints.read().unwrap();
+    let overviews = mints
+        .iter()
+        .map(|(mint, state)| {
+            Ok(MintOverview {
+                mint: mint.to_string(),
+                slot: state.mirror.slot()?,
+                allow_listed: state.mirror.allow_listed(now)?.len(),
+                blocked: state.mirror.blocked(now)?.len(),
+                token_accounts: state.freeze_index.accounts.len(),
+                frozen: state.freeze_index.frozen_count(),
+                decisions: state.analytics.events().len(),
+            })
+        })
+        .collect::<Result<_, QueryError>>()?;
+    Ok(Json(overviews))
+}
+
+async fn allow_list(
+    State(service): State<QueryService>,
+    Path(mint): Path<String>,
+    Query(params): Query<ListParams>,
+) -> Result<Json<Vec<AllowListEntry>>, QueryError> {
+    let now = params.at.unwrap_or_else(now);
+    service.with_mint(&mint, |state| {
+        Ok(state
+            .mirror
+            .allow_list_records()?
+            .iter()
+            .map(|view| AllowListEntry::new(view, now))
+            .filter(|entry| params.active.is_none_or(|active| entry.active == active))
+            .collect())
+    })
+}
+
+async fn block_list(
+    State(service): State<QueryService>,
+    Path(mint): Path<String>,
+    Query(params): Query<ListParams>,
+) -> Result<Json<Vec<BlockListEntry>>, QueryError> {
+    let now = params.at.unwrap_or_else(now);
+    service.with_mint(&mint, |state| {
+        Ok(state
+            .mirror
+            .block_list_records()?
+            .iter()
+            .map(|view| BlockListEntry::new(view, now))
+            .filter(|entry| {
+                params
+                    .blocked
+                    .is_none_or(|blocked| entry.blocked == blocked)
+                    && params.active.is_none_or(|active| entry.active == active)
+            })
+            .collect())
+    })
+}
+
+async fn membership(
+    State(service): State<QueryService>,
+    Path((mint, owner)): Path<(String, String)>,
+    Query(params): Query<ListParams>,
+) -> Result<Json<Membership>, QueryError> {
+    let owner = parse_pubkey(&owner)?;
+    let now = params.at.unwrap_or_else(now);
+    service.with_mint(&mint, |state| {
+        let allow_list = state
+            .mirror
+            .allow_list_record(&owner)?
+            .map(|view| AllowListEntry::new(&view, now));
+        let block_list = state
+            .mirror
+            .block_list_record(&owner)?
+            .map(|view| BlockListEntry::new(&view, now));
+        Ok(Membership {
+            mint: state.mirror.mint().to_string(),
+            owner: owner.to_string(),
+            allow_listed: allow_list.as_ref().is_some_and(|entry| entry.active),
+            blocked: block_list.as_ref().is_some_and(|entry| entry.active),
+            allow_list,
+            block_list,
+        })
+    })
+}
+
+async fn analytics(
+    State(service): State<QueryService>,
+    Path(mint): Path<String>,
+) -> Result<Json<DecisionSummary>, QueryError> {
+    service.with_mint(&mint, |state| Ok(state.analytics.summary()))
+}
+
+async fn freeze_states(
+    State(service): State<QueryService>,
+    Path(mint): Path<String>,
+    Query(params): Query<FreezeParams>,
+) -> Result<Json<FreezeStates>, QueryError> {
+    service.with_mint(&mint, |state| {
+        let index = &state.freeze_index;
+        Ok(FreezeStates {
+            mint: index.mint().to_string(),
+            token_accounts: index.accounts.len(),
+            frozen: index.frozen_count(),
+            accounts: index
+                .accounts()
+                .filter(|(_, account)| params.frozen.is_none_or(|frozen| account.frozen == frozen))
+                .map(|(address, account)| FreezeStateEntry {
+                    address: address.to_string(),
+                    owner: account.owner.to_string(),
+                    frozen: account.frozen,
+                    slot: account.slot,
+                })
+                .collect(),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::program_option::COption;
+
+    fn token_account_update(
+        address: Pubkey,
+        slot: Slot,
+        mint: Pubkey,
+        state: AccountState,
+    ) -> AccountUpdate {
+        let mut data = vec![0; TokenAccount::LEN];
+        TokenAccount::pack(
+            TokenAccount {
+                mint,
+                owner: Pubkey::new_unique(),
+                amount: 1,
+                delegate: COption::None,
+                state,
+                is_native: COption::None,
+                delegated_amount: 0,
+                close_authority: COption::None,
+            },
+            &mut data,
+        )
+        .unwrap();
+        AccountUpdate {
+            address,
+            slot,
+            owner: spl_token_2022::id(),
+            data,
+        }
+    }
+
+    #[test]
+    fn test_freeze_index_orders_updates_by_slot() {
+        let mint = Pubkey::new_unique();
+        let address = Pubkey::new_unique();
+        let mut index = FreezeIndex::new(mint);
+
+        assert!(index.apply(&token_account_update(
+            address,
+            5,
+            mint,
+            AccountState::Frozen
+        )));
+        assert!(!index.apply(&token_account_update(
+            address,
+            4,
+            mint,
+            AccountState::Initialized
+        )));
+        assert!(index.get(&address).unwrap().frozen);
+
+        assert!(index.apply(&AccountUpdate::closed(address, 6)));
+        assert!(!index.apply(&token_account_update(
+            address,
+            5,
+            mint,
+            AccountState::Frozen
+        )));
+        assert!(index.get(&address).is_none());
+    }
+
+    #[test]
+    fn test_freeze_index_ignores_other_mints() {
+        let mint = Pubkey::new_unique();
+        let mut index = FreezeIndex::new(mint);
+        let other = token_account_update(
+            Pubkey::new_unique(),
+            1,
+            Pubkey::new_unique(),
+            AccountState::Frozen,
+        );
+
+        assert!(!index.apply(&other));
+        assert_eq!(index.accounts().count(), 0);
+    }
+}
@@ -0,0 +1,245 @@
+//! Rate-limited RPC client for live-cluster runs
+//!
+//! Public endpoints throttle aggressively (devnet allows roughly 100 requests
+//! per 10 seconds per IP), and a large scenario suite easily exceeds that
+//! mid-run. [`RateLimitedRpc`] wraps the nonblocking `RpcClient` with:
+//!
+//! - a token bucket, so requests wait for capacity instead of getting 429s,
+//! - request deduplication, so concurrent fetches of the same account share
+//!   one request,
+//! - a cache for data that does not change during a run: executable program
+//!   accounts and mint accounts (decimals, extensions, authorities).
+//!
+//! Only successful responses are shared or cached; a failed fetch is retried
+//! by the next caller and every caller sees its own error.
+
+use solana_client::{client_error::Result as ClientResult, nonblocking::rpc_client::RpcClient};
+use solana_sdk::{
+    account::Account, clock::Slot, hash::Hash, pubkey::Pubkey, signature::Signature,
+    transaction::Transaction,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::OnceCell;
+
+/// Public devnet endpoint
+pub const DEVNET_URL: &str = "https://api.devnet.solana.com";
+
+/// Request rate and burst allowance
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RpcLimits {
+    pub requests_per_second: f64,
+    pub burst: u32,
+}
+
+impl RpcLimits {
+    /// Stays under the public devnet limit with headroom for other tools
+    pub fn devnet() -> Self {
+        Self {
+            requests_per_second: 8.0,
+            burst: 10,
+        }
+    }
+}
+
+/// Classic token bucket: `burst` tokens, refilled continuously
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    refill_per_second: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// A full bucket as of `now`
+    pub fn new(limits: RpcLimits, now: Instant) -> Self {
+        let capacity = f64::from(limits.burst.max(1));
+        Self {
+            capacity,
+            refill_per_second: limits.requests_per_second,
+            tokens: capacity,
+            last_refill: now,
+        }
+    }
+
+    /// Take a token at `now`, or return how long to wait for one
+    pub fn try_acquire(&mut self, now: Instant) -> Result<(), Duration> {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_second).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            let missing = 1.0 - self.tokens;
+            Err(Duration::from_secs_f64(missing / self.refill_per_second))
+        }
+    }
+}
+
+/// What the wrapper did with the requests it was asked to make
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RpcStats {
+    /// Requests actually sent to the endpoint
+    pub requests: u64,
+    /// Account fetches answered from the cache
+    pub cache_hits: u64,
+    /// Account fetches that joined an identical in-flight request
+    pub deduplicated: u64,
+    /// Times a request had to wait for the token bucket
+    pub throttled: u64,
+}
+
+/// Nonblocking RPC client with rate limiting, deduplication, and caching
+pub struct RateLimitedRpc {
+    client: RpcClient,
+    bucket: Mutex<TokenBucket>,
+    in_flight: Mutex<HashMap<Pubkey, Arc<OnceCell<Account>>>>,
+    cache: Mutex<HashMap<Pubkey, Account>>,
+    stats: Mutex<RpcStats>,
+}
+
+impl RateLimitedRpc {
+    /// Connect to `url` with the given limits
+    pub fn new(url: &str, limits: RpcLimits) -> Self {
+        Self::from_client(RpcClient::new(url.to_string()), limits)
+    }
+
+    /// Public devnet with [`RpcLimits::devnet`]
+    pub fn devnet() -> Self {
+        Self::new(DEVNET_URL, RpcLimits::devnet())
+    }
+
+    /// Wrap an existing client, e.g. a mock
+    pub fn from_client(client: RpcClient, limits: RpcLimits) -> Self {
+        Self {
+            client,
+            bucket: Mutex::new(TokenBucket::new(limits, Instant::now())),
+            in_flight: Mutex::new(HashMap::new()),
+            cache: Mutex::new(HashMap::new()),
+            stats: Mutex::new(RpcStats::default()),
+        }
+    }
+
+    /// The underlying client, for calls the wrapper does not cover
+    ///
+    /// Requests made through it bypass the rate limit.
+    pub fn inner(&self) -> &RpcClient {
+        &self.client
+    }
+
+    pub fn stats(&self) -> RpcStats {
+        *self.stats.lock().unwrap()
+    }
+
+    /// Fetch an account; executable program accounts are cached
+    pub async fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        self.fetch_account(pubkey, false).await
+    }
+
+    /// Fetch a mint account and cache it for the rest of the run
+    ///
+    /// Supply is stale after the first fetch; use [`Self::get_account`] when
+    /// it matters.
+    pub async fn get_mint_account(&self, mint: &Pubkey) -> ClientResult<Account> {
+        self.fetch_account(mint, true).await
+    }
+
+    /// Drop a cached account, e.g. after upgrading a program
+    pub fn invalidate(&self, pubkey: &Pubkey) {
+        self.cache.lock().unwrap().remove(pubkey);
+    }
+
+    pub async fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        self.throttle().await;
+        self.client.get_latest_blockhash().await
+    }
+
+    /// Send without waiting for confirmation; never deduplicated
+    pub async fn send_transaction(&self, transaction: &Transaction) -> ClientResult<Signature> {
+        self.throttle().await;
+        self.client.send_transaction(transaction).await
+    }
+
+    /// Slot a transaction was processed in, or `None` if the cluster has not
+    /// seen it (yet)
+    pub async fn get_signature_slot(&self, signature: &Signature) -> ClientResult<Option<Slot>> {
+        self.throttle().await;
+        let statuses = self.client.get_signature_statuses(&[*signature]).await?;
+        Ok(statuses
+            .value
+            .into_iter()
+            .next()
+            .flatten()
+            .map(|status| status.slot))
+    }
+
+    async fn fetch_account(&self, pubkey: &Pubkey, always_cache: bool) -> ClientResult<Account> {
+        if let Some(account) = self.cache.lock().unwrap().get(pubkey) {
+            self.stats.lock().unwrap().cache_hits += 1;
+            return Ok(account.clone());
+        }
+
+        let cell = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            match in_flight.get(pubkey) {
+                Some(cell) => {
+                    self.stats.lock().unwrap().deduplicated += 1;
+                    cell.clone()
+                }
+                None => {
+                    let cell = Arc::new(OnceCell::new());
+                    in_flight.insert(*pubkey, cell.clone());
+                    cell
+                }
+            }
+        };
+
+        let result = cell
+            .get_or_try_init(|| async {
+                self.throttle().await;
+                self.client.get_account(pubkey).await
+            })
+            .await
+            .cloned();
+
+        {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            if in_flight
+                .get(pubkey)
+                .is_some_and(|current| Arc::ptr_eq(current, &cell))
+            {
+                in_flight.remove(pubkey);
+            }
+        }
+
+        if let Ok(account) = &result {
+            if always_cache || account.executable {
+                self.cache.lock().unwrap().insert(*pubkey, account.clone());
+            }
+        }
+        result
+    }
+
+    /// Wait for a token, then count the request
+    async fn throttle(&self) {
+        loop {
+            let wait = self.bucket.lock().unwrap().try_acquire(Instant::now());
+            match wait {
+                Ok(()) => break,
+                Err(wait) => {
+                    self.stats.lock().unwrap().throttled += 1;
+                    tokio::time::sleep(wait).await;
+                }
+            }
+        }
+        self.stats.lock().unwrap().requests += 1;
+    }
+}
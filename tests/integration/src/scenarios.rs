@@ -0,0 +1,780 @@
+//! Narrative scenarios shared by the suite and the test client
+//!
+//! The end-to-end stories the suite tells (the integration flow, the RWA
+//! investor workflow, one-transaction onboarding and the freeze-all
+//! emergency) are written once here, against a [`ScenarioCluster`]. The
+//! `scenarios` test runs them on a `solana-program-test` bank in CI, and the
+//! `test-client` binary runs the same code interactively against devnet or
+//! any other cluster where the production allow list is deployed.
+//!
+//! A scenario only sends real instructions, so it runs unchanged on a live
+//! cluster: it creates a Token-2022 mint whose new accounts start frozen,
+//! initializes the gate's config for it, and drives holders through the
+//! gate. The payer is the issuer: the mint and freeze authority and the
+//! config authority. As in [`crate::program_test`], a permissionless thaw is
+//! the gate's check followed, in the same transaction, by the thaw the FAMP
+//! would make on its approval, with the issuer standing in for the FAMP.
+//!
+//! Each scenario returns the steps it took and the checks it made, or fails
+//! at the first check that does not hold.
+
+use borsh::BorshSerialize;
+use production_allow_list::{
+    allow_list_address, config_address, AccessLevel, AddToAllowListArgs, InitMode, RemoveMode,
+    ADD_TO_ALLOW_LIST, INITIALIZE, REMOVE_FROM_ALLOW_LIST, REVOKE,
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_program_test::{processor, BanksClient, ProgramTest};
+use solana_sdk::{
+    account::{from_account, Account},
+    clock::Clock,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction, system_program, sysvar,
+    transaction::Transaction,
+};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account_idempotent,
+};
+use spl_token_2022::{
+    extension::{default_account_state, ExtensionType, StateWithExtensions},
+    instruction as token_instruction,
+    state::{Account as TokenAccount, AccountState, Mint},
+};
+use token_acl_interface::{
+    self as interface, find_missing_record_policy_address, find_thaw_extra_account_metas_address,
+};
+
+use crate::{context::TestContext, nonce, onboarding::CreateAndThawBuilder};
+
+/// Seconds in a day, for record expiries
+const DAY: i64 = 86_400;
+
+/// Holders of the emergency scenario's mint
+const EMERGENCY_HOLDERS: usize = 6;
+
+/// What a scenario sends transactions to and reads accounts from
+#[allow(async_fn_in_trait)]
+pub trait ScenarioCluster {
+    async fn latest_blockhash(&self) -> Result<Hash, String>;
+
+    /// Process `transaction` and wait for its outcome; a failed transaction
+    /// is an error naming the instruction error
+    async fn process(&self, transaction: Transaction) -> Result<(), String>;
+
+    /// The account at `address`, `None` if it does not exist
+    async fn account(&self, address: &Pubkey) -> Result<Option<Account>, String>;
+
+    /// Lamports an account of `len` bytes needs to be rent exempt
+    async fn minimum_balance(&self, len: usize) -> Result<u64, String>;
+
+    /// The cluster's clock, which record expiries are judged by
+    async fn unix_timestamp(&self) -> Result<i64, String>;
+}
+
+impl ScenarioCluster for BanksClient {
+    async fn latest_blockhash(&self) -> Result<Hash, String> {
+        self.clone()
+            .get_latest_blockhash()
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn process(&self, transaction: Transaction) -> Result<(), String> {
+        nonce::submit(&mut self.clone(), transaction).await
+    }
+
+    async fn account(&self, address: &Pubkey) -> Result<Option<Account>, String> {
+        self.clone()
+            .get_account(*address)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn minimum_balance(&self, len: usize) -> Result<u64, String> {
+        let rent = self.clone().get_rent().await.map_err(|e| e.to_string())?;
+        Ok(rent.minimum_balance(len))
+    }
+
+    async fn unix_timestamp(&self) -> Result<i64, String> {
+        let clock: Clock = self.clone().get_sysvar().await.map_err(|e| e.to_string())?;
+        Ok(clock.unix_timestamp)
+    }
+}
+
+impl ScenarioCluster for RpcClient {
+    async fn latest_blockhash(&self) -> Result<Hash, String> {
+        self.get_latest_blockhash().await.map_err(|e| e.to_string())
+    }
+
+    async fn process(&self, transaction: Transaction) -> Result<(), String> {
+        self.send_and_confirm_transaction(&transaction)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn account(&self, address: &Pubkey) -> Result<Option<Account>, String> {
+        self.get_account_with_commitment(address, self.commitment())
+            .await
+            .map(|response| response.value)
+            .map_err(|e| e.to_string())
+    }
+
+    async fn minimum_balance(&self, len: usize) -> Result<u64, String> {
+        self.get_minimum_balance_for_rent_exemption(len)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn unix_timestamp(&self) -> Result<i64, String> {
+        let account = self
+            .get_account(&sysvar::clock::id())
+            .await
+            .map_err(|e| e.to_string())?;
+        let clock: Clock =
+            from_account(&account).ok_or_else(|| "Malformed clock sysvar".to_string())?;
+        Ok(clock.unix_timestamp)
+    }
+}
+
+/// The narrative scenarios
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scenario {
+    /// A listed holder's frozen account is thawed through the gate and
+    /// funded; an unlisted holder's stays frozen
+    IntegrationFlow,
+    /// Investors at each tier are onboarded with expiries, while those
+    /// never listed, deactivated or revoked are kept out
+    RwaWorkflow,
+    /// One transaction creates a holder's account, passes the gate and
+    /// thaws it, and a denial leaves nothing behind
+    Onboarding,
+    /// The issuer freezes every holder at once, revokes the one found
+    /// responsible, and thaws the rest
+    Emergency,
+}
+
+impl Scenario {
+    pub const ALL: [Scenario; 4] = [
+        Scenario::IntegrationFlow,
+        Scenario::RwaWorkflow,
+        Scenario::Onboarding,
+        Scenario::Emergency,
+    ];
+
+    /// Name on the test client's command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Scenario::IntegrationFlow => "integration_flow",
+            Scenario::RwaWorkflow => "rwa_workflow",
+            Scenario::Onboarding => "onboarding",
+            Scenario::Emergency => "emergency",
+        }
+    }
+
+    /// Name in test reports
+    pub fn title(self) -> &'static str {
+        match self {
+            Scenario::IntegrationFlow => "Integration Flow",
+            Scenario::RwaWorkflow => "Multi-step RWA Workflow",
+            Scenario::Onboarding => "One-transaction Onboarding",
+            Scenario::Emergency => "Freeze-all Emergency",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|scenario| scenario.name() == name)
+    }
+
+    /// Run the scenario on `cluster` with `payer` as the issuer, against
+    /// the production allow list deployed at `gate_program_id`
+    pub async fn run<C: ScenarioCluster>(
+        self,
+        cluster: &C,
+        payer: &Keypair,
+        gate_program_id: Pubkey,
+    ) -> Result<ScenarioOutcome, String> {
+        let mut issuer = Issuer::create(cluster, payer, gate_program_id).await?;
+        match self {
+            Scenario::IntegrationFlow => integration_flow(&mut issuer).await?,
+            Scenario::RwaWorkflow => rwa_workflow(&mut issuer).await?,
+            Scenario::Onboarding => onboarding(&mut issuer).await?,
+            Scenario::Emergency => emergency(&mut issuer).await?,
+        }
+        Ok(ScenarioOutcome {
+            scenario: self,
+            mint: issuer.mint,
+            steps: issuer.steps,
+            checks: issuer.checks,
+        })
+    }
+
+    /// Run the scenario on a bank of its own with the production allow
+    /// list deployed, as the suite does
+    pub async fn run_on_bank(self, test_name: &str) -> Result<ScenarioOutcome, String> {
+        let gate_program_id = Pubkey::new_unique();
+        let program_test = ProgramTest::new(
+            "production_allow_list",
+            gate_program_id,
+            processor!(production_allow_list::process_instruction),
+        );
+        // The bank stops producing blockhashes once the context is dropped
+        let context = TestContext::start(test_name, program_test).await?;
+        let payer = context.payer();
+        self.run(&context.client(), &payer, gate_program_id).await
+    }
+}
+
+/// What a scenario did
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScenarioOutcome {
+    pub scenario: Scenario,
+    /// The mint it created
+    pub mint: Pubkey,
+    /// What it did, in order
+    pub steps: Vec<String>,
+    /// Checks it made, all of which held
+    pub checks: usize,
+}
+
+/// A fresh mint, the gate's config for it, and the record of what the
+/// scenario did with them
+struct Issuer<'a, C> {
+    cluster: &'a C,
+    payer: &'a Keypair,
+    gate_program_id: Pubkey,
+    mint: Pubkey,
+    config: Pubkey,
+    steps: Vec<String>,
+    checks: usize,
+}
+
+impl<'a, C: ScenarioCluster> Issuer<'a, C> {
+    /// Create a Token-2022 mint whose accounts start frozen, with the payer
+    /// as its authorities, and initialize the gate's config for it
+    async fn create(
+        cluster: &'a C,
+        payer: &'a Keypair,
+        gate_program_id: Pubkey,
+    ) -> Result<Issuer<'a, C>, String> {
+        let mint = Keypair::new();
+        let authority = payer.pubkey();
+        let space =
+            ExtensionType::try_calculate_account_len::<Mint>(&[ExtensionType::DefaultAccountState])
+                .map_err(|e| e.to_string())?;
+        let lamports = cluster.minimum_balance(space).await?;
+        let config = config_address(&mint.pubkey(), &gate_program_id).address();
+
+        let mut issuer = Issuer {
+            cluster,
+            payer,
+            gate_program_id,
+            mint: mint.pubkey(),
+            config,
+            steps: Vec::new(),
+            checks: 0,
+        };
+        let create_mint = [
+            system_instruction::create_account(
+                &authority,
+                &mint.pubkey(),
+                lamports,
+                space as u64,
+                &spl_token_2022::id(),
+            ),
+            default_account_state::instruction::initialize_default_account_state(
+                &spl_token_2022::id(),
+                &mint.pubkey(),
+                &AccountState::Frozen,
+            )
+            .map_err(|e| e.to_string())?,
+            token_instruction::initialize_mint2(
+                &spl_token_2022::id(),
+                &mint.pubkey(),
+                &authority,
+                Some(&authority),
+                0,
+            )
+            .map_err(|e| e.to_string())?,
+        ];
+        issuer
+            .send(
+                "Create a mint whose accounts start frozen",
+                &create_mint,
+                &[&mint],
+            )
+            .await?;
+        let initialize = Instruction::new_with_bytes(
+            gate_program_id,
+            &[INITIALIZE, InitMode::Create as u8],
+            vec![
+                AccountMeta::new(config, false),
+                AccountMeta::new_readonly(issuer.mint, false),
+                AccountMeta::new_readonly(authority, true),
+                AccountMeta::new(authority, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        issuer
+            .send("Initialize the allow list for the mint", &[initialize], &[])
+            .await?;
+        Ok(issuer)
+    }
+
+    /// Sign `instructions` with the payer and `signers`, send them, and
+    /// record `step` once they land
+    async fn send(
+        &mut self,
+        step: &str,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), String> {
+        let blockhash = self.cluster.latest_blockhash().await?;
+        let mut all_signers = vec![self.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        self.cluster
+            .process(transaction)
+            .await
+            .map_err(|e| format!("{}: {}", step, e))?;
+        self.steps.push(step.to_string());
+        Ok(())
+    }
+
+    /// Fail with `message` unless `holds`
+    fn check(&mut self, holds: bool, message: impl Into<String>) -> Result<(), String> {
+        self.checks += 1;
+        if holds {
+            Ok(())
+        } else {
+            Err(message.into())
+        }
+    }
+
+    fn add_instruction(&self, holder: &Pubkey, args: &AddToAllowListArgs) -> Instruction {
+        let mut data = vec![ADD_TO_ALLOW_LIST, InitMode::Create as u8];
+        data.extend(args.try_to_vec().expect("add args serialize"));
+        Instruction::new_with_bytes(
+            self.gate_program_id,
+            &data,
+            vec![
+                AccountMeta::new_readonly(self.config, false),
+                AccountMeta::new(self.record(holder), false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(*holder, false),
+                AccountMeta::new_readonly(self.payer.pubkey(), true),
+                AccountMeta::new(self.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    async fn list(
+        &mut self,
+        who: &str,
+        holder: &Pubkey,
+        access_level: AccessLevel,
+        expiry_timestamp: Option<i64>,
+    ) -> Result<(), String> {
+        let add = self.add_instruction(
+            holder,
+            &AddToAllowListArgs {
+                access_level,
+                expiry_timestamp,
+            },
+        );
+        let step = format!("List {} at {:?} access", who, access_level);
+        self.send(&step, &[add], &[]).await
+    }
+
+    /// `RemoveFromAllowList` in `Deactivate` mode, or `Revoke`
+    async fn delist(&mut self, who: &str, holder: &Pubkey, revoke: bool) -> Result<(), String> {
+        let accounts = vec![
+            AccountMeta::new_readonly(self.config, false),
+            AccountMeta::new(self.record(holder), false),
+            AccountMeta::new_readonly(self.payer.pubkey(), true),
+        ];
+        let (data, step) = if revoke {
+            (vec![REVOKE], format!("Revoke {}", who))
+        } else {
+            (
+                vec![REMOVE_FROM_ALLOW_LIST, RemoveMode::Deactivate as u8],
+                format!("Deactivate {}", who),
+            )
+        };
+        let instruction = Instruction::new_with_bytes(self.gate_program_id, &data, accounts);
+        self.send(&step, &[instruction], &[]).await
+    }
+
+    fn record(&self, holder: &Pubkey) -> Pubkey {
+        allow_list_address(&self.mint, holder, &self.gate_program_id).address()
+    }
+
+    fn token_account(&self, holder: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(holder, &self.mint, &spl_token_2022::id())
+    }
+
+    /// Create `holder`'s associated token account, frozen by the mint's
+    /// default state
+    async fn open_account(&mut self, who: &str, holder: &Pubkey) -> Result<Pubkey, String> {
+        let create = create_associated_token_account_idempotent(
+            &self.payer.pubkey(),
+            holder,
+            &self.mint,
+            &spl_token_2022::id(),
+        );
+        self.send(&format!("Open {}'s token account", who), &[create], &[])
+            .await?;
+        Ok(self.token_account(holder))
+    }
+
+    /// The extra accounts of the production allow list's thaw check
+    fn thaw_check_accounts(&self, holder: &Pubkey) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new_readonly(*holder, false),
+            AccountMeta::new_readonly(self.record(holder), false),
+            AccountMeta::new_readonly(
+                find_missing_record_policy_address(&self.mint, &self.gate_program_id).0,
+                false,
+            ),
+        ]
+    }
+
+    fn thaw_instruction(&self, token_account: &Pubkey) -> Instruction {
+        token_instruction::thaw_account(
+            &spl_token_2022::id(),
+            token_account,
+            &self.mint,
+            &self.payer.pubkey(),
+            &[],
+        )
+        .expect("thaw instruction builds")
+    }
+
+    fn freeze_instruction(&self, token_account: &Pubkey) -> Instruction {
+        token_instruction::freeze_account(
+            &spl_token_2022::id(),
+            token_account,
+            &self.mint,
+            &self.payer.pubkey(),
+            &[],
+        )
+        .expect("freeze instruction builds")
+    }
+
+    /// The gate's check for `holder`'s account, then the thaw, in one
+    /// transaction; a denial fails both
+    async fn permissionless_thaw(&mut self, who: &str, holder: &Pubkey) -> Result<(), String> {
+        let token_account = self.token_account(holder);
+        let (extra_metas, _) =
+            find_thaw_extra_account_metas_address(&self.mint, &self.gate_program_id);
+        let mut check = interface::can_thaw_permissionless(
+            &self.gate_program_id,
+            self.payer.pubkey(),
+            token_account,
+            self.mint,
+            extra_metas,
+            &[],
+        );
+        check.accounts.extend(self.thaw_check_accounts(holder));
+        let thaw = self.thaw_instruction(&token_account);
+        let step = format!("Thaw {}'s account through the gate", who);
+        self.send(&step, &[check, thaw], &[]).await
+    }
+
+    async fn state(&self, token_account: &Pubkey) -> Result<Option<AccountState>, String> {
+        let Some(account) = self.cluster.account(token_account).await? else {
+            return Ok(None);
+        };
+        StateWithExtensions::<TokenAccount>::unpack(&account.data)
+            .map(|state| Some(state.base.state))
+            .map_err(|e| format!("{} is not a token account: {}", token_account, e))
+    }
+
+    async fn balance(&self, token_account: &Pubkey) -> Result<u64, String> {
+        let account = self
+            .cluster
+            .account(token_account)
+            .await?
+            .ok_or_else(|| format!("{} does not exist", token_account))?;
+        StateWithExtensions::<TokenAccount>::unpack(&account.data)
+            .map(|state| state.base.amount)
+            .map_err(|e| e.to_string())
+    }
+
+    async fn mint_to(
+        &mut self,
+        who: &str,
+        token_account: &Pubkey,
+        amount: u64,
+    ) -> Result<(), String> {
+        let mint_to = token_instruction::mint_to(
+            &spl_token_2022::id(),
+            &self.mint,
+            token_account,
+            &self.payer.pubkey(),
+            &[],
+            amount,
+        )
+        .map_err(|e| e.to_string())?;
+        self.send(&format!("Mint {} to {}", amount, who), &[mint_to], &[])
+            .await
+    }
+
+    /// The thaw through the gate must fail, and the account stay frozen
+    async fn expect_denied(&mut self, who: &str, holder: &Pubkey) -> Result<(), String> {
+        let result = self.permissionless_thaw(who, holder).await;
+        let state = self.state(&self.token_account(holder)).await?;
+        self.check(
+            result.is_err() && state == Some(AccountState::Frozen),
+            format!("{} was thawed through the gate: {:?}", who, result),
+        )?;
+        self.steps
+            .push(format!("The gate keeps {}'s account frozen", who));
+        Ok(())
+    }
+}
+
+/// A listed holder's frozen account is thawed through the gate and funded;
+/// an unlisted holder's stays frozen
+async fn integration_flow<C: ScenarioCluster>(issuer: &mut Issuer<'_, C>) -> Result<(), String> {
+    let holder = Keypair::new().pubkey();
+    let outsider = Keypair::new().pubkey();
+
+    issuer
+        .list("the holder", &holder, AccessLevel::Enhanced, None)
+        .await?;
+    let account = issuer.open_account("the holder", &holder).await?;
+    let state = issuer.state(&account).await?;
+    issuer.check(
+        state == Some(AccountState::Frozen),
+        format!("New account starts {:?}, not frozen", state),
+    )?;
+
+    issuer.permissionless_thaw("the holder", &holder).await?;
+    let state = issuer.state(&account).await?;
+    issuer.check(
+        state == Some(AccountState::Initialized),
+        format!("Holder's account is {:?} after the thaw", state),
+    )?;
+
+    issuer.mint_to("the holder", &account, 1_000).await?;
+    let balance = issuer.balance(&account).await?;
+    issuer.check(balance == 1_000, format!("Holder holds {}", balance))?;
+
+    issuer.open_account("an outsider", &outsider).await?;
+    issuer.expect_denied("an outsider", &outsider).await
+}
+
+/// Investors at each tier are onboarded with expiries, while those never
+/// listed, deactivated or revoked are kept out
+async fn rwa_workflow<C: ScenarioCluster>(issuer: &mut Issuer<'_, C>) -> Result<(), String> {
+    let now = issuer.cluster.unix_timestamp().await?;
+    let institution = Keypair::new().pubkey();
+    let accredited = Keypair::new().pubkey();
+    let retail = Keypair::new().pubkey();
+    let unverified = Keypair::new().pubkey();
+    let lapsed = Keypair::new().pubkey();
+    let sanctioned = Keypair::new().pubkey();
+
+    // KYC and accreditation completed: listed at their tier, until their
+    // review is due
+    let onboarded = [
+        (
+            "the institution",
+            institution,
+            AccessLevel::Institutional,
+            365,
+        ),
+        (
+            "the accredited investor",
+            accredited,
+            AccessLevel::Enhanced,
+            180,
+        ),
+        ("the retail investor", retail, AccessLevel::Basic, 30),
+    ];
+    for (who, investor, access_level, days) in onboarded {
+        issuer
+            .list(who, &investor, access_level, Some(now + days * DAY))
+            .await?;
+        issuer.open_account(who, &investor).await?;
+        issuer.permissionless_thaw(who, &investor).await?;
+        let state = issuer.state(&issuer.token_account(&investor)).await?;
+        issuer.check(
+            state == Some(AccountState::Initialized),
+            format!("{}'s account is {:?}", who, state),
+        )?;
+    }
+
+    // KYC never completed
+    issuer
+        .open_account("the unverified investor", &unverified)
+        .await?;
+    issuer
+        .expect_denied("the unverified investor", &unverified)
+        .await?;
+
+    // Accreditation lapsed after onboarding
+    issuer
+        .list("the lapsed investor", &lapsed, AccessLevel::Enhanced, None)
+        .await?;
+    issuer.delist("the lapsed investor", &lapsed, false).await?;
+    issuer.open_account("the lapsed investor", &lapsed).await?;
+    issuer.expect_denied("the lapsed investor", &lapsed).await?;
+
+    // Sanctioned: revoked, which a later add cannot undo
+    issuer
+        .list(
+            "the sanctioned investor",
+            &sanctioned,
+            AccessLevel::Institutional,
+            None,
+        )
+        .await?;
+    issuer
+        .delist("the sanctioned investor", &sanctioned, true)
+        .await?;
+    issuer
+        .open_account("the sanctioned investor", &sanctioned)
+        .await?;
+    issuer
+        .expect_denied("the sanctioned investor", &sanctioned)
+        .await?;
+    let relist = issuer
+        .list(
+            "the sanctioned investor",
+            &sanctioned,
+            AccessLevel::Basic,
+            None,
+        )
+        .await;
+    issuer.check(
+        relist.is_err(),
+        "A revoked investor was listed again".to_string(),
+    )
+}
+
+/// One transaction creates a holder's account, passes the gate and thaws
+/// it, and a denial leaves nothing behind
+async fn onboarding<C: ScenarioCluster>(issuer: &mut Issuer<'_, C>) -> Result<(), String> {
+    let holder = Keypair::new();
+    let outsider = Keypair::new();
+    issuer
+        .list("the holder", &holder.pubkey(), AccessLevel::Basic, None)
+        .await?;
+
+    for (who, owner, listed) in [
+        ("the holder", &holder, true),
+        ("an outsider", &outsider, false),
+    ] {
+        let token_account = issuer.token_account(&owner.pubkey());
+        let builder = issuer
+            .thaw_check_accounts(&owner.pubkey())
+            .into_iter()
+            .fold(
+                CreateAndThawBuilder::new(
+                    issuer.payer.pubkey(),
+                    owner.pubkey(),
+                    issuer.mint,
+                    issuer.gate_program_id,
+                ),
+                |builder, meta| builder.extra_account(meta),
+            )
+            .then(issuer.thaw_instruction(&token_account));
+        let step = format!("Create and thaw {}'s account in one transaction", who);
+        let result = issuer.send(&step, &builder.instructions(), &[owner]).await;
+        let state = issuer.state(&token_account).await?;
+        if listed {
+            result?;
+            issuer.check(
+                state == Some(AccountState::Initialized),
+                format!("{}'s new account is {:?}", who, state),
+            )?;
+        } else {
+            issuer.check(
+                result.is_err() && state.is_none(),
+                format!("{}'s denied onboarding left {:?}", who, state),
+            )?;
+            issuer
+                .steps
+                .push(format!("The gate's denial rolled back {}'s account", who));
+        }
+    }
+    Ok(())
+}
+
+/// The issuer freezes every holder at once, revokes the one found
+/// responsible, and thaws the rest
+async fn emergency<C: ScenarioCluster>(issuer: &mut Issuer<'_, C>) -> Result<(), String> {
+    let holders: Vec<Pubkey> = (0..EMERGENCY_HOLDERS)
+        .map(|_| Keypair::new().pubkey())
+        .collect();
+    for (index, holder) in holders.iter().enumerate() {
+        let who = format!("holder {}", index + 1);
+        issuer
+            .list(&who, holder, AccessLevel::Enhanced, None)
+            .await?;
+        let account = issuer.open_account(&who, holder).await?;
+        issuer.permissionless_thaw(&who, holder).await?;
+        issuer
+            .mint_to(&who, &account, 1_000 * (index as u64 + 1))
+            .await?;
+    }
+
+    // Kill switch: one transaction freezes every holder
+    let freezes: Vec<Instruction> = holders
+        .iter()
+        .map(|holder| issuer.freeze_instruction(&issuer.token_account(holder)))
+        .collect();
+    issuer
+        .send("Freeze every holder's account", &freezes, &[])
+        .await?;
+    for holder in &holders {
+        let state = issuer.state(&issuer.token_account(holder)).await?;
+        issuer.check(
+            state == Some(AccountState::Frozen),
+            format!("{} is {:?} after the freeze", holder, state),
+        )?;
+    }
+
+    // The largest holder is found responsible and revoked; the others are
+    // cleared and thaw through the gate again
+    let (responsible, cleared) = holders.split_last().expect("the scenario has holders");
+    issuer
+        .delist("the responsible holder", responsible, true)
+        .await?;
+    issuer
+        .expect_denied("the responsible holder", responsible)
+        .await?;
+    for (index, holder) in cleared.iter().enumerate() {
+        let who = format!("holder {}", index + 1);
+        issuer.permissionless_thaw(&who, holder).await?;
+        let state = issuer.state(&issuer.token_account(holder)).await?;
+        issuer.check(
+            state == Some(AccountState::Initialized),
+            format!("Cleared {} is {:?}", who, state),
+        )?;
+    }
+
+    // Balances were never touched
+    for (index, holder) in holders.iter().enumerate() {
+        let balance = issuer.balance(&issuer.token_account(holder)).await?;
+        issuer.check(
+            balance == 1_000 * (index as u64 + 1),
+            format!("{} holds {} after the emergency", holder, balance),
+        )?;
+    }
+    Ok(())
+}
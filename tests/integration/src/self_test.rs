@@ -0,0 +1,318 @@
+//! Suite self-test
+//!
+//! A check that can never fail proves nothing. The self-test runs the
+//! suite's own checks against known-bad fixtures (a wrong discriminator, an
+//! expired record, ...) and requires every one of them to be reported as a
+//! failure, alongside a known-good baseline that must still pass. A check
+//! that has degraded into comparing a constant with itself shows up here as
+//! a fixture the suite missed.
+//!
+//! The checks are the ones the suites call: [`check_discriminators`] backs
+//! the discriminator validation test and [`check_thaw_record`] the record
+//! semantics the gate enforces.
+
+use production_allow_list::{AccessLevel, AllowListRecord};
+use sha2::{Digest, Sha256};
+use solana_sdk::pubkey::Pubkey;
+use token_acl_interface::{
+    CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR, CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+};
+
+use crate::{fixtures::test_data, time, utils, TestResultReport};
+
+/// Namespace sRFC 37 hashes instruction names under
+pub const DISCRIMINATOR_NAMESPACE: &str = "efficient-allow-block-list-standard";
+
+/// Timestamp the gate programs check records at
+pub const GATE_TIMESTAMP: i64 = 0;
+
+/// The discriminator sRFC 37 derives for `instruction`: the first eight
+/// bytes of `sha256("<namespace>:<instruction>")`
+pub fn spec_discriminator(instruction: &str) -> [u8; 8] {
+    let digest = Sha256::digest(format!("{}:{}", DISCRIMINATOR_NAMESPACE, instruction));
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&digest[..8]);
+    discriminator
+}
+
+/// Check a thaw and freeze discriminator pair against the specification
+/// and the interface crate the gate programs dispatch on
+pub fn check_discriminators(thaw: [u8; 8], freeze: [u8; 8]) -> TestResultReport {
+    let test_name = "Discriminator Validation";
+    let mut assertions = 0;
+
+    // Assertion 1: Thaw discriminator is the spec derivation
+    assertions += 1;
+    let expected_thaw = spec_discriminator("can-thaw-permissionless");
+    if thaw != expected_thaw {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Thaw discriminator mismatch: {:?} != {:?}",
+                thaw, expected_thaw
+            ),
+        );
+    }
+
+    // Assertion 2: Freeze discriminator is the spec derivation
+    assertions += 1;
+    let expected_freeze = spec_discriminator("can-freeze-permissionless");
+    if freeze != expected_freeze {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Freeze discriminator mismatch: {:?} != {:?}",
+                freeze, expected_freeze
+            ),
+        );
+    }
+
+    // Assertion 3: The gate programs dispatch on the same bytes
+    assertions += 1;
+    if thaw != CAN_THAW_PERMISSIONLESS_DISCRIMINATOR
+        || freeze != CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Discriminators differ from token_acl_interface".to_string(),
+        );
+    }
+
+    // Assertion 4: Discriminators are different
+    assertions += 1;
+    if thaw == freeze {
+        return TestResultReport::failure(
+            test_name,
+            "Thaw and freeze discriminators must be different".to_string(),
+        );
+    }
+
+    // Assertion 5: Discriminators are valid (not all zeros)
+    assertions += 1;
+    if !utils::is_valid_discriminator(&thaw) || !utils::is_valid_discriminator(&freeze) {
+        return TestResultReport::failure(
+            test_name,
+            "Discriminators should not be all zeros".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Check that `record` lets `user` thaw their `mint` account at `now`
+pub fn check_thaw_record(
+    record: &AllowListRecord,
+    mint: &Pubkey,
+    user: &Pubkey,
+    now: i64,
+) -> TestResultReport {
+    let test_name = "Allow List Record Validation";
+    let mut assertions = 0;
+
+    // Assertion 1: The record is for this mint and user
+    assertions += 1;
+    if record.mint != *mint || record.user != *user {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Record is for user {} of mint {}, not user {} of mint {}",
+                record.user, record.mint, user, mint
+            ),
+        );
+    }
+
+    // Assertion 2: The record has not expired
+    assertions += 1;
+    if record.is_expired(now) {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Record expired at {:?}, checked at {}",
+                record.expiry_timestamp, now
+            ),
+        );
+    }
+
+    // Assertion 3: The record permits a permissionless thaw
+    assertions += 1;
+    if !record.permits_thaw(now) {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Record does not permit thaw (allowed: {}, revoked: {})",
+                record.allowed, record.revoked
+            ),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// An active, unexpiring allow list record for `user` of `mint`
+pub fn valid_record(mint: Pubkey, user: Pubkey) -> AllowListRecord {
+    AllowListRecord {
+        mint,
+        user,
+        allowed: true,
+        access_level: AccessLevel::Basic,
+        added_timestamp: GATE_TIMESTAMP,
+        expiry_timestamp: None,
+        bump: 255,
+        revoked: false,
+    }
+}
+
+/// A fixture with a known defect the suite must report as a failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KnownBadFixture {
+    /// Thaw discriminator with one byte flipped
+    WrongThawDiscriminator,
+    /// Freeze discriminator with one byte flipped
+    WrongFreezeDiscriminator,
+    /// Thaw and freeze discriminators swapped
+    SwappedDiscriminators,
+    /// Record whose expiry is before the gate's clock
+    ExpiredRecord,
+    /// Record that was revoked but still says allowed
+    RevokedRecord,
+    /// Record for the same user under another mint
+    RecordForAnotherMint,
+}
+
+impl KnownBadFixture {
+    /// Every fixture, in report order
+    pub const ALL: [KnownBadFixture; 6] = [
+        KnownBadFixture::WrongThawDiscriminator,
+        KnownBadFixture::WrongFreezeDiscriminator,
+        KnownBadFixture::SwappedDiscriminators,
+        KnownBadFixture::ExpiredRecord,
+        KnownBadFixture::RevokedRecord,
+        KnownBadFixture::RecordForAnotherMint,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KnownBadFixture::WrongThawDiscriminator => "Wrong thaw discriminator",
+            KnownBadFixture::WrongFreezeDiscriminator => "Wrong freeze discriminator",
+            KnownBadFixture::SwappedDiscriminators => "Swapped discriminators",
+            KnownBadFixture::ExpiredRecord => "Expired record",
+            KnownBadFixture::RevokedRecord => "Revoked record",
+            KnownBadFixture::RecordForAnotherMint => "Record for another mint",
+        }
+    }
+
+    /// Run the suite's check against this fixture
+    pub fn run(self) -> TestResultReport {
+        let thaw = test_data::THAW_DISCRIMINATOR;
+        let freeze = test_data::FREEZE_DISCRIMINATOR;
+        let mint = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let mut record = valid_record(mint, user);
+        match self {
+            KnownBadFixture::WrongThawDiscriminator => {
+                check_discriminators(flip_first_byte(thaw), freeze)
+            }
+            KnownBadFixture::WrongFreezeDiscriminator => {
+                check_discriminators(thaw, flip_first_byte(freeze))
+            }
+            KnownBadFixture::SwappedDiscriminators => check_discriminators(freeze, thaw),
+            KnownBadFixture::ExpiredRecord => {
+                record.expiry_timestamp = Some(GATE_TIMESTAMP - 1);
+                check_thaw_record(&record, &mint, &user, GATE_TIMESTAMP)
+            }
+            KnownBadFixture::RevokedRecord => {
+                record.revoked = true;
+                check_thaw_record(&record, &mint, &user, GATE_TIMESTAMP)
+            }
+            KnownBadFixture::RecordForAnotherMint => {
+                record.mint = Pubkey::new_unique();
+                check_thaw_record(&record, &mint, &user, GATE_TIMESTAMP)
+            }
+        }
+    }
+}
+
+fn flip_first_byte(mut discriminator: [u8; 8]) -> [u8; 8] {
+    discriminator[0] ^= 0xff;
+    discriminator
+}
+
+/// What the suite reported for one known-bad fixture
+#[derive(Debug, Clone)]
+pub struct SelfTestOutcome {
+    pub fixture: String,
+    pub report: TestResultReport,
+}
+
+impl SelfTestOutcome {
+    /// The suite reported the fixture as a failure
+    pub fn caught(&self) -> bool {
+        !self.report.passed
+    }
+}
+
+/// The suite's checks against correct fixtures; each must pass, or a caught
+/// fixture would only mean the check rejects everything
+pub fn run_known_good() -> Vec<TestResultReport> {
+    let mint = Pubkey::new_unique();
+    let user = Pubkey::new_unique();
+    vec![
+        check_discriminators(
+            test_data::THAW_DISCRIMINATOR,
+            test_data::FREEZE_DISCRIMINATOR,
+        ),
+        check_thaw_record(&valid_record(mint, user), &mint, &user, GATE_TIMESTAMP),
+    ]
+}
+
+/// Run every known-bad fixture through the suite's checks
+pub fn run_self_test() -> Vec<SelfTestOutcome> {
+    KnownBadFixture::ALL
+        .into_iter()
+        .map(|fixture| SelfTestOutcome {
+            fixture: fixture.name().to_string(),
+            report: fixture.run(),
+        })
+        .collect()
+}
+
+/// Markdown report of a self-test run
+pub fn render_self_test_report(
+    known_good: &[TestResultReport],
+    outcomes: &[SelfTestOutcome],
+) -> String {
+    let caught = outcomes.iter().filter(|outcome| outcome.caught()).count();
+    let mut report = String::from("# Suite Self-Test\n\n");
+    report.push_str(&format!("**Generated**: {}\n\n", time::report_timestamp()));
+    report.push_str(&format!(
+        "- **Known-bad fixtures caught**: {}/{}\n",
+        caught,
+        outcomes.len()
+    ));
+    report.push_str(&format!(
+        "- **Known-good baseline passing**: {}/{}\n\n",
+        known_good.iter().filter(|report| report.passed).count(),
+        known_good.len()
+    ));
+    report.push_str("| Fixture | Check | Caught | Reported |\n");
+    report.push_str("|---------|-------|--------|----------|\n");
+    for outcome in outcomes {
+        report.push_str(&format!(
+            "| {} | {} | {} | {} |\n",
+            outcome.fixture,
+            outcome.report.name,
+            if outcome.caught() {
+                "✅"
+            } else {
+                "❌ MISSED"
+            },
+            outcome
+                .report
+                .error
+                .as_deref()
+                .unwrap_or("passed")
+                .replace('|', "\\|")
+        ));
+    }
+    report
+}
@@ -0,0 +1,266 @@
+//! Randomized operation sequences, their shrinking, and regression files
+//!
+//! A differential scenario runs a random [`Operation`] sequence against the
+//! deployed allow list and compares each outcome with [`AllowListModel`].
+//! When they diverge, [`shrink`] reduces the sequence to a smallest one that
+//! still diverges, and [`RegressionScenario::save`] writes it under
+//! [`REGRESSIONS_DIR`] so the reproduction is replayed on every later run.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    future::Future,
+    io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Where regression scenarios are kept, relative to the integration crate
+pub const REGRESSIONS_DIR: &str = "tests/regressions";
+
+/// One allow list operation, by user index so sequences are portable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Operation {
+    Add { user: u8 },
+    Remove { user: u8 },
+    Close { user: u8 },
+    CheckThaw { user: u8 },
+}
+
+impl Operation {
+    pub fn user(&self) -> u8 {
+        match *self {
+            Operation::Add { user }
+            | Operation::Remove { user }
+            | Operation::Close { user }
+            | Operation::CheckThaw { user } => user,
+        }
+    }
+
+    /// The same operation for another user
+    pub fn with_user(&self, user: u8) -> Self {
+        match self {
+            Operation::Add { .. } => Operation::Add { user },
+            Operation::Remove { .. } => Operation::Remove { user },
+            Operation::Close { .. } => Operation::Close { user },
+            Operation::CheckThaw { .. } => Operation::CheckThaw { user },
+        }
+    }
+}
+
+/// A small deterministic generator (SplitMix64), so a seed reproduces a run
+#[derive(Debug, Clone)]
+pub struct SequenceRng {
+    state: u64,
+}
+
+impl SequenceRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A value in `0..bound`
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+}
+
+/// `len` random operations over `users` users
+pub fn random_sequence(seed: u64, len: usize, users: u8) -> Vec<Operation> {
+    let mut rng = SequenceRng::new(seed);
+    (0..len)
+        .map(|_| {
+            let user = rng.below(u64::from(users.max(1))) as u8;
+            match rng.below(4) {
+                0 => Operation::Add { user },
+                1 => Operation::Remove { user },
+                2 => Operation::Close { user },
+                _ => Operation::CheckThaw { user },
+            }
+        })
+        .collect()
+}
+
+/// The allow list as a map from user to their record's `allowed` flag
+///
+/// Mirrors the program: adding needs no record (a deactivated one still
+/// blocks it), removing and closing need a record, and a thaw check needs an
+/// allowed record.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AllowListModel {
+    records: BTreeMap<u8, bool>,
+}
+
+impl AllowListModel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply `operation`, returning whether the program should accept it
+    pub fn apply(&mut self, operation: &Operation) -> bool {
+        let user = operation.user();
+        match operation {
+            Operation::Add { .. } => {
+                if self.records.contains_key(&user) {
+                    return false;
+                }
+                self.records.insert(user, true);
+                true
+            }
+            Operation::Remove { .. } => match self.records.get_mut(&user) {
+                Some(allowed) => {
+                    *allowed = false;
+                    true
+                }
+                None => false,
+            },
+            Operation::Close { .. } => self.records.remove(&user).is_some(),
+            Operation::CheckThaw { .. } => self.records.get(&user) == Some(&true),
+        }
+    }
+
+    /// The expected outcome of every operation, from an empty allow list
+    pub fn outcomes(operations: &[Operation]) -> Vec<bool> {
+        let mut model = Self::new();
+        operations.iter().map(|op| model.apply(op)).collect()
+    }
+}
+
+/// The first operation whose actual outcome differs from the expected one
+pub fn first_divergence(expected: &[bool], actual: &[bool]) -> Option<usize> {
+    expected
+        .iter()
+        .zip(actual)
+        .position(|(expected, actual)| expected != actual)
+        .or_else(|| (expected.len() != actual.len()).then(|| expected.len().min(actual.len())))
+}
+
+/// A shrunk failing sequence and how many candidate runs it took
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shrunk<T> {
+    pub operations: Vec<T>,
+    pub runs: usize,
+}
+
+/// Reduce `operations` to a smallest sequence `fails` still holds for
+///
+/// Delta debugging: drop ever-smaller chunks while the failure reproduces,
+/// down to single operations, then try each `simplify` candidate for every
+/// remaining operation. The result is 1-minimal: removing any single
+/// operation makes the failure go away. `operations` must fail to begin with.
+pub async fn shrink<T, F, Fut>(
+    operations: Vec<T>,
+    simplify: impl Fn(&T) -> Vec<T>,
+    mut fails: F,
+) -> Shrunk<T>
+where
+    T: Clone + PartialEq,
+    F: FnMut(Vec<T>) -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let mut current = operations;
+    let mut runs = 0;
+
+    let mut chunk = current.len().div_ceil(2).max(1);
+    loop {
+        let mut start = 0;
+        let mut removed = false;
+        while start < current.len() {
+            let end = (start + chunk).min(current.len());
+            let mut candidate = current.clone();
+            candidate.drain(start..end);
+            runs += 1;
+            if !candidate.is_empty() && fails(candidate.clone()).await {
+                current = candidate;
+                removed = true;
+            } else {
+                start = end;
+            }
+        }
+        if chunk == 1 && !removed {
+            break;
+        }
+        if !removed {
+            chunk = chunk.div_ceil(2);
+        }
+    }
+
+    for index in 0..current.len() {
+        for simpler in simplify(&current[index]) {
+            if simpler == current[index] {
+                continue;
+            }
+            let mut candidate = current.clone();
+            candidate[index] = simpler;
+            runs += 1;
+            if fails(candidate.clone()).await {
+                current = candidate;
+                break;
+            }
+        }
+    }
+
+    Shrunk {
+        operations: current,
+        runs,
+    }
+}
+
+/// Candidates for a simpler operation: the same one for a lower user index
+pub fn simplify_operation(operation: &Operation) -> Vec<Operation> {
+    (0..operation.user())
+        .map(|user| operation.with_user(user))
+        .collect()
+}
+
+/// A minimized failing sequence, replayed as a regression scenario
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegressionScenario {
+    pub name: String,
+    /// Seed of the randomized run the sequence was shrunk from
+    pub seed: Option<u64>,
+    /// What diverged when the scenario was recorded
+    pub reason: String,
+    pub operations: Vec<Operation>,
+}
+
+impl RegressionScenario {
+    /// Write the scenario to `<dir>/<name>.json`
+    pub fn save(&self, dir: impl AsRef<Path>) -> io::Result<PathBuf> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.json", self.name));
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(&path, json + "\n")?;
+        Ok(path)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Every scenario in `dir`, by file name; a missing directory has none
+    pub fn load_all(dir: impl AsRef<Path>) -> io::Result<Vec<Self>> {
+        let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<io::Result<_>>()?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        paths.retain(|path| path.extension().is_some_and(|ext| ext == "json"));
+        paths.sort();
+        paths.into_iter().map(Self::load).collect()
+    }
+}
@@ -0,0 +1,132 @@
+//! Pre-submission compute budget simulation
+//!
+//! A transaction without a compute-unit limit gets the default per
+//! instruction, which priority fees are then paid on whether used or not,
+//! and a hand-picked limit goes stale as the gate changes. The builders can
+//! instead simulate the transaction first and attach a limit sized from the
+//! units it actually consumed, with the same margin as the compute budget
+//! guidance ([`budget_guidance::limit_with_margin`]).
+//!
+//! The simulation runs with the maximum limit, so a transaction heavier
+//! than the default is measured rather than cut off, and with the same
+//! number of compute budget instructions as the submitted one, so the
+//! units measured are the units it will consume.
+
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcSimulateTransactionConfig};
+use solana_program_runtime::compute_budget_processor::MAX_COMPUTE_UNIT_LIMIT;
+use solana_program_test::BanksClient;
+use solana_sdk::{
+    borsh1::try_from_slice_unchecked,
+    compute_budget::{self, ComputeBudgetInstruction},
+    hash::Hash,
+    instruction::Instruction,
+    message::Message,
+    pubkey::Pubkey,
+    transaction::Transaction,
+};
+
+use crate::budget_guidance;
+
+/// Runs a transaction without landing it
+#[allow(async_fn_in_trait)]
+pub trait TransactionSimulator {
+    /// Compute units `transaction` consumes, or why it would fail
+    ///
+    /// Signatures are not checked, so the transaction may be unsigned.
+    async fn units_consumed(&self, transaction: &Transaction) -> Result<u64, String>;
+}
+
+impl TransactionSimulator for BanksClient {
+    async fn units_consumed(&self, transaction: &Transaction) -> Result<u64, String> {
+        let simulation = self
+            .clone()
+            .simulate_transaction(transaction.clone())
+            .await
+            .map_err(|e| e.to_string())?;
+        match simulation.result {
+            Some(Ok(())) => {}
+            Some(Err(e)) => return Err(format!("Simulation failed: {}", e)),
+            None => return Err("Simulation did not run".to_string()),
+        }
+        simulation
+            .simulation_details
+            .map(|details| details.units_consumed)
+            .ok_or_else(|| "Simulation reported no units".to_string())
+    }
+}
+
+impl TransactionSimulator for RpcClient {
+    async fn units_consumed(&self, transaction: &Transaction) -> Result<u64, String> {
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            commitment: Some(self.commitment()),
+            ..RpcSimulateTransactionConfig::default()
+        };
+        let response = self
+            .simulate_transaction_with_config(transaction, config)
+            .await
+            .map_err(|e| e.to_string())?;
+        if let Some(e) = response.value.err {
+            return Err(format!("Simulation failed: {}", e));
+        }
+        response
+            .value
+            .units_consumed
+            .ok_or_else(|| "Simulation reported no units".to_string())
+    }
+}
+
+/// A compute-unit limit sized by simulation
+#[derive(Debug, Clone)]
+pub struct SimulatedBudget {
+    /// Units the simulated transaction consumed
+    pub units_consumed: u64,
+    /// The limit attached: `units_consumed` plus the guidance margin
+    pub compute_unit_limit: u32,
+    /// The instructions with the limit first and any earlier limit removed
+    pub instructions: Vec<Instruction>,
+}
+
+/// Whether `instruction` sets a compute-unit limit
+pub fn is_compute_unit_limit(instruction: &Instruction) -> bool {
+    instruction.program_id == compute_budget::id()
+        && matches!(
+            try_from_slice_unchecked(&instruction.data),
+            Ok(ComputeBudgetInstruction::SetComputeUnitLimit(_))
+        )
+}
+
+/// `instructions` with a compute-unit limit of `units` first, replacing any
+/// limit they already set
+pub fn with_compute_unit_limit(instructions: &[Instruction], units: u32) -> Vec<Instruction> {
+    std::iter::once(ComputeBudgetInstruction::set_compute_unit_limit(units))
+        .chain(
+            instructions
+                .iter()
+                .filter(|instruction| !is_compute_unit_limit(instruction))
+                .cloned(),
+        )
+        .collect()
+}
+
+/// Simulate `instructions` paid by `payer` and attach a compute-unit limit
+/// sized from the units they consumed
+pub async fn simulate_budget<S: TransactionSimulator>(
+    simulator: &S,
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    recent_blockhash: Hash,
+) -> Result<SimulatedBudget, String> {
+    let probe = with_compute_unit_limit(instructions, MAX_COMPUTE_UNIT_LIMIT);
+    let message = Message::new_with_blockhash(&probe, Some(payer), &recent_blockhash);
+    let units_consumed = simulator
+        .units_consumed(&Transaction::new_unsigned(message))
+        .await?;
+    let compute_unit_limit = budget_guidance::limit_with_margin(units_consumed);
+    Ok(SimulatedBudget {
+        units_consumed,
+        compute_unit_limit,
+        instructions: with_compute_unit_limit(instructions, compute_unit_limit),
+    })
+}
@@ -0,0 +1,150 @@
+//! Golden-file snapshot assertions for rendered reports
+//!
+//! Report renderers are compared against committed snapshots in
+//! `tests/snapshots`, so any formatting change shows up in review as a diff
+//! to a `.snap` file rather than slipping through.
+//!
+//! On a mismatch (or a missing snapshot) the actual output is written next to
+//! the snapshot as `<name>.snap.new` and the assertion fails with a line diff.
+//! After reviewing, accept every change by rerunning with
+//! `UPDATE_SNAPSHOTS=1`, or by renaming the `.snap.new` files.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Directory holding the committed snapshots
+pub const SNAPSHOT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots");
+
+/// Environment variable that makes assertions overwrite their snapshots
+pub const UPDATE_ENV: &str = "UPDATE_SNAPSHOTS";
+
+/// Placeholder substituted for wall-clock timestamps
+pub const REDACTED_TIMESTAMP: &str = "[timestamp]";
+
+/// Most differing lines shown in a failure message
+const MAX_DIFF_LINES: usize = 20;
+
+/// Compare `actual` against the committed snapshot `name`
+pub fn assert_snapshot(name: &str, actual: &str) -> Result<(), String> {
+    let update = std::env::var(UPDATE_ENV).is_ok_and(|value| value != "0");
+    assert_snapshot_in(Path::new(SNAPSHOT_DIR), name, actual, update)
+}
+
+/// Compare `actual` against snapshot `name` in `dir`, overwriting it instead
+/// when `update` is set
+pub fn assert_snapshot_in(
+    dir: &Path,
+    name: &str,
+    actual: &str,
+    update: bool,
+) -> Result<(), String> {
+    let path = dir.join(format!("{}.snap", name));
+    let pending = pending_path(&path);
+
+    if update {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        fs::write(&path, actual).map_err(|e| e.to_string())?;
+        fs::remove_file(&pending).ok();
+        return Ok(());
+    }
+
+    let expected = match fs::read_to_string(&path) {
+        Ok(expected) => expected,
+        Err(_) => {
+            write_pending(&pending, actual)?;
+            return Err(format!(
+                "No snapshot '{}'; review {} and rerun with {}=1",
+                name,
+                pending.display(),
+                UPDATE_ENV
+            ));
+        }
+    };
+
+    if expected == actual {
+        fs::remove_file(&pending).ok();
+        return Ok(());
+    }
+
+    write_pending(&pending, actual)?;
+    Err(format!(
+        "Snapshot '{}' changed (new output in {}):\n{}",
+        name,
+        pending.display(),
+        line_diff(&expected, actual)
+    ))
+}
+
+/// Replace `YYYY-MM-DD HH:MM:SS UTC` timestamps with [`REDACTED_TIMESTAMP`]
+pub fn redact_timestamps(text: &str) -> String {
+    const SHAPE: &[u8] = b"dddd-dd-dd dd:dd:dd UTC";
+
+    let bytes = text.as_bytes();
+    let matches_at = |start: usize| {
+        bytes.len() >= start + SHAPE.len()
+            && SHAPE.iter().enumerate().all(|(i, expected)| {
+                let byte = bytes[start + i];
+                match expected {
+                    b'd' => byte.is_ascii_digit(),
+                    other => byte == *other,
+                }
+            })
+    };
+
+    let mut redacted = String::with_capacity(text.len());
+    let mut start = 0;
+    let mut index = 0;
+    while index < bytes.len() {
+        if matches_at(index) {
+            redacted.push_str(&text[start..index]);
+            redacted.push_str(REDACTED_TIMESTAMP);
+            index += SHAPE.len();
+            start = index;
+        } else {
+            index += 1;
+        }
+    }
+    redacted.push_str(&text[start..]);
+    redacted
+}
+
+fn pending_path(path: &Path) -> PathBuf {
+    let mut pending = path.as_os_str().to_owned();
+    pending.push(".new");
+    PathBuf::from(pending)
+}
+
+fn write_pending(pending: &Path, actual: &str) -> Result<(), String> {
+    if let Some(parent) = pending.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(pending, actual).map_err(|e| e.to_string())
+}
+
+/// Line-by-line diff: `-` lines are the snapshot, `+` lines the new output
+fn line_diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+
+    let mut diff = Vec::new();
+    for line in 0..expected.len().max(actual.len()) {
+        let (old, new) = (expected.get(line), actual.get(line));
+        if old == new {
+            continue;
+        }
+        if let Some(old) = old {
+            diff.push(format!("{:>4} - {}", line + 1, old));
+        }
+        if let Some(new) = new {
+            diff.push(format!("{:>4} + {}", line + 1, new));
+        }
+    }
+    if diff.len() > MAX_DIFF_LINES {
+        let hidden = diff.len() - MAX_DIFF_LINES;
+        diff.truncate(MAX_DIFF_LINES);
+        diff.push(format!("... {} more differing lines", hidden));
+    }
+    diff.join("\n")
+}
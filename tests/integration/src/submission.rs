@@ -0,0 +1,542 @@
+//! Bulk submission with blockhash and nonce management
+//!
+//! Batched admin operations and load tests submit thousands of transactions.
+//! Sent one by one with `process_transaction`, a lost confirmation either
+//! stalls the run or, if the client simply re-signs and resends, lands the
+//! operation twice: the first transaction was never lost, only its
+//! confirmation. [`SubmissionEngine`] keeps a window of transactions in
+//! flight and tracks each by signature:
+//!
+//! - a transaction that has not landed is rebroadcast as is, under the same
+//!   signature, so the runtime deduplicates it,
+//! - it is re-signed only once its lifetime has provably ended without it
+//!   landing: its blockhash is no longer valid, or its durable nonce has
+//!   advanced, and a status query made after that still finds nothing,
+//! - a failed status query, send or validity check proves nothing, and the
+//!   transaction stays in flight.
+//!
+//! Each operation therefore lands at most once, even an admin operation
+//! whose second landing would be harmless or fail on its own: the fees, logs
+//! and audit trail stay one per operation.
+//!
+//! Recent blockhashes are shared by every transaction signed within
+//! [`SubmissionConfig::blockhash_max_age`]. With
+//! [`Lifetime::DurableNonce`], each transaction in flight holds one nonce
+//! account of the pool and advances it, so it never expires while the
+//! cluster is unreachable.
+
+use solana_client::{
+    nonblocking::rpc_client::RpcClient, nonce_utils, rpc_config::RpcSendTransactionConfig,
+};
+use solana_program_test::BanksClient;
+use solana_sdk::{
+    hash::Hash,
+    instruction::Instruction,
+    message::Message,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    system_instruction,
+    transaction::{Transaction, TransactionError},
+};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// What the engine submits to and polls
+#[allow(async_fn_in_trait)]
+pub trait SubmissionCluster {
+    async fn latest_blockhash(&self) -> Result<Hash, String>;
+
+    /// Whether a transaction signed with `blockhash` can still land
+    async fn is_blockhash_valid(&self, blockhash: &Hash) -> Result<bool, String>;
+
+    /// The blockhash a transaction advancing `nonce_account` must be signed
+    /// with
+    async fn nonce_blockhash(&self, nonce_account: &Pubkey) -> Result<Hash, String>;
+
+    /// Send `transaction` without waiting for it; an error does not mean it
+    /// was not received
+    async fn send(&self, transaction: &Transaction) -> Result<(), String>;
+
+    /// The result of the transaction signed `signature`, `None` if it has
+    /// not landed
+    async fn signature_status(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<Result<(), TransactionError>>, String>;
+}
+
+impl SubmissionCluster for BanksClient {
+    async fn latest_blockhash(&self) -> Result<Hash, String> {
+        self.clone()
+            .get_latest_blockhash()
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn is_blockhash_valid(&self, blockhash: &Hash) -> Result<bool, String> {
+        // The bank only prices messages whose blockhash it still holds
+        let message = Message::new_with_blockhash(&[], Some(&Pubkey::default()), blockhash);
+        self.clone()
+            .get_fee_for_message(message)
+            .await
+            .map(|fee| fee.is_some())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn nonce_blockhash(&self, nonce_account: &Pubkey) -> Result<Hash, String> {
+        let account = self
+            .clone()
+            .get_account(*nonce_account)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Nonce account {} not found", nonce_account))?;
+        nonce_utils::data_from_account(&account)
+            .map(|data| data.blockhash())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn send(&self, transaction: &Transaction) -> Result<(), String> {
+        // `send_transaction` panics the bank server on a blockhash it does
+        // not hold, durable nonces included; this processes it instead and
+        // leaves the outcome to `signature_status`
+        let outcome = self
+            .clone()
+            .process_transaction_with_metadata(transaction.clone())
+            .await
+            .map_err(|e| e.to_string())?;
+        match (outcome.result, outcome.metadata) {
+            (Err(e), None) => Err(format!("Not executed: {}", e)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn signature_status(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<Result<(), TransactionError>>, String> {
+        let status = self
+            .clone()
+            .get_transaction_status(*signature)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(status.map(|status| match status.err {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }))
+    }
+}
+
+impl SubmissionCluster for RpcClient {
+    async fn latest_blockhash(&self) -> Result<Hash, String> {
+        self.get_latest_blockhash().await.map_err(|e| e.to_string())
+    }
+
+    async fn is_blockhash_valid(&self, blockhash: &Hash) -> Result<bool, String> {
+        RpcClient::is_blockhash_valid(self, blockhash, self.commitment())
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn nonce_blockhash(&self, nonce_account: &Pubkey) -> Result<Hash, String> {
+        let account = self
+            .get_account_with_commitment(nonce_account, self.commitment())
+            .await
+            .map_err(|e| e.to_string())?
+            .value
+            .ok_or_else(|| format!("Nonce account {} not found", nonce_account))?;
+        nonce_utils::data_from_account(&account)
+            .map(|data| data.blockhash())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn send(&self, transaction: &Transaction) -> Result<(), String> {
+        // The engine rebroadcasts; the node should not retry on its own
+        let config = RpcSendTransactionConfig {
+            skip_preflight: true,
+            max_retries: Some(0),
+            ..RpcSendTransactionConfig::default()
+        };
+        self.send_transaction_with_config(transaction, config)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn signature_status(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<Result<(), TransactionError>>, String> {
+        let statuses = self
+            .get_signature_statuses_with_history(&[*signature])
+            .await
+            .map_err(|e| e.to_string())?;
+        match statuses.value.into_iter().next().flatten() {
+            None => Ok(None),
+            Some(status) if status.satisfies_commitment(self.commitment()) => {
+                Ok(Some(status.status))
+            }
+            // Processed on a fork that may still be dropped: neither landed
+            // nor safe to re-sign
+            Some(_) => Err("Awaiting confirmation".to_string()),
+        }
+    }
+}
+
+/// A durable nonce account and the authority that advances it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NonceAccount {
+    pub address: Pubkey,
+    pub authority: Pubkey,
+}
+
+/// What bounds the lifetime of the transactions the engine signs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lifetime {
+    /// A recent blockhash, valid for about 150 blocks
+    RecentBlockhash,
+    /// A pool of durable nonces, one per transaction in flight; their
+    /// authorities must be among the signers
+    DurableNonce(Vec<NonceAccount>),
+}
+
+/// Tuning of the submission loop
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubmissionConfig {
+    pub lifetime: Lifetime,
+    /// Transactions sent but not yet resolved
+    pub max_in_flight: usize,
+    /// Wait between status polls of the transactions in flight
+    pub poll_interval: Duration,
+    /// Wait before rebroadcasting a transaction that has not landed
+    pub rebroadcast_interval: Duration,
+    /// Age after which a new blockhash is fetched for the next transaction
+    pub blockhash_max_age: Duration,
+    /// Times one operation is re-signed after expiring before it is
+    /// abandoned
+    pub max_resigns: u32,
+}
+
+impl Default for SubmissionConfig {
+    fn default() -> Self {
+        Self {
+            lifetime: Lifetime::RecentBlockhash,
+            max_in_flight: 32,
+            poll_interval: Duration::from_millis(400),
+            rebroadcast_interval: Duration::from_secs(2),
+            blockhash_max_age: Duration::from_secs(20),
+            max_resigns: 5,
+        }
+    }
+}
+
+/// One operation: instructions that must land together, once
+#[derive(Debug, Clone)]
+pub struct Operation {
+    pub label: String,
+    pub instructions: Vec<Instruction>,
+}
+
+impl Operation {
+    pub fn new(label: &str, instructions: Vec<Instruction>) -> Self {
+        Self {
+            label: label.to_string(),
+            instructions,
+        }
+    }
+}
+
+/// How an operation ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Landed under `signature` after `resigns` expired attempts
+    Landed { signature: Signature, resigns: u32 },
+    /// Landed under `signature` and failed; not retried
+    Failed {
+        signature: Signature,
+        error: TransactionError,
+    },
+    /// Never landed: it could not be signed, or expired too often
+    Abandoned { reason: String },
+}
+
+/// Outcome of every operation, in submission order, and what it took
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubmissionReport {
+    pub outcomes: Vec<(String, Outcome)>,
+    /// Transactions signed and sent for the first time
+    pub sends: u64,
+    /// Sends of a transaction already sent, under the same signature
+    pub rebroadcasts: u64,
+    /// Operations signed again after their transaction expired
+    pub resigns: u64,
+}
+
+impl SubmissionReport {
+    /// Operations that landed successfully
+    pub fn landed(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|(_, outcome)| matches!(outcome, Outcome::Landed { .. }))
+            .count()
+    }
+
+    pub fn all_landed(&self) -> bool {
+        self.landed() == self.outcomes.len()
+    }
+}
+
+/// A signed transaction awaiting its outcome
+struct InFlight {
+    operation: usize,
+    transaction: Transaction,
+    /// Index into the nonce pool, for durable nonce transactions
+    nonce: Option<usize>,
+    resigns: u32,
+    last_sent: Instant,
+}
+
+/// What one poll found out about a transaction in flight
+enum Poll {
+    Landed(Result<(), TransactionError>),
+    /// Can still land
+    Pending,
+    /// Can no longer land and has not
+    Expired,
+    /// A query failed; nothing is known
+    Unknown,
+}
+
+/// Submits operations at most once each over a [`SubmissionCluster`]
+pub struct SubmissionEngine<C> {
+    cluster: C,
+    config: SubmissionConfig,
+    blockhash: Option<(Hash, Instant)>,
+}
+
+impl<C: SubmissionCluster> SubmissionEngine<C> {
+    pub fn new(cluster: C, config: SubmissionConfig) -> Self {
+        Self {
+            cluster,
+            config,
+            blockhash: None,
+        }
+    }
+
+    pub fn cluster(&self) -> &C {
+        &self.cluster
+    }
+
+    pub fn config(&self) -> &SubmissionConfig {
+        &self.config
+    }
+
+    /// Submit every operation, fees paid by `payer`, each transaction signed
+    /// by whichever of `signers` it requires
+    ///
+    /// Returns once every operation has landed, failed or been abandoned.
+    pub async fn submit_all(
+        &mut self,
+        operations: Vec<Operation>,
+        payer: &Keypair,
+        signers: &[&Keypair],
+    ) -> SubmissionReport {
+        let mut report = SubmissionReport::default();
+        let mut outcomes: Vec<Option<Outcome>> = vec![None; operations.len()];
+        let mut pending: VecDeque<(usize, u32)> = (0..operations.len()).map(|i| (i, 0)).collect();
+        let mut in_flight: Vec<InFlight> = Vec::new();
+        let mut free_nonces: Vec<usize> = match &self.config.lifetime {
+            Lifetime::RecentBlockhash => Vec::new(),
+            Lifetime::DurableNonce(pool) => (0..pool.len()).rev().collect(),
+        };
+        let mut signers = signers.to_vec();
+        signers.push(payer);
+
+        loop {
+            while in_flight.len() < self.config.max_in_flight {
+                let nonce = match self.config.lifetime {
+                    Lifetime::RecentBlockhash => None,
+                    Lifetime::DurableNonce(_) if free_nonces.is_empty() => break,
+                    Lifetime::DurableNonce(_) => free_nonces.pop(),
+                };
+                let Some((operation, resigns)) = pending.pop_front() else {
+                    free_nonces.extend(nonce);
+                    break;
+                };
+                let signed = self
+                    .sign(&operations[operation].instructions, nonce, payer, &signers)
+                    .await;
+                match signed {
+                    Ok(transaction) => {
+                        // A failed send is resolved by polling like a lost one
+                        let _ = self.cluster.send(&transaction).await;
+                        report.sends += 1;
+                        in_flight.push(InFlight {
+                            operation,
+                            transaction,
+                            nonce,
+                            resigns,
+                            last_sent: Instant::now(),
+                        });
+                    }
+                    Err(reason) => {
+                        free_nonces.extend(nonce);
+                        outcomes[operation] = Some(Outcome::Abandoned { reason });
+                    }
+                }
+            }
+            if in_flight.is_empty() && pending.is_empty() {
+                break;
+            }
+
+            tokio::time::sleep(self.config.poll_interval).await;
+            let mut unresolved = Vec::with_capacity(in_flight.len());
+            for mut flight in in_flight.drain(..) {
+                let signature = flight.transaction.signatures[0];
+                match self.poll(&flight).await {
+                    Poll::Landed(result) => {
+                        free_nonces.extend(flight.nonce);
+                        outcomes[flight.operation] = Some(match result {
+                            Ok(()) => Outcome::Landed {
+                                signature,
+                                resigns: flight.resigns,
+                            },
+                            Err(error) => Outcome::Failed { signature, error },
+                        });
+                    }
+                    Poll::Pending => {
+                        if flight.last_sent.elapsed() >= self.config.rebroadcast_interval {
+                            let _ = self.cluster.send(&flight.transaction).await;
+                            report.rebroadcasts += 1;
+                            flight.last_sent = Instant::now();
+                        }
+                        unresolved.push(flight);
+                    }
+                    Poll::Expired => {
+                        free_nonces.extend(flight.nonce);
+                        let blockhash = flight.transaction.message.recent_blockhash;
+                        if self
+                            .blockhash
+                            .is_some_and(|(cached, _)| cached == blockhash)
+                        {
+                            self.blockhash = None;
+                        }
+                        if flight.resigns < self.config.max_resigns {
+                            report.resigns += 1;
+                            pending.push_front((flight.operation, flight.resigns + 1));
+                        } else {
+                            outcomes[flight.operation] = Some(Outcome::Abandoned {
+                                reason: format!("Expired {} times", flight.resigns + 1),
+                            });
+                        }
+                    }
+                    Poll::Unknown => unresolved.push(flight),
+                }
+            }
+            in_flight = unresolved;
+        }
+
+        report.outcomes = operations
+            .into_iter()
+            .zip(outcomes)
+            .map(|(operation, outcome)| {
+                let outcome = outcome.unwrap_or_else(|| Outcome::Abandoned {
+                    reason: "Never resolved".to_string(),
+                });
+                (operation.label, outcome)
+            })
+            .collect();
+        report
+    }
+
+    /// The cached blockhash, or a new one once it is too old
+    async fn recent_blockhash(&mut self) -> Result<Hash, String> {
+        if let Some((blockhash, fetched)) = self.blockhash {
+            if fetched.elapsed() < self.config.blockhash_max_age {
+                return Ok(blockhash);
+            }
+        }
+        let blockhash = self.cluster.latest_blockhash().await?;
+        self.blockhash = Some((blockhash, Instant::now()));
+        Ok(blockhash)
+    }
+
+    fn nonce_account(&self, index: usize) -> NonceAccount {
+        match &self.config.lifetime {
+            Lifetime::DurableNonce(pool) => pool[index],
+            Lifetime::RecentBlockhash => unreachable!("no nonce pool"),
+        }
+    }
+
+    async fn sign(
+        &mut self,
+        instructions: &[Instruction],
+        nonce: Option<usize>,
+        payer: &Keypair,
+        signers: &[&Keypair],
+    ) -> Result<Transaction, String> {
+        let (instructions, blockhash) = match nonce.map(|index| self.nonce_account(index)) {
+            Some(account) => {
+                let advance =
+                    system_instruction::advance_nonce_account(&account.address, &account.authority);
+                let instructions = std::iter::once(advance)
+                    .chain(instructions.iter().cloned())
+                    .collect();
+                (
+                    instructions,
+                    self.cluster.nonce_blockhash(&account.address).await?,
+                )
+            }
+            None => (instructions.to_vec(), self.recent_blockhash().await?),
+        };
+
+        let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
+        let message = &transaction.message;
+        let required = &message.account_keys[..message.header.num_required_signatures as usize];
+        let relevant: Vec<&Keypair> = signers
+            .iter()
+            .copied()
+            .filter(|signer| required.contains(&signer.pubkey()))
+            .collect();
+        transaction
+            .try_partial_sign(&relevant, blockhash)
+            .map_err(|e| format!("Signing failed: {}", e))?;
+        if !transaction.is_signed() {
+            return Err("Missing a required signature".to_string());
+        }
+        Ok(transaction)
+    }
+
+    async fn poll(&self, flight: &InFlight) -> Poll {
+        let signature = &flight.transaction.signatures[0];
+        match self.cluster.signature_status(signature).await {
+            Ok(Some(result)) => return Poll::Landed(result),
+            Ok(None) => {}
+            Err(_) => return Poll::Unknown,
+        }
+
+        let blockhash = &flight.transaction.message.recent_blockhash;
+        let live = match flight.nonce.map(|index| self.nonce_account(index)) {
+            Some(account) => self
+                .cluster
+                .nonce_blockhash(&account.address)
+                .await
+                .map(|current| current == *blockhash),
+            None => self.cluster.is_blockhash_valid(blockhash).await,
+        };
+        match live {
+            Ok(true) => return Poll::Pending,
+            Ok(false) => {}
+            Err(_) => return Poll::Unknown,
+        }
+
+        // It may have landed between the two queries, advancing its own
+        // nonce; only a miss after the lifetime ended proves it never will
+        match self.cluster.signature_status(signature).await {
+            Ok(Some(result)) => Poll::Landed(result),
+            Ok(None) => Poll::Expired,
+            Err(_) => Poll::Unknown,
+        }
+    }
+}
@@ -0,0 +1,423 @@
+//! Per-category enablement of expensive suites
+//!
+//! Heavy categories (load tests, fuzz smoke, devnet runs, long scenarios, the
+//! suite self-test, live-cluster experiments) can be switched on and off individually instead of
+//! running everything or nothing. The configuration is read once per test
+//! binary from:
+//!
+//! 1. `suite.toml` at the repository root, or the file named by
+//!    `TOKEN_ACL_SUITE_CONFIG`
+//! 2. Flags in `TOKEN_ACL_SUITE_ARGS`, applied on top
+//!    (`--enable <category>`, `--disable <category>`, `--only <a,b>`)
+//!
+//! `scripts/run_comprehensive_tests.sh` accepts the same flags and forwards
+//! them. Gated tests report as skipped, and every generated report lists the
+//! disabled categories.
+//!
+//! The optional `[budgets]` table caps the net lamports a single test of a
+//! category may spend on a live cluster (see [`crate::metrics`]); devnet
+//! tests default to [`DEFAULT_DEVNET_BUDGET`].
+//!
+//! `--keep-state` leaves the accounts a live run created in place instead of
+//! returning them to the faucet (see [`crate::teardown`]), for inspecting a
+//! failed run.
+//!
+//! `--deactivate-feature <id,...>`, or the `[features]` table, starts every
+//! [`crate::context::TestContext`] bank without those runtime feature gates,
+//! so the whole suite runs as a cluster that has not activated them yet.
+//!
+//! ```toml
+//! [categories]
+//! load_tests = true
+//! fuzz_smoke = true
+//! devnet = false
+//! long_scenarios = true
+//! self_test = true
+//! experiments = false
+//!
+//! [budgets]
+//! devnet = 100000000
+//!
+//! [features]
+//! deactivated = ["k6uR1J9VtKJnTukBV2Eo15BEy434MBg8bT6hHQgmU8v"]
+//! ```
+
+use serde::Deserialize;
+use solana_sdk::{feature_set::FEATURE_NAMES, pubkey::Pubkey};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, fs,
+    path::Path,
+    str::FromStr,
+    sync::OnceLock,
+};
+
+use crate::TestResultReport;
+
+/// Configuration file read when `TOKEN_ACL_SUITE_CONFIG` is unset
+pub const DEFAULT_CONFIG_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../suite.toml");
+
+/// Environment variable naming an alternative configuration file
+pub const CONFIG_ENV: &str = "TOKEN_ACL_SUITE_CONFIG";
+
+/// Environment variable holding `--enable` / `--disable` / `--only` flags
+pub const ARGS_ENV: &str = "TOKEN_ACL_SUITE_ARGS";
+
+/// Net lamports one devnet test may spend unless `suite.toml` says otherwise
+pub const DEFAULT_DEVNET_BUDGET: u64 = 100_000_000;
+
+/// An expensive test category that can be disabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SuiteCategory {
+    /// Many-mint and high-iteration load runs
+    LoadTests,
+    /// Randomized input smoke runs
+    FuzzSmoke,
+    /// Runs against the public devnet cluster
+    Devnet,
+    /// Multi-step end-to-end scenarios
+    LongScenarios,
+    /// Known-bad fixtures the suite must report as failures
+    SelfTest,
+    /// Performance experiments against a live cluster
+    Experiments,
+}
+
+impl SuiteCategory {
+    /// Every category, in report order
+    pub const ALL: [SuiteCategory; 6] = [
+        SuiteCategory::LoadTests,
+        SuiteCategory::FuzzSmoke,
+        SuiteCategory::Devnet,
+        SuiteCategory::LongScenarios,
+        SuiteCategory::SelfTest,
+        SuiteCategory::Experiments,
+    ];
+
+    /// Name used in `suite.toml` and on the command line
+    pub fn key(self) -> &'static str {
+        match self {
+            SuiteCategory::LoadTests => "load_tests",
+            SuiteCategory::FuzzSmoke => "fuzz_smoke",
+            SuiteCategory::Devnet => "devnet",
+            SuiteCategory::LongScenarios => "long_scenarios",
+            SuiteCategory::SelfTest => "self_test",
+            SuiteCategory::Experiments => "experiments",
+        }
+    }
+
+    /// One-line description for reports
+    pub fn description(self) -> &'static str {
+        match self {
+            SuiteCategory::LoadTests => "Many-mint and high-iteration load runs",
+            SuiteCategory::FuzzSmoke => "Randomized input smoke runs",
+            SuiteCategory::Devnet => "Runs against the public devnet cluster",
+            SuiteCategory::LongScenarios => "Multi-step end-to-end scenarios",
+            SuiteCategory::SelfTest => "Known-bad fixtures the suite must report as failures",
+            SuiteCategory::Experiments => "Performance experiments against a live cluster",
+        }
+    }
+}
+
+impl fmt::Display for SuiteCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.key())
+    }
+}
+
+impl FromStr for SuiteCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized = s.trim().replace('-', "_");
+        SuiteCategory::ALL
+            .into_iter()
+            .find(|category| category.key() == normalized)
+            .ok_or_else(|| {
+                let known: Vec<&str> = SuiteCategory::ALL.iter().map(|c| c.key()).collect();
+                format!(
+                    "Unknown suite category '{}' (expected one of: {})",
+                    s,
+                    known.join(", ")
+                )
+            })
+    }
+}
+
+/// Which heavy categories run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuiteConfig {
+    disabled: BTreeSet<SuiteCategory>,
+    budgets: BTreeMap<SuiteCategory, u64>,
+    keep_state: bool,
+    deactivated_features: BTreeSet<Pubkey>,
+}
+
+/// Devnet and experiments are off by default: they need network access and
+/// a funded keypair
+impl Default for SuiteConfig {
+    fn default() -> Self {
+        Self {
+            disabled: BTreeSet::from([SuiteCategory::Devnet, SuiteCategory::Experiments]),
+            budgets: BTreeMap::from([(SuiteCategory::Devnet, DEFAULT_DEVNET_BUDGET)]),
+            keep_state: false,
+            deactivated_features: BTreeSet::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    categories: CategoryTable<bool>,
+    #[serde(default)]
+    budgets: CategoryTable<u64>,
+    #[serde(default)]
+    features: FeatureTable,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct FeatureTable {
+    #[serde(default)]
+    deactivated: Vec<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct CategoryTable<T> {
+    load_tests: Option<T>,
+    fuzz_smoke: Option<T>,
+    devnet: Option<T>,
+    long_scenarios: Option<T>,
+    self_test: Option<T>,
+    experiments: Option<T>,
+}
+
+impl<T: Copy> CategoryTable<T> {
+    fn get(&self, category: SuiteCategory) -> Option<T> {
+        match category {
+            SuiteCategory::LoadTests => self.load_tests,
+            SuiteCategory::FuzzSmoke => self.fuzz_smoke,
+            SuiteCategory::Devnet => self.devnet,
+            SuiteCategory::LongScenarios => self.long_scenarios,
+            SuiteCategory::SelfTest => self.self_test,
+            SuiteCategory::Experiments => self.experiments,
+        }
+    }
+}
+
+impl SuiteConfig {
+    /// Every category enabled
+    pub fn all_enabled() -> Self {
+        Self {
+            disabled: BTreeSet::new(),
+            ..Self::default()
+        }
+    }
+
+    /// Parse a `suite.toml`; categories it does not mention keep their defaults
+    pub fn from_toml_str(text: &str) -> Result<Self, String> {
+        let file: ConfigFile =
+            toml::from_str(text).map_err(|e| format!("Invalid suite config: {}", e))?;
+        let mut config = Self::default();
+        for category in SuiteCategory::ALL {
+            match file.categories.get(category) {
+                Some(true) => config.enable(category),
+                Some(false) => config.disable(category),
+                None => {}
+            }
+            if let Some(budget) = file.budgets.get(category) {
+                config.set_budget(category, budget);
+            }
+        }
+        for feature in &file.features.deactivated {
+            config.deactivate_feature(parse_feature(feature)?);
+        }
+        Ok(config)
+    }
+
+    /// Read a `suite.toml` from disk
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::from_toml_str(&text)
+    }
+
+    /// Build the configuration from the config file and `TOKEN_ACL_SUITE_ARGS`
+    pub fn from_env() -> Result<Self, String> {
+        let mut config = match std::env::var(CONFIG_ENV) {
+            Ok(path) => Self::load(Path::new(&path))?,
+            Err(_) if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Self::load(Path::new(DEFAULT_CONFIG_PATH))?
+            }
+            Err(_) => Self::default(),
+        };
+        if let Ok(args) = std::env::var(ARGS_ENV) {
+            config.apply_args(args.split_whitespace())?;
+        }
+        Ok(config)
+    }
+
+    /// Apply `--enable <category>`, `--disable <category>`, `--only <a,b>`,
+    /// `--keep-state` and `--deactivate-feature <id,...>` flags in order;
+    /// `all` names every category
+    pub fn apply_args<I, S>(&mut self, args: I) -> Result<(), String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let args: Vec<String> = args.into_iter().map(|a| a.as_ref().to_string()).collect();
+        let mut index = 0;
+        while index < args.len() {
+            let (flag, inline) = match args[index].split_once('=') {
+                Some((flag, value)) => (flag, Some(value.to_string())),
+                None => (args[index].as_str(), None),
+            };
+            if flag == "--keep-state" {
+                if inline.is_some() {
+                    return Err("--keep-state takes no value".to_string());
+                }
+                self.keep_state = true;
+                index += 1;
+                continue;
+            }
+            let value = match inline {
+                Some(value) => value,
+                None => {
+                    index += 1;
+                    args.get(index)
+                        .cloned()
+                        .ok_or_else(|| format!("{} needs a value", flag))?
+                }
+            };
+            if flag == "--deactivate-feature" {
+                for feature in value.split(',').filter(|id| !id.trim().is_empty()) {
+                    self.deactivate_feature(parse_feature(feature)?);
+                }
+                index += 1;
+                continue;
+            }
+            let categories = parse_categories(&value)?;
+            match flag {
+                "--enable" => categories.into_iter().for_each(|c| self.enable(c)),
+                "--disable" => categories.into_iter().for_each(|c| self.disable(c)),
+                "--only" => {
+                    self.disabled = SuiteCategory::ALL.into_iter().collect();
+                    categories.into_iter().for_each(|c| self.enable(c));
+                }
+                other => return Err(format!("Unknown suite flag '{}'", other)),
+            }
+            index += 1;
+        }
+        Ok(())
+    }
+
+    /// Turn a category on
+    pub fn enable(&mut self, category: SuiteCategory) {
+        self.disabled.remove(&category);
+    }
+
+    /// Turn a category off
+    pub fn disable(&mut self, category: SuiteCategory) {
+        self.disabled.insert(category);
+    }
+
+    /// Whether tests in `category` should run
+    pub fn is_enabled(&self, category: SuiteCategory) -> bool {
+        !self.disabled.contains(&category)
+    }
+
+    /// Cap the net lamports one test of `category` may spend
+    pub fn set_budget(&mut self, category: SuiteCategory, lamports: u64) {
+        self.budgets.insert(category, lamports);
+    }
+
+    /// Net lamports one test of `category` may spend, if capped
+    pub fn budget(&self, category: SuiteCategory) -> Option<u64> {
+        self.budgets.get(&category).copied()
+    }
+
+    /// Leave live-run accounts in place instead of closing them
+    pub fn set_keep_state(&mut self, keep_state: bool) {
+        self.keep_state = keep_state;
+    }
+
+    /// Whether `--keep-state` was given
+    pub fn keep_state(&self) -> bool {
+        self.keep_state
+    }
+
+    /// Start test banks without the runtime feature gate `feature`
+    pub fn deactivate_feature(&mut self, feature: Pubkey) {
+        self.deactivated_features.insert(feature);
+    }
+
+    /// Feature gates test banks start without
+    pub fn deactivated_features(&self) -> Vec<Pubkey> {
+        self.deactivated_features.iter().copied().collect()
+    }
+
+    /// Disabled categories, in report order
+    pub fn disabled(&self) -> Vec<SuiteCategory> {
+        self.disabled.iter().copied().collect()
+    }
+}
+
+fn parse_categories(value: &str) -> Result<Vec<SuiteCategory>, String> {
+    if value == "all" {
+        return Ok(SuiteCategory::ALL.to_vec());
+    }
+    value
+        .split(',')
+        .filter(|name| !name.trim().is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// A feature id the runtime knows; anything else would deactivate nothing
+fn parse_feature(id: &str) -> Result<Pubkey, String> {
+    let feature: Pubkey = id
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid feature id '{}'", id))?;
+    if !FEATURE_NAMES.contains_key(&feature) {
+        return Err(format!("Unknown runtime feature '{}'", id));
+    }
+    Ok(feature)
+}
+
+/// The configuration for this test binary, read once from the environment
+///
+/// # Panics
+///
+/// Panics if the config file or flags are invalid, so a typo cannot silently
+/// run (or skip) the wrong suites.
+pub fn current() -> &'static SuiteConfig {
+    static CURRENT: OnceLock<SuiteConfig> = OnceLock::new();
+    CURRENT.get_or_init(|| SuiteConfig::from_env().unwrap_or_else(|e| panic!("{}", e)))
+}
+
+/// A skipped report for `test_name` if `category` is disabled
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use token_acl_integration_tests::{suite_config::{self, SuiteCategory}, TestResultReport};
+/// fn run_load_test() -> TestResultReport {
+///     let test_name = "Load Test";
+///     if let Some(skipped) = suite_config::skip_unless(SuiteCategory::LoadTests, test_name) {
+///         return skipped;
+///     }
+///     TestResultReport::success(test_name, 1)
+/// }
+/// ```
+pub fn skip_unless(category: SuiteCategory, test_name: &str) -> Option<TestResultReport> {
+    if current().is_enabled(category) {
+        None
+    } else {
+        println!("⏭️  Skipping {} ({} disabled)", test_name, category);
+        Some(TestResultReport::skipped(test_name, category))
+    }
+}
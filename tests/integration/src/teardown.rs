@@ -0,0 +1,365 @@
+//! Cleanup of temporary accounts after live runs
+//!
+//! A devnet run creates wallets, token accounts, nonce accounts and gate
+//! records. Left behind, each strands its rent, and every funded wallet its
+//! funding: dozens of accounts per run. Tests register what they create with
+//! a [`Teardown`], which at the end of the run returns every lamport those
+//! accounts hold to the faucet wallet that funded the run:
+//!
+//! - wallets are swept,
+//! - token accounts are burned empty and closed,
+//! - nonce accounts are withdrawn,
+//! - gate accounts are closed by the instruction registered with them, which
+//!   must send the rent to [`Teardown::faucet`].
+//!
+//! The faucet pays the cleanup fees, so a wallet can be swept to zero even
+//! if it signs other closes. Accounts that are already gone are skipped.
+//!
+//! With `--keep-state` (see [`crate::suite_config`]) nothing is closed and
+//! the report lists what was left in place, for inspecting a failed run.
+
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_program_test::BanksClient;
+use solana_sdk::{
+    account::Account,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction,
+};
+use spl_token_2022::{
+    extension::StateWithExtensions, instruction as token_instruction,
+    state::Account as TokenAccount,
+};
+
+use crate::{
+    submission::{Operation, Outcome, SubmissionCluster, SubmissionEngine},
+    suite_config, units,
+};
+
+/// Reads the accounts to clean up
+#[allow(async_fn_in_trait)]
+pub trait AccountFetcher {
+    /// The account at `address`, `None` if it does not exist
+    async fn account(&self, address: &Pubkey) -> Result<Option<Account>, String>;
+}
+
+impl AccountFetcher for BanksClient {
+    async fn account(&self, address: &Pubkey) -> Result<Option<Account>, String> {
+        self.clone()
+            .get_account(*address)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl AccountFetcher for RpcClient {
+    async fn account(&self, address: &Pubkey) -> Result<Option<Account>, String> {
+        self.get_account_with_commitment(address, self.commitment())
+            .await
+            .map(|response| response.value)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// An account a test created and the keys needed to close it
+#[derive(Debug)]
+pub enum TemporaryAccount {
+    /// A system wallet; swept to the faucet
+    Wallet(Keypair),
+    /// A token account; any balance is burned, then it is closed
+    TokenAccount { address: Pubkey, owner: Keypair },
+    /// A durable nonce account; withdrawn in full
+    NonceAccount { address: Pubkey, authority: Keypair },
+    /// A gate-owned account, closed by `close`
+    GateAccount {
+        address: Pubkey,
+        close: Instruction,
+        signers: Vec<Keypair>,
+    },
+}
+
+impl TemporaryAccount {
+    pub fn address(&self) -> Pubkey {
+        match self {
+            TemporaryAccount::Wallet(keypair) => keypair.pubkey(),
+            TemporaryAccount::TokenAccount { address, .. }
+            | TemporaryAccount::NonceAccount { address, .. }
+            | TemporaryAccount::GateAccount { address, .. } => *address,
+        }
+    }
+
+    fn signers(&self) -> Vec<&Keypair> {
+        match self {
+            TemporaryAccount::Wallet(keypair) => vec![keypair],
+            TemporaryAccount::TokenAccount { owner, .. } => vec![owner],
+            TemporaryAccount::NonceAccount { authority, .. } => vec![authority],
+            TemporaryAccount::GateAccount { signers, .. } => signers.iter().collect(),
+        }
+    }
+
+    /// The instructions returning `account` to `faucet`
+    fn close_instructions(
+        &self,
+        account: &Account,
+        faucet: &Pubkey,
+    ) -> Result<Vec<Instruction>, String> {
+        match self {
+            TemporaryAccount::Wallet(keypair) => Ok(vec![system_instruction::transfer(
+                &keypair.pubkey(),
+                faucet,
+                account.lamports,
+            )]),
+            TemporaryAccount::TokenAccount { address, owner } => {
+                let state = StateWithExtensions::<TokenAccount>::unpack(&account.data)
+                    .map_err(|e| format!("Not a token account: {}", e))?;
+                let program = account.owner;
+                let mut instructions = Vec::new();
+                if state.base.amount > 0 {
+                    instructions.push(
+                        token_instruction::burn(
+                            &program,
+                            address,
+                            &state.base.mint,
+                            &owner.pubkey(),
+                            &[],
+                            state.base.amount,
+                        )
+                        .map_err(|e| e.to_string())?,
+                    );
+                }
+                instructions.push(
+                    token_instruction::close_account(
+                        &program,
+                        address,
+                        faucet,
+                        &owner.pubkey(),
+                        &[],
+                    )
+                    .map_err(|e| e.to_string())?,
+                );
+                Ok(instructions)
+            }
+            TemporaryAccount::NonceAccount { address, authority } => {
+                Ok(vec![system_instruction::withdraw_nonce_account(
+                    address,
+                    &authority.pubkey(),
+                    faucet,
+                    account.lamports,
+                )])
+            }
+            TemporaryAccount::GateAccount { close, .. } => Ok(vec![close.clone()]),
+        }
+    }
+}
+
+/// What happened to one registered account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cleanup {
+    /// Closed, its lamports returned to the faucet
+    Closed { lamports: u64 },
+    /// Left in place by `--keep-state`
+    Kept { lamports: u64 },
+    /// Did not exist when the teardown ran
+    Gone,
+    /// Could not be closed; its lamports are stranded
+    Failed { lamports: u64, reason: String },
+}
+
+/// Every registered account and how its cleanup ended
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TeardownReport {
+    pub accounts: Vec<(String, Pubkey, Cleanup)>,
+    /// Whether `--keep-state` left everything in place
+    pub kept_state: bool,
+}
+
+impl TeardownReport {
+    /// Lamports returned to the faucet
+    pub fn returned(&self) -> u64 {
+        self.accounts
+            .iter()
+            .map(|(_, _, cleanup)| match cleanup {
+                Cleanup::Closed { lamports } => *lamports,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// Lamports still held by registered accounts
+    pub fn stranded(&self) -> u64 {
+        self.accounts
+            .iter()
+            .map(|(_, _, cleanup)| match cleanup {
+                Cleanup::Kept { lamports } | Cleanup::Failed { lamports, .. } => *lamports,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// Accounts that could not be closed
+    pub fn failures(&self) -> Vec<&(String, Pubkey, Cleanup)> {
+        self.accounts
+            .iter()
+            .filter(|(_, _, cleanup)| matches!(cleanup, Cleanup::Failed { .. }))
+            .collect()
+    }
+
+    /// A markdown section listing what was closed, kept and stranded
+    pub fn render_markdown(&self) -> String {
+        let mut section = String::from("## Teardown\n\n");
+        if self.kept_state {
+            section.push_str(&format!(
+                "`--keep-state` left {} accounts holding {} in place.\n\n",
+                self.accounts.len(),
+                units::sol_and_lamports(self.stranded())
+            ));
+        } else {
+            section.push_str(&format!(
+                "Returned {} to the faucet; {} failed, {} stranded.\n\n",
+                units::sol_and_lamports(self.returned()),
+                self.failures().len(),
+                units::lamports(self.stranded())
+            ));
+        }
+        section.push_str("| Account | Address | Cleanup |\n");
+        section.push_str("|---------|---------|---------|\n");
+        for (label, address, cleanup) in &self.accounts {
+            let outcome = match cleanup {
+                Cleanup::Closed { lamports } => format!("closed, {}", units::lamports(*lamports)),
+                Cleanup::Kept { lamports } => format!("kept, {}", units::lamports(*lamports)),
+                Cleanup::Gone => "already gone".to_string(),
+                Cleanup::Failed { lamports, reason } => {
+                    format!(
+                        "failed, {} stranded: {}",
+                        units::lamports(*lamports),
+                        reason
+                    )
+                }
+            };
+            section.push_str(&format!("| {} | `{}` | {} |\n", label, address, outcome));
+        }
+        section.push('\n');
+        section
+    }
+}
+
+/// The temporary accounts of a run, closed to `faucet` at its end
+#[derive(Debug)]
+pub struct Teardown {
+    faucet: Pubkey,
+    accounts: Vec<(String, TemporaryAccount)>,
+    keep_state: bool,
+}
+
+impl Teardown {
+    /// Return everything to `faucet`, unless the suite runs with
+    /// `--keep-state`
+    pub fn new(faucet: Pubkey) -> Self {
+        Self {
+            faucet,
+            accounts: Vec::new(),
+            keep_state: suite_config::current().keep_state(),
+        }
+    }
+
+    /// Override `--keep-state` for this teardown
+    pub fn with_keep_state(mut self, keep_state: bool) -> Self {
+        self.keep_state = keep_state;
+        self
+    }
+
+    /// Where closed accounts' lamports go; gate close instructions must
+    /// refund here
+    pub fn faucet(&self) -> Pubkey {
+        self.faucet
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    /// Register `account` under `label`, e.g. "holder 3"
+    pub fn register(&mut self, label: &str, account: TemporaryAccount) {
+        self.accounts.push((label.to_string(), account));
+    }
+
+    /// Close every registered account through `engine`, fees paid by
+    /// `faucet`
+    ///
+    /// Each account is closed for the lamports it holds now, so a close that
+    /// paid out to a registered wallet instead of the faucet would strand
+    /// them there.
+    pub async fn run<C: SubmissionCluster + AccountFetcher>(
+        self,
+        engine: &mut SubmissionEngine<C>,
+        faucet: &Keypair,
+    ) -> TeardownReport {
+        // Cleanup per registered account, and the closes still to submit
+        let mut cleanups: Vec<Option<Cleanup>> = vec![None; self.accounts.len()];
+        let mut closing: Vec<(usize, u64, Operation)> = Vec::new();
+        for (index, (label, account)) in self.accounts.iter().enumerate() {
+            cleanups[index] = Some(match engine.cluster().account(&account.address()).await {
+                Ok(None) => Cleanup::Gone,
+                Ok(Some(state)) if state.lamports == 0 => Cleanup::Gone,
+                Ok(Some(state)) if self.keep_state => Cleanup::Kept {
+                    lamports: state.lamports,
+                },
+                Ok(Some(state)) => match account.close_instructions(&state, &self.faucet) {
+                    Ok(instructions) => {
+                        closing.push((index, state.lamports, Operation::new(label, instructions)));
+                        continue;
+                    }
+                    Err(reason) => Cleanup::Failed {
+                        lamports: state.lamports,
+                        reason,
+                    },
+                },
+                Err(e) => Cleanup::Failed {
+                    lamports: 0,
+                    reason: format!("Could not read the account: {}", e),
+                },
+            });
+        }
+
+        let signers: Vec<&Keypair> = closing
+            .iter()
+            .flat_map(|(index, _, _)| self.accounts[*index].1.signers())
+            .collect();
+        let operations = closing.iter().map(|(_, _, op)| op.clone()).collect();
+        let submission = engine.submit_all(operations, faucet, &signers).await;
+
+        // Outcomes come back in submission order
+        for ((index, lamports, _), (_, outcome)) in closing.iter().zip(submission.outcomes) {
+            let lamports = *lamports;
+            cleanups[*index] = Some(match outcome {
+                Outcome::Landed { .. } => Cleanup::Closed { lamports },
+                Outcome::Failed { error, .. } => Cleanup::Failed {
+                    lamports,
+                    reason: error.to_string(),
+                },
+                Outcome::Abandoned { reason } => Cleanup::Failed { lamports, reason },
+            });
+        }
+
+        TeardownReport {
+            accounts: self
+                .accounts
+                .iter()
+                .zip(cleanups)
+                .map(|((label, account), cleanup)| {
+                    (
+                        label.clone(),
+                        account.address(),
+                        cleanup.unwrap_or(Cleanup::Gone),
+                    )
+                })
+                .collect(),
+            kept_state: self.keep_state,
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! Report timestamps and on-chain time conversions
+//!
+//! All wall-clock reads go through [`now`], so report output can be made
+//! deterministic with [`mock_now`]. Formatting and parsing work on Unix
+//! timestamps (the on-chain `UnixTimestamp` and RPC `block_time`
+//! representation) and always normalize to UTC.
+//!
+//! The mock is per thread: `#[tokio::test]` runs each test on its own
+//! current-thread runtime, so parallel tests cannot see each other's clocks.
+
+use solana_sdk::clock::UnixTimestamp;
+use std::{
+    cell::Cell,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+thread_local! {
+    static MOCK_NOW: Cell<Option<UnixTimestamp>> = const { Cell::new(None) };
+}
+
+/// Current Unix time in seconds, or the mocked time on this thread
+pub fn now() -> UnixTimestamp {
+    MOCK_NOW.with(Cell::get).unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs() as i64)
+            .unwrap_or_default()
+    })
+}
+
+/// Restores the previous clock when dropped
+#[must_use = "the mock is removed when the guard is dropped"]
+pub struct MockClockGuard {
+    previous: Option<UnixTimestamp>,
+}
+
+impl Drop for MockClockGuard {
+    fn drop(&mut self) {
+        MOCK_NOW.with(|mock| mock.set(self.previous));
+    }
+}
+
+/// Pin [`now`] to `timestamp` on this thread until the guard is dropped
+pub fn mock_now(timestamp: UnixTimestamp) -> MockClockGuard {
+    let previous = MOCK_NOW.with(|mock| mock.replace(Some(timestamp)));
+    MockClockGuard { previous }
+}
+
+/// `YYYY-MM-DD HH:MM:SS UTC`, the format of the report "Generated" lines
+pub fn format_utc(timestamp: UnixTimestamp) -> String {
+    let (year, month, day, hour, minute, second) = to_civil(timestamp);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year, month, day, hour, minute, second
+    )
+}
+
+/// RFC 3339 in UTC, e.g. `2024-10-16T09:30:00Z`
+pub fn format_rfc3339(timestamp: UnixTimestamp) -> String {
+    let (year, month, day, hour, minute, second) = to_civil(timestamp);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, hour, minute, second
+    )
+}
+
+/// Timestamp for the "Generated" line of a report
+pub fn report_timestamp() -> String {
+    format_utc(now())
+}
+
+/// Render an RPC `block_time`, which is absent for unconfirmed or pruned slots
+pub fn format_block_time(block_time: Option<UnixTimestamp>) -> String {
+    block_time
+        .map(format_utc)
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Parse an RFC 3339 timestamp with a `Z` or `±HH:MM` offset into Unix time
+///
+/// Fractional seconds are truncated.
+pub fn parse_rfc3339(text: &str) -> Result<UnixTimestamp, String> {
+    let invalid = || format!("Invalid RFC 3339 timestamp '{}'", text);
+    let bytes = text.as_bytes();
+    if bytes.len() < 20 || !matches!(bytes[10], b'T' | b't' | b' ') {
+        return Err(invalid());
+    }
+
+    let number = |range| number_at(text, range).ok_or_else(invalid);
+    let separators = [(4, b'-'), (7, b'-'), (13, b':'), (16, b':')];
+    if separators
+        .iter()
+        .any(|(index, byte)| bytes[*index] != *byte)
+    {
+        return Err(invalid());
+    }
+
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    if !(1..=12).contains(&month)
+        || day < 1
+        || day > days_in_month(year, month)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return Err(invalid());
+    }
+
+    let mut rest = &text[19..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction.bytes().take_while(u8::is_ascii_digit).count();
+        if digits == 0 {
+            return Err(invalid());
+        }
+        rest = &fraction[digits..];
+    }
+
+    let offset = match rest {
+        "Z" | "z" => 0,
+        _ if rest.len() == 6 && rest.as_bytes()[3] == b':' => {
+            let sign = match rest.as_bytes()[0] {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return Err(invalid()),
+            };
+            let hours = number_at(rest, 1..3).ok_or_else(invalid)?;
+            let minutes = number_at(rest, 4..6).ok_or_else(invalid)?;
+            sign * (hours * 3_600 + minutes * 60)
+        }
+        _ => return Err(invalid()),
+    };
+
+    let local = days_from_civil(year, month, day) * SECONDS_PER_DAY
+        + hour * 3_600
+        + minute * 60
+        + second.min(59);
+    Ok(local - offset)
+}
+
+fn number_at(text: &str, range: std::ops::Range<usize>) -> Option<i64> {
+    let field = text.get(range)?;
+    field
+        .bytes()
+        .all(|byte| byte.is_ascii_digit())
+        .then(|| field.parse().ok())?
+}
+
+fn to_civil(timestamp: UnixTimestamp) -> (i64, i64, i64, i64, i64, i64) {
+    let days = timestamp.div_euclid(SECONDS_PER_DAY);
+    let seconds = timestamp.rem_euclid(SECONDS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+    (
+        year,
+        month,
+        day,
+        seconds / 3_600,
+        seconds % 3_600 / 60,
+        seconds % 60,
+    )
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Proleptic Gregorian calendar conversions, after Howard Hinnant's
+// `days_from_civil` / `civil_from_days` (eras of 400 years starting in March)
+
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
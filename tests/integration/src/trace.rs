@@ -0,0 +1,217 @@
+//! Transaction traces for failing tests
+//!
+//! A failed transaction's logs are a flat list of `invoke`, `consumed` and
+//! `failed` lines that leave it to the reader to work out which instruction
+//! and which CPI went wrong. [`TransactionTrace`], from the client's
+//! [`token_acl_client::trace`], rebuilds the tree; [`render`] prints it with
+//! each instruction's accounts and their signer/writable flags, and under it
+//! the program's logs, compute units and the CPIs it made, all named through
+//! [`crate::labels`]. Instructions [`crate::decoder`] knows are shown with
+//! their names and the roles of their accounts.
+//!
+//! The submit helpers record every failed transaction with [`record`]. The
+//! last one recorded on the test's thread is attached to the test's report
+//! by [`TestResultReport::failure`](crate::TestResultReport::failure), and
+//! rendered under its transactions.
+
+use solana_program_test::BanksTransactionResultWithMetadata;
+use solana_sdk::{pubkey::Pubkey, transaction::Transaction};
+use std::cell::RefCell;
+pub use token_acl_client::trace::{
+    invocations, AccountUse, Event, InstructionTrace, Invocation, Outcome, TransactionTrace,
+};
+
+use crate::{
+    decoder::{self, InstructionCategory},
+    labels, units,
+};
+
+/// Trace `transaction` from what a program-test bank returned for it
+pub fn from_outcome(
+    transaction: &Transaction,
+    outcome: &BanksTransactionResultWithMetadata,
+) -> TransactionTrace {
+    let metadata = outcome.metadata.as_ref();
+    TransactionTrace::new(
+        transaction,
+        &outcome.result,
+        metadata.map_or(&[], |metadata| &metadata.log_messages),
+        metadata.map(|metadata| metadata.compute_units_consumed),
+    )
+}
+
+/// `trace` as a tree, one line per node
+///
+/// # Examples
+///
+/// ```rust
+/// # use solana_sdk::{pubkey::Pubkey, system_instruction, transaction::Transaction};
+/// # use token_acl_integration_tests::{labels, trace::{self, TransactionTrace}};
+/// let payer = labels::label(Pubkey::new_unique(), "payer");
+/// let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1);
+/// let transaction = Transaction::new_with_payer(&[transfer], Some(&payer));
+/// let logs = ["Program 11111111111111111111111111111111 invoke [1]".to_string()];
+/// let rendered = trace::render(&TransactionTrace::new(&transaction, &Ok(()), &logs, None));
+/// assert!(rendered.contains("└── #0 system_program transfer: unfinished"));
+/// assert!(rendered.contains("from: payer [signer, writable]"));
+/// ```
+pub fn render(trace: &TransactionTrace) -> String {
+    let status = match &trace.error {
+        Some(error) => format!("failed: {}", error),
+        None => "succeeded".to_string(),
+    };
+    let mut root = Node::new(format!("Transaction {} {}", trace.signature, status));
+    root.push(Node::new(format!(
+        "fee payer: {}",
+        labels::name(&trace.fee_payer)
+    )));
+    if let Some(units) = trace.compute_units {
+        root.push(Node::new(format!(
+            "compute units: {}",
+            units::compute_units(units)
+        )));
+    }
+    root.children
+        .extend(trace.logs.iter().map(|line| Node::new(log_line(line))));
+    for (index, instruction) in trace.instructions.iter().enumerate() {
+        root.push(instruction_node(index, instruction));
+    }
+
+    let mut rendered = root.text.clone();
+    rendered.push('\n');
+    render_children(&root, "", &mut rendered);
+    rendered
+}
+
+thread_local! {
+    static LAST_FAILURE: RefCell<Option<TransactionTrace>> = const { RefCell::new(None) };
+}
+
+/// Remember `transaction` for the test on this thread if it failed
+pub fn record(transaction: &Transaction, outcome: &BanksTransactionResultWithMetadata) {
+    if outcome.result.is_err() {
+        let trace = from_outcome(transaction, outcome);
+        LAST_FAILURE.with(|last| *last.borrow_mut() = Some(trace));
+    }
+}
+
+/// The last failed transaction recorded on this thread since the last call
+pub fn take_failure() -> Option<TransactionTrace> {
+    LAST_FAILURE.with(|last| last.borrow_mut().take())
+}
+
+/// `Program log: ...` as `log: ...`
+fn log_line(line: &str) -> String {
+    line.strip_prefix("Program ").unwrap_or(line).to_string()
+}
+
+/// A program's label, or the name of a well-known program
+pub fn program_name(program: &Pubkey) -> String {
+    labels::with_labels(|labels| labels.get(program).map(str::to_string))
+        .unwrap_or_else(|| token_acl_client::trace::program_name(program))
+}
+
+fn instruction_node(index: usize, instruction: &InstructionTrace) -> Node {
+    let shape =
+        decoder::with_registry(|decoder| decoder.shape(&instruction.program, &instruction.data));
+    // Instructions the decoder knows are named, with their accounts' roles
+    let known = shape.category != InstructionCategory::Unknown;
+    let mut program = program_name(&instruction.program);
+    if known {
+        program = format!("{} {}", program, shape.name);
+    }
+    let mut node = match &instruction.invocation {
+        Some(invocation) => invocation_node(&format!("#{} {}", index, program), invocation),
+        None => Node::new(format!("#{} {}: not executed", index, program)),
+    };
+
+    let mut accounts = Node::new("accounts".to_string());
+    for (position, account) in instruction.accounts.iter().enumerate() {
+        let role = if known {
+            format!("{}: ", shape.role(position))
+        } else {
+            String::new()
+        };
+        let flags: Vec<&str> = [
+            (account.is_signer, "signer"),
+            (account.is_writable, "writable"),
+        ]
+        .into_iter()
+        .filter_map(|(set, flag)| set.then_some(flag))
+        .collect();
+        let flags = if flags.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", flags.join(", "))
+        };
+        accounts.push(Node::new(format!(
+            "{}{}{}",
+            role,
+            labels::name(&account.address),
+            flags
+        )));
+    }
+    if !accounts.children.is_empty() {
+        node.children.insert(0, accounts);
+    }
+    node
+}
+
+fn invocation_node(name: &str, invocation: &Invocation) -> Node {
+    let units = invocation
+        .compute_units
+        .map(|(units, limit)| {
+            format!(
+                " ({} of {})",
+                units::group_digits(units),
+                units::compute_units(limit)
+            )
+        })
+        .unwrap_or_default();
+    let mut node = Node::new(format!(
+        "{}: {}{}",
+        name,
+        labels::annotate(&invocation.outcome.to_string()),
+        units
+    ));
+    for event in &invocation.events {
+        node.push(match event {
+            Event::Log(line) => Node::new(labels::annotate(&log_line(line))),
+            Event::Cpi(inner) => {
+                invocation_node(&format!("CPI {}", program_name(&inner.program)), inner)
+            }
+        });
+    }
+    node
+}
+
+struct Node {
+    text: String,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn new(text: String) -> Self {
+        Self {
+            text,
+            children: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, child: Node) {
+        self.children.push(child);
+    }
+}
+
+fn render_children(node: &Node, prefix: &str, out: &mut String) {
+    for (index, child) in node.children.iter().enumerate() {
+        let last = index + 1 == node.children.len();
+        let (branch, indent) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        out.push_str(&format!("{}{}{}\n", prefix, branch, child.text));
+        render_children(child, &format!("{}{}", prefix, indent), out);
+    }
+}
@@ -0,0 +1,187 @@
+//! Report number formatting
+//!
+//! Every report renders compute units, lamports, durations and percentages
+//! through these functions, so a quantity reads the same in the markdown,
+//! HTML and JSON reports. The output never depends on the locale: digits
+//! are grouped with `,` and the decimal separator is always `.`.
+//!
+//! Values are rounded, never truncated, and a ratio only reads 0% or 100%
+//! when it is exactly that: one failure in a thousand tests shows as 99.9%,
+//! not 100.0%.
+
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use std::time::Duration;
+
+/// `value` with its digits grouped in threes
+///
+/// ```rust
+/// # use token_acl_integration_tests::units;
+/// assert_eq!(units::group_digits(1_234_567), "1,234,567");
+/// assert_eq!(units::group_digits(999), "999");
+/// ```
+pub fn group_digits(value: u64) -> String {
+    let digits = value.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Compute units, e.g. `12,345 CU`
+pub fn compute_units(units: u64) -> String {
+    format!("{} CU", group_digits(units))
+}
+
+/// Lamports, e.g. `5,000 lamports`
+pub fn lamports(lamports: u64) -> String {
+    format!("{} lamports", group_digits(lamports))
+}
+
+/// Lamports as an exact amount of SOL, e.g. `0.000005 SOL`
+///
+/// ```rust
+/// # use token_acl_integration_tests::units;
+/// assert_eq!(units::sol(1_500_000_000), "1.5 SOL");
+/// assert_eq!(units::sol(1), "0.000000001 SOL");
+/// ```
+pub fn sol(lamports: u64) -> String {
+    let whole = group_digits(lamports / LAMPORTS_PER_SOL);
+    let fraction = lamports % LAMPORTS_PER_SOL;
+    if fraction == 0 {
+        return format!("{} SOL", whole);
+    }
+    let fraction = format!("{:09}", fraction);
+    format!("{}.{} SOL", whole, fraction.trim_end_matches('0'))
+}
+
+/// SOL with the lamport count, for costs that are usually fractions of a SOL
+pub fn sol_and_lamports(lamports: u64) -> String {
+    format!("{} ({})", sol(lamports), self::lamports(lamports))
+}
+
+/// A duration in the largest unit that keeps it above one: `850ns`,
+/// `12.5μs`, `3.2ms`, `1.25s`, or `2m 05s` from a minute up
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # use token_acl_integration_tests::units;
+/// assert_eq!(units::duration(Duration::from_micros(1_500)), "1.5ms");
+/// assert_eq!(units::duration(Duration::from_secs(125)), "2m 05s");
+/// ```
+pub fn duration(duration: Duration) -> String {
+    let nanos = duration.as_nanos();
+    if nanos < 1_000 {
+        format!("{}ns", nanos)
+    } else if nanos < 1_000_000 {
+        format!("{:.1}μs", nanos as f64 / 1e3)
+    } else if nanos < 1_000_000_000 {
+        format!("{:.1}ms", nanos as f64 / 1e6)
+    } else if duration.as_secs() < 60 {
+        format!("{:.2}s", duration.as_secs_f64())
+    } else {
+        // Rounded to the second first, so 59.6s past a minute is not "1m 60s"
+        let seconds = duration.as_secs_f64().round() as u64;
+        format!("{}m {:02}s", seconds / 60, seconds % 60)
+    }
+}
+
+/// `part` of `whole` as a percentage, rounded to one decimal but never to
+/// 0% or 100% unless exactly; `-` when `whole` is zero
+///
+/// ```rust
+/// # use token_acl_integration_tests::units;
+/// assert_eq!(units::percent(2, 3), "66.7%");
+/// assert_eq!(units::percent(999, 1_000), "99.9%");
+/// assert_eq!(units::percent(9_999, 10_000), "99.9%");
+/// assert_eq!(units::percent(0, 0), "-");
+/// ```
+pub fn percent(part: u64, whole: u64) -> String {
+    match rounded_ratio(part, whole) {
+        Some(percentage) => format!("{:.1}%", percentage),
+        None => "-".to_string(),
+    }
+}
+
+/// `part` of `whole` as a percentage, rounded as [`percent`] renders it,
+/// for JSON renderers; `None` when `whole` is zero
+pub fn rounded_ratio(part: u64, whole: u64) -> Option<f64> {
+    if whole == 0 {
+        return None;
+    }
+    let rounded = round_percentage(part as f64 / whole as f64 * 100.0);
+    Some(if part == 0 || part >= whole {
+        rounded
+    } else {
+        rounded.clamp(0.1, 99.9)
+    })
+}
+
+/// A percentage computed elsewhere, e.g. `66.7%`
+pub fn percentage(value: f64) -> String {
+    format!("{:.1}%", round_percentage(value))
+}
+
+/// `value` rounded to one decimal, half away from zero
+pub fn round_percentage(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_digits() {
+        assert_eq!(group_digits(0), "0");
+        assert_eq!(group_digits(1_000), "1,000");
+        assert_eq!(group_digits(12_345), "12,345");
+        assert_eq!(group_digits(u64::MAX), "18,446,744,073,709,551,615");
+    }
+
+    #[test]
+    fn formats_compute_units_and_lamports() {
+        assert_eq!(compute_units(200_000), "200,000 CU");
+        assert_eq!(lamports(5_000), "5,000 lamports");
+        assert_eq!(
+            sol_and_lamports(2_039_280),
+            "0.00203928 SOL (2,039,280 lamports)"
+        );
+    }
+
+    #[test]
+    fn formats_sol_exactly() {
+        assert_eq!(sol(0), "0 SOL");
+        assert_eq!(sol(LAMPORTS_PER_SOL), "1 SOL");
+        assert_eq!(sol(5_000), "0.000005 SOL");
+        assert_eq!(sol(1_234 * LAMPORTS_PER_SOL + 1), "1,234.000000001 SOL");
+    }
+
+    #[test]
+    fn picks_duration_units() {
+        assert_eq!(duration(Duration::ZERO), "0ns");
+        assert_eq!(duration(Duration::from_nanos(999)), "999ns");
+        assert_eq!(duration(Duration::from_nanos(1_250)), "1.2μs");
+        assert_eq!(duration(Duration::from_micros(999)), "999.0μs");
+        assert_eq!(duration(Duration::from_millis(250)), "250.0ms");
+        assert_eq!(duration(Duration::from_millis(1_250)), "1.25s");
+        assert_eq!(duration(Duration::from_secs(60)), "1m 00s");
+        assert_eq!(duration(Duration::from_millis(119_600)), "2m 00s");
+    }
+
+    #[test]
+    fn rounds_percentages_without_reaching_the_bounds() {
+        assert_eq!(percent(1, 3), "33.3%");
+        assert_eq!(percent(2, 3), "66.7%");
+        assert_eq!(percent(1, 1), "100.0%");
+        assert_eq!(percent(0, 5), "0.0%");
+        assert_eq!(percent(1, 100_000), "0.1%");
+        assert_eq!(percent(99_999, 100_000), "99.9%");
+        assert_eq!(rounded_ratio(1, 3), Some(33.3));
+        assert_eq!(rounded_ratio(3, 0), None);
+        assert_eq!(percentage(66.66), "66.7%");
+    }
+}
@@ -0,0 +1,1048 @@
+//! Account ordering tests
+//!
+//! A FAMP resolves a gate's extra accounts from its extra-account-metas,
+//! and one that resolves them slightly differently may leave an account
+//! out, pass two swapped, or append accounts of its own. These tests put
+//! every gate in the repository in one bank, each with a holder it approves
+//! and a holder it denies, and check both decisions first, so that a denial
+//! below is the gate's and not a broken fixture's. They then send the
+//! denied holder's check with its accounts rearranged, as
+//! [`arrangements`] lists them and in random orders and counts. The
+//! approved holder's accounts, its owner and records included, are mixed
+//! in as decoys, so a gate that does not bind the token account to the
+//! owner it is given approves an arrangement. No arrangement may be
+//! approved.
+
+use std::path::Path;
+
+use borsh::BorshSerialize;
+use jurisdiction_registry::{
+    holder_address, registry_address, HolderJurisdiction, JurisdictionEntry, JurisdictionRegistry,
+    JurisdictionStatus,
+};
+use proptest::{
+    collection::vec,
+    prelude::*,
+    strategy::ValueTree,
+    test_runner::{Config as ProptestConfig, TestRunner},
+};
+use solana_program_test::processor;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program, sysvar,
+};
+use spl_token_2022::state::{AccountState, Mint};
+use token_acl_integration_tests::{
+    account_ordering::{arrangements, workspace_gates, Decoy, FIXED_ACCOUNTS, WORKSPACE_MANIFEST},
+    program_test::{mint_account, rent_exempt_account, token_account},
+    reporting,
+    suite_config::{self, SuiteCategory},
+    SvmHarness, TestResultReport,
+};
+use token_acl_interface::{
+    self as interface, find_freeze_extra_account_metas_address, find_missing_record_policy_address,
+    find_thaw_extra_account_metas_address, ReadonlyMeta,
+};
+
+/// Random arrangements sent per check
+const RANDOM_ARRANGEMENTS: u32 = 48;
+
+/// A holder of the mint
+struct Holder {
+    owner: Keypair,
+    token_account: Pubkey,
+}
+
+/// One gate's check, for a holder it approves and one it denies
+struct OrderingCase {
+    /// The gate and the check, e.g. "Hybrid Gate can-freeze"
+    name: String,
+    /// Sent before the check in the same transaction
+    prelude: Vec<Instruction>,
+    /// Sign the prelude besides the payer
+    signers: Vec<Keypair>,
+    approved: Instruction,
+    denied: Instruction,
+    /// The denied holder, when the check takes the owner
+    denied_owner: Option<Pubkey>,
+    /// The approved holder's accounts the denied holder's check does not
+    /// take, its owner among them, and an account no gate knows
+    decoys: Vec<Decoy>,
+}
+
+impl OrderingCase {
+    fn new(
+        name: &str,
+        approved: Instruction,
+        denied: Instruction,
+        denied_owner: Option<Pubkey>,
+    ) -> Self {
+        let mut decoys = vec![Decoy::new(
+            "the approved holder's token account",
+            approved.accounts[1].pubkey,
+        )];
+        for (index, meta) in approved.accounts.iter().enumerate().skip(FIXED_ACCOUNTS) {
+            let taken = denied
+                .accounts
+                .iter()
+                .any(|denied| denied.pubkey == meta.pubkey);
+            if !taken {
+                decoys.push(Decoy::new(
+                    format!(
+                        "the approved holder's extra account {}",
+                        index - FIXED_ACCOUNTS + 1
+                    ),
+                    meta.pubkey,
+                ));
+            }
+        }
+        decoys.push(Decoy::new("an unknown account", Pubkey::new_unique()));
+        Self {
+            name: name.to_string(),
+            prelude: Vec::new(),
+            signers: Vec::new(),
+            approved,
+            denied,
+            denied_owner,
+            decoys,
+        }
+    }
+
+    /// The accounts random arrangements draw extra accounts from
+    fn pool(&self) -> Vec<AccountMeta> {
+        let mut pool: Vec<AccountMeta> = Vec::new();
+        let candidates = self.denied.accounts[FIXED_ACCOUNTS..]
+            .iter()
+            .chain(self.decoys.iter().map(|decoy| &decoy.meta));
+        for meta in candidates {
+            if !pool.iter().any(|taken| taken.pubkey == meta.pubkey) {
+                pool.push(meta.clone());
+            }
+        }
+        pool
+    }
+}
+
+/// A bank with every gate and the jurisdiction registry, sharing one mint
+struct OrderingBank {
+    svm: SvmHarness,
+    mint: Pubkey,
+    caller: Keypair,
+    allow_list: Pubkey,
+    block_list: Pubkey,
+    production: Pubkey,
+    hybrid: Pubkey,
+    threshold: Pubkey,
+    velocity: Pubkey,
+    geo: Pubkey,
+    nft: Pubkey,
+    registry_program: Pubkey,
+    registry: Pubkey,
+}
+
+impl OrderingBank {
+    fn new() -> Result<Self, String> {
+        let mut svm = SvmHarness::new();
+        let allow_list = Pubkey::new_unique();
+        let block_list = Pubkey::new_unique();
+        let production = Pubkey::new_unique();
+        let hybrid = Pubkey::new_unique();
+        let threshold = Pubkey::new_unique();
+        let velocity = Pubkey::new_unique();
+        let geo = Pubkey::new_unique();
+        let nft = Pubkey::new_unique();
+        let registry_program = Pubkey::new_unique();
+        svm.add_program(
+            "example_allow_list",
+            allow_list,
+            processor!(example_allow_list::process_instruction),
+        )?;
+        svm.add_program(
+            "example_block_list",
+            block_list,
+            processor!(example_block_list::process_instruction),
+        )?;
+        svm.add_program(
+            "production_allow_list",
+            production,
+            processor!(production_allow_list::process_instruction),
+        )?;
+        svm.add_program(
+            "hybrid_gate",
+            hybrid,
+            processor!(hybrid_gate::process_instruction),
+        )?;
+        svm.add_program(
+            "threshold_gate",
+            threshold,
+            processor!(threshold_gate::process_instruction),
+        )?;
+        svm.add_program(
+            "velocity_gate",
+            velocity,
+            processor!(velocity_gate::process_instruction),
+        )?;
+        svm.add_program("geo_gate", geo, processor!(geo_gate::process_instruction))?;
+        svm.add_program("nft_gate", nft, processor!(nft_gate::process_instruction))?;
+        svm.add_program(
+            "jurisdiction_registry",
+            registry_program,
+            processor!(jurisdiction_registry::process_instruction),
+        )?;
+
+        // The payer is the mint's authority, so it can stand in for every
+        // gate's admin
+        let authority = svm.payer.pubkey();
+        let mint = svm
+            .create_mint(&authority, AccountState::Frozen)
+            .map_err(|e| format!("Mint creation failed: {:?}", e))?;
+
+        let registry = registry_address(&authority, &registry_program);
+        let mut data = JurisdictionRegistry {
+            authority,
+            version: 1,
+            bump: registry.bump(),
+            entries: vec![
+                JurisdictionEntry {
+                    code: *b"US",
+                    status: JurisdictionStatus::Allowed,
+                },
+                JurisdictionEntry {
+                    code: *b"FR",
+                    status: JurisdictionStatus::Allowed,
+                },
+            ],
+        }
+        .try_to_vec()
+        .map_err(|e| e.to_string())?;
+        data.resize(JurisdictionRegistry::LEN, 0);
+        svm.set_account(
+            registry.address(),
+            rent_exempt_account(data, registry_program),
+        );
+
+        Ok(Self {
+            svm,
+            mint,
+            caller: Keypair::new(),
+            allow_list,
+            block_list,
+            production,
+            hybrid,
+            threshold,
+            velocity,
+            geo,
+            nft,
+            registry_program,
+            registry: registry.address(),
+        })
+    }
+
+    /// The package of the gate at `program_id`
+    fn package(&self, program_id: &Pubkey) -> Option<&'static str> {
+        [
+            ("example_allow_list", self.allow_list),
+            ("example_block_list", self.block_list),
+            ("production_allow_list", self.production),
+            ("hybrid_gate", self.hybrid),
+            ("threshold_gate", self.threshold),
+            ("velocity_gate", self.velocity),
+            ("geo_gate", self.geo),
+            ("nft_gate", self.nft),
+        ]
+        .into_iter()
+        .find(|(_, gate)| gate == program_id)
+        .map(|(package, _)| package)
+    }
+
+    fn holder(&mut self) -> Result<Holder, String> {
+        let owner = Keypair::new();
+        let token_account = self
+            .svm
+            .create_token_account(&self.mint, &owner.pubkey())
+            .map_err(|e| format!("Token account creation failed: {:?}", e))?;
+        Ok(Holder {
+            owner,
+            token_account,
+        })
+    }
+
+    /// Store `record`, Borsh-serialized, at `address` under `owner`
+    fn plant(&mut self, address: Pubkey, record: &impl BorshSerialize, owner: Pubkey) {
+        let data = record.try_to_vec().expect("records serialize");
+        self.svm
+            .set_account(address, rent_exempt_account(data, owner));
+    }
+
+    /// Attest `holder` to be in `code`
+    fn attest(&mut self, holder: &Pubkey, code: [u8; 2]) -> Pubkey {
+        let record = holder_address(&self.registry, holder, &self.registry_program);
+        self.plant(
+            record.address(),
+            &HolderJurisdiction {
+                registry: self.registry,
+                holder: *holder,
+                code,
+                bump: record.bump(),
+            },
+            self.registry_program,
+        );
+        record.address()
+    }
+
+    fn thaw(&self, gate: &Pubkey, holder: &Holder, extras: &[Pubkey]) -> Instruction {
+        let extras: Vec<ReadonlyMeta> = extras.iter().map(|&address| address.into()).collect();
+        interface::can_thaw_permissionless(
+            gate,
+            self.caller.pubkey(),
+            holder.token_account,
+            self.mint,
+            find_thaw_extra_account_metas_address(&self.mint, gate).0,
+            &extras,
+        )
+    }
+
+    fn freeze(&self, gate: &Pubkey, holder: &Holder, extras: &[Pubkey]) -> Instruction {
+        let extras: Vec<ReadonlyMeta> = extras.iter().map(|&address| address.into()).collect();
+        interface::can_freeze_permissionless(
+            gate,
+            self.caller.pubkey(),
+            holder.token_account,
+            self.mint,
+            find_freeze_extra_account_metas_address(&self.mint, gate).0,
+            &extras,
+        )
+    }
+
+    /// Whether `check`, after `case`'s prelude, was approved
+    fn approves(&mut self, case: &OrderingCase, check: Instruction) -> bool {
+        let mut instructions = case.prelude.clone();
+        instructions.push(check);
+        let signers: Vec<&Keypair> = case.signers.iter().collect();
+        self.svm.send(&instructions, &signers).is_ok()
+    }
+
+    /// Allow list example: thaws holders whose record is allowed
+    fn allow_list_cases(&mut self) -> Result<Vec<OrderingCase>, String> {
+        let (approved, denied) = (self.holder()?, self.holder()?);
+        let mut records = Vec::new();
+        for (holder, allowed) in [(&approved, true), (&denied, false)] {
+            let user = holder.owner.pubkey();
+            let record =
+                example_allow_list::allow_list_address(&self.mint, &user, &self.allow_list);
+            self.plant(
+                record.address(),
+                &example_allow_list::AllowListRecord {
+                    mint: self.mint,
+                    user,
+                    allowed,
+                    added_timestamp: 0,
+                },
+                self.allow_list,
+            );
+            records.push(record.address());
+        }
+        let gate = self.allow_list;
+        Ok(vec![OrderingCase::new(
+            "Example Allow List can-thaw",
+            self.thaw(&gate, &approved, &[approved.owner.pubkey(), records[0]]),
+            self.thaw(&gate, &denied, &[denied.owner.pubkey(), records[1]]),
+            Some(denied.owner.pubkey()),
+        )])
+    }
+
+    /// Block list example: thaws holders it has not blocked and freezes the
+    /// ones it has
+    fn block_list_cases(&mut self) -> Result<Vec<OrderingCase>, String> {
+        let gate = self.block_list;
+        let initialize = Instruction::new_with_bytes(
+            gate,
+            &[example_block_list::INITIALIZE_FREEZE_EXTRA_ACCOUNT_METAS],
+            vec![
+                AccountMeta::new(self.svm.payer.pubkey(), true),
+                AccountMeta::new(
+                    find_freeze_extra_account_metas_address(&self.mint, &gate).0,
+                    false,
+                ),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        self.svm
+            .send(&[initialize], &[])
+            .map_err(|e| format!("Block list setup failed: {:?}", e))?;
+
+        let (cleared, blocked) = (self.holder()?, self.holder()?);
+        let mut records = Vec::new();
+        for (holder, is_blocked) in [(&cleared, false), (&blocked, true)] {
+            let user = holder.owner.pubkey();
+            let record = example_block_list::block_list_address(&self.mint, &user, &gate);
+            self.plant(
+                record.address(),
+                &example_block_list::BlockListRecord {
+                    mint: self.mint,
+                    user,
+                    blocked: is_blocked,
+                    reason: example_block_list::BlockReason::Sanctions,
+                    added_timestamp: 0,
+                    expiry_timestamp: None,
+                },
+                gate,
+            );
+            records.push(record.address());
+        }
+        let policy = find_missing_record_policy_address(&self.mint, &gate).0;
+        Ok(vec![
+            OrderingCase::new(
+                "Example Block List can-thaw",
+                self.thaw(
+                    &gate,
+                    &cleared,
+                    &[cleared.owner.pubkey(), records[0], policy],
+                ),
+                self.thaw(
+                    &gate,
+                    &blocked,
+                    &[blocked.owner.pubkey(), records[1], policy],
+                ),
+                Some(blocked.owner.pubkey()),
+            ),
+            OrderingCase::new(
+                "Example Block List can-freeze",
+                self.freeze(&gate, &blocked, &[records[1]]),
+                self.freeze(&gate, &cleared, &[records[0]]),
+                None,
+            ),
+        ])
+    }
+
+    /// Production allow list: thaws holders whose record is allowed and not
+    /// revoked
+    fn production_cases(&mut self) -> Result<Vec<OrderingCase>, String> {
+        let gate = self.production;
+        let payer = self.svm.payer.pubkey();
+        let initialize = Instruction::new_with_bytes(
+            gate,
+            &[production_allow_list::INITIALIZE],
+            vec![
+                AccountMeta::new(
+                    production_allow_list::config_address(&self.mint, &gate).address(),
+                    false,
+                ),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new(payer, true),
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        self.svm
+            .send(&[initialize], &[])
+            .map_err(|e| format!("Production allow list setup failed: {:?}", e))?;
+
+        let (approved, revoked) = (self.holder()?, self.holder()?);
+        let mut records = Vec::new();
+        for (holder, is_revoked) in [(&approved, false), (&revoked, true)] {
+            let record = self.production_record(&holder.owner.pubkey(), is_revoked);
+            records.push(record);
+        }
+        let policy = find_missing_record_policy_address(&self.mint, &gate).0;
+        Ok(vec![OrderingCase::new(
+            "Production Allow List can-thaw",
+            self.thaw(
+                &gate,
+                &approved,
+                &[approved.owner.pubkey(), records[0], policy],
+            ),
+            self.thaw(
+                &gate,
+                &revoked,
+                &[revoked.owner.pubkey(), records[1], policy],
+            ),
+            Some(revoked.owner.pubkey()),
+        )])
+    }
+
+    /// An allowed production allow list record of `user`
+    fn production_record(&mut self, user: &Pubkey, revoked: bool) -> Pubkey {
+        let record = production_allow_list::allow_list_address(&self.mint, user, &self.production);
+        self.plant(
+            record.address(),
+            &production_allow_list::AllowListRecord {
+                mint: self.mint,
+                user: *user,
+                allowed: true,
+                access_level: production_allow_list::AccessLevel::Basic,
+                added_timestamp: 0,
+                expiry_timestamp: None,
+                bump: record.bump(),
+                revoked,
+            },
+            self.production,
+        );
+        record.address()
+    }
+
+    /// Hybrid gate over the production allow list, the block list example
+    /// and the registry: thaws allow-listed holders who are not sanctioned,
+    /// freezes sanctioned ones
+    fn hybrid_cases(&mut self) -> Result<Vec<OrderingCase>, String> {
+        let gate = self.hybrid;
+        let config = hybrid_gate::config_address(&self.mint, &gate);
+        self.plant(
+            config.address(),
+            &hybrid_gate::HybridConfig {
+                authority: self.svm.payer.pubkey(),
+                mint: self.mint,
+                allow_list_program: self.production,
+                block_list_program: self.block_list,
+                jurisdictions: Some(hybrid_gate::JurisdictionSource {
+                    program: self.registry_program,
+                    registry: self.registry,
+                }),
+                cooling_off_seconds: 0,
+                paused: false,
+                bump: config.bump(),
+            },
+            gate,
+        );
+
+        // Both are allow-listed and in the US; only one is sanctioned
+        let (clean, sanctioned) = (self.holder()?, self.holder()?);
+        let mut accounts = Vec::new();
+        for (holder, is_sanctioned) in [(&clean, false), (&sanctioned, true)] {
+            let user = holder.owner.pubkey();
+            let allow_record = self.production_record(&user, false);
+            let block_record =
+                example_block_list::block_list_address(&self.mint, &user, &self.block_list);
+            if is_sanctioned {
+                self.plant(
+                    block_record.address(),
+                    &example_block_list::BlockListRecord {
+                        mint: self.mint,
+                        user,
+                        blocked: true,
+                        reason: example_block_list::BlockReason::Sanctions,
+                        added_timestamp: 0,
+                        expiry_timestamp: None,
+                    },
+                    self.block_list,
+                );
+            }
+            let holder_record = self.attest(&user, *b"US");
+            accounts.push((allow_record, block_record.address(), holder_record));
+        }
+        let thaw_extras = |holder: &Holder, (allow, block, record): (Pubkey, Pubkey, Pubkey)| {
+            vec![
+                holder.owner.pubkey(),
+                config.address(),
+                allow,
+                block,
+                self.registry,
+                record,
+            ]
+        };
+        let freeze_extras = |(_, block, record): (Pubkey, Pubkey, Pubkey)| {
+            vec![config.address(), block, self.registry, record]
+        };
+        Ok(vec![
+            OrderingCase::new(
+                "Hybrid Gate can-thaw",
+                self.thaw(&gate, &clean, &thaw_extras(&clean, accounts[0])),
+                self.thaw(&gate, &sanctioned, &thaw_extras(&sanctioned, accounts[1])),
+                Some(sanctioned.owner.pubkey()),
+            ),
+            OrderingCase::new(
+                "Hybrid Gate can-freeze",
+                self.freeze(&gate, &sanctioned, &freeze_extras(accounts[1])),
+                self.freeze(&gate, &clean, &freeze_extras(accounts[0])),
+                None,
+            ),
+        ])
+    }
+
+    /// Threshold gate: thaws holders with two of two approvals
+    fn threshold_cases(&mut self) -> Result<Vec<OrderingCase>, String> {
+        let gate = self.threshold;
+        let approvers = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let config = threshold_gate::config_address(&self.mint, &gate);
+        self.plant(
+            config.address(),
+            &threshold_gate::ThresholdConfig {
+                authority: self.svm.payer.pubkey(),
+                mint: self.mint,
+                threshold: 2,
+                approvers: approvers.to_vec(),
+                bump: config.bump(),
+            },
+            gate,
+        );
+
+        // The denied holder has the first approver's approval only
+        let (approved, denied) = (self.holder()?, self.holder()?);
+        let mut extras = Vec::new();
+        for (holder, approvals) in [(&approved, 2), (&denied, 1)] {
+            let user = holder.owner.pubkey();
+            let mut holder_extras = vec![user, config.address()];
+            for (index, approver) in approvers.iter().enumerate() {
+                let approval = threshold_gate::approval_address(&self.mint, &user, approver, &gate);
+                if index < approvals {
+                    self.plant(
+                        approval.address(),
+                        &threshold_gate::Approval {
+                            mint: self.mint,
+                            user,
+                            approver: *approver,
+                            bump: approval.bump(),
+                        },
+                        gate,
+                    );
+                }
+                holder_extras.push(approval.address());
+            }
+            extras.push(holder_extras);
+        }
+        Ok(vec![OrderingCase::new(
+            "Threshold Gate can-thaw",
+            self.thaw(&gate, &approved, &extras[0]),
+            self.thaw(&gate, &denied, &extras[1]),
+            Some(denied.owner.pubkey()),
+        )])
+    }
+
+    /// Velocity gate: thaws a token account whose thaw was recorded
+    /// directly before; every check follows a recorded thaw of the approved
+    /// holder's account
+    fn velocity_cases(&mut self) -> Result<Vec<OrderingCase>, String> {
+        let gate = self.velocity;
+        let config = velocity_gate::config_address(&self.mint, &gate);
+        self.plant(
+            config.address(),
+            &velocity_gate::VelocityConfig {
+                authority: self.svm.payer.pubkey(),
+                mint: self.mint,
+                max_thaws: velocity_gate::LEDGER_CAPACITY as u8,
+                window_seconds: 60,
+                bump: config.bump(),
+            },
+            gate,
+        );
+
+        let (approved, denied) = (self.holder()?, self.holder()?);
+        let extras = |holder: &Holder| {
+            let user = holder.owner.pubkey();
+            vec![
+                user,
+                config.address(),
+                velocity_gate::ledger_address(&self.mint, &user, &gate).address(),
+                sysvar::instructions::id(),
+            ]
+        };
+        let record_thaw = Instruction::new_with_bytes(
+            gate,
+            &[velocity_gate::RECORD_THAW],
+            vec![
+                AccountMeta::new_readonly(config.address(), false),
+                AccountMeta::new(
+                    velocity_gate::ledger_address(&self.mint, &approved.owner.pubkey(), &gate)
+                        .address(),
+                    false,
+                ),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(approved.token_account, false),
+                AccountMeta::new_readonly(approved.owner.pubkey(), true),
+                AccountMeta::new(self.svm.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        let mut case = OrderingCase::new(
+            "Velocity Gate can-thaw",
+            self.thaw(&gate, &approved, &extras(&approved)),
+            self.thaw(&gate, &denied, &extras(&denied)),
+            Some(denied.owner.pubkey()),
+        );
+        case.prelude = vec![record_thaw];
+        case.signers = vec![approved.owner];
+        Ok(vec![case])
+    }
+
+    /// Geo gate over the registry, for a mint held in the US only: thaws
+    /// US holders, freezes holders elsewhere
+    fn geo_cases(&mut self) -> Result<Vec<OrderingCase>, String> {
+        let gate = self.geo;
+        let config = geo_gate::config_address(&self.mint, &gate);
+        let data = geo_gate::GeoConfig {
+            authority: self.svm.payer.pubkey(),
+            mint: self.mint,
+            registry_program: self.registry_program,
+            registry: self.registry,
+            bump: config.bump(),
+            allowed: vec![*b"US"],
+        }
+        .pack()
+        .map_err(|e| e.to_string())?;
+        self.svm
+            .set_account(config.address(), rent_exempt_account(data, gate));
+
+        // The registry allows France, but the mint does not
+        let (domestic, abroad) = (self.holder()?, self.holder()?);
+        let domestic_record = self.attest(&domestic.owner.pubkey(), *b"US");
+        let abroad_record = self.attest(&abroad.owner.pubkey(), *b"FR");
+        let thaw_extras = |holder: &Holder, record: Pubkey| {
+            vec![
+                holder.owner.pubkey(),
+                config.address(),
+                self.registry,
+                record,
+            ]
+        };
+        let freeze_extras = |record: Pubkey| vec![config.address(), self.registry, record];
+        Ok(vec![
+            OrderingCase::new(
+                "Geo Gate can-thaw",
+                self.thaw(&gate, &domestic, &thaw_extras(&domestic, domestic_record)),
+                self.thaw(&gate, &abroad, &thaw_extras(&abroad, abroad_record)),
+                Some(abroad.owner.pubkey()),
+            ),
+            OrderingCase::new(
+                "Geo Gate can-freeze",
+                self.freeze(&gate, &abroad, &freeze_extras(abroad_record)),
+                self.freeze(&gate, &domestic, &freeze_extras(domestic_record)),
+                None,
+            ),
+        ])
+    }
+
+    /// NFT gate: thaws holders of an NFT verified to be in the collection
+    fn nft_cases(&mut self) -> Result<Vec<OrderingCase>, String> {
+        let gate = self.nft;
+        let collection = Pubkey::new_unique();
+        let config = nft_gate::config_address(&self.mint, &gate);
+        self.plant(
+            config.address(),
+            &nft_gate::NftConfig {
+                authority: self.svm.payer.pubkey(),
+                mint: self.mint,
+                collection,
+                bump: config.bump(),
+            },
+            gate,
+        );
+
+        // Both hold an NFT claiming the collection; only the member's is
+        // verified
+        let (member, claimant) = (self.holder()?, self.holder()?);
+        let mut passes = Vec::new();
+        for (holder, verified) in [(&member, true), (&claimant, false)] {
+            let nft_mint = Pubkey::new_unique();
+            let mut nft_mint_account = mint_account(&self.svm.payer.pubkey(), 1);
+            let mut unique = Mint::unpack(&nft_mint_account.data)
+                .map_err(|e| format!("Failed to read the NFT mint: {}", e))?;
+            unique.decimals = 0;
+            Mint::pack(unique, &mut nft_mint_account.data)
+                .map_err(|e| format!("Failed to write the NFT mint: {}", e))?;
+            self.svm.set_account(nft_mint, nft_mint_account);
+            let nft_account = Pubkey::new_unique();
+            self.svm.set_account(
+                nft_account,
+                token_account(
+                    &nft_mint,
+                    &holder.owner.pubkey(),
+                    1,
+                    AccountState::Initialized,
+                ),
+            );
+            let metadata = nft_gate::metadata_address(&nft_mint);
+            self.plant(
+                metadata,
+                &nft_gate::Metadata {
+                    key: nft_gate::METADATA_V1_KEY,
+                    update_authority: self.svm.payer.pubkey(),
+                    mint: nft_mint,
+                    data: nft_gate::MetadataData {
+                        name: "Pass".to_string(),
+                        symbol: "PASS".to_string(),
+                        uri: String::new(),
+                        seller_fee_basis_points: 0,
+                        creators: None,
+                    },
+                    primary_sale_happened: false,
+                    is_mutable: true,
+                    edition_nonce: None,
+                    token_standard: Some(0),
+                    collection: Some(nft_gate::Collection {
+                        verified,
+                        key: collection,
+                    }),
+                },
+                nft_gate::METADATA_PROGRAM_ID,
+            );
+            passes.push(vec![
+                holder.owner.pubkey(),
+                nft_account,
+                nft_mint,
+                metadata,
+                config.address(),
+            ]);
+        }
+        Ok(vec![OrderingCase::new(
+            "NFT Gate can-thaw",
+            self.thaw(&gate, &member, &passes[0]),
+            self.thaw(&gate, &claimant, &passes[1]),
+            Some(claimant.owner.pubkey()),
+        )])
+    }
+
+    /// Every gate's checks
+    fn cases(&mut self) -> Result<Vec<OrderingCase>, String> {
+        let mut cases = self.allow_list_cases()?;
+        cases.extend(self.block_list_cases()?);
+        cases.extend(self.production_cases()?);
+        cases.extend(self.hybrid_cases()?);
+        cases.extend(self.threshold_cases()?);
+        cases.extend(self.velocity_cases()?);
+        cases.extend(self.geo_cases()?);
+        cases.extend(self.nft_cases()?);
+        Ok(cases)
+    }
+}
+
+/// A bank with every gate, and its checks with their decisions confirmed
+fn confirmed_cases() -> Result<(OrderingBank, Vec<OrderingCase>), String> {
+    let mut bank = OrderingBank::new()?;
+    let cases = bank.cases()?;
+    for case in &cases {
+        if !bank.approves(case, case.approved.clone()) {
+            return Err(format!("{} denied the approved holder", case.name));
+        }
+        if bank.approves(case, case.denied.clone()) {
+            return Err(format!("{} approved the denied holder", case.name));
+        }
+    }
+    Ok((bank, cases))
+}
+
+/// Test 1: Every gate approves one holder and denies the other
+#[test]
+fn test_fixture_decisions() {
+    let report = run_fixture_decisions_test();
+    assert!(
+        report.passed,
+        "Fixture decisions test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_fixture_decisions_test() -> TestResultReport {
+    let test_name = "Fixture Decisions";
+    let mut assertions = 0;
+
+    let (bank, cases) = match confirmed_cases() {
+        Ok(confirmed) => confirmed,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+
+    // Assertion 1: Every gate has its thaw checked, and the gates with a
+    // permissionless freeze their freeze
+    assertions += 1;
+    let gates = match workspace_gates(Path::new(WORKSPACE_MANIFEST)) {
+        Ok(gates) => gates,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let mut expected: Vec<(String, [u8; 8])> = Vec::new();
+    for gate in gates {
+        if gate.can_thaw {
+            expected.push((
+                gate.package.clone(),
+                interface::CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+            ));
+        }
+        if gate.can_freeze {
+            expected.push((
+                gate.package,
+                interface::CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR,
+            ));
+        }
+    }
+    let mut checked = Vec::new();
+    for case in &cases {
+        let Some(package) = bank.package(&case.approved.program_id) else {
+            return TestResultReport::failure(
+                test_name,
+                format!("{} checks a program not in the bank", case.name),
+            );
+        };
+        let mut discriminator = [0; 8];
+        discriminator.copy_from_slice(&case.approved.data[..8]);
+        checked.push((package.to_string(), discriminator));
+    }
+    expected.sort();
+    checked.sort();
+    if checked != expected {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Checked {:?}, the workspace's gates implement {:?}",
+                checked, expected
+            ),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: No listed arrangement of a denied holder's accounts is approved
+#[test]
+fn test_listed_arrangements_denied() {
+    let report = run_listed_arrangements_test();
+    assert!(
+        report.passed,
+        "Listed arrangements test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_listed_arrangements_test() -> TestResultReport {
+    let test_name = "Listed Arrangements Denied";
+    let mut assertions = 0;
+
+    let (mut bank, cases) = match confirmed_cases() {
+        Ok(confirmed) => confirmed,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+
+    for case in &cases {
+        let listed = arrangements(&case.denied, case.denied_owner.as_ref(), &case.decoys);
+
+        // Assertion: The arrangements include the ones FAMPs are known to
+        // differ in
+        assertions += 1;
+        let names: Vec<&str> = listed
+            .iter()
+            .map(|arrangement| arrangement.name.as_str())
+            .collect();
+        let owner_missing = case.denied_owner.is_none() || names.contains(&"owner missing");
+        if !owner_missing
+            || !names.contains(&"mint and token account swapped")
+            || !names.contains(&"an unknown account appended")
+        {
+            return TestResultReport::failure(
+                test_name,
+                format!("{} arrangements: {:?}", case.name, names),
+            );
+        }
+
+        // Assertion: None of them is approved
+        assertions += 1;
+        let approved: Vec<&str> = listed
+            .iter()
+            .filter(|arrangement| bank.approves(case, arrangement.apply(&case.denied)))
+            .map(|arrangement| arrangement.name.as_str())
+            .collect();
+        if !approved.is_empty() {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "{} approved the denied holder with {:?}",
+                    case.name, approved
+                ),
+            );
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// A random arrangement: whether the mint and the token account are
+/// swapped, and indexes into the case's pool for the extra accounts
+type RandomArrangement = (bool, Vec<usize>);
+
+fn random_arrangement(pool: usize) -> impl Strategy<Value = RandomArrangement> {
+    (any::<bool>(), vec(0..pool, 0..=pool + 2))
+}
+
+/// Test 3: No random order or count of accounts approves a denied holder
+#[test]
+fn test_random_arrangements_denied() {
+    let report = run_random_arrangements_test();
+    assert!(
+        report.passed,
+        "Random arrangements test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_random_arrangements_test() -> TestResultReport {
+    let test_name = "Random Arrangements Denied";
+    if let Some(skipped) = suite_config::skip_unless(SuiteCategory::FuzzSmoke, test_name) {
+        return skipped;
+    }
+    let mut assertions = 0;
+
+    let (mut bank, cases) = match confirmed_cases() {
+        Ok(confirmed) => confirmed,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+
+    let mut runner = TestRunner::new(ProptestConfig {
+        failure_persistence: None,
+        ..ProptestConfig::default()
+    });
+    for case in &cases {
+        let pool = case.pool();
+        let strategy = random_arrangement(pool.len());
+        for _ in 0..RANDOM_ARRANGEMENTS {
+            let (swapped, extras) = match strategy.new_tree(&mut runner) {
+                Ok(tree) => tree.current(),
+                Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+            };
+            let mut check = case.denied.clone();
+            check.accounts.truncate(FIXED_ACCOUNTS);
+            if swapped {
+                check.accounts.swap(1, 2);
+            }
+            check
+                .accounts
+                .extend(extras.iter().map(|&index| pool[index].clone()));
+
+            // Assertion: The arrangement is denied
+            assertions += 1;
+            if bank.approves(case, check.clone()) {
+                let accounts: Vec<Pubkey> = check.accounts.iter().map(|meta| meta.pubkey).collect();
+                return TestResultReport::failure(
+                    test_name,
+                    format!(
+                        "{} approved the denied holder with {:?}",
+                        case.name, accounts
+                    ),
+                );
+            }
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate account ordering report
+#[test]
+fn generate_account_ordering_report() {
+    let results = vec![
+        run_fixture_decisions_test(),
+        run_listed_arrangements_test(),
+        run_random_arrangements_test(),
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Account Ordering Test Results",
+        "../../tests/reports/account_ordering.md",
+    ) {
+        panic!("Failed to generate account ordering report: {}", e);
+    }
+
+    for result in &results {
+        assert!(result.passed, "{} failed: {:?}", result.name, result.error);
+    }
+}
@@ -0,0 +1,259 @@
+//! Address labeling tests
+//!
+//! Failures name the accounts involved the way the test named them. These
+//! tests pin the registry's naming rules and that logs, account diffs,
+//! invariant violations and reports all show the names.
+
+use solana_sdk::{pubkey::Pubkey, signature::Signer};
+
+use token_acl_integration_tests::{
+    account_snapshots::{AccountSnapshot, AccountState},
+    context::Namespace,
+    invariants::InvariantViolation,
+    labels::{self, AddressLabels},
+    logging::{LogEntry, LogLevel},
+    reporting, TestResultReport,
+};
+
+/// Names stay put once given, and never collide
+#[test]
+fn test_label_registry() {
+    let report = run_label_registry_test();
+    assert!(
+        report.passed,
+        "Label registry test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_label_registry_test() -> TestResultReport {
+    let test_name = "Labels: Registry";
+    let mut assertions = 0;
+
+    let (issuer, investor, record) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let mut labels = AddressLabels::new();
+    labels.label(issuer, "issuer");
+    labels.label(investor, "investor_3");
+    labels.label_derived(record, "allow_pda", &investor);
+
+    // Assertion: derived addresses are named after their base
+    assertions += 1;
+    if labels.name(&record) != "allow_pda(investor_3)" {
+        return TestResultReport::failure(
+            test_name,
+            format!("Record named {}", labels.name(&record)),
+        );
+    }
+
+    // Assertion: an address keeps its first name
+    assertions += 1;
+    labels.label(issuer, "treasury");
+    if labels.get(&issuer) != Some("issuer") {
+        return TestResultReport::failure(
+            test_name,
+            format!("Issuer renamed {:?}", labels.get(&issuer)),
+        );
+    }
+
+    // Assertion: a taken name is suffixed rather than shared
+    assertions += 1;
+    let second_issuer = labels.label(Pubkey::new_unique(), "issuer");
+    if labels.name(&second_issuer) != "issuer#2" || labels.len() != 4 {
+        return TestResultReport::failure(
+            test_name,
+            format!("Second issuer named {}", labels.name(&second_issuer)),
+        );
+    }
+
+    // Assertion: an unnamed address reads as itself
+    assertions += 1;
+    let stranger = Pubkey::new_unique();
+    if labels.name(&stranger) != stranger.to_string() {
+        return TestResultReport::failure(test_name, "Unnamed address was named".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Keys a namespace derives carry their labels, whatever the run
+#[test]
+fn test_namespace_labels() {
+    let report = run_namespace_labels_test();
+    assert!(
+        report.passed,
+        "Namespace label test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_namespace_labels_test() -> TestResultReport {
+    let test_name = "Labels: Namespaces";
+    let mut assertions = 0;
+
+    labels::clear();
+    let first = Namespace::new("Devnet Thaw").keypair("investor_3").pubkey();
+    let second = Namespace::new("Devnet Thaw").keypair("investor_3").pubkey();
+
+    // Assertion: two runs' keys differ but read the same
+    assertions += 1;
+    if first == second || labels::name(&first) != "investor_3" {
+        return TestResultReport::failure(
+            test_name,
+            format!("First run key named {}", labels::name(&first)),
+        );
+    }
+
+    // Assertion: the registry keeps names unique within the thread
+    assertions += 1;
+    if labels::name(&second) != "investor_3#2" {
+        return TestResultReport::failure(
+            test_name,
+            format!("Second run key named {}", labels::name(&second)),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Logs, diffs and violations show names instead of base58
+#[test]
+fn test_labeled_diagnostics() {
+    let report = run_labeled_diagnostics_test();
+    assert!(
+        report.passed,
+        "Labeled diagnostics test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_labeled_diagnostics_test() -> TestResultReport {
+    let test_name = "Labels: Diagnostics";
+    let mut assertions = 0;
+
+    labels::clear();
+    let investor = labels::label(Pubkey::new_unique(), "investor_3");
+    let record = labels::label_derived(Pubkey::new_unique(), "allow_pda", &investor);
+    let gate = labels::label(Pubkey::new_unique(), "gate");
+
+    // Assertion: log messages name the accounts
+    assertions += 1;
+    let entry = LogEntry::new(
+        LogLevel::Error,
+        "gate",
+        &format!("{} was not thawed", investor),
+    );
+    if entry.message != "investor_3 was not thawed" {
+        return TestResultReport::failure(test_name, format!("Logged {:?}", entry.message));
+    }
+
+    // Assertion: a diff lists each changed account by name
+    assertions += 1;
+    let mut after = AccountSnapshot::new();
+    after.insert(
+        record,
+        Some(AccountState {
+            lamports: 1_002_240,
+            owner: gate,
+            data: vec![0; 16],
+        }),
+    );
+    let diff = AccountSnapshot::new().diff(&after).to_string();
+    if diff != "  allow_pda(investor_3): 0 -> 1,002,240 lamports (created)\n" {
+        return TestResultReport::failure(test_name, format!("Diff read {:?}", diff));
+    }
+
+    // Assertion: a violation names its account, and accounts in its detail
+    assertions += 1;
+    let violation = InvariantViolation {
+        invariant: "lamport-drain",
+        address: record,
+        detail: format!("refunded to {}", investor),
+    };
+    if violation.to_string() != "[lamport-drain] allow_pda(investor_3): refunded to investor_3" {
+        return TestResultReport::failure(test_name, format!("Violation read {}", violation));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Reports name the accounts in errors and list them in a legend
+#[test]
+fn test_labeled_reports() {
+    let report = run_labeled_reports_test();
+    assert!(
+        report.passed,
+        "Labeled report test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_labeled_reports_test() -> TestResultReport {
+    let test_name = "Labels: Reports";
+    let mut assertions = 0;
+
+    labels::clear();
+    let investor = labels::label(Pubkey::new_unique(), "investor_3");
+    let issuer = labels::label(Pubkey::new_unique(), "issuer");
+    let results = vec![
+        TestResultReport::success("Thaw", 2),
+        TestResultReport::failure("Freeze", format!("{} still frozen", investor)),
+    ];
+    let markdown = reporting::render_test_report(&results, "Labeled", &[]);
+
+    // Assertion: errors read by name
+    assertions += 1;
+    if !markdown.contains("- **Error**: investor_3 still frozen")
+        || markdown.contains(&format!("**Error**: {}", investor))
+    {
+        return TestResultReport::failure(test_name, "Error kept the address".to_string());
+    }
+
+    // Assertion: the legend leads back to the mentioned accounts only
+    assertions += 1;
+    let legend = format!(
+        "## Accounts\n\n| Name | Address |\n|------|---------|\n| investor_3 | `{}` |\n",
+        investor
+    );
+    if !markdown.contains(&legend) || markdown.contains(&issuer.to_string()) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Legend missing or too long:\n{}", markdown),
+        );
+    }
+
+    // Assertion: a report mentioning no named account has no legend
+    assertions += 1;
+    let plain = reporting::render_test_report(&results[..1], "Plain", &[]);
+    if plain.contains("## Accounts") {
+        return TestResultReport::failure(test_name, "Legend without accounts".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate the address labels report
+#[test]
+fn generate_address_labels_report() {
+    let results = vec![
+        run_label_registry_test(),
+        run_namespace_labels_test(),
+        run_labeled_diagnostics_test(),
+        run_labeled_reports_test(),
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Address Labels Results",
+        "../../tests/reports/address_labels.md",
+    ) {
+        panic!("Failed to generate address labels report: {}", e);
+    }
+
+    for result in &results {
+        assert!(result.passed, "{} failed: {:?}", result.name, result.error);
+    }
+}
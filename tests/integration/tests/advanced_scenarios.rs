@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use borsh::{BorshDeserialize, BorshSerialize};
 /**
  * ADVANCED REAL-WORLD SCENARIO TESTS
@@ -9,12 +11,31 @@ use borsh::{BorshDeserialize, BorshSerialize};
  * - Token freeze/thaw with revocation
  * - Multi-step workflow validation
  */
+use example_block_list::{block_list_address, create_block_list_record, BlockReason};
+use jurisdiction_registry::{JurisdictionCode, JurisdictionStatus};
+use solana_program_test::{processor, ProgramTest};
 use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    program_pack::Pack,
     pubkey::Pubkey,
     signature::{Keypair, Signer},
+    system_program,
+    transaction::Transaction,
+};
+use spl_token_2022::{
+    instruction as token_instruction,
+    state::{Account as TokenAccount, AccountState},
 };
 
-use token_acl_integration_tests::{fixtures::test_data, reporting, utils, TestResultReport};
+use token_acl_integration_tests::{
+    context::TestContext,
+    lifecycle::{HolderState, Transition},
+    program_test::{mint_account, rent_exempt_account, token_account},
+    scenarios::Scenario,
+    suite_config::{self, SuiteCategory},
+    time, units, TestResultReport,
+};
+use token_acl_interface as interface;
 
 /// Real-world Scenario 1: KYC Allowlist with Expiration
 #[test]
@@ -105,9 +126,12 @@ fn run_kyc_expiration_test() -> TestResultReport {
 }
 
 /// Real-world Scenario 2: Sanctions List Precedence
-#[test]
-fn test_sanctions_precedence() {
-    let report = run_sanctions_precedence_test();
+///
+/// Runs the hybrid gate against the production allow list and the block
+/// list example, with one holder on each combination of the two lists.
+#[tokio::test]
+async fn test_sanctions_precedence() {
+    let report = run_sanctions_precedence_test().await;
     assert!(
         report.passed,
         "Sanctions precedence test failed: {:?}",
@@ -115,79 +139,663 @@ fn test_sanctions_precedence() {
     );
 }
 
-fn run_sanctions_precedence_test() -> TestResultReport {
-    let test_name = "Sanctions List Precedence";
-    let mut assertions = 0;
+/// The hybrid gate and the lists it reads, deployed for one mint, with a
+/// frozen account for each holder
+struct SanctionsBank {
+    context: TestContext,
+    gate: Pubkey,
+    allow_list: Pubkey,
+    block_list: Pubkey,
+    issuer: Keypair,
+    mint: Pubkey,
+    token_accounts: HashMap<Pubkey, Pubkey>,
+}
 
-    struct ComplianceCheck {
-        in_sanctions: bool,
-        in_allowlist: bool,
-    }
+impl SanctionsBank {
+    /// Start a bank where each of `holders` is allow-listed and sanctioned
+    /// as given
+    ///
+    /// Sanctions records are written into the bank at genesis, owned by
+    /// the block list program; allow list records are added by the issuer.
+    async fn start(test_name: &str, holders: &[(Pubkey, bool, bool)]) -> Result<Self, String> {
+        let (gate, allow_list, block_list) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let issuer = Keypair::new();
+        let mint = Pubkey::new_unique();
+
+        let mut program_test = ProgramTest::new(
+            "hybrid_gate",
+            gate,
+            processor!(hybrid_gate::process_instruction),
+        );
+        program_test.add_program(
+            "production_allow_list",
+            allow_list,
+            processor!(production_allow_list::process_instruction),
+        );
+        program_test.add_program(
+            "example_block_list",
+            block_list,
+            processor!(example_block_list::process_instruction),
+        );
+
+        program_test.add_account(
+            mint,
+            mint_account(&issuer.pubkey(), 1_000 * holders.len() as u64),
+        );
 
-    impl ComplianceCheck {
-        fn is_allowed(&self) -> bool {
-            // Sanctions ALWAYS takes precedence
-            if self.in_sanctions {
-                return false;
+        let mut token_accounts = HashMap::new();
+        for &(holder, _, sanctioned) in holders {
+            let holder_account = Pubkey::new_unique();
+            program_test.add_account(
+                holder_account,
+                token_account(&mint, &holder, 1_000, AccountState::Frozen),
+            );
+            token_accounts.insert(holder, holder_account);
+
+            if sanctioned {
+                let record = create_block_list_record(&mint, &holder, BlockReason::Sanctions, 0);
+                program_test.add_account(
+                    block_list_address(&mint, &holder, &block_list).address(),
+                    rent_exempt_account(
+                        record.try_to_vec().map_err(|e| e.to_string())?,
+                        block_list,
+                    ),
+                );
             }
-            self.in_allowlist
         }
+
+        let mut bank = Self {
+            context: TestContext::start(test_name, program_test).await?,
+            gate,
+            allow_list,
+            block_list,
+            issuer,
+            mint,
+            token_accounts,
+        };
+        let mut setup = vec![bank.initialize_allow_list(), bank.initialize_gate()];
+        setup.extend(
+            holders
+                .iter()
+                .filter(|(_, allowed, _)| *allowed)
+                .map(|(holder, _, _)| bank.add_to_allow_list(holder)),
+        );
+        bank.send(&setup).await?;
+        Ok(bank)
     }
 
-    // Scenario 1: In allowlist, not in sanctions (allowed)
-    let normal_user = ComplianceCheck {
-        in_sanctions: false,
-        in_allowlist: true,
-    };
+    fn config(&self) -> Pubkey {
+        hybrid_gate::config_address(&self.mint, &self.gate).address()
+    }
 
-    assertions += 1;
-    if !normal_user.is_allowed() {
-        return TestResultReport::failure(test_name, "Normal allowlisted user blocked".to_string());
+    fn allow_list_config(&self) -> Pubkey {
+        production_allow_list::config_address(&self.mint, &self.allow_list).address()
     }
 
-    // Scenario 2: In BOTH sanctions and allowlist (BLOCKED - sanctions wins)
-    let sanctioned_but_allowlisted = ComplianceCheck {
-        in_sanctions: true,
-        in_allowlist: true,
+    fn allow_list_record(&self, holder: &Pubkey) -> Pubkey {
+        production_allow_list::allow_list_address(&self.mint, holder, &self.allow_list).address()
+    }
+
+    fn block_list_record(&self, holder: &Pubkey) -> Pubkey {
+        block_list_address(&self.mint, holder, &self.block_list).address()
+    }
+
+    fn token_account(&self, holder: &Pubkey) -> Pubkey {
+        self.token_accounts[holder]
+    }
+
+    fn initialize_allow_list(&self) -> Instruction {
+        Instruction::new_with_bytes(
+            self.allow_list,
+            &[production_allow_list::INITIALIZE],
+            vec![
+                AccountMeta::new(self.allow_list_config(), false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    fn initialize_gate(&self) -> Instruction {
+        let mut data = vec![hybrid_gate::INITIALIZE];
+        data.extend_from_slice(self.allow_list.as_ref());
+        data.extend_from_slice(self.block_list.as_ref());
+        Instruction::new_with_bytes(
+            self.gate,
+            &data,
+            vec![
+                AccountMeta::new(self.config(), false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    fn add_to_allow_list(&self, holder: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            self.allow_list,
+            &[production_allow_list::ADD_TO_ALLOW_LIST],
+            vec![
+                AccountMeta::new_readonly(self.allow_list_config(), false),
+                AccountMeta::new(self.allow_list_record(holder), false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(*holder, false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// The gate's thaw check for `holder`, then the thaw it authorizes,
+    /// with the issuer standing in for the FAMP PDA
+    fn thaw(&self, holder: &Pubkey) -> Result<Vec<Instruction>, String> {
+        let token_account = self.token_account(holder);
+        let check = interface::can_thaw_permissionless(
+            &self.gate,
+            *holder,
+            token_account,
+            self.mint,
+            interface::find_thaw_extra_account_metas_address(&self.mint, &self.gate).0,
+            &[
+                (*holder).into(),
+                self.config().into(),
+                self.allow_list_record(holder).into(),
+                self.block_list_record(holder).into(),
+            ],
+        );
+        let thaw = token_instruction::thaw_account(
+            &spl_token_2022::id(),
+            &token_account,
+            &self.mint,
+            &self.issuer.pubkey(),
+            &[],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(vec![check, thaw])
+    }
+
+    /// `keeper`'s freeze check for `holder`
+    fn keeper_freeze_check(&self, keeper: &Pubkey, holder: &Pubkey) -> Instruction {
+        interface::can_freeze_permissionless(
+            &self.gate,
+            keeper,
+            self.token_account(holder),
+            self.mint,
+            interface::find_freeze_extra_account_metas_address(&self.mint, &self.gate).0,
+            &[self.config().into(), self.block_list_record(holder).into()],
+        )
+    }
+
+    /// Send `instructions`, signed by the payer and, where they need it,
+    /// the issuer; a failure comes back with the transaction's logs
+    async fn send(&mut self, instructions: &[Instruction]) -> Result<(), String> {
+        let blockhash = self
+            .context
+            .get_new_latest_blockhash()
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut transaction =
+            Transaction::new_with_payer(instructions, Some(&self.context.payer.pubkey()));
+        let signers: Vec<&Keypair> = [&self.context.payer, &self.issuer]
+            .into_iter()
+            .filter(|signer| {
+                transaction
+                    .message
+                    .signer_keys()
+                    .contains(&&signer.pubkey())
+            })
+            .collect();
+        transaction.sign(&signers, blockhash);
+        let outcome = self
+            .context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .map_err(|e| e.to_string())?;
+        match outcome.result {
+            Ok(()) => Ok(()),
+            Err(e) => Err(format!(
+                "{}: {:?}",
+                e,
+                outcome
+                    .metadata
+                    .map(|metadata| metadata.log_messages)
+                    .unwrap_or_default()
+            )),
+        }
+    }
+
+    async fn token_state(&mut self, holder: &Pubkey) -> Option<AccountState> {
+        let token_account = self.token_account(holder);
+        let account = self
+            .context
+            .banks_client
+            .get_account(token_account)
+            .await
+            .ok()??;
+        TokenAccount::unpack(&account.data)
+            .ok()
+            .map(|account| account.state)
+    }
+}
+
+async fn run_sanctions_precedence_test() -> TestResultReport {
+    let test_name = "Sanctions List Precedence";
+    let mut assertions = 0;
+
+    let (listed, both, sanctioned, unknown) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let mut bank = match SanctionsBank::start(
+        test_name,
+        &[
+            (listed, true, false),
+            (both, true, true),
+            (sanctioned, false, true),
+            (unknown, false, false),
+        ],
+    )
+    .await
+    {
+        Ok(bank) => bank,
+        Err(e) => return TestResultReport::failure(test_name, e),
     };
 
+    // Scenario 1: In allowlist, not in sanctions (allowed)
     assertions += 1;
-    if sanctioned_but_allowlisted.is_allowed() {
+    let thaw = match bank.thaw(&listed) {
+        Ok(thaw) => thaw,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    if let Err(e) = bank.send(&thaw).await {
         return TestResultReport::failure(
             test_name,
-            "CRITICAL: Sanctioned user allowed despite being on allowlist".to_string(),
+            format!("Normal allowlisted user blocked: {}", e),
         );
     }
+    if bank.token_state(&listed).await != Some(AccountState::Initialized) {
+        return TestResultReport::failure(test_name, "Allowlisted user still frozen".to_string());
+    }
 
-    // Scenario 3: In sanctions, not in allowlist (blocked)
-    let sanctioned = ComplianceCheck {
-        in_sanctions: true,
-        in_allowlist: false,
-    };
-
+    // Scenario 2: In BOTH sanctions and allowlist (BLOCKED - sanctions wins)
     assertions += 1;
-    if sanctioned.is_allowed() {
-        return TestResultReport::failure(test_name, "Sanctioned user allowed".to_string());
+    let thaw = match bank.thaw(&both) {
+        Ok(thaw) => thaw,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    match bank.send(&thaw).await {
+        Err(e) if e.contains("sanctions override") => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "CRITICAL: Sanctioned user not denied by sanctions despite being on allowlist: {:?}",
+                    other
+                ),
+            )
+        }
+    }
+    if bank.token_state(&both).await != Some(AccountState::Frozen) {
+        return TestResultReport::failure(
+            test_name,
+            "CRITICAL: Sanctioned, allowlisted user thawed".to_string(),
+        );
     }
 
+    // Scenario 3: In sanctions, not in allowlist (blocked)
     // Scenario 4: Neither list (blocked)
-    let unknown = ComplianceCheck {
-        in_sanctions: false,
-        in_allowlist: false,
-    };
+    for (holder, who) in [(sanctioned, "Sanctioned"), (unknown, "Unknown")] {
+        assertions += 1;
+        let thaw = match bank.thaw(&holder) {
+            Ok(thaw) => thaw,
+            Err(e) => return TestResultReport::failure(test_name, e),
+        };
+        if bank.send(&thaw).await.is_ok()
+            || bank.token_state(&holder).await != Some(AccountState::Frozen)
+        {
+            return TestResultReport::failure(test_name, format!("{} user allowed", who));
+        }
+    }
 
+    // Scenario 5: A keeper may freeze any sanctioned user, allowlisted or
+    // not, and nobody else; the sanctioned accounts are still frozen, so
+    // only the gate's check is sent
     assertions += 1;
-    if unknown.is_allowed() {
-        return TestResultReport::failure(test_name, "Unknown user allowed".to_string());
+    let keeper = Pubkey::new_unique();
+    for (holder, permitted) in [(both, true), (sanctioned, true), (listed, false)] {
+        let check = bank.keeper_freeze_check(&keeper, &holder);
+        if bank.send(&[check]).await.is_ok() != permitted {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "Keeper freeze does not follow the sanctions list: {} permitted={}",
+                    holder, permitted
+                ),
+            );
+        }
     }
 
     TestResultReport::success(test_name, assertions)
 }
 
+/// The geo-blocking gate and the jurisdiction registry it reads, deployed
+/// for one mint, with a frozen account for each holder
+struct GeoBank {
+    context: TestContext,
+    gate: Pubkey,
+    registry_program: Pubkey,
+    /// Freeze authority of the mint and authority of the gate's config
+    issuer: Keypair,
+    /// Authority of the registry, who attests holders' jurisdictions
+    compliance: Keypair,
+    mint: Pubkey,
+    token_accounts: HashMap<Pubkey, Pubkey>,
+}
+
+impl GeoBank {
+    /// Start a bank whose registry holds `registry` and whose mint allows
+    /// `allowed`, with each of `holders` attested in the given jurisdiction
+    /// (or not at all)
+    async fn start(
+        test_name: &str,
+        registry: &[(JurisdictionCode, JurisdictionStatus)],
+        allowed: &[JurisdictionCode],
+        holders: &[(Pubkey, Option<JurisdictionCode>)],
+    ) -> Result<Self, String> {
+        let (gate, registry_program) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let issuer = Keypair::new();
+        let mint = Pubkey::new_unique();
+
+        let mut program_test =
+            ProgramTest::new("geo_gate", gate, processor!(geo_gate::process_instruction));
+        program_test.add_program(
+            "jurisdiction_registry",
+            registry_program,
+            processor!(jurisdiction_registry::process_instruction),
+        );
+
+        program_test.add_account(
+            mint,
+            mint_account(&issuer.pubkey(), 1_000 * holders.len() as u64),
+        );
+
+        let mut token_accounts = HashMap::new();
+        for &(holder, _) in holders {
+            let holder_account = Pubkey::new_unique();
+            program_test.add_account(
+                holder_account,
+                token_account(&mint, &holder, 1_000, AccountState::Frozen),
+            );
+            token_accounts.insert(holder, holder_account);
+        }
+
+        let mut bank = Self {
+            context: TestContext::start(test_name, program_test).await?,
+            gate,
+            registry_program,
+            issuer,
+            compliance: Keypair::new(),
+            mint,
+            token_accounts,
+        };
+        let mut setup = vec![bank.initialize_registry()];
+        setup.extend(
+            registry
+                .iter()
+                .map(|(code, status)| bank.set_jurisdiction(*code, *status)),
+        );
+        setup.push(bank.initialize_gate(allowed));
+        bank.send(&setup).await?;
+        for &(holder, code) in holders {
+            if let Some(code) = code {
+                let attest = bank.set_holder_jurisdiction(&holder, code);
+                bank.send(&[attest]).await?;
+            }
+        }
+        Ok(bank)
+    }
+
+    fn config(&self) -> Pubkey {
+        geo_gate::config_address(&self.mint, &self.gate).address()
+    }
+
+    fn registry(&self) -> Pubkey {
+        jurisdiction_registry::registry_address(&self.compliance.pubkey(), &self.registry_program)
+            .address()
+    }
+
+    fn holder_record(&self, holder: &Pubkey) -> Pubkey {
+        jurisdiction_registry::holder_address(&self.registry(), holder, &self.registry_program)
+            .address()
+    }
+
+    fn token_account(&self, holder: &Pubkey) -> Pubkey {
+        self.token_accounts[holder]
+    }
+
+    fn initialize_registry(&self) -> Instruction {
+        Instruction::new_with_bytes(
+            self.registry_program,
+            &[jurisdiction_registry::INITIALIZE_REGISTRY],
+            vec![
+                AccountMeta::new(self.registry(), false),
+                AccountMeta::new_readonly(self.compliance.pubkey(), true),
+                AccountMeta::new(self.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    fn set_jurisdiction(&self, code: JurisdictionCode, status: JurisdictionStatus) -> Instruction {
+        Instruction::new_with_bytes(
+            self.registry_program,
+            &[
+                jurisdiction_registry::SET_JURISDICTION,
+                code[0],
+                code[1],
+                status as u8,
+            ],
+            vec![
+                AccountMeta::new(self.registry(), false),
+                AccountMeta::new_readonly(self.compliance.pubkey(), true),
+            ],
+        )
+    }
+
+    fn set_holder_jurisdiction(&self, holder: &Pubkey, code: JurisdictionCode) -> Instruction {
+        Instruction::new_with_bytes(
+            self.registry_program,
+            &[
+                jurisdiction_registry::SET_HOLDER_JURISDICTION,
+                code[0],
+                code[1],
+            ],
+            vec![
+                AccountMeta::new_readonly(self.registry(), false),
+                AccountMeta::new(self.holder_record(holder), false),
+                AccountMeta::new_readonly(*holder, false),
+                AccountMeta::new_readonly(self.compliance.pubkey(), true),
+                AccountMeta::new(self.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    fn initialize_gate(&self, allowed: &[JurisdictionCode]) -> Instruction {
+        let mut data = vec![geo_gate::INITIALIZE];
+        data.extend_from_slice(self.registry_program.as_ref());
+        data.extend_from_slice(self.registry().as_ref());
+        data.extend(allowed.iter().flatten());
+        Instruction::new_with_bytes(
+            self.gate,
+            &data,
+            vec![
+                AccountMeta::new(self.config(), false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// `authority`'s replacement of the mint's allowed jurisdictions
+    fn set_allowed(&self, allowed: &[JurisdictionCode], authority: &Pubkey) -> Instruction {
+        let mut data = vec![geo_gate::SET_ALLOWED_JURISDICTIONS];
+        data.extend(allowed.iter().flatten());
+        Instruction::new_with_bytes(
+            self.gate,
+            &data,
+            vec![
+                AccountMeta::new(self.config(), false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(*authority, true),
+            ],
+        )
+    }
+
+    /// The gate's thaw check for `holder`, reading `record` as their holder
+    /// record, then the thaw it authorizes, with the issuer standing in for
+    /// the FAMP PDA
+    fn thaw_with_record(
+        &self,
+        holder: &Pubkey,
+        record: Pubkey,
+    ) -> Result<Vec<Instruction>, String> {
+        let token_account = self.token_account(holder);
+        let check = interface::can_thaw_permissionless(
+            &self.gate,
+            *holder,
+            token_account,
+            self.mint,
+            interface::find_thaw_extra_account_metas_address(&self.mint, &self.gate).0,
+            &[
+                (*holder).into(),
+                self.config().into(),
+                self.registry().into(),
+                record.into(),
+            ],
+        );
+        let thaw = token_instruction::thaw_account(
+            &spl_token_2022::id(),
+            &token_account,
+            &self.mint,
+            &self.issuer.pubkey(),
+            &[],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(vec![check, thaw])
+    }
+
+    fn thaw(&self, holder: &Pubkey) -> Result<Vec<Instruction>, String> {
+        self.thaw_with_record(holder, self.holder_record(holder))
+    }
+
+    /// `keeper`'s freeze check for `holder`, then the freeze it authorizes
+    fn keeper_freeze(&self, keeper: &Pubkey, holder: &Pubkey) -> Result<Vec<Instruction>, String> {
+        let token_account = self.token_account(holder);
+        let check = interface::can_freeze_permissionless(
+            &self.gate,
+            keeper,
+            token_account,
+            self.mint,
+            interface::find_freeze_extra_account_metas_address(&self.mint, &self.gate).0,
+            &[
+                self.config().into(),
+                self.registry().into(),
+                self.holder_record(holder).into(),
+            ],
+        );
+        let freeze = token_instruction::freeze_account(
+            &spl_token_2022::id(),
+            &token_account,
+            &self.mint,
+            &self.issuer.pubkey(),
+            &[],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(vec![check, freeze])
+    }
+
+    /// Send `instructions`, signed by the payer and, where they need them,
+    /// the issuer, the compliance authority and `signers`; a failure comes
+    /// back with the transaction's logs
+    async fn send_signed(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), String> {
+        let blockhash = self
+            .context
+            .get_new_latest_blockhash()
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut transaction =
+            Transaction::new_with_payer(instructions, Some(&self.context.payer.pubkey()));
+        let signers: Vec<&Keypair> = [&self.context.payer, &self.issuer, &self.compliance]
+            .into_iter()
+            .chain(signers.iter().copied())
+            .filter(|signer| {
+                transaction
+                    .message
+                    .signer_keys()
+                    .contains(&&signer.pubkey())
+            })
+            .collect();
+        transaction.sign(&signers, blockhash);
+        let outcome = self
+            .context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .map_err(|e| e.to_string())?;
+        match outcome.result {
+            Ok(()) => Ok(()),
+            Err(e) => Err(format!(
+                "{}: {:?}",
+                e,
+                outcome
+                    .metadata
+                    .map(|metadata| metadata.log_messages)
+                    .unwrap_or_default()
+            )),
+        }
+    }
+
+    async fn send(&mut self, instructions: &[Instruction]) -> Result<(), String> {
+        self.send_signed(instructions, &[]).await
+    }
+
+    async fn token_state(&mut self, holder: &Pubkey) -> Option<AccountState> {
+        let token_account = self.token_account(holder);
+        let account = self
+            .context
+            .banks_client
+            .get_account(token_account)
+            .await
+            .ok()??;
+        TokenAccount::unpack(&account.data)
+            .ok()
+            .map(|account| account.state)
+    }
+}
+
 /// Real-world Scenario 3: Geo-blocking by Jurisdiction
-#[test]
-fn test_geo_blocking() {
-    let report = run_geo_blocking_test();
+#[tokio::test]
+async fn test_geo_blocking() {
+    let report = run_geo_blocking_test().await;
     assert!(
         report.passed,
         "Geo-blocking test failed: {:?}",
@@ -195,60 +803,182 @@ fn test_geo_blocking() {
     );
 }
 
-fn run_geo_blocking_test() -> TestResultReport {
+async fn run_geo_blocking_test() -> TestResultReport {
     let test_name = "Geo-blocking by Jurisdiction";
     let mut assertions = 0;
 
-    #[derive(Debug, PartialEq, Clone)]
-    enum Jurisdiction {
-        US,
-        EU,
-        OFAC, // Sanctioned countries
-        Other,
-    }
+    let (us, de, fr, kp, br, unattested) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    // The registry allows the US and the EU and embargoes North Korea; this
+    // security token is only offered in the US and Germany
+    let mut bank = match GeoBank::start(
+        test_name,
+        &[
+            (*b"US", JurisdictionStatus::Allowed),
+            (*b"DE", JurisdictionStatus::Allowed),
+            (*b"FR", JurisdictionStatus::Allowed),
+            (*b"KP", JurisdictionStatus::Blocked),
+        ],
+        &[*b"US", *b"DE"],
+        &[
+            (us, Some(*b"US")),
+            (de, Some(*b"DE")),
+            (fr, Some(*b"FR")),
+            (kp, Some(*b"KP")),
+            (br, Some(*b"BR")),
+            (unattested, None),
+        ],
+    )
+    .await
+    {
+        Ok(bank) => bank,
+        Err(e) => return TestResultReport::failure(test_name, format!("Setup failed: {}", e)),
+    };
 
-    struct GeoGate {
-        allowed_jurisdictions: Vec<Jurisdiction>,
+    // Assertion 1: Holders in the mint's jurisdictions thaw through the gate
+    assertions += 1;
+    for (holder, label) in [(us, "US"), (de, "EU")] {
+        let thaw = match bank.thaw(&holder) {
+            Ok(thaw) => thaw,
+            Err(e) => return TestResultReport::failure(test_name, e),
+        };
+        if let Err(e) = bank.send(&thaw).await {
+            return TestResultReport::failure(
+                test_name,
+                format!("{} user blocked incorrectly: {}", label, e),
+            );
+        }
+        if bank.token_state(&holder).await != Some(AccountState::Initialized) {
+            return TestResultReport::failure(test_name, format!("{} user still frozen", label));
+        }
     }
 
-    impl GeoGate {
-        fn is_allowed(&self, jurisdiction: &Jurisdiction) -> bool {
-            self.allowed_jurisdictions.contains(jurisdiction)
+    // Assertion 2: The embargoed jurisdiction, one the mint does not offer,
+    // one the registry does not know, and an unattested holder are denied
+    assertions += 1;
+    for (holder, label) in [
+        (kp, "OFAC sanctioned country"),
+        (fr, "Jurisdiction outside the offering"),
+        (br, "Unregistered jurisdiction"),
+        (unattested, "Holder without a jurisdiction"),
+    ] {
+        let thaw = match bank.thaw(&holder) {
+            Ok(thaw) => thaw,
+            Err(e) => return TestResultReport::failure(test_name, e),
+        };
+        if bank.send(&thaw).await.is_ok()
+            || bank.token_state(&holder).await != Some(AccountState::Frozen)
+        {
+            return TestResultReport::failure(
+                test_name,
+                format!("CRITICAL: {} granted access", label),
+            );
         }
     }
 
-    // Security token: Only US and EU allowed
-    let security_token_gate = GeoGate {
-        allowed_jurisdictions: vec![Jurisdiction::US, Jurisdiction::EU],
+    // Assertion 3: A holder cannot borrow another holder's attestation
+    assertions += 1;
+    let borrowed = match bank.thaw_with_record(&fr, bank.holder_record(&de)) {
+        Ok(thaw) => thaw,
+        Err(e) => return TestResultReport::failure(test_name, e),
     };
+    if bank.send(&borrowed).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "CRITICAL: Thawed with another holder's jurisdiction record".to_string(),
+        );
+    }
 
-    // Assertion 1: US user allowed
+    // Assertion 4: Keepers may freeze holders outside the mint's
+    // jurisdictions, but not those inside them or without a record
     assertions += 1;
-    if !security_token_gate.is_allowed(&Jurisdiction::US) {
-        return TestResultReport::failure(test_name, "US user blocked incorrectly".to_string());
+    let keeper = Pubkey::new_unique();
+    for (holder, permitted) in [(kp, true), (fr, true), (us, false), (unattested, false)] {
+        let freeze = match bank.keeper_freeze(&keeper, &holder) {
+            Ok(freeze) => freeze,
+            Err(e) => return TestResultReport::failure(test_name, e),
+        };
+        if bank.send(&freeze[..1]).await.is_ok() != permitted {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "Keeper freeze check does not follow the jurisdictions: {} permitted={}",
+                    holder, permitted
+                ),
+            );
+        }
     }
 
-    // Assertion 2: EU user allowed
+    // Assertion 5: An embargo is one registry write; the keeper then freezes
+    // the US holder and their thaw is denied
     assertions += 1;
-    if !security_token_gate.is_allowed(&Jurisdiction::EU) {
-        return TestResultReport::failure(test_name, "EU user blocked incorrectly".to_string());
+    let embargo = bank.set_jurisdiction(*b"US", JurisdictionStatus::Blocked);
+    if let Err(e) = bank.send(&[embargo]).await {
+        return TestResultReport::failure(test_name, format!("Embargo failed: {}", e));
+    }
+    let freeze = match bank.keeper_freeze(&keeper, &us) {
+        Ok(freeze) => freeze,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    if let Err(e) = bank.send(&freeze).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("Keeper could not freeze the embargoed holder: {}", e),
+        );
+    }
+    let thaw = match bank.thaw(&us) {
+        Ok(thaw) => thaw,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    if bank.send(&thaw).await.is_ok() || bank.token_state(&us).await != Some(AccountState::Frozen) {
+        return TestResultReport::failure(
+            test_name,
+            "Embargo did not take effect in one update".to_string(),
+        );
     }
 
-    // Assertion 3: OFAC country blocked
+    // Assertion 6: Only the mint's authority changes its jurisdictions
     assertions += 1;
-    if security_token_gate.is_allowed(&Jurisdiction::OFAC) {
+    let stranger = Keypair::new();
+    let widen = bank.set_allowed(&[*b"US", *b"DE", *b"FR"], &stranger.pubkey());
+    if bank.send_signed(&[widen], &[&stranger]).await.is_ok() {
         return TestResultReport::failure(
             test_name,
-            "CRITICAL: OFAC sanctioned country allowed".to_string(),
+            "CRITICAL: A stranger changed the mint's jurisdictions".to_string(),
         );
     }
 
-    // Assertion 4: Other jurisdiction blocked
+    // Assertion 7: Once the issuer extends the offering to France, the
+    // French holder thaws, and the embargo still wins over the list
     assertions += 1;
-    if security_token_gate.is_allowed(&Jurisdiction::Other) {
+    let widen = bank.set_allowed(&[*b"US", *b"DE", *b"FR"], &bank.issuer.pubkey());
+    if let Err(e) = bank.send(&[widen]).await {
+        return TestResultReport::failure(test_name, format!("List update failed: {}", e));
+    }
+    let thaw = match bank.thaw(&fr) {
+        Ok(thaw) => thaw,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    if let Err(e) = bank.send(&thaw).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("French holder denied after the list update: {}", e),
+        );
+    }
+    let thaw = match bank.thaw(&us) {
+        Ok(thaw) => thaw,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    if bank.send(&thaw).await.is_ok() {
         return TestResultReport::failure(
             test_name,
-            "Non-allowed jurisdiction granted access".to_string(),
+            "CRITICAL: The mint's list overrode an embargo".to_string(),
         );
     }
 
@@ -270,94 +1000,44 @@ fn run_freeze_revocation_test() -> TestResultReport {
     let test_name = "Freeze/Thaw with Revocation";
     let mut assertions = 0;
 
-    #[derive(Debug, PartialEq, Clone)]
-    enum AccountState {
-        Frozen,
-        Thawed,
-    }
-
-    struct TokenAccount {
-        state: AccountState,
-        _owner: Pubkey,
-        revoked: bool, // Revoked accounts can never be thawed again
-    }
-
-    impl TokenAccount {
-        fn can_thaw(&self) -> bool {
-            !self.revoked && self.state == AccountState::Frozen
-        }
-
-        fn can_freeze(&self) -> bool {
-            self.state == AccountState::Thawed
-        }
-
-        fn thaw(&mut self) -> Result<(), String> {
-            if !self.can_thaw() {
-                return Err("Cannot thaw: either revoked or already thawed".to_string());
-            }
-            self.state = AccountState::Thawed;
-            Ok(())
-        }
-
-        fn freeze(&mut self) -> Result<(), String> {
-            if !self.can_freeze() {
-                return Err("Cannot freeze: already frozen".to_string());
-            }
-            self.state = AccountState::Frozen;
-            Ok(())
-        }
-
-        fn revoke(&mut self) {
-            self.revoked = true;
-            self.state = AccountState::Frozen; // Revoke implies freeze
-        }
-    }
-
-    let user = Keypair::new();
+    // The lifecycle is the state machine in `lifecycle`; `lifecycle_model`
+    // checks the same transitions against the deployed programs
 
     // Scenario 1: Normal freeze/thaw cycle
-    let mut normal_account = TokenAccount {
-        state: AccountState::Thawed,
-        _owner: user.pubkey(),
-        revoked: false,
-    };
+    let account = HolderState::Active;
 
     assertions += 1;
-    if normal_account.freeze().is_err() {
+    let Some(account) = account.transition(Transition::Freeze) else {
         return TestResultReport::failure(test_name, "Normal freeze failed".to_string());
-    }
+    };
 
     assertions += 1;
-    if normal_account.state != AccountState::Frozen {
+    if account != HolderState::Frozen {
         return TestResultReport::failure(test_name, "Account not frozen after freeze".to_string());
     }
 
     assertions += 1;
-    if normal_account.thaw().is_err() {
+    let Some(account) = account.transition(Transition::Thaw) else {
         return TestResultReport::failure(test_name, "Normal thaw failed".to_string());
-    }
+    };
 
     assertions += 1;
-    if normal_account.state != AccountState::Thawed {
+    if account != HolderState::Active {
         return TestResultReport::failure(test_name, "Account not thawed after thaw".to_string());
     }
 
     // Scenario 2: Revoked account cannot be thawed
-    let mut revoked_account = TokenAccount {
-        state: AccountState::Thawed,
-        _owner: user.pubkey(),
-        revoked: false,
+    let Some(revoked_account) = HolderState::Active.transition(Transition::Revoke) else {
+        return TestResultReport::failure(test_name, "Revoke was rejected".to_string());
     };
 
-    revoked_account.revoke();
-
     assertions += 1;
-    if revoked_account.state != AccountState::Frozen {
+    if !revoked_account.is_frozen() {
         return TestResultReport::failure(test_name, "Revoke didn't freeze account".to_string());
     }
 
     assertions += 1;
-    if revoked_account.thaw().is_ok() {
+    if revoked_account.accepts(Transition::Thaw) {
         return TestResultReport::failure(
             test_name,
             "CRITICAL: Revoked account was thawed".to_string(),
@@ -365,7 +1045,7 @@ fn run_freeze_revocation_test() -> TestResultReport {
     }
 
     assertions += 1;
-    if !revoked_account.revoked {
+    if revoked_account.is_allowed() {
         return TestResultReport::failure(test_name, "Revoke flag not set".to_string());
     }
 
@@ -373,9 +1053,12 @@ fn run_freeze_revocation_test() -> TestResultReport {
 }
 
 /// Real-world Scenario 5: Multi-step RWA Workflow
-#[test]
-fn test_multistep_rwa_workflow() {
-    let report = run_multistep_workflow_test();
+///
+/// The shared `scenarios::Scenario::RwaWorkflow`, on a bank of its own; the
+/// test client runs the same workflow against devnet.
+#[tokio::test]
+async fn test_multistep_rwa_workflow() {
+    let report = run_multistep_workflow_test().await;
     assert!(
         report.passed,
         "Multi-step workflow test failed: {:?}",
@@ -383,159 +1066,29 @@ fn test_multistep_rwa_workflow() {
     );
 }
 
-fn run_multistep_workflow_test() -> TestResultReport {
+async fn run_multistep_workflow_test() -> TestResultReport {
     let test_name = "Multi-step RWA Workflow";
-    let mut assertions = 0;
-
-    #[derive(Debug, Clone)]
-    struct InvestorOnboarding {
-        kyc_complete: bool,
-        accreditation_verified: bool,
-        jurisdiction_allowed: bool,
-        not_sanctioned: bool,
-        account_created: bool,
-        account_thawed: bool,
-    }
-
-    impl InvestorOnboarding {
-        fn new() -> Self {
-            Self {
-                kyc_complete: false,
-                accreditation_verified: false,
-                jurisdiction_allowed: false,
-                not_sanctioned: false,
-                account_created: false,
-                account_thawed: false,
-            }
-        }
-
-        fn can_proceed_to_trading(&self) -> bool {
-            self.kyc_complete
-                && self.accreditation_verified
-                && self.jurisdiction_allowed
-                && self.not_sanctioned
-                && self.account_created
-                && self.account_thawed
-        }
-
-        fn progress(&self) -> f32 {
-            let mut steps_complete = 0;
-            if self.kyc_complete {
-                steps_complete += 1;
-            }
-            if self.accreditation_verified {
-                steps_complete += 1;
-            }
-            if self.jurisdiction_allowed {
-                steps_complete += 1;
-            }
-            if self.not_sanctioned {
-                steps_complete += 1;
-            }
-            if self.account_created {
-                steps_complete += 1;
-            }
-            if self.account_thawed {
-                steps_complete += 1;
-            }
-            (steps_complete as f32 / 6.0) * 100.0
-        }
+    if let Some(skipped) = suite_config::skip_unless(SuiteCategory::LongScenarios, test_name) {
+        return skipped;
     }
 
-    // Scenario 1: Successful complete onboarding
-    let mut successful_investor = InvestorOnboarding::new();
-
-    successful_investor.kyc_complete = true;
-    successful_investor.accreditation_verified = true;
-    successful_investor.jurisdiction_allowed = true;
-    successful_investor.not_sanctioned = true;
-    successful_investor.account_created = true;
-    successful_investor.account_thawed = true;
-
-    assertions += 1;
-    if !successful_investor.can_proceed_to_trading() {
-        return TestResultReport::failure(
-            test_name,
-            "Fully onboarded investor cannot trade".to_string(),
-        );
+    match Scenario::RwaWorkflow.run_on_bank(test_name).await {
+        Ok(outcome) => TestResultReport::success(test_name, outcome.checks),
+        Err(e) => TestResultReport::failure(test_name, e),
     }
-
-    assertions += 1;
-    if successful_investor.progress() != 100.0 {
-        return TestResultReport::failure(
-            test_name,
-            format!(
-                "Progress should be 100%, got {}",
-                successful_investor.progress()
-            ),
-        );
-    }
-
-    // Scenario 2: Incomplete onboarding (missing accreditation)
-    let mut incomplete_investor = InvestorOnboarding::new();
-    incomplete_investor.kyc_complete = true;
-    incomplete_investor.jurisdiction_allowed = true;
-    incomplete_investor.not_sanctioned = true;
-    incomplete_investor.account_created = true;
-    incomplete_investor.account_thawed = true;
-    // accreditation_verified = false
-
-    assertions += 1;
-    if incomplete_investor.can_proceed_to_trading() {
-        return TestResultReport::failure(
-            test_name,
-            "Non-accredited investor allowed to trade".to_string(),
-        );
-    }
-
-    // Scenario 3: Sanctioned investor (everything else complete)
-    let mut sanctioned_investor = InvestorOnboarding::new();
-    sanctioned_investor.kyc_complete = true;
-    sanctioned_investor.accreditation_verified = true;
-    sanctioned_investor.jurisdiction_allowed = true;
-    sanctioned_investor.not_sanctioned = false; // SANCTIONED
-    sanctioned_investor.account_created = true;
-    sanctioned_investor.account_thawed = true;
-
-    assertions += 1;
-    if sanctioned_investor.can_proceed_to_trading() {
-        return TestResultReport::failure(
-            test_name,
-            "CRITICAL: Sanctioned investor allowed to trade".to_string(),
-        );
-    }
-
-    // Scenario 4: Account created but not thawed (DAS frozen)
-    let mut frozen_investor = InvestorOnboarding::new();
-    frozen_investor.kyc_complete = true;
-    frozen_investor.accreditation_verified = true;
-    frozen_investor.jurisdiction_allowed = true;
-    frozen_investor.not_sanctioned = true;
-    frozen_investor.account_created = true;
-    frozen_investor.account_thawed = false; // Still frozen
-
-    assertions += 1;
-    if frozen_investor.can_proceed_to_trading() {
-        return TestResultReport::failure(
-            test_name,
-            "Investor with frozen account allowed to trade".to_string(),
-        );
-    }
-
-    TestResultReport::success(test_name, assertions)
 }
 
 /// Generate comprehensive test report for advanced scenarios
-#[test]
-fn generate_advanced_scenarios_report() {
-    let mut results = vec![];
-
+#[tokio::test]
+async fn generate_advanced_scenarios_report() {
     // Run all advanced scenario tests
-    results.push(run_kyc_expiration_test());
-    results.push(run_sanctions_precedence_test());
-    results.push(run_geo_blocking_test());
-    results.push(run_freeze_revocation_test());
-    results.push(run_multistep_workflow_test());
+    let results = vec![
+        run_kyc_expiration_test(),
+        run_sanctions_precedence_test().await,
+        run_geo_blocking_test().await,
+        run_freeze_revocation_test(),
+        run_multistep_workflow_test().await,
+    ];
 
     // Generate report
     let total = results.len();
@@ -544,17 +1097,14 @@ fn generate_advanced_scenarios_report() {
     let total_assertions: usize = results.iter().map(|r| r.assertions_run).sum();
 
     let mut report = String::from("# Token ACL Advanced Scenarios Test Results\n\n");
-    report.push_str(&format!(
-        "**Generated**: {}\n\n",
-        chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC")
-    ));
+    report.push_str(&format!("**Generated**: {}\n\n", time::report_timestamp()));
 
     report.push_str("## Summary\n\n");
     report.push_str(&format!("- **Total Tests**: {}\n", total));
     report.push_str(&format!(
-        "- **Passed**: {} ({}%)\n",
+        "- **Passed**: {} ({})\n",
         passed,
-        (passed * 100) / total
+        units::percent(passed as u64, total as u64)
     ));
     report.push_str(&format!("- **Failed**: {}\n", failed));
     report.push_str(&format!("- **Total Assertions**: {}\n\n", total_assertions));
@@ -575,9 +1125,10 @@ fn generate_advanced_scenarios_report() {
     report.push_str("✅ Unknown users blocked by default\n\n");
 
     report.push_str("### 3. Geo-blocking\n");
-    report.push_str("✅ Allowed jurisdictions granted access\n");
-    report.push_str("✅ OFAC countries blocked\n");
-    report.push_str("✅ Non-allowed jurisdictions blocked\n\n");
+    report.push_str("✅ Holders in the mint's jurisdictions thaw through the geo gate\n");
+    report.push_str("✅ OFAC countries blocked, and frozen by keepers after an embargo\n");
+    report.push_str("✅ Jurisdictions outside the offering, and unattested holders, blocked\n");
+    report.push_str("✅ Only the mint's authority changes its jurisdictions\n\n");
 
     report.push_str("### 4. Freeze/Thaw with Revocation\n");
     report.push_str("✅ Normal freeze/thaw cycle works\n");
@@ -585,10 +1136,9 @@ fn generate_advanced_scenarios_report() {
     report.push_str("✅ Revoke implies freeze\n\n");
 
     report.push_str("### 5. Multi-step RWA Workflow\n");
-    report.push_str("✅ All steps required for trading\n");
-    report.push_str("✅ Incomplete onboarding blocks trading\n");
-    report.push_str("✅ Sanctioned investors blocked regardless\n");
-    report.push_str("✅ Frozen accounts cannot trade\n\n");
+    report.push_str("✅ Investors at every tier onboarded with expiries\n");
+    report.push_str("✅ Unverified and lapsed investors stay frozen\n");
+    report.push_str("✅ Sanctioned investors blocked and cannot be re-listed\n\n");
 
     report.push_str("## Detailed Results\n\n");
     report.push_str("| Test | Status | Assertions | Details |\n");
@@ -607,7 +1157,7 @@ fn generate_advanced_scenarios_report() {
         ));
     }
 
-    report.push_str("\n");
+    report.push('\n');
 
     // Write to file
     std::fs::create_dir_all("../../tests/reports").ok();
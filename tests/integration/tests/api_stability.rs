@@ -0,0 +1,804 @@
+//! Public API and account layout stability tests
+//!
+//! Gate developers build against `token_acl_interface` and `token_acl_client`
+//! and read gate accounts by offset, so both are covered by semver: a patch
+//! or minor release may add to them, never take away or change.
+//!
+//! The programs in `tests/api_stability` use every public item of the two
+//! crates with its signature spelled out, and trybuild checks they still
+//! build. Removing or renaming an item, changing a signature, or adding a
+//! variant or public field (which breaks exhaustive matches and struct
+//! literals downstream) fails here. Additions are free, but a new item
+//! should be added to the surface program so it is pinned from then on.
+//!
+//! The Borsh layout of every published account type is rendered as an
+//! offset table and compared against the `account_layouts` snapshot. Each
+//! table is built field by field from a sample value whose fields all
+//! differ, and the fields must encode to exactly the account, so a field
+//! that is added, dropped, reordered or resized changes the snapshot.
+//! Variable-length fields are sampled both ways (`None` and `Some`, no
+//! entries and a full account). Accepting a change with `UPDATE_SNAPSHOTS=1`
+//! breaks every reader of the account, and needs a major release.
+
+use std::{fmt::Debug, panic};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_sdk::pubkey::Pubkey;
+
+use token_acl_integration_tests::{reporting, snapshots::assert_snapshot, TestResultReport};
+
+const SURFACE_CASES: [&str; 2] = [
+    "tests/api_stability/interface_surface.rs",
+    "tests/api_stability/client_surface.rs",
+];
+
+/// One account type's fields, with the offset and size of each
+struct AccountLayout {
+    name: String,
+    fields: Vec<(&'static str, usize, usize)>,
+    len: usize,
+}
+
+/// Lay out `fields`, which must encode to exactly `account`, in order
+fn account_layout(
+    name: &str,
+    account: Vec<u8>,
+    fields: Vec<(&'static str, Vec<u8>)>,
+) -> Result<AccountLayout, String> {
+    let concatenated: Vec<u8> = fields.iter().flat_map(|(_, bytes)| bytes.clone()).collect();
+    if concatenated != account {
+        return Err(format!(
+            "{}: the listed fields do not encode to the account; is one missing or out of order?",
+            name
+        ));
+    }
+    let mut offset = 0;
+    let fields = fields
+        .into_iter()
+        .map(|(field, bytes)| {
+            let entry = (field, offset, bytes.len());
+            offset += bytes.len();
+            entry
+        })
+        .collect();
+    Ok(AccountLayout {
+        name: name.to_string(),
+        fields,
+        len: account.len(),
+    })
+}
+
+fn encode<T: BorshSerialize>(value: &T) -> Vec<u8> {
+    value.try_to_vec().expect("account types always encode")
+}
+
+/// Lay out a Borsh account type from `value`, naming every field in
+/// declaration order; the destructuring makes a new field a compile error
+macro_rules! borsh_layout {
+    ($name:expr, $value:expr, $ty:path { $($field:ident),+ $(,)? }) => {{
+        let value: $ty = $value;
+        let $ty { $($field: _),+ } = &value;
+        account_layout(
+            $name,
+            encode(&value),
+            vec![$((stringify!($field), encode(&value.$field))),+],
+        )
+    }};
+}
+
+/// Every byte a one-byte enum decodes from, with the variant it decodes to
+fn enum_bytes<T: Debug>(decode: impl Fn(u8) -> Option<T>) -> Vec<(u8, String)> {
+    (0..=u8::MAX)
+        .filter_map(|byte| decode(byte).map(|variant| (byte, format!("{:?}", variant))))
+        .collect()
+}
+
+fn borsh_enum<T: BorshDeserialize + Debug>() -> Vec<(u8, String)> {
+    enum_bytes(|byte| T::try_from_slice(&[byte]).ok())
+}
+
+fn production_record(expiry_timestamp: Option<i64>) -> production_allow_list::AllowListRecord {
+    production_allow_list::AllowListRecord {
+        mint: Pubkey::new_unique(),
+        user: Pubkey::new_unique(),
+        allowed: true,
+        access_level: production_allow_list::AccessLevel::Enhanced,
+        added_timestamp: 1_700_000_000,
+        expiry_timestamp,
+        bump: 254,
+        revoked: false,
+    }
+}
+
+fn hybrid_config(
+    jurisdictions: Option<hybrid_gate::JurisdictionSource>,
+) -> hybrid_gate::HybridConfig {
+    hybrid_gate::HybridConfig {
+        authority: Pubkey::new_unique(),
+        mint: Pubkey::new_unique(),
+        allow_list_program: Pubkey::new_unique(),
+        block_list_program: Pubkey::new_unique(),
+        jurisdictions,
+        cooling_off_seconds: 86_400,
+        paused: true,
+        bump: 253,
+    }
+}
+
+fn jurisdiction_registry(entries: usize) -> jurisdiction_registry::JurisdictionRegistry {
+    use jurisdiction_registry::{JurisdictionEntry, JurisdictionStatus};
+    jurisdiction_registry::JurisdictionRegistry {
+        authority: Pubkey::new_unique(),
+        version: 7,
+        bump: 252,
+        entries: (0..entries)
+            .map(|i| JurisdictionEntry {
+                code: [b'A' + (i / 26) as u8, b'A' + (i % 26) as u8],
+                status: JurisdictionStatus::Blocked,
+            })
+            .collect(),
+    }
+}
+
+fn geo_config(allowed: usize) -> geo_gate::GeoConfig {
+    geo_gate::GeoConfig {
+        authority: Pubkey::new_unique(),
+        mint: Pubkey::new_unique(),
+        registry_program: Pubkey::new_unique(),
+        registry: Pubkey::new_unique(),
+        bump: 251,
+        allowed: (0..allowed)
+            .map(|i| [b'A' + (i / 26) as u8, b'A' + (i % 26) as u8])
+            .collect(),
+    }
+}
+
+/// Every published account type, laid out from sample values
+fn account_layouts() -> Result<Vec<AccountLayout>, String> {
+    use token_acl_interface::{events::ThawDecision, GateInterfaceVersion};
+
+    let version = GateInterfaceVersion::CURRENT;
+    let decision = ThawDecision::new(
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        token_acl_interface::events::DecisionReason::Revoked,
+    );
+
+    Ok(vec![
+        account_layout(
+            "token_acl_interface::GateInterfaceVersion",
+            version.pack().to_vec(),
+            vec![
+                ("version", vec![version.version]),
+                ("features", version.features.to_le_bytes().to_vec()),
+            ],
+        )?,
+        account_layout(
+            "token_acl_interface::events::ThawDecision",
+            decision.pack().to_vec(),
+            vec![
+                ("mint", decision.mint.to_bytes().to_vec()),
+                ("owner", decision.owner.to_bytes().to_vec()),
+                ("approved", vec![decision.approved as u8]),
+                ("reason", vec![decision.reason as u8]),
+            ],
+        )?,
+        borsh_layout!(
+            "production_allow_list::Config",
+            production_allow_list::Config {
+                authority: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                bump: 255,
+            },
+            production_allow_list::Config {
+                authority,
+                mint,
+                bump
+            }
+        )?,
+        borsh_layout!(
+            "production_allow_list::PendingAuthority",
+            production_allow_list::PendingAuthority {
+                mint: Pubkey::new_unique(),
+                proposed: Pubkey::new_unique(),
+                bump: 255,
+            },
+            production_allow_list::PendingAuthority {
+                mint,
+                proposed,
+                bump
+            }
+        )?,
+        borsh_layout!(
+            "production_allow_list::AllowListRecord (no expiry)",
+            production_record(None),
+            production_allow_list::AllowListRecord {
+                mint,
+                user,
+                allowed,
+                access_level,
+                added_timestamp,
+                expiry_timestamp,
+                bump,
+                revoked,
+            }
+        )?,
+        borsh_layout!(
+            "production_allow_list::AllowListRecord (with expiry)",
+            production_record(Some(1_800_000_000)),
+            production_allow_list::AllowListRecord {
+                mint,
+                user,
+                allowed,
+                access_level,
+                added_timestamp,
+                expiry_timestamp,
+                bump,
+                revoked,
+            }
+        )?,
+        borsh_layout!(
+            "production_allow_list::SnapshotRoot",
+            production_allow_list::SnapshotRoot {
+                mint: Pubkey::new_unique(),
+                root: [7; 32],
+                bump: 255,
+            },
+            production_allow_list::SnapshotRoot { mint, root, bump }
+        )?,
+        borsh_layout!(
+            "production_allow_list::ReviewRequest",
+            production_allow_list::ReviewRequest {
+                mint: Pubkey::new_unique(),
+                user: Pubkey::new_unique(),
+                requested_timestamp: 1_700_000_000,
+                bump: 255,
+            },
+            production_allow_list::ReviewRequest {
+                mint,
+                user,
+                requested_timestamp,
+                bump
+            }
+        )?,
+        borsh_layout!(
+            "example_allow_list::AllowListRecord",
+            example_allow_list::AllowListRecord {
+                mint: Pubkey::new_unique(),
+                user: Pubkey::new_unique(),
+                allowed: true,
+                added_timestamp: 1_700_000_000,
+            },
+            example_allow_list::AllowListRecord {
+                mint,
+                user,
+                allowed,
+                added_timestamp,
+            }
+        )?,
+        borsh_layout!(
+            "example_block_list::Config",
+            example_block_list::Config {
+                authority: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                bump: 254,
+            },
+            example_block_list::Config {
+                authority,
+                mint,
+                bump
+            }
+        )?,
+        borsh_layout!(
+            "example_block_list::PendingAuthority",
+            example_block_list::PendingAuthority {
+                mint: Pubkey::new_unique(),
+                proposed: Pubkey::new_unique(),
+                bump: 255,
+            },
+            example_block_list::PendingAuthority {
+                mint,
+                proposed,
+                bump
+            }
+        )?,
+        {
+            // Written before blocks could expire, and still decoded
+            let record = example_block_list::BlockListRecord {
+                mint: Pubkey::new_unique(),
+                user: Pubkey::new_unique(),
+                blocked: true,
+                reason: example_block_list::BlockReason::RiskAssessment,
+                added_timestamp: 1_700_000_000,
+                expiry_timestamp: None,
+            };
+            let mut legacy = encode(&record);
+            legacy.truncate(example_block_list::BlockListRecord::LEGACY_LEN);
+            account_layout(
+                "example_block_list::BlockListRecord (legacy)",
+                legacy,
+                vec![
+                    ("mint", encode(&record.mint)),
+                    ("user", encode(&record.user)),
+                    ("blocked", encode(&record.blocked)),
+                    ("reason", encode(&record.reason)),
+                    ("added_timestamp", encode(&record.added_timestamp)),
+                ],
+            )?
+        },
+        borsh_layout!(
+            "example_block_list::BlockListRecord (no expiry)",
+            example_block_list::BlockListRecord {
+                mint: Pubkey::new_unique(),
+                user: Pubkey::new_unique(),
+                blocked: true,
+                reason: example_block_list::BlockReason::RiskAssessment,
+                added_timestamp: 1_700_000_000,
+                expiry_timestamp: None,
+            },
+            example_block_list::BlockListRecord {
+                mint,
+                user,
+                blocked,
+                reason,
+                added_timestamp,
+                expiry_timestamp,
+            }
+        )?,
+        borsh_layout!(
+            "example_block_list::BlockListRecord (with expiry)",
+            example_block_list::BlockListRecord {
+                mint: Pubkey::new_unique(),
+                user: Pubkey::new_unique(),
+                blocked: true,
+                reason: example_block_list::BlockReason::RiskAssessment,
+                added_timestamp: 1_700_000_000,
+                expiry_timestamp: Some(1_800_000_000),
+            },
+            example_block_list::BlockListRecord {
+                mint,
+                user,
+                blocked,
+                reason,
+                added_timestamp,
+                expiry_timestamp,
+            }
+        )?,
+        borsh_layout!(
+            "hybrid_gate::HybridConfig (no jurisdictions)",
+            hybrid_config(None),
+            hybrid_gate::HybridConfig {
+                authority,
+                mint,
+                allow_list_program,
+                block_list_program,
+                jurisdictions,
+                cooling_off_seconds,
+                paused,
+                bump,
+            }
+        )?,
+        borsh_layout!(
+            "hybrid_gate::HybridConfig (with jurisdictions)",
+            hybrid_config(Some(hybrid_gate::JurisdictionSource {
+                program: Pubkey::new_unique(),
+                registry: Pubkey::new_unique(),
+            })),
+            hybrid_gate::HybridConfig {
+                authority,
+                mint,
+                allow_list_program,
+                block_list_program,
+                jurisdictions,
+                cooling_off_seconds,
+                paused,
+                bump,
+            }
+        )?,
+        borsh_layout!(
+            "jurisdiction_registry::JurisdictionRegistry (no entries)",
+            jurisdiction_registry(0),
+            jurisdiction_registry::JurisdictionRegistry {
+                authority,
+                version,
+                bump,
+                entries,
+            }
+        )?,
+        borsh_layout!(
+            "jurisdiction_registry::JurisdictionRegistry (full)",
+            jurisdiction_registry(jurisdiction_registry::MAX_JURISDICTIONS),
+            jurisdiction_registry::JurisdictionRegistry {
+                authority,
+                version,
+                bump,
+                entries,
+            }
+        )?,
+        borsh_layout!(
+            "jurisdiction_registry::HolderJurisdiction",
+            jurisdiction_registry::HolderJurisdiction {
+                registry: Pubkey::new_unique(),
+                holder: Pubkey::new_unique(),
+                code: *b"CH",
+                bump: 255,
+            },
+            jurisdiction_registry::HolderJurisdiction {
+                registry,
+                holder,
+                code,
+                bump,
+            }
+        )?,
+        borsh_layout!(
+            "threshold_gate::ThresholdConfig (two approvers)",
+            threshold_gate::ThresholdConfig {
+                authority: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                threshold: 2,
+                approvers: vec![Pubkey::new_unique(), Pubkey::new_unique()],
+                bump: 255,
+            },
+            threshold_gate::ThresholdConfig {
+                authority,
+                mint,
+                threshold,
+                approvers,
+                bump,
+            }
+        )?,
+        borsh_layout!(
+            "threshold_gate::Approval",
+            threshold_gate::Approval {
+                mint: Pubkey::new_unique(),
+                user: Pubkey::new_unique(),
+                approver: Pubkey::new_unique(),
+                bump: 255,
+            },
+            threshold_gate::Approval {
+                mint,
+                user,
+                approver,
+                bump,
+            }
+        )?,
+        borsh_layout!(
+            "geo_gate::GeoConfig (no jurisdictions)",
+            geo_config(0),
+            geo_gate::GeoConfig {
+                authority,
+                mint,
+                registry_program,
+                registry,
+                bump,
+                allowed,
+            }
+        )?,
+        borsh_layout!(
+            "geo_gate::GeoConfig (full)",
+            geo_config(geo_gate::MAX_ALLOWED_JURISDICTIONS),
+            geo_gate::GeoConfig {
+                authority,
+                mint,
+                registry_program,
+                registry,
+                bump,
+                allowed,
+            }
+        )?,
+        borsh_layout!(
+            "nft_gate::NftConfig",
+            nft_gate::NftConfig {
+                authority: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                collection: Pubkey::new_unique(),
+                bump: 254,
+            },
+            nft_gate::NftConfig {
+                authority,
+                mint,
+                collection,
+                bump,
+            }
+        )?,
+        borsh_layout!(
+            "velocity_gate::VelocityConfig",
+            velocity_gate::VelocityConfig {
+                authority: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                max_thaws: 3,
+                window_seconds: 3_600,
+                bump: 255,
+            },
+            velocity_gate::VelocityConfig {
+                authority,
+                mint,
+                max_thaws,
+                window_seconds,
+                bump,
+            }
+        )?,
+        borsh_layout!(
+            "velocity_gate::ThawLedger",
+            velocity_gate::ThawLedger {
+                mint: Pubkey::new_unique(),
+                user: Pubkey::new_unique(),
+                next: 2,
+                len: 5,
+                timestamps: [1_700_000_000; velocity_gate::LEDGER_CAPACITY],
+                bump: 255,
+            },
+            velocity_gate::ThawLedger {
+                mint,
+                user,
+                next,
+                len,
+                timestamps,
+                bump,
+            }
+        )?,
+        borsh_layout!(
+            "decision_cache::DecisionEntry",
+            decision_cache::DecisionEntry {
+                gate_program: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                owner: Pubkey::new_unique(),
+                payer: Pubkey::new_unique(),
+                accounts_hash: [1; 32],
+                state_hash: [2; 32],
+                decided_slot: 42,
+                decided_timestamp: 1_700_000_000,
+                bump: 255,
+            },
+            decision_cache::DecisionEntry {
+                gate_program,
+                mint,
+                owner,
+                payer,
+                accounts_hash,
+                state_hash,
+                decided_slot,
+                decided_timestamp,
+                bump,
+            }
+        )?,
+    ])
+}
+
+/// Every one-byte enum stored in a published account, with the bytes it
+/// decodes from
+fn enum_layouts() -> Vec<(&'static str, Vec<(u8, String)>)> {
+    use token_acl_interface::{events::DecisionReason, MissingRecordPolicy};
+    vec![
+        (
+            "token_acl_interface::MissingRecordPolicy",
+            enum_bytes(|byte| MissingRecordPolicy::unpack(&[byte]).ok()),
+        ),
+        (
+            "token_acl_interface::events::DecisionReason",
+            enum_bytes(DecisionReason::from_u8),
+        ),
+        (
+            "production_allow_list::AccessLevel",
+            borsh_enum::<production_allow_list::AccessLevel>(),
+        ),
+        (
+            "example_block_list::BlockReason",
+            borsh_enum::<example_block_list::BlockReason>(),
+        ),
+        (
+            "jurisdiction_registry::JurisdictionStatus",
+            borsh_enum::<jurisdiction_registry::JurisdictionStatus>(),
+        ),
+    ]
+}
+
+fn render_layouts(
+    accounts: &[AccountLayout],
+    enums: &[(&'static str, Vec<(u8, String)>)],
+) -> String {
+    let mut out = String::from("# Published account layouts\n");
+    for account in accounts {
+        out.push_str(&format!(
+            "\n## {}\n\n| Offset | Size | Field |\n|--------|------|-------|\n",
+            account.name
+        ));
+        for (field, offset, size) in &account.fields {
+            out.push_str(&format!("| {} | {} | {} |\n", offset, size, field));
+        }
+        out.push_str(&format!("\n{} bytes\n", account.len));
+    }
+    for (name, variants) in enums {
+        out.push_str(&format!(
+            "\n## {}\n\n| Byte | Variant |\n|------|---------|\n",
+            name
+        ));
+        for (byte, variant) in variants {
+            out.push_str(&format!("| {} | {} |\n", byte, variant));
+        }
+    }
+    out
+}
+
+/// Test 1: The public surface of the interface and client crates still
+/// builds against every pinned signature
+#[tokio::test]
+async fn test_public_surface_pinned() {
+    let report = run_public_surface_test();
+    assert!(
+        report.passed,
+        "Public surface test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_public_surface_test() -> TestResultReport {
+    let test_name = "Public Surface Pinned";
+    let mut assertions = 0;
+
+    // Assertion 1: Both surface programs build and run (trybuild reports
+    // every case when the test cases drop)
+    assertions += 1;
+    let outcome = panic::catch_unwind(|| {
+        let cases = trybuild::TestCases::new();
+        for case in SURFACE_CASES {
+            cases.pass(case);
+        }
+    });
+    if outcome.is_err() {
+        return TestResultReport::failure(
+            test_name,
+            "A pinned public item was removed or changed; see the trybuild output".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: The Borsh layouts of the published account types match the
+/// committed snapshot
+#[tokio::test]
+async fn test_account_layouts_unchanged() {
+    let report = run_account_layouts_test();
+    assert!(
+        report.passed,
+        "Account layouts test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_account_layouts_test() -> TestResultReport {
+    let test_name = "Account Layouts Unchanged";
+    let mut assertions = 0;
+
+    // Assertion 1: Every account type's fields encode to exactly the account
+    assertions += 1;
+    let accounts = match account_layouts() {
+        Ok(accounts) => accounts,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+
+    // Assertion 2: The enums decode from the bytes they always have
+    assertions += 1;
+    let enums = enum_layouts();
+    if let Some((name, _)) = enums.iter().find(|(_, variants)| variants.is_empty()) {
+        return TestResultReport::failure(test_name, format!("{} decodes from no byte", name));
+    }
+
+    // Assertion 3: The offset tables match the snapshot
+    assertions += 1;
+    if let Err(e) = assert_snapshot("account_layouts", &render_layouts(&accounts, &enums)) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: The sizes published as constants agree with the layouts
+#[tokio::test]
+async fn test_published_sizes_agree() {
+    let report = run_published_sizes_test();
+    assert!(
+        report.passed,
+        "Published sizes test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_published_sizes_test() -> TestResultReport {
+    let test_name = "Published Sizes Agree";
+    let mut assertions = 0;
+
+    let accounts = match account_layouts() {
+        Ok(accounts) => accounts,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let len = |name: &str| {
+        accounts
+            .iter()
+            .find(|account| account.name == name)
+            .map(|account| account.len)
+    };
+
+    let constants = [
+        (
+            "token_acl_interface::GateInterfaceVersion",
+            token_acl_interface::GateInterfaceVersion::LEN,
+        ),
+        (
+            "token_acl_interface::events::ThawDecision",
+            token_acl_interface::events::ThawDecision::LEN,
+        ),
+        (
+            "production_allow_list::AllowListRecord (no expiry)",
+            gate_view::ALLOW_LIST_RECORD_LEN,
+        ),
+        (
+            "production_allow_list::AllowListRecord (with expiry)",
+            gate_view::ALLOW_LIST_RECORD_WITH_EXPIRY_LEN,
+        ),
+        (
+            "example_block_list::BlockListRecord (legacy)",
+            gate_view::BLOCK_LIST_LEGACY_RECORD_LEN,
+        ),
+        (
+            "example_block_list::BlockListRecord (no expiry)",
+            gate_view::BLOCK_LIST_RECORD_LEN,
+        ),
+        (
+            "example_block_list::BlockListRecord (with expiry)",
+            gate_view::BLOCK_LIST_RECORD_WITH_EXPIRY_LEN,
+        ),
+        (
+            "jurisdiction_registry::JurisdictionRegistry (full)",
+            jurisdiction_registry::JurisdictionRegistry::LEN,
+        ),
+        ("geo_gate::GeoConfig (full)", geo_gate::GeoConfig::LEN),
+        ("nft_gate::NftConfig", nft_gate::NftConfig::LEN),
+        ("threshold_gate::Approval", threshold_gate::Approval::LEN),
+        ("velocity_gate::ThawLedger", velocity_gate::ThawLedger::LEN),
+        (
+            "decision_cache::DecisionEntry",
+            decision_cache::DecisionEntry::LEN,
+        ),
+    ];
+
+    // Assertions 1-13: Each constant is the size of the account it describes
+    for (name, constant) in constants {
+        assertions += 1;
+        if len(name) != Some(constant) {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "{} is published as {} bytes but lays out as {:?}",
+                    name,
+                    constant,
+                    len(name)
+                ),
+            );
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate API stability test report
+#[tokio::test]
+async fn generate_api_stability_report() {
+    let results = vec![
+        run_public_surface_test(),
+        run_account_layouts_test(),
+        run_published_sizes_test(),
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "API Stability Test Results",
+        "../../tests/reports/api_stability.md",
+    ) {
+        panic!("Failed to generate API stability report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} API stability tests failed", failed);
+}
@@ -0,0 +1,149 @@
+// Every public item of token_acl_client, with its signature spelled out:
+// removing one or changing its signature fails to compile
+
+use gate_view::{AllowListView, BlockListView};
+use production_allow_list::{AccessLevel, SnapshotEntry};
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_filter::RpcFilterType};
+use solana_program::{instruction::Instruction, program_error::ProgramError, pubkey::Pubkey};
+use spl_tlv_account_resolution::state::AccountDataResult;
+use token_acl_client::{
+    instructions::{self, FreezeAuthority},
+    snapshot::{self, AllowListSnapshot, SnapshotError},
+    status::{
+        self, Gates, HolderStatus, RpcStatusSource, StatusError, StatusSource, TokenAccountStatus,
+    },
+};
+
+fn instruction_builders() {
+    let _: &[u8] = instructions::MINT_CONFIG_SEED;
+    let _: fn(&Pubkey, &Pubkey) -> (Pubkey, u8) = instructions::find_mint_config_address;
+    let _: fn(&FreezeAuthority, &Pubkey) -> Pubkey = FreezeAuthority::address;
+    let _: Pubkey = match FreezeAuthority::Key(Pubkey::default()) {
+        FreezeAuthority::MintConfig { famp_program_id } => famp_program_id,
+        FreezeAuthority::Key(key) => key,
+    };
+    let _: fn(&Pubkey, &Pubkey, &Pubkey, &Pubkey) -> Instruction =
+        instructions::permissionless_thaw;
+    let _: fn(&Pubkey, &Pubkey, &Pubkey, &Pubkey) -> Instruction =
+        instructions::permissionless_freeze;
+    let _: fn(Instruction, &[Pubkey]) -> Instruction = instructions::with_extra_accounts;
+    let _: fn(Instruction, &FreezeAuthority) -> Result<[Instruction; 2], ProgramError> =
+        instructions::authorized;
+}
+
+async fn resolve(check: Instruction) -> Result<Instruction, ProgramError> {
+    instructions::resolve_extra_accounts(check, |_address: Pubkey| async {
+        AccountDataResult::Ok(None)
+    })
+    .await
+}
+
+fn snapshots() {
+    let _: fn(Pubkey, Vec<SnapshotEntry>) -> Result<AllowListSnapshot, SnapshotError> =
+        AllowListSnapshot::new;
+    let _: fn(&AllowListSnapshot) -> &Pubkey = AllowListSnapshot::mint;
+    let _: fn(&AllowListSnapshot) -> usize = AllowListSnapshot::len;
+    let _: fn(&AllowListSnapshot) -> bool = AllowListSnapshot::is_empty;
+    let _: fn(&AllowListSnapshot) -> [u8; 32] = AllowListSnapshot::root;
+    let _: for<'a> fn(&'a AllowListSnapshot, &Pubkey) -> Option<&'a SnapshotEntry> =
+        AllowListSnapshot::entry;
+    let _: fn(&AllowListSnapshot, &Pubkey) -> Option<Vec<[u8; 32]>> = AllowListSnapshot::proof;
+    let _: fn(&AllowListSnapshot, &Pubkey, &Pubkey) -> Option<Instruction> =
+        AllowListSnapshot::materialize;
+    let _: fn(&Pubkey, &Pubkey, &Pubkey, &Pubkey, [u8; 32]) -> Instruction = snapshot::set_root;
+    let _: fn(&Pubkey, &Pubkey, &SnapshotEntry, Vec<[u8; 32]>) -> Instruction =
+        snapshot::materialize;
+    let _: Option<Pubkey> = match SnapshotError::Empty {
+        SnapshotError::Empty => None,
+        SnapshotError::DuplicateUser(user) => Some(user),
+    };
+    let _: &dyn std::error::Error = &SnapshotError::Empty;
+
+    let entry = SnapshotEntry {
+        user: Pubkey::new_unique(),
+        access_level: AccessLevel::Basic,
+        expiry_timestamp: None,
+    };
+    let _ = AllowListSnapshot::new(Pubkey::new_unique(), [entry]);
+}
+
+// The trait's methods, pinned by implementing it
+struct NoAccounts;
+
+impl StatusSource for NoAccounts {
+    async fn account(&self, _address: &Pubkey) -> Result<Option<(Pubkey, Vec<u8>)>, StatusError> {
+        Ok(None)
+    }
+
+    async fn token_accounts(
+        &self,
+        _mint: &Pubkey,
+        _owner: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Vec<u8>)>, StatusError> {
+        Ok(Vec::new())
+    }
+}
+
+async fn status_queries(rpc: RpcClient) -> Result<(), StatusError> {
+    let _: fn(&str) -> RpcStatusSource = RpcStatusSource::new;
+    let source = RpcStatusSource::from_client(rpc);
+    let unix_timestamp: i64 = source.unix_timestamp().await?;
+    let _ = source.account(&Pubkey::default()).await?;
+
+    let gates = Gates {
+        allow_list: Some(Pubkey::new_unique()),
+        block_list: None,
+    };
+    let status: HolderStatus = HolderStatus::query(
+        &NoAccounts,
+        gates,
+        &Pubkey::default(),
+        &Pubkey::default(),
+        unix_timestamp,
+    )
+    .await?;
+    let _: Option<bool> = status.allow_listed();
+    let _: Option<bool> = status.blocked();
+    let _: String = status.render();
+
+    let HolderStatus {
+        mint,
+        owner,
+        gates,
+        allow_list,
+        block_list,
+        token_accounts,
+        unix_timestamp,
+    } = status;
+    let _: (Pubkey, Pubkey, Gates, i64) = (mint, owner, gates, unix_timestamp);
+    let _: (Option<AllowListView>, Option<BlockListView>) = (allow_list, block_list);
+    for TokenAccountStatus {
+        address,
+        amount,
+        frozen,
+    } in token_accounts
+    {
+        let _: (Pubkey, u64, bool) = (address, amount, frozen);
+    }
+    Ok(())
+}
+
+fn status_helpers() {
+    let _: fn(&Pubkey, &Pubkey) -> Vec<RpcFilterType> = status::token_account_filters;
+    let _: fn(u8) -> String = status::tier_name;
+    let _: fn(u8) -> String = status::reason_name;
+    let _: Option<Pubkey> = match StatusError::Rpc(String::new()) {
+        StatusError::Rpc(_) => None,
+        StatusError::InvalidRecord(address) => Some(address),
+    };
+    let _: &dyn std::error::Error = &StatusError::Rpc(String::new());
+    let _: Gates = Gates::default();
+}
+
+fn main() {
+    instruction_builders();
+    let _ = resolve;
+    snapshots();
+    let _ = status_queries;
+    status_helpers();
+}
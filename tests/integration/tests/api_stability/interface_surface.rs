@@ -0,0 +1,211 @@
+// Every public item of token_acl_interface, with its signature spelled out:
+// removing one or changing its signature fails to compile
+
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::clock::Clock,
+};
+use spl_discriminator::{ArrayDiscriminator, SplDiscriminate};
+use spl_tlv_account_resolution::state::AccountDataResult;
+use token_acl_interface::{
+    self as interface,
+    events::{self, DecisionReason, EventFormat, ThawDecision},
+    CanFreezePermissionless, CanThawPermissionless, CanonicalPda, GateInterfaceVersion,
+    GateVersionError, MissingRecordPolicy, ReadonlyAccount, ReadonlyMeta,
+};
+
+fn constants() {
+    let _: [u8; 8] = interface::CAN_THAW_PERMISSIONLESS_DISCRIMINATOR;
+    let _: [u8; 8] = interface::CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR;
+    let _: &[u8] = interface::THAW_EXTRA_ACCOUNT_METAS_SEED;
+    let _: &[u8] = interface::FREEZE_EXTRA_ACCOUNT_METAS_SEED;
+    let _: &[u8] = interface::MISSING_RECORD_POLICY_SEED;
+    let _: u32 = interface::FEATURE_MISSING_RECORD_POLICY;
+    let _: u32 = interface::FEATURE_VERSIONED_EXTRA_ACCOUNT_METAS;
+    let _: ArrayDiscriminator = CanThawPermissionless::SPL_DISCRIMINATOR;
+    let _: ArrayDiscriminator = CanFreezePermissionless::SPL_DISCRIMINATOR;
+    let _: &[u8] = events::THAW_DECISION_EVENT_TAG;
+}
+
+fn pdas() {
+    let _: fn(&[&[u8]], &Pubkey) -> CanonicalPda = CanonicalPda::find;
+    let _: fn(&CanonicalPda) -> Pubkey = CanonicalPda::address;
+    let _: fn(&CanonicalPda) -> u8 = CanonicalPda::bump;
+    let _: fn(&CanonicalPda, &Pubkey) -> ProgramResult = CanonicalPda::verify;
+    let _: fn(CanonicalPda) -> (Pubkey, u8) = Into::into;
+    let _: fn(&Pubkey, &Pubkey) -> (Pubkey, u8) = interface::find_thaw_extra_account_metas_address;
+    let _: fn(&Pubkey, &Pubkey) -> (Pubkey, u8) =
+        interface::find_freeze_extra_account_metas_address;
+    let _: fn(&Pubkey, &Pubkey) -> (Pubkey, u8) = interface::find_missing_record_policy_address;
+}
+
+fn missing_record_policy() {
+    let _: fn(&[u8]) -> Result<MissingRecordPolicy, ProgramError> = MissingRecordPolicy::unpack;
+    let _: fn(MissingRecordPolicy) -> bool = MissingRecordPolicy::permits_thaw;
+    let _: fn(
+        &Pubkey,
+        &AccountInfo,
+        &Pubkey,
+        MissingRecordPolicy,
+    ) -> Result<MissingRecordPolicy, ProgramError> = MissingRecordPolicy::load;
+    let _: for<'a> fn(
+        MissingRecordPolicy,
+        &Pubkey,
+        &AccountInfo<'a>,
+        &Pubkey,
+        &AccountInfo<'a>,
+        &AccountInfo<'a>,
+    ) -> ProgramResult = MissingRecordPolicy::store;
+    let _: u8 = match MissingRecordPolicy::Deny {
+        MissingRecordPolicy::Deny => MissingRecordPolicy::Deny as u8,
+        MissingRecordPolicy::Allow => MissingRecordPolicy::Allow as u8,
+        MissingRecordPolicy::Review => MissingRecordPolicy::Review as u8,
+    };
+}
+
+fn interface_version() {
+    let GateInterfaceVersion { version, features } = GateInterfaceVersion::CURRENT;
+    let _: (u8, u32) = (version, features);
+    let _: usize = GateInterfaceVersion::LEN;
+    let _: GateInterfaceVersion = GateInterfaceVersion::LEGACY;
+    let _: ArrayDiscriminator = GateInterfaceVersion::SPL_DISCRIMINATOR;
+    let _: fn(&GateInterfaceVersion) -> [u8; GateInterfaceVersion::LEN] =
+        GateInterfaceVersion::pack;
+    let _: fn(&[u8]) -> Result<GateInterfaceVersion, ProgramError> = GateInterfaceVersion::unpack;
+    let _: fn(&[u8]) -> Result<GateInterfaceVersion, ProgramError> =
+        GateInterfaceVersion::from_extra_account_metas;
+    let _: fn(&Pubkey, &AccountInfo) -> Result<GateInterfaceVersion, ProgramError> =
+        GateInterfaceVersion::read;
+    let _: fn(&GateInterfaceVersion, &GateInterfaceVersion) -> Result<(), GateVersionError> =
+        GateInterfaceVersion::check_gate;
+    let _: fn(&GateInterfaceVersion, &AccountInfo, &AccountInfo) -> ProgramResult =
+        interface::check_gate_interface_version;
+    let _: u32 = match GateVersionError::GateTooNew {
+        GateVersionError::GateTooNew => GateVersionError::GateTooNew as u32,
+        GateVersionError::GateTooOld => GateVersionError::GateTooOld as u32,
+        GateVersionError::UnsupportedFeatures => GateVersionError::UnsupportedFeatures as u32,
+    };
+    let _: fn(GateVersionError) -> ProgramError = Into::into;
+}
+
+// `'a` is the accounts' lifetime, `'k` a borrowed key's
+fn readonly_accounts<'a: 'k, 'k>() {
+    let _: fn(Pubkey) -> ReadonlyMeta = ReadonlyMeta::new;
+    let _: fn(&ReadonlyMeta) -> &Pubkey = ReadonlyMeta::pubkey;
+    let _: fn(Pubkey) -> ReadonlyMeta = Into::into;
+    let _: fn(&'k Pubkey) -> ReadonlyMeta = Into::into;
+    let _: fn(ReadonlyMeta) -> AccountMeta = Into::into;
+    let _: fn(&AccountInfo<'a>) -> ReadonlyAccount<'a> = ReadonlyAccount::new;
+    let _: fn(&ReadonlyAccount<'a>) -> ReadonlyMeta = ReadonlyAccount::meta;
+    let _: for<'b> fn(&'b ReadonlyAccount<'a>) -> &'b AccountInfo<'a> = ReadonlyAccount::info;
+    let _: fn(&'k AccountInfo<'a>) -> ReadonlyAccount<'a> = Into::into;
+    let _: fn(&[AccountInfo<'a>]) -> Vec<ReadonlyAccount<'a>> = interface::readonly_accounts;
+}
+
+fn account_checks() {
+    let _: fn(&AccountInfo) -> ProgramResult = interface::check_system_program;
+    let _: fn(&AccountInfo) -> ProgramResult = interface::check_sysvar::<Clock>;
+    let _: fn(&AccountInfo, &[u8]) -> ProgramResult = interface::store_account_data;
+}
+
+fn instructions(key: Pubkey) {
+    // Keys, references to keys and metas all convert into the account
+    // arguments
+    let _: Instruction = interface::can_thaw_permissionless(
+        &key,
+        key,
+        &key,
+        ReadonlyMeta::new(key),
+        key,
+        &[ReadonlyMeta::new(key)],
+    );
+    let _: Instruction = interface::can_freeze_permissionless(
+        &key,
+        key,
+        &key,
+        ReadonlyMeta::new(key),
+        key,
+        &[ReadonlyMeta::new(key)],
+    );
+}
+
+fn invoke<'a>(accounts: &[AccountInfo<'a>]) -> ProgramResult {
+    let [gate, caller, token_account, mint, extra_account_metas, extras @ ..] = accounts else {
+        return Ok(());
+    };
+    let extras = interface::readonly_accounts(extras);
+    interface::invoke_can_thaw_permissionless(
+        gate,
+        caller,
+        token_account,
+        ReadonlyAccount::new(mint),
+        extra_account_metas,
+        &extras,
+    )?;
+    interface::invoke_can_freeze_permissionless(
+        gate,
+        caller,
+        token_account,
+        ReadonlyAccount::new(mint),
+        extra_account_metas,
+        &extras,
+    )
+}
+
+async fn resolve(instruction: &mut Instruction) -> Result<(), ProgramError> {
+    interface::resolve_extra_account_metas(instruction, |_address: Pubkey| async {
+        AccountDataResult::Ok(None)
+    })
+    .await
+}
+
+fn decision_events() {
+    let _: fn(Pubkey, Pubkey, DecisionReason) -> ThawDecision = ThawDecision::new;
+    let ThawDecision {
+        mint,
+        owner,
+        approved,
+        reason,
+    } = ThawDecision::new(Pubkey::default(), Pubkey::default(), DecisionReason::Listed);
+    let _: (Pubkey, Pubkey, bool, DecisionReason) = (mint, owner, approved, reason);
+    let _: usize = ThawDecision::LEN;
+    let _: [u8; 8] = ThawDecision::ANCHOR_DISCRIMINATOR;
+    let _: fn(&ThawDecision) -> [u8; ThawDecision::LEN] = ThawDecision::pack;
+    let _: fn(&[u8]) -> Option<ThawDecision> = ThawDecision::unpack;
+    let _: fn(&ThawDecision, EventFormat) -> Vec<Vec<u8>> = ThawDecision::log_fields;
+    let _: fn(&ThawDecision, EventFormat) = ThawDecision::emit;
+    let _: fn(&[Vec<u8>]) -> Option<(EventFormat, ThawDecision)> = ThawDecision::from_log_fields;
+
+    let _: fn(u8) -> Option<DecisionReason> = DecisionReason::from_u8;
+    let _: fn(&DecisionReason) -> bool = DecisionReason::approves;
+    let _: fn(&DecisionReason) -> &'static str = DecisionReason::name;
+    let _: u8 = match DecisionReason::Listed {
+        DecisionReason::Listed => DecisionReason::Listed as u8,
+        DecisionReason::MissingRecordAllowed => DecisionReason::MissingRecordAllowed as u8,
+        DecisionReason::NotListed => DecisionReason::NotListed as u8,
+        DecisionReason::Revoked => DecisionReason::Revoked as u8,
+        DecisionReason::NotAllowed => DecisionReason::NotAllowed as u8,
+        DecisionReason::Expired => DecisionReason::Expired as u8,
+        DecisionReason::PendingReview => DecisionReason::PendingReview as u8,
+    };
+    let _ = match EventFormat::Native {
+        EventFormat::Native | EventFormat::Anchor => (),
+    };
+}
+
+fn main() {
+    constants();
+    pdas();
+    missing_record_policy();
+    interface_version();
+    readonly_accounts();
+    account_checks();
+    instructions(Pubkey::new_unique());
+    let _ = invoke(&[]);
+    let _ = resolve;
+    decision_events();
+}
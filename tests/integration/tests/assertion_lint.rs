@@ -0,0 +1,244 @@
+//! Literal assertion lint tests
+//!
+//! Checks the lint against sample sources, that its findings fail the
+//! coverage gate, and that none of the suite's own tests assert only
+//! literals.
+
+use std::path::Path;
+
+use token_acl_integration_tests::{
+    assertion_lint::{self, TestLint},
+    coverage_utils, CoverageRequirements, CoverageResults, TestResultReport,
+};
+
+/// Sample sources: one literal-only test, one mixed, one executable
+const SAMPLE: &str = include_str!("lint_fixtures/sample.rs");
+
+fn find<'a>(lints: &'a [TestLint], function: &str) -> Option<&'a TestLint> {
+    lints.iter().find(|lint| lint.function == function)
+}
+
+/// Test 1: The lint finds literal assertions and literal-only tests
+#[test]
+fn test_lint_flags_literal_assertions() {
+    let report = run_lint_samples_test();
+    assert!(
+        report.passed,
+        "Lint samples test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_lint_samples_test() -> TestResultReport {
+    let test_name = "Literal Assertion Lint";
+    let mut assertions = 0;
+
+    let lints = assertion_lint::lint_source("sample.rs", SAMPLE);
+
+    // Assertion 1: Only functions that count assertions are linted
+    assertions += 1;
+    let functions: Vec<&str> = lints.iter().map(|lint| lint.function.as_str()).collect();
+    if functions
+        != [
+            "run_literal_only_test",
+            "run_mixed_test",
+            "run_executable_test",
+        ]
+    {
+        return TestResultReport::failure(test_name, format!("Linted {:?}", functions));
+    }
+
+    // Assertion 2: A test bound to and asserting only literals is flagged,
+    // through `if` and `assert!` alike
+    assertions += 1;
+    match find(&lints, "run_literal_only_test") {
+        Some(lint) if lint.is_literal_only() && lint.literal_assertions.len() == 2 => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Literal-only test linted as {:?}", other),
+            )
+        }
+    }
+
+    // Assertion 3: A test with one executable assertion is not flagged, but
+    // its literal assertion is reported, multi-line condition and all
+    assertions += 1;
+    match find(&lints, "run_mixed_test") {
+        Some(lint)
+            if !lint.is_literal_only()
+                && lint.literal_assertions.len() == 1
+                && lint.literal_assertions[0]
+                    .condition
+                    .starts_with("enforced &&") => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Mixed test linted as {:?}", other),
+            )
+        }
+    }
+
+    // Assertion 4: A literal used as input to the code under test is not a
+    // literal assertion
+    assertions += 1;
+    match find(&lints, "run_executable_test") {
+        Some(lint) if lint.literal_assertions.is_empty() && lint.assertions == 1 => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Executable test linted as {:?}", other),
+            )
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: Literal-only tests fail the coverage gate
+#[test]
+fn test_literal_only_tests_fail_coverage_gate() {
+    let report = run_coverage_gate_test();
+    assert!(
+        report.passed,
+        "Coverage gate test failed: {:?}",
+        report.error
+    );
+}
+
+fn coverage_results(lints: &[TestLint]) -> CoverageResults {
+    let results: Vec<TestResultReport> = (0..10)
+        .map(|i| TestResultReport::success(&format!("Security Test {}", i), 4))
+        .collect();
+    let mut analysis = coverage_utils::analyze_test_results(&results);
+    analysis.record_assertion_lint(lints);
+    let mut coverage = CoverageResults {
+        analysis,
+        requirements: CoverageRequirements {
+            critical_tests_required: 0,
+            performance_tests_required: 0,
+            ..CoverageRequirements::default()
+        },
+        meets_requirements: false,
+        recommendations: Vec::new(),
+    };
+    coverage.check_requirements();
+    coverage
+}
+
+fn run_coverage_gate_test() -> TestResultReport {
+    let test_name = "Literal Assertion Coverage Gate";
+    let mut assertions = 0;
+
+    let lints = assertion_lint::lint_source("sample.rs", SAMPLE);
+
+    // Assertion 1: Without the sample's literal-only test the gate passes
+    assertions += 1;
+    let converted: Vec<TestLint> = lints
+        .iter()
+        .filter(|lint| !lint.is_literal_only())
+        .cloned()
+        .collect();
+    let passing = coverage_results(&converted);
+    if !passing.meets_requirements {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Gate failed without findings: {:?}",
+                passing.recommendations
+            ),
+        );
+    }
+
+    // Assertion 2: With it the gate fails, naming the test
+    assertions += 1;
+    let failing = coverage_results(&lints);
+    if failing.meets_requirements
+        || !failing
+            .recommendations
+            .iter()
+            .any(|r| r.contains("sample.rs::run_literal_only_test"))
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Gate passed a literal-only test: {:?}",
+                failing.recommendations
+            ),
+        );
+    }
+
+    // Assertion 3: The coverage report lists it
+    assertions += 1;
+    let report = token_acl_integration_tests::coverage_reporting::render_coverage_report(&failing);
+    if !report.contains("## Literal-Only Tests")
+        || !report.contains("| sample.rs::run_literal_only_test |")
+    {
+        return TestResultReport::failure(test_name, "Report omits the test".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: No test in the suite asserts only literals
+#[test]
+fn test_suite_has_no_literal_only_tests() {
+    let report = run_suite_lint_test();
+    assert!(report.passed, "Suite lint test failed: {:?}", report.error);
+}
+
+fn run_suite_lint_test() -> TestResultReport {
+    let test_name = "Suite Literal Assertion Lint";
+    let mut assertions = 0;
+
+    let lints = match assertion_lint::lint_test_sources(Path::new(assertion_lint::TESTS_DIR)) {
+        Ok(lints) => lints,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+
+    // Assertion 1: The scan found the suite's counted tests
+    assertions += 1;
+    if find(&lints, "run_attack_vector_test").is_none() || lints.len() < 50 {
+        return TestResultReport::failure(
+            test_name,
+            format!("Scan found only {} counted tests", lints.len()),
+        );
+    }
+
+    // Assertion 2: None of them asserts only literals
+    assertions += 1;
+    let flagged: Vec<String> = assertion_lint::literal_only(&lints)
+        .iter()
+        .map(|lint| lint.location())
+        .collect();
+    if !flagged.is_empty() {
+        return TestResultReport::failure(
+            test_name,
+            format!("Tests asserting only literals: {}", flagged.join(", ")),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate the lint report
+#[test]
+fn generate_assertion_lint_report() {
+    let lints = assertion_lint::lint_test_sources(Path::new(assertion_lint::TESTS_DIR))
+        .expect("test sources should be readable");
+    let results = vec![
+        run_lint_samples_test(),
+        run_coverage_gate_test(),
+        run_suite_lint_test(),
+    ];
+    let mut report = token_acl_integration_tests::reporting::render_test_report(
+        &results,
+        "Literal Assertion Lint Results",
+        &token_acl_integration_tests::suite_config::current().disabled(),
+    );
+    report.push('\n');
+    report.push_str(&assertion_lint::render_lint_report(&lints));
+    std::fs::create_dir_all("../../tests/reports").ok();
+    std::fs::write("../../tests/reports/assertion_lint.md", report)
+        .expect("Failed to write assertion lint report");
+}
@@ -0,0 +1,1295 @@
+//! Block list permissionless freeze tests
+//!
+//! These tests drive the block list example through its freeze
+//! extra-account-metas: creating and updating the list, then resolving
+//! `can-freeze-permissionless` the way a keeper bot would, starting from
+//! nothing but the gate, the mint and a token account. Updates are versioned,
+//! and a freeze resolved against an older list must fail rather than run on
+//! stale accounts. The list itself is kept on chain by the program's admin
+//! instructions, so blocking, unblocking and handing the list over are
+//! driven end to end too, as are blocks that lapse at their expiry and
+//! records written before blocks could expire. Handovers take a proposal
+//! and an acceptance, and a proposal nobody accepts changes nothing.
+
+use std::slice;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use example_block_list::{
+    accept_authority, block_list_address, block_user, cancel_authority_transfer, config_address,
+    create_block_list_record, freeze_extra_account_metas, freeze_extra_account_metas_size,
+    freeze_extra_account_metas_version, initialize_freeze_extra_account_metas,
+    pack_freeze_extra_account_metas, pending_authority_address, unblock_user, update_authority,
+    update_freeze_extra_account_metas, BlockListRecord, BlockReason, Config, PendingAuthority,
+};
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    clock::Clock,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_tlv_account_resolution::{
+    account::ExtraAccountMeta, error::AccountResolutionError, state::AccountFetchError,
+};
+use spl_token_2022::state::AccountState;
+
+use token_acl_integration_tests::{
+    invariants::{
+        ConfigAuthorityUnchanged, FreezeStateConsistency, InvariantEngine, LamportInvariants, Step,
+    },
+    program_test::{mint_account, rent_exempt_account, token_account},
+    reporting, trace, TestResultReport,
+};
+use token_acl_interface as interface;
+
+/// Every transaction the fixture sends is a step checked for rent safety,
+/// lamport flow, and freeze state against the block list records
+struct BlockListFixture {
+    context: ProgramTestContext,
+    gate: Pubkey,
+    mint: Pubkey,
+    mint_authority: Keypair,
+    invariants: InvariantEngine,
+}
+
+/// The error a freeze resolved against a stale list fails with
+fn stale_resolution_error() -> TransactionError {
+    let ProgramError::Custom(code) = AccountResolutionError::IncorrectAccount.into() else {
+        unreachable!("account resolution errors are custom program errors");
+    };
+    TransactionError::InstructionError(0, InstructionError::Custom(code))
+}
+
+impl BlockListFixture {
+    /// Start a validator with the mint, `token_accounts` (address, owner) and
+    /// a block list record (blocked or not) for each `records` owner
+    async fn new(token_accounts: &[(Pubkey, Pubkey)], records: &[(Pubkey, bool)]) -> Self {
+        let gate = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let mint_authority = Keypair::new();
+
+        let mut program_test = ProgramTest::new(
+            "example_block_list",
+            gate,
+            processor!(example_block_list::process_instruction),
+        );
+        program_test.add_account(mint, mint_account(&mint_authority.pubkey(), 0));
+        for (address, owner) in token_accounts {
+            program_test.add_account(
+                *address,
+                token_account(&mint, owner, 1_000, AccountState::Initialized),
+            );
+        }
+        for (owner, blocked) in records {
+            let mut record = create_block_list_record(&mint, owner, BlockReason::Sanctions, 0);
+            record.blocked = *blocked;
+            program_test.add_account(
+                block_list_address(&mint, owner, &gate).address(),
+                rent_exempt_account(record.try_to_vec().unwrap(), gate),
+            );
+        }
+        let mut context = program_test.start_with_context().await;
+        let rent = context.banks_client.get_rent().await.unwrap();
+        // Closed pending authorities refund their rent to the payer
+        let mut lamports = LamportInvariants::new(&[gate], rent);
+        lamports.allow_refunds_to(context.payer.pubkey());
+        let mut invariants = InvariantEngine::new();
+        invariants
+            .register(lamports)
+            .register(FreezeStateConsistency::block_list(mint, gate))
+            .register(ConfigAuthorityUnchanged::block_list(&mint, &gate));
+
+        Self {
+            context,
+            gate,
+            mint,
+            mint_authority,
+            invariants,
+        }
+    }
+
+    fn freeze_extra_account_metas_address(&self) -> Pubkey {
+        interface::find_freeze_extra_account_metas_address(&self.mint, &self.gate).0
+    }
+
+    fn initialize_instruction(&self) -> Instruction {
+        initialize_freeze_extra_account_metas(&self.gate, &self.context.payer.pubkey(), &self.mint)
+    }
+
+    async fn initialize(&mut self) -> Result<(), TransactionError> {
+        let init = self.initialize_instruction();
+        self.send(&[init], &[]).await
+    }
+
+    /// Update the list as `authority`, expecting it at `expected_version`
+    async fn update(
+        &mut self,
+        authority: &Keypair,
+        expected_version: u64,
+        metas: &[ExtraAccountMeta],
+    ) -> Result<(), TransactionError> {
+        let update = update_freeze_extra_account_metas(
+            &self.gate,
+            &authority.pubkey(),
+            &self.context.payer.pubkey(),
+            &self.mint,
+            expected_version,
+            metas,
+        )
+        .unwrap();
+        self.send(&[update], &[authority]).await
+    }
+
+    async fn version(&mut self) -> Option<u64> {
+        let address = self.freeze_extra_account_metas_address();
+        let account = self.account(&address).await?;
+        freeze_extra_account_metas_version(&account.data).ok()
+    }
+
+    /// Build and resolve `can-freeze-permissionless` for `token_account`
+    /// from on-chain data only, as a keeper bot does
+    async fn keeper_freeze_instruction(
+        &self,
+        keeper: &Pubkey,
+        token_account: &Pubkey,
+    ) -> Result<Instruction, String> {
+        let mut instruction = interface::can_freeze_permissionless(
+            &self.gate,
+            keeper,
+            token_account,
+            self.mint,
+            self.freeze_extra_account_metas_address(),
+            &[],
+        );
+        interface::resolve_extra_account_metas(&mut instruction, |address| {
+            let mut banks_client = self.context.banks_client.clone();
+            async move {
+                banks_client
+                    .get_account(address)
+                    .await
+                    .map(|account| account.map(|a| a.data))
+                    .map_err(|e| Box::new(e) as AccountFetchError)
+            }
+        })
+        .await
+        .map_err(|e| format!("Resolution failed: {}", e))?;
+        Ok(instruction)
+    }
+
+    async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), TransactionError> {
+        // A fresh blockhash keeps retried instructions from being deduplicated
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let mut all_signers = vec![&self.context.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.context.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        let banks_client = &mut self.context.banks_client;
+        let before = self
+            .invariants
+            .snapshot(banks_client, &transaction.message.account_keys)
+            .await
+            .unwrap();
+        let outcome = banks_client
+            .process_transaction_with_metadata(transaction.clone())
+            .await
+            .unwrap();
+        trace::record(&transaction, &outcome);
+        let result = outcome.result;
+        let after = self
+            .invariants
+            .snapshot_after(banks_client, &before)
+            .await
+            .unwrap();
+        self.invariants.assert_holds(&Step::from_transactions(
+            "block list transaction",
+            &[&transaction],
+            before,
+            after,
+        ));
+        result
+    }
+
+    /// Create the list's config as `authority`
+    async fn initialize_config(&mut self, authority: &Keypair) -> Result<(), TransactionError> {
+        let init = example_block_list::initialize(
+            &self.gate,
+            &self.mint,
+            &authority.pubkey(),
+            &self.context.payer.pubkey(),
+        );
+        self.send(&[init], &[authority]).await
+    }
+
+    async fn block(
+        &mut self,
+        authority: &Keypair,
+        user: &Pubkey,
+        reason: BlockReason,
+    ) -> Result<(), TransactionError> {
+        self.block_until(authority, user, reason, None).await
+    }
+
+    /// Block `user`, until `expiry_timestamp` if one is given
+    async fn block_until(
+        &mut self,
+        authority: &Keypair,
+        user: &Pubkey,
+        reason: BlockReason,
+        expiry_timestamp: Option<i64>,
+    ) -> Result<(), TransactionError> {
+        let block = block_user(
+            &self.gate,
+            &self.mint,
+            user,
+            &authority.pubkey(),
+            &self.context.payer.pubkey(),
+            reason,
+            expiry_timestamp,
+        );
+        self.send(&[block], &[authority]).await
+    }
+
+    async fn unblock(
+        &mut self,
+        authority: &Keypair,
+        user: &Pubkey,
+    ) -> Result<(), TransactionError> {
+        let unblock = unblock_user(&self.gate, &self.mint, user, &authority.pubkey());
+        self.send(&[unblock], &[authority]).await
+    }
+
+    /// Propose `proposed` as the list's authority, signed by `authority`
+    async fn propose_authority(
+        &mut self,
+        authority: &Keypair,
+        proposed: &Pubkey,
+    ) -> Result<(), TransactionError> {
+        let propose = update_authority(
+            &self.gate,
+            &self.mint,
+            &authority.pubkey(),
+            proposed,
+            &self.context.payer.pubkey(),
+        );
+        self.send(&[propose], &[authority]).await
+    }
+
+    /// Accept the pending proposal as `proposed`
+    async fn accept_authority(&mut self, proposed: &Keypair) -> Result<(), TransactionError> {
+        let accept = accept_authority(
+            &self.gate,
+            &self.mint,
+            &proposed.pubkey(),
+            &self.context.payer.pubkey(),
+        );
+        self.send(&[accept], &[proposed]).await
+    }
+
+    /// Withdraw the pending proposal as `authority`
+    async fn cancel_authority_transfer(
+        &mut self,
+        authority: &Keypair,
+    ) -> Result<(), TransactionError> {
+        let cancel = cancel_authority_transfer(
+            &self.gate,
+            &self.mint,
+            &authority.pubkey(),
+            &self.context.payer.pubkey(),
+        );
+        self.send(&[cancel], &[authority]).await
+    }
+
+    async fn pending_authority(&mut self) -> Option<PendingAuthority> {
+        let address = pending_authority_address(&self.mint, &self.gate).address();
+        let account = self.account(&address).await?;
+        PendingAuthority::try_from_slice(&account.data).ok()
+    }
+
+    async fn config(&mut self) -> Option<Config> {
+        let address = config_address(&self.mint, &self.gate).address();
+        let account = self.account(&address).await?;
+        Config::try_from_slice(&account.data).ok()
+    }
+
+    async fn record(&mut self, user: &Pubkey) -> Option<BlockListRecord> {
+        let address = block_list_address(&self.mint, user, &self.gate).address();
+        let account = self.account(&address).await?;
+        BlockListRecord::unpack(&account.data).ok()
+    }
+
+    /// Send `can-thaw-permissionless` for `owner`'s `token_account`
+    async fn thaw_check(
+        &mut self,
+        owner: &Pubkey,
+        token_account: &Pubkey,
+    ) -> Result<(), TransactionError> {
+        let extra_metas =
+            interface::find_thaw_extra_account_metas_address(&self.mint, &self.gate).0;
+        let record = block_list_address(&self.mint, owner, &self.gate).address();
+        let policy = interface::find_missing_record_policy_address(&self.mint, &self.gate).0;
+        let check = interface::can_thaw_permissionless(
+            &self.gate,
+            *owner,
+            *token_account,
+            self.mint,
+            extra_metas,
+            &[(*owner).into(), record.into(), policy.into()],
+        );
+        self.send(&[check], &[]).await
+    }
+
+    /// Move the bank past its current slot and set its clock to
+    /// `unix_timestamp`
+    async fn set_clock(&mut self, unix_timestamp: i64) {
+        let slot = self.context.banks_client.get_root_slot().await.unwrap();
+        self.context.warp_to_slot(slot + 100).unwrap();
+        let mut clock: Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        clock.unix_timestamp = unix_timestamp;
+        self.context.set_sysvar(&clock);
+    }
+
+    /// Resolve and send `can-freeze-permissionless` for `token_account`
+    async fn keeper_freeze(
+        &mut self,
+        keeper: &Pubkey,
+        token_account: &Pubkey,
+    ) -> Result<(), String> {
+        let freeze = self
+            .keeper_freeze_instruction(keeper, token_account)
+            .await?;
+        self.send(&[freeze], &[])
+            .await
+            .map_err(|e| format!("Freeze denied: {}", e))
+    }
+
+    /// Replace `user`'s record with one in the layout written before blocks
+    /// could expire
+    async fn plant_legacy_record(&mut self, user: &Pubkey, blocked: bool) {
+        let mut record = create_block_list_record(&self.mint, user, BlockReason::Sanctions, 0);
+        record.blocked = blocked;
+        let mut data = record.try_to_vec().unwrap();
+        data.truncate(BlockListRecord::LEGACY_LEN);
+        let address = block_list_address(&self.mint, user, &self.gate).address();
+        self.context
+            .set_account(&address, &rent_exempt_account(data, self.gate).into());
+    }
+
+    /// Length of `user`'s record, if they have one
+    async fn record_len(&mut self, user: &Pubkey) -> Option<usize> {
+        let address = block_list_address(&self.mint, user, &self.gate).address();
+        Some(self.account(&address).await?.data.len())
+    }
+
+    async fn account(&mut self, address: &Pubkey) -> Option<Account> {
+        self.context
+            .banks_client
+            .get_account(*address)
+            .await
+            .unwrap()
+    }
+}
+
+/// Test 1: Freeze extra-account-metas initialization and update
+#[tokio::test]
+async fn test_freeze_extra_account_metas_lifecycle() {
+    let report = run_freeze_extra_account_metas_lifecycle_test().await;
+    assert!(
+        report.passed,
+        "Freeze extra-account-metas lifecycle test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_freeze_extra_account_metas_lifecycle_test() -> TestResultReport {
+    let test_name = "Freeze Extra-Account-Metas Lifecycle";
+    let mut assertions = 0;
+
+    let mut fixture = BlockListFixture::new(&[], &[]).await;
+    let address = fixture.freeze_extra_account_metas_address();
+    let expected = freeze_extra_account_metas().unwrap();
+
+    // Assertion 1: Initialization refuses any address but the mint's PDA
+    assertions += 1;
+    let mut init = fixture.initialize_instruction();
+    init.accounts[1].pubkey = Pubkey::new_unique();
+    if fixture.send(&[init], &[]).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Initialized extra-account-metas at a non-PDA address".to_string(),
+        );
+    }
+
+    // Assertion 2: Initialization creates the gate-owned list at version 1
+    assertions += 1;
+    if let Err(e) = fixture.initialize().await {
+        return TestResultReport::failure(test_name, format!("Initialization failed: {}", e));
+    }
+    let Some(account) = fixture.account(&address).await else {
+        return TestResultReport::failure(test_name, "List account missing".to_string());
+    };
+    if account.owner != fixture.gate {
+        return TestResultReport::failure(test_name, format!("List owned by {}", account.owner));
+    }
+    let mut expected_data = vec![0; freeze_extra_account_metas_size(expected.len()).unwrap()];
+    pack_freeze_extra_account_metas(&mut expected_data, 1, &expected).unwrap();
+    if account.data != expected_data {
+        return TestResultReport::failure(test_name, "List content differs".to_string());
+    }
+
+    // Assertion 3: A second initialization is rejected
+    assertions += 1;
+    if fixture.initialize().await.is_ok() {
+        return TestResultReport::failure(test_name, "List initialized twice".to_string());
+    }
+
+    // Assertion 4: Only the mint authority can update
+    assertions += 1;
+    let impostor = Keypair::new();
+    if fixture.update(&impostor, 1, &expected).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "List updated without the mint authority".to_string(),
+        );
+    }
+
+    // Assertion 5: Updates against another version are rejected
+    assertions += 1;
+    let authority = fixture.mint_authority.insecure_clone();
+    for version in [0, 2] {
+        if fixture.update(&authority, version, &expected).await.is_ok() {
+            return TestResultReport::failure(
+                test_name,
+                format!("Update expecting version {} applied to version 1", version),
+            );
+        }
+    }
+
+    // Assertion 6: Updates cannot drop the block list record
+    assertions += 1;
+    let oracle = ExtraAccountMeta::new_with_pubkey(&Pubkey::new_unique(), false, false).unwrap();
+    if fixture.update(&authority, 1, &[oracle]).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Update removed the block list record".to_string(),
+        );
+    }
+
+    // Assertion 7: The mint authority's update bumps the version
+    assertions += 1;
+    if let Err(e) = fixture.update(&authority, 1, &expected).await {
+        return TestResultReport::failure(test_name, format!("Update failed: {}", e));
+    }
+    if fixture.version().await != Some(2) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Version after update was {:?}", fixture.version().await),
+        );
+    }
+
+    // Assertion 8: Update refuses to create a missing list
+    assertions += 1;
+    let mut empty = BlockListFixture::new(&[], &[]).await;
+    let authority = empty.mint_authority.insecure_clone();
+    if empty.update(&authority, 1, &expected).await.is_ok() {
+        return TestResultReport::failure(test_name, "Updated a missing list".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: A keeper bot resolves the block list record from the token account
+#[tokio::test]
+async fn test_keeper_resolves_block_list_record() {
+    let report = run_keeper_resolution_test().await;
+    assert!(
+        report.passed,
+        "Keeper resolution test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_keeper_resolution_test() -> TestResultReport {
+    let test_name = "Keeper Resolves Block List Record";
+    let mut assertions = 0;
+
+    let (blocked_owner, cleared_owner, unlisted_owner) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let (blocked_account, cleared_account, unlisted_account) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let mut fixture = BlockListFixture::new(
+        &[
+            (blocked_account, blocked_owner),
+            (cleared_account, cleared_owner),
+            (unlisted_account, unlisted_owner),
+        ],
+        &[(blocked_owner, true), (cleared_owner, false)],
+    )
+    .await;
+    let keeper = Pubkey::new_unique();
+
+    // Assertion 1: Resolution fails until the gate's list exists
+    assertions += 1;
+    if fixture
+        .keeper_freeze_instruction(&keeper, &blocked_account)
+        .await
+        .is_ok()
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Resolved without an extra-account-metas list".to_string(),
+        );
+    }
+    if let Err(e) = fixture.initialize().await {
+        return TestResultReport::failure(test_name, format!("Initialization failed: {}", e));
+    }
+
+    // Assertion 2: The keeper resolves exactly the owner's block list PDA,
+    // appended read-only after the interface accounts
+    assertions += 1;
+    let instruction = match fixture
+        .keeper_freeze_instruction(&keeper, &blocked_account)
+        .await
+    {
+        Ok(instruction) => instruction,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let expected = AccountMeta::new_readonly(
+        block_list_address(&fixture.mint, &blocked_owner, &fixture.gate).address(),
+        false,
+    );
+    if instruction.accounts.len() != 5 || instruction.accounts[4] != expected {
+        return TestResultReport::failure(
+            test_name,
+            format!("Resolved accounts were {:?}", &instruction.accounts[4..]),
+        );
+    }
+
+    // Assertion 3: The gate approves freezing a blocked owner's account
+    assertions += 1;
+    if let Err(e) = fixture.send(slice::from_ref(&instruction), &[]).await {
+        return TestResultReport::failure(test_name, format!("Blocked owner not frozen: {}", e));
+    }
+
+    // Assertion 4: Cleared and unlisted owners resolve to their own records
+    // and are denied
+    assertions += 1;
+    for (account, owner) in [
+        (cleared_account, cleared_owner),
+        (unlisted_account, unlisted_owner),
+    ] {
+        let resolved = match fixture.keeper_freeze_instruction(&keeper, &account).await {
+            Ok(instruction) => instruction,
+            Err(e) => return TestResultReport::failure(test_name, e),
+        };
+        if resolved.accounts[4].pubkey
+            != block_list_address(&fixture.mint, &owner, &fixture.gate).address()
+        {
+            return TestResultReport::failure(test_name, format!("Wrong record for {}", owner));
+        }
+        if fixture.send(&[resolved], &[]).await.is_ok() {
+            return TestResultReport::failure(
+                test_name,
+                format!("Froze {} without a blocking record", owner),
+            );
+        }
+    }
+
+    // Assertion 5: Another owner's blocking record cannot be substituted
+    assertions += 1;
+    let mut forged = match fixture
+        .keeper_freeze_instruction(&keeper, &unlisted_account)
+        .await
+    {
+        Ok(instruction) => instruction,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    forged.accounts[4] = instruction.accounts[4].clone();
+    if fixture.send(&[forged], &[]).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Gate accepted another owner's block record".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: Freezes resolved against an older list fail cleanly
+#[tokio::test]
+async fn test_stale_resolution_rejected() {
+    let report = run_stale_resolution_test().await;
+    assert!(
+        report.passed,
+        "Stale resolution test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_stale_resolution_test() -> TestResultReport {
+    let test_name = "Stale Resolution Rejected";
+    let mut assertions = 0;
+
+    let (owner, token_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut fixture = BlockListFixture::new(&[(token_account, owner)], &[(owner, true)]).await;
+    if let Err(e) = fixture.initialize().await {
+        return TestResultReport::failure(test_name, format!("Initialization failed: {}", e));
+    }
+    let keeper = Pubkey::new_unique();
+    let authority = fixture.mint_authority.insecure_clone();
+    let mut metas = freeze_extra_account_metas().unwrap();
+
+    // Resolved against version 1, submitted after the update below
+    let in_flight = match fixture
+        .keeper_freeze_instruction(&keeper, &token_account)
+        .await
+    {
+        Ok(instruction) => instruction,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+
+    // Assertion 1: Adding an oracle account grows the list to version 2
+    assertions += 1;
+    let oracle = Pubkey::new_unique();
+    metas.push(ExtraAccountMeta::new_with_pubkey(&oracle, false, false).unwrap());
+    if let Err(e) = fixture.update(&authority, 1, &metas).await {
+        return TestResultReport::failure(test_name, format!("Update failed: {}", e));
+    }
+    let address = fixture.freeze_extra_account_metas_address();
+    let grown = fixture.account(&address).await;
+    if fixture.version().await != Some(2)
+        || grown.is_none_or(|a| a.lamports < Rent::default().minimum_balance(a.data.len()))
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Grown list not at version 2 and rent-exempt".to_string(),
+        );
+    }
+
+    // Assertion 2: The in-flight freeze fails with IncorrectAccount
+    assertions += 1;
+    match fixture.send(&[in_flight], &[]).await {
+        Err(e) if e == stale_resolution_error() => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Stale freeze returned {:?}", other),
+            )
+        }
+    }
+
+    // Assertion 3: Re-resolving picks up the oracle and is approved
+    assertions += 1;
+    let current = match fixture
+        .keeper_freeze_instruction(&keeper, &token_account)
+        .await
+    {
+        Ok(instruction) => instruction,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    if current.accounts.len() != 6
+        || current.accounts[5] != AccountMeta::new_readonly(oracle, false)
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!("Resolved accounts were {:?}", &current.accounts[4..]),
+        );
+    }
+    if let Err(e) = fixture.send(slice::from_ref(&current), &[]).await {
+        return TestResultReport::failure(test_name, format!("Current freeze failed: {}", e));
+    }
+
+    // Assertion 4: Shrinking the list makes the longer resolution stale too
+    assertions += 1;
+    metas.pop();
+    if let Err(e) = fixture.update(&authority, 2, &metas).await {
+        return TestResultReport::failure(test_name, format!("Shrinking update failed: {}", e));
+    }
+    match fixture.send(&[current], &[]).await {
+        Err(e) if e == stale_resolution_error() => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Stale freeze after shrink returned {:?}", other),
+            )
+        }
+    }
+    if fixture.version().await != Some(3) {
+        return TestResultReport::failure(test_name, "Shrink did not bump the version".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 4: The block list is administered on chain, and permissionless
+/// freezes follow it
+#[tokio::test]
+async fn test_block_list_administered_on_chain() {
+    let report = run_block_list_administration_test().await;
+    assert!(
+        report.passed,
+        "Block list administration test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_block_list_administration_test() -> TestResultReport {
+    let test_name = "Block List Administered On Chain";
+    let mut assertions = 0;
+
+    let (owner, token_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut fixture = BlockListFixture::new(&[(token_account, owner)], &[]).await;
+    if let Err(e) = fixture.initialize().await {
+        return TestResultReport::failure(test_name, format!("Initialization failed: {}", e));
+    }
+    let keeper = Pubkey::new_unique();
+    let authority = fixture.mint_authority.insecure_clone();
+    let impostor = Keypair::new();
+
+    // Assertion 1: Only the mint authority initializes the config, once
+    assertions += 1;
+    if fixture.initialize_config(&impostor).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Config initialized without the mint authority".to_string(),
+        );
+    }
+    if let Err(e) = fixture.initialize_config(&authority).await {
+        return TestResultReport::failure(test_name, format!("Config failed: {}", e));
+    }
+    if fixture.config().await.map(|c| c.authority) != Some(authority.pubkey()) {
+        return TestResultReport::failure(test_name, "Config names another authority".to_string());
+    }
+    if fixture.initialize_config(&authority).await.is_ok() {
+        return TestResultReport::failure(test_name, "Config initialized twice".to_string());
+    }
+
+    // Assertion 2: Only the list's authority blocks users
+    assertions += 1;
+    if fixture
+        .block(&impostor, &owner, BlockReason::Sanctions)
+        .await
+        .is_ok()
+    {
+        return TestResultReport::failure(test_name, "Impostor blocked a user".to_string());
+    }
+    if fixture.keeper_freeze(&keeper, &token_account).await.is_ok() {
+        return TestResultReport::failure(test_name, "Froze an owner nobody blocked".to_string());
+    }
+
+    // Assertion 3: Blocking records the reason, and the keeper's freeze of
+    // the blocked owner is approved
+    assertions += 1;
+    if let Err(e) = fixture
+        .block(&authority, &owner, BlockReason::Compliance)
+        .await
+    {
+        return TestResultReport::failure(test_name, format!("Block failed: {}", e));
+    }
+    match fixture.record(&owner).await {
+        Some(record) if record.blocked && record.reason == BlockReason::Compliance => {}
+        other => return TestResultReport::failure(test_name, format!("Record was {:?}", other)),
+    }
+    if let Err(e) = fixture.keeper_freeze(&keeper, &token_account).await {
+        return TestResultReport::failure(test_name, e);
+    }
+    if fixture
+        .block(&authority, &owner, BlockReason::Sanctions)
+        .await
+        .is_ok()
+    {
+        return TestResultReport::failure(test_name, "Blocked a user twice".to_string());
+    }
+
+    // Assertion 4: Unblocking keeps the record but denies the freeze
+    assertions += 1;
+    if fixture.unblock(&impostor, &owner).await.is_ok() {
+        return TestResultReport::failure(test_name, "Impostor unblocked a user".to_string());
+    }
+    if let Err(e) = fixture.unblock(&authority, &owner).await {
+        return TestResultReport::failure(test_name, format!("Unblock failed: {}", e));
+    }
+    if fixture.record(&owner).await.is_none_or(|r| r.blocked) {
+        return TestResultReport::failure(
+            test_name,
+            "Unblocked record missing or still blocked".to_string(),
+        );
+    }
+    if fixture.keeper_freeze(&keeper, &token_account).await.is_ok() {
+        return TestResultReport::failure(test_name, "Froze an unblocked owner".to_string());
+    }
+
+    // Assertion 5: A proposal hands nothing over until the proposed key
+    // accepts it, and nobody else can accept it
+    assertions += 1;
+    let successor = Keypair::new();
+    if fixture
+        .propose_authority(&impostor, &successor.pubkey())
+        .await
+        .is_ok()
+    {
+        return TestResultReport::failure(test_name, "Impostor proposed an authority".to_string());
+    }
+    if let Err(e) = fixture
+        .propose_authority(&authority, &successor.pubkey())
+        .await
+    {
+        return TestResultReport::failure(test_name, format!("Proposal failed: {}", e));
+    }
+    if fixture.config().await.map(|c| c.authority) != Some(authority.pubkey()) {
+        return TestResultReport::failure(test_name, "Proposal changed the authority".to_string());
+    }
+    if fixture.accept_authority(&impostor).await.is_ok() {
+        return TestResultReport::failure(test_name, "Impostor accepted the proposal".to_string());
+    }
+    if let Err(e) = fixture.accept_authority(&successor).await {
+        return TestResultReport::failure(test_name, format!("Acceptance failed: {}", e));
+    }
+    if fixture.pending_authority().await.is_some() {
+        return TestResultReport::failure(
+            test_name,
+            "Acceptance left the proposal open".to_string(),
+        );
+    }
+
+    // Assertion 6: After the handover only the successor re-blocks
+    assertions += 1;
+    if fixture
+        .block(&authority, &owner, BlockReason::Sanctions)
+        .await
+        .is_ok()
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Previous authority still blocks users".to_string(),
+        );
+    }
+    if let Err(e) = fixture
+        .block(&successor, &owner, BlockReason::Sanctions)
+        .await
+    {
+        return TestResultReport::failure(test_name, format!("Re-block failed: {}", e));
+    }
+    if let Err(e) = fixture.keeper_freeze(&keeper, &token_account).await {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 5: A block with an expiry holds through it and lapses the second
+/// after, without anyone unblocking the user
+#[tokio::test]
+async fn test_block_expiry_boundaries() {
+    let report = run_block_expiry_test().await;
+    assert!(
+        report.passed,
+        "Block expiry test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_block_expiry_test() -> TestResultReport {
+    let test_name = "Block Expiry Boundaries";
+    let mut assertions = 0;
+
+    let (owner, token_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (held, held_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut fixture =
+        BlockListFixture::new(&[(token_account, owner), (held_account, held)], &[]).await;
+    if let Err(e) = fixture.initialize().await {
+        return TestResultReport::failure(test_name, format!("Initialization failed: {}", e));
+    }
+    let keeper = Pubkey::new_unique();
+    let authority = fixture.mint_authority.insecure_clone();
+    if let Err(e) = fixture.initialize_config(&authority).await {
+        return TestResultReport::failure(test_name, format!("Config failed: {}", e));
+    }
+    let start = 1_800_000_000;
+    let expiry = start + 3_600;
+    fixture.set_clock(start).await;
+
+    // Assertion 1: A block must expire after the current time
+    assertions += 1;
+    for lapsed in [start - 1, start] {
+        if fixture
+            .block_until(&authority, &owner, BlockReason::Compliance, Some(lapsed))
+            .await
+            .is_ok()
+        {
+            return TestResultReport::failure(
+                test_name,
+                format!("Blocked until {} at {}", lapsed, start),
+            );
+        }
+    }
+    if let Err(e) = fixture
+        .block_until(&authority, &owner, BlockReason::Compliance, Some(expiry))
+        .await
+    {
+        return TestResultReport::failure(test_name, format!("Block failed: {}", e));
+    }
+    if fixture
+        .record(&owner)
+        .await
+        .and_then(|r| r.expiry_timestamp)
+        != Some(expiry)
+    {
+        return TestResultReport::failure(test_name, "Expiry not recorded".to_string());
+    }
+
+    // Assertion 2: At the expiry itself the block is in force: the freeze
+    // is authorized and the thaw denied
+    assertions += 1;
+    fixture.set_clock(expiry).await;
+    if let Err(e) = fixture.keeper_freeze(&keeper, &token_account).await {
+        return TestResultReport::failure(test_name, format!("At the expiry: {}", e));
+    }
+    if fixture.thaw_check(&owner, &token_account).await.is_ok() {
+        return TestResultReport::failure(test_name, "Thaw permitted at the expiry".to_string());
+    }
+
+    // Assertion 3: A second later the block has lapsed, with the record
+    // still marked blocked: the freeze is refused and the thaw permitted
+    assertions += 1;
+    fixture.set_clock(expiry + 1).await;
+    if fixture.keeper_freeze(&keeper, &token_account).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Froze an owner whose block lapsed".to_string(),
+        );
+    }
+    if let Err(e) = fixture.thaw_check(&owner, &token_account).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("Thaw denied after the expiry: {}", e),
+        );
+    }
+    if fixture.record(&owner).await.is_none_or(|r| !r.blocked) {
+        return TestResultReport::failure(test_name, "The lapsed record was changed".to_string());
+    }
+
+    // Assertion 4: A lapsed block can be renewed, and is in force again
+    assertions += 1;
+    if let Err(e) = fixture
+        .block_until(
+            &authority,
+            &owner,
+            BlockReason::Sanctions,
+            Some(expiry + 60),
+        )
+        .await
+    {
+        return TestResultReport::failure(test_name, format!("Renewal failed: {}", e));
+    }
+    if let Err(e) = fixture.keeper_freeze(&keeper, &token_account).await {
+        return TestResultReport::failure(test_name, format!("After renewal: {}", e));
+    }
+    if fixture.thaw_check(&owner, &token_account).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Thaw permitted under a renewed block".to_string(),
+        );
+    }
+
+    // Assertion 5: A block without an expiry never lapses
+    assertions += 1;
+    if let Err(e) = fixture
+        .block(&authority, &held, BlockReason::Sanctions)
+        .await
+    {
+        return TestResultReport::failure(test_name, format!("Block failed: {}", e));
+    }
+    fixture.set_clock(i64::MAX).await;
+    if let Err(e) = fixture.keeper_freeze(&keeper, &held_account).await {
+        return TestResultReport::failure(test_name, format!("Far in the future: {}", e));
+    }
+    if fixture.thaw_check(&held, &held_account).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Thaw permitted under an indefinite block".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 6: Records written before blocks could expire still block, and keep
+/// their layout until a block with an expiry needs the room
+#[tokio::test]
+async fn test_legacy_block_list_records() {
+    let report = run_legacy_records_test().await;
+    assert!(
+        report.passed,
+        "Legacy records test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_legacy_records_test() -> TestResultReport {
+    let test_name = "Legacy Records";
+    let mut assertions = 0;
+
+    let (owner, token_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut fixture = BlockListFixture::new(&[(token_account, owner)], &[]).await;
+    if let Err(e) = fixture.initialize().await {
+        return TestResultReport::failure(test_name, format!("Initialization failed: {}", e));
+    }
+    let keeper = Pubkey::new_unique();
+    let authority = fixture.mint_authority.insecure_clone();
+    if let Err(e) = fixture.initialize_config(&authority).await {
+        return TestResultReport::failure(test_name, format!("Config failed: {}", e));
+    }
+    fixture.plant_legacy_record(&owner, true).await;
+
+    // Assertion 1: A legacy record reads as a block without an expiry
+    assertions += 1;
+    match fixture.record(&owner).await {
+        Some(record) if record.blocked && record.expiry_timestamp.is_none() => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Legacy record read as {:?}", other),
+            )
+        }
+    }
+
+    // Assertion 2: The block is in force: the freeze is authorized and the
+    // thaw denied
+    assertions += 1;
+    if let Err(e) = fixture.keeper_freeze(&keeper, &token_account).await {
+        return TestResultReport::failure(test_name, format!("Legacy freeze: {}", e));
+    }
+    if fixture.thaw_check(&owner, &token_account).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Thaw permitted under a legacy block".to_string(),
+        );
+    }
+
+    // Assertion 3: Unblocking and re-blocking without an expiry keep the
+    // legacy layout
+    assertions += 1;
+    if let Err(e) = fixture.unblock(&authority, &owner).await {
+        return TestResultReport::failure(test_name, format!("Unblock failed: {}", e));
+    }
+    if let Err(e) = fixture.thaw_check(&owner, &token_account).await {
+        return TestResultReport::failure(test_name, format!("Thaw after unblock: {}", e));
+    }
+    if let Err(e) = fixture
+        .block(&authority, &owner, BlockReason::Compliance)
+        .await
+    {
+        return TestResultReport::failure(test_name, format!("Re-block failed: {}", e));
+    }
+    if fixture.record_len(&owner).await != Some(BlockListRecord::LEGACY_LEN) {
+        return TestResultReport::failure(test_name, "Legacy record was resized".to_string());
+    }
+
+    // Assertion 4: A block with an expiry grows the record to hold it
+    assertions += 1;
+    if let Err(e) = fixture.unblock(&authority, &owner).await {
+        return TestResultReport::failure(test_name, format!("Second unblock failed: {}", e));
+    }
+    let expiry = i64::MAX;
+    if let Err(e) = fixture
+        .block_until(&authority, &owner, BlockReason::Sanctions, Some(expiry))
+        .await
+    {
+        return TestResultReport::failure(test_name, format!("Expiring block failed: {}", e));
+    }
+    if fixture
+        .record(&owner)
+        .await
+        .and_then(|r| r.expiry_timestamp)
+        != Some(expiry)
+    {
+        return TestResultReport::failure(test_name, "Expiry not recorded".to_string());
+    }
+    if fixture.thaw_check(&owner, &token_account).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Thaw permitted under the expiring block".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 7: A blocked holder cannot thaw their account by naming a clean
+/// owner
+#[tokio::test]
+async fn test_thaw_bound_to_token_account_owner() {
+    let report = run_thaw_owner_binding_test().await;
+    assert!(
+        report.passed,
+        "Thaw owner binding test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_thaw_owner_binding_test() -> TestResultReport {
+    let test_name = "Thaw Bound To Token Account Owner";
+    let mut assertions = 0;
+
+    let (blocked, blocked_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (clean, clean_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut fixture =
+        BlockListFixture::new(&[(blocked_account, blocked), (clean_account, clean)], &[]).await;
+    let authority = fixture.mint_authority.insecure_clone();
+    if let Err(e) = fixture.initialize_config(&authority).await {
+        return TestResultReport::failure(test_name, format!("Config failed: {}", e));
+    }
+    if let Err(e) = fixture
+        .block(&authority, &blocked, BlockReason::Sanctions)
+        .await
+    {
+        return TestResultReport::failure(test_name, format!("Block failed: {}", e));
+    }
+
+    // Assertion 1: Each holder's own check is decided by their record
+    assertions += 1;
+    if let Err(e) = fixture.thaw_check(&clean, &clean_account).await {
+        return TestResultReport::failure(test_name, format!("Clean holder denied: {}", e));
+    }
+    if fixture.thaw_check(&blocked, &blocked_account).await.is_ok() {
+        return TestResultReport::failure(test_name, "Blocked holder thawed".to_string());
+    }
+
+    // Assertion 2: The blocked holder's account with the clean owner and
+    // their record is denied
+    assertions += 1;
+    if fixture.thaw_check(&clean, &blocked_account).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Blocked holder thawed on a clean owner's record".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 8: A proposed authority that never accepts leaves the list with
+/// its authority
+#[tokio::test]
+async fn test_unaccepted_proposal_keeps_authority() {
+    let report = run_unaccepted_proposal_test().await;
+    assert!(
+        report.passed,
+        "Unaccepted proposal test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_unaccepted_proposal_test() -> TestResultReport {
+    let test_name = "Unaccepted Proposal Keeps Authority";
+    let mut assertions = 0;
+
+    let (owner, token_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut fixture = BlockListFixture::new(&[(token_account, owner)], &[]).await;
+    if let Err(e) = fixture.initialize().await {
+        return TestResultReport::failure(test_name, format!("Initialization failed: {}", e));
+    }
+    let keeper = Pubkey::new_unique();
+    let authority = fixture.mint_authority.insecure_clone();
+    if let Err(e) = fixture.initialize_config(&authority).await {
+        return TestResultReport::failure(test_name, format!("Config failed: {}", e));
+    }
+    // A key nobody holds, as if mistyped
+    let mistyped = Pubkey::new_unique();
+    if let Err(e) = fixture.propose_authority(&authority, &mistyped).await {
+        return TestResultReport::failure(test_name, format!("Proposal failed: {}", e));
+    }
+
+    // Assertion 1: The proposal is pending and the config keeps the
+    // authority
+    assertions += 1;
+    match fixture.pending_authority().await {
+        Some(pending) if pending.proposed == mistyped && pending.mint == fixture.mint => {}
+        other => return TestResultReport::failure(test_name, format!("Pending was {:?}", other)),
+    }
+    if fixture.config().await.map(|c| c.authority) != Some(authority.pubkey()) {
+        return TestResultReport::failure(test_name, "Proposal changed the authority".to_string());
+    }
+
+    // Assertion 2: The authority still blocks users, and the keeper's
+    // freeze follows
+    assertions += 1;
+    if let Err(e) = fixture
+        .block(&authority, &owner, BlockReason::Sanctions)
+        .await
+    {
+        return TestResultReport::failure(test_name, format!("Block failed: {}", e));
+    }
+    if let Err(e) = fixture.keeper_freeze(&keeper, &token_account).await {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 3: The authority withdraws the proposal, closing its PDA,
+    // and nothing is left to cancel
+    assertions += 1;
+    let pending = pending_authority_address(&fixture.mint, &fixture.gate).address();
+    if let Err(e) = fixture.cancel_authority_transfer(&authority).await {
+        return TestResultReport::failure(test_name, format!("Cancel failed: {}", e));
+    }
+    if fixture.account(&pending).await.is_some() {
+        return TestResultReport::failure(
+            test_name,
+            "Cancel left the pending authority open".to_string(),
+        );
+    }
+    if fixture.cancel_authority_transfer(&authority).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Cancelled a transfer that was not pending".to_string(),
+        );
+    }
+    if fixture.config().await.map(|c| c.authority) != Some(authority.pubkey()) {
+        return TestResultReport::failure(test_name, "Cancel changed the authority".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate block list freeze test report
+#[tokio::test]
+async fn generate_block_list_freeze_report() {
+    let results = vec![
+        run_freeze_extra_account_metas_lifecycle_test().await,
+        run_keeper_resolution_test().await,
+        run_stale_resolution_test().await,
+        run_block_list_administration_test().await,
+        run_block_expiry_test().await,
+        run_legacy_records_test().await,
+        run_thaw_owner_binding_test().await,
+        run_unaccepted_proposal_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Block List Freeze Test Results",
+        "../../tests/reports/block_list_freeze.md",
+    ) {
+        panic!("Failed to generate block list freeze report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} block list freeze tests failed", failed);
+}
@@ -0,0 +1,514 @@
+//! Property tests for the Borsh account layouts
+//!
+//! The gates decode every record and config with `try_from_slice`, so what
+//! they accept is whatever Borsh accepts. For random values of each account
+//! structure these tests check that the encoding has the documented length
+//! and decodes back to the same value, that no truncated buffer and no
+//! buffer with trailing bytes decodes, and that a `bool`, enum or `Option`
+//! byte outside its range is rejected rather than read as some value.
+
+use std::fmt::Debug;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use proptest::{
+    collection::vec,
+    prelude::*,
+    test_runner::{Config as ProptestConfig, TestCaseError, TestError, TestRunner},
+};
+use solana_sdk::pubkey::Pubkey;
+
+use token_acl_integration_tests::{
+    fixtures::{test_data, TestMintConfig},
+    reporting, TestResultReport,
+};
+
+/// Random values checked per structure
+const CASES: u32 = 256;
+
+// Byte offsets of the fields after the two leading pubkeys
+const FLAG_OFFSET: usize = 64;
+const ENUM_OFFSET: usize = 65;
+
+fn pubkey() -> impl Strategy<Value = Pubkey> {
+    any::<[u8; 32]>().prop_map(Pubkey::new_from_array)
+}
+
+/// Bytes appended to an encoding; never empty
+fn trailing_bytes() -> impl Strategy<Value = Vec<u8>> {
+    vec(any::<u8>(), 1..16)
+}
+
+fn access_level() -> impl Strategy<Value = production_allow_list::AccessLevel> {
+    use production_allow_list::AccessLevel;
+    prop::sample::select(vec![
+        AccessLevel::None,
+        AccessLevel::Basic,
+        AccessLevel::Enhanced,
+        AccessLevel::Institutional,
+    ])
+}
+
+/// The block reason with variant index `index`, which must be under 4;
+/// `BlockReason` is not `Clone`, so strategies produce the index
+fn block_reason(index: u8) -> example_block_list::BlockReason {
+    use example_block_list::BlockReason;
+    match index {
+        0 => BlockReason::Sanctions,
+        1 => BlockReason::Compliance,
+        2 => BlockReason::RiskAssessment,
+        _ => BlockReason::Other,
+    }
+}
+
+fn production_record() -> impl Strategy<Value = production_allow_list::AllowListRecord> {
+    (
+        (pubkey(), pubkey()),
+        (any::<bool>(), access_level(), any::<i64>()),
+        (any::<Option<i64>>(), any::<u8>(), any::<bool>()),
+    )
+        .prop_map(
+            |((mint, user), (allowed, access_level, added_timestamp), (expiry, bump, revoked))| {
+                production_allow_list::AllowListRecord {
+                    mint,
+                    user,
+                    allowed,
+                    access_level,
+                    added_timestamp,
+                    expiry_timestamp: expiry,
+                    bump,
+                    revoked,
+                }
+            },
+        )
+}
+
+fn mint_config() -> impl Strategy<Value = TestMintConfig> {
+    (
+        (pubkey(), pubkey(), pubkey()),
+        (any::<u8>(), any::<bool>(), any::<bool>()),
+    )
+        .prop_map(
+            |((mint, authority, gating_program), (discriminator, thaw, freeze))| TestMintConfig {
+                discriminator,
+                mint,
+                authority,
+                gating_program,
+                enable_permissionless_thaw: thaw,
+                enable_permissionless_freeze: freeze,
+            },
+        )
+}
+
+/// Run `test` on `CASES` values of `strategy`, reporting the minimal
+/// failing value
+fn check<S: Strategy>(
+    strategy: S,
+    test: impl Fn(S::Value) -> Result<(), TestCaseError>,
+) -> Result<(), String>
+where
+    S::Value: Debug,
+{
+    let mut runner = TestRunner::new(ProptestConfig {
+        cases: CASES,
+        failure_persistence: None,
+        ..ProptestConfig::default()
+    });
+    runner.run(&strategy, test).map_err(|e| match e {
+        TestError::Fail(reason, value) => format!("{} for {:?}", reason, value),
+        TestError::Abort(reason) => reason.to_string(),
+    })
+}
+
+/// Encode `value` and check what every layout must hold: the encoding is
+/// `len` bytes and decodes to a value that encodes the same, no strict
+/// prefix decodes, and the encoding followed by `trailing` does not decode
+/// in one piece, though reading it as a stream leaves exactly `trailing`
+fn check_encoding<T: BorshSerialize + BorshDeserialize>(
+    value: &T,
+    len: usize,
+    trailing: &[u8],
+) -> Result<Vec<u8>, TestCaseError> {
+    let bytes = value
+        .try_to_vec()
+        .map_err(|e| TestCaseError::fail(format!("encode: {}", e)))?;
+    prop_assert_eq!(bytes.len(), len, "encoded length");
+
+    let decoded =
+        T::try_from_slice(&bytes).map_err(|e| TestCaseError::fail(format!("decode: {}", e)))?;
+    prop_assert_eq!(
+        decoded.try_to_vec().ok(),
+        Some(bytes.clone()),
+        "decoded value encodes differently"
+    );
+
+    for prefix in 0..bytes.len() {
+        prop_assert!(
+            T::try_from_slice(&bytes[..prefix]).is_err(),
+            "decoded a {}-byte prefix",
+            prefix
+        );
+    }
+
+    let mut padded = bytes.clone();
+    padded.extend_from_slice(trailing);
+    prop_assert!(
+        T::try_from_slice(&padded).is_err(),
+        "decoded with {} trailing bytes",
+        trailing.len()
+    );
+    let mut rest = padded.as_slice();
+    T::deserialize(&mut rest).map_err(|e| TestCaseError::fail(format!("stream: {}", e)))?;
+    prop_assert_eq!(rest, trailing, "stream read left");
+
+    Ok(bytes)
+}
+
+/// `bytes` with the byte at `offset` replaced by `value` does not decode
+fn check_rejected<T: BorshDeserialize>(
+    bytes: &[u8],
+    offset: usize,
+    value: u8,
+    field: &str,
+) -> Result<(), TestCaseError> {
+    let mut corrupted = bytes.to_vec();
+    corrupted[offset] = value;
+    prop_assert!(
+        T::try_from_slice(&corrupted).is_err(),
+        "decoded {} byte {}",
+        field,
+        value
+    );
+    Ok(())
+}
+
+/// Test 1: The allow list example's record round-trips, and rejects
+/// truncation, trailing bytes and an out-of-range `allowed`
+#[test]
+fn test_allow_list_record_encoding() {
+    let report = run_allow_list_record_test();
+    assert!(
+        report.passed,
+        "Allow list record encoding test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_allow_list_record_test() -> TestResultReport {
+    let test_name = "Borsh: Allow List Record";
+    let mut assertions = 0;
+
+    // Assertion: Every record is 73 bytes, round-trips, and its bool is
+    // strict
+    assertions += 1;
+    let strategy = (
+        pubkey(),
+        pubkey(),
+        any::<bool>(),
+        any::<i64>(),
+        trailing_bytes(),
+        2u8..,
+    );
+    let result = check(
+        strategy,
+        |(mint, user, allowed, added_timestamp, trailing, bad_bool)| {
+            let record = example_allow_list::AllowListRecord {
+                mint,
+                user,
+                allowed,
+                added_timestamp,
+            };
+            let bytes = check_encoding(&record, 32 + 32 + 1 + 8, &trailing)?;
+            let decoded = example_allow_list::AllowListRecord::try_from_slice(&bytes)?;
+            prop_assert_eq!(
+                (
+                    decoded.mint,
+                    decoded.user,
+                    decoded.allowed,
+                    decoded.added_timestamp
+                ),
+                (mint, user, allowed, added_timestamp)
+            );
+            check_rejected::<example_allow_list::AllowListRecord>(
+                &bytes,
+                FLAG_OFFSET,
+                bad_bool,
+                "allowed",
+            )
+        },
+    );
+    if let Err(e) = result {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: The production allow list's record round-trips with and without
+/// an expiry, and rejects an out-of-range access level, `Option` tag or
+/// `bool`
+#[test]
+fn test_production_allow_list_record_encoding() {
+    let report = run_production_allow_list_record_test();
+    assert!(
+        report.passed,
+        "Production allow list record encoding test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_production_allow_list_record_test() -> TestResultReport {
+    use production_allow_list::AllowListRecord;
+
+    let test_name = "Borsh: Production Allow List Record";
+    let mut assertions = 0;
+
+    // Assertion 1: A record is 77 bytes, 8 more with an expiry, and
+    // round-trips field for field
+    assertions += 1;
+    let result = check(
+        (production_record(), trailing_bytes()),
+        |(record, trailing)| {
+            let len = 32 + 32 + 1 + 1 + 8 + 1 + 1 + 1 + record.expiry_timestamp.map_or(0, |_| 8);
+            let bytes = check_encoding(&record, len, &trailing)?;
+            let decoded = AllowListRecord::try_from_slice(&bytes)?;
+            prop_assert_eq!(decoded.mint, record.mint);
+            prop_assert_eq!(decoded.user, record.user);
+            prop_assert_eq!(decoded.allowed, record.allowed);
+            prop_assert_eq!(decoded.access_level, record.access_level);
+            prop_assert_eq!(decoded.added_timestamp, record.added_timestamp);
+            prop_assert_eq!(decoded.expiry_timestamp, record.expiry_timestamp);
+            prop_assert_eq!(decoded.bump, record.bump);
+            prop_assert_eq!(decoded.revoked, record.revoked);
+            Ok(())
+        },
+    );
+    if let Err(e) = result {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 2: Access levels past `Institutional` are rejected
+    assertions += 1;
+    let result = check((production_record(), 4u8..), |(record, level)| {
+        let bytes = record.try_to_vec()?;
+        check_rejected::<AllowListRecord>(&bytes, ENUM_OFFSET, level, "access level")
+    });
+    if let Err(e) = result {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 3: An expiry tag other than 0 or 1, and `allowed` or
+    // `revoked` other than 0 or 1, are rejected
+    assertions += 1;
+    let result = check(
+        (production_record(), 2u8.., 2u8..),
+        |(record, bad_tag, bad_bool)| {
+            let bytes = record.try_to_vec()?;
+            let tag_offset = ENUM_OFFSET + 1 + 8;
+            let revoked_offset = bytes.len() - 1;
+            check_rejected::<AllowListRecord>(&bytes, tag_offset, bad_tag, "expiry tag")?;
+            check_rejected::<AllowListRecord>(&bytes, FLAG_OFFSET, bad_bool, "allowed")?;
+            check_rejected::<AllowListRecord>(&bytes, revoked_offset, bad_bool, "revoked")
+        },
+    );
+    if let Err(e) = result {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: The block list record round-trips and rejects an out-of-range
+/// block reason or expiry tag
+#[test]
+fn test_block_list_record_encoding() {
+    let report = run_block_list_record_test();
+    assert!(
+        report.passed,
+        "Block list record encoding test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_block_list_record_test() -> TestResultReport {
+    use example_block_list::BlockListRecord;
+
+    let test_name = "Borsh: Block List Record";
+    let mut assertions = 0;
+
+    // Assertion: Every record is 75 bytes, 8 more with an expiry,
+    // round-trips, and its reason, bool and expiry tag are strict
+    assertions += 1;
+    let strategy = (
+        (pubkey(), pubkey()),
+        (
+            any::<bool>(),
+            0u8..4,
+            any::<i64>(),
+            prop::option::of(any::<i64>()),
+        ),
+        (trailing_bytes(), 4u8.., 2u8..),
+    );
+    let result = check(
+        strategy,
+        |(
+            (mint, user),
+            (blocked, reason, added_timestamp, expiry_timestamp),
+            (trailing, bad_reason, bad_byte),
+        )| {
+            let record = BlockListRecord {
+                mint,
+                user,
+                blocked,
+                reason: block_reason(reason),
+                added_timestamp,
+                expiry_timestamp,
+            };
+            let len = 32 + 32 + 1 + 1 + 8 + 1 + expiry_timestamp.map_or(0, |_| 8);
+            let bytes = check_encoding(&record, len, &trailing)?;
+            let decoded = BlockListRecord::try_from_slice(&bytes)?;
+            prop_assert_eq!(
+                (
+                    decoded.mint,
+                    decoded.user,
+                    decoded.blocked,
+                    decoded.added_timestamp,
+                    decoded.expiry_timestamp
+                ),
+                (mint, user, blocked, added_timestamp, expiry_timestamp)
+            );
+            prop_assert_eq!(decoded.reason, block_reason(reason));
+            let tag_offset = ENUM_OFFSET + 1 + 8;
+            check_rejected::<BlockListRecord>(&bytes, ENUM_OFFSET, bad_reason, "reason")?;
+            check_rejected::<BlockListRecord>(&bytes, FLAG_OFFSET, bad_byte, "blocked")?;
+            check_rejected::<BlockListRecord>(&bytes, tag_offset, bad_byte, "expiry tag")
+        },
+    );
+    if let Err(e) = result {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 4: The production allow list's config round-trips
+#[test]
+fn test_config_encoding() {
+    let report = run_config_test();
+    assert!(
+        report.passed,
+        "Config encoding test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_config_test() -> TestResultReport {
+    use production_allow_list::Config;
+
+    let test_name = "Borsh: Production Allow List Config";
+    let mut assertions = 0;
+
+    // Assertion: Every config is 65 bytes and round-trips
+    assertions += 1;
+    let strategy = (pubkey(), pubkey(), any::<u8>(), trailing_bytes());
+    let result = check(strategy, |(authority, mint, bump, trailing)| {
+        let config = Config {
+            authority,
+            mint,
+            bump,
+        };
+        let bytes = check_encoding(&config, 32 + 32 + 1, &trailing)?;
+        let decoded = Config::try_from_slice(&bytes)?;
+        prop_assert_eq!(
+            (decoded.authority, decoded.mint, decoded.bump),
+            (authority, mint, bump)
+        );
+        Ok(())
+    });
+    if let Err(e) = result {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 5: The mock FAMP mint config round-trips and rejects out-of-range
+/// permissionless flags
+#[test]
+fn test_mint_config_encoding() {
+    let report = run_mint_config_test();
+    assert!(
+        report.passed,
+        "Mint config encoding test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_mint_config_test() -> TestResultReport {
+    let test_name = "Borsh: Mint Config";
+    let mut assertions = 0;
+
+    // Assertion 1: Every config is 99 bytes and round-trips
+    assertions += 1;
+    let result = check((mint_config(), trailing_bytes()), |(config, trailing)| {
+        let bytes = check_encoding(&config, 1 + 32 + 32 + 32 + 1 + 1, &trailing)?;
+        prop_assert_eq!(TestMintConfig::try_from_slice(&bytes)?, config);
+        Ok(())
+    });
+    if let Err(e) = result {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 2: Either flag outside 0 or 1 is rejected
+    assertions += 1;
+    let result = check((mint_config(), 2u8..), |(config, bad_bool)| {
+        let bytes = config.try_to_vec()?;
+        check_rejected::<TestMintConfig>(&bytes, 97, bad_bool, "permissionless thaw")?;
+        check_rejected::<TestMintConfig>(&bytes, 98, bad_bool, "permissionless freeze")
+    });
+    if let Err(e) = result {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 3: The fixture's default config is one of them
+    assertions += 1;
+    let fixture = test_data::create_test_mint_config(
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    match fixture
+        .try_to_vec()
+        .map(|bytes| TestMintConfig::try_from_slice(&bytes))
+    {
+        Ok(Ok(decoded)) if decoded == fixture => {}
+        other => {
+            return TestResultReport::failure(test_name, format!("Fixture config: {:?}", other))
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate Borsh property test report
+#[test]
+fn generate_borsh_properties_report() {
+    let results = vec![
+        run_allow_list_record_test(),
+        run_production_allow_list_record_test(),
+        run_block_list_record_test(),
+        run_config_test(),
+        run_mint_config_test(),
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Borsh Account Layout Properties",
+        "../../tests/reports/borsh_properties.md",
+    ) {
+        panic!("Failed to generate Borsh properties report: {}", e);
+    }
+
+    for result in &results {
+        assert!(result.passed, "{} failed: {:?}", result.name, result.error);
+    }
+}
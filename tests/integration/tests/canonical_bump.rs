@@ -0,0 +1,747 @@
+//! Canonical-bump enforcement tests
+//!
+//! The same seeds with a bump below the canonical one can still produce a
+//! valid PDA. If a program accepted such an address, a second config, record
+//! or extra-account-metas list could stand in for the real one; a stale
+//! "allowed" record at a lower bump would vouch for a user whose canonical
+//! record says otherwise. These tests build non-canonical PDAs for every
+//! program-derived account the gates use, plant program-owned accounts there
+//! where the program would never create them itself, and assert each gate
+//! rejects them.
+
+use borsh::BorshSerialize;
+use example_block_list::{
+    block_list_address, create_block_list_record, freeze_extra_account_metas,
+    freeze_extra_account_metas_size, initialize_freeze_extra_account_metas,
+    pack_freeze_extra_account_metas, BlockReason, BLOCK_LIST_SEED,
+};
+use production_allow_list::{
+    allow_list_address, config_address, pending_authority_address, AccessLevel, AllowListRecord,
+    Config, RemoveMode, ADD_TO_ALLOW_LIST, ALLOW_LIST_SEED, CONFIG_SEED, INITIALIZE,
+    REMOVE_FROM_ALLOW_LIST, UPDATE_AUTHORITY,
+};
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+use spl_tlv_account_resolution::error::AccountResolutionError;
+use spl_token_2022::state::AccountState;
+
+use token_acl_integration_tests::{
+    program_test::{rent_exempt_account, token_account},
+    reporting, TestResultReport,
+};
+use token_acl_interface::{self as interface, FREEZE_EXTRA_ACCOUNT_METAS_SEED};
+
+/// A valid PDA for `seeds` whose bump is below the canonical one
+fn non_canonical_address(seeds: &[&[u8]], program_id: &Pubkey) -> Pubkey {
+    let (_, canonical_bump) = Pubkey::find_program_address(seeds, program_id);
+    (0..canonical_bump)
+        .rev()
+        .find_map(|bump| {
+            let bump = [bump];
+            let mut bumped = seeds.to_vec();
+            bumped.push(&bump);
+            Pubkey::create_program_address(&bumped, program_id).ok()
+        })
+        .expect("seeds have no valid bump below the canonical one")
+}
+
+fn allowed_record(mint: &Pubkey, user: &Pubkey, bump: u8) -> AllowListRecord {
+    AllowListRecord {
+        mint: *mint,
+        user: *user,
+        allowed: true,
+        access_level: AccessLevel::Enhanced,
+        added_timestamp: 0,
+        expiry_timestamp: None,
+        bump,
+        revoked: false,
+    }
+}
+
+fn instruction_error(error: ProgramError) -> TransactionError {
+    let error = match error {
+        ProgramError::Custom(code) => InstructionError::Custom(code),
+        ProgramError::InvalidSeeds => InstructionError::InvalidSeeds,
+        ProgramError::InvalidAccountData => InstructionError::InvalidAccountData,
+        other => unreachable!("no instruction error mapping for {:?}", other),
+    };
+    TransactionError::InstructionError(0, error)
+}
+
+/// Check that `result` is the rejection `expected`, describing `case`
+fn expect_rejection(
+    result: Result<(), TransactionError>,
+    expected: ProgramError,
+    case: &str,
+) -> Result<(), String> {
+    match result {
+        Ok(()) => Err(format!("{} was accepted", case)),
+        Err(e) if e == instruction_error(expected.clone()) => Ok(()),
+        Err(e) => Err(format!(
+            "{} failed with {:?}, expected {:?}",
+            case, e, expected
+        )),
+    }
+}
+
+async fn send(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), TransactionError> {
+    // A fresh blockhash keeps retried instructions from being deduplicated
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| e.unwrap())
+}
+
+fn initialize_instruction(
+    gate: &Pubkey,
+    config: &Pubkey,
+    mint: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *gate,
+        &[INITIALIZE],
+        vec![
+            AccountMeta::new(*config, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+fn add_to_allow_list_instruction(
+    gate: &Pubkey,
+    config: &Pubkey,
+    record: &Pubkey,
+    mint: &Pubkey,
+    user: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *gate,
+        &[ADD_TO_ALLOW_LIST],
+        vec![
+            AccountMeta::new_readonly(*config, false),
+            AccountMeta::new(*record, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(*user, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Test 1: Production allow list config must sit at its canonical PDA
+#[tokio::test]
+async fn test_allow_list_config_canonical_bump() {
+    let report = run_allow_list_config_canonical_bump_test().await;
+    assert!(
+        report.passed,
+        "Allow list config canonical-bump test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_allow_list_config_canonical_bump_test() -> TestResultReport {
+    let test_name = "Allow List Config Canonical Bump";
+    let mut assertions = 0;
+
+    let gate = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let authority = Keypair::new();
+    let user = Pubkey::new_unique();
+    let canonical = config_address(&mint, &gate);
+    let forged = non_canonical_address(&[CONFIG_SEED, mint.as_ref()], &gate);
+
+    // A config the program would never create: right seeds, wrong bump
+    let mut program_test = ProgramTest::new(
+        "production_allow_list",
+        gate,
+        processor!(production_allow_list::process_instruction),
+    );
+    let forged_config = Config {
+        authority: authority.pubkey(),
+        mint,
+        bump: canonical.bump(),
+    };
+    program_test.add_account(
+        forged,
+        rent_exempt_account(forged_config.try_to_vec().unwrap(), gate),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.pubkey();
+
+    // Assertion 1: Initialize refuses a non-canonical config address
+    assertions += 1;
+    let init = initialize_instruction(&gate, &forged, &mint, &authority.pubkey(), &payer);
+    if let Err(e) = expect_rejection(
+        send(&mut context, &[init], &[&authority]).await,
+        ProgramError::InvalidAccountData,
+        "Initialize at a non-canonical config",
+    ) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 2: A planted non-canonical config cannot authorize additions
+    assertions += 1;
+    let add = add_to_allow_list_instruction(
+        &gate,
+        &forged,
+        &allow_list_address(&mint, &user, &gate).address(),
+        &mint,
+        &user,
+        &authority.pubkey(),
+        &payer,
+    );
+    if let Err(e) = expect_rejection(
+        send(&mut context, &[add], &[&authority]).await,
+        ProgramError::InvalidSeeds,
+        "Add through a non-canonical config",
+    ) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 3: A planted non-canonical config cannot change authority
+    assertions += 1;
+    let update = Instruction::new_with_bytes(
+        gate,
+        &[UPDATE_AUTHORITY],
+        vec![
+            AccountMeta::new_readonly(forged, false),
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new_readonly(Pubkey::new_unique(), false),
+            AccountMeta::new(pending_authority_address(&mint, &gate).address(), false),
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    if let Err(e) = expect_rejection(
+        send(&mut context, &[update], &[&authority]).await,
+        ProgramError::InvalidSeeds,
+        "Authority update through a non-canonical config",
+    ) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 4: The canonical config still initializes and is usable
+    assertions += 1;
+    let setup = [
+        initialize_instruction(
+            &gate,
+            &canonical.address(),
+            &mint,
+            &authority.pubkey(),
+            &payer,
+        ),
+        add_to_allow_list_instruction(
+            &gate,
+            &canonical.address(),
+            &allow_list_address(&mint, &user, &gate).address(),
+            &mint,
+            &user,
+            &authority.pubkey(),
+            &payer,
+        ),
+    ];
+    if let Err(e) = send(&mut context, &setup, &[&authority]).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("Canonical config setup failed: {}", e),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: Production allow list records must sit at their canonical PDA
+#[tokio::test]
+async fn test_allow_list_record_canonical_bump() {
+    let report = run_allow_list_record_canonical_bump_test().await;
+    assert!(
+        report.passed,
+        "Allow list record canonical-bump test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_allow_list_record_canonical_bump_test() -> TestResultReport {
+    let test_name = "Allow List Record Canonical Bump";
+    let mut assertions = 0;
+
+    let gate = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let authority = Keypair::new();
+    let user = Pubkey::new_unique();
+    let config = config_address(&mint, &gate).address();
+    let canonical = allow_list_address(&mint, &user, &gate);
+    let forged = non_canonical_address(&[ALLOW_LIST_SEED, mint.as_ref(), user.as_ref()], &gate);
+
+    // An "allowed" record the program never wrote, for a user it never added
+    let mut program_test = ProgramTest::new(
+        "production_allow_list",
+        gate,
+        processor!(production_allow_list::process_instruction),
+    );
+    let forged_record = allowed_record(&mint, &user, canonical.bump());
+    program_test.add_account(
+        forged,
+        rent_exempt_account(forged_record.try_to_vec().unwrap(), gate),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.pubkey();
+
+    let init = initialize_instruction(&gate, &config, &mint, &authority.pubkey(), &payer);
+    if let Err(e) = send(&mut context, &[init], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Setup failed: {}", e));
+    }
+
+    let thaw = |record: Pubkey| {
+        let extra_account_metas = interface::find_thaw_extra_account_metas_address(&mint, &gate).0;
+        interface::can_thaw_permissionless(
+            &gate,
+            payer,
+            Pubkey::new_unique(),
+            mint,
+            extra_account_metas,
+            &[
+                user.into(),
+                record.into(),
+                interface::find_missing_record_policy_address(&mint, &gate)
+                    .0
+                    .into(),
+            ],
+        )
+    };
+
+    // Assertion 1: The forged record does not vouch for the user
+    assertions += 1;
+    if let Err(e) = expect_rejection(
+        send(&mut context, &[thaw(forged)], &[]).await,
+        ProgramError::InvalidAccountData,
+        "Thaw through a non-canonical record",
+    ) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 2: Additions refuse a non-canonical record address
+    assertions += 1;
+    let other_user = Pubkey::new_unique();
+    let add = add_to_allow_list_instruction(
+        &gate,
+        &config,
+        &non_canonical_address(
+            &[ALLOW_LIST_SEED, mint.as_ref(), other_user.as_ref()],
+            &gate,
+        ),
+        &mint,
+        &other_user,
+        &authority.pubkey(),
+        &payer,
+    );
+    if let Err(e) = expect_rejection(
+        send(&mut context, &[add], &[&authority]).await,
+        ProgramError::InvalidAccountData,
+        "Add at a non-canonical record",
+    ) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 3: Removal refuses the non-canonical record
+    assertions += 1;
+    let remove = Instruction::new_with_bytes(
+        gate,
+        &[REMOVE_FROM_ALLOW_LIST, RemoveMode::Deactivate as u8],
+        vec![
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(forged, false),
+            AccountMeta::new_readonly(authority.pubkey(), true),
+        ],
+    );
+    if let Err(e) = expect_rejection(
+        send(&mut context, &[remove], &[&authority]).await,
+        ProgramError::InvalidSeeds,
+        "Remove of a non-canonical record",
+    ) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 4: Once added at the canonical PDA, the user thaws
+    assertions += 1;
+    let add = add_to_allow_list_instruction(
+        &gate,
+        &config,
+        &canonical.address(),
+        &mint,
+        &user,
+        &authority.pubkey(),
+        &payer,
+    );
+    if let Err(e) = send(&mut context, &[add], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Canonical add failed: {}", e));
+    }
+    if let Err(e) = send(&mut context, &[thaw(canonical.address())], &[]).await {
+        return TestResultReport::failure(test_name, format!("Canonical thaw failed: {}", e));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: Block list records must sit at their canonical PDA
+#[tokio::test]
+async fn test_block_list_record_canonical_bump() {
+    let report = run_block_list_record_canonical_bump_test().await;
+    assert!(
+        report.passed,
+        "Block list record canonical-bump test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_block_list_record_canonical_bump_test() -> TestResultReport {
+    let test_name = "Block List Record Canonical Bump";
+    let mut assertions = 0;
+
+    let gate = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let blocked_owner = Pubkey::new_unique();
+    let clean_owner = Pubkey::new_unique();
+    let blocked_account = Pubkey::new_unique();
+    let clean_account = Pubkey::new_unique();
+    let forged_record = |owner: &Pubkey| {
+        non_canonical_address(&[BLOCK_LIST_SEED, mint.as_ref(), owner.as_ref()], &gate)
+    };
+
+    // The blocked owner's canonical record blocks them; each owner also gets
+    // a non-canonical record claiming the opposite
+    let mut program_test = ProgramTest::new(
+        "example_block_list",
+        gate,
+        processor!(example_block_list::process_instruction),
+    );
+    let record = |owner: &Pubkey, blocked: bool| {
+        let mut record = create_block_list_record(&mint, owner, BlockReason::Sanctions, 0);
+        record.blocked = blocked;
+        rent_exempt_account(record.try_to_vec().unwrap(), gate)
+    };
+    program_test.add_account(
+        block_list_address(&mint, &blocked_owner, &gate).address(),
+        record(&blocked_owner, true),
+    );
+    program_test.add_account(forged_record(&blocked_owner), record(&blocked_owner, false));
+    program_test.add_account(forged_record(&clean_owner), record(&clean_owner, true));
+    program_test.add_account(
+        blocked_account,
+        token_account(&mint, &blocked_owner, 1_000, AccountState::Initialized),
+    );
+    program_test.add_account(
+        clean_account,
+        token_account(&mint, &clean_owner, 1_000, AccountState::Initialized),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.pubkey();
+
+    let init = initialize_freeze_extra_account_metas(&gate, &payer, &mint);
+    if let Err(e) = send(&mut context, &[init], &[]).await {
+        return TestResultReport::failure(test_name, format!("Setup failed: {}", e));
+    }
+
+    // Assertion 1: A non-canonical "not blocked" record does not thaw a
+    // blocked owner
+    assertions += 1;
+    let thaw = interface::can_thaw_permissionless(
+        &gate,
+        payer,
+        blocked_account,
+        mint,
+        interface::find_thaw_extra_account_metas_address(&mint, &gate).0,
+        &[
+            blocked_owner.into(),
+            forged_record(&blocked_owner).into(),
+            interface::find_missing_record_policy_address(&mint, &gate)
+                .0
+                .into(),
+        ],
+    );
+    if let Err(e) = expect_rejection(
+        send(&mut context, &[thaw], &[]).await,
+        ProgramError::InvalidAccountData,
+        "Thaw through a non-canonical record",
+    ) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    let freeze = |token_account: Pubkey, record: Pubkey| {
+        interface::can_freeze_permissionless(
+            &gate,
+            payer,
+            token_account,
+            mint,
+            interface::find_freeze_extra_account_metas_address(&mint, &gate).0,
+            &[record.into()],
+        )
+    };
+
+    // Assertion 2: A non-canonical "blocked" record does not freeze a clean
+    // owner
+    assertions += 1;
+    if let Err(e) = expect_rejection(
+        send(
+            &mut context,
+            &[freeze(clean_account, forged_record(&clean_owner))],
+            &[],
+        )
+        .await,
+        AccountResolutionError::IncorrectAccount.into(),
+        "Freeze through a non-canonical record",
+    ) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 3: The canonical record freezes the blocked owner
+    assertions += 1;
+    let canonical_record = block_list_address(&mint, &blocked_owner, &gate).address();
+    if let Err(e) = send(
+        &mut context,
+        &[freeze(blocked_account, canonical_record)],
+        &[],
+    )
+    .await
+    {
+        return TestResultReport::failure(test_name, format!("Canonical freeze failed: {}", e));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 4: Freeze extra-account-metas must sit at their canonical PDA
+#[tokio::test]
+async fn test_freeze_extra_account_metas_canonical_bump() {
+    let report = run_freeze_extra_account_metas_canonical_bump_test().await;
+    assert!(
+        report.passed,
+        "Freeze extra-account-metas canonical-bump test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_freeze_extra_account_metas_canonical_bump_test() -> TestResultReport {
+    let test_name = "Freeze Extra-Account-Metas Canonical Bump";
+    let mut assertions = 0;
+
+    let gate = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let account = Pubkey::new_unique();
+    let forged = non_canonical_address(&[FREEZE_EXTRA_ACCOUNT_METAS_SEED, mint.as_ref()], &gate);
+
+    // A well-formed list at a lower bump, as if the gate had created it
+    let metas = freeze_extra_account_metas().unwrap();
+    let mut list = vec![0; freeze_extra_account_metas_size(metas.len()).unwrap()];
+    pack_freeze_extra_account_metas(&mut list, 1, &metas).unwrap();
+
+    let mut program_test = ProgramTest::new(
+        "example_block_list",
+        gate,
+        processor!(example_block_list::process_instruction),
+    );
+    let mut record = create_block_list_record(&mint, &owner, BlockReason::Sanctions, 0);
+    record.blocked = true;
+    program_test.add_account(
+        block_list_address(&mint, &owner, &gate).address(),
+        rent_exempt_account(record.try_to_vec().unwrap(), gate),
+    );
+    program_test.add_account(forged, rent_exempt_account(list, gate));
+    program_test.add_account(
+        account,
+        token_account(&mint, &owner, 1_000, AccountState::Initialized),
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.pubkey();
+
+    // Assertion 1: Initialization refuses the non-canonical address
+    assertions += 1;
+    let mut init = initialize_freeze_extra_account_metas(&gate, &payer, &mint);
+    init.accounts[1].pubkey = forged;
+    if let Err(e) = expect_rejection(
+        send(&mut context, &[init], &[]).await,
+        ProgramError::InvalidSeeds,
+        "Initialize at a non-canonical list address",
+    ) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 2: Freeze refuses the planted non-canonical list
+    assertions += 1;
+    let freeze = |extra_account_metas: Pubkey| {
+        interface::can_freeze_permissionless(
+            &gate,
+            payer,
+            account,
+            mint,
+            extra_account_metas,
+            &[block_list_address(&mint, &owner, &gate).address().into()],
+        )
+    };
+    if let Err(e) = expect_rejection(
+        send(&mut context, &[freeze(forged)], &[]).await,
+        ProgramError::InvalidSeeds,
+        "Freeze through a non-canonical list",
+    ) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 3: The canonical list initializes and authorizes the freeze
+    assertions += 1;
+    let init = initialize_freeze_extra_account_metas(&gate, &payer, &mint);
+    let canonical = interface::find_freeze_extra_account_metas_address(&mint, &gate).0;
+    if let Err(e) = send(&mut context, &[init, freeze(canonical)], &[]).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("Canonical list setup or freeze failed: {}", e),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 5: Example allow list records must sit at their canonical PDA
+#[tokio::test]
+async fn test_example_allow_list_record_canonical_bump() {
+    let report = run_example_allow_list_record_canonical_bump_test().await;
+    assert!(
+        report.passed,
+        "Example allow list record canonical-bump test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_example_allow_list_record_canonical_bump_test() -> TestResultReport {
+    let test_name = "Example Allow List Record Canonical Bump";
+    let mut assertions = 0;
+
+    let gate = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let listed = Pubkey::new_unique();
+    let unlisted = Pubkey::new_unique();
+    let canonical = example_allow_list::allow_list_address(&mint, &listed, &gate).address();
+    let forged = non_canonical_address(
+        &[
+            example_allow_list::ALLOW_LIST_SEED,
+            mint.as_ref(),
+            unlisted.as_ref(),
+        ],
+        &gate,
+    );
+    let record = |user: &Pubkey| {
+        let record = example_allow_list::create_allow_list_record(&mint, user, 0);
+        rent_exempt_account(record.try_to_vec().unwrap(), gate)
+    };
+
+    // The unlisted user only has an "allowed" record at a lower bump
+    let mut program_test = ProgramTest::new(
+        "example_allow_list",
+        gate,
+        processor!(example_allow_list::process_instruction),
+    );
+    program_test.add_account(canonical, record(&listed));
+    program_test.add_account(forged, record(&unlisted));
+    let listed_account = Pubkey::new_unique();
+    let unlisted_account = Pubkey::new_unique();
+    for (account, owner) in [(listed_account, listed), (unlisted_account, unlisted)] {
+        program_test.add_account(
+            account,
+            token_account(&mint, &owner, 1_000, AccountState::Frozen),
+        );
+    }
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.pubkey();
+
+    let thaw = |account: Pubkey, user: Pubkey, record: Pubkey| {
+        interface::can_thaw_permissionless(
+            &gate,
+            payer,
+            account,
+            mint,
+            interface::find_thaw_extra_account_metas_address(&mint, &gate).0,
+            &[user.into(), record.into()],
+        )
+    };
+
+    // Assertion 1: The non-canonical record does not vouch for its user
+    assertions += 1;
+    if let Err(e) = expect_rejection(
+        send(
+            &mut context,
+            &[thaw(unlisted_account, unlisted, forged)],
+            &[],
+        )
+        .await,
+        ProgramError::InvalidAccountData,
+        "Thaw through a non-canonical record",
+    ) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 2: The canonical record thaws its user
+    assertions += 1;
+    if let Err(e) = send(
+        &mut context,
+        &[thaw(listed_account, listed, canonical)],
+        &[],
+    )
+    .await
+    {
+        return TestResultReport::failure(test_name, format!("Canonical thaw failed: {}", e));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate canonical-bump test report
+#[tokio::test]
+async fn generate_canonical_bump_report() {
+    let results = vec![
+        run_allow_list_config_canonical_bump_test().await,
+        run_allow_list_record_canonical_bump_test().await,
+        run_block_list_record_canonical_bump_test().await,
+        run_freeze_extra_account_metas_canonical_bump_test().await,
+        run_example_allow_list_record_canonical_bump_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Canonical-Bump Enforcement Test Results",
+        "../../tests/reports/canonical_bump.md",
+    ) {
+        panic!("Failed to generate canonical-bump report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} canonical-bump tests failed", failed);
+}
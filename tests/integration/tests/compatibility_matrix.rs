@@ -0,0 +1,407 @@
+//! Runtime compatibility matrix tests
+//!
+//! Runs the production allow list's onboarding and offboarding on the newest
+//! runtime and on runtimes from before gates that touch rent, compute and
+//! sysvars were activated, and reports what differs. The gate's decisions
+//! must not: only the facts a feature gate is known to change may.
+
+use production_allow_list::{
+    RemoveMode, ADD_TO_ALLOW_LIST, ALLOW_LIST_SEED, CONFIG_SEED, INITIALIZE, REMOVE_FROM_ALLOW_LIST,
+};
+use solana_program_test::{processor, ProgramTest};
+use solana_sdk::{
+    feature_set,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program, sysvar,
+    transaction::Transaction,
+};
+
+use token_acl_integration_tests::{
+    compatibility::{CompatibilityMatrix, Difference, FeatureProfile, Observation},
+    context::TestContext,
+    reporting, TestResultReport,
+};
+
+const BASELINE: &str = "current";
+const RENT_EPOCH: &str = "before set_exempt_rent_epoch_max";
+const BUILTIN_CU: &str = "before native_programs_consume_cu";
+const RESTART_SYSVAR: &str = "before last_restart_slot_sysvar";
+
+fn matrix() -> CompatibilityMatrix {
+    let mut matrix = CompatibilityMatrix::new(FeatureProfile::all_active(BASELINE));
+    matrix
+        .add_profile(FeatureProfile::without(
+            RENT_EPOCH,
+            &[feature_set::set_exempt_rent_epoch_max::id()],
+        ))
+        .add_profile(FeatureProfile::without(
+            BUILTIN_CU,
+            &[feature_set::native_programs_consume_cu::id()],
+        ))
+        .add_profile(FeatureProfile::without(
+            RESTART_SYSVAR,
+            &[feature_set::last_restart_slot_sysvar::id()],
+        ));
+    matrix
+}
+
+/// The allow list gate on a bank running `profile`
+struct Gate {
+    context: TestContext,
+    program_id: Pubkey,
+    authority: Keypair,
+    mint: Pubkey,
+    config: Pubkey,
+}
+
+impl Gate {
+    async fn start(test_name: &str, profile: &FeatureProfile) -> Result<Self, String> {
+        let program_id = Pubkey::new_unique();
+        let mut program_test = ProgramTest::new(
+            "production_allow_list",
+            program_id,
+            processor!(production_allow_list::process_instruction),
+        );
+        profile.apply(&mut program_test);
+        let context = TestContext::start(test_name, program_test).await?;
+        let authority = context.namespace().keypair("authority");
+        let mint = context.namespace().address("mint");
+        let (config, _) = Pubkey::find_program_address(&[CONFIG_SEED, mint.as_ref()], &program_id);
+
+        let mut gate = Self {
+            context,
+            program_id,
+            authority,
+            mint,
+            config,
+        };
+        let initialize = Instruction::new_with_bytes(
+            program_id,
+            &[INITIALIZE],
+            vec![
+                AccountMeta::new(config, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(gate.authority.pubkey(), true),
+                AccountMeta::new(gate.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        gate.send(initialize).await?;
+        Ok(gate)
+    }
+
+    fn record(&self, holder: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[ALLOW_LIST_SEED, self.mint.as_ref(), holder.as_ref()],
+            &self.program_id,
+        )
+        .0
+    }
+
+    /// Send `instruction` signed by the payer and the authority; the
+    /// outcome's error, if any, and the compute units it consumed
+    async fn send(&mut self, instruction: Instruction) -> Result<Option<u64>, String> {
+        let payer = self.context.payer();
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&payer.pubkey()),
+            &[&payer, &self.authority],
+            self.context.last_blockhash,
+        );
+        let outcome = self
+            .context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .map_err(|e| e.to_string())?;
+        outcome.result.map_err(|e| e.to_string())?;
+        Ok(outcome
+            .metadata
+            .map(|metadata| metadata.compute_units_consumed))
+    }
+
+    fn add_instruction(&self, holder: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            self.program_id,
+            &[ADD_TO_ALLOW_LIST],
+            vec![
+                AccountMeta::new_readonly(self.config, false),
+                AccountMeta::new(self.record(holder), false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(*holder, false),
+                AccountMeta::new_readonly(self.authority.pubkey(), true),
+                AccountMeta::new(self.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    fn close_instruction(&self, holder: &Pubkey, recipient: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            self.program_id,
+            &[REMOVE_FROM_ALLOW_LIST, RemoveMode::Close as u8],
+            vec![
+                AccountMeta::new_readonly(self.config, false),
+                AccountMeta::new(self.record(holder), false),
+                AccountMeta::new_readonly(self.authority.pubkey(), true),
+                AccountMeta::new(*recipient, false),
+            ],
+        )
+    }
+}
+
+/// Adding a holder: whether it lands, and the record it creates
+async fn onboarding(gate: &mut Gate) -> Observation {
+    let holder = gate.context.namespace().address("holder");
+    let add = gate.add_instruction(&holder);
+    let observation = match gate.send(add).await {
+        Ok(units) => Observation::new()
+            .with("result", "ok")
+            .with("compute units", units.unwrap_or_default()),
+        Err(e) => return Observation::new().with("result", e),
+    };
+    let record = gate.record(&holder);
+    match gate.context.banks_client.get_account(record).await {
+        Ok(Some(account)) => observation
+            .with("record owner is gate", account.owner == gate.program_id)
+            .with(
+                "record rent-exempt",
+                gate.context
+                    .rent
+                    .is_exempt(account.lamports, account.data.len()),
+            )
+            .with("record rent epoch", account.rent_epoch),
+        other => observation.with("record", format!("{:?}", other)),
+    }
+}
+
+/// Closing a holder's record: whether it lands, and where the rent goes
+async fn offboarding(gate: &mut Gate) -> Observation {
+    let holder = gate.context.namespace().address("holder");
+    let recipient = gate.context.namespace().address("refund recipient");
+    let record = gate.record(&holder);
+    let add = gate.add_instruction(&holder);
+    if let Err(e) = gate.send(add).await {
+        return Observation::new().with("setup", e);
+    }
+    let rent = match gate.context.banks_client.get_balance(record).await {
+        Ok(rent) => rent,
+        Err(e) => return Observation::new().with("setup", e),
+    };
+    let close = gate.close_instruction(&holder, &recipient);
+    let observation = match gate.send(close).await {
+        Ok(_) => Observation::new().with("result", "ok"),
+        Err(e) => return Observation::new().with("result", e),
+    };
+    let banks_client = &mut gate.context.banks_client;
+    observation
+        .with(
+            "record closed",
+            matches!(banks_client.get_account(record).await, Ok(None)),
+        )
+        .with(
+            "rent refunded",
+            banks_client.get_balance(recipient).await.ok() == Some(rent),
+        )
+}
+
+/// Which sysvars a gate could read
+async fn sysvars(gate: &mut Gate) -> Observation {
+    let banks_client = &mut gate.context.banks_client;
+    let mut observation = Observation::new();
+    for (name, address) in [
+        ("clock sysvar", sysvar::clock::id()),
+        ("rent sysvar", sysvar::rent::id()),
+        ("last restart slot sysvar", sysvar::last_restart_slot::id()),
+    ] {
+        let present = matches!(banks_client.get_account(address).await, Ok(Some(_)));
+        observation = observation.with(name, present);
+    }
+    observation
+}
+
+/// Every scenario on every profile
+async fn run_matrix(test_name: &str) -> Result<CompatibilityMatrix, String> {
+    let mut matrix = matrix();
+    for profile in matrix.profiles().to_vec() {
+        let mut gate = Gate::start(test_name, &profile).await?;
+        matrix.record(profile.name(), "onboarding", onboarding(&mut gate).await);
+        let mut gate = Gate::start(test_name, &profile).await?;
+        matrix.record(profile.name(), "offboarding", offboarding(&mut gate).await);
+        let mut gate = Gate::start(test_name, &profile).await?;
+        matrix.record(profile.name(), "sysvars", sysvars(&mut gate).await);
+    }
+    Ok(matrix)
+}
+
+/// The gate decides the same on every runtime; only the facts a gate is
+/// known to change differ
+#[tokio::test]
+async fn test_compatibility_matrix() {
+    let report = run_compatibility_matrix_test().await;
+    assert!(
+        report.passed,
+        "Compatibility matrix test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_compatibility_matrix_test() -> TestResultReport {
+    let test_name = "Runtime Compatibility Matrix";
+    let mut assertions = 0;
+
+    let matrix = match run_matrix(test_name).await {
+        Ok(matrix) => matrix,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let differences = matrix.differences();
+    println!("{}", matrix.render_markdown());
+
+    // Assertion: every scenario ran and landed on every runtime
+    assertions += 1;
+    for profile in matrix.profiles() {
+        for scenario in ["onboarding", "offboarding"] {
+            let result = matrix
+                .observation(profile.name(), scenario)
+                .and_then(|observation| observation.get("result"));
+            if result != Some("ok") {
+                return TestResultReport::failure(
+                    test_name,
+                    format!("{} on {}: {:?}", scenario, profile.name(), result),
+                );
+            }
+        }
+    }
+
+    // Assertion: the gate's decisions and the rent it holds never differ
+    assertions += 1;
+    let allowed = |difference: &&Difference| {
+        matches!(
+            (difference.profile.as_str(), difference.fact.as_str()),
+            (RENT_EPOCH, "record rent epoch") | (RESTART_SYSVAR, "last restart slot sysvar")
+        )
+    };
+    if let Some(difference) = differences.iter().find(|d| !allowed(d)) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Unexpected difference: {:?}", difference),
+        );
+    }
+
+    // Assertion: a processor-built gate is metered the same with or without
+    // native programs consuming compute units
+    assertions += 1;
+    if differences.iter().any(|d| d.profile == BUILTIN_CU) {
+        return TestResultReport::failure(
+            test_name,
+            format!("{} metered the gate differently", BUILTIN_CU),
+        );
+    }
+
+    // Assertion: the other pinned runtimes show the change their gate makes
+    assertions += 1;
+    for profile in [RENT_EPOCH, RESTART_SYSVAR] {
+        if !differences.iter().any(|d| d.profile == profile) {
+            return TestResultReport::failure(
+                test_name,
+                format!("{} behaved like the baseline", profile),
+            );
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Facts are compared against the baseline, and rendered by profile
+#[test]
+fn test_matrix_differences() {
+    let report = run_matrix_differences_test();
+    assert!(
+        report.passed,
+        "Matrix differences test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_matrix_differences_test() -> TestResultReport {
+    let test_name = "Runtime Compatibility: Differences";
+    let mut assertions = 0;
+
+    let mut matrix = matrix();
+    let facts = Observation::new()
+        .with("result", "ok")
+        .with("compute units", 1_200);
+    matrix.record(BASELINE, "onboarding", facts.clone());
+    matrix.record(RENT_EPOCH, "onboarding", facts.clone());
+    matrix.record(
+        BUILTIN_CU,
+        "onboarding",
+        Observation::new()
+            .with("result", "ok")
+            .with("compute units", 900),
+    );
+    matrix.record(
+        RESTART_SYSVAR,
+        "onboarding",
+        Observation::new().with("result", "ok"),
+    );
+
+    // Assertion: changed and missing facts are differences, equal ones not
+    assertions += 1;
+    let differences: Vec<(String, Option<String>)> = matrix
+        .differences()
+        .into_iter()
+        .map(|d| (d.profile, d.observed))
+        .collect();
+    let expected = vec![
+        (BUILTIN_CU.to_string(), Some("900".to_string())),
+        (RESTART_SYSVAR.to_string(), None),
+    ];
+    if differences != expected {
+        return TestResultReport::failure(test_name, format!("Differences {:?}", differences));
+    }
+
+    // Assertion: the report names each profile's gates and lists the changes
+    assertions += 1;
+    let markdown = matrix.render_markdown();
+    let row = format!(
+        "| onboarding | {} | compute units | 1200 | 900 |",
+        BUILTIN_CU
+    );
+    if !markdown.contains("| current | none (baseline) |")
+        || !markdown.contains("Native program should consume compute units")
+        || !markdown.contains(&row)
+    {
+        return TestResultReport::failure(test_name, format!("Rendered:\n{}", markdown));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate the runtime compatibility report, with the matrix
+#[tokio::test]
+async fn generate_compatibility_matrix_report() {
+    let test_name = "Runtime Compatibility Matrix";
+    let results = vec![
+        run_compatibility_matrix_test().await,
+        run_matrix_differences_test(),
+    ];
+    let matrix = run_matrix(test_name)
+        .await
+        .expect("every profile should start");
+
+    let path = "../../tests/reports/compatibility_matrix.md";
+    if let Err(e) = reporting::generate_test_report(&results, "Runtime Compatibility Results", path)
+    {
+        panic!("Failed to generate compatibility matrix report: {}", e);
+    }
+    let mut report = std::fs::read_to_string(path).expect("report should be readable");
+    report.push_str(&matrix.render_markdown());
+    std::fs::write(path, report).expect("matrix should be appended");
+
+    for result in &results {
+        assert!(result.passed, "{} failed: {:?}", result.name, result.error);
+    }
+}
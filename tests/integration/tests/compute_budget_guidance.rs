@@ -0,0 +1,484 @@
+//! Compute budget guidance benchmark
+//!
+//! Runs every operation an integrator budgets compute units for on a bank
+//! with all four in-repo gates: creating a gate config, a permissioned
+//! freeze and thaw, and a permissionless thaw through each gate. Each
+//! operation runs once per mint over a few fresh mints, and the units
+//! consumed become the compute budget guidance artifact, in markdown and
+//! JSON, so the recommended limits follow the programs as they change.
+//!
+//! Token-2022 always runs as SBF and is metered. The gates only are under
+//! `cargo test-sbf`; otherwise their operations are reported without a
+//! recommended limit. The issuer signs the thaws, standing in for the FAMP
+//! PDA.
+
+use borsh::BorshSerialize;
+use example_allow_list::create_allow_list_record;
+use example_block_list::block_list_address;
+use production_allow_list::{allow_list_address, ADD_TO_ALLOW_LIST, INITIALIZE};
+use solana_program_runtime::compute_budget_processor::DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT;
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::Transaction,
+};
+use spl_token_2022::{instruction as token_instruction, state::AccountState};
+
+use token_acl_integration_tests::{
+    budget_guidance::{self, BudgetGuidance},
+    program_test::{mint_account, rent_exempt_account, token_account},
+    reporting, suite_config, TestResultReport,
+};
+use token_acl_interface as interface;
+
+/// Mints every operation is measured on
+const SAMPLES: usize = 5;
+
+const CREATE_ALLOW_LIST_CONFIG: &str = "Create config (production_allow_list)";
+const CREATE_HYBRID_CONFIG: &str = "Create config (hybrid_gate)";
+const ADD_TO_ALLOW_LIST_OPERATION: &str = "Add to allow list (production_allow_list)";
+const PERMISSIONED_FREEZE: &str = "Permissioned freeze";
+const PERMISSIONED_THAW: &str = "Permissioned thaw";
+
+/// The in-repo gates a permissionless thaw can go through
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Gate {
+    ProductionAllowList,
+    ExampleAllowList,
+    ExampleBlockList,
+    Hybrid,
+}
+
+impl Gate {
+    const ALL: [Gate; 4] = [
+        Gate::ProductionAllowList,
+        Gate::ExampleAllowList,
+        Gate::ExampleBlockList,
+        Gate::Hybrid,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Gate::ProductionAllowList => "production_allow_list",
+            Gate::ExampleAllowList => "example_allow_list",
+            Gate::ExampleBlockList => "example_block_list",
+            Gate::Hybrid => "hybrid_gate",
+        }
+    }
+
+    fn thaw_operation(self) -> String {
+        format!("Permissionless thaw ({})", self.name())
+    }
+}
+
+/// A bank with every gate loaded; the hybrid gate reads the production
+/// allow list and the block list example
+struct GuidanceFixture {
+    context: ProgramTestContext,
+    issuer: Keypair,
+    production_allow_list: Pubkey,
+    example_allow_list: Pubkey,
+    example_block_list: Pubkey,
+    hybrid_gate: Pubkey,
+}
+
+impl GuidanceFixture {
+    async fn new() -> Self {
+        let production_allow_list = Pubkey::new_unique();
+        let example_allow_list = Pubkey::new_unique();
+        let example_block_list = Pubkey::new_unique();
+        let hybrid_gate = Pubkey::new_unique();
+
+        let mut program_test = ProgramTest::new(
+            "production_allow_list",
+            production_allow_list,
+            processor!(production_allow_list::process_instruction),
+        );
+        program_test.add_program(
+            "example_allow_list",
+            example_allow_list,
+            processor!(example_allow_list::process_instruction),
+        );
+        program_test.add_program(
+            "example_block_list",
+            example_block_list,
+            processor!(example_block_list::process_instruction),
+        );
+        program_test.add_program(
+            "hybrid_gate",
+            hybrid_gate,
+            processor!(hybrid_gate::process_instruction),
+        );
+
+        Self {
+            context: program_test.start_with_context().await,
+            issuer: Keypair::new(),
+            production_allow_list,
+            example_allow_list,
+            example_block_list,
+            hybrid_gate,
+        }
+    }
+
+    fn program_id(&self, gate: Gate) -> Pubkey {
+        match gate {
+            Gate::ProductionAllowList => self.production_allow_list,
+            Gate::ExampleAllowList => self.example_allow_list,
+            Gate::ExampleBlockList => self.example_block_list,
+            Gate::Hybrid => self.hybrid_gate,
+        }
+    }
+
+    /// A new mint the issuer can freeze
+    fn new_mint(&mut self) -> Pubkey {
+        let mint = Pubkey::new_unique();
+        self.context
+            .set_account(&mint, &mint_account(&self.issuer.pubkey(), 0).into());
+        mint
+    }
+
+    /// A new token account for `owner` in `state`
+    fn new_token_account(&mut self, mint: &Pubkey, owner: &Pubkey, state: AccountState) -> Pubkey {
+        let address = Pubkey::new_unique();
+        self.context
+            .set_account(&address, &token_account(mint, owner, 1_000, state).into());
+        address
+    }
+
+    /// Put `holder` on the example allow list; it has no admin instruction,
+    /// so the record is written directly
+    fn allow_on_example_list(&mut self, mint: &Pubkey, holder: &Pubkey) {
+        let record = create_allow_list_record(mint, holder, 0);
+        let account = rent_exempt_account(record.try_to_vec().unwrap(), self.example_allow_list);
+        self.context.set_account(
+            &example_allow_list::allow_list_address(mint, holder, &self.example_allow_list)
+                .address(),
+            &account.into(),
+        );
+    }
+
+    fn create_allow_list_config(&self, mint: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            self.production_allow_list,
+            &[INITIALIZE],
+            vec![
+                AccountMeta::new(
+                    production_allow_list::config_address(mint, &self.production_allow_list)
+                        .address(),
+                    false,
+                ),
+                AccountMeta::new_readonly(*mint, false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    fn create_hybrid_config(&self, mint: &Pubkey) -> Instruction {
+        let mut data = vec![hybrid_gate::INITIALIZE];
+        data.extend_from_slice(self.production_allow_list.as_ref());
+        data.extend_from_slice(self.example_block_list.as_ref());
+        Instruction::new_with_bytes(
+            self.hybrid_gate,
+            &data,
+            vec![
+                AccountMeta::new(
+                    hybrid_gate::config_address(mint, &self.hybrid_gate).address(),
+                    false,
+                ),
+                AccountMeta::new_readonly(*mint, false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    fn add_to_allow_list(&self, mint: &Pubkey, holder: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            self.production_allow_list,
+            &[ADD_TO_ALLOW_LIST],
+            vec![
+                AccountMeta::new_readonly(
+                    production_allow_list::config_address(mint, &self.production_allow_list)
+                        .address(),
+                    false,
+                ),
+                AccountMeta::new(
+                    allow_list_address(mint, holder, &self.production_allow_list).address(),
+                    false,
+                ),
+                AccountMeta::new_readonly(*mint, false),
+                AccountMeta::new_readonly(*holder, false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// The extra accounts `gate` reads for `holder`'s thaw check
+    fn thaw_extra_accounts(&self, gate: Gate, mint: &Pubkey, holder: &Pubkey) -> Vec<Pubkey> {
+        let program_id = self.program_id(gate);
+        let allow_record = allow_list_address(mint, holder, &self.production_allow_list).address();
+        let block_record = block_list_address(mint, holder, &self.example_block_list).address();
+        let policy = interface::find_missing_record_policy_address(mint, &program_id).0;
+        match gate {
+            Gate::ProductionAllowList => vec![*holder, allow_record, policy],
+            Gate::ExampleAllowList => vec![
+                *holder,
+                example_allow_list::allow_list_address(mint, holder, &program_id).address(),
+            ],
+            Gate::ExampleBlockList => vec![*holder, block_record, policy],
+            Gate::Hybrid => vec![
+                *holder,
+                hybrid_gate::config_address(mint, &program_id).address(),
+                allow_record,
+                block_record,
+            ],
+        }
+    }
+
+    /// `gate`'s thaw check followed by the thaw it authorizes
+    fn permissionless_thaw(
+        &self,
+        gate: Gate,
+        mint: &Pubkey,
+        holder: &Pubkey,
+        token_account: &Pubkey,
+    ) -> [Instruction; 2] {
+        let program_id = self.program_id(gate);
+        let extra_accounts: Vec<_> = self
+            .thaw_extra_accounts(gate, mint, holder)
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        let check = interface::can_thaw_permissionless(
+            &program_id,
+            *holder,
+            *token_account,
+            *mint,
+            interface::find_thaw_extra_account_metas_address(mint, &program_id).0,
+            &extra_accounts,
+        );
+        [check, self.thaw(mint, token_account)]
+    }
+
+    fn freeze(&self, mint: &Pubkey, token_account: &Pubkey) -> Instruction {
+        token_instruction::freeze_account(
+            &spl_token_2022::id(),
+            token_account,
+            mint,
+            &self.issuer.pubkey(),
+            &[],
+        )
+        .unwrap()
+    }
+
+    fn thaw(&self, mint: &Pubkey, token_account: &Pubkey) -> Instruction {
+        token_instruction::thaw_account(
+            &spl_token_2022::id(),
+            token_account,
+            mint,
+            &self.issuer.pubkey(),
+            &[],
+        )
+        .unwrap()
+    }
+
+    /// Submit `instructions`, signed by the payer and the issuer, and
+    /// return the compute units they consumed
+    async fn units(&mut self, instructions: &[Instruction]) -> Result<u64, String> {
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.context.payer.pubkey()),
+            &[&self.context.payer, &self.issuer],
+            self.context.last_blockhash,
+        );
+        let outcome = self
+            .context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .map_err(|e| e.to_string())?;
+        outcome.result.map_err(|e| e.to_string())?;
+        Ok(outcome
+            .metadata
+            .map(|metadata| metadata.compute_units_consumed)
+            .unwrap_or_default())
+    }
+
+    /// Run every operation once on a fresh mint
+    async fn measure(&mut self, guidance: &mut BudgetGuidance) -> Result<(), String> {
+        let gates_metered = budget_guidance::programs_metered();
+        let mint = self.new_mint();
+        let holder = Pubkey::new_unique();
+
+        let instruction = self.create_allow_list_config(&mint);
+        let units = self
+            .units(&[instruction])
+            .await
+            .map_err(|e| format!("{}: {}", CREATE_ALLOW_LIST_CONFIG, e))?;
+        guidance.record(CREATE_ALLOW_LIST_CONFIG, units, gates_metered);
+
+        let instruction = self.create_hybrid_config(&mint);
+        let units = self
+            .units(&[instruction])
+            .await
+            .map_err(|e| format!("{}: {}", CREATE_HYBRID_CONFIG, e))?;
+        guidance.record(CREATE_HYBRID_CONFIG, units, gates_metered);
+
+        let instruction = self.add_to_allow_list(&mint, &holder);
+        let units = self
+            .units(&[instruction])
+            .await
+            .map_err(|e| format!("{}: {}", ADD_TO_ALLOW_LIST_OPERATION, e))?;
+        guidance.record(ADD_TO_ALLOW_LIST_OPERATION, units, gates_metered);
+        self.allow_on_example_list(&mint, &holder);
+
+        let token_account = self.new_token_account(&mint, &holder, AccountState::Initialized);
+        let instruction = self.freeze(&mint, &token_account);
+        let units = self
+            .units(&[instruction])
+            .await
+            .map_err(|e| format!("{}: {}", PERMISSIONED_FREEZE, e))?;
+        guidance.record(PERMISSIONED_FREEZE, units, true);
+        let instruction = self.thaw(&mint, &token_account);
+        let units = self
+            .units(&[instruction])
+            .await
+            .map_err(|e| format!("{}: {}", PERMISSIONED_THAW, e))?;
+        guidance.record(PERMISSIONED_THAW, units, true);
+
+        for gate in Gate::ALL {
+            let token_account = self.new_token_account(&mint, &holder, AccountState::Frozen);
+            let instructions = self.permissionless_thaw(gate, &mint, &holder, &token_account);
+            let units = self
+                .units(&instructions)
+                .await
+                .map_err(|e| format!("{}: {}", gate.thaw_operation(), e))?;
+            guidance.record(&gate.thaw_operation(), units, gates_metered);
+        }
+        Ok(())
+    }
+}
+
+/// Measure every operation and write the compute budget guidance
+#[tokio::test]
+async fn benchmark_compute_budget_guidance() {
+    let (report, guidance) = run_compute_budget_guidance_benchmark().await;
+
+    println!("{}", guidance.to_markdown());
+    if let Err(e) = budget_guidance::generate_budget_guidance(
+        &guidance,
+        "../../tests/reports/compute_budget_guidance.md",
+        "../../tests/reports/compute_budget_guidance.json",
+    ) {
+        panic!("Failed to generate compute budget guidance: {}", e);
+    }
+    let markdown = reporting::render_test_report(
+        std::slice::from_ref(&report),
+        "Compute Budget Guidance Results",
+        &suite_config::current().disabled(),
+    );
+    if let Err(e) = std::fs::write("../../tests/reports/compute_budget_results.md", markdown) {
+        panic!("Failed to generate compute budget results report: {}", e);
+    }
+
+    assert!(
+        report.passed,
+        "Compute budget guidance benchmark failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_compute_budget_guidance_benchmark() -> (TestResultReport, BudgetGuidance) {
+    let test_name = "Compute Budget Guidance";
+    let mut assertions = 0;
+    let mut guidance = BudgetGuidance::new();
+    let mut fixture = GuidanceFixture::new().await;
+
+    // Assertion 1: Every operation succeeds on every mint
+    assertions += 1;
+    for _ in 0..SAMPLES {
+        if let Err(e) = fixture.measure(&mut guidance).await {
+            return (TestResultReport::failure(test_name, e), guidance);
+        }
+    }
+
+    // Assertion 2: Token-2022 is metered, so the permissioned operations get
+    // a limit covering every run within the default instruction limit
+    assertions += 1;
+    for operation in [PERMISSIONED_FREEZE, PERMISSIONED_THAW] {
+        let units = guidance.get(operation).unwrap();
+        let limit = units.recommended_limit();
+        let covered = limit.is_some_and(|limit| units.max() > 0 && u64::from(limit) > units.max());
+        if !covered || limit.unwrap() > DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT {
+            return (
+                TestResultReport::failure(
+                    test_name,
+                    format!(
+                        "{} recommends {:?} for a maximum of {} CU",
+                        operation,
+                        limit,
+                        units.max()
+                    ),
+                ),
+                guidance,
+            );
+        }
+    }
+
+    // Assertion 3: A permissionless thaw costs at least the thaw it
+    // authorizes, whichever gate checks it
+    assertions += 1;
+    let thaw = guidance.get(PERMISSIONED_THAW).unwrap().max();
+    for gate in Gate::ALL {
+        let units = guidance.get(&gate.thaw_operation()).unwrap();
+        if units.samples.iter().any(|&sample| sample < thaw) {
+            return (
+                TestResultReport::failure(
+                    test_name,
+                    format!(
+                        "{} consumed {:?} CU, less than the {} CU thaw alone",
+                        units.operation, units.samples, thaw
+                    ),
+                ),
+                guidance,
+            );
+        }
+    }
+
+    // Assertion 4: The JSON artifact carries every operation, with a limit
+    // exactly where the operation was metered
+    assertions += 1;
+    let json: serde_json::Value = match guidance
+        .to_json()
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+    {
+        Ok(json) => json,
+        Err(e) => return (TestResultReport::failure(test_name, e), guidance),
+    };
+    let entries = json.as_array().cloned().unwrap_or_default();
+    let consistent = entries.len() == guidance.operations.len()
+        && entries.iter().all(|entry| {
+            entry["metered"].as_bool() == Some(!entry["recommended_limit"].is_null())
+                && entry["samples"].as_array().map(Vec::len) == Some(SAMPLES)
+        });
+    if !consistent {
+        return (
+            TestResultReport::failure(
+                test_name,
+                format!("Guidance JSON does not match the measurements: {}", json),
+            ),
+            guidance,
+        );
+    }
+
+    (TestResultReport::success(test_name, assertions), guidance)
+}
@@ -0,0 +1,428 @@
+//! Compute unit profile tests
+//!
+//! These tests measure what Token-2022 and the allow list gate consume by
+//! executing them on [`SvmHarness`] and reading the `consumed` logs, rather
+//! than assuming a figure. Token-2022 always runs from its SBF build, so its
+//! profiles are metered and checked against the baselines committed in
+//! `tests/baselines/compute_units.json`; the gate's are metered only when its
+//! SBF build is present.
+
+use borsh::BorshSerialize;
+use example_allow_list::{allow_list_address, create_allow_list_record};
+use solana_program_test::processor;
+use solana_sdk::{
+    account::Account,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+};
+use spl_token_2022::{instruction as token_instruction, state::AccountState};
+use std::{fs, path::Path};
+use token_acl_client::instructions::{self, FreezeAuthority};
+use token_acl_integration_tests::{
+    benchmarks::compute_profile::{
+        check_baselines, check_baselines_in, render_compute_profile, BaselineCheck,
+        ComputeBaselines, ComputeProfile, ComputeProfiler, DEFAULT_TOLERANCE_PERCENT,
+    },
+    reporting, time, SvmHarness, TestResultReport,
+};
+
+/// Token-2022 freezes and thaws of an account with the issuer signing
+fn issuer_freeze_and_thaw(
+    svm: &mut SvmHarness,
+    profiler: &mut ComputeProfiler,
+) -> Result<(), String> {
+    let issuer = Keypair::new();
+    let mint = svm
+        .create_mint(&issuer.pubkey(), AccountState::Initialized)
+        .map_err(|e| format!("Mint creation failed: {}", e))?;
+    let token_account = svm
+        .create_token_account(&mint, &Pubkey::new_unique())
+        .map_err(|e| format!("Token account creation failed: {}", e))?;
+
+    let freeze = token_instruction::freeze_account(
+        &spl_token_2022::id(),
+        &token_account,
+        &mint,
+        &issuer.pubkey(),
+        &[],
+    )
+    .expect("the Token-2022 program id is always valid");
+    profiler
+        .profile(svm, "token_2022 freeze_account", &[freeze], &[&issuer])
+        .map_err(|e| format!("Freeze failed: {}", e))?;
+
+    let thaw = token_instruction::thaw_account(
+        &spl_token_2022::id(),
+        &token_account,
+        &mint,
+        &issuer.pubkey(),
+        &[],
+    )
+    .expect("the Token-2022 program id is always valid");
+    profiler
+        .profile(svm, "token_2022 thaw_account", &[thaw], &[&issuer])
+        .map_err(|e| format!("Thaw failed: {}", e))?;
+    Ok(())
+}
+
+/// A profile of one instruction of `program` logged as consuming `units`
+fn logged_profile(name: &str, program: &Pubkey, units: u64) -> ComputeProfile {
+    let logs = [
+        format!("Program {} invoke [1]", program),
+        format!(
+            "Program {} consumed {} of 200000 compute units",
+            program, units
+        ),
+        format!("Program {} success", program),
+    ];
+    ComputeProfile::from_logs(name, &logs, units, |_| false)
+}
+
+/// Test 1: Token-2022's freeze and thaw are measured from its logs and fill
+/// in the test's metrics
+#[test]
+fn test_token_2022_profile() {
+    let report = run_token_2022_profile_test();
+    assert!(
+        report.passed,
+        "Token-2022 profile test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_token_2022_profile_test() -> TestResultReport {
+    let test_name = "Token-2022 Compute Profile";
+    let mut assertions = 0;
+
+    let mut svm = SvmHarness::new();
+    let mut profiler = ComputeProfiler::new(None);
+    if let Err(e) = issuer_freeze_and_thaw(&mut svm, &mut profiler) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 1: Both profiles ran on the SBF build and consumed units
+    assertions += 1;
+    for profile in profiler.profiles() {
+        if !profile.is_metered() || profile.compute_units == 0 {
+            return TestResultReport::failure(
+                test_name,
+                format!("{} was not measured: {:?}", profile.name, profile),
+            );
+        }
+    }
+
+    // Assertion 2: The one instruction's logged units are the transaction's
+    assertions += 1;
+    for profile in profiler.profiles() {
+        let logged = profile.units_of(&spl_token_2022::id());
+        if logged != profile.compute_units {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "{} logged {} units but the bank reported {}",
+                    profile.name, logged, profile.compute_units
+                ),
+            );
+        }
+    }
+
+    // Assertion 3: The metrics hold both transactions, their fees and units
+    assertions += 1;
+    let metrics = profiler.metrics();
+    let measured: u64 = profiler.profiles().iter().map(|p| p.compute_units).sum();
+    if metrics.transactions != 2
+        || metrics.cost.fees != 2 * svm.fee(2)
+        || metrics.compute_units != measured
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!("Metrics did not follow the profiles: {:?}", metrics),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(profiler.into_metrics())
+}
+
+/// Test 2: A gate check is metered only when the gate ran from its SBF
+/// build, and the Token-2022 thaw next to it always is
+#[test]
+fn test_gate_profile() {
+    let report = run_gate_profile_test();
+    assert!(
+        report.passed,
+        "Gate profile test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_gate_profile_test() -> TestResultReport {
+    let test_name = "Allow List Gate Compute Profile";
+    let mut assertions = 0;
+
+    let mut svm = SvmHarness::new();
+    let gate = Pubkey::new_unique();
+    let sbf = match svm.add_program(
+        "example_allow_list",
+        gate,
+        processor!(example_allow_list::process_instruction),
+    ) {
+        Ok(sbf) => sbf,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let issuer = Keypair::new();
+    let owner = Pubkey::new_unique();
+    let setup = svm
+        .create_mint(&issuer.pubkey(), AccountState::Frozen)
+        .and_then(|mint| Ok((mint, svm.create_token_account(&mint, &owner)?)));
+    let (mint, token_account) = match setup {
+        Ok(setup) => setup,
+        Err(e) => return TestResultReport::failure(test_name, format!("Setup failed: {}", e)),
+    };
+    let record = allow_list_address(&mint, &owner, &gate).address();
+    let data = create_allow_list_record(&mint, &owner, 0)
+        .try_to_vec()
+        .expect("records always serialize");
+    svm.set_account(
+        record,
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: gate,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+
+    let check = instructions::with_extra_accounts(
+        instructions::permissionless_thaw(&mint, &token_account, &owner, &gate),
+        &[owner, record],
+    );
+    let instructions = instructions::authorized(check, &FreezeAuthority::Key(issuer.pubkey()))
+        .expect("a thaw check authorizes a thaw");
+    let mut profiler = ComputeProfiler::new(None);
+    let profile = match profiler.profile(
+        &mut svm,
+        "allow_list thaw check and thaw",
+        &instructions,
+        &[&issuer],
+    ) {
+        Ok(profile) => profile.clone(),
+        Err(e) => return TestResultReport::failure(test_name, format!("Thaw failed: {}", e)),
+    };
+
+    // Assertion 1: The profile is metered exactly when the gate's SBF build
+    // ran
+    assertions += 1;
+    if profile.is_metered() != sbf || svm.is_native(&gate) == sbf {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Gate ran {} but the profile says metered: {}",
+                if sbf { "from SBF" } else { "natively" },
+                profile.is_metered()
+            ),
+        );
+    }
+
+    // Assertion 2: Each instruction is profiled on its own, the thaw
+    // metered either way
+    assertions += 1;
+    let thaw = profile.instructions.get(1);
+    if profile.instructions.len() != 2
+        || profile.instructions[0].program != gate
+        || !thaw.is_some_and(|thaw| thaw.metered && thaw.compute_units.unwrap_or(0) > 0)
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!("Unexpected instructions: {:?}", profile.instructions),
+        );
+    }
+
+    // Assertion 3: A native gate's units stay out of the metrics
+    assertions += 1;
+    let expected = if sbf { profile.compute_units } else { 0 };
+    if profiler.metrics().compute_units != expected {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Metrics hold {} units, expected {}",
+                profiler.metrics().compute_units,
+                expected
+            ),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(profiler.into_metrics())
+}
+
+/// Test 3: Profiles are checked against their baselines with a tolerance,
+/// and an update records the new numbers
+#[test]
+fn test_baseline_regression() {
+    let report = run_baseline_regression_test();
+    assert!(
+        report.passed,
+        "Baseline regression test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_baseline_regression_test() -> TestResultReport {
+    let test_name = "Compute Baseline Regression";
+    let mut assertions = 0;
+
+    let dir = std::env::temp_dir().join(format!("token_acl_cu_baselines_{}", std::process::id()));
+    let path = dir.join("compute_units.json");
+    fs::remove_file(&path).ok();
+    let program = Pubkey::new_unique();
+    let native = Pubkey::new_unique();
+    let unmetered = ComputeProfile::from_logs(
+        "native",
+        &[format!("Program {} invoke [1]", native)],
+        150,
+        |id| *id == native,
+    );
+
+    // Assertion 1: Without a baseline a profile is new, and an unmetered one
+    // is never compared
+    assertions += 1;
+    let profiles = vec![logged_profile("check", &program, 1_000), unmetered.clone()];
+    match check_baselines_in(&path, &profiles, 5, false) {
+        Ok(checks) if checks == [BaselineCheck::New, BaselineCheck::Unmetered] => {}
+        other => {
+            return TestResultReport::failure(test_name, format!("Fresh check gave {:?}", other))
+        }
+    }
+
+    // Assertion 2: An update records only the metered profile
+    assertions += 1;
+    if let Err(e) = check_baselines_in(&path, &profiles, 5, true) {
+        return TestResultReport::failure(test_name, format!("Update failed: {}", e));
+    }
+    let baselines = match ComputeBaselines::load(&path) {
+        Ok(baselines) => baselines,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    if baselines.get("check") != Some(1_000) || baselines.get("native").is_some() {
+        return TestResultReport::failure(test_name, format!("Recorded {:?}", baselines));
+    }
+
+    // Assertion 3: Within the tolerance either way is not a change
+    assertions += 1;
+    for units in [950, 1_000, 1_050] {
+        let profile = logged_profile("check", &program, units);
+        let check = baselines.check(&profile, 5);
+        if check != (BaselineCheck::Within { baseline: 1_000 }) {
+            return TestResultReport::failure(test_name, format!("{} units: {:?}", units, check));
+        }
+    }
+
+    // Assertion 4: Beyond it, more units are a regression that fails the
+    // check and fewer are an improvement that does not
+    assertions += 1;
+    let regressed = [logged_profile("check", &program, 1_051)];
+    match check_baselines_in(&path, &regressed, 5, false) {
+        Err(e) if e.contains("check consumed 1,051 CU, over its baseline of 1,000 CU") => {}
+        other => {
+            return TestResultReport::failure(test_name, format!("Regression gave {:?}", other))
+        }
+    }
+    let improved = [logged_profile("check", &program, 900)];
+    match check_baselines_in(&path, &improved, 5, false) {
+        Ok(checks) if checks == [BaselineCheck::Improved { baseline: 1_000 }] => {}
+        other => {
+            return TestResultReport::failure(test_name, format!("Improvement gave {:?}", other))
+        }
+    }
+    fs::remove_dir_all(&dir).ok();
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 4: Token-2022's profiles match the committed baselines
+#[test]
+fn test_committed_baselines() {
+    let (report, _) = run_committed_baselines_test();
+    assert!(
+        report.passed,
+        "Committed baselines test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_committed_baselines_test() -> (TestResultReport, String) {
+    let test_name = "Committed Compute Baselines";
+    let mut assertions = 0;
+
+    let mut svm = SvmHarness::new();
+    let mut profiler = ComputeProfiler::new(None);
+    if let Err(e) = issuer_freeze_and_thaw(&mut svm, &mut profiler) {
+        return (TestResultReport::failure(test_name, e), String::new());
+    }
+
+    // Assertion 1: No profile regressed beyond the tolerance
+    assertions += 1;
+    let checks = match check_baselines(profiler.profiles(), DEFAULT_TOLERANCE_PERCENT) {
+        Ok(checks) => checks,
+        Err(e) => return (TestResultReport::failure(test_name, e), String::new()),
+    };
+    let rendered = render_compute_profile(profiler.profiles(), &checks);
+
+    // Assertion 2: Every profile has a committed baseline
+    assertions += 1;
+    if let Some((profile, _)) = profiler
+        .profiles()
+        .iter()
+        .zip(&checks)
+        .find(|(_, check)| check.baseline().is_none())
+    {
+        return (
+            TestResultReport::failure(
+                test_name,
+                format!(
+                    "{} has no baseline in {}",
+                    profile.name,
+                    Path::new("tests/baselines/compute_units.json").display()
+                ),
+            ),
+            rendered,
+        );
+    }
+
+    (
+        TestResultReport::success(test_name, assertions).with_metrics(profiler.into_metrics()),
+        rendered,
+    )
+}
+
+/// Generate compute profile test report
+#[test]
+fn generate_compute_profile_report() {
+    let (baselines, profile) = run_committed_baselines_test();
+    let results = vec![
+        run_token_2022_profile_test(),
+        run_gate_profile_test(),
+        run_baseline_regression_test(),
+        baselines,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Compute Profile Test Results",
+        "../../tests/reports/compute_profile.md",
+    ) {
+        panic!("Failed to generate compute profile report: {}", e);
+    }
+
+    let mut report = String::from("# Compute Profile\n\n");
+    report.push_str(&format!("**Generated**: {}\n\n", time::report_timestamp()));
+    report.push_str(&profile);
+    fs::create_dir_all("../../tests/reports").ok();
+    if let Err(e) = fs::write("../../tests/reports/compute_profile_baselines.md", report) {
+        panic!("Failed to write compute profile baselines report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} compute profile tests failed", failed);
+}
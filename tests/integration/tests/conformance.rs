@@ -0,0 +1,258 @@
+//! Gate conformance tests
+//!
+//! These tests run the conformance battery against the gates in this
+//! repository, from the configs in `tests/conformance/`: their SBF builds
+//! when present and their native processors otherwise. The production allow
+//! list and both example gates must be certified. A third-party gate
+//! is certified from its config when `TOKEN_ACL_CONFORMANCE_CONFIG` names
+//! one.
+
+use solana_program_runtime::invoke_context::BuiltinFunctionWithContext;
+use solana_program_test::processor;
+use std::path::{Path, PathBuf};
+use token_acl_integration_tests::{
+    conformance::{
+        certify, CertificationReport, CheckCategory, CheckOutcome, ConformanceRunner, GateConfig,
+        CONFORMANCE_CONFIG_ENV,
+    },
+    reporting, TestResultReport,
+};
+
+fn config_path(file: &str) -> PathBuf {
+    Path::new("tests/conformance").join(file)
+}
+
+/// Certify the repository gate configured in `file`
+fn certify_repository_gate(
+    file: &str,
+    builtin: Option<BuiltinFunctionWithContext>,
+) -> Result<CertificationReport, String> {
+    let config = GateConfig::load(&config_path(file))?;
+    let report = ConformanceRunner::start(&config, builtin)?.run();
+    let name = file.trim_end_matches(".toml");
+    report
+        .write(Path::new(&format!(
+            "../../tests/reports/conformance_{}.md",
+            name
+        )))
+        .map_err(|e| format!("Cannot write the report of {}: {}", name, e))?;
+    Ok(report)
+}
+
+/// Names of the checks `report` failed
+fn failed_checks(report: &CertificationReport) -> Vec<String> {
+    report.failures().map(|check| check.name.clone()).collect()
+}
+
+/// Test 1: The production allow list passes every check
+#[test]
+fn test_production_allow_list_certified() {
+    let report = run_production_allow_list_certified_test();
+    assert!(
+        report.passed,
+        "Production allow list certification test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_production_allow_list_certified_test() -> TestResultReport {
+    let test_name = "Production Allow List Certified";
+    let mut assertions = 0;
+
+    let report = match certify_repository_gate(
+        "production_allow_list.toml",
+        processor!(production_allow_list::process_instruction),
+    ) {
+        Ok(report) => report,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+
+    // Assertion 1: No check failed
+    assertions += 1;
+    if !report.certified() {
+        return TestResultReport::failure(
+            test_name,
+            format!("Failed {:?}", failed_checks(&report)),
+        );
+    }
+
+    // Assertion 2: Every PDA validation check applied to the thaw, which
+    // has holders approved and denied
+    assertions += 1;
+    let thaw_pda_checks = report
+        .checks
+        .iter()
+        .filter(|check| {
+            check.category == CheckCategory::PdaValidation && check.name.starts_with("can-thaw")
+        })
+        .collect::<Vec<_>>();
+    if thaw_pda_checks.len() != 3
+        || thaw_pda_checks
+            .iter()
+            .any(|check| check.outcome != CheckOutcome::Passed)
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!("Thaw PDA validation checks: {:?}", thaw_pda_checks),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: The example gates pass every check
+#[test]
+fn test_example_gates_certified() {
+    let report = run_example_gates_certified_test();
+    assert!(
+        report.passed,
+        "Example gates certified test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_example_gates_certified_test() -> TestResultReport {
+    let test_name = "Example Gates Certified";
+    let mut assertions = 0;
+
+    let gates: [(&str, Option<BuiltinFunctionWithContext>); 2] = [
+        (
+            "example_allow_list.toml",
+            processor!(example_allow_list::process_instruction),
+        ),
+        (
+            "example_block_list.toml",
+            processor!(example_block_list::process_instruction),
+        ),
+    ];
+    for (file, builtin) in gates {
+        let report = match certify_repository_gate(file, builtin) {
+            Ok(report) => report,
+            Err(e) => return TestResultReport::failure(test_name, e),
+        };
+
+        // Assertion: No check failed, owner binding included
+        assertions += 1;
+        if !report.certified() {
+            return TestResultReport::failure(
+                test_name,
+                format!("{} failed {:?}", report.gate, failed_checks(&report)),
+            );
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: Configs are rejected before anything runs when they name unknown
+/// accounts, seeds or per-holder accounts in setup
+#[test]
+fn test_config_validation() {
+    let report = run_config_validation_test();
+    assert!(
+        report.passed,
+        "Config validation test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_config_validation_test() -> TestResultReport {
+    let test_name = "Conformance Config Validation";
+    let mut assertions = 0;
+
+    let config = |thaw: &str, setup: &str| {
+        format!(
+            "name = \"gate\"\nprogram = \"gate.so\"\nrecord_seeds = [\"record\", \"{{owner}}\"]\n\
+             [thaw]\nextra_accounts = [{}]\n{}\n\
+             [[holders]]\nlabel = \"holder\"\nthaw = \"allow\"\n",
+            thaw, setup
+        )
+    };
+
+    // Assertion 1: A well-formed config parses
+    assertions += 1;
+    if let Err(e) = GateConfig::parse(&config("\"owner\", \"pda:policy/{mint}\"", "")) {
+        return TestResultReport::failure(test_name, format!("Valid config rejected: {}", e));
+    }
+
+    // Assertion 2: Unknown accounts, seeds and fields, and per-holder
+    // accounts in setup, are rejected
+    for (case, text) in [
+        ("unknown account", config("\"holder\"", "")),
+        ("unknown seed", config("\"pda:policy/{caller}\"", "")),
+        (
+            "per-holder setup account",
+            config(
+                "",
+                "[[setup]]\ndata = \"00\"\naccounts = [\"payer\", \"record\"]",
+            ),
+        ),
+        (
+            "bad setup data",
+            config("", "[[setup]]\ndata = \"0\"\naccounts = [\"payer\"]"),
+        ),
+        ("unknown field", config("", "retries = 3")),
+    ] {
+        assertions += 1;
+        if GateConfig::parse(&text).is_ok() {
+            return TestResultReport::failure(test_name, format!("Accepted a {}", case));
+        }
+    }
+
+    // Assertion 3: A gate with no build and no processor is an error
+    assertions += 1;
+    let config = GateConfig::parse(&config("\"owner\"", "")).expect("parsed above");
+    if ConformanceRunner::start(&config, None).is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Started a gate with nothing to run".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 4: The gate `TOKEN_ACL_CONFORMANCE_CONFIG` names, if any, is
+/// certified from its SBF build
+#[test]
+fn test_third_party_gate() {
+    let Ok(path) = std::env::var(CONFORMANCE_CONFIG_ENV) else {
+        println!(
+            "{} is not set; no third-party gate to certify",
+            CONFORMANCE_CONFIG_ENV
+        );
+        return;
+    };
+    let report = certify(Path::new(&path)).unwrap_or_else(|e| panic!("{}", e));
+    report
+        .write(Path::new("../../tests/reports/conformance_third_party.md"))
+        .expect("the report is writable");
+    assert!(
+        report.certified(),
+        "{} is not certified: {:?}",
+        report.gate,
+        failed_checks(&report)
+    );
+}
+
+/// Generate conformance test report
+#[test]
+fn generate_conformance_report() {
+    let results = vec![
+        run_production_allow_list_certified_test(),
+        run_example_gates_certified_test(),
+        run_config_validation_test(),
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Gate Conformance Test Results",
+        "../../tests/reports/conformance_tests.md",
+    ) {
+        panic!("Failed to generate conformance report: {}", e);
+    }
+
+    for result in &results {
+        assert!(result.passed, "{} failed: {:?}", result.name, result.error);
+    }
+}
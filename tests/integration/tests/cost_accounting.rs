@@ -0,0 +1,305 @@
+//! Per-test cost accounting tests
+//!
+//! Reports sum what each test spent so a team can price a live run, and
+//! devnet tests fail when they spend more than their budget. These tests pin
+//! what counts as a fee, as rent and as reclaimed rent, the budgets read from
+//! `suite.toml`, and the cost section of the report.
+
+use solana_sdk::{
+    instruction::Instruction, pubkey::Pubkey, system_program, transaction::Transaction,
+};
+
+use token_acl_integration_tests::{
+    account_snapshots::{AccountSnapshot, AccountState},
+    metrics::{self, LamportCost, TestMetrics},
+    reporting,
+    suite_config::{SuiteCategory, SuiteConfig, DEFAULT_DEVNET_BUDGET},
+    TestResultReport,
+};
+
+const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+fn state(lamports: u64, owner: Pubkey, len: usize) -> Option<AccountState> {
+    Some(AccountState {
+        lamports,
+        owner,
+        data: vec![0; len],
+    })
+}
+
+fn snapshot(accounts: &[(Pubkey, Option<AccountState>)]) -> AccountSnapshot {
+    let mut snapshot = AccountSnapshot::new();
+    for (address, state) in accounts {
+        snapshot.insert(*address, state.clone());
+    }
+    snapshot
+}
+
+/// An unsigned transaction paid for by `payer` with `signers` signatures
+fn transaction(payer: &Pubkey, signers: usize) -> Transaction {
+    let mut transaction = Transaction::new_with_payer(
+        &[Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[],
+            vec![],
+        )],
+        Some(payer),
+    );
+    transaction.signatures = vec![Default::default(); signers];
+    transaction
+}
+
+/// Steps are charged fees, rent and reclaimed rent from what they changed
+#[test]
+fn test_step_accounting() {
+    let report = run_step_accounting_test();
+    assert!(
+        report.passed,
+        "Step accounting test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_step_accounting_test() -> TestResultReport {
+    let test_name = "Step Cost Accounting";
+    let mut assertions = 0;
+
+    let gate = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let created = Pubkey::new_unique();
+    let closed = Pubkey::new_unique();
+    let wallet = Pubkey::new_unique();
+
+    // Assertion 1: A created account is rent, a closed one reclaimed, and a
+    // funded wallet neither
+    assertions += 1;
+    let before = snapshot(&[
+        (payer, state(100_000_000, system_program::id(), 0)),
+        (created, None),
+        (closed, state(1_500_000, gate, 82)),
+        (wallet, None),
+    ]);
+    let after = snapshot(&[
+        (payer, state(95_990_000, system_program::id(), 0)),
+        (created, state(2_000_000, gate, 120)),
+        (closed, None),
+        (wallet, state(3_500_000, system_program::id(), 0)),
+    ]);
+    let mut metrics = TestMetrics::default();
+    metrics.record_step(
+        &[&transaction(&payer, 2)],
+        LAMPORTS_PER_SIGNATURE,
+        &before.diff(&after),
+    );
+    let expected = LamportCost {
+        fees: 10_000,
+        rent: 2_000_000,
+        reclaimed: 1_500_000,
+    };
+    if metrics.cost != expected || metrics.transactions != 1 {
+        return TestResultReport::failure(test_name, format!("Counted {:?}", metrics));
+    }
+
+    // Assertion 2: A transaction whose fee payer was not charged pays nothing
+    assertions += 1;
+    let untouched = snapshot(&[(payer, state(95_990_000, system_program::id(), 0))]);
+    metrics.record_step(
+        &[&transaction(&payer, 1)],
+        LAMPORTS_PER_SIGNATURE,
+        &untouched.diff(&untouched),
+    );
+    if metrics.transactions != 1 {
+        return TestResultReport::failure(
+            test_name,
+            format!("Rejected transaction charged: {:?}", metrics),
+        );
+    }
+
+    // Assertion 3: Net spend is fees plus rent minus reclaimed, and can go
+    // negative
+    assertions += 1;
+    let refund = LamportCost {
+        fees: 5_000,
+        rent: 0,
+        reclaimed: 2_000_000,
+    };
+    if metrics.cost.net() != 510_000 || refund.net() != -1_995_000 {
+        return TestResultReport::failure(
+            test_name,
+            format!("Net {} and {}", metrics.cost.net(), refund.net()),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Budgets come from `suite.toml` and fail tests that overspend
+#[test]
+fn test_cost_budgets() {
+    let report = run_cost_budgets_test();
+    assert!(report.passed, "Cost budget test failed: {:?}", report.error);
+}
+
+fn run_cost_budgets_test() -> TestResultReport {
+    let test_name = "Cost Budgets";
+    let mut assertions = 0;
+
+    // Assertion 1: Only devnet has a budget by default
+    assertions += 1;
+    let defaults = SuiteConfig::default();
+    let budgets: Vec<_> = SuiteCategory::ALL
+        .iter()
+        .filter_map(|category| defaults.budget(*category).map(|b| (*category, b)))
+        .collect();
+    if budgets != vec![(SuiteCategory::Devnet, DEFAULT_DEVNET_BUDGET)] {
+        return TestResultReport::failure(test_name, format!("Default budgets {:?}", budgets));
+    }
+
+    // Assertion 2: The budgets table overrides and adds budgets
+    assertions += 1;
+    let config =
+        match SuiteConfig::from_toml_str("[budgets]\ndevnet = 20000000\nexperiments = 500000000\n")
+        {
+            Ok(config) => config,
+            Err(e) => return TestResultReport::failure(test_name, e),
+        };
+    if config.budget(SuiteCategory::Devnet) != Some(20_000_000)
+        || config.budget(SuiteCategory::Experiments) != Some(500_000_000)
+        || config.budget(SuiteCategory::LoadTests).is_some()
+    {
+        return TestResultReport::failure(test_name, format!("Parsed {:?}", config));
+    }
+
+    // Assertion 3: Unknown budget categories are rejected
+    assertions += 1;
+    if SuiteConfig::from_toml_str("[budgets]\nmainnet = 1\n").is_ok() {
+        return TestResultReport::failure(test_name, "Unknown budget accepted".to_string());
+    }
+
+    // Assertion 4: Spending up to the budget passes, a lamport more fails
+    assertions += 1;
+    let mut metrics = TestMetrics::new(Some(SuiteCategory::Devnet));
+    metrics.cost.rent = 995_000;
+    metrics.record_fee(5_000);
+    if let Err(e) = metrics.check_budget(1_000_000) {
+        return TestResultReport::failure(test_name, e);
+    }
+    match metrics.check_budget(999_999) {
+        Err(e) if e.contains("devnet budget of 999,999 lamports") => {}
+        other => return TestResultReport::failure(test_name, format!("Over budget: {:?}", other)),
+    }
+
+    // Assertion 5: Tests outside a budgeted category are never over budget
+    assertions += 1;
+    let mut core = TestMetrics::default();
+    core.cost.rent = u64::MAX / 2;
+    if let Some(failure) = metrics::over_budget("Core Test", &core) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Core test failed its budget: {:?}", failure.error),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// The report sums costs per test and per category
+#[test]
+fn test_cost_section() {
+    let report = run_cost_section_test();
+    assert!(
+        report.passed,
+        "Cost section test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_cost_section_test() -> TestResultReport {
+    let test_name = "Report Cost Section";
+    let mut assertions = 0;
+
+    let with_cost = |name: &str, category: Option<SuiteCategory>, fees: u64, rent: u64| {
+        let mut metrics = TestMetrics::new(category);
+        metrics.record_fee(fees);
+        metrics.cost.rent = rent;
+        TestResultReport::success(name, 1).with_metrics(metrics)
+    };
+
+    // Assertion 1: Reports without costs have no cost section
+    assertions += 1;
+    let untracked = vec![
+        TestResultReport::success("A", 1),
+        TestResultReport::skipped("B", SuiteCategory::Devnet),
+    ];
+    if !metrics::render_cost_section(&untracked).is_empty() {
+        return TestResultReport::failure(test_name, "Empty costs rendered".to_string());
+    }
+
+    // Assertion 2: Costs are summed per category, core first
+    assertions += 1;
+    let results = vec![
+        with_cost("Add", None, 10_000, 1_000_000),
+        with_cost("Devnet Thaw", Some(SuiteCategory::Devnet), 5_000, 2_000_000),
+        with_cost("Close", None, 5_000, 0),
+        TestResultReport::success("Untracked", 1),
+    ];
+    let by_category = metrics::costs_by_category(&results);
+    let expected = vec![
+        (
+            "core",
+            2,
+            LamportCost {
+                fees: 15_000,
+                rent: 1_000_000,
+                reclaimed: 0,
+            },
+        ),
+        (
+            "devnet",
+            1,
+            LamportCost {
+                fees: 5_000,
+                rent: 2_000_000,
+                reclaimed: 0,
+            },
+        ),
+    ];
+    if by_category != expected {
+        return TestResultReport::failure(test_name, format!("By category {:?}", by_category));
+    }
+
+    // Assertion 3: The section totals the run and lists only tracked tests
+    assertions += 1;
+    let section = metrics::render_cost_section(&results);
+    let total = "spends 0.00302 SOL (3,020,000 lamports) net";
+    if !section.contains(total)
+        || !section.contains("| Devnet Thaw | devnet | 1 |")
+        || section.contains("Untracked")
+    {
+        return TestResultReport::failure(test_name, format!("Section:\n{}", section));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate the cost accounting test report
+#[test]
+fn generate_cost_accounting_report() {
+    let results = vec![
+        run_step_accounting_test(),
+        run_cost_budgets_test(),
+        run_cost_section_test(),
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Cost Accounting Test Results",
+        "../../tests/reports/cost_accounting.md",
+    ) {
+        panic!("Failed to generate cost accounting report: {}", e);
+    }
+
+    for result in &results {
+        assert!(result.passed, "{} failed: {:?}", result.name, result.error);
+    }
+}
@@ -0,0 +1,446 @@
+//! Gate CPI account-count scaling benchmark
+//!
+//! Grows a gate's thaw extra-account-metas list from 1 to 20 entries, with
+//! literal seeds (`["scale", i]`) and with dynamic seeds that also read the
+//! mint key and the token account owner, and sends one thaw check through a
+//! mock FAMP for each list. The FAMP resolves the extra accounts on-chain
+//! from the list and CPIs into a probe gate, which validates them against
+//! the same list, the way a production gate checks its record PDA.
+//!
+//! Transaction size, account locks and client fetches are measured. The
+//! native processors program-test runs do not meter syscalls, so compute
+//! units are priced from the runtime's cost table instead: both PDA
+//! derivations of every extra account (one per bump tried) plus the CPI.
+//! Dynamic lists hang off a different mint each, so unlike the literal
+//! lists their bumps, and compute units, do not grow monotonically. The
+//! points go into the cost report.
+
+use solana_program_runtime::compute_budget::ComputeBudget;
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    program::invoke,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    signature::Signer,
+    transaction::Transaction,
+};
+use spl_tlv_account_resolution::{
+    account::ExtraAccountMeta,
+    seeds::Seed,
+    state::{AccountFetchError, ExtraAccountMetaList},
+};
+use spl_token_2022::state::AccountState;
+use spl_type_length_value::state::{TlvState, TlvStateBorrowed, TlvStateMut};
+use std::cell::Cell;
+
+use token_acl_integration_tests::{
+    costs::{self, GateCheckCost},
+    onboarding::signed_size,
+    program_test::{rent_exempt_account, token_account},
+    reporting, TestResultReport,
+};
+use token_acl_interface::{
+    self as interface, CanThawPermissionless, GateInterfaceVersion,
+    CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+};
+
+/// Largest extra-account-metas list benchmarked
+const MAX_EXTRA_ACCOUNTS: usize = 20;
+
+/// Literal seed every benchmarked extra account starts with
+const SCALE_SEED: &[u8] = b"scale";
+
+/// How the benchmarked extra accounts are derived
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SeedKind {
+    /// `["scale", i]`
+    Literal,
+    /// `["scale", i, mint, token account owner]`
+    Dynamic,
+}
+
+impl SeedKind {
+    fn name(self) -> &'static str {
+        match self {
+            SeedKind::Literal => "literal",
+            SeedKind::Dynamic => "dynamic",
+        }
+    }
+
+    /// The `index`th entry of the list
+    fn meta(self, index: usize) -> ExtraAccountMeta {
+        let mut seeds = vec![
+            Seed::Literal {
+                bytes: SCALE_SEED.to_vec(),
+            },
+            Seed::Literal {
+                bytes: vec![index as u8],
+            },
+        ];
+        if self == SeedKind::Dynamic {
+            seeds.push(Seed::AccountKey { index: 2 });
+            seeds.push(Seed::AccountData {
+                account_index: 1,
+                data_index: 32,
+                length: 32,
+            });
+        }
+        ExtraAccountMeta::new_with_seeds(&seeds, false, false).unwrap()
+    }
+
+    /// The `index`th extra account and its bump, derived off-chain
+    fn address(self, index: usize, gate: &Pubkey, mint: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+        let index = [index as u8];
+        let mut seeds: Vec<&[u8]> = vec![SCALE_SEED, &index];
+        if self == SeedKind::Dynamic {
+            seeds.push(mint.as_ref());
+            seeds.push(owner.as_ref());
+        }
+        Pubkey::find_program_address(&seeds, gate)
+    }
+}
+
+/// Mock FAMP: resolves the gate's extra accounts on-chain and CPIs into it
+///
+/// Accounts: the gate program, the interface accounts, then every account
+/// the extra-account-metas list may resolve to.
+fn famp_process(_program_id: &Pubkey, accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    let [gate, caller, token_account, mint, extra_metas, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    let mut instruction = interface::can_thaw_permissionless(
+        gate.key,
+        caller.key,
+        token_account.key,
+        mint.key,
+        extra_metas.key,
+        &[],
+    );
+    let mut infos = vec![
+        caller.clone(),
+        token_account.clone(),
+        mint.clone(),
+        extra_metas.clone(),
+    ];
+    ExtraAccountMetaList::add_to_cpi_instruction::<CanThawPermissionless>(
+        &mut instruction,
+        &mut infos,
+        &extra_metas.try_borrow_data()?,
+        accounts,
+    )?;
+    infos.push(gate.clone());
+    invoke(&instruction, &infos)
+}
+
+/// Probe gate: allows any thaw whose extra accounts match its list
+fn probe_gate_process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if data.get(..8) != Some(&CAN_THAW_PERMISSIONLESS_DISCRIMINATOR[..]) {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let extra_metas = accounts.get(3).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    ExtraAccountMetaList::check_account_infos::<CanThawPermissionless>(
+        accounts,
+        data,
+        program_id,
+        &extra_metas.try_borrow_data()?,
+    )
+}
+
+/// Versioned thaw extra-account-metas data holding `metas`
+fn thaw_extra_account_metas(metas: &[ExtraAccountMeta]) -> Vec<u8> {
+    let entry = TlvStateBorrowed::get_base_len() + GateInterfaceVersion::LEN;
+    let mut data = vec![0; entry + ExtraAccountMetaList::size_of(metas.len()).unwrap()];
+    {
+        let mut state = TlvStateMut::unpack(&mut data).unwrap();
+        let (value, _) = state
+            .alloc::<GateInterfaceVersion>(GateInterfaceVersion::LEN, false)
+            .unwrap();
+        value.copy_from_slice(&GateInterfaceVersion::CURRENT.pack());
+    }
+    ExtraAccountMetaList::init::<CanThawPermissionless>(&mut data, metas).unwrap();
+    data
+}
+
+/// Compute units the thaw check costs in the FAMP and the gate
+///
+/// Each extra account is derived twice, by the FAMP resolving it and by the
+/// gate validating it, and a derivation is charged once per bump tried. The
+/// CPI is charged its base cost plus the instruction and account data it
+/// carries.
+fn estimated_compute_units(bumps: &[u8], instruction: &Instruction, cpi_data_len: usize) -> u64 {
+    let budget = ComputeBudget::default();
+    let derivations: u64 = bumps
+        .iter()
+        .map(|bump| budget.create_program_address_units * (256 - u64::from(*bump)))
+        .sum();
+    let cpi = budget.invoke_units
+        + (instruction.data.len() + cpi_data_len) as u64 / budget.cpi_bytes_per_unit;
+    2 * derivations + cpi
+}
+
+/// A bank with the mock FAMP, the probe gate, one token account, and one
+/// mint per list benchmarked, each with its thaw extra-account-metas
+///
+/// Keys are fixed so that bumps, and with them compute units, are the same
+/// on every run.
+struct ScalingFixture {
+    context: ProgramTestContext,
+    famp: Pubkey,
+    gate: Pubkey,
+    owner: Pubkey,
+    token_account: Pubkey,
+}
+
+impl ScalingFixture {
+    async fn new() -> Self {
+        let famp = Pubkey::new_from_array([0xFA; 32]);
+        let gate = Pubkey::new_from_array([0x6A; 32]);
+        let owner = Pubkey::new_from_array([0x0E; 32]);
+        let holder_account = Pubkey::new_from_array([0x7A; 32]);
+
+        let mut program_test = ProgramTest::new("mock_famp", famp, processor!(famp_process));
+        program_test.add_program("probe_gate", gate, processor!(probe_gate_process));
+
+        program_test.add_account(
+            holder_account,
+            token_account(&Pubkey::default(), &owner, 1_000, AccountState::Frozen),
+        );
+
+        for kind in [SeedKind::Literal, SeedKind::Dynamic] {
+            for extra_accounts in 1..=MAX_EXTRA_ACCOUNTS {
+                let mint = Self::mint(kind, extra_accounts);
+                let metas: Vec<_> = (0..extra_accounts).map(|i| kind.meta(i)).collect();
+                program_test.add_account(
+                    interface::find_thaw_extra_account_metas_address(&mint, &gate).0,
+                    rent_exempt_account(thaw_extra_account_metas(&metas), gate),
+                );
+            }
+        }
+
+        Self {
+            context: program_test.start_with_context().await,
+            famp,
+            gate,
+            owner,
+            token_account: holder_account,
+        }
+    }
+
+    /// The mint whose list has `extra_accounts` entries of `kind`
+    fn mint(kind: SeedKind, extra_accounts: usize) -> Pubkey {
+        let mut key = [kind as u8 + 1; 32];
+        key[0] = extra_accounts as u8;
+        Pubkey::new_from_array(key)
+    }
+
+    /// Resolve, send and price one thaw check against the list with
+    /// `extra_accounts` entries of `kind`
+    async fn measure(
+        &mut self,
+        kind: SeedKind,
+        extra_accounts: usize,
+    ) -> Result<GateCheckCost, String> {
+        let mint = Self::mint(kind, extra_accounts);
+        let extra_metas = interface::find_thaw_extra_account_metas_address(&mint, &self.gate).0;
+        let payer = self.context.payer.pubkey();
+        let mut check = interface::can_thaw_permissionless(
+            &self.gate,
+            payer,
+            self.token_account,
+            mint,
+            extra_metas,
+            &[],
+        );
+
+        let fetches = Cell::new(0);
+        let banks_client = &self.context.banks_client;
+        interface::resolve_extra_account_metas(&mut check, |address| {
+            fetches.set(fetches.get() + 1);
+            let mut banks_client = banks_client.clone();
+            async move {
+                banks_client
+                    .get_account(address)
+                    .await
+                    .map(|account| account.map(|a| a.data))
+                    .map_err(|e| Box::new(e) as AccountFetchError)
+            }
+        })
+        .await
+        .map_err(|e| format!("Resolution failed: {}", e))?;
+
+        let derived: Vec<_> = (0..extra_accounts)
+            .map(|i| kind.address(i, &self.gate, &mint, &self.owner))
+            .collect();
+        let resolved: Vec<_> = check.accounts[4..].iter().map(|meta| meta.pubkey).collect();
+        if resolved
+            != derived
+                .iter()
+                .map(|(address, _)| *address)
+                .collect::<Vec<_>>()
+        {
+            return Err(format!(
+                "Resolved {:?}, derived {:?}",
+                resolved,
+                derived
+                    .iter()
+                    .map(|(address, _)| address)
+                    .collect::<Vec<_>>()
+            ));
+        }
+
+        let mut cpi_data_len = 0;
+        for meta in &check.accounts {
+            if let Ok(Some(account)) = self.context.banks_client.get_account(meta.pubkey).await {
+                cpi_data_len += account.data.len();
+            }
+        }
+        let bumps: Vec<u8> = derived.iter().map(|(_, bump)| *bump).collect();
+        let compute_units = estimated_compute_units(&bumps, &check, cpi_data_len);
+
+        let famp_accounts = [AccountMeta::new_readonly(self.gate, false)]
+            .into_iter()
+            .chain(check.accounts)
+            .collect();
+        let thaw = Instruction::new_with_bytes(self.famp, &[], famp_accounts);
+        let transaction = Transaction::new_signed_with_payer(
+            &[thaw],
+            Some(&payer),
+            &[&self.context.payer],
+            self.context.last_blockhash,
+        );
+        let transaction_size = signed_size(&transaction);
+        let transaction_accounts = transaction.message.account_keys.len();
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .map_err(|e| format!("Thaw check failed: {}", e))?;
+
+        Ok(GateCheckCost {
+            seeds: kind.name().to_string(),
+            extra_accounts,
+            transaction_accounts,
+            transaction_size,
+            account_fetches: fetches.get(),
+            compute_units,
+        })
+    }
+}
+
+/// Measure thaw checks from 1 to 20 extra accounts, literal and dynamic
+#[tokio::test]
+async fn benchmark_gate_cpi_account_scaling() {
+    let (report, gate_checks) = run_account_scaling_benchmark().await;
+
+    if !gate_checks.is_empty() {
+        if let Err(e) = costs::generate_cost_report(
+            &[],
+            &gate_checks,
+            "../../tests/reports/gate_check_costs.md",
+        ) {
+            panic!("Failed to generate gate check cost report: {}", e);
+        }
+    }
+    if let Err(e) = reporting::generate_test_report(
+        std::slice::from_ref(&report),
+        "Gate CPI Account Scaling Results",
+        "../../tests/reports/cpi_account_scaling.md",
+    ) {
+        panic!("Failed to generate account scaling report: {}", e);
+    }
+
+    assert!(
+        report.passed,
+        "Account scaling benchmark failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_account_scaling_benchmark() -> (TestResultReport, Vec<GateCheckCost>) {
+    let test_name = "Gate CPI Account Scaling";
+    let mut assertions = 0;
+    let mut fixture = ScalingFixture::new().await;
+
+    // Assertion 1: Every list resolves to the derived accounts, on-chain and
+    // off, and the gate accepts the check
+    assertions += 1;
+    let mut gate_checks = Vec::new();
+    for kind in [SeedKind::Literal, SeedKind::Dynamic] {
+        for extra_accounts in 1..=MAX_EXTRA_ACCOUNTS {
+            match fixture.measure(kind, extra_accounts).await {
+                Ok(cost) => gate_checks.push(cost),
+                Err(e) => {
+                    return (
+                        TestResultReport::failure(
+                            test_name,
+                            format!("{} seeds, {} accounts: {}", kind.name(), extra_accounts, e),
+                        ),
+                        gate_checks,
+                    )
+                }
+            }
+        }
+    }
+
+    // Assertion 2: Each extra account adds a lock, a key and an index byte,
+    // and at least two derivations
+    assertions += 1;
+    let derivation = ComputeBudget::default().create_program_address_units;
+    if let Some(check) = gate_checks.iter().find(|check| {
+        check.transaction_accounts != gate_checks[0].transaction_accounts + check.extra_accounts - 1
+            || check.transaction_size
+                != gate_checks[0].transaction_size + 33 * (check.extra_accounts - 1)
+            || check.compute_units < 2 * derivation * check.extra_accounts as u64
+    }) {
+        return (
+            TestResultReport::failure(
+                test_name,
+                format!("Cost did not grow with extra accounts: {:?}", check),
+            ),
+            gate_checks,
+        );
+    }
+
+    // Assertion 3: Seed kind changes neither the transaction nor what a
+    // client fetches, only the bumps
+    assertions += 1;
+    let (literal, dynamic) = gate_checks.split_at(MAX_EXTRA_ACCOUNTS);
+    if let Some((literal, dynamic)) = literal.iter().zip(dynamic).find(|(literal, dynamic)| {
+        literal.transaction_size != dynamic.transaction_size
+            || literal.transaction_accounts != dynamic.transaction_accounts
+            || literal.account_fetches != dynamic.account_fetches
+    }) {
+        return (
+            TestResultReport::failure(
+                test_name,
+                format!("Seed kinds differ: {:?} vs {:?}", literal, dynamic),
+            ),
+            gate_checks,
+        );
+    }
+
+    // Assertion 4: The largest lists still fit every ceiling
+    assertions += 1;
+    if let Some(check) = gate_checks.iter().find(|check| {
+        check.extra_accounts == MAX_EXTRA_ACCOUNTS
+            && (check.compute_headroom() == 0
+                || check.size_headroom() == 0
+                || check.account_headroom() == 0)
+    }) {
+        return (
+            TestResultReport::failure(
+                test_name,
+                format!("{} accounts hit a ceiling: {:?}", MAX_EXTRA_ACCOUNTS, check),
+            ),
+            gate_checks,
+        );
+    }
+
+    (
+        TestResultReport::success(test_name, assertions),
+        gate_checks,
+    )
+}
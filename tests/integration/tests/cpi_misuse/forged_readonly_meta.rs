@@ -0,0 +1,9 @@
+// `ReadonlyMeta`'s field is private, so it cannot be built around escalated flags
+
+use solana_program::{instruction::AccountMeta, pubkey::Pubkey};
+use token_acl_interface::ReadonlyMeta;
+
+fn main() {
+    let escalated = ReadonlyMeta(AccountMeta::new(Pubkey::new_unique(), true));
+    println!("{:?}", escalated);
+}
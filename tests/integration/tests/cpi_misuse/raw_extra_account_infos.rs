@@ -0,0 +1,15 @@
+// Extra accounts must be de-escalated into `ReadonlyAccount`s before the CPI
+
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult};
+use token_acl_interface::invoke_can_thaw_permissionless;
+
+fn forward<'a>(accounts: &[AccountInfo<'a>]) -> ProgramResult {
+    let [gate, caller, token_account, mint, extra_metas, extras @ ..] = accounts else {
+        return Ok(());
+    };
+    invoke_can_thaw_permissionless(gate, caller, token_account, mint, extra_metas, extras)
+}
+
+fn main() {
+    let _ = forward(&[]);
+}
@@ -0,0 +1,27 @@
+// The intended usage compiles: keys and account infos convert implicitly
+
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+use token_acl_interface::{
+    can_thaw_permissionless, invoke_can_freeze_permissionless, readonly_accounts, ReadonlyMeta,
+};
+
+fn forward<'a>(accounts: &[AccountInfo<'a>]) -> ProgramResult {
+    let [gate, caller, token_account, mint, extra_metas, extras @ ..] = accounts else {
+        return Ok(());
+    };
+    invoke_can_freeze_permissionless(
+        gate,
+        caller,
+        token_account,
+        mint,
+        extra_metas,
+        &readonly_accounts(extras),
+    )
+}
+
+fn main() {
+    let key = Pubkey::new_unique();
+    let instruction = can_thaw_permissionless(&key, key, &key, &key, &key, &[ReadonlyMeta::new(key)]);
+    assert!(instruction.accounts.iter().all(|meta| !meta.is_signer && !meta.is_writable));
+    let _ = forward(&[]);
+}
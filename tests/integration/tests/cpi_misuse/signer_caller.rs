@@ -0,0 +1,10 @@
+// There is no conversion from `AccountMeta`, so a signer caller is rejected
+
+use solana_program::{instruction::AccountMeta, pubkey::Pubkey};
+use token_acl_interface::can_freeze_permissionless;
+
+fn main() {
+    let key = Pubkey::new_unique();
+    let caller = AccountMeta::new_readonly(Pubkey::new_unique(), true);
+    can_freeze_permissionless(&key, caller, &key, &key, &key, &[]);
+}
@@ -0,0 +1,10 @@
+// Extra accounts must be `ReadonlyMeta`s, so a writable `AccountMeta` is rejected
+
+use solana_program::{instruction::AccountMeta, pubkey::Pubkey};
+use token_acl_interface::can_thaw_permissionless;
+
+fn main() {
+    let key = Pubkey::new_unique();
+    let record = AccountMeta::new(Pubkey::new_unique(), false);
+    can_thaw_permissionless(&key, &key, &key, &key, &key, &[record]);
+}
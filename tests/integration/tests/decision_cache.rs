@@ -0,0 +1,768 @@
+//! Decision cache tests
+//!
+//! These tests run the decision cache prototype with the production allow
+//! list in `solana-program-test`. An entry must only ever be written for a
+//! holder the gate approves, every list update must make it read as stale,
+//! and no entry, stale or fresh, may let a thaw through that the gate
+//! denies: the thaw always runs the gate's own check.
+
+use decision_cache::{
+    decision_address, DecisionEntry, Freshness, ObservedAccount, INVALIDATE, MAX_DECISION_AGE,
+    RECORD_DECISION,
+};
+use production_allow_list::{
+    allow_list_address, config_address, AccessLevel, AddToAllowListArgs, InitMode, RemoveMode,
+    ADD_TO_ALLOW_LIST, INITIALIZE, LIFT_REVOCATION, REMOVE_FROM_ALLOW_LIST, REVOKE,
+    SET_MISSING_RECORD_POLICY,
+};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    clock::Clock,
+    instruction::{AccountMeta, Instruction},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::Transaction,
+};
+use spl_token_2022::{
+    instruction as token_instruction,
+    state::{Account as TokenAccount, AccountState},
+};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use token_acl_integration_tests::{
+    program_test::{mint_account, token_account},
+    reporting, TestResultReport,
+};
+use token_acl_interface::{self as interface, find_missing_record_policy_address};
+
+const HOLDERS: usize = 3;
+
+/// A bank with the cache and the allow list gate, a mint whose freeze
+/// authority is the issuer, and a frozen token account per holder; holders
+/// 0 and 1 are listed, holder 2 is not. A second allow list deployment for
+/// the same mint lets holders without a record through.
+struct CacheFixture {
+    context: ProgramTestContext,
+    cache: Pubkey,
+    gate: Pubkey,
+    permissive: Pubkey,
+    issuer: Keypair,
+    mint: Pubkey,
+    /// (owner, token account)
+    holders: Vec<(Pubkey, Pubkey)>,
+}
+
+impl CacheFixture {
+    async fn new() -> Self {
+        let cache = Pubkey::new_unique();
+        let gate = Pubkey::new_unique();
+        let permissive = Pubkey::new_unique();
+        let issuer = Keypair::new();
+        let mint = Pubkey::new_unique();
+
+        let mut program_test = ProgramTest::new(
+            "decision_cache",
+            cache,
+            processor!(decision_cache::process_instruction),
+        );
+        program_test.add_program(
+            "production_allow_list",
+            gate,
+            processor!(production_allow_list::process_instruction),
+        );
+        program_test.add_program(
+            "production_allow_list",
+            permissive,
+            processor!(production_allow_list::process_instruction),
+        );
+
+        program_test.add_account(mint, mint_account(&issuer.pubkey(), 0));
+
+        let mut holders = Vec::new();
+        for _ in 0..HOLDERS {
+            let owner = Pubkey::new_unique();
+            let address = Pubkey::new_unique();
+            program_test.add_account(
+                address,
+                token_account(&mint, &owner, 0, AccountState::Frozen),
+            );
+            holders.push((owner, address));
+        }
+
+        let mut fixture = Self {
+            context: program_test.start_with_context().await,
+            cache,
+            gate,
+            permissive,
+            issuer,
+            mint,
+            holders,
+        };
+
+        let setup = vec![
+            fixture.initialize_instruction(gate),
+            fixture.initialize_instruction(permissive),
+            fixture.add_instruction(0, None),
+            fixture.add_instruction(1, None),
+            fixture.set_policy_instruction(permissive, 1),
+        ];
+        fixture
+            .send(&setup, &[])
+            .await
+            .expect("gate setup should succeed");
+        fixture
+    }
+
+    fn owner(&self, holder: usize) -> Pubkey {
+        self.holders[holder].0
+    }
+
+    fn record(&self, holder: usize) -> Pubkey {
+        allow_list_address(&self.mint, &self.owner(holder), &self.gate).address()
+    }
+
+    fn entry(&self, holder: usize) -> Pubkey {
+        decision_address(&self.gate, &self.mint, &self.owner(holder), &self.cache).address()
+    }
+
+    fn policy(&self) -> Pubkey {
+        find_missing_record_policy_address(&self.mint, &self.gate).0
+    }
+
+    /// The gate's extra accounts for a holder, in the order it reads them
+    fn extra_accounts(&self, holder: usize) -> Vec<Pubkey> {
+        vec![self.owner(holder), self.record(holder), self.policy()]
+    }
+
+    fn initialize_instruction(&self, gate: Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            gate,
+            &[INITIALIZE],
+            vec![
+                AccountMeta::new(config_address(&self.mint, &gate).address(), false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    fn add_instruction(&self, holder: usize, expiry_timestamp: Option<i64>) -> Instruction {
+        let mut data = vec![ADD_TO_ALLOW_LIST, InitMode::Create as u8];
+        data.extend(
+            AddToAllowListArgs {
+                access_level: AccessLevel::Basic,
+                expiry_timestamp,
+            }
+            .try_to_vec()
+            .unwrap(),
+        );
+        Instruction::new_with_bytes(
+            self.gate,
+            &data,
+            vec![
+                AccountMeta::new_readonly(config_address(&self.mint, &self.gate).address(), false),
+                AccountMeta::new(self.record(holder), false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(self.owner(holder), false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// A record instruction of the gate that takes config, record and
+    /// authority: deactivate, close, revoke or lift a revocation
+    fn manage_instruction(&self, holder: usize, data: &[u8]) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new_readonly(config_address(&self.mint, &self.gate).address(), false),
+            AccountMeta::new(self.record(holder), false),
+            AccountMeta::new_readonly(self.issuer.pubkey(), true),
+        ];
+        if data == [REMOVE_FROM_ALLOW_LIST, RemoveMode::Close as u8] {
+            accounts.push(AccountMeta::new(self.issuer.pubkey(), false));
+        }
+        Instruction::new_with_bytes(self.gate, data, accounts)
+    }
+
+    fn set_policy_instruction(&self, gate: Pubkey, policy: u8) -> Instruction {
+        Instruction::new_with_bytes(
+            gate,
+            &[SET_MISSING_RECORD_POLICY, policy],
+            vec![
+                AccountMeta::new_readonly(config_address(&self.mint, &gate).address(), false),
+                AccountMeta::new(
+                    find_missing_record_policy_address(&self.mint, &gate).0,
+                    false,
+                ),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// `RecordDecision` for a holder through `gate`, writing to `entry`
+    fn record_instruction(&self, holder: usize, gate: Pubkey, entry: Pubkey) -> Instruction {
+        let (owner, token_account) = self.holders[holder];
+        let mut accounts = vec![
+            AccountMeta::new(entry, false),
+            AccountMeta::new(self.context.payer.pubkey(), true),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(gate, false),
+            AccountMeta::new_readonly(owner, false),
+            AccountMeta::new_readonly(token_account, false),
+            AccountMeta::new_readonly(self.mint, false),
+            AccountMeta::new_readonly(
+                interface::find_thaw_extra_account_metas_address(&self.mint, &gate).0,
+                false,
+            ),
+        ];
+        accounts.extend(
+            [
+                owner,
+                allow_list_address(&self.mint, &owner, &gate).address(),
+                find_missing_record_policy_address(&self.mint, &gate).0,
+            ]
+            .map(|key| AccountMeta::new_readonly(key, false)),
+        );
+        Instruction::new_with_bytes(self.cache, &[RECORD_DECISION], accounts)
+    }
+
+    fn invalidate_instruction(&self, holder: usize, extra_accounts: &[Pubkey]) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new(self.entry(holder), false),
+            AccountMeta::new(self.context.payer.pubkey(), false),
+        ];
+        accounts.extend(
+            extra_accounts
+                .iter()
+                .map(|key| AccountMeta::new_readonly(*key, false)),
+        );
+        Instruction::new_with_bytes(self.cache, &[INVALIDATE], accounts)
+    }
+
+    /// The gate's check for a holder's account followed, in the same
+    /// transaction, by the thaw the FAMP makes on its approval
+    fn thaw_instructions(&self, holder: usize) -> Vec<Instruction> {
+        let (owner, token_account) = self.holders[holder];
+        let check = interface::can_thaw_permissionless(
+            &self.gate,
+            owner,
+            token_account,
+            self.mint,
+            interface::find_thaw_extra_account_metas_address(&self.mint, &self.gate).0,
+            &self
+                .extra_accounts(holder)
+                .into_iter()
+                .map(Into::into)
+                .collect::<Vec<_>>(),
+        );
+        let thaw = token_instruction::thaw_account(
+            &spl_token_2022::id(),
+            &token_account,
+            &self.mint,
+            &self.issuer.pubkey(),
+            &[],
+        )
+        .unwrap();
+        vec![check, thaw]
+    }
+
+    fn transaction(
+        &self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+        blockhash: solana_sdk::hash::Hash,
+    ) -> Transaction {
+        let mut all_signers = vec![&self.context.payer];
+        let issuer_signs = instructions
+            .iter()
+            .flat_map(|instruction| &instruction.accounts)
+            .any(|meta| meta.is_signer && meta.pubkey == self.issuer.pubkey());
+        if issuer_signs {
+            all_signers.push(&self.issuer);
+        }
+        all_signers.extend_from_slice(signers);
+        Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.context.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        )
+    }
+
+    async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        // A fresh blockhash keeps repeated transactions from being deduplicated
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let transaction = self.transaction(instructions, signers, blockhash);
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+    }
+
+    /// Whether `instructions` would succeed
+    async fn simulate(&mut self, instructions: &[Instruction]) -> bool {
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let transaction = self.transaction(instructions, &[], blockhash);
+        let simulation = self
+            .context
+            .banks_client
+            .simulate_transaction(transaction)
+            .await
+            .unwrap();
+        simulation.result.is_some_and(|result| result.is_ok())
+    }
+
+    /// Whether the gate would let a holder's account thaw right now
+    async fn gate_approves(&mut self, holder: usize) -> bool {
+        let instructions = self.thaw_instructions(holder);
+        self.simulate(&instructions).await
+    }
+
+    async fn account(&mut self, address: Pubkey) -> Option<Account> {
+        self.context
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+    }
+
+    async fn clock(&mut self) -> Clock {
+        self.context
+            .banks_client
+            .get_sysvar::<Clock>()
+            .await
+            .unwrap()
+    }
+
+    async fn decision(&mut self, holder: usize) -> Option<DecisionEntry> {
+        let account = self.account(self.entry(holder)).await?;
+        Some(DecisionEntry::try_from_slice(&account.data).unwrap())
+    }
+
+    /// What a wallet reading a holder's entry would conclude, as of the
+    /// bank's clock; `None` if there is no entry
+    async fn freshness(&mut self, holder: usize, max_age: i64) -> Option<Freshness> {
+        let entry = self.decision(holder).await?;
+        let keys = self.extra_accounts(holder);
+        let mut accounts = Vec::new();
+        for key in &keys {
+            accounts.push(self.account(*key).await);
+        }
+        let observed: Vec<ObservedAccount> = keys
+            .iter()
+            .zip(&accounts)
+            .map(|(key, account)| match account {
+                Some(account) => ObservedAccount::new(key, &account.owner, &account.data),
+                None => ObservedAccount::missing(key),
+            })
+            .collect();
+        let now = self.clock().await.unix_timestamp;
+        Some(entry.freshness(&observed, now, max_age))
+    }
+
+    async fn token_state(&mut self, holder: usize) -> AccountState {
+        let account = self.account(self.holders[holder].1).await.unwrap();
+        TokenAccount::unpack(&account.data).unwrap().state
+    }
+}
+
+/// Test 1: Entries are written only for holders the gate approves, at the
+/// canonical address for the gate that approved
+#[tokio::test]
+async fn test_record_requires_approval() {
+    let report = run_record_requires_approval_test().await;
+    assert!(
+        report.passed,
+        "Record requires approval test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_record_requires_approval_test() -> TestResultReport {
+    let test_name = "Record Requires Approval";
+    let mut assertions = 0;
+    let mut fixture = CacheFixture::new().await;
+
+    // Assertion 1: A listed holder's approval is cached, fresh, for the
+    // token account's owner
+    assertions += 1;
+    let record = fixture.record_instruction(0, fixture.gate, fixture.entry(0));
+    if let Err(e) = fixture.send(&[record], &[]).await {
+        return TestResultReport::failure(test_name, format!("Record failed: {}", e));
+    }
+    let entry = fixture.decision(0).await;
+    let freshness = fixture.freshness(0, MAX_DECISION_AGE).await;
+    let written = entry.as_ref().is_some_and(|entry| {
+        entry.owner == fixture.owner(0)
+            && entry.gate_program == fixture.gate
+            && entry.mint == fixture.mint
+            && entry.payer == fixture.context.payer.pubkey()
+    });
+    if !written || freshness != Some(Freshness::Fresh) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Entry {:?} is {:?}", entry, freshness),
+        );
+    }
+
+    // Assertion 2: An unlisted holder's denial writes nothing
+    assertions += 1;
+    let record = fixture.record_instruction(2, fixture.gate, fixture.entry(2));
+    if fixture.send(&[record], &[]).await.is_ok() || fixture.decision(2).await.is_some() {
+        return TestResultReport::failure(
+            test_name,
+            "A denied holder's decision was cached".to_string(),
+        );
+    }
+
+    // Assertion 3: An approval cannot be written at another holder's
+    // address, which would let an approved owner vouch for a denied one
+    assertions += 1;
+    let record = fixture.record_instruction(1, fixture.gate, fixture.entry(2));
+    if fixture.send(&[record], &[]).await.is_ok() || fixture.decision(2).await.is_some() {
+        return TestResultReport::failure(
+            test_name,
+            "An approval was cached for another holder".to_string(),
+        );
+    }
+
+    // Assertion 4: An approval from a gate that lets everyone through lands
+    // at that gate's address, never at the mint's gate's
+    assertions += 1;
+    let permissive = fixture.permissive;
+    let record = fixture.record_instruction(2, permissive, fixture.entry(2));
+    if fixture.send(&[record], &[]).await.is_ok() || fixture.decision(2).await.is_some() {
+        return TestResultReport::failure(
+            test_name,
+            "Another gate's decision was cached for the mint's gate".to_string(),
+        );
+    }
+    let elsewhere = decision_address(
+        &permissive,
+        &fixture.mint,
+        &fixture.owner(2),
+        &fixture.cache,
+    )
+    .address();
+    let record = fixture.record_instruction(2, permissive, elsewhere);
+    if let Err(e) = fixture.send(&[record], &[]).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("Permissive gate's approval was not cached: {}", e),
+        );
+    }
+    if fixture.decision(2).await.is_some() {
+        return TestResultReport::failure(
+            test_name,
+            "Permissive gate's approval landed at the mint's gate's address".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: Every list update makes a holder's entry stale, and only a stale
+/// entry can be invalidated
+#[tokio::test]
+async fn test_list_updates_invalidate() {
+    let report = run_list_updates_invalidate_test().await;
+    assert!(
+        report.passed,
+        "List updates invalidate test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_list_updates_invalidate_test() -> TestResultReport {
+    let test_name = "List Updates Invalidate";
+    let mut assertions = 0;
+
+    let updates: [(&str, Vec<u8>); 3] = [
+        (
+            "deactivation",
+            vec![REMOVE_FROM_ALLOW_LIST, RemoveMode::Deactivate as u8],
+        ),
+        (
+            "closing the record",
+            vec![REMOVE_FROM_ALLOW_LIST, RemoveMode::Close as u8],
+        ),
+        ("revocation", vec![REVOKE]),
+    ];
+    for (update, data) in updates {
+        let mut fixture = CacheFixture::new().await;
+        let record = fixture.record_instruction(0, fixture.gate, fixture.entry(0));
+        if let Err(e) = fixture.send(&[record], &[]).await {
+            return TestResultReport::failure(test_name, format!("Record failed: {}", e));
+        }
+
+        // Assertion: A fresh entry cannot be invalidated
+        assertions += 1;
+        let invalidate = fixture.invalidate_instruction(0, &fixture.extra_accounts(0));
+        if fixture.send(&[invalidate], &[]).await.is_ok() {
+            return TestResultReport::failure(
+                test_name,
+                "A fresh entry was invalidated".to_string(),
+            );
+        }
+
+        let manage = fixture.manage_instruction(0, &data);
+        if let Err(e) = fixture.send(&[manage], &[]).await {
+            return TestResultReport::failure(test_name, format!("{} failed: {}", update, e));
+        }
+
+        // Assertion: The update shows as a list change
+        assertions += 1;
+        let freshness = fixture.freshness(0, MAX_DECISION_AGE).await;
+        if freshness != Some(Freshness::ListChanged) {
+            return TestResultReport::failure(
+                test_name,
+                format!("Entry is {:?} after {}", freshness, update),
+            );
+        }
+
+        // Assertion: The gate no longer approves, so the entry cannot be
+        // refreshed
+        assertions += 1;
+        let record = fixture.record_instruction(0, fixture.gate, fixture.entry(0));
+        if fixture.gate_approves(0).await || fixture.send(&[record], &[]).await.is_ok() {
+            return TestResultReport::failure(
+                test_name,
+                format!("Holder still approved after {}", update),
+            );
+        }
+
+        // Assertion: Invalidating with other accounts is refused, even
+        // though their state differs
+        assertions += 1;
+        let mut others = fixture.extra_accounts(0);
+        others[1] = fixture.record(1);
+        let invalidate = fixture.invalidate_instruction(0, &others);
+        if fixture.send(&[invalidate], &[]).await.is_ok() {
+            return TestResultReport::failure(
+                test_name,
+                "Entry invalidated with other accounts".to_string(),
+            );
+        }
+
+        // Assertion: With the recorded accounts it closes, refunding the
+        // payer
+        assertions += 1;
+        let payer = fixture.context.payer.pubkey();
+        let rent = fixture.account(fixture.entry(0)).await.unwrap().lamports;
+        let before = fixture.account(payer).await.unwrap().lamports;
+        let invalidate = fixture.invalidate_instruction(0, &fixture.extra_accounts(0));
+        if let Err(e) = fixture.send(&[invalidate], &[]).await {
+            return TestResultReport::failure(test_name, format!("Invalidate failed: {}", e));
+        }
+        let after = fixture.account(payer).await.unwrap().lamports;
+        if fixture.decision(0).await.is_some() || after + 5_000 != before + rent {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "After invalidation: payer {} -> {}, rent {}",
+                    before, after, rent
+                ),
+            );
+        }
+    }
+
+    // Assertion: A new missing-record policy is a list update too for a
+    // holder the gate approved without a record
+    assertions += 1;
+    let mut fixture = CacheFixture::new().await;
+    let allow_missing = fixture.set_policy_instruction(fixture.gate, 1);
+    if let Err(e) = fixture.send(&[allow_missing], &[]).await {
+        return TestResultReport::failure(test_name, format!("Policy update failed: {}", e));
+    }
+    let record = fixture.record_instruction(2, fixture.gate, fixture.entry(2));
+    if let Err(e) = fixture.send(&[record], &[]).await {
+        return TestResultReport::failure(test_name, format!("Record failed: {}", e));
+    }
+    let deny_missing = fixture.set_policy_instruction(fixture.gate, 0);
+    if let Err(e) = fixture.send(&[deny_missing], &[]).await {
+        return TestResultReport::failure(test_name, format!("Policy update failed: {}", e));
+    }
+    let freshness = fixture.freshness(2, MAX_DECISION_AGE).await;
+    if freshness != Some(Freshness::ListChanged) || fixture.gate_approves(2).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("Entry is {:?} after the policy changed", freshness),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: Through a sequence of list updates, an entry that reads fresh
+/// always agrees with the gate, and a thaw never goes through on an entry
+/// alone
+#[tokio::test]
+async fn test_staleness_never_grants_thaw() {
+    let report = run_staleness_never_grants_thaw_test().await;
+    assert!(
+        report.passed,
+        "Staleness never grants thaw test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_staleness_never_grants_thaw_test() -> TestResultReport {
+    let test_name = "Staleness Never Grants Thaw";
+    let mut assertions = 0;
+    let mut fixture = CacheFixture::new().await;
+
+    let record = fixture.record_instruction(0, fixture.gate, fixture.entry(0));
+    if let Err(e) = fixture.send(&[record], &[]).await {
+        return TestResultReport::failure(test_name, format!("Record failed: {}", e));
+    }
+
+    // Each step changes the list, then tries to refresh the entry the way
+    // the FAMP would after a check
+    let steps: [(&str, Instruction); 6] = [
+        (
+            "deactivate",
+            fixture.manage_instruction(0, &[REMOVE_FROM_ALLOW_LIST, RemoveMode::Deactivate as u8]),
+        ),
+        ("revoke", fixture.manage_instruction(0, &[REVOKE])),
+        ("lift", fixture.manage_instruction(0, &[LIFT_REVOCATION])),
+        (
+            "close",
+            fixture.manage_instruction(0, &[REMOVE_FROM_ALLOW_LIST, RemoveMode::Close as u8]),
+        ),
+        ("re-add", fixture.add_instruction(0, None)),
+        (
+            "deactivate again",
+            fixture.manage_instruction(0, &[REMOVE_FROM_ALLOW_LIST, RemoveMode::Deactivate as u8]),
+        ),
+    ];
+    for (step, instruction) in steps {
+        if let Err(e) = fixture.send(&[instruction], &[]).await {
+            return TestResultReport::failure(test_name, format!("{} failed: {}", step, e));
+        }
+        let approves = fixture.gate_approves(0).await;
+
+        // Assertion: A fresh entry implies the gate approves
+        assertions += 1;
+        let freshness = fixture.freshness(0, MAX_DECISION_AGE).await;
+        if freshness == Some(Freshness::Fresh) && !approves {
+            return TestResultReport::failure(
+                test_name,
+                format!("Entry reads fresh after {} but the gate denies", step),
+            );
+        }
+
+        // Assertion: The thaw follows the gate, whatever the entry says
+        assertions += 1;
+        let thaw = fixture.thaw_instructions(0);
+        let thawed = fixture.send(&thaw, &[]).await.is_ok();
+        if thawed != approves || fixture.token_state(0).await != thawed_state(thawed) {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "After {}: gate approves {}, thaw succeeded {}",
+                    step, approves, thawed
+                ),
+            );
+        }
+        if thawed {
+            // Refreeze for the next step
+            let freeze = token_instruction::freeze_account(
+                &spl_token_2022::id(),
+                &fixture.holders[0].1,
+                &fixture.mint,
+                &fixture.issuer.pubkey(),
+                &[],
+            )
+            .unwrap();
+            fixture.send(&[freeze], &[]).await.unwrap();
+        }
+
+        let record = fixture.record_instruction(0, fixture.gate, fixture.entry(0));
+        let refreshed = fixture.send(&[record], &[]).await.is_ok();
+        if refreshed != approves {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "After {}: gate approves {}, refresh succeeded {}",
+                    step, approves, refreshed
+                ),
+            );
+        }
+    }
+
+    // Assertion: An expiry passing changes no account, so only the age
+    // bound catches it; the entry reads fresh within it, but the thaw
+    // still follows the gate and fails
+    assertions += 1;
+    let mut fixture = CacheFixture::new().await;
+    let clock = fixture.clock().await;
+    let expiring = fixture.add_instruction(2, Some(clock.unix_timestamp + 60));
+    let record = fixture.record_instruction(2, fixture.gate, fixture.entry(2));
+    if let Err(e) = fixture.send(&[expiring, record], &[]).await {
+        return TestResultReport::failure(test_name, format!("Setup failed: {}", e));
+    }
+    fixture.context.set_sysvar(&Clock {
+        unix_timestamp: clock.unix_timestamp + 61,
+        ..clock.clone()
+    });
+    let within_age = fixture.freshness(2, MAX_DECISION_AGE).await;
+    let short_age = fixture.freshness(2, 30).await;
+    let thaw = fixture.thaw_instructions(2);
+    let thawed = fixture.send(&thaw, &[]).await.is_ok();
+    if within_age != Some(Freshness::Fresh)
+        || short_age != Some(Freshness::TooOld)
+        || thawed
+        || fixture.token_state(2).await != AccountState::Frozen
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Expired holder: {:?} within the default age, {:?} within 30s, thawed {}",
+                within_age, short_age, thawed
+            ),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+fn thawed_state(thawed: bool) -> AccountState {
+    if thawed {
+        AccountState::Initialized
+    } else {
+        AccountState::Frozen
+    }
+}
+
+/// Generate decision cache test report
+#[tokio::test]
+async fn generate_decision_cache_report() {
+    let results = vec![
+        run_record_requires_approval_test().await,
+        run_list_updates_invalidate_test().await,
+        run_staleness_never_grants_thaw_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Decision Cache Test Results",
+        "../../tests/reports/decision_cache.md",
+    ) {
+        panic!("Failed to generate decision cache report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} decision cache tests failed", failed);
+}
@@ -0,0 +1,487 @@
+//! Decision event tests
+//!
+//! The production allow list logs each `can-thaw-permissionless` decision
+//! as a `ThawDecision` event with `sol_log_data`. These tests check that it
+//! logs the right decision for approvals and denials, and that both kinds
+//! of consumers read it from the `Program data:` lines the runtime writes:
+//! this suite's decoder, which reads the native layout and Anchor's, and an
+//! Anchor-style parser that knows nothing of Token ACL beyond the event's
+//! IDL.
+//!
+//! Native program-test prints `sol_log_data` to stdout instead of the
+//! transaction logs, so the events are captured by wrapping its syscall
+//! stubs, and written as log lines with the runtime's own
+//! `stable_log::program_data`.
+
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::{Mutex, Once},
+};
+use tokio::sync::{Mutex as AsyncMutex, MutexGuard};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use borsh::BorshDeserialize;
+use sha2::{Digest, Sha256};
+use solana_program_runtime::{log_collector::LogCollector, stable_log};
+use solana_sdk::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::Instruction,
+    program_stubs::{self, SyscallStubs},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_token_2022::state::AccountState;
+
+use token_acl_integration_tests::{
+    decoder::{self, Decoder, ProgramKind},
+    program_test::{Gate, GateHarness},
+    reporting, TestResultReport,
+};
+use token_acl_interface::events::{DecisionReason, EventFormat, ThawDecision};
+
+/// Fields of every `sol_log_data` call since the stubs were installed
+static LOGGED_DATA: Mutex<Vec<Vec<Vec<u8>>>> = Mutex::new(Vec::new());
+
+/// Program-test's syscall stubs, with `sol_log_data` captured
+struct CapturingStubs(Box<dyn SyscallStubs>);
+
+impl SyscallStubs for CapturingStubs {
+    fn sol_log(&self, message: &str) {
+        self.0.sol_log(message)
+    }
+    fn sol_log_compute_units(&self) {
+        self.0.sol_log_compute_units()
+    }
+    fn sol_remaining_compute_units(&self) -> u64 {
+        self.0.sol_remaining_compute_units()
+    }
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        self.0
+            .sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_clock_sysvar(var_addr)
+    }
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_epoch_schedule_sysvar(var_addr)
+    }
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_fees_sysvar(var_addr)
+    }
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_rent_sysvar(var_addr)
+    }
+    fn sol_get_epoch_rewards_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_epoch_rewards_sysvar(var_addr)
+    }
+    fn sol_get_last_restart_slot(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_last_restart_slot(var_addr)
+    }
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        self.0.sol_get_return_data()
+    }
+    fn sol_set_return_data(&self, data: &[u8]) {
+        self.0.sol_set_return_data(data)
+    }
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        LOGGED_DATA
+            .lock()
+            .expect("no test panics while holding the log")
+            .push(fields.iter().map(|field| field.to_vec()).collect());
+    }
+    fn sol_get_processed_sibling_instruction(&self, index: usize) -> Option<Instruction> {
+        self.0.sol_get_processed_sibling_instruction(index)
+    }
+    fn sol_get_stack_height(&self) -> u64 {
+        self.0.sol_get_stack_height()
+    }
+}
+
+/// Placeholder while program-test's stubs are taken out to be wrapped
+struct NoStubs;
+
+impl SyscallStubs for NoStubs {}
+
+/// Held by each test running a bank, so none runs while the stubs are
+/// swapped
+static BANK: AsyncMutex<()> = AsyncMutex::const_new(());
+
+/// A harness whose gates' events are captured, and the lock on running it
+///
+/// Program-test installs its stubs once, when the first bank starts; the
+/// capturing stubs wrap them after that.
+async fn start(test_name: &str) -> Result<(GateHarness, MutexGuard<'static, ()>), String> {
+    let guard = BANK.lock().await;
+    let harness = GateHarness::start(test_name).await?;
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let stubs = program_stubs::set_syscall_stubs(Box::new(NoStubs));
+        program_stubs::set_syscall_stubs(Box::new(CapturingStubs(stubs)));
+    });
+    Ok((harness, guard))
+}
+
+/// The thaw decisions about `mint` captured so far, in either format
+fn captured_decisions(mint: &Pubkey) -> Vec<(EventFormat, ThawDecision)> {
+    LOGGED_DATA
+        .lock()
+        .expect("no test panics while holding the log")
+        .iter()
+        .filter_map(|fields| ThawDecision::from_log_fields(fields))
+        .filter(|(_, decision)| decision.mint == *mint)
+        .collect()
+}
+
+/// `fields` as the runtime writes them to a transaction's logs
+fn program_data_line(fields: &[Vec<u8>]) -> String {
+    let collector = Rc::new(RefCell::new(LogCollector::default()));
+    let fields: Vec<&[u8]> = fields.iter().map(Vec::as_slice).collect();
+    stable_log::program_data(&Some(collector.clone()), &fields);
+    let lines = collector.borrow().get_recorded_content().concat();
+    lines
+}
+
+/// The event as an Anchor program declares it
+///
+/// `#[event] pub struct ThawDecision { mint: Pubkey, owner: Pubkey,
+/// approved: bool, reason: u8 }`
+#[derive(Debug, BorshDeserialize)]
+struct AnchorThawDecision {
+    mint: Pubkey,
+    owner: Pubkey,
+    approved: bool,
+    reason: u8,
+}
+
+/// The `ThawDecision` events in `logs`, read the way Anchor's event parser
+/// reads them: a `Program data:` line is one base64 value starting with
+/// `sha256("event:<Name>")[..8]`, and lines that are not are skipped
+fn anchor_events(logs: &[String]) -> Vec<AnchorThawDecision> {
+    let discriminator = &Sha256::digest(b"event:ThawDecision")[..8];
+    logs.iter()
+        .filter_map(|line| line.strip_prefix("Program data: "))
+        .filter_map(|data| BASE64.decode(data).ok())
+        .filter_map(|data| {
+            let event = data.strip_prefix(discriminator)?;
+            AnchorThawDecision::try_from_slice(event).ok()
+        })
+        .collect()
+}
+
+/// Run a thaw check of `owner`'s `token_account` through the production
+/// allow list, whatever its outcome
+async fn check(
+    harness: &mut GateHarness,
+    token_account: &Pubkey,
+    owner: &Pubkey,
+) -> (Transaction, Result<(), TransactionError>, Vec<String>) {
+    let caller = Pubkey::new_unique();
+    let check = harness.can_thaw(Gate::ProductionAllowList, &caller, token_account, owner);
+    let blockhash = harness
+        .context
+        .get_new_latest_blockhash()
+        .await
+        .expect("the bank should hand out a blockhash");
+    let payer = harness.context.payer.insecure_clone();
+    let transaction =
+        Transaction::new_signed_with_payer(&[check], Some(&payer.pubkey()), &[&payer], blockhash);
+    let outcome = harness
+        .context
+        .banks_client
+        .process_transaction_with_metadata(transaction.clone())
+        .await
+        .expect("the bank should process the transaction");
+    let logs = outcome
+        .metadata
+        .map(|metadata| metadata.log_messages)
+        .unwrap_or_default();
+    (transaction, outcome.result, logs)
+}
+
+/// A listed holder, a deactivated one, and one the gate never heard of,
+/// each with a frozen token account
+struct Holders {
+    listed: (Pubkey, Pubkey),
+    deactivated: (Pubkey, Pubkey),
+    unknown: (Pubkey, Pubkey),
+}
+
+async fn holders(harness: &mut GateHarness) -> Result<Holders, String> {
+    let issuer = harness.issuer.pubkey();
+    let mut holder = || {
+        let owner = Pubkey::new_unique();
+        (
+            owner,
+            harness.create_token_account(&owner, AccountState::Frozen),
+        )
+    };
+    let holders = Holders {
+        listed: holder(),
+        deactivated: holder(),
+        unknown: holder(),
+    };
+    let add_listed = harness.add_to_allow_list(&holders.listed.0, &issuer, true);
+    let add_deactivated = harness.add_to_allow_list(&holders.deactivated.0, &issuer, true);
+    let deactivate = harness.remove_from_allow_list(&holders.deactivated.0, &issuer, None);
+    harness
+        .send(
+            &[add_listed, add_deactivated, deactivate],
+            &[] as &[&Keypair],
+        )
+        .await
+        .map_err(|e| format!("Listing failed: {:?}", e))?;
+    Ok(holders)
+}
+
+/// Test 1: The gate logs one decision per check, approving or denying with
+/// the reason it decided on, in its configured format
+#[tokio::test]
+async fn test_gate_logs_thaw_decisions() {
+    let report = run_gate_logs_thaw_decisions_test().await;
+    assert!(
+        report.passed,
+        "Gate logs thaw decisions test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_gate_logs_thaw_decisions_test() -> TestResultReport {
+    let test_name = "Decision Events: Gate Logs Thaw Decisions";
+    let mut assertions = 0;
+
+    let (mut harness, _bank) = match start(test_name).await {
+        Ok(started) => started,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let holders = match holders(&mut harness).await {
+        Ok(holders) => holders,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+
+    let cases = [
+        (holders.listed, DecisionReason::Listed),
+        (holders.deactivated, DecisionReason::NotAllowed),
+        (holders.unknown, DecisionReason::NotListed),
+    ];
+    for ((owner, token_account), reason) in cases {
+        let before = captured_decisions(&harness.mint).len();
+        let (_, result, _) = check(&mut harness, &token_account, &owner).await;
+
+        // Assertion: The check logged exactly one decision, about the owner,
+        // that agrees with how the check ended
+        assertions += 1;
+        let decisions = captured_decisions(&harness.mint);
+        let expected = ThawDecision::new(harness.mint, owner, reason);
+        if decisions[before..] != [(production_allow_list::EVENT_FORMAT, expected)] {
+            return TestResultReport::failure(
+                test_name,
+                format!("Expected {:?}, logged {:?}", expected, &decisions[before..]),
+            );
+        }
+        if result.is_ok() != expected.approved {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "{} decision, but the check ended {:?}",
+                    reason.name(),
+                    result
+                ),
+            );
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: The decoder and an Anchor-style event parser both read a
+/// decision from the `Program data:` line of its format, and the Anchor
+/// parser skips the native layout rather than misreading it
+#[test]
+fn test_native_and_anchor_consumers_read_decisions() {
+    let report = run_native_and_anchor_consumers_read_decisions_test();
+    assert!(
+        report.passed,
+        "Native and Anchor consumers read decisions test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_native_and_anchor_consumers_read_decisions_test() -> TestResultReport {
+    let test_name = "Decision Events: Native and Anchor Consumers";
+    let mut assertions = 0;
+
+    let mint = Pubkey::new_unique();
+    let approved = ThawDecision::new(mint, Pubkey::new_unique(), DecisionReason::Listed);
+    let denied = ThawDecision::new(mint, Pubkey::new_unique(), DecisionReason::Revoked);
+    let native: Vec<String> = [approved, denied]
+        .iter()
+        .map(|decision| program_data_line(&decision.log_fields(EventFormat::Native)))
+        .collect();
+    let anchor: Vec<String> = [approved, denied]
+        .iter()
+        .map(|decision| program_data_line(&decision.log_fields(EventFormat::Anchor)))
+        .collect();
+
+    // Assertion 1: The decoder reads both layouts
+    assertions += 1;
+    for (format, logs) in [
+        (EventFormat::Native, &native),
+        (EventFormat::Anchor, &anchor),
+    ] {
+        let read = decoder::thaw_decisions(logs);
+        if read != [(format, approved), (format, denied)] {
+            return TestResultReport::failure(
+                test_name,
+                format!("Decoder read {:?} from {:?}", read, logs),
+            );
+        }
+    }
+
+    // Assertion 2: The Anchor parser reads the Anchor layout field for field
+    assertions += 1;
+    let events = anchor_events(&anchor);
+    let read: Vec<ThawDecision> = events
+        .iter()
+        .filter_map(|event| {
+            Some(ThawDecision {
+                mint: event.mint,
+                owner: event.owner,
+                approved: event.approved,
+                reason: DecisionReason::from_u8(event.reason)?,
+            })
+        })
+        .collect();
+    if read != [approved, denied] {
+        return TestResultReport::failure(
+            test_name,
+            format!("Anchor parser read {:?} from {:?}", events, anchor),
+        );
+    }
+
+    // Assertion 3: The Anchor parser finds no events of its own in the
+    // native layout
+    assertions += 1;
+    let events = anchor_events(&native);
+    if !events.is_empty() {
+        return TestResultReport::failure(
+            test_name,
+            format!("Anchor parser read {:?} from native events", events),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: A decoded check carries the event the gate logged, placed in its
+/// logs where the runtime writes it
+#[tokio::test]
+async fn test_decoded_check_carries_decision_event() {
+    let report = run_decoded_check_carries_decision_event_test().await;
+    assert!(
+        report.passed,
+        "Decoded check carries decision event test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_decoded_check_carries_decision_event_test() -> TestResultReport {
+    let test_name = "Decision Events: Decoded Checks";
+    let mut assertions = 0;
+
+    let (mut harness, _bank) = match start(test_name).await {
+        Ok(started) => started,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let holders = match holders(&mut harness).await {
+        Ok(holders) => holders,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let mut decoder = Decoder::new();
+    decoder.register(
+        harness.production_allow_list,
+        ProgramKind::ProductionAllowList,
+    );
+
+    for ((owner, token_account), reason) in [
+        (holders.listed, DecisionReason::Listed),
+        (holders.unknown, DecisionReason::NotListed),
+    ] {
+        let before = captured_decisions(&harness.mint).len();
+        let (transaction, result, mut logs) = check(&mut harness, &token_account, &owner).await;
+        let logged: Vec<(EventFormat, ThawDecision)> =
+            captured_decisions(&harness.mint).split_off(before);
+
+        // The gate's `Program data:` line follows its invoke line, as the
+        // event is the first thing it logs
+        let invoke = format!("Program {} invoke [1]", harness.production_allow_list);
+        let Some(position) = logs.iter().position(|line| *line == invoke) else {
+            return TestResultReport::failure(test_name, format!("No invocation in {:?}", logs));
+        };
+        for (offset, (format, decision)) in logged.iter().enumerate() {
+            logs.insert(
+                position + 1 + offset,
+                program_data_line(&decision.log_fields(*format)),
+            );
+        }
+        let decoded = decoder.decode_transaction(&transaction, &result, &logs);
+
+        // Assertion: The decision carries the event, which agrees with the
+        // outcome
+        assertions += 1;
+        let event = decoded
+            .checks()
+            .next()
+            .and_then(|check| check.decision.as_ref())
+            .and_then(|decision| {
+                decision
+                    .event
+                    .clone()
+                    .map(|event| (decision.approved, event))
+            });
+        match event {
+            Some((approved, event))
+                if event.owner == owner
+                    && event.reason == reason.name()
+                    && event.approved == reason.approves()
+                    && approved == reason.approves() => {}
+            other => {
+                return TestResultReport::failure(
+                    test_name,
+                    format!("Expected a {} event, decoded {:?}", reason.name(), other),
+                );
+            }
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate decision event test report
+#[tokio::test]
+async fn generate_decision_events_report() {
+    let results = vec![
+        run_gate_logs_thaw_decisions_test().await,
+        run_native_and_anchor_consumers_read_decisions_test(),
+        run_decoded_check_carries_decision_event_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Decision Event Results",
+        "../../tests/reports/decision_events.md",
+    ) {
+        panic!("Failed to generate decision events report: {}", e);
+    }
+
+    for result in &results {
+        assert!(result.passed, "{} failed: {:?}", result.name, result.error);
+    }
+}
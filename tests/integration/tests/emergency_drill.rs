@@ -0,0 +1,612 @@
+//! Per-mint freeze-all emergency drill
+//!
+//! Rehearses what an issuer does when a mint is compromised, on a
+//! `solana-program-test` bank holding a hybrid-gated mint with dozens of
+//! thawed holders. The issuer flips the kill switch: it pauses the hybrid
+//! gate, so no permissionless thaw can reopen an account, and freezes the
+//! largest holders with its own freeze authority, several per transaction.
+//! Once the incident is understood it recovers: it unpauses the gate and
+//! thaws the holders that were cleared, while the one found responsible is
+//! sanctioned and stays frozen. The drill report gives the wall-clock, fees
+//! and compute of each step, as the bank charged them, and where every
+//! holder ended up.
+
+use borsh::BorshSerialize;
+use example_block_list::{block_list_address, create_block_list_record, BlockReason};
+use hybrid_gate::{config_address, HybridConfig, SET_PAUSED};
+use production_allow_list::{allow_list_address, AccessLevel, AllowListRecord};
+use solana_program_test::{processor, BanksClient, ProgramTest};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use spl_token_2022::{
+    instruction as token_instruction,
+    state::{Account as TokenAccount, AccountState},
+};
+use std::{fs, time::Instant};
+use token_acl_interface as interface;
+
+use token_acl_integration_tests::{
+    context::TestContext,
+    costs::{CampaignCost, PhaseCost},
+    program_test::{mint_account, rent_exempt_account, token_account},
+    reporting, time, units, TestResultReport,
+};
+
+/// Holders of the mint, all thawed when the incident starts
+const HOLDER_COUNT: usize = 48;
+
+/// Largest holders the kill switch freezes
+const TOP_HOLDERS: usize = 32;
+
+/// Freezes per transaction; each only adds the token account to the message
+const FREEZE_BATCH: usize = 16;
+
+/// One holder of the drill's mint
+#[derive(Debug, Clone)]
+struct Holder {
+    owner: Pubkey,
+    token_account: Pubkey,
+    amount: u64,
+}
+
+/// Where one holder ended up
+#[derive(Debug, Clone)]
+struct HolderOutcome {
+    holder: Holder,
+    /// Rank by balance, from 1
+    rank: usize,
+    frozen_by_drill: bool,
+    sanctioned: bool,
+    final_state: AccountState,
+}
+
+/// What the drill cost and what it left behind
+#[derive(Debug, Clone)]
+struct DrillReport {
+    cost: CampaignCost,
+    outcomes: Vec<HolderOutcome>,
+}
+
+impl DrillReport {
+    fn to_markdown(&self) -> String {
+        let mut report = String::from("# Token ACL Emergency Drill Report\n\n");
+        report.push_str(&format!("**Generated**: {}\n\n", time::report_timestamp()));
+        report.push_str(&format!(
+            "Freeze-all drill on one mint: pause the hybrid gate, freeze the top {} of {} holders \
+             {} per transaction with the issuer's freeze authority, then unpause and thaw the \
+             holders that were cleared.\n\n",
+            TOP_HOLDERS, HOLDER_COUNT, FREEZE_BATCH
+        ));
+        report.push_str(&self.cost.to_markdown());
+
+        report.push_str("## Holders\n\n");
+        report.push_str("| Rank | Owner | Balance | Drill Froze | Sanctioned | Final State |\n");
+        report.push_str("|------|-------|---------|-------------|------------|-------------|\n");
+        for outcome in &self.outcomes {
+            report.push_str(&format!(
+                "| {} | `{}` | {} | {} | {} | {:?} |\n",
+                outcome.rank,
+                outcome.holder.owner,
+                units::group_digits(outcome.holder.amount),
+                if outcome.frozen_by_drill { "yes" } else { "no" },
+                if outcome.sanctioned { "yes" } else { "no" },
+                outcome.final_state
+            ));
+        }
+        report.push('\n');
+        report
+    }
+}
+
+struct Drill {
+    /// The test's bank, kept alive while its client is in use
+    context: TestContext,
+    banks_client: BanksClient,
+    payer: Keypair,
+    gate: Pubkey,
+    allow_list: Pubkey,
+    block_list: Pubkey,
+    issuer: Keypair,
+    mint: Pubkey,
+    holders: Vec<Holder>,
+}
+
+impl Drill {
+    /// Start a bank where every holder is allow-listed, unsanctioned and
+    /// thawed, with balances that do not follow creation order
+    async fn start(test_name: &str) -> Self {
+        let gate = Pubkey::new_unique();
+        let allow_list = Pubkey::new_unique();
+        let block_list = Pubkey::new_unique();
+        let issuer = Keypair::new();
+        let mint = Pubkey::new_unique();
+
+        let mut program_test = ProgramTest::new(
+            "hybrid_gate",
+            gate,
+            processor!(hybrid_gate::process_instruction),
+        );
+        program_test.add_program(
+            "production_allow_list",
+            allow_list,
+            processor!(production_allow_list::process_instruction),
+        );
+        program_test.add_program(
+            "example_block_list",
+            block_list,
+            processor!(example_block_list::process_instruction),
+        );
+
+        let holders: Vec<Holder> = (0..HOLDER_COUNT)
+            .map(|i| Holder {
+                owner: Pubkey::new_unique(),
+                token_account: Pubkey::new_unique(),
+                amount: ((i * 37) % HOLDER_COUNT + 1) as u64 * 1_000,
+            })
+            .collect();
+
+        let supply = holders.iter().map(|holder| holder.amount).sum();
+        program_test.add_account(mint, mint_account(&issuer.pubkey(), supply));
+
+        let config = HybridConfig {
+            authority: issuer.pubkey(),
+            mint,
+            allow_list_program: allow_list,
+            block_list_program: block_list,
+            jurisdictions: None,
+            cooling_off_seconds: 0,
+            paused: false,
+            bump: config_address(&mint, &gate).bump(),
+        };
+        program_test.add_account(
+            config_address(&mint, &gate).address(),
+            rent_exempt_account(config.try_to_vec().unwrap(), gate),
+        );
+
+        for holder in &holders {
+            program_test.add_account(
+                holder.token_account,
+                token_account(
+                    &mint,
+                    &holder.owner,
+                    holder.amount,
+                    AccountState::Initialized,
+                ),
+            );
+
+            let record_address = allow_list_address(&mint, &holder.owner, &allow_list);
+            let record = AllowListRecord {
+                mint,
+                user: holder.owner,
+                allowed: true,
+                access_level: AccessLevel::Basic,
+                added_timestamp: 0,
+                expiry_timestamp: None,
+                bump: record_address.bump(),
+                revoked: false,
+            };
+            program_test.add_account(
+                record_address.address(),
+                rent_exempt_account(record.try_to_vec().unwrap(), allow_list),
+            );
+        }
+
+        let context = TestContext::start(test_name, program_test)
+            .await
+            .expect("program-test bank should start");
+        Self {
+            banks_client: context.client(),
+            payer: context.payer(),
+            gate,
+            allow_list,
+            block_list,
+            issuer,
+            mint,
+            holders,
+            context,
+        }
+    }
+
+    fn config(&self) -> Pubkey {
+        config_address(&self.mint, &self.gate).address()
+    }
+
+    fn block_list_record(&self, owner: &Pubkey) -> Pubkey {
+        block_list_address(&self.mint, owner, &self.block_list).address()
+    }
+
+    /// The gate authority's instruction flipping the kill switch
+    fn set_paused_instruction(&self, paused: bool) -> Instruction {
+        Instruction::new_with_bytes(
+            self.gate,
+            &[SET_PAUSED, u8::from(paused)],
+            vec![
+                AccountMeta::new(self.config(), false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+            ],
+        )
+    }
+
+    /// The issuer's own freeze, which no gate is asked about
+    fn freeze_instruction(&self, holder: &Holder) -> Instruction {
+        token_instruction::freeze_account(
+            &spl_token_2022::id(),
+            &holder.token_account,
+            &self.mint,
+            &self.issuer.pubkey(),
+            &[],
+        )
+        .unwrap()
+    }
+
+    /// The gate's thaw check followed by the thaw the Token ACL program
+    /// would make on its approval, with the issuer standing in for the FAMP
+    /// PDA
+    fn thaw_instructions(&self, holder: &Holder) -> Vec<Instruction> {
+        let check = interface::can_thaw_permissionless(
+            &self.gate,
+            holder.owner,
+            holder.token_account,
+            self.mint,
+            interface::find_thaw_extra_account_metas_address(&self.mint, &self.gate).0,
+            &[
+                holder.owner.into(),
+                self.config().into(),
+                allow_list_address(&self.mint, &holder.owner, &self.allow_list)
+                    .address()
+                    .into(),
+                self.block_list_record(&holder.owner).into(),
+            ],
+        );
+        let thaw = token_instruction::thaw_account(
+            &spl_token_2022::id(),
+            &holder.token_account,
+            &self.mint,
+            &self.issuer.pubkey(),
+            &[],
+        )
+        .unwrap();
+        vec![check, thaw]
+    }
+
+    /// Put `owner` on the sanctions list
+    ///
+    /// The block list example has no admin instructions, so the record is
+    /// written directly, owned by the block list program.
+    fn sanction(&mut self, owner: &Pubkey) {
+        let record = create_block_list_record(&self.mint, owner, BlockReason::Sanctions, 0);
+        let account = rent_exempt_account(record.try_to_vec().unwrap(), self.block_list);
+        let address = self.block_list_record(owner);
+        self.context.set_account(&address, &account.into());
+    }
+
+    async fn token_state(&mut self, holder: &Holder) -> Result<TokenAccount, String> {
+        let account = self
+            .banks_client
+            .get_account(holder.token_account)
+            .await
+            .map_err(|e| format!("Reading {} failed: {}", holder.token_account, e))?
+            .ok_or_else(|| format!("Token account {} is gone", holder.token_account))?;
+        TokenAccount::unpack(&account.data).map_err(|e| e.to_string())
+    }
+
+    /// The holders by balance on the bank, largest first
+    async fn ranked_holders(&mut self) -> Result<Vec<Holder>, String> {
+        let mut ranked = Vec::with_capacity(self.holders.len());
+        for holder in self.holders.clone() {
+            let amount = self.token_state(&holder).await?.amount;
+            ranked.push(Holder { amount, ..holder });
+        }
+        ranked.sort_by_key(|holder| std::cmp::Reverse(holder.amount));
+        Ok(ranked)
+    }
+
+    async fn balance(&mut self, address: Pubkey) -> u64 {
+        self.banks_client
+            .get_balance(address)
+            .await
+            .unwrap_or_default()
+    }
+
+    /// Sign `instructions` with the payer and the issuer, on a fresh
+    /// blockhash so repeated transactions are not deduplicated
+    async fn sign(&mut self, instructions: &[Instruction]) -> Result<Transaction, String> {
+        let blockhash = self
+            .context
+            .get_new_latest_blockhash()
+            .await
+            .map_err(|e| format!("Fetching a blockhash failed: {}", e))?;
+        Ok(Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.payer.pubkey()),
+            &[&self.payer, &self.issuer],
+            blockhash,
+        ))
+    }
+
+    /// Submit `instructions` and count the transaction against `phase`
+    async fn submit(
+        &mut self,
+        instructions: &[Instruction],
+        phase: &mut PhaseCost,
+    ) -> Result<(), String> {
+        let transaction = self.sign(instructions).await?;
+        let fee = self.context.fee(transaction.signatures.len());
+        let outcome = self
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .map_err(|e| e.to_string())?;
+        outcome.result.map_err(|e| e.to_string())?;
+        let compute_units = outcome
+            .metadata
+            .map(|metadata| metadata.compute_units_consumed)
+            .unwrap_or_default();
+        phase.record_transaction(fee, compute_units);
+        Ok(())
+    }
+
+    /// Whether `instructions` would land, and their logs
+    async fn simulate(
+        &mut self,
+        instructions: &[Instruction],
+    ) -> Result<(bool, Vec<String>), String> {
+        let transaction = self.sign(instructions).await?;
+        let simulation = self
+            .banks_client
+            .simulate_transaction(transaction)
+            .await
+            .map_err(|e| e.to_string())?;
+        let logs = simulation
+            .simulation_details
+            .map(|details| details.logs)
+            .unwrap_or_default();
+        Ok((simulation.result.is_some_and(|result| result.is_ok()), logs))
+    }
+
+    /// Pause or unpause the gate in one transaction
+    async fn flip_switch(&mut self, name: &str, paused: bool) -> Result<PhaseCost, String> {
+        let mut phase = PhaseCost::new(name);
+        let started = Instant::now();
+        let instruction = self.set_paused_instruction(paused);
+        self.submit(&[instruction], &mut phase).await?;
+        phase.elapsed = started.elapsed();
+        Ok(phase)
+    }
+
+    /// Freeze `holders`, `FREEZE_BATCH` per transaction
+    async fn mass_freeze(&mut self, holders: &[Holder]) -> Result<PhaseCost, String> {
+        let mut phase = PhaseCost::new("Mass freeze");
+        let started = Instant::now();
+        for (batch, chunk) in holders.chunks(FREEZE_BATCH).enumerate() {
+            let instructions: Vec<Instruction> = chunk
+                .iter()
+                .map(|holder| self.freeze_instruction(holder))
+                .collect();
+            self.submit(&instructions, &mut phase)
+                .await
+                .map_err(|e| format!("Freeze batch {} failed: {}", batch, e))?;
+        }
+        phase.elapsed = started.elapsed();
+        Ok(phase)
+    }
+
+    /// Thaw each of `holders` through the gate, one transaction each
+    async fn selective_thaw(&mut self, holders: &[Holder]) -> Result<PhaseCost, String> {
+        let mut phase = PhaseCost::new("Selective thaw");
+        let started = Instant::now();
+        for holder in holders {
+            let instructions = self.thaw_instructions(holder);
+            self.submit(&instructions, &mut phase)
+                .await
+                .map_err(|e| format!("Thaw of {} failed: {}", holder.owner, e))?;
+        }
+        phase.elapsed = started.elapsed();
+        Ok(phase)
+    }
+}
+
+/// Run the freeze-all drill and report what it cost
+#[tokio::test]
+async fn drill_freeze_all_emergency() {
+    let (report, drill) = run_emergency_drill().await;
+
+    if let Some(drill) = &drill {
+        println!("{}", drill.cost.to_markdown());
+        fs::create_dir_all("../../tests/reports").ok();
+        if let Err(e) = fs::write(
+            "../../tests/reports/emergency_drill_report.md",
+            drill.to_markdown(),
+        ) {
+            panic!("Failed to write emergency drill report: {}", e);
+        }
+    }
+    if let Err(e) = reporting::generate_test_report(
+        std::slice::from_ref(&report),
+        "Emergency Drill Results",
+        "../../tests/reports/emergency_drill.md",
+    ) {
+        panic!("Failed to generate emergency drill report: {}", e);
+    }
+
+    assert!(report.passed, "Emergency drill failed: {:?}", report.error);
+}
+
+async fn run_emergency_drill() -> (TestResultReport, Option<DrillReport>) {
+    let test_name = "Freeze-All Emergency Drill";
+    let mut assertions = 0;
+
+    let mut drill = Drill::start(test_name).await;
+    let payer = drill.payer.pubkey();
+    let payer_before = drill.balance(payer).await;
+    let mut cost = CampaignCost::new("Freeze-all drill", TOP_HOLDERS);
+
+    let ranked = match drill.ranked_holders().await {
+        Ok(ranked) => ranked,
+        Err(e) => return (TestResultReport::failure(test_name, e), None),
+    };
+    let (top, rest) = ranked.split_at(TOP_HOLDERS);
+    let culprit = top[0].clone();
+
+    // Assertion 1: The kill switch lands: the gate is paused and the top
+    // holders are frozen in as few transactions as the batch size allows
+    assertions += 1;
+    let phases = [
+        drill.flip_switch("Kill switch", true).await,
+        drill.mass_freeze(top).await,
+    ];
+    for phase in phases {
+        match phase {
+            Ok(phase) => cost.add_phase(phase),
+            Err(e) => return (TestResultReport::failure(test_name, e), None),
+        }
+    }
+    let freeze_transactions = cost.phases[1].transactions;
+    if freeze_transactions != TOP_HOLDERS.div_ceil(FREEZE_BATCH) {
+        return (
+            TestResultReport::failure(
+                test_name,
+                format!(
+                    "{} holders frozen in {} transactions",
+                    TOP_HOLDERS, freeze_transactions
+                ),
+            ),
+            None,
+        );
+    }
+
+    // Assertion 2: Exactly the top holders are frozen; the rest keep
+    // trading, and no balance moved
+    assertions += 1;
+    for (holder, expected) in top
+        .iter()
+        .map(|holder| (holder, AccountState::Frozen))
+        .chain(
+            rest.iter()
+                .map(|holder| (holder, AccountState::Initialized)),
+        )
+    {
+        match drill.token_state(holder).await {
+            Ok(state) if state.state == expected && state.amount == holder.amount => {}
+            other => {
+                return (
+                    TestResultReport::failure(
+                        test_name,
+                        format!(
+                            "Holder {} after the freeze: expected {:?} with {}, got {:?}",
+                            holder.owner, expected, holder.amount, other
+                        ),
+                    ),
+                    None,
+                )
+            }
+        }
+    }
+
+    // Assertion 3: While paused, the gate denies the thaw of a frozen
+    // holder who is still allow-listed and not sanctioned
+    assertions += 1;
+    let thaw = drill.thaw_instructions(&top[1]);
+    match drill.simulate(&thaw).await {
+        Ok((false, logs)) if logs.iter().any(|log| log.contains("are paused")) => {}
+        other => {
+            return (
+                TestResultReport::failure(test_name, format!("Thaw while paused: {:?}", other)),
+                None,
+            )
+        }
+    }
+
+    // Assertion 4: Recovery: once unpaused, every cleared holder thaws
+    // through the gate
+    assertions += 1;
+    drill.sanction(&culprit.owner);
+    let cleared: Vec<Holder> = top[1..].to_vec();
+    let phases = [
+        drill.flip_switch("Unpause", false).await,
+        drill.selective_thaw(&cleared).await,
+    ];
+    for phase in phases {
+        match phase {
+            Ok(phase) => cost.add_phase(phase),
+            Err(e) => return (TestResultReport::failure(test_name, e), None),
+        }
+    }
+
+    // Assertion 5: The holder found responsible is denied and stays frozen
+    assertions += 1;
+    let thaw = drill.thaw_instructions(&culprit);
+    match drill.simulate(&thaw).await {
+        Ok((false, logs)) if logs.iter().any(|log| log.contains("sanctions override")) => {}
+        other => {
+            return (
+                TestResultReport::failure(
+                    test_name,
+                    format!("Culprit's thaw after recovery: {:?}", other),
+                ),
+                None,
+            )
+        }
+    }
+
+    let mut outcomes = Vec::with_capacity(ranked.len());
+    for (index, holder) in ranked.iter().enumerate() {
+        let final_state = match drill.token_state(holder).await {
+            Ok(state) => state.state,
+            Err(e) => return (TestResultReport::failure(test_name, e), None),
+        };
+        outcomes.push(HolderOutcome {
+            holder: holder.clone(),
+            rank: index + 1,
+            frozen_by_drill: index < TOP_HOLDERS,
+            sanctioned: holder.owner == culprit.owner,
+            final_state,
+        });
+    }
+
+    // Assertion 6: Only the culprit ends frozen
+    assertions += 1;
+    if let Some(outcome) = outcomes
+        .iter()
+        .find(|outcome| (outcome.final_state == AccountState::Frozen) != outcome.sanctioned)
+    {
+        return (
+            TestResultReport::failure(
+                test_name,
+                format!(
+                    "Holder {} ended {:?} (sanctioned: {})",
+                    outcome.holder.owner, outcome.final_state, outcome.sanctioned
+                ),
+            ),
+            None,
+        );
+    }
+
+    // Assertion 7: The payer spent exactly the fees the drill accounted for
+    assertions += 1;
+    let payer_spent = payer_before - drill.balance(payer).await;
+    if payer_spent != cost.fees() {
+        return (
+            TestResultReport::failure(
+                test_name,
+                format!(
+                    "Payer spent {} but the drill accounted for {}",
+                    payer_spent,
+                    cost.fees()
+                ),
+            ),
+            None,
+        );
+    }
+
+    (
+        TestResultReport::success(test_name, assertions),
+        Some(DrillReport { cost, outcomes }),
+    )
+}
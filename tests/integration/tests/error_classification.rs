@@ -0,0 +1,505 @@
+//! Error classification tests
+//!
+//! These tests pin down how transaction, banks, and RPC client errors map to
+//! retryable, fatal, and needs-user-action, including gate denials recognised
+//! from the production allow list's logs.
+
+use production_allow_list::{ALLOW_LIST_SEED, CONFIG_SEED, INITIALIZE};
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_request::{RpcError, RpcResponseErrorData},
+    rpc_response::RpcSimulateTransactionResult,
+};
+use solana_program_test::{processor, BanksClientError, ProgramTest};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction, InstructionError},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    signer::SignerError,
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+use token_acl_interface::find_missing_record_policy_address;
+
+use token_acl_integration_tests::{
+    errors::{
+        classify_banks_error, classify_client_error, classify_transaction_error,
+        classify_with_logs, ErrorClass,
+    },
+    fixtures::test_data,
+    reporting, TestResultReport,
+};
+
+fn preflight_failure(err: TransactionError, logs: &[&str]) -> ClientError {
+    ClientError::from(ClientErrorKind::RpcError(RpcError::RpcResponseError {
+        code: -32002,
+        message: "Transaction simulation failed".to_string(),
+        data: RpcResponseErrorData::SendTransactionPreflightFailure(RpcSimulateTransactionResult {
+            err: Some(err),
+            logs: Some(logs.iter().map(|line| line.to_string()).collect()),
+            accounts: None,
+            units_consumed: None,
+            return_data: None,
+            inner_instructions: None,
+        }),
+    }))
+}
+
+/// Test 1: Transaction errors map to the expected class
+#[tokio::test]
+async fn test_transaction_error_classes() {
+    let report = run_transaction_error_classes_test();
+    assert!(
+        report.passed,
+        "Transaction error classification test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_transaction_error_classes_test() -> TestResultReport {
+    let test_name = "Transaction Error Classes";
+    let mut assertions = 0;
+
+    let cases = vec![
+        (TransactionError::BlockhashNotFound, ErrorClass::Retryable),
+        (TransactionError::AccountInUse, ErrorClass::Retryable),
+        (
+            TransactionError::WouldExceedMaxAccountCostLimit,
+            ErrorClass::Retryable,
+        ),
+        (TransactionError::ClusterMaintenance, ErrorClass::Retryable),
+        (TransactionError::AlreadyProcessed, ErrorClass::Fatal),
+        (TransactionError::SignatureFailure, ErrorClass::Fatal),
+        (TransactionError::InvalidAccountIndex, ErrorClass::Fatal),
+        (
+            TransactionError::InsufficientFundsForFee,
+            ErrorClass::NeedsUserAction,
+        ),
+        (
+            TransactionError::InsufficientFundsForRent { account_index: 1 },
+            ErrorClass::NeedsUserAction,
+        ),
+        (
+            TransactionError::AccountNotFound,
+            ErrorClass::NeedsUserAction,
+        ),
+        (
+            TransactionError::InstructionError(0, InstructionError::MissingRequiredSignature),
+            ErrorClass::NeedsUserAction,
+        ),
+        (
+            TransactionError::InstructionError(0, InstructionError::InsufficientFunds),
+            ErrorClass::NeedsUserAction,
+        ),
+        (
+            TransactionError::InstructionError(0, InstructionError::ComputationalBudgetExceeded),
+            ErrorClass::Fatal,
+        ),
+        (
+            TransactionError::InstructionError(0, InstructionError::Custom(6000)),
+            ErrorClass::Fatal,
+        ),
+    ];
+
+    for (error, expected) in &cases {
+        // Assertion N: Each error lands in its class
+        assertions += 1;
+        let classification = classify_transaction_error(error);
+        if classification.class != *expected {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "{:?} classified as {:?} ({}), expected {:?}",
+                    error, classification.class, classification.reason, expected
+                ),
+            );
+        }
+    }
+
+    // Assertion: Only retryable errors should be retried
+    assertions += 1;
+    let retried = cases
+        .iter()
+        .filter(|(error, _)| classify_transaction_error(error).should_retry())
+        .count();
+    let retryable = cases
+        .iter()
+        .filter(|(_, class)| *class == ErrorClass::Retryable)
+        .count();
+    if retried != retryable {
+        return TestResultReport::failure(
+            test_name,
+            format!("{} errors retried, expected {}", retried, retryable),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: Gate denials are recognised from logs
+#[tokio::test]
+async fn test_gate_denial_classes() {
+    let report = run_gate_denial_classes_test();
+    assert!(
+        report.passed,
+        "Gate denial classification test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_gate_denial_classes_test() -> TestResultReport {
+    let test_name = "Gate Denial Classes";
+    let mut assertions = 0;
+
+    let denied = TransactionError::InstructionError(0, InstructionError::InvalidAccountData);
+    let cases = vec![
+        (
+            "Program log: User 111 not in allow list",
+            ErrorClass::NeedsUserAction,
+            "gate_denied:not_listed",
+        ),
+        (
+            "Program log: User 111 is not allowed",
+            ErrorClass::NeedsUserAction,
+            "gate_denied:revoked",
+        ),
+        (
+            "Program log: User 111 access has expired",
+            ErrorClass::NeedsUserAction,
+            "gate_denied:expired",
+        ),
+        (
+            "Program log: User 111 is blocked",
+            ErrorClass::NeedsUserAction,
+            "gate_denied:blocked",
+        ),
+        (
+            "Program log: Invalid allow list PDA",
+            ErrorClass::Fatal,
+            "gate_rejected:invalid_pda",
+        ),
+        (
+            "Program log: Token account 222 is not owned by 111 for this mint",
+            ErrorClass::Fatal,
+            "gate_rejected:owner_mismatch",
+        ),
+        (
+            "Program log: Something unrelated",
+            ErrorClass::Fatal,
+            "instruction_failed",
+        ),
+    ];
+
+    for (log, expected_class, expected_reason) in cases {
+        // Assertion N: Each denial log lands in its class with its reason
+        assertions += 1;
+        let classification = classify_with_logs(&denied, &[log.to_string()]);
+        if classification.class != expected_class || classification.reason != expected_reason {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "'{}' classified as {:?} ({}), expected {:?} ({})",
+                    log,
+                    classification.class,
+                    classification.reason,
+                    expected_class,
+                    expected_reason
+                ),
+            );
+        }
+    }
+
+    // Assertion: Logs do not override non-gate errors
+    assertions += 1;
+    let classification = classify_with_logs(
+        &TransactionError::BlockhashNotFound,
+        &["Program log: User 111 not in allow list".to_string()],
+    );
+    if classification.class != ErrorClass::Retryable {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Expired blockhash with denial logs classified as {:?}",
+                classification.class
+            ),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: RPC client errors map to the expected class
+#[tokio::test]
+async fn test_client_error_classes() {
+    let report = run_client_error_classes_test();
+    assert!(
+        report.passed,
+        "Client error classification test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_client_error_classes_test() -> TestResultReport {
+    let test_name = "Client Error Classes";
+    let mut assertions = 0;
+
+    let cases = vec![
+        (
+            "io",
+            ClientError::from(ClientErrorKind::Io(std::io::Error::new(
+                std::io::ErrorKind::ConnectionReset,
+                "reset",
+            ))),
+            ErrorClass::Retryable,
+        ),
+        (
+            "request",
+            ClientError::from(ClientErrorKind::RpcError(RpcError::RpcRequestError(
+                "timed out".to_string(),
+            ))),
+            ErrorClass::Retryable,
+        ),
+        (
+            "node unhealthy",
+            ClientError::from(ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                code: -32005,
+                message: "Node is behind".to_string(),
+                data: RpcResponseErrorData::NodeUnhealthy {
+                    num_slots_behind: Some(42),
+                },
+            })),
+            ErrorClass::Retryable,
+        ),
+        (
+            "node unhealthy without data",
+            ClientError::from(ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                code: -32005,
+                message: "Node is unhealthy".to_string(),
+                data: RpcResponseErrorData::Empty,
+            })),
+            ErrorClass::Retryable,
+        ),
+        (
+            "invalid params",
+            ClientError::from(ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                code: -32602,
+                message: "Invalid params".to_string(),
+                data: RpcResponseErrorData::Empty,
+            })),
+            ErrorClass::Fatal,
+        ),
+        (
+            "preflight expired blockhash",
+            preflight_failure(TransactionError::BlockhashNotFound, &[]),
+            ErrorClass::Retryable,
+        ),
+        (
+            "preflight gate denial",
+            preflight_failure(
+                TransactionError::InstructionError(1, InstructionError::InvalidAccountData),
+                &["Program log: User 111 not in allow list"],
+            ),
+            ErrorClass::NeedsUserAction,
+        ),
+        (
+            "signing",
+            ClientError::from(ClientErrorKind::SigningError(SignerError::NotEnoughSigners)),
+            ErrorClass::NeedsUserAction,
+        ),
+        (
+            "transaction",
+            ClientError::from(ClientErrorKind::TransactionError(
+                TransactionError::AlreadyProcessed,
+            )),
+            ErrorClass::Fatal,
+        ),
+        (
+            "parse",
+            ClientError::from(ClientErrorKind::RpcError(RpcError::ParseError(
+                "u64".to_string(),
+            ))),
+            ErrorClass::Fatal,
+        ),
+        (
+            "custom",
+            ClientError::from(ClientErrorKind::Custom("bad config".to_string())),
+            ErrorClass::Fatal,
+        ),
+    ];
+
+    for (name, error, expected) in &cases {
+        // Assertion N: Each client error lands in its class
+        assertions += 1;
+        let classification = classify_client_error(error);
+        if classification.class != *expected {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "{} classified as {:?} ({}), expected {:?}",
+                    name, classification.class, classification.reason, expected
+                ),
+            );
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 4: A real denial from the production gate is classified from its
+/// simulation logs
+#[tokio::test]
+async fn test_production_gate_denial_class() {
+    let report = run_production_gate_denial_class_test().await;
+    assert!(
+        report.passed,
+        "Production gate denial classification test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_production_gate_denial_class_test() -> TestResultReport {
+    let test_name = "Production Gate Denial Class";
+    let mut assertions = 0;
+
+    let gate = Pubkey::new_unique();
+    let (mut banks_client, payer, blockhash) = ProgramTest::new(
+        "production_allow_list",
+        gate,
+        processor!(production_allow_list::process_instruction),
+    )
+    .start()
+    .await;
+
+    let mint = Pubkey::new_unique();
+    let authority = Keypair::new();
+    let holder = Keypair::new();
+    let (config, _) = Pubkey::find_program_address(&[CONFIG_SEED, mint.as_ref()], &gate);
+    let (record, _) = Pubkey::find_program_address(
+        &[ALLOW_LIST_SEED, mint.as_ref(), holder.pubkey().as_ref()],
+        &gate,
+    );
+    let (extra_metas, _) = Pubkey::find_program_address(
+        &[test_data::THAW_EXTRA_ACCOUNT_METAS_SEED, mint.as_ref()],
+        &gate,
+    );
+
+    let initialize = Transaction::new_signed_with_payer(
+        &[Instruction::new_with_bytes(
+            gate,
+            &[INITIALIZE],
+            vec![
+                AccountMeta::new(config, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(authority.pubkey(), true),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        blockhash,
+    );
+    if let Err(e) = banks_client.process_transaction(initialize).await {
+        return TestResultReport::failure(test_name, format!("Initialize failed: {}", e));
+    }
+
+    let check = Transaction::new_signed_with_payer(
+        &[Instruction::new_with_bytes(
+            gate,
+            &test_data::THAW_DISCRIMINATOR,
+            vec![
+                AccountMeta::new_readonly(holder.pubkey(), false),
+                AccountMeta::new_readonly(Pubkey::new_unique(), false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(extra_metas, false),
+                AccountMeta::new_readonly(holder.pubkey(), false),
+                AccountMeta::new_readonly(record, false),
+                AccountMeta::new_readonly(
+                    find_missing_record_policy_address(&mint, &gate).0,
+                    false,
+                ),
+            ],
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        blockhash,
+    );
+
+    // Simulate first: once processed, the signature is already known
+    let simulation = match banks_client.simulate_transaction(check.clone()).await {
+        Ok(simulation) => simulation,
+        Err(e) => return TestResultReport::failure(test_name, format!("Simulate failed: {}", e)),
+    };
+
+    // Assertion 1: Without logs the denial is only a generic instruction failure
+    assertions += 1;
+    let error = match banks_client.process_transaction(check).await {
+        Ok(()) => {
+            return TestResultReport::failure(
+                test_name,
+                "Unlisted holder passed the gate".to_string(),
+            )
+        }
+        Err(e) => e,
+    };
+    let classification = classify_banks_error(&error);
+    if classification.class != ErrorClass::Fatal {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Bare denial classified as {:?} ({})",
+                classification.class, classification.reason
+            ),
+        );
+    }
+
+    // Assertion 2: With simulation logs it needs user action
+    assertions += 1;
+    let (Some(Err(err)), Some(details)) = (simulation.result, simulation.simulation_details) else {
+        return TestResultReport::failure(
+            test_name,
+            "Simulation did not report the denial".to_string(),
+        );
+    };
+    let classification = classify_banks_error(&BanksClientError::SimulationError {
+        err,
+        logs: details.logs,
+        units_consumed: details.units_consumed,
+        return_data: details.return_data,
+    });
+    if classification.class != ErrorClass::NeedsUserAction
+        || classification.reason != "gate_denied:not_listed"
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Simulated denial classified as {:?} ({})",
+                classification.class, classification.reason
+            ),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate error classification report
+#[tokio::test]
+async fn generate_error_classification_report() {
+    let results = vec![
+        run_transaction_error_classes_test(),
+        run_gate_denial_classes_test(),
+        run_client_error_classes_test(),
+        run_production_gate_denial_class_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Error Classification Test Results",
+        "../../tests/reports/error_classification.md",
+    ) {
+        panic!("Failed to generate error classification report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} error classification tests failed", failed);
+}
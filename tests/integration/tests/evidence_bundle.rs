@@ -0,0 +1,329 @@
+//! Compliance evidence bundle tests
+//!
+//! These tests export suite artifacts into a signed archive and verify that
+//! the manifest, signature, and file digests protect the bundle against
+//! tampering.
+
+use serde::Serialize;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use std::io::Read;
+use std::time::Duration;
+
+use token_acl_integration_tests::{
+    benchmarks::{performance_analysis, BenchmarkResult},
+    coverage::{coverage_reporting, coverage_utils, CoverageRequirements, CoverageResults},
+    evidence::{self, EvidenceBundle, EvidenceKind, MANIFEST_NAME, SIGNATURE_NAME},
+    logging::{LogLevel, Logger},
+    reporting, TestResultReport,
+};
+
+#[derive(Serialize)]
+struct AllowListSnapshotRow {
+    mint: String,
+    user: String,
+    allowed: bool,
+}
+
+/// Generate every artifact kind into `dir` and collect them into a bundle
+fn build_bundle(dir: &str) -> Result<EvidenceBundle, Box<dyn std::error::Error>> {
+    let results = vec![
+        TestResultReport::success("Allow List Soft Remove", 4),
+        TestResultReport::success("Sponsored Permissionless Thaw", 4),
+    ];
+
+    let test_report = format!("{}/test_report.md", dir);
+    reporting::generate_test_report(&results, "Evidence Test Results", &test_report)?;
+
+    let audit_log = format!("{}/audit_log.md", dir);
+    let mut logger = Logger::new(LogLevel::Info);
+    logger.info("evidence", "User added to allow list");
+    logger.warn("evidence", "User removed from allow list");
+    logger.export_to_file(&audit_log)?;
+
+    let coverage = format!("{}/coverage.md", dir);
+    let mut coverage_results = CoverageResults {
+        analysis: coverage_utils::analyze_test_results(&results),
+        requirements: CoverageRequirements::default(),
+        meets_requirements: false,
+        recommendations: Vec::new(),
+    };
+    coverage_results.check_requirements();
+    coverage_reporting::generate_coverage_report(&coverage_results, &coverage)?;
+
+    let benchmarks = format!("{}/benchmarks.md", dir);
+    let timing = Duration::from_micros(10);
+    performance_analysis::generate_performance_report(
+        &[BenchmarkResult::success(
+            "PDA Derivation",
+            timing * 100,
+            100,
+            timing,
+            timing,
+        )],
+        &benchmarks,
+    )?;
+
+    let mint = Pubkey::new_unique();
+    let snapshot: Vec<AllowListSnapshotRow> = (0..3)
+        .map(|i| AllowListSnapshotRow {
+            mint: mint.to_string(),
+            user: Pubkey::new_unique().to_string(),
+            allowed: i != 2,
+        })
+        .collect();
+
+    let mut bundle = EvidenceBundle::new();
+    bundle
+        .add_file(EvidenceKind::TestReport, &test_report)?
+        .add_file(EvidenceKind::AuditLog, &audit_log)?
+        .add_file(EvidenceKind::Coverage, &coverage)?
+        .add_file(EvidenceKind::Benchmarks, &benchmarks)?
+        .add_list_snapshot("allow_list", &snapshot)?;
+    Ok(bundle)
+}
+
+/// Rewrites one archive entry's contents, given its path
+type EntryEdit = dyn Fn(&str, Vec<u8>) -> Vec<u8>;
+
+/// Copy an archive, letting `edit` rewrite each entry's contents
+fn rewrite_bundle(
+    source: &str,
+    destination: &str,
+    edit: impl Fn(&str, Vec<u8>) -> Vec<u8>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(std::fs::File::open(source)?));
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+        std::fs::File::create(destination)?,
+        flate2::Compression::default(),
+    ));
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+        let data = edit(&path, data);
+
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, &path, data.as_slice())?;
+    }
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Test 1: Exported bundle is complete and verifies
+#[test]
+fn test_evidence_bundle_round_trip() {
+    let report = run_bundle_round_trip_test();
+    assert!(
+        report.passed,
+        "Evidence bundle round trip failed: {:?}",
+        report.error
+    );
+}
+
+fn run_bundle_round_trip_test() -> TestResultReport {
+    let test_name = "Evidence Bundle Round Trip";
+    let mut assertions = 0;
+
+    let workspace = match tempfile::tempdir() {
+        Ok(workspace) => workspace,
+        Err(e) => return TestResultReport::failure(test_name, format!("Temp dir failed: {}", e)),
+    };
+    let dir = &workspace.path().to_string_lossy().into_owned();
+    let bundle = match build_bundle(dir) {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            return TestResultReport::failure(test_name, format!("Artifact setup failed: {}", e))
+        }
+    };
+    let signer = Keypair::new();
+    let archive = format!("{}/evidence.tar.gz", dir);
+
+    // Assertion 1: Export succeeds
+    assertions += 1;
+    let exported = match bundle.export(&archive, &signer) {
+        Ok(manifest) => manifest,
+        Err(e) => return TestResultReport::failure(test_name, format!("Export failed: {}", e)),
+    };
+
+    // Assertion 2: Every artifact kind is listed in the manifest
+    assertions += 1;
+    for kind in [
+        EvidenceKind::TestReport,
+        EvidenceKind::AuditLog,
+        EvidenceKind::Coverage,
+        EvidenceKind::Benchmarks,
+        EvidenceKind::ListSnapshot,
+    ] {
+        if !exported.files.iter().any(|file| file.kind == kind) {
+            return TestResultReport::failure(
+                test_name,
+                format!("Manifest is missing {:?} evidence", kind),
+            );
+        }
+    }
+
+    // Assertion 3: The manifest names the exporting key
+    assertions += 1;
+    if exported.signer != signer.pubkey().to_string() {
+        return TestResultReport::failure(
+            test_name,
+            "Manifest signer does not match the exporting key".to_string(),
+        );
+    }
+
+    // Assertion 4: The archive verifies and yields the same manifest
+    assertions += 1;
+    match evidence::verify_bundle(&archive) {
+        Ok(verified) if verified == exported => {}
+        Ok(_) => {
+            return TestResultReport::failure(
+                test_name,
+                "Verified manifest differs from the exported one".to_string(),
+            )
+        }
+        Err(e) => {
+            return TestResultReport::failure(test_name, format!("Verification failed: {}", e))
+        }
+    }
+
+    // Assertion 5: An empty bundle is refused
+    assertions += 1;
+    if EvidenceBundle::new()
+        .export(format!("{}/empty.tar.gz", dir), &signer)
+        .is_ok()
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Empty evidence bundle was exported".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: Tampered bundles fail verification
+#[test]
+fn test_evidence_bundle_tamper_detection() {
+    let report = run_bundle_tamper_test();
+    assert!(
+        report.passed,
+        "Evidence bundle tamper test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_bundle_tamper_test() -> TestResultReport {
+    let test_name = "Evidence Bundle Tamper Detection";
+    let mut assertions = 0;
+
+    let workspace = match tempfile::tempdir() {
+        Ok(workspace) => workspace,
+        Err(e) => return TestResultReport::failure(test_name, format!("Temp dir failed: {}", e)),
+    };
+    let dir = &workspace.path().to_string_lossy().into_owned();
+    let bundle = match build_bundle(dir) {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            return TestResultReport::failure(test_name, format!("Artifact setup failed: {}", e))
+        }
+    };
+    let signer = Keypair::new();
+    let archive = format!("{}/evidence.tar.gz", dir);
+    if let Err(e) = bundle.export(&archive, &signer) {
+        return TestResultReport::failure(test_name, format!("Export failed: {}", e));
+    }
+
+    let tampered = format!("{}/tampered.tar.gz", dir);
+    let cases: [(&str, Box<EntryEdit>); 3] = [
+        (
+            "altered list snapshot",
+            Box::new(|path, data| {
+                if path.starts_with("snapshots/") {
+                    String::from_utf8_lossy(&data)
+                        .replace("false", "true")
+                        .into_bytes()
+                } else {
+                    data
+                }
+            }),
+        ),
+        (
+            "manifest re-signed by another key",
+            Box::new(|path, data| {
+                if path == SIGNATURE_NAME {
+                    Keypair::new()
+                        .sign_message(b"forged")
+                        .to_string()
+                        .into_bytes()
+                } else {
+                    data
+                }
+            }),
+        ),
+        (
+            "edited manifest",
+            Box::new(|path, data| {
+                if path == MANIFEST_NAME {
+                    String::from_utf8_lossy(&data)
+                        .replace("token-acl-testing-suite", "other-suite")
+                        .into_bytes()
+                } else {
+                    data
+                }
+            }),
+        ),
+    ];
+
+    // Assertions 1-3: Each kind of tampering is detected
+    for (description, edit) in cases {
+        assertions += 1;
+        if let Err(e) = rewrite_bundle(&archive, &tampered, edit) {
+            return TestResultReport::failure(test_name, format!("Rewrite failed: {}", e));
+        }
+        if evidence::verify_bundle(&tampered).is_ok() {
+            return TestResultReport::failure(
+                test_name,
+                format!("Bundle with {} passed verification", description),
+            );
+        }
+    }
+
+    // Assertion 4: An untouched copy still verifies
+    assertions += 1;
+    if let Err(e) = rewrite_bundle(&archive, &tampered, |_, data| data) {
+        return TestResultReport::failure(test_name, format!("Rewrite failed: {}", e));
+    }
+    if let Err(e) = evidence::verify_bundle(&tampered) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Unmodified copy failed verification: {}", e),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate evidence bundle test report
+#[test]
+fn generate_evidence_bundle_report() {
+    let results = vec![run_bundle_round_trip_test(), run_bundle_tamper_test()];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Evidence Bundle Test Results",
+        "../../tests/reports/evidence_bundle_tests.md",
+    ) {
+        panic!("Failed to generate evidence bundle report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} evidence bundle tests failed", failed);
+}
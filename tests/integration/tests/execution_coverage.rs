@@ -0,0 +1,513 @@
+//! On-chain execution coverage
+//!
+//! Sets up every in-repo gate on a bank, then replays the suite's thaw and
+//! freeze checks, allowed and denied, against each gate's SBF build with
+//! instruction tracing on, along with the Token-2022 freezes and thaws they
+//! authorize. The basic blocks executed are merged into `CoverageAnalysis`
+//! and written to the coverage report.
+//!
+//! Gates without an SBF build (no `cargo build-sbf` run) are reported as
+//! such; Token-2022 is always traced from program-test's bundled build.
+//! Suite tests whose assertions are all on literals fail the gate, and so
+//! do saved fuzz inputs that still panic a gate.
+
+use borsh::BorshSerialize;
+use example_allow_list::create_allow_list_record;
+use example_block_list::{
+    block_list_address, create_block_list_record, initialize_freeze_extra_account_metas,
+    BlockReason,
+};
+use production_allow_list::{allow_list_address, ADD_TO_ALLOW_LIST, INITIALIZE};
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::Transaction,
+};
+use spl_token_2022::{instruction as token_instruction, state::AccountState};
+
+use std::path::Path;
+use token_acl_integration_tests::{
+    assertion_lint,
+    bpf_coverage::{self, ProgramCoverage, SbfProgram},
+    coverage_reporting, coverage_utils, gate_fuzz,
+    program_test::{mint_account, rent_exempt_account, token_account},
+    CoverageRequirements, CoverageResults, TestResultReport,
+};
+use token_acl_interface as interface;
+
+/// One check replayed against a gate, and whether the gate should allow it
+struct Check {
+    name: &'static str,
+    instruction: Instruction,
+    allowed: bool,
+}
+
+/// A bank with every gate loaded and one mint: `allowed` is on both allow
+/// lists, `sanctioned` is on the block list, `unlisted` is on neither
+struct CoverageFixture {
+    context: ProgramTestContext,
+    issuer: Keypair,
+    mint: Pubkey,
+    production_allow_list: Pubkey,
+    example_allow_list: Pubkey,
+    example_block_list: Pubkey,
+    hybrid_gate: Pubkey,
+    allowed: Pubkey,
+    sanctioned: Pubkey,
+    unlisted: Pubkey,
+}
+
+impl CoverageFixture {
+    async fn new() -> Self {
+        let production_allow_list = Pubkey::new_unique();
+        let example_allow_list = Pubkey::new_unique();
+        let example_block_list = Pubkey::new_unique();
+        let hybrid_gate = Pubkey::new_unique();
+        let issuer = Keypair::new();
+        let mint = Pubkey::new_unique();
+
+        let mut program_test = ProgramTest::new(
+            "production_allow_list",
+            production_allow_list,
+            processor!(production_allow_list::process_instruction),
+        );
+        program_test.add_program(
+            "example_allow_list",
+            example_allow_list,
+            processor!(example_allow_list::process_instruction),
+        );
+        program_test.add_program(
+            "example_block_list",
+            example_block_list,
+            processor!(example_block_list::process_instruction),
+        );
+        program_test.add_program(
+            "hybrid_gate",
+            hybrid_gate,
+            processor!(hybrid_gate::process_instruction),
+        );
+        program_test.add_account(mint, mint_account(&issuer.pubkey(), 0));
+
+        let mut fixture = Self {
+            context: program_test.start_with_context().await,
+            issuer,
+            mint,
+            production_allow_list,
+            example_allow_list,
+            example_block_list,
+            hybrid_gate,
+            allowed: Pubkey::new_unique(),
+            sanctioned: Pubkey::new_unique(),
+            unlisted: Pubkey::new_unique(),
+        };
+
+        let payer = fixture.context.payer.pubkey();
+        let allow_list_config =
+            production_allow_list::config_address(&mint, &production_allow_list).address();
+        let initialize_allow_list = Instruction::new_with_bytes(
+            production_allow_list,
+            &[INITIALIZE],
+            vec![
+                AccountMeta::new(allow_list_config, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(fixture.issuer.pubkey(), true),
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        let mut hybrid_data = vec![hybrid_gate::INITIALIZE];
+        hybrid_data.extend_from_slice(production_allow_list.as_ref());
+        hybrid_data.extend_from_slice(example_block_list.as_ref());
+        let initialize_hybrid = Instruction::new_with_bytes(
+            hybrid_gate,
+            &hybrid_data,
+            vec![
+                AccountMeta::new(fixture.hybrid_config(), false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(fixture.issuer.pubkey(), true),
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        let mut instructions = vec![
+            initialize_allow_list,
+            initialize_hybrid,
+            initialize_freeze_extra_account_metas(&example_block_list, &payer, &mint),
+        ];
+        for holder in [fixture.allowed, fixture.sanctioned] {
+            instructions.push(Instruction::new_with_bytes(
+                production_allow_list,
+                &[ADD_TO_ALLOW_LIST],
+                vec![
+                    AccountMeta::new_readonly(allow_list_config, false),
+                    AccountMeta::new(fixture.allow_record(&holder), false),
+                    AccountMeta::new_readonly(mint, false),
+                    AccountMeta::new_readonly(holder, false),
+                    AccountMeta::new_readonly(fixture.issuer.pubkey(), true),
+                    AccountMeta::new(payer, true),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            ));
+        }
+        let transaction = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&payer),
+            &[&fixture.context.payer, &fixture.issuer],
+            fixture.context.last_blockhash,
+        );
+        fixture
+            .context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .expect("setup should succeed");
+
+        // Neither example has an admin instruction, so their records are
+        // written directly
+        for holder in [fixture.allowed, fixture.sanctioned] {
+            let record = create_allow_list_record(&mint, &holder, 0);
+            let account =
+                rent_exempt_account(record.try_to_vec().unwrap(), fixture.example_allow_list);
+            fixture.context.set_account(
+                &example_allow_list::allow_list_address(&mint, &holder, &example_allow_list)
+                    .address(),
+                &account.into(),
+            );
+        }
+        let record =
+            create_block_list_record(&mint, &fixture.sanctioned, BlockReason::Sanctions, 0);
+        let account = rent_exempt_account(record.try_to_vec().unwrap(), example_block_list);
+        fixture
+            .context
+            .set_account(&fixture.block_record(&fixture.sanctioned), &account.into());
+        fixture
+    }
+
+    fn hybrid_config(&self) -> Pubkey {
+        hybrid_gate::config_address(&self.mint, &self.hybrid_gate).address()
+    }
+
+    fn allow_record(&self, holder: &Pubkey) -> Pubkey {
+        allow_list_address(&self.mint, holder, &self.production_allow_list).address()
+    }
+
+    fn block_record(&self, holder: &Pubkey) -> Pubkey {
+        block_list_address(&self.mint, holder, &self.example_block_list).address()
+    }
+
+    /// A new token account for `owner` in `state`
+    fn token_account(&mut self, owner: &Pubkey, state: AccountState) -> Pubkey {
+        let address = Pubkey::new_unique();
+        self.context.set_account(
+            &address,
+            &token_account(&self.mint, owner, 1_000, state).into(),
+        );
+        address
+    }
+
+    /// `gate`'s thaw check of a frozen account `holder` owns
+    fn thaw_check(&mut self, gate: Pubkey, holder: Pubkey) -> Instruction {
+        let token_account = self.token_account(&holder, AccountState::Frozen);
+        let policy = interface::find_missing_record_policy_address(&self.mint, &gate).0;
+        let extra_accounts: Vec<Pubkey> = if gate == self.production_allow_list {
+            vec![holder, self.allow_record(&holder), policy]
+        } else if gate == self.example_allow_list {
+            vec![
+                holder,
+                example_allow_list::allow_list_address(&self.mint, &holder, &gate).address(),
+            ]
+        } else if gate == self.example_block_list {
+            vec![holder, self.block_record(&holder), policy]
+        } else {
+            vec![
+                holder,
+                self.hybrid_config(),
+                self.allow_record(&holder),
+                self.block_record(&holder),
+            ]
+        };
+        let extra_accounts: Vec<_> = extra_accounts.into_iter().map(Into::into).collect();
+        interface::can_thaw_permissionless(
+            &gate,
+            holder,
+            token_account,
+            self.mint,
+            interface::find_thaw_extra_account_metas_address(&self.mint, &gate).0,
+            &extra_accounts,
+        )
+    }
+
+    /// `gate`'s freeze check of an account `holder` owns
+    fn freeze_check(&mut self, gate: Pubkey, holder: Pubkey) -> Instruction {
+        let token_account = self.token_account(&holder, AccountState::Initialized);
+        let extra_accounts: Vec<_> = if gate == self.hybrid_gate {
+            vec![
+                self.hybrid_config().into(),
+                self.block_record(&holder).into(),
+            ]
+        } else {
+            vec![self.block_record(&holder).into()]
+        };
+        interface::can_freeze_permissionless(
+            &gate,
+            self.issuer.pubkey(),
+            token_account,
+            self.mint,
+            interface::find_freeze_extra_account_metas_address(&self.mint, &gate).0,
+            &extra_accounts,
+        )
+    }
+
+    /// The checks replayed against each gate, by SBF build name
+    fn gate_checks(&mut self) -> Vec<(&'static str, Pubkey, Vec<Check>)> {
+        let (allowed, sanctioned, unlisted) = (self.allowed, self.sanctioned, self.unlisted);
+        let gates = [
+            ("production_allow_list", self.production_allow_list),
+            ("example_allow_list", self.example_allow_list),
+            ("example_block_list", self.example_block_list),
+            ("hybrid_gate", self.hybrid_gate),
+        ];
+        gates
+            .into_iter()
+            .map(|(name, gate)| {
+                let mut checks = vec![
+                    Check {
+                        name: "thaw allowed holder",
+                        instruction: self.thaw_check(gate, allowed),
+                        allowed: true,
+                    },
+                    Check {
+                        name: "thaw unlisted holder",
+                        instruction: self.thaw_check(gate, unlisted),
+                        allowed: gate == self.example_block_list,
+                    },
+                ];
+                if gate == self.example_block_list || gate == self.hybrid_gate {
+                    checks.push(Check {
+                        name: "thaw sanctioned holder",
+                        instruction: self.thaw_check(gate, sanctioned),
+                        allowed: false,
+                    });
+                    checks.push(Check {
+                        name: "freeze sanctioned holder",
+                        instruction: self.freeze_check(gate, sanctioned),
+                        allowed: true,
+                    });
+                }
+                (name, gate, checks)
+            })
+            .collect()
+    }
+
+    async fn accounts(&mut self, instruction: &Instruction) -> Vec<(Pubkey, Account)> {
+        bpf_coverage::instruction_accounts(&mut self.context.banks_client, instruction)
+            .await
+            .expect("accounts should load")
+    }
+}
+
+/// Trace every check against the gates with SBF builds, and Token-2022's
+/// freezes and thaws, then write the coverage report
+#[tokio::test]
+async fn test_execution_coverage() {
+    let (report, coverage) = run_execution_coverage_test().await;
+
+    let results = vec![report.clone()];
+    let mut analysis = coverage_utils::analyze_test_results(&results);
+    for program in &coverage {
+        analysis.merge_program_coverage(program);
+    }
+    match assertion_lint::lint_test_sources(Path::new(assertion_lint::TESTS_DIR)) {
+        Ok(lints) => analysis.record_assertion_lint(&lints),
+        Err(e) => panic!("Failed to lint test sources: {}", e),
+    }
+    match gate_fuzz::replay(Path::new(gate_fuzz::FUZZ_DIR)) {
+        Ok(replays) => analysis.record_fuzz_replays(&replays),
+        Err(e) => panic!("Failed to replay fuzz inputs: {}", e),
+    }
+    let mut coverage_results = CoverageResults {
+        analysis,
+        requirements: CoverageRequirements::default(),
+        meets_requirements: false,
+        recommendations: Vec::new(),
+    };
+    coverage_results.check_requirements();
+    println!(
+        "{}",
+        coverage_reporting::render_coverage_report(&coverage_results)
+    );
+    if let Err(e) = coverage_reporting::generate_coverage_report(
+        &coverage_results,
+        "../../tests/reports/execution_coverage.md",
+    ) {
+        panic!("Failed to generate execution coverage report: {}", e);
+    }
+
+    assert!(
+        report.passed,
+        "Execution coverage test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_execution_coverage_test() -> (TestResultReport, Vec<ProgramCoverage>) {
+    let test_name = "On-Chain Execution Coverage";
+    let mut assertions = 0;
+    let mut coverage = Vec::new();
+    let mut fixture = CoverageFixture::new().await;
+
+    let token_2022 = match SbfProgram::from_elf(
+        bpf_coverage::SPL_TOKEN_2022,
+        spl_token_2022::id(),
+        bpf_coverage::spl_token_2022_elf(),
+    ) {
+        Ok(program) => program,
+        Err(e) => return (TestResultReport::failure(test_name, e), coverage),
+    };
+    let mut token_coverage = token_2022.coverage();
+
+    // Assertion 1: A traced freeze runs to completion and executes
+    // Token-2022 code
+    assertions += 1;
+    let holder = fixture.allowed;
+    let token_account = fixture.token_account(&holder, AccountState::Initialized);
+    let freeze = token_instruction::freeze_account(
+        &spl_token_2022::id(),
+        &token_account,
+        &fixture.mint,
+        &fixture.issuer.pubkey(),
+        &[],
+    )
+    .unwrap();
+    let accounts = fixture.accounts(&freeze).await;
+    let trace = match token_2022.trace_into(&mut token_coverage, &freeze, &accounts) {
+        Ok(trace) => trace,
+        Err(e) => return (TestResultReport::failure(test_name, e), coverage),
+    };
+    if trace.result.is_err() || trace.program_counters.is_empty() {
+        return (
+            TestResultReport::failure(
+                test_name,
+                format!(
+                    "Traced freeze returned {:?} after {} instructions: {:?}",
+                    trace.result,
+                    trace.program_counters.len(),
+                    trace.logs
+                ),
+            ),
+            coverage,
+        );
+    }
+    let after_freeze = token_coverage.executed_blocks.len();
+
+    // Assertion 2: A thaw reaches blocks the freeze did not, and a thaw of
+    // an account that is not frozen fails under tracing as on the bank
+    assertions += 1;
+    let frozen = fixture.token_account(&holder, AccountState::Frozen);
+    let thaw = |account: &Pubkey| {
+        token_instruction::thaw_account(
+            &spl_token_2022::id(),
+            account,
+            &fixture.mint,
+            &fixture.issuer.pubkey(),
+            &[],
+        )
+        .unwrap()
+    };
+    let (thaw_frozen, thaw_unfrozen) = (thaw(&frozen), thaw(&token_account));
+    let accounts = fixture.accounts(&thaw_frozen).await;
+    let thawed = token_2022.trace_into(&mut token_coverage, &thaw_frozen, &accounts);
+    let accounts = fixture.accounts(&thaw_unfrozen).await;
+    let rejected = token_2022.trace_into(&mut token_coverage, &thaw_unfrozen, &accounts);
+    match (thawed, rejected) {
+        (Ok(thawed), Ok(rejected)) if thawed.result.is_ok() && rejected.result.is_err() => {}
+        (thawed, rejected) => {
+            return (
+                TestResultReport::failure(
+                    test_name,
+                    format!(
+                        "Expected the thaw to pass and the repeat to fail: {:?} / {:?}",
+                        thawed.map(|trace| trace.result),
+                        rejected.map(|trace| trace.result)
+                    ),
+                ),
+                coverage,
+            );
+        }
+    }
+    if token_coverage.executed_blocks.len() <= after_freeze
+        || token_coverage.executed_blocks.len() >= token_coverage.total_blocks
+    {
+        return (
+            TestResultReport::failure(
+                test_name,
+                format!(
+                    "Token-2022 coverage went from {} to {} of {} blocks",
+                    after_freeze,
+                    token_coverage.executed_blocks.len(),
+                    token_coverage.total_blocks
+                ),
+            ),
+            coverage,
+        );
+    }
+    coverage.push(token_coverage);
+
+    // Assertion 3: Every gate with an SBF build decides each check as it
+    // does natively, and executes its own code doing so
+    assertions += 1;
+    for (name, gate, checks) in fixture.gate_checks() {
+        let Some(elf) = bpf_coverage::find_sbf_program(name) else {
+            coverage.push(ProgramCoverage::not_built(name));
+            continue;
+        };
+        let program = match SbfProgram::from_elf(name, gate, elf) {
+            Ok(program) => program,
+            Err(e) => return (TestResultReport::failure(test_name, e), coverage),
+        };
+        let mut gate_coverage = program.coverage();
+        for check in checks {
+            let accounts = fixture.accounts(&check.instruction).await;
+            let trace = match program.trace_into(&mut gate_coverage, &check.instruction, &accounts)
+            {
+                Ok(trace) => trace,
+                Err(e) => return (TestResultReport::failure(test_name, e), coverage),
+            };
+            if trace.result.is_ok() != check.allowed || trace.program_counters.is_empty() {
+                return (
+                    TestResultReport::failure(
+                        test_name,
+                        format!(
+                            "{} {}: traced {:?}, expected {}",
+                            name,
+                            check.name,
+                            trace.result,
+                            if check.allowed { "allow" } else { "deny" }
+                        ),
+                    ),
+                    coverage,
+                );
+            }
+        }
+        coverage.push(gate_coverage);
+    }
+
+    // Assertion 4: Coverage of the same program merges as a union
+    assertions += 1;
+    let mut merged = coverage[0].clone();
+    merged.merge(&coverage[0]);
+    if merged.executed_blocks != coverage[0].executed_blocks
+        || merged.instructions_traced != 2 * coverage[0].instructions_traced
+    {
+        return (
+            TestResultReport::failure(test_name, "Merging coverage is not a union".to_string()),
+            coverage,
+        );
+    }
+
+    (TestResultReport::success(test_name, assertions), coverage)
+}
@@ -0,0 +1,483 @@
+//! Extra-account-metas list tests
+//!
+//! The example gates and the production allow list create their thaw
+//! extra-account-metas through the `extra_account_metas` crate, and the
+//! block list its freeze list. These
+//! tests create the lists in program-test through `program_test::GateHarness`,
+//! read them back with `ListState`, then resolve the permissionless checks
+//! from on-chain data alone, with `resolve_extra_accounts`, and send them to
+//! the gates: a resolved check must reach the same decision a hand-built one
+//! does.
+
+use example_block_list::GATE_INTERFACE_VERSION as BLOCK_LIST_INTERFACE_VERSION;
+use extra_account_metas::{resolve_extra_accounts, ListKind, ListState, INTERFACE_ACCOUNTS};
+use solana_sdk::{
+    instruction::{Instruction, InstructionError},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    signature::Signer,
+    transaction::TransactionError,
+};
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, state::AccountFetchError};
+use spl_token_2022::state::AccountState;
+use token_acl_interface::{
+    self as interface, find_missing_record_policy_address, GateInterfaceVersion,
+};
+
+use token_acl_integration_tests::{
+    program_test::{Gate, GateHarness},
+    reporting, TestResultReport,
+};
+
+/// The gates that create a thaw list
+const THAW_GATES: [Gate; 3] = [Gate::AllowList, Gate::BlockList, Gate::ProductionAllowList];
+
+fn expect_error(
+    result: Result<(), TransactionError>,
+    expected: InstructionError,
+    case: &str,
+) -> Result<(), String> {
+    match result {
+        Ok(()) => Err(format!("{} was accepted", case)),
+        Err(TransactionError::InstructionError(0, error)) if error == expected => Ok(()),
+        Err(e) => Err(format!(
+            "{} failed with {:?}, expected {:?}",
+            case, e, expected
+        )),
+    }
+}
+
+fn initialize_thaw_list(harness: &GateHarness, gate: Gate) -> Instruction {
+    let payer = harness.context.payer.pubkey();
+    let program_id = harness.program_id(gate);
+    match gate {
+        Gate::AllowList => example_allow_list::initialize_thaw_extra_account_metas(
+            &program_id,
+            &payer,
+            &harness.mint,
+        ),
+        Gate::BlockList => example_block_list::initialize_thaw_extra_account_metas(
+            &program_id,
+            &payer,
+            &harness.mint,
+        ),
+        Gate::ProductionAllowList => production_allow_list::initialize_thaw_extra_account_metas(
+            &program_id,
+            &payer,
+            &harness.mint,
+        ),
+    }
+}
+
+/// What `gate`'s thaw list must hold
+fn expected_thaw_list(gate: Gate) -> (GateInterfaceVersion, Vec<ExtraAccountMeta>) {
+    match gate {
+        Gate::AllowList => (
+            example_allow_list::GATE_INTERFACE_VERSION,
+            example_allow_list::thaw_extra_account_metas().unwrap(),
+        ),
+        Gate::BlockList => (
+            BLOCK_LIST_INTERFACE_VERSION,
+            example_block_list::thaw_extra_account_metas().unwrap(),
+        ),
+        Gate::ProductionAllowList => (
+            production_allow_list::GATE_INTERFACE_VERSION,
+            production_allow_list::thaw_extra_account_metas().unwrap(),
+        ),
+    }
+}
+
+/// `mint`'s `kind` list under `gate`, as stored on chain
+async fn list_state(harness: &mut GateHarness, gate: Gate, kind: ListKind) -> Option<ListState> {
+    let (address, _) = kind.find_address(&harness.mint, &harness.program_id(gate));
+    let account = harness.account(&address).await?;
+    ListState::unpack(kind, &account.data).ok()
+}
+
+/// `gate`'s `kind` check of `token_account`, resolved from on-chain data
+/// after the `leading` accounts, as a caller holding only the token account
+/// and its owner builds it
+async fn resolve_check(
+    harness: &GateHarness,
+    gate: Gate,
+    kind: ListKind,
+    token_account: &Pubkey,
+    leading: &[Pubkey],
+) -> Result<Instruction, ProgramError> {
+    let program_id = harness.program_id(gate);
+    let (list, _) = kind.find_address(&harness.mint, &program_id);
+    let caller = Pubkey::new_unique();
+    let check = match kind {
+        ListKind::Thaw => interface::can_thaw_permissionless(
+            &program_id,
+            caller,
+            token_account,
+            harness.mint,
+            list,
+            &[],
+        ),
+        ListKind::Freeze => interface::can_freeze_permissionless(
+            &program_id,
+            caller,
+            token_account,
+            harness.mint,
+            list,
+            &[],
+        ),
+    };
+    let client = harness.context.client();
+    resolve_extra_accounts(check, leading, |address| {
+        let mut client = client.clone();
+        async move {
+            client
+                .get_account(address)
+                .await
+                .map(|account| account.map(|a| a.data))
+                .map_err(|e| Box::new(e) as AccountFetchError)
+        }
+    })
+    .await
+}
+
+/// Test 1: Every thaw gate creates its thaw list once, at the mint's PDA
+#[tokio::test]
+async fn test_thaw_list_creation() {
+    let report = run_thaw_list_creation_test().await;
+    assert!(
+        report.passed,
+        "Thaw list creation test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_thaw_list_creation_test() -> TestResultReport {
+    let test_name = "Thaw List Creation";
+    let mut assertions = 0;
+
+    let mut harness = match GateHarness::start(test_name).await {
+        Ok(harness) => harness,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+
+    for gate in THAW_GATES {
+        // Assertion 1: Creation refuses any address but the mint's PDA
+        assertions += 1;
+        let mut init = initialize_thaw_list(&harness, gate);
+        init.accounts[1].pubkey = Pubkey::new_unique();
+        let result = harness.send(&[init], &[]).await;
+        if let Err(e) = expect_error(
+            result,
+            InstructionError::InvalidSeeds,
+            &format!("{:?} thaw list at a non-PDA address", gate),
+        ) {
+            return TestResultReport::failure(test_name, e);
+        }
+
+        // Assertion 2: The list holds the gate's metas and interface version
+        // at version 1
+        assertions += 1;
+        let init = initialize_thaw_list(&harness, gate);
+        if let Err(e) = harness.send(&[init], &[]).await {
+            return TestResultReport::failure(
+                test_name,
+                format!("{:?} thaw list creation failed: {:?}", gate, e),
+            );
+        }
+        let (interface_version, metas) = expected_thaw_list(gate);
+        let expected = ListState {
+            version: 1,
+            interface_version,
+            metas,
+        };
+        let state = list_state(&mut harness, gate, ListKind::Thaw).await;
+        if state.as_ref() != Some(&expected) {
+            return TestResultReport::failure(
+                test_name,
+                format!("{:?} thaw list was {:?}", gate, state),
+            );
+        }
+
+        // Assertion 3: A second creation is rejected
+        assertions += 1;
+        let init = initialize_thaw_list(&harness, gate);
+        let result = harness.send(&[init], &[]).await;
+        if let Err(e) = expect_error(
+            result,
+            InstructionError::AccountAlreadyInitialized,
+            &format!("Second {:?} thaw list", gate),
+        ) {
+            return TestResultReport::failure(test_name, e);
+        }
+    }
+
+    // Assertion 4: The block list's freeze list is a separate list, still at
+    // version 1
+    assertions += 1;
+    let freeze = list_state(&mut harness, Gate::BlockList, ListKind::Freeze).await;
+    let expected = ListState {
+        version: 1,
+        interface_version: BLOCK_LIST_INTERFACE_VERSION,
+        metas: example_block_list::freeze_extra_account_metas().unwrap(),
+    };
+    if freeze.as_ref() != Some(&expected) {
+        return TestResultReport::failure(test_name, format!("Freeze list was {:?}", freeze));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: Thaw checks resolved from the lists reach the gates' decisions
+#[tokio::test]
+async fn test_thaw_resolution() {
+    let report = run_thaw_resolution_test().await;
+    assert!(
+        report.passed,
+        "Thaw resolution test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_thaw_resolution_test() -> TestResultReport {
+    let test_name = "Thaw Resolution";
+    let mut assertions = 0;
+
+    let mut harness = match GateHarness::start(test_name).await {
+        Ok(harness) => harness,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let (approved, denied) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let approved_account = harness.create_token_account(&approved, AccountState::Frozen);
+    let denied_account = harness.create_token_account(&denied, AccountState::Frozen);
+    harness.set_listed(&approved, true);
+    harness.set_blocked(&denied, true);
+    let add = harness.add_to_allow_list(&approved, &harness.issuer.pubkey(), true);
+    if let Err(e) = harness.send(&[add], &[]).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("Production allow list add failed: {:?}", e),
+        );
+    }
+
+    for gate in THAW_GATES {
+        // Assertion 1: Resolution fails until the gate's thaw list exists
+        assertions += 1;
+        match resolve_check(
+            &harness,
+            gate,
+            ListKind::Thaw,
+            &approved_account,
+            &[approved],
+        )
+        .await
+        {
+            Err(ProgramError::UninitializedAccount) => {}
+            other => {
+                return TestResultReport::failure(
+                    test_name,
+                    format!("{:?} resolved without a thaw list: {:?}", gate, other),
+                )
+            }
+        }
+        let init = initialize_thaw_list(&harness, gate);
+        if let Err(e) = harness.send(&[init], &[]).await {
+            return TestResultReport::failure(
+                test_name,
+                format!("{:?} thaw list creation failed: {:?}", gate, e),
+            );
+        }
+
+        // Assertion 2: The owner is followed by exactly the accounts the
+        // gate reads: its record, then the policy PDA of the gates that
+        // have one
+        assertions += 1;
+        let check = match resolve_check(
+            &harness,
+            gate,
+            ListKind::Thaw,
+            &approved_account,
+            &[approved],
+        )
+        .await
+        {
+            Ok(check) => check,
+            Err(e) => {
+                return TestResultReport::failure(
+                    test_name,
+                    format!("{:?} thaw resolution failed: {}", gate, e),
+                )
+            }
+        };
+        let program_id = harness.program_id(gate);
+        let mut expected = vec![approved, harness.record_address(gate, &approved)];
+        if gate != Gate::AllowList {
+            expected.push(find_missing_record_policy_address(&harness.mint, &program_id).0);
+        }
+        let resolved: Vec<Pubkey> = check.accounts[INTERFACE_ACCOUNTS..]
+            .iter()
+            .map(|meta| meta.pubkey)
+            .collect();
+        if resolved != expected {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "{:?} resolved {:?}, expected {:?}",
+                    gate, resolved, expected
+                ),
+            );
+        }
+
+        // Assertion 3: The gate approves the approved owner's resolved check
+        assertions += 1;
+        if let Err(e) = harness.send(&[check], &[]).await {
+            return TestResultReport::failure(
+                test_name,
+                format!("{:?} denied a resolved thaw: {:?}", gate, e),
+            );
+        }
+
+        // Assertion 4: The gate denies the other owner's resolved check
+        assertions += 1;
+        let check =
+            match resolve_check(&harness, gate, ListKind::Thaw, &denied_account, &[denied]).await {
+                Ok(check) => check,
+                Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+            };
+        let result = harness.send(&[check], &[]).await;
+        if let Err(e) = expect_error(
+            result,
+            InstructionError::InvalidAccountData,
+            &format!("{:?} thaw of a denied owner", gate),
+        ) {
+            return TestResultReport::failure(test_name, e);
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: Freeze checks resolved from the block list's freeze list
+#[tokio::test]
+async fn test_freeze_resolution() {
+    let report = run_freeze_resolution_test().await;
+    assert!(
+        report.passed,
+        "Freeze resolution test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_freeze_resolution_test() -> TestResultReport {
+    let test_name = "Freeze Resolution";
+    let mut assertions = 0;
+
+    let mut harness = match GateHarness::start(test_name).await {
+        Ok(harness) => harness,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let (blocked, cleared) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let blocked_account = harness.create_token_account(&blocked, AccountState::Initialized);
+    let cleared_account = harness.create_token_account(&cleared, AccountState::Initialized);
+    harness.set_blocked(&blocked, true);
+    harness.set_blocked(&cleared, false);
+
+    // Assertion 1: The allow list never freezes, so it has no freeze list to
+    // resolve
+    assertions += 1;
+    match resolve_check(
+        &harness,
+        Gate::AllowList,
+        ListKind::Freeze,
+        &blocked_account,
+        &[],
+    )
+    .await
+    {
+        Err(ProgramError::UninitializedAccount) => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Allow list freeze resolved: {:?}", other),
+            )
+        }
+    }
+
+    // Assertion 2: Without leading accounts, the block list's freeze list
+    // resolves the owner's record from the token account alone
+    assertions += 1;
+    let check = match resolve_check(
+        &harness,
+        Gate::BlockList,
+        ListKind::Freeze,
+        &blocked_account,
+        &[],
+    )
+    .await
+    {
+        Ok(check) => check,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+    let record = harness.record_address(Gate::BlockList, &blocked);
+    if check.accounts.len() != INTERFACE_ACCOUNTS + 1
+        || check.accounts[INTERFACE_ACCOUNTS].pubkey != record
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Resolved accounts were {:?}",
+                &check.accounts[INTERFACE_ACCOUNTS..]
+            ),
+        );
+    }
+
+    // Assertion 3: The gate approves freezing the blocked owner
+    assertions += 1;
+    if let Err(e) = harness.send(&[check], &[]).await {
+        return TestResultReport::failure(test_name, format!("Blocked owner not frozen: {:?}", e));
+    }
+
+    // Assertion 4: The gate denies freezing the cleared owner
+    assertions += 1;
+    let check = match resolve_check(
+        &harness,
+        Gate::BlockList,
+        ListKind::Freeze,
+        &cleared_account,
+        &[],
+    )
+    .await
+    {
+        Ok(check) => check,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+    let result = harness.send(&[check], &[]).await;
+    if let Err(e) = expect_error(
+        result,
+        InstructionError::InvalidAccountData,
+        "Freeze of a cleared owner",
+    ) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate extra-account-metas report
+#[tokio::test]
+async fn generate_extra_account_metas_report() {
+    let results = vec![
+        run_thaw_list_creation_test().await,
+        run_thaw_resolution_test().await,
+        run_freeze_resolution_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Extra-Account-Metas Test Results",
+        "../../tests/reports/extra_account_metas_tests.md",
+    ) {
+        panic!("Failed to generate extra-account-metas report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} extra-account-metas tests failed", failed);
+}
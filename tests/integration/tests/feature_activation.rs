@@ -0,0 +1,502 @@
+//! Feature-gate activation tests
+//!
+//! A cluster upgrade activates feature gates at an epoch boundary. These
+//! tests start banks without a gate, activate it mid-run through the
+//! harness, and pin what an issuer would see change: the write privileges
+//! the runtime grants a gate program over the accounts it is passed, and
+//! what Token-2022 does to the holders' token accounts.
+
+use production_allow_list::{
+    RemoveMode, ADD_TO_ALLOW_LIST, ALLOW_LIST_SEED, CONFIG_SEED, INITIALIZE, REMOVE_FROM_ALLOW_LIST,
+};
+use solana_program_test::{processor, ProgramTest};
+use solana_sdk::{
+    account::AccountSharedData,
+    clock::Epoch,
+    feature_set,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+use spl_token_2022::{instruction as token_instruction, state::AccountState};
+
+use token_acl_integration_tests::{
+    compatibility::{CompatibilityMatrix, FeatureProfile, Observation},
+    context::{Namespace, TestContext},
+    program_test::{mint_account, token_account},
+    reporting, TestResultReport,
+};
+
+/// Send `instructions` signed by the payer and `signers`; the compute units
+/// they consumed
+async fn send(
+    context: &mut TestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<u64, String> {
+    let payer = context.payer();
+    let mut all_signers = vec![&payer];
+    all_signers.extend_from_slice(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&payer.pubkey()),
+        &all_signers,
+        context.last_blockhash,
+    );
+    let outcome = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .map_err(|e| e.to_string())?;
+    outcome.result.map_err(|e| e.to_string())?;
+    Ok(outcome
+        .metadata
+        .map(|metadata| metadata.compute_units_consumed)
+        .unwrap_or_default())
+}
+
+/// A record the allow list gate keeps, on a bank started under `profile`
+struct ListedHolder {
+    context: TestContext,
+    gate: Pubkey,
+    authority: Keypair,
+    config: Pubkey,
+    record: Pubkey,
+}
+
+impl ListedHolder {
+    async fn start(test_name: &str, profile: &FeatureProfile) -> Result<Self, String> {
+        let gate = Pubkey::new_unique();
+        let mut program_test = ProgramTest::new(
+            "production_allow_list",
+            gate,
+            processor!(production_allow_list::process_instruction),
+        );
+        profile.apply(&mut program_test);
+        let mut context = TestContext::start(test_name, program_test).await?;
+        let authority = context.namespace().keypair("authority");
+        let mint = context.namespace().address("mint");
+        let holder = context.namespace().address("holder");
+        let (config, _) = Pubkey::find_program_address(&[CONFIG_SEED, mint.as_ref()], &gate);
+        let (record, _) =
+            Pubkey::find_program_address(&[ALLOW_LIST_SEED, mint.as_ref(), holder.as_ref()], &gate);
+        let payer = context.payer.pubkey();
+
+        let initialize = Instruction::new_with_bytes(
+            gate,
+            &[INITIALIZE],
+            vec![
+                AccountMeta::new(config, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(authority.pubkey(), true),
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        let add = Instruction::new_with_bytes(
+            gate,
+            &[ADD_TO_ALLOW_LIST],
+            vec![
+                AccountMeta::new_readonly(config, false),
+                AccountMeta::new(record, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(holder, false),
+                AccountMeta::new_readonly(authority.pubkey(), true),
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        send(&mut context, &[initialize, add], &[&authority])
+            .await
+            .map_err(|e| format!("Listing the holder failed: {}", e))?;
+
+        Ok(Self {
+            context,
+            gate,
+            authority,
+            config,
+            record,
+        })
+    }
+
+    /// Close the record, refunding its rent to a fresh recipient
+    async fn close(&mut self) -> Result<u64, String> {
+        let recipient = Pubkey::new_unique();
+        let close = Instruction::new_with_bytes(
+            self.gate,
+            &[REMOVE_FROM_ALLOW_LIST, RemoveMode::Close as u8],
+            vec![
+                AccountMeta::new_readonly(self.config, false),
+                AccountMeta::new(self.record, false),
+                AccountMeta::new_readonly(self.authority.pubkey(), true),
+                AccountMeta::new(recipient, false),
+            ],
+        );
+        let authority = self.authority.insecure_clone();
+        send(&mut self.context, &[close], &[&authority]).await
+    }
+}
+
+/// Before `deprecate_executable_meta_update_in_bpf_loader`, an account
+/// flagged executable is write-protected whoever owns it; after it, only
+/// loader-owned programs are
+#[tokio::test]
+async fn test_executable_flag_privileges() {
+    let report = run_executable_flag_privileges_test().await;
+    assert!(
+        report.passed,
+        "Executable flag privileges test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_executable_flag_privileges_test() -> TestResultReport {
+    let test_name = "Feature Activation: Executable Flag Privileges";
+    let mut assertions = 0;
+
+    let feature = feature_set::deprecate_executable_meta_update_in_bpf_loader::id();
+    let profile = FeatureProfile::without("before executable deprecation", &[feature]);
+    let mut listed = match ListedHolder::start(test_name, &profile).await {
+        Ok(listed) => listed,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+
+    // A record that carries the executable flag, e.g. one migrated from an
+    // old deployment; the gate still owns it
+    let record = match listed.context.banks_client.get_account(listed.record).await {
+        Ok(Some(record)) => record,
+        other => return TestResultReport::failure(test_name, format!("Record {:?}", other)),
+    };
+    let mut flagged = record.clone();
+    flagged.executable = true;
+    listed
+        .context
+        .set_account(&listed.record, &AccountSharedData::from(flagged));
+
+    // Assertion: the feature starts inactive
+    assertions += 1;
+    if listed.context.is_feature_active(&feature).await != Ok(false) {
+        return TestResultReport::failure(test_name, "Feature started active".to_string());
+    }
+
+    // Assertion: before activation, the gate may not drain the record
+    assertions += 1;
+    let denied = TransactionError::InstructionError(0, InstructionError::ExecutableLamportChange);
+    let before = listed.close().await;
+    if before != Err(denied.to_string()) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Close before activation: {:?}", before),
+        );
+    }
+    let kept = listed
+        .context
+        .banks_client
+        .get_balance(listed.record)
+        .await
+        .ok();
+    if kept != Some(record.lamports) {
+        return TestResultReport::failure(test_name, format!("Record balance {:?}", kept));
+    }
+
+    // Assertion: the feature activates at the next epoch boundary
+    assertions += 1;
+    let slot = match listed.context.activate_feature(feature).await {
+        Ok(slot) => slot,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let schedule = listed.context.genesis_config().epoch_schedule.clone();
+    if schedule.get_first_slot_in_epoch(schedule.get_epoch(slot)) != slot {
+        return TestResultReport::failure(
+            test_name,
+            format!("Activated mid-epoch at slot {}", slot),
+        );
+    }
+
+    // Assertion: after activation, the same close succeeds
+    assertions += 1;
+    if let Err(e) = listed.close().await {
+        return TestResultReport::failure(test_name, format!("Close after activation: {}", e));
+    }
+    if !matches!(
+        listed.context.banks_client.get_account(listed.record).await,
+        Ok(None)
+    ) {
+        return TestResultReport::failure(test_name, "Record survived the close".to_string());
+    }
+
+    // Assertion: activating an active feature is refused
+    assertions += 1;
+    if listed.context.activate_feature(feature).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Activated an active feature twice".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// A Token-2022 mint with two holder accounts, on a bank started under
+/// `profile`
+struct TokenBank {
+    context: TestContext,
+    issuer: Keypair,
+    owner: Keypair,
+    mint: Pubkey,
+    accounts: [Pubkey; 2],
+}
+
+impl TokenBank {
+    async fn start(test_name: &str, profile: &FeatureProfile) -> Result<Self, String> {
+        let namespace = Namespace::new(test_name);
+        let issuer = namespace.keypair("issuer");
+        let owner = namespace.keypair("holder");
+        let mint = namespace.address("mint");
+        let accounts = [namespace.address("holder_a"), namespace.address("holder_b")];
+
+        let mut program_test = ProgramTest::default();
+        profile.apply(&mut program_test);
+        program_test.add_account(mint, mint_account(&issuer.pubkey(), 2_000));
+        for account in accounts {
+            program_test.add_account(
+                account,
+                token_account(&mint, &owner.pubkey(), 1_000, AccountState::Initialized),
+            );
+        }
+
+        let context = TestContext::start(test_name, program_test).await?;
+        Ok(Self {
+            context,
+            issuer,
+            owner,
+            mint,
+            accounts,
+        })
+    }
+
+    /// Transfer between the holder's accounts, then freeze and thaw the
+    /// sender: the result and compute units of each step
+    async fn observe(&mut self) -> Observation {
+        let [from, to] = self.accounts;
+        let program = spl_token_2022::id();
+        let steps = [
+            (
+                "transfer",
+                token_instruction::transfer_checked(
+                    &program,
+                    &from,
+                    &self.mint,
+                    &to,
+                    &self.owner.pubkey(),
+                    &[],
+                    5,
+                    6,
+                ),
+                &self.owner,
+            ),
+            (
+                "freeze",
+                token_instruction::freeze_account(
+                    &program,
+                    &from,
+                    &self.mint,
+                    &self.issuer.pubkey(),
+                    &[],
+                ),
+                &self.issuer,
+            ),
+            (
+                "thaw",
+                token_instruction::thaw_account(
+                    &program,
+                    &from,
+                    &self.mint,
+                    &self.issuer.pubkey(),
+                    &[],
+                ),
+                &self.issuer,
+            ),
+        ];
+        let mut observation = Observation::new();
+        for (step, instruction, signer) in steps {
+            let outcome = match instruction {
+                Ok(instruction) => send(&mut self.context, &[instruction], &[signer]).await,
+                Err(e) => Err(e.to_string()),
+            };
+            observation = match outcome {
+                Ok(units) => observation
+                    .with(step, "ok")
+                    .with(&format!("{} compute units", step), units),
+                Err(e) => observation.with(step, e),
+            };
+        }
+        observation
+    }
+
+    async fn rent_epoch(&mut self, account: Pubkey) -> Option<Epoch> {
+        let account = self
+            .context
+            .banks_client
+            .get_account(account)
+            .await
+            .ok()??;
+        Some(account.rent_epoch)
+    }
+}
+
+/// Token-2022 freezes, thaws and transfers the same, for the same compute
+/// units, with or without the gates that change how programs load and run
+#[tokio::test]
+async fn test_token_2022_across_feature_gates() {
+    let report = run_token_2022_across_feature_gates_test().await;
+    assert!(
+        report.passed,
+        "Token-2022 feature gate test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_token_2022_across_feature_gates_test() -> TestResultReport {
+    let test_name = "Feature Activation: Token-2022 Across Gates";
+    let mut assertions = 0;
+
+    let mut matrix = CompatibilityMatrix::new(FeatureProfile::all_active("current"));
+    for (name, feature) in [
+        (
+            "before curve25519 syscalls",
+            feature_set::curve25519_syscall_enabled::id(),
+        ),
+        (
+            "before direct mapping",
+            feature_set::bpf_account_data_direct_mapping::id(),
+        ),
+        (
+            "before executable deprecation",
+            feature_set::deprecate_executable_meta_update_in_bpf_loader::id(),
+        ),
+        (
+            "before callx r10 rejection",
+            feature_set::reject_callx_r10::id(),
+        ),
+        (
+            "before the new ELF parser",
+            feature_set::switch_to_new_elf_parser::id(),
+        ),
+    ] {
+        matrix.add_profile(FeatureProfile::without(name, &[feature]));
+    }
+    for profile in matrix.profiles().to_vec() {
+        let mut bank = match TokenBank::start(test_name, &profile).await {
+            Ok(bank) => bank,
+            Err(e) => return TestResultReport::failure(test_name, e),
+        };
+        matrix.record(profile.name(), "holder operations", bank.observe().await);
+    }
+
+    // Assertion: every operation lands on the newest runtime
+    assertions += 1;
+    let baseline = matrix.observation("current", "holder operations");
+    for step in ["transfer", "freeze", "thaw"] {
+        if baseline.and_then(|observation| observation.get(step)) != Some("ok") {
+            return TestResultReport::failure(
+                test_name,
+                format!("{} failed: {:?}", step, baseline),
+            );
+        }
+    }
+
+    // Assertion: no gate changes a result or a compute unit
+    assertions += 1;
+    let differences = matrix.differences();
+    if !differences.is_empty() {
+        return TestResultReport::failure(
+            test_name,
+            format!("Token-2022 changed:\n{}", matrix.render_markdown()),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Token accounts Token-2022 writes are stamped exempt from rent collection
+/// once `set_exempt_rent_epoch_max` activates, and not before
+#[tokio::test]
+async fn test_token_account_rent_epoch_activation() {
+    let report = run_token_account_rent_epoch_activation_test().await;
+    assert!(
+        report.passed,
+        "Token account rent epoch test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_token_account_rent_epoch_activation_test() -> TestResultReport {
+    let test_name = "Feature Activation: Token Account Rent Epoch";
+    let mut assertions = 0;
+
+    let feature = feature_set::set_exempt_rent_epoch_max::id();
+    let profile = FeatureProfile::without("before exempt rent epoch max", &[feature]);
+    let mut bank = match TokenBank::start(test_name, &profile).await {
+        Ok(bank) => bank,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let [sender, _] = bank.accounts;
+
+    // Assertion: before activation, a written token account keeps epoch 0
+    assertions += 1;
+    let before = bank.observe().await;
+    if before.get("transfer") != Some("ok") || bank.rent_epoch(sender).await != Some(0) {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Before activation: {:?}, rent epoch {:?}",
+                before,
+                bank.rent_epoch(sender).await
+            ),
+        );
+    }
+
+    // Assertion: after activation, the next write stamps it Epoch::MAX
+    assertions += 1;
+    if let Err(e) = bank.context.activate_feature(feature).await {
+        return TestResultReport::failure(test_name, e);
+    }
+    let after = bank.observe().await;
+    if after.get("transfer") != Some("ok") || bank.rent_epoch(sender).await != Some(Epoch::MAX) {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "After activation: {:?}, rent epoch {:?}",
+                after,
+                bank.rent_epoch(sender).await
+            ),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate the feature activation report
+#[tokio::test]
+async fn generate_feature_activation_report() {
+    let results = vec![
+        run_executable_flag_privileges_test().await,
+        run_token_2022_across_feature_gates_test().await,
+        run_token_account_rent_epoch_activation_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Feature Activation Results",
+        "../../tests/reports/feature_activation.md",
+    ) {
+        panic!("Failed to generate feature activation report: {}", e);
+    }
+
+    for result in &results {
+        assert!(result.passed, "{} failed: {:?}", result.name, result.error);
+    }
+}
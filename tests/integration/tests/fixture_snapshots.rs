@@ -0,0 +1,429 @@
+//! Fixture snapshot tests
+//!
+//! The committed allow list snapshot stands in for the transactions that
+//! would build it, so these tests hold it to them: a build is deterministic,
+//! the artifact is exactly what a build produces today, and a bank booted
+//! from it holds the built accounts and behaves like the built bank, for
+//! the holders it lists, users it does not, and the authority's
+//! instructions.
+
+use borsh::BorshDeserialize;
+use extra_account_metas::{resolve_extra_accounts, ListKind};
+use production_allow_list::{AllowListRecord, Config, ADD_TO_ALLOW_LIST};
+use solana_sdk::{
+    clock::Clock,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    pubkey::Pubkey,
+    signature::Signer,
+    system_program,
+    transaction::Transaction,
+};
+use spl_tlv_account_resolution::state::AccountFetchError;
+use token_acl_interface as interface;
+
+use token_acl_integration_tests::{
+    common::nonce,
+    context::TestContext,
+    fixture_snapshots::{FixtureSnapshot, ALLOW_LIST_FIXTURE, ALLOW_LIST_FIXTURE_HOLDERS},
+    reporting, TestResultReport,
+};
+
+/// Holders of the snapshots the tests build for themselves
+const SMALL_FIXTURE_HOLDERS: usize = 12;
+
+/// Send the thaw check for `token_account` of `owner`, resolved from the
+/// snapshot's extra-account-metas list
+async fn resolved_thaw(
+    snapshot: &FixtureSnapshot,
+    context: &mut TestContext,
+    token_account: &Pubkey,
+    owner: &Pubkey,
+) -> Result<(), String> {
+    let (list, _) = ListKind::Thaw.find_address(&snapshot.mint(), &snapshot.gate_program_id);
+    let check = interface::can_thaw_permissionless(
+        &snapshot.gate_program_id,
+        Pubkey::new_unique(),
+        token_account,
+        snapshot.mint(),
+        list,
+        &[],
+    );
+    let client = context.client();
+    let check = resolve_extra_accounts(check, &[*owner], |address| {
+        let mut client = client.clone();
+        async move {
+            client
+                .get_account(address)
+                .await
+                .map(|account| account.map(|a| a.data))
+                .map_err(|e| Box::new(e) as AccountFetchError)
+        }
+    })
+    .await
+    .map_err(|e| format!("Failed to resolve the thaw check: {}", e))?;
+
+    let payer = context.payer();
+    let blockhash = context
+        .get_new_latest_blockhash()
+        .await
+        .map_err(|e| e.to_string())?;
+    let transaction =
+        Transaction::new_signed_with_payer(&[check], Some(&payer.pubkey()), &[&payer], blockhash);
+    nonce::submit(&mut context.banks_client, transaction).await
+}
+
+/// Test 1: Two builds of the same snapshot are byte-identical, and the
+/// JSON artifact round-trips
+#[tokio::test]
+async fn test_build_is_deterministic() {
+    let report = run_build_is_deterministic_test().await;
+    assert!(
+        report.passed,
+        "Build is deterministic test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_build_is_deterministic_test() -> TestResultReport {
+    let test_name = "Fixture Snapshots: Deterministic Build";
+    let mut assertions = 0;
+
+    let first = match FixtureSnapshot::build("deterministic", SMALL_FIXTURE_HOLDERS).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let second = match FixtureSnapshot::build("deterministic", SMALL_FIXTURE_HOLDERS).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+
+    // Assertion 1: The mint, config, thaw list and a record per holder,
+    // in that order
+    assertions += 1;
+    let labels: Vec<&str> = first
+        .accounts
+        .iter()
+        .map(|entry| entry.label.as_str())
+        .collect();
+    if labels.len() != 3 + SMALL_FIXTURE_HOLDERS
+        || labels[..4] != ["mint", "config", "thaw_extra_account_metas", "holder-000"]
+        || first.holders().len() != SMALL_FIXTURE_HOLDERS
+    {
+        return TestResultReport::failure(test_name, format!("Accounts {:?}", labels));
+    }
+
+    // Assertion 2: Both builds hold the same accounts
+    assertions += 1;
+    if first != second {
+        return TestResultReport::failure(test_name, "Two builds differ".to_string());
+    }
+
+    // Assertion 3: The JSON reads back into the same snapshot
+    assertions += 1;
+    let json = match first.to_json() {
+        Ok(json) => json,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    match FixtureSnapshot::from_json(&json) {
+        Ok(read) if read == first => {}
+        Ok(_) => {
+            return TestResultReport::failure(test_name, "The JSON reads back differently".into())
+        }
+        Err(e) => return TestResultReport::failure(test_name, e),
+    }
+
+    // Assertion 4: Another name is another mint, authority and gate
+    assertions += 1;
+    let other = match FixtureSnapshot::build("other", 1).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    if other.mint() == first.mint() || other.gate_program_id == first.gate_program_id {
+        return TestResultReport::failure(test_name, "Names share keys".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: The committed allow list snapshot is what a build produces
+/// today
+#[tokio::test]
+async fn test_committed_snapshot_is_current() {
+    let report = run_committed_snapshot_is_current_test().await;
+    assert!(
+        report.passed,
+        "Committed snapshot is current test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_committed_snapshot_is_current_test() -> TestResultReport {
+    let test_name = "Fixture Snapshots: Committed Artifact";
+    let mut assertions = 0;
+
+    // Assertion 1: The artifact is committed and reads
+    assertions += 1;
+    let path = FixtureSnapshot::path(ALLOW_LIST_FIXTURE);
+    let committed = match FixtureSnapshot::load(&path) {
+        Ok(snapshot) => snapshot,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+
+    // Assertion 2: A fresh build renders to the same bytes
+    assertions += 1;
+    let built = match FixtureSnapshot::build(ALLOW_LIST_FIXTURE, ALLOW_LIST_FIXTURE_HOLDERS).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let (Ok(committed_json), Ok(built_json)) = (committed.to_json(), built.to_json()) else {
+        return TestResultReport::failure(test_name, "Failed to render".to_string());
+    };
+    let on_disk = std::fs::read_to_string(&path).unwrap_or_default();
+    if built_json != on_disk || committed_json != on_disk {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "{} is stale; regenerate it with UPDATE_FIXTURE_SNAPSHOTS=1",
+                path.display()
+            ),
+        );
+    }
+
+    // Assertion 3: Its config and records belong to its mint and authority
+    assertions += 1;
+    let config = committed
+        .account("config")
+        .and_then(|entry| Config::try_from_slice(&entry.account.data).ok());
+    let records_match = committed
+        .accounts
+        .iter()
+        .filter(|entry| entry.label.starts_with("holder-"))
+        .all(|entry| {
+            AllowListRecord::try_from_slice(&entry.account.data).is_ok_and(|record| {
+                record.mint == committed.mint() && record.allowed && !record.revoked
+            })
+        });
+    if !config.is_some_and(|config| {
+        config.mint == committed.mint() && config.authority == committed.authority().pubkey()
+    }) || !records_match
+        || committed.holders().len() != ALLOW_LIST_FIXTURE_HOLDERS
+    {
+        return TestResultReport::failure(test_name, "Accounts do not match".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: A bank booted from the snapshot thaws its holders, denies
+/// others, and takes the authority's instructions
+#[tokio::test]
+async fn test_booted_bank() {
+    let report = run_booted_bank_test().await;
+    assert!(report.passed, "Booted bank test failed: {:?}", report.error);
+}
+
+async fn run_booted_bank_test() -> TestResultReport {
+    let test_name = "Fixture Snapshots: Booted Bank";
+    let mut assertions = 0;
+
+    let snapshot =
+        match FixtureSnapshot::committed(ALLOW_LIST_FIXTURE, ALLOW_LIST_FIXTURE_HOLDERS).await {
+            Ok(snapshot) => snapshot,
+            Err(e) => return TestResultReport::failure(test_name, e),
+        };
+    let mut context = match snapshot.boot(test_name).await {
+        Ok(context) => context,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+
+    // Assertion 1: Every account of the snapshot is on the bank as
+    // committed
+    assertions += 1;
+    for entry in &snapshot.accounts {
+        match context.banks_client.get_account(entry.address).await {
+            Ok(Some(account)) if account.data == entry.account.data => {}
+            other => {
+                return TestResultReport::failure(
+                    test_name,
+                    format!("{} on the bank: {:?}", entry.label, other),
+                )
+            }
+        }
+    }
+
+    // Assertion 2: The last holder's frozen account passes the thaw check
+    // resolved from the snapshot's list
+    assertions += 1;
+    let holders = snapshot.holders();
+    let holder = holders[holders.len() - 1];
+    let token_account = snapshot.create_token_account(&mut context, &holder);
+    if let Err(e) = resolved_thaw(&snapshot, &mut context, &token_account, &holder).await {
+        return TestResultReport::failure(test_name, format!("Holder denied: {}", e));
+    }
+
+    // Assertion 3: A user without a record is denied
+    assertions += 1;
+    let stranger = Pubkey::new_unique();
+    let token_account = snapshot.create_token_account(&mut context, &stranger);
+    match resolved_thaw(&snapshot, &mut context, &token_account, &stranger).await {
+        Err(e) if e.contains(&InstructionError::InvalidAccountData.to_string()) => {}
+        other => {
+            return TestResultReport::failure(test_name, format!("Stranger thawed: {:?}", other))
+        }
+    }
+
+    // Assertion 4: The snapshot's authority adds them, after which they
+    // pass
+    assertions += 1;
+    let authority = snapshot.authority();
+    let payer = context.payer();
+    let add = Instruction::new_with_bytes(
+        snapshot.gate_program_id,
+        &[ADD_TO_ALLOW_LIST],
+        vec![
+            AccountMeta::new_readonly(snapshot.config(), false),
+            AccountMeta::new(
+                production_allow_list::allow_list_address(
+                    &snapshot.mint(),
+                    &stranger,
+                    &snapshot.gate_program_id,
+                )
+                .address(),
+                false,
+            ),
+            AccountMeta::new_readonly(snapshot.mint(), false),
+            AccountMeta::new_readonly(stranger, false),
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let blockhash = match context.get_new_latest_blockhash().await {
+        Ok(blockhash) => blockhash,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+    let transaction = Transaction::new_signed_with_payer(
+        &[add],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        blockhash,
+    );
+    if let Err(e) = nonce::submit(&mut context.banks_client, transaction).await {
+        return TestResultReport::failure(test_name, format!("Add failed: {}", e));
+    }
+    if let Err(e) = resolved_thaw(&snapshot, &mut context, &token_account, &stranger).await {
+        return TestResultReport::failure(test_name, format!("Added user denied: {}", e));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 4: A bank booted from a fresh build holds the state the build
+/// left behind
+#[tokio::test]
+async fn test_boot_restores_built_state() {
+    let report = run_boot_restores_built_state_test().await;
+    assert!(
+        report.passed,
+        "Boot restores built state test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_boot_restores_built_state_test() -> TestResultReport {
+    let test_name = "Fixture Snapshots: Restored State";
+    let mut assertions = 0;
+
+    let snapshot = match FixtureSnapshot::build(test_name, SMALL_FIXTURE_HOLDERS).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let mut context = match snapshot.boot(test_name).await {
+        Ok(context) => context,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+
+    // Assertion 1: Every built account is on the bank with its lamports,
+    // owner and data
+    assertions += 1;
+    let mut booted = Vec::with_capacity(snapshot.accounts.len());
+    for entry in &snapshot.accounts {
+        match context.banks_client.get_account(entry.address).await {
+            Ok(Some(account))
+                if account.lamports == entry.account.lamports
+                    && account.owner == entry.account.owner
+                    && account.data == entry.account.data =>
+            {
+                booted.push((entry.label.as_str(), entry.address, account))
+            }
+            other => {
+                return TestResultReport::failure(
+                    test_name,
+                    format!("{} on the bank: {:?}", entry.label, other),
+                )
+            }
+        }
+    }
+
+    // Assertion 2: The booted config is the snapshot's, each booted record
+    // allows the holder whose PDA it sits at, and the clock is pinned
+    assertions += 1;
+    let mint = snapshot.mint();
+    let gate = snapshot.gate_program_id;
+    let mut holders = 0;
+    for (label, address, account) in &booted {
+        let restored = match *label {
+            "config" => Config::try_from_slice(&account.data).is_ok_and(|config| {
+                config.mint == mint && config.authority == snapshot.authority().pubkey()
+            }),
+            label if label.starts_with("holder-") => {
+                holders += 1;
+                AllowListRecord::try_from_slice(&account.data).is_ok_and(|record| {
+                    record.mint == mint
+                        && record.allowed
+                        && production_allow_list::allow_list_address(&mint, &record.user, &gate)
+                            .address()
+                            == *address
+                })
+            }
+            _ => true,
+        };
+        if !restored {
+            return TestResultReport::failure(test_name, format!("{} was not restored", label));
+        }
+    }
+    let clock = context.banks_client.get_sysvar::<Clock>().await;
+    if holders != SMALL_FIXTURE_HOLDERS
+        || !clock.is_ok_and(|clock| clock.unix_timestamp == snapshot.unix_timestamp)
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!("{} holders restored, or the clock is not pinned", holders),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate fixture snapshot report
+#[tokio::test]
+async fn generate_fixture_snapshots_report() {
+    let results = vec![
+        run_build_is_deterministic_test().await,
+        run_committed_snapshot_is_current_test().await,
+        run_booted_bank_test().await,
+        run_boot_restores_built_state_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Fixture Snapshot Results",
+        "../../tests/reports/fixture_snapshots.md",
+    ) {
+        panic!("Failed to generate fixture snapshots report: {}", e);
+    }
+
+    for result in &results {
+        assert!(result.passed, "{} failed: {:?}", result.name, result.error);
+    }
+}
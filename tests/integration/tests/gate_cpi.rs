@@ -0,0 +1,461 @@
+//! Gate CPI helper tests
+//!
+//! These tests drive `token_acl_interface`'s `invoke_can_thaw_permissionless`
+//! and `invoke_can_freeze_permissionless` from a mock FAMP program, checking
+//! that the gate never receives a signer or writable account even when the
+//! caller signed the outer transaction, and that gate decisions propagate.
+
+use production_allow_list::{ADD_TO_ALLOW_LIST, ALLOW_LIST_SEED, CONFIG_SEED, INITIALIZE};
+use solana_program_test::{processor, BanksClient, ProgramTest};
+use solana_sdk::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    msg,
+    program::invoke,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+
+use token_acl_integration_tests::{interface_spec::InterfaceSpec, reporting, TestResultReport};
+use token_acl_interface::{
+    self as interface, ReadonlyMeta, CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR,
+    CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+};
+
+/// Mock FAMP modes, selected by the first instruction byte
+const FAMP_THAW: u8 = 0;
+const FAMP_FREEZE: u8 = 1;
+/// Hand-rolled CPI that forwards the caller's privileges, as a control
+const FAMP_ESCALATED_THAW: u8 = 2;
+
+/// Error the probe gate returns when it sees an escalated account
+const ESCALATED: u32 = 0xE5CA;
+
+/// Mock FAMP: accounts are the gate program, then the interface accounts
+fn famp_process(_program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [gate, caller, token_account, mint, extra_metas, extras @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    match data.first() {
+        Some(&FAMP_THAW) => interface::invoke_can_thaw_permissionless(
+            gate,
+            caller,
+            token_account,
+            mint,
+            extra_metas,
+            &interface::readonly_accounts(extras),
+        ),
+        Some(&FAMP_FREEZE) => interface::invoke_can_freeze_permissionless(
+            gate,
+            caller,
+            token_account,
+            mint,
+            extra_metas,
+            &interface::readonly_accounts(extras),
+        ),
+        Some(&FAMP_ESCALATED_THAW) => {
+            let metas = accounts[1..]
+                .iter()
+                .map(|account| AccountMeta {
+                    pubkey: *account.key,
+                    is_signer: account.is_signer,
+                    is_writable: account.is_writable,
+                })
+                .collect();
+            let instruction = Instruction::new_with_bytes(
+                *gate.key,
+                &CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+                metas,
+            );
+            invoke(&instruction, accounts)
+        }
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
+/// Probe gate: approves only if no account arrives signer or writable
+fn probe_gate_process(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    if data != CAN_THAW_PERMISSIONLESS_DISCRIMINATOR
+        && data != CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR
+    {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    for account in accounts {
+        if account.is_signer || account.is_writable {
+            msg!(
+                "Escalated account {} (signer: {}, writable: {})",
+                account.key,
+                account.is_signer,
+                account.is_writable
+            );
+            return Err(ProgramError::Custom(ESCALATED));
+        }
+    }
+    msg!("Probe gate saw {} de-escalated accounts", accounts.len());
+    Ok(())
+}
+
+struct CpiFixture {
+    banks_client: BanksClient,
+    payer: Keypair,
+    blockhash: Hash,
+    famp: Pubkey,
+    probe_gate: Pubkey,
+    allow_list_gate: Pubkey,
+}
+
+impl CpiFixture {
+    async fn new() -> Self {
+        let famp = Pubkey::new_unique();
+        let probe_gate = Pubkey::new_unique();
+        let allow_list_gate = Pubkey::new_unique();
+
+        let mut program_test = ProgramTest::new("mock_famp", famp, processor!(famp_process));
+        program_test.add_program("probe_gate", probe_gate, processor!(probe_gate_process));
+        program_test.add_program(
+            "production_allow_list",
+            allow_list_gate,
+            processor!(production_allow_list::process_instruction),
+        );
+        let (banks_client, payer, blockhash) = program_test.start().await;
+
+        Self {
+            banks_client,
+            payer,
+            blockhash,
+            famp,
+            probe_gate,
+            allow_list_gate,
+        }
+    }
+
+    /// Mock FAMP call with the caller signing and the token account writable
+    fn famp_instruction(
+        &self,
+        mode: u8,
+        gate: Pubkey,
+        token_account: Pubkey,
+        mint: Pubkey,
+        extras: &[Pubkey],
+    ) -> Instruction {
+        let (extra_metas, _) = if mode == FAMP_FREEZE {
+            interface::find_freeze_extra_account_metas_address(&mint, &gate)
+        } else {
+            interface::find_thaw_extra_account_metas_address(&mint, &gate)
+        };
+        let mut accounts = vec![
+            AccountMeta::new_readonly(gate, false),
+            AccountMeta::new(self.payer.pubkey(), true),
+            AccountMeta::new(token_account, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(extra_metas, false),
+        ];
+        accounts.extend(extras.iter().map(|key| AccountMeta::new(*key, false)));
+        Instruction::new_with_bytes(self.famp, &[mode], accounts)
+    }
+
+    async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), TransactionError> {
+        let mut all_signers = vec![&self.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.payer.pubkey()),
+            &all_signers,
+            self.blockhash,
+        );
+        self.banks_client
+            .process_transaction(transaction)
+            .await
+            .map_err(|e| e.unwrap())
+    }
+}
+
+/// Test 1: Built instructions are de-escalated whatever the inputs
+#[tokio::test]
+async fn test_helper_instruction_metas() {
+    let report = run_helper_instruction_metas_test();
+    assert!(
+        report.passed,
+        "Helper instruction metas test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_helper_instruction_metas_test() -> TestResultReport {
+    let test_name = "CPI Helper Instruction Metas";
+    let mut assertions = 0;
+
+    let gate = Pubkey::new_unique();
+    let caller = Pubkey::new_unique();
+    let token_account = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let (thaw_metas, _) = interface::find_thaw_extra_account_metas_address(&mint, &gate);
+    let (freeze_metas, _) = interface::find_freeze_extra_account_metas_address(&mint, &gate);
+
+    // Assertion 1: No meta is signer or writable, for any number of extras,
+    // including when the caller repeats as an extra account
+    assertions += 1;
+    for extra_count in 0..8 {
+        let mut extras: Vec<ReadonlyMeta> = (0..extra_count)
+            .map(|_| ReadonlyMeta::new(Pubkey::new_unique()))
+            .collect();
+        extras.push(ReadonlyMeta::new(caller));
+        let instructions = [
+            interface::can_thaw_permissionless(
+                &gate,
+                caller,
+                token_account,
+                mint,
+                thaw_metas,
+                &extras,
+            ),
+            interface::can_freeze_permissionless(
+                &gate,
+                caller,
+                token_account,
+                mint,
+                freeze_metas,
+                &extras,
+            ),
+        ];
+        for instruction in &instructions {
+            if let Some(meta) = instruction
+                .accounts
+                .iter()
+                .find(|meta| meta.is_signer || meta.is_writable)
+            {
+                return TestResultReport::failure(
+                    test_name,
+                    format!("{} was escalated with {} extras", meta.pubkey, extra_count),
+                );
+            }
+            if instruction.accounts.len() != 4 + extras.len() {
+                return TestResultReport::failure(
+                    test_name,
+                    format!("Expected {} metas", 4 + extras.len()),
+                );
+            }
+        }
+    }
+
+    // Assertion 2: Interface accounts come first, in sRFC 37 order
+    assertions += 1;
+    let instruction =
+        interface::can_thaw_permissionless(&gate, caller, token_account, mint, thaw_metas, &[]);
+    let order: Vec<Pubkey> = instruction.accounts.iter().map(|m| m.pubkey).collect();
+    if order != vec![caller, token_account, mint, thaw_metas] {
+        return TestResultReport::failure(test_name, format!("Account order was {:?}", order));
+    }
+
+    // Assertion 3: The interface constants match the published
+    // discriminators the gates dispatch on
+    assertions += 1;
+    let spec = InterfaceSpec::published();
+    let published = |name: &str| spec.instruction(name).map(|i| i.discriminator_bytes());
+    if published("can-thaw-permissionless") != Some(CAN_THAW_PERMISSIONLESS_DISCRIMINATOR)
+        || published("can-freeze-permissionless") != Some(CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR)
+        || instruction.data != CAN_THAW_PERMISSIONLESS_DISCRIMINATOR
+    {
+        return TestResultReport::failure(test_name, "Discriminator mismatch".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: The gate sees de-escalated accounts across a real CPI
+#[tokio::test]
+async fn test_cpi_de_escalation() {
+    let report = run_cpi_de_escalation_test().await;
+    assert!(
+        report.passed,
+        "CPI de-escalation test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_cpi_de_escalation_test() -> TestResultReport {
+    let test_name = "CPI De-escalation";
+    let mut assertions = 0;
+
+    let mut fixture = CpiFixture::new().await;
+    let token_account = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let extras = [Pubkey::new_unique(), fixture.payer.pubkey()];
+    let gate = fixture.probe_gate;
+
+    // Assertion 1: Thaw CPI reaches the gate without signer or writable flags
+    assertions += 1;
+    let thaw = fixture.famp_instruction(FAMP_THAW, gate, token_account, mint, &extras);
+    if let Err(e) = fixture.send(&[thaw], &[]).await {
+        return TestResultReport::failure(test_name, format!("Thaw CPI failed: {}", e));
+    }
+
+    // Assertion 2: So does the freeze CPI
+    assertions += 1;
+    let freeze = fixture.famp_instruction(FAMP_FREEZE, gate, token_account, mint, &extras);
+    if let Err(e) = fixture.send(&[freeze], &[]).await {
+        return TestResultReport::failure(test_name, format!("Freeze CPI failed: {}", e));
+    }
+
+    // Assertion 3: A hand-rolled CPI forwarding privileges is caught by the
+    // probe, so the two passes above are meaningful
+    assertions += 1;
+    let escalated = fixture.famp_instruction(FAMP_ESCALATED_THAW, gate, token_account, mint, &[]);
+    match fixture.send(&[escalated], &[]).await {
+        Err(TransactionError::InstructionError(0, InstructionError::Custom(ESCALATED))) => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Escalated CPI was not caught: {:?}", other),
+            );
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: Production gate decisions propagate through the helper
+#[tokio::test]
+async fn test_cpi_gate_decisions() {
+    let report = run_cpi_gate_decisions_test().await;
+    assert!(
+        report.passed,
+        "CPI gate decisions test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_cpi_gate_decisions_test() -> TestResultReport {
+    let test_name = "CPI Gate Decisions";
+    let mut assertions = 0;
+
+    let mut fixture = CpiFixture::new().await;
+    let gate = fixture.allow_list_gate;
+    let mint = Pubkey::new_unique();
+    let authority = Keypair::new();
+    let holder = Pubkey::new_unique();
+    let stranger = Pubkey::new_unique();
+    let (config, _) = Pubkey::find_program_address(&[CONFIG_SEED, mint.as_ref()], &gate);
+    let record = |user: &Pubkey| {
+        Pubkey::find_program_address(&[ALLOW_LIST_SEED, mint.as_ref(), user.as_ref()], &gate).0
+    };
+    let (policy, _) = interface::find_missing_record_policy_address(&mint, &gate);
+
+    let payer = fixture.payer.pubkey();
+    let setup = [
+        Instruction::new_with_bytes(
+            gate,
+            &[INITIALIZE],
+            vec![
+                AccountMeta::new(config, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(authority.pubkey(), true),
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        ),
+        Instruction::new_with_bytes(
+            gate,
+            &[ADD_TO_ALLOW_LIST],
+            vec![
+                AccountMeta::new_readonly(config, false),
+                AccountMeta::new(record(&holder), false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(holder, false),
+                AccountMeta::new_readonly(authority.pubkey(), true),
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        ),
+    ];
+    if let Err(e) = fixture.send(&setup, &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Setup failed: {}", e));
+    }
+
+    // Assertion 1: An allow-listed holder is approved through the CPI
+    assertions += 1;
+    let approved = fixture.famp_instruction(
+        FAMP_THAW,
+        gate,
+        Pubkey::new_unique(),
+        mint,
+        &[holder, record(&holder), policy],
+    );
+    if let Err(e) = fixture.send(&[approved], &[]).await {
+        return TestResultReport::failure(test_name, format!("Holder was denied: {}", e));
+    }
+
+    // Assertion 2: A holder not on the list is denied with the gate's error
+    assertions += 1;
+    let denied = fixture.famp_instruction(
+        FAMP_THAW,
+        gate,
+        Pubkey::new_unique(),
+        mint,
+        &[stranger, record(&stranger), policy],
+    );
+    match fixture.send(&[denied], &[]).await {
+        Err(TransactionError::InstructionError(0, InstructionError::InvalidAccountData)) => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Stranger was not denied: {:?}", other),
+            );
+        }
+    }
+
+    // Assertion 3: The gate's unsupported freeze is surfaced, not swallowed
+    assertions += 1;
+    let freeze = fixture.famp_instruction(
+        FAMP_FREEZE,
+        gate,
+        Pubkey::new_unique(),
+        mint,
+        &[holder, record(&holder), policy],
+    );
+    match fixture.send(&[freeze], &[]).await {
+        Err(TransactionError::InstructionError(0, InstructionError::InvalidInstructionData)) => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Unsupported freeze was not surfaced: {:?}", other),
+            );
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate gate CPI test report
+#[tokio::test]
+async fn generate_gate_cpi_report() {
+    let results = vec![
+        run_helper_instruction_metas_test(),
+        run_cpi_de_escalation_test().await,
+        run_cpi_gate_decisions_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Gate CPI Helper Test Results",
+        "../../tests/reports/gate_cpi.md",
+    ) {
+        panic!("Failed to generate gate CPI report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} gate CPI tests failed", failed);
+}
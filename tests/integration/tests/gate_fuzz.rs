@@ -0,0 +1,333 @@
+//! Gate fuzzing tests
+//!
+//! The cargo-fuzz targets in `fuzz/` run for as long as someone lets them;
+//! these tests keep what they found in force on every run. Every saved
+//! input (the seeds, a local corpus, libFuzzer's crash artifacts) is
+//! replayed against its gate and must be answered without a panic, and in
+//! the `fuzz_smoke` category random inputs and mutations of the seeds are
+//! thrown at every gate in-process. Inputs that panic reach the coverage
+//! report, which fails until they are fixed.
+
+use std::path::Path;
+
+use proptest::{
+    collection::vec,
+    prelude::*,
+    strategy::ValueTree,
+    test_runner::{Config as ProptestConfig, TestRunner},
+};
+use solana_sdk::{program_error::ProgramError, system_program};
+
+use token_acl_integration_tests::{
+    coverage_reporting, coverage_utils,
+    gate_fuzz::{self, FuzzInput, FuzzOutcome, FuzzReplay, FuzzTarget, MAX_ACCOUNT_DATA},
+    reporting,
+    suite_config::{self, SuiteCategory},
+    CoverageRequirements, CoverageResults, TestResultReport,
+};
+
+/// Random inputs per gate per fuzz smoke run
+const RANDOM_CASES: u32 = 512;
+
+/// Test 1: The fuzz input layout reads accounts, duplicates and
+/// instruction data as documented, and a duplicate is the same account
+#[test]
+fn test_fuzz_input_layout() {
+    let report = run_fuzz_input_layout_test();
+    assert!(
+        report.passed,
+        "Fuzz input layout test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_fuzz_input_layout_test() -> TestResultReport {
+    let test_name = "Gate Fuzz: Input Layout";
+    let mut assertions = 0;
+
+    let program_id = FuzzTarget::BlockList.program_id();
+    // A writable signer owned by the system program with 3 bytes of data,
+    // the system program itself, then a duplicate of the first account
+    let bytes = [
+        3, 0x07, 3, 0, 0xAA, 0xBB, 0xCC, 0x24, 0, 0, 0x40, 0, 0, 9, 9,
+    ];
+    let input = FuzzInput::parse(&program_id, &bytes);
+
+    // Assertion 1: Each account is read from its flags and length
+    assertions += 1;
+    let [first, second, third] = input.accounts.as_slice() else {
+        return TestResultReport::failure(test_name, format!("Parsed {:?}", input));
+    };
+    if !(first.is_signer && first.is_writable)
+        || first.owner != system_program::id()
+        || first.data != [0xAA, 0xBB, 0xCC]
+        || first.duplicate_of.is_some()
+        || second.key != system_program::id()
+        || !second.data.is_empty()
+        || third.duplicate_of != Some(0)
+    {
+        return TestResultReport::failure(test_name, format!("Parsed {:?}", input));
+    }
+
+    // Assertion 2: What follows the accounts is the instruction data
+    assertions += 1;
+    if input.instruction_data != [9, 9] {
+        return TestResultReport::failure(
+            test_name,
+            format!("Instruction data {:?}", input.instruction_data),
+        );
+    }
+
+    // Assertion 3: Data lengths wrap, and an input that runs out pads with
+    // zeros instead of failing
+    assertions += 1;
+    let length = (MAX_ACCOUNT_DATA + 2) as u16;
+    let [low, high] = length.to_le_bytes();
+    let input = FuzzInput::parse(&program_id, &[1, 0, low, high, 0x11]);
+    if input.accounts.len() != 1
+        || input.accounts[0].data != [0x11, 0]
+        || input.accounts[0].owner != program_id
+        || !input.instruction_data.is_empty()
+    {
+        return TestResultReport::failure(test_name, format!("Parsed {:?}", input));
+    }
+
+    // Assertion 4: Every byte string is an input, the empty one included
+    assertions += 1;
+    let empty = FuzzInput::parse(&program_id, &[]);
+    if !empty.accounts.is_empty()
+        || FuzzTarget::BlockList.run(&[]) != FuzzOutcome::Err(ProgramError::InvalidInstructionData)
+    {
+        return TestResultReport::failure(test_name, format!("Parsed {:?}", empty));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: Every saved input replays without a panic, and every target
+/// has seeds
+#[test]
+fn test_saved_inputs_replay() {
+    let report = run_saved_inputs_replay_test();
+    assert!(
+        report.passed,
+        "Saved inputs replay test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_saved_inputs_replay_test() -> TestResultReport {
+    let test_name = "Gate Fuzz: Saved Inputs";
+    let mut assertions = 0;
+
+    let replays = match gate_fuzz::replay(Path::new(gate_fuzz::FUZZ_DIR)) {
+        Ok(replays) => replays,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+
+    for target in FuzzTarget::ALL {
+        // Assertion: The target has seeds, and none of its inputs panics
+        assertions += 1;
+        let seeds = format!("seeds/{}/", target.name());
+        if !replays
+            .iter()
+            .any(|replay| replay.target == target && replay.input.starts_with(&seeds))
+        {
+            return TestResultReport::failure(
+                test_name,
+                format!("No seeds for the {} target", target.name()),
+            );
+        }
+        if let Some(finding) = replays
+            .iter()
+            .find(|replay| replay.target == target && replay.is_finding())
+        {
+            return TestResultReport::failure(
+                test_name,
+                format!("fuzz/{} panics: {:?}", finding.input, finding.outcome),
+            );
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: Random inputs, and seeds with random bytes overwritten, never
+/// panic a gate
+#[test]
+fn test_random_inputs() {
+    let report = run_random_inputs_test();
+    assert!(
+        report.passed,
+        "Random inputs test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_random_inputs_test() -> TestResultReport {
+    let test_name = "Gate Fuzz: Random Inputs";
+    if let Some(skipped) = suite_config::skip_unless(SuiteCategory::FuzzSmoke, test_name) {
+        return skipped;
+    }
+    let mut assertions = 0;
+
+    let replays = match gate_fuzz::replay(Path::new(gate_fuzz::FUZZ_DIR)) {
+        Ok(replays) => replays,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+    let mut runner = TestRunner::new(ProptestConfig {
+        failure_persistence: None,
+        ..ProptestConfig::default()
+    });
+    let random = vec(any::<u8>(), 0..1024);
+    let mutations = vec((any::<prop::sample::Index>(), any::<u8>()), 1..8);
+
+    for target in FuzzTarget::ALL {
+        let seeds: Vec<Vec<u8>> = replays
+            .iter()
+            .filter(|replay| replay.target == target)
+            .filter_map(|replay| {
+                std::fs::read(Path::new(gate_fuzz::FUZZ_DIR).join(&replay.input)).ok()
+            })
+            .collect();
+
+        // Assertion: No random input or mutated seed panics the gate
+        assertions += 1;
+        for case in 0..RANDOM_CASES {
+            let mut bytes = match random.new_tree(&mut runner) {
+                Ok(tree) => tree.current(),
+                Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+            };
+            if !seeds.is_empty() && case % 2 == 1 {
+                bytes = seeds[case as usize / 2 % seeds.len()].clone();
+                let edits = match mutations.new_tree(&mut runner) {
+                    Ok(tree) => tree.current(),
+                    Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+                };
+                if !bytes.is_empty() {
+                    for (index, byte) in edits {
+                        let position = index.index(bytes.len());
+                        bytes[position] = byte;
+                    }
+                }
+            }
+            if let FuzzOutcome::Panic(message) = target.run(&bytes) {
+                return TestResultReport::failure(
+                    test_name,
+                    format!(
+                        "{} panicked on {}: {}",
+                        target.name(),
+                        hex::encode(&bytes),
+                        message
+                    ),
+                );
+            }
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 4: An input that still panics a gate fails the coverage
+/// requirements and is listed in the coverage report
+#[test]
+fn test_findings_reach_coverage_report() {
+    let report = run_findings_reach_coverage_report_test();
+    assert!(
+        report.passed,
+        "Findings reach coverage report test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_findings_reach_coverage_report_test() -> TestResultReport {
+    let test_name = "Gate Fuzz: Findings in the Coverage Report";
+    let mut assertions = 0;
+
+    let clean = FuzzReplay {
+        target: FuzzTarget::AllowList,
+        input: "seeds/allow_list/can_thaw_permissionless".to_string(),
+        outcome: FuzzOutcome::Err(ProgramError::InvalidAccountData),
+    };
+    let finding = FuzzReplay {
+        target: FuzzTarget::ProductionAllowList,
+        input: "artifacts/production_allow_list/crash-0".to_string(),
+        outcome: FuzzOutcome::Panic("index out of bounds: the len is 0".to_string()),
+    };
+    let results = |replays: &[FuzzReplay]| {
+        let mut analysis = coverage_utils::analyze_test_results(&[TestResultReport::success(
+            "Core Logic: Placeholder",
+            1,
+        )]);
+        analysis.record_fuzz_replays(replays);
+        let mut results = CoverageResults {
+            analysis,
+            requirements: CoverageRequirements {
+                critical_tests_required: 0,
+                performance_tests_required: 0,
+                security_tests_required: 0,
+                ..CoverageRequirements::default()
+            },
+            meets_requirements: false,
+            recommendations: Vec::new(),
+        };
+        results.check_requirements();
+        results
+    };
+
+    // Assertion 1: Inputs the gates answer leave the requirements met, and
+    // the report says how many were replayed
+    assertions += 1;
+    let passing = results(std::slice::from_ref(&clean));
+    let report = coverage_reporting::render_coverage_report(&passing);
+    if !passing.meets_requirements || !report.contains("any of the 1 saved fuzz inputs") {
+        return TestResultReport::failure(
+            test_name,
+            format!("{:?}\n{}", passing.recommendations, report),
+        );
+    }
+
+    // Assertion 2: A panicking input fails them, with the input named in
+    // the recommendations and the findings table
+    assertions += 1;
+    let failing = results(&[clean, finding]);
+    let report = coverage_reporting::render_coverage_report(&failing);
+    if failing.meets_requirements
+        || !failing.recommendations.iter().any(|recommendation| {
+            recommendation.contains("fuzz/artifacts/production_allow_list/crash-0")
+        })
+        || !report.contains(
+            "| production_allow_list | artifacts/production_allow_list/crash-0 | index out of bounds",
+        )
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!("{:?}\n{}", failing.recommendations, report),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate gate fuzzing report
+#[test]
+fn generate_gate_fuzz_report() {
+    let results = vec![
+        run_fuzz_input_layout_test(),
+        run_saved_inputs_replay_test(),
+        run_random_inputs_test(),
+        run_findings_reach_coverage_report_test(),
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Gate Fuzzing Results",
+        "../../tests/reports/gate_fuzz.md",
+    ) {
+        panic!("Failed to generate gate fuzz report: {}", e);
+    }
+
+    for result in &results {
+        assert!(result.passed, "{} failed: {:?}", result.name, result.error);
+    }
+}
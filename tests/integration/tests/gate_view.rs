@@ -0,0 +1,532 @@
+//! Cross-program read API tests
+//!
+//! Programs that are not gates can read gate records through `gate_view`,
+//! from accounts passed to them, without a CPI. These tests check the view
+//! layouts against what the gates actually write, then run the lending
+//! consumer example next to the production allow list and the block list:
+//! it must follow every change the issuer makes to the records, and reject
+//! records that aren't the borrower's own under the gates it trusts.
+
+use borsh::BorshSerialize;
+use example_block_list::{create_block_list_record, BlockListRecord, BlockReason};
+use example_lending_consumer::{
+    check_borrower, initialize_market, market_address, BorrowerError, Market,
+};
+use gate_view::{AllowListView, BlockListView};
+use production_allow_list::{
+    config_address, AccessLevel, AllowListRecord, ADD_TO_ALLOW_LIST, INITIALIZE, REVOKE,
+};
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+
+use token_acl_integration_tests::{program_test::rent_exempt_account, reporting, TestResultReport};
+
+/// A bank with both gates and a lending market trusting them
+///
+/// `allowed` is on the allow list, `blocked` on both lists, and `stranger`
+/// on neither. A second allow list deployment, which the market does not
+/// trust, also lists `stranger`, and a look-alike record for `impostor`
+/// sits at its allow list address, owned by the wrong program.
+struct ConsumerFixture {
+    context: ProgramTestContext,
+    allow_list: Pubkey,
+    block_list: Pubkey,
+    untrusted_allow_list: Pubkey,
+    consumer: Pubkey,
+    issuer: Keypair,
+    mint: Pubkey,
+    allowed: Keypair,
+    blocked: Keypair,
+    stranger: Keypair,
+    impostor: Keypair,
+}
+
+impl ConsumerFixture {
+    async fn new() -> Self {
+        let allow_list = Pubkey::new_unique();
+        let block_list = Pubkey::new_unique();
+        let untrusted_allow_list = Pubkey::new_unique();
+        let consumer = Pubkey::new_unique();
+        let issuer = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let (allowed, blocked, stranger, impostor) = (
+            Keypair::new(),
+            Keypair::new(),
+            Keypair::new(),
+            Keypair::new(),
+        );
+
+        let mut program_test = ProgramTest::new(
+            "production_allow_list",
+            allow_list,
+            processor!(production_allow_list::process_instruction),
+        );
+        program_test.add_program(
+            "production_allow_list",
+            untrusted_allow_list,
+            processor!(production_allow_list::process_instruction),
+        );
+        program_test.add_program(
+            "example_block_list",
+            block_list,
+            processor!(example_block_list::process_instruction),
+        );
+        program_test.add_program(
+            "example_lending_consumer",
+            consumer,
+            processor!(example_lending_consumer::process_instruction),
+        );
+
+        // The block list example has no admin instruction; its records are
+        // planted as the issuer's tooling would write them
+        let record = create_block_list_record(&mint, &blocked.pubkey(), BlockReason::Sanctions, 0);
+        program_test.add_account(
+            gate_view::block_list_address(&block_list, &mint, &blocked.pubkey()).address(),
+            rent_exempt_account(record.try_to_vec().unwrap(), block_list),
+        );
+
+        // A record that claims `impostor` is allowed, at the right address
+        // but owned by another program
+        let look_alike = AllowListRecord {
+            mint,
+            user: impostor.pubkey(),
+            allowed: true,
+            access_level: AccessLevel::Institutional,
+            added_timestamp: 0,
+            expiry_timestamp: None,
+            bump: 0,
+            revoked: false,
+        };
+        program_test.add_account(
+            gate_view::allow_list_address(&allow_list, &mint, &impostor.pubkey()).address(),
+            rent_exempt_account(look_alike.try_to_vec().unwrap(), untrusted_allow_list),
+        );
+
+        let mut fixture = Self {
+            context: program_test.start_with_context().await,
+            allow_list,
+            block_list,
+            untrusted_allow_list,
+            consumer,
+            issuer,
+            mint,
+            allowed,
+            blocked,
+            stranger,
+            impostor,
+        };
+
+        let payer = fixture.context.payer.pubkey();
+        let mut setup = vec![
+            fixture.initialize_allow_list(allow_list),
+            fixture.initialize_allow_list(untrusted_allow_list),
+            initialize_market(&consumer, &payer, &mint, &allow_list, &block_list),
+        ];
+        for user in [fixture.allowed.pubkey(), fixture.blocked.pubkey()] {
+            setup.push(fixture.allow_list_instruction(ADD_TO_ALLOW_LIST, allow_list, &user));
+        }
+        let stranger = fixture.stranger.pubkey();
+        setup.push(fixture.allow_list_instruction(
+            ADD_TO_ALLOW_LIST,
+            untrusted_allow_list,
+            &stranger,
+        ));
+        fixture
+            .send(&setup, &[])
+            .await
+            .expect("setup should succeed");
+        fixture
+    }
+
+    fn market(&self) -> Market {
+        Market {
+            mint: self.mint,
+            allow_list_program: self.allow_list,
+            block_list_program: self.block_list,
+            bump: market_address(&self.mint, &self.consumer).bump(),
+        }
+    }
+
+    fn initialize_allow_list(&self, program: Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            program,
+            &[INITIALIZE],
+            vec![
+                AccountMeta::new(config_address(&self.mint, &program).address(), false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// `ADD_TO_ALLOW_LIST` or `REVOKE` for `user` on the allow list at
+    /// `program`, signed by the issuer
+    fn allow_list_instruction(
+        &self,
+        discriminator: u8,
+        program: Pubkey,
+        user: &Pubkey,
+    ) -> Instruction {
+        let config = config_address(&self.mint, &program).address();
+        let record = gate_view::allow_list_address(&program, &self.mint, user).address();
+        let accounts = if discriminator == ADD_TO_ALLOW_LIST {
+            vec![
+                AccountMeta::new_readonly(config, false),
+                AccountMeta::new(record, false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(*user, false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ]
+        } else {
+            vec![
+                AccountMeta::new_readonly(config, false),
+                AccountMeta::new(record, false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+            ]
+        };
+        Instruction::new_with_bytes(program, &[discriminator], accounts)
+    }
+
+    /// Check `borrower` with the market, swapping in other record accounts
+    /// where given
+    async fn check(
+        &mut self,
+        borrower: &Keypair,
+        allow_list_record: Option<Pubkey>,
+        block_list_record: Option<Pubkey>,
+    ) -> Result<(), TransactionError> {
+        let mut instruction = check_borrower(&self.consumer, &self.market(), &borrower.pubkey());
+        if let Some(record) = allow_list_record {
+            instruction.accounts[2].pubkey = record;
+        }
+        if let Some(record) = block_list_record {
+            instruction.accounts[3].pubkey = record;
+        }
+        self.send(&[instruction], &[borrower]).await
+    }
+
+    /// Send with the payer and issuer signing, plus `signers`
+    async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), TransactionError> {
+        // A fresh blockhash keeps repeated transactions from being deduplicated
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let issuer_signs = instructions
+            .iter()
+            .flat_map(|instruction| &instruction.accounts)
+            .any(|meta| meta.is_signer && meta.pubkey == self.issuer.pubkey());
+        let mut all_signers = vec![&self.context.payer];
+        if issuer_signs {
+            all_signers.push(&self.issuer);
+        }
+        all_signers.extend_from_slice(signers);
+
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.context.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .map_err(|e| e.unwrap())
+    }
+}
+
+fn rejected_with(result: &Result<(), TransactionError>, error: InstructionError) -> bool {
+    *result == Err(TransactionError::InstructionError(0, error))
+}
+
+fn refused(result: &Result<(), TransactionError>, error: BorrowerError) -> bool {
+    rejected_with(result, InstructionError::Custom(error as u32))
+}
+
+/// Test 1: The view layouts match the gates' own encoding
+#[test]
+fn test_view_layouts_match_gates() {
+    let report = run_view_layouts_test();
+    assert!(
+        report.passed,
+        "View layouts test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_view_layouts_test() -> TestResultReport {
+    let test_name = "Gate View Layouts";
+    let mut assertions = 0;
+    let mint = Pubkey::new_unique();
+    let user = Pubkey::new_unique();
+    let gate = Pubkey::new_unique();
+
+    // Assertions 1-4: Every shape of allow list record reads back as written
+    for (expiry_timestamp, revoked) in [
+        (None, false),
+        (Some(1_700_000_000), false),
+        (None, true),
+        (Some(-1), true),
+    ] {
+        assertions += 1;
+        let record = AllowListRecord {
+            mint,
+            user,
+            allowed: !revoked,
+            access_level: AccessLevel::Institutional,
+            added_timestamp: 42,
+            expiry_timestamp,
+            bump: 253,
+            revoked,
+        };
+        let expected = AllowListView {
+            mint,
+            owner: user,
+            allowed: !revoked,
+            access_level: AccessLevel::Institutional as u8,
+            added_timestamp: 42,
+            expiry_timestamp,
+            revoked,
+        };
+        let view = AllowListView::unpack(&record.try_to_vec().unwrap());
+        if view != Ok(expected) {
+            return TestResultReport::failure(
+                test_name,
+                format!("Allow list record {:?} read as {:?}", record, view),
+            );
+        }
+    }
+
+    // Assertion 5: Every block list reason, with and without an expiry,
+    // reads back as written
+    assertions += 1;
+    for (reason, byte, expiry_timestamp) in [
+        (BlockReason::Sanctions, 0, None),
+        (BlockReason::Compliance, 1, Some(1_700_000_000)),
+        (BlockReason::RiskAssessment, 2, Some(-1)),
+        (BlockReason::Other, 3, None),
+    ] {
+        let record = BlockListRecord {
+            mint,
+            user,
+            blocked: true,
+            reason,
+            added_timestamp: -7,
+            expiry_timestamp,
+        };
+        let view = BlockListView::unpack(&record.try_to_vec().unwrap());
+        let expected = BlockListView {
+            mint,
+            owner: user,
+            blocked: true,
+            reason: byte,
+            added_timestamp: -7,
+            expiry_timestamp,
+        };
+        if view != Ok(expected) {
+            return TestResultReport::failure(
+                test_name,
+                format!("Block list record {:?} read as {:?}", record, view),
+            );
+        }
+    }
+
+    // Assertion 6: The view derives the same record addresses as the gates
+    assertions += 1;
+    let same_allow_list = gate_view::allow_list_address(&gate, &mint, &user)
+        == production_allow_list::allow_list_address(&mint, &user, &gate);
+    let same_block_list = gate_view::block_list_address(&gate, &mint, &user)
+        == example_block_list::block_list_address(&mint, &user, &gate);
+    if !same_allow_list || !same_block_list {
+        return TestResultReport::failure(
+            test_name,
+            "View record addresses differ from the gates'".to_string(),
+        );
+    }
+
+    // Assertion 7: A record of another layout is refused, not misread
+    assertions += 1;
+    let block_record = create_block_list_record(&mint, &user, BlockReason::Other, 0);
+    if AllowListView::unpack(&block_record.try_to_vec().unwrap())
+        != Err(ProgramError::InvalidAccountData)
+    {
+        return TestResultReport::failure(
+            test_name,
+            "A block list record was read as an allow list record".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: The consumer follows the records the issuer writes
+#[tokio::test]
+async fn test_consumer_reads_records() {
+    let report = run_consumer_reads_records_test().await;
+    assert!(
+        report.passed,
+        "Consumer reads records test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_consumer_reads_records_test() -> TestResultReport {
+    let test_name = "Gate View Consumer Reads Records";
+    let mut assertions = 0;
+    let mut fixture = ConsumerFixture::new().await;
+    let allowed = fixture.allowed.insecure_clone();
+    let blocked = fixture.blocked.insecure_clone();
+    let stranger = fixture.stranger.insecure_clone();
+
+    // Assertion 1: An allow-listed borrower is let in
+    assertions += 1;
+    if let Err(e) = fixture.check(&allowed, None, None).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("Allow-listed borrower was refused: {}", e),
+        );
+    }
+
+    // Assertion 2: A blocked borrower is refused despite the allow list
+    assertions += 1;
+    let result = fixture.check(&blocked, None, None).await;
+    if !refused(&result, BorrowerError::Blocked) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Blocked borrower was not refused as blocked: {:?}", result),
+        );
+    }
+
+    // Assertion 3: A borrower without records is not allow-listed
+    assertions += 1;
+    let result = fixture.check(&stranger, None, None).await;
+    if !refused(&result, BorrowerError::NotAllowListed) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Borrower without records was let in: {:?}", result),
+        );
+    }
+
+    // Assertion 4: Revoking the allowed borrower takes effect at once
+    assertions += 1;
+    let revoke = fixture.allow_list_instruction(REVOKE, fixture.allow_list, &allowed.pubkey());
+    if let Err(e) = fixture.send(&[revoke], &[]).await {
+        return TestResultReport::failure(test_name, format!("Revoke failed: {}", e));
+    }
+    let result = fixture.check(&allowed, None, None).await;
+    if !refused(&result, BorrowerError::NotAllowListed) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Revoked borrower was let in: {:?}", result),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: The consumer only reads the borrower's own records under the
+/// gates it trusts
+#[tokio::test]
+async fn test_consumer_rejects_foreign_records() {
+    let report = run_consumer_rejects_foreign_records_test().await;
+    assert!(
+        report.passed,
+        "Consumer rejects foreign records test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_consumer_rejects_foreign_records_test() -> TestResultReport {
+    let test_name = "Gate View Consumer Rejects Foreign Records";
+    let mut assertions = 0;
+    let mut fixture = ConsumerFixture::new().await;
+    let stranger = fixture.stranger.insecure_clone();
+    let impostor = fixture.impostor.insecure_clone();
+    let (mint, allow_list, block_list) = (fixture.mint, fixture.allow_list, fixture.block_list);
+
+    // Assertion 1: Another borrower's allow list record is refused
+    assertions += 1;
+    let borrowed =
+        gate_view::allow_list_address(&allow_list, &mint, &fixture.allowed.pubkey()).address();
+    let result = fixture.check(&stranger, Some(borrowed), None).await;
+    if !rejected_with(&result, InstructionError::InvalidAccountData) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Another borrower's record was accepted: {:?}", result),
+        );
+    }
+
+    // Assertion 2: A record from an allow list the market doesn't trust is
+    // refused
+    assertions += 1;
+    let untrusted =
+        gate_view::allow_list_address(&fixture.untrusted_allow_list, &mint, &stranger.pubkey())
+            .address();
+    let result = fixture.check(&stranger, Some(untrusted), None).await;
+    if !rejected_with(&result, InstructionError::InvalidAccountData) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Untrusted allow list record was accepted: {:?}", result),
+        );
+    }
+
+    // Assertion 3: A look-alike record at the right address is refused for
+    // its owner
+    assertions += 1;
+    let result = fixture.check(&impostor, None, None).await;
+    if !rejected_with(&result, InstructionError::IllegalOwner) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Look-alike record was accepted: {:?}", result),
+        );
+    }
+
+    // Assertion 4: Hiding a block list record behind another borrower's
+    // empty one is refused
+    assertions += 1;
+    let blocked = fixture.blocked.insecure_clone();
+    let hidden = gate_view::block_list_address(&block_list, &mint, &stranger.pubkey()).address();
+    let result = fixture.check(&blocked, None, Some(hidden)).await;
+    if !rejected_with(&result, InstructionError::InvalidAccountData) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Blocked borrower hid their record: {:?}", result),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate gate view test report
+#[tokio::test]
+async fn generate_gate_view_report() {
+    let results = vec![
+        run_view_layouts_test(),
+        run_consumer_reads_records_test().await,
+        run_consumer_rejects_foreign_records_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Cross-Program Read API Test Results",
+        "../../tests/reports/gate_view.md",
+    ) {
+        panic!("Failed to generate gate view report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} gate view tests failed", failed);
+}
@@ -0,0 +1,488 @@
+//! Holder enumeration tests
+//!
+//! These tests enumerate the token accounts of a mint with
+//! [`enumerate_holders`] against state seeded on [`SvmHarness`]'s in-process
+//! bank: accounts Token-2022 created and sized itself, accounts written
+//! directly, other mints' accounts that must be left out, and accounts that
+//! vanish between the address scan and their page. The RPC source is checked
+//! against a mock endpoint, and a 10,000-holder mint gives the performance
+//! figures.
+
+use serde_json::json;
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::{
+    nonblocking::rpc_client::{Mocks, RpcClient},
+    rpc_filter::RpcFilterType,
+    rpc_request::{RpcRequest, MAX_MULTIPLE_ACCOUNTS},
+};
+use solana_sdk::{
+    account::Account,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use spl_token_2022::{instruction as token_instruction, state::AccountState};
+use std::{collections::HashSet, fs, time::Duration};
+
+use token_acl_integration_tests::{
+    holders::{enumerate_holders, HolderEnumeration, HolderSource},
+    program_test::token_account,
+    reporting, time, SvmHarness, TestResultReport,
+};
+
+/// Holders of the mint the performance test enumerates
+const SCALE_HOLDERS: usize = 10_000;
+
+/// Accounts of another mint seeded alongside them
+const SCALE_NOISE: usize = 1_000;
+
+/// Budget for enumerating `SCALE_HOLDERS` on the in-process bank
+const SCALE_BUDGET: Duration = Duration::from_secs(10);
+
+/// Seed `count` accounts of `mint` with distinct balances, every tenth
+/// frozen; returns their addresses
+fn seed(svm: &mut SvmHarness, mint: Pubkey, count: usize) -> Vec<Pubkey> {
+    (0..count)
+        .map(|i| {
+            let address = Pubkey::new_unique();
+            let state = if i % 10 == 0 {
+                AccountState::Frozen
+            } else {
+                AccountState::Initialized
+            };
+            let amount = ((i * 7_919) % count + 1) as u64;
+            svm.set_account(
+                address,
+                token_account(&mint, &Pubkey::new_unique(), amount, state),
+            );
+            address
+        })
+        .collect()
+}
+
+/// Reads through to the bank, except that `vanished` accounts are gone by
+/// the time their page is read
+struct VanishingSource<'a> {
+    svm: &'a SvmHarness,
+    vanished: HashSet<Pubkey>,
+}
+
+impl HolderSource for VanishingSource<'_> {
+    async fn program_account_addresses(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<Pubkey>, String> {
+        self.svm
+            .program_account_addresses(program_id, filters)
+            .await
+    }
+
+    async fn accounts(&self, addresses: &[Pubkey]) -> Result<Vec<Option<Account>>, String> {
+        if addresses.len() > MAX_MULTIPLE_ACCOUNTS {
+            return Err(format!("Page of {} accounts", addresses.len()));
+        }
+        Ok(addresses
+            .iter()
+            .map(|address| {
+                (!self.vanished.contains(address))
+                    .then(|| self.svm.account(address))
+                    .flatten()
+            })
+            .collect())
+    }
+}
+
+/// Test 1: Every token account of the mint is found, whether Token-2022
+/// created it with extensions or it was seeded, and nothing else is
+#[tokio::test]
+async fn test_enumerates_seeded_state() {
+    let report = run_seeded_state_test().await;
+    assert!(
+        report.passed,
+        "Seeded state enumeration test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_seeded_state_test() -> TestResultReport {
+    let test_name = "Holder Enumeration Seeded State";
+    let mut assertions = 0;
+
+    let mut svm = SvmHarness::new();
+    let issuer = Keypair::new();
+    let mint = match svm.create_mint(&issuer.pubkey(), AccountState::Frozen) {
+        Ok(mint) => mint,
+        Err(e) => return TestResultReport::failure(test_name, format!("Mint creation: {}", e)),
+    };
+    let other_mint = Pubkey::new_unique();
+
+    // Two accounts Token-2022 creates, born frozen with the Default Account
+    // State extension, one of them thawed and funded
+    let mut created = Vec::new();
+    for _ in 0..2 {
+        match svm.create_token_account(&mint, &Pubkey::new_unique()) {
+            Ok(address) => created.push(address),
+            Err(e) => {
+                return TestResultReport::failure(test_name, format!("Account creation: {}", e))
+            }
+        }
+    }
+    let thaw = token_instruction::thaw_account(
+        &spl_token_2022::id(),
+        &created[0],
+        &mint,
+        &issuer.pubkey(),
+        &[],
+    )
+    .unwrap();
+    let mint_to = token_instruction::mint_to(
+        &spl_token_2022::id(),
+        &mint,
+        &created[0],
+        &issuer.pubkey(),
+        &[],
+        500,
+    )
+    .unwrap();
+    if let Err(e) = svm.send(&[thaw, mint_to], &[&issuer]) {
+        return TestResultReport::failure(test_name, format!("Funding: {}", e));
+    }
+    let seeded = seed(&mut svm, mint, 25);
+    seed(&mut svm, other_mint, 10);
+
+    let enumeration = match enumerate_holders(&svm, &mint, MAX_MULTIPLE_ACCOUNTS).await {
+        Ok(enumeration) => enumeration,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+
+    // Assertion 1: Exactly the mint's accounts are found: the created ones
+    // and the seeded ones, not the other mint's nor the mint itself
+    assertions += 1;
+    let found: HashSet<Pubkey> = enumeration.holders.iter().map(|h| h.address).collect();
+    let expected: HashSet<Pubkey> = created.iter().chain(&seeded).copied().collect();
+    if found != expected || enumeration.stats.skipped != 0 {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Found {} accounts ({} skipped), expected {}",
+                found.len(),
+                enumeration.stats.skipped,
+                expected.len()
+            ),
+        );
+    }
+
+    // Assertion 2: Extension-sized accounts decode with their state and
+    // balance
+    assertions += 1;
+    let state_of = |address: &Pubkey| {
+        enumeration
+            .holders
+            .iter()
+            .find(|holder| holder.address == *address)
+            .map(|holder| (holder.state, holder.amount))
+    };
+    if state_of(&created[0]) != Some((AccountState::Initialized, 500))
+        || state_of(&created[1]) != Some((AccountState::Frozen, 0))
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Created accounts decoded as {:?} and {:?}",
+                state_of(&created[0]),
+                state_of(&created[1])
+            ),
+        );
+    }
+
+    // Assertion 3: The holders come back ordered by address, with the
+    // seeded frozen accounts and the born-frozen one counted
+    assertions += 1;
+    let ordered = enumeration
+        .holders
+        .windows(2)
+        .all(|pair| pair[0].address < pair[1].address);
+    let frozen = enumeration.frozen().count();
+    if !ordered || frozen != 3 + 1 {
+        return TestResultReport::failure(
+            test_name,
+            format!("Ordered: {}, frozen: {}", ordered, frozen),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: Any page size gives the same holders in the expected number of
+/// pages, and accounts gone by the time their page is read are skipped
+#[tokio::test]
+async fn test_pagination() {
+    let report = run_pagination_test().await;
+    assert!(report.passed, "Pagination test failed: {:?}", report.error);
+}
+
+async fn run_pagination_test() -> TestResultReport {
+    let test_name = "Holder Enumeration Pagination";
+    let mut assertions = 0;
+
+    const HOLDERS: usize = 250;
+    let mut svm = SvmHarness::new();
+    let mint = Pubkey::new_unique();
+    let seeded = seed(&mut svm, mint, HOLDERS);
+
+    let mut reference: Option<HolderEnumeration> = None;
+    for (page_size, pages) in [(1, HOLDERS), (7, HOLDERS.div_ceil(7)), (100, 3), (1_000, 3)] {
+        // Assertion: One per page size, oversized pages capped at the RPC
+        // limit
+        assertions += 1;
+        let enumeration = match enumerate_holders(&svm, &mint, page_size).await {
+            Ok(enumeration) => enumeration,
+            Err(e) => return TestResultReport::failure(test_name, e),
+        };
+        let same = reference
+            .as_ref()
+            .is_none_or(|reference| reference.holders == enumeration.holders);
+        if enumeration.holders.len() != HOLDERS || enumeration.stats.pages != pages || !same {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "Page size {}: {} holders in {} pages (expected {}), same as before: {}",
+                    page_size,
+                    enumeration.holders.len(),
+                    enumeration.stats.pages,
+                    pages,
+                    same
+                ),
+            );
+        }
+        reference.get_or_insert(enumeration);
+    }
+
+    // Assertion: Accounts closed between the scan and their page are
+    // skipped, not reported and not fatal
+    assertions += 1;
+    let source = VanishingSource {
+        svm: &svm,
+        vanished: seeded.iter().step_by(50).copied().collect(),
+    };
+    match enumerate_holders(&source, &mint, 100).await {
+        Ok(enumeration)
+            if enumeration.stats.candidates == HOLDERS
+                && enumeration.stats.skipped == source.vanished.len()
+                && enumeration
+                    .holders
+                    .iter()
+                    .all(|holder| !source.vanished.contains(&holder.address)) => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "With {} vanished accounts: {:?}",
+                    source.vanished.len(),
+                    other.map(|enumeration| enumeration.stats)
+                ),
+            )
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: Over RPC, the scan and the pages are read from the node's
+/// responses
+#[tokio::test]
+async fn test_rpc_source() {
+    let report = run_rpc_source_test().await;
+    assert!(report.passed, "RPC source test failed: {:?}", report.error);
+}
+
+async fn run_rpc_source_test() -> TestResultReport {
+    let test_name = "Holder Enumeration Over RPC";
+    let mut assertions = 0;
+
+    let mint = Pubkey::new_unique();
+    let holder = Pubkey::new_unique();
+    let account = token_account(&mint, &Pubkey::new_unique(), 42, AccountState::Frozen);
+    let stranger = Pubkey::new_unique();
+    let encode = |address: &Pubkey, account: &Account| {
+        UiAccount::encode(address, account, UiAccountEncoding::Base64, None, None)
+    };
+
+    let mut mocks = Mocks::new();
+    mocks.insert(
+        RpcRequest::GetProgramAccounts,
+        json!([
+            { "pubkey": holder.to_string(), "account": encode(&holder, &Account::default()) },
+            { "pubkey": stranger.to_string(), "account": encode(&stranger, &Account::default()) },
+        ]),
+    );
+    mocks.insert(
+        RpcRequest::GetMultipleAccounts,
+        json!({
+            "context": { "slot": 1 },
+            "value": [encode(&holder, &account), null],
+        }),
+    );
+    let rpc = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+
+    // Assertion 1: Both scanned addresses are paged in, and the one the
+    // node no longer has is skipped
+    assertions += 1;
+    let enumeration = match enumerate_holders(&rpc, &mint, MAX_MULTIPLE_ACCOUNTS).await {
+        Ok(enumeration) => enumeration,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    if enumeration.stats.candidates != 2 || enumeration.stats.pages != 1 {
+        return TestResultReport::failure(
+            test_name,
+            format!("Scan and pages: {:?}", enumeration.stats),
+        );
+    }
+
+    // Assertion 2: The account the node returned decodes as a frozen holder
+    assertions += 1;
+    let decoded: Vec<(u64, bool)> = enumeration
+        .holders
+        .iter()
+        .map(|holder| (holder.amount, holder.is_frozen()))
+        .collect();
+    if decoded != [(42, true)] || enumeration.stats.skipped != 1 {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Decoded {:?}, skipped {}",
+                decoded, enumeration.stats.skipped
+            ),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 4: Ten thousand holders are enumerated in 100-account pages within
+/// the budget, and the largest are ranked for a mass freeze
+#[tokio::test]
+async fn test_enumeration_at_scale() {
+    let (report, enumeration) = run_scale_test().await;
+    if let Some(enumeration) = &enumeration {
+        println!("{}", enumeration.to_markdown());
+        write_performance_report(enumeration);
+    }
+    assert!(report.passed, "Scale test failed: {:?}", report.error);
+}
+
+async fn run_scale_test() -> (TestResultReport, Option<HolderEnumeration>) {
+    let test_name = "Holder Enumeration At Scale";
+    let mut assertions = 0;
+
+    let mut svm = SvmHarness::new();
+    let mint = Pubkey::new_unique();
+    seed(&mut svm, mint, SCALE_HOLDERS);
+    seed(&mut svm, Pubkey::new_unique(), SCALE_NOISE);
+
+    let enumeration = match enumerate_holders(&svm, &mint, MAX_MULTIPLE_ACCOUNTS).await {
+        Ok(enumeration) => enumeration,
+        Err(e) => return (TestResultReport::failure(test_name, e), None),
+    };
+
+    // Assertion 1: Every holder, and only them, in full pages
+    assertions += 1;
+    if enumeration.holders.len() != SCALE_HOLDERS
+        || enumeration.stats.pages != SCALE_HOLDERS.div_ceil(MAX_MULTIPLE_ACCOUNTS)
+    {
+        return (
+            TestResultReport::failure(
+                test_name,
+                format!(
+                    "{} holders in {} pages",
+                    enumeration.holders.len(),
+                    enumeration.stats.pages
+                ),
+            ),
+            None,
+        );
+    }
+
+    // Assertion 2: Within the budget
+    assertions += 1;
+    if enumeration.stats.elapsed > SCALE_BUDGET {
+        return (
+            TestResultReport::failure(
+                test_name,
+                format!(
+                    "Enumeration took {:?}, over the {:?} budget",
+                    enumeration.stats.elapsed, SCALE_BUDGET
+                ),
+            ),
+            None,
+        );
+    }
+
+    // Assertion 3: The seeded balances are a permutation of 1..=n, so the
+    // hundred largest are n down to n - 99
+    assertions += 1;
+    let top: Vec<u64> = enumeration
+        .top_by_balance(100)
+        .iter()
+        .map(|holder| holder.amount)
+        .collect();
+    let expected: Vec<u64> = (0..100).map(|i| (SCALE_HOLDERS - i) as u64).collect();
+    if top != expected {
+        return (
+            TestResultReport::failure(
+                test_name,
+                format!("Top balances start {:?}", &top[..top.len().min(5)]),
+            ),
+            None,
+        );
+    }
+
+    (
+        TestResultReport::success(test_name, assertions),
+        Some(enumeration),
+    )
+}
+
+fn write_performance_report(enumeration: &HolderEnumeration) {
+    let mut report = String::from("# Holder Enumeration Performance\n\n");
+    report.push_str(&format!("**Generated**: {}\n\n", time::report_timestamp()));
+    report.push_str(&format!(
+        "{} holders of one mint and {} accounts of another, on the in-process bank, read {} \
+         accounts per page.\n\n",
+        SCALE_HOLDERS, SCALE_NOISE, MAX_MULTIPLE_ACCOUNTS
+    ));
+    report.push_str(&enumeration.to_markdown());
+    fs::create_dir_all("../../tests/reports").ok();
+    if let Err(e) = fs::write(
+        "../../tests/reports/holder_enumeration_performance.md",
+        report,
+    ) {
+        panic!(
+            "Failed to write holder enumeration performance report: {}",
+            e
+        );
+    }
+}
+
+/// Generate holder enumeration test report
+#[tokio::test]
+async fn generate_holder_enumeration_report() {
+    let (scale, _) = run_scale_test().await;
+    let results = vec![
+        run_seeded_state_test().await,
+        run_pagination_test().await,
+        run_rpc_source_test().await,
+        scale,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Holder Enumeration Test Results",
+        "../../tests/reports/holder_enumeration.md",
+    ) {
+        panic!("Failed to generate holder enumeration report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} holder enumeration tests failed", failed);
+}
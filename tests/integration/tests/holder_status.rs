@@ -0,0 +1,465 @@
+//! Holder status tests
+//!
+//! These tests seed an in-process bank with allow list and block list
+//! records and Token-2022 accounts, then check what `token-acl status`
+//! prints for each holder: the same `HolderStatus` query and rendering the
+//! command runs, served from the bank instead of RPC.
+
+use borsh::BorshSerialize;
+use production_allow_list::{AccessLevel, AllowListRecord};
+use solana_sdk::{account::Account, pubkey::Pubkey, rent::Rent, signature::Signer};
+use spl_token_2022::{instruction as token_instruction, state::AccountState};
+use token_acl_client::status::{
+    token_account_filters, Gates, HolderStatus, StatusError, StatusSource,
+};
+use token_acl_integration_tests::{reporting, SvmHarness, TestResultReport};
+
+/// Cluster time the fixtures are judged at
+const NOW: i64 = 1_700_000_000;
+
+/// A bank served as a status source
+struct BankSource<'a>(&'a SvmHarness);
+
+impl StatusSource for BankSource<'_> {
+    async fn account(&self, address: &Pubkey) -> Result<Option<(Pubkey, Vec<u8>)>, StatusError> {
+        Ok(self
+            .0
+            .account(address)
+            .map(|account| (account.owner, account.data)))
+    }
+
+    async fn token_accounts(
+        &self,
+        mint: &Pubkey,
+        owner: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Vec<u8>)>, StatusError> {
+        let accounts = self
+            .0
+            .program_accounts(&spl_token_2022::id(), &token_account_filters(mint, owner))
+            .map_err(StatusError::Rpc)?;
+        Ok(accounts
+            .into_iter()
+            .map(|(address, account)| (address, account.data))
+            .collect())
+    }
+}
+
+/// A bank with a mint whose accounts start frozen, and the two gates'
+/// program ids
+struct Fixtures {
+    svm: SvmHarness,
+    mint: Pubkey,
+    gates: Gates,
+}
+
+impl Fixtures {
+    fn new() -> Result<Self, String> {
+        let mut svm = SvmHarness::new();
+        let authority = svm.payer.pubkey();
+        let mint = svm
+            .create_mint(&authority, AccountState::Frozen)
+            .map_err(|e| format!("Mint creation failed: {}", e))?;
+        Ok(Self {
+            svm,
+            mint,
+            gates: Gates {
+                allow_list: Some(Pubkey::new_unique()),
+                block_list: Some(Pubkey::new_unique()),
+            },
+        })
+    }
+
+    fn plant(&mut self, address: Pubkey, owner: Pubkey, data: Vec<u8>) {
+        self.svm.set_account(
+            address,
+            Account {
+                lamports: Rent::default().minimum_balance(data.len()),
+                data,
+                owner,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+    }
+
+    fn allow_list_address(&self, owner: &Pubkey) -> Pubkey {
+        let gate = self.gates.allow_list.expect("fixtures have an allow list");
+        gate_view::allow_list_address(&gate, &self.mint, owner).address()
+    }
+
+    fn block_list_address(&self, owner: &Pubkey) -> Pubkey {
+        let gate = self.gates.block_list.expect("fixtures have a block list");
+        gate_view::block_list_address(&gate, &self.mint, owner).address()
+    }
+
+    /// Give `owner` an allow list record
+    fn list(
+        &mut self,
+        owner: &Pubkey,
+        access_level: AccessLevel,
+        expiry_timestamp: Option<i64>,
+        revoked: bool,
+    ) {
+        let gate = self.gates.allow_list.expect("fixtures have an allow list");
+        let pda = gate_view::allow_list_address(&gate, &self.mint, owner);
+        let record = AllowListRecord {
+            mint: self.mint,
+            user: *owner,
+            allowed: true,
+            access_level,
+            added_timestamp: NOW - 86_400,
+            expiry_timestamp,
+            bump: pda.bump(),
+            revoked,
+        };
+        let data = record.try_to_vec().expect("records always serialize");
+        self.plant(pda.address(), gate, data);
+    }
+
+    /// Give `owner` a block list record
+    fn block(&mut self, owner: &Pubkey, reason: example_block_list::BlockReason) {
+        let gate = self.gates.block_list.expect("fixtures have a block list");
+        let mut record =
+            example_block_list::create_block_list_record(&self.mint, owner, reason, NOW);
+        record.blocked = true;
+        let data = record.try_to_vec().expect("records always serialize");
+        let address = self.block_list_address(owner);
+        self.plant(address, gate, data);
+    }
+
+    /// A token account of `owner` holding `amount`, thawed unless `frozen`
+    fn token_account(
+        &mut self,
+        owner: &Pubkey,
+        amount: u64,
+        frozen: bool,
+    ) -> Result<Pubkey, String> {
+        let token_account = self
+            .svm
+            .create_token_account(&self.mint, owner)
+            .map_err(|e| format!("Token account creation failed: {}", e))?;
+        let authority = self.svm.payer.pubkey();
+        let thaw = token_instruction::thaw_account(
+            &spl_token_2022::id(),
+            &token_account,
+            &self.mint,
+            &authority,
+            &[],
+        )
+        .map_err(|e| e.to_string())?;
+        let mint_to = token_instruction::mint_to(
+            &spl_token_2022::id(),
+            &self.mint,
+            &token_account,
+            &authority,
+            &[],
+            amount,
+        )
+        .map_err(|e| e.to_string())?;
+        let mut instructions = vec![thaw, mint_to];
+        if frozen {
+            instructions.push(
+                token_instruction::freeze_account(
+                    &spl_token_2022::id(),
+                    &token_account,
+                    &self.mint,
+                    &authority,
+                    &[],
+                )
+                .map_err(|e| e.to_string())?,
+            );
+        }
+        self.svm
+            .send(&instructions, &[])
+            .map_err(|e| format!("Funding {} failed: {}", token_account, e))?;
+        Ok(token_account)
+    }
+
+    fn status(&self, gates: Gates, owner: &Pubkey) -> Result<HolderStatus, StatusError> {
+        futures::executor::block_on(HolderStatus::query(
+            &BankSource(&self.svm),
+            gates,
+            &self.mint,
+            owner,
+            NOW,
+        ))
+    }
+}
+
+/// The lines `token-acl status` prints before the token accounts
+fn header(mint: &Pubkey, owner: &Pubkey, allow_list: &str, block_list: &str) -> String {
+    format!(
+        "Mint:  {}\nOwner: {}\nAllow list: {}\nBlock list: {}\n",
+        mint, owner, allow_list, block_list
+    )
+}
+
+/// Test 1: An allowed holder sees their tier, expiry and every token
+/// account with its state
+#[test]
+fn test_allowed_holder_status() {
+    let report = run_allowed_holder_status_test();
+    assert!(
+        report.passed,
+        "Allowed holder status test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_allowed_holder_status_test() -> TestResultReport {
+    let test_name = "Allowed Holder Status";
+    let mut assertions = 0;
+
+    let mut fixtures = match Fixtures::new() {
+        Ok(fixtures) => fixtures,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let owner = Pubkey::new_unique();
+    fixtures.list(&owner, AccessLevel::Enhanced, Some(NOW + 3_600), false);
+    let mut accounts = Vec::new();
+    for (amount, frozen) in [(250, false), (0, true)] {
+        match fixtures.token_account(&owner, amount, frozen) {
+            Ok(account) => accounts.push((account, amount, frozen)),
+            Err(e) => return TestResultReport::failure(test_name, e),
+        }
+    }
+    // Another holder's account of the mint is not theirs
+    if let Err(e) = fixtures.token_account(&Pubkey::new_unique(), 10, false) {
+        return TestResultReport::failure(test_name, e);
+    }
+    accounts.sort_by_key(|(account, _, _)| *account);
+
+    let status = match fixtures.status(fixtures.gates, &owner) {
+        Ok(status) => status,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+
+    // Assertion 1: Listed and not blocked
+    assertions += 1;
+    if status.allow_listed() != Some(true) || status.blocked() != Some(false) {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Allow listed {:?}, blocked {:?}",
+                status.allow_listed(),
+                status.blocked()
+            ),
+        );
+    }
+
+    // Assertion 2: The printed status names the tier, the expiry and each
+    // of the owner's accounts, frozen or not
+    assertions += 1;
+    let mut expected = header(
+        &fixtures.mint,
+        &owner,
+        &format!("allowed (tier enhanced, expires at {})", NOW + 3_600),
+        "not listed",
+    );
+    expected.push_str("Token accounts (2):\n");
+    for (account, amount, frozen) in &accounts {
+        expected.push_str(&format!(
+            "  {}  {}  {}\n",
+            account,
+            if *frozen { "frozen" } else { "active" },
+            amount
+        ));
+    }
+    if status.render() != expected {
+        return TestResultReport::failure(
+            test_name,
+            format!("Printed\n{}\nexpected\n{}", status.render(), expected),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: Revoked, expired, blocked and unknown holders each see why
+#[test]
+fn test_denied_holder_status() {
+    let report = run_denied_holder_status_test();
+    assert!(
+        report.passed,
+        "Denied holder status test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_denied_holder_status_test() -> TestResultReport {
+    let test_name = "Denied Holder Status";
+    let mut assertions = 0;
+
+    let mut fixtures = match Fixtures::new() {
+        Ok(fixtures) => fixtures,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let revoked = Pubkey::new_unique();
+    fixtures.list(&revoked, AccessLevel::Basic, None, true);
+    let expired = Pubkey::new_unique();
+    fixtures.list(&expired, AccessLevel::Institutional, Some(NOW - 1), false);
+    let blocked = Pubkey::new_unique();
+    fixtures.list(&blocked, AccessLevel::Basic, None, false);
+    fixtures.block(&blocked, example_block_list::BlockReason::Compliance);
+    let unknown = Pubkey::new_unique();
+
+    for (owner, allow_list, block_list) in [
+        (revoked, "revoked (tier basic, no expiry)", "not listed"),
+        (
+            expired,
+            &*format!("expired (tier institutional, expires at {})", NOW - 1),
+            "not listed",
+        ),
+        (
+            blocked,
+            "allowed (tier basic, no expiry)",
+            "blocked (compliance)",
+        ),
+        (unknown, "not listed", "not listed"),
+    ] {
+        // Assertion: The printed status says why, with no token accounts
+        assertions += 1;
+        let status = match fixtures.status(fixtures.gates, &owner) {
+            Ok(status) => status,
+            Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+        };
+        let expected =
+            header(&fixtures.mint, &owner, allow_list, block_list) + "Token accounts: none\n";
+        if status.render() != expected {
+            return TestResultReport::failure(
+                test_name,
+                format!("Printed\n{}\nexpected\n{}", status.render(), expected),
+            );
+        }
+    }
+
+    // Assertion: Only the allowed holder who is blocked is let in by the
+    // allow list, and only they are blocked
+    assertions += 1;
+    for (owner, allow_listed, is_blocked) in [
+        (revoked, false, false),
+        (expired, false, false),
+        (blocked, true, true),
+        (unknown, false, false),
+    ] {
+        let status = match fixtures.status(fixtures.gates, &owner) {
+            Ok(status) => status,
+            Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+        };
+        if status.allow_listed() != Some(allow_listed) || status.blocked() != Some(is_blocked) {
+            return TestResultReport::failure(test_name, format!("{} read as {:?}", owner, status));
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: Lists without a gate are not checked, and a record the gate did
+/// not write is an error rather than a status
+#[test]
+fn test_unchecked_and_forged_records() {
+    let report = run_unchecked_and_forged_records_test();
+    assert!(
+        report.passed,
+        "Unchecked and forged records test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_unchecked_and_forged_records_test() -> TestResultReport {
+    let test_name = "Unchecked And Forged Records";
+    let mut assertions = 0;
+
+    let mut fixtures = match Fixtures::new() {
+        Ok(fixtures) => fixtures,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let owner = Pubkey::new_unique();
+    fixtures.list(&owner, AccessLevel::Basic, None, false);
+
+    // Assertion 1: Without a block list gate only the allow list is read
+    assertions += 1;
+    let gates = Gates {
+        block_list: None,
+        ..fixtures.gates
+    };
+    match fixtures.status(gates, &owner) {
+        Ok(status) => {
+            let expected = header(
+                &fixtures.mint,
+                &owner,
+                "allowed (tier basic, no expiry)",
+                "not checked",
+            ) + "Token accounts: none\n";
+            if status.render() != expected || status.blocked().is_some() {
+                return TestResultReport::failure(
+                    test_name,
+                    format!("Printed\n{}\nexpected\n{}", status.render(), expected),
+                );
+            }
+        }
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    }
+
+    // Assertion 2: A record at the PDA owned by another program is rejected
+    assertions += 1;
+    let address = fixtures.allow_list_address(&owner);
+    let mut forged = fixtures
+        .svm
+        .account(&address)
+        .expect("the record was planted");
+    forged.owner = Pubkey::new_unique();
+    fixtures.svm.set_account(address, forged);
+    match fixtures.status(fixtures.gates, &owner) {
+        Err(StatusError::InvalidRecord(at)) if at == address => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Forged record read as {:?}", other),
+            )
+        }
+    }
+
+    // Assertion 3: So is another owner's record copied to the PDA
+    assertions += 1;
+    let other = Pubkey::new_unique();
+    fixtures.list(&other, AccessLevel::Basic, None, false);
+    let copy = fixtures
+        .svm
+        .account(&fixtures.allow_list_address(&other))
+        .expect("the record was planted");
+    fixtures.svm.set_account(address, copy);
+    match fixtures.status(fixtures.gates, &owner) {
+        Err(StatusError::InvalidRecord(at)) if at == address => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Copied record read as {:?}", other),
+            )
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate holder status test report
+#[test]
+fn generate_holder_status_report() {
+    let results = vec![
+        run_allowed_holder_status_test(),
+        run_denied_holder_status_test(),
+        run_unchecked_and_forged_records_test(),
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Holder Status Test Results",
+        "../../tests/reports/holder_status.md",
+    ) {
+        panic!("Failed to generate holder status report: {}", e);
+    }
+
+    for result in &results {
+        assert!(result.passed, "{} failed: {:?}", result.name, result.error);
+    }
+}
@@ -0,0 +1,785 @@
+//! Hybrid gate sanctions-precedence tests
+//!
+//! These tests run the hybrid gate against the production allow list and the
+//! block list example in `solana-program-test`. The gate reads both programs'
+//! records, and a sanctions record must win over an active allow list
+//! record: a sanctioned user can never thaw, and a keeper can always freeze
+//! them, even with a thaw already signed and waiting to land. A cooling-off
+//! period, set only by the gate's authority, delays the thaw of a newly
+//! allow-listed holder, and pausing the gate denies every thaw until it is
+//! unpaused.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use example_block_list::{block_list_address, create_block_list_record, BlockReason};
+use hybrid_gate::{
+    check_thaw, config_address, HybridConfig, ThawDenial, SET_COOLING_OFF, SET_PAUSED,
+};
+use production_allow_list::{allow_list_address, AllowListRecord, ADD_TO_ALLOW_LIST, INITIALIZE};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    clock::Clock,
+    instruction::{AccountMeta, Instruction},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::Transaction,
+};
+use spl_token_2022::{
+    instruction as token_instruction,
+    state::{Account as TokenAccount, AccountState},
+};
+
+use token_acl_integration_tests::{
+    program_test::{mint_account, rent_exempt_account, token_account},
+    reporting, TestResultReport,
+};
+use token_acl_interface as interface;
+
+/// A bank with the three programs, a mint the issuer can freeze, and one
+/// holder's token account
+struct HybridFixture {
+    context: ProgramTestContext,
+    gate: Pubkey,
+    allow_list: Pubkey,
+    block_list: Pubkey,
+    issuer: Keypair,
+    mint: Pubkey,
+    holder: Pubkey,
+    token_account: Pubkey,
+}
+
+impl HybridFixture {
+    /// Start a validator with the holder's account in `state`, the allow
+    /// list and hybrid gate initialized for the mint, and the holder added
+    /// to the allow list
+    async fn new(state: AccountState) -> Self {
+        let gate = Pubkey::new_unique();
+        let allow_list = Pubkey::new_unique();
+        let block_list = Pubkey::new_unique();
+        let issuer = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let holder = Pubkey::new_unique();
+        let holder_account = Pubkey::new_unique();
+
+        let mut program_test = ProgramTest::new(
+            "hybrid_gate",
+            gate,
+            processor!(hybrid_gate::process_instruction),
+        );
+        program_test.add_program(
+            "production_allow_list",
+            allow_list,
+            processor!(production_allow_list::process_instruction),
+        );
+        program_test.add_program(
+            "example_block_list",
+            block_list,
+            processor!(example_block_list::process_instruction),
+        );
+
+        program_test.add_account(mint, mint_account(&issuer.pubkey(), 1_000));
+
+        program_test.add_account(holder_account, token_account(&mint, &holder, 1_000, state));
+
+        let context = program_test.start_with_context().await;
+        let mut fixture = Self {
+            context,
+            gate,
+            allow_list,
+            block_list,
+            issuer,
+            mint,
+            holder,
+            token_account: holder_account,
+        };
+
+        let payer = fixture.context.payer.pubkey();
+        let issuer = fixture.issuer.pubkey();
+        let allow_list_config = production_allow_list::config_address(&mint, &allow_list).address();
+        let initialize_allow_list = Instruction::new_with_bytes(
+            allow_list,
+            &[INITIALIZE],
+            vec![
+                AccountMeta::new(allow_list_config, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(issuer, true),
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        let mut hybrid_data = vec![hybrid_gate::INITIALIZE];
+        hybrid_data.extend_from_slice(allow_list.as_ref());
+        hybrid_data.extend_from_slice(block_list.as_ref());
+        let initialize_gate = Instruction::new_with_bytes(
+            gate,
+            &hybrid_data,
+            vec![
+                AccountMeta::new(fixture.config(), false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(issuer, true),
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        let add_holder = Instruction::new_with_bytes(
+            allow_list,
+            &[ADD_TO_ALLOW_LIST],
+            vec![
+                AccountMeta::new_readonly(allow_list_config, false),
+                AccountMeta::new(fixture.allow_list_record(), false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(holder, false),
+                AccountMeta::new_readonly(issuer, true),
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        fixture
+            .send(&[initialize_allow_list, initialize_gate, add_holder], &[])
+            .await
+            .expect("setup should succeed");
+        fixture
+    }
+
+    fn config(&self) -> Pubkey {
+        config_address(&self.mint, &self.gate).address()
+    }
+
+    fn allow_list_record(&self) -> Pubkey {
+        allow_list_address(&self.mint, &self.holder, &self.allow_list).address()
+    }
+
+    fn block_list_record(&self) -> Pubkey {
+        block_list_address(&self.mint, &self.holder, &self.block_list).address()
+    }
+
+    /// Put the holder on the sanctions list, or take them off it
+    ///
+    /// The block list example has no admin instructions, so the record is
+    /// written directly, owned by the block list program.
+    fn set_sanctioned(&mut self, sanctioned: bool) {
+        let mut record =
+            create_block_list_record(&self.mint, &self.holder, BlockReason::Sanctions, 0);
+        record.blocked = sanctioned;
+        let account = rent_exempt_account(record.try_to_vec().unwrap(), self.block_list);
+        self.context
+            .set_account(&self.block_list_record(), &account.into());
+    }
+
+    /// Delete the holder's sanctions record
+    ///
+    /// Unlike lifting the sanction, this also takes back the lamports
+    /// `set_sanctioned` created, which a later warp checks the bank's
+    /// capitalization against.
+    fn remove_block_list_record(&mut self) {
+        self.context
+            .set_account(&self.block_list_record(), &Account::default().into());
+    }
+
+    /// The gate's thaw check followed by the thaw the Token ACL program
+    /// would make on its approval, with the issuer standing in for the FAMP
+    /// PDA
+    fn thaw_instructions(&self) -> Vec<Instruction> {
+        let extra_metas =
+            interface::find_thaw_extra_account_metas_address(&self.mint, &self.gate).0;
+        let check = interface::can_thaw_permissionless(
+            &self.gate,
+            self.holder,
+            self.token_account,
+            self.mint,
+            extra_metas,
+            &[
+                self.holder.into(),
+                self.config().into(),
+                self.allow_list_record().into(),
+                self.block_list_record().into(),
+            ],
+        );
+        let thaw = token_instruction::thaw_account(
+            &spl_token_2022::id(),
+            &self.token_account,
+            &self.mint,
+            &self.issuer.pubkey(),
+            &[],
+        )
+        .unwrap();
+        vec![check, thaw]
+    }
+
+    /// A keeper's freeze check followed by the freeze it authorizes
+    fn keeper_freeze_instructions(&self, keeper: &Pubkey) -> Vec<Instruction> {
+        let extra_metas =
+            interface::find_freeze_extra_account_metas_address(&self.mint, &self.gate).0;
+        let check = interface::can_freeze_permissionless(
+            &self.gate,
+            keeper,
+            self.token_account,
+            self.mint,
+            extra_metas,
+            &[self.config().into(), self.block_list_record().into()],
+        );
+        let freeze = token_instruction::freeze_account(
+            &spl_token_2022::id(),
+            &self.token_account,
+            &self.mint,
+            &self.issuer.pubkey(),
+            &[],
+        )
+        .unwrap();
+        vec![check, freeze]
+    }
+
+    /// The gate authority's instruction setting the cooling-off period,
+    /// signed by `authority`
+    fn set_cooling_off_instruction(&self, authority: &Pubkey, seconds: u32) -> Instruction {
+        let mut data = vec![SET_COOLING_OFF];
+        data.extend_from_slice(&seconds.to_le_bytes());
+        Instruction::new_with_bytes(
+            self.gate,
+            &data,
+            vec![
+                AccountMeta::new(self.config(), false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(*authority, true),
+            ],
+        )
+    }
+
+    /// The gate authority's instruction pausing or unpausing thaws, signed
+    /// by `authority`
+    fn set_paused_instruction(&self, authority: &Pubkey, paused: bool) -> Instruction {
+        Instruction::new_with_bytes(
+            self.gate,
+            &[SET_PAUSED, u8::from(paused)],
+            vec![
+                AccountMeta::new(self.config(), false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(*authority, true),
+            ],
+        )
+    }
+
+    async fn hybrid_config(&mut self) -> HybridConfig {
+        let account = self
+            .context
+            .banks_client
+            .get_account(self.config())
+            .await
+            .unwrap()
+            .expect("config should exist");
+        HybridConfig::try_from_slice(&account.data).unwrap()
+    }
+
+    async fn cooling_off_seconds(&mut self) -> u32 {
+        self.hybrid_config().await.cooling_off_seconds
+    }
+
+    /// When the holder was added to the allow list
+    async fn added_timestamp(&mut self) -> i64 {
+        let account = self
+            .context
+            .banks_client
+            .get_account(self.allow_list_record())
+            .await
+            .unwrap()
+            .expect("allow list record should exist");
+        AllowListRecord::try_from_slice(&account.data)
+            .unwrap()
+            .added_timestamp
+    }
+
+    /// Warp to a later slot and set the cluster time to `unix_timestamp`
+    async fn advance_clock(&mut self, unix_timestamp: i64) {
+        let slot = self.context.banks_client.get_root_slot().await.unwrap();
+        self.context.warp_to_slot(slot + 100).unwrap();
+        let mut clock: Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        clock.unix_timestamp = unix_timestamp;
+        self.context.set_sysvar(&clock);
+    }
+
+    async fn is_frozen(&mut self) -> bool {
+        let account = self
+            .context
+            .banks_client
+            .get_account(self.token_account)
+            .await
+            .unwrap()
+            .expect("token account should exist");
+        TokenAccount::unpack(&account.data).unwrap().state == AccountState::Frozen
+    }
+
+    /// Sign `instructions` now, to be submitted later
+    async fn sign(&mut self, instructions: &[Instruction], signers: &[&Keypair]) -> Transaction {
+        // A fresh blockhash keeps repeated transactions from being deduplicated
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let mut transaction =
+            Transaction::new_with_payer(instructions, Some(&self.context.payer.pubkey()));
+        // The issuer signs only the instructions that need it
+        let mut all_signers = vec![&self.context.payer];
+        if transaction
+            .message
+            .signer_keys()
+            .contains(&&self.issuer.pubkey())
+        {
+            all_signers.push(&self.issuer);
+        }
+        all_signers.extend_from_slice(signers);
+        transaction.sign(&all_signers, blockhash);
+        transaction
+    }
+
+    async fn submit(&mut self, transaction: Transaction) -> Result<(), BanksClientError> {
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+    }
+
+    async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        let transaction = self.sign(instructions, signers).await;
+        self.submit(transaction).await
+    }
+
+    /// Logs of simulating `instructions`, and whether they would succeed
+    async fn simulate(&mut self, instructions: &[Instruction]) -> (bool, Vec<String>) {
+        let transaction = self.sign(instructions, &[]).await;
+        let simulation = self
+            .context
+            .banks_client
+            .simulate_transaction(transaction)
+            .await
+            .unwrap();
+        let logs = simulation
+            .simulation_details
+            .map(|details| details.logs)
+            .unwrap_or_default();
+        (simulation.result.is_some_and(|result| result.is_ok()), logs)
+    }
+}
+
+/// Test 1: The precedence rule itself, for every combination of the lists
+#[test]
+fn test_sanctions_precedence_rule() {
+    let report = run_precedence_rule_test();
+    assert!(
+        report.passed,
+        "Sanctions precedence rule test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_precedence_rule_test() -> TestResultReport {
+    let test_name = "Hybrid Gate Precedence Rule";
+    let mut assertions = 0;
+
+    let cases = [
+        (false, true, Ok(())),
+        (true, true, Err(ThawDenial::Sanctioned)),
+        (true, false, Err(ThawDenial::Sanctioned)),
+        (false, false, Err(ThawDenial::NotAllowed)),
+    ];
+    for (sanctioned, allowed, expected) in cases {
+        // Assertion: One per combination of sanctioned and allow-listed
+        assertions += 1;
+        let decision = check_thaw(sanctioned, true, allowed);
+        if decision != expected {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "sanctioned={} allowed={}: expected {:?}, got {:?}",
+                    sanctioned, allowed, expected, decision
+                ),
+            );
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: A user on both lists is denied a thaw, and allowed again once the
+/// sanction is lifted
+#[tokio::test]
+async fn test_user_on_both_lists_cannot_thaw() {
+    let report = run_both_lists_test().await;
+    assert!(report.passed, "Both lists test failed: {:?}", report.error);
+}
+
+async fn run_both_lists_test() -> TestResultReport {
+    let test_name = "Hybrid Gate User On Both Lists";
+    let mut assertions = 0;
+
+    let mut fixture = HybridFixture::new(AccountState::Frozen).await;
+    let thaw = fixture.thaw_instructions();
+
+    // Assertion 1: An allow-listed, unsanctioned holder can thaw
+    assertions += 1;
+    if !fixture.simulate(&thaw).await.0 {
+        return TestResultReport::failure(test_name, "Allow-listed holder cannot thaw".to_string());
+    }
+
+    // Assertion 2: Once sanctioned, the same holder is denied, and the gate
+    // says sanctions decided it
+    assertions += 1;
+    fixture.set_sanctioned(true);
+    match fixture.simulate(&thaw).await {
+        (false, logs) if logs.iter().any(|log| log.contains("sanctions override")) => {}
+        (passed, logs) => {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "Sanctioned holder's thaw: passed={}, logs={:?}",
+                    passed, logs
+                ),
+            )
+        }
+    }
+
+    // Assertion 3: The holder is still on the allow list, so it is the
+    // sanction alone that denies
+    assertions += 1;
+    let record = fixture.allow_list_record();
+    match fixture.context.banks_client.get_account(record).await {
+        Ok(Some(account)) if account.owner == fixture.allow_list => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Allow list record missing: {:?}", other),
+            )
+        }
+    }
+    if fixture.send(&thaw, &[]).await.is_ok() || !fixture.is_frozen().await {
+        return TestResultReport::failure(
+            test_name,
+            "CRITICAL: Sanctioned holder thawed despite being on the allow list".to_string(),
+        );
+    }
+
+    // Assertion 4: Lifting the sanction restores the allow list's say
+    assertions += 1;
+    fixture.set_sanctioned(false);
+    if let Err(e) = fixture.send(&thaw, &[]).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("Thaw after lifting the sanction failed: {}", e),
+        );
+    }
+    if fixture.is_frozen().await {
+        return TestResultReport::failure(test_name, "Account still frozen".to_string());
+    }
+
+    // Assertion 5: An allow-listed, unsanctioned holder cannot be frozen by
+    // a keeper
+    assertions += 1;
+    let keeper = Pubkey::new_unique();
+    let freeze = fixture.keeper_freeze_instructions(&keeper);
+    if fixture.send(&freeze, &[]).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Keeper froze an unsanctioned holder".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: A keeper freeze wins over a thaw that is still pending
+#[tokio::test]
+async fn test_keeper_freeze_wins_over_pending_thaw() {
+    let report = run_keeper_precedence_test().await;
+    assert!(
+        report.passed,
+        "Keeper precedence test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_keeper_precedence_test() -> TestResultReport {
+    let test_name = "Hybrid Gate Keeper Freeze Precedence";
+    let mut assertions = 0;
+
+    let keeper = Pubkey::new_unique();
+
+    // Assertion 1: A thaw signed before the sanction lands is rejected after
+    // it, and the account stays frozen
+    assertions += 1;
+    let mut fixture = HybridFixture::new(AccountState::Frozen).await;
+    let thaw = fixture.thaw_instructions();
+    let pending_thaw = fixture.sign(&thaw, &[]).await;
+    fixture.set_sanctioned(true);
+    if fixture.submit(pending_thaw).await.is_ok() || !fixture.is_frozen().await {
+        return TestResultReport::failure(
+            test_name,
+            "Pending thaw landed after the sanction".to_string(),
+        );
+    }
+
+    // Assertion 2: A thaw that lands before the sanction does not shield
+    // the holder: the keeper's freeze is authorized and freezes the account
+    assertions += 1;
+    let mut fixture = HybridFixture::new(AccountState::Frozen).await;
+    let thaw = fixture.thaw_instructions();
+    if let Err(e) = fixture.send(&thaw, &[]).await {
+        return TestResultReport::failure(test_name, format!("Thaw failed: {}", e));
+    }
+    fixture.set_sanctioned(true);
+    let freeze = fixture.keeper_freeze_instructions(&keeper);
+    if let Err(e) = fixture.send(&freeze, &[]).await {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Keeper freeze of an allow-listed, sanctioned holder failed: {}",
+                e
+            ),
+        );
+    }
+    if !fixture.is_frozen().await {
+        return TestResultReport::failure(test_name, "Account not frozen".to_string());
+    }
+
+    // Assertion 3: The holder's retried thaw is denied after the freeze
+    assertions += 1;
+    if fixture.send(&thaw, &[]).await.is_ok() || !fixture.is_frozen().await {
+        return TestResultReport::failure(test_name, "Thaw undid the keeper's freeze".to_string());
+    }
+
+    // Assertion 4: With the freeze and the thaw in the same slot, whichever
+    // comes second, the account ends frozen
+    assertions += 1;
+    let mut fixture = HybridFixture::new(AccountState::Initialized).await;
+    fixture.set_sanctioned(true);
+    let freeze = fixture
+        .sign(&fixture.keeper_freeze_instructions(&keeper), &[])
+        .await;
+    let thaw = fixture.sign(&fixture.thaw_instructions(), &[]).await;
+    let (frozen, thawed) = (fixture.submit(freeze).await, fixture.submit(thaw).await);
+    if frozen.is_err() || thawed.is_ok() || !fixture.is_frozen().await {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Freeze then thaw in one slot: freeze={:?}, thaw={:?}",
+                frozen, thawed
+            ),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 4: A newly allow-listed holder waits out the cooling-off period, and
+/// only the gate authority can set it
+#[tokio::test]
+async fn test_cooling_off_delays_thaw() {
+    let report = run_cooling_off_test().await;
+    assert!(report.passed, "Cooling-off test failed: {:?}", report.error);
+}
+
+async fn run_cooling_off_test() -> TestResultReport {
+    let test_name = "Hybrid Gate Cooling-Off Period";
+    let mut assertions = 0;
+
+    const COOLING_OFF: u32 = 3_600;
+    let mut fixture = HybridFixture::new(AccountState::Frozen).await;
+    let thaw = fixture.thaw_instructions();
+    let added = fixture.added_timestamp().await;
+
+    // Assertion 1: The allow list stamps the record with the cluster time
+    assertions += 1;
+    if added <= 0 {
+        return TestResultReport::failure(
+            test_name,
+            format!("Allow list record added at {}", added),
+        );
+    }
+
+    // Assertion 2: Anyone but the gate authority is refused, and the period
+    // stays off
+    assertions += 1;
+    let intruder = Keypair::new();
+    let set_by_intruder = fixture.set_cooling_off_instruction(&intruder.pubkey(), 0);
+    if fixture.send(&[set_by_intruder], &[&intruder]).await.is_ok()
+        || fixture.cooling_off_seconds().await != 0
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Cooling-off period set by someone other than the gate authority".to_string(),
+        );
+    }
+
+    // Assertion 3: The gate authority sets the period
+    assertions += 1;
+    let set_by_issuer = fixture.set_cooling_off_instruction(&fixture.issuer.pubkey(), COOLING_OFF);
+    if let Err(e) = fixture.send(&[set_by_issuer], &[]).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("Gate authority could not set the cooling-off period: {}", e),
+        );
+    }
+    if fixture.cooling_off_seconds().await != COOLING_OFF {
+        return TestResultReport::failure(test_name, "Cooling-off period not stored".to_string());
+    }
+
+    // Assertion 4: One second before the period ends the thaw is denied,
+    // and the gate says the holder is cooling off
+    assertions += 1;
+    fixture
+        .advance_clock(added + i64::from(COOLING_OFF) - 1)
+        .await;
+    match fixture.simulate(&thaw).await {
+        (false, logs) if logs.iter().any(|log| log.contains("cooling off")) => {}
+        (passed, logs) => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Thaw while cooling off: passed={}, logs={:?}", passed, logs),
+            )
+        }
+    }
+
+    // Assertion 5: The cooling-off period is checked after the lists, so a
+    // sanctioned holder is denied for the sanction
+    assertions += 1;
+    fixture.set_sanctioned(true);
+    match fixture.simulate(&thaw).await {
+        (false, logs)
+            if logs.iter().any(|log| log.contains("sanctions override"))
+                && !logs.iter().any(|log| log.contains("cooling off")) => {}
+        (passed, logs) => {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "Sanctioned thaw while cooling off: passed={}, logs={:?}",
+                    passed, logs
+                ),
+            )
+        }
+    }
+    fixture.remove_block_list_record();
+
+    // Assertion 6: From the second the period ends, the thaw goes through
+    assertions += 1;
+    fixture.advance_clock(added + i64::from(COOLING_OFF)).await;
+    if let Err(e) = fixture.send(&thaw, &[]).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("Thaw after the cooling-off period failed: {}", e),
+        );
+    }
+    if fixture.is_frozen().await {
+        return TestResultReport::failure(test_name, "Account still frozen".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 5: Pausing the gate denies every thaw but no freeze, and only the
+/// gate authority can flip the switch
+#[tokio::test]
+async fn test_pause_denies_thaws() {
+    let report = run_pause_test().await;
+    assert!(report.passed, "Pause test failed: {:?}", report.error);
+}
+
+async fn run_pause_test() -> TestResultReport {
+    let test_name = "Hybrid Gate Pause";
+    let mut assertions = 0;
+
+    let mut fixture = HybridFixture::new(AccountState::Initialized).await;
+    let thaw = fixture.thaw_instructions();
+
+    // Assertion 1: Anyone but the gate authority is refused, and the gate
+    // stays unpaused
+    assertions += 1;
+    let intruder = Keypair::new();
+    let pause_by_intruder = fixture.set_paused_instruction(&intruder.pubkey(), true);
+    if fixture
+        .send(&[pause_by_intruder], &[&intruder])
+        .await
+        .is_ok()
+        || fixture.hybrid_config().await.paused
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Gate paused by someone other than the gate authority".to_string(),
+        );
+    }
+
+    // Assertion 2: Once the gate authority pauses, a keeper can still
+    // freeze a sanctioned holder
+    assertions += 1;
+    let pause = fixture.set_paused_instruction(&fixture.issuer.pubkey(), true);
+    if let Err(e) = fixture.send(&[pause], &[]).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("Gate authority could not pause: {}", e),
+        );
+    }
+    fixture.set_sanctioned(true);
+    let freeze = fixture.keeper_freeze_instructions(&Pubkey::new_unique());
+    if let Err(e) = fixture.send(&freeze, &[]).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("Keeper freeze while paused failed: {}", e),
+        );
+    }
+
+    // Assertion 3: With the sanction lifted, the allow-listed holder is
+    // still denied, and the gate says it is paused
+    assertions += 1;
+    fixture.remove_block_list_record();
+    match fixture.simulate(&thaw).await {
+        (false, logs) if logs.iter().any(|log| log.contains("are paused")) => {}
+        (passed, logs) => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Thaw while paused: passed={}, logs={:?}", passed, logs),
+            )
+        }
+    }
+
+    // Assertion 4: Unpausing restores the lists' say, and the thaw goes
+    // through
+    assertions += 1;
+    let unpause = fixture.set_paused_instruction(&fixture.issuer.pubkey(), false);
+    if let Err(e) = fixture.send(&[unpause], &[]).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("Gate authority could not unpause: {}", e),
+        );
+    }
+    if let Err(e) = fixture.send(&thaw, &[]).await {
+        return TestResultReport::failure(test_name, format!("Thaw after unpausing failed: {}", e));
+    }
+    if fixture.is_frozen().await {
+        return TestResultReport::failure(test_name, "Account still frozen".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate hybrid gate test report
+#[tokio::test]
+async fn generate_hybrid_gate_report() {
+    let results = vec![
+        run_precedence_rule_test(),
+        run_both_lists_test().await,
+        run_keeper_precedence_test().await,
+        run_cooling_off_test().await,
+        run_pause_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Hybrid Gate Test Results",
+        "../../tests/reports/hybrid_gate.md",
+    ) {
+        panic!("Failed to generate hybrid gate report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} hybrid gate tests failed", failed);
+}
@@ -10,7 +10,7 @@ use solana_sdk::{
     signature::{Keypair, Signer},
 };
 
-use token_acl_integration_tests::{fixtures::test_data, reporting, utils, TestResultReport};
+use token_acl_integration_tests::{fixtures::test_data, reporting, self_test, TestResultReport};
 
 /// Test 1: PDA Derivation Correctness
 #[test]
@@ -100,69 +100,12 @@ fn test_discriminator_validation() {
 }
 
 fn run_discriminator_test() -> TestResultReport {
-    let test_name = "Discriminator Validation";
-    let mut assertion_count = 0;
-
-    // From sRFC 37 specification
-    let expected_thaw = test_data::THAW_DISCRIMINATOR;
-    let expected_freeze = test_data::FREEZE_DISCRIMINATOR;
-
-    // Assertion 1: Thaw discriminator matches spec
-    assertion_count += 1;
-    let actual_thaw = test_data::THAW_DISCRIMINATOR;
-    if actual_thaw != expected_thaw {
-        return TestResultReport::failure(
-            test_name,
-            format!(
-                "Thaw discriminator mismatch: {:?} != {:?}",
-                actual_thaw, expected_thaw
-            ),
-        );
-    }
-
-    // Assertion 2: Freeze discriminator matches spec
-    assertion_count += 1;
-    let actual_freeze = test_data::FREEZE_DISCRIMINATOR;
-    if actual_freeze != expected_freeze {
-        return TestResultReport::failure(
-            test_name,
-            format!(
-                "Freeze discriminator mismatch: {:?} != {:?}",
-                actual_freeze, expected_freeze
-            ),
-        );
-    }
-
-    // Assertion 3: Discriminators are different
-    assertion_count += 1;
-    if expected_thaw == expected_freeze {
-        return TestResultReport::failure(
-            test_name,
-            "Thaw and freeze discriminators must be different".to_string(),
-        );
-    }
-
-    // Assertion 4: Discriminators are 8 bytes
-    assertion_count += 1;
-    if expected_thaw.len() != 8 || expected_freeze.len() != 8 {
-        return TestResultReport::failure(
-            test_name,
-            "Discriminators must be exactly 8 bytes".to_string(),
-        );
-    }
-
-    // Assertion 5: Discriminators are valid (not all zeros)
-    assertion_count += 1;
-    if !utils::is_valid_discriminator(&expected_thaw)
-        || !utils::is_valid_discriminator(&expected_freeze)
-    {
-        return TestResultReport::failure(
-            test_name,
-            "Discriminators should not be all zeros".to_string(),
-        );
-    }
-
-    TestResultReport::success(test_name, assertion_count)
+    // From sRFC 37 specification; the self-test checks this rejects wrong
+    // discriminators
+    self_test::check_discriminators(
+        test_data::THAW_DISCRIMINATOR,
+        test_data::FREEZE_DISCRIMINATOR,
+    )
 }
 
 /// Test 3: MintConfig Structure Validation
@@ -399,14 +342,14 @@ fn run_gating_program_validation_test() -> TestResultReport {
 /// Generate test report
 #[test]
 fn generate_test_report() {
-    let mut results = vec![];
-
     // Run all tests and collect results
-    results.push(run_pda_derivation_test());
-    results.push(run_discriminator_test());
-    results.push(run_mint_config_structure_test());
-    results.push(run_permission_flags_test());
-    results.push(run_gating_program_validation_test());
+    let results = vec![
+        run_pda_derivation_test(),
+        run_discriminator_test(),
+        run_mint_config_structure_test(),
+        run_permission_flags_test(),
+        run_gating_program_validation_test(),
+    ];
 
     // Generate report using shared reporting module
     if let Err(e) = reporting::generate_test_report(
@@ -0,0 +1,512 @@
+//! The interface crate and the suite against the published sRFC 37 table
+//!
+//! `programs/token_acl_interface/spec/srfc37.toml` is the machine-readable
+//! copy of the spec. These tests check that it is consistent with itself,
+//! that every constant the interface crate exports matches it, that the
+//! suite's own copies (the fixtures' discriminators, the decoder's names)
+//! do too, and, over random and mutated instruction data, that the decoder
+//! recognizes exactly the calls the table defines. A spec revision is then
+//! an edit of the TOML file, and these tests point at every constant that
+//! has to follow it.
+
+use std::collections::BTreeSet;
+
+use proptest::{
+    collection::vec,
+    prelude::*,
+    test_runner::{Config as ProptestConfig, TestCaseError, TestError, TestRunner},
+};
+use solana_sdk::pubkey::Pubkey;
+use spl_discriminator::SplDiscriminate;
+
+use token_acl_integration_tests::{
+    decoder::{Decoder, InstructionCategory, ProgramKind},
+    fixtures::test_data,
+    interface_spec::{hashed_discriminator, InterfaceSpec},
+    reporting, TestResultReport,
+};
+use token_acl_interface::{
+    self as interface, CanFreezePermissionless, CanThawPermissionless, GateInterfaceVersion,
+};
+
+/// Instruction data checked by the differential
+const CASES: u32 = 1024;
+
+/// The programs the decoder knows, each of which must classify interface
+/// calls the same way
+const KINDS: [ProgramKind; 10] = [
+    ProgramKind::AllowList,
+    ProgramKind::BlockList,
+    ProgramKind::ProductionAllowList,
+    ProgramKind::HybridGate,
+    ProgramKind::JurisdictionRegistry,
+    ProgramKind::ThresholdGate,
+    ProgramKind::VelocityGate,
+    ProgramKind::GeoGate,
+    ProgramKind::NftGate,
+    ProgramKind::LendingConsumer,
+];
+
+/// The interface's name for a check, as the decoder spells it
+fn decoder_name(spec_name: &str) -> String {
+    spec_name.replace('-', "_")
+}
+
+/// Test 1: The spec file is consistent with itself
+#[test]
+fn test_spec_is_consistent() {
+    let report = run_spec_consistency_test();
+    assert!(
+        report.passed,
+        "Spec consistency test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_spec_consistency_test() -> TestResultReport {
+    let test_name = "sRFC 37 Spec Consistency";
+    let mut assertions = 0;
+    let spec = InterfaceSpec::published();
+
+    // Assertion 1: Every discriminator is the hash of its preimage
+    assertions += 1;
+    let unhashed: Vec<&str> = spec
+        .instructions
+        .iter()
+        .map(|i| (i.name.as_str(), &i.preimage, i.discriminator_bytes()))
+        .chain(
+            spec.tlv_entries
+                .iter()
+                .map(|e| (e.name.as_str(), &e.preimage, e.discriminator_bytes())),
+        )
+        .filter(|(_, preimage, discriminator)| hashed_discriminator(preimage) != *discriminator)
+        .map(|(name, _, _)| name)
+        .collect();
+    if !unhashed.is_empty() {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Discriminators that are not their preimage's hash: {:?}",
+                unhashed
+            ),
+        );
+    }
+
+    // Assertion 2: No two entries share a name or a discriminator
+    assertions += 1;
+    let discriminators = spec.discriminators();
+    let names: BTreeSet<&str> = discriminators.iter().map(|(name, _)| *name).collect();
+    let bytes: BTreeSet<[u8; 8]> = discriminators.iter().map(|(_, bytes)| *bytes).collect();
+    if names.len() != discriminators.len() || bytes.len() != discriminators.len() {
+        return TestResultReport::failure(
+            test_name,
+            format!("Duplicate names or discriminators in {:?}", discriminators),
+        );
+    }
+
+    // Assertion 3: Every instruction's extra-account-metas PDA has a seed
+    assertions += 1;
+    if let Some(instruction) = spec
+        .instructions
+        .iter()
+        .find(|i| spec.seed(&i.extra_account_metas).is_none())
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "{} names the unknown seed {}",
+                instruction.name, instruction.extra_account_metas
+            ),
+        );
+    }
+
+    // Assertion 4: Feature bits fit the u32 bitmap and are distinct
+    assertions += 1;
+    let masks: Vec<Option<u32>> = spec.features.iter().map(|f| f.mask()).collect();
+    let distinct: BTreeSet<u32> = masks.iter().flatten().copied().collect();
+    if masks.contains(&None) || distinct.len() != masks.len() {
+        return TestResultReport::failure(
+            test_name,
+            format!("Feature bits overflow or repeat: {:?}", spec.features),
+        );
+    }
+
+    // Assertion 5: A malformed discriminator fails to load instead of being
+    // read as some other value
+    assertions += 1;
+    let truncated = spec_toml_with_discriminator("08afa981894a3d");
+    let not_hex = spec_toml_with_discriminator("08afa981894a3dzz");
+    if InterfaceSpec::parse(&truncated).is_ok() || InterfaceSpec::parse(&not_hex).is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "A malformed discriminator was accepted".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// The checked-in spec with the thaw discriminator replaced by `hex_bytes`
+fn spec_toml_with_discriminator(hex_bytes: &str) -> String {
+    let spec = InterfaceSpec::published();
+    let thaw = spec
+        .instruction("can-thaw-permissionless")
+        .expect("the spec defines the thaw check");
+    token_acl_integration_tests::interface_spec::SPEC_TOML.replace(
+        &format!("discriminator = \"{}\"", thaw.discriminator),
+        &format!("discriminator = \"{}\"", hex_bytes),
+    )
+}
+
+/// Test 2: Every constant the interface crate exports matches the spec
+#[test]
+fn test_interface_crate_matches_spec() {
+    let report = run_interface_crate_test();
+    assert!(
+        report.passed,
+        "Interface crate test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_interface_crate_test() -> TestResultReport {
+    let test_name = "Interface Crate Against the Spec";
+    let mut assertions = 0;
+    let spec = InterfaceSpec::published();
+    let Some(thaw) = spec.instruction("can-thaw-permissionless") else {
+        return TestResultReport::failure(test_name, "Spec has no thaw check".to_string());
+    };
+    let Some(freeze) = spec.instruction("can-freeze-permissionless") else {
+        return TestResultReport::failure(test_name, "Spec has no freeze check".to_string());
+    };
+    let Some(version) = spec.tlv_entry("gate-interface-version") else {
+        return TestResultReport::failure(test_name, "Spec has no version entry".to_string());
+    };
+
+    // Assertion 1: The instruction discriminators
+    assertions += 1;
+    if interface::CAN_THAW_PERMISSIONLESS_DISCRIMINATOR != thaw.discriminator_bytes()
+        || interface::CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR != freeze.discriminator_bytes()
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Interface discriminators {:?} and {:?}, spec {} and {}",
+                interface::CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+                interface::CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR,
+                thaw.discriminator,
+                freeze.discriminator
+            ),
+        );
+    }
+
+    // Assertion 2: The TLV entry types, the lists' included
+    assertions += 1;
+    if CanThawPermissionless::SPL_DISCRIMINATOR_SLICE != thaw.discriminator_bytes()
+        || CanFreezePermissionless::SPL_DISCRIMINATOR_SLICE != freeze.discriminator_bytes()
+        || GateInterfaceVersion::SPL_DISCRIMINATOR_SLICE != version.discriminator_bytes()
+        || GateInterfaceVersion::LEN != version.len
+    {
+        return TestResultReport::failure(
+            test_name,
+            "A TLV entry type or length differs from the spec".to_string(),
+        );
+    }
+
+    // Assertion 3: The seeds, and the PDAs derived from them
+    assertions += 1;
+    let gate = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let crate_seeds: [(&str, &[u8], (Pubkey, u8)); 3] = [
+        (
+            "thaw-extra-account-metas",
+            interface::THAW_EXTRA_ACCOUNT_METAS_SEED,
+            interface::find_thaw_extra_account_metas_address(&mint, &gate),
+        ),
+        (
+            "freeze-extra-account-metas",
+            interface::FREEZE_EXTRA_ACCOUNT_METAS_SEED,
+            interface::find_freeze_extra_account_metas_address(&mint, &gate),
+        ),
+        (
+            "missing-record-policy",
+            interface::MISSING_RECORD_POLICY_SEED,
+            interface::find_missing_record_policy_address(&mint, &gate),
+        ),
+    ];
+    for (name, seed, address) in crate_seeds {
+        let Some(spec_seed) = spec.seed(name) else {
+            return TestResultReport::failure(test_name, format!("Spec has no seed {}", name));
+        };
+        if seed != spec_seed.seed.as_bytes()
+            || spec_seed.mint_address(&mint, &gate) != Some(address)
+        {
+            return TestResultReport::failure(
+                test_name,
+                format!("Seed {} differs from the spec", name),
+            );
+        }
+    }
+
+    // Assertion 4: The instruction builders send the discriminator alone,
+    // over the spec's accounts
+    assertions += 1;
+    let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+    let built = [
+        (
+            thaw,
+            interface::can_thaw_permissionless(&gate, keys[0], keys[1], keys[2], keys[3], &[]),
+        ),
+        (
+            freeze,
+            interface::can_freeze_permissionless(&gate, keys[0], keys[1], keys[2], keys[3], &[]),
+        ),
+    ];
+    for (spec_instruction, instruction) in built {
+        if spec.classify(&instruction.data) != Some(spec_instruction)
+            || instruction.accounts.len() != spec_instruction.accounts.len()
+        {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "The {} builder sends {:?} over {} accounts",
+                    spec_instruction.name,
+                    instruction.data,
+                    instruction.accounts.len()
+                ),
+            );
+        }
+    }
+
+    // Assertion 5: The version and feature bits
+    assertions += 1;
+    let features = [
+        (
+            "missing-record-policy",
+            interface::FEATURE_MISSING_RECORD_POLICY,
+        ),
+        (
+            "versioned-extra-account-metas",
+            interface::FEATURE_VERSIONED_EXTRA_ACCOUNT_METAS,
+        ),
+    ];
+    for (name, bit) in features {
+        if spec.feature(name).and_then(|f| f.mask()) != Some(bit) {
+            return TestResultReport::failure(
+                test_name,
+                format!("Feature {} is {:#x} in the crate", name, bit),
+            );
+        }
+    }
+    let spec_features = spec
+        .features
+        .iter()
+        .filter_map(|f| f.mask())
+        .fold(0, |all, mask| all | mask);
+    if GateInterfaceVersion::CURRENT.version != spec.version
+        || GateInterfaceVersion::CURRENT.features != spec_features
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "The crate speaks {:?}, the spec version {} with features {:#x}",
+                GateInterfaceVersion::CURRENT,
+                spec.version,
+                spec_features
+            ),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: The suite's own copies of the interface match the spec
+#[test]
+fn test_suite_copies_match_spec() {
+    let report = run_suite_copies_test();
+    assert!(
+        report.passed,
+        "Suite copies test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_suite_copies_test() -> TestResultReport {
+    let test_name = "Suite Copies Against the Spec";
+    let mut assertions = 0;
+    let spec = InterfaceSpec::published();
+
+    // Assertion 1: The fixtures' discriminators and seeds
+    assertions += 1;
+    let fixture_discriminators = [
+        ("can-thaw-permissionless", test_data::THAW_DISCRIMINATOR),
+        ("can-freeze-permissionless", test_data::FREEZE_DISCRIMINATOR),
+    ];
+    for (name, discriminator) in fixture_discriminators {
+        if spec.instruction(name).map(|i| i.discriminator_bytes()) != Some(discriminator) {
+            return TestResultReport::failure(
+                test_name,
+                format!("The fixtures' {} discriminator differs", name),
+            );
+        }
+    }
+    let fixture_seeds = [
+        (
+            "thaw-extra-account-metas",
+            test_data::THAW_EXTRA_ACCOUNT_METAS_SEED,
+        ),
+        (
+            "freeze-extra-account-metas",
+            test_data::FREEZE_EXTRA_ACCOUNT_METAS_SEED,
+        ),
+    ];
+    for (name, seed) in fixture_seeds {
+        if spec.seed(name).map(|s| s.seed.as_bytes()) != Some(seed) {
+            return TestResultReport::failure(
+                test_name,
+                format!("The fixtures' {} seed differs", name),
+            );
+        }
+    }
+
+    // Assertion 2: The decoder names every check as the spec does and gives
+    // it the spec's interface accounts
+    assertions += 1;
+    let decoder = Decoder::new();
+    let program = Pubkey::new_unique();
+    for instruction in &spec.instructions {
+        let shape = decoder.shape(&program, &instruction.discriminator_bytes());
+        let roles: Vec<String> = (0..instruction.accounts.len())
+            .map(|index| shape.role(index))
+            .collect();
+        if shape.category != InstructionCategory::PermissionlessCheck
+            || shape.name != decoder_name(&instruction.name)
+            || roles != instruction.accounts
+        {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "The decoder reads {} as {} over {:?}",
+                    instruction.name, shape.name, roles
+                ),
+            );
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Instruction data near the spec's calls: a discriminator alone, with a
+/// byte flipped, truncated, or with bytes appended, and arbitrary bytes
+fn instruction_data(spec: &InterfaceSpec) -> impl Strategy<Value = Vec<u8>> {
+    let discriminators: Vec<Vec<u8>> = spec
+        .instructions
+        .iter()
+        .map(|i| i.discriminator_bytes().to_vec())
+        .collect();
+    let exact = prop::sample::select(discriminators.clone());
+    let flipped = (
+        prop::sample::select(discriminators.clone()),
+        0..8usize,
+        1..=255u8,
+    )
+        .prop_map(|(mut data, index, mask)| {
+            data[index] ^= mask;
+            data
+        });
+    let truncated = (prop::sample::select(discriminators.clone()), 0..8usize)
+        .prop_map(|(data, len)| data[..len].to_vec());
+    let extended = (
+        prop::sample::select(discriminators),
+        vec(any::<u8>(), 1..16),
+    )
+        .prop_map(|(mut data, tail)| {
+            data.extend(tail);
+            data
+        });
+    prop_oneof![exact, flipped, truncated, extended, vec(any::<u8>(), 0..24)]
+}
+
+/// Test 4: Over random and mutated instruction data, the decoder recognizes
+/// exactly the calls the spec defines, whichever program it is sent to
+#[test]
+fn test_decoder_differential() {
+    let report = run_decoder_differential_test();
+    assert!(
+        report.passed,
+        "Decoder differential test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_decoder_differential_test() -> TestResultReport {
+    let test_name = "Decoder Differential Against the Spec";
+    let mut assertions = 0;
+    let spec = InterfaceSpec::published();
+    let mut decoder = Decoder::new();
+    let programs: Vec<Pubkey> = KINDS
+        .iter()
+        .map(|kind| {
+            let program = Pubkey::new_unique();
+            decoder.register(program, *kind);
+            program
+        })
+        .chain([Pubkey::new_unique()])
+        .collect();
+
+    // Assertion 1: The decoder classifies every input as the spec does
+    assertions += 1;
+    let mut runner = TestRunner::new(ProptestConfig {
+        cases: CASES,
+        failure_persistence: None,
+        ..ProptestConfig::default()
+    });
+    let result = runner.run(&instruction_data(&spec), |data| {
+        let expected = spec.classify(&data).map(|i| decoder_name(&i.name));
+        for program in &programs {
+            let shape = decoder.shape(program, &data);
+            let decoded =
+                (shape.category == InstructionCategory::PermissionlessCheck).then_some(shape.name);
+            if decoded != expected {
+                return Err(TestCaseError::fail(format!(
+                    "decoded as {:?} for {:?}, the spec says {:?}",
+                    decoded,
+                    decoder.kind(program),
+                    expected
+                )));
+            }
+        }
+        Ok(())
+    });
+    if let Err(e) = result {
+        let reason = match e {
+            TestError::Fail(reason, data) => format!("{} for {:?}", reason, data),
+            TestError::Abort(reason) => reason.to_string(),
+        };
+        return TestResultReport::failure(test_name, reason);
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate the interface spec report
+#[test]
+fn generate_interface_spec_report() {
+    let results = vec![
+        run_spec_consistency_test(),
+        run_interface_crate_test(),
+        run_suite_copies_test(),
+        run_decoder_differential_test(),
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "sRFC 37 Interface Spec Test Results",
+        "../../tests/reports/interface_spec.md",
+    ) {
+        panic!("Failed to generate interface spec report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} interface spec tests failed", failed);
+}
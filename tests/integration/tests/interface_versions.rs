@@ -0,0 +1,514 @@
+//! Gate interface version negotiation tests
+//!
+//! A mock FAMP declares the interface version and features it was built
+//! against and checks the gate's advertised `GateInterfaceVersion` before
+//! every CPI. These tests pair it with the block list example and with a
+//! gate advertising a newer version, in every direction: mismatches must
+//! fail with an explicit `GateVersionError` before the gate sees any
+//! accounts, never with the gate deciding on accounts it misread.
+
+use borsh::BorshSerialize;
+use example_block_list::{
+    block_list_address, create_block_list_record, initialize_freeze_extra_account_metas,
+    BlockReason,
+};
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    msg,
+    program::invoke,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    signature::Signer,
+    transaction::{Transaction, TransactionError},
+};
+use spl_tlv_account_resolution::state::{AccountFetchError, ExtraAccountMetaList};
+use spl_token_2022::state::AccountState;
+use spl_type_length_value::state::{TlvState, TlvStateBorrowed, TlvStateMut};
+
+use token_acl_integration_tests::{
+    program_test::{mint_account, rent_exempt_account, token_account},
+    reporting, TestResultReport,
+};
+use token_acl_interface::{
+    self as interface, CanThawPermissionless, GateInterfaceVersion, GateVersionError,
+};
+
+/// Mock FAMP modes, selected by the first instruction byte
+const FAMP_THAW: u8 = 0;
+const FAMP_FREEZE: u8 = 1;
+
+/// Error the newer gate returns if it is ever reached
+const REACHED_NEWER_GATE: u32 = 0x2EAC;
+
+/// A version 2 gate's requirements; version 1 callers lay out its extra
+/// accounts differently
+const NEWER: GateInterfaceVersion = GateInterfaceVersion {
+    version: 2,
+    features: 0,
+};
+
+/// Mock FAMP built against the version in its instruction data
+///
+/// Data: mode, then the caller's `GateInterfaceVersion` as packed. Accounts:
+/// the gate program, then the interface accounts and extra accounts.
+fn famp_process(_program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [gate, caller, token_account, mint, extra_metas, extras @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    let [mode, version @ ..] = data else {
+        return Err(ProgramError::InvalidInstructionData);
+    };
+    let built_against = GateInterfaceVersion::unpack(version)?;
+    interface::check_gate_interface_version(&built_against, gate, extra_metas)?;
+
+    let extras: Vec<_> = extras.iter().map(|account| (*account.key).into()).collect();
+    let instruction = match *mode {
+        FAMP_THAW => interface::can_thaw_permissionless(
+            gate.key,
+            caller.key,
+            token_account.key,
+            mint.key,
+            extra_metas.key,
+            &extras,
+        ),
+        FAMP_FREEZE => interface::can_freeze_permissionless(
+            gate.key,
+            caller.key,
+            token_account.key,
+            mint.key,
+            extra_metas.key,
+            &extras,
+        ),
+        _ => return Err(ProgramError::InvalidInstructionData),
+    };
+    invoke(&instruction, accounts)
+}
+
+/// Newer gate: whatever it is passed, a version 1 caller's accounts would
+/// be misread, so reaching it at all is a failure
+fn newer_gate_process(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    msg!("Newer gate reached with {} accounts", accounts.len());
+    Err(ProgramError::Custom(REACHED_NEWER_GATE))
+}
+
+/// Thaw extra-account-metas data advertising `version`, with no extra
+/// accounts
+fn versioned_thaw_extra_account_metas(version: GateInterfaceVersion) -> Vec<u8> {
+    let entry = TlvStateBorrowed::get_base_len() + GateInterfaceVersion::LEN;
+    let mut data = vec![0; entry + ExtraAccountMetaList::size_of(0).unwrap()];
+    {
+        let mut state = TlvStateMut::unpack(&mut data).unwrap();
+        let (value, _) = state
+            .alloc::<GateInterfaceVersion>(GateInterfaceVersion::LEN, false)
+            .unwrap();
+        value.copy_from_slice(&version.pack());
+    }
+    ExtraAccountMetaList::init::<CanThawPermissionless>(&mut data, &[]).unwrap();
+    data
+}
+
+/// A bank with the mock FAMP, the block list (with its freeze list created)
+/// and the newer gate, and one token account whose owner is blocked
+struct VersionFixture {
+    context: ProgramTestContext,
+    famp: Pubkey,
+    block_list: Pubkey,
+    newer_gate: Pubkey,
+    mint: Pubkey,
+    owner: Pubkey,
+    token_account: Pubkey,
+}
+
+impl VersionFixture {
+    async fn new() -> Self {
+        let famp = Pubkey::new_unique();
+        let block_list = Pubkey::new_unique();
+        let newer_gate = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let holder_account = Pubkey::new_unique();
+
+        let mut program_test = ProgramTest::new("mock_famp", famp, processor!(famp_process));
+        program_test.add_program(
+            "example_block_list",
+            block_list,
+            processor!(example_block_list::process_instruction),
+        );
+        program_test.add_program("newer_gate", newer_gate, processor!(newer_gate_process));
+
+        // No one in the test holds the mint's authorities
+        program_test.add_account(mint, mint_account(&Pubkey::new_unique(), 1_000));
+
+        program_test.add_account(
+            holder_account,
+            token_account(&mint, &owner, 1_000, AccountState::Initialized),
+        );
+
+        let record = create_block_list_record(&mint, &owner, BlockReason::Sanctions, 0);
+        program_test.add_account(
+            block_list_address(&mint, &owner, &block_list).address(),
+            rent_exempt_account(record.try_to_vec().unwrap(), block_list),
+        );
+
+        let (newer_metas, _) = interface::find_thaw_extra_account_metas_address(&mint, &newer_gate);
+        program_test.add_account(
+            newer_metas,
+            rent_exempt_account(versioned_thaw_extra_account_metas(NEWER), newer_gate),
+        );
+
+        let mut fixture = Self {
+            context: program_test.start_with_context().await,
+            famp,
+            block_list,
+            newer_gate,
+            mint,
+            owner,
+            token_account: holder_account,
+        };
+        let payer = fixture.context.payer.pubkey();
+        let init = initialize_freeze_extra_account_metas(&block_list, &payer, &mint);
+        fixture.send(init).await.expect("setup should succeed");
+        fixture
+    }
+
+    fn block_list_record(&self) -> Pubkey {
+        block_list_address(&self.mint, &self.owner, &self.block_list).address()
+    }
+
+    /// Mock FAMP call from a FAMP built against `built_against`
+    fn famp_instruction(
+        &self,
+        mode: u8,
+        gate: Pubkey,
+        built_against: GateInterfaceVersion,
+        extras: &[Pubkey],
+    ) -> Instruction {
+        let (extra_metas, _) = if mode == FAMP_FREEZE {
+            interface::find_freeze_extra_account_metas_address(&self.mint, &gate)
+        } else {
+            interface::find_thaw_extra_account_metas_address(&self.mint, &gate)
+        };
+        let mut accounts = vec![
+            AccountMeta::new_readonly(gate, false),
+            AccountMeta::new_readonly(Pubkey::new_unique(), false),
+            AccountMeta::new_readonly(self.token_account, false),
+            AccountMeta::new_readonly(self.mint, false),
+            AccountMeta::new_readonly(extra_metas, false),
+        ];
+        accounts.extend(
+            extras
+                .iter()
+                .map(|key| AccountMeta::new_readonly(*key, false)),
+        );
+        let mut data = vec![mode];
+        data.extend_from_slice(&built_against.pack());
+        Instruction::new_with_bytes(self.famp, &data, accounts)
+    }
+
+    /// Freeze of the blocked owner's account through the mock FAMP
+    fn block_list_freeze(&self, built_against: GateInterfaceVersion) -> Instruction {
+        self.famp_instruction(
+            FAMP_FREEZE,
+            self.block_list,
+            built_against,
+            &[self.block_list_record()],
+        )
+    }
+
+    /// Thaw through the mock FAMP on the newer gate, with a version 1 layout
+    fn newer_gate_thaw(&self, built_against: GateInterfaceVersion) -> Instruction {
+        self.famp_instruction(
+            FAMP_THAW,
+            self.newer_gate,
+            built_against,
+            &[self.owner, Pubkey::new_unique()],
+        )
+    }
+
+    async fn send(&mut self, instruction: Instruction) -> Result<(), TransactionError> {
+        // A fresh blockhash keeps repeated transactions from being deduplicated
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&self.context.payer.pubkey()),
+            &[&self.context.payer],
+            blockhash,
+        );
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .map_err(|e| e.unwrap())
+    }
+
+    async fn resolve(&mut self, gate: Pubkey, thaw: bool) -> Result<Instruction, ProgramError> {
+        let mut instruction = if thaw {
+            let (metas, _) = interface::find_thaw_extra_account_metas_address(&self.mint, &gate);
+            interface::can_thaw_permissionless(
+                &gate,
+                Pubkey::new_unique(),
+                self.token_account,
+                self.mint,
+                metas,
+                &[],
+            )
+        } else {
+            let (metas, _) = interface::find_freeze_extra_account_metas_address(&self.mint, &gate);
+            interface::can_freeze_permissionless(
+                &gate,
+                Pubkey::new_unique(),
+                self.token_account,
+                self.mint,
+                metas,
+                &[],
+            )
+        };
+        let banks_client = self.context.banks_client.clone();
+        interface::resolve_extra_account_metas(&mut instruction, |address| {
+            let mut banks_client = banks_client.clone();
+            async move {
+                banks_client
+                    .get_account(address)
+                    .await
+                    .map(|account| account.map(|a| a.data))
+                    .map_err(|e| Box::new(e) as AccountFetchError)
+            }
+        })
+        .await?;
+        Ok(instruction)
+    }
+}
+
+/// Whether `result` is the FAMP refusing with `error`
+fn refused_with(result: &Result<(), TransactionError>, error: GateVersionError) -> bool {
+    *result
+        == Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(error as u32),
+        ))
+}
+
+/// Test 1: Matching versions call through, an older FAMP is refused by
+/// feature
+#[tokio::test]
+async fn test_matching_and_older_famp() {
+    let report = run_matching_and_older_famp_test().await;
+    assert!(
+        report.passed,
+        "Matching and older FAMP test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_matching_and_older_famp_test() -> TestResultReport {
+    let test_name = "Interface Version Matching and Older FAMP";
+    let mut assertions = 0;
+    let mut fixture = VersionFixture::new().await;
+
+    // Assertion 1: The block list advertises its version in its freeze list
+    assertions += 1;
+    let (freeze_metas, _) =
+        interface::find_freeze_extra_account_metas_address(&fixture.mint, &fixture.block_list);
+    let list = fixture
+        .context
+        .banks_client
+        .get_account(freeze_metas)
+        .await
+        .unwrap()
+        .expect("freeze list should exist");
+    match GateInterfaceVersion::from_extra_account_metas(&list.data) {
+        Ok(version) if version == example_block_list::GATE_INTERFACE_VERSION => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Block list advertised {:?}", other),
+            );
+        }
+    }
+
+    // Assertion 2: A FAMP built against the current version calls through,
+    // and the gate authorizes the freeze
+    assertions += 1;
+    let freeze = fixture.block_list_freeze(GateInterfaceVersion::CURRENT);
+    if let Err(e) = fixture.send(freeze).await {
+        return TestResultReport::failure(test_name, format!("Current FAMP failed: {}", e));
+    }
+
+    // Assertion 3: A legacy FAMP, which knows neither the versioned list nor
+    // the policy account, is refused before the CPI
+    assertions += 1;
+    let freeze = fixture.block_list_freeze(GateInterfaceVersion::LEGACY);
+    let result = fixture.send(freeze).await;
+    if !refused_with(&result, GateVersionError::UnsupportedFeatures) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Legacy FAMP was not refused by feature: {:?}", result),
+        );
+    }
+
+    // Assertion 4: A gate without any extra-account-metas speaks the legacy
+    // version, so the current FAMP still calls it
+    assertions += 1;
+    let legacy_gate = Pubkey::new_unique();
+    let metas = Pubkey::new_unique();
+    let mut lamports = 0;
+    let mut data = [];
+    let owner = solana_sdk::system_program::id();
+    let account = AccountInfo::new(
+        &metas,
+        false,
+        false,
+        &mut lamports,
+        &mut data,
+        &owner,
+        false,
+        0,
+    );
+    if GateInterfaceVersion::read(&legacy_gate, &account) != Ok(GateInterfaceVersion::LEGACY) {
+        return TestResultReport::failure(
+            test_name,
+            "Missing extra-account-metas were not read as legacy".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: An older FAMP calling a newer gate, and a newer FAMP calling an
+/// older gate, both fail with version errors
+#[tokio::test]
+async fn test_version_mismatches() {
+    let report = run_version_mismatches_test().await;
+    assert!(
+        report.passed,
+        "Version mismatches test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_version_mismatches_test() -> TestResultReport {
+    let test_name = "Interface Version Mismatches";
+    let mut assertions = 0;
+    let mut fixture = VersionFixture::new().await;
+
+    // Assertions 1-2: Current and legacy FAMPs are refused by the newer gate
+    // before it ever sees their version 1 account layout
+    for built_against in [GateInterfaceVersion::CURRENT, GateInterfaceVersion::LEGACY] {
+        assertions += 1;
+        let thaw = fixture.newer_gate_thaw(built_against);
+        let result = fixture.send(thaw).await;
+        if !refused_with(&result, GateVersionError::GateTooNew) {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "Version {} FAMP reached the newer gate: {:?}",
+                    built_against.version, result
+                ),
+            );
+        }
+    }
+
+    // Assertion 3: A FAMP built against the newer version is refused by the
+    // version 1 block list
+    assertions += 1;
+    let freeze = fixture.block_list_freeze(NEWER);
+    let result = fixture.send(freeze).await;
+    if !refused_with(&result, GateVersionError::GateTooOld) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Newer FAMP called the older gate: {:?}", result),
+        );
+    }
+
+    // Assertion 4: A FAMP matching the newer gate gets through the check, so
+    // the refusals above came from negotiation and not the gate
+    assertions += 1;
+    let thaw = fixture.newer_gate_thaw(NEWER);
+    let result = fixture.send(thaw).await;
+    if result
+        != Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(REACHED_NEWER_GATE),
+        ))
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!("Matching FAMP did not reach the newer gate: {:?}", result),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: Off-chain resolution checks the version before resolving
+#[tokio::test]
+async fn test_client_resolution_checks_version() {
+    let report = run_client_resolution_test().await;
+    assert!(
+        report.passed,
+        "Client resolution test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_client_resolution_test() -> TestResultReport {
+    let test_name = "Interface Version Client Resolution";
+    let mut assertions = 0;
+    let mut fixture = VersionFixture::new().await;
+
+    // Assertion 1: The block list's freeze resolves to the owner's record
+    assertions += 1;
+    let block_list = fixture.block_list;
+    match fixture.resolve(block_list, false).await {
+        Ok(instruction)
+            if instruction.accounts.get(4).map(|meta| meta.pubkey)
+                == Some(fixture.block_list_record()) => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Block list freeze did not resolve: {:?}", other),
+            );
+        }
+    }
+
+    // Assertion 2: The newer gate's list is refused instead of resolved
+    assertions += 1;
+    let newer_gate = fixture.newer_gate;
+    let result = fixture.resolve(newer_gate, true).await;
+    if result.as_ref().err() != Some(&GateVersionError::GateTooNew.into()) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Newer gate's list was resolved: {:?}", result),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate interface version test report
+#[tokio::test]
+async fn generate_interface_versions_report() {
+    let results = vec![
+        run_matching_and_older_famp_test().await,
+        run_version_mismatches_test().await,
+        run_client_resolution_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Interface Version Negotiation Test Results",
+        "../../tests/reports/interface_versions.md",
+    ) {
+        panic!("Failed to generate interface version report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} interface version tests failed", failed);
+}
@@ -0,0 +1,508 @@
+//! Invariant engine tests
+//!
+//! The scenario fixtures check every step against an `InvariantEngine`; these
+//! tests make sure each registered invariant fires on the step that breaks
+//! it and stays quiet otherwise. Hand-built steps cover the rules, and a
+//! gate deployed into `solana-program-test` shows the engine catching a thaw
+//! whose gate check vouched for a different owner.
+
+use borsh::BorshSerialize;
+use production_allow_list::{
+    AccessLevel, AllowListRecord, Config, ACCEPT_AUTHORITY, ADD_TO_ALLOW_LIST, UPDATE_AUTHORITY,
+};
+use solana_program_test::{processor, ProgramTest};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use spl_token_2022::{instruction as token_instruction, state::AccountState as TokenAccountState};
+use token_acl_interface::find_missing_record_policy_address;
+
+use token_acl_integration_tests::{
+    account_snapshots::{AccountSnapshot, AccountState},
+    fixtures::test_data,
+    invariants::{
+        ConfigAuthorityUnchanged, FreezeStateConsistency, Invariant, InvariantEngine,
+        LamportInvariants, Step,
+    },
+    program_test::{mint_account, rent_exempt_account, token_account},
+    reporting, TestResultReport,
+};
+
+fn allow_list_record_data(mint: &Pubkey, user: &Pubkey, allowed: bool) -> Vec<u8> {
+    AllowListRecord {
+        mint: *mint,
+        user: *user,
+        allowed,
+        access_level: AccessLevel::Basic,
+        added_timestamp: 0,
+        expiry_timestamp: None,
+        bump: 0,
+        revoked: false,
+    }
+    .try_to_vec()
+    .unwrap()
+}
+
+fn config_data(authority: &Pubkey, mint: &Pubkey) -> Vec<u8> {
+    Config {
+        authority: *authority,
+        mint: *mint,
+        bump: 0,
+    }
+    .try_to_vec()
+    .unwrap()
+}
+
+fn state(data: Vec<u8>, owner: Pubkey) -> Option<AccountState> {
+    Some(AccountState {
+        lamports: Rent::default().minimum_balance(data.len()),
+        owner,
+        data,
+    })
+}
+
+fn snapshot(accounts: &[(Pubkey, Option<AccountState>)]) -> AccountSnapshot {
+    let mut snapshot = AccountSnapshot::new();
+    for (address, state) in accounts {
+        snapshot.insert(*address, state.clone());
+    }
+    snapshot
+}
+
+/// The invariant names `invariant` reports for the step
+fn violated(
+    invariant: &dyn Invariant,
+    instructions: Vec<Instruction>,
+    before: &[(Pubkey, Option<AccountState>)],
+    after: &[(Pubkey, Option<AccountState>)],
+) -> Vec<&'static str> {
+    let step = Step::new("case", instructions, snapshot(before), snapshot(after));
+    let mut names: Vec<&'static str> = invariant
+        .check(&step)
+        .into_iter()
+        .map(|violation| violation.invariant)
+        .collect();
+    names.dedup();
+    names
+}
+
+/// Test 1: The config authority only changes alongside AcceptAuthority
+#[test]
+fn test_config_authority_invariant() {
+    let report = run_config_authority_test();
+    assert!(
+        report.passed,
+        "Config authority invariant test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_config_authority_test() -> TestResultReport {
+    let test_name = "Config Authority Invariant";
+    let mut assertions = 0;
+
+    let program = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let invariant = ConfigAuthorityUnchanged::allow_list(&mint, &program);
+    let config = production_allow_list::config_address(&mint, &program).address();
+    let (authority, attacker) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    let original = config_data(&authority, &mint);
+    let replaced = config_data(&attacker, &mint);
+    let mut rebumped = original.clone();
+    *rebumped.last_mut().unwrap() = 1;
+    let add = Instruction::new_with_bytes(program, &[ADD_TO_ALLOW_LIST], vec![]);
+    let propose = Instruction::new_with_bytes(program, &[UPDATE_AUTHORITY], vec![]);
+    let accept = Instruction::new_with_bytes(program, &[ACCEPT_AUTHORITY], vec![]);
+    let foreign_accept =
+        Instruction::new_with_bytes(Pubkey::new_unique(), &[ACCEPT_AUTHORITY], vec![]);
+
+    let cases: [(&str, Instruction, Vec<u8>, Vec<&str>); 5] = [
+        (
+            "Authority replaced by an add",
+            add.clone(),
+            replaced.clone(),
+            vec!["config-authority"],
+        ),
+        (
+            "Authority replaced by another program's accept",
+            foreign_accept,
+            replaced.clone(),
+            vec!["config-authority"],
+        ),
+        (
+            "Authority replaced by a mere proposal",
+            propose,
+            replaced.clone(),
+            vec!["config-authority"],
+        ),
+        (
+            "Authority replaced by AcceptAuthority",
+            accept,
+            replaced,
+            vec![],
+        ),
+        ("Other config fields change", add, rebumped, vec![]),
+    ];
+
+    // Assertions 1-5: Each case reports exactly what it breaks
+    for (case, instruction, after, expected) in cases {
+        assertions += 1;
+        let names = violated(
+            &invariant,
+            vec![instruction],
+            &[(config, state(original.clone(), program))],
+            &[(config, state(after, program))],
+        );
+        if names != expected {
+            return TestResultReport::failure(
+                test_name,
+                format!("{}: expected {:?}, got {:?}", case, expected, names),
+            );
+        }
+    }
+
+    // Assertion 6: The config is watched even when a step does not reference it
+    assertions += 1;
+    if invariant.watched_accounts(&AccountSnapshot::new()) != vec![config] {
+        return TestResultReport::failure(test_name, "Config is not watched".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: Freeze-state changes through the gate agree with the records
+#[test]
+fn test_freeze_state_invariant() {
+    let report = run_freeze_state_test();
+    assert!(
+        report.passed,
+        "Freeze state invariant test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_freeze_state_test() -> TestResultReport {
+    let test_name = "Freeze State Invariant";
+    let mut assertions = 0;
+
+    let gate = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let account = Pubkey::new_unique();
+    let token = |frozen: bool| {
+        let token_state = if frozen {
+            TokenAccountState::Frozen
+        } else {
+            TokenAccountState::Initialized
+        };
+        state(
+            token_account(&mint, &owner, 1_000, token_state).data,
+            spl_token_2022::id(),
+        )
+    };
+    let gate_check = Instruction::new_with_bytes(gate, &test_data::THAW_DISCRIMINATOR, vec![]);
+    let issuer_only = Instruction::new_with_bytes(spl_token_2022::id(), &[], vec![]);
+
+    let allow_list = FreezeStateConsistency::allow_list(mint, gate);
+    let allow_record = production_allow_list::allow_list_address(&mint, &owner, &gate).address();
+    let allowed = state(allow_list_record_data(&mint, &owner, true), gate);
+    let removed = state(allow_list_record_data(&mint, &owner, false), gate);
+
+    let allow_cases: [(&str, &Instruction, Option<AccountState>, Vec<&str>); 4] = [
+        (
+            "Thawed without a record",
+            &gate_check,
+            None,
+            vec!["freeze-state"],
+        ),
+        (
+            "Thawed with a removed record",
+            &gate_check,
+            removed,
+            vec!["freeze-state"],
+        ),
+        (
+            "Thawed with an allowed record",
+            &gate_check,
+            allowed,
+            vec![],
+        ),
+        (
+            "Permissioned thaw by the issuer",
+            &issuer_only,
+            None,
+            vec![],
+        ),
+    ];
+
+    // Assertions 1-4: Allow list thaws need an allowed record
+    for (case, instruction, record, expected) in allow_cases {
+        assertions += 1;
+        let names = violated(
+            &allow_list,
+            vec![instruction.clone()],
+            &[(account, token(true)), (allow_record, record.clone())],
+            &[(account, token(false)), (allow_record, record)],
+        );
+        if names != expected {
+            return TestResultReport::failure(
+                test_name,
+                format!("{}: expected {:?}, got {:?}", case, expected, names),
+            );
+        }
+    }
+
+    let block_list = FreezeStateConsistency::block_list(mint, gate);
+    let block_record = example_block_list::block_list_address(&mint, &owner, &gate).address();
+    let blocked = state(
+        example_block_list::create_block_list_record(
+            &mint,
+            &owner,
+            example_block_list::BlockReason::Sanctions,
+            0,
+        )
+        .try_to_vec()
+        .unwrap(),
+        gate,
+    );
+
+    let block_cases: [(&str, bool, Option<AccountState>, Vec<&str>); 4] = [
+        (
+            "Frozen without a blocking record",
+            true,
+            None,
+            vec!["freeze-state"],
+        ),
+        ("Frozen while blocked", true, blocked.clone(), vec![]),
+        ("Thawed while blocked", false, blocked, vec!["freeze-state"]),
+        ("Thawed without a blocking record", false, None, vec![]),
+    ];
+
+    // Assertions 5-8: Block list freezes need a blocking record, thaws need
+    // none
+    for (case, freezes, record, expected) in block_cases {
+        assertions += 1;
+        let names = violated(
+            &block_list,
+            vec![gate_check.clone()],
+            &[
+                (account, token(!freezes)),
+                (block_record, record.clone()),
+            ],
+            &[(account, token(freezes)), (block_record, record)],
+        );
+        if names != expected {
+            return TestResultReport::failure(
+                test_name,
+                format!("{}: expected {:?}, got {:?}", case, expected, names),
+            );
+        }
+    }
+
+    // Assertion 9: The owner's record is watched for a referenced token account
+    assertions += 1;
+    let referenced = snapshot(&[(account, token(true))]);
+    if allow_list.watched_accounts(&referenced) != vec![allow_record] {
+        return TestResultReport::failure(test_name, "Owner record is not watched".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: A thaw vouched for by another owner's record is caught on-chain
+#[tokio::test]
+async fn test_engine_catches_unbound_thaw() {
+    let report = run_unbound_thaw_test().await;
+    assert!(
+        report.passed,
+        "Unbound thaw test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_unbound_thaw_test() -> TestResultReport {
+    let test_name = "Engine Catches Unbound Thaw";
+    let mut assertions = 0;
+
+    let gate = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let freeze_authority = Keypair::new();
+    let (allowed_owner, other_owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (allowed_account, other_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let rent = Rent::default();
+
+    let mut program_test = ProgramTest::new(
+        "production_allow_list",
+        gate,
+        processor!(production_allow_list::process_instruction),
+    );
+    program_test.add_account(mint, mint_account(&freeze_authority.pubkey(), 2_000));
+    for (address, owner) in [
+        (allowed_account, allowed_owner),
+        (other_account, other_owner),
+    ] {
+        program_test.add_account(
+            address,
+            token_account(&mint, &owner, 1_000, TokenAccountState::Frozen),
+        );
+    }
+    let allowed_record =
+        production_allow_list::allow_list_address(&mint, &allowed_owner, &gate).address();
+    program_test.add_account(
+        allowed_record,
+        rent_exempt_account(allow_list_record_data(&mint, &allowed_owner, true), gate),
+    );
+    let mut context = program_test.start_with_context().await;
+
+    let mut engine = InvariantEngine::new();
+    engine
+        .register(LamportInvariants::new(&[gate], rent))
+        .register(FreezeStateConsistency::allow_list(mint, gate));
+
+    // The gate check vouches for the allowed owner; the thaw that follows is
+    // for whichever account the caller chose
+    let gate_check = |token_account: &Pubkey| {
+        Instruction::new_with_bytes(
+            gate,
+            &test_data::THAW_DISCRIMINATOR,
+            vec![
+                AccountMeta::new_readonly(allowed_owner, false),
+                AccountMeta::new_readonly(*token_account, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(Pubkey::new_unique(), false),
+                AccountMeta::new_readonly(allowed_owner, false),
+                AccountMeta::new_readonly(allowed_record, false),
+                AccountMeta::new_readonly(
+                    find_missing_record_policy_address(&mint, &gate).0,
+                    false,
+                ),
+            ],
+        )
+    };
+    let thaw = |token_account: &Pubkey| {
+        token_instruction::thaw_account(
+            &spl_token_2022::id(),
+            token_account,
+            &mint,
+            &freeze_authority.pubkey(),
+            &[],
+        )
+        .unwrap()
+    };
+
+    let mut steps = Vec::new();
+    for (name, instructions) in [
+        (
+            "bound thaw",
+            vec![gate_check(&allowed_account), thaw(&allowed_account)],
+        ),
+        (
+            "unbound thaw",
+            vec![gate_check(&allowed_account), thaw(&other_account)],
+        ),
+    ] {
+        let blockhash = context.get_new_latest_blockhash().await.unwrap();
+        let transaction = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&context.payer.pubkey()),
+            &[&context.payer, &freeze_authority],
+            blockhash,
+        );
+        let before = match engine
+            .snapshot(&mut context.banks_client, &transaction.message.account_keys)
+            .await
+        {
+            Ok(snapshot) => snapshot,
+            Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+        };
+        if let Err(e) = context
+            .banks_client
+            .process_transaction(transaction.clone())
+            .await
+        {
+            return TestResultReport::failure(test_name, format!("{} failed: {}", name, e));
+        }
+        let after = match engine
+            .snapshot_after(&mut context.banks_client, &before)
+            .await
+        {
+            Ok(snapshot) => snapshot,
+            Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+        };
+        steps.push(Step::from_transactions(
+            name,
+            &[&transaction],
+            before,
+            after,
+        ));
+    }
+
+    // Assertion 1: A thaw bound to the checked owner holds every invariant
+    assertions += 1;
+    let violations = engine.check(&steps[0]);
+    if !violations.is_empty() {
+        return TestResultReport::failure(
+            test_name,
+            format!("Bound thaw reported {:?}", violations),
+        );
+    }
+
+    // Assertion 2: The engine watched the other owner's record, which no
+    // instruction referenced
+    assertions += 1;
+    let other_record =
+        production_allow_list::allow_list_address(&mint, &other_owner, &gate).address();
+    if !steps[1]
+        .before
+        .addresses()
+        .any(|address| *address == other_record)
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Other owner's record was not watched".to_string(),
+        );
+    }
+
+    // Assertion 3: The unbound thaw breaks freeze-state consistency for the
+    // other owner's account only
+    assertions += 1;
+    let violations = engine.check(&steps[1]);
+    let flagged: Vec<(&str, Pubkey)> = violations
+        .iter()
+        .map(|violation| (violation.invariant, violation.address))
+        .collect();
+    if flagged != [("freeze-state", other_account)] {
+        return TestResultReport::failure(
+            test_name,
+            format!("Expected a freeze-state violation, got {:?}", violations),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate invariant engine test report
+#[tokio::test]
+async fn generate_invariant_engine_report() {
+    let results = vec![
+        run_config_authority_test(),
+        run_freeze_state_test(),
+        run_unbound_thaw_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Invariant Engine Test Results",
+        "../../tests/reports/invariant_engine.md",
+    ) {
+        panic!("Failed to generate invariant engine report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} invariant engine tests failed", failed);
+}
@@ -0,0 +1,761 @@
+//! Jurisdiction registry tests
+//!
+//! These tests run the jurisdiction registry with the hybrid gate, the
+//! production allow list and the block list example in `solana-program-test`.
+//! Two mints share one registry, and every holder is allow-listed on both.
+//! The registry instructions must only be usable by its authority, and a
+//! single `SetJurisdiction` must change every gate decision for every holder
+//! in that jurisdiction on every mint reading the registry.
+
+use jurisdiction_registry::{
+    holder_address, registry_address, JurisdictionCode, JurisdictionRegistry, JurisdictionStatus,
+    INITIALIZE_REGISTRY, SET_HOLDER_JURISDICTION, SET_JURISDICTION,
+};
+use production_allow_list::{allow_list_address, ADD_TO_ALLOW_LIST, INITIALIZE};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::Transaction,
+};
+use spl_token_2022::{
+    instruction as token_instruction,
+    state::{Account as TokenAccount, AccountState},
+};
+
+use token_acl_integration_tests::{
+    program_test::{mint_account, token_account},
+    reporting, TestResultReport,
+};
+use token_acl_interface as interface;
+
+const MINTS: usize = 2;
+
+/// Holder 0 is attested in the US, holder 1 in France, holder 2 nowhere
+const HOLDER_JURISDICTIONS: [Option<JurisdictionCode>; 3] = [Some(*b"US"), Some(*b"FR"), None];
+
+/// A bank with the four programs, a registry shared by two mints, and a
+/// frozen, allow-listed token account per holder and mint
+struct RegistryFixture {
+    context: ProgramTestContext,
+    registry_program: Pubkey,
+    gate: Pubkey,
+    allow_list: Pubkey,
+    block_list: Pubkey,
+    issuer: Keypair,
+    compliance: Keypair,
+    mints: Vec<Pubkey>,
+    /// (owner, token account per mint)
+    holders: Vec<(Pubkey, Vec<Pubkey>)>,
+}
+
+impl RegistryFixture {
+    async fn new() -> Self {
+        let registry_program = Pubkey::new_unique();
+        let gate = Pubkey::new_unique();
+        let allow_list = Pubkey::new_unique();
+        let block_list = Pubkey::new_unique();
+        let issuer = Keypair::new();
+
+        let mut program_test = ProgramTest::new(
+            "jurisdiction_registry",
+            registry_program,
+            processor!(jurisdiction_registry::process_instruction),
+        );
+        program_test.add_program(
+            "hybrid_gate",
+            gate,
+            processor!(hybrid_gate::process_instruction),
+        );
+        program_test.add_program(
+            "production_allow_list",
+            allow_list,
+            processor!(production_allow_list::process_instruction),
+        );
+        program_test.add_program(
+            "example_block_list",
+            block_list,
+            processor!(example_block_list::process_instruction),
+        );
+
+        let mints: Vec<Pubkey> = (0..MINTS).map(|_| Pubkey::new_unique()).collect();
+        for mint in &mints {
+            program_test.add_account(*mint, mint_account(&issuer.pubkey(), 1_000));
+        }
+
+        let mut holders = Vec::new();
+        for _ in HOLDER_JURISDICTIONS {
+            let owner = Pubkey::new_unique();
+            let mut token_accounts = Vec::new();
+            for mint in &mints {
+                let address = Pubkey::new_unique();
+                program_test.add_account(
+                    address,
+                    token_account(mint, &owner, 100, AccountState::Frozen),
+                );
+                token_accounts.push(address);
+            }
+            holders.push((owner, token_accounts));
+        }
+
+        let mut fixture = Self {
+            context: program_test.start_with_context().await,
+            registry_program,
+            gate,
+            allow_list,
+            block_list,
+            issuer,
+            compliance: Keypair::new(),
+            mints,
+            holders,
+        };
+
+        let compliance = fixture.compliance.insecure_clone();
+        let initialize = Instruction::new_with_bytes(
+            registry_program,
+            &[INITIALIZE_REGISTRY],
+            vec![
+                AccountMeta::new(fixture.registry(), false),
+                AccountMeta::new_readonly(compliance.pubkey(), true),
+                AccountMeta::new(fixture.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        let mut setup = vec![
+            initialize,
+            fixture.set_jurisdiction_instruction(*b"US", 0, &compliance.pubkey()),
+            fixture.set_jurisdiction_instruction(*b"FR", 0, &compliance.pubkey()),
+        ];
+        for (holder, code) in HOLDER_JURISDICTIONS.iter().enumerate() {
+            if let Some(code) = code {
+                setup.push(fixture.set_holder_instruction(holder, *code, &compliance.pubkey()));
+            }
+        }
+        fixture
+            .send(&setup, &[&compliance])
+            .await
+            .expect("registry setup should succeed");
+
+        for mint in 0..MINTS {
+            let mut setup = fixture.mint_setup_instructions(mint);
+            for holder in 0..fixture.holders.len() {
+                setup.push(fixture.add_instruction(holder, mint));
+            }
+            fixture
+                .send(&setup, &[])
+                .await
+                .expect("mint setup should succeed");
+        }
+        fixture
+    }
+
+    fn registry(&self) -> Pubkey {
+        registry_address(&self.compliance.pubkey(), &self.registry_program).address()
+    }
+
+    fn holder_record(&self, holder: usize) -> Pubkey {
+        holder_address(
+            &self.registry(),
+            &self.holders[holder].0,
+            &self.registry_program,
+        )
+        .address()
+    }
+
+    fn set_jurisdiction_instruction(
+        &self,
+        code: JurisdictionCode,
+        status: u8,
+        authority: &Pubkey,
+    ) -> Instruction {
+        Instruction::new_with_bytes(
+            self.registry_program,
+            &[SET_JURISDICTION, code[0], code[1], status],
+            vec![
+                AccountMeta::new(self.registry(), false),
+                AccountMeta::new_readonly(*authority, true),
+            ],
+        )
+    }
+
+    fn set_holder_instruction(
+        &self,
+        holder: usize,
+        code: JurisdictionCode,
+        authority: &Pubkey,
+    ) -> Instruction {
+        Instruction::new_with_bytes(
+            self.registry_program,
+            &[SET_HOLDER_JURISDICTION, code[0], code[1]],
+            vec![
+                AccountMeta::new_readonly(self.registry(), false),
+                AccountMeta::new(self.holder_record(holder), false),
+                AccountMeta::new_readonly(self.holders[holder].0, false),
+                AccountMeta::new_readonly(*authority, true),
+                AccountMeta::new(self.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// Initialize the allow list and a hybrid gate reading the registry for
+    /// `mint`
+    fn mint_setup_instructions(&self, mint: usize) -> Vec<Instruction> {
+        let mint = self.mints[mint];
+        let payer = self.context.payer.pubkey();
+        let issuer = self.issuer.pubkey();
+        let initialize_allow_list = Instruction::new_with_bytes(
+            self.allow_list,
+            &[INITIALIZE],
+            vec![
+                AccountMeta::new(
+                    production_allow_list::config_address(&mint, &self.allow_list).address(),
+                    false,
+                ),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(issuer, true),
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        let mut data = vec![hybrid_gate::INITIALIZE];
+        for key in [
+            self.allow_list,
+            self.block_list,
+            self.registry_program,
+            self.registry(),
+        ] {
+            data.extend_from_slice(key.as_ref());
+        }
+        let initialize_gate = Instruction::new_with_bytes(
+            self.gate,
+            &data,
+            vec![
+                AccountMeta::new(
+                    hybrid_gate::config_address(&mint, &self.gate).address(),
+                    false,
+                ),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(issuer, true),
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        vec![initialize_allow_list, initialize_gate]
+    }
+
+    fn add_instruction(&self, holder: usize, mint: usize) -> Instruction {
+        let owner = self.holders[holder].0;
+        let mint = self.mints[mint];
+        Instruction::new_with_bytes(
+            self.allow_list,
+            &[ADD_TO_ALLOW_LIST],
+            vec![
+                AccountMeta::new_readonly(
+                    production_allow_list::config_address(&mint, &self.allow_list).address(),
+                    false,
+                ),
+                AccountMeta::new(
+                    allow_list_address(&mint, &owner, &self.allow_list).address(),
+                    false,
+                ),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(owner, false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// The gate's thaw check for a holder's account of `mint`, reading
+    /// `holder_record` as their jurisdiction record
+    fn thaw_check_instruction(
+        &self,
+        holder: usize,
+        mint: usize,
+        registry: Pubkey,
+        holder_record: Pubkey,
+    ) -> Instruction {
+        let (owner, token_accounts) = &self.holders[holder];
+        let token_account = token_accounts[mint];
+        let mint = self.mints[mint];
+        interface::can_thaw_permissionless(
+            &self.gate,
+            owner,
+            token_account,
+            mint,
+            interface::find_thaw_extra_account_metas_address(&mint, &self.gate).0,
+            &[
+                owner.into(),
+                hybrid_gate::config_address(&mint, &self.gate)
+                    .address()
+                    .into(),
+                allow_list_address(&mint, owner, &self.allow_list)
+                    .address()
+                    .into(),
+                example_block_list::block_list_address(&mint, owner, &self.block_list)
+                    .address()
+                    .into(),
+                registry.into(),
+                holder_record.into(),
+            ],
+        )
+    }
+
+    /// The gate's thaw check followed by the thaw it authorizes
+    fn thaw_instructions(&self, holder: usize, mint: usize) -> Vec<Instruction> {
+        let check =
+            self.thaw_check_instruction(holder, mint, self.registry(), self.holder_record(holder));
+        let thaw = token_instruction::thaw_account(
+            &spl_token_2022::id(),
+            &self.holders[holder].1[mint],
+            &self.mints[mint],
+            &self.issuer.pubkey(),
+            &[],
+        )
+        .unwrap();
+        vec![check, thaw]
+    }
+
+    /// A keeper's freeze check followed by the freeze it authorizes
+    fn keeper_freeze_instructions(&self, holder: usize, mint: usize) -> Vec<Instruction> {
+        let (owner, token_accounts) = &self.holders[holder];
+        let mint_key = self.mints[mint];
+        let check = interface::can_freeze_permissionless(
+            &self.gate,
+            Pubkey::new_unique(),
+            token_accounts[mint],
+            mint_key,
+            interface::find_freeze_extra_account_metas_address(&mint_key, &self.gate).0,
+            &[
+                hybrid_gate::config_address(&mint_key, &self.gate)
+                    .address()
+                    .into(),
+                example_block_list::block_list_address(&mint_key, owner, &self.block_list)
+                    .address()
+                    .into(),
+                self.registry().into(),
+                self.holder_record(holder).into(),
+            ],
+        );
+        let freeze = token_instruction::freeze_account(
+            &spl_token_2022::id(),
+            &token_accounts[mint],
+            &mint_key,
+            &self.issuer.pubkey(),
+            &[],
+        )
+        .unwrap();
+        vec![check, freeze]
+    }
+
+    async fn registry_state(&mut self) -> Option<JurisdictionRegistry> {
+        let registry = self.registry();
+        let account = self
+            .context
+            .banks_client
+            .get_account(registry)
+            .await
+            .ok()??;
+        JurisdictionRegistry::unpack(&account.data).ok()
+    }
+
+    async fn is_frozen(&mut self, holder: usize, mint: usize) -> bool {
+        let address = self.holders[holder].1[mint];
+        let account = self
+            .context
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .expect("token account should exist");
+        TokenAccount::unpack(&account.data).unwrap().state == AccountState::Frozen
+    }
+
+    fn transaction(
+        &self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+        blockhash: solana_sdk::hash::Hash,
+    ) -> Transaction {
+        let mut all_signers = vec![&self.context.payer];
+        let issuer_signs = instructions
+            .iter()
+            .flat_map(|instruction| &instruction.accounts)
+            .any(|meta| meta.is_signer && meta.pubkey == self.issuer.pubkey());
+        if issuer_signs {
+            all_signers.push(&self.issuer);
+        }
+        all_signers.extend_from_slice(signers);
+        Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.context.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        )
+    }
+
+    async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        // A fresh blockhash keeps repeated transactions from being deduplicated
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let transaction = self.transaction(instructions, signers, blockhash);
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+    }
+
+    /// Whether `instructions` would succeed, and their logs
+    async fn simulate(&mut self, instructions: &[Instruction]) -> (bool, Vec<String>) {
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let transaction = self.transaction(instructions, &[], blockhash);
+        let simulation = self
+            .context
+            .banks_client
+            .simulate_transaction(transaction)
+            .await
+            .unwrap();
+        let logs = simulation
+            .simulation_details
+            .map(|details| details.logs)
+            .unwrap_or_default();
+        (simulation.result.is_some_and(|result| result.is_ok()), logs)
+    }
+
+    /// Whether the gate authorizes a thaw of every one of a holder's
+    /// accounts, one entry per mint
+    async fn thaw_decisions(&mut self, holder: usize) -> Vec<bool> {
+        let mut decisions = Vec::new();
+        for mint in 0..MINTS {
+            let check = self.thaw_check_instruction(
+                holder,
+                mint,
+                self.registry(),
+                self.holder_record(holder),
+            );
+            decisions.push(self.simulate(&[check]).await.0);
+        }
+        decisions
+    }
+}
+
+/// Test 1: Only the registry authority can update codes and holders
+#[tokio::test]
+async fn test_registry_updates() {
+    let report = run_registry_updates_test().await;
+    assert!(
+        report.passed,
+        "Registry updates test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_registry_updates_test() -> TestResultReport {
+    let test_name = "Jurisdiction Registry Updates";
+    let mut assertions = 0;
+
+    let mut fixture = RegistryFixture::new().await;
+    let compliance = fixture.compliance.insecure_clone();
+    let impostor = Keypair::new();
+
+    // Assertion 1: Setup wrote both codes, one version per update
+    assertions += 1;
+    match fixture.registry_state().await {
+        Some(registry)
+            if registry.version == 2
+                && registry.status(b"US") == Some(JurisdictionStatus::Allowed)
+                && registry.status(b"FR") == Some(JurisdictionStatus::Allowed) => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Registry after setup: {:?}", other),
+            )
+        }
+    }
+
+    // Assertion 2: Someone other than the authority cannot change a code
+    assertions += 1;
+    let forged = fixture.set_jurisdiction_instruction(*b"FR", 1, &impostor.pubkey());
+    if fixture.send(&[forged], &[&impostor]).await.is_ok() {
+        return TestResultReport::failure(test_name, "Impostor changed a jurisdiction".to_string());
+    }
+
+    // Assertion 3: Nor move a holder to another jurisdiction
+    assertions += 1;
+    let forged = fixture.set_holder_instruction(1, *b"US", &impostor.pubkey());
+    if fixture.send(&[forged], &[&impostor]).await.is_ok() {
+        return TestResultReport::failure(test_name, "Impostor moved a holder".to_string());
+    }
+
+    // Assertion 4: An unknown status byte is rejected
+    assertions += 1;
+    let invalid = fixture.set_jurisdiction_instruction(*b"FR", 7, &compliance.pubkey());
+    if fixture.send(&[invalid], &[&compliance]).await.is_ok() {
+        return TestResultReport::failure(test_name, "Accepted status byte 7".to_string());
+    }
+
+    // Assertion 5: Re-setting a code updates it in place and bumps the
+    // version
+    assertions += 1;
+    let block = fixture.set_jurisdiction_instruction(*b"FR", 1, &compliance.pubkey());
+    if let Err(e) = fixture.send(&[block], &[&compliance]).await {
+        return TestResultReport::failure(test_name, format!("Blocking FR failed: {}", e));
+    }
+    match fixture.registry_state().await {
+        Some(registry)
+            if registry.version == 3
+                && registry.entries.len() == 2
+                && registry.status(b"FR") == Some(JurisdictionStatus::Blocked) => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Registry after update: {:?}", other),
+            )
+        }
+    }
+
+    // Assertion 6: The authority can move a holder, and the gate follows
+    assertions += 1;
+    let move_holder = fixture.set_holder_instruction(1, *b"US", &compliance.pubkey());
+    if let Err(e) = fixture.send(&[move_holder], &[&compliance]).await {
+        return TestResultReport::failure(test_name, format!("Moving holder failed: {}", e));
+    }
+    if fixture.thaw_decisions(1).await != vec![true; MINTS] {
+        return TestResultReport::failure(
+            test_name,
+            "Holder moved to an allowed jurisdiction still denied".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: One registry write embargoes a jurisdiction across every mint
+#[tokio::test]
+async fn test_embargo_propagates_across_gates() {
+    let report = run_embargo_propagation_test().await;
+    assert!(
+        report.passed,
+        "Embargo propagation test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_embargo_propagation_test() -> TestResultReport {
+    let test_name = "Jurisdiction Embargo Propagation";
+    let mut assertions = 0;
+
+    let mut fixture = RegistryFixture::new().await;
+    let compliance = fixture.compliance.insecure_clone();
+    let (us, fr, unattested) = (0, 1, 2);
+
+    // Assertion 1: Before the embargo, attested holders can thaw on both
+    // mints and a holder without a jurisdiction cannot
+    assertions += 1;
+    let before = (
+        fixture.thaw_decisions(us).await,
+        fixture.thaw_decisions(fr).await,
+        fixture.thaw_decisions(unattested).await,
+    );
+    if before != (vec![true; MINTS], vec![true; MINTS], vec![false; MINTS]) {
+        return TestResultReport::failure(test_name, format!("Decisions before: {:?}", before));
+    }
+    for mint in 0..MINTS {
+        let thaw = fixture.thaw_instructions(us, mint);
+        if let Err(e) = fixture.send(&thaw, &[]).await {
+            return TestResultReport::failure(test_name, format!("US holder thaw failed: {}", e));
+        }
+    }
+
+    // Assertion 2: A single registry write denies the US holder on every mint
+    assertions += 1;
+    let embargo = fixture.set_jurisdiction_instruction(*b"US", 1, &compliance.pubkey());
+    if let Err(e) = fixture.send(&[embargo], &[&compliance]).await {
+        return TestResultReport::failure(test_name, format!("Embargo failed: {}", e));
+    }
+    let check =
+        fixture.thaw_check_instruction(us, 0, fixture.registry(), fixture.holder_record(us));
+    match fixture.simulate(&[check]).await {
+        (false, logs) if logs.iter().any(|log| log.contains("allowed jurisdiction")) => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Embargoed holder's thaw check: {:?}", other),
+            )
+        }
+    }
+    if fixture.thaw_decisions(us).await != vec![false; MINTS] {
+        return TestResultReport::failure(
+            test_name,
+            "Embargo did not reach every mint".to_string(),
+        );
+    }
+
+    // Assertion 3: Holders elsewhere are unaffected
+    assertions += 1;
+    if fixture.thaw_decisions(fr).await != vec![true; MINTS] {
+        return TestResultReport::failure(
+            test_name,
+            "Embargo on US denied a French holder".to_string(),
+        );
+    }
+
+    // Assertion 4: Keepers can freeze the embargoed holder's thawed accounts
+    // on every mint, but not a holder elsewhere
+    assertions += 1;
+    for mint in 0..MINTS {
+        let freeze = fixture.keeper_freeze_instructions(us, mint);
+        if let Err(e) = fixture.send(&freeze, &[]).await {
+            return TestResultReport::failure(
+                test_name,
+                format!("Keeper freeze on mint {} failed: {}", mint, e),
+            );
+        }
+        if !fixture.is_frozen(us, mint).await {
+            return TestResultReport::failure(test_name, format!("Mint {} not frozen", mint));
+        }
+    }
+    let freeze = fixture.keeper_freeze_instructions(fr, 0);
+    if fixture.simulate(&freeze).await.0 {
+        return TestResultReport::failure(
+            test_name,
+            "Keeper could freeze a holder outside the embargo".to_string(),
+        );
+    }
+
+    // Assertion 5: Lifting the embargo is one write too
+    assertions += 1;
+    let lift = fixture.set_jurisdiction_instruction(*b"US", 0, &compliance.pubkey());
+    if let Err(e) = fixture.send(&[lift], &[&compliance]).await {
+        return TestResultReport::failure(test_name, format!("Lift failed: {}", e));
+    }
+    if fixture.thaw_decisions(us).await != vec![true; MINTS] {
+        return TestResultReport::failure(
+            test_name,
+            "Lifting the embargo did not reach every mint".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: The gate only believes the configured registry and the holder's
+/// own record
+#[tokio::test]
+async fn test_registry_accounts_cannot_be_substituted() {
+    let report = run_substitution_test().await;
+    assert!(
+        report.passed,
+        "Registry substitution test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_substitution_test() -> TestResultReport {
+    let test_name = "Jurisdiction Registry Substitution";
+    let mut assertions = 0;
+
+    let mut fixture = RegistryFixture::new().await;
+    let compliance = fixture.compliance.insecure_clone();
+    let embargo = fixture.set_jurisdiction_instruction(*b"US", 1, &compliance.pubkey());
+    if let Err(e) = fixture.send(&[embargo], &[&compliance]).await {
+        return TestResultReport::failure(test_name, format!("Embargo failed: {}", e));
+    }
+
+    // Assertion 1: A registry of another authority that allows the US is not
+    // the configured one
+    assertions += 1;
+    let rogue = Keypair::new();
+    let rogue_registry = registry_address(&rogue.pubkey(), &fixture.registry_program).address();
+    let create_rogue = vec![
+        Instruction::new_with_bytes(
+            fixture.registry_program,
+            &[INITIALIZE_REGISTRY],
+            vec![
+                AccountMeta::new(rogue_registry, false),
+                AccountMeta::new_readonly(rogue.pubkey(), true),
+                AccountMeta::new(fixture.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        ),
+        Instruction::new_with_bytes(
+            fixture.registry_program,
+            &[SET_JURISDICTION, b'U', b'S', 0],
+            vec![
+                AccountMeta::new(rogue_registry, false),
+                AccountMeta::new_readonly(rogue.pubkey(), true),
+            ],
+        ),
+    ];
+    if let Err(e) = fixture.send(&create_rogue, &[&rogue]).await {
+        return TestResultReport::failure(test_name, format!("Rogue registry failed: {}", e));
+    }
+    let rogue_record = holder_address(
+        &rogue_registry,
+        &fixture.holders[0].0,
+        &fixture.registry_program,
+    )
+    .address();
+    let check = fixture.thaw_check_instruction(0, 0, rogue_registry, rogue_record);
+    if fixture.simulate(&[check]).await.0 {
+        return TestResultReport::failure(
+            test_name,
+            "Gate accepted an unconfigured registry".to_string(),
+        );
+    }
+
+    // Assertion 2: Another holder's record cannot stand in for the
+    // embargoed holder's
+    assertions += 1;
+    let check = fixture.thaw_check_instruction(0, 0, fixture.registry(), fixture.holder_record(1));
+    if fixture.simulate(&[check]).await.0 {
+        return TestResultReport::failure(
+            test_name,
+            "Gate accepted another holder's jurisdiction".to_string(),
+        );
+    }
+
+    // Assertion 3: Leaving out the registry accounts fails instead of
+    // skipping the jurisdiction check
+    assertions += 1;
+    let mut check =
+        fixture.thaw_check_instruction(0, 0, fixture.registry(), fixture.holder_record(0));
+    check.accounts.truncate(check.accounts.len() - 2);
+    if fixture.simulate(&[check]).await.0 {
+        return TestResultReport::failure(
+            test_name,
+            "Gate skipped the registry when it was left out".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate jurisdiction registry test report
+#[tokio::test]
+async fn generate_jurisdiction_registry_report() {
+    let results = vec![
+        run_registry_updates_test().await,
+        run_embargo_propagation_test().await,
+        run_substitution_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Jurisdiction Registry Test Results",
+        "../../tests/reports/jurisdiction_registry.md",
+    ) {
+        panic!("Failed to generate jurisdiction registry report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} jurisdiction registry tests failed", failed);
+}
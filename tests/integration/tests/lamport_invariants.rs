@@ -0,0 +1,286 @@
+//! Lamport and rent-exemption invariant tests
+//!
+//! The scenario fixtures run every step through `LamportInvariants`; these
+//! tests make sure the invariants actually fire. Hand-built diffs cover each
+//! rule, and a deliberately leaky gate deployed into `solana-program-test`
+//! shows that a real drain is caught through the account snapshot differ.
+
+use solana_program_test::{processor, ProgramTest};
+use solana_sdk::{
+    account::Account,
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::Signer,
+    transaction::Transaction,
+};
+
+use token_acl_integration_tests::{
+    account_snapshots::{AccountSnapshot, AccountState},
+    invariants::LamportInvariants,
+    reporting, TestResultReport,
+};
+
+const RECORD_LEN: usize = 82;
+
+/// Moves `data[0]` percent of the record's lamports to the recipient
+fn leaky_gate_process(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let record = next_account_info(account_info_iter)?;
+    let recipient = next_account_info(account_info_iter)?;
+
+    let percent = u64::from(data.first().copied().unwrap_or(0));
+    let amount = record.lamports() * percent / 100;
+    **record.lamports.borrow_mut() -= amount;
+    **recipient.lamports.borrow_mut() += amount;
+    Ok(())
+}
+
+fn state(lamports: u64, owner: Pubkey, len: usize) -> Option<AccountState> {
+    Some(AccountState {
+        lamports,
+        owner,
+        data: vec![0; len],
+    })
+}
+
+/// The rule names `invariants` reports for a step from `before` to `after`
+fn violated(
+    invariants: &LamportInvariants,
+    before: &[(Pubkey, Option<AccountState>)],
+    after: &[(Pubkey, Option<AccountState>)],
+) -> Vec<&'static str> {
+    let snapshot = |accounts: &[(Pubkey, Option<AccountState>)]| {
+        let mut snapshot = AccountSnapshot::new();
+        for (address, state) in accounts {
+            snapshot.insert(*address, state.clone());
+        }
+        snapshot
+    };
+    let mut rules: Vec<&'static str> = invariants
+        .check(&snapshot(before).diff(&snapshot(after)))
+        .into_iter()
+        .map(|violation| violation.invariant)
+        .collect();
+    rules.sort();
+    rules.dedup();
+    rules
+}
+
+/// Test 1: Each invariant fires on the step that breaks it
+#[test]
+fn test_lamport_invariant_rules() {
+    let report = run_lamport_invariant_rules_test();
+    assert!(
+        report.passed,
+        "Lamport invariant rules test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_lamport_invariant_rules_test() -> TestResultReport {
+    let test_name = "Lamport Invariant Rules";
+    let mut assertions = 0;
+
+    let gate = Pubkey::new_unique();
+    let system = solana_sdk::system_program::id();
+    let rent = Rent::default();
+    let exempt = rent.minimum_balance(RECORD_LEN);
+    let record = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let mut invariants = LamportInvariants::new(&[gate], rent);
+
+    let cases: [(&str, Vec<_>, Vec<_>, Vec<&str>); 5] = [
+        (
+            "Rent-exempt creation paid by the payer",
+            vec![(record, None), (payer, state(10 * exempt, system, 0))],
+            vec![
+                (record, state(exempt, gate, RECORD_LEN)),
+                (payer, state(9 * exempt - 5_000, system, 0)),
+            ],
+            vec![],
+        ),
+        (
+            "Creation below rent exemption",
+            vec![(record, None), (payer, state(10 * exempt, system, 0))],
+            vec![
+                (record, state(exempt - 1, gate, RECORD_LEN)),
+                (payer, state(9 * exempt + 1, system, 0)),
+            ],
+            vec!["rent-exemption"],
+        ),
+        (
+            "Partial drain to an undeclared account",
+            vec![
+                (record, state(2 * exempt, gate, RECORD_LEN)),
+                (recipient, state(0, system, 0)),
+            ],
+            vec![
+                (record, state(exempt, gate, RECORD_LEN)),
+                (recipient, state(exempt, system, 0)),
+            ],
+            vec!["lamport-drain", "lamport-leak"],
+        ),
+        (
+            "Close refunded to an undeclared account",
+            vec![
+                (record, state(exempt, gate, RECORD_LEN)),
+                (recipient, state(0, system, 0)),
+            ],
+            vec![(record, None), (recipient, state(exempt, system, 0))],
+            vec!["lamport-leak"],
+        ),
+        (
+            "Lamports out of thin air",
+            vec![(payer, state(exempt, system, 0))],
+            vec![(payer, state(exempt + 1, system, 0))],
+            vec!["lamport-creation"],
+        ),
+    ];
+
+    // Assertions 1-5: Each case reports exactly the rules it breaks
+    for (case, before, after, expected) in cases {
+        assertions += 1;
+        let rules = violated(&invariants, &before, &after);
+        if rules != expected {
+            return TestResultReport::failure(
+                test_name,
+                format!("{}: expected {:?}, got {:?}", case, expected, rules),
+            );
+        }
+    }
+
+    // Assertion 6: Declaring the recipient accepts the close refund
+    assertions += 1;
+    invariants.allow_refunds_to(recipient);
+    let rules = violated(
+        &invariants,
+        &[
+            (record, state(exempt, gate, RECORD_LEN)),
+            (recipient, state(0, system, 0)),
+        ],
+        &[(record, None), (recipient, state(exempt, system, 0))],
+    );
+    if !rules.is_empty() {
+        return TestResultReport::failure(
+            test_name,
+            format!("Declared close refund reported {:?}", rules),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: A real drain is caught through the account snapshot differ
+#[tokio::test]
+async fn test_leaky_gate_detected() {
+    let report = run_leaky_gate_test().await;
+    assert!(report.passed, "Leaky gate test failed: {:?}", report.error);
+}
+
+async fn run_leaky_gate_test() -> TestResultReport {
+    let test_name = "Leaky Gate Detected";
+    let mut assertions = 0;
+
+    let gate = Pubkey::new_unique();
+    let record = Pubkey::new_unique();
+    let attacker = Pubkey::new_unique();
+    let rent = Rent::default();
+
+    let mut program_test = ProgramTest::new("leaky_gate", gate, processor!(leaky_gate_process));
+    program_test.add_account(
+        record,
+        Account {
+            lamports: 2 * rent.minimum_balance(RECORD_LEN),
+            data: vec![0; RECORD_LEN],
+            owner: gate,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+    let mut context = program_test.start_with_context().await;
+    let invariants = LamportInvariants::new(&[gate], rent);
+
+    // Skim half: both accounts end up rent-exempt, so the runtime's own
+    // rent-state check lets the transaction land
+    let leak = Instruction::new_with_bytes(
+        gate,
+        &[50],
+        vec![
+            AccountMeta::new(record, false),
+            AccountMeta::new(attacker, false),
+        ],
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &[leak],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    let addresses = transaction.message.account_keys.clone();
+
+    let before = match AccountSnapshot::capture(&mut context.banks_client, &addresses).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => return TestResultReport::failure(test_name, format!("Snapshot failed: {}", e)),
+    };
+    if let Err(e) = context.banks_client.process_transaction(transaction).await {
+        return TestResultReport::failure(test_name, format!("Leak failed to land: {}", e));
+    }
+    let after = match AccountSnapshot::capture(&mut context.banks_client, &addresses).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => return TestResultReport::failure(test_name, format!("Snapshot failed: {}", e)),
+    };
+    let diff = before.diff(&after);
+
+    // Assertion 1: The differ sees the record and the attacker change
+    assertions += 1;
+    if diff.get(&record).is_none() || diff.get(&attacker).is_none() {
+        return TestResultReport::failure(test_name, format!("Diff missed the leak: {:?}", diff));
+    }
+
+    // Assertion 2: Both the drain and the leak are reported
+    assertions += 1;
+    let mut rules: Vec<&str> = invariants
+        .check(&diff)
+        .into_iter()
+        .map(|violation| violation.invariant)
+        .collect();
+    rules.sort();
+    rules.dedup();
+    let expected = ["lamport-drain", "lamport-leak"];
+    if rules != expected {
+        return TestResultReport::failure(
+            test_name,
+            format!("Expected {:?}, got {:?}", expected, rules),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate lamport invariant test report
+#[tokio::test]
+async fn generate_lamport_invariants_report() {
+    let results = vec![
+        run_lamport_invariant_rules_test(),
+        run_leaky_gate_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Lamport Invariant Test Results",
+        "../../tests/reports/lamport_invariants.md",
+    ) {
+        panic!("Failed to generate lamport invariants report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} lamport invariant tests failed", failed);
+}
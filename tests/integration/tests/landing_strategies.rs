@@ -0,0 +1,695 @@
+//! Transaction landing strategy experiment
+//!
+//! Compares how many permissionless thaws land, and how fast, when a burst
+//! of them is sent through a plain RPC node, with priority fees at several
+//! levels, and through staked or Jito endpoints when they are configured.
+//!
+//! The comparison itself needs a live cluster and runs in the `experiments`
+//! category, configured through the environment variables listed in
+//! [`token_acl_integration_tests::landing`]. The strategies' mechanics are
+//! checked on a `solana-program-test` bank in every run: every thaw lands,
+//! and each one costs its payer exactly the base fee plus the premium the
+//! report shows for its strategy.
+//!
+//! Every thaw check is paid by its own holder: transactions sent at once
+//! that write-lock the same fee payer would compete with each other rather
+//! than with the rest of the cluster.
+//!
+//! Holders and their allow list records are registered with a [`Teardown`]
+//! as they are onboarded, so a live run hands their lamports back to the
+//! keypair that funded it, whether or not the comparison succeeded. Only the
+//! gate config of the run's mint is left behind, as the gate cannot close it.
+
+use production_allow_list::{
+    allow_list_address, config_address, RemoveMode, ADD_TO_ALLOW_LIST, INITIALIZE,
+    REMOVE_FROM_ALLOW_LIST,
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_program_test::{processor, ProgramTest};
+use solana_sdk::{
+    account::Account,
+    commitment_config::CommitmentConfig,
+    compute_budget::ComputeBudgetInstruction,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{read_keypair_file, Keypair, Signer},
+    system_instruction, system_program,
+    transaction::Transaction,
+};
+use std::{collections::HashMap, str::FromStr, time::Duration};
+use token_acl_interface::{
+    can_thaw_permissionless, find_missing_record_policy_address,
+    find_thaw_extra_account_metas_address,
+};
+
+use token_acl_integration_tests::{
+    context::{Namespace, TestContext},
+    landing::{self, LandingConfig, LandingStrategy, StrategyResult},
+    reporting, rpc,
+    submission::{Operation, Outcome, SubmissionCluster, SubmissionConfig, SubmissionEngine},
+    suite_config::{self, SuiteCategory},
+    teardown::{AccountFetcher, Cleanup, Teardown, TeardownReport, TemporaryAccount},
+    TestResultReport,
+};
+
+/// Compute units requested by every thaw check, so priority fees are paid
+/// on a known limit
+const THAW_COMPUTE_UNIT_LIMIT: u32 = 40_000;
+
+/// Lamports each holder receives to pay for their own thaw check
+const HOLDER_FUNDING: u64 = 1_000_000;
+
+/// Holders added per allow list transaction
+const BATCH_SIZE: usize = 8;
+
+/// Thaws per strategy on the local bank
+const LOCAL_BURST: usize = 8;
+
+/// Jito tip used on the local bank
+const LOCAL_TIP: u64 = 10_000;
+
+/// How long a burst has to land; a blockhash expires well before this on a
+/// live cluster
+const LIVE_DEADLINE: Duration = Duration::from_secs(90);
+const LOCAL_DEADLINE: Duration = Duration::from_secs(30);
+
+/// A fresh allow-listed mint on some cluster, onboarding holders through the
+/// submission engine
+struct Experiment<C> {
+    engine: SubmissionEngine<C>,
+    payer: Keypair,
+    issuer: Keypair,
+    gate: Pubkey,
+    mint: Pubkey,
+    /// Every holder and record created so far, returned to `payer`
+    teardown: Teardown,
+}
+
+impl<C: SubmissionCluster> Experiment<C> {
+    fn new(cluster: C, payer: Keypair, issuer: Keypair, gate: Pubkey, mint: Pubkey) -> Self {
+        Self {
+            engine: SubmissionEngine::new(cluster, SubmissionConfig::default()),
+            teardown: Teardown::new(payer.pubkey()),
+            payer,
+            issuer,
+            gate,
+            mint,
+        }
+    }
+
+    async fn submit(&mut self, operations: Vec<Operation>) -> Result<(), String> {
+        let issuer = self.issuer.insecure_clone();
+        let report = self
+            .engine
+            .submit_all(operations, &self.payer, &[&issuer])
+            .await;
+        match report
+            .outcomes
+            .iter()
+            .find(|(_, outcome)| !matches!(outcome, Outcome::Landed { .. }))
+        {
+            Some((label, outcome)) => Err(format!("{} did not land: {:?}", label, outcome)),
+            None => Ok(()),
+        }
+    }
+
+    async fn initialize(&mut self) -> Result<(), String> {
+        let initialize = Instruction::new_with_bytes(
+            self.gate,
+            &[INITIALIZE],
+            vec![
+                AccountMeta::new(config_address(&self.mint, &self.gate).address(), false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        self.submit(vec![Operation::new("initialize", vec![initialize])])
+            .await
+    }
+
+    /// `count` new holders, allow-listed and funded for their own thaws
+    async fn onboard(&mut self, count: usize) -> Result<Vec<Keypair>, String> {
+        let holders: Vec<Keypair> = (0..count).map(|_| Keypair::new()).collect();
+        // Registered before they exist, so a batch that partly landed is
+        // still cleaned up
+        for holder in &holders {
+            let label = format!("holder {}", self.teardown.len() / 2);
+            self.teardown.register(
+                &format!("{} record", label),
+                TemporaryAccount::GateAccount {
+                    address: allow_list_address(&self.mint, &holder.pubkey(), &self.gate).address(),
+                    close: self.close_instruction(&holder.pubkey()),
+                    signers: vec![self.issuer.insecure_clone()],
+                },
+            );
+            self.teardown
+                .register(&label, TemporaryAccount::Wallet(holder.insecure_clone()));
+        }
+        let operations = holders
+            .chunks(BATCH_SIZE)
+            .enumerate()
+            .map(|(batch, chunk)| {
+                let instructions = chunk
+                    .iter()
+                    .flat_map(|holder| {
+                        [
+                            self.add_instruction(&holder.pubkey()),
+                            system_instruction::transfer(
+                                &self.payer.pubkey(),
+                                &holder.pubkey(),
+                                HOLDER_FUNDING,
+                            ),
+                        ]
+                    })
+                    .collect();
+                Operation::new(&format!("onboard batch {}", batch), instructions)
+            })
+            .collect();
+        self.submit(operations).await?;
+        Ok(holders)
+    }
+
+    fn add_instruction(&self, holder: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            self.gate,
+            &[ADD_TO_ALLOW_LIST],
+            vec![
+                AccountMeta::new_readonly(config_address(&self.mint, &self.gate).address(), false),
+                AccountMeta::new(
+                    allow_list_address(&self.mint, holder, &self.gate).address(),
+                    false,
+                ),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(*holder, false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// Close the holder's record, refunding its rent to the payer
+    fn close_instruction(&self, holder: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            self.gate,
+            &[REMOVE_FROM_ALLOW_LIST, RemoveMode::Close as u8],
+            vec![
+                AccountMeta::new_readonly(config_address(&self.mint, &self.gate).address(), false),
+                AccountMeta::new(
+                    allow_list_address(&self.mint, holder, &self.gate).address(),
+                    false,
+                ),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.teardown.faucet(), false),
+            ],
+        )
+    }
+
+    /// The holder's own thaw check with a fixed compute unit limit
+    fn thaw_instructions(&self, holder: &Pubkey) -> Vec<Instruction> {
+        vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(THAW_COMPUTE_UNIT_LIMIT),
+            can_thaw_permissionless(
+                &self.gate,
+                *holder,
+                Pubkey::new_unique(),
+                self.mint,
+                find_thaw_extra_account_metas_address(&self.mint, &self.gate).0,
+                &[
+                    (*holder).into(),
+                    allow_list_address(&self.mint, holder, &self.gate)
+                        .address()
+                        .into(),
+                    find_missing_record_policy_address(&self.mint, &self.gate)
+                        .0
+                        .into(),
+                ],
+            ),
+        ]
+    }
+
+    /// Send a burst of thaws per strategy, each through its own endpoint
+    /// when it has one; returns each result with the holders that paid
+    async fn compare(
+        &mut self,
+        strategies: Vec<LandingStrategy>,
+        endpoints: &HashMap<String, C>,
+        burst: usize,
+        deadline: Duration,
+    ) -> Result<Vec<(StrategyResult, Vec<Pubkey>)>, String> {
+        let mut results = Vec::with_capacity(strategies.len());
+        for strategy in strategies {
+            let holders = self.onboard(burst).await?;
+            let sender = match strategy.endpoint() {
+                Some(url) => endpoints
+                    .get(url)
+                    .ok_or_else(|| format!("No client for {}", url))?,
+                None => self.engine.cluster(),
+            };
+            let blockhash = self.engine.cluster().latest_blockhash().await?;
+            let transactions = holders
+                .iter()
+                .map(|holder| {
+                    let instructions =
+                        strategy.apply(&self.thaw_instructions(&holder.pubkey()), &holder.pubkey());
+                    Transaction::new_signed_with_payer(
+                        &instructions,
+                        Some(&holder.pubkey()),
+                        &[holder],
+                        blockhash,
+                    )
+                })
+                .collect();
+            let premium = strategy.premium(THAW_COMPUTE_UNIT_LIMIT);
+            let result = landing::run_strategy(
+                strategy,
+                sender,
+                self.engine.cluster(),
+                transactions,
+                deadline,
+                self.engine.config().poll_interval,
+                premium,
+            )
+            .await;
+            results.push((result, holders.iter().map(|h| h.pubkey()).collect()));
+        }
+        Ok(results)
+    }
+}
+
+impl<C: SubmissionCluster + AccountFetcher> Experiment<C> {
+    /// Close every record and sweep every holder back to the payer
+    async fn tear_down(&mut self) -> TeardownReport {
+        let teardown = std::mem::replace(&mut self.teardown, Teardown::new(self.payer.pubkey()));
+        teardown.run(&mut self.engine, &self.payer).await
+    }
+}
+
+/// Check every strategy's mechanics on a local bank
+#[tokio::test]
+async fn test_landing_strategies_localnet() {
+    let report = run_localnet_test().await;
+    assert!(
+        report.passed,
+        "Landing strategies on localnet failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_localnet_test() -> TestResultReport {
+    let test_name = "Landing Strategies: Local Mechanics";
+    let mut assertions = 0;
+
+    let gate = Pubkey::new_unique();
+    let tip_account = Pubkey::new_unique();
+    let tip_account_lamports = Rent::default().minimum_balance(0);
+    let mut program_test = ProgramTest::new(
+        "production_allow_list",
+        gate,
+        processor!(production_allow_list::process_instruction),
+    );
+    // Tips below the rent-exempt minimum can only go to a funded account
+    program_test.add_account(
+        tip_account,
+        Account::new(tip_account_lamports, 0, &system_program::id()),
+    );
+    let context = match TestContext::start(test_name, program_test).await {
+        Ok(context) => context,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let banks_client = context.client();
+    let lamports_per_signature = context.lamports_per_signature;
+
+    // The Jito strategy is sent to the same bank under its own name
+    let config = LandingConfig {
+        jito: Some(("localnet-block-engine".to_string(), tip_account, LOCAL_TIP)),
+        ..LandingConfig::default()
+    };
+    let strategies = config.strategies();
+    let endpoints: HashMap<String, _> = strategies
+        .iter()
+        .filter_map(|strategy| strategy.endpoint())
+        .map(|url| (url.to_string(), banks_client.clone()))
+        .collect();
+
+    let mut experiment = Experiment::new(
+        banks_client.clone(),
+        context.payer(),
+        Keypair::new(),
+        gate,
+        context.namespace().address("mint"),
+    );
+    // Nothing is worth keeping on a local bank, whatever `--keep-state` says
+    experiment.teardown = Teardown::new(experiment.payer.pubkey()).with_keep_state(false);
+    if let Err(e) = experiment.initialize().await {
+        return TestResultReport::failure(test_name, e);
+    }
+    let results = match experiment
+        .compare(strategies.clone(), &endpoints, LOCAL_BURST, LOCAL_DEADLINE)
+        .await
+    {
+        Ok(results) => results,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+
+    // Assertion: every strategy was run
+    assertions += 1;
+    if results.len() != strategies.len() {
+        return TestResultReport::failure(
+            test_name,
+            format!("{} of {} strategies ran", results.len(), strategies.len()),
+        );
+    }
+
+    // Assertion: every thaw of every strategy landed and succeeded
+    assertions += 1;
+    if let Some((result, _)) = results
+        .iter()
+        .find(|(result, _)| result.landed != LOCAL_BURST || result.failed != 0)
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "{}: {} landed and {} failed of {}",
+                result.strategy.name(),
+                result.landed,
+                result.failed,
+                result.sent
+            ),
+        );
+    }
+
+    // Assertion: each holder paid the base fee plus its strategy's premium
+    assertions += 1;
+    for (result, holders) in &results {
+        for holder in holders {
+            let balance = banks_client
+                .clone()
+                .get_balance(*holder)
+                .await
+                .unwrap_or_default();
+            let expected = HOLDER_FUNDING - lamports_per_signature - result.premium;
+            if balance != expected {
+                return TestResultReport::failure(
+                    test_name,
+                    format!(
+                        "{}: holder {} has {} lamports, expected {}",
+                        result.strategy.name(),
+                        holder,
+                        balance,
+                        expected
+                    ),
+                );
+            }
+        }
+    }
+
+    // Assertion: the premium grows with the priority fee level
+    assertions += 1;
+    let fee_premiums: Vec<u64> = results
+        .iter()
+        .filter(|(result, _)| matches!(result.strategy, LandingStrategy::PriorityFee { .. }))
+        .map(|(result, _)| result.premium)
+        .collect();
+    if !fee_premiums.windows(2).all(|pair| pair[0] < pair[1]) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Premiums do not grow with the fee: {:?}", fee_premiums),
+        );
+    }
+
+    // Assertion: the tip account received one tip per Jito thaw
+    assertions += 1;
+    let tips = banks_client
+        .clone()
+        .get_balance(tip_account)
+        .await
+        .unwrap_or_default()
+        - tip_account_lamports;
+    if tips != LOCAL_TIP * LOCAL_BURST as u64 {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Tip account received {} lamports, expected {}",
+                tips,
+                LOCAL_TIP * LOCAL_BURST as u64
+            ),
+        );
+    }
+
+    // Assertion: the teardown closed every record and holder it registered
+    assertions += 1;
+    let registered = experiment.teardown.len();
+    let payer = experiment.payer.pubkey();
+    let payer_before = banks_client
+        .clone()
+        .get_balance(payer)
+        .await
+        .unwrap_or_default();
+    let teardown = experiment.tear_down().await;
+    let expected_accounts = 2 * LOCAL_BURST * strategies.len();
+    if registered != expected_accounts
+        || teardown.accounts.len() != registered
+        || teardown.stranded() != 0
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Registered {} accounts, expected {}:\n{}",
+                registered,
+                expected_accounts,
+                teardown.render_markdown()
+            ),
+        );
+    }
+    if let Some((label, _, cleanup)) = teardown
+        .accounts
+        .iter()
+        .find(|(_, _, cleanup)| !matches!(cleanup, Cleanup::Closed { .. }))
+    {
+        return TestResultReport::failure(test_name, format!("{}: {:?}", label, cleanup));
+    }
+
+    // Assertion: the closed accounts are gone and their lamports are back
+    // with the payer, less one fee per close signed by the payer and one
+    // other signer
+    assertions += 1;
+    for (label, address, _) in &teardown.accounts {
+        if let Ok(Some(account)) = banks_client.clone().get_account(*address).await {
+            return TestResultReport::failure(
+                test_name,
+                format!("{} still holds {} lamports", label, account.lamports),
+            );
+        }
+    }
+    let payer_after = banks_client
+        .clone()
+        .get_balance(payer)
+        .await
+        .unwrap_or_default();
+    let fees = 2 * lamports_per_signature * registered as u64;
+    if payer_after + fees != payer_before + teardown.returned() {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Payer went from {} to {} lamports after {} returned and {} in fees",
+                payer_before,
+                payer_after,
+                teardown.returned(),
+                fees
+            ),
+        );
+    }
+
+    // Assertion: the report has a row per strategy and no missing endpoint note
+    assertions += 1;
+    let results: Vec<StrategyResult> = results.into_iter().map(|(result, _)| result).collect();
+    let markdown = landing::render_landing_report(&results, LOCAL_BURST, "localnet");
+    let missing = strategies
+        .iter()
+        .find(|strategy| !markdown.contains(&format!("| {} |", strategy.name())));
+    if let Some(strategy) = missing {
+        return TestResultReport::failure(
+            test_name,
+            format!("Report has no row for {}", strategy.name()),
+        );
+    }
+    if markdown.contains("were not compared") {
+        return TestResultReport::failure(
+            test_name,
+            "Report says endpoints were not compared".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// What the live experiment runs against, from the environment
+struct LiveTarget {
+    url: String,
+    keypair: Keypair,
+    gate: Pubkey,
+    burst: usize,
+}
+
+impl LiveTarget {
+    fn from_env() -> Result<Self, String> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let keypair_path = var(landing::KEYPAIR_ENV)
+            .ok_or_else(|| format!("{} is not set", landing::KEYPAIR_ENV))?;
+        let gate =
+            var(landing::GATE_ENV).ok_or_else(|| format!("{} is not set", landing::GATE_ENV))?;
+        Ok(Self {
+            url: var(landing::RPC_URL_ENV).unwrap_or_else(|| rpc::DEVNET_URL.to_string()),
+            keypair: read_keypair_file(&keypair_path)
+                .map_err(|e| format!("Reading {}: {}", keypair_path, e))?,
+            gate: Pubkey::from_str(gate.trim())
+                .map_err(|_| format!("Invalid {} value: {}", landing::GATE_ENV, gate))?,
+            burst: match var(landing::BURST_ENV) {
+                Some(burst) => burst
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid {} value: {}", landing::BURST_ENV, burst))?,
+                None => landing::DEFAULT_BURST,
+            },
+        })
+    }
+}
+
+fn rpc_client(url: &str) -> RpcClient {
+    RpcClient::new_with_commitment(url.to_string(), CommitmentConfig::confirmed())
+}
+
+/// Compare the landing strategies on a live cluster
+#[tokio::test]
+async fn experiment_landing_strategies() {
+    let (report, results, teardown) = run_landing_experiment().await;
+
+    let mut markdown = reporting::render_test_report(
+        std::slice::from_ref(&report),
+        "Transaction Landing Strategy Results",
+        &suite_config::current().disabled(),
+    );
+    if let Some((results, burst, cluster)) = results {
+        let comparison = landing::render_landing_report(&results, burst, &cluster);
+        println!("{}", comparison);
+        markdown.push_str(&comparison);
+    }
+    if let Some(teardown) = teardown {
+        let section = teardown.render_markdown();
+        println!("{}", section);
+        markdown.push_str(&section);
+    }
+    std::fs::create_dir_all("../../tests/reports").ok();
+    if let Err(e) = std::fs::write("../../tests/reports/landing_strategies.md", markdown) {
+        panic!("Failed to generate landing strategies report: {}", e);
+    }
+
+    assert!(
+        report.passed,
+        "Landing strategy experiment failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_landing_experiment() -> (
+    TestResultReport,
+    Option<(Vec<StrategyResult>, usize, String)>,
+    Option<TeardownReport>,
+) {
+    let test_name = "Landing Strategies: Live Comparison";
+    let mut assertions = 0;
+    if let Some(skipped) = suite_config::skip_unless(SuiteCategory::Experiments, test_name) {
+        return (skipped, None, None);
+    }
+
+    // Assertion: the experiment is configured
+    assertions += 1;
+    let (target, config) = match LiveTarget::from_env()
+        .and_then(|target| LandingConfig::from_env().map(|config| (target, config)))
+    {
+        Ok(configured) => configured,
+        Err(e) => return (TestResultReport::failure(test_name, e), None, None),
+    };
+
+    let strategies = config.strategies();
+    let endpoints: HashMap<String, RpcClient> = strategies
+        .iter()
+        .filter_map(|strategy| strategy.endpoint())
+        .map(|url| (url.to_string(), rpc_client(url)))
+        .collect();
+    // A mint of this run's own: the gate already holds configs from earlier runs
+    let mut experiment = Experiment::new(
+        rpc_client(&target.url),
+        target.keypair.insecure_clone(),
+        target.keypair.insecure_clone(),
+        target.gate,
+        Namespace::new(test_name).address("mint"),
+    );
+
+    // Assertion: the experiment's mint is set up on the gate
+    assertions += 1;
+    let compared = match experiment.initialize().await {
+        // Assertion: every strategy's burst was onboarded and sent
+        Ok(()) => {
+            assertions += 1;
+            experiment
+                .compare(strategies, &endpoints, target.burst, LIVE_DEADLINE)
+                .await
+        }
+        Err(e) => Err(e),
+    };
+
+    // Whatever was onboarded goes back to the keypair, even on failure
+    let teardown = experiment.tear_down().await;
+    let results = match compared {
+        Ok(results) => results
+            .into_iter()
+            .map(|(result, _)| result)
+            .collect::<Vec<_>>(),
+        Err(e) => {
+            return (
+                TestResultReport::failure(test_name, e),
+                None,
+                Some(teardown),
+            )
+        }
+    };
+
+    // Assertion: no thaw of an allow-listed holder was denied
+    assertions += 1;
+    let denied = results.iter().find(|result| result.failed > 0);
+
+    // Assertion: every account the run created was closed or kept on request
+    assertions += 1;
+    let stranded = teardown
+        .failures()
+        .first()
+        .map(|(label, address, cleanup)| {
+            format!(
+                "Teardown could not close {} ({}): {:?}",
+                label, address, cleanup
+            )
+        });
+
+    let report = match (denied, stranded) {
+        (Some(result), _) => TestResultReport::failure(
+            test_name,
+            format!(
+                "{}: {} thaws landed and failed",
+                result.strategy.name(),
+                result.failed
+            ),
+        ),
+        (None, Some(error)) => TestResultReport::failure(test_name, error),
+        (None, None) => TestResultReport::success(test_name, assertions),
+    };
+    (
+        report,
+        Some((results, target.burst, target.url)),
+        Some(teardown),
+    )
+}
@@ -0,0 +1,509 @@
+//! Model-based tests of the freeze lifecycle
+//!
+//! `proptest-stateful` generates sequences of freeze, thaw and revoke
+//! transitions for a few holders, runs each against the production allow
+//! list and Token-2022 in `solana-program-test`, and checks after every step
+//! that the program accepted exactly the transitions the `lifecycle` state
+//! machine accepts and that every holder's observed state matches the model.
+//! Failing sequences are shrunk by dropping transitions.
+
+use std::{any::Any, cell::RefCell, collections::BTreeSet, panic, time::Duration};
+
+use async_trait::async_trait;
+use borsh::BorshDeserialize;
+use production_allow_list::{
+    AllowListRecord, ADD_TO_ALLOW_LIST, ALLOW_LIST_SEED, CONFIG_SEED, INITIALIZE, REVOKE,
+};
+use proptest::{prelude::*, strategy::BoxedStrategy, test_runner::Config as ProptestConfig};
+use proptest_stateful::{ModelState, ProptestStatefulConfig};
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction, system_program,
+    transaction::Transaction,
+};
+use spl_token_2022::{
+    extension::StateWithExtensions,
+    instruction as token_instruction,
+    solana_program::program_pack::Pack,
+    state::{Account as TokenAccount, AccountState, Mint},
+};
+use token_acl_interface::find_missing_record_policy_address;
+
+use token_acl_integration_tests::{
+    fixtures::test_data,
+    lifecycle::{reachable_states, HolderState, Transition},
+    reporting, TestResultReport,
+};
+
+const HOLDERS: usize = 3;
+
+thread_local! {
+    /// Holder states observed on-chain during the current property run
+    static OBSERVED: RefCell<BTreeSet<HolderState>> = const { RefCell::new(BTreeSet::new()) };
+}
+
+/// One generated step: a transition for one holder
+#[derive(Debug, Clone, Copy)]
+struct LifecycleStep {
+    holder: usize,
+    transition: Transition,
+}
+
+/// The model: every holder's lifecycle state
+#[derive(Debug, Clone, Default)]
+struct FreezeLifecycle {
+    holders: [HolderState; HOLDERS],
+}
+
+/// A bank with the allow list, a mint the issuer freezes, and a thawed,
+/// allow-listed token account per holder
+struct LifecycleBank {
+    context: ProgramTestContext,
+    program_id: Pubkey,
+    issuer: Keypair,
+    mint: Pubkey,
+    config: Pubkey,
+    holders: Vec<(Pubkey, Pubkey)>,
+}
+
+impl LifecycleBank {
+    async fn new() -> Self {
+        let program_id = Pubkey::new_unique();
+        let program_test = ProgramTest::new(
+            "production_allow_list",
+            program_id,
+            processor!(production_allow_list::process_instruction),
+        );
+        let context = program_test.start_with_context().await;
+        let mint = Keypair::new();
+        let (config, _) =
+            Pubkey::find_program_address(&[CONFIG_SEED, mint.pubkey().as_ref()], &program_id);
+        let mut bank = Self {
+            context,
+            program_id,
+            issuer: Keypair::new(),
+            mint: mint.pubkey(),
+            config,
+            holders: Vec::new(),
+        };
+
+        let rent = bank.context.banks_client.get_rent().await.unwrap();
+        let payer = bank.context.payer.pubkey();
+        let issuer = bank.issuer.pubkey();
+        let initialize = Instruction::new_with_bytes(
+            program_id,
+            &[INITIALIZE],
+            vec![
+                AccountMeta::new(config, false),
+                AccountMeta::new_readonly(bank.mint, false),
+                AccountMeta::new_readonly(issuer, true),
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        let create_mint = [
+            system_instruction::create_account(
+                &payer,
+                &bank.mint,
+                rent.minimum_balance(Mint::LEN),
+                Mint::LEN as u64,
+                &spl_token_2022::id(),
+            ),
+            token_instruction::initialize_mint2(
+                &spl_token_2022::id(),
+                &bank.mint,
+                &issuer,
+                Some(&issuer),
+                0,
+            )
+            .unwrap(),
+        ];
+        bank.send(&[initialize], &[])
+            .await
+            .expect("config initialization should succeed");
+        bank.send(&create_mint, &[&mint])
+            .await
+            .expect("mint creation should succeed");
+
+        for _ in 0..HOLDERS {
+            let owner = Pubkey::new_unique();
+            let token_account = Keypair::new();
+            let open = [
+                system_instruction::create_account(
+                    &payer,
+                    &token_account.pubkey(),
+                    rent.minimum_balance(TokenAccount::LEN),
+                    TokenAccount::LEN as u64,
+                    &spl_token_2022::id(),
+                ),
+                token_instruction::initialize_account3(
+                    &spl_token_2022::id(),
+                    &token_account.pubkey(),
+                    &bank.mint,
+                    &owner,
+                )
+                .unwrap(),
+                bank.add_instruction(&owner),
+            ];
+            bank.send(&open, &[&token_account])
+                .await
+                .expect("holder setup should succeed");
+            bank.holders.push((owner, token_account.pubkey()));
+        }
+        bank
+    }
+
+    fn record(&self, owner: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[ALLOW_LIST_SEED, self.mint.as_ref(), owner.as_ref()],
+            &self.program_id,
+        )
+        .0
+    }
+
+    fn add_instruction(&self, owner: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            self.program_id,
+            &[ADD_TO_ALLOW_LIST],
+            vec![
+                AccountMeta::new_readonly(self.config, false),
+                AccountMeta::new(self.record(owner), false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(*owner, false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// The issuer's freeze or thaw of `token_account`
+    fn set_frozen_instruction(&self, token_account: &Pubkey, frozen: bool) -> Instruction {
+        let change = if frozen {
+            token_instruction::freeze_account
+        } else {
+            token_instruction::thaw_account
+        };
+        change(
+            &spl_token_2022::id(),
+            token_account,
+            &self.mint,
+            &self.issuer.pubkey(),
+            &[],
+        )
+        .unwrap()
+    }
+
+    /// The instructions that carry out `transition` for a holder
+    ///
+    /// A thaw is the gate check followed by the thaw the Token ACL program
+    /// would make on its approval, with the issuer standing in for the FAMP
+    /// PDA. Revoking freezes the account only if it is not frozen already.
+    async fn instructions(&mut self, holder: usize, transition: Transition) -> Vec<Instruction> {
+        let (owner, token_account) = self.holders[holder];
+        match transition {
+            Transition::Freeze => vec![self.set_frozen_instruction(&token_account, true)],
+            Transition::Thaw => {
+                let (extra_metas, _) = Pubkey::find_program_address(
+                    &[test_data::THAW_EXTRA_ACCOUNT_METAS_SEED, self.mint.as_ref()],
+                    &self.program_id,
+                );
+                let gate_check = Instruction::new_with_bytes(
+                    self.program_id,
+                    &test_data::THAW_DISCRIMINATOR,
+                    vec![
+                        AccountMeta::new_readonly(owner, false),
+                        AccountMeta::new_readonly(token_account, false),
+                        AccountMeta::new_readonly(self.mint, false),
+                        AccountMeta::new_readonly(extra_metas, false),
+                        AccountMeta::new_readonly(owner, false),
+                        AccountMeta::new_readonly(self.record(&owner), false),
+                        AccountMeta::new_readonly(
+                            find_missing_record_policy_address(&self.mint, &self.program_id).0,
+                            false,
+                        ),
+                    ],
+                );
+                vec![
+                    gate_check,
+                    self.set_frozen_instruction(&token_account, false),
+                ]
+            }
+            Transition::Revoke => {
+                let revoke = Instruction::new_with_bytes(
+                    self.program_id,
+                    &[REVOKE],
+                    vec![
+                        AccountMeta::new_readonly(self.config, false),
+                        AccountMeta::new(self.record(&owner), false),
+                        AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                    ],
+                );
+                let mut instructions = vec![revoke];
+                if !self.observe(holder).await.0 {
+                    instructions.push(self.set_frozen_instruction(&token_account, true));
+                }
+                instructions
+            }
+        }
+    }
+
+    /// Whether the holder's account is frozen and their record is active and
+    /// unrevoked
+    async fn observe(&mut self, holder: usize) -> (bool, bool) {
+        let (owner, token_account) = self.holders[holder];
+        let record = self.record(&owner);
+        let banks_client = &mut self.context.banks_client;
+
+        let account = banks_client
+            .get_account(token_account)
+            .await
+            .unwrap()
+            .expect("token account should exist");
+        let frozen = StateWithExtensions::<TokenAccount>::unpack(&account.data)
+            .unwrap()
+            .base
+            .state
+            == AccountState::Frozen;
+        let allowed = banks_client
+            .get_account(record)
+            .await
+            .unwrap()
+            .and_then(|record| AllowListRecord::deserialize(&mut &record.data[..]).ok())
+            .is_some_and(|record| record.allowed && !record.revoked);
+        (frozen, allowed)
+    }
+
+    async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), String> {
+        // A fresh blockhash keeps repeated transitions from being deduplicated
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let mut all_signers = vec![&self.context.payer];
+        let issuer_signs = instructions
+            .iter()
+            .flat_map(|instruction| &instruction.accounts)
+            .any(|meta| meta.is_signer && meta.pubkey == self.issuer.pubkey());
+        if issuer_signs {
+            all_signers.push(&self.issuer);
+        }
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.context.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[async_trait(?Send)]
+impl ModelState for FreezeLifecycle {
+    type Operation = LifecycleStep;
+    type RunContext = LifecycleBank;
+    type OperationStrategy = BoxedStrategy<LifecycleStep>;
+
+    fn op_generators(&self) -> Vec<Self::OperationStrategy> {
+        // Rejected transitions are generated too: the program must refuse
+        // exactly what the model refuses
+        Transition::ALL
+            .into_iter()
+            .map(|transition| {
+                (0..HOLDERS)
+                    .prop_map(move |holder| LifecycleStep { holder, transition })
+                    .boxed()
+            })
+            .collect()
+    }
+
+    fn preconditions_met(&self, _step: &LifecycleStep) -> bool {
+        true
+    }
+
+    fn next_state(&mut self, step: &LifecycleStep) {
+        let state = &mut self.holders[step.holder];
+        if let Some(next) = state.transition(step.transition) {
+            *state = next;
+        }
+    }
+
+    async fn init_test_run(&self) -> LifecycleBank {
+        LifecycleBank::new().await
+    }
+
+    async fn run_op(&self, step: &LifecycleStep, bank: &mut LifecycleBank) {
+        let state = self.holders[step.holder];
+        let instructions = bank.instructions(step.holder, step.transition).await;
+        let result = bank.send(&instructions, &[]).await;
+        assert_eq!(
+            result.is_ok(),
+            state.accepts(step.transition),
+            "{:?} of holder {} in {:?}: program returned {:?}",
+            step.transition,
+            step.holder,
+            state,
+            result
+        );
+    }
+
+    async fn check_postconditions(&self, bank: &mut LifecycleBank) {
+        for (holder, expected) in self.holders.iter().enumerate() {
+            let (frozen, allowed) = bank.observe(holder).await;
+            let observed = HolderState::observe(frozen, allowed);
+            assert_eq!(
+                observed,
+                Some(*expected),
+                "holder {} is frozen={} allowed={}",
+                holder,
+                frozen,
+                allowed
+            );
+            OBSERVED.with(|states| states.borrow_mut().insert(*expected));
+        }
+    }
+
+    async fn clean_up_test_run(&self, _bank: &mut LifecycleBank) {}
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    payload
+        .downcast_ref::<String>()
+        .cloned()
+        .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+        .unwrap_or_else(|| "property test panicked".to_string())
+}
+
+/// Test 1: The transition table matches the documented lifecycle
+#[test]
+fn test_lifecycle_transition_table() {
+    let report = run_transition_table_test();
+    assert!(
+        report.passed,
+        "Lifecycle transition table test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_transition_table_test() -> TestResultReport {
+    let test_name = "Lifecycle Transition Table";
+    let mut assertions = 0;
+
+    use HolderState::*;
+    use Transition::*;
+    let table = [
+        (Active, Freeze, Some(Frozen)),
+        (Active, Thaw, None),
+        (Active, Revoke, Some(Revoked)),
+        (Frozen, Freeze, None),
+        (Frozen, Thaw, Some(Active)),
+        (Frozen, Revoke, Some(Revoked)),
+        (Revoked, Freeze, None),
+        (Revoked, Thaw, None),
+        (Revoked, Revoke, Some(Revoked)),
+    ];
+
+    // Assertions 1-9: Every state/transition pair
+    for (state, transition, expected) in table {
+        assertions += 1;
+        if state.transition(transition) != expected {
+            return TestResultReport::failure(
+                test_name,
+                format!("{:?} + {:?} should give {:?}", state, transition, expected),
+            );
+        }
+    }
+
+    // Assertion 10: Every state is reachable from a new holder
+    assertions += 1;
+    let reachable = reachable_states(Active);
+    if reachable != BTreeSet::from(HolderState::ALL) {
+        return TestResultReport::failure(test_name, format!("Reachable: {:?}", reachable));
+    }
+
+    // Assertion 11: Nothing leaves Revoked
+    assertions += 1;
+    if reachable_states(Revoked) != BTreeSet::from([Revoked]) {
+        return TestResultReport::failure(test_name, "Revoked is not terminal".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: Generated transition sequences against the deployed programs
+/// reach exactly the model's states
+#[test]
+fn test_lifecycle_model_against_programs() {
+    let report = run_lifecycle_model_test();
+    assert!(
+        report.passed,
+        "Lifecycle model test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_lifecycle_model_test() -> TestResultReport {
+    let test_name = "Lifecycle Model Against Programs";
+    let mut assertions = 0;
+
+    OBSERVED.with(|states| states.borrow_mut().clear());
+    let config = ProptestStatefulConfig {
+        min_ops: 5,
+        max_ops: 20,
+        test_case_timeout: Duration::from_secs(120),
+        proptest_config: ProptestConfig {
+            cases: 12,
+            failure_persistence: None,
+            ..ProptestConfig::default()
+        },
+    };
+
+    // Assertion 1: Every generated sequence is accepted and rejected
+    // step for step as the model predicts, with matching states
+    assertions += 1;
+    if let Err(payload) = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        proptest_stateful::test::<FreezeLifecycle>(config)
+    })) {
+        return TestResultReport::failure(test_name, panic_message(payload));
+    }
+
+    // Assertion 2: The states reached on-chain are the model's reachable
+    // states
+    assertions += 1;
+    let observed = OBSERVED.with(|states| states.borrow().clone());
+    let reachable = reachable_states(HolderState::Active);
+    if observed != reachable {
+        return TestResultReport::failure(
+            test_name,
+            format!("Observed {:?}, model reaches {:?}", observed, reachable),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate lifecycle model test report
+#[test]
+fn generate_lifecycle_model_report() {
+    let results = vec![run_transition_table_test(), run_lifecycle_model_test()];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Freeze Lifecycle Model Test Results",
+        "../../tests/reports/lifecycle_model.md",
+    ) {
+        panic!("Failed to generate lifecycle model report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} lifecycle model tests failed", failed);
+}
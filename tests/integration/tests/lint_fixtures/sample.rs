@@ -0,0 +1,63 @@
+// Sample test sources for the literal assertion lint; read as text, never
+// compiled.
+
+fn run_literal_only_test() -> TestResultReport {
+    let test_name = "Literal Only";
+    let mut assertion_count = 0;
+
+    // Assertion 1: Bound to a literal
+    assertion_count += 1;
+    let reentrancy_prevented = true; // Should be prevented
+    if !reentrancy_prevented {
+        return TestResultReport::failure(test_name, "Reentrancy".to_string());
+    }
+
+    // Assertion 2: A literal expression, in an assertion macro
+    assertion_count += 1;
+    let limit = 10 * 2;
+    assert!(limit > 5, "limit was {}", limit);
+
+    TestResultReport::success(test_name, assertion_count)
+}
+
+fn run_mixed_test() -> TestResultReport {
+    let test_name = "Mixed";
+    let mut assertions = 0;
+
+    // Assertion 1: Reads the code under test
+    assertions += 1;
+    let record = load_record();
+    if !record.allowed || record.user != expected_user() {
+        return TestResultReport::failure(test_name, "Record".to_string());
+    }
+
+    // Assertion 2: Only literals, with a string literal that looks like code
+    assertions += 1;
+    let enforced = false;
+    if enforced
+        && "record.allowed" != ""
+    {
+        return TestResultReport::failure(test_name, "Enforced".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+async fn run_executable_test() -> TestResultReport {
+    let test_name = "Executable";
+    let mut assertions = 0;
+
+    // Assertion 1: A literal bound but compared with a result
+    assertions += 1;
+    let max_length = 50;
+    if is_valid_input(&"a".repeat(max_length + 1)) || c != '_' {
+        return TestResultReport::failure(test_name, "Length".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+fn helper_without_assertions() -> bool {
+    let always = true;
+    always
+}
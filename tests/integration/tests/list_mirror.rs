@@ -0,0 +1,556 @@
+//! Off-chain list mirror tests
+//!
+//! These tests run random bursts of adds, removes and closes against the
+//! production allow list, and of block list changes written the way the
+//! issuer's tooling would, then feed the resulting account updates to a
+//! `ListMirror`. Whatever order the updates arrive in, duplicated or with
+//! closures never notified, the mirror must converge on what the bank
+//! holds.
+
+use example_block_list::{create_block_list_record, BlockReason};
+use futures::stream;
+use gate_view::{AllowListView, BlockListView};
+use production_allow_list::{
+    config_address, ADD_TO_ALLOW_LIST, INITIALIZE, REMOVE_FROM_ALLOW_LIST,
+};
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    clock::Slot,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::Transaction,
+};
+
+use token_acl_integration_tests::{
+    mirror::{AccountUpdate, ListMirror, MirrorChange, MirroredGates},
+    reporting,
+    sequences::{random_sequence, Operation, SequenceRng},
+    TestResultReport,
+};
+
+/// Users the bursts are spread over
+const USERS: u8 = 8;
+
+/// A bank with both gates for one mint, recording every account update the
+/// operations cause
+struct MirrorFixture {
+    context: ProgramTestContext,
+    allow_list: Pubkey,
+    block_list: Pubkey,
+    issuer: Keypair,
+    mint: Pubkey,
+    users: Vec<Pubkey>,
+    slot: Slot,
+    updates: Vec<AccountUpdate>,
+}
+
+impl MirrorFixture {
+    async fn new() -> Self {
+        let allow_list = Pubkey::new_unique();
+        let block_list = Pubkey::new_unique();
+        let mut program_test = ProgramTest::new(
+            "production_allow_list",
+            allow_list,
+            processor!(production_allow_list::process_instruction),
+        );
+        program_test.add_program(
+            "example_block_list",
+            block_list,
+            processor!(example_block_list::process_instruction),
+        );
+
+        let mut fixture = Self {
+            context: program_test.start_with_context().await,
+            allow_list,
+            block_list,
+            issuer: Keypair::new(),
+            mint: Pubkey::new_unique(),
+            users: (0..USERS).map(|_| Pubkey::new_unique()).collect(),
+            slot: 0,
+            updates: Vec::new(),
+        };
+        let initialize = Instruction::new_with_bytes(
+            allow_list,
+            &[INITIALIZE],
+            vec![
+                AccountMeta::new(fixture.config(), false),
+                AccountMeta::new_readonly(fixture.mint, false),
+                AccountMeta::new_readonly(fixture.issuer.pubkey(), true),
+                AccountMeta::new(fixture.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        fixture
+            .send(initialize)
+            .await
+            .expect("setup should succeed");
+        fixture
+    }
+
+    fn gates(&self) -> MirroredGates {
+        MirroredGates {
+            allow_list_program: Some(self.allow_list),
+            block_list_program: Some(self.block_list),
+        }
+    }
+
+    fn config(&self) -> Pubkey {
+        config_address(&self.mint, &self.allow_list).address()
+    }
+
+    fn allow_list_record(&self, user: &Pubkey) -> Pubkey {
+        gate_view::allow_list_address(&self.allow_list, &self.mint, user).address()
+    }
+
+    fn block_list_record(&self, user: &Pubkey) -> Pubkey {
+        gate_view::block_list_address(&self.block_list, &self.mint, user).address()
+    }
+
+    /// Move to the next slot, so every operation lands in its own
+    async fn next_slot(&mut self) {
+        let root = self.context.banks_client.get_root_slot().await.unwrap();
+        self.slot = self.slot.max(root) + 1;
+        self.context.warp_to_slot(self.slot).unwrap();
+    }
+
+    async fn send(&mut self, instruction: Instruction) -> Result<(), String> {
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&self.context.payer.pubkey()),
+            &[&self.context.payer, &self.issuer],
+            blockhash,
+        );
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn account(&mut self, address: Pubkey) -> Option<Account> {
+        self.context
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .filter(|account| account.lamports > 0)
+    }
+
+    /// Record the state of `address` at the current slot
+    async fn record_update(&mut self, address: Pubkey) {
+        let update = match self.account(address).await {
+            Some(account) => AccountUpdate::from_account(address, self.slot, &account),
+            None => AccountUpdate::closed(address, self.slot),
+        };
+        self.updates.push(update);
+    }
+
+    /// Run one operation in its own slot
+    ///
+    /// Allow list operations go through the program; a failed one (adding a
+    /// user twice, removing a missing one) changes nothing. `CheckThaw`
+    /// stands in for the issuer's block list tooling and moves the user's
+    /// block list record along missing, blocked, unblocked, closed.
+    async fn run(&mut self, operation: Operation) {
+        self.next_slot().await;
+        let user = self.users[usize::from(operation.user())];
+        let payer = self.context.payer.pubkey();
+        let record = self.allow_list_record(&user);
+        let instruction = match operation {
+            Operation::Add { .. } => Instruction::new_with_bytes(
+                self.allow_list,
+                &[ADD_TO_ALLOW_LIST],
+                vec![
+                    AccountMeta::new_readonly(self.config(), false),
+                    AccountMeta::new(record, false),
+                    AccountMeta::new_readonly(self.mint, false),
+                    AccountMeta::new_readonly(user, false),
+                    AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                    AccountMeta::new(payer, true),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            ),
+            Operation::Remove { .. } | Operation::Close { .. } => {
+                let close = matches!(operation, Operation::Close { .. });
+                let mut accounts = vec![
+                    AccountMeta::new_readonly(self.config(), false),
+                    AccountMeta::new(record, false),
+                    AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                ];
+                if close {
+                    accounts.push(AccountMeta::new(payer, false));
+                }
+                Instruction::new_with_bytes(
+                    self.allow_list,
+                    &[REMOVE_FROM_ALLOW_LIST, u8::from(close)],
+                    accounts,
+                )
+            }
+            Operation::CheckThaw { .. } => {
+                self.toggle_block(user).await;
+                return;
+            }
+        };
+        if self.send(instruction).await.is_ok() {
+            self.record_update(record).await;
+        }
+    }
+
+    async fn toggle_block(&mut self, user: Pubkey) {
+        let address = self.block_list_record(&user);
+        let next = match self.account(address).await {
+            None => Some(true),
+            Some(account) => match BlockListView::unpack(&account.data) {
+                Ok(view) if view.blocked => Some(false),
+                _ => None,
+            },
+        };
+        let account = match next {
+            Some(blocked) => {
+                let mut record =
+                    create_block_list_record(&self.mint, &user, BlockReason::Compliance, 0);
+                record.blocked = blocked;
+                let data = borsh::to_vec(&record).unwrap();
+                let mut account = AccountSharedData::new(
+                    Rent::default().minimum_balance(data.len()),
+                    data.len(),
+                    &self.block_list,
+                );
+                account.set_data_from_slice(&data);
+                account
+            }
+            None => AccountSharedData::new(0, 0, &system_program::id()),
+        };
+        self.context.set_account(&address, &account);
+        self.record_update(address).await;
+    }
+
+    /// Both records of every user, as the bank holds them
+    async fn truth(&mut self) -> Vec<(Option<AllowListView>, Option<BlockListView>)> {
+        let mut truth = Vec::new();
+        for user in self.users.clone() {
+            let allow = self
+                .account(self.allow_list_record(&user))
+                .await
+                .map(|account| AllowListView::unpack(&account.data).unwrap());
+            let block = self
+                .account(self.block_list_record(&user))
+                .await
+                .map(|account| BlockListView::unpack(&account.data).unwrap());
+            truth.push((allow, block));
+        }
+        truth
+    }
+
+    /// The slot of the last recorded update; a failed operation moves the
+    /// bank on without one
+    fn last_update_slot(&self) -> Slot {
+        self.updates.last().map_or(0, |update| update.slot)
+    }
+
+    /// Every existing record account, as `getProgramAccounts` would list
+    async fn snapshot(&mut self) -> Vec<(Pubkey, Account)> {
+        let mut snapshot = Vec::new();
+        for user in self.users.clone() {
+            for address in [self.allow_list_record(&user), self.block_list_record(&user)] {
+                if let Some(account) = self.account(address).await {
+                    snapshot.push((address, account));
+                }
+            }
+        }
+        snapshot
+    }
+}
+
+/// Where the mirror disagrees with the bank, if anywhere
+fn divergence(
+    mirror: &ListMirror,
+    users: &[Pubkey],
+    truth: &[(Option<AllowListView>, Option<BlockListView>)],
+) -> Option<String> {
+    for (user, (allow, block)) in users.iter().zip(truth) {
+        let mirrored = (
+            mirror.allow_list_record(user).unwrap(),
+            mirror.block_list_record(user).unwrap(),
+        );
+        if mirrored != (*allow, *block) {
+            return Some(format!(
+                "user {}: mirror holds {:?}, bank holds {:?}",
+                user,
+                mirrored,
+                (allow, block)
+            ));
+        }
+    }
+    None
+}
+
+/// Run `bursts` bursts of `len` random operations, returning the fixture
+/// with every update recorded
+async fn run_bursts(seed: u64, bursts: u64, len: usize) -> MirrorFixture {
+    let mut fixture = MirrorFixture::new().await;
+    for burst in 0..bursts {
+        for operation in random_sequence(seed + burst, len, USERS) {
+            fixture.run(operation).await;
+        }
+    }
+    fixture
+}
+
+fn temporary_db() -> sled::Db {
+    sled::Config::new().temporary(true).open().unwrap()
+}
+
+/// Test 1: Updates applied in order converge on the bank
+#[tokio::test]
+async fn test_mirror_converges_in_order() {
+    let report = run_converges_in_order_test().await;
+    assert!(
+        report.passed,
+        "In-order convergence test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_converges_in_order_test() -> TestResultReport {
+    let test_name = "List Mirror In-Order Convergence";
+    let mut assertions = 0;
+    let mut fixture = run_bursts(3724, 3, 24).await;
+    let mirror = ListMirror::open(&temporary_db(), fixture.mint, fixture.gates()).unwrap();
+
+    // Assertion 1: Every burst produced changes for the mirror to apply
+    assertions += 1;
+    let changes = mirror
+        .sync(stream::iter(fixture.updates.clone()))
+        .await
+        .unwrap();
+    let removed = changes
+        .iter()
+        .filter(|change| matches!(change, MirrorChange::Removed(..)))
+        .count();
+    if changes.is_empty() || removed == 0 {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Bursts too tame: {} changes, {} removals",
+                changes.len(),
+                removed
+            ),
+        );
+    }
+
+    // Assertion 2: The mirror holds exactly the bank's records
+    assertions += 1;
+    let truth = fixture.truth().await;
+    if let Some(divergence) = divergence(&mirror, &fixture.users, &truth) {
+        return TestResultReport::failure(test_name, divergence);
+    }
+
+    // Assertion 3: Membership queries agree with the records
+    assertions += 1;
+    let allowed: Vec<_> = fixture
+        .users
+        .iter()
+        .zip(&truth)
+        .filter(|(_, (allow, _))| allow.is_some_and(|record| record.is_active(0)))
+        .map(|(user, _)| *user)
+        .collect();
+    let mut mirrored = mirror.allow_listed(0).unwrap();
+    mirrored.sort();
+    let mut expected = allowed.clone();
+    expected.sort();
+    if mirrored != expected
+        || allowed
+            .iter()
+            .any(|user| !mirror.is_allow_listed(user, 0).unwrap())
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Allow-listed owners {:?}, expected {:?}",
+                mirrored, expected
+            ),
+        );
+    }
+
+    // Assertion 4: The mirror is at the slot of the last update
+    assertions += 1;
+    if mirror.slot().unwrap() != fixture.last_update_slot() {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Mirror at slot {}, last update at {}",
+                mirror.slot().unwrap(),
+                fixture.last_update_slot()
+            ),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: Late, duplicated and missing notifications still converge
+#[tokio::test]
+async fn test_mirror_converges_out_of_order() {
+    let report = run_converges_out_of_order_test().await;
+    assert!(
+        report.passed,
+        "Out-of-order convergence test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_converges_out_of_order_test() -> TestResultReport {
+    let test_name = "List Mirror Out-of-Order Convergence";
+    let mut assertions = 0;
+    let mut fixture = run_bursts(9001, 3, 24).await;
+    let truth = fixture.truth().await;
+
+    // Assertions 1-3: Shuffled deliveries with every third update repeated
+    for seed in 0..3 {
+        assertions += 1;
+        let mut rng = SequenceRng::new(seed);
+        let mut updates = fixture.updates.clone();
+        for i in (1..updates.len()).rev() {
+            updates.swap(i, rng.below(i as u64 + 1) as usize);
+        }
+        let repeats: Vec<_> = updates.iter().step_by(3).cloned().collect();
+        updates.extend(repeats);
+
+        let mirror = ListMirror::open(&temporary_db(), fixture.mint, fixture.gates()).unwrap();
+        mirror.sync(stream::iter(updates)).await.unwrap();
+        if let Some(divergence) = divergence(&mirror, &fixture.users, &truth) {
+            return TestResultReport::failure(
+                test_name,
+                format!("Shuffle {}: {}", seed, divergence),
+            );
+        }
+    }
+
+    // Assertion 4: Without closure notifications, as over programSubscribe,
+    // a reconciliation against a snapshot removes what was closed
+    assertions += 1;
+    let notified: Vec<_> = fixture
+        .updates
+        .iter()
+        .filter(|update| !update.data.is_empty())
+        .cloned()
+        .collect();
+    let mirror = ListMirror::open(&temporary_db(), fixture.mint, fixture.gates()).unwrap();
+    mirror.sync(stream::iter(notified)).await.unwrap();
+    let snapshot = fixture.snapshot().await;
+    let changes = mirror.reconcile(fixture.slot, &snapshot).unwrap();
+    if !changes
+        .iter()
+        .any(|change| matches!(change, MirrorChange::Removed(..)))
+    {
+        return TestResultReport::failure(
+            test_name,
+            "No missed closure was found by reconciliation".to_string(),
+        );
+    }
+    if let Some(divergence) = divergence(&mirror, &fixture.users, &truth) {
+        return TestResultReport::failure(test_name, format!("After reconcile: {}", divergence));
+    }
+
+    // Assertion 5: An update older than the reconciliation is dropped
+    assertions += 1;
+    let oldest = fixture.updates[0].clone();
+    if mirror.apply(&oldest).unwrap() != MirrorChange::Stale {
+        return TestResultReport::failure(
+            test_name,
+            "An update from before the reconciliation was applied".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: The mirror survives a restart, and mints don't share state
+#[tokio::test]
+async fn test_mirror_persists() {
+    let report = run_persistence_test().await;
+    assert!(report.passed, "Persistence test failed: {:?}", report.error);
+}
+
+async fn run_persistence_test() -> TestResultReport {
+    let test_name = "List Mirror Persistence";
+    let mut assertions = 0;
+    let mut fixture = run_bursts(77, 1, 32).await;
+    let truth = fixture.truth().await;
+    let path = std::env::temp_dir().join(format!("token_acl_mirror_{}", Pubkey::new_unique()));
+    let _ = std::fs::remove_dir_all(&path);
+
+    {
+        let db = sled::open(&path).unwrap();
+        let mirror = ListMirror::open(&db, fixture.mint, fixture.gates()).unwrap();
+        mirror
+            .sync(stream::iter(fixture.updates.clone()))
+            .await
+            .unwrap();
+        db.flush().unwrap();
+    }
+
+    let db = sled::open(&path).unwrap();
+
+    // Assertion 1: A reopened mirror answers as before, from the same slot
+    assertions += 1;
+    let mirror = ListMirror::open(&db, fixture.mint, fixture.gates()).unwrap();
+    if let Some(divergence) = divergence(&mirror, &fixture.users, &truth) {
+        return TestResultReport::failure(test_name, format!("After reopening: {}", divergence));
+    }
+    if mirror.slot().unwrap() != fixture.last_update_slot() {
+        return TestResultReport::failure(test_name, "Slot was not persisted".to_string());
+    }
+
+    // Assertion 2: Another mint's mirror in the same database is empty, and
+    // ignores this mint's records
+    assertions += 1;
+    let other = ListMirror::open(&db, Pubkey::new_unique(), fixture.gates()).unwrap();
+    let changes = other
+        .sync(stream::iter(fixture.updates.clone()))
+        .await
+        .unwrap();
+    let other_records =
+        other.allow_list_records().unwrap().len() + other.block_list_records().unwrap().len();
+    if other_records != 0
+        || changes
+            .iter()
+            .any(|change| !matches!(change, MirrorChange::Removed(..)))
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!("Another mint's mirror picked up {} records", other_records),
+        );
+    }
+
+    drop(db);
+    let _ = std::fs::remove_dir_all(&path);
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate list mirror test report
+#[tokio::test]
+async fn generate_list_mirror_report() {
+    let results = vec![
+        run_converges_in_order_test().await,
+        run_converges_out_of_order_test().await,
+        run_persistence_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Off-Chain List Mirror Test Results",
+        "../../tests/reports/list_mirror.md",
+    ) {
+        panic!("Failed to generate list mirror report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} list mirror tests failed", failed);
+}
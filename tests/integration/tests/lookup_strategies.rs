@@ -0,0 +1,301 @@
+//! Record lookup strategies comparison benchmark
+//!
+//! Prices per-user PDAs, Merkle proofs, bloom filters and compressed
+//! accounts for membership checks on lists of 1k, 100k and 10M wallets, and
+//! writes the decision matrix. The models are checked against the real
+//! thing where it fits: one allow list add on a bank for the per-user PDA
+//! rent and fee, and in-memory Merkle trees and bloom filters at 1k and
+//! 100k wallets for proof sizes and false-positive rates.
+
+use production_allow_list::{allow_list_address, config_address, ADD_TO_ALLOW_LIST, INITIALIZE};
+use solana_program_test::{processor, ProgramTest};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::Signer,
+    system_program,
+    transaction::Transaction,
+};
+
+use token_acl_integration_tests::{
+    lookups::{self, BloomFilter, LookupCost, MerkleTree},
+    reporting,
+    suite_config::{self, SuiteCategory},
+    TestResultReport,
+};
+
+/// List sizes compared
+const LIST_SIZES: [u64; 3] = [1_000, 100_000, 10_000_000];
+
+/// List sizes small enough to build in memory
+const BUILT_LIST_SIZES: [u64; 2] = [1_000, 100_000];
+
+/// Bloom filters are sized for one false positive in a million checks
+const FALSE_POSITIVE_RATE: f64 = 1e-6;
+
+/// Non-members probed against each bloom filter
+const BLOOM_PROBES: usize = 10_000;
+
+/// Proofs verified per Merkle tree
+const MERKLE_SAMPLES: usize = 100;
+
+fn wallets(count: u64) -> Vec<Pubkey> {
+    (0..count).map(|_| Pubkey::new_unique()).collect()
+}
+
+/// Add one wallet to a fresh allow list, paid and signed by the authority
+/// alone, and return what it cost and the record's size
+async fn measure_user_pda_add() -> Result<(u64, usize), String> {
+    let gate = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let user = Pubkey::new_unique();
+    let program_test = ProgramTest::new(
+        "production_allow_list",
+        gate,
+        processor!(production_allow_list::process_instruction),
+    );
+    let (mut banks_client, payer, blockhash) = program_test.start().await;
+    let config = config_address(&mint, &gate).address();
+    let record = allow_list_address(&mint, &user, &gate).address();
+
+    let initialize = Instruction::new_with_bytes(
+        gate,
+        &[INITIALIZE],
+        vec![
+            AccountMeta::new(config, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(payer.pubkey(), true),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let add = Instruction::new_with_bytes(
+        gate,
+        &[ADD_TO_ALLOW_LIST],
+        vec![
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(record, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(user, false),
+            AccountMeta::new_readonly(payer.pubkey(), true),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &[initialize],
+        Some(&payer.pubkey()),
+        &[&payer],
+        blockhash,
+    );
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| format!("Initialize failed: {}", e))?;
+
+    let before = banks_client
+        .get_balance(payer.pubkey())
+        .await
+        .map_err(|e| e.to_string())?;
+    let transaction =
+        Transaction::new_signed_with_payer(&[add], Some(&payer.pubkey()), &[&payer], blockhash);
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| format!("Add failed: {}", e))?;
+    let after = banks_client
+        .get_balance(payer.pubkey())
+        .await
+        .map_err(|e| e.to_string())?;
+    let record_size = banks_client
+        .get_account(record)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("Record was not created")?
+        .data
+        .len();
+    Ok((before - after, record_size))
+}
+
+/// Build a tree over `size` wallets and check sampled proofs against the
+/// model's depth
+fn check_merkle_tree(size: u64) -> Result<(), String> {
+    let wallets = wallets(size);
+    let tree = MerkleTree::new(
+        wallets
+            .iter()
+            .map(|w| lookups::merkle_leaf(w.as_ref()))
+            .collect(),
+    );
+    let root = tree.root();
+    let depth = lookups::merkle_depth(size) as usize;
+    let step = (size as usize / MERKLE_SAMPLES).max(1);
+    for index in (0..wallets.len()).step_by(step) {
+        let proof = tree.proof(index);
+        if proof.len() > depth {
+            return Err(format!(
+                "Proof of leaf {} has {} nodes, model says {}",
+                index,
+                proof.len(),
+                depth
+            ));
+        }
+        if !MerkleTree::verify(&root, lookups::merkle_leaf(wallets[index].as_ref()), &proof) {
+            return Err(format!("Proof of leaf {} does not verify", index));
+        }
+        let outsider = Pubkey::new_unique();
+        if MerkleTree::verify(&root, lookups::merkle_leaf(outsider.as_ref()), &proof) {
+            return Err(format!("Leaf {}'s proof verifies an outsider", index));
+        }
+    }
+    if tree.proof(0).len() != depth {
+        return Err(format!(
+            "Proof of leaf 0 has {} nodes, model says {}",
+            tree.proof(0).len(),
+            depth
+        ));
+    }
+    Ok(())
+}
+
+/// Fill a filter with `size` wallets and probe it with outsiders
+fn check_bloom_filter(size: u64, model: &LookupCost) -> Result<(), String> {
+    let members = wallets(size);
+    let mut filter = BloomFilter::with_capacity(size, FALSE_POSITIVE_RATE);
+    for member in &members {
+        filter.insert(member.as_ref());
+    }
+    if let Some(member) = members
+        .iter()
+        .find(|member| !filter.contains(member.as_ref()))
+    {
+        return Err(format!("Member {} is missing from the filter", member));
+    }
+    let false_positives = (0..BLOOM_PROBES)
+        .filter(|_| filter.contains(Pubkey::new_unique().as_ref()))
+        .count();
+    // One in a million expected; more than one in ten thousand is a bug
+    if false_positives > 1 {
+        return Err(format!(
+            "{} of {} outsiders passed the filter",
+            false_positives, BLOOM_PROBES
+        ));
+    }
+    if Rent::default().minimum_balance(filter.size()) != model.rent {
+        return Err(format!(
+            "Filter of {} bytes does not match the modelled rent {}",
+            filter.size(),
+            model.rent
+        ));
+    }
+    Ok(())
+}
+
+/// Compare lookup strategies and write the decision matrix
+#[tokio::test]
+async fn benchmark_record_lookup_strategies() {
+    let (report, costs) = run_lookup_strategies_benchmark().await;
+
+    let mut markdown = reporting::render_test_report(
+        std::slice::from_ref(&report),
+        "Record Lookup Strategies Results",
+        &suite_config::current().disabled(),
+    );
+    if !costs.is_empty() {
+        let lookups = lookups::render_lookup_report(&costs);
+        println!("{}", lookups);
+        markdown.push_str(&lookups);
+    }
+    std::fs::create_dir_all("../../tests/reports").ok();
+    if let Err(e) = std::fs::write("../../tests/reports/lookup_strategies.md", markdown) {
+        panic!("Failed to generate lookup strategies report: {}", e);
+    }
+
+    assert!(
+        report.passed,
+        "Lookup strategies benchmark failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_lookup_strategies_benchmark() -> (TestResultReport, Vec<Vec<LookupCost>>) {
+    let test_name = "Record Lookup Strategies";
+    let mut assertions = 0;
+    if let Some(skipped) = suite_config::skip_unless(SuiteCategory::LoadTests, test_name) {
+        return (skipped, Vec::new());
+    }
+
+    // Assertion 1: Adding a wallet costs what the per-user PDA model says
+    assertions += 1;
+    let (add_cost, record_size) = match measure_user_pda_add().await {
+        Ok(measured) => measured,
+        Err(e) => return (TestResultReport::failure(test_name, e), Vec::new()),
+    };
+    let costs: Vec<_> = LIST_SIZES
+        .iter()
+        .map(|&size| lookups::lookup_costs(size, record_size, FALSE_POSITIVE_RATE))
+        .collect();
+    if costs[0][0].update_lamports != add_cost {
+        return (
+            TestResultReport::failure(
+                test_name,
+                format!(
+                    "Adding a wallet cost {} lamports, model says {}",
+                    add_cost, costs[0][0].update_lamports
+                ),
+            ),
+            costs,
+        );
+    }
+
+    // Assertion 2: Real trees prove membership within the modelled depth
+    assertions += 1;
+    for size in BUILT_LIST_SIZES {
+        if let Err(e) = check_merkle_tree(size) {
+            return (
+                TestResultReport::failure(test_name, format!("{} wallets: {}", size, e)),
+                costs,
+            );
+        }
+    }
+
+    // Assertion 3: Real filters have no false negatives, the modelled size,
+    // and about the modelled false-positive rate
+    assertions += 1;
+    for (size, size_costs) in BUILT_LIST_SIZES.iter().zip(&costs) {
+        if let Err(e) = check_bloom_filter(*size, &size_costs[2]) {
+            return (
+                TestResultReport::failure(test_name, format!("{} wallets: {}", size, e)),
+                costs,
+            );
+        }
+    }
+
+    // Assertion 4: A 10M-wallet bloom filter no longer fits in one account,
+    // and every exact strategy still fits the thaw transaction
+    assertions += 1;
+    let largest = costs.last().unwrap();
+    let unbounded = largest
+        .iter()
+        .filter(|cost| cost.false_positive_rate == 0.0)
+        .find(|cost| cost.limit.is_some());
+    if largest[2].limit.is_none() || unbounded.is_some() {
+        return (
+            TestResultReport::failure(
+                test_name,
+                format!(
+                    "Unexpected ceilings at {} wallets: {:?}",
+                    LIST_SIZES[2],
+                    largest
+                        .iter()
+                        .map(|cost| (cost.strategy.name(), &cost.limit))
+                        .collect::<Vec<_>>()
+                ),
+            ),
+            costs,
+        );
+    }
+
+    (TestResultReport::success(test_name, assertions), costs)
+}
@@ -0,0 +1,970 @@
+//! NFT-gated thaw tests
+//!
+//! The NFT gate lets holders of an NFT collection thaw their token accounts
+//! of a permissioned mint, as with a membership pass. The fixtures mint a
+//! test collection the way a creator would with Metaplex: a collection NFT,
+//! then member NFTs under SPL Token and Token-2022 whose metadata names the
+//! collection. Metaplex itself is not deployed, so the metadata accounts are
+//! written at their PDAs as Metaplex would lay them out, verified or not.
+//! Checks name the NFT account, its mint and its metadata, and resolve the
+//! gate's config from the mint's thaw extra-account-metas list.
+//!
+//! Every check runs in a transaction with the thaw it authorizes, the issuer
+//! standing in for the FAMP, so a denial leaves the account frozen.
+
+use borsh::BorshSerialize;
+use extra_account_metas::resolve_extra_accounts;
+use futures::executor::block_on;
+use nft_gate::{
+    config_address, initialize_thaw_extra_account_metas, metadata_address, Collection, Metadata,
+    MetadataData, INITIALIZE, METADATA_PROGRAM_ID, METADATA_V1_KEY, SET_COLLECTION,
+    SPL_TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID,
+};
+use solana_program_test::processor;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    system_instruction, system_program,
+    transaction::TransactionError,
+};
+use spl_token_2022::{
+    instruction as token_instruction,
+    state::{Account as TokenAccount, AccountState, Mint},
+};
+use token_acl_integration_tests::{
+    program_test::rent_exempt_account, reporting, svm::Executed, SvmHarness, TestResultReport,
+};
+use token_acl_interface::{
+    self as interface, find_freeze_extra_account_metas_address,
+    find_thaw_extra_account_metas_address,
+};
+
+/// Size Metaplex allocates a metadata account at
+const MAX_METADATA_LEN: usize = 679;
+
+/// Metaplex `TokenStandard::NonFungible`
+const NON_FUNGIBLE: u8 = 0;
+
+/// Metaplex `TokenStandard::FungibleAsset`
+const FUNGIBLE_ASSET: u8 = 1;
+
+/// An NFT and the token account holding it
+#[derive(Debug, Clone, Copy)]
+struct Nft {
+    token_program: Pubkey,
+    mint: Pubkey,
+    token_account: Pubkey,
+}
+
+impl Nft {
+    fn metadata(&self) -> Pubkey {
+        metadata_address(&self.mint)
+    }
+
+    /// The NFT as a check names it
+    fn pass(&self) -> Pass {
+        Pass {
+            nft_account: self.token_account,
+            nft_mint: self.mint,
+            metadata: self.metadata(),
+        }
+    }
+}
+
+/// The accounts a thaw check names as the holder's pass
+#[derive(Debug, Clone, Copy)]
+struct Pass {
+    nft_account: Pubkey,
+    nft_mint: Pubkey,
+    metadata: Pubkey,
+}
+
+/// How many tokens a mint issues, and in how many decimals
+#[derive(Debug, Clone, Copy)]
+struct Supply {
+    amount: u64,
+    decimals: u8,
+}
+
+impl Supply {
+    /// One indivisible token
+    const NFT: Self = Self {
+        amount: 1,
+        decimals: 0,
+    };
+}
+
+/// A holder of the gated mint, frozen until they thaw
+struct Holder {
+    owner: Keypair,
+    token_account: Pubkey,
+}
+
+/// A bank with the NFT gate, a frozen permissioned mint and a test
+/// collection its config names
+struct NftBank {
+    svm: SvmHarness,
+    gate: Pubkey,
+    /// Freeze authority of the mint and authority of the gate's config
+    issuer: Keypair,
+    /// Mints the collection's NFTs and verifies them as members
+    creator: Keypair,
+    mint: Pubkey,
+    /// The collection NFT, held by the creator
+    collection: Nft,
+    /// NFTs minted so far, which number their names
+    minted: usize,
+}
+
+impl NftBank {
+    fn start() -> Result<Self, String> {
+        let mut svm = SvmHarness::new();
+        let gate = Pubkey::new_unique();
+        svm.add_program("nft_gate", gate, processor!(nft_gate::process_instruction))?;
+        let issuer = Keypair::new();
+        let mint = svm
+            .create_mint(&issuer.pubkey(), AccountState::Frozen)
+            .map_err(|e| format!("Creating the mint failed: {:?}", e))?;
+
+        // The collection NFT is minted through the bank, once it exists
+        let placeholder = Nft {
+            token_program: SPL_TOKEN_PROGRAM_ID,
+            mint: Pubkey::default(),
+            token_account: Pubkey::default(),
+        };
+        let mut bank = Self {
+            svm,
+            gate,
+            issuer,
+            creator: Keypair::new(),
+            mint,
+            collection: placeholder,
+            minted: 0,
+        };
+        bank.collection = bank.mint_collection()?;
+        let initialize = bank.initialize(&bank.collection.mint);
+        let list = initialize_thaw_extra_account_metas(&gate, &bank.svm.payer.pubkey(), &mint);
+        bank.svm
+            .send(&[initialize, list], &[&bank.issuer])
+            .map_err(|e| format!("Initializing the gate failed: {:?}", e))?;
+        Ok(bank)
+    }
+
+    fn config(&self) -> Pubkey {
+        config_address(&self.mint, &self.gate).address()
+    }
+
+    fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<Executed, TransactionError> {
+        self.svm.send(instructions, signers)
+    }
+
+    fn initialize(&self, collection: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            self.gate,
+            &[[INITIALIZE].as_slice(), collection.as_ref()].concat(),
+            vec![
+                AccountMeta::new(self.config(), false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.svm.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// `authority`'s replacement of the mint's collection
+    fn set_collection(&self, collection: &Pubkey, authority: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            self.gate,
+            &[[SET_COLLECTION].as_slice(), collection.as_ref()].concat(),
+            vec![
+                AccountMeta::new(self.config(), false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(*authority, true),
+            ],
+        )
+    }
+
+    /// An empty token account of `nft_mint` for `owner`
+    fn nft_account(
+        &mut self,
+        token_program: &Pubkey,
+        nft_mint: &Pubkey,
+        owner: &Pubkey,
+    ) -> Result<Pubkey, String> {
+        let token_account = Keypair::new();
+        let instructions = [
+            system_instruction::create_account(
+                &self.svm.payer.pubkey(),
+                &token_account.pubkey(),
+                Rent::default().minimum_balance(TokenAccount::LEN),
+                TokenAccount::LEN as u64,
+                token_program,
+            ),
+            token_instruction::initialize_account3(
+                token_program,
+                &token_account.pubkey(),
+                nft_mint,
+                owner,
+            )
+            .map_err(|e| e.to_string())?,
+        ];
+        self.send(&instructions, &[&token_account])
+            .map_err(|e| format!("Creating an NFT account failed: {:?}", e))?;
+        Ok(token_account.pubkey())
+    }
+
+    /// Mint an NFT to `owner` under `token_program` and write its metadata,
+    /// in `collection` if any
+    fn mint_nft(
+        &mut self,
+        token_program: Pubkey,
+        owner: &Pubkey,
+        token_standard: u8,
+        collection: Option<Collection>,
+    ) -> Result<Nft, String> {
+        self.mint_token(
+            token_program,
+            owner,
+            Supply::NFT,
+            Some(token_standard),
+            collection,
+        )
+    }
+
+    /// Mint `supply` to `owner` under `token_program` and write its metadata,
+    /// with `token_standard` and in `collection` if any
+    fn mint_token(
+        &mut self,
+        token_program: Pubkey,
+        owner: &Pubkey,
+        supply: Supply,
+        token_standard: Option<u8>,
+        collection: Option<Collection>,
+    ) -> Result<Nft, String> {
+        let nft_mint = Keypair::new();
+        let creator = self.creator.pubkey();
+        let create_mint = [
+            system_instruction::create_account(
+                &self.svm.payer.pubkey(),
+                &nft_mint.pubkey(),
+                Rent::default().minimum_balance(Mint::LEN),
+                Mint::LEN as u64,
+                &token_program,
+            ),
+            token_instruction::initialize_mint2(
+                &token_program,
+                &nft_mint.pubkey(),
+                &creator,
+                None,
+                supply.decimals,
+            )
+            .map_err(|e| e.to_string())?,
+        ];
+        self.send(&create_mint, &[&nft_mint])
+            .map_err(|e| format!("Creating an NFT mint failed: {:?}", e))?;
+        let token_account = self.nft_account(&token_program, &nft_mint.pubkey(), owner)?;
+        let mint_to = token_instruction::mint_to(
+            &token_program,
+            &nft_mint.pubkey(),
+            &token_account,
+            &creator,
+            &[],
+            supply.amount,
+        )
+        .map_err(|e| e.to_string())?;
+        self.svm
+            .send(&[mint_to], &[&self.creator])
+            .map_err(|e| format!("Minting an NFT failed: {:?}", e))?;
+
+        self.minted += 1;
+        let metadata = Metadata {
+            key: METADATA_V1_KEY,
+            update_authority: creator,
+            mint: nft_mint.pubkey(),
+            data: MetadataData {
+                name: format!("Pass #{}", self.minted),
+                symbol: "PASS".to_string(),
+                uri: format!("https://example.com/pass/{}.json", self.minted),
+                seller_fee_basis_points: 0,
+                creators: None,
+            },
+            primary_sale_happened: false,
+            is_mutable: true,
+            edition_nonce: None,
+            token_standard,
+            collection,
+        };
+        let nft = Nft {
+            token_program,
+            mint: nft_mint.pubkey(),
+            token_account,
+        };
+        self.write_metadata(nft.metadata(), &metadata, METADATA_PROGRAM_ID)?;
+        Ok(nft)
+    }
+
+    /// Write `metadata` at `address` the way Metaplex lays it out, owned by
+    /// `owner`
+    fn write_metadata(
+        &mut self,
+        address: Pubkey,
+        metadata: &Metadata,
+        owner: Pubkey,
+    ) -> Result<(), String> {
+        let mut data = metadata.try_to_vec().map_err(|e| e.to_string())?;
+        data.resize(MAX_METADATA_LEN, 0);
+        self.svm
+            .set_account(address, rent_exempt_account(data, owner));
+        Ok(())
+    }
+
+    /// The collection NFT, which is in no collection itself
+    fn mint_collection(&mut self) -> Result<Nft, String> {
+        let creator = self.creator.pubkey();
+        self.mint_nft(SPL_TOKEN_PROGRAM_ID, &creator, NON_FUNGIBLE, None)
+    }
+
+    /// A member of the configured collection for `owner`, `verified` by the
+    /// creator or merely claiming the collection
+    fn mint_member(
+        &mut self,
+        token_program: Pubkey,
+        owner: &Pubkey,
+        verified: bool,
+    ) -> Result<Nft, String> {
+        let collection = Collection {
+            verified,
+            key: self.collection.mint,
+        };
+        self.mint_nft(token_program, owner, NON_FUNGIBLE, Some(collection))
+    }
+
+    fn holder(&mut self) -> Result<Holder, String> {
+        let owner = Keypair::new();
+        let token_account = self
+            .svm
+            .create_token_account(&self.mint, &owner.pubkey())
+            .map_err(|e| format!("Creating a token account failed: {:?}", e))?;
+        Ok(Holder {
+            owner,
+            token_account,
+        })
+    }
+
+    /// Move `nft` to a new account of `to`
+    fn transfer(&mut self, nft: &Nft, from: &Keypair, to: &Pubkey) -> Result<Nft, String> {
+        let token_account = self.nft_account(&nft.token_program, &nft.mint, to)?;
+        let transfer = token_instruction::transfer_checked(
+            &nft.token_program,
+            &nft.token_account,
+            &nft.mint,
+            &token_account,
+            &from.pubkey(),
+            &[],
+            1,
+            0,
+        )
+        .map_err(|e| e.to_string())?;
+        self.send(&[transfer], &[from])
+            .map_err(|e| format!("Transferring an NFT failed: {:?}", e))?;
+        Ok(Nft {
+            token_account,
+            ..*nft
+        })
+    }
+
+    /// The gate's thaw check for `holder`, naming `pass` as theirs and
+    /// resolving the rest from the mint's list
+    fn check(&self, holder: &Holder, pass: &Pass) -> Instruction {
+        let owner = holder.owner.pubkey();
+        let check = interface::can_thaw_permissionless(
+            &self.gate,
+            owner,
+            holder.token_account,
+            self.mint,
+            find_thaw_extra_account_metas_address(&self.mint, &self.gate).0,
+            &[],
+        );
+        let leading = [owner, pass.nft_account, pass.nft_mint, pass.metadata];
+        block_on(resolve_extra_accounts(check, &leading, |address| {
+            let data = self.svm.account(&address).map(|account| account.data);
+            async move { Ok(data) }
+        }))
+        .expect("the mint's thaw list resolves")
+    }
+
+    /// The check with `pass`, then the thaw it authorizes
+    fn thaw_with(&mut self, holder: &Holder, pass: &Pass) -> Result<Executed, TransactionError> {
+        let check = self.check(holder, pass);
+        let thaw = token_instruction::thaw_account(
+            &spl_token_2022::id(),
+            &holder.token_account,
+            &self.mint,
+            &self.issuer.pubkey(),
+            &[],
+        )
+        .expect("thaw instruction");
+        self.svm.send(&[check, thaw], &[&self.issuer])
+    }
+
+    fn thaw(&mut self, holder: &Holder, nft: &Nft) -> Result<Executed, TransactionError> {
+        self.thaw_with(holder, &nft.pass())
+    }
+
+    fn is_frozen(&self, holder: &Holder) -> bool {
+        self.svm.token_state(&holder.token_account) == Some(AccountState::Frozen)
+    }
+
+    /// Holders without a verified pass of the collection, each naming the
+    /// nearest thing to one they can get; `member_pass` is a member's pass
+    fn non_holders(&mut self, member_pass: &Nft) -> Result<Vec<Denial>, String> {
+        let mut denials = Vec::new();
+
+        let borrower = self.holder()?;
+        denials.push(Denial {
+            name: "borrowed pass",
+            holder: borrower,
+            pass: member_pass.pass(),
+        });
+
+        let claimant = self.holder()?;
+        let claimed = self.mint_member(SPL_TOKEN_PROGRAM_ID, &claimant.owner.pubkey(), false)?;
+        denials.push(Denial {
+            name: "unverified pass",
+            holder: claimant,
+            pass: claimed.pass(),
+        });
+
+        let outsider = self.holder()?;
+        let other_collection = self.mint_collection()?;
+        let other = self.mint_nft(
+            SPL_TOKEN_PROGRAM_ID,
+            &outsider.owner.pubkey(),
+            NON_FUNGIBLE,
+            Some(Collection {
+                verified: true,
+                key: other_collection.mint,
+            }),
+        )?;
+        denials.push(Denial {
+            name: "pass of another collection",
+            holder: outsider,
+            pass: other.pass(),
+        });
+
+        let collector = self.holder()?;
+        let loose = self.mint_nft(
+            TOKEN_2022_PROGRAM_ID,
+            &collector.owner.pubkey(),
+            NON_FUNGIBLE,
+            None,
+        )?;
+        denials.push(Denial {
+            name: "NFT in no collection",
+            holder: collector,
+            pass: loose.pass(),
+        });
+
+        let fungible_holder = self.holder()?;
+        let collection = Collection {
+            verified: true,
+            key: self.collection.mint,
+        };
+        let fungible = self.mint_nft(
+            SPL_TOKEN_PROGRAM_ID,
+            &fungible_holder.owner.pubkey(),
+            FUNGIBLE_ASSET,
+            Some(collection.clone()),
+        )?;
+        denials.push(Denial {
+            name: "fungible asset",
+            holder: fungible_holder,
+            pass: fungible.pass(),
+        });
+
+        // Legacy metadata names no standard, so only the mint tells a
+        // fungible token from an NFT
+        let legacy_holder = self.holder()?;
+        let legacy = self.mint_token(
+            SPL_TOKEN_PROGRAM_ID,
+            &legacy_holder.owner.pubkey(),
+            Supply {
+                amount: 1_000,
+                decimals: 0,
+            },
+            None,
+            Some(collection.clone()),
+        )?;
+        denials.push(Denial {
+            name: "legacy fungible token",
+            holder: legacy_holder,
+            pass: legacy.pass(),
+        });
+
+        let divisible_holder = self.holder()?;
+        let divisible = self.mint_token(
+            TOKEN_2022_PROGRAM_ID,
+            &divisible_holder.owner.pubkey(),
+            Supply {
+                amount: 1,
+                decimals: 6,
+            },
+            Some(NON_FUNGIBLE),
+            Some(collection.clone()),
+        )?;
+        denials.push(Denial {
+            name: "divisible token",
+            holder: divisible_holder,
+            pass: divisible.pass(),
+        });
+
+        let edition_holder = self.holder()?;
+        let edition = self.mint_token(
+            SPL_TOKEN_PROGRAM_ID,
+            &edition_holder.owner.pubkey(),
+            Supply {
+                amount: 2,
+                decimals: 0,
+            },
+            Some(NON_FUNGIBLE),
+            Some(collection.clone()),
+        )?;
+        denials.push(Denial {
+            name: "mint of two",
+            holder: edition_holder,
+            pass: edition.pass(),
+        });
+
+        // The holder's own fungible tokens, named with the member's mint and
+        // metadata
+        let swapper = self.holder()?;
+        let swapped = self.mint_token(
+            SPL_TOKEN_PROGRAM_ID,
+            &swapper.owner.pubkey(),
+            Supply {
+                amount: 1_000,
+                decimals: 0,
+            },
+            None,
+            None,
+        )?;
+        denials.push(Denial {
+            name: "swapped mint",
+            holder: swapper,
+            pass: Pass {
+                nft_account: swapped.token_account,
+                ..member_pass.pass()
+            },
+        });
+
+        // Metadata verified in the collection, but written by another
+        // program at another address
+        let forger = self.holder()?;
+        let forged = self.mint_nft(
+            TOKEN_2022_PROGRAM_ID,
+            &forger.owner.pubkey(),
+            NON_FUNGIBLE,
+            None,
+        )?;
+        let forged_metadata = Pubkey::new_unique();
+        let forgery = Metadata {
+            key: METADATA_V1_KEY,
+            update_authority: forger.owner.pubkey(),
+            mint: forged.mint,
+            data: MetadataData {
+                name: "Pass".to_string(),
+                symbol: "PASS".to_string(),
+                uri: String::new(),
+                seller_fee_basis_points: 0,
+                creators: None,
+            },
+            primary_sale_happened: false,
+            is_mutable: true,
+            edition_nonce: None,
+            token_standard: Some(NON_FUNGIBLE),
+            collection: Some(collection),
+        };
+        self.write_metadata(forged_metadata, &forgery, Pubkey::new_unique())?;
+        denials.push(Denial {
+            name: "forged metadata",
+            holder: forger,
+            pass: Pass {
+                metadata: forged_metadata,
+                ..forged.pass()
+            },
+        });
+
+        // A copy of the member's pass account, owned by the copier and by
+        // another program than a token program
+        let copier = self.holder()?;
+        let mut copy = self
+            .svm
+            .account(&member_pass.token_account)
+            .ok_or("The member's pass account is missing")?;
+        copy.data[32..64].copy_from_slice(copier.owner.pubkey().as_ref());
+        copy.owner = Pubkey::new_unique();
+        let copied_account = Pubkey::new_unique();
+        self.svm.set_account(copied_account, copy);
+        denials.push(Denial {
+            name: "copied pass account",
+            holder: copier,
+            pass: Pass {
+                nft_account: copied_account,
+                ..member_pass.pass()
+            },
+        });
+
+        Ok(denials)
+    }
+}
+
+/// A holder the gate must deny, and the pass accounts their check names
+struct Denial {
+    name: &'static str,
+    holder: Holder,
+    pass: Pass,
+}
+
+/// A check the gate denied, failing its transaction
+fn is_denial(result: &Result<Executed, TransactionError>) -> bool {
+    matches!(
+        result,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::InvalidAccountData
+        ))
+    )
+}
+
+/// Test 1: Holders of a verified pass thaw, under either token program
+#[test]
+fn test_holders_thaw() {
+    let report = run_holders_thaw_test();
+    assert!(
+        report.passed,
+        "Holders thaw test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_holders_thaw_test() -> TestResultReport {
+    let test_name = "Holders Thaw";
+    let mut assertions = 0;
+
+    let mut bank = match NftBank::start() {
+        Ok(bank) => bank,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+
+    // Assertion 1: The gate's Token-2022 id is the one the bank runs
+    assertions += 1;
+    if TOKEN_2022_PROGRAM_ID != spl_token_2022::id() {
+        return TestResultReport::failure(
+            test_name,
+            "TOKEN_2022_PROGRAM_ID is not Token-2022".to_string(),
+        );
+    }
+
+    // Assertions 2-5: A holder starts frozen and thaws with a verified pass
+    // minted under either token program
+    for (token_program, label) in [
+        (SPL_TOKEN_PROGRAM_ID, "SPL Token"),
+        (TOKEN_2022_PROGRAM_ID, "Token-2022"),
+    ] {
+        let holder = match bank.holder() {
+            Ok(holder) => holder,
+            Err(e) => return TestResultReport::failure(test_name, e),
+        };
+        let pass = match bank.mint_member(token_program, &holder.owner.pubkey(), true) {
+            Ok(pass) => pass,
+            Err(e) => return TestResultReport::failure(test_name, e),
+        };
+
+        assertions += 1;
+        if !bank.is_frozen(&holder) {
+            return TestResultReport::failure(
+                test_name,
+                format!("{} holder's account is not frozen", label),
+            );
+        }
+
+        assertions += 1;
+        match bank.thaw(&holder, &pass) {
+            Ok(executed)
+                if !bank.is_frozen(&holder)
+                    && executed
+                        .logs
+                        .iter()
+                        .any(|log| log.contains("permissionless thaw authorized")) => {}
+            other => {
+                return TestResultReport::failure(
+                    test_name,
+                    format!("{} pass holder: {:?}", label, other),
+                )
+            }
+        }
+    }
+
+    // Assertion 6: A pass moves with its NFT: the buyer thaws, and the
+    // seller, who holds none left, is denied
+    let (seller, buyer) = match (bank.holder(), bank.holder()) {
+        (Ok(seller), Ok(buyer)) => (seller, buyer),
+        (Err(e), _) | (_, Err(e)) => return TestResultReport::failure(test_name, e),
+    };
+    let sold = match bank.mint_member(SPL_TOKEN_PROGRAM_ID, &seller.owner.pubkey(), true) {
+        Ok(sold) => sold,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let bought = match bank.transfer(&sold, &seller.owner, &buyer.owner.pubkey()) {
+        Ok(bought) => bought,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    assertions += 1;
+    let seller_result = bank.thaw(&seller, &sold);
+    if !is_denial(&seller_result) || !bank.is_frozen(&seller) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Seller of the pass: {:?}", seller_result),
+        );
+    }
+    let buyer_result = bank.thaw(&buyer, &bought);
+    if buyer_result.is_err() || bank.is_frozen(&buyer) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Buyer of the pass: {:?}", buyer_result),
+        );
+    }
+
+    // Assertion 7: The mint's thaw list resolves the gate's config after
+    // the accounts the holder names
+    assertions += 1;
+    let check = bank.check(&buyer, &bought.pass());
+    let resolved: Vec<Pubkey> = check.accounts[4..].iter().map(|meta| meta.pubkey).collect();
+    let named = bought.pass();
+    let expected = vec![
+        buyer.owner.pubkey(),
+        named.nft_account,
+        named.nft_mint,
+        named.metadata,
+        bank.config(),
+    ];
+    if resolved != expected {
+        return TestResultReport::failure(
+            test_name,
+            format!("Resolved {:?}, expected {:?}", resolved, expected),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: Holders without a verified pass of the collection stay frozen
+#[test]
+fn test_non_holders_denied() {
+    let report = run_non_holders_denied_test();
+    assert!(
+        report.passed,
+        "Non-holders denied test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_non_holders_denied_test() -> TestResultReport {
+    let test_name = "Non-holders Denied";
+    let mut assertions = 0;
+
+    let mut bank = match NftBank::start() {
+        Ok(bank) => bank,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let member = match bank.holder() {
+        Ok(member) => member,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let member_pass = match bank.mint_member(SPL_TOKEN_PROGRAM_ID, &member.owner.pubkey(), true) {
+        Ok(pass) => pass,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let denials = match bank.non_holders(&member_pass) {
+        Ok(denials) => denials,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+
+    // Assertion 1: The member's own pass is approved, so the denials below
+    // are down to the passes named
+    assertions += 1;
+    let check = bank.check(&member, &member_pass.pass());
+    if let Err(e) = bank.send(&[check], &[]) {
+        return TestResultReport::failure(test_name, format!("Member denied: {:?}", e));
+    }
+
+    // Assertions 2-12: Each non-holder's thaw is denied and their account
+    // stays frozen
+    for denial in &denials {
+        assertions += 1;
+        let result = bank.thaw_with(&denial.holder, &denial.pass);
+        if !is_denial(&result) || !bank.is_frozen(&denial.holder) {
+            return TestResultReport::failure(test_name, format!("{}: {:?}", denial.name, result));
+        }
+    }
+
+    // Assertion 13: The member's pass does not approve another holder's
+    // token account in the member's name
+    let other = match bank.holder() {
+        Ok(other) => other,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    assertions += 1;
+    let mut check = bank.check(&member, &member_pass.pass());
+    check.accounts[1].pubkey = other.token_account;
+    let result = bank.send(&[check], &[]);
+    if !is_denial(&result) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Another holder's account: {:?}", result),
+        );
+    }
+
+    // Assertion 14: The gate never authorizes a permissionless freeze
+    assertions += 1;
+    let freeze = interface::can_freeze_permissionless(
+        &bank.gate,
+        member.owner.pubkey(),
+        other.token_account,
+        bank.mint,
+        find_freeze_extra_account_metas_address(&bank.mint, &bank.gate).0,
+        &[bank.config().into()],
+    );
+    let result = bank.send(&[freeze], &[]);
+    if result
+        != Err(TransactionError::InstructionError(
+            0,
+            InstructionError::InvalidInstructionData,
+        ))
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!("Permissionless freeze: {:?}", result),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: Only the config's authority moves the mint to another collection
+#[test]
+fn test_set_collection() {
+    let report = run_set_collection_test();
+    assert!(
+        report.passed,
+        "Set collection test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_set_collection_test() -> TestResultReport {
+    let test_name = "Set Collection";
+    let mut assertions = 0;
+
+    let mut bank = match NftBank::start() {
+        Ok(bank) => bank,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let (old_member, new_member) = match (bank.holder(), bank.holder()) {
+        (Ok(old_member), Ok(new_member)) => (old_member, new_member),
+        (Err(e), _) | (_, Err(e)) => return TestResultReport::failure(test_name, e),
+    };
+    let old_pass = match bank.mint_member(SPL_TOKEN_PROGRAM_ID, &old_member.owner.pubkey(), true) {
+        Ok(pass) => pass,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let new_collection = match bank.mint_collection() {
+        Ok(collection) => collection,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let new_pass = match bank.mint_nft(
+        TOKEN_2022_PROGRAM_ID,
+        &new_member.owner.pubkey(),
+        NON_FUNGIBLE,
+        Some(Collection {
+            verified: true,
+            key: new_collection.mint,
+        }),
+    ) {
+        Ok(pass) => pass,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+
+    // Assertion 1: Someone other than the authority cannot change the
+    // collection
+    assertions += 1;
+    let intruder = Keypair::new();
+    let set = bank.set_collection(&new_collection.mint, &intruder.pubkey());
+    if bank.send(&[set], &[&intruder]).is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "An intruder changed the collection".to_string(),
+        );
+    }
+
+    // Assertion 2: The authority can
+    assertions += 1;
+    let set = bank.set_collection(&new_collection.mint, &bank.issuer.pubkey());
+    if let Err(e) = bank.svm.send(&[set], &[&bank.issuer]) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Setting the collection failed: {:?}", e),
+        );
+    }
+
+    // Assertion 3: The old collection's passes no longer thaw
+    assertions += 1;
+    let result = bank.thaw(&old_member, &old_pass);
+    if !is_denial(&result) || !bank.is_frozen(&old_member) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Old collection's pass: {:?}", result),
+        );
+    }
+
+    // Assertion 4: The new collection's do
+    assertions += 1;
+    let result = bank.thaw(&new_member, &new_pass);
+    if result.is_err() || bank.is_frozen(&new_member) {
+        return TestResultReport::failure(
+            test_name,
+            format!("New collection's pass: {:?}", result),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate NFT gate test report
+#[test]
+fn generate_nft_gate_report() {
+    let results = vec![
+        run_holders_thaw_test(),
+        run_non_holders_denied_test(),
+        run_set_collection_test(),
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "NFT Gate Test Results",
+        "../../tests/reports/nft_gate.md",
+    ) {
+        panic!("Failed to generate NFT gate report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} NFT gate tests failed", failed);
+}
@@ -0,0 +1,834 @@
+//! Offboarding: freezing a holder's accounts once they leave the allow list
+//!
+//! The flow an issuer demonstrates to a regulator: the holder is removed
+//! from the allow list, a keeper notices on its next scan, and every token
+//! account the holder owns ends up frozen while other holders are left
+//! alone. The keeper always tries the gate's `can-freeze-permissionless`
+//! check first. A gate with a block path (the hybrid gate, for a sanctioned
+//! holder) authorizes it and the keeper freezes on its own; a gate without
+//! one (the production allow list) or a holder who is only delisted gets a
+//! denial, and the issuer freezes with its freeze authority instead.
+//!
+//! Each run is timed from the removal to the last freeze, split into the
+//! removal, the keeper's detection and the freezes, and the report lists
+//! the timeline of every run. The bank has no account index, so the keeper
+//! is handed the holders and token accounts to watch; the block list
+//! example has no admin instructions, so a sanction is written into the bank
+//! directly. As elsewhere in the suite, the issuer signs the freezes in
+//! place of the FAMP PDA.
+
+use borsh::BorshSerialize;
+use example_block_list::{block_list_address, create_block_list_record, BlockReason};
+use gate_view::AllowListView;
+use production_allow_list::{
+    allow_list_address, RemoveMode, ADD_TO_ALLOW_LIST, CLOSE_RECORD, INITIALIZE,
+    REMOVE_FROM_ALLOW_LIST,
+};
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    clock::Clock,
+    instruction::{AccountMeta, Instruction},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::Transaction,
+};
+use spl_token_2022::{
+    instruction as token_instruction,
+    state::{Account as TokenAccount, AccountState},
+};
+use std::time::{Duration, Instant};
+
+use token_acl_integration_tests::{
+    program_test::{mint_account, rent_exempt_account, token_account},
+    reporting, suite_config, units, TestResultReport,
+};
+use token_acl_interface as interface;
+
+/// Token accounts the offboarded holder owns
+const HOLDER_ACCOUNTS: usize = 3;
+
+/// Tokens in each of them
+const BALANCE: u64 = 1_000;
+
+/// Check-and-freeze pairs, or freezes, per transaction
+const FREEZES_PER_TRANSACTION: usize = 8;
+
+/// Which program gates the mint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GateKind {
+    /// The production allow list, which has no freeze path
+    AllowList,
+    /// The hybrid gate, which authorizes freezing sanctioned holders
+    Hybrid,
+}
+
+impl GateKind {
+    fn name(self) -> &'static str {
+        match self {
+            GateKind::AllowList => "Production allow list",
+            GateKind::Hybrid => "Hybrid gate",
+        }
+    }
+}
+
+/// How the holder leaves the allow list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Removal {
+    /// `RemoveFromAllowList` in `Deactivate` mode
+    Deactivate,
+    /// `CloseRecord`, refunding the rent to the issuer
+    Close,
+}
+
+impl Removal {
+    fn name(self) -> &'static str {
+        match self {
+            Removal::Deactivate => "Deactivated",
+            Removal::Close => "Closed",
+        }
+    }
+}
+
+/// Who froze the holder's accounts
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FreezePath {
+    /// The keeper, on the gate's approval
+    Permissionless,
+    /// The issuer, after the gate denied the keeper
+    Permissioned { denial: String },
+}
+
+impl FreezePath {
+    fn name(&self) -> &'static str {
+        match self {
+            FreezePath::Permissionless => "Permissionless (keeper)",
+            FreezePath::Permissioned { .. } => "Permissioned (issuer)",
+        }
+    }
+}
+
+/// One offboarding run, timed from the removal to the last freeze
+struct OffboardingTimeline {
+    gate: GateKind,
+    removal: Removal,
+    path: FreezePath,
+    accounts: usize,
+    /// Until the removal landed
+    removed: Duration,
+    /// Until the keeper's scan reported the holder
+    detected: Duration,
+    /// Until every account was frozen
+    frozen: Duration,
+    /// Freeze transactions, the keeper's denied attempt included
+    transactions: usize,
+}
+
+impl OffboardingTimeline {
+    fn total(&self) -> Duration {
+        self.removed + self.detected + self.frozen
+    }
+}
+
+/// A bank with the mint's gate, the allow list holding two listed holders
+/// with thawed accounts, and a keeper
+struct OffboardingFixture {
+    context: ProgramTestContext,
+    kind: GateKind,
+    /// The mint's gate; the allow list itself for `GateKind::AllowList`
+    gate: Pubkey,
+    allow_list: Pubkey,
+    block_list: Pubkey,
+    issuer: Keypair,
+    keeper: Keypair,
+    mint: Pubkey,
+    /// The holder being offboarded
+    holder: Pubkey,
+    holder_accounts: Vec<Pubkey>,
+    /// A holder who stays listed
+    bystander: Pubkey,
+    bystander_account: Pubkey,
+}
+
+impl OffboardingFixture {
+    async fn new(kind: GateKind) -> Self {
+        let allow_list = Pubkey::new_unique();
+        let block_list = Pubkey::new_unique();
+        let gate = match kind {
+            GateKind::AllowList => allow_list,
+            GateKind::Hybrid => Pubkey::new_unique(),
+        };
+        let issuer = Keypair::new();
+        let keeper = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let holder = Pubkey::new_unique();
+        let holder_accounts: Vec<Pubkey> =
+            (0..HOLDER_ACCOUNTS).map(|_| Pubkey::new_unique()).collect();
+        let bystander = Pubkey::new_unique();
+        let bystander_account = Pubkey::new_unique();
+
+        let mut program_test = ProgramTest::new(
+            "production_allow_list",
+            allow_list,
+            processor!(production_allow_list::process_instruction),
+        );
+        if kind == GateKind::Hybrid {
+            program_test.add_program(
+                "hybrid_gate",
+                gate,
+                processor!(hybrid_gate::process_instruction),
+            );
+            program_test.add_program(
+                "example_block_list",
+                block_list,
+                processor!(example_block_list::process_instruction),
+            );
+        }
+
+        let supply = BALANCE * (HOLDER_ACCOUNTS as u64 + 1);
+        program_test.add_account(mint, mint_account(&issuer.pubkey(), supply));
+        for address in &holder_accounts {
+            program_test.add_account(
+                *address,
+                token_account(&mint, &holder, BALANCE, AccountState::Initialized),
+            );
+        }
+        program_test.add_account(
+            bystander_account,
+            token_account(&mint, &bystander, BALANCE, AccountState::Initialized),
+        );
+        for signer in [&issuer, &keeper] {
+            program_test.add_account(
+                signer.pubkey(),
+                Account::new(1_000_000_000, 0, &system_program::id()),
+            );
+        }
+
+        let context = program_test.start_with_context().await;
+        let mut fixture = Self {
+            context,
+            kind,
+            gate,
+            allow_list,
+            block_list,
+            issuer,
+            keeper,
+            mint,
+            holder,
+            holder_accounts,
+            bystander,
+            bystander_account,
+        };
+
+        let issuer = fixture.issuer.pubkey();
+        let allow_list_config = fixture.allow_list_config();
+        let mut setup = vec![Instruction::new_with_bytes(
+            allow_list,
+            &[INITIALIZE],
+            vec![
+                AccountMeta::new(allow_list_config, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(issuer, true),
+                AccountMeta::new(issuer, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )];
+        if kind == GateKind::Hybrid {
+            let mut data = vec![hybrid_gate::INITIALIZE];
+            data.extend_from_slice(allow_list.as_ref());
+            data.extend_from_slice(block_list.as_ref());
+            setup.push(Instruction::new_with_bytes(
+                gate,
+                &data,
+                vec![
+                    AccountMeta::new(fixture.hybrid_config(), false),
+                    AccountMeta::new_readonly(mint, false),
+                    AccountMeta::new_readonly(issuer, true),
+                    AccountMeta::new(issuer, true),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            ));
+        }
+        for user in [holder, bystander] {
+            setup.push(Instruction::new_with_bytes(
+                allow_list,
+                &[ADD_TO_ALLOW_LIST],
+                vec![
+                    AccountMeta::new_readonly(allow_list_config, false),
+                    AccountMeta::new(fixture.allow_list_record(&user), false),
+                    AccountMeta::new_readonly(mint, false),
+                    AccountMeta::new_readonly(user, false),
+                    AccountMeta::new_readonly(issuer, true),
+                    AccountMeta::new(issuer, true),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            ));
+        }
+        let issuer = fixture.issuer.insecure_clone();
+        fixture
+            .send(&setup, &issuer, &[])
+            .await
+            .expect("setup should succeed");
+        fixture
+    }
+
+    fn allow_list_config(&self) -> Pubkey {
+        production_allow_list::config_address(&self.mint, &self.allow_list).address()
+    }
+
+    fn hybrid_config(&self) -> Pubkey {
+        hybrid_gate::config_address(&self.mint, &self.gate).address()
+    }
+
+    fn allow_list_record(&self, user: &Pubkey) -> Pubkey {
+        allow_list_address(&self.mint, user, &self.allow_list).address()
+    }
+
+    fn block_list_record(&self, user: &Pubkey) -> Pubkey {
+        block_list_address(&self.mint, user, &self.block_list).address()
+    }
+
+    /// Sign `instructions` with `payer` and `signers` and process them
+    async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        payer: &Keypair,
+        signers: &[&Keypair],
+    ) -> Result<(), String> {
+        let blockhash = self
+            .context
+            .get_new_latest_blockhash()
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut all_signers = vec![payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        let outcome = self
+            .context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .map_err(|e| e.to_string())?;
+        match outcome.result {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                let logs = outcome.metadata.map(|m| m.log_messages).unwrap_or_default();
+                Err(format!("{}: {}", e, logs.join(" | ")))
+            }
+        }
+    }
+
+    /// Take the holder off the allow list
+    async fn remove(&mut self, removal: Removal) -> Result<(), String> {
+        let mut accounts = vec![
+            AccountMeta::new_readonly(self.allow_list_config(), false),
+            AccountMeta::new(self.allow_list_record(&self.holder), false),
+            AccountMeta::new_readonly(self.issuer.pubkey(), true),
+        ];
+        let data = match removal {
+            Removal::Deactivate => vec![REMOVE_FROM_ALLOW_LIST, RemoveMode::Deactivate as u8],
+            Removal::Close => {
+                accounts.push(AccountMeta::new(self.issuer.pubkey(), false));
+                vec![CLOSE_RECORD]
+            }
+        };
+        let remove = Instruction::new_with_bytes(self.allow_list, &data, accounts);
+        let issuer = self.issuer.insecure_clone();
+        self.send(&[remove], &issuer, &[]).await
+    }
+
+    /// Put the holder on the hybrid gate's sanctions list
+    fn sanction(&mut self) {
+        let record = create_block_list_record(&self.mint, &self.holder, BlockReason::Sanctions, 0);
+        let account = rent_exempt_account(record.try_to_vec().unwrap(), self.block_list);
+        self.context
+            .set_account(&self.block_list_record(&self.holder), &account.into());
+    }
+
+    async fn account(&mut self, address: &Pubkey) -> Option<Account> {
+        self.context
+            .banks_client
+            .get_account(*address)
+            .await
+            .unwrap()
+    }
+
+    async fn token_state(&mut self, address: &Pubkey) -> Option<(AccountState, u64)> {
+        let account = self.account(address).await?;
+        let state = TokenAccount::unpack(&account.data).ok()?;
+        Some((state.state, state.amount))
+    }
+
+    /// The keeper's scan: every watched holder whose allow list record no
+    /// longer lets them thaw, a missing record included
+    async fn detect(&mut self) -> Vec<Pubkey> {
+        let clock: Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        let mut offboarded = Vec::new();
+        for user in [self.holder, self.bystander] {
+            let record = self.allow_list_record(&user);
+            let active = match self.account(&record).await {
+                Some(account) if !account.data.is_empty() => AllowListView::unpack(&account.data)
+                    .is_ok_and(|view| view.is_active(clock.unix_timestamp)),
+                _ => false,
+            };
+            if !active {
+                offboarded.push(user);
+            }
+        }
+        offboarded
+    }
+
+    /// The extra accounts of the gate's freeze check for `owner`
+    fn freeze_check_accounts(&self, owner: &Pubkey) -> Vec<AccountMeta> {
+        match self.kind {
+            GateKind::AllowList => Vec::new(),
+            GateKind::Hybrid => vec![
+                AccountMeta::new_readonly(self.hybrid_config(), false),
+                AccountMeta::new_readonly(self.block_list_record(owner), false),
+            ],
+        }
+    }
+
+    /// The extra accounts of the gate's thaw check for `owner`
+    fn thaw_check_accounts(&self, owner: &Pubkey) -> Vec<AccountMeta> {
+        match self.kind {
+            GateKind::AllowList => vec![
+                AccountMeta::new_readonly(*owner, false),
+                AccountMeta::new_readonly(self.allow_list_record(owner), false),
+                AccountMeta::new_readonly(
+                    interface::find_missing_record_policy_address(&self.mint, &self.gate).0,
+                    false,
+                ),
+            ],
+            GateKind::Hybrid => vec![
+                AccountMeta::new_readonly(*owner, false),
+                AccountMeta::new_readonly(self.hybrid_config(), false),
+                AccountMeta::new_readonly(self.allow_list_record(owner), false),
+                AccountMeta::new_readonly(self.block_list_record(owner), false),
+            ],
+        }
+    }
+
+    fn freeze_instruction(&self, token_account: &Pubkey) -> Instruction {
+        token_instruction::freeze_account(
+            &spl_token_2022::id(),
+            token_account,
+            &self.mint,
+            &self.issuer.pubkey(),
+            &[],
+        )
+        .unwrap()
+    }
+
+    /// Freeze `owner`'s `token_accounts`: the keeper's check-and-freeze
+    /// first, the issuer's freeze if the gate denies it
+    ///
+    /// Returns the path taken and the transactions sent.
+    async fn freeze(
+        &mut self,
+        owner: &Pubkey,
+        token_accounts: &[Pubkey],
+    ) -> Result<(FreezePath, usize), String> {
+        let extra_metas =
+            interface::find_freeze_extra_account_metas_address(&self.mint, &self.gate).0;
+        let mut transactions = 0;
+        let mut denial = None;
+        for batch in token_accounts.chunks(FREEZES_PER_TRANSACTION) {
+            let mut instructions = Vec::new();
+            for token_account in batch {
+                let mut check = interface::can_freeze_permissionless(
+                    &self.gate,
+                    self.keeper.pubkey(),
+                    *token_account,
+                    self.mint,
+                    extra_metas,
+                    &[],
+                );
+                check.accounts.extend(self.freeze_check_accounts(owner));
+                instructions.push(check);
+                instructions.push(self.freeze_instruction(token_account));
+            }
+            transactions += 1;
+            let keeper = self.keeper.insecure_clone();
+            let issuer = self.issuer.insecure_clone();
+            if let Err(e) = self.send(&instructions, &keeper, &[&issuer]).await {
+                denial = Some(e);
+                break;
+            }
+        }
+        let Some(denial) = denial else {
+            return Ok((FreezePath::Permissionless, transactions));
+        };
+
+        // The gate denied the keeper, so nothing of that batch froze; the
+        // issuer freezes whatever is still thawed
+        let mut thawed = Vec::new();
+        for token_account in token_accounts {
+            if let Some((AccountState::Initialized, _)) = self.token_state(token_account).await {
+                thawed.push(*token_account);
+            }
+        }
+        let issuer = self.issuer.insecure_clone();
+        for batch in thawed.chunks(FREEZES_PER_TRANSACTION) {
+            let freezes: Vec<Instruction> = batch
+                .iter()
+                .map(|token_account| self.freeze_instruction(token_account))
+                .collect();
+            transactions += 1;
+            self.send(&freezes, &issuer, &[]).await?;
+        }
+        Ok((FreezePath::Permissioned { denial }, transactions))
+    }
+
+    /// Whether the gate's thaw check for `owner`'s `token_account` passes
+    async fn thaw_check_passes(&mut self, owner: &Pubkey, token_account: &Pubkey) -> bool {
+        let mut check = interface::can_thaw_permissionless(
+            &self.gate,
+            self.keeper.pubkey(),
+            *token_account,
+            self.mint,
+            interface::find_thaw_extra_account_metas_address(&self.mint, &self.gate).0,
+            &[],
+        );
+        check.accounts.extend(self.thaw_check_accounts(owner));
+        let keeper = self.keeper.insecure_clone();
+        self.send(&[check], &keeper, &[]).await.is_ok()
+    }
+}
+
+/// An offboarding to run and what it must end with
+struct OffboardingCase {
+    gate: GateKind,
+    removal: Removal,
+    /// Also put the holder on the sanctions list
+    sanction: bool,
+    /// Whether the keeper may freeze on its own
+    permissionless: bool,
+    /// Text the gate's denial of the keeper must log
+    denial_log: Option<&'static str>,
+}
+
+async fn run_offboarding(
+    test_name: &str,
+    case: OffboardingCase,
+) -> (TestResultReport, Option<OffboardingTimeline>) {
+    let mut assertions = 0;
+    let mut fixture = OffboardingFixture::new(case.gate).await;
+    let holder = fixture.holder;
+    let holder_accounts = fixture.holder_accounts.clone();
+    let bystander = fixture.bystander;
+    let bystander_account = fixture.bystander_account;
+
+    // Assertion 1: Before the removal the keeper sees nobody to offboard
+    assertions += 1;
+    let offboarded = fixture.detect().await;
+    if !offboarded.is_empty() {
+        return (
+            TestResultReport::failure(
+                test_name,
+                format!("Keeper flagged listed holders: {:?}", offboarded),
+            ),
+            None,
+        );
+    }
+
+    let started = Instant::now();
+    if let Err(e) = fixture.remove(case.removal).await {
+        return (
+            TestResultReport::failure(test_name, format!("Removal failed: {}", e)),
+            None,
+        );
+    }
+    if case.sanction {
+        fixture.sanction();
+    }
+    let removed = started.elapsed();
+
+    // Assertion 2: The keeper's next scan reports the holder, and only them
+    assertions += 1;
+    let detecting = Instant::now();
+    let offboarded = fixture.detect().await;
+    let detected = detecting.elapsed();
+    if offboarded != [holder] {
+        return (
+            TestResultReport::failure(
+                test_name,
+                format!("Keeper detected {:?}, expected {}", offboarded, holder),
+            ),
+            None,
+        );
+    }
+
+    // Assertion 3: The freezes take the path the gate allows
+    assertions += 1;
+    let freezing = Instant::now();
+    let (path, transactions) = match fixture.freeze(&holder, &holder_accounts).await {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            return (
+                TestResultReport::failure(test_name, format!("Freezing failed: {}", e)),
+                None,
+            )
+        }
+    };
+    let frozen = freezing.elapsed();
+    let expected_path = match (&path, case.permissionless, case.denial_log) {
+        (FreezePath::Permissionless, true, _) => true,
+        (FreezePath::Permissioned { denial }, false, Some(log)) => denial.contains(log),
+        _ => false,
+    };
+    if !expected_path {
+        return (
+            TestResultReport::failure(test_name, format!("Unexpected freeze path: {:?}", path)),
+            None,
+        );
+    }
+
+    // Assertion 4: Every one of the holder's accounts is frozen with its
+    // balance intact
+    assertions += 1;
+    for token_account in &holder_accounts {
+        let state = fixture.token_state(token_account).await;
+        if state != Some((AccountState::Frozen, BALANCE)) {
+            return (
+                TestResultReport::failure(
+                    test_name,
+                    format!("{} ended {:?}", token_account, state),
+                ),
+                None,
+            );
+        }
+    }
+
+    // Assertion 5: The gate refuses to thaw any of them again
+    assertions += 1;
+    for token_account in &holder_accounts {
+        if fixture.thaw_check_passes(&holder, token_account).await {
+            return (
+                TestResultReport::failure(
+                    test_name,
+                    format!("The gate would thaw {} again", token_account),
+                ),
+                None,
+            );
+        }
+    }
+
+    // Assertion 6: The holder's record ends as the removal left it
+    assertions += 1;
+    let record = fixture.account(&fixture.allow_list_record(&holder)).await;
+    let record_state = match case.removal {
+        Removal::Deactivate => record
+            .and_then(|account| AllowListView::unpack(&account.data).ok())
+            .is_some_and(|view| !view.allowed && !view.revoked),
+        Removal::Close => record.is_none(),
+    };
+    if !record_state {
+        return (
+            TestResultReport::failure(
+                test_name,
+                format!("Holder's record does not show the {:?}", case.removal),
+            ),
+            None,
+        );
+    }
+
+    // Assertion 7: The bystander's account is untouched and still passes the
+    // gate
+    assertions += 1;
+    let state = fixture.token_state(&bystander_account).await;
+    if state != Some((AccountState::Initialized, BALANCE))
+        || !fixture
+            .thaw_check_passes(&bystander, &bystander_account)
+            .await
+    {
+        return (
+            TestResultReport::failure(test_name, format!("Bystander's account ended {:?}", state)),
+            None,
+        );
+    }
+
+    let timeline = OffboardingTimeline {
+        gate: case.gate,
+        removal: case.removal,
+        path,
+        accounts: holder_accounts.len(),
+        removed,
+        detected,
+        frozen,
+        transactions,
+    };
+    (
+        TestResultReport::success(test_name, assertions),
+        Some(timeline),
+    )
+}
+
+/// Test 1: Without a block path the gate denies the keeper and the issuer
+/// freezes a deactivated holder
+#[tokio::test]
+async fn test_offboarding_without_block_path() {
+    let (report, _) = run_offboarding_without_block_path_test().await;
+    assert!(
+        report.passed,
+        "Offboarding without a block path failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_offboarding_without_block_path_test() -> (TestResultReport, Option<OffboardingTimeline>)
+{
+    run_offboarding(
+        "Offboarding Without a Block Path",
+        OffboardingCase {
+            gate: GateKind::AllowList,
+            removal: Removal::Deactivate,
+            sanction: false,
+            permissionless: false,
+            denial_log: Some("Permissionless freeze not supported"),
+        },
+    )
+    .await
+}
+
+/// Test 2: A holder whose record was closed is detected and frozen like a
+/// deactivated one
+#[tokio::test]
+async fn test_offboarding_closed_record() {
+    let (report, _) = run_offboarding_closed_record_test().await;
+    assert!(
+        report.passed,
+        "Offboarding a closed record failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_offboarding_closed_record_test() -> (TestResultReport, Option<OffboardingTimeline>) {
+    run_offboarding(
+        "Offboarding a Closed Record",
+        OffboardingCase {
+            gate: GateKind::AllowList,
+            removal: Removal::Close,
+            sanction: false,
+            permissionless: false,
+            denial_log: Some("Permissionless freeze not supported"),
+        },
+    )
+    .await
+}
+
+/// Test 3: Through the hybrid gate's block path the keeper freezes a
+/// delisted and sanctioned holder on its own
+#[tokio::test]
+async fn test_offboarding_through_block_path() {
+    let (report, _) = run_offboarding_through_block_path_test().await;
+    assert!(
+        report.passed,
+        "Offboarding through the block path failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_offboarding_through_block_path_test() -> (TestResultReport, Option<OffboardingTimeline>)
+{
+    run_offboarding(
+        "Offboarding Through the Block Path",
+        OffboardingCase {
+            gate: GateKind::Hybrid,
+            removal: Removal::Deactivate,
+            sanction: true,
+            permissionless: true,
+            denial_log: None,
+        },
+    )
+    .await
+}
+
+/// Test 4: A holder who is only delisted does not open the hybrid gate's
+/// block path, so the issuer freezes them
+#[tokio::test]
+async fn test_offboarding_delisted_only() {
+    let (report, _) = run_offboarding_delisted_only_test().await;
+    assert!(
+        report.passed,
+        "Offboarding a delisted holder failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_offboarding_delisted_only_test() -> (TestResultReport, Option<OffboardingTimeline>) {
+    run_offboarding(
+        "Offboarding a Holder Who Is Only Delisted",
+        OffboardingCase {
+            gate: GateKind::Hybrid,
+            removal: Removal::Deactivate,
+            sanction: false,
+            permissionless: false,
+            denial_log: Some("neither sanctioned nor embargoed"),
+        },
+    )
+    .await
+}
+
+fn render_timeline_report(timelines: &[OffboardingTimeline]) -> String {
+    let mut report = String::from("## Offboarding Timelines\n\n");
+    report.push_str(&format!(
+        "Time from removing a holder with {} token accounts from the allow list until all of them are frozen.\n\n",
+        HOLDER_ACCOUNTS
+    ));
+    report.push_str(
+        "| Gate | Removal | Frozen by | Accounts | Removal | Detection | Freezes | Total | Transactions |\n",
+    );
+    report.push_str(
+        "|------|---------|-----------|----------|---------|-----------|---------|-------|--------------|\n",
+    );
+    for timeline in timelines {
+        report.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} | {} | {} | {} |\n",
+            timeline.gate.name(),
+            timeline.removal.name(),
+            timeline.path.name(),
+            timeline.accounts,
+            units::duration(timeline.removed),
+            units::duration(timeline.detected),
+            units::duration(timeline.frozen),
+            units::duration(timeline.total()),
+            timeline.transactions
+        ));
+    }
+    report
+}
+
+/// Generate the offboarding report with every run's timeline
+#[tokio::test]
+async fn generate_offboarding_report() {
+    let runs = vec![
+        run_offboarding_without_block_path_test().await,
+        run_offboarding_closed_record_test().await,
+        run_offboarding_through_block_path_test().await,
+        run_offboarding_delisted_only_test().await,
+    ];
+    let (results, timelines): (Vec<_>, Vec<_>) = runs.into_iter().unzip();
+    let timelines: Vec<OffboardingTimeline> = timelines.into_iter().flatten().collect();
+
+    let mut markdown = reporting::render_test_report(
+        &results,
+        "Offboarding Test Results",
+        &suite_config::current().disabled(),
+    );
+    markdown.push_str(&render_timeline_report(&timelines));
+    std::fs::create_dir_all("../../tests/reports").ok();
+    if let Err(e) = std::fs::write("../../tests/reports/offboarding.md", markdown) {
+        panic!("Failed to generate offboarding report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} offboarding tests failed", failed);
+}
@@ -0,0 +1,382 @@
+//! Onboarding campaign macro-benchmark
+//!
+//! Onboards 10,000 investors to an allow-listed mint end to end on a
+//! `solana-program-test` bank, the way an issuer would run a campaign: the
+//! issuer adds investors to the allow list in batches (funding each one in
+//! the same transaction), then every investor pays for their own
+//! permissionless thaw check. The cost report gives the total wall-clock,
+//! fees and rent, and the cost per investor, all taken from what the bank
+//! charged rather than from estimates.
+
+use production_allow_list::{allow_list_address, config_address, ADD_TO_ALLOW_LIST, INITIALIZE};
+use solana_program_test::{processor, BanksClient, ProgramTest};
+use solana_sdk::{
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction, system_program,
+    transaction::Transaction,
+};
+use std::time::Instant;
+use token_acl_interface::{
+    can_thaw_permissionless, find_missing_record_policy_address,
+    find_thaw_extra_account_metas_address,
+};
+
+use token_acl_integration_tests::{
+    context::TestContext,
+    costs::{self, CampaignCost, PhaseCost},
+    reporting,
+    suite_config::{self, SuiteCategory},
+    TestResultReport,
+};
+
+/// Investors onboarded by the campaign
+const INVESTOR_COUNT: usize = 10_000;
+
+/// Investors added per allow list transaction; nine would still fit
+const BATCH_SIZE: usize = 8;
+
+/// Lamports each investor receives to pay for their own thaw check
+const INVESTOR_FUNDING: u64 = 1_000_000;
+
+/// Fees and rent per investor the campaign must stay under
+const MAX_COST_PER_INVESTOR: u64 = 5_000_000;
+
+/// Transactions submitted on one blockhash before fetching a fresh one
+const BLOCKHASH_REFRESH: usize = 100;
+
+struct Campaign {
+    /// The test's bank, kept alive while its client is in use
+    context: TestContext,
+    banks_client: BanksClient,
+    payer: Keypair,
+    gate: Pubkey,
+    mint: Pubkey,
+    issuer: Keypair,
+    investors: Vec<Keypair>,
+}
+
+impl Campaign {
+    async fn start(test_name: &str, investors: usize) -> Self {
+        let gate = Pubkey::new_unique();
+        let program_test = ProgramTest::new(
+            "production_allow_list",
+            gate,
+            processor!(production_allow_list::process_instruction),
+        );
+        let context = TestContext::start(test_name, program_test)
+            .await
+            .expect("program-test bank should start");
+
+        Self {
+            banks_client: context.client(),
+            payer: context.payer(),
+            gate,
+            mint: Pubkey::new_unique(),
+            issuer: Keypair::new(),
+            investors: (0..investors).map(|_| Keypair::new()).collect(),
+            context,
+        }
+    }
+
+    fn initialize_instruction(&self) -> Instruction {
+        Instruction::new_with_bytes(
+            self.gate,
+            &[INITIALIZE],
+            vec![
+                AccountMeta::new(config_address(&self.mint, &self.gate).address(), false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    fn add_instruction(&self, investor: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            self.gate,
+            &[ADD_TO_ALLOW_LIST],
+            vec![
+                AccountMeta::new_readonly(config_address(&self.mint, &self.gate).address(), false),
+                AccountMeta::new(
+                    allow_list_address(&self.mint, investor, &self.gate).address(),
+                    false,
+                ),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(*investor, false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// The investor's own thaw check, as a wallet would build it
+    fn self_thaw_instruction(&self, investor: &Pubkey) -> Instruction {
+        can_thaw_permissionless(
+            &self.gate,
+            *investor,
+            Pubkey::new_unique(),
+            self.mint,
+            find_thaw_extra_account_metas_address(&self.mint, &self.gate).0,
+            &[
+                (*investor).into(),
+                allow_list_address(&self.mint, investor, &self.gate)
+                    .address()
+                    .into(),
+                find_missing_record_policy_address(&self.mint, &self.gate)
+                    .0
+                    .into(),
+            ],
+        )
+    }
+
+    async fn balance(&mut self, address: Pubkey) -> u64 {
+        self.banks_client
+            .get_balance(address)
+            .await
+            .unwrap_or_default()
+    }
+
+    async fn rent_of(&mut self, address: Pubkey) -> u64 {
+        match self.banks_client.get_account(address).await {
+            Ok(Some(account)) => account.lamports,
+            _ => 0,
+        }
+    }
+
+    async fn blockhash(&mut self) -> Result<Hash, String> {
+        self.banks_client
+            .get_latest_blockhash()
+            .await
+            .map_err(|e| format!("Fetching a blockhash failed: {}", e))
+    }
+
+    /// Submit `transaction` and count it against `phase`
+    async fn submit(
+        &mut self,
+        transaction: Transaction,
+        phase: &mut PhaseCost,
+    ) -> Result<(), String> {
+        let fee = self.context.fee(transaction.signatures.len());
+        let outcome = self
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .map_err(|e| e.to_string())?;
+        outcome.result.map_err(|e| e.to_string())?;
+        let compute_units = outcome
+            .metadata
+            .map(|metadata| metadata.compute_units_consumed)
+            .unwrap_or_default();
+        phase.record_transaction(fee, compute_units);
+        Ok(())
+    }
+
+    /// Create the mint's allow list config
+    async fn initialize(&mut self) -> Result<PhaseCost, String> {
+        let mut phase = PhaseCost::new("Initialize allow list");
+        let started = Instant::now();
+        let blockhash = self.blockhash().await?;
+        let transaction = Transaction::new_signed_with_payer(
+            &[self.initialize_instruction()],
+            Some(&self.payer.pubkey()),
+            &[&self.payer, &self.issuer],
+            blockhash,
+        );
+        self.submit(transaction, &mut phase)
+            .await
+            .map_err(|e| format!("Initialize failed: {}", e))?;
+        phase.elapsed = started.elapsed();
+        phase.rent = self
+            .rent_of(config_address(&self.mint, &self.gate).address())
+            .await;
+        Ok(phase)
+    }
+
+    /// Add and fund every investor, `BATCH_SIZE` per transaction
+    async fn add_investors(&mut self) -> Result<PhaseCost, String> {
+        let mut phase = PhaseCost::new("Batch allow list adds");
+        let payer = self.payer.pubkey();
+        let started = Instant::now();
+        let mut blockhash = self.blockhash().await?;
+
+        let investors: Vec<Pubkey> = self.investors.iter().map(Signer::pubkey).collect();
+        for (batch, chunk) in investors.chunks(BATCH_SIZE).enumerate() {
+            if batch % BLOCKHASH_REFRESH == 0 {
+                blockhash = self.blockhash().await?;
+            }
+            let instructions: Vec<Instruction> = chunk
+                .iter()
+                .flat_map(|investor| {
+                    [
+                        self.add_instruction(investor),
+                        system_instruction::transfer(&payer, investor, INVESTOR_FUNDING),
+                    ]
+                })
+                .collect();
+            let transaction = Transaction::new_signed_with_payer(
+                &instructions,
+                Some(&payer),
+                &[&self.payer, &self.issuer],
+                blockhash,
+            );
+            self.submit(transaction, &mut phase)
+                .await
+                .map_err(|e| format!("Batch {} failed: {}", batch, e))?;
+        }
+        phase.elapsed = started.elapsed();
+
+        for investor in &investors {
+            phase.rent += self
+                .rent_of(allow_list_address(&self.mint, investor, &self.gate).address())
+                .await;
+        }
+        Ok(phase)
+    }
+
+    /// Every investor thaws themselves, paying their own fee
+    async fn self_thaw(&mut self) -> Result<PhaseCost, String> {
+        let mut phase = PhaseCost::new("Investor self-thaws");
+        let started = Instant::now();
+        let mut blockhash = self.blockhash().await?;
+
+        for index in 0..self.investors.len() {
+            if index % BLOCKHASH_REFRESH == 0 {
+                blockhash = self.blockhash().await?;
+            }
+            let investor = &self.investors[index];
+            let transaction = Transaction::new_signed_with_payer(
+                &[self.self_thaw_instruction(&investor.pubkey())],
+                Some(&investor.pubkey()),
+                &[investor],
+                blockhash,
+            );
+            self.submit(transaction, &mut phase)
+                .await
+                .map_err(|e| format!("Investor {} could not thaw: {}", index, e))?;
+        }
+        phase.elapsed = started.elapsed();
+        Ok(phase)
+    }
+}
+
+/// Onboard 10,000 investors and report what it cost
+#[tokio::test]
+async fn benchmark_onboarding_10k_investors() {
+    let (report, campaign) = run_onboarding_benchmark().await;
+
+    if let Some(campaign) = &campaign {
+        println!("{}", campaign.to_markdown());
+        if let Err(e) = costs::generate_cost_report(
+            std::slice::from_ref(campaign),
+            &[],
+            "../../tests/reports/onboarding_costs.md",
+        ) {
+            panic!("Failed to generate onboarding cost report: {}", e);
+        }
+    }
+    if let Err(e) = reporting::generate_test_report(
+        std::slice::from_ref(&report),
+        "Onboarding Benchmark Results",
+        "../../tests/reports/onboarding_benchmark.md",
+    ) {
+        panic!("Failed to generate onboarding benchmark report: {}", e);
+    }
+
+    assert!(
+        report.passed,
+        "Onboarding benchmark failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_onboarding_benchmark() -> (TestResultReport, Option<CampaignCost>) {
+    let test_name = "Onboard 10k Investors";
+    let mut assertions = 0;
+    if let Some(skipped) = suite_config::skip_unless(SuiteCategory::LoadTests, test_name) {
+        return (skipped, None);
+    }
+
+    let investors = INVESTOR_COUNT;
+    let mut campaign = Campaign::start(test_name, investors).await;
+    let payer = campaign.payer.pubkey();
+    let payer_before = campaign.balance(payer).await;
+
+    let mut cost = CampaignCost::new("Onboarding 10k investors", investors);
+    let phases = [campaign.initialize().await, campaign.add_investors().await];
+    for phase in phases {
+        match phase {
+            Ok(phase) => cost.add_phase(phase),
+            Err(e) => return (TestResultReport::failure(test_name, e), None),
+        }
+    }
+    let payer_spent = payer_before - campaign.balance(payer).await;
+
+    // Assertion 1: The issuer paid exactly the fees and rent accounted for,
+    // plus the investors' funding
+    assertions += 1;
+    let funding = INVESTOR_FUNDING * investors as u64;
+    if payer_spent != cost.fees() + cost.rent() + funding {
+        return (
+            TestResultReport::failure(
+                test_name,
+                format!(
+                    "Issuer spent {} lamports, accounted for {} fees + {} rent + {} funding",
+                    payer_spent,
+                    cost.fees(),
+                    cost.rent(),
+                    funding
+                ),
+            ),
+            Some(cost),
+        );
+    }
+
+    // Assertion 2: Every investor thawed themselves
+    assertions += 1;
+    match campaign.self_thaw().await {
+        Ok(phase) => cost.add_phase(phase),
+        Err(e) => return (TestResultReport::failure(test_name, e), Some(cost)),
+    }
+
+    // Assertion 3: Investors paid only their own fee
+    assertions += 1;
+    let sample = campaign.investors[investors - 1].pubkey();
+    let fee = campaign.context.lamports_per_signature;
+    let remaining = campaign.balance(sample).await;
+    if remaining != INVESTOR_FUNDING - fee {
+        return (
+            TestResultReport::failure(
+                test_name,
+                format!(
+                    "Investor has {} lamports left, expected {}",
+                    remaining,
+                    INVESTOR_FUNDING - fee
+                ),
+            ),
+            Some(cost),
+        );
+    }
+
+    // Assertion 4: Onboarding stays affordable per investor
+    assertions += 1;
+    if cost.per_user_lamports() > MAX_COST_PER_INVESTOR {
+        return (
+            TestResultReport::failure(
+                test_name,
+                format!(
+                    "Onboarding costs {} lamports per investor, above {}",
+                    cost.per_user_lamports(),
+                    MAX_COST_PER_INVESTOR
+                ),
+            ),
+            Some(cost),
+        );
+    }
+
+    (TestResultReport::success(test_name, assertions), Some(cost))
+}
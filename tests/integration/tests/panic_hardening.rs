@@ -0,0 +1,365 @@
+//! Gate program panic hardening tests
+//!
+//! A gate that panics burns the whole compute budget and fails with an
+//! opaque "program panicked" instead of the error that explains the
+//! rejection. Every input in [`CORPUS`] panicked a gate program before its
+//! writes and arithmetic returned typed errors; the corpus is replayed on
+//! every run and each input must now fail with its documented error.
+//!
+//! The fuzz smoke run (`fuzz_smoke` category) sends random instruction data
+//! with random, possibly aliased, account lists to every gate program and
+//! checks the bank keeps answering with typed errors.
+
+use borsh::BorshSerialize;
+use jurisdiction_registry::{
+    holder_address, registry_address, JurisdictionRegistry, SET_HOLDER_JURISDICTION,
+    SET_JURISDICTION,
+};
+use proptest::{
+    collection::vec,
+    prelude::*,
+    strategy::ValueTree,
+    test_runner::{Config as ProptestConfig, TestRunner},
+};
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction, InstructionError},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+
+use token_acl_integration_tests::{
+    program_test::rent_exempt_account,
+    reporting,
+    suite_config::{self, SuiteCategory},
+    TestResultReport,
+};
+
+/// Random transactions sent per fuzz smoke run
+const FUZZ_CASES: u32 = 96;
+
+/// The instruction a corpus input sends
+#[derive(Debug, Clone, Copy)]
+enum CorpusInstruction {
+    SetJurisdiction,
+    SetHolderJurisdiction,
+}
+
+/// An input that panicked a gate program, and the error it returns now
+struct PanicInput {
+    name: &'static str,
+    /// What panicked, and where
+    panicked: &'static str,
+    /// Version the registry holds before the instruction
+    registry_version: u64,
+    /// Size of a holder record already at the holder's PDA, if any
+    holder_record_len: Option<usize>,
+    instruction: CorpusInstruction,
+    expected: InstructionError,
+}
+
+/// Inputs that panicked a gate program, replayed on every run
+const CORPUS: &[PanicInput] = &[
+    PanicInput {
+        name: "registry_version_overflow",
+        panicked: "`version += 1` overflowed in JurisdictionRegistry::set",
+        registry_version: u64::MAX,
+        holder_record_len: None,
+        instruction: CorpusInstruction::SetJurisdiction,
+        expected: InstructionError::ArithmeticOverflow,
+    },
+    PanicInput {
+        name: "legacy_holder_record",
+        panicked: "copy_from_slice into a holder record allocated without its bump",
+        registry_version: 0,
+        holder_record_len: Some(32 + 32 + 2),
+        instruction: CorpusInstruction::SetHolderJurisdiction,
+        expected: InstructionError::InvalidAccountData,
+    },
+    PanicInput {
+        name: "oversized_holder_record",
+        panicked: "copy_from_slice into a holder record larger than the record",
+        registry_version: 0,
+        holder_record_len: Some(128),
+        instruction: CorpusInstruction::SetHolderJurisdiction,
+        expected: InstructionError::InvalidAccountData,
+    },
+];
+
+/// A bank running every gate program, with a registry written directly
+/// into its PDA so it can hold states no instruction reaches
+struct HardeningBank {
+    context: ProgramTestContext,
+    registry_program: Pubkey,
+    allow_list: Pubkey,
+    gate: Pubkey,
+    authority: Keypair,
+    holder: Pubkey,
+    mint: Pubkey,
+}
+
+impl HardeningBank {
+    async fn new(registry_version: u64, holder_record_len: Option<usize>) -> Self {
+        let registry_program = Pubkey::new_unique();
+        let allow_list = Pubkey::new_unique();
+        let gate = Pubkey::new_unique();
+        let authority = Keypair::new();
+        let holder = Pubkey::new_unique();
+
+        let mut program_test = ProgramTest::new(
+            "jurisdiction_registry",
+            registry_program,
+            processor!(jurisdiction_registry::process_instruction),
+        );
+        program_test.add_program(
+            "production_allow_list",
+            allow_list,
+            processor!(production_allow_list::process_instruction),
+        );
+        program_test.add_program(
+            "hybrid_gate",
+            gate,
+            processor!(hybrid_gate::process_instruction),
+        );
+
+        let registry = registry_address(&authority.pubkey(), &registry_program);
+        let mut data = JurisdictionRegistry {
+            authority: authority.pubkey(),
+            version: registry_version,
+            bump: registry.bump(),
+            entries: Vec::new(),
+        }
+        .try_to_vec()
+        .unwrap();
+        data.resize(JurisdictionRegistry::LEN, 0);
+        program_test.add_account(
+            registry.address(),
+            rent_exempt_account(data, registry_program),
+        );
+        if let Some(len) = holder_record_len {
+            let record = holder_address(&registry.address(), &holder, &registry_program);
+            program_test.add_account(
+                record.address(),
+                rent_exempt_account(vec![0; len], registry_program),
+            );
+        }
+
+        Self {
+            context: program_test.start_with_context().await,
+            registry_program,
+            allow_list,
+            gate,
+            authority,
+            holder,
+            mint: Pubkey::new_unique(),
+        }
+    }
+
+    fn registry(&self) -> Pubkey {
+        registry_address(&self.authority.pubkey(), &self.registry_program).address()
+    }
+
+    fn holder_record(&self) -> Pubkey {
+        holder_address(&self.registry(), &self.holder, &self.registry_program).address()
+    }
+
+    fn corpus_instruction(&self, instruction: CorpusInstruction) -> Instruction {
+        match instruction {
+            CorpusInstruction::SetJurisdiction => Instruction::new_with_bytes(
+                self.registry_program,
+                &[SET_JURISDICTION, b'U', b'S', 0],
+                vec![
+                    AccountMeta::new(self.registry(), false),
+                    AccountMeta::new_readonly(self.authority.pubkey(), true),
+                ],
+            ),
+            CorpusInstruction::SetHolderJurisdiction => Instruction::new_with_bytes(
+                self.registry_program,
+                &[SET_HOLDER_JURISDICTION, b'U', b'S'],
+                vec![
+                    AccountMeta::new_readonly(self.registry(), false),
+                    AccountMeta::new(self.holder_record(), false),
+                    AccountMeta::new_readonly(self.holder, false),
+                    AccountMeta::new_readonly(self.authority.pubkey(), true),
+                    AccountMeta::new(self.context.payer.pubkey(), true),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            ),
+        }
+    }
+
+    /// Accounts fuzzed instructions draw from: signers, the registry's
+    /// accounts, and accounts no gate knows
+    fn account_pool(&self) -> Vec<(Pubkey, bool)> {
+        vec![
+            (self.context.payer.pubkey(), true),
+            (self.authority.pubkey(), true),
+            (self.registry(), false),
+            (self.holder_record(), false),
+            (self.holder, false),
+            (self.mint, false),
+            (Pubkey::new_unique(), false),
+            (system_program::id(), false),
+        ]
+    }
+
+    /// Send `instruction` signed by whichever of the payer and authority it
+    /// names; `Err` only when the bank itself failed to answer
+    async fn send(
+        &mut self,
+        instruction: Instruction,
+    ) -> Result<Result<(), TransactionError>, String> {
+        let mut transaction =
+            Transaction::new_with_payer(&[instruction], Some(&self.context.payer.pubkey()));
+        let required = transaction.message.header.num_required_signatures as usize;
+        let signers: Vec<&Keypair> = [&self.context.payer, &self.authority]
+            .into_iter()
+            .filter(|signer| {
+                transaction.message.account_keys[..required].contains(&signer.pubkey())
+            })
+            .collect();
+        transaction.sign(&signers, self.context.last_blockhash);
+        self.context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .map(|outcome| outcome.result)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Test 1: Every corpus input fails with its typed error
+#[tokio::test]
+async fn test_panic_corpus_returns_errors() {
+    let report = run_panic_corpus_test().await;
+    assert!(
+        report.passed,
+        "Panic corpus test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_panic_corpus_test() -> TestResultReport {
+    let test_name = "Panic Corpus Returns Errors";
+    let mut assertions = 0;
+
+    for input in CORPUS {
+        let mut bank = HardeningBank::new(input.registry_version, input.holder_record_len).await;
+        let instruction = bank.corpus_instruction(input.instruction);
+
+        // Assertion: the input that panicked fails with its typed error
+        assertions += 1;
+        let expected = TransactionError::InstructionError(0, input.expected.clone());
+        match bank.send(instruction).await {
+            Ok(Err(error)) if error == expected => {}
+            outcome => {
+                return TestResultReport::failure(
+                    test_name,
+                    format!(
+                        "{} (previously: {}) returned {:?}, expected {:?}",
+                        input.name, input.panicked, outcome, expected
+                    ),
+                )
+            }
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// A fuzzed instruction: target program, data, and (pool index, writable)
+/// per account
+type FuzzCase = (usize, Vec<u8>, Vec<(usize, bool)>);
+
+fn fuzz_case() -> impl Strategy<Value = FuzzCase> {
+    // Half the data starts with a real discriminator so it reaches the
+    // instruction handlers instead of stopping at dispatch
+    let data = prop_oneof![
+        vec(any::<u8>(), 0..48),
+        (0u8..8, vec(any::<u8>(), 0..40)).prop_map(|(discriminator, mut rest)| {
+            rest.insert(0, discriminator);
+            rest
+        }),
+    ];
+    (0..3usize, data, vec((0..8usize, any::<bool>()), 0..8))
+}
+
+/// Test 2: Random data and aliased accounts never take a gate down
+#[tokio::test]
+async fn test_fuzz_smoke() {
+    let report = run_fuzz_smoke_test().await;
+    assert!(report.passed, "Fuzz smoke test failed: {:?}", report.error);
+}
+
+async fn run_fuzz_smoke_test() -> TestResultReport {
+    let test_name = "Fuzz Smoke";
+    if let Some(skipped) = suite_config::skip_unless(SuiteCategory::FuzzSmoke, test_name) {
+        return skipped;
+    }
+    let mut assertions = 0;
+
+    let mut bank = HardeningBank::new(0, Some(32 + 32 + 2 + 1)).await;
+    let programs = [bank.registry_program, bank.allow_list, bank.gate];
+    let pool = bank.account_pool();
+
+    let mut runner = TestRunner::new(ProptestConfig {
+        failure_persistence: None,
+        ..ProptestConfig::default()
+    });
+    let strategy = fuzz_case();
+    for case in 0..FUZZ_CASES {
+        let (program, data, accounts) = match strategy.new_tree(&mut runner) {
+            Ok(tree) => tree.current(),
+            Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+        };
+        let metas = accounts
+            .iter()
+            .map(|&(index, writable)| {
+                let (address, signer) = pool[index];
+                if writable {
+                    AccountMeta::new(address, signer)
+                } else {
+                    AccountMeta::new_readonly(address, signer)
+                }
+            })
+            .collect();
+        let instruction = Instruction::new_with_bytes(programs[program], &data, metas);
+
+        // Assertion: the bank answers, and a rejection is a typed error
+        assertions += 1;
+        match bank.send(instruction.clone()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(TransactionError::InstructionError(0, error)))
+                if !matches!(error, InstructionError::ProgramFailedToComplete) => {}
+            Ok(Err(TransactionError::AlreadyProcessed)) => {}
+            outcome => {
+                return TestResultReport::failure(
+                    test_name,
+                    format!("Case {} {:?} returned {:?}", case, instruction, outcome),
+                )
+            }
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate panic hardening report
+#[tokio::test]
+async fn generate_panic_hardening_report() {
+    let results = vec![run_panic_corpus_test().await, run_fuzz_smoke_test().await];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Panic Hardening Test Results",
+        "../../tests/reports/panic_hardening.md",
+    ) {
+        panic!("Failed to generate panic hardening report: {}", e);
+    }
+
+    for result in &results {
+        assert!(result.passed, "{} failed: {:?}", result.name, result.error);
+    }
+}
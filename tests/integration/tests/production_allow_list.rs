@@ -0,0 +1,3805 @@
+//! Execution tests for the production allow list gate program
+//!
+//! Unlike the logic-level suites, these tests deploy the program into
+//! `solana-program-test` and assert on real account state after each
+//! transaction.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use production_allow_list::{
+    pending_authority_address, AccessLevel, AddToAllowListArgs, AllowListRecord, Config, InitMode,
+    PendingAuthority, RemoveMode, ACCEPT_AUTHORITY, ADD_TO_ALLOW_LIST, ALLOW_LIST_SEED,
+    CANCEL_AUTHORITY_TRANSFER, CLOSE_RECORD, CONFIG_SEED, INITIALIZE, LIFT_REVOCATION,
+    REMOVE_FROM_ALLOW_LIST, REVOKE, UPDATE_AUTHORITY,
+};
+use solana_program_test::{processor, ProgramTest};
+use solana_sdk::{
+    account::Account,
+    clock::Clock,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    packet::PACKET_DATA_SIZE,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction, system_program,
+    transaction::Transaction,
+};
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use spl_token_2022::{
+    extension::StateWithExtensions,
+    instruction as token_instruction,
+    solana_program::program_pack::Pack,
+    state::{Account as TokenAccount, AccountState},
+};
+use token_acl_interface::find_missing_record_policy_address;
+
+use token_acl_integration_tests::{
+    account_snapshots::AccountSnapshot,
+    analytics::{DecisionAnalytics, GateOperation},
+    bundles::{self, BundleOutcome, BundleTransaction, TransactionBundle, MAX_BUNDLE_TRANSACTIONS},
+    context::TestContext,
+    fixtures::test_data,
+    history::{
+        AccountTimeline, AuthorizationMode, FreezeAction, HistoryConfig, RecordedTransaction,
+    },
+    invariants::{
+        ConfigAuthorityUnchanged, FreezeStateConsistency, InvariantEngine, LamportInvariants, Step,
+    },
+    labels,
+    metrics::TestMetrics,
+    nonce,
+    onboarding::{self, CreateAndThawBuilder},
+    program_test, reporting, trace, TestResultReport,
+};
+
+/// A bank of the test's own with an initialized allow list config
+///
+/// Every transaction the fixture runs is a step checked by the invariant
+/// engine: rent safety and lamport flow, the config authority, and freeze
+/// state against the allow list records. Steps that close records declare
+/// the refund recipient with `invariants.allow_refunds_to` first. The fees
+/// and rent of every step are counted in `metrics`.
+struct AllowListFixture {
+    context: TestContext,
+    program_id: Pubkey,
+    authority: Keypair,
+    mint: Pubkey,
+    config: Pubkey,
+    invariants: InvariantEngine,
+    metrics: TestMetrics,
+}
+
+impl AllowListFixture {
+    async fn new(test_name: &str) -> Self {
+        Self::with_mint(test_name, Pubkey::new_unique()).await
+    }
+
+    async fn with_mint(test_name: &str, mint: Pubkey) -> Self {
+        Self::with_accounts(test_name, mint, |_| Vec::new()).await
+    }
+
+    /// A fixture for `mint` whose bank starts with the accounts `accounts`
+    /// returns for the fixture's authority, e.g. the mint itself
+    async fn with_accounts(
+        test_name: &str,
+        mint: Pubkey,
+        accounts: impl FnOnce(&Pubkey) -> Vec<(Pubkey, Account)>,
+    ) -> Self {
+        let program_id = Pubkey::new_unique();
+        let mut program_test = ProgramTest::new(
+            "production_allow_list",
+            program_id,
+            processor!(production_allow_list::process_instruction),
+        );
+        let authority = Keypair::new();
+        for (address, account) in accounts(&authority.pubkey()) {
+            program_test.add_account(address, account);
+        }
+        let context = TestContext::start(test_name, program_test)
+            .await
+            .expect("program-test bank should start");
+
+        let (config, _) = Pubkey::find_program_address(&[CONFIG_SEED, mint.as_ref()], &program_id);
+        labels::label(program_id, "gate");
+        labels::label(authority.pubkey(), "authority");
+        labels::label(mint, "mint");
+        labels::label_derived(config, "config", &mint);
+
+        let mut invariants = InvariantEngine::new();
+        invariants
+            .register(LamportInvariants::new(&[program_id], context.rent.clone()))
+            .register(ConfigAuthorityUnchanged::allow_list(&mint, &program_id))
+            .register(FreezeStateConsistency::allow_list(mint, program_id));
+
+        let mut fixture = Self {
+            context,
+            program_id,
+            authority,
+            mint,
+            config,
+            invariants,
+            metrics: TestMetrics::default(),
+        };
+
+        let authority = fixture.authority.insecure_clone();
+        let initialize =
+            fixture.initialize_instruction(&mint, &authority.pubkey(), InitMode::Create);
+        fixture
+            .send(&[initialize], &[&authority])
+            .await
+            .expect("config initialization should succeed");
+
+        fixture
+    }
+
+    /// `Initialize` of the config for `mint`, which need not be the
+    /// fixture's
+    fn initialize_instruction(
+        &self,
+        mint: &Pubkey,
+        authority: &Pubkey,
+        mode: InitMode,
+    ) -> Instruction {
+        let (config, _) =
+            Pubkey::find_program_address(&[CONFIG_SEED, mint.as_ref()], &self.program_id);
+        Instruction::new_with_bytes(
+            self.program_id,
+            &[INITIALIZE, mode as u8],
+            vec![
+                AccountMeta::new(config, false),
+                AccountMeta::new_readonly(*mint, false),
+                AccountMeta::new_readonly(*authority, true),
+                AccountMeta::new(self.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    fn allow_list_pda(&self, user: &Pubkey) -> Pubkey {
+        let (pda, _) = Pubkey::find_program_address(
+            &[ALLOW_LIST_SEED, self.mint.as_ref(), user.as_ref()],
+            &self.program_id,
+        );
+        labels::label_derived(pda, "allow_pda", user)
+    }
+
+    fn missing_record_policy_pda(&self) -> Pubkey {
+        find_missing_record_policy_address(&self.mint, &self.program_id).0
+    }
+
+    fn add_instruction(&self, user: &Pubkey) -> Instruction {
+        self.add_instruction_with_payer(user, &self.context.payer.pubkey())
+    }
+
+    fn add_instruction_with_payer(&self, user: &Pubkey, rent_payer: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            self.program_id,
+            &[ADD_TO_ALLOW_LIST],
+            vec![
+                AccountMeta::new_readonly(self.config, false),
+                AccountMeta::new(self.allow_list_pda(user), false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(*user, false),
+                AccountMeta::new_readonly(self.authority.pubkey(), true),
+                AccountMeta::new(*rent_payer, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// `AddToAllowList` that leaves an existing record as it is
+    fn add_if_needed_instruction(&self, user: &Pubkey, rent_payer: &Pubkey) -> Instruction {
+        let mut add = self.add_instruction_with_payer(user, rent_payer);
+        add.data.push(InitMode::IfNeeded as u8);
+        add
+    }
+
+    /// `AddToAllowList` with explicit args, in `Create` mode
+    fn add_with_args_instruction(&self, user: &Pubkey, args: &AddToAllowListArgs) -> Instruction {
+        let mut add = self.add_instruction(user);
+        add.data.push(InitMode::Create as u8);
+        add.data
+            .extend(args.try_to_vec().expect("add args serialize"));
+        add
+    }
+
+    async fn add_user_with_args(
+        &mut self,
+        user: &Pubkey,
+        args: &AddToAllowListArgs,
+    ) -> Result<(), String> {
+        let add = self.add_with_args_instruction(user, args);
+        let authority = self.authority.insecure_clone();
+        self.send(&[add], &[&authority]).await
+    }
+
+    async fn clock(&mut self) -> Clock {
+        self.context
+            .banks_client
+            .get_sysvar::<Clock>()
+            .await
+            .expect("the bank has a clock")
+    }
+
+    fn remove_instruction(
+        &self,
+        user: &Pubkey,
+        authority: &Pubkey,
+        mode: RemoveMode,
+        recipient: Option<&Pubkey>,
+    ) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new_readonly(self.config, false),
+            AccountMeta::new(self.allow_list_pda(user), false),
+            AccountMeta::new_readonly(*authority, true),
+        ];
+        if let Some(recipient) = recipient {
+            accounts.push(AccountMeta::new(*recipient, false));
+        }
+        Instruction::new_with_bytes(
+            self.program_id,
+            &[REMOVE_FROM_ALLOW_LIST, mode as u8],
+            accounts,
+        )
+    }
+
+    /// `CloseRecord` for `user`'s record, refunding its rent to `recipient`
+    fn close_record_instruction(
+        &self,
+        user: &Pubkey,
+        authority: &Pubkey,
+        recipient: &Pubkey,
+    ) -> Instruction {
+        Instruction::new_with_bytes(
+            self.program_id,
+            &[CLOSE_RECORD],
+            vec![
+                AccountMeta::new_readonly(self.config, false),
+                AccountMeta::new(self.allow_list_pda(user), false),
+                AccountMeta::new_readonly(*authority, true),
+                AccountMeta::new(*recipient, false),
+            ],
+        )
+    }
+
+    fn pending_authority_pda(&self) -> Pubkey {
+        let pda = pending_authority_address(&self.mint, &self.program_id).address();
+        labels::label_derived(pda, "pending_authority", &self.mint)
+    }
+
+    /// `UpdateAuthority` proposing `proposed`, signed by `authority`
+    fn propose_authority_instruction(&self, authority: &Pubkey, proposed: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            self.program_id,
+            &[UPDATE_AUTHORITY],
+            vec![
+                AccountMeta::new_readonly(self.config, false),
+                AccountMeta::new_readonly(*authority, true),
+                AccountMeta::new_readonly(*proposed, false),
+                AccountMeta::new(self.pending_authority_pda(), false),
+                AccountMeta::new(self.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// `AcceptAuthority` signed by `signer`, refunding the proposal's rent to
+    /// `recipient`
+    fn accept_authority_instruction(&self, signer: &Pubkey, recipient: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            self.program_id,
+            &[ACCEPT_AUTHORITY],
+            vec![
+                AccountMeta::new(self.config, false),
+                AccountMeta::new(self.pending_authority_pda(), false),
+                AccountMeta::new_readonly(*signer, true),
+                AccountMeta::new(*recipient, false),
+            ],
+        )
+    }
+
+    /// `CancelAuthorityTransfer` signed by `authority`, refunding the
+    /// proposal's rent to `recipient`
+    fn cancel_authority_instruction(&self, authority: &Pubkey, recipient: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            self.program_id,
+            &[CANCEL_AUTHORITY_TRANSFER],
+            vec![
+                AccountMeta::new_readonly(self.config, false),
+                AccountMeta::new(self.pending_authority_pda(), false),
+                AccountMeta::new_readonly(*authority, true),
+                AccountMeta::new(*recipient, false),
+            ],
+        )
+    }
+
+    /// `Revoke` or `LiftRevocation` (by discriminator) for `user`'s record
+    fn revocation_instruction(
+        &self,
+        discriminator: u8,
+        user: &Pubkey,
+        authority: &Pubkey,
+    ) -> Instruction {
+        Instruction::new_with_bytes(
+            self.program_id,
+            &[discriminator],
+            vec![
+                AccountMeta::new_readonly(self.config, false),
+                AccountMeta::new(self.allow_list_pda(user), false),
+                AccountMeta::new_readonly(*authority, true),
+            ],
+        )
+    }
+
+    fn can_thaw_instruction(&self, user: &Pubkey) -> Instruction {
+        self.can_thaw_instruction_from(&self.context.payer.pubkey(), false, user)
+    }
+
+    /// Builds a thaw check whose sRFC 37 caller is independent of the fee payer
+    fn can_thaw_instruction_from(
+        &self,
+        caller: &Pubkey,
+        caller_signs: bool,
+        user: &Pubkey,
+    ) -> Instruction {
+        self.can_thaw_instruction_for(caller, caller_signs, &Pubkey::new_unique(), user)
+    }
+
+    fn can_thaw_instruction_for(
+        &self,
+        caller: &Pubkey,
+        caller_signs: bool,
+        token_account: &Pubkey,
+        user: &Pubkey,
+    ) -> Instruction {
+        let (extra_metas, _) = Pubkey::find_program_address(
+            &[test_data::THAW_EXTRA_ACCOUNT_METAS_SEED, self.mint.as_ref()],
+            &self.program_id,
+        );
+        Instruction::new_with_bytes(
+            self.program_id,
+            &test_data::THAW_DISCRIMINATOR,
+            vec![
+                AccountMeta::new_readonly(*caller, caller_signs),
+                AccountMeta::new_readonly(*token_account, false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(extra_metas, false),
+                AccountMeta::new_readonly(*user, false),
+                AccountMeta::new_readonly(self.allow_list_pda(user), false),
+                AccountMeta::new_readonly(self.missing_record_policy_pda(), false),
+            ],
+        )
+    }
+
+    async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), String> {
+        let payer = self.context.payer.insecure_clone();
+        self.send_with_payer(instructions, &payer, signers).await
+    }
+
+    /// Submits a transaction whose fees are paid by `fee_payer`
+    async fn send_with_payer(
+        &mut self,
+        instructions: &[Instruction],
+        fee_payer: &Keypair,
+        signers: &[&Keypair],
+    ) -> Result<(), String> {
+        let recent_blockhash = self
+            .context
+            .get_new_latest_blockhash()
+            .await
+            .map_err(|e| e.to_string())?;
+        self.submit(instructions, fee_payer, signers, recent_blockhash)
+            .await
+    }
+
+    async fn submit(
+        &mut self,
+        instructions: &[Instruction],
+        fee_payer: &Keypair,
+        signers: &[&Keypair],
+        blockhash: Hash,
+    ) -> Result<(), String> {
+        let mut all_signers: Vec<&Keypair> = vec![fee_payer];
+        all_signers.extend_from_slice(signers);
+
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&fee_payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        self.submit_transaction(transaction).await
+    }
+
+    /// Submits a signed (possibly nonce-anchored) transaction, checking the
+    /// invariants over the accounts it references
+    async fn submit_transaction(&mut self, transaction: Transaction) -> Result<(), String> {
+        let before = self.snapshot(&transaction.message.account_keys).await;
+        let result = nonce::submit(&mut self.context.banks_client, transaction.clone()).await;
+        self.check_invariants(&transaction, before).await;
+        result
+    }
+
+    /// `addresses`, and the accounts the invariants watch for them, as they
+    /// are now
+    async fn snapshot(&mut self, addresses: &[Pubkey]) -> AccountSnapshot {
+        self.invariants
+            .snapshot(&mut self.context.banks_client, addresses)
+            .await
+            .expect("account snapshot should succeed")
+    }
+
+    /// Assert the invariants over the step `transactions` ran since `before`
+    async fn check_step(
+        &mut self,
+        name: &str,
+        transactions: &[&Transaction],
+        before: AccountSnapshot,
+    ) {
+        let after = self
+            .invariants
+            .snapshot_after(&mut self.context.banks_client, &before)
+            .await
+            .expect("account snapshot should succeed");
+        let step = Step::from_transactions(name, transactions, before, after);
+        self.invariants.assert_holds(&step);
+        self.metrics.record_step(
+            transactions,
+            self.context.lamports_per_signature,
+            &step.diff,
+        );
+    }
+
+    /// Assert the invariants over what `transaction` changed since `before`
+    async fn check_invariants(&mut self, transaction: &Transaction, before: AccountSnapshot) {
+        let name = format!("transaction {}", transaction.signatures[0]);
+        self.check_step(&name, &[transaction], before).await;
+    }
+
+    /// Runs a thaw check and returns its outcome with the program logs
+    async fn thaw_check(&mut self, user: &Pubkey) -> Result<(bool, Vec<String>), String> {
+        let can_thaw = self.can_thaw_instruction(user);
+        let recent_blockhash = self
+            .context
+            .get_new_latest_blockhash()
+            .await
+            .map_err(|e| e.to_string())?;
+        let transaction = Transaction::new_signed_with_payer(
+            &[can_thaw],
+            Some(&self.context.payer.pubkey()),
+            &[&self.context.payer],
+            recent_blockhash,
+        );
+        let before = self.snapshot(&transaction.message.account_keys).await;
+        let outcome = self
+            .context
+            .banks_client
+            .process_transaction_with_metadata(transaction.clone())
+            .await
+            .map_err(|e| e.to_string())?;
+        trace::record(&transaction, &outcome);
+        self.check_invariants(&transaction, before).await;
+        let logs = outcome
+            .metadata
+            .map(|metadata| metadata.log_messages)
+            .unwrap_or_default();
+        Ok((outcome.result.is_ok(), logs))
+    }
+
+    /// Submits a transaction and records it as it would appear in history
+    async fn send_recorded(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<RecordedTransaction, String> {
+        let recent_blockhash = self
+            .context
+            .get_new_latest_blockhash()
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut all_signers: Vec<&Keypair> = vec![&self.context.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.context.payer.pubkey()),
+            &all_signers,
+            recent_blockhash,
+        );
+
+        let before = self.snapshot(&transaction.message.account_keys).await;
+        let banks_client = &mut self.context.banks_client;
+        let slot = banks_client
+            .get_root_slot()
+            .await
+            .map_err(|e| e.to_string())?;
+        let clock: Clock = banks_client.get_sysvar().await.map_err(|e| e.to_string())?;
+        let outcome = banks_client
+            .process_transaction_with_metadata(transaction.clone())
+            .await
+            .map_err(|e| e.to_string())?;
+        self.check_invariants(&transaction, before).await;
+        let logs = outcome
+            .metadata
+            .map(|metadata| metadata.log_messages)
+            .unwrap_or_default();
+
+        Ok(RecordedTransaction::from_transaction(
+            &transaction,
+            slot,
+            Some(clock.unix_timestamp),
+            logs,
+            outcome.result.is_ok(),
+        ))
+    }
+
+    /// Warps to a later slot and sets the cluster clock to `unix_timestamp`
+    async fn advance_clock(&mut self, unix_timestamp: i64) -> Result<(), String> {
+        let slot = self
+            .context
+            .banks_client
+            .get_root_slot()
+            .await
+            .map_err(|e| e.to_string())?;
+        self.context
+            .warp_to_slot(slot + 100)
+            .map_err(|e| format!("{:?}", e))?;
+
+        let mut clock: Clock = self
+            .context
+            .banks_client
+            .get_sysvar()
+            .await
+            .map_err(|e| e.to_string())?;
+        clock.unix_timestamp = unix_timestamp;
+        self.context.set_sysvar(&clock);
+        Ok(())
+    }
+
+    /// Creates a token account of the fixture mint at `token_account`'s address
+    async fn open_token_account(
+        &mut self,
+        token_account: &Keypair,
+        owner: &Pubkey,
+    ) -> Result<(), String> {
+        let rent = self.context.rent.clone();
+        let instructions = [
+            system_instruction::create_account(
+                &self.context.payer.pubkey(),
+                &token_account.pubkey(),
+                rent.minimum_balance(TokenAccount::LEN),
+                TokenAccount::LEN as u64,
+                &spl_token_2022::id(),
+            ),
+            token_instruction::initialize_account3(
+                &spl_token_2022::id(),
+                &token_account.pubkey(),
+                &self.mint,
+                owner,
+            )
+            .map_err(|e| e.to_string())?,
+        ];
+        self.send(&instructions, &[token_account]).await
+    }
+
+    /// Freezes or thaws `token_account` with the fixture's freeze authority
+    async fn set_frozen(&mut self, token_account: &Pubkey, frozen: bool) -> Result<(), String> {
+        let authority = self.authority.insecure_clone();
+        let change = if frozen {
+            token_instruction::freeze_account
+        } else {
+            token_instruction::thaw_account
+        };
+        let instruction = change(
+            &spl_token_2022::id(),
+            token_account,
+            &self.mint,
+            &authority.pubkey(),
+            &[],
+        )
+        .map_err(|e| e.to_string())?;
+        self.send(&[instruction], &[&authority]).await
+    }
+
+    async fn token_balance(&mut self, token_account: &Pubkey) -> Option<u64> {
+        let account = self
+            .context
+            .banks_client
+            .get_account(*token_account)
+            .await
+            .ok()??;
+        let state = StateWithExtensions::<TokenAccount>::unpack(&account.data).ok()?;
+        Some(state.base.amount)
+    }
+
+    async fn is_frozen(&mut self, token_account: &Pubkey) -> Option<bool> {
+        let account = self
+            .context
+            .banks_client
+            .get_account(*token_account)
+            .await
+            .ok()??;
+        let state = StateWithExtensions::<TokenAccount>::unpack(&account.data).ok()?;
+        Some(state.base.state == AccountState::Frozen)
+    }
+
+    /// Creates and funds a keypair that can sponsor fees and rent
+    async fn funded_sponsor(&mut self, lamports: u64) -> Result<Keypair, String> {
+        let sponsor = Keypair::new();
+        let fund =
+            system_instruction::transfer(&self.context.payer.pubkey(), &sponsor.pubkey(), lamports);
+        self.send(&[fund], &[]).await?;
+        Ok(sponsor)
+    }
+
+    /// Creates a durable nonce account owned by `authority`, funded by the fixture payer
+    async fn create_nonce_account(&mut self, authority: &Pubkey) -> Result<Pubkey, String> {
+        let nonce_account = Keypair::new();
+        let create = nonce::create_nonce_account_instructions(
+            &self.context.payer.pubkey(),
+            &nonce_account.pubkey(),
+            authority,
+        );
+        self.send(&create, &[&nonce_account]).await?;
+        Ok(nonce_account.pubkey())
+    }
+
+    /// Reads the blockhash currently stored in a durable nonce account
+    async fn nonce_blockhash(&mut self, nonce_account: &Pubkey) -> Option<Hash> {
+        nonce::fetch_nonce(&mut self.context.banks_client, nonce_account).await
+    }
+
+    async fn add_user(&mut self, user: &Pubkey) -> Result<(), String> {
+        let add = self.add_instruction(user);
+        let authority = self.authority.insecure_clone();
+        self.send(&[add], &[&authority]).await
+    }
+
+    async fn lamports(&mut self, address: &Pubkey) -> u64 {
+        self.context
+            .banks_client
+            .get_balance(*address)
+            .await
+            .unwrap_or_default()
+    }
+
+    async fn record(&mut self, user: &Pubkey) -> Option<AllowListRecord> {
+        let record = self.allow_list_pda(user);
+        let account = self.context.banks_client.get_account(record).await.ok()??;
+        AllowListRecord::try_from_slice(&account.data).ok()
+    }
+
+    async fn account(&mut self, address: &Pubkey) -> Option<solana_sdk::account::Account> {
+        self.context.banks_client.get_account(*address).await.ok()?
+    }
+
+    async fn pending_authority(&mut self) -> Option<PendingAuthority> {
+        let pending = self.pending_authority_pda();
+        let account = self
+            .context
+            .banks_client
+            .get_account(pending)
+            .await
+            .ok()??;
+        PendingAuthority::try_from_slice(&account.data).ok()
+    }
+
+    async fn config_of(&mut self, mint: &Pubkey) -> Option<Config> {
+        let (config, _) =
+            Pubkey::find_program_address(&[CONFIG_SEED, mint.as_ref()], &self.program_id);
+        let account = self.context.banks_client.get_account(config).await.ok()??;
+        Config::try_from_slice(&account.data).ok()
+    }
+}
+
+/// Whether `result` failed because the account the instruction creates
+/// already exists
+fn failed_as_already_initialized(result: &Result<(), String>) -> bool {
+    let expected = InstructionError::AccountAlreadyInitialized.to_string();
+    matches!(result, Err(e) if e.contains(&expected))
+}
+
+/// Test 1: Soft remove keeps the record but denies thaw
+#[tokio::test]
+async fn test_remove_deactivates_record() {
+    let report = run_soft_remove_test().await;
+    assert!(report.passed, "Soft remove test failed: {:?}", report.error);
+}
+
+async fn run_soft_remove_test() -> TestResultReport {
+    let test_name = "Allow List Soft Remove";
+    let mut assertions = 0;
+
+    let mut fixture = AllowListFixture::new(test_name).await;
+    let user = labels::label(Pubkey::new_unique(), "user");
+
+    if let Err(e) = fixture.add_user(&user).await {
+        return TestResultReport::failure(test_name, format!("Add to allow list failed: {}", e));
+    }
+    let pda = fixture.allow_list_pda(&user);
+    let lamports_before = fixture.lamports(&pda).await;
+
+    let authority = fixture.authority.insecure_clone();
+    let remove =
+        fixture.remove_instruction(&user, &authority.pubkey(), RemoveMode::Deactivate, None);
+
+    // Assertion 1: Soft remove succeeds without a rent recipient
+    assertions += 1;
+    if let Err(e) = fixture.send(&[remove], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Soft remove failed: {}", e));
+    }
+
+    // Assertion 2: Record still exists and is marked not allowed
+    assertions += 1;
+    match fixture.record(&user).await {
+        Some(record) if !record.allowed => {}
+        Some(_) => {
+            return TestResultReport::failure(
+                test_name,
+                "Record still allowed after soft remove".to_string(),
+            )
+        }
+        None => {
+            return TestResultReport::failure(
+                test_name,
+                "Soft remove must not close the record".to_string(),
+            )
+        }
+    }
+
+    // Assertion 3: Rent stays locked in the record
+    assertions += 1;
+    if fixture.lamports(&pda).await != lamports_before {
+        return TestResultReport::failure(
+            test_name,
+            "Soft remove must not move lamports".to_string(),
+        );
+    }
+
+    // Assertion 4: Deactivated record denies permissionless thaw
+    assertions += 1;
+    let can_thaw = fixture.can_thaw_instruction(&user);
+    if fixture.send(&[can_thaw], &[]).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Deactivated record still authorizes thaw".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 2: Hard close refunds rent to the recipient and denies thaw
+#[tokio::test]
+async fn test_remove_closes_record() {
+    let report = run_hard_close_test().await;
+    assert!(report.passed, "Hard close test failed: {:?}", report.error);
+}
+
+async fn run_hard_close_test() -> TestResultReport {
+    let test_name = "Allow List Hard Close";
+    let mut assertions = 0;
+
+    let mut fixture = AllowListFixture::new(test_name).await;
+    let user = labels::label(Pubkey::new_unique(), "user");
+    let recipient = Pubkey::new_unique();
+
+    if let Err(e) = fixture.add_user(&user).await {
+        return TestResultReport::failure(test_name, format!("Add to allow list failed: {}", e));
+    }
+    let pda = fixture.allow_list_pda(&user);
+    let record_lamports = fixture.lamports(&pda).await;
+
+    let authority = fixture.authority.insecure_clone();
+    let close = fixture.remove_instruction(
+        &user,
+        &authority.pubkey(),
+        RemoveMode::Close,
+        Some(&recipient),
+    );
+    fixture.invariants.allow_refunds_to(recipient);
+
+    // Assertion 1: Close succeeds
+    assertions += 1;
+    if let Err(e) = fixture.send(&[close], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Close failed: {}", e));
+    }
+
+    // Assertion 2: Record account no longer exists
+    assertions += 1;
+    if fixture
+        .context
+        .banks_client
+        .get_account(pda)
+        .await
+        .ok()
+        .flatten()
+        .is_some()
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Record account still exists after close".to_string(),
+        );
+    }
+
+    // Assertion 3: Recipient received the full rent refund
+    assertions += 1;
+    let refunded = fixture.lamports(&recipient).await;
+    if refunded != record_lamports {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Recipient should receive {} lamports, got {}",
+                record_lamports, refunded
+            ),
+        );
+    }
+
+    // Assertion 4: Closed record denies permissionless thaw
+    assertions += 1;
+    let can_thaw = fixture.can_thaw_instruction(&user);
+    if fixture.send(&[can_thaw], &[]).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Closed record still authorizes thaw".to_string(),
+        );
+    }
+
+    // Assertion 5: The user can be re-added after a close
+    assertions += 1;
+    if let Err(e) = fixture.add_user(&user).await {
+        return TestResultReport::failure(test_name, format!("Re-add after close failed: {}", e));
+    }
+
+    // Assertion 6: The cost counts the config and both records as rent, the
+    // closed record as reclaimed, and a fee for every executed transaction
+    assertions += 1;
+    let config = fixture.config;
+    let config_lamports = fixture.lamports(&config).await;
+    let cost = fixture.metrics.cost;
+    if cost.rent != config_lamports + 2 * record_lamports
+        || cost.reclaimed != record_lamports
+        || fixture.metrics.transactions != 5
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Counted {:?} over {} transactions, expected rent {} and {} reclaimed over 5",
+                cost,
+                fixture.metrics.transactions,
+                config_lamports + 2 * record_lamports,
+                record_lamports
+            ),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 3: Close requires the configured authority and a rent recipient
+#[tokio::test]
+async fn test_remove_close_validation() {
+    let report = run_close_validation_test().await;
+    assert!(
+        report.passed,
+        "Close validation test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_close_validation_test() -> TestResultReport {
+    let test_name = "Allow List Close Validation";
+    let mut assertions = 0;
+
+    let mut fixture = AllowListFixture::new(test_name).await;
+    let user = labels::label(Pubkey::new_unique(), "user");
+    let recipient = Pubkey::new_unique();
+
+    if let Err(e) = fixture.add_user(&user).await {
+        return TestResultReport::failure(test_name, format!("Add to allow list failed: {}", e));
+    }
+
+    // Assertion 1: A non-authority signer cannot close the record
+    assertions += 1;
+    let attacker = Keypair::new();
+    let close = fixture.remove_instruction(
+        &user,
+        &attacker.pubkey(),
+        RemoveMode::Close,
+        Some(&attacker.pubkey()),
+    );
+    if fixture.send(&[close], &[&attacker]).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Non-authority was able to close the record".to_string(),
+        );
+    }
+
+    // Assertion 2: Close without a rent recipient is rejected
+    assertions += 1;
+    let authority = fixture.authority.insecure_clone();
+    let close = fixture.remove_instruction(&user, &authority.pubkey(), RemoveMode::Close, None);
+    if fixture.send(&[close], &[&authority]).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Close without a rent recipient should fail".to_string(),
+        );
+    }
+
+    // Assertion 3: Unknown remove modes are rejected
+    assertions += 1;
+    let mut invalid_mode = fixture.remove_instruction(
+        &user,
+        &authority.pubkey(),
+        RemoveMode::Close,
+        Some(&recipient),
+    );
+    invalid_mode.data = vec![REMOVE_FROM_ALLOW_LIST, 7];
+    if fixture.send(&[invalid_mode], &[&authority]).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Unknown remove mode should be rejected".to_string(),
+        );
+    }
+
+    // Assertion 4: Failed attempts left the record intact and allowed
+    assertions += 1;
+    match fixture.record(&user).await {
+        Some(record) if record.allowed => {}
+        _ => {
+            return TestResultReport::failure(
+                test_name,
+                "Rejected close attempts must not modify the record".to_string(),
+            )
+        }
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 4: A sponsor can pay rent for onboarding on the issuer's behalf
+#[tokio::test]
+async fn test_sponsor_pays_onboarding_rent() {
+    let report = run_sponsored_onboarding_test().await;
+    assert!(
+        report.passed,
+        "Sponsored onboarding test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_sponsored_onboarding_test() -> TestResultReport {
+    let test_name = "Sponsored Onboarding Rent";
+    let mut assertions = 0;
+
+    let mut fixture = AllowListFixture::new(test_name).await;
+    let sponsor = match fixture.funded_sponsor(1_000_000_000).await {
+        Ok(sponsor) => sponsor,
+        Err(e) => return TestResultReport::failure(test_name, format!("Funding failed: {}", e)),
+    };
+    let user = labels::label(Pubkey::new_unique(), "user");
+    let authority = fixture.authority.insecure_clone();
+    let payer = fixture.context.payer.pubkey();
+
+    let sponsor_before = fixture.lamports(&sponsor.pubkey()).await;
+    let payer_before = fixture.lamports(&payer).await;
+
+    // Assertion 1: Add succeeds with the sponsor as fee payer and rent payer
+    assertions += 1;
+    let add = fixture.add_instruction_with_payer(&user, &sponsor.pubkey());
+    if let Err(e) = fixture
+        .send_with_payer(&[add], &sponsor, &[&authority])
+        .await
+    {
+        return TestResultReport::failure(test_name, format!("Sponsored add failed: {}", e));
+    }
+
+    // Assertion 2: The authority only signs and never needs lamports
+    assertions += 1;
+    if fixture.lamports(&authority.pubkey()).await != 0 {
+        return TestResultReport::failure(
+            test_name,
+            "Authority should not be charged for a sponsored add".to_string(),
+        );
+    }
+
+    // Assertion 3: The sponsor covered at least the record rent
+    assertions += 1;
+    let record_lamports = fixture.lamports(&fixture.allow_list_pda(&user)).await;
+    let sponsor_spent = sponsor_before - fixture.lamports(&sponsor.pubkey()).await;
+    if sponsor_spent <= record_lamports {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Sponsor spent {} lamports, expected rent {} plus fees",
+                sponsor_spent, record_lamports
+            ),
+        );
+    }
+
+    // Assertion 4: The default payer was not charged
+    assertions += 1;
+    if fixture.lamports(&payer).await != payer_before {
+        return TestResultReport::failure(
+            test_name,
+            "Default payer charged for a sponsored transaction".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 5: Permissionless thaw does not assume caller == payer
+#[tokio::test]
+async fn test_sponsored_permissionless_thaw() {
+    let report = run_sponsored_thaw_test().await;
+    assert!(
+        report.passed,
+        "Sponsored thaw test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_sponsored_thaw_test() -> TestResultReport {
+    let test_name = "Sponsored Permissionless Thaw";
+    let mut assertions = 0;
+
+    let mut fixture = AllowListFixture::new(test_name).await;
+    let sponsor = match fixture.funded_sponsor(1_000_000_000).await {
+        Ok(sponsor) => sponsor,
+        Err(e) => return TestResultReport::failure(test_name, format!("Funding failed: {}", e)),
+    };
+
+    // The user never holds SOL; a gasless wallet only signs
+    let user = Keypair::new();
+    if let Err(e) = fixture.add_user(&user.pubkey()).await {
+        return TestResultReport::failure(test_name, format!("Add to allow list failed: {}", e));
+    }
+
+    // Assertion 1: User signs as caller while the sponsor pays fees
+    assertions += 1;
+    let can_thaw = fixture.can_thaw_instruction_from(&user.pubkey(), true, &user.pubkey());
+    if let Err(e) = fixture
+        .send_with_payer(&[can_thaw], &sponsor, &[&user])
+        .await
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!("Sponsored thaw for allowed user failed: {}", e),
+        );
+    }
+
+    // Assertion 2: The user was never charged
+    assertions += 1;
+    if fixture.lamports(&user.pubkey()).await != 0 {
+        return TestResultReport::failure(
+            test_name,
+            "User charged for a sponsored thaw".to_string(),
+        );
+    }
+
+    // Assertion 3: A caller that is neither owner nor payer is accepted
+    assertions += 1;
+    let relayer = Pubkey::new_unique();
+    let can_thaw = fixture.can_thaw_instruction_from(&relayer, false, &user.pubkey());
+    if let Err(e) = fixture.send_with_payer(&[can_thaw], &sponsor, &[]).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("Gate rejected a third-party caller: {}", e),
+        );
+    }
+
+    // Assertion 4: Sponsorship does not grant access to unlisted users
+    assertions += 1;
+    let outsider = Keypair::new();
+    let can_thaw = fixture.can_thaw_instruction_from(&outsider.pubkey(), true, &outsider.pubkey());
+    if fixture
+        .send_with_payer(&[can_thaw], &sponsor, &[&outsider])
+        .await
+        .is_ok()
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Sponsored thaw authorized an unlisted user".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 6: A sponsor can submit the thaw check with a durable nonce
+#[tokio::test]
+async fn test_sponsored_thaw_with_durable_nonce() {
+    let report = run_sponsored_nonce_thaw_test().await;
+    assert!(
+        report.passed,
+        "Sponsored durable nonce thaw test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_sponsored_nonce_thaw_test() -> TestResultReport {
+    let test_name = "Sponsored Thaw with Durable Nonce";
+    let mut assertions = 0;
+
+    let mut fixture = AllowListFixture::new(test_name).await;
+    let sponsor = match fixture.funded_sponsor(1_000_000_000).await {
+        Ok(sponsor) => sponsor,
+        Err(e) => return TestResultReport::failure(test_name, format!("Funding failed: {}", e)),
+    };
+    let user = Keypair::new();
+    if let Err(e) = fixture.add_user(&user.pubkey()).await {
+        return TestResultReport::failure(test_name, format!("Add to allow list failed: {}", e));
+    }
+
+    let nonce_account = match fixture.create_nonce_account(&sponsor.pubkey()).await {
+        Ok(nonce_account) => nonce_account,
+        Err(e) => {
+            return TestResultReport::failure(test_name, format!("Nonce creation failed: {}", e))
+        }
+    };
+    let Some(stored_nonce) = fixture.nonce_blockhash(&nonce_account).await else {
+        return TestResultReport::failure(test_name, "Nonce account not initialized".to_string());
+    };
+
+    // A nonce can only be advanced once the bank has moved past its blockhash
+    if let Err(e) = fixture.context.get_new_latest_blockhash().await {
+        return TestResultReport::failure(test_name, format!("Blockhash refresh failed: {}", e));
+    }
+
+    // Assertion 1: Nonce-based sponsored thaw succeeds
+    assertions += 1;
+    let instructions = [
+        system_instruction::advance_nonce_account(&nonce_account, &sponsor.pubkey()),
+        fixture.can_thaw_instruction_from(&user.pubkey(), true, &user.pubkey()),
+    ];
+    if let Err(e) = fixture
+        .submit(&instructions, &sponsor, &[&user], stored_nonce)
+        .await
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!("Durable nonce sponsored thaw failed: {}", e),
+        );
+    }
+
+    // Assertion 2: The nonce was consumed
+    assertions += 1;
+    if fixture.nonce_blockhash(&nonce_account).await == Some(stored_nonce) {
+        return TestResultReport::failure(
+            test_name,
+            "Nonce was not advanced by the sponsored transaction".to_string(),
+        );
+    }
+
+    // Assertion 3: The user still holds no lamports
+    assertions += 1;
+    if fixture.lamports(&user.pubkey()).await != 0 {
+        return TestResultReport::failure(
+            test_name,
+            "User charged for a durable nonce thaw".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 7: Admin batch signed offline against a durable nonce
+#[tokio::test]
+async fn test_offline_signed_nonce_batch() {
+    let report = run_offline_nonce_batch_test().await;
+    assert!(
+        report.passed,
+        "Offline nonce batch test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_offline_nonce_batch_test() -> TestResultReport {
+    let test_name = "Offline-Signed Durable Nonce Batch";
+    let mut assertions = 0;
+
+    let mut fixture = AllowListFixture::new(test_name).await;
+    let authority = fixture.authority.insecure_clone();
+    let payer = fixture.context.payer.insecure_clone();
+
+    // The compliance key owns the nonce so the batch can sit unsigned indefinitely
+    let nonce_account = match fixture.create_nonce_account(&authority.pubkey()).await {
+        Ok(nonce_account) => nonce_account,
+        Err(e) => {
+            return TestResultReport::failure(test_name, format!("Nonce creation failed: {}", e))
+        }
+    };
+    let Some(stored_nonce) = fixture.nonce_blockhash(&nonce_account).await else {
+        return TestResultReport::failure(test_name, "Nonce account not initialized".to_string());
+    };
+
+    let users: Vec<Pubkey> = (0..3)
+        .map(|i| labels::label(Pubkey::new_unique(), &format!("investor_{}", i)))
+        .collect();
+    let batch: Vec<Instruction> = users
+        .iter()
+        .map(|user| fixture.add_instruction(user))
+        .collect();
+    let mut transaction = nonce::build_nonce_transaction(
+        &batch,
+        &payer.pubkey(),
+        &nonce_account,
+        &authority.pubkey(),
+        stored_nonce,
+    );
+
+    // Assertion 1: A partially signed batch is not submittable
+    assertions += 1;
+    nonce::sign_offline(&mut transaction, &authority);
+    if fixture
+        .submit_transaction(transaction.clone())
+        .await
+        .is_ok()
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Batch accepted without the fee payer signature".to_string(),
+        );
+    }
+
+    // The signatures outlive any recent blockhash; move the bank forward
+    if let Err(e) = fixture.context.get_new_latest_blockhash().await {
+        return TestResultReport::failure(test_name, format!("Blockhash refresh failed: {}", e));
+    }
+
+    // Assertion 2: The fully signed batch lands after the blockhash moved on
+    assertions += 1;
+    nonce::sign_offline(&mut transaction, &payer);
+    if let Err(e) = fixture.submit_transaction(transaction).await {
+        return TestResultReport::failure(test_name, format!("Nonce batch failed: {}", e));
+    }
+
+    // Assertion 3: Every user in the batch was added
+    assertions += 1;
+    for user in &users {
+        if !matches!(fixture.record(user).await, Some(record) if record.allowed) {
+            return TestResultReport::failure(
+                test_name,
+                format!("User {} missing after nonce batch", user),
+            );
+        }
+    }
+
+    // Assertion 4: Landing the batch advanced the nonce
+    assertions += 1;
+    if fixture.nonce_blockhash(&nonce_account).await == Some(stored_nonce) {
+        return TestResultReport::failure(
+            test_name,
+            "Nonce was not advanced by the batch".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 8: Consumed durable nonces cannot be replayed
+#[tokio::test]
+async fn test_nonce_replay_rejected() {
+    let report = run_nonce_replay_test().await;
+    assert!(
+        report.passed,
+        "Nonce replay test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_nonce_replay_test() -> TestResultReport {
+    let test_name = "Durable Nonce Replay Rejection";
+    let mut assertions = 0;
+
+    let mut fixture = AllowListFixture::new(test_name).await;
+    let authority = fixture.authority.insecure_clone();
+    let payer = fixture.context.payer.insecure_clone();
+    let user = labels::label(Pubkey::new_unique(), "user");
+    fixture.invariants.allow_refunds_to(payer.pubkey());
+
+    let nonce_account = match fixture.create_nonce_account(&authority.pubkey()).await {
+        Ok(nonce_account) => nonce_account,
+        Err(e) => {
+            return TestResultReport::failure(test_name, format!("Nonce creation failed: {}", e))
+        }
+    };
+    let Some(stored_nonce) = fixture.nonce_blockhash(&nonce_account).await else {
+        return TestResultReport::failure(test_name, "Nonce account not initialized".to_string());
+    };
+    if let Err(e) = fixture.context.get_new_latest_blockhash().await {
+        return TestResultReport::failure(test_name, format!("Blockhash refresh failed: {}", e));
+    }
+
+    let build_remove = |fixture: &AllowListFixture, nonce: Hash| {
+        let remove = fixture.remove_instruction(
+            &user,
+            &authority.pubkey(),
+            RemoveMode::Close,
+            Some(&payer.pubkey()),
+        );
+        let mut transaction = nonce::build_nonce_transaction(
+            &[remove],
+            &payer.pubkey(),
+            &nonce_account,
+            &authority.pubkey(),
+            nonce,
+        );
+        nonce::sign_offline(&mut transaction, &authority);
+        nonce::sign_offline(&mut transaction, &payer);
+        transaction
+    };
+
+    if let Err(e) = fixture.add_user(&user).await {
+        return TestResultReport::failure(test_name, format!("Add to allow list failed: {}", e));
+    }
+    let transaction = build_remove(&fixture, stored_nonce);
+
+    // Assertion 1: First submission succeeds
+    assertions += 1;
+    if let Err(e) = fixture.submit_transaction(transaction.clone()).await {
+        return TestResultReport::failure(test_name, format!("Nonce remove failed: {}", e));
+    }
+
+    // Re-allow the user so a replay would be observable
+    if let Err(e) = fixture.add_user(&user).await {
+        return TestResultReport::failure(test_name, format!("Re-add failed: {}", e));
+    }
+    if let Err(e) = fixture.context.get_new_latest_blockhash().await {
+        return TestResultReport::failure(test_name, format!("Blockhash refresh failed: {}", e));
+    }
+
+    // Assertion 2: Replaying the exact signed transaction is rejected
+    assertions += 1;
+    if fixture.submit_transaction(transaction).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Replayed nonce transaction was accepted".to_string(),
+        );
+    }
+
+    // Assertion 3: A freshly signed transaction on the stale nonce is rejected
+    assertions += 1;
+    let stale = build_remove(&fixture, stored_nonce);
+    if fixture.submit_transaction(stale).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Transaction on a consumed nonce was accepted".to_string(),
+        );
+    }
+
+    // Assertion 4: Rejected replays left the user allowed
+    assertions += 1;
+    if !matches!(fixture.record(&user).await, Some(record) if record.allowed) {
+        return TestResultReport::failure(
+            test_name,
+            "Replay attempt modified the record".to_string(),
+        );
+    }
+
+    // Assertion 5: The current nonce still works
+    assertions += 1;
+    let Some(current_nonce) = fixture.nonce_blockhash(&nonce_account).await else {
+        return TestResultReport::failure(test_name, "Nonce account missing".to_string());
+    };
+    let fresh = build_remove(&fixture, current_nonce);
+    if let Err(e) = fixture.submit_transaction(fresh).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("Transaction on the current nonce failed: {}", e),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 9: Decision analytics aggregate a real run's gate logs
+#[tokio::test]
+async fn test_decision_analytics_from_run() {
+    let report = run_decision_analytics_test().await;
+    assert!(
+        report.passed,
+        "Decision analytics test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_decision_analytics_test() -> TestResultReport {
+    let test_name = "Decision Analytics Aggregation";
+    let mut assertions = 0;
+
+    let mut fixture = AllowListFixture::new(test_name).await;
+    let allowed = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let revoked = Pubkey::new_unique();
+    let unlisted = Pubkey::new_unique();
+
+    for user in allowed.iter().chain([&revoked]) {
+        if let Err(e) = fixture.add_user(user).await {
+            return TestResultReport::failure(
+                test_name,
+                format!("Add to allow list failed: {}", e),
+            );
+        }
+    }
+    let authority = fixture.authority.insecure_clone();
+    let remove =
+        fixture.remove_instruction(&revoked, &authority.pubkey(), RemoveMode::Deactivate, None);
+    if let Err(e) = fixture.send(&[remove], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Soft remove failed: {}", e));
+    }
+
+    let mut analytics = DecisionAnalytics::new();
+    for user in allowed.iter().chain([&revoked, &unlisted]) {
+        match fixture.thaw_check(user).await {
+            Ok((succeeded, logs)) => analytics.ingest_logs(GateOperation::Thaw, &logs, succeeded),
+            Err(e) => {
+                return TestResultReport::failure(test_name, format!("Thaw check failed: {}", e))
+            }
+        }
+    }
+    let summary = analytics.summary();
+
+    // Assertion 1: Approval and denial counts match the scenario
+    assertions += 1;
+    if summary.total != 4 || summary.approved != 2 || summary.denied != 2 {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Expected 2 approved / 2 denied, got {} / {}",
+                summary.approved, summary.denied
+            ),
+        );
+    }
+
+    // Assertion 2: Denial reasons are classified from the gate logs
+    assertions += 1;
+    if summary.denial_reasons.get("revoked") != Some(&1)
+        || summary.denial_reasons.get("not_listed") != Some(&1)
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!("Unexpected denial reasons: {:?}", summary.denial_reasons),
+        );
+    }
+
+    // Assertion 3: Approvals are attributed to the logged tier
+    assertions += 1;
+    match summary.by_tier.get("Enhanced") {
+        Some(counts) if counts.approved == 2 && counts.denied == 0 => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Unexpected Enhanced tier counts: {:?}", other),
+            )
+        }
+    }
+
+    // Assertion 4: Events identify the user each decision was about
+    assertions += 1;
+    let unlisted_key = unlisted.to_string();
+    if !analytics
+        .events()
+        .iter()
+        .any(|event| event.user.as_deref() == Some(unlisted_key.as_str()) && !event.approved)
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Denied event for the unlisted user is missing".to_string(),
+        );
+    }
+
+    // Assertion 5: CSV artifact carries every bucket
+    assertions += 1;
+    let csv = analytics.to_csv();
+    for row in [
+        "overall,all,2,2",
+        "operation,thaw,2,2",
+        "tier,Enhanced,2,0",
+        "denial_reason,not_listed,0,1",
+        "denial_reason,revoked,0,1",
+    ] {
+        if !csv.lines().any(|line| line == row) {
+            return TestResultReport::failure(test_name, format!("CSV missing row: {}", row));
+        }
+    }
+
+    // Assertion 6: JSON and CSV artifacts are written
+    assertions += 1;
+    if let Err(e) = analytics.write_artifacts(
+        "../../tests/reports/decision_analytics.json",
+        "../../tests/reports/decision_analytics.csv",
+    ) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Failed to write analytics artifacts: {}", e),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 10: Freeze/thaw timeline is reconstructed from recorded history
+#[tokio::test]
+async fn test_freeze_thaw_history_reconstruction() {
+    let report = run_history_reconstruction_test().await;
+    assert!(
+        report.passed,
+        "History reconstruction test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_history_reconstruction_test() -> TestResultReport {
+    let test_name = "Freeze/Thaw History Reconstruction";
+    let mut assertions = 0;
+
+    const DAY: i64 = 86_400;
+    let start = 1_700_000_000;
+
+    let mint = Pubkey::new_unique();
+    let user = Keypair::new();
+    let token_account = Pubkey::new_unique();
+    let mut fixture = AllowListFixture::with_accounts(test_name, mint, |authority| {
+        vec![
+            (mint, program_test::mint_account(authority, 0)),
+            (
+                token_account,
+                program_test::token_account(&mint, &user.pubkey(), 0, AccountState::Initialized),
+            ),
+        ]
+    })
+    .await;
+    let authority = fixture.authority.insecure_clone();
+
+    let freeze = token_instruction::freeze_account(
+        &spl_token_2022::id(),
+        &token_account,
+        &mint,
+        &authority.pubkey(),
+        &[],
+    )
+    .expect("valid freeze instruction");
+    let thaw = token_instruction::thaw_account(
+        &spl_token_2022::id(),
+        &token_account,
+        &mint,
+        &authority.pubkey(),
+        &[],
+    )
+    .expect("valid thaw instruction");
+    // The gate check precedes the thaw; the authority key stands in for the
+    // FAMP PDA that would sign the thaw CPI on-chain
+    let permissionless_thaw = [
+        fixture.can_thaw_instruction_for(&user.pubkey(), true, &token_account, &user.pubkey()),
+        thaw.clone(),
+    ];
+    let add = fixture.add_instruction(&user.pubkey());
+
+    // Recorded scenario, one step per day
+    let steps: [(&[Instruction], Vec<&Keypair>); 5] = [
+        (std::slice::from_ref(&freeze), vec![&authority]),
+        (&permissionless_thaw, vec![&user, &authority]),
+        (std::slice::from_ref(&add), vec![&authority]),
+        (&permissionless_thaw, vec![&user, &authority]),
+        (std::slice::from_ref(&freeze), vec![&authority]),
+    ];
+    let mut history = Vec::new();
+    for (day, (instructions, signers)) in steps.iter().enumerate() {
+        if let Err(e) = fixture.advance_clock(start + day as i64 * DAY).await {
+            return TestResultReport::failure(test_name, format!("Clock advance failed: {}", e));
+        }
+        match fixture.send_recorded(instructions, signers).await {
+            Ok(recorded) => history.push(recorded),
+            Err(e) => {
+                return TestResultReport::failure(test_name, format!("Recording failed: {}", e))
+            }
+        }
+    }
+
+    let config = HistoryConfig::new(fixture.program_id);
+    let timeline = AccountTimeline::reconstruct(&token_account, &history, &config);
+
+    // Assertion 1: Only freeze/thaw attempts appear in the timeline
+    assertions += 1;
+    let shape: Vec<(FreezeAction, AuthorizationMode, bool)> = timeline
+        .entries
+        .iter()
+        .map(|entry| (entry.action, entry.mode, entry.applied))
+        .collect();
+    let expected = vec![
+        (FreezeAction::Freeze, AuthorizationMode::Permissioned, true),
+        (FreezeAction::Thaw, AuthorizationMode::Permissionless, false),
+        (FreezeAction::Thaw, AuthorizationMode::Permissionless, true),
+        (FreezeAction::Freeze, AuthorizationMode::Permissioned, true),
+    ];
+    if shape != expected {
+        return TestResultReport::failure(test_name, format!("Unexpected timeline: {:?}", shape));
+    }
+
+    // Assertion 2: The failed attempt records the gate's denial reason
+    assertions += 1;
+    let denied = &timeline.entries[1];
+    match &denied.gate_decision {
+        Some(decision)
+            if !decision.approved && decision.denial_reason.as_deref() == Some("not_listed") => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Unexpected denied gate decision: {:?}", other),
+            )
+        }
+    }
+
+    // Assertion 3: The thaw names the user as trigger and the approving tier
+    assertions += 1;
+    let thawed = &timeline.entries[2];
+    let approved_tier = thawed
+        .gate_decision
+        .as_ref()
+        .filter(|decision| decision.approved)
+        .and_then(|decision| decision.tier.as_deref());
+    if thawed.triggered_by != user.pubkey() || approved_tier != Some("Enhanced") {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Thaw attributed to {} with tier {:?}",
+                thawed.triggered_by, approved_tier
+            ),
+        );
+    }
+
+    // Assertion 4: Permissioned changes name the freeze authority
+    assertions += 1;
+    if timeline.entries[0].triggered_by != authority.pubkey() {
+        return TestResultReport::failure(
+            test_name,
+            "Permissioned freeze not attributed to the freeze authority".to_string(),
+        );
+    }
+
+    // Assertion 5: "Why was this account thawed on day 4?" resolves to the gated thaw
+    assertions += 1;
+    let day_four = start + 3 * DAY + DAY / 2;
+    if timeline
+        .explain_thaw_at(day_four)
+        .map(|entry| &entry.signature)
+        != Some(&thawed.signature)
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Day 4 thaw not explained by the permissionless thaw".to_string(),
+        );
+    }
+
+    // Assertion 6: No thaw is reported while the account was frozen
+    assertions += 1;
+    let day_two = start + DAY + DAY / 2;
+    let day_five = start + 4 * DAY + DAY / 2;
+    if timeline.explain_thaw_at(day_two).is_some() || timeline.explain_thaw_at(day_five).is_some() {
+        return TestResultReport::failure(
+            test_name,
+            "Thaw reported for a period when the account was frozen".to_string(),
+        );
+    }
+
+    // Assertion 7: Reconstructed final state matches the chain
+    assertions += 1;
+    let last_slot = history
+        .last()
+        .map(|recorded| recorded.slot)
+        .unwrap_or_default();
+    let on_chain = fixture.is_frozen(&token_account).await;
+    if timeline.is_frozen_at(last_slot) != on_chain || on_chain != Some(true) {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Timeline state {:?} does not match on-chain state {:?}",
+                timeline.is_frozen_at(last_slot),
+                on_chain
+            ),
+        );
+    }
+
+    // Assertion 8: History order does not affect the reconstruction
+    assertions += 1;
+    let mut reversed = history.clone();
+    reversed.reverse();
+    if AccountTimeline::reconstruct(&token_account, &reversed, &config) != timeline {
+        return TestResultReport::failure(
+            test_name,
+            "Reconstruction depends on history order".to_string(),
+        );
+    }
+
+    // Assertion 9: Audit report is written
+    assertions += 1;
+    if let Err(e) = std::fs::write(
+        "../../tests/reports/freeze_thaw_history.md",
+        timeline.to_markdown(),
+    ) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Failed to write history report: {}", e),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 11: A record for a token account's previous owner does not vouch
+/// for an account recreated at the same address by a new owner
+#[tokio::test]
+async fn test_owner_change_race() {
+    let report = run_owner_change_race_test().await;
+    assert!(
+        report.passed,
+        "Owner change race test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_owner_change_race_test() -> TestResultReport {
+    let test_name = "Owner Change Race";
+    let mut assertions = 0;
+
+    let mint = Pubkey::new_unique();
+    let mut fixture = AllowListFixture::with_accounts(test_name, mint, |authority| {
+        vec![(mint, program_test::mint_account(authority, 0))]
+    })
+    .await;
+    let caller = fixture.context.payer.pubkey();
+    let (old_owner, new_owner) = (Keypair::new(), Keypair::new());
+    let token_account = Keypair::new();
+
+    if let Err(e) = fixture
+        .open_token_account(&token_account, &old_owner.pubkey())
+        .await
+    {
+        return TestResultReport::failure(test_name, format!("Account setup failed: {}", e));
+    }
+    if let Err(e) = fixture.add_user(&old_owner.pubkey()).await {
+        return TestResultReport::failure(test_name, format!("Add failed: {}", e));
+    }
+
+    // Assertion 1: The listed owner's account is approved
+    assertions += 1;
+    let check = fixture.can_thaw_instruction_for(
+        &caller,
+        false,
+        &token_account.pubkey(),
+        &old_owner.pubkey(),
+    );
+    if let Err(e) = fixture.send(&[check], &[]).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("Listed owner's thaw check rejected: {}", e),
+        );
+    }
+
+    // The old owner closes the account and a new owner recreates it at the
+    // same address; the issuer freezes it as usual
+    let close = match token_instruction::close_account(
+        &spl_token_2022::id(),
+        &token_account.pubkey(),
+        &old_owner.pubkey(),
+        &old_owner.pubkey(),
+        &[],
+    ) {
+        Ok(close) => close,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+    if let Err(e) = fixture.send(&[close], &[&old_owner]).await {
+        return TestResultReport::failure(test_name, format!("Close failed: {}", e));
+    }
+    if let Err(e) = fixture
+        .open_token_account(&token_account, &new_owner.pubkey())
+        .await
+    {
+        return TestResultReport::failure(test_name, format!("Recreate failed: {}", e));
+    }
+    if let Err(e) = fixture.set_frozen(&token_account.pubkey(), true).await {
+        return TestResultReport::failure(test_name, format!("Freeze failed: {}", e));
+    }
+
+    // Assertion 2: The previous owner's record no longer vouches for the account
+    assertions += 1;
+    let stale = fixture.can_thaw_instruction_for(
+        &caller,
+        false,
+        &token_account.pubkey(),
+        &old_owner.pubkey(),
+    );
+    if fixture.send(&[stale], &[]).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Stale record for the previous owner approved the thaw".to_string(),
+        );
+    }
+
+    // Assertion 3: The unlisted new owner is denied
+    assertions += 1;
+    let unlisted = fixture.can_thaw_instruction_for(
+        &caller,
+        false,
+        &token_account.pubkey(),
+        &new_owner.pubkey(),
+    );
+    if fixture.send(&[unlisted], &[]).await.is_ok() {
+        return TestResultReport::failure(test_name, "Unlisted new owner was approved".to_string());
+    }
+
+    // Assertion 4: The previous owner's record still covers its other accounts
+    assertions += 1;
+    let other_account = Keypair::new();
+    if let Err(e) = fixture
+        .open_token_account(&other_account, &old_owner.pubkey())
+        .await
+    {
+        return TestResultReport::failure(test_name, format!("Account setup failed: {}", e));
+    }
+    let other = fixture.can_thaw_instruction_for(
+        &caller,
+        false,
+        &other_account.pubkey(),
+        &old_owner.pubkey(),
+    );
+    if let Err(e) = fixture.send(&[other], &[]).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("Previous owner's other account rejected: {}", e),
+        );
+    }
+
+    // Assertion 5: Once the new owner is listed, the recreated account is approved
+    assertions += 1;
+    if let Err(e) = fixture.add_user(&new_owner.pubkey()).await {
+        return TestResultReport::failure(test_name, format!("Add failed: {}", e));
+    }
+    let listed = fixture.can_thaw_instruction_for(
+        &caller,
+        false,
+        &token_account.pubkey(),
+        &new_owner.pubkey(),
+    );
+    if let Err(e) = fixture.send(&[listed], &[]).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("Listed new owner's thaw check rejected: {}", e),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 12: Closing and recreating a token account leaves no stale state
+/// behind for permissionless thaw decisions
+#[tokio::test]
+async fn test_closed_and_recreated_account_replay() {
+    let report = run_recreated_account_replay_test().await;
+    assert!(
+        report.passed,
+        "Recreated account replay test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_recreated_account_replay_test() -> TestResultReport {
+    let test_name = "Closed And Recreated Account Replay";
+    let mut assertions = 0;
+
+    let mint = Pubkey::new_unique();
+    let mut fixture = AllowListFixture::with_accounts(test_name, mint, |authority| {
+        vec![(
+            mint,
+            program_test::mint_account_with_default_state(authority, 0, AccountState::Frozen),
+        )]
+    })
+    .await;
+    let authority = fixture.authority.insecure_clone();
+    let caller = fixture.context.payer.pubkey();
+    let holder = Keypair::new();
+    let token_account = Keypair::new();
+
+    if let Err(e) = fixture.add_user(&holder.pubkey()).await {
+        return TestResultReport::failure(test_name, format!("Add failed: {}", e));
+    }
+    let close = match token_instruction::close_account(
+        &spl_token_2022::id(),
+        &token_account.pubkey(),
+        &holder.pubkey(),
+        &holder.pubkey(),
+        &[],
+    ) {
+        Ok(close) => close,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+
+    // Assertion 1: A new account starts frozen under DefaultAccountState
+    assertions += 1;
+    if let Err(e) = fixture
+        .open_token_account(&token_account, &holder.pubkey())
+        .await
+    {
+        return TestResultReport::failure(test_name, format!("Account setup failed: {}", e));
+    }
+    if fixture.is_frozen(&token_account.pubkey()).await != Some(true) {
+        return TestResultReport::failure(test_name, "New account should start frozen".to_string());
+    }
+
+    // Assertion 2: The first permissionless thaw is approved and applied
+    assertions += 1;
+    let check =
+        fixture.can_thaw_instruction_for(&caller, false, &token_account.pubkey(), &holder.pubkey());
+    if let Err(e) = fixture.send(std::slice::from_ref(&check), &[]).await {
+        return TestResultReport::failure(test_name, format!("First thaw check failed: {}", e));
+    }
+    if let Err(e) = fixture.set_frozen(&token_account.pubkey(), false).await {
+        return TestResultReport::failure(test_name, format!("First thaw failed: {}", e));
+    }
+
+    // Assertion 3: Once closed, the account is gone and cannot be thawed
+    assertions += 1;
+    if let Err(e) = fixture.send(std::slice::from_ref(&close), &[&holder]).await {
+        return TestResultReport::failure(test_name, format!("Close failed: {}", e));
+    }
+    let closed = fixture
+        .context
+        .banks_client
+        .get_account(token_account.pubkey())
+        .await
+        .ok()
+        .flatten();
+    if closed.is_some()
+        || fixture
+            .set_frozen(&token_account.pubkey(), false)
+            .await
+            .is_ok()
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Closed account should be gone and not thawable".to_string(),
+        );
+    }
+
+    // Assertion 4: Recreated at the same address, the account is frozen again
+    assertions += 1;
+    if let Err(e) = fixture
+        .open_token_account(&token_account, &holder.pubkey())
+        .await
+    {
+        return TestResultReport::failure(test_name, format!("Recreate failed: {}", e));
+    }
+    if fixture.is_frozen(&token_account.pubkey()).await != Some(true) {
+        return TestResultReport::failure(
+            test_name,
+            "Recreated account should start frozen".to_string(),
+        );
+    }
+
+    // Assertion 5: Replaying the permissionless thaw works for the same holder
+    assertions += 1;
+    if let Err(e) = fixture.send(std::slice::from_ref(&check), &[]).await {
+        return TestResultReport::failure(test_name, format!("Replayed check failed: {}", e));
+    }
+    if let Err(e) = fixture.set_frozen(&token_account.pubkey(), false).await {
+        return TestResultReport::failure(test_name, format!("Replayed thaw failed: {}", e));
+    }
+
+    // Assertion 6: A revocation made while the account was closed is honoured
+    // after it is recreated
+    assertions += 1;
+    if let Err(e) = fixture.send(std::slice::from_ref(&close), &[&holder]).await {
+        return TestResultReport::failure(test_name, format!("Close failed: {}", e));
+    }
+    let revoke = fixture.remove_instruction(
+        &holder.pubkey(),
+        &authority.pubkey(),
+        RemoveMode::Deactivate,
+        None,
+    );
+    if let Err(e) = fixture.send(&[revoke], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Revoke failed: {}", e));
+    }
+    if let Err(e) = fixture
+        .open_token_account(&token_account, &holder.pubkey())
+        .await
+    {
+        return TestResultReport::failure(test_name, format!("Recreate failed: {}", e));
+    }
+    if fixture
+        .send(std::slice::from_ref(&check), &[])
+        .await
+        .is_ok()
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Revoked holder approved after account was recreated".to_string(),
+        );
+    }
+    if fixture.is_frozen(&token_account.pubkey()).await != Some(true) {
+        return TestResultReport::failure(
+            test_name,
+            "Recreated account of a revoked holder should stay frozen".to_string(),
+        );
+    }
+
+    // Assertion 7: Closing the record and onboarding again yields a fresh
+    // record that approves the recreated account
+    assertions += 1;
+    let close_record = fixture.remove_instruction(
+        &holder.pubkey(),
+        &authority.pubkey(),
+        RemoveMode::Close,
+        Some(&caller),
+    );
+    fixture.invariants.allow_refunds_to(caller);
+    if let Err(e) = fixture.send(&[close_record], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Record close failed: {}", e));
+    }
+    if let Err(e) = fixture.add_user(&holder.pubkey()).await {
+        return TestResultReport::failure(test_name, format!("Re-add failed: {}", e));
+    }
+    match fixture.record(&holder.pubkey()).await {
+        Some(record) if record.allowed && record.user == holder.pubkey() => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Re-added record is stale: {:?}", other),
+            )
+        }
+    }
+    if let Err(e) = fixture.send(&[check], &[]).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("Fresh record did not approve the thaw: {}", e),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 13: One transaction creates the holder's ATA and thaws it
+#[tokio::test]
+async fn test_create_and_thaw_onboarding() {
+    let report = run_create_and_thaw_test().await;
+    assert!(
+        report.passed,
+        "Create and thaw onboarding test failed: {:?}",
+        report.error
+    );
+}
+
+/// Builds the onboarding composition for `holder` on the fixture's DAS mint
+fn onboarding_builder(fixture: &AllowListFixture, holder: &Pubkey) -> CreateAndThawBuilder {
+    CreateAndThawBuilder::new(
+        fixture.context.payer.pubkey(),
+        *holder,
+        fixture.mint,
+        fixture.program_id,
+    )
+    .extra_account(AccountMeta::new_readonly(*holder, false))
+    .extra_account(AccountMeta::new_readonly(
+        fixture.allow_list_pda(holder),
+        false,
+    ))
+    .extra_account(AccountMeta::new_readonly(
+        fixture.missing_record_policy_pda(),
+        false,
+    ))
+    .compute_unit_limit(200_000)
+}
+
+/// Signs and submits a composed onboarding transaction
+async fn submit_onboarding(
+    fixture: &mut AllowListFixture,
+    builder: &CreateAndThawBuilder,
+    signers: &[&Keypair],
+) -> Result<(), String> {
+    let blockhash = fixture
+        .context
+        .get_new_latest_blockhash()
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut transaction = builder.build(blockhash)?;
+    let mut all_signers: Vec<&Keypair> = vec![&fixture.context.payer];
+    all_signers.extend_from_slice(signers);
+    transaction
+        .try_sign(&all_signers, blockhash)
+        .map_err(|e| e.to_string())?;
+    fixture.submit_transaction(transaction).await
+}
+
+async fn run_create_and_thaw_test() -> TestResultReport {
+    let test_name = "Create And Thaw Onboarding";
+    let mut assertions = 0;
+
+    let mint = Pubkey::new_unique();
+    let mut fixture = AllowListFixture::with_accounts(test_name, mint, |authority| {
+        vec![(
+            mint,
+            program_test::mint_account_with_default_state(authority, 0, AccountState::Frozen),
+        )]
+    })
+    .await;
+    let authority = fixture.authority.insecure_clone();
+    let holder = Keypair::new();
+    if let Err(e) = fixture.add_user(&holder.pubkey()).await {
+        return TestResultReport::failure(test_name, format!("Add failed: {}", e));
+    }
+
+    // The Token ACL program is not deployed here, so the freeze authority's
+    // thaw stands in for its permissionless thaw after the gate approves
+    let check_only = onboarding_builder(&fixture, &holder.pubkey());
+    let ata = check_only.associated_token_address();
+    let thaw = match token_instruction::thaw_account(
+        &spl_token_2022::id(),
+        &ata,
+        &fixture.mint,
+        &authority.pubkey(),
+        &[],
+    ) {
+        Ok(thaw) => thaw,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+    let builder = check_only.clone().then(thaw);
+
+    // Assertion 1: The composition fits in a single packet
+    assertions += 1;
+    let size = match builder.build(Hash::default()) {
+        Ok(transaction) => onboarding::signed_size(&transaction),
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    if size > PACKET_DATA_SIZE {
+        return TestResultReport::failure(test_name, format!("Composition is {} bytes", size));
+    }
+
+    // Assertion 2: The ATA is created and thawed in one transaction
+    assertions += 1;
+    if let Err(e) = submit_onboarding(&mut fixture, &builder, &[&holder, &authority]).await {
+        return TestResultReport::failure(test_name, format!("Onboarding failed: {}", e));
+    }
+    if fixture.is_frozen(&ata).await != Some(false) {
+        return TestResultReport::failure(test_name, "ATA should exist and be thawed".to_string());
+    }
+
+    // Assertion 3: Retrying the creation and check is harmless, because the
+    // ATA creation is idempotent
+    assertions += 1;
+    if let Err(e) = submit_onboarding(&mut fixture, &check_only, &[&holder]).await {
+        return TestResultReport::failure(test_name, format!("Retry failed: {}", e));
+    }
+    if fixture.is_frozen(&ata).await != Some(false) {
+        return TestResultReport::failure(
+            test_name,
+            "Retry should leave the ATA thawed".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 14: A gate denial rolls back the whole onboarding transaction
+#[tokio::test]
+async fn test_create_and_thaw_atomic_on_denial() {
+    let report = run_create_and_thaw_denial_test().await;
+    assert!(
+        report.passed,
+        "Create and thaw denial test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_create_and_thaw_denial_test() -> TestResultReport {
+    let test_name = "Create And Thaw Atomic On Denial";
+    let mut assertions = 0;
+
+    let mint = Pubkey::new_unique();
+    let mut fixture = AllowListFixture::with_accounts(test_name, mint, |authority| {
+        vec![(
+            mint,
+            program_test::mint_account_with_default_state(authority, 0, AccountState::Frozen),
+        )]
+    })
+    .await;
+    let payer = fixture.context.payer.pubkey();
+    let outsider = Keypair::new();
+
+    let builder = onboarding_builder(&fixture, &outsider.pubkey());
+    let ata = builder.associated_token_address();
+    let payer_before = fixture.lamports(&payer).await;
+
+    // Assertion 1: The unlisted holder's onboarding is rejected
+    assertions += 1;
+    if submit_onboarding(&mut fixture, &builder, &[&outsider])
+        .await
+        .is_ok()
+    {
+        return TestResultReport::failure(test_name, "Unlisted holder was onboarded".to_string());
+    }
+
+    // Assertion 2: No ATA was left behind and no rent was charged
+    assertions += 1;
+    let leftover = fixture
+        .context
+        .banks_client
+        .get_account(ata)
+        .await
+        .ok()
+        .flatten();
+    let rent = match fixture.context.banks_client.get_rent().await {
+        Ok(rent) => rent,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+    let spent = payer_before - fixture.lamports(&payer).await;
+    if leftover.is_some() || spent >= rent.minimum_balance(TokenAccount::LEN) {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Denied onboarding left state behind (ATA: {}, spent: {})",
+                leftover.is_some(),
+                spent
+            ),
+        );
+    }
+
+    // Assertion 3: Compositions beyond the packet limit are refused up front
+    assertions += 1;
+    let oversized = (0..40).fold(builder, |builder, _| {
+        builder.extra_account(AccountMeta::new_readonly(Pubkey::new_unique(), false))
+    });
+    if oversized.build(Hash::default()).is_ok() {
+        return TestResultReport::failure(test_name, "Oversized composition was built".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Create, thaw, and first transfer from a treasury owned by the authority,
+/// as three ordered transactions
+fn first_transfer_bundle(
+    fixture: &AllowListFixture,
+    holder: &Pubkey,
+    treasury: &Pubkey,
+    amount: u64,
+) -> Result<TransactionBundle, String> {
+    let authority = fixture.authority.pubkey();
+    let onboarding = onboarding_builder(fixture, holder);
+    let ata = onboarding.associated_token_address();
+
+    let create = create_associated_token_account_idempotent(
+        &fixture.context.payer.pubkey(),
+        holder,
+        &fixture.mint,
+        &spl_token_2022::id(),
+    );
+    let thaw = token_instruction::thaw_account(
+        &spl_token_2022::id(),
+        &ata,
+        &fixture.mint,
+        &authority,
+        &[],
+    )
+    .map_err(|e| e.to_string())?;
+    let transfer = token_instruction::transfer_checked(
+        &spl_token_2022::id(),
+        treasury,
+        &fixture.mint,
+        &ata,
+        &authority,
+        &[],
+        amount,
+        // The shared mint fixture's decimals
+        6,
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(TransactionBundle::new(fixture.context.payer.pubkey())
+        .step("create_account", vec![create])
+        .step("thaw", vec![onboarding.thaw_check_instruction(), thaw])
+        .step("first_transfer", vec![transfer]))
+}
+
+/// Builds, signs, and submits a bundle one transaction at a time
+async fn submit_bundle(
+    fixture: &mut AllowListFixture,
+    bundle: &TransactionBundle,
+    signers: &[&Keypair],
+) -> Result<BundleOutcome, String> {
+    let blockhash = fixture
+        .context
+        .get_new_latest_blockhash()
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut transactions = bundle.build(blockhash)?;
+    let mut all_signers: Vec<&Keypair> = vec![&fixture.context.payer];
+    all_signers.extend_from_slice(signers);
+    bundles::sign_bundle(&mut transactions, &all_signers, blockhash)?;
+
+    // The bundle is one step: its seams may leave partial state, but never
+    // break the invariants
+    let addresses: Vec<Pubkey> = transactions
+        .iter()
+        .flat_map(|entry| entry.transaction.message.account_keys.iter().copied())
+        .collect();
+    let signed: Vec<Transaction> = transactions
+        .iter()
+        .map(|entry| entry.transaction.clone())
+        .collect();
+    let before = fixture.snapshot(&addresses).await;
+    let outcome = bundles::submit_sequential(&mut fixture.context.banks_client, transactions).await;
+    fixture
+        .check_step(
+            "transaction bundle",
+            &signed.iter().collect::<Vec<_>>(),
+            before,
+        )
+        .await;
+    Ok(outcome)
+}
+
+/// Test 15: Each seam of a multi-transaction flow leaves a documented
+/// partial state when submitted without a bundle engine
+#[tokio::test]
+async fn test_bundle_seam_partial_completion() {
+    let report = run_bundle_seam_test().await;
+    assert!(report.passed, "Bundle seam test failed: {:?}", report.error);
+}
+
+async fn run_bundle_seam_test() -> TestResultReport {
+    let test_name = "Bundle Seam Partial Completion";
+    let mut assertions = 0;
+
+    let mint = Pubkey::new_unique();
+    let mut fixture = AllowListFixture::with_accounts(test_name, mint, |authority| {
+        vec![(
+            mint,
+            program_test::mint_account_with_default_state(authority, 0, AccountState::Frozen),
+        )]
+    })
+    .await;
+    let authority = fixture.authority.insecure_clone();
+    let treasury = Keypair::new();
+    let (holder, late_holder) = (Keypair::new(), Keypair::new());
+
+    let setup: Result<(), String> = async {
+        fixture
+            .open_token_account(&treasury, &authority.pubkey())
+            .await?;
+        fixture.set_frozen(&treasury.pubkey(), false).await?;
+        let mint_to = token_instruction::mint_to(
+            &spl_token_2022::id(),
+            &fixture.mint,
+            &treasury.pubkey(),
+            &authority.pubkey(),
+            &[],
+            100,
+        )
+        .map_err(|e| e.to_string())?;
+        fixture.send(&[mint_to], &[&authority]).await?;
+        fixture.add_user(&late_holder.pubkey()).await
+    }
+    .await;
+    if let Err(e) = setup {
+        return TestResultReport::failure(test_name, format!("Setup failed: {}", e));
+    }
+
+    let steps = |landed: &[&str], failed: Option<&str>, skipped: &[&str]| {
+        (
+            landed.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+            failed.map(str::to_string),
+            skipped.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+        )
+    };
+    let shape = |outcome: &BundleOutcome| {
+        (
+            outcome.landed.clone(),
+            outcome.failed.as_ref().map(|(step, _)| step.clone()),
+            outcome.skipped.clone(),
+        )
+    };
+
+    // Assertion 1: Seam 1 - nothing lands when the first step fails
+    assertions += 1;
+    let missing_mint = TransactionBundle::new(fixture.context.payer.pubkey())
+        .step(
+            "create_account",
+            vec![create_associated_token_account_idempotent(
+                &fixture.context.payer.pubkey(),
+                &holder.pubkey(),
+                &Pubkey::new_unique(),
+                &spl_token_2022::id(),
+            )],
+        )
+        .step("thaw", Vec::new())
+        .step("first_transfer", Vec::new());
+    match submit_bundle(&mut fixture, &missing_mint, &[]).await {
+        Ok(outcome)
+            if shape(&outcome)
+                == steps(&[], Some("create_account"), &["thaw", "first_transfer"])
+                && !outcome.is_partial() => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Unexpected seam 1 outcome: {:?}", other),
+            )
+        }
+    }
+
+    // Assertion 2: Seam 2 - the account is created but stays frozen when
+    // the gate denies the thaw
+    assertions += 1;
+    let bundle = match first_transfer_bundle(&fixture, &holder.pubkey(), &treasury.pubkey(), 10) {
+        Ok(bundle) => bundle,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let ata = onboarding_builder(&fixture, &holder.pubkey()).associated_token_address();
+    match submit_bundle(&mut fixture, &bundle, &[&holder, &authority]).await {
+        Ok(outcome)
+            if shape(&outcome) == steps(&["create_account"], Some("thaw"), &["first_transfer"])
+                && outcome.is_partial() => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Unexpected seam 2 outcome: {:?}", other),
+            )
+        }
+    }
+    if fixture.is_frozen(&ata).await != Some(true) {
+        return TestResultReport::failure(
+            test_name,
+            "Account left by a seam 2 failure should exist frozen".to_string(),
+        );
+    }
+
+    // Assertion 3: Resubmitting the whole bundle after listing the holder
+    // completes, because account creation is idempotent
+    assertions += 1;
+    if let Err(e) = fixture.add_user(&holder.pubkey()).await {
+        return TestResultReport::failure(test_name, format!("Add failed: {}", e));
+    }
+    match submit_bundle(&mut fixture, &bundle, &[&holder, &authority]).await {
+        Ok(outcome) if outcome.is_complete() => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Resubmitted bundle did not complete: {:?}", other),
+            )
+        }
+    }
+    if fixture.token_balance(&ata).await != Some(10) {
+        return TestResultReport::failure(
+            test_name,
+            "Completed bundle should deliver the first transfer".to_string(),
+        );
+    }
+
+    // Assertion 4: Seam 3 - the account is thawed but empty when the first
+    // transfer fails
+    assertions += 1;
+    let overdrawn =
+        match first_transfer_bundle(&fixture, &late_holder.pubkey(), &treasury.pubkey(), 1_000) {
+            Ok(bundle) => bundle,
+            Err(e) => return TestResultReport::failure(test_name, e),
+        };
+    let late_ata = onboarding_builder(&fixture, &late_holder.pubkey()).associated_token_address();
+    match submit_bundle(&mut fixture, &overdrawn, &[&late_holder, &authority]).await {
+        Ok(outcome)
+            if shape(&outcome)
+                == steps(&["create_account", "thaw"], Some("first_transfer"), &[]) => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Unexpected seam 3 outcome: {:?}", other),
+            )
+        }
+    }
+    if fixture.is_frozen(&late_ata).await != Some(false)
+        || fixture.token_balance(&late_ata).await != Some(0)
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Account left by a seam 3 failure should be thawed and empty".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 16: Bundles respect transaction count, size, tip, and signer rules
+#[tokio::test]
+async fn test_bundle_limits() {
+    let report = run_bundle_limits_test().await;
+    assert!(
+        report.passed,
+        "Bundle limits test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_bundle_limits_test() -> TestResultReport {
+    let test_name = "Bundle Limits";
+    let mut assertions = 0;
+
+    let fixture = AllowListFixture::new(test_name).await;
+    let payer = fixture.context.payer.pubkey();
+    let tip_account = Pubkey::new_unique();
+    let noop = |to: Pubkey| vec![system_instruction::transfer(&payer, &to, 1)];
+
+    // Assertion 1: Empty bundles and bundles above the transaction limit fail
+    assertions += 1;
+    let too_many = (0..=MAX_BUNDLE_TRANSACTIONS)
+        .fold(TransactionBundle::new(payer), |bundle, i| {
+            bundle.step(&format!("step_{}", i), noop(Pubkey::new_unique()))
+        });
+    if TransactionBundle::new(payer).build(Hash::default()).is_ok()
+        || too_many.build(Hash::default()).is_ok()
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Empty or oversized bundle was built".to_string(),
+        );
+    }
+
+    // Assertion 2: The tip rides in the last transaction only
+    assertions += 1;
+    let tipped = TransactionBundle::new(payer)
+        .step("first", noop(Pubkey::new_unique()))
+        .step("second", noop(Pubkey::new_unique()))
+        .with_tip(tip_account, 1_000);
+    let built = match tipped.build(Hash::default()) {
+        Ok(built) => built,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let pays_tip = |entry: &BundleTransaction| {
+        entry
+            .transaction
+            .message
+            .account_keys
+            .contains(&tip_account)
+    };
+    if built.len() != 2 || pays_tip(&built[0]) || !pays_tip(&built[1]) {
+        return TestResultReport::failure(
+            test_name,
+            "Tip should only be paid by the last transaction".to_string(),
+        );
+    }
+
+    // Assertion 3: A step above the packet limit is refused
+    assertions += 1;
+    let crowded: Vec<Instruction> = (0..40)
+        .map(|_| noop(Pubkey::new_unique())[0].clone())
+        .collect();
+    if TransactionBundle::new(payer)
+        .step("crowded", crowded)
+        .build(Hash::default())
+        .is_ok()
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Step above the packet limit was built".to_string(),
+        );
+    }
+
+    // Assertion 4: Signing fails when a step's signer is missing
+    assertions += 1;
+    let other_signer = Keypair::new();
+    let needs_other = TransactionBundle::new(payer).step(
+        "needs_other",
+        vec![system_instruction::transfer(
+            &other_signer.pubkey(),
+            &tip_account,
+            1,
+        )],
+    );
+    let mut unsigned = match needs_other.build(Hash::default()) {
+        Ok(built) => built,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    if bundles::sign_bundle(&mut unsigned, &[&fixture.context.payer], Hash::default()).is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Bundle with a missing signer was signed".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 17: A revoked record denies thaw whatever `allowed` and the expiry say
+#[tokio::test]
+async fn test_revoke_denies_thaw() {
+    let report = run_revoke_test().await;
+    assert!(report.passed, "Revoke test failed: {:?}", report.error);
+}
+
+async fn run_revoke_test() -> TestResultReport {
+    let test_name = "Allow List Revoke";
+    let mut assertions = 0;
+
+    let mut fixture = AllowListFixture::new(test_name).await;
+    let authority = fixture.authority.insecure_clone();
+    let user = labels::label(Pubkey::new_unique(), "user");
+    if let Err(e) = fixture.add_user(&user).await {
+        return TestResultReport::failure(test_name, format!("Add to allow list failed: {}", e));
+    }
+
+    // Assertion 1: Only the config authority can revoke
+    assertions += 1;
+    let impostor = Keypair::new();
+    let forged = fixture.revocation_instruction(REVOKE, &user, &impostor.pubkey());
+    if fixture.send(&[forged], &[&impostor]).await.is_ok() {
+        return TestResultReport::failure(test_name, "Impostor revoked a user".to_string());
+    }
+
+    // Assertion 2: The authority's revoke marks the record revoked and not
+    // allowed
+    assertions += 1;
+    let revoke = fixture.revocation_instruction(REVOKE, &user, &authority.pubkey());
+    if let Err(e) = fixture.send(&[revoke], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Revoke failed: {}", e));
+    }
+    match fixture.record(&user).await {
+        Some(record) if record.revoked && !record.allowed => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Record after revoke: {:?}", other),
+            )
+        }
+    }
+
+    // Assertion 3: The revoked user cannot thaw
+    assertions += 1;
+    match fixture.thaw_check(&user).await {
+        Ok((false, logs)) if logs.iter().any(|log| log.contains("is revoked")) => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Revoked user's thaw check: {:?}", other),
+            )
+        }
+    }
+
+    // Assertion 4: Even a record that also says allowed with a far expiry
+    // denies while revoked
+    assertions += 1;
+    let pda = fixture.allow_list_pda(&user);
+    let mut account = match fixture.context.banks_client.get_account(pda).await {
+        Ok(Some(account)) => account,
+        other => return TestResultReport::failure(test_name, format!("Record: {:?}", other)),
+    };
+    let mut record = match AllowListRecord::try_from_slice(&account.data) {
+        Ok(record) => record,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+    record.allowed = true;
+    record.expiry_timestamp = Some(i64::MAX);
+    account.data = record.try_to_vec().expect("record serializes");
+    fixture.context.set_account(&pda, &account.into());
+    match fixture.thaw_check(&user).await {
+        Ok((false, _)) => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Allowed but revoked record authorized thaw: {:?}", other),
+            )
+        }
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 18: Only `LiftRevocation` by the authority clears a revocation
+#[tokio::test]
+async fn test_lift_revocation_is_the_only_way_back() {
+    let report = run_lift_revocation_test().await;
+    assert!(
+        report.passed,
+        "Lift revocation test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_lift_revocation_test() -> TestResultReport {
+    let test_name = "Allow List Lift Revocation";
+    let mut assertions = 0;
+
+    let mut fixture = AllowListFixture::new(test_name).await;
+    let authority = fixture.authority.insecure_clone();
+    let user = labels::label(Pubkey::new_unique(), "user");
+    let recipient = Pubkey::new_unique();
+    if let Err(e) = fixture.add_user(&user).await {
+        return TestResultReport::failure(test_name, format!("Add to allow list failed: {}", e));
+    }
+
+    // Assertion 1: Lifting a revocation that does not exist fails
+    assertions += 1;
+    let lift = fixture.revocation_instruction(LIFT_REVOCATION, &user, &authority.pubkey());
+    if fixture
+        .send(std::slice::from_ref(&lift), &[&authority])
+        .await
+        .is_ok()
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Lifted a revocation from an unrevoked record".to_string(),
+        );
+    }
+
+    let revoke = fixture.revocation_instruction(REVOKE, &user, &authority.pubkey());
+    if let Err(e) = fixture.send(&[revoke], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Revoke failed: {}", e));
+    }
+
+    // Assertion 2: A revoked record cannot be closed, so close-and-re-add
+    // cannot lift it
+    assertions += 1;
+    let close = fixture.remove_instruction(
+        &user,
+        &authority.pubkey(),
+        RemoveMode::Close,
+        Some(&recipient),
+    );
+    if fixture
+        .send(std::slice::from_ref(&close), &[&authority])
+        .await
+        .is_ok()
+    {
+        return TestResultReport::failure(test_name, "Closed a revoked record".to_string());
+    }
+
+    // Assertion 3: Deactivating leaves the revocation in place
+    assertions += 1;
+    let deactivate =
+        fixture.remove_instruction(&user, &authority.pubkey(), RemoveMode::Deactivate, None);
+    if let Err(e) = fixture.send(&[deactivate], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Deactivate failed: {}", e));
+    }
+    if !fixture
+        .record(&user)
+        .await
+        .is_some_and(|record| record.revoked)
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Deactivating cleared the revocation".to_string(),
+        );
+    }
+
+    // Assertion 4: Only the config authority can lift it
+    assertions += 1;
+    let impostor = Keypair::new();
+    let forged = fixture.revocation_instruction(LIFT_REVOCATION, &user, &impostor.pubkey());
+    if fixture.send(&[forged], &[&impostor]).await.is_ok() {
+        return TestResultReport::failure(test_name, "Impostor lifted a revocation".to_string());
+    }
+
+    // Assertion 5: Lifting leaves the record deactivated, so the user still
+    // cannot thaw
+    assertions += 1;
+    if let Err(e) = fixture.send(&[lift], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Lift failed: {}", e));
+    }
+    match fixture.record(&user).await {
+        Some(record) if !record.revoked && !record.allowed => {}
+        other => {
+            return TestResultReport::failure(test_name, format!("Record after lift: {:?}", other))
+        }
+    }
+    if !matches!(fixture.thaw_check(&user).await, Ok((false, _))) {
+        return TestResultReport::failure(
+            test_name,
+            "Lifting a revocation allowed the user".to_string(),
+        );
+    }
+
+    // Assertion 6: Once lifted, the normal close-and-re-add allows the user
+    assertions += 1;
+    fixture.invariants.allow_refunds_to(recipient);
+    if let Err(e) = fixture.send(&[close], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Close after lift failed: {}", e));
+    }
+    if let Err(e) = fixture.add_user(&user).await {
+        return TestResultReport::failure(test_name, format!("Re-add failed: {}", e));
+    }
+    if !matches!(fixture.thaw_check(&user).await, Ok((true, _))) {
+        return TestResultReport::failure(test_name, "Re-added user cannot thaw".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 19: A second `Initialize` fails as already initialized unless it
+/// asks for the config only if needed and matches it
+#[tokio::test]
+async fn test_double_initialize() {
+    let report = run_double_initialize_test().await;
+    assert!(
+        report.passed,
+        "Double initialize test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_double_initialize_test() -> TestResultReport {
+    let test_name = "Allow List Double Initialize";
+    let mut assertions = 0;
+
+    let mut fixture = AllowListFixture::new(test_name).await;
+    let authority = fixture.authority.insecure_clone();
+    let mint = fixture.mint;
+    let config_address = fixture.config;
+    let config = fixture.account(&config_address).await;
+
+    // Assertion 1: Initializing again fails with `AccountAlreadyInitialized`
+    // rather than the system program's complaint about the address
+    assertions += 1;
+    let again = fixture.initialize_instruction(&mint, &authority.pubkey(), InitMode::Create);
+    let result = fixture.send(&[again], &[&authority]).await;
+    if !failed_as_already_initialized(&result) {
+        return TestResultReport::failure(test_name, format!("Second initialize: {:?}", result));
+    }
+
+    // Assertion 2: `IfNeeded` with the same authority succeeds and changes
+    // nothing
+    assertions += 1;
+    let if_needed = fixture.initialize_instruction(&mint, &authority.pubkey(), InitMode::IfNeeded);
+    if let Err(e) = fixture.send(&[if_needed], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Initialize if needed failed: {}", e));
+    }
+    if fixture.account(&config_address).await != config {
+        return TestResultReport::failure(
+            test_name,
+            "Initialize if needed changed the config".to_string(),
+        );
+    }
+
+    // Assertion 3: `IfNeeded` by anyone else does not hand them the config
+    assertions += 1;
+    let intruder = Keypair::new();
+    let takeover = fixture.initialize_instruction(&mint, &intruder.pubkey(), InitMode::IfNeeded);
+    let result = fixture.send(&[takeover], &[&intruder]).await;
+    if !failed_as_already_initialized(&result) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Initialize if needed by another authority: {:?}", result),
+        );
+    }
+    if fixture.account(&config_address).await != config {
+        return TestResultReport::failure(test_name, "Config changed hands".to_string());
+    }
+
+    // Assertion 4: An unknown mode is rejected
+    assertions += 1;
+    let mut unknown = fixture.initialize_instruction(&mint, &authority.pubkey(), InitMode::Create);
+    unknown.data[1] = 2;
+    let result = fixture.send(&[unknown], &[&authority]).await;
+    let expected = InstructionError::InvalidInstructionData.to_string();
+    if !matches!(&result, Err(e) if e.contains(&expected)) {
+        return TestResultReport::failure(test_name, format!("Unknown mode: {:?}", result));
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 20: Adding a listed user again fails as already initialized, and
+/// `IfNeeded` leaves their record, deactivated or not, as it is
+#[tokio::test]
+async fn test_double_add_to_allow_list() {
+    let report = run_double_add_test().await;
+    assert!(report.passed, "Double add test failed: {:?}", report.error);
+}
+
+async fn run_double_add_test() -> TestResultReport {
+    let test_name = "Allow List Double Add";
+    let mut assertions = 0;
+
+    let mut fixture = AllowListFixture::new(test_name).await;
+    let authority = fixture.authority.insecure_clone();
+    let payer = fixture.context.payer.pubkey();
+    let user = labels::label(Pubkey::new_unique(), "user");
+    if let Err(e) = fixture.add_user(&user).await {
+        return TestResultReport::failure(test_name, format!("Add to allow list failed: {}", e));
+    }
+    let pda = fixture.allow_list_pda(&user);
+    let record = fixture.account(&pda).await;
+
+    // Assertion 1: Adding again fails with `AccountAlreadyInitialized`
+    assertions += 1;
+    let result = fixture.add_user(&user).await;
+    if !failed_as_already_initialized(&result) {
+        return TestResultReport::failure(test_name, format!("Second add: {:?}", result));
+    }
+
+    // Assertion 2: `IfNeeded` succeeds without touching the record
+    assertions += 1;
+    let if_needed = fixture.add_if_needed_instruction(&user, &payer);
+    if let Err(e) = fixture.send(&[if_needed], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Add if needed failed: {}", e));
+    }
+    if fixture.account(&pda).await != record {
+        return TestResultReport::failure(
+            test_name,
+            "Add if needed changed the record".to_string(),
+        );
+    }
+
+    // Assertion 3: `IfNeeded` does not re-allow a deactivated user
+    assertions += 1;
+    let deactivate =
+        fixture.remove_instruction(&user, &authority.pubkey(), RemoveMode::Deactivate, None);
+    if let Err(e) = fixture.send(&[deactivate], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Deactivate failed: {}", e));
+    }
+    let if_needed = fixture.add_if_needed_instruction(&user, &payer);
+    if let Err(e) = fixture.send(&[if_needed], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Add if needed failed: {}", e));
+    }
+    if !matches!(fixture.thaw_check(&user).await, Ok((false, _))) {
+        return TestResultReport::failure(
+            test_name,
+            "Add if needed re-allowed a deactivated user".to_string(),
+        );
+    }
+
+    // Assertion 4: `IfNeeded` for a new user creates their record
+    assertions += 1;
+    let newcomer = labels::label(Pubkey::new_unique(), "newcomer");
+    let if_needed = fixture.add_if_needed_instruction(&newcomer, &payer);
+    if let Err(e) = fixture.send(&[if_needed], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Add if needed failed: {}", e));
+    }
+    if !matches!(fixture.thaw_check(&newcomer).await, Ok((true, _))) {
+        return TestResultReport::failure(test_name, "Newcomer cannot thaw".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 21: Of two initializations racing for a config exactly one lands,
+/// and two adds-if-needed racing for a record both land with the rent paid
+/// once
+#[tokio::test]
+async fn test_concurrent_initialize_race() {
+    let report = run_concurrent_initialize_test().await;
+    assert!(
+        report.passed,
+        "Concurrent initialize test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_concurrent_initialize_test() -> TestResultReport {
+    let test_name = "Allow List Concurrent Initialize";
+    let mut assertions = 0;
+
+    let mut fixture = AllowListFixture::new(test_name).await;
+    let payer = fixture.context.payer.insecure_clone();
+    let blockhash = match fixture.context.get_new_latest_blockhash().await {
+        Ok(blockhash) => blockhash,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+
+    // Two admins initialize the config of a new mint at once
+    let mint = labels::label(Pubkey::new_unique(), "second_mint");
+    let (alice, bob) = (Keypair::new(), Keypair::new());
+    let transactions = [&alice, &bob].map(|admin| {
+        Transaction::new_signed_with_payer(
+            &[fixture.initialize_instruction(&mint, &admin.pubkey(), InitMode::Create)],
+            Some(&payer.pubkey()),
+            &[&payer, admin],
+            blockhash,
+        )
+    });
+    let [alice_init, bob_init] = transactions;
+    let (mut first, mut second) = (fixture.context.client(), fixture.context.client());
+    let (alice_result, bob_result) = tokio::join!(
+        nonce::submit(&mut first, alice_init),
+        nonce::submit(&mut second, bob_init)
+    );
+
+    // Assertion 1: Exactly one landed; the other found the config there
+    assertions += 1;
+    let results = [alice_result, bob_result];
+    let landed: Vec<bool> = results.iter().map(Result::is_ok).collect();
+    let lost = results.iter().find(|result| result.is_err());
+    if landed.iter().filter(|landed| **landed).count() != 1
+        || !lost.is_some_and(failed_as_already_initialized)
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!("Racing initializations ended {:?}", results),
+        );
+    }
+
+    // Assertion 2: The config belongs to whoever landed
+    assertions += 1;
+    let winner = if landed[0] {
+        alice.pubkey()
+    } else {
+        bob.pubkey()
+    };
+    match fixture.config_of(&mint).await {
+        Some(config) if config.authority == winner && config.mint == mint => {}
+        other => {
+            return TestResultReport::failure(test_name, format!("Config after race: {:?}", other))
+        }
+    }
+
+    // Assertion 3: Two adds-if-needed of one user, paid by different
+    // payers, both land
+    assertions += 1;
+    let authority = fixture.authority.insecure_clone();
+    let sponsor = match fixture.funded_sponsor(1_000_000_000).await {
+        Ok(sponsor) => sponsor,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let user = labels::label(Pubkey::new_unique(), "user");
+    let payer_before = fixture.lamports(&payer.pubkey()).await;
+    let sponsor_before = fixture.lamports(&sponsor.pubkey()).await;
+    let blockhash = match fixture.context.get_new_latest_blockhash().await {
+        Ok(blockhash) => blockhash,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+    let transactions = [&payer, &sponsor].map(|rent_payer| {
+        Transaction::new_signed_with_payer(
+            &[fixture.add_if_needed_instruction(&user, &rent_payer.pubkey())],
+            Some(&rent_payer.pubkey()),
+            &[rent_payer, &authority],
+            blockhash,
+        )
+    });
+    let [paid, sponsored] = transactions;
+    let (mut first, mut second) = (fixture.context.client(), fixture.context.client());
+    let results = tokio::join!(
+        nonce::submit(&mut first, paid),
+        nonce::submit(&mut second, sponsored)
+    );
+    if results.0.is_err() || results.1.is_err() {
+        return TestResultReport::failure(
+            test_name,
+            format!("Racing adds if needed ended {:?}", results),
+        );
+    }
+
+    // Assertion 4: The record's rent was paid once between them
+    assertions += 1;
+    let rent = fixture.lamports(&fixture.allow_list_pda(&user)).await;
+    let spent = (payer_before - fixture.lamports(&payer.pubkey()).await)
+        + (sponsor_before - fixture.lamports(&sponsor.pubkey()).await);
+    let expected = rent + 2 * fixture.context.fee(2);
+    if spent != expected {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Payers spent {} lamports, expected {} (rent {})",
+                spent, expected, rent
+            ),
+        );
+    }
+    if !matches!(fixture.thaw_check(&user).await, Ok((true, _))) {
+        return TestResultReport::failure(test_name, "User cannot thaw".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 22: Every access level, with and without an expiry, is stored as
+/// sent, and an add without args keeps the enhanced, non-expiring default
+#[tokio::test]
+async fn test_add_to_allow_list_args() {
+    let report = run_add_args_test().await;
+    assert!(report.passed, "Add args test failed: {:?}", report.error);
+}
+
+async fn run_add_args_test() -> TestResultReport {
+    let test_name = "Allow List Add Args";
+    let mut assertions = 0;
+
+    let mut fixture = AllowListFixture::new(test_name).await;
+    let now = fixture.clock().await.unix_timestamp;
+
+    // Assertion 1: No args add an enhanced record that never expires
+    assertions += 1;
+    let user = labels::label(Pubkey::new_unique(), "default_user");
+    if let Err(e) = fixture.add_user(&user).await {
+        return TestResultReport::failure(test_name, format!("Add failed: {}", e));
+    }
+    match fixture.record(&user).await {
+        Some(record)
+            if record.access_level == AccessLevel::Enhanced
+                && record.expiry_timestamp.is_none()
+                && record.added_timestamp == now => {}
+        other => return TestResultReport::failure(test_name, format!("Record: {:?}", other)),
+    }
+
+    for access_level in [
+        AccessLevel::Basic,
+        AccessLevel::Enhanced,
+        AccessLevel::Institutional,
+    ] {
+        for expiry_timestamp in [None, Some(now + 3_600)] {
+            // Assertion: The record holds the level and expiry, and its
+            // holder can thaw
+            assertions += 1;
+            let args = AddToAllowListArgs {
+                access_level,
+                expiry_timestamp,
+            };
+            let user = labels::label(Pubkey::new_unique(), "user");
+            if let Err(e) = fixture.add_user_with_args(&user, &args).await {
+                return TestResultReport::failure(
+                    test_name,
+                    format!("Add with {:?} failed: {}", args, e),
+                );
+            }
+            match fixture.record(&user).await {
+                Some(record)
+                    if record.access_level == access_level
+                        && record.expiry_timestamp == expiry_timestamp
+                        && record.allowed => {}
+                other => {
+                    return TestResultReport::failure(
+                        test_name,
+                        format!("Record for {:?}: {:?}", args, other),
+                    )
+                }
+            }
+            if !matches!(fixture.thaw_check(&user).await, Ok((true, _))) {
+                return TestResultReport::failure(
+                    test_name,
+                    format!("Holder added with {:?} cannot thaw", args),
+                );
+            }
+        }
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 23: Args that could never thaw, or do not decode, are rejected and
+/// create no record
+#[tokio::test]
+async fn test_add_to_allow_list_args_validation() {
+    let report = run_add_args_validation_test().await;
+    assert!(
+        report.passed,
+        "Add args validation test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_add_args_validation_test() -> TestResultReport {
+    let test_name = "Allow List Add Args Validation";
+    let mut assertions = 0;
+
+    let mut fixture = AllowListFixture::new(test_name).await;
+    let authority = fixture.authority.insecure_clone();
+    let now = fixture.clock().await.unix_timestamp;
+
+    let invalid = [
+        ("access level None", AccessLevel::None, None),
+        ("expiry now", AccessLevel::Basic, Some(now)),
+        (
+            "expiry in the past",
+            AccessLevel::Institutional,
+            Some(now - 1),
+        ),
+    ];
+    for (case, access_level, expiry_timestamp) in invalid {
+        // Assertion: The add fails with `InvalidArgument` and creates nothing
+        assertions += 1;
+        let user = labels::label(Pubkey::new_unique(), "user");
+        let args = AddToAllowListArgs {
+            access_level,
+            expiry_timestamp,
+        };
+        let result = fixture.add_user_with_args(&user, &args).await;
+        let expected = InstructionError::InvalidArgument.to_string();
+        if !matches!(&result, Err(e) if e.contains(&expected)) {
+            return TestResultReport::failure(test_name, format!("{}: {:?}", case, result));
+        }
+        if fixture.record(&user).await.is_some() {
+            return TestResultReport::failure(test_name, format!("{} created a record", case));
+        }
+    }
+
+    let malformed: [(&str, &[u8]); 3] = [
+        ("access level out of range", &[4, 0]),
+        ("truncated expiry", &[1, 1, 0xD0, 0x07]),
+        ("trailing bytes", &[1, 0, 0]),
+    ];
+    for (case, bytes) in malformed {
+        // Assertion: The add fails with `InvalidInstructionData` and
+        // creates nothing
+        assertions += 1;
+        let user = labels::label(Pubkey::new_unique(), "user");
+        let mut add = fixture.add_instruction(&user);
+        add.data.push(InitMode::Create as u8);
+        add.data.extend_from_slice(bytes);
+        let result = fixture.send(&[add], &[&authority]).await;
+        let expected = InstructionError::InvalidInstructionData.to_string();
+        if !matches!(&result, Err(e) if e.contains(&expected)) {
+            return TestResultReport::failure(test_name, format!("{}: {:?}", case, result));
+        }
+        if fixture.record(&user).await.is_some() {
+            return TestResultReport::failure(test_name, format!("{} created a record", case));
+        }
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 24: A record with an expiry thaws until the cluster clock passes
+/// it, then denies
+#[tokio::test]
+async fn test_expired_record_denies_thaw() {
+    let report = run_expired_record_test().await;
+    assert!(
+        report.passed,
+        "Expired record test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_expired_record_test() -> TestResultReport {
+    let test_name = "Allow List Expired Record";
+    let mut assertions = 0;
+
+    let mut fixture = AllowListFixture::new(test_name).await;
+    let clock = fixture.clock().await;
+    let expiry = clock.unix_timestamp + 60;
+    let user = labels::label(Pubkey::new_unique(), "user");
+    let args = AddToAllowListArgs {
+        access_level: AccessLevel::Basic,
+        expiry_timestamp: Some(expiry),
+    };
+    if let Err(e) = fixture.add_user_with_args(&user, &args).await {
+        return TestResultReport::failure(test_name, format!("Add failed: {}", e));
+    }
+
+    // Assertion 1: The holder thaws before the expiry
+    assertions += 1;
+    if !matches!(fixture.thaw_check(&user).await, Ok((true, _))) {
+        return TestResultReport::failure(test_name, "Holder cannot thaw".to_string());
+    }
+
+    // Assertion 2: At the expiry itself the record still thaws
+    assertions += 1;
+    fixture.context.set_sysvar(&Clock {
+        unix_timestamp: expiry,
+        ..clock.clone()
+    });
+    if !matches!(fixture.thaw_check(&user).await, Ok((true, _))) {
+        return TestResultReport::failure(test_name, "Holder denied at the expiry".to_string());
+    }
+
+    // Assertion 3: Past it the check denies as expired
+    assertions += 1;
+    fixture.context.set_sysvar(&Clock {
+        unix_timestamp: expiry + 1,
+        ..clock
+    });
+    match fixture.thaw_check(&user).await {
+        Ok((false, logs)) if logs.iter().any(|log| log.contains("has expired")) => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Expired holder's thaw check: {:?}", other),
+            )
+        }
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 25: `CloseRecord` reclaims the rent of a deactivated record, and
+/// the closed record denies thaw
+#[tokio::test]
+async fn test_close_record() {
+    let report = run_close_record_test().await;
+    assert!(
+        report.passed,
+        "Close record test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_close_record_test() -> TestResultReport {
+    let test_name = "Allow List Close Record";
+    let mut assertions = 0;
+
+    let mut fixture = AllowListFixture::new(test_name).await;
+    let user = labels::label(Pubkey::new_unique(), "user");
+    let recipient = Pubkey::new_unique();
+
+    if let Err(e) = fixture.add_user(&user).await {
+        return TestResultReport::failure(test_name, format!("Add to allow list failed: {}", e));
+    }
+    let pda = fixture.allow_list_pda(&user);
+    let record_lamports = fixture.lamports(&pda).await;
+
+    let authority = fixture.authority.insecure_clone();
+    let deactivate =
+        fixture.remove_instruction(&user, &authority.pubkey(), RemoveMode::Deactivate, None);
+    if let Err(e) = fixture.send(&[deactivate], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Deactivate failed: {}", e));
+    }
+
+    // Assertion 1: The deactivated record still holds its rent
+    assertions += 1;
+    if fixture.lamports(&pda).await != record_lamports {
+        return TestResultReport::failure(
+            test_name,
+            "Deactivating should leave the rent in the record".to_string(),
+        );
+    }
+
+    // Assertion 2: CloseRecord succeeds on the deactivated record
+    assertions += 1;
+    let close = fixture.close_record_instruction(&user, &authority.pubkey(), &recipient);
+    fixture.invariants.allow_refunds_to(recipient);
+    if let Err(e) = fixture.send(&[close], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("CloseRecord failed: {}", e));
+    }
+
+    // Assertion 3: The account is gone rather than left zeroed with rent
+    assertions += 1;
+    if fixture
+        .context
+        .banks_client
+        .get_account(pda)
+        .await
+        .ok()
+        .flatten()
+        .is_some()
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Record account still exists after CloseRecord".to_string(),
+        );
+    }
+
+    // Assertion 4: The recipient received the full rent refund
+    assertions += 1;
+    let refunded = fixture.lamports(&recipient).await;
+    if refunded != record_lamports {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Recipient should receive {} lamports, got {}",
+                record_lamports, refunded
+            ),
+        );
+    }
+
+    // Assertion 5: The closed record denies thaw
+    assertions += 1;
+    match fixture.thaw_check(&user).await {
+        Ok((false, _)) => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Closed record's thaw check: {:?}", other),
+            )
+        }
+    }
+
+    // Assertion 6: Closing an active record directly also works and denies
+    assertions += 1;
+    let active = labels::label(Pubkey::new_unique(), "active user");
+    if let Err(e) = fixture.add_user(&active).await {
+        return TestResultReport::failure(test_name, format!("Add failed: {}", e));
+    }
+    let close = fixture.close_record_instruction(&active, &authority.pubkey(), &recipient);
+    if let Err(e) = fixture.send(&[close], &[&authority]).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("CloseRecord on an active record failed: {}", e),
+        );
+    }
+    if !matches!(fixture.thaw_check(&active).await, Ok((false, _))) {
+        return TestResultReport::failure(
+            test_name,
+            "Closed active record still authorizes thaw".to_string(),
+        );
+    }
+
+    // Assertion 7: The user can be re-added after the close
+    assertions += 1;
+    if let Err(e) = fixture.add_user(&user).await {
+        return TestResultReport::failure(test_name, format!("Re-add after close failed: {}", e));
+    }
+    if !matches!(fixture.thaw_check(&user).await, Ok((true, _))) {
+        return TestResultReport::failure(test_name, "Re-added user cannot thaw".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 26: `CloseRecord` requires the authority and a separate recipient,
+/// and refuses revoked records
+#[tokio::test]
+async fn test_close_record_validation() {
+    let report = run_close_record_validation_test().await;
+    assert!(
+        report.passed,
+        "Close record validation test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_close_record_validation_test() -> TestResultReport {
+    let test_name = "Allow List Close Record Validation";
+    let mut assertions = 0;
+
+    let mut fixture = AllowListFixture::new(test_name).await;
+    let user = labels::label(Pubkey::new_unique(), "user");
+    let recipient = Pubkey::new_unique();
+
+    if let Err(e) = fixture.add_user(&user).await {
+        return TestResultReport::failure(test_name, format!("Add to allow list failed: {}", e));
+    }
+    let authority = fixture.authority.insecure_clone();
+
+    // Assertion 1: A non-authority signer cannot close the record
+    assertions += 1;
+    let attacker = Keypair::new();
+    let close = fixture.close_record_instruction(&user, &attacker.pubkey(), &attacker.pubkey());
+    if fixture.send(&[close], &[&attacker]).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Non-authority was able to close the record".to_string(),
+        );
+    }
+
+    // Assertion 2: A missing recipient is rejected
+    assertions += 1;
+    let mut close = fixture.close_record_instruction(&user, &authority.pubkey(), &recipient);
+    close.accounts.pop();
+    if fixture.send(&[close], &[&authority]).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "CloseRecord without a rent recipient should fail".to_string(),
+        );
+    }
+
+    // Assertion 3: The record cannot be its own recipient
+    assertions += 1;
+    let pda = fixture.allow_list_pda(&user);
+    let close = fixture.close_record_instruction(&user, &authority.pubkey(), &pda);
+    if fixture.send(&[close], &[&authority]).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "The record should not be its own rent recipient".to_string(),
+        );
+    }
+
+    // Assertion 4: A revoked record cannot be closed, so a re-add cannot lift
+    // the revocation
+    assertions += 1;
+    let revoke = fixture.revocation_instruction(REVOKE, &user, &authority.pubkey());
+    if let Err(e) = fixture.send(&[revoke], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Revoke failed: {}", e));
+    }
+    let close = fixture.close_record_instruction(&user, &authority.pubkey(), &recipient);
+    if fixture.send(&[close], &[&authority]).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "A revoked record should not close".to_string(),
+        );
+    }
+
+    // Assertion 5: Failed attempts left the record in place
+    assertions += 1;
+    match fixture.record(&user).await {
+        Some(record) if record.revoked => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Rejected closes must not modify the record: {:?}", other),
+            )
+        }
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 27: `UpdateAuthority` only proposes; the config changes hands when
+/// the proposed key signs `AcceptAuthority`
+#[tokio::test]
+async fn test_authority_handshake() {
+    let report = run_authority_handshake_test().await;
+    assert!(
+        report.passed,
+        "Authority handshake test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_authority_handshake_test() -> TestResultReport {
+    let test_name = "Allow List Authority Handshake";
+    let mut assertions = 0;
+
+    let mut fixture = AllowListFixture::new(test_name).await;
+    let authority = fixture.authority.insecure_clone();
+    let successor = Keypair::new();
+    labels::label(successor.pubkey(), "successor");
+    let recipient = Pubkey::new_unique();
+
+    let propose = fixture.propose_authority_instruction(&authority.pubkey(), &successor.pubkey());
+    if let Err(e) = fixture.send(&[propose], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Proposal failed: {}", e));
+    }
+
+    // Assertion 1: The proposal is recorded and the config is unchanged
+    assertions += 1;
+    match fixture.pending_authority().await {
+        Some(pending) if pending.proposed == successor.pubkey() && pending.mint == fixture.mint => {
+        }
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Pending authority after the proposal: {:?}", other),
+            )
+        }
+    }
+    let mint = fixture.mint;
+    if !matches!(fixture.config_of(&mint).await, Some(config) if config.authority == authority.pubkey())
+    {
+        return TestResultReport::failure(
+            test_name,
+            "A proposal must not change the config's authority".to_string(),
+        );
+    }
+
+    // Assertion 2: The current authority keeps control while the proposal
+    // is pending
+    assertions += 1;
+    if let Err(e) = fixture.add_user(&Pubkey::new_unique()).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("Add during a pending transfer failed: {}", e),
+        );
+    }
+
+    // Assertion 3: A key other than the proposed one cannot accept
+    assertions += 1;
+    let stranger = Keypair::new();
+    let accept = fixture.accept_authority_instruction(&stranger.pubkey(), &recipient);
+    if fixture.send(&[accept], &[&stranger]).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "A key that was not proposed accepted the authority".to_string(),
+        );
+    }
+
+    // Assertion 4: The proposed key must sign its acceptance
+    assertions += 1;
+    let mut accept = fixture.accept_authority_instruction(&successor.pubkey(), &recipient);
+    accept.accounts[2].is_signer = false;
+    if fixture.send(&[accept], &[]).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "An unsigned acceptance took over the config".to_string(),
+        );
+    }
+
+    // Assertion 5: The proposed key accepts, and the proposal's rent is
+    // refunded
+    assertions += 1;
+    let pending_lamports = fixture.lamports(&fixture.pending_authority_pda()).await;
+    let accept = fixture.accept_authority_instruction(&successor.pubkey(), &recipient);
+    fixture.invariants.allow_refunds_to(recipient);
+    if let Err(e) = fixture.send(&[accept], &[&successor]).await {
+        return TestResultReport::failure(test_name, format!("Acceptance failed: {}", e));
+    }
+    if !matches!(fixture.config_of(&mint).await, Some(config) if config.authority == successor.pubkey())
+    {
+        return TestResultReport::failure(
+            test_name,
+            "The config should belong to the successor".to_string(),
+        );
+    }
+    let pending = fixture.pending_authority_pda();
+    if fixture.account(&pending).await.is_some() {
+        return TestResultReport::failure(
+            test_name,
+            "The pending authority should be closed once accepted".to_string(),
+        );
+    }
+    if fixture.lamports(&recipient).await != pending_lamports {
+        return TestResultReport::failure(
+            test_name,
+            "The recipient should receive the proposal's rent".to_string(),
+        );
+    }
+
+    // Assertion 6: The former authority lost control and the successor has
+    // it
+    assertions += 1;
+    if fixture.add_user(&Pubkey::new_unique()).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "The former authority can still add users".to_string(),
+        );
+    }
+    fixture.authority = successor.insecure_clone();
+    if let Err(e) = fixture.add_user(&Pubkey::new_unique()).await {
+        return TestResultReport::failure(test_name, format!("Add by the successor failed: {}", e));
+    }
+
+    // Assertion 7: The acceptance cannot be replayed
+    assertions += 1;
+    let accept = fixture.accept_authority_instruction(&successor.pubkey(), &recipient);
+    if fixture.send(&[accept], &[&successor]).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Accepting without a pending proposal succeeded".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Test 28: A proposal can be replaced or cancelled by the current
+/// authority, and a cancelled one can no longer be accepted
+#[tokio::test]
+async fn test_authority_transfer_cancel() {
+    let report = run_authority_transfer_cancel_test().await;
+    assert!(
+        report.passed,
+        "Authority transfer cancel test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_authority_transfer_cancel_test() -> TestResultReport {
+    let test_name = "Allow List Authority Transfer Cancel";
+    let mut assertions = 0;
+
+    let mut fixture = AllowListFixture::new(test_name).await;
+    let authority = fixture.authority.insecure_clone();
+    let mistyped = Keypair::new();
+    let intended = Keypair::new();
+    let recipient = Pubkey::new_unique();
+
+    // Assertion 1: Only the current authority can propose
+    assertions += 1;
+    let propose = fixture.propose_authority_instruction(&mistyped.pubkey(), &mistyped.pubkey());
+    if fixture.send(&[propose], &[&mistyped]).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "A non-authority proposed a new authority".to_string(),
+        );
+    }
+
+    // Assertion 2: A later proposal replaces an earlier one
+    assertions += 1;
+    for proposed in [&mistyped, &intended] {
+        let propose =
+            fixture.propose_authority_instruction(&authority.pubkey(), &proposed.pubkey());
+        if let Err(e) = fixture.send(&[propose], &[&authority]).await {
+            return TestResultReport::failure(test_name, format!("Proposal failed: {}", e));
+        }
+    }
+    if !matches!(fixture.pending_authority().await, Some(pending) if pending.proposed == intended.pubkey())
+    {
+        return TestResultReport::failure(
+            test_name,
+            "The second proposal should replace the first".to_string(),
+        );
+    }
+    let accept = fixture.accept_authority_instruction(&mistyped.pubkey(), &recipient);
+    if fixture.send(&[accept], &[&mistyped]).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "A replaced proposal was accepted".to_string(),
+        );
+    }
+
+    // Assertion 3: Only the current authority can cancel
+    assertions += 1;
+    let cancel = fixture.cancel_authority_instruction(&intended.pubkey(), &recipient);
+    if fixture.send(&[cancel], &[&intended]).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "The proposed key cancelled the transfer".to_string(),
+        );
+    }
+
+    // Assertion 4: The authority cancels, closing the proposal
+    assertions += 1;
+    let cancel = fixture.cancel_authority_instruction(&authority.pubkey(), &recipient);
+    fixture.invariants.allow_refunds_to(recipient);
+    if let Err(e) = fixture.send(&[cancel], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Cancel failed: {}", e));
+    }
+    let pending = fixture.pending_authority_pda();
+    if fixture.account(&pending).await.is_some() {
+        return TestResultReport::failure(
+            test_name,
+            "The pending authority should be closed once cancelled".to_string(),
+        );
+    }
+
+    // Assertion 5: The cancelled proposal cannot be accepted and the
+    // authority is unchanged
+    assertions += 1;
+    let accept = fixture.accept_authority_instruction(&intended.pubkey(), &recipient);
+    if fixture.send(&[accept], &[&intended]).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "A cancelled proposal was accepted".to_string(),
+        );
+    }
+    let mint = fixture.mint;
+    if !matches!(fixture.config_of(&mint).await, Some(config) if config.authority == authority.pubkey())
+    {
+        return TestResultReport::failure(
+            test_name,
+            "The authority should be unchanged after a cancel".to_string(),
+        );
+    }
+
+    // Assertion 6: Cancelling with nothing pending fails
+    assertions += 1;
+    let cancel = fixture.cancel_authority_instruction(&authority.pubkey(), &recipient);
+    if fixture.send(&[cancel], &[&authority]).await.is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Cancelling without a pending proposal succeeded".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions).with_metrics(fixture.metrics)
+}
+
+/// Generate production allow list execution report
+#[tokio::test]
+async fn generate_production_allow_list_report() {
+    let results = vec![
+        run_soft_remove_test().await,
+        run_hard_close_test().await,
+        run_close_validation_test().await,
+        run_sponsored_onboarding_test().await,
+        run_sponsored_thaw_test().await,
+        run_sponsored_nonce_thaw_test().await,
+        run_offline_nonce_batch_test().await,
+        run_nonce_replay_test().await,
+        run_decision_analytics_test().await,
+        run_history_reconstruction_test().await,
+        run_owner_change_race_test().await,
+        run_recreated_account_replay_test().await,
+        run_create_and_thaw_test().await,
+        run_create_and_thaw_denial_test().await,
+        run_bundle_seam_test().await,
+        run_bundle_limits_test().await,
+        run_revoke_test().await,
+        run_lift_revocation_test().await,
+        run_double_initialize_test().await,
+        run_double_add_test().await,
+        run_concurrent_initialize_test().await,
+        run_add_args_test().await,
+        run_add_args_validation_test().await,
+        run_expired_record_test().await,
+        run_close_record_test().await,
+        run_close_record_validation_test().await,
+        run_authority_handshake_test().await,
+        run_authority_transfer_cancel_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Production Allow List Execution Test Results",
+        "../../tests/reports/production_allow_list_tests.md",
+    ) {
+        panic!("Failed to generate production allow list report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} production allow list tests failed", failed);
+}
@@ -0,0 +1,452 @@
+//! Python binding parity cases
+//!
+//! The `token_acl` Python module (`bindings/python`) wraps the client's
+//! status preflight, the PDA derivations and the transaction decoder. This
+//! test runs the gates on a bank, puts the same questions to the Rust code
+//! and records the inputs with the Rust answers in
+//! `tests/reports/python_parity.json`; `bindings/python/tests/test_parity.py`
+//! replays each case through the module and expects the same answers.
+//!
+//! ```sh
+//! cd tests/integration && cargo test --test python_parity
+//! cd ../.. && pip install ./bindings/python pytest && pytest bindings/python/tests
+//! ```
+
+use std::{collections::BTreeMap, fs};
+
+use serde_json::{json, Value};
+use solana_sdk::{
+    clock::Clock,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, VersionedTransaction},
+};
+use solana_transaction_status::{
+    ConfirmedTransactionWithStatusMeta, EncodedConfirmedTransactionWithStatusMeta,
+    TransactionStatusMeta, TransactionWithStatusMeta, UiTransactionEncoding,
+    VersionedTransactionWithStatusMeta,
+};
+use spl_token_2022::state::AccountState;
+
+use token_acl_client::{
+    instructions::find_mint_config_address,
+    status::{Gates, HolderStatus, StatusError, StatusSource},
+};
+use token_acl_integration_tests::{
+    decoder::{Decoder, ProgramKind},
+    program_test::{Gate, GateHarness},
+    reporting, TestResultReport,
+};
+use token_acl_interface::{
+    find_freeze_extra_account_metas_address, find_missing_record_policy_address,
+    find_thaw_extra_account_metas_address, CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR,
+    CAN_THAW_PERMISSIONLESS_DISCRIMINATOR,
+};
+
+/// Where the cases are recorded for the Python test
+const FIXTURE_PATH: &str = "../../tests/reports/python_parity.json";
+
+/// Accounts read from the bank, served from memory the way the bindings
+/// serve the accounts a script passes in
+struct FetchedAccounts {
+    accounts: BTreeMap<Pubkey, (Pubkey, Vec<u8>)>,
+    token_accounts: Vec<(Pubkey, Vec<u8>)>,
+}
+
+impl FetchedAccounts {
+    /// Read `addresses` and `token_accounts` from the harness's bank,
+    /// leaving out addresses with no account
+    async fn read(
+        harness: &mut GateHarness,
+        addresses: &[Pubkey],
+        token_accounts: &[Pubkey],
+    ) -> Self {
+        let mut accounts = BTreeMap::new();
+        for address in addresses {
+            if let Some(account) = harness.account(address).await {
+                accounts.insert(*address, (account.owner, account.data));
+            }
+        }
+        let mut tokens = Vec::new();
+        for address in token_accounts {
+            if let Some(account) = harness.account(address).await {
+                tokens.push((*address, account.data));
+            }
+        }
+        Self {
+            accounts,
+            token_accounts: tokens,
+        }
+    }
+
+    /// The accounts as the bindings take them, with data in hex
+    fn to_json(&self) -> (Value, Value) {
+        let accounts = self
+            .accounts
+            .iter()
+            .map(|(address, (owner, data))| {
+                (
+                    address.to_string(),
+                    json!({ "owner": owner.to_string(), "data": hex::encode(data) }),
+                )
+            })
+            .collect::<serde_json::Map<_, _>>();
+        let token_accounts = self
+            .token_accounts
+            .iter()
+            .map(|(address, data)| json!({ "address": address.to_string(), "data": hex::encode(data) }))
+            .collect::<Vec<_>>();
+        (Value::Object(accounts), Value::Array(token_accounts))
+    }
+}
+
+impl StatusSource for FetchedAccounts {
+    async fn account(&self, address: &Pubkey) -> Result<Option<(Pubkey, Vec<u8>)>, StatusError> {
+        Ok(self.accounts.get(address).cloned())
+    }
+
+    async fn token_accounts(
+        &self,
+        _mint: &Pubkey,
+        _owner: &Pubkey,
+    ) -> Result<Vec<(Pubkey, Vec<u8>)>, StatusError> {
+        Ok(self.token_accounts.clone())
+    }
+}
+
+/// A PDA case: the binding to call, its arguments and the Rust answer
+fn pda_case(function: &str, args: &[&Pubkey], (address, bump): (Pubkey, u8)) -> Value {
+    json!({
+        "function": function,
+        "args": args.iter().map(|key| key.to_string()).collect::<Vec<_>>(),
+        "address": address.to_string(),
+        "bump": bump,
+    })
+}
+
+/// Run `instructions` on the harness's bank, whatever the outcome, and
+/// return the transaction as `getTransaction` would with the `base64`
+/// encoding
+async fn execute_confirmed(
+    harness: &mut GateHarness,
+    instructions: &[Instruction],
+) -> Result<EncodedConfirmedTransactionWithStatusMeta, String> {
+    let blockhash = harness
+        .context
+        .get_new_latest_blockhash()
+        .await
+        .map_err(|e| e.to_string())?;
+    let payer = harness.context.payer.insecure_clone();
+    let issuer = harness.issuer.insecure_clone();
+    let mut transaction = Transaction::new_with_payer(instructions, Some(&payer.pubkey()));
+    let mut signers: Vec<&Keypair> = vec![&payer];
+    if transaction
+        .message
+        .signer_keys()
+        .contains(&&issuer.pubkey())
+    {
+        signers.push(&issuer);
+    }
+    transaction.sign(&signers, blockhash);
+    let outcome = harness
+        .context
+        .banks_client
+        .process_transaction_with_metadata(transaction.clone())
+        .await
+        .map_err(|e| e.to_string())?;
+    let (log_messages, compute_units_consumed) = outcome
+        .metadata
+        .map(|metadata| (metadata.log_messages, metadata.compute_units_consumed))
+        .unwrap_or_default();
+    let confirmed = ConfirmedTransactionWithStatusMeta {
+        slot: 1,
+        tx_with_meta: TransactionWithStatusMeta::Complete(VersionedTransactionWithStatusMeta {
+            transaction: VersionedTransaction::from(transaction),
+            meta: TransactionStatusMeta {
+                status: outcome.result,
+                log_messages: Some(log_messages),
+                compute_units_consumed: Some(compute_units_consumed),
+                ..TransactionStatusMeta::default()
+            },
+        }),
+        block_time: None,
+    };
+    confirmed
+        .encode(UiTransactionEncoding::Base64, Some(0))
+        .map_err(|e| e.to_string())
+}
+
+/// Test 1: The PDAs, holder statuses and decoded transactions the bindings
+/// are checked against are recorded from the Rust code
+#[tokio::test]
+async fn test_python_parity_cases_recorded() {
+    let report = run_python_parity_cases_test().await;
+    assert!(
+        report.passed,
+        "Python parity cases test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_python_parity_cases_test() -> TestResultReport {
+    let test_name = "Python Parity: Recorded Cases";
+    let mut assertions = 0;
+
+    let mut harness = match GateHarness::start(test_name).await {
+        Ok(harness) => harness,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let mint = harness.mint;
+    let (allow_gate, block_gate) = (harness.production_allow_list, harness.block_list);
+    let issuer = harness.issuer.pubkey();
+    let listed = Pubkey::new_unique();
+    let listed_account = harness.create_token_account(&listed, AccountState::Frozen);
+    let add = harness.add_to_allow_list(&listed, &issuer, true);
+    if let Err(e) = harness.send(&[add], &[]).await {
+        return TestResultReport::failure(test_name, format!("Listing failed: {:?}", e));
+    }
+    let blocked = Pubkey::new_unique();
+    let blocked_account = harness.create_token_account(&blocked, AccountState::Initialized);
+    harness.set_blocked(&blocked, true);
+    let unix_timestamp = match harness.context.banks_client.get_sysvar::<Clock>().await {
+        Ok(clock) => clock.unix_timestamp,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+
+    // Assertion 1: Every PDA the bindings derive is the one the gates use
+    assertions += 1;
+    let famp = Pubkey::new_unique();
+    let mut pdas = vec![
+        pda_case(
+            "find_thaw_extra_account_metas_address",
+            &[&mint, &allow_gate],
+            find_thaw_extra_account_metas_address(&mint, &allow_gate),
+        ),
+        pda_case(
+            "find_freeze_extra_account_metas_address",
+            &[&mint, &block_gate],
+            find_freeze_extra_account_metas_address(&mint, &block_gate),
+        ),
+        pda_case(
+            "find_missing_record_policy_address",
+            &[&mint, &allow_gate],
+            find_missing_record_policy_address(&mint, &allow_gate),
+        ),
+        pda_case(
+            "find_mint_config_address",
+            &[&mint, &famp],
+            find_mint_config_address(&mint, &famp),
+        ),
+    ];
+    for owner in [&listed, &blocked] {
+        let allow = gate_view::allow_list_address(&allow_gate, &mint, owner);
+        let block = gate_view::block_list_address(&block_gate, &mint, owner);
+        if allow.address() != harness.record_address(Gate::ProductionAllowList, owner)
+            || block.address() != harness.record_address(Gate::BlockList, owner)
+        {
+            return TestResultReport::failure(
+                test_name,
+                format!("Record PDAs of {} differ from the gates'", owner),
+            );
+        }
+        pdas.push(pda_case(
+            "allow_list_address",
+            &[&allow_gate, &mint, owner],
+            (allow.address(), allow.bump()),
+        ));
+        pdas.push(pda_case(
+            "block_list_address",
+            &[&block_gate, &mint, owner],
+            (block.address(), block.bump()),
+        ));
+    }
+
+    // Assertion 2: The listed holder reads as allow-listed, not blocked,
+    // with their frozen account, and the blocked holder as blocked
+    assertions += 1;
+    let gates = Gates {
+        allow_list: Some(allow_gate),
+        block_list: Some(block_gate),
+    };
+    let mut statuses = Vec::new();
+    for (name, owner, token_account, expected) in [
+        ("listed", listed, listed_account, (Some(true), Some(false))),
+        (
+            "blocked",
+            blocked,
+            blocked_account,
+            (Some(false), Some(true)),
+        ),
+    ] {
+        let records = [
+            harness.record_address(Gate::ProductionAllowList, &owner),
+            harness.record_address(Gate::BlockList, &owner),
+        ];
+        let source = FetchedAccounts::read(&mut harness, &records, &[token_account]).await;
+        let status = match HolderStatus::query(&source, gates, &mint, &owner, unix_timestamp).await
+        {
+            Ok(status) => status,
+            Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+        };
+        if (status.allow_listed(), status.blocked()) != expected || status.token_accounts.len() != 1
+        {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "Unexpected status of the {} holder:\n{}",
+                    name,
+                    status.render()
+                ),
+            );
+        }
+        let (accounts, token_accounts) = source.to_json();
+        statuses.push(json!({
+            "name": name,
+            "mint": mint.to_string(),
+            "owner": owner.to_string(),
+            "allow_list": allow_gate.to_string(),
+            "block_list": block_gate.to_string(),
+            "unix_timestamp": unix_timestamp,
+            "accounts": accounts,
+            "token_accounts": token_accounts,
+            "expected": {
+                "allow_listed": status.allow_listed(),
+                "blocked": status.blocked(),
+                "token_accounts": status.token_accounts.iter().map(|account| json!({
+                    "address": account.address.to_string(),
+                    "amount": account.amount,
+                    "frozen": account.frozen,
+                })).collect::<Vec<_>>(),
+                "render": status.render(),
+            },
+        }));
+    }
+
+    // Assertion 3: An approved and a denied thaw decode with their
+    // decisions, from the JSON the bindings take as well as from the
+    // response itself
+    assertions += 1;
+    let mut decoder = Decoder::new();
+    decoder
+        .register(allow_gate, ProgramKind::ProductionAllowList)
+        .register(block_gate, ProgramKind::BlockList);
+    let programs: BTreeMap<String, ProgramKind> = [
+        (allow_gate.to_string(), ProgramKind::ProductionAllowList),
+        (block_gate.to_string(), ProgramKind::BlockList),
+    ]
+    .into_iter()
+    .collect();
+    let caller = Pubkey::new_unique();
+    let mut transactions = Vec::new();
+    for (name, gate, owner, token_account, approved) in [
+        (
+            "approved_thaw",
+            Gate::ProductionAllowList,
+            listed,
+            listed_account,
+            true,
+        ),
+        (
+            "denied_thaw",
+            Gate::BlockList,
+            blocked,
+            blocked_account,
+            false,
+        ),
+    ] {
+        let thaw = harness.permissionless_thaw(gate, &caller, &token_account, &owner);
+        let encoded = match execute_confirmed(&mut harness, &thaw).await {
+            Ok(encoded) => encoded,
+            Err(e) => return TestResultReport::failure(test_name, e),
+        };
+        let text = match serde_json::to_string(&encoded) {
+            Ok(text) => text,
+            Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+        };
+        let decode = |confirmed: &EncodedConfirmedTransactionWithStatusMeta| {
+            decoder
+                .decode_confirmed(confirmed)
+                .map(|decoded| decoded.to_json())
+        };
+        let reparsed = serde_json::from_str::<EncodedConfirmedTransactionWithStatusMeta>(&text)
+            .map_err(|e| e.to_string())
+            .and_then(|confirmed| decode(&confirmed));
+        let decoded = match (decode(&encoded), reparsed) {
+            (Ok(decoded), Ok(reparsed)) if decoded == reparsed => decoded,
+            other => {
+                return TestResultReport::failure(
+                    test_name,
+                    format!("The {} decodes differently from JSON: {:?}", name, other),
+                )
+            }
+        };
+        let decision = decoded["instructions"][0]["decision"]["approved"].as_bool();
+        if decision != Some(approved) {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "The {} decoded with decision {:?}: {}",
+                    name, decision, decoded
+                ),
+            );
+        }
+        transactions.push(json!({
+            "name": name,
+            "programs": programs,
+            "transaction": text,
+            "expected": decoded,
+        }));
+    }
+
+    // Assertion 4: The cases are written where the Python test reads them
+    assertions += 1;
+    let fixture = json!({
+        "discriminators": {
+            "can_thaw_permissionless": hex::encode(CAN_THAW_PERMISSIONLESS_DISCRIMINATOR),
+            "can_freeze_permissionless": hex::encode(CAN_FREEZE_PERMISSIONLESS_DISCRIMINATOR),
+        },
+        "pdas": pdas,
+        "statuses": statuses,
+        "transactions": transactions,
+    });
+    // Written aside and renamed, since the report regenerates the cases
+    // while the test runs
+    let staged = format!("{}.{}", FIXTURE_PATH, mint);
+    let written = fs::create_dir_all("../../tests/reports")
+        .and_then(|_| fs::write(&staged, format!("{:#}\n", fixture)))
+        .and_then(|_| fs::read_to_string(&staged));
+    match written.map(|text| serde_json::from_str::<Value>(&text).ok()) {
+        Ok(Some(read)) if read == fixture => {}
+        other => {
+            fs::remove_file(&staged).ok();
+            return TestResultReport::failure(
+                test_name,
+                format!("{} did not round-trip: {:?}", staged, other.map(|_| ())),
+            );
+        }
+    }
+    if let Err(e) = fs::rename(&staged, FIXTURE_PATH) {
+        return TestResultReport::failure(test_name, format!("{}: {}", FIXTURE_PATH, e));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate the Python parity report
+#[tokio::test]
+async fn generate_python_parity_report() {
+    let results = vec![run_python_parity_cases_test().await];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Python Parity Results",
+        "../../tests/reports/python_parity.md",
+    ) {
+        panic!("Failed to generate Python parity report: {}", e);
+    }
+
+    for result in &results {
+        assert!(result.passed, "{} failed: {:?}", result.name, result.error);
+    }
+}
@@ -0,0 +1,659 @@
+//! Compliance query service tests
+//!
+//! These tests seed a `solana-program-test` bank with both gates, a
+//! freezable Token-2022 mint and four holders, run real thaw checks through
+//! the production allow list, and feed the resulting records, logs and token
+//! accounts to a `QueryService`. The service is then queried over HTTP the
+//! way a dashboard would, and must report what the bank holds.
+
+use borsh::BorshSerialize;
+use example_block_list::{create_block_list_record, BlockReason};
+use production_allow_list::{
+    config_address, ADD_TO_ALLOW_LIST, INITIALIZE, REMOVE_FROM_ALLOW_LIST,
+};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    clock::Slot,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::Transaction,
+};
+use spl_token_2022::{instruction as token_instruction, state::AccountState};
+
+use token_acl_integration_tests::{
+    analytics::{DecisionSummary, GateOperation},
+    mirror::{AccountUpdate, ListMirror, MirroredGates},
+    program_test::{mint_account, rent_exempt_account, token_account},
+    query_service::{
+        AllowListEntry, BlockListEntry, ErrorBody, FreezeStates, Membership, MintOverview,
+        QueryService, RunningQueryService,
+    },
+    reporting, TestResultReport,
+};
+use token_acl_interface as interface;
+
+/// A holder of the mint and the token account they hold it in
+#[derive(Debug, Clone, Copy)]
+struct Holder {
+    owner: Pubkey,
+    token_account: Pubkey,
+}
+
+/// Who the seeded holders are
+const ALICE: usize = 0; // allow-listed, frozen until she thaws
+const BOB: usize = 1; // allow-listed, already thawed
+const CAROL: usize = 2; // blocked, frozen
+const DAVE: usize = 3; // on neither list, frozen
+
+/// A seeded bank and a query service fed from it
+struct DashboardFixture {
+    context: ProgramTestContext,
+    allow_list: Pubkey,
+    block_list: Pubkey,
+    issuer: Keypair,
+    mint: Pubkey,
+    holders: Vec<Holder>,
+    mirror: ListMirror,
+    service: QueryService,
+    slot: Slot,
+}
+
+impl DashboardFixture {
+    /// Seed the bank, fill the service from it, and run a thaw check for
+    /// Alice (approved) and Dave (denied)
+    async fn new() -> Self {
+        let allow_list = Pubkey::new_unique();
+        let block_list = Pubkey::new_unique();
+        let issuer = Keypair::new();
+        let mint = Pubkey::new_unique();
+
+        let mut program_test = ProgramTest::new(
+            "production_allow_list",
+            allow_list,
+            processor!(production_allow_list::process_instruction),
+        );
+        program_test.add_program(
+            "example_block_list",
+            block_list,
+            processor!(example_block_list::process_instruction),
+        );
+
+        program_test.add_account(mint, mint_account(&issuer.pubkey(), 4_000));
+
+        let holders: Vec<Holder> = (0..4)
+            .map(|_| Holder {
+                owner: Pubkey::new_unique(),
+                token_account: Pubkey::new_unique(),
+            })
+            .collect();
+        for (i, holder) in holders.iter().enumerate() {
+            let state = if i == BOB {
+                AccountState::Initialized
+            } else {
+                AccountState::Frozen
+            };
+            program_test.add_account(
+                holder.token_account,
+                token_account(&mint, &holder.owner, 1_000, state),
+            );
+        }
+
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let gates = MirroredGates {
+            allow_list_program: Some(allow_list),
+            block_list_program: Some(block_list),
+        };
+        // The fixture keeps feeding the mirror the service reads; both open
+        // the same tree
+        let mirror = ListMirror::open(&db, mint, gates).unwrap();
+        let service = QueryService::new();
+        service.serve_mint(ListMirror::open(&db, mint, gates).unwrap());
+
+        let mut fixture = Self {
+            context: program_test.start_with_context().await,
+            allow_list,
+            block_list,
+            issuer,
+            mint,
+            holders,
+            mirror,
+            service,
+            slot: 0,
+        };
+
+        let payer = fixture.context.payer.pubkey();
+        let initialize = Instruction::new_with_bytes(
+            allow_list,
+            &[INITIALIZE],
+            vec![
+                AccountMeta::new(fixture.config(), false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(fixture.issuer.pubkey(), true),
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        let adds = [ALICE, BOB].map(|i| fixture.add_instruction(i));
+        fixture
+            .send(&[vec![initialize], adds.to_vec()].concat())
+            .await
+            .expect("setup should succeed");
+        fixture.block(CAROL);
+
+        fixture.refresh().await;
+        for holder in [ALICE, DAVE] {
+            fixture.thaw(holder).await;
+        }
+        fixture.refresh().await;
+        fixture
+    }
+
+    fn config(&self) -> Pubkey {
+        config_address(&self.mint, &self.allow_list).address()
+    }
+
+    fn owner(&self, holder: usize) -> Pubkey {
+        self.holders[holder].owner
+    }
+
+    fn allow_list_record(&self, holder: usize) -> Pubkey {
+        gate_view::allow_list_address(&self.allow_list, &self.mint, &self.owner(holder)).address()
+    }
+
+    fn block_list_record(&self, holder: usize) -> Pubkey {
+        gate_view::block_list_address(&self.block_list, &self.mint, &self.owner(holder)).address()
+    }
+
+    fn add_instruction(&self, holder: usize) -> Instruction {
+        Instruction::new_with_bytes(
+            self.allow_list,
+            &[ADD_TO_ALLOW_LIST],
+            vec![
+                AccountMeta::new_readonly(self.config(), false),
+                AccountMeta::new(self.allow_list_record(holder), false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(self.owner(holder), false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// Write a block list record for `holder`
+    ///
+    /// The block list example has no admin instructions, so the record is
+    /// written directly, owned by the block list program.
+    fn block(&mut self, holder: usize) {
+        let record =
+            create_block_list_record(&self.mint, &self.owner(holder), BlockReason::Sanctions, 0);
+        let account = rent_exempt_account(record.try_to_vec().unwrap(), self.block_list);
+        let address = self.block_list_record(holder);
+        self.context.set_account(&address, &account.into());
+    }
+
+    /// Run the allow list's thaw check for `holder`, followed by the thaw it
+    /// authorizes, and hand the decision to the service
+    async fn thaw(&mut self, holder: usize) {
+        let Holder {
+            owner,
+            token_account,
+        } = self.holders[holder];
+        let extra_metas =
+            interface::find_thaw_extra_account_metas_address(&self.mint, &self.allow_list).0;
+        let policy = interface::find_missing_record_policy_address(&self.mint, &self.allow_list).0;
+        let check = interface::can_thaw_permissionless(
+            &self.allow_list,
+            self.context.payer.pubkey(),
+            token_account,
+            self.mint,
+            extra_metas,
+            &[
+                owner.into(),
+                self.allow_list_record(holder).into(),
+                policy.into(),
+            ],
+        );
+        let thaw = token_instruction::thaw_account(
+            &spl_token_2022::id(),
+            &token_account,
+            &self.mint,
+            &self.issuer.pubkey(),
+            &[],
+        )
+        .unwrap();
+
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let transaction = Transaction::new_signed_with_payer(
+            &[check, thaw],
+            Some(&self.context.payer.pubkey()),
+            &[&self.context.payer, &self.issuer],
+            blockhash,
+        );
+        let outcome = self
+            .context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .unwrap();
+        let logs = outcome
+            .metadata
+            .map(|metadata| metadata.log_messages)
+            .unwrap_or_default();
+        self.service.ingest_logs(
+            &self.mint,
+            GateOperation::Thaw,
+            &logs,
+            outcome.result.is_ok(),
+        );
+    }
+
+    async fn send(&mut self, instructions: &[Instruction]) -> Result<(), BanksClientError> {
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.context.payer.pubkey()),
+            &[&self.context.payer, &self.issuer],
+            blockhash,
+        );
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+    }
+
+    async fn account(&mut self, address: Pubkey) -> Option<Account> {
+        self.context
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .filter(|account| account.lamports > 0)
+    }
+
+    /// Feed the service the state of every record and token account at a
+    /// new slot, the way its subscriptions would
+    async fn refresh(&mut self) {
+        let root = self.context.banks_client.get_root_slot().await.unwrap();
+        self.slot = self.slot.max(root) + 1;
+        self.context.warp_to_slot(self.slot).unwrap();
+
+        let mut snapshot = Vec::new();
+        for holder in 0..self.holders.len() {
+            for address in [
+                self.allow_list_record(holder),
+                self.block_list_record(holder),
+            ] {
+                if let Some(account) = self.account(address).await {
+                    snapshot.push((address, account));
+                }
+            }
+            let token_account = self.holders[holder].token_account;
+            let update = match self.account(token_account).await {
+                Some(account) => AccountUpdate::from_account(token_account, self.slot, &account),
+                None => AccountUpdate::closed(token_account, self.slot),
+            };
+            self.service.apply_token_account(&update);
+        }
+        self.mirror.reconcile(self.slot, &snapshot).unwrap();
+    }
+
+    fn spawn(&self) -> RunningQueryService {
+        self.service
+            .spawn("127.0.0.1:0".parse().unwrap())
+            .expect("service should bind")
+    }
+}
+
+/// GET `path` from the service and decode a 200 response
+async fn get_json<T: DeserializeOwned>(
+    server: &RunningQueryService,
+    path: &str,
+) -> Result<T, String> {
+    let response = reqwest::get(format!("{}{}", server.base_url(), path))
+        .await
+        .map_err(|e| format!("GET {} failed: {}", path, e))?;
+    if response.status() != StatusCode::OK {
+        return Err(format!("GET {} returned {}", path, response.status()));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| format!("GET {} returned an undecodable body: {}", path, e))
+}
+
+/// GET `path` from the service, expecting an error response
+async fn get_error(
+    server: &RunningQueryService,
+    path: &str,
+) -> Result<(StatusCode, ErrorBody), String> {
+    let response = reqwest::get(format!("{}{}", server.base_url(), path))
+        .await
+        .map_err(|e| format!("GET {} failed: {}", path, e))?;
+    let status = response.status();
+    let body = response
+        .json()
+        .await
+        .map_err(|e| format!("GET {} returned an undecodable error: {}", path, e))?;
+    Ok((status, body))
+}
+
+/// Test 1: List membership is served as the bank holds it
+#[tokio::test]
+async fn test_list_membership_queries() {
+    let report = run_list_membership_test().await;
+    assert!(
+        report.passed,
+        "List membership test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_list_membership_test() -> TestResultReport {
+    let test_name = "List Membership Queries";
+    let mut assertions = 0;
+
+    let fixture = DashboardFixture::new().await;
+    let server = fixture.spawn();
+    let mint = fixture.mint;
+    let owner = |holder| fixture.owner(holder).to_string();
+
+    let result = async {
+        // Assertion 1: The overview counts the seeded lists and accounts
+        assertions += 1;
+        let overviews: Vec<MintOverview> = get_json(&server, "/mints").await?;
+        match overviews.as_slice() {
+            [overview]
+                if overview.mint == mint.to_string()
+                    && overview.allow_listed == 2
+                    && overview.blocked == 1
+                    && overview.token_accounts == 4 => {}
+            other => return Err(format!("Unexpected overview: {:?}", other)),
+        }
+
+        // Assertion 2: The allow list holds Alice and Bob, both active
+        assertions += 1;
+        let allow_list: Vec<AllowListEntry> =
+            get_json(&server, &format!("/mints/{}/allow-list", mint)).await?;
+        let mut listed: Vec<_> = allow_list.iter().map(|entry| entry.owner.clone()).collect();
+        listed.sort();
+        let mut expected = vec![owner(ALICE), owner(BOB)];
+        expected.sort();
+        if listed != expected || !allow_list.iter().all(|entry| entry.active) {
+            return Err(format!("Unexpected allow list: {:?}", allow_list));
+        }
+
+        // Assertion 3: The block list holds Carol, blocked for sanctions
+        assertions += 1;
+        let block_list: Vec<BlockListEntry> =
+            get_json(&server, &format!("/mints/{}/block-list?blocked=true", mint)).await?;
+        if block_list.len() != 1
+            || block_list[0].owner != owner(CAROL)
+            || block_list[0].reason != BlockReason::Sanctions as u8
+        {
+            return Err(format!("Unexpected block list: {:?}", block_list));
+        }
+
+        // Assertion 4: Membership of one owner combines both lists
+        assertions += 1;
+        let carol: Membership = get_json(
+            &server,
+            &format!("/mints/{}/members/{}", mint, owner(CAROL)),
+        )
+        .await?;
+        let dave: Membership =
+            get_json(&server, &format!("/mints/{}/members/{}", mint, owner(DAVE))).await?;
+        if !carol.blocked || carol.allow_listed || carol.allow_list.is_some() {
+            return Err(format!("Unexpected membership of Carol: {:?}", carol));
+        }
+        if dave.blocked
+            || dave.allow_listed
+            || dave.allow_list.is_some()
+            || dave.block_list.is_some()
+        {
+            return Err(format!("Unexpected membership of Dave: {:?}", dave));
+        }
+        Ok(())
+    }
+    .await;
+
+    server.shutdown().await.unwrap();
+    match result {
+        Ok(()) => TestResultReport::success(test_name, assertions),
+        Err(e) => TestResultReport::failure(test_name, e),
+    }
+}
+
+/// Test 2: Decision analytics and freeze states reflect the thaw checks
+#[tokio::test]
+async fn test_analytics_and_freeze_state_queries() {
+    let report = run_analytics_and_freeze_states_test().await;
+    assert!(
+        report.passed,
+        "Analytics and freeze states test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_analytics_and_freeze_states_test() -> TestResultReport {
+    let test_name = "Analytics And Freeze State Queries";
+    let mut assertions = 0;
+
+    let fixture = DashboardFixture::new().await;
+    let server = fixture.spawn();
+    let mint = fixture.mint;
+    let token_account = |holder: usize| fixture.holders[holder].token_account.to_string();
+
+    let result = async {
+        // Assertion 1: Alice's thaw was approved and Dave's denied as unlisted
+        assertions += 1;
+        let summary: DecisionSummary =
+            get_json(&server, &format!("/mints/{}/analytics", mint)).await?;
+        if summary.total != 2
+            || summary.approved != 1
+            || summary.denial_reasons.get("not_listed") != Some(&1)
+        {
+            return Err(format!("Unexpected analytics: {:?}", summary));
+        }
+
+        // Assertion 2: Only Carol's and Dave's accounts are still frozen
+        assertions += 1;
+        let states: FreezeStates =
+            get_json(&server, &format!("/mints/{}/freeze-states", mint)).await?;
+        if states.token_accounts != 4 || states.frozen != 2 {
+            return Err(format!("Unexpected freeze totals: {:?}", states));
+        }
+
+        // Assertion 3: The frozen filter lists exactly those accounts
+        assertions += 1;
+        let frozen: FreezeStates = get_json(
+            &server,
+            &format!("/mints/{}/freeze-states?frozen=true", mint),
+        )
+        .await?;
+        let mut addresses: Vec<_> = frozen.accounts.iter().map(|a| a.address.clone()).collect();
+        addresses.sort();
+        let mut expected = vec![token_account(CAROL), token_account(DAVE)];
+        expected.sort();
+        if addresses != expected {
+            return Err(format!("Unexpected frozen accounts: {:?}", frozen.accounts));
+        }
+        Ok(())
+    }
+    .await;
+
+    server.shutdown().await.unwrap();
+    match result {
+        Ok(()) => TestResultReport::success(test_name, assertions),
+        Err(e) => TestResultReport::failure(test_name, e),
+    }
+}
+
+/// Test 3: A running service follows later changes to the bank
+#[tokio::test]
+async fn test_service_follows_updates() {
+    let report = run_follows_updates_test().await;
+    assert!(
+        report.passed,
+        "Follows updates test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_follows_updates_test() -> TestResultReport {
+    let test_name = "Service Follows Updates";
+    let mut assertions = 0;
+
+    let mut fixture = DashboardFixture::new().await;
+    let server = fixture.spawn();
+    let mint = fixture.mint;
+    let bob = fixture.owner(BOB);
+    let bob_account = fixture.holders[BOB].token_account;
+
+    // The issuer deactivates Bob and freezes his account
+    let deactivate = Instruction::new_with_bytes(
+        fixture.allow_list,
+        &[REMOVE_FROM_ALLOW_LIST, 0],
+        vec![
+            AccountMeta::new_readonly(fixture.config(), false),
+            AccountMeta::new(fixture.allow_list_record(BOB), false),
+            AccountMeta::new_readonly(fixture.issuer.pubkey(), true),
+        ],
+    );
+    let freeze = token_instruction::freeze_account(
+        &spl_token_2022::id(),
+        &bob_account,
+        &mint,
+        &fixture.issuer.pubkey(),
+        &[],
+    )
+    .unwrap();
+    if let Err(e) = fixture.send(&[deactivate, freeze]).await {
+        server.shutdown().await.unwrap();
+        return TestResultReport::failure(test_name, format!("Issuer update failed: {}", e));
+    }
+    fixture.refresh().await;
+
+    let result = async {
+        // Assertion 1: Bob's record is kept but no longer active
+        assertions += 1;
+        let membership: Membership =
+            get_json(&server, &format!("/mints/{}/members/{}", mint, bob)).await?;
+        let deactivated = matches!(&membership.allow_list, Some(entry) if !entry.allowed);
+        if membership.allow_listed || !deactivated {
+            return Err(format!("Unexpected membership of Bob: {:?}", membership));
+        }
+
+        // Assertion 2: The active filter drops him
+        assertions += 1;
+        let active: Vec<AllowListEntry> =
+            get_json(&server, &format!("/mints/{}/allow-list?active=true", mint)).await?;
+        if active.len() != 1 || active[0].owner != fixture.owner(ALICE).to_string() {
+            return Err(format!("Unexpected active allow list: {:?}", active));
+        }
+
+        // Assertion 3: His account is reported frozen at the new slot
+        assertions += 1;
+        let states: FreezeStates = get_json(
+            &server,
+            &format!("/mints/{}/freeze-states?frozen=true", mint),
+        )
+        .await?;
+        let entry = states
+            .accounts
+            .iter()
+            .find(|entry| entry.address == bob_account.to_string());
+        if states.frozen != 3 || entry.is_none_or(|entry| entry.slot != fixture.slot) {
+            return Err(format!("Bob's freeze was not followed: {:?}", states));
+        }
+        Ok(())
+    }
+    .await;
+
+    server.shutdown().await.unwrap();
+    match result {
+        Ok(()) => TestResultReport::success(test_name, assertions),
+        Err(e) => TestResultReport::failure(test_name, e),
+    }
+}
+
+/// Test 4: Malformed keys and unserved mints are client errors
+#[tokio::test]
+async fn test_query_errors() {
+    let report = run_query_errors_test().await;
+    assert!(
+        report.passed,
+        "Query errors test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_query_errors_test() -> TestResultReport {
+    let test_name = "Query Errors";
+    let mut assertions = 0;
+
+    let fixture = DashboardFixture::new().await;
+    let server = fixture.spawn();
+    let mint = fixture.mint;
+
+    let result = async {
+        // Assertion 1: A malformed mint is a 400
+        assertions += 1;
+        let (status, body) = get_error(&server, "/mints/not-a-key/allow-list").await?;
+        if status != StatusCode::BAD_REQUEST || !body.error.contains("not-a-key") {
+            return Err(format!("Malformed mint answered {} {:?}", status, body));
+        }
+
+        // Assertion 2: A malformed owner is a 400
+        assertions += 1;
+        let (status, _) = get_error(&server, &format!("/mints/{}/members/xyz", mint)).await?;
+        if status != StatusCode::BAD_REQUEST {
+            return Err(format!("Malformed owner answered {}", status));
+        }
+
+        // Assertion 3: A mint the service does not serve is a 404
+        assertions += 1;
+        let unknown = Pubkey::new_unique();
+        for route in ["allow-list", "block-list", "analytics", "freeze-states"] {
+            let (status, _) = get_error(&server, &format!("/mints/{}/{}", unknown, route)).await?;
+            if status != StatusCode::NOT_FOUND {
+                return Err(format!("Unknown mint's {} answered {}", route, status));
+            }
+        }
+        Ok(())
+    }
+    .await;
+
+    server.shutdown().await.unwrap();
+    match result {
+        Ok(()) => TestResultReport::success(test_name, assertions),
+        Err(e) => TestResultReport::failure(test_name, e),
+    }
+}
+
+/// Generate the query service test report
+#[tokio::test]
+async fn generate_query_service_report() {
+    let results = vec![
+        run_list_membership_test().await,
+        run_analytics_and_freeze_states_test().await,
+        run_follows_updates_test().await,
+        run_query_errors_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Compliance Query Service Test Results",
+        "../../tests/reports/query_service.md",
+    ) {
+        panic!("Failed to generate query service report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} query service tests failed", failed);
+}
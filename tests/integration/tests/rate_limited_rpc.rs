@@ -0,0 +1,293 @@
+//! Rate-limited RPC client tests
+//!
+//! These tests exercise the token bucket, deduplication, and caching of the
+//! live-cluster RPC wrapper against a mock endpoint. Mocked responses are
+//! consumed by the first request that reads them, so a second request that
+//! reaches the endpoint gets no account back.
+
+use serde_json::json;
+use solana_client::{
+    nonblocking::rpc_client::{Mocks, RpcClient},
+    rpc_request::RpcRequest,
+};
+use solana_sdk::pubkey::Pubkey;
+use std::time::{Duration, Instant};
+
+use token_acl_integration_tests::{
+    reporting,
+    rpc::{RateLimitedRpc, RpcLimits, TokenBucket},
+    TestResultReport,
+};
+
+const UNLIMITED: RpcLimits = RpcLimits {
+    requests_per_second: 1_000_000.0,
+    burst: 1_000,
+};
+
+fn account_response(executable: bool) -> serde_json::Value {
+    json!({
+        "context": { "slot": 1 },
+        "value": {
+            "lamports": 1_000_000,
+            "data": ["", "base64"],
+            "owner": Pubkey::new_unique().to_string(),
+            "executable": executable,
+            "rentEpoch": 0,
+            "space": 0,
+        }
+    })
+}
+
+fn mocked_rpc(executable: bool, limits: RpcLimits) -> RateLimitedRpc {
+    let mut mocks = Mocks::new();
+    mocks.insert(RpcRequest::GetAccountInfo, account_response(executable));
+    RateLimitedRpc::from_client(
+        RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks),
+        limits,
+    )
+}
+
+/// Test 1: Token bucket allows a burst, then paces requests
+#[tokio::test]
+async fn test_token_bucket() {
+    let report = run_token_bucket_test().await;
+    assert!(
+        report.passed,
+        "Token bucket test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_token_bucket_test() -> TestResultReport {
+    let test_name = "Token Bucket Pacing";
+    let mut assertions = 0;
+
+    let start = Instant::now();
+    let mut bucket = TokenBucket::new(
+        RpcLimits {
+            requests_per_second: 10.0,
+            burst: 3,
+        },
+        start,
+    );
+
+    // Assertion 1: The full burst is available immediately
+    assertions += 1;
+    if (0..3).any(|_| bucket.try_acquire(start).is_err()) {
+        return TestResultReport::failure(test_name, "Burst was not available".to_string());
+    }
+
+    // Assertion 2: The next request waits one refill interval
+    assertions += 1;
+    match bucket.try_acquire(start) {
+        Err(wait) if wait > Duration::from_millis(90) && wait <= Duration::from_millis(100) => {}
+        other => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Expected a ~100ms wait, got {:?}", other),
+            )
+        }
+    }
+
+    // Assertion 3: Refill never exceeds the burst
+    assertions += 1;
+    let later = start + Duration::from_secs(60);
+    let granted = (0..10)
+        .filter(|_| bucket.try_acquire(later).is_ok())
+        .count();
+    if granted != 3 {
+        return TestResultReport::failure(
+            test_name,
+            format!("{} requests granted after idling, expected 3", granted),
+        );
+    }
+
+    // Assertion 4: The wrapper waits instead of exceeding the rate
+    assertions += 1;
+    let rpc = RateLimitedRpc::from_client(
+        RpcClient::new_mock("succeeds".to_string()),
+        RpcLimits {
+            requests_per_second: 20.0,
+            burst: 2,
+        },
+    );
+    let started = Instant::now();
+    for _ in 0..5 {
+        if let Err(e) = rpc.get_latest_blockhash().await {
+            return TestResultReport::failure(test_name, format!("Blockhash failed: {}", e));
+        }
+    }
+    let elapsed = started.elapsed();
+    let stats = rpc.stats();
+    if elapsed < Duration::from_millis(140) || stats.requests != 5 || stats.throttled < 3 {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "5 requests at 20/s with burst 2 took {:?} ({:?})",
+                elapsed, stats
+            ),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: Concurrent fetches of one account share a request
+#[tokio::test]
+async fn test_request_deduplication() {
+    let report = run_request_deduplication_test().await;
+    assert!(
+        report.passed,
+        "Request deduplication test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_request_deduplication_test() -> TestResultReport {
+    let test_name = "Request Deduplication";
+    let mut assertions = 0;
+
+    // Throttled so the first fetch is still in flight when the others join
+    let rpc = mocked_rpc(
+        false,
+        RpcLimits {
+            requests_per_second: 20.0,
+            burst: 1,
+        },
+    );
+    let _ = rpc.get_latest_blockhash().await;
+    let account = Pubkey::new_unique();
+
+    let (first, second, third) = tokio::join!(
+        rpc.get_account(&account),
+        rpc.get_account(&account),
+        rpc.get_account(&account)
+    );
+
+    // Assertion 1: Every caller got the single mocked account
+    assertions += 1;
+    if first.is_err() || second.is_err() || third.is_err() {
+        return TestResultReport::failure(
+            test_name,
+            "A concurrent caller made its own request".to_string(),
+        );
+    }
+
+    // Assertion 2: One request reached the endpoint for the account
+    assertions += 1;
+    let stats = rpc.stats();
+    if stats.requests != 2 || stats.deduplicated != 2 {
+        return TestResultReport::failure(
+            test_name,
+            format!("Expected 2 requests and 2 deduplicated, got {:?}", stats),
+        );
+    }
+
+    // Assertion 3: Non-executable accounts are not cached afterwards
+    assertions += 1;
+    if rpc.get_account(&account).await.is_ok() || rpc.stats().cache_hits != 0 {
+        return TestResultReport::failure(
+            test_name,
+            "Mutable account was served from cache".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: Immutable data is cached, mutable data is not
+#[tokio::test]
+async fn test_immutable_data_cache() {
+    let report = run_immutable_data_cache_test().await;
+    assert!(
+        report.passed,
+        "Immutable data cache test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_immutable_data_cache_test() -> TestResultReport {
+    let test_name = "Immutable Data Cache";
+    let mut assertions = 0;
+
+    // Assertion 1: Program accounts are served from cache after one fetch
+    assertions += 1;
+    let rpc = mocked_rpc(true, UNLIMITED);
+    let program = Pubkey::new_unique();
+    for _ in 0..3 {
+        if let Err(e) = rpc.get_account(&program).await {
+            return TestResultReport::failure(test_name, format!("Program fetch failed: {}", e));
+        }
+    }
+    let stats = rpc.stats();
+    if stats.requests != 1 || stats.cache_hits != 2 {
+        return TestResultReport::failure(
+            test_name,
+            format!("Expected 1 request and 2 cache hits, got {:?}", stats),
+        );
+    }
+
+    // Assertion 2: Mint accounts are cached even though not executable
+    assertions += 1;
+    let rpc = mocked_rpc(false, UNLIMITED);
+    let mint = Pubkey::new_unique();
+    for _ in 0..3 {
+        if let Err(e) = rpc.get_mint_account(&mint).await {
+            return TestResultReport::failure(test_name, format!("Mint fetch failed: {}", e));
+        }
+    }
+    if rpc.stats().requests != 1 {
+        return TestResultReport::failure(
+            test_name,
+            format!("Mint fetched {} times", rpc.stats().requests),
+        );
+    }
+
+    // Assertion 3: Invalidation forces a fresh fetch
+    assertions += 1;
+    rpc.invalidate(&mint);
+    if rpc.get_mint_account(&mint).await.is_ok() || rpc.stats().requests != 2 {
+        return TestResultReport::failure(
+            test_name,
+            "Invalidated mint was served from cache".to_string(),
+        );
+    }
+
+    // Assertion 4: Failed fetches are not cached
+    assertions += 1;
+    let missing = Pubkey::new_unique();
+    let _ = rpc.get_mint_account(&missing).await;
+    let _ = rpc.get_mint_account(&missing).await;
+    if rpc.stats().requests != 4 {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Expected 2 requests for a missing mint, got {}",
+                rpc.stats().requests - 2
+            ),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate rate-limited RPC report
+#[tokio::test]
+async fn generate_rate_limited_rpc_report() {
+    let results = vec![
+        run_token_bucket_test().await,
+        run_request_deduplication_test().await,
+        run_immutable_data_cache_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Rate-Limited RPC Test Results",
+        "../../tests/reports/rate_limited_rpc.md",
+    ) {
+        panic!("Failed to generate rate-limited RPC report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} rate-limited RPC tests failed", failed);
+}
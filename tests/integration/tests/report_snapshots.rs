@@ -0,0 +1,334 @@
+//! Report snapshot tests
+//!
+//! These tests render every report format from fixed inputs and compare the
+//! output against the golden files in `tests/snapshots`. A deliberate
+//! formatting change is accepted by rerunning with `UPDATE_SNAPSHOTS=1` and
+//! committing the updated `.snap` files. The clock is mocked, so "Generated"
+//! lines are stable too.
+
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::Signature,
+};
+use std::{fs, time::Duration};
+
+use token_acl_integration_tests::{
+    analytics::{DecisionAnalytics, GateOperation},
+    contention::ContentionAnalyzer,
+    coverage_reporting, coverage_utils,
+    explorer::Cluster,
+    metrics::TestMetrics,
+    performance_analysis, reporting,
+    snapshots::{self, assert_snapshot, redact_timestamps},
+    suite_config::SuiteCategory,
+    time, BenchmarkResult, CoverageRequirements, CoverageResults, TestResultReport,
+    TransactionRecord,
+};
+
+/// 2024-10-16 09:30:00 UTC
+const SNAPSHOT_TIME: i64 = 1_729_071_000;
+
+fn fixed_key(byte: u8) -> Pubkey {
+    Pubkey::new_from_array([byte; 32])
+}
+
+fn sample_results() -> Vec<TestResultReport> {
+    vec![
+        TestResultReport::success("PDA Derivation Test", 3),
+        TestResultReport::success("Permission De-escalation Security", 5).with_transactions(vec![
+            TransactionRecord {
+                label: "add holder".to_string(),
+                signature: Signature::from([1; 64]),
+                slot: Some(42),
+                trace: None,
+            },
+            TransactionRecord {
+                label: "thaw".to_string(),
+                signature: Signature::from([2; 64]),
+                slot: None,
+                trace: None,
+            },
+        ]),
+        TestResultReport::failure("KYC Expiration Scenario", "Expired user thawed".to_string()),
+    ]
+}
+
+/// Test 1: Markdown and HTML test reports, with and without skipped tests
+#[tokio::test]
+async fn test_test_report_snapshots() {
+    let report = run_test_report_snapshots_test();
+    assert!(
+        report.passed,
+        "Test report snapshot test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_test_report_snapshots_test() -> TestResultReport {
+    let test_name = "Test Report Snapshots";
+    let mut assertions = 0;
+    let _clock = time::mock_now(SNAPSHOT_TIME);
+    let results = sample_results();
+
+    // Assertion 1: Markdown report
+    assertions += 1;
+    let markdown = reporting::render_test_report(&results, "Sample Results", &[]);
+    if let Err(e) = assert_snapshot("test_report_markdown", &markdown) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 2: HTML report
+    assertions += 1;
+    let html = reporting::render_html_report(&results, "Sample Results", &Cluster::Devnet);
+    if let Err(e) = assert_snapshot("test_report_html", &html) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 3: Skipped tests and disabled categories
+    assertions += 1;
+    let mut partial = sample_results();
+    partial.push(TestResultReport::skipped(
+        "Shared Gate Parallel Thaw Load",
+        SuiteCategory::LoadTests,
+    ));
+    let markdown = reporting::render_test_report(
+        &partial,
+        "Partial Results",
+        &[SuiteCategory::LoadTests, SuiteCategory::Devnet],
+    );
+    if let Err(e) = assert_snapshot("test_report_disabled_categories", &markdown) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 4: Cost section per test and per category
+    assertions += 1;
+    let mut costed = sample_results();
+    let mut add = TestMetrics::default();
+    add.record_fee(10_000);
+    add.cost.rent = 1_426_800;
+    let mut devnet = TestMetrics::new(Some(SuiteCategory::Devnet));
+    devnet.record_fee(5_000);
+    devnet.record_fee(5_000);
+    devnet.cost.rent = 2_039_280;
+    devnet.cost.reclaimed = 2_039_280;
+    costed[0] = costed[0].clone().with_metrics(add);
+    costed[1] = costed[1].clone().with_metrics(devnet);
+    let markdown = reporting::render_test_report(&costed, "Costed Results", &[]);
+    if let Err(e) = assert_snapshot("test_report_costs", &markdown) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: Performance and coverage reports
+#[tokio::test]
+async fn test_summary_report_snapshots() {
+    let report = run_summary_report_snapshots_test();
+    assert!(
+        report.passed,
+        "Summary report snapshot test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_summary_report_snapshots_test() -> TestResultReport {
+    let test_name = "Summary Report Snapshots";
+    let mut assertions = 0;
+    let _clock = time::mock_now(SNAPSHOT_TIME);
+
+    // Assertion 1: Performance report
+    assertions += 1;
+    let benchmarks = vec![
+        BenchmarkResult::success(
+            "PDA Derivation",
+            Duration::from_millis(150),
+            100,
+            Duration::from_micros(1_200),
+            Duration::from_micros(2_100),
+        ),
+        BenchmarkResult::success(
+            "Discriminator Validation",
+            Duration::from_micros(500),
+            1_000,
+            Duration::ZERO,
+            Duration::from_micros(3),
+        ),
+        BenchmarkResult::failure("Serialization", "Buffer too small".to_string()),
+    ];
+    let performance = performance_analysis::render_performance_report(&benchmarks);
+    if let Err(e) = assert_snapshot("performance_report", &performance) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 2: Coverage report
+    assertions += 1;
+    let mut coverage = CoverageResults {
+        analysis: coverage_utils::analyze_test_results(&sample_results()),
+        requirements: CoverageRequirements::default(),
+        meets_requirements: false,
+        recommendations: Vec::new(),
+    };
+    coverage.check_requirements();
+    let coverage = coverage_reporting::render_coverage_report(&coverage);
+    if let Err(e) = assert_snapshot("coverage_report", &coverage) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: Decision analytics and contention artifacts
+#[tokio::test]
+async fn test_analysis_artifact_snapshots() {
+    let report = run_analysis_artifact_snapshots_test();
+    assert!(
+        report.passed,
+        "Analysis artifact snapshot test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_analysis_artifact_snapshots_test() -> TestResultReport {
+    let test_name = "Analysis Artifact Snapshots";
+    let mut assertions = 0;
+
+    let user = fixed_key(7);
+    let mut analytics = DecisionAnalytics::new();
+    analytics.ingest_logs(
+        GateOperation::Thaw,
+        &[format!("Program log: User {} allowed (level: kyc)", user)],
+        true,
+    );
+    analytics.ingest_logs(
+        GateOperation::Thaw,
+        &[format!("Program log: User {} not in allow list", user)],
+        false,
+    );
+    analytics.ingest_logs(
+        GateOperation::Freeze,
+        &[format!("Program log: User {} is blocked", user)],
+        false,
+    );
+
+    // Assertion 1: Decision summary JSON
+    assertions += 1;
+    let json = match analytics.to_json() {
+        Ok(json) => json,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+    if let Err(e) = assert_snapshot("decision_summary_json", &json) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 2: Decision summary CSV
+    assertions += 1;
+    if let Err(e) = assert_snapshot("decision_summary_csv", &analytics.to_csv()) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 3: Contention markdown
+    assertions += 1;
+    let gate = fixed_key(1);
+    let counter = fixed_key(2);
+    let mut contention = ContentionAnalyzer::new();
+    contention.label(counter, "global counter");
+    for (operation, record) in [("add", 3), ("remove", 4)] {
+        contention.record_instructions(
+            operation,
+            &[Instruction::new_with_bytes(
+                gate,
+                &[0],
+                vec![
+                    AccountMeta::new(counter, false),
+                    AccountMeta::new(fixed_key(record), false),
+                ],
+            )],
+        );
+    }
+    if let Err(e) = assert_snapshot("contention_markdown", &contention.to_markdown()) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 4: Snapshot mismatches fail with a diff and leave the new output
+#[tokio::test]
+async fn test_snapshot_mismatch_handling() {
+    let report = run_snapshot_mismatch_test();
+    assert!(
+        report.passed,
+        "Snapshot mismatch handling test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_snapshot_mismatch_test() -> TestResultReport {
+    let test_name = "Snapshot Mismatch Handling";
+    let mut assertions = 0;
+
+    let dir = std::env::temp_dir().join(format!("token_acl_snapshots_{}", std::process::id()));
+    fs::remove_dir_all(&dir).ok();
+
+    // Assertion 1: A missing snapshot fails and leaves a pending file
+    assertions += 1;
+    if snapshots::assert_snapshot_in(&dir, "sample", "a\nb\n", false).is_ok()
+        || !dir.join("sample.snap.new").exists()
+    {
+        return TestResultReport::failure(test_name, "Missing snapshot was accepted".to_string());
+    }
+
+    // Assertion 2: Updating accepts the output and clears the pending file
+    assertions += 1;
+    if let Err(e) = snapshots::assert_snapshot_in(&dir, "sample", "a\nb\n", true) {
+        return TestResultReport::failure(test_name, e);
+    }
+    if dir.join("sample.snap.new").exists()
+        || snapshots::assert_snapshot_in(&dir, "sample", "a\nb\n", false).is_err()
+    {
+        return TestResultReport::failure(test_name, "Updated snapshot did not match".to_string());
+    }
+
+    // Assertion 3: A changed line is reported as a diff
+    assertions += 1;
+    match snapshots::assert_snapshot_in(&dir, "sample", "a\nc\n", false) {
+        Err(message) if message.contains("2 - b") && message.contains("2 + c") => {}
+        other => {
+            return TestResultReport::failure(test_name, format!("Unexpected result: {:?}", other))
+        }
+    }
+
+    // Assertion 4: Timestamps are redacted, other digits are kept
+    assertions += 1;
+    let redacted = redact_timestamps("**Generated**: 2024-10-16 09:30:00 UTC (run 12)");
+    if redacted != "**Generated**: [timestamp] (run 12)" {
+        return TestResultReport::failure(test_name, format!("Redacted to '{}'", redacted));
+    }
+
+    fs::remove_dir_all(&dir).ok();
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate report snapshot test report
+#[tokio::test]
+async fn generate_report_snapshots_report() {
+    let results = vec![
+        run_test_report_snapshots_test(),
+        run_summary_report_snapshots_test(),
+        run_analysis_artifact_snapshots_test(),
+        run_snapshot_mismatch_test(),
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Report Snapshot Test Results",
+        "../../tests/reports/report_snapshots.md",
+    ) {
+        panic!("Failed to generate report snapshot report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} report snapshot tests failed", failed);
+}
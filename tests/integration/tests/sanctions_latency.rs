@@ -0,0 +1,463 @@
+//! Sanctions-response latency macro-benchmark
+//!
+//! Measures how long it takes from "user added to the block list" until
+//! every one of the user's token accounts is frozen, the number a compliance
+//! SLA is written against. Each trial lists a fresh offender holding N token
+//! accounts and runs the keeper crank: enumerate the offender's accounts,
+//! resolve `can-freeze-permissionless` for each from the freeze
+//! extra-account-metas, and submit the freezes in batches, all at once.
+//!
+//! Trials run with the cluster idle and with a backlog of other holders'
+//! thaw checks submitted alongside the crank. The block list example has no
+//! admin instruction to add a record, so listing writes the record into the
+//! bank directly, the way the hybrid gate tests sanction a holder; the clock
+//! starts at that write. The issuer signs the freezes, standing in for the
+//! FAMP PDA.
+//!
+//! Transactions in flight together each have their own fee payer: the
+//! program-test bank processes whatever arrives at once as a single entry,
+//! and a second transaction write-locking the same payer is dropped rather
+//! than retried. A keeper running batches in parallel needs a pool of fee
+//! payers for the same reason on a real cluster.
+
+use borsh::BorshSerialize;
+use example_block_list::{
+    block_list_address, create_block_list_record, initialize_freeze_extra_account_metas,
+    BlockReason,
+};
+use solana_program_test::{processor, BanksClient, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    hash::Hash,
+    instruction::Instruction,
+    packet::PACKET_DATA_SIZE,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use spl_tlv_account_resolution::state::AccountFetchError;
+use spl_token_2022::{
+    instruction as token_instruction,
+    state::{Account as TokenAccount, AccountState},
+};
+use std::time::{Duration, Instant};
+
+use token_acl_integration_tests::{
+    benchmarks::LatencyDistribution,
+    onboarding::signed_size,
+    program_test::{mint_account, rent_exempt_account, token_account},
+    reporting,
+    suite_config::{self, SuiteCategory},
+    units, TestResultReport,
+};
+use token_acl_interface as interface;
+
+/// Token accounts held by the offender, per scenario
+const ACCOUNT_COUNTS: [usize; 3] = [1, 10, 50];
+
+/// Other holders' thaw checks in flight during the crank, per load level
+const LOAD_LEVELS: [(&str, usize); 2] = [("idle", 0), ("busy", 250)];
+
+/// Offenders listed per scenario
+const TRIALS: usize = 10;
+
+/// Check-and-freeze pairs per keeper transaction
+const FREEZES_PER_TRANSACTION: usize = 8;
+
+/// Lamports given to every fee payer, enough for any number of trials
+const FEE_PAYER_LAMPORTS: u64 = 1_000_000_000;
+
+/// p99 the crank must meet in every scenario on a local bank
+const MAX_P99: Duration = Duration::from_secs(10);
+
+/// Latency of one scenario over all its trials
+struct ScenarioLatency {
+    accounts: usize,
+    load: &'static str,
+    latency: LatencyDistribution,
+}
+
+struct SanctionsFixture {
+    context: ProgramTestContext,
+    gate: Pubkey,
+    mint: Pubkey,
+    issuer: Keypair,
+}
+
+impl SanctionsFixture {
+    async fn new() -> Self {
+        let gate = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let issuer = Keypair::new();
+
+        let mut program_test = ProgramTest::new(
+            "example_block_list",
+            gate,
+            processor!(example_block_list::process_instruction),
+        );
+        program_test.add_account(mint, mint_account(&issuer.pubkey(), 0));
+        let mut context = program_test.start_with_context().await;
+
+        let init = initialize_freeze_extra_account_metas(&gate, &context.payer.pubkey(), &mint);
+        let transaction = Transaction::new_signed_with_payer(
+            &[init],
+            Some(&context.payer.pubkey()),
+            &[&context.payer],
+            context.last_blockhash,
+        );
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .expect("freeze extra-account-metas should initialize");
+
+        Self {
+            context,
+            gate,
+            mint,
+            issuer,
+        }
+    }
+
+    /// A new holder with `count` thawed token accounts
+    fn new_holder(&mut self, count: usize) -> (Pubkey, Vec<Pubkey>) {
+        let holder = Pubkey::new_unique();
+        let accounts: Vec<Pubkey> = (0..count).map(|_| Pubkey::new_unique()).collect();
+        for address in &accounts {
+            self.context.set_account(
+                address,
+                &token_account(&self.mint, &holder, 1_000, AccountState::Initialized).into(),
+            );
+        }
+        (holder, accounts)
+    }
+
+    /// A new keypair holding `FEE_PAYER_LAMPORTS`
+    fn fee_payer(&mut self) -> Keypair {
+        let payer = Keypair::new();
+        self.context.set_account(
+            &payer.pubkey(),
+            &Account::new(FEE_PAYER_LAMPORTS, 0, &solana_sdk::system_program::id()).into(),
+        );
+        payer
+    }
+
+    /// Put `holder` on the sanctions list
+    fn list(&mut self, holder: &Pubkey) {
+        let record = create_block_list_record(&self.mint, holder, BlockReason::Sanctions, 0);
+        let account = rent_exempt_account(record.try_to_vec().unwrap(), self.gate);
+        self.context.set_account(
+            &block_list_address(&self.mint, holder, &self.gate).address(),
+            &account.into(),
+        );
+    }
+
+    /// Backlog of thaw checks for holders that are not listed, each unique
+    fn load(&mut self, count: usize, blockhash: Hash) -> Vec<Transaction> {
+        let extra_metas =
+            interface::find_thaw_extra_account_metas_address(&self.mint, &self.gate).0;
+        let policy = interface::find_missing_record_policy_address(&self.mint, &self.gate).0;
+        (0..count)
+            .map(|_| {
+                let (holder, token_accounts) = self.new_holder(1);
+                let payer = self.fee_payer();
+                let check = interface::can_thaw_permissionless(
+                    &self.gate,
+                    payer.pubkey(),
+                    token_accounts[0],
+                    self.mint,
+                    extra_metas,
+                    &[
+                        holder.into(),
+                        block_list_address(&self.mint, &holder, &self.gate)
+                            .address()
+                            .into(),
+                        policy.into(),
+                    ],
+                );
+                Transaction::new_signed_with_payer(
+                    &[check],
+                    Some(&payer.pubkey()),
+                    &[&payer],
+                    blockhash,
+                )
+            })
+            .collect()
+    }
+
+    async fn frozen(&mut self, accounts: &[Pubkey]) -> usize {
+        let mut frozen = 0;
+        for address in accounts {
+            let account = self.context.banks_client.get_account(*address).await;
+            if let Ok(Some(account)) = account {
+                if TokenAccount::unpack(&account.data).map(|a| a.state) == Ok(AccountState::Frozen)
+                {
+                    frozen += 1;
+                }
+            }
+        }
+        frozen
+    }
+}
+
+/// The keeper crank: resolves and submits the freezes of an offender's
+/// token accounts
+struct KeeperCrank {
+    banks_client: BanksClient,
+    /// One fee payer per batch in flight
+    keepers: Vec<Keypair>,
+    /// Signs the freezes in place of the FAMP PDA
+    freeze_authority: Keypair,
+    gate: Pubkey,
+    mint: Pubkey,
+}
+
+impl KeeperCrank {
+    /// `can-freeze-permissionless` for `token_account`, resolved from the
+    /// freeze extra-account-metas, followed by the freeze it authorizes
+    async fn freeze_instructions(
+        &self,
+        keeper: &Pubkey,
+        token_account: &Pubkey,
+    ) -> Result<[Instruction; 2], String> {
+        let mut check = interface::can_freeze_permissionless(
+            &self.gate,
+            *keeper,
+            *token_account,
+            self.mint,
+            interface::find_freeze_extra_account_metas_address(&self.mint, &self.gate).0,
+            &[],
+        );
+        interface::resolve_extra_account_metas(&mut check, |address| {
+            let mut banks_client = self.banks_client.clone();
+            async move {
+                banks_client
+                    .get_account(address)
+                    .await
+                    .map(|account| account.map(|a| a.data))
+                    .map_err(|e| Box::new(e) as AccountFetchError)
+            }
+        })
+        .await
+        .map_err(|e| format!("Resolving the freeze of {} failed: {}", token_account, e))?;
+        let freeze = token_instruction::freeze_account(
+            &spl_token_2022::id(),
+            token_account,
+            &self.mint,
+            &self.freeze_authority.pubkey(),
+            &[],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok([check, freeze])
+    }
+
+    /// Freeze every account in `token_accounts`, batches in parallel
+    async fn freeze_all(&self, token_accounts: &[Pubkey], blockhash: Hash) -> Result<(), String> {
+        let mut transactions = Vec::new();
+        for (batch, keeper) in token_accounts
+            .chunks(FREEZES_PER_TRANSACTION)
+            .zip(&self.keepers)
+        {
+            let mut instructions = Vec::new();
+            for token_account in batch {
+                instructions.extend(
+                    self.freeze_instructions(&keeper.pubkey(), token_account)
+                        .await?,
+                );
+            }
+            let transaction = Transaction::new_signed_with_payer(
+                &instructions,
+                Some(&keeper.pubkey()),
+                &[keeper, &self.freeze_authority],
+                blockhash,
+            );
+            if signed_size(&transaction) > PACKET_DATA_SIZE {
+                return Err(format!(
+                    "A batch of {} freezes does not fit in a transaction",
+                    batch.len()
+                ));
+            }
+            transactions.push(transaction);
+        }
+
+        let handles: Vec<_> = transactions
+            .into_iter()
+            .map(|transaction| {
+                let mut banks_client = self.banks_client.clone();
+                tokio::spawn(async move { banks_client.process_transaction(transaction).await })
+            })
+            .collect();
+        for handle in handles {
+            handle
+                .await
+                .map_err(|e| e.to_string())?
+                .map_err(|e| format!("Freeze batch failed: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Run `TRIALS` listings of an offender with `accounts` token accounts while
+/// `load` other thaw checks are in flight
+async fn measure(
+    fixture: &mut SanctionsFixture,
+    accounts: usize,
+    load: usize,
+) -> Result<LatencyDistribution, String> {
+    let crank = KeeperCrank {
+        banks_client: fixture.context.banks_client.clone(),
+        keepers: (0..accounts.div_ceil(FREEZES_PER_TRANSACTION))
+            .map(|_| fixture.fee_payer())
+            .collect(),
+        freeze_authority: fixture.issuer.insecure_clone(),
+        gate: fixture.gate,
+        mint: fixture.mint,
+    };
+
+    let mut samples = Vec::with_capacity(TRIALS);
+    for trial in 0..TRIALS {
+        let (offender, token_accounts) = fixture.new_holder(accounts);
+        let blockhash = fixture
+            .context
+            .banks_client
+            .get_latest_blockhash()
+            .await
+            .map_err(|e| e.to_string())?;
+        let backlog: Vec<_> = fixture
+            .load(load, blockhash)
+            .into_iter()
+            .map(|transaction| {
+                let mut banks_client = fixture.context.banks_client.clone();
+                tokio::spawn(async move { banks_client.process_transaction(transaction).await })
+            })
+            .collect();
+
+        let listed = Instant::now();
+        fixture.list(&offender);
+        crank.freeze_all(&token_accounts, blockhash).await?;
+        samples.push(listed.elapsed());
+
+        for handle in backlog {
+            handle
+                .await
+                .map_err(|e| e.to_string())?
+                .map_err(|e| format!("Background thaw check failed: {}", e))?;
+        }
+        let frozen = fixture.frozen(&token_accounts).await;
+        if frozen != accounts {
+            return Err(format!(
+                "Trial {}: {} of {} accounts frozen",
+                trial, frozen, accounts
+            ));
+        }
+    }
+    Ok(LatencyDistribution::new(samples))
+}
+
+fn render_latency_report(scenarios: &[ScenarioLatency]) -> String {
+    let mut report = String::from("## Sanctions-Response Latency\n\n");
+    report.push_str(&format!(
+        "Time from listing to every token account frozen, over {} trials per scenario.\n\n",
+        TRIALS
+    ));
+    report.push_str("| Accounts | Load | p50 | p90 | p99 | Max |\n");
+    report.push_str("|----------|------|-----|-----|-----|-----|\n");
+    for scenario in scenarios {
+        let latency = &scenario.latency;
+        report.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} |\n",
+            scenario.accounts,
+            scenario.load,
+            units::duration(latency.percentile(50.0)),
+            units::duration(latency.percentile(90.0)),
+            units::duration(latency.percentile(99.0)),
+            units::duration(latency.max())
+        ));
+    }
+    report
+}
+
+/// Measure sanctions-response latency across account counts and load
+#[tokio::test]
+async fn benchmark_sanctions_response_latency() {
+    let (report, scenarios) = run_sanctions_latency_benchmark().await;
+
+    let mut markdown = reporting::render_test_report(
+        std::slice::from_ref(&report),
+        "Sanctions-Response Latency Results",
+        &suite_config::current().disabled(),
+    );
+    if !scenarios.is_empty() {
+        let latency = render_latency_report(&scenarios);
+        println!("{}", latency);
+        markdown.push_str(&latency);
+    }
+    std::fs::create_dir_all("../../tests/reports").ok();
+    if let Err(e) = std::fs::write("../../tests/reports/sanctions_latency.md", markdown) {
+        panic!("Failed to generate sanctions latency report: {}", e);
+    }
+
+    assert!(
+        report.passed,
+        "Sanctions latency benchmark failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_sanctions_latency_benchmark() -> (TestResultReport, Vec<ScenarioLatency>) {
+    let test_name = "Sanctions-Response Latency";
+    let mut assertions = 0;
+    if let Some(skipped) = suite_config::skip_unless(SuiteCategory::LoadTests, test_name) {
+        return (skipped, Vec::new());
+    }
+
+    let mut scenarios = Vec::new();
+    for (load, backlog) in LOAD_LEVELS {
+        let mut fixture = SanctionsFixture::new().await;
+        for accounts in ACCOUNT_COUNTS {
+            // Assertion: every trial froze all of the offender's accounts
+            assertions += 1;
+            match measure(&mut fixture, accounts, backlog).await {
+                Ok(latency) => scenarios.push(ScenarioLatency {
+                    accounts,
+                    load,
+                    latency,
+                }),
+                Err(e) => {
+                    return (
+                        TestResultReport::failure(
+                            test_name,
+                            format!("{} accounts, {} load: {}", accounts, load, e),
+                        ),
+                        scenarios,
+                    )
+                }
+            }
+        }
+    }
+
+    // Assertion: the crank meets the p99 budget in every scenario
+    assertions += 1;
+    if let Some(slow) = scenarios
+        .iter()
+        .find(|scenario| scenario.latency.percentile(99.0) > MAX_P99)
+    {
+        let p99 = units::duration(slow.latency.percentile(99.0));
+        return (
+            TestResultReport::failure(
+                test_name,
+                format!(
+                    "p99 of {} with {} accounts under {} load, above {}",
+                    p99,
+                    slow.accounts,
+                    slow.load,
+                    units::duration(MAX_P99)
+                ),
+            ),
+            scenarios,
+        );
+    }
+
+    (TestResultReport::success(test_name, assertions), scenarios)
+}
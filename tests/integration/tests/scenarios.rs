@@ -0,0 +1,97 @@
+//! Shared scenario tests
+//!
+//! Runs every narrative scenario of the `scenarios` library on a
+//! `solana-program-test` bank with the production allow list deployed. The
+//! `test-client` binary runs the same scenarios against devnet.
+
+use token_acl_integration_tests::{
+    reporting,
+    scenarios::{Scenario, ScenarioOutcome},
+    TestResultReport,
+};
+
+async fn run_scenario_test(scenario: Scenario) -> TestResultReport {
+    let test_name = format!("Scenario: {}", scenario.title());
+    match scenario.run_on_bank(&test_name).await {
+        Ok(ScenarioOutcome { checks, .. }) => TestResultReport::success(&test_name, checks),
+        Err(e) => TestResultReport::failure(&test_name, e),
+    }
+}
+
+/// Test 1: A listed holder is thawed through the gate and funded, an
+/// unlisted one stays frozen
+#[tokio::test]
+async fn test_integration_flow_scenario() {
+    let report = run_scenario_test(Scenario::IntegrationFlow).await;
+    assert!(
+        report.passed,
+        "Integration flow scenario failed: {:?}",
+        report.error
+    );
+}
+
+/// Test 2: Investors at each tier are onboarded, and unverified, lapsed
+/// and sanctioned ones are kept out
+#[tokio::test]
+async fn test_rwa_workflow_scenario() {
+    let report = run_scenario_test(Scenario::RwaWorkflow).await;
+    assert!(
+        report.passed,
+        "RWA workflow scenario failed: {:?}",
+        report.error
+    );
+}
+
+/// Test 3: One transaction onboards a listed holder, and a denial leaves no
+/// account behind
+#[tokio::test]
+async fn test_onboarding_scenario() {
+    let report = run_scenario_test(Scenario::Onboarding).await;
+    assert!(
+        report.passed,
+        "Onboarding scenario failed: {:?}",
+        report.error
+    );
+}
+
+/// Test 4: A freeze-all keeps the revoked holder frozen and thaws the rest
+/// with their balances intact
+#[tokio::test]
+async fn test_emergency_scenario() {
+    let report = run_scenario_test(Scenario::Emergency).await;
+    assert!(
+        report.passed,
+        "Emergency scenario failed: {:?}",
+        report.error
+    );
+}
+
+/// Test 5: The test client's scenario names parse back to their scenario
+#[test]
+fn test_scenario_names() {
+    for scenario in Scenario::ALL {
+        assert_eq!(Scenario::parse(scenario.name()), Some(scenario));
+    }
+    assert_eq!(Scenario::parse("unknown"), None);
+}
+
+/// Generate shared scenario report
+#[tokio::test]
+async fn generate_scenarios_report() {
+    let mut results = Vec::new();
+    for scenario in Scenario::ALL {
+        results.push(run_scenario_test(scenario).await);
+    }
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Shared Scenario Results",
+        "../../tests/reports/scenarios.md",
+    ) {
+        panic!("Failed to generate scenarios report: {}", e);
+    }
+
+    for result in &results {
+        assert!(result.passed, "{} failed: {:?}", result.name, result.error);
+    }
+}
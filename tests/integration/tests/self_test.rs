@@ -0,0 +1,294 @@
+//! Suite self-test
+//!
+//! Feeds the suite's checks known-bad fixtures and asserts each is reported
+//! as a failure, so a check that can no longer fail is caught. The logic
+//! checks run from `self_test`; the on-chain fixtures put a wrong
+//! discriminator and an expired record in front of the production allow
+//! list in program-test.
+
+use borsh::BorshSerialize;
+use production_allow_list::{allow_list_address, AllowListRecord};
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::Signer,
+    transaction::Transaction,
+};
+use std::fs;
+
+use token_acl_integration_tests::{
+    fixtures::test_data,
+    reporting,
+    self_test::{self, SelfTestOutcome, GATE_TIMESTAMP},
+    suite_config::{self, SuiteCategory},
+    TestResultReport,
+};
+use token_acl_interface::find_missing_record_policy_address;
+
+/// A bank with the production allow list and one holder whose record is
+/// written directly, so fixtures can plant any record state
+struct ThawFixture {
+    context: ProgramTestContext,
+    program_id: Pubkey,
+    mint: Pubkey,
+    holder: Pubkey,
+}
+
+impl ThawFixture {
+    async fn new() -> Self {
+        let program_id = Pubkey::new_unique();
+        let program_test = ProgramTest::new(
+            "production_allow_list",
+            program_id,
+            processor!(production_allow_list::process_instruction),
+        );
+        Self {
+            context: program_test.start_with_context().await,
+            program_id,
+            mint: Pubkey::new_unique(),
+            holder: Pubkey::new_unique(),
+        }
+    }
+
+    fn set_record(&mut self, record: &AllowListRecord) {
+        let data = record.try_to_vec().expect("record serializes");
+        let account = Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: self.program_id,
+            executable: false,
+            rent_epoch: 0,
+        };
+        let pda = allow_list_address(&self.mint, &self.holder, &self.program_id).address();
+        self.context.set_account(&pda, &account.into());
+    }
+
+    fn can_thaw_instruction(&self, discriminator: [u8; 8]) -> Instruction {
+        let (extra_metas, _) = Pubkey::find_program_address(
+            &[test_data::THAW_EXTRA_ACCOUNT_METAS_SEED, self.mint.as_ref()],
+            &self.program_id,
+        );
+        Instruction::new_with_bytes(
+            self.program_id,
+            &discriminator,
+            vec![
+                AccountMeta::new_readonly(self.context.payer.pubkey(), false),
+                AccountMeta::new_readonly(Pubkey::new_unique(), false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(extra_metas, false),
+                AccountMeta::new_readonly(self.holder, false),
+                AccountMeta::new_readonly(
+                    allow_list_address(&self.mint, &self.holder, &self.program_id).address(),
+                    false,
+                ),
+                AccountMeta::new_readonly(
+                    find_missing_record_policy_address(&self.mint, &self.program_id).0,
+                    false,
+                ),
+            ],
+        )
+    }
+
+    /// The suite's allow-listed-holder check: the gate must authorize the
+    /// holder's permissionless thaw
+    async fn check_holder_thaws(&mut self, discriminator: [u8; 8]) -> TestResultReport {
+        let test_name = "Allow-Listed Holder Thaws";
+        let instruction = self.can_thaw_instruction(discriminator);
+        let recent_blockhash = match self.context.get_new_latest_blockhash().await {
+            Ok(blockhash) => blockhash,
+            Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+        };
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&self.context.payer.pubkey()),
+            &[&self.context.payer],
+            recent_blockhash,
+        );
+        match self
+            .context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+        {
+            Ok(()) => TestResultReport::success(test_name, 1),
+            Err(e) => TestResultReport::failure(test_name, format!("Gate denied thaw: {}", e)),
+        }
+    }
+}
+
+/// Test 1: The suite's logic checks catch every known-bad fixture
+#[tokio::test]
+async fn test_known_bad_fixtures_fail() {
+    let report = run_known_bad_fixtures_test();
+    assert!(
+        report.passed,
+        "Known-bad fixtures test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_known_bad_fixtures_test() -> TestResultReport {
+    let test_name = "Self-Test Known-Bad Fixtures";
+    if let Some(skipped) = suite_config::skip_unless(SuiteCategory::SelfTest, test_name) {
+        return skipped;
+    }
+    let mut assertions = 0;
+
+    // Assertion 1: The known-good baseline passes, so a caught fixture is
+    // not just a check that rejects everything
+    assertions += 1;
+    if let Some(failed) = self_test::run_known_good().into_iter().find(|r| !r.passed) {
+        return TestResultReport::failure(
+            test_name,
+            format!("{} fails on good fixtures: {:?}", failed.name, failed.error),
+        );
+    }
+
+    // Assertion 2: Every known-bad fixture is reported as a failure
+    assertions += 1;
+    let outcomes = self_test::run_self_test();
+    let missed: Vec<&str> = outcomes
+        .iter()
+        .filter(|outcome| !outcome.caught())
+        .map(|outcome| outcome.fixture.as_str())
+        .collect();
+    if !missed.is_empty() {
+        return TestResultReport::failure(
+            test_name,
+            format!("Suite passed known-bad fixtures: {}", missed.join(", ")),
+        );
+    }
+
+    // Assertion 3: Each fixture is caught for its own defect, not an
+    // earlier one
+    assertions += 1;
+    for outcome in &outcomes {
+        let error = outcome.report.error.as_deref().unwrap_or_default();
+        let expected = match outcome.fixture.as_str() {
+            "Wrong thaw discriminator" | "Swapped discriminators" => "Thaw discriminator",
+            "Wrong freeze discriminator" => "Freeze discriminator",
+            "Expired record" => "expired",
+            "Revoked record" => "revoked: true",
+            "Record for another mint" => "Record is for",
+            _ => continue,
+        };
+        if !error.contains(expected) {
+            return TestResultReport::failure(
+                test_name,
+                format!("{} reported as: {}", outcome.fixture, error),
+            );
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: On-chain, the allow-listed-holder check fails for a wrong
+/// discriminator and an expired record
+#[tokio::test]
+async fn test_known_bad_fixtures_fail_on_chain() {
+    let report = run_on_chain_fixtures_test().await;
+    assert!(
+        report.passed,
+        "On-chain known-bad fixtures test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_on_chain_fixtures_test() -> TestResultReport {
+    let test_name = "Self-Test On-Chain Fixtures";
+    if let Some(skipped) = suite_config::skip_unless(SuiteCategory::SelfTest, test_name) {
+        return skipped;
+    }
+    let (_, outcomes) = match run_on_chain_outcomes().await {
+        Ok(results) => results,
+        Err(report) => return report,
+    };
+    let mut assertions = 0;
+
+    // Assertion 1: Every on-chain known-bad fixture fails the check
+    assertions += 1;
+    if let Some(missed) = outcomes.iter().find(|outcome| !outcome.caught()) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Holder check passed with {}", missed.fixture),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Run the holder check against a good record, then against each on-chain
+/// fixture; the good run must pass or there is nothing to compare against
+async fn run_on_chain_outcomes(
+) -> Result<(TestResultReport, Vec<SelfTestOutcome>), TestResultReport> {
+    let test_name = "Self-Test On-Chain Fixtures";
+    let mut fixture = ThawFixture::new().await;
+    let mut record = self_test::valid_record(fixture.mint, fixture.holder);
+    fixture.set_record(&record);
+
+    let baseline = fixture
+        .check_holder_thaws(test_data::THAW_DISCRIMINATOR)
+        .await;
+    if !baseline.passed {
+        return Err(TestResultReport::failure(
+            test_name,
+            format!("Good record denied: {:?}", baseline.error),
+        ));
+    }
+
+    let mut wrong_discriminator = test_data::THAW_DISCRIMINATOR;
+    wrong_discriminator[0] ^= 0xff;
+    let wrong = fixture.check_holder_thaws(wrong_discriminator).await;
+
+    record.expiry_timestamp = Some(GATE_TIMESTAMP - 1);
+    fixture.set_record(&record);
+    let expired = fixture
+        .check_holder_thaws(test_data::THAW_DISCRIMINATOR)
+        .await;
+
+    Ok((
+        baseline,
+        vec![
+            SelfTestOutcome {
+                fixture: "Wrong thaw discriminator (on-chain)".to_string(),
+                report: wrong,
+            },
+            SelfTestOutcome {
+                fixture: "Expired record (on-chain)".to_string(),
+                report: expired,
+            },
+        ],
+    ))
+}
+
+/// Generate the self-test report
+#[tokio::test]
+async fn generate_self_test_report() {
+    if suite_config::skip_unless(SuiteCategory::SelfTest, "Self-Test Report").is_some() {
+        return;
+    }
+    let mut known_good = self_test::run_known_good();
+    let mut outcomes = self_test::run_self_test();
+    if let Ok((baseline, on_chain)) = run_on_chain_outcomes().await {
+        known_good.push(baseline);
+        outcomes.extend(on_chain);
+    }
+
+    fs::create_dir_all("../../tests/reports").ok();
+    let mut report = self_test::render_self_test_report(&known_good, &outcomes);
+    report.push_str(&reporting::render_test_report(
+        &[
+            run_known_bad_fixtures_test(),
+            run_on_chain_fixtures_test().await,
+        ],
+        "Suite Self-Test Results",
+        &suite_config::current().disabled(),
+    ));
+    fs::write("../../tests/reports/self_test.md", report)
+        .expect("Failed to write self-test report");
+    println!("✅ Self-test report generated: tests/reports/self_test.md");
+}
@@ -0,0 +1,503 @@
+//! Randomized differential scenarios with sequence shrinking
+//!
+//! Random operation sequences run against the production allow list deployed
+//! into `solana-program-test` and are compared with `AllowListModel`. A
+//! divergence is shrunk to a smallest reproduction and written to
+//! `tests/regressions`, whose scenarios are replayed on every run.
+
+use production_allow_list::{
+    RemoveMode, ADD_TO_ALLOW_LIST, ALLOW_LIST_SEED, CONFIG_SEED, INITIALIZE, REMOVE_FROM_ALLOW_LIST,
+};
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::Transaction,
+};
+use token_acl_interface::find_missing_record_policy_address;
+
+use token_acl_integration_tests::{
+    fixtures::test_data,
+    reporting,
+    sequences::{
+        first_divergence, random_sequence, shrink, simplify_operation, AllowListModel, Operation,
+        RegressionScenario, REGRESSIONS_DIR,
+    },
+    TestResultReport,
+};
+
+const SEEDS: [u64; 4] = [1, 2, 3, 4];
+const SEQUENCE_LEN: usize = 24;
+const USERS: u8 = 3;
+
+/// A fresh bank with the allow list deployed and its config initialized
+struct SequenceRunner {
+    context: ProgramTestContext,
+    program_id: Pubkey,
+    authority: Keypair,
+    mint: Pubkey,
+    config: Pubkey,
+}
+
+impl SequenceRunner {
+    async fn new() -> Self {
+        let program_id = Pubkey::new_unique();
+        let program_test = ProgramTest::new(
+            "production_allow_list",
+            program_id,
+            processor!(production_allow_list::process_instruction),
+        );
+        let context = program_test.start_with_context().await;
+        let mint = Pubkey::new_unique();
+        let (config, _) = Pubkey::find_program_address(&[CONFIG_SEED, mint.as_ref()], &program_id);
+        let mut runner = Self {
+            context,
+            program_id,
+            authority: Keypair::new(),
+            mint,
+            config,
+        };
+
+        let initialize = Instruction::new_with_bytes(
+            program_id,
+            &[INITIALIZE],
+            vec![
+                AccountMeta::new(config, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(runner.authority.pubkey(), true),
+                AccountMeta::new(runner.context.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        assert!(
+            runner.send(initialize).await,
+            "config initialization should succeed"
+        );
+        runner
+    }
+
+    fn user(index: u8) -> Pubkey {
+        Pubkey::new_from_array([index.wrapping_add(1); 32])
+    }
+
+    fn record(&self, user: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[ALLOW_LIST_SEED, self.mint.as_ref(), user.as_ref()],
+            &self.program_id,
+        )
+        .0
+    }
+
+    fn instruction(&self, operation: &Operation) -> Instruction {
+        let user = Self::user(operation.user());
+        let record = self.record(&user);
+        let authority = self.authority.pubkey();
+        let remove = |mode: RemoveMode, recipient: Option<Pubkey>| {
+            let mut accounts = vec![
+                AccountMeta::new_readonly(self.config, false),
+                AccountMeta::new(record, false),
+                AccountMeta::new_readonly(authority, true),
+            ];
+            accounts.extend(recipient.map(|recipient| AccountMeta::new(recipient, false)));
+            Instruction::new_with_bytes(
+                self.program_id,
+                &[REMOVE_FROM_ALLOW_LIST, mode as u8],
+                accounts,
+            )
+        };
+
+        match operation {
+            Operation::Add { .. } => Instruction::new_with_bytes(
+                self.program_id,
+                &[ADD_TO_ALLOW_LIST],
+                vec![
+                    AccountMeta::new_readonly(self.config, false),
+                    AccountMeta::new(record, false),
+                    AccountMeta::new_readonly(self.mint, false),
+                    AccountMeta::new_readonly(user, false),
+                    AccountMeta::new_readonly(authority, true),
+                    AccountMeta::new(self.context.payer.pubkey(), true),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            ),
+            Operation::Remove { .. } => remove(RemoveMode::Deactivate, None),
+            Operation::Close { .. } => remove(RemoveMode::Close, Some(self.context.payer.pubkey())),
+            Operation::CheckThaw { .. } => {
+                let (extra_metas, _) = Pubkey::find_program_address(
+                    &[test_data::THAW_EXTRA_ACCOUNT_METAS_SEED, self.mint.as_ref()],
+                    &self.program_id,
+                );
+                Instruction::new_with_bytes(
+                    self.program_id,
+                    &test_data::THAW_DISCRIMINATOR,
+                    vec![
+                        AccountMeta::new_readonly(self.context.payer.pubkey(), false),
+                        AccountMeta::new_readonly(Pubkey::new_unique(), false),
+                        AccountMeta::new_readonly(self.mint, false),
+                        AccountMeta::new_readonly(extra_metas, false),
+                        AccountMeta::new_readonly(user, false),
+                        AccountMeta::new_readonly(record, false),
+                        AccountMeta::new_readonly(
+                            find_missing_record_policy_address(&self.mint, &self.program_id).0,
+                            false,
+                        ),
+                    ],
+                )
+            }
+        }
+    }
+
+    /// Whether the program accepted `instruction`
+    async fn send(&mut self, instruction: Instruction) -> bool {
+        // A fresh blockhash keeps repeated operations from being deduplicated
+        let blockhash = self
+            .context
+            .get_new_latest_blockhash()
+            .await
+            .expect("blockhash should be available");
+        let mut signers = vec![&self.context.payer];
+        let authority_signs = instruction
+            .accounts
+            .iter()
+            .any(|meta| meta.is_signer && meta.pubkey == self.authority.pubkey());
+        if authority_signs {
+            signers.push(&self.authority);
+        }
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&self.context.payer.pubkey()),
+            &signers,
+            blockhash,
+        );
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .is_ok()
+    }
+}
+
+/// Each operation's outcome on a freshly deployed allow list
+async fn run_on_chain(operations: &[Operation]) -> Vec<bool> {
+    let mut runner = SequenceRunner::new().await;
+    let mut outcomes = Vec::with_capacity(operations.len());
+    for operation in operations {
+        let instruction = runner.instruction(operation);
+        outcomes.push(runner.send(instruction).await);
+    }
+    outcomes
+}
+
+/// Whether `operations` run on-chain diverge from what `model` expects
+async fn diverges(operations: Vec<Operation>, model: fn(&[Operation]) -> Vec<bool>) -> bool {
+    let actual = run_on_chain(&operations).await;
+    first_divergence(&model(&operations), &actual).is_some()
+}
+
+/// An outdated model that assumes adding a deactivated user reactivates them
+fn reactivating_model(operations: &[Operation]) -> Vec<bool> {
+    let mut model = AllowListModel::new();
+    let mut deactivated = std::collections::BTreeSet::new();
+    operations
+        .iter()
+        .map(|operation| match operation {
+            Operation::Add { user } if deactivated.contains(user) => {
+                deactivated.remove(user);
+                model.apply(&Operation::Close { user: *user });
+                model.apply(operation)
+            }
+            Operation::Remove { user } => {
+                let accepted = model.apply(operation);
+                if accepted {
+                    deactivated.insert(*user);
+                }
+                accepted
+            }
+            Operation::Close { user } => {
+                deactivated.remove(user);
+                model.apply(operation)
+            }
+            _ => model.apply(operation),
+        })
+        .collect()
+}
+
+/// Test 1: Shrinking reaches a 1-minimal sequence with the simplest users
+#[tokio::test]
+async fn test_shrink_to_minimal_sequence() {
+    let report = run_shrink_minimal_test().await;
+    assert!(report.passed, "Shrink test failed: {:?}", report.error);
+}
+
+async fn run_shrink_minimal_test() -> TestResultReport {
+    let test_name = "Shrink To Minimal Sequence";
+    let mut assertions = 0;
+
+    // Fails when a thaw check follows a close for the same user
+    let fails = |operations: &[Operation]| {
+        operations.iter().enumerate().any(|(i, operation)| {
+            matches!(operation, Operation::Close { .. })
+                && operations[i + 1..].contains(&Operation::CheckThaw {
+                    user: operation.user(),
+                })
+        })
+    };
+    let Some(operations) = (0..100)
+        .map(|seed| random_sequence(seed, 40, 4))
+        .find(|operations| fails(operations))
+    else {
+        return TestResultReport::failure(test_name, "No failing sequence generated".to_string());
+    };
+
+    let shrunk = shrink(operations.clone(), simplify_operation, |candidate| {
+        let failing = fails(&candidate);
+        async move { failing }
+    })
+    .await;
+
+    // Assertion 1: The shrunk sequence is the two-operation reproduction for
+    // user 0
+    assertions += 1;
+    let expected = [
+        Operation::Close { user: 0 },
+        Operation::CheckThaw { user: 0 },
+    ];
+    if shrunk.operations != expected {
+        return TestResultReport::failure(
+            test_name,
+            format!("Expected {:?}, got {:?}", expected, shrunk.operations),
+        );
+    }
+
+    // Assertion 2: Removing any single operation makes the failure go away
+    assertions += 1;
+    let one_minimal = (0..shrunk.operations.len()).all(|i| {
+        let mut candidate = shrunk.operations.clone();
+        candidate.remove(i);
+        !fails(&candidate)
+    });
+    if !one_minimal {
+        return TestResultReport::failure(
+            test_name,
+            "Shrunk sequence is not 1-minimal".to_string(),
+        );
+    }
+
+    // Assertion 3: Shrinking took fewer runs than trying every subsequence
+    // of the original would
+    assertions += 1;
+    if shrunk.runs >= operations.len() * operations.len() {
+        return TestResultReport::failure(
+            test_name,
+            format!("{} runs for {} operations", shrunk.runs, operations.len()),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: Random sequences agree with the model; a divergence is shrunk and
+/// written as a regression scenario
+#[tokio::test]
+async fn test_randomized_differential_scenarios() {
+    let report = run_randomized_differential_test().await;
+    assert!(
+        report.passed,
+        "Randomized differential test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_randomized_differential_test() -> TestResultReport {
+    let test_name = "Randomized Differential Scenarios";
+    let mut assertions = 0;
+
+    // Assertions 1-4: Each seeded sequence matches the model on-chain
+    for seed in SEEDS {
+        assertions += 1;
+        let operations = random_sequence(seed, SEQUENCE_LEN, USERS);
+        let expected = AllowListModel::outcomes(&operations);
+        let actual = run_on_chain(&operations).await;
+        let Some(index) = first_divergence(&expected, &actual) else {
+            continue;
+        };
+
+        let shrunk = shrink(operations, simplify_operation, |candidate| {
+            diverges(candidate, AllowListModel::outcomes)
+        })
+        .await;
+        let scenario = RegressionScenario {
+            name: format!("seed_{}", seed),
+            seed: Some(seed),
+            reason: format!(
+                "operation {} diverged from the model (expected {}, got {})",
+                index,
+                expected.get(index).copied().unwrap_or_default(),
+                actual.get(index).copied().unwrap_or_default()
+            ),
+            operations: shrunk.operations,
+        };
+        let saved = scenario
+            .save(REGRESSIONS_DIR)
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|e| format!("<not saved: {}>", e));
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Seed {} diverged; shrunk to {} operations in {}",
+                seed,
+                scenario.operations.len(),
+                saved
+            ),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: A real divergence from an outdated model shrinks to its
+/// three-operation core and round-trips through a regression file
+#[tokio::test]
+async fn test_shrink_real_divergence() {
+    let report = run_shrink_real_divergence_test().await;
+    assert!(
+        report.passed,
+        "Real divergence shrink test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_shrink_real_divergence_test() -> TestResultReport {
+    let test_name = "Shrink Real Divergence";
+    let mut assertions = 0;
+
+    // The current model matches the program, so it finds the seeds where the
+    // outdated one will diverge without deploying anything
+    let Some((seed, operations)) = (0..100).find_map(|seed| {
+        let operations = random_sequence(seed, SEQUENCE_LEN, USERS);
+        let outdated = reactivating_model(&operations);
+        first_divergence(&outdated, &AllowListModel::outcomes(&operations))
+            .map(|_| (seed, operations))
+    }) else {
+        return TestResultReport::failure(test_name, "No diverging seed found".to_string());
+    };
+
+    // Assertion 1: The sequence diverges on-chain
+    assertions += 1;
+    if !diverges(operations.clone(), reactivating_model).await {
+        return TestResultReport::failure(
+            test_name,
+            format!("Seed {} did not diverge on-chain", seed),
+        );
+    }
+
+    // Assertion 2: It shrinks to add, deactivate, re-add for user 0
+    assertions += 1;
+    let shrunk = shrink(operations, simplify_operation, |candidate| {
+        diverges(candidate, reactivating_model)
+    })
+    .await;
+    let expected = [
+        Operation::Add { user: 0 },
+        Operation::Remove { user: 0 },
+        Operation::Add { user: 0 },
+    ];
+    if shrunk.operations != expected {
+        return TestResultReport::failure(
+            test_name,
+            format!("Expected {:?}, got {:?}", expected, shrunk.operations),
+        );
+    }
+
+    // Assertion 3: The regression file round-trips
+    assertions += 1;
+    let dir = match tempfile::tempdir() {
+        Ok(dir) => dir,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+    let scenario = RegressionScenario {
+        name: "outdated_reactivation".to_string(),
+        seed: Some(seed),
+        reason: "re-adding a deactivated user".to_string(),
+        operations: shrunk.operations,
+    };
+    let loaded = scenario
+        .save(dir.path())
+        .and_then(|_| RegressionScenario::load_all(dir.path()));
+    if loaded.as_deref().ok() != Some(std::slice::from_ref(&scenario)) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Regression file did not round-trip: {:?}", loaded),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 4: Every committed regression scenario matches the model on-chain
+#[tokio::test]
+async fn test_replay_regression_scenarios() {
+    let report = run_replay_regressions_test().await;
+    assert!(
+        report.passed,
+        "Regression replay test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_replay_regressions_test() -> TestResultReport {
+    let test_name = "Replay Regression Scenarios";
+    let mut assertions = 0;
+
+    let scenarios = match RegressionScenario::load_all(REGRESSIONS_DIR) {
+        Ok(scenarios) => scenarios,
+        Err(e) => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Loading regressions failed: {}", e),
+            )
+        }
+    };
+
+    // Assertions 1-N: No scenario diverges any more
+    for scenario in &scenarios {
+        assertions += 1;
+        let expected = AllowListModel::outcomes(&scenario.operations);
+        let actual = run_on_chain(&scenario.operations).await;
+        if let Some(index) = first_divergence(&expected, &actual) {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "Regression {} diverges at operation {} ({})",
+                    scenario.name, index, scenario.reason
+                ),
+            );
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate sequence shrinking test report
+#[tokio::test]
+async fn generate_sequence_shrinking_report() {
+    let results = vec![
+        run_shrink_minimal_test().await,
+        run_randomized_differential_test().await,
+        run_shrink_real_divergence_test().await,
+        run_replay_regressions_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Sequence Shrinking Test Results",
+        "../../tests/reports/sequence_shrinking.md",
+    ) {
+        panic!("Failed to generate sequence shrinking report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} sequence shrinking tests failed", failed);
+}
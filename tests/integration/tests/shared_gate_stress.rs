@@ -0,0 +1,481 @@
+//! Shared gate stress tests
+//!
+//! One gate program deployment serving many mints at once, as a gating
+//! provider would. These tests check that allow list records are namespaced
+//! by mint and that parallel permissionless thaw checks across mints never
+//! contend for the same write lock.
+
+use borsh::BorshDeserialize;
+use production_allow_list::{
+    AllowListRecord, ADD_TO_ALLOW_LIST, ALLOW_LIST_SEED, CONFIG_SEED, INITIALIZE,
+};
+use solana_program_test::{processor, BanksClient, ProgramTest};
+use solana_sdk::{
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction, system_program,
+    transaction::Transaction,
+};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use token_acl_interface::find_missing_record_policy_address;
+
+use token_acl_integration_tests::{
+    contention::ContentionAnalyzer,
+    fixtures::test_data,
+    reporting,
+    suite_config::{self, SuiteCategory},
+    units, TestResultReport,
+};
+
+/// Number of mints served by the shared gate
+const MINT_COUNT: usize = 55;
+
+/// Lamports given to each holder so it can pay for its own thaw checks
+const HOLDER_FUNDING: u64 = 10_000_000;
+
+/// One mint onboarded to the shared gate, with a single allow listed holder
+struct GatedMint {
+    mint: Pubkey,
+    authority: Keypair,
+    holder: Keypair,
+}
+
+impl GatedMint {
+    fn new() -> Self {
+        Self {
+            mint: Pubkey::new_unique(),
+            authority: Keypair::new(),
+            holder: Keypair::new(),
+        }
+    }
+}
+
+/// Outcome of a batch of thaw checks submitted concurrently
+struct ParallelThawRun {
+    approved: usize,
+    elapsed: Duration,
+    /// Writable accounts locked by more than one transaction in the batch
+    contended: Vec<Pubkey>,
+}
+
+struct SharedGate {
+    banks_client: BanksClient,
+    payer: Keypair,
+    blockhash: Hash,
+    gate: Pubkey,
+    mints: Vec<GatedMint>,
+}
+
+impl SharedGate {
+    /// Starts one gate deployment and onboards `count` mints to it
+    async fn new(count: usize) -> Result<Self, String> {
+        let gate = Pubkey::new_unique();
+        let program_test = ProgramTest::new(
+            "production_allow_list",
+            gate,
+            processor!(production_allow_list::process_instruction),
+        );
+        let (banks_client, payer, blockhash) = program_test.start().await;
+
+        let mut shared = Self {
+            banks_client,
+            payer,
+            blockhash,
+            gate,
+            mints: (0..count).map(|_| GatedMint::new()).collect(),
+        };
+
+        // One transaction per mint: config, holder record, and holder funding
+        for index in 0..count {
+            let gated = &shared.mints[index];
+            let payer = shared.payer.pubkey();
+            let instructions = [
+                shared.initialize_instruction(gated),
+                shared.add_instruction(gated.mint, &gated.authority, &gated.holder.pubkey()),
+                system_instruction::transfer(&payer, &gated.holder.pubkey(), HOLDER_FUNDING),
+            ];
+            let transaction = Transaction::new_signed_with_payer(
+                &instructions,
+                Some(&payer),
+                &[&shared.payer, &gated.authority],
+                shared.blockhash,
+            );
+            shared
+                .banks_client
+                .process_transaction(transaction)
+                .await
+                .map_err(|e| format!("Onboarding mint {} failed: {}", index, e))?;
+        }
+
+        Ok(shared)
+    }
+
+    fn config(&self, mint: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[CONFIG_SEED, mint.as_ref()], &self.gate).0
+    }
+
+    fn allow_list_pda(&self, mint: &Pubkey, user: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[ALLOW_LIST_SEED, mint.as_ref(), user.as_ref()], &self.gate).0
+    }
+
+    fn initialize_instruction(&self, gated: &GatedMint) -> Instruction {
+        Instruction::new_with_bytes(
+            self.gate,
+            &[INITIALIZE],
+            vec![
+                AccountMeta::new(self.config(&gated.mint), false),
+                AccountMeta::new_readonly(gated.mint, false),
+                AccountMeta::new_readonly(gated.authority.pubkey(), true),
+                AccountMeta::new(self.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    fn add_instruction(&self, mint: Pubkey, authority: &Keypair, user: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            self.gate,
+            &[ADD_TO_ALLOW_LIST],
+            vec![
+                AccountMeta::new_readonly(self.config(&mint), false),
+                AccountMeta::new(self.allow_list_pda(&mint, user), false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(*user, false),
+                AccountMeta::new_readonly(authority.pubkey(), true),
+                AccountMeta::new(self.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// Thaw check for `holder` on `mint`, reading the record at `allow_list_pda`
+    fn can_thaw_instruction(
+        &self,
+        mint: &Pubkey,
+        holder: &Pubkey,
+        allow_list_pda: Pubkey,
+    ) -> Instruction {
+        let (extra_metas, _) = Pubkey::find_program_address(
+            &[test_data::THAW_EXTRA_ACCOUNT_METAS_SEED, mint.as_ref()],
+            &self.gate,
+        );
+        Instruction::new_with_bytes(
+            self.gate,
+            &test_data::THAW_DISCRIMINATOR,
+            vec![
+                AccountMeta::new_readonly(*holder, false),
+                AccountMeta::new_readonly(Pubkey::new_unique(), false),
+                AccountMeta::new_readonly(*mint, false),
+                AccountMeta::new_readonly(extra_metas, false),
+                AccountMeta::new_readonly(*holder, false),
+                AccountMeta::new_readonly(allow_list_pda, false),
+                AccountMeta::new_readonly(
+                    find_missing_record_policy_address(mint, &self.gate).0,
+                    false,
+                ),
+            ],
+        )
+    }
+
+    /// Thaw check transaction signed and paid for by the holder
+    fn holder_thaw_transaction(&self, gated: &GatedMint, allow_list_pda: Pubkey) -> Transaction {
+        let check = self.can_thaw_instruction(&gated.mint, &gated.holder.pubkey(), allow_list_pda);
+        Transaction::new_signed_with_payer(
+            &[check],
+            Some(&gated.holder.pubkey()),
+            &[&gated.holder],
+            self.blockhash,
+        )
+    }
+
+    async fn send(&self, transaction: Transaction) -> Result<(), String> {
+        self.banks_client
+            .clone()
+            .process_transaction(transaction)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn record(&mut self, address: Pubkey) -> Option<AllowListRecord> {
+        let account = self.banks_client.get_account(address).await.ok()??;
+        AllowListRecord::try_from_slice(&account.data).ok()
+    }
+
+    /// Submits every holder's own thaw check at once and waits for all of them
+    async fn parallel_thaw(&self) -> ParallelThawRun {
+        let transactions: Vec<Transaction> = self
+            .mints
+            .iter()
+            .map(|gated| {
+                let pda = self.allow_list_pda(&gated.mint, &gated.holder.pubkey());
+                self.holder_thaw_transaction(gated, pda)
+            })
+            .collect();
+        let contended = contended_write_locks(&transactions);
+
+        let started = Instant::now();
+        let handles: Vec<_> = transactions
+            .into_iter()
+            .map(|transaction| {
+                let mut banks_client = self.banks_client.clone();
+                tokio::spawn(async move { banks_client.process_transaction(transaction).await })
+            })
+            .collect();
+
+        let mut approved = 0;
+        for handle in handles {
+            if matches!(handle.await, Ok(Ok(()))) {
+                approved += 1;
+            }
+        }
+
+        ParallelThawRun {
+            approved,
+            elapsed: started.elapsed(),
+            contended,
+        }
+    }
+}
+
+/// Writable accounts, fee payers included, that more than one of
+/// `transactions` would lock
+fn contended_write_locks(transactions: &[Transaction]) -> Vec<Pubkey> {
+    let mut analyzer = ContentionAnalyzer::new().include_fee_payers();
+    for transaction in transactions {
+        analyzer.record_transaction("can_thaw", transaction);
+    }
+    analyzer
+        .hot_accounts()
+        .into_iter()
+        .map(|account| account.address)
+        .collect()
+}
+
+/// Test 1: Records for many mints on one gate are namespaced by mint
+#[tokio::test]
+async fn test_shared_gate_records_namespaced_by_mint() {
+    let report = run_record_namespacing_test().await;
+    assert!(
+        report.passed,
+        "Record namespacing test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_record_namespacing_test() -> TestResultReport {
+    let test_name = "Shared Gate Record Namespacing";
+    let mut assertions = 0;
+
+    let mut shared = match SharedGate::new(MINT_COUNT).await {
+        Ok(shared) => shared,
+        Err(e) => return TestResultReport::failure(test_name, format!("Setup failed: {}", e)),
+    };
+
+    // Assertion 1: Every mint has its own config and record address
+    assertions += 1;
+    let mut addresses = HashSet::new();
+    for gated in &shared.mints {
+        addresses.insert(shared.config(&gated.mint));
+        addresses.insert(shared.allow_list_pda(&gated.mint, &gated.holder.pubkey()));
+    }
+    if addresses.len() != MINT_COUNT * 2 {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Expected {} distinct config and record addresses, found {}",
+                MINT_COUNT * 2,
+                addresses.len()
+            ),
+        );
+    }
+
+    // Assertion 2: Each stored record names its own mint and holder
+    assertions += 1;
+    let expected: Vec<(Pubkey, Pubkey)> = shared
+        .mints
+        .iter()
+        .map(|gated| (gated.mint, gated.holder.pubkey()))
+        .collect();
+    for (mint, holder) in expected {
+        let pda = shared.allow_list_pda(&mint, &holder);
+        match shared.record(pda).await {
+            Some(record) if record.mint == mint && record.user == holder && record.allowed => {}
+            other => {
+                return TestResultReport::failure(
+                    test_name,
+                    format!("Record for mint {} is wrong: {:?}", mint, other),
+                )
+            }
+        }
+    }
+
+    // Assertion 3: The same holder key gets a distinct record per mint
+    assertions += 1;
+    let holder = shared.mints[0].holder.pubkey();
+    let per_mint: HashSet<Pubkey> = shared
+        .mints
+        .iter()
+        .map(|gated| shared.allow_list_pda(&gated.mint, &holder))
+        .collect();
+    if per_mint.len() != MINT_COUNT {
+        return TestResultReport::failure(
+            test_name,
+            "One holder's records collide across mints".to_string(),
+        );
+    }
+
+    // Assertion 4: A holder listed for one mint is denied on its neighbour,
+    // whether the neighbour's PDA or the holder's own record is supplied
+    assertions += 1;
+    for index in 0..MINT_COUNT {
+        let (own, neighbour) = (
+            &shared.mints[index],
+            &shared.mints[(index + 1) % MINT_COUNT],
+        );
+        let neighbour_pda = shared.allow_list_pda(&neighbour.mint, &own.holder.pubkey());
+        let own_pda = shared.allow_list_pda(&own.mint, &own.holder.pubkey());
+
+        let attempts = [
+            shared.can_thaw_instruction(&neighbour.mint, &own.holder.pubkey(), neighbour_pda),
+            shared.can_thaw_instruction(&neighbour.mint, &own.holder.pubkey(), own_pda),
+        ];
+        for check in attempts {
+            let transaction = Transaction::new_signed_with_payer(
+                &[check],
+                Some(&own.holder.pubkey()),
+                &[&own.holder],
+                shared.blockhash,
+            );
+            if shared.send(transaction).await.is_ok() {
+                return TestResultReport::failure(
+                    test_name,
+                    format!("Holder of mint {} was approved for another mint", index),
+                );
+            }
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: Parallel thaw load across mints has no shared write locks
+#[tokio::test]
+async fn test_shared_gate_parallel_thaw_load() {
+    let report = run_parallel_thaw_load_test().await;
+    assert!(
+        report.passed,
+        "Parallel thaw load test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_parallel_thaw_load_test() -> TestResultReport {
+    let test_name = "Shared Gate Parallel Thaw Load";
+    let mut assertions = 0;
+    if let Some(skipped) = suite_config::skip_unless(SuiteCategory::LoadTests, test_name) {
+        return skipped;
+    }
+
+    let shared = match SharedGate::new(MINT_COUNT).await {
+        Ok(shared) => shared,
+        Err(e) => return TestResultReport::failure(test_name, format!("Setup failed: {}", e)),
+    };
+
+    let run = shared.parallel_thaw().await;
+    println!("Shared Gate Parallel Thaw Load:");
+    println!("  Mints: {}", MINT_COUNT);
+    println!("  Approved: {}", run.approved);
+    println!("  Elapsed: {}", units::duration(run.elapsed));
+    println!("  Contended write locks: {}", run.contended.len());
+
+    // Assertion 1: Every holder's concurrent thaw check is approved
+    assertions += 1;
+    if run.approved != MINT_COUNT {
+        return TestResultReport::failure(
+            test_name,
+            format!("{} of {} thaw checks approved", run.approved, MINT_COUNT),
+        );
+    }
+
+    // Assertion 2: No account is write-locked by more than one thaw check
+    assertions += 1;
+    if !run.contended.is_empty() {
+        return TestResultReport::failure(
+            test_name,
+            format!("Thaw checks contend on write locks: {:?}", run.contended),
+        );
+    }
+
+    // Assertion 3: Gate state (configs and records) is never write-locked
+    // by a thaw check; only the holder paying the fee is
+    assertions += 1;
+    for gated in &shared.mints {
+        let pda = shared.allow_list_pda(&gated.mint, &gated.holder.pubkey());
+        let transaction = shared.holder_thaw_transaction(gated, pda);
+        let message = &transaction.message;
+        let writable: Vec<Pubkey> = message
+            .account_keys
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| message.is_writable(*index))
+            .map(|(_, key)| *key)
+            .collect();
+        if writable != vec![gated.holder.pubkey()] {
+            return TestResultReport::failure(
+                test_name,
+                format!(
+                    "Thaw check for mint {} write-locks {:?}",
+                    gated.mint, writable
+                ),
+            );
+        }
+    }
+
+    // Assertion 4: A shared fee payer is the only lock the batch would share
+    assertions += 1;
+    let sponsored: Vec<Transaction> = shared
+        .mints
+        .iter()
+        .map(|gated| {
+            let pda = shared.allow_list_pda(&gated.mint, &gated.holder.pubkey());
+            let check = shared.can_thaw_instruction(&gated.mint, &gated.holder.pubkey(), pda);
+            Transaction::new_signed_with_payer(
+                &[check],
+                Some(&shared.payer.pubkey()),
+                &[&shared.payer],
+                shared.blockhash,
+            )
+        })
+        .collect();
+    if contended_write_locks(&sponsored) != vec![shared.payer.pubkey()] {
+        return TestResultReport::failure(
+            test_name,
+            "Sponsored batch should only contend on the fee payer".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate shared gate stress report
+#[tokio::test]
+async fn generate_shared_gate_stress_report() {
+    let results = vec![
+        run_record_namespacing_test().await,
+        run_parallel_thaw_load_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Shared Gate Stress Test Results",
+        "../../tests/reports/shared_gate_stress.md",
+    ) {
+        panic!("Failed to generate shared gate stress report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} shared gate stress tests failed", failed);
+}
@@ -0,0 +1,234 @@
+//! Status badge tests
+//!
+//! The badges are the suite's status as embedding repositories see it, so
+//! these tests pin the shields.io endpoint schema, the numbers each badge
+//! is computed from, and the pass-rate tally kept across test binaries.
+
+use std::{collections::BTreeSet, fs, path::Path};
+
+use token_acl_integration_tests::{
+    badges::{self, Badge},
+    bpf_coverage::ProgramCoverage,
+    budget_guidance::OperationUnits,
+    reporting,
+    suite_config::SuiteCategory,
+    TestResultReport,
+};
+
+fn program(name: &str, total_blocks: usize, executed: usize) -> ProgramCoverage {
+    ProgramCoverage {
+        program: name.to_string(),
+        total_blocks,
+        executed_blocks: (0..executed).collect::<BTreeSet<_>>(),
+        instructions_traced: executed,
+    }
+}
+
+/// Badges serialize as shields.io endpoint documents
+#[test]
+fn test_badge_schema() {
+    let report = run_badge_schema_test();
+    assert!(
+        report.passed,
+        "Badge schema test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_badge_schema_test() -> TestResultReport {
+    let test_name = "Badge Endpoint Schema";
+    let mut assertions = 0;
+
+    let badge = Badge::new("tests", "100.0% (3/3)", "brightgreen");
+    let json: serde_json::Value = match badge.to_json().map(|json| serde_json::from_str(&json)) {
+        Ok(Ok(json)) => json,
+        Ok(Err(e)) | Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+
+    // Assertion: the document has exactly the endpoint fields
+    assertions += 1;
+    let expected = serde_json::json!({
+        "schemaVersion": 1,
+        "label": "tests",
+        "message": "100.0% (3/3)",
+        "color": "brightgreen",
+    });
+    if json != expected {
+        return TestResultReport::failure(test_name, format!("Unexpected document: {}", json));
+    }
+
+    // Assertion: a written badge reads back as the same badge
+    assertions += 1;
+    let dir = match tempfile::tempdir() {
+        Ok(dir) => dir,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+    if let Err(e) = badges::write_badge(dir.path(), "tests", &badge) {
+        return TestResultReport::failure(test_name, e.to_string());
+    }
+    let read: Option<Badge> = fs::read(dir.path().join("tests.json"))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok());
+    if read.as_ref() != Some(&badge) {
+        return TestResultReport::failure(test_name, format!("Read back {:?}", read));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Each badge is computed from the numbers it shows
+#[test]
+fn test_badge_values() {
+    let report = run_badge_values_test();
+    assert!(
+        report.passed,
+        "Badge values test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_badge_values_test() -> TestResultReport {
+    let test_name = "Badge Values";
+    let mut assertions = 0;
+
+    // Assertion: the pass rate counts passed over run, red on any failure
+    assertions += 1;
+    let cases = [
+        (Badge::pass_rate(3, 0), "100.0% (3/3)", "brightgreen"),
+        (Badge::pass_rate(2, 1), "66.7% (2/3)", "red"),
+        (Badge::pass_rate(0, 0), "none run", "lightgrey"),
+    ];
+    for (badge, message, color) in &cases {
+        if badge.message != *message || badge.color != *color {
+            return TestResultReport::failure(
+                test_name,
+                format!("Expected {} ({}), got {:?}", message, color, badge),
+            );
+        }
+    }
+
+    // Assertion: coverage weighs programs by their blocks and skips unbuilt ones
+    assertions += 1;
+    let badge = Badge::coverage(&[
+        program("gate_a", 100, 90),
+        program("gate_b", 300, 150),
+        ProgramCoverage::not_built("gate_c"),
+    ]);
+    if badge.message != "60.0%" || badge.color != "yellow" {
+        return TestResultReport::failure(test_name, format!("Coverage badge {:?}", badge));
+    }
+
+    // Assertion: coverage with nothing built is not reported as 0%
+    assertions += 1;
+    let badge = Badge::coverage(&[ProgramCoverage::not_built("gate_a")]);
+    if badge.message != "not traced" {
+        return TestResultReport::failure(test_name, format!("Unbuilt badge {:?}", badge));
+    }
+
+    // Assertion: compute units show the worst sample, only when metered
+    assertions += 1;
+    let mut units = OperationUnits {
+        operation: "Permissionless thaw (example_allow_list)".to_string(),
+        samples: vec![3_100, 3_400, 3_250],
+        metered: true,
+    };
+    let metered = Badge::compute_units("thaw", &units);
+    units.metered = false;
+    let unmetered = Badge::compute_units("thaw", &units);
+    if metered.message != "3,400 CU" || unmetered.message != "not metered" {
+        return TestResultReport::failure(
+            test_name,
+            format!("Compute unit badges {:?} and {:?}", metered, unmetered),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// The pass rate sums every report's latest results
+#[test]
+fn test_pass_rate_tally() {
+    let report = run_pass_rate_tally_test();
+    assert!(
+        report.passed,
+        "Pass rate tally test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_pass_rate_tally_test() -> TestResultReport {
+    let test_name = "Pass Rate Tally";
+    let mut assertions = 0;
+    let dir = match tempfile::tempdir() {
+        Ok(dir) => dir,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+
+    let first = vec![
+        TestResultReport::success("A", 1),
+        TestResultReport::skipped("B", SuiteCategory::LoadTests),
+    ];
+    let second = vec![
+        TestResultReport::success("C", 1),
+        TestResultReport::failure("D", "denied".to_string()),
+    ];
+
+    // Assertion: skipped tests are left out of the rate
+    assertions += 1;
+    match badges::record_results(dir.path(), "first", &first) {
+        Ok(badge) if badge.message == "100.0% (1/1)" => {}
+        other => return TestResultReport::failure(test_name, format!("First: {:?}", other)),
+    }
+
+    // Assertion: a second report adds to the first
+    assertions += 1;
+    match badges::record_results(dir.path(), "second", &second) {
+        Ok(badge) if badge.message == "66.7% (2/3)" && badge.color == "red" => {}
+        other => return TestResultReport::failure(test_name, format!("Second: {:?}", other)),
+    }
+
+    // Assertion: regenerating a report replaces its tally, and the badge file
+    // matches what was returned
+    assertions += 1;
+    let rerun = vec![
+        TestResultReport::success("C", 1),
+        TestResultReport::success("D", 1),
+    ];
+    let badge = match badges::record_results(dir.path(), "second", &rerun) {
+        Ok(badge) if badge.message == "100.0% (3/3)" => badge,
+        other => return TestResultReport::failure(test_name, format!("Rerun: {:?}", other)),
+    };
+    let written: Option<Badge> = fs::read(dir.path().join("tests.json"))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok());
+    if written != Some(badge) {
+        return TestResultReport::failure(test_name, format!("Badge file {:?}", written));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate the status badge test report
+#[test]
+fn generate_status_badges_report() {
+    let results = vec![
+        run_badge_schema_test(),
+        run_badge_values_test(),
+        run_pass_rate_tally_test(),
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Status Badge Test Results",
+        "../../tests/reports/status_badges.md",
+    ) {
+        panic!("Failed to generate status badge report: {}", e);
+    }
+
+    // The report itself lands in the suite's pass-rate badge
+    let badge = Path::new(badges::BADGES_DIR).join("tests.json");
+    assert!(badge.exists(), "Pass-rate badge not written");
+    for result in &results {
+        assert!(result.passed, "{} failed: {:?}", result.name, result.error);
+    }
+}
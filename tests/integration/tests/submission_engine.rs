@@ -0,0 +1,692 @@
+//! Submission engine chaos tests
+//!
+//! Submits batches of allow list adds (admin operations) and transfers
+//! through a cluster that drops sends, reports landed sends as failed, and
+//! drops status responses. However the confirmations are lost, every
+//! operation must land exactly once: each transfer recipient holds its
+//! amount once, and the payer is charged the fees of exactly one
+//! transaction per operation, so not even a duplicate that failed landed.
+//!
+//! Program-test registers a new blockhash every few milliseconds, so a
+//! blockhash expires within a couple of seconds and the re-signing path
+//! runs in real time.
+
+use production_allow_list::{allow_list_address, config_address, ADD_TO_ALLOW_LIST, INITIALIZE};
+use solana_program_test::{processor, BanksClient, ProgramTest};
+use solana_sdk::{
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    nonce::State as NonceState,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signature, Signer},
+    system_instruction, system_program,
+    transaction::{Transaction, TransactionError},
+};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use token_acl_integration_tests::{
+    context::TestContext,
+    reporting,
+    submission::{
+        Lifetime, NonceAccount, Operation, Outcome, SubmissionCluster, SubmissionConfig,
+        SubmissionEngine, SubmissionReport,
+    },
+    TestResultReport,
+};
+
+/// Admin operations and transfers per batch
+const ADDS: usize = 12;
+const TRANSFERS: usize = 12;
+
+/// Lamports sent by the first transfer; each next one sends one more, so a
+/// duplicate shows in the recipient's balance
+const TRANSFER_BASE: u64 = 1_000_000;
+
+/// Which sends and status queries the chaos cluster loses
+#[derive(Debug, Clone, Copy)]
+struct Chaos {
+    /// Every n-th send never reaches the bank
+    drop_send_every: u64,
+    /// Every n-th send lands but reports an error
+    lose_ack_every: u64,
+    /// Status queries of each signature that fail before one succeeds
+    lost_status_polls: u64,
+}
+
+/// A bank behind an unreliable connection
+struct ChaosCluster {
+    banks_client: BanksClient,
+    chaos: Chaos,
+    sends: AtomicU64,
+    dropped_sends: AtomicU64,
+    lost_acks: AtomicU64,
+    status_polls: Mutex<HashMap<Signature, u64>>,
+}
+
+impl ChaosCluster {
+    fn new(banks_client: BanksClient, chaos: Chaos) -> Self {
+        Self {
+            banks_client,
+            chaos,
+            sends: AtomicU64::new(0),
+            dropped_sends: AtomicU64::new(0),
+            lost_acks: AtomicU64::new(0),
+            status_polls: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl SubmissionCluster for ChaosCluster {
+    async fn latest_blockhash(&self) -> Result<Hash, String> {
+        self.banks_client.latest_blockhash().await
+    }
+
+    async fn is_blockhash_valid(&self, blockhash: &Hash) -> Result<bool, String> {
+        self.banks_client.is_blockhash_valid(blockhash).await
+    }
+
+    async fn nonce_blockhash(&self, nonce_account: &Pubkey) -> Result<Hash, String> {
+        self.banks_client.nonce_blockhash(nonce_account).await
+    }
+
+    async fn send(&self, transaction: &Transaction) -> Result<(), String> {
+        let send = self.sends.fetch_add(1, Ordering::Relaxed) + 1;
+        if send.is_multiple_of(self.chaos.drop_send_every) {
+            self.dropped_sends.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+        self.banks_client.send(transaction).await?;
+        if send.is_multiple_of(self.chaos.lose_ack_every) {
+            self.lost_acks.fetch_add(1, Ordering::Relaxed);
+            return Err("Confirmation dropped".to_string());
+        }
+        Ok(())
+    }
+
+    async fn signature_status(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<Result<(), TransactionError>>, String> {
+        let polls = {
+            let mut status_polls = self.status_polls.lock().unwrap();
+            let polls = status_polls.entry(*signature).or_default();
+            *polls += 1;
+            *polls
+        };
+        if polls <= self.chaos.lost_status_polls {
+            return Err("Status response dropped".to_string());
+        }
+        self.banks_client.signature_status(signature).await
+    }
+}
+
+/// An allow list gate with its config created, and what a batch spends
+struct EngineFixture {
+    /// The test's bank, kept alive while its client is in use
+    context: TestContext,
+    banks_client: BanksClient,
+    payer: Keypair,
+    issuer: Keypair,
+    gate: Pubkey,
+    mint: Pubkey,
+}
+
+impl EngineFixture {
+    async fn new(test_name: &str) -> Result<Self, String> {
+        let gate = Pubkey::new_unique();
+        let program_test = ProgramTest::new(
+            "production_allow_list",
+            gate,
+            processor!(production_allow_list::process_instruction),
+        );
+        let context = TestContext::start(test_name, program_test).await?;
+        let blockhash = context.last_blockhash;
+
+        let fixture = Self {
+            banks_client: context.client(),
+            payer: context.payer(),
+            issuer: Keypair::new(),
+            gate,
+            mint: Pubkey::new_unique(),
+            context,
+        };
+        let initialize = Instruction::new_with_bytes(
+            gate,
+            &[INITIALIZE],
+            vec![
+                AccountMeta::new(config_address(&fixture.mint, &gate).address(), false),
+                AccountMeta::new_readonly(fixture.mint, false),
+                AccountMeta::new_readonly(fixture.issuer.pubkey(), true),
+                AccountMeta::new(fixture.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        let transaction = Transaction::new_signed_with_payer(
+            &[initialize],
+            Some(&fixture.payer.pubkey()),
+            &[&fixture.payer, &fixture.issuer],
+            blockhash,
+        );
+        fixture
+            .banks_client
+            .clone()
+            .process_transaction(transaction)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(fixture)
+    }
+
+    fn add_instruction(&self, holder: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            self.gate,
+            &[ADD_TO_ALLOW_LIST],
+            vec![
+                AccountMeta::new_readonly(config_address(&self.mint, &self.gate).address(), false),
+                AccountMeta::new(
+                    allow_list_address(&self.mint, holder, &self.gate).address(),
+                    false,
+                ),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(*holder, false),
+                AccountMeta::new_readonly(self.issuer.pubkey(), true),
+                AccountMeta::new(self.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        )
+    }
+
+    /// `ADDS` allow list adds and `TRANSFERS` transfers of distinct amounts
+    fn batch(&self) -> Batch {
+        let holders: Vec<Pubkey> = (0..ADDS).map(|_| Pubkey::new_unique()).collect();
+        let recipients: Vec<Pubkey> = (0..TRANSFERS).map(|_| Pubkey::new_unique()).collect();
+        let mut operations: Vec<Operation> = holders
+            .iter()
+            .enumerate()
+            .map(|(i, holder)| {
+                Operation::new(&format!("add {}", i), vec![self.add_instruction(holder)])
+            })
+            .collect();
+        operations.extend(recipients.iter().enumerate().map(|(i, recipient)| {
+            let transfer = system_instruction::transfer(
+                &self.payer.pubkey(),
+                recipient,
+                TRANSFER_BASE + i as u64,
+            );
+            Operation::new(&format!("transfer {}", i), vec![transfer])
+        }));
+        Batch {
+            holders,
+            recipients,
+            operations,
+        }
+    }
+
+    async fn balance(&self, address: Pubkey) -> u64 {
+        self.banks_client
+            .clone()
+            .get_balance(address)
+            .await
+            .unwrap_or_default()
+    }
+
+    /// Durable nonce accounts advanced by the payer
+    async fn create_nonce_accounts(&self, count: usize) -> Result<Vec<NonceAccount>, String> {
+        let lamports = Rent::default().minimum_balance(NonceState::size());
+        let mut accounts = Vec::new();
+        for _ in 0..count {
+            let nonce = Keypair::new();
+            let instructions = system_instruction::create_nonce_account(
+                &self.payer.pubkey(),
+                &nonce.pubkey(),
+                &self.payer.pubkey(),
+                lamports,
+            );
+            let blockhash = self.banks_client.latest_blockhash().await?;
+            let transaction = Transaction::new_signed_with_payer(
+                &instructions,
+                Some(&self.payer.pubkey()),
+                &[&self.payer, &nonce],
+                blockhash,
+            );
+            self.banks_client
+                .clone()
+                .process_transaction(transaction)
+                .await
+                .map_err(|e| e.to_string())?;
+            accounts.push(NonceAccount {
+                address: nonce.pubkey(),
+                authority: self.payer.pubkey(),
+            });
+        }
+        Ok(accounts)
+    }
+}
+
+/// Operations of one batch and the accounts they touch
+struct Batch {
+    holders: Vec<Pubkey>,
+    recipients: Vec<Pubkey>,
+    operations: Vec<Operation>,
+}
+
+impl Batch {
+    /// Lamports the payer spends if every operation lands exactly once: the
+    /// transfers, the allow list records' rent, and one fee per operation
+    /// (adds are signed by the issuer too)
+    fn expected_spend(&self, lamports_per_signature: u64, record_rent: u64) -> u64 {
+        let transfers: u64 = (0..TRANSFERS as u64).map(|i| TRANSFER_BASE + i).sum();
+        let fees = lamports_per_signature * (2 * ADDS + TRANSFERS) as u64;
+        transfers + record_rent * ADDS as u64 + fees
+    }
+}
+
+/// Check every operation landed exactly once; returns the number of checks
+async fn verify_exactly_once(
+    fixture: &EngineFixture,
+    batch: &Batch,
+    report: &SubmissionReport,
+    payer_before: u64,
+) -> Result<usize, String> {
+    if !report.all_landed() {
+        let unlanded: Vec<_> = report
+            .outcomes
+            .iter()
+            .filter(|(_, outcome)| !matches!(outcome, Outcome::Landed { .. }))
+            .collect();
+        return Err(format!("Operations did not land: {:?}", unlanded));
+    }
+
+    for (i, recipient) in batch.recipients.iter().enumerate() {
+        let balance = fixture.balance(*recipient).await;
+        if balance != TRANSFER_BASE + i as u64 {
+            return Err(format!("Transfer {} recipient holds {}", i, balance));
+        }
+    }
+
+    let mut record_rent = 0;
+    for holder in &batch.holders {
+        let record = allow_list_address(&fixture.mint, holder, &fixture.gate).address();
+        record_rent = fixture.balance(record).await;
+        if record_rent == 0 {
+            return Err(format!("{} is not on the allow list", holder));
+        }
+    }
+
+    let spent = payer_before - fixture.balance(fixture.payer.pubkey()).await;
+    let expected = batch.expected_spend(fixture.context.lamports_per_signature, record_rent);
+    if spent != expected {
+        return Err(format!(
+            "Payer spent {} lamports, {} for exactly one transaction per operation",
+            spent, expected
+        ));
+    }
+    Ok(3)
+}
+
+/// A quick loop for program-test's fast-expiring blockhashes
+fn chaos_config(rebroadcast_interval: Duration) -> SubmissionConfig {
+    SubmissionConfig {
+        max_in_flight: 8,
+        poll_interval: Duration::from_millis(20),
+        rebroadcast_interval,
+        blockhash_max_age: Duration::from_millis(200),
+        ..SubmissionConfig::default()
+    }
+}
+
+/// Test 1: Dropped sends are re-signed only after expiring; lost
+/// confirmations are never re-signed
+#[tokio::test]
+async fn test_resigns_only_expired_transactions() {
+    let report = run_resign_test().await;
+    assert!(report.passed, "Re-sign test failed: {:?}", report.error);
+}
+
+async fn run_resign_test() -> TestResultReport {
+    let test_name = "Re-signs Only Expired Transactions";
+    let mut assertions = 0;
+    let fixture = match EngineFixture::new(test_name).await {
+        Ok(fixture) => fixture,
+        Err(e) => return TestResultReport::failure(test_name, format!("Setup failed: {}", e)),
+    };
+    let batch = fixture.batch();
+    let payer_before = fixture.balance(fixture.payer.pubkey()).await;
+
+    // Never rebroadcast, so a dropped send can only land by re-signing
+    let chaos = Chaos {
+        drop_send_every: 4,
+        lose_ack_every: 3,
+        lost_status_polls: 2,
+    };
+    let cluster = ChaosCluster::new(fixture.banks_client.clone(), chaos);
+    let mut engine = SubmissionEngine::new(cluster, chaos_config(Duration::from_secs(3600)));
+    let report = engine
+        .submit_all(batch.operations.clone(), &fixture.payer, &[&fixture.issuer])
+        .await;
+
+    // Assertions: Every operation landed once and was paid for once
+    match verify_exactly_once(&fixture, &batch, &report, payer_before).await {
+        Ok(checks) => assertions += checks,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    }
+
+    // Assertion: Exactly the dropped sends were re-signed; the lost
+    // confirmations landed under their first signature
+    assertions += 1;
+    let dropped = engine.cluster().dropped_sends.load(Ordering::Relaxed);
+    let lost_acks = engine.cluster().lost_acks.load(Ordering::Relaxed);
+    if report.resigns != dropped || dropped == 0 || lost_acks == 0 || report.rebroadcasts != 0 {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "{} re-signs and {} rebroadcasts for {} dropped sends and {} lost acks",
+                report.resigns, report.rebroadcasts, dropped, lost_acks
+            ),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: A transaction still valid is rebroadcast under its signature,
+/// never re-signed
+#[tokio::test]
+async fn test_rebroadcasts_before_expiry() {
+    let report = run_rebroadcast_test().await;
+    assert!(report.passed, "Rebroadcast test failed: {:?}", report.error);
+}
+
+async fn run_rebroadcast_test() -> TestResultReport {
+    let test_name = "Rebroadcasts Before Expiry";
+    let mut assertions = 0;
+    let fixture = match EngineFixture::new(test_name).await {
+        Ok(fixture) => fixture,
+        Err(e) => return TestResultReport::failure(test_name, format!("Setup failed: {}", e)),
+    };
+    let batch = fixture.batch();
+    let payer_before = fixture.balance(fixture.payer.pubkey()).await;
+
+    let chaos = Chaos {
+        drop_send_every: 2,
+        lose_ack_every: 5,
+        lost_status_polls: 1,
+    };
+    let cluster = ChaosCluster::new(fixture.banks_client.clone(), chaos);
+    let mut engine = SubmissionEngine::new(cluster, chaos_config(Duration::from_millis(40)));
+    let report = engine
+        .submit_all(batch.operations.clone(), &fixture.payer, &[&fixture.issuer])
+        .await;
+
+    // Assertions: Every operation landed once and was paid for once
+    match verify_exactly_once(&fixture, &batch, &report, payer_before).await {
+        Ok(checks) => assertions += checks,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    }
+
+    // Assertion: Half the sends were dropped, yet every operation landed
+    // under the first signature it was signed with
+    assertions += 1;
+    let first_signatures = report
+        .outcomes
+        .iter()
+        .all(|(_, outcome)| matches!(outcome, Outcome::Landed { resigns: 0, .. }));
+    if !first_signatures || report.resigns != 0 || report.rebroadcasts == 0 {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "{} re-signs and {} rebroadcasts",
+                report.resigns, report.rebroadcasts
+            ),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: Durable nonce transactions outlive any outage and land once
+#[tokio::test]
+async fn test_durable_nonce_pool() {
+    let report = run_durable_nonce_test().await;
+    assert!(
+        report.passed,
+        "Durable nonce test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_durable_nonce_test() -> TestResultReport {
+    let test_name = "Durable Nonce Pool";
+    let mut assertions = 0;
+    let fixture = match EngineFixture::new(test_name).await {
+        Ok(fixture) => fixture,
+        Err(e) => return TestResultReport::failure(test_name, format!("Setup failed: {}", e)),
+    };
+    let nonces = match fixture.create_nonce_accounts(4).await {
+        Ok(nonces) => nonces,
+        Err(e) => return TestResultReport::failure(test_name, format!("Nonces: {}", e)),
+    };
+    let mut initial = Vec::new();
+    for nonce in &nonces {
+        initial.push(fixture.banks_client.nonce_blockhash(&nonce.address).await);
+    }
+    let batch = fixture.batch();
+    let payer_before = fixture.balance(fixture.payer.pubkey()).await;
+
+    // Rebroadcasts wait longer than a blockhash lives: only the nonces keep
+    // the dropped transactions valid
+    let chaos = Chaos {
+        drop_send_every: 3,
+        lose_ack_every: 4,
+        lost_status_polls: 2,
+    };
+    let cluster = ChaosCluster::new(fixture.banks_client.clone(), chaos);
+    let config = SubmissionConfig {
+        lifetime: Lifetime::DurableNonce(nonces.clone()),
+        ..chaos_config(Duration::from_secs(3))
+    };
+    let mut engine = SubmissionEngine::new(cluster, config);
+    let report = engine
+        .submit_all(batch.operations.clone(), &fixture.payer, &[&fixture.issuer])
+        .await;
+
+    // Assertions: Every operation landed once and was paid for once; the
+    // nonce advance adds no signature, the payer being its authority
+    match verify_exactly_once(&fixture, &batch, &report, payer_before).await {
+        Ok(checks) => assertions += checks,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    }
+
+    // Assertion: Nothing expired, and every nonce in the pool was used
+    assertions += 1;
+    if report.resigns != 0 || report.rebroadcasts == 0 {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "{} re-signs and {} rebroadcasts",
+                report.resigns, report.rebroadcasts
+            ),
+        );
+    }
+    for (nonce, initial) in nonces.iter().zip(initial) {
+        let current = fixture.banks_client.nonce_blockhash(&nonce.address).await;
+        if current.is_err() || current == initial {
+            return TestResultReport::failure(
+                test_name,
+                format!("Nonce {} never advanced", nonce.address),
+            );
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 4: Re-signing after a lost confirmation, as a naive client does,
+/// lands the operation twice
+#[tokio::test]
+async fn test_naive_resubmission_lands_twice() {
+    let report = run_naive_resubmission_test().await;
+    assert!(
+        report.passed,
+        "Naive resubmission test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_naive_resubmission_test() -> TestResultReport {
+    let test_name = "Naive Resubmission Lands Twice";
+    let mut assertions = 0;
+    let fixture = match EngineFixture::new(test_name).await {
+        Ok(fixture) => fixture,
+        Err(e) => return TestResultReport::failure(test_name, format!("Setup failed: {}", e)),
+    };
+    // Every send lands and reports an error
+    let chaos = Chaos {
+        drop_send_every: u64::MAX,
+        lose_ack_every: 1,
+        lost_status_polls: 0,
+    };
+    let cluster = ChaosCluster::new(fixture.banks_client.clone(), chaos);
+    let recipient = Pubkey::new_unique();
+    let transfer = system_instruction::transfer(&fixture.payer.pubkey(), &recipient, TRANSFER_BASE);
+
+    // Assertion: Each attempt, signed with a fresh blockhash, lands
+    assertions += 1;
+    let mut last_blockhash = Hash::default();
+    for attempt in 0..2 {
+        let blockhash = loop {
+            match cluster.latest_blockhash().await {
+                Ok(blockhash) if blockhash != last_blockhash => break blockhash,
+                Ok(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+                Err(e) => return TestResultReport::failure(test_name, e),
+            }
+        };
+        last_blockhash = blockhash;
+        let transaction = Transaction::new_signed_with_payer(
+            std::slice::from_ref(&transfer),
+            Some(&fixture.payer.pubkey()),
+            &[&fixture.payer],
+            blockhash,
+        );
+        if cluster.send(&transaction).await.is_ok() {
+            return TestResultReport::failure(
+                test_name,
+                format!("Attempt {} was acknowledged", attempt),
+            );
+        }
+    }
+
+    // Assertion: The recipient was paid twice
+    assertions += 1;
+    let balance = fixture.balance(recipient).await;
+    if balance != 2 * TRANSFER_BASE {
+        return TestResultReport::failure(
+            test_name,
+            format!("Recipient holds {} after two attempts", balance),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 5: Operations that cannot land are abandoned, not retried forever
+#[tokio::test]
+async fn test_abandons_unlandable_operations() {
+    let report = run_abandon_test().await;
+    assert!(report.passed, "Abandon test failed: {:?}", report.error);
+}
+
+async fn run_abandon_test() -> TestResultReport {
+    let test_name = "Abandons Unlandable Operations";
+    let mut assertions = 0;
+    let fixture = match EngineFixture::new(test_name).await {
+        Ok(fixture) => fixture,
+        Err(e) => return TestResultReport::failure(test_name, format!("Setup failed: {}", e)),
+    };
+    let payer_before = fixture.balance(fixture.payer.pubkey()).await;
+    let recipient = Pubkey::new_unique();
+    let operations = vec![
+        Operation::new(
+            "unreachable",
+            vec![system_instruction::transfer(
+                &fixture.payer.pubkey(),
+                &recipient,
+                TRANSFER_BASE,
+            )],
+        ),
+        // The issuer's signature is withheld
+        Operation::new(
+            "unsigned",
+            vec![fixture.add_instruction(&Pubkey::new_unique())],
+        ),
+    ];
+
+    // Every send is dropped
+    let chaos = Chaos {
+        drop_send_every: 1,
+        lose_ack_every: u64::MAX,
+        lost_status_polls: 0,
+    };
+    let cluster = ChaosCluster::new(fixture.banks_client.clone(), chaos);
+    let config = SubmissionConfig {
+        max_resigns: 1,
+        ..chaos_config(Duration::from_millis(40))
+    };
+    let mut engine = SubmissionEngine::new(cluster, config);
+    let report = engine.submit_all(operations, &fixture.payer, &[]).await;
+
+    // Assertion: The dropped operation expired twice and was abandoned; the
+    // unsigned one was never sent
+    assertions += 1;
+    let abandoned = |label: &str, reason: &str| {
+        report.outcomes.iter().any(|(l, outcome)| {
+            l == label && matches!(outcome, Outcome::Abandoned { reason: r } if r.contains(reason))
+        })
+    };
+    if !abandoned("unreachable", "Expired 2 times")
+        || !abandoned("unsigned", "signature")
+        || report.sends != 2
+        || report.resigns != 1
+    {
+        return TestResultReport::failure(test_name, format!("Report: {:?}", report));
+    }
+
+    // Assertion: Nothing landed and nothing was charged
+    assertions += 1;
+    let after = fixture.balance(fixture.payer.pubkey()).await;
+    if after != payer_before || fixture.balance(recipient).await != 0 {
+        return TestResultReport::failure(
+            test_name,
+            format!("Payer went from {} to {}", payer_before, after),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate submission engine test report
+#[tokio::test]
+async fn generate_submission_engine_report() {
+    let results = vec![
+        run_resign_test().await,
+        run_rebroadcast_test().await,
+        run_durable_nonce_test().await,
+        run_naive_resubmission_test().await,
+        run_abandon_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Submission Engine Test Results",
+        "../../tests/reports/submission_engine.md",
+    ) {
+        panic!("Failed to generate submission engine report: {}", e);
+    }
+}
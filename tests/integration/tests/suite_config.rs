@@ -0,0 +1,319 @@
+//! Suite configuration tests
+//!
+//! These tests check that heavy categories can be toggled from `suite.toml`
+//! and from command-line flags, and that reports list what was disabled.
+
+use solana_sdk::{feature_set, pubkey::Pubkey};
+use std::path::Path;
+
+use token_acl_integration_tests::{
+    reporting,
+    suite_config::{self, SuiteCategory, SuiteConfig},
+    TestResultReport,
+};
+
+/// Test 1: TOML configuration
+#[tokio::test]
+async fn test_toml_configuration() {
+    let report = run_toml_configuration_test();
+    assert!(
+        report.passed,
+        "TOML configuration test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_toml_configuration_test() -> TestResultReport {
+    let test_name = "TOML Suite Configuration";
+    let mut assertions = 0;
+
+    // Assertion 1: Devnet and experiments are the only categories disabled
+    // by default
+    assertions += 1;
+    if SuiteConfig::default().disabled() != vec![SuiteCategory::Devnet, SuiteCategory::Experiments]
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!("Default disables {:?}", SuiteConfig::default().disabled()),
+        );
+    }
+
+    // Assertion 2: Listed categories are toggled, others keep their defaults
+    assertions += 1;
+    let config = match SuiteConfig::from_toml_str(
+        "[categories]\nload_tests = false\nlong_scenarios = false\n",
+    ) {
+        Ok(config) => config,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let expected = vec![
+        SuiteCategory::LoadTests,
+        SuiteCategory::Devnet,
+        SuiteCategory::LongScenarios,
+        SuiteCategory::Experiments,
+    ];
+    if config.disabled() != expected || !config.is_enabled(SuiteCategory::FuzzSmoke) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Config disables {:?}", config.disabled()),
+        );
+    }
+
+    // Assertion 3: Typos are rejected rather than ignored
+    assertions += 1;
+    for text in [
+        "[categories]\nload_test = false\n",
+        "[categories]\ndevnet = \"yes\"\n",
+        "[category]\ndevnet = true\n",
+    ] {
+        if SuiteConfig::from_toml_str(text).is_ok() {
+            return TestResultReport::failure(test_name, format!("Accepted {:?}", text));
+        }
+    }
+
+    // Assertion 4: The committed suite.toml parses
+    assertions += 1;
+    if let Err(e) = SuiteConfig::load(Path::new(suite_config::DEFAULT_CONFIG_PATH)) {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: Command-line flags
+#[tokio::test]
+async fn test_command_line_flags() {
+    let report = run_command_line_flags_test();
+    assert!(
+        report.passed,
+        "Command-line flags test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_command_line_flags_test() -> TestResultReport {
+    let test_name = "Suite Command-Line Flags";
+    let mut assertions = 0;
+
+    // Assertion 1: Flags apply in order, in both `--flag value` and `--flag=value` form
+    assertions += 1;
+    let mut config = SuiteConfig::default();
+    if let Err(e) = config.apply_args([
+        "--enable",
+        "devnet",
+        "--disable=load-tests,fuzz_smoke",
+        "--enable",
+        "fuzz_smoke",
+    ]) {
+        return TestResultReport::failure(test_name, e);
+    }
+    if config.disabled() != vec![SuiteCategory::LoadTests, SuiteCategory::Experiments] {
+        return TestResultReport::failure(
+            test_name,
+            format!("Flags left {:?} disabled", config.disabled()),
+        );
+    }
+
+    // Assertion 2: `--only` disables everything else
+    assertions += 1;
+    let mut config = SuiteConfig::all_enabled();
+    if let Err(e) = config.apply_args(["--only", "devnet"]) {
+        return TestResultReport::failure(test_name, e);
+    }
+    if SuiteCategory::ALL
+        .iter()
+        .any(|c| config.is_enabled(*c) != (*c == SuiteCategory::Devnet))
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!("--only left {:?} disabled", config.disabled()),
+        );
+    }
+
+    // Assertion 3: `all` names every category
+    assertions += 1;
+    if let Err(e) = config.apply_args(["--enable", "all"]) {
+        return TestResultReport::failure(test_name, e);
+    }
+    if config != SuiteConfig::all_enabled() {
+        return TestResultReport::failure(test_name, "--enable all had no effect".to_string());
+    }
+
+    // Assertion 4: Unknown flags, unknown categories and missing values fail
+    assertions += 1;
+    for args in [
+        vec!["--skip", "devnet"],
+        vec!["--disable", "mainnet"],
+        vec!["--enable"],
+        vec!["--keep-state=yes"],
+    ] {
+        if SuiteConfig::default().apply_args(&args).is_ok() {
+            return TestResultReport::failure(test_name, format!("Accepted {:?}", args));
+        }
+    }
+
+    // Assertion 5: `--keep-state` takes no value and leaves categories alone
+    assertions += 1;
+    let mut config = SuiteConfig::default();
+    if let Err(e) = config.apply_args(["--keep-state", "--enable", "devnet"]) {
+        return TestResultReport::failure(test_name, e);
+    }
+    if !config.keep_state()
+        || !config.is_enabled(SuiteCategory::Devnet)
+        || SuiteConfig::default().keep_state()
+    {
+        return TestResultReport::failure(test_name, format!("Parsed {:?}", config));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: Skipped tests and disabled categories in reports
+#[tokio::test]
+async fn test_disabled_categories_report() {
+    let report = run_disabled_categories_report_test();
+    assert!(
+        report.passed,
+        "Disabled categories report test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_disabled_categories_report_test() -> TestResultReport {
+    let test_name = "Disabled Categories Report";
+    let mut assertions = 0;
+
+    let results = vec![
+        TestResultReport::success("PDA Derivation Test", 3),
+        TestResultReport::skipped("Devnet Thaw", SuiteCategory::Devnet),
+    ];
+    let report = reporting::render_test_report(&results, "Sample", &[SuiteCategory::Devnet]);
+
+    // Assertion 1: Skipped tests are neither passed nor failed
+    assertions += 1;
+    if !report.contains("- **Passed**: 1 (50.0%)")
+        || !report.contains("- **Failed**: 0")
+        || !report.contains("- **Skipped**: 1")
+    {
+        return TestResultReport::failure(test_name, "Summary miscounts skips".to_string());
+    }
+
+    // Assertion 2: Disabled categories are listed with their descriptions
+    assertions += 1;
+    let listing = format!("- `devnet` - {}", SuiteCategory::Devnet.description());
+    if !report.contains("## Disabled Categories") || !report.contains(&listing) {
+        return TestResultReport::failure(test_name, "Disabled categories missing".to_string());
+    }
+
+    // Assertion 3: The skipped test names its category
+    assertions += 1;
+    if !report.contains("| Devnet Thaw | ⏭️ SKIP | 0 | `devnet` disabled |") {
+        return TestResultReport::failure(test_name, "Skipped row missing".to_string());
+    }
+
+    // Assertion 4: Disabled categories skip their tests
+    assertions += 1;
+    let skipped = SuiteCategory::ALL
+        .into_iter()
+        .filter(|c| !suite_config::current().is_enabled(*c))
+        .all(|c| suite_config::skip_unless(c, "Gated").is_some_and(|r| r.skipped == Some(c)));
+    if !skipped {
+        return TestResultReport::failure(test_name, "Disabled category ran".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 4: Runtime features deactivated for every test bank
+#[tokio::test]
+async fn test_deactivated_features() {
+    let report = run_deactivated_features_test();
+    assert!(
+        report.passed,
+        "Deactivated features test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_deactivated_features_test() -> TestResultReport {
+    let test_name = "Deactivated Runtime Features";
+    let mut assertions = 0;
+
+    let rent_epoch = feature_set::set_exempt_rent_epoch_max::id();
+    let executable = feature_set::deprecate_executable_meta_update_in_bpf_loader::id();
+
+    // Assertion 1: Every feature is active by default
+    assertions += 1;
+    if !SuiteConfig::default().deactivated_features().is_empty() {
+        return TestResultReport::failure(test_name, "Features deactivated by default".to_string());
+    }
+
+    // Assertion 2: The [features] table and the flag both deactivate
+    assertions += 1;
+    let toml = format!("[features]\ndeactivated = [\"{}\"]\n", rent_epoch);
+    let mut config = match SuiteConfig::from_toml_str(&toml) {
+        Ok(config) => config,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let flag = format!("--deactivate-feature={}", executable);
+    if let Err(e) = config.apply_args([flag.as_str()]) {
+        return TestResultReport::failure(test_name, e);
+    }
+    let mut expected = vec![rent_epoch, executable];
+    expected.sort();
+    if config.deactivated_features() != expected {
+        return TestResultReport::failure(test_name, format!("Parsed {:?}", config));
+    }
+
+    // Assertion 3: An id the runtime does not know is rejected
+    assertions += 1;
+    let unknown = Pubkey::new_unique().to_string();
+    if SuiteConfig::default()
+        .apply_args(["--deactivate-feature", unknown.as_str()])
+        .is_ok()
+        || SuiteConfig::default()
+            .apply_args(["--deactivate-feature", "not-a-key"])
+            .is_ok()
+    {
+        return TestResultReport::failure(test_name, "Accepted an unknown feature".to_string());
+    }
+
+    // Assertion 4: Reports list the deactivated features by description
+    assertions += 1;
+    let section = reporting::render_deactivated_features(&[rent_epoch]);
+    let listing = format!(
+        "- `{}` - {}",
+        rent_epoch,
+        feature_set::FEATURE_NAMES[&rent_epoch]
+    );
+    if !section.starts_with("## Deactivated Runtime Features")
+        || !section.contains(&listing)
+        || !reporting::render_deactivated_features(&[]).is_empty()
+    {
+        return TestResultReport::failure(test_name, format!("Rendered:\n{}", section));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate suite configuration test report
+#[tokio::test]
+async fn generate_suite_config_report() {
+    let results = vec![
+        run_toml_configuration_test(),
+        run_command_line_flags_test(),
+        run_disabled_categories_report_test(),
+        run_deactivated_features_test(),
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Suite Configuration Test Results",
+        "../../tests/reports/suite_config.md",
+    ) {
+        panic!("Failed to generate suite configuration report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} suite configuration tests failed", failed);
+}
@@ -0,0 +1,488 @@
+//! SVM harness tests
+//!
+//! These tests drive Token-2022 and the gate programs through
+//! [`SvmHarness`], the synchronous in-process bank. Token-2022 always runs
+//! from its SBF build, so Default Account State and the thaws are the real
+//! program's; the gates run from their SBF builds when present and natively
+//! otherwise, including their CPIs, sysvar reads and instruction
+//! introspection. A whole scenario must also run in well under a second.
+
+use borsh::BorshSerialize;
+use example_allow_list::{allow_list_address, create_allow_list_record};
+use solana_program_test::processor;
+use solana_sdk::{
+    account::Account,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    system_instruction, system_program, sysvar,
+};
+use spl_token_2022::{
+    instruction as token_instruction,
+    state::{AccountState, Mint},
+};
+use std::time::{Duration, Instant};
+use token_acl_client::instructions::{self, FreezeAuthority};
+use token_acl_integration_tests::{
+    bpf_coverage::spl_token_2022_elf, program_test::failed_at, reporting, SvmHarness,
+    TestResultReport,
+};
+
+/// Budget for a full onboarding scenario on the in-process bank
+const SCENARIO_BUDGET: Duration = Duration::from_secs(1);
+
+/// A harness with the allow list example at `gate` and a mint whose new
+/// accounts start frozen
+fn allow_list_harness(issuer: &Keypair) -> Result<(SvmHarness, Pubkey, Pubkey), String> {
+    let mut svm = SvmHarness::new();
+    let gate = Pubkey::new_unique();
+    svm.add_program(
+        "example_allow_list",
+        gate,
+        processor!(example_allow_list::process_instruction),
+    )?;
+    let mint = svm
+        .create_mint(&issuer.pubkey(), AccountState::Frozen)
+        .map_err(|e| format!("Mint creation failed: {}", e))?;
+    Ok((svm, gate, mint))
+}
+
+/// List `user` on the allow list example, which has no admin instructions
+fn list(svm: &mut SvmHarness, gate: &Pubkey, mint: &Pubkey, user: &Pubkey) {
+    let data = create_allow_list_record(mint, user, 0)
+        .try_to_vec()
+        .expect("records always serialize");
+    svm.set_account(
+        allow_list_address(mint, user, gate).address(),
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: *gate,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+}
+
+/// The allow list's thaw check of `owner`'s account, then the thaw the
+/// FAMP would make, with the issuer standing in for the FAMP PDA
+fn permissionless_thaw(
+    gate: &Pubkey,
+    mint: &Pubkey,
+    issuer: &Pubkey,
+    token_account: &Pubkey,
+    owner: &Pubkey,
+) -> Vec<Instruction> {
+    let record = allow_list_address(mint, owner, gate).address();
+    let check = instructions::with_extra_accounts(
+        instructions::permissionless_thaw(mint, token_account, owner, gate),
+        &[*owner, record],
+    );
+    instructions::authorized(check, &FreezeAuthority::Key(*issuer))
+        .expect("a thaw check authorizes a thaw")
+        .to_vec()
+}
+
+/// Test 1: Token-2022 applies the mint's Default Account State to new
+/// accounts
+#[test]
+fn test_default_account_state() {
+    let report = run_default_account_state_test();
+    assert!(
+        report.passed,
+        "Default account state test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_default_account_state_test() -> TestResultReport {
+    let test_name = "SVM Harness Default Account State";
+    let mut assertions = 0;
+
+    let mut svm = SvmHarness::new();
+    let issuer = Keypair::new();
+    let owner = Pubkey::new_unique();
+
+    for (default_state, expected) in [
+        (AccountState::Frozen, AccountState::Frozen),
+        (AccountState::Initialized, AccountState::Initialized),
+    ] {
+        // Assertion: One per default state
+        assertions += 1;
+        let mint = match svm.create_mint(&issuer.pubkey(), default_state) {
+            Ok(mint) => mint,
+            Err(e) => return TestResultReport::failure(test_name, format!("Mint creation: {}", e)),
+        };
+        let token_account = match svm.create_token_account(&mint, &owner) {
+            Ok(token_account) => token_account,
+            Err(e) => {
+                return TestResultReport::failure(
+                    test_name,
+                    format!("Token account creation: {}", e),
+                )
+            }
+        };
+        let state = svm.token_state(&token_account);
+        if state != Some(expected) {
+            return TestResultReport::failure(
+                test_name,
+                format!("{:?} mint created an account in {:?}", default_state, state),
+            );
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: A permissionless thaw through the allow list gate, and its
+/// denial, execute against the real Token-2022
+#[test]
+fn test_allow_list_thaw() {
+    let report = run_allow_list_thaw_test();
+    assert!(
+        report.passed,
+        "Allow list thaw test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_allow_list_thaw_test() -> TestResultReport {
+    let test_name = "SVM Harness Allow List Thaw";
+    let mut assertions = 0;
+
+    let issuer = Keypair::new();
+    let (mut svm, gate, mint) = match allow_list_harness(&issuer) {
+        Ok(harness) => harness,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let listed = Pubkey::new_unique();
+    let unlisted = Pubkey::new_unique();
+    list(&mut svm, &gate, &mint, &listed);
+
+    // Assertion 1: A listed holder's account is born frozen and thawed
+    assertions += 1;
+    let listed_account = match svm.create_token_account(&mint, &listed) {
+        Ok(token_account) => token_account,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+    if svm.token_state(&listed_account) != Some(AccountState::Frozen) {
+        return TestResultReport::failure(test_name, "Account not born frozen".to_string());
+    }
+    let thaw = permissionless_thaw(&gate, &mint, &issuer.pubkey(), &listed_account, &listed);
+    if let Err(e) = svm.send(&thaw, &[&issuer]) {
+        return TestResultReport::failure(test_name, format!("Listed thaw failed: {}", e));
+    }
+    if svm.token_state(&listed_account) != Some(AccountState::Initialized) {
+        return TestResultReport::failure(test_name, "Listed account still frozen".to_string());
+    }
+
+    // Assertion 2: An unlisted holder's check fails the whole transaction
+    assertions += 1;
+    let token_account = match svm.create_token_account(&mint, &unlisted) {
+        Ok(token_account) => token_account,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+    let thaw = permissionless_thaw(&gate, &mint, &issuer.pubkey(), &token_account, &unlisted);
+    let result = svm.send(&thaw, &[&issuer]);
+    if !failed_at(&result, 0, InstructionError::InvalidAccountData)
+        || svm.token_state(&token_account) != Some(AccountState::Frozen)
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!("CRITICAL: Unlisted thaw: {:?}", result),
+        );
+    }
+
+    // Assertion 3: Token-2022's execution is metered and logged
+    assertions += 1;
+    let freeze = token_instruction::freeze_account(
+        &spl_token_2022::id(),
+        &listed_account,
+        &mint,
+        &issuer.pubkey(),
+        &[],
+    )
+    .expect("the Token-2022 program id is always valid");
+    match svm.send(&[freeze], &[&issuer]) {
+        Ok(executed)
+            if executed.compute_units > 0
+                && executed
+                    .logs
+                    .iter()
+                    .any(|log| log.contains("Instruction: FreezeAccount")) => {}
+        other => {
+            return TestResultReport::failure(test_name, format!("Freeze: {:?}", other));
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: A gate with CPIs, clock reads and instruction introspection
+/// runs natively on the harness, including across a clock change
+#[test]
+fn test_velocity_gate() {
+    let report = run_velocity_gate_test();
+    assert!(
+        report.passed,
+        "Velocity gate test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_velocity_gate_test() -> TestResultReport {
+    let test_name = "SVM Harness Velocity Gate";
+    let mut assertions = 0;
+
+    let mut svm = SvmHarness::new();
+    let gate = Pubkey::new_unique();
+    if let Err(e) = svm.add_program(
+        "velocity_gate",
+        gate,
+        processor!(velocity_gate::process_instruction),
+    ) {
+        return TestResultReport::failure(test_name, e);
+    }
+    let issuer = Keypair::new();
+    let holder = Keypair::new();
+    let mint = match svm.create_mint(&issuer.pubkey(), AccountState::Frozen) {
+        Ok(mint) => mint,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+    let config = velocity_gate::config_address(&mint, &gate).address();
+    let ledger = velocity_gate::ledger_address(&mint, &holder.pubkey(), &gate).address();
+
+    let mut initialize = vec![velocity_gate::INITIALIZE, 1];
+    initialize.extend_from_slice(&60u32.to_le_bytes());
+    let initialize = Instruction::new_with_bytes(
+        gate,
+        &initialize,
+        vec![
+            AccountMeta::new(config, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(issuer.pubkey(), true),
+            AccountMeta::new(svm.payer.pubkey(), true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    if let Err(e) = svm.send(&[initialize], &[&issuer]) {
+        return TestResultReport::failure(test_name, format!("Initialize failed: {}", e));
+    }
+
+    let thaw = |svm: &SvmHarness, token_account: Pubkey| {
+        let record = Instruction::new_with_bytes(
+            gate,
+            &[velocity_gate::RECORD_THAW],
+            vec![
+                AccountMeta::new_readonly(config, false),
+                AccountMeta::new(ledger, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(token_account, false),
+                AccountMeta::new_readonly(holder.pubkey(), true),
+                AccountMeta::new(svm.payer.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        let check = instructions::with_extra_accounts(
+            instructions::permissionless_thaw(&mint, &token_account, &holder.pubkey(), &gate),
+            &[holder.pubkey(), config, ledger, sysvar::instructions::id()],
+        );
+        let [check, thaw] = instructions::authorized(check, &FreezeAuthority::Key(issuer.pubkey()))
+            .expect("a thaw check authorizes a thaw");
+        vec![record, check, thaw]
+    };
+
+    let mut accounts = Vec::new();
+    for _ in 0..2 {
+        match svm.create_token_account(&mint, &holder.pubkey()) {
+            Ok(token_account) => accounts.push(token_account),
+            Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+        }
+    }
+
+    // Assertion 1: The first thaw creates the ledger by CPI and goes through
+    assertions += 1;
+    let instructions = thaw(&svm, accounts[0]);
+    if let Err(e) = svm.send(&instructions, &[&issuer, &holder]) {
+        return TestResultReport::failure(test_name, format!("First thaw failed: {}", e));
+    }
+    if svm.token_state(&accounts[0]) != Some(AccountState::Initialized) {
+        return TestResultReport::failure(test_name, "First account still frozen".to_string());
+    }
+
+    // Assertion 2: The second in the same minute is over the limit
+    assertions += 1;
+    let instructions = thaw(&svm, accounts[1]);
+    let result = svm.send(&instructions, &[&issuer, &holder]);
+    if !failed_at(&result, 0, InstructionError::InvalidAccountData) {
+        return TestResultReport::failure(test_name, format!("Second thaw: {:?}", result));
+    }
+
+    // Assertion 3: A minute later it fits
+    assertions += 1;
+    let now = svm.clock().unix_timestamp;
+    svm.set_unix_timestamp(now + 60);
+    if let Err(e) = svm.send(&instructions, &[&issuer, &holder]) {
+        return TestResultReport::failure(test_name, format!("Thaw a minute later: {}", e));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 4: SBF artifacts are loaded and executed, and a program with
+/// neither a build nor a processor is refused
+#[test]
+fn test_sbf_loading() {
+    let report = run_sbf_loading_test();
+    assert!(report.passed, "SBF loading test failed: {:?}", report.error);
+}
+
+fn run_sbf_loading_test() -> TestResultReport {
+    let test_name = "SVM Harness SBF Loading";
+    let mut assertions = 0;
+
+    let mut svm = SvmHarness::new();
+
+    // Assertion 1: Token-2022's build deployed at another address runs as
+    // metered SBF and initializes a mint owned by that address
+    assertions += 1;
+    let copy = Pubkey::new_unique();
+    svm.load_program(copy, spl_token_2022_elf());
+    let mint = Keypair::new();
+    let rent = Rent::default().minimum_balance(Mint::LEN);
+    let create = system_instruction::create_account(
+        &svm.payer.pubkey(),
+        &mint.pubkey(),
+        rent,
+        Mint::LEN as u64,
+        &copy,
+    );
+    let mut initialize = token_instruction::initialize_mint2(
+        &spl_token_2022::id(),
+        &mint.pubkey(),
+        &mint.pubkey(),
+        None,
+        0,
+    )
+    .expect("the Token-2022 program id is always valid");
+    initialize.program_id = copy;
+    let executed = match svm.send(&[create, initialize], &[&mint]) {
+        Ok(executed) => executed,
+        Err(e) => {
+            return TestResultReport::failure(test_name, format!("Copy of Token-2022: {:?}", e))
+        }
+    };
+    let metered = executed
+        .logs
+        .iter()
+        .any(|log| log.starts_with(&format!("Program {} consumed", copy)));
+    let initialized = svm
+        .account(&mint.pubkey())
+        .filter(|account| account.owner == copy)
+        .and_then(|account| Mint::unpack(&account.data).ok())
+        .is_some_and(|state| state.is_initialized);
+    if !metered || !initialized {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Copy of Token-2022: metered {}, mint initialized {}",
+                metered, initialized
+            ),
+        );
+    }
+
+    // Assertion 2: A program with no SBF build and no processor is an
+    // error, not a bank without it
+    assertions += 1;
+    if svm
+        .add_program("no_such_program", Pubkey::new_unique(), None)
+        .is_ok()
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Added a program with nothing to run".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 5: Onboarding twenty holders, each a mint-frozen account and a
+/// permissionless thaw, runs in under a second
+#[test]
+fn test_scenario_runtime() {
+    let report = run_scenario_runtime_test();
+    assert!(
+        report.passed,
+        "Scenario runtime test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_scenario_runtime_test() -> TestResultReport {
+    let test_name = "SVM Harness Scenario Runtime";
+    let mut assertions = 0;
+
+    let started = Instant::now();
+    let issuer = Keypair::new();
+    let (mut svm, gate, mint) = match allow_list_harness(&issuer) {
+        Ok(harness) => harness,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+
+    // Assertion 1: Every holder is onboarded
+    assertions += 1;
+    for holder in 0..20 {
+        let owner = Pubkey::new_unique();
+        list(&mut svm, &gate, &mint, &owner);
+        let token_account = match svm.create_token_account(&mint, &owner) {
+            Ok(token_account) => token_account,
+            Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+        };
+        let thaw = permissionless_thaw(&gate, &mint, &issuer.pubkey(), &token_account, &owner);
+        if let Err(e) = svm.send(&thaw, &[&issuer]) {
+            return TestResultReport::failure(
+                test_name,
+                format!("Holder {} thaw failed: {}", holder, e),
+            );
+        }
+    }
+
+    // Assertion 2: Within the budget, bank start-up included
+    assertions += 1;
+    let elapsed = started.elapsed();
+    if elapsed > SCENARIO_BUDGET {
+        return TestResultReport::failure(
+            test_name,
+            format!("Took {:?}, over {:?}", elapsed, SCENARIO_BUDGET),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate SVM harness test report
+#[test]
+fn generate_svm_harness_report() {
+    let results = vec![
+        run_default_account_state_test(),
+        run_allow_list_thaw_test(),
+        run_velocity_gate_test(),
+        run_sbf_loading_test(),
+        run_scenario_runtime_test(),
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "SVM Harness Test Results",
+        "../../tests/reports/svm_harness.md",
+    ) {
+        panic!("Failed to generate SVM harness report: {}", e);
+    }
+
+    for result in &results {
+        assert!(result.passed, "{} failed: {:?}", result.name, result.error);
+    }
+}
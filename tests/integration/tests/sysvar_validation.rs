@@ -0,0 +1,442 @@
+//! System program and sysvar validation tests
+//!
+//! Admin instructions take the system program to create and grow accounts.
+//! These tests hand every such instruction a spoofed "system program" (a
+//! deployed no-op program at another address) and assert the gate refuses
+//! it up front, before touching any state. The gates read `Rent` through
+//! `Sysvar::get`, which cannot be spoofed; for instructions that take `Rent`
+//! or `Clock` as accounts, a probe program checks them with the interface's
+//! `check_sysvar` and must refuse look-alike accounts carrying sysvar data
+//! at another address.
+
+use example_block_list::{
+    freeze_extra_account_metas, freeze_extra_account_metas_version,
+    initialize_freeze_extra_account_metas, update_freeze_extra_account_metas,
+};
+use production_allow_list::{allow_list_address, config_address, ADD_TO_ALLOW_LIST, INITIALIZE};
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::{create_account_for_test, Account},
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    msg,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    system_program,
+    sysvar::{self, Sysvar},
+    transaction::{Transaction, TransactionError},
+};
+
+use token_acl_integration_tests::{program_test::mint_account, reporting, TestResultReport};
+use token_acl_interface::check_sysvar;
+
+/// Stands in for the system program; accepts anything
+fn spoofed_system_program_process(
+    _program_id: &Pubkey,
+    _accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    msg!("Spoofed system program accepted the instruction");
+    Ok(())
+}
+
+/// Reads `[rent, clock]` from accounts, checking their addresses first
+fn sysvar_probe_process(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let rent = next_account_info(account_info_iter)?;
+    let clock = next_account_info(account_info_iter)?;
+
+    check_sysvar::<Rent>(rent)?;
+    check_sysvar::<Clock>(clock)?;
+
+    let rent = Rent::from_account_info(rent)?;
+    let clock = Clock::from_account_info(clock)?;
+    msg!(
+        "Probe read rent {} lamports/byte-year at slot {}",
+        rent.lamports_per_byte_year,
+        clock.slot
+    );
+    Ok(())
+}
+
+struct SysvarFixture {
+    context: ProgramTestContext,
+    allow_list_gate: Pubkey,
+    block_list_gate: Pubkey,
+    spoofed_system_program: Pubkey,
+    sysvar_probe: Pubkey,
+    spoofed_rent: Pubkey,
+    spoofed_clock: Pubkey,
+    mint: Pubkey,
+    mint_authority: Keypair,
+}
+
+impl SysvarFixture {
+    async fn new() -> Self {
+        let allow_list_gate = Pubkey::new_unique();
+        let block_list_gate = Pubkey::new_unique();
+        let spoofed_system_program = Pubkey::new_unique();
+        let sysvar_probe = Pubkey::new_unique();
+        let spoofed_rent = Pubkey::new_unique();
+        let spoofed_clock = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let mint_authority = Keypair::new();
+
+        let mut program_test = ProgramTest::new(
+            "production_allow_list",
+            allow_list_gate,
+            processor!(production_allow_list::process_instruction),
+        );
+        program_test.add_program(
+            "example_block_list",
+            block_list_gate,
+            processor!(example_block_list::process_instruction),
+        );
+        program_test.add_program(
+            "spoofed_system_program",
+            spoofed_system_program,
+            processor!(spoofed_system_program_process),
+        );
+        program_test.add_program(
+            "sysvar_probe",
+            sysvar_probe,
+            processor!(sysvar_probe_process),
+        );
+
+        // Look-alikes: real sysvar layouts and owner, wrong addresses
+        let rent = Rent {
+            lamports_per_byte_year: 0,
+            ..Rent::default()
+        };
+        program_test.add_account(spoofed_rent, create_account_for_test(&rent));
+        program_test.add_account(spoofed_clock, create_account_for_test(&Clock::default()));
+
+        program_test.add_account(mint, mint_account(&mint_authority.pubkey(), 0));
+
+        let context = program_test.start_with_context().await;
+
+        Self {
+            context,
+            allow_list_gate,
+            block_list_gate,
+            spoofed_system_program,
+            sysvar_probe,
+            spoofed_rent,
+            spoofed_clock,
+            mint,
+            mint_authority,
+        }
+    }
+
+    /// Replace the system program in `instruction` with the spoofed one
+    fn spoof_system_program(&self, mut instruction: Instruction) -> Instruction {
+        for meta in &mut instruction.accounts {
+            if meta.pubkey == system_program::id() {
+                meta.pubkey = self.spoofed_system_program;
+            }
+        }
+        instruction
+    }
+
+    fn allow_list_setup(&self, authority: &Pubkey, user: &Pubkey) -> [Instruction; 2] {
+        let payer = self.context.payer.pubkey();
+        let config = config_address(&self.mint, &self.allow_list_gate).address();
+        [
+            Instruction::new_with_bytes(
+                self.allow_list_gate,
+                &[INITIALIZE],
+                vec![
+                    AccountMeta::new(config, false),
+                    AccountMeta::new_readonly(self.mint, false),
+                    AccountMeta::new_readonly(*authority, true),
+                    AccountMeta::new(payer, true),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            ),
+            Instruction::new_with_bytes(
+                self.allow_list_gate,
+                &[ADD_TO_ALLOW_LIST],
+                vec![
+                    AccountMeta::new_readonly(config, false),
+                    AccountMeta::new(
+                        allow_list_address(&self.mint, user, &self.allow_list_gate).address(),
+                        false,
+                    ),
+                    AccountMeta::new_readonly(self.mint, false),
+                    AccountMeta::new_readonly(*user, false),
+                    AccountMeta::new_readonly(*authority, true),
+                    AccountMeta::new(payer, true),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            ),
+        ]
+    }
+
+    fn sysvar_probe_instruction(&self, rent: Pubkey, clock: Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            self.sysvar_probe,
+            &[],
+            vec![
+                AccountMeta::new_readonly(rent, false),
+                AccountMeta::new_readonly(clock, false),
+            ],
+        )
+    }
+
+    async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), TransactionError> {
+        // A fresh blockhash keeps retried instructions from being deduplicated
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let mut all_signers = vec![&self.context.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.context.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .map_err(|e| e.unwrap())
+    }
+
+    async fn account(&mut self, address: &Pubkey) -> Option<Account> {
+        self.context
+            .banks_client
+            .get_account(*address)
+            .await
+            .unwrap()
+    }
+}
+
+fn expect_rejection(
+    result: Result<(), TransactionError>,
+    index: u8,
+    expected: InstructionError,
+    case: &str,
+) -> Result<(), String> {
+    match result {
+        Ok(()) => Err(format!("{} was accepted", case)),
+        Err(TransactionError::InstructionError(i, e)) if i == index && e == expected => Ok(()),
+        Err(e) => Err(format!(
+            "{} failed with {:?}, expected {:?} at instruction {}",
+            case, e, expected, index
+        )),
+    }
+}
+
+/// Test 1: Allow list admin instructions refuse a spoofed system program
+#[tokio::test]
+async fn test_allow_list_spoofed_system_program() {
+    let report = run_allow_list_spoofed_system_program_test().await;
+    assert!(
+        report.passed,
+        "Allow list spoofed system program test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_allow_list_spoofed_system_program_test() -> TestResultReport {
+    let test_name = "Allow List Spoofed System Program";
+    let mut assertions = 0;
+
+    let mut fixture = SysvarFixture::new().await;
+    let authority = Keypair::new();
+    let user = Pubkey::new_unique();
+    let [initialize, add] = fixture.allow_list_setup(&authority.pubkey(), &user);
+    let config = config_address(&fixture.mint, &fixture.allow_list_gate).address();
+    let record = allow_list_address(&fixture.mint, &user, &fixture.allow_list_gate).address();
+
+    // Assertion 1: Initialize refuses the spoofed system program
+    assertions += 1;
+    let spoofed = fixture.spoof_system_program(initialize.clone());
+    if let Err(e) = expect_rejection(
+        fixture.send(&[spoofed], &[&authority]).await,
+        0,
+        InstructionError::IncorrectProgramId,
+        "Initialize with a spoofed system program",
+    ) {
+        return TestResultReport::failure(test_name, e);
+    }
+    if fixture.account(&config).await.is_some() {
+        return TestResultReport::failure(test_name, "Config was created".to_string());
+    }
+
+    // Assertion 2: Add refuses the spoofed system program
+    assertions += 1;
+    let spoofed = fixture.spoof_system_program(add.clone());
+    if let Err(e) = expect_rejection(
+        fixture.send(&[initialize, spoofed], &[&authority]).await,
+        1,
+        InstructionError::IncorrectProgramId,
+        "Add with a spoofed system program",
+    ) {
+        return TestResultReport::failure(test_name, e);
+    }
+    if fixture.account(&record).await.is_some() {
+        return TestResultReport::failure(test_name, "Record was created".to_string());
+    }
+
+    // Assertion 3: The real system program still works
+    assertions += 1;
+    let [initialize, add] = fixture.allow_list_setup(&authority.pubkey(), &user);
+    if let Err(e) = fixture.send(&[initialize, add], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Setup failed: {}", e));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: Block list admin instructions refuse a spoofed system program
+#[tokio::test]
+async fn test_block_list_spoofed_system_program() {
+    let report = run_block_list_spoofed_system_program_test().await;
+    assert!(
+        report.passed,
+        "Block list spoofed system program test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_block_list_spoofed_system_program_test() -> TestResultReport {
+    let test_name = "Block List Spoofed System Program";
+    let mut assertions = 0;
+
+    let mut fixture = SysvarFixture::new().await;
+    let gate = fixture.block_list_gate;
+    let payer = fixture.context.payer.pubkey();
+    let mint = fixture.mint;
+    let list = token_acl_interface::find_freeze_extra_account_metas_address(&mint, &gate).0;
+
+    // Assertion 1: Initialization refuses the spoofed system program
+    assertions += 1;
+    let init = initialize_freeze_extra_account_metas(&gate, &payer, &mint);
+    let spoofed = fixture.spoof_system_program(init.clone());
+    if let Err(e) = expect_rejection(
+        fixture.send(&[spoofed], &[]).await,
+        0,
+        InstructionError::IncorrectProgramId,
+        "Initialize with a spoofed system program",
+    ) {
+        return TestResultReport::failure(test_name, e);
+    }
+    if fixture.account(&list).await.is_some() {
+        return TestResultReport::failure(test_name, "List was created".to_string());
+    }
+
+    // Assertion 2: Update refuses the spoofed system program
+    assertions += 1;
+    if let Err(e) = fixture.send(&[init], &[]).await {
+        return TestResultReport::failure(test_name, format!("Initialization failed: {}", e));
+    }
+    let authority = fixture.mint_authority.insecure_clone();
+    let metas = freeze_extra_account_metas().unwrap();
+    let update =
+        update_freeze_extra_account_metas(&gate, &authority.pubkey(), &payer, &mint, 1, &metas)
+            .unwrap();
+    let spoofed = fixture.spoof_system_program(update.clone());
+    if let Err(e) = expect_rejection(
+        fixture.send(&[spoofed], &[&authority]).await,
+        0,
+        InstructionError::IncorrectProgramId,
+        "Update with a spoofed system program",
+    ) {
+        return TestResultReport::failure(test_name, e);
+    }
+    let version = fixture
+        .account(&list)
+        .await
+        .and_then(|account| freeze_extra_account_metas_version(&account.data).ok());
+    if version != Some(1) {
+        return TestResultReport::failure(test_name, format!("List version is {:?}", version));
+    }
+
+    // Assertion 3: The real system program still works
+    assertions += 1;
+    if let Err(e) = fixture.send(&[update], &[&authority]).await {
+        return TestResultReport::failure(test_name, format!("Update failed: {}", e));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: Rent and Clock accounts are checked against the sysvar addresses
+#[tokio::test]
+async fn test_spoofed_rent_and_clock_sysvars() {
+    let report = run_spoofed_rent_and_clock_sysvars_test().await;
+    assert!(
+        report.passed,
+        "Spoofed rent and clock sysvar test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_spoofed_rent_and_clock_sysvars_test() -> TestResultReport {
+    let test_name = "Spoofed Rent and Clock Sysvars";
+    let mut assertions = 0;
+
+    let mut fixture = SysvarFixture::new().await;
+    let rent = sysvar::rent::id();
+    let clock = sysvar::clock::id();
+
+    // Assertion 1: The real sysvars are accepted
+    assertions += 1;
+    let probe = fixture.sysvar_probe_instruction(rent, clock);
+    if let Err(e) = fixture.send(&[probe], &[]).await {
+        return TestResultReport::failure(test_name, format!("Real sysvars rejected: {}", e));
+    }
+
+    // Assertion 2: Look-alike and swapped sysvars are refused
+    assertions += 1;
+    let spoofed_cases = [
+        (fixture.spoofed_rent, clock, "Spoofed rent"),
+        (rent, fixture.spoofed_clock, "Spoofed clock"),
+        (clock, rent, "Swapped rent and clock"),
+    ];
+    for (rent, clock, case) in spoofed_cases {
+        let probe = fixture.sysvar_probe_instruction(rent, clock);
+        if let Err(e) = expect_rejection(
+            fixture.send(&[probe], &[]).await,
+            0,
+            InstructionError::InvalidArgument,
+            case,
+        ) {
+            return TestResultReport::failure(test_name, e);
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate system program and sysvar validation report
+#[tokio::test]
+async fn generate_sysvar_validation_report() {
+    let results = vec![
+        run_allow_list_spoofed_system_program_test().await,
+        run_block_list_spoofed_system_program_test().await,
+        run_spoofed_rent_and_clock_sysvars_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "System Program and Sysvar Validation Test Results",
+        "../../tests/reports/sysvar_validation.md",
+    ) {
+        panic!("Failed to generate sysvar validation report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} sysvar validation tests failed", failed);
+}
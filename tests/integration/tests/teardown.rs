@@ -0,0 +1,483 @@
+//! Live-run teardown tests
+//!
+//! A devnet run hands every temporary account back to its faucet at the end.
+//! These tests run the teardown on a `solana-program-test` bank: each kind of
+//! account is closed and its lamports reach the faucet, `--keep-state` leaves
+//! everything in place, and an account that cannot be closed is reported as
+//! stranded without holding up the others.
+
+use solana_program_test::{BanksClient, ProgramTest, ProgramTestBanksClientExt};
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+use spl_token_2022::{
+    extension::StateWithExtensions,
+    instruction as token_instruction,
+    solana_program::program_pack::Pack,
+    state::{Account as TokenAccount, Mint},
+};
+
+use token_acl_integration_tests::{
+    common::nonce,
+    context::TestContext,
+    reporting,
+    submission::{SubmissionConfig, SubmissionEngine},
+    teardown::{Cleanup, Teardown, TemporaryAccount},
+    TestResultReport,
+};
+
+const WALLET_FUNDING: u64 = 2_000_000;
+const MINTED: u64 = 1_000;
+
+/// A bank with a Token-2022 mint whose authority is `issuer`
+struct Bank {
+    /// The test's bank, kept alive while its client is in use
+    context: TestContext,
+    client: BanksClient,
+    faucet: Keypair,
+    issuer: Keypair,
+    mint: Pubkey,
+}
+
+impl Bank {
+    async fn start(test_name: &str) -> Result<Self, String> {
+        let context = TestContext::start(test_name, ProgramTest::default()).await?;
+        let mut bank = Self {
+            client: context.client(),
+            faucet: context.payer(),
+            issuer: Keypair::new(),
+            mint: Pubkey::default(),
+            context,
+        };
+
+        let mint = Keypair::new();
+        bank.mint = mint.pubkey();
+        let instructions = [
+            system_instruction::create_account(
+                &bank.faucet.pubkey(),
+                &bank.mint,
+                Rent::default().minimum_balance(Mint::LEN),
+                Mint::LEN as u64,
+                &spl_token_2022::id(),
+            ),
+            token_instruction::initialize_mint2(
+                &spl_token_2022::id(),
+                &bank.mint,
+                &bank.issuer.pubkey(),
+                Some(&bank.issuer.pubkey()),
+                0,
+            )
+            .map_err(|e| e.to_string())?,
+        ];
+        bank.send(&instructions, &[&mint]).await?;
+        Ok(bank)
+    }
+
+    async fn send(&self, instructions: &[Instruction], signers: &[&Keypair]) -> Result<(), String> {
+        let mut client = self.client.clone();
+        let blockhash = client
+            .get_latest_blockhash()
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut all_signers = vec![&self.faucet];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.faucet.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        client
+            .process_transaction(transaction)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// A token account of `owner` holding `amount`, frozen if asked
+    async fn token_account(
+        &self,
+        owner: &Keypair,
+        amount: u64,
+        frozen: bool,
+    ) -> Result<Pubkey, String> {
+        let account = Keypair::new();
+        let program = spl_token_2022::id();
+        let mut instructions = vec![
+            system_instruction::create_account(
+                &self.faucet.pubkey(),
+                &account.pubkey(),
+                Rent::default().minimum_balance(TokenAccount::LEN),
+                TokenAccount::LEN as u64,
+                &program,
+            ),
+            token_instruction::initialize_account3(
+                &program,
+                &account.pubkey(),
+                &self.mint,
+                &owner.pubkey(),
+            )
+            .map_err(|e| e.to_string())?,
+            token_instruction::mint_to(
+                &program,
+                &self.mint,
+                &account.pubkey(),
+                &self.issuer.pubkey(),
+                &[],
+                amount,
+            )
+            .map_err(|e| e.to_string())?,
+        ];
+        if frozen {
+            instructions.push(
+                token_instruction::freeze_account(
+                    &program,
+                    &account.pubkey(),
+                    &self.mint,
+                    &self.issuer.pubkey(),
+                    &[],
+                )
+                .map_err(|e| e.to_string())?,
+            );
+        }
+        self.send(&instructions, &[&account, &self.issuer]).await?;
+        Ok(account.pubkey())
+    }
+
+    /// A system wallet funded by the faucet
+    async fn wallet(&self) -> Result<Keypair, String> {
+        let wallet = Keypair::new();
+        self.send(
+            &[system_instruction::transfer(
+                &self.faucet.pubkey(),
+                &wallet.pubkey(),
+                WALLET_FUNDING,
+            )],
+            &[],
+        )
+        .await?;
+        Ok(wallet)
+    }
+
+    /// Wait for the bank to move past its latest blockhash
+    ///
+    /// A nonce account can only be withdrawn from once the blockhash its
+    /// nonce was taken from is no longer the latest, and a teardown right
+    /// after creating it can run before the bank moves on.
+    async fn advance_blockhash(&self) -> Result<(), String> {
+        let mut client = self.client.clone();
+        let blockhash = client
+            .get_latest_blockhash()
+            .await
+            .map_err(|e| e.to_string())?;
+        client
+            .get_new_latest_blockhash(&blockhash)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn lamports(&self, address: &Pubkey) -> u64 {
+        self.client
+            .clone()
+            .get_balance(*address)
+            .await
+            .unwrap_or_default()
+    }
+
+    fn engine(&self) -> SubmissionEngine<BanksClient> {
+        SubmissionEngine::new(self.client.clone(), SubmissionConfig::default())
+    }
+}
+
+/// Every kind of account is closed and its lamports returned to the faucet
+#[tokio::test]
+async fn test_teardown_returns_accounts() {
+    let report = run_teardown_returns_accounts_test().await;
+    assert!(
+        report.passed,
+        "Teardown return test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_teardown_returns_accounts_test() -> TestResultReport {
+    let test_name = "Teardown: Accounts Returned";
+    let mut assertions = 0;
+
+    let bank = match Bank::start(test_name).await {
+        Ok(bank) => bank,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let owner = Keypair::new();
+    let nonce_account = Keypair::new();
+    let wallet = match bank.wallet().await {
+        Ok(wallet) => wallet,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let token_account = match bank.token_account(&owner, MINTED, false).await {
+        Ok(address) => address,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let create_nonce = nonce::create_nonce_account_instructions(
+        &bank.faucet.pubkey(),
+        &nonce_account.pubkey(),
+        &owner.pubkey(),
+    );
+    if let Err(e) = bank.send(&create_nonce, &[&nonce_account]).await {
+        return TestResultReport::failure(test_name, e);
+    }
+    if let Err(e) = bank.advance_blockhash().await {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    let mut teardown = Teardown::new(bank.faucet.pubkey()).with_keep_state(false);
+    teardown.register("wallet", TemporaryAccount::Wallet(wallet.insecure_clone()));
+    teardown.register(
+        "token account",
+        TemporaryAccount::TokenAccount {
+            address: token_account,
+            owner: owner.insecure_clone(),
+        },
+    );
+    teardown.register(
+        "nonce account",
+        TemporaryAccount::NonceAccount {
+            address: nonce_account.pubkey(),
+            authority: owner.insecure_clone(),
+        },
+    );
+    teardown.register("never funded", TemporaryAccount::Wallet(Keypair::new()));
+
+    let held = [
+        bank.lamports(&wallet.pubkey()).await,
+        bank.lamports(&token_account).await,
+        bank.lamports(&nonce_account.pubkey()).await,
+    ];
+    let faucet_before = bank.lamports(&bank.faucet.pubkey()).await;
+    let report = teardown.run(&mut bank.engine(), &bank.faucet).await;
+
+    // Assertion: each account was closed for what it held, the missing one
+    // skipped
+    assertions += 1;
+    let cleanups: Vec<&Cleanup> = report.accounts.iter().map(|(_, _, c)| c).collect();
+    let expected = [
+        Cleanup::Closed { lamports: held[0] },
+        Cleanup::Closed { lamports: held[1] },
+        Cleanup::Closed { lamports: held[2] },
+        Cleanup::Gone,
+    ];
+    if !cleanups.iter().copied().eq(expected.iter()) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Cleanups {:?}, expected {:?}", cleanups, expected),
+        );
+    }
+
+    // Assertion: the closed accounts no longer exist
+    assertions += 1;
+    for (label, address, _) in &report.accounts {
+        if bank.lamports(address).await != 0 {
+            return TestResultReport::failure(test_name, format!("{} still exists", label));
+        }
+    }
+
+    // Assertion: the faucet got everything back, less a two-signature fee
+    // per close
+    assertions += 1;
+    let faucet_after = bank.lamports(&bank.faucet.pubkey()).await;
+    let fees = 3 * 2 * bank.context.lamports_per_signature;
+    if faucet_after + fees != faucet_before + report.returned() || report.stranded() != 0 {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Faucet went from {} to {} after {} returned, {} stranded",
+                faucet_before,
+                faucet_after,
+                report.returned(),
+                report.stranded()
+            ),
+        );
+    }
+
+    // Assertion: the token account's balance was burned, not left in supply
+    assertions += 1;
+    let supply = match bank.client.clone().get_account(bank.mint).await {
+        Ok(Some(account)) => StateWithExtensions::<Mint>::unpack(&account.data)
+            .map(|mint| mint.base.supply)
+            .map_err(|e| e.to_string()),
+        other => Err(format!("Mint not found: {:?}", other)),
+    };
+    if supply != Ok(0) {
+        return TestResultReport::failure(test_name, format!("Supply after burn {:?}", supply));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// `--keep-state` leaves every account in place
+#[tokio::test]
+async fn test_teardown_keep_state() {
+    let report = run_teardown_keep_state_test().await;
+    assert!(
+        report.passed,
+        "Teardown keep-state test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_teardown_keep_state_test() -> TestResultReport {
+    let test_name = "Teardown: Keep State";
+    let mut assertions = 0;
+
+    let bank = match Bank::start(test_name).await {
+        Ok(bank) => bank,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let wallet = match bank.wallet().await {
+        Ok(wallet) => wallet,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let mut teardown = Teardown::new(bank.faucet.pubkey()).with_keep_state(true);
+    teardown.register("wallet", TemporaryAccount::Wallet(wallet.insecure_clone()));
+    let report = teardown.run(&mut bank.engine(), &bank.faucet).await;
+
+    // Assertion: the wallet is reported kept and still holds its funding
+    assertions += 1;
+    let kept = Cleanup::Kept {
+        lamports: WALLET_FUNDING,
+    };
+    if report.accounts.first().map(|(_, _, c)| c) != Some(&kept)
+        || bank.lamports(&wallet.pubkey()).await != WALLET_FUNDING
+    {
+        return TestResultReport::failure(test_name, format!("Report {:?}", report));
+    }
+
+    // Assertion: the report says why nothing was returned
+    assertions += 1;
+    let markdown = report.render_markdown();
+    if !report.kept_state
+        || report.returned() != 0
+        || !markdown.contains("`--keep-state` left 1 accounts")
+    {
+        return TestResultReport::failure(test_name, format!("Section:\n{}", markdown));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Accounts that cannot be closed are reported as stranded, the rest closed
+#[tokio::test]
+async fn test_teardown_failures() {
+    let report = run_teardown_failures_test().await;
+    assert!(
+        report.passed,
+        "Teardown failure test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_teardown_failures_test() -> TestResultReport {
+    let test_name = "Teardown: Stranded Accounts";
+    let mut assertions = 0;
+
+    let bank = match Bank::start(test_name).await {
+        Ok(bank) => bank,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let owner = Keypair::new();
+    let (frozen, wallet) = match (
+        bank.token_account(&owner, MINTED, true).await,
+        bank.wallet().await,
+    ) {
+        (Ok(frozen), Ok(wallet)) => (frozen, wallet),
+        (Err(e), _) | (_, Err(e)) => return TestResultReport::failure(test_name, e),
+    };
+
+    let mut teardown = Teardown::new(bank.faucet.pubkey()).with_keep_state(false);
+    teardown.register(
+        "frozen token account",
+        TemporaryAccount::TokenAccount {
+            address: frozen,
+            owner: owner.insecure_clone(),
+        },
+    );
+    // A wallet registered as a token account cannot be unpacked
+    teardown.register(
+        "mislabeled wallet",
+        TemporaryAccount::TokenAccount {
+            address: wallet.pubkey(),
+            owner: wallet.insecure_clone(),
+        },
+    );
+    let sweepable = match bank.wallet().await {
+        Ok(wallet) => wallet,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    teardown.register("wallet", TemporaryAccount::Wallet(sweepable));
+    let report = teardown.run(&mut bank.engine(), &bank.faucet).await;
+
+    // Assertion: a frozen account cannot be burned and keeps its rent
+    assertions += 1;
+    let rent = Rent::default().minimum_balance(TokenAccount::LEN);
+    match &report.accounts[0].2 {
+        Cleanup::Failed { lamports, .. } if *lamports == rent => {}
+        other => return TestResultReport::failure(test_name, format!("Frozen: {:?}", other)),
+    }
+
+    // Assertion: an account of the wrong kind fails before anything is sent
+    assertions += 1;
+    match &report.accounts[1].2 {
+        Cleanup::Failed { reason, .. } if reason.starts_with("Not a token account") => {}
+        other => return TestResultReport::failure(test_name, format!("Mislabeled: {:?}", other)),
+    }
+
+    // Assertion: the failures did not stop the wallet from being swept
+    assertions += 1;
+    if report.accounts[2].2
+        != (Cleanup::Closed {
+            lamports: WALLET_FUNDING,
+        })
+    {
+        return TestResultReport::failure(test_name, format!("Wallet: {:?}", report.accounts[2]));
+    }
+
+    // Assertion: the stranded lamports are totalled and listed
+    assertions += 1;
+    let markdown = report.render_markdown();
+    if report.failures().len() != 2
+        || report.stranded() != rent + WALLET_FUNDING
+        || !markdown.contains("2 failed")
+        || bank.lamports(&frozen).await != rent
+    {
+        return TestResultReport::failure(test_name, format!("Section:\n{}", markdown));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate the teardown test report
+#[tokio::test]
+async fn generate_teardown_report() {
+    let results = vec![
+        run_teardown_returns_accounts_test().await,
+        run_teardown_keep_state_test().await,
+        run_teardown_failures_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Teardown Test Results",
+        "../../tests/reports/teardown.md",
+    ) {
+        panic!("Failed to generate teardown report: {}", e);
+    }
+
+    for result in &results {
+        assert!(result.passed, "{} failed: {:?}", result.name, result.error);
+    }
+}
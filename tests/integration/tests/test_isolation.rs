@@ -0,0 +1,217 @@
+//! Test isolation tests
+//!
+//! Stateful tests only stay independent if nothing they write is shared.
+//! These tests pin the three places that could leak between tests: the bank
+//! each test starts, the keys a test derives on a shared validator, and the
+//! logger its error helpers write to.
+
+use solana_program_test::ProgramTest;
+use solana_sdk::{
+    rent::Rent,
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+
+use token_acl_integration_tests::{
+    context::{Namespace, TestContext},
+    logging::{self, error_handling, LogLevel, TestError},
+    reporting, TestResultReport,
+};
+
+const TRANSFER: u64 = 5_000_000;
+
+/// Every test's bank starts from genesis, whatever other tests did
+#[tokio::test]
+async fn test_fresh_contexts() {
+    let report = run_fresh_contexts_test().await;
+    assert!(
+        report.passed,
+        "Fresh context test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_fresh_contexts_test() -> TestResultReport {
+    let test_name = "Isolation: Fresh Contexts";
+    let mut assertions = 0;
+
+    let (mut first, second) = match (
+        TestContext::start(test_name, ProgramTest::default()).await,
+        TestContext::start(test_name, ProgramTest::default()).await,
+    ) {
+        (Ok(first), Ok(second)) => (first, second),
+        (Err(e), _) | (_, Err(e)) => return TestResultReport::failure(test_name, e),
+    };
+    let recipient = first.namespace().address("recipient");
+    let payer = first.payer();
+    let payer_before = first
+        .banks_client
+        .get_balance(payer.pubkey())
+        .await
+        .unwrap_or_default();
+    let transfer = Transaction::new_signed_with_payer(
+        &[system_instruction::transfer(
+            &payer.pubkey(),
+            &recipient,
+            TRANSFER,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        first.last_blockhash,
+    );
+    if let Err(e) = first.banks_client.process_transaction(transfer).await {
+        return TestResultReport::failure(test_name, e.to_string());
+    }
+
+    // Assertion: the context priced the transfer's one signature
+    assertions += 1;
+    let payer_after = first
+        .banks_client
+        .get_balance(payer.pubkey())
+        .await
+        .unwrap_or_default();
+    if first.lamports_per_signature == 0 || payer_before - payer_after != TRANSFER + first.fee(1) {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Payer spent {} at {} lamports per signature",
+                payer_before - payer_after,
+                first.lamports_per_signature
+            ),
+        );
+    }
+
+    // Assertion: a second context never sees what the first wrote
+    assertions += 1;
+    let seen = second.client().get_account(recipient).await;
+    if !matches!(seen, Ok(None)) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Second context sees the transfer: {:?}", seen),
+        );
+    }
+
+    // Assertion: the rent is the bank's
+    assertions += 1;
+    if first.rent != Rent::default() || first.test_name() != test_name {
+        return TestResultReport::failure(test_name, format!("Rent {:?}", first.rent));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Keys on a shared validator are private to a test and a run
+#[test]
+fn test_namespaces() {
+    let report = run_namespaces_test();
+    assert!(report.passed, "Namespace test failed: {:?}", report.error);
+}
+
+fn run_namespaces_test() -> TestResultReport {
+    let test_name = "Isolation: Namespaces";
+    let mut assertions = 0;
+
+    let namespace = Namespace::new("Devnet Thaw");
+
+    // Assertion: a label names the same key every time, and a signer for it
+    assertions += 1;
+    if namespace.address("mint") != namespace.address("mint")
+        || namespace.keypair("mint").pubkey() != namespace.address("mint")
+        || namespace.address("mint") == namespace.address("issuer")
+    {
+        return TestResultReport::failure(test_name, "Labels are not stable".to_string());
+    }
+
+    // Assertion: other tests and other runs of the same test get other keys
+    assertions += 1;
+    let others = [
+        Namespace::new("Devnet Freeze").address("mint"),
+        Namespace::new("Devnet Thaw").address("mint"),
+    ];
+    if others.contains(&namespace.address("mint")) || others[0] == others[1] {
+        return TestResultReport::failure(test_name, "Namespaces share keys".to_string());
+    }
+
+    // Assertion: a run seed replays the same keys
+    assertions += 1;
+    let run = Keypair::new().pubkey().to_bytes();
+    let replayed = Namespace::with_run_seed("Devnet Thaw", &run);
+    let other_test = Namespace::with_run_seed("Devnet Freeze", &run);
+    if replayed.address("mint") != Namespace::with_run_seed("Devnet Thaw", &run).address("mint")
+        || replayed.address("mint") == other_test.address("mint")
+    {
+        return TestResultReport::failure(test_name, "Run seed did not replay".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Log entries stay with the test that wrote them
+#[test]
+fn test_logger_isolation() {
+    let report = run_logger_isolation_test();
+    assert!(
+        report.passed,
+        "Logger isolation test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_logger_isolation_test() -> TestResultReport {
+    let test_name = "Isolation: Logger";
+    let mut assertions = 0;
+
+    logging::init_logger(LogLevel::Info);
+    logging::with_logger(|logger| logger.info("isolation", "this test"));
+
+    // A test running on another thread fails and logs its error
+    let other = std::thread::spawn(|| {
+        error_handling::handle_test_error(TestError::SetupError("other test".to_string()), "other");
+        logging::with_logger(|logger| logger.entries().len())
+    })
+    .join();
+
+    // Assertion: the other test logged to a logger of its own
+    assertions += 1;
+    if !matches!(other, Ok(1)) {
+        return TestResultReport::failure(test_name, format!("Other test logged {:?}", other));
+    }
+
+    // Assertion: this test's logger holds only its own entry
+    assertions += 1;
+    let modules: Vec<String> = logging::with_logger(|logger| {
+        logger
+            .entries()
+            .iter()
+            .map(|entry| entry.module.clone())
+            .collect()
+    });
+    if modules != vec!["isolation".to_string()] {
+        return TestResultReport::failure(test_name, format!("Logged by {:?}", modules));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate the test isolation report
+#[tokio::test]
+async fn generate_test_isolation_report() {
+    let results = vec![
+        run_fresh_contexts_test().await,
+        run_namespaces_test(),
+        run_logger_isolation_test(),
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Test Isolation Results",
+        "../../tests/reports/test_isolation.md",
+    ) {
+        panic!("Failed to generate test isolation report: {}", e);
+    }
+
+    for result in &results {
+        assert!(result.passed, "{} failed: {:?}", result.name, result.error);
+    }
+}
@@ -0,0 +1,197 @@
+//! Time handling tests
+//!
+//! These tests check the UTC formatting and RFC 3339 parsing used for report
+//! timestamps and on-chain times, and that the mock clock pins report output.
+
+use token_acl_integration_tests::{reporting, time, TestResultReport};
+
+/// Test 1: Unix timestamps format as UTC
+#[tokio::test]
+async fn test_utc_formatting() {
+    let report = run_utc_formatting_test();
+    assert!(
+        report.passed,
+        "UTC formatting test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_utc_formatting_test() -> TestResultReport {
+    let test_name = "UTC Formatting";
+    let mut assertions = 0;
+
+    // Assertion 1: Known timestamps, including a leap day and pre-epoch times
+    assertions += 1;
+    let cases = [
+        (0, "1970-01-01 00:00:00 UTC"),
+        (1_729_071_000, "2024-10-16 09:30:00 UTC"),
+        (951_782_399, "2000-02-28 23:59:59 UTC"),
+        (951_782_400, "2000-02-29 00:00:00 UTC"),
+        (-1, "1969-12-31 23:59:59 UTC"),
+        (-2_208_988_800, "1900-01-01 00:00:00 UTC"),
+    ];
+    for (timestamp, expected) in cases {
+        let formatted = time::format_utc(timestamp);
+        if formatted != expected {
+            return TestResultReport::failure(
+                test_name,
+                format!("{} formatted as '{}'", timestamp, formatted),
+            );
+        }
+    }
+
+    // Assertion 2: RFC 3339 output uses the `Z` suffix
+    assertions += 1;
+    if time::format_rfc3339(1_729_071_000) != "2024-10-16T09:30:00Z" {
+        return TestResultReport::failure(
+            test_name,
+            format!("RFC 3339 was '{}'", time::format_rfc3339(1_729_071_000)),
+        );
+    }
+
+    // Assertion 3: Missing block times are reported as unknown
+    assertions += 1;
+    if time::format_block_time(None) != "unknown"
+        || time::format_block_time(Some(0)) != "1970-01-01 00:00:00 UTC"
+    {
+        return TestResultReport::failure(test_name, "Block time formatting is wrong".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: RFC 3339 parsing normalizes offsets to UTC
+#[tokio::test]
+async fn test_rfc3339_parsing() {
+    let report = run_rfc3339_parsing_test();
+    assert!(
+        report.passed,
+        "RFC 3339 parsing test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_rfc3339_parsing_test() -> TestResultReport {
+    let test_name = "RFC 3339 Parsing";
+    let mut assertions = 0;
+
+    // Assertion 1: Formatting round-trips
+    assertions += 1;
+    for timestamp in [0, -86_401, 951_782_400, 1_729_071_000, 4_102_444_800] {
+        let parsed = time::parse_rfc3339(&time::format_rfc3339(timestamp));
+        if parsed != Ok(timestamp) {
+            return TestResultReport::failure(
+                test_name,
+                format!("{} round-tripped to {:?}", timestamp, parsed),
+            );
+        }
+    }
+
+    // Assertion 2: Offsets and fractions normalize to the same instant
+    assertions += 1;
+    for text in [
+        "2024-10-16T09:30:00Z",
+        "2024-10-16T11:30:00+02:00",
+        "2024-10-15T23:30:00-10:00",
+        "2024-10-16t09:30:00.999z",
+        "2024-10-16 15:00:00+05:30",
+    ] {
+        if time::parse_rfc3339(text) != Ok(1_729_071_000) {
+            return TestResultReport::failure(
+                test_name,
+                format!("'{}' parsed as {:?}", text, time::parse_rfc3339(text)),
+            );
+        }
+    }
+
+    // Assertion 3: Malformed and impossible timestamps are rejected
+    assertions += 1;
+    for text in [
+        "",
+        "2024-10-16",
+        "2024-10-16T09:30:00",
+        "2024-10-16T09:30:00+0200",
+        "2024-13-01T00:00:00Z",
+        "2023-02-29T00:00:00Z",
+        "2024-10-16T24:00:00Z",
+        "2024-10-16T09:30:00.Z",
+        "2024-10-16T09:30:00*02:00",
+        "+024-10-16T09:30:00Z",
+        "2024-10-16T09:30:00+é:00",
+    ] {
+        if time::parse_rfc3339(text).is_ok() {
+            return TestResultReport::failure(test_name, format!("Accepted '{}'", text));
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: The mock clock pins report timestamps
+#[tokio::test]
+async fn test_mock_clock() {
+    let report = run_mock_clock_test();
+    assert!(report.passed, "Mock clock test failed: {:?}", report.error);
+}
+
+fn run_mock_clock_test() -> TestResultReport {
+    let test_name = "Mock Clock";
+    let mut assertions = 0;
+
+    // Assertion 1: Mocking pins `now` and the report timestamp
+    assertions += 1;
+    {
+        let _clock = time::mock_now(1_729_071_000);
+        if time::now() != 1_729_071_000 || time::report_timestamp() != "2024-10-16 09:30:00 UTC" {
+            return TestResultReport::failure(test_name, "Mock was not applied".to_string());
+        }
+
+        // Assertion 2: Nested mocks restore the outer one when dropped
+        assertions += 1;
+        {
+            let _inner = time::mock_now(0);
+            if time::now() != 0 {
+                return TestResultReport::failure(test_name, "Inner mock ignored".to_string());
+            }
+        }
+        if time::now() != 1_729_071_000 {
+            return TestResultReport::failure(test_name, "Outer mock not restored".to_string());
+        }
+
+        // Assertion 3: Other threads keep the real clock
+        assertions += 1;
+        let other = std::thread::spawn(time::now).join().unwrap_or_default();
+        if other < 1_729_071_000 + 1 {
+            return TestResultReport::failure(test_name, format!("Other thread saw {}", other));
+        }
+    }
+
+    // Assertion 4: Dropping the last guard restores the real clock
+    assertions += 1;
+    if time::now() <= 1_729_071_000 {
+        return TestResultReport::failure(test_name, "Real clock not restored".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate time handling test report
+#[tokio::test]
+async fn generate_time_handling_report() {
+    let results = vec![
+        run_utc_formatting_test(),
+        run_rfc3339_parsing_test(),
+        run_mock_clock_test(),
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Time Handling Test Results",
+        "../../tests/reports/time_handling.md",
+    ) {
+        panic!("Failed to generate time handling report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} time handling tests failed", failed);
+}
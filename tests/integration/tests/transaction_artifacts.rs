@@ -0,0 +1,296 @@
+//! Transaction artifact tests
+//!
+//! These tests check that the signatures and slots of submitted transactions
+//! are recorded per test, and that the reports carry them with working
+//! explorer deep links.
+
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_program_test::ProgramTest;
+use solana_sdk::{
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+use std::fs;
+
+use token_acl_integration_tests::{
+    explorer::{self, Cluster, TransactionRecorder},
+    reporting,
+    rpc::{RateLimitedRpc, RpcLimits},
+    TestResultReport, TransactionRecord,
+};
+
+const MARKDOWN_PATH: &str = "../../tests/reports/transaction_artifacts_sample.md";
+const HTML_PATH: &str = "../../tests/reports/transaction_artifacts_sample.html";
+
+fn transfer(from: &Keypair, to: &Pubkey, lamports: u64, blockhash: Hash) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[system_instruction::transfer(&from.pubkey(), to, lamports)],
+        Some(&from.pubkey()),
+        &[from],
+        blockhash,
+    )
+}
+
+/// Test 1: Explorer links target the right cluster
+#[tokio::test]
+async fn test_explorer_links() {
+    let report = run_explorer_links_test();
+    assert!(
+        report.passed,
+        "Explorer links test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_explorer_links_test() -> TestResultReport {
+    let test_name = "Explorer Deep Links";
+    let mut assertions = 0;
+
+    let signature = Signature::from([7; 64]);
+    let address = Pubkey::new_unique();
+
+    // Assertion 1: Public clusters are recognised from their RPC URLs
+    assertions += 1;
+    let recognised = [
+        ("https://api.mainnet-beta.solana.com", Cluster::MainnetBeta),
+        ("https://api.devnet.solana.com", Cluster::Devnet),
+        ("https://api.testnet.solana.com", Cluster::Testnet),
+    ];
+    for (url, cluster) in recognised {
+        if Cluster::from_rpc_url(url) != cluster {
+            return TestResultReport::failure(test_name, format!("{} not recognised", url));
+        }
+    }
+
+    // Assertion 2: Devnet links carry the cluster parameter
+    assertions += 1;
+    let expected = format!(
+        "https://explorer.solana.com/tx/{}?cluster=devnet",
+        signature
+    );
+    if explorer::transaction_url(&signature, &Cluster::Devnet) != expected {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Devnet link was {}",
+                explorer::transaction_url(&signature, &Cluster::Devnet)
+            ),
+        );
+    }
+
+    // Assertion 3: Mainnet links carry no parameter
+    assertions += 1;
+    if explorer::account_url(&address, &Cluster::MainnetBeta)
+        != format!("https://explorer.solana.com/address/{}", address)
+    {
+        return TestResultReport::failure(test_name, "Mainnet link has a cluster".to_string());
+    }
+
+    // Assertion 4: Custom endpoints are percent-encoded
+    assertions += 1;
+    let local = Cluster::from_rpc_url("http://127.0.0.1:8899");
+    let expected = "https://explorer.solana.com/block/42?cluster=custom&customUrl=http%3A%2F%2F127.0.0.1%3A8899";
+    if explorer::block_url(42, &local) != expected {
+        return TestResultReport::failure(
+            test_name,
+            format!("Local link was {}", explorer::block_url(42, &local)),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: Every submitted transaction is recorded with its slot
+#[tokio::test]
+async fn test_transaction_recording() {
+    let report = run_transaction_recording_test().await;
+    assert!(
+        report.passed,
+        "Transaction recording test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_transaction_recording_test() -> TestResultReport {
+    let test_name = "Transaction Recording";
+    let mut assertions = 0;
+
+    let (mut banks_client, payer, blockhash) = ProgramTest::default().start().await;
+    let mut recorder = TransactionRecorder::new();
+
+    let funded = transfer(&payer, &Pubkey::new_unique(), 1_000_000, blockhash);
+    let overdraft = transfer(&payer, &Pubkey::new_unique(), u64::MAX / 2, blockhash);
+    let expected = [funded.signatures[0], overdraft.signatures[0]];
+
+    // Assertion 1: The funded transfer lands
+    assertions += 1;
+    if let Err(e) = recorder
+        .process(&mut banks_client, "fund recipient", funded)
+        .await
+    {
+        return TestResultReport::failure(test_name, format!("Transfer failed: {}", e));
+    }
+
+    // Assertion 2: The failing transfer is recorded too
+    assertions += 1;
+    if recorder
+        .process(&mut banks_client, "overdraft", overdraft)
+        .await
+        .is_ok()
+    {
+        return TestResultReport::failure(test_name, "Overdraft succeeded".to_string());
+    }
+    let records = recorder.records();
+    if records.len() != 2
+        || records[0].signature != expected[0]
+        || records[1].signature != expected[1]
+        || records[1].label != "overdraft"
+    {
+        return TestResultReport::failure(test_name, format!("Unexpected records: {:?}", records));
+    }
+
+    // Assertion 3: Slots were captured
+    assertions += 1;
+    if records.iter().any(|record| record.slot.is_none()) {
+        return TestResultReport::failure(test_name, "A slot is missing".to_string());
+    }
+
+    // Assertion 4: Live sends record the signature and resolve the slot later
+    assertions += 1;
+    let rpc = RateLimitedRpc::from_client(
+        RpcClient::new_mock("succeeds".to_string()),
+        RpcLimits::devnet(),
+    );
+    let live = transfer(&payer, &Pubkey::new_unique(), 1, Hash::new_unique());
+    let mut live_recorder = TransactionRecorder::new();
+    match live_recorder.send(&rpc, "live transfer", &live).await {
+        Ok(signature) if signature == live.signatures[0] => {}
+        other => {
+            return TestResultReport::failure(test_name, format!("Live send: {:?}", other));
+        }
+    }
+    let pending = live_recorder.records()[0].slot;
+    if let Err(e) = live_recorder.resolve_slots(&rpc).await {
+        return TestResultReport::failure(test_name, format!("Slot lookup failed: {}", e));
+    }
+    if pending.is_some() || live_recorder.records()[0].slot != Some(1) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Live slot was {:?}", live_recorder.records()[0].slot),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions).with_transactions(recorder.into_records())
+}
+
+/// Test 3: Reports list the recorded transactions with explorer links
+#[tokio::test]
+async fn test_report_artifacts() {
+    let report = run_report_artifacts_test();
+    assert!(
+        report.passed,
+        "Report artifacts test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_report_artifacts_test() -> TestResultReport {
+    let test_name = "Report Transaction Artifacts";
+    let mut assertions = 0;
+
+    let landed = Signature::from([1; 64]);
+    let failed = Signature::from([2; 64]);
+    let results = vec![
+        TestResultReport::success("Add <holder>", 1).with_transactions(vec![TransactionRecord {
+            label: "add & thaw".to_string(),
+            signature: landed,
+            slot: Some(1234),
+            trace: None,
+        }]),
+        TestResultReport::failure("Remove holder", "Gate denied".to_string()).with_transactions(
+            vec![TransactionRecord {
+                label: "remove".to_string(),
+                signature: failed,
+                slot: None,
+                trace: None,
+            }],
+        ),
+    ];
+
+    // Assertion 1: Markdown lists signatures and slots
+    assertions += 1;
+    if let Err(e) = reporting::generate_test_report(&results, "Sample", MARKDOWN_PATH) {
+        return TestResultReport::failure(test_name, format!("Markdown failed: {}", e));
+    }
+    let markdown = fs::read_to_string(MARKDOWN_PATH).unwrap_or_default();
+    if !markdown.contains(&format!("add & thaw: `{}` (slot 1234)", landed))
+        || !markdown.contains(&format!("remove: `{}` (slot unknown)", failed))
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Markdown is missing transactions".to_string(),
+        );
+    }
+
+    // Assertion 2: HTML links each transaction and its block
+    assertions += 1;
+    if let Err(e) = reporting::generate_html_report(&results, "Sample", HTML_PATH, &Cluster::Devnet)
+    {
+        return TestResultReport::failure(test_name, format!("HTML failed: {}", e));
+    }
+    let html = fs::read_to_string(HTML_PATH).unwrap_or_default();
+    let links = [
+        explorer::transaction_url(&landed, &Cluster::Devnet),
+        explorer::block_url(1234, &Cluster::Devnet),
+        explorer::transaction_url(&failed, &Cluster::Devnet),
+    ];
+    if let Some(missing) = links
+        .iter()
+        .find(|link| !html.contains(&format!("href=\"{}\"", link)))
+    {
+        return TestResultReport::failure(test_name, format!("HTML is missing {}", missing));
+    }
+
+    // Assertion 3: Names and labels are escaped
+    assertions += 1;
+    if !html.contains("Add &lt;holder&gt;") || !html.contains("add &amp; thaw") {
+        return TestResultReport::failure(test_name, "HTML was not escaped".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate transaction artifacts report
+#[tokio::test]
+async fn generate_transaction_artifacts_report() {
+    let results = vec![
+        run_explorer_links_test(),
+        run_transaction_recording_test().await,
+        run_report_artifacts_test(),
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Transaction Artifacts Test Results",
+        "../../tests/reports/transaction_artifacts.md",
+    ) {
+        panic!("Failed to generate transaction artifacts report: {}", e);
+    }
+    if let Err(e) = reporting::generate_html_report(
+        &results,
+        "Transaction Artifacts Test Results",
+        "../../tests/reports/transaction_artifacts.html",
+        &Cluster::Custom("http://127.0.0.1:8899".to_string()),
+    ) {
+        panic!(
+            "Failed to generate transaction artifacts HTML report: {}",
+            e
+        );
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} transaction artifacts tests failed", failed);
+}
@@ -0,0 +1,986 @@
+//! Transaction decoder tests
+//!
+//! Every instruction of this repository's gates and consumer decodes to its
+//! name and account roles, and so do the Token-2022, system, compute budget
+//! and associated token account instructions that travel with them.
+//! Permissionless checks executed on a bank decode with the gate's
+//! decision, the same whether the transaction comes from a recording or,
+//! as a v0 transaction with a lookup table, from `getTransaction`, and the
+//! trace printer shows the decoded names and roles.
+
+use solana_sdk::{
+    address_lookup_table::AddressLookupTableAccount,
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    message::{
+        v0::{self, LoadedAddresses},
+        VersionedMessage,
+    },
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    system_instruction,
+    transaction::{Transaction, VersionedTransaction},
+};
+use solana_transaction_status::{
+    ConfirmedTransactionWithStatusMeta, TransactionStatusMeta, TransactionWithStatusMeta,
+    UiTransactionEncoding, VersionedTransactionWithStatusMeta,
+};
+use spl_token_2022::{instruction as token_instruction, state::AccountState};
+
+use token_acl_integration_tests::{
+    decoder::{DecodedInstruction, DecodedTransaction, Decoder, InstructionCategory, ProgramKind},
+    program_test::{Gate, GateHarness},
+    reporting,
+    trace::{self, TransactionTrace},
+    TestResultReport,
+};
+
+/// Check that `instruction` is `name` with the account playing `role` at
+/// `address`
+fn expect_role(
+    instruction: &DecodedInstruction,
+    name: &str,
+    role: &str,
+    address: &Pubkey,
+) -> Result<(), String> {
+    if instruction.instruction != name {
+        return Err(format!("Decoded {} as {}", name, instruction.instruction));
+    }
+    match instruction
+        .accounts
+        .iter()
+        .find(|account| account.role == role)
+    {
+        Some(account) if account.address == *address => Ok(()),
+        Some(account) => Err(format!("{} of {} is {}", role, name, account.address)),
+        None => Err(format!(
+            "{} has no {}: {:?}",
+            name, role, instruction.accounts
+        )),
+    }
+}
+
+/// Decode `instructions` as a transaction that was never executed
+fn decode(decoder: &Decoder, instructions: &[Instruction]) -> DecodedTransaction {
+    let transaction = Transaction::new_with_payer(instructions, Some(&Pubkey::new_unique()));
+    decoder.decode_transaction(&transaction, &Ok(()), &[])
+}
+
+/// Test 1: Every admin instruction of the repository's programs decodes to
+/// its name, with a role for each account
+#[test]
+fn test_program_instructions_decoded() {
+    let report = run_program_instructions_decoded_test();
+    assert!(
+        report.passed,
+        "Program instructions decoded test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_program_instructions_decoded_test() -> TestResultReport {
+    let test_name = "Decoder: Program Instructions";
+    let mut assertions = 0;
+
+    let kinds = [
+        ProgramKind::AllowList,
+        ProgramKind::BlockList,
+        ProgramKind::ProductionAllowList,
+        ProgramKind::HybridGate,
+        ProgramKind::JurisdictionRegistry,
+        ProgramKind::ThresholdGate,
+        ProgramKind::VelocityGate,
+        ProgramKind::GeoGate,
+        ProgramKind::NftGate,
+        ProgramKind::LendingConsumer,
+    ];
+    let mut decoder = Decoder::new();
+    let program_ids: Vec<Pubkey> = kinds.iter().map(|_| Pubkey::new_unique()).collect();
+    for (kind, program_id) in kinds.iter().zip(&program_ids) {
+        decoder.register(*program_id, *kind);
+    }
+    let program_id =
+        |kind: ProgramKind| program_ids[kinds.iter().position(|k| *k == kind).unwrap()];
+
+    // Assertion 1: Instructions built by the programs' own builders name
+    // their accounts by role
+    let payer = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let borrower = Pubkey::new_unique();
+    let authority = Pubkey::new_unique();
+    let allow_list = program_id(ProgramKind::AllowList);
+    let block_list = program_id(ProgramKind::BlockList);
+    let lending = program_id(ProgramKind::LendingConsumer);
+    let market = example_lending_consumer::Market {
+        mint,
+        allow_list_program: allow_list,
+        block_list_program: block_list,
+        bump: 0,
+    };
+    let built = decode(
+        &decoder,
+        &[
+            example_allow_list::initialize_thaw_extra_account_metas(&allow_list, &payer, &mint),
+            example_block_list::initialize_freeze_extra_account_metas(&block_list, &payer, &mint),
+            example_block_list::initialize_thaw_extra_account_metas(&block_list, &payer, &mint),
+            example_block_list::initialize(&block_list, &mint, &authority, &payer),
+            example_block_list::block_user(
+                &block_list,
+                &mint,
+                &borrower,
+                &authority,
+                &payer,
+                example_block_list::BlockReason::Sanctions,
+                Some(1_700_000_000),
+            ),
+            example_block_list::unblock_user(&block_list, &mint, &borrower, &authority),
+            example_block_list::update_authority(&block_list, &mint, &authority, &borrower, &payer),
+            example_block_list::accept_authority(&block_list, &mint, &borrower, &payer),
+            example_block_list::cancel_authority_transfer(&block_list, &mint, &authority, &payer),
+            example_lending_consumer::initialize_market(
+                &lending,
+                &payer,
+                &mint,
+                &allow_list,
+                &block_list,
+            ),
+            example_lending_consumer::check_borrower(&lending, &market, &borrower),
+        ],
+    );
+    let expected = [
+        ("initialize_thaw_extra_account_metas", "mint", mint),
+        ("initialize_freeze_extra_account_metas", "payer", payer),
+        ("initialize_thaw_extra_account_metas", "mint", mint),
+        ("initialize", "authority", authority),
+        ("block_user", "user", borrower),
+        (
+            "unblock_user",
+            "block_list_record",
+            gate_view::block_list_address(&block_list, &mint, &borrower).address(),
+        ),
+        ("update_authority", "new_authority", borrower),
+        (
+            "accept_authority",
+            "pending_authority",
+            example_block_list::pending_authority_address(&mint, &block_list).address(),
+        ),
+        ("cancel_authority_transfer", "authority", authority),
+        ("initialize_market", "payer", payer),
+        (
+            "check_borrower",
+            "block_list_record",
+            gate_view::block_list_address(&block_list, &mint, &borrower).address(),
+        ),
+    ];
+    for (instruction, (name, role, address)) in built.instructions.iter().zip(expected) {
+        assertions += 1;
+        if let Err(e) = expect_role(instruction, name, role, &address) {
+            return TestResultReport::failure(test_name, e);
+        }
+    }
+
+    // Assertion 2: Every other admin instruction decodes by name, with a
+    // role for each account it takes
+    let cases: [(ProgramKind, Vec<u8>, &str, usize); 23] = [
+        (
+            ProgramKind::BlockList,
+            vec![example_block_list::UPDATE_FREEZE_EXTRA_ACCOUNT_METAS, 1],
+            "update_freeze_extra_account_metas",
+            5,
+        ),
+        (
+            ProgramKind::BlockList,
+            vec![example_block_list::SET_MISSING_RECORD_POLICY, 0],
+            "set_missing_record_policy",
+            5,
+        ),
+        (
+            ProgramKind::ProductionAllowList,
+            vec![production_allow_list::INITIALIZE],
+            "initialize",
+            5,
+        ),
+        (
+            ProgramKind::ProductionAllowList,
+            vec![production_allow_list::ADD_TO_ALLOW_LIST],
+            "add_to_allow_list",
+            7,
+        ),
+        (
+            ProgramKind::ProductionAllowList,
+            vec![production_allow_list::REMOVE_FROM_ALLOW_LIST, 1],
+            "remove_from_allow_list",
+            4,
+        ),
+        (
+            ProgramKind::ProductionAllowList,
+            vec![production_allow_list::UPDATE_AUTHORITY],
+            "update_authority",
+            6,
+        ),
+        (
+            ProgramKind::ProductionAllowList,
+            vec![production_allow_list::REVOKE],
+            "revoke",
+            3,
+        ),
+        (
+            ProgramKind::ProductionAllowList,
+            vec![production_allow_list::LIFT_REVOCATION],
+            "lift_revocation",
+            3,
+        ),
+        (
+            ProgramKind::ProductionAllowList,
+            vec![production_allow_list::SET_MISSING_RECORD_POLICY, 1],
+            "set_missing_record_policy",
+            5,
+        ),
+        (
+            ProgramKind::ProductionAllowList,
+            [vec![production_allow_list::SET_SNAPSHOT_ROOT], vec![0; 32]].concat(),
+            "set_snapshot_root",
+            5,
+        ),
+        (
+            ProgramKind::ProductionAllowList,
+            vec![production_allow_list::MATERIALIZE_FROM_SNAPSHOT, 0],
+            "materialize_from_snapshot",
+            5,
+        ),
+        (
+            ProgramKind::ProductionAllowList,
+            vec![production_allow_list::CLOSE_RECORD],
+            "close_record",
+            4,
+        ),
+        (
+            ProgramKind::ProductionAllowList,
+            vec![production_allow_list::ACCEPT_AUTHORITY],
+            "accept_authority",
+            4,
+        ),
+        (
+            ProgramKind::ProductionAllowList,
+            vec![production_allow_list::CANCEL_AUTHORITY_TRANSFER],
+            "cancel_authority_transfer",
+            4,
+        ),
+        (
+            ProgramKind::ProductionAllowList,
+            vec![production_allow_list::INITIALIZE_THAW_EXTRA_ACCOUNT_METAS],
+            "initialize_thaw_extra_account_metas",
+            4,
+        ),
+        (
+            ProgramKind::HybridGate,
+            vec![hybrid_gate::INITIALIZE, 0],
+            "initialize",
+            5,
+        ),
+        (
+            ProgramKind::HybridGate,
+            vec![hybrid_gate::SET_COOLING_OFF, 60, 0, 0, 0],
+            "set_cooling_off",
+            3,
+        ),
+        (
+            ProgramKind::HybridGate,
+            vec![hybrid_gate::SET_PAUSED, 1],
+            "set_paused",
+            3,
+        ),
+        (
+            ProgramKind::JurisdictionRegistry,
+            vec![jurisdiction_registry::INITIALIZE_REGISTRY],
+            "initialize_registry",
+            4,
+        ),
+        (
+            ProgramKind::JurisdictionRegistry,
+            vec![jurisdiction_registry::SET_JURISDICTION, b'U', b'S', 1],
+            "set_jurisdiction",
+            2,
+        ),
+        (
+            ProgramKind::JurisdictionRegistry,
+            vec![jurisdiction_registry::SET_HOLDER_JURISDICTION, b'U', b'S'],
+            "set_holder_jurisdiction",
+            6,
+        ),
+        (
+            ProgramKind::ThresholdGate,
+            vec![threshold_gate::POST_APPROVAL],
+            "post_approval",
+            7,
+        ),
+        (
+            ProgramKind::VelocityGate,
+            vec![velocity_gate::RECORD_THAW],
+            "record_thaw",
+            7,
+        ),
+    ];
+    let more: [(ProgramKind, Vec<u8>, &str, usize); 7] = [
+        (
+            ProgramKind::ThresholdGate,
+            vec![threshold_gate::INITIALIZE, 1, 0],
+            "initialize",
+            5,
+        ),
+        (
+            ProgramKind::ThresholdGate,
+            vec![threshold_gate::REVOKE_APPROVAL],
+            "revoke_approval",
+            6,
+        ),
+        (
+            ProgramKind::VelocityGate,
+            vec![velocity_gate::INITIALIZE, 3, 60, 0, 0, 0],
+            "initialize",
+            5,
+        ),
+        (
+            ProgramKind::GeoGate,
+            [[geo_gate::INITIALIZE].as_slice(), &[0; 64], b"USDE"].concat(),
+            "initialize",
+            5,
+        ),
+        (
+            ProgramKind::GeoGate,
+            vec![geo_gate::SET_ALLOWED_JURISDICTIONS, b'U', b'S'],
+            "set_allowed_jurisdictions",
+            3,
+        ),
+        (
+            ProgramKind::NftGate,
+            [[nft_gate::INITIALIZE].as_slice(), &[0; 32]].concat(),
+            "initialize",
+            5,
+        ),
+        (
+            ProgramKind::NftGate,
+            [[nft_gate::SET_COLLECTION].as_slice(), &[0; 32]].concat(),
+            "set_collection",
+            3,
+        ),
+    ];
+    for (kind, data, name, account_count) in cases.into_iter().chain(more) {
+        assertions += 1;
+        let accounts = (0..account_count)
+            .map(|_| AccountMeta::new_readonly(Pubkey::new_unique(), false))
+            .collect();
+        let instruction = Instruction::new_with_bytes(program_id(kind), &data, accounts);
+        let decoded = decode(&decoder, &[instruction]);
+        let decoded = &decoded.instructions[0];
+        if decoded.instruction != name
+            || decoded.category != InstructionCategory::Admin
+            || decoded.program_kind != Some(kind)
+            || decoded.discriminator != hex::encode(&data[..1])
+        {
+            return TestResultReport::failure(
+                test_name,
+                format!("{:?} {:?} decoded as {:?}", kind, data, decoded),
+            );
+        }
+        if let Some(account) = decoded
+            .accounts
+            .iter()
+            .find(|account| account.role.starts_with("account_"))
+        {
+            return TestResultReport::failure(
+                test_name,
+                format!("{:?} {} has an unnamed {}", kind, name, account.role),
+            );
+        }
+    }
+
+    // Assertion 3: The same bytes sent to a program nobody registered stay
+    // unknown
+    assertions += 1;
+    let unregistered = Instruction::new_with_bytes(
+        Pubkey::new_unique(),
+        &[production_allow_list::ADD_TO_ALLOW_LIST],
+        vec![AccountMeta::new_readonly(Pubkey::new_unique(), false)],
+    );
+    let decoded = decode(&decoder, &[unregistered]);
+    let decoded = &decoded.instructions[0];
+    if decoded.category != InstructionCategory::Unknown
+        || decoded.program_kind.is_some()
+        || decoded.accounts[0].role != "account_0"
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!("Unregistered program decoded as {:?}", decoded),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: The token, system, compute budget and associated token account
+/// instructions around gates decode by name
+#[test]
+fn test_well_known_instructions_decoded() {
+    let report = run_well_known_instructions_decoded_test();
+    assert!(
+        report.passed,
+        "Well-known instructions decoded test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_well_known_instructions_decoded_test() -> TestResultReport {
+    let test_name = "Decoder: Well-Known Instructions";
+    let mut assertions = 0;
+
+    let token = spl_token_2022::id();
+    let mint = Pubkey::new_unique();
+    let account = Pubkey::new_unique();
+    let destination = Pubkey::new_unique();
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let build = |result: Result<Instruction, _>| -> Instruction {
+        result.expect("Token-2022 instructions build for the Token-2022 id")
+    };
+    let instructions = [
+        ComputeBudgetInstruction::set_compute_unit_limit(50_000),
+        system_instruction::create_account(&payer, &account, 1, 165, &token),
+        system_instruction::transfer(&payer, &destination, 1),
+        build(token_instruction::initialize_mint2(
+            &token,
+            &mint,
+            &authority,
+            Some(&authority),
+            6,
+        )),
+        build(token_instruction::initialize_account3(
+            &token, &account, &mint, &payer,
+        )),
+        spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+            &payer, &payer, &mint, &token,
+        ),
+        build(token_instruction::mint_to(
+            &token,
+            &mint,
+            &account,
+            &authority,
+            &[],
+            1,
+        )),
+        build(token_instruction::transfer_checked(
+            &token,
+            &account,
+            &mint,
+            &destination,
+            &payer,
+            &[],
+            1,
+            6,
+        )),
+        build(token_instruction::freeze_account(
+            &token,
+            &account,
+            &mint,
+            &authority,
+            &[],
+        )),
+        build(token_instruction::thaw_account(
+            &token,
+            &account,
+            &mint,
+            &authority,
+            &[],
+        )),
+        build(token_instruction::burn(
+            &token,
+            &account,
+            &mint,
+            &payer,
+            &[],
+            1,
+        )),
+        build(token_instruction::close_account(
+            &token,
+            &account,
+            &destination,
+            &payer,
+            &[],
+        )),
+    ];
+    let expected = [
+        (
+            InstructionCategory::ComputeBudget,
+            "set_compute_unit_limit",
+            None,
+        ),
+        (
+            InstructionCategory::System,
+            "create_account",
+            Some(("new_account", account)),
+        ),
+        (
+            InstructionCategory::System,
+            "transfer",
+            Some(("to", destination)),
+        ),
+        (
+            InstructionCategory::Token,
+            "initialize_mint2",
+            Some(("mint", mint)),
+        ),
+        (
+            InstructionCategory::Token,
+            "initialize_account3",
+            Some(("account", account)),
+        ),
+        (
+            InstructionCategory::AssociatedToken,
+            "create_idempotent",
+            Some(("wallet", payer)),
+        ),
+        (
+            InstructionCategory::Token,
+            "mint_to",
+            Some(("authority", authority)),
+        ),
+        (
+            InstructionCategory::Token,
+            "transfer_checked",
+            Some(("destination", destination)),
+        ),
+        (
+            InstructionCategory::Token,
+            "freeze_account",
+            Some(("freeze_authority", authority)),
+        ),
+        (
+            InstructionCategory::Token,
+            "thaw_account",
+            Some(("freeze_authority", authority)),
+        ),
+        (
+            InstructionCategory::Token,
+            "burn",
+            Some(("account", account)),
+        ),
+        (
+            InstructionCategory::Token,
+            "close_account",
+            Some(("destination", destination)),
+        ),
+    ];
+
+    let decoded = decode(&Decoder::new(), &instructions);
+    for (instruction, (category, name, role)) in decoded.instructions.iter().zip(expected) {
+        // Assertion: Each instruction is named, and its key account found
+        assertions += 1;
+        if instruction.category != category {
+            return TestResultReport::failure(
+                test_name,
+                format!("{} decoded as {:?}", name, instruction.category),
+            );
+        }
+        let checked = match role {
+            Some((role, address)) => expect_role(instruction, name, role, &address),
+            None if instruction.instruction == name => Ok(()),
+            None => Err(format!("Decoded {} as {}", name, instruction.instruction)),
+        };
+        if let Err(e) = checked {
+            return TestResultReport::failure(test_name, e);
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// A token account of a fresh holder of the harness's mint
+fn holder(harness: &mut GateHarness, state: AccountState) -> (Pubkey, Pubkey) {
+    let owner = Pubkey::new_unique();
+    let token_account = harness.create_token_account(&owner, state);
+    (owner, token_account)
+}
+
+/// Run `instructions` on the harness's bank, whatever the outcome, and
+/// trace them; also returns the logs
+async fn execute_with_logs(
+    harness: &mut GateHarness,
+    instructions: &[Instruction],
+) -> (TransactionTrace, Vec<String>) {
+    let blockhash = harness
+        .context
+        .get_new_latest_blockhash()
+        .await
+        .expect("the bank should hand out a blockhash");
+    let payer = harness.context.payer.insecure_clone();
+    let issuer = harness.issuer.insecure_clone();
+    let mut transaction = Transaction::new_with_payer(instructions, Some(&payer.pubkey()));
+    let mut signers: Vec<&Keypair> = vec![&payer];
+    if transaction
+        .message
+        .signer_keys()
+        .contains(&&issuer.pubkey())
+    {
+        signers.push(&issuer);
+    }
+    transaction.sign(&signers, blockhash);
+    let outcome = harness
+        .context
+        .banks_client
+        .process_transaction_with_metadata(transaction.clone())
+        .await
+        .expect("the bank should process the transaction");
+    let logs = outcome
+        .metadata
+        .as_ref()
+        .map(|metadata| metadata.log_messages.clone())
+        .unwrap_or_default();
+    (trace::from_outcome(&transaction, &outcome), logs)
+}
+
+async fn execute(harness: &mut GateHarness, instructions: &[Instruction]) -> TransactionTrace {
+    execute_with_logs(harness, instructions).await.0
+}
+
+/// A decoder for the harness's gates
+fn harness_decoder(harness: &GateHarness) -> Decoder {
+    let mut decoder = Decoder::new();
+    decoder
+        .register(harness.allow_list, ProgramKind::AllowList)
+        .register(harness.block_list, ProgramKind::BlockList)
+        .register(
+            harness.production_allow_list,
+            ProgramKind::ProductionAllowList,
+        );
+    decoder
+}
+
+/// Test 3: Executed permissionless checks decode with the gate's decision,
+/// and the trace printer names them
+#[tokio::test]
+async fn test_permissionless_decisions_decoded() {
+    let report = run_permissionless_decisions_decoded_test().await;
+    assert!(
+        report.passed,
+        "Permissionless decisions decoded test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_permissionless_decisions_decoded_test() -> TestResultReport {
+    let test_name = "Decoder: Permissionless Decisions";
+    let mut assertions = 0;
+
+    let mut harness = match GateHarness::start(test_name).await {
+        Ok(harness) => harness,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let decoder = harness_decoder(&harness);
+    let caller = Pubkey::new_unique();
+    let issuer = harness.issuer.pubkey();
+
+    let (investor, investor_account) = holder(&mut harness, AccountState::Frozen);
+    let add = harness.add_to_allow_list(&investor, &issuer, true);
+    if let Err(e) = harness.send(&[add], &[]).await {
+        return TestResultReport::failure(test_name, format!("Add failed: {:?}", e));
+    }
+    let thaw = harness.permissionless_thaw(
+        Gate::ProductionAllowList,
+        &caller,
+        &investor_account,
+        &investor,
+    );
+    let approved = decoder.decode_trace(&execute(&mut harness, &thaw).await);
+
+    // Assertion 1: The approved check names every account it was given and
+    // the thaw the FAMP makes after it
+    assertions += 1;
+    if approved.error.is_some() || approved.instructions.len() != 2 {
+        return TestResultReport::failure(test_name, format!("Approved thaw: {:?}", approved));
+    }
+    let check = &approved.instructions[0];
+    let roles: Vec<&str> = check
+        .accounts
+        .iter()
+        .map(|account| account.role.as_str())
+        .collect();
+    if check.category != InstructionCategory::PermissionlessCheck
+        || check.discriminator
+            != hex::encode(token_acl_interface::CAN_THAW_PERMISSIONLESS_DISCRIMINATOR)
+        || roles
+            != [
+                "caller",
+                "token_account",
+                "mint",
+                "extra_account_metas",
+                "owner",
+                "allow_list_record",
+                "missing_record_policy",
+            ]
+    {
+        return TestResultReport::failure(test_name, format!("Decoded check {:?}", check));
+    }
+    let famp_thaw = &approved.instructions[1];
+    if let Err(e) = expect_role(famp_thaw, "thaw_account", "freeze_authority", &issuer)
+        .and_then(|()| expect_role(check, "can_thaw_permissionless", "owner", &investor))
+    {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // Assertion 2: The approval is the decision, and the JSON carries it
+    assertions += 1;
+    let json = approved.to_json();
+    if check.decision.as_ref().map(|decision| decision.approved) != Some(true)
+        || json["instructions"][0]["decision"]["approved"] != true
+        || json["instructions"][0]["accounts"][4]["address"] != investor.to_string()
+        || json["instructions"][1]["instruction"] != "thaw_account"
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!("Approved JSON:\n{}", approved.to_json_pretty()),
+        );
+    }
+
+    // Assertion 3: A denial carries the error and the reason the gate
+    // logged, and the FAMP's thaw never runs
+    assertions += 1;
+    let (blocked, blocked_account) = holder(&mut harness, AccountState::Frozen);
+    harness.set_blocked(&blocked, true);
+    let thaw = harness.permissionless_thaw(Gate::BlockList, &caller, &blocked_account, &blocked);
+    let denied = decoder.decode_trace(&execute(&mut harness, &thaw).await);
+    let decision = denied.instructions[0].decision.as_ref();
+    if denied.error.is_none()
+        || decision.map(|decision| decision.approved) != Some(false)
+        || decision
+            .and_then(|decision| decision.error.as_ref())
+            .is_none()
+        || decision
+            .and_then(|decision| decision.reason.as_ref())
+            .is_none()
+        || denied.instructions[1].outcome != "not executed"
+        || denied.instructions[1].decision.is_some()
+    {
+        return TestResultReport::failure(test_name, format!("Denied thaw: {:?}", denied));
+    }
+
+    // Assertion 4: Freeze checks decode with their own extra accounts
+    assertions += 1;
+    let active_account = harness.create_token_account(&blocked, AccountState::Initialized);
+    let freeze = harness.permissionless_freeze(Gate::BlockList, &caller, &active_account, &blocked);
+    let frozen = decoder.decode_trace(&execute(&mut harness, &freeze).await);
+    let record = harness.record_address(Gate::BlockList, &blocked);
+    if let Err(e) = expect_role(
+        &frozen.instructions[0],
+        "can_freeze_permissionless",
+        "block_list_record",
+        &record,
+    ) {
+        return TestResultReport::failure(test_name, e);
+    }
+    if frozen.checks().count() != 1
+        || frozen.instructions[0].decision.as_ref().map(|d| d.approved) != Some(true)
+        || frozen.instructions[1].instruction != "freeze_account"
+    {
+        return TestResultReport::failure(test_name, format!("Freeze: {:?}", frozen));
+    }
+
+    // Assertion 5: Without registration, the check's interface accounts are
+    // still named and the rest left positional
+    assertions += 1;
+    let generic = Decoder::new().decode_trace(&execute(&mut harness, &thaw).await);
+    let roles: Vec<&str> = generic.instructions[0]
+        .accounts
+        .iter()
+        .map(|account| account.role.as_str())
+        .collect();
+    if roles[..4] != ["caller", "token_account", "mint", "extra_account_metas"]
+        || roles[4] != "account_4"
+        || generic.instructions[0].program_kind.is_some()
+    {
+        return TestResultReport::failure(test_name, format!("Unregistered roles {:?}", roles));
+    }
+
+    // Assertion 6: The harness registered its gates for the trace printer
+    assertions += 1;
+    let rendered = trace::render(&execute(&mut harness, &thaw).await);
+    if !rendered.contains("can_thaw_permissionless: failed")
+        || !rendered.contains(&format!("caller: {}", caller))
+        || !rendered.contains("block_list_record: ")
+        || !rendered.contains("token_2022 thaw_account: not executed")
+    {
+        return TestResultReport::failure(test_name, format!("Rendered:\n{}", rendered));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 4: A v0 transaction as `getTransaction` returns it, with accounts
+/// loaded from a lookup table, decodes like the recording
+#[tokio::test]
+async fn test_confirmed_transaction_decoded() {
+    let report = run_confirmed_transaction_decoded_test().await;
+    assert!(
+        report.passed,
+        "Confirmed transaction decoded test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_confirmed_transaction_decoded_test() -> TestResultReport {
+    let test_name = "Decoder: Confirmed Transaction";
+    let mut assertions = 0;
+
+    let mut harness = match GateHarness::start(test_name).await {
+        Ok(harness) => harness,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let decoder = harness_decoder(&harness);
+    let caller = Pubkey::new_unique();
+    let (listed, token_account) = holder(&mut harness, AccountState::Frozen);
+    harness.set_listed(&listed, true);
+    let thaw = harness.permissionless_thaw(Gate::AllowList, &caller, &token_account, &listed);
+    let (trace, logs) = execute_with_logs(&mut harness, &thaw).await;
+    let recorded = decoder.decode_trace(&trace);
+
+    // The same instructions with the check's extra accounts in a lookup
+    // table; executed, they would produce the same logs
+    let lookup_table = AddressLookupTableAccount {
+        key: Pubkey::new_unique(),
+        addresses: vec![listed, harness.record_address(Gate::AllowList, &listed)],
+    };
+    let payer = harness.context.payer.pubkey();
+    let message = match v0::Message::try_compile(
+        &payer,
+        &thaw,
+        std::slice::from_ref(&lookup_table),
+        Hash::default(),
+    ) {
+        Ok(message) => message,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+    let Some(lookup) = message.address_table_lookups.first() else {
+        return TestResultReport::failure(test_name, "Nothing was looked up".to_string());
+    };
+    let loaded = |indexes: &[u8]| -> Vec<Pubkey> {
+        indexes
+            .iter()
+            .map(|index| lookup_table.addresses[usize::from(*index)])
+            .collect()
+    };
+    let loaded_addresses = LoadedAddresses {
+        writable: loaded(&lookup.writable_indexes),
+        readonly: loaded(&lookup.readonly_indexes),
+    };
+    let transaction = VersionedTransaction {
+        signatures: vec![Signature::default(); usize::from(message.header.num_required_signatures)],
+        message: VersionedMessage::V0(message),
+    };
+    let confirmed = ConfirmedTransactionWithStatusMeta {
+        slot: 1,
+        tx_with_meta: TransactionWithStatusMeta::Complete(VersionedTransactionWithStatusMeta {
+            transaction,
+            meta: TransactionStatusMeta {
+                log_messages: Some(logs),
+                loaded_addresses,
+                compute_units_consumed: trace.compute_units,
+                ..TransactionStatusMeta::default()
+            },
+        }),
+        block_time: None,
+    };
+    let encoded = match confirmed.encode(UiTransactionEncoding::Base64, Some(0)) {
+        Ok(encoded) => encoded,
+        Err(e) => return TestResultReport::failure(test_name, e.to_string()),
+    };
+
+    // Assertion 1: The RPC transaction decodes to the recording's
+    // instructions, roles and decision
+    assertions += 1;
+    let fetched = match decoder.decode_confirmed(&encoded) {
+        Ok(fetched) => fetched,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let summary = |decoded: &DecodedTransaction| -> Vec<_> {
+        decoded
+            .instructions
+            .iter()
+            .map(|instruction| {
+                (
+                    instruction.instruction.clone(),
+                    instruction
+                        .accounts
+                        .iter()
+                        .map(|account| (account.role.clone(), account.address))
+                        .collect::<Vec<_>>(),
+                    instruction.decision.clone(),
+                )
+            })
+            .collect()
+    };
+    if summary(&fetched) != summary(&recorded)
+        || recorded.instructions[0]
+            .decision
+            .as_ref()
+            .map(|d| d.approved)
+            != Some(true)
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!(
+                "Fetched:\n{}\nRecorded:\n{}",
+                fetched.to_json_pretty(),
+                recorded.to_json_pretty()
+            ),
+        );
+    }
+
+    // Assertion 2: The JSON round-trips as JSON
+    assertions += 1;
+    let text = fetched.to_json_pretty();
+    match serde_json::from_str::<serde_json::Value>(&text) {
+        Ok(value) if value == fetched.to_json() => {}
+        other => {
+            return TestResultReport::failure(test_name, format!("Reparsed as {:?}", other));
+        }
+    }
+
+    // Assertion 3: A v0 transaction whose lookups were not returned is an
+    // error, not a guess
+    assertions += 1;
+    let mut unresolved = encoded;
+    if let Some(meta) = unresolved.transaction.meta.as_mut() {
+        meta.loaded_addresses = None.into();
+    }
+    if decoder.decode_confirmed(&unresolved).is_ok() {
+        return TestResultReport::failure(
+            test_name,
+            "Decoded without the loaded addresses".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate the transaction decoder report
+#[tokio::test]
+async fn generate_transaction_decoder_report() {
+    let results = vec![
+        run_program_instructions_decoded_test(),
+        run_well_known_instructions_decoded_test(),
+        run_permissionless_decisions_decoded_test().await,
+        run_confirmed_transaction_decoded_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Transaction Decoder Results",
+        "../../tests/reports/transaction_decoder.md",
+    ) {
+        panic!("Failed to generate transaction decoder report: {}", e);
+    }
+
+    for result in &results {
+        assert!(result.passed, "{} failed: {:?}", result.name, result.error);
+    }
+}
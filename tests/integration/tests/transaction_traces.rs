@@ -0,0 +1,329 @@
+//! Transaction trace tests
+//!
+//! A failing test's report carries the tree of the last transaction that
+//! failed. These tests pin how logs become that tree, that a real failed
+//! CPI is traced through the shared submit helper, and that only failing
+//! reports carry traces.
+
+use production_allow_list::{ADD_TO_ALLOW_LIST, ALLOW_LIST_SEED, CONFIG_SEED, INITIALIZE};
+use solana_program_test::{processor, BanksTransactionResultWithMetadata, ProgramTest};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction, InstructionError},
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    system_instruction, system_program,
+    transaction::{Transaction, TransactionError},
+};
+
+use token_acl_integration_tests::{
+    context::TestContext,
+    labels, nonce, reporting,
+    trace::{self, Outcome, TransactionTrace},
+    TestResultReport, TransactionRecord,
+};
+
+/// Logs become one branch per instruction, with CPIs nested in order
+#[test]
+fn test_trace_tree() {
+    let report = run_trace_tree_test();
+    assert!(report.passed, "Trace tree test failed: {:?}", report.error);
+}
+
+fn run_trace_tree_test() -> TestResultReport {
+    let test_name = "Traces: Tree";
+    let mut assertions = 0;
+
+    labels::clear();
+    let gate = labels::label(Pubkey::new_unique(), "gate");
+    let payer = labels::label(Pubkey::new_unique(), "payer");
+    let investor = labels::label(Pubkey::new_unique(), "investor_3");
+    let record = labels::label_derived(Pubkey::new_unique(), "allow_pda", &investor);
+    let mint = labels::label(Pubkey::new_unique(), "mint");
+    let add = Instruction::new_with_bytes(
+        gate,
+        &[ADD_TO_ALLOW_LIST],
+        vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(record, false),
+            AccountMeta::new_readonly(mint, false),
+        ],
+    );
+    let transfer = system_instruction::transfer(&payer, &investor, 1);
+    let transaction = Transaction::new_with_payer(&[add, transfer], Some(&payer));
+    let logs = vec![
+        format!("Program {} invoke [1]", gate),
+        "Program log: Instruction: Add".to_string(),
+        "Program 11111111111111111111111111111111 invoke [2]".to_string(),
+        "Transfer: insufficient lamports 0, need 1002240".to_string(),
+        "Program 11111111111111111111111111111111 failed: custom program error: 0x1".to_string(),
+        format!("Program {} consumed 4321 of 200000 compute units", gate),
+        format!("Program {} failed: custom program error: 0x1", gate),
+    ];
+    let result = Err(TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(1),
+    ));
+    let trace = TransactionTrace::new(&transaction, &result, &logs, Some(4_321));
+
+    // Assertion: the failed instruction holds its CPI, the next never ran
+    assertions += 1;
+    let failed = trace.instructions[0].invocation.as_ref();
+    let cpi = failed.and_then(|invocation| invocation.cpis().next());
+    if !matches!(cpi.map(|cpi| &cpi.outcome), Some(Outcome::Failed(_)))
+        || failed.and_then(|invocation| invocation.compute_units) != Some((4_321, 200_000))
+        || trace.instructions[1].invocation.is_some()
+    {
+        return TestResultReport::failure(test_name, format!("Parsed {:?}", trace));
+    }
+
+    // Assertion: the tree names accounts and programs, in execution order
+    assertions += 1;
+    let expected = format!(
+        "Transaction {} failed: Error processing Instruction 0: custom program error: 0x1
+├── fee payer: payer
+├── compute units: 4,321 CU
+├── #0 gate: failed: custom program error: 0x1 (4,321 of 200,000 CU)
+│   ├── accounts
+│   │   ├── payer [signer, writable]
+│   │   ├── allow_pda(investor_3) [writable]
+│   │   └── mint
+│   ├── log: Instruction: Add
+│   └── CPI system_program: failed: custom program error: 0x1
+│       └── Transfer: insufficient lamports 0, need 1002240
+└── #1 system_program transfer: not executed
+    └── accounts
+        ├── from: payer [signer, writable]
+        └── to: investor_3 [writable]
+",
+        Signature::default()
+    );
+    let rendered = trace::render(&trace);
+    if rendered != expected {
+        return TestResultReport::failure(
+            test_name,
+            format!("Rendered:\n{}\nExpected:\n{}", rendered, expected),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// A failed CPI on a real bank is traced and attached to the failing report
+#[tokio::test]
+async fn test_failed_transaction_traced() {
+    let report = run_failed_transaction_test().await;
+    assert!(
+        report.passed,
+        "Failed transaction trace test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_failed_transaction_test() -> TestResultReport {
+    let test_name = "Traces: Failed Transaction";
+    let mut assertions = 0;
+
+    labels::clear();
+    let gate = labels::label(Pubkey::new_unique(), "gate");
+    let program_test = ProgramTest::new(
+        "production_allow_list",
+        gate,
+        processor!(production_allow_list::process_instruction),
+    );
+    let mut context = match TestContext::start(test_name, program_test).await {
+        Ok(context) => context,
+        Err(e) => return TestResultReport::failure(test_name, e),
+    };
+    let payer = context.payer();
+    let authority = context.namespace().keypair("authority");
+    let mint = context.namespace().address("mint");
+    let investor = context.namespace().address("investor_3");
+    let unfunded = context.namespace().keypair("unfunded");
+    let (config, _) = Pubkey::find_program_address(&[CONFIG_SEED, mint.as_ref()], &gate);
+    let (record, _) =
+        Pubkey::find_program_address(&[ALLOW_LIST_SEED, mint.as_ref(), investor.as_ref()], &gate);
+    labels::label_derived(config, "config", &mint);
+    labels::label_derived(record, "allow_pda", &investor);
+
+    let initialize = Instruction::new_with_bytes(
+        gate,
+        &[INITIALIZE],
+        vec![
+            AccountMeta::new(config, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &[initialize],
+        Some(&payer.pubkey()),
+        &[&payer, &authority],
+        context.last_blockhash,
+    );
+    if let Err(e) = nonce::submit(&mut context.banks_client, transaction).await {
+        return TestResultReport::failure(test_name, e);
+    }
+
+    // The record's rent payer holds nothing, so the CPI creating it fails
+    let add = Instruction::new_with_bytes(
+        gate,
+        &[ADD_TO_ALLOW_LIST],
+        vec![
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(record, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(investor, false),
+            AccountMeta::new_readonly(authority.pubkey(), true),
+            AccountMeta::new(unfunded.pubkey(), true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &[add],
+        Some(&payer.pubkey()),
+        &[&payer, &authority, &unfunded],
+        context.last_blockhash,
+    );
+    let signature = transaction.signatures[0];
+    if nonce::submit(&mut context.banks_client, transaction)
+        .await
+        .is_ok()
+    {
+        return TestResultReport::failure(test_name, "Unfunded add succeeded".to_string());
+    }
+    let report = TestResultReport::failure("Add Investor", "add failed".to_string());
+
+    // Assertion: the failing report carries the trace of the failed add
+    assertions += 1;
+    let Some(trace) = report
+        .transactions
+        .first()
+        .and_then(|record| record.trace.as_ref())
+    else {
+        return TestResultReport::failure(test_name, "No trace attached".to_string());
+    };
+    if trace.signature != signature || !trace.failed() {
+        return TestResultReport::failure(test_name, format!("Traced {}", trace.signature));
+    }
+
+    // Assertion: the tree shows the gate's failed CPI into the system program
+    assertions += 1;
+    let rendered = trace::render(trace);
+    if !rendered.contains("#0 gate: failed")
+        || !rendered.contains("CPI system_program: failed")
+        || !rendered.contains("allow_pda(investor_3) [writable]")
+        || !rendered.contains("config(mint)\n")
+        || !rendered.contains("unfunded [signer, writable]")
+    {
+        return TestResultReport::failure(test_name, format!("Rendered:\n{}", rendered));
+    }
+
+    // Assertion: the markdown report renders the tree under the transaction
+    assertions += 1;
+    let markdown = reporting::render_test_report(&[report], "Traced", &[]);
+    let branch = "        └── CPI system_program: failed";
+    if !markdown.contains("    ```text\n    Transaction ") || !markdown.contains(branch) {
+        return TestResultReport::failure(test_name, format!("Report:\n{}", markdown));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Only a failing report carries a trace, and only its own test's
+#[test]
+fn test_traces_stay_with_failures() {
+    let report = run_traces_stay_with_failures_test();
+    assert!(
+        report.passed,
+        "Trace attachment test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_traces_stay_with_failures_test() -> TestResultReport {
+    let test_name = "Traces: Attachment";
+    let mut assertions = 0;
+
+    let payer = Keypair::new();
+    let transaction = Transaction::new_with_payer(
+        &[system_instruction::transfer(
+            &payer.pubkey(),
+            &Pubkey::new_unique(),
+            1,
+        )],
+        Some(&payer.pubkey()),
+    );
+    let denied = |transaction: &Transaction| {
+        let outcome = BanksTransactionResultWithMetadata {
+            result: Err(TransactionError::AccountNotFound),
+            metadata: None,
+        };
+        trace::record(transaction, &outcome);
+    };
+
+    // Assertion: a test that expected the failure and passed drops it
+    assertions += 1;
+    denied(&transaction);
+    TestResultReport::success("Expected Denial", 1);
+    if TestResultReport::failure("Next Test", "unrelated".to_string())
+        .transactions
+        .iter()
+        .any(|record| record.trace.is_some())
+    {
+        return TestResultReport::failure(
+            test_name,
+            "Passing test's trace leaked into the next report".to_string(),
+        );
+    }
+
+    // Assertion: transactions a test attaches come before the traced failure
+    assertions += 1;
+    denied(&transaction);
+    let listed = reporting::render_test_report(
+        &[
+            TestResultReport::failure("Denied", "denied".to_string()).with_transactions(vec![
+                TransactionRecord {
+                    label: "setup".to_string(),
+                    signature: Signature::from([7; 64]),
+                    slot: Some(1),
+                    trace: None,
+                },
+            ]),
+        ],
+        "Listed",
+        &[],
+    );
+    let setup = listed.find("  - setup:");
+    let failed = listed.find("  - failed transaction:");
+    if !matches!((setup, failed), (Some(setup), Some(failed)) if setup < failed)
+        || !listed.contains(&format!("failed: {}", TransactionError::AccountNotFound))
+    {
+        return TestResultReport::failure(test_name, format!("Report:\n{}", listed));
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate the transaction traces report
+#[tokio::test]
+async fn generate_transaction_traces_report() {
+    let results = vec![
+        run_trace_tree_test(),
+        run_failed_transaction_test().await,
+        run_traces_stay_with_failures_test(),
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Transaction Trace Results",
+        "../../tests/reports/transaction_traces.md",
+    ) {
+        panic!("Failed to generate transaction traces report: {}", e);
+    }
+
+    for result in &results {
+        assert!(result.passed, "{} failed: {:?}", result.name, result.error);
+    }
+}
@@ -0,0 +1,533 @@
+//! Webhook notification tests
+//!
+//! These tests point a `WebhookNotifier` at a local receiver that can be
+//! told to fail, and check what arrives: signed bodies, retries carrying the
+//! same notification, and final answers for permanent rejections and
+//! unreachable hosts. The last test wires the notifier into a
+//! `QueryService` over a seeded `solana-program-test` bank and checks that
+//! a denied thaw and an issuer freeze reach the receiver.
+
+use std::collections::VecDeque;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::{body::Bytes, extract::State, http::HeaderMap, http::StatusCode, routing::post, Router};
+use production_allow_list::{config_address, INITIALIZE};
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::Transaction,
+};
+use spl_token_2022::{instruction as token_instruction, state::AccountState};
+
+use token_acl_integration_tests::{
+    analytics::GateOperation,
+    mirror::{AccountUpdate, ListMirror, MirroredGates},
+    notifier::{
+        verify_signature, DeliveryOutcome, GateEvent, Notification, RetryPolicy, Webhook,
+        WebhookNotifier, SIGNATURE_HEADER, TIMESTAMP_HEADER,
+    },
+    program_test::{mint_account, token_account},
+    query_service::QueryService,
+    reporting, TestResultReport,
+};
+use token_acl_interface as interface;
+
+/// A request as the receiver saw it
+#[derive(Debug, Clone)]
+struct Received {
+    timestamp: i64,
+    signature: String,
+    body: Vec<u8>,
+}
+
+impl Received {
+    fn notification(&self) -> Notification {
+        serde_json::from_slice(&self.body).expect("body should be a notification")
+    }
+}
+
+#[derive(Default)]
+struct ReceiverState {
+    /// Statuses to answer with, in order, before answering 200
+    script: VecDeque<StatusCode>,
+    received: Vec<Received>,
+}
+
+/// A local webhook receiver
+#[derive(Clone, Default)]
+struct Receiver {
+    state: Arc<Mutex<ReceiverState>>,
+    url: String,
+}
+
+impl Receiver {
+    /// Start a receiver answering `script` before succeeding
+    fn start(script: &[StatusCode]) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let receiver = Receiver {
+            url: format!("http://{}/hook", listener.local_addr().unwrap()),
+            ..Receiver::default()
+        };
+        receiver.state.lock().unwrap().script = script.iter().copied().collect();
+
+        let app = Router::new()
+            .route("/hook", post(receive))
+            .with_state(receiver.state.clone());
+        let server = axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app.into_make_service());
+        tokio::spawn(server);
+        receiver
+    }
+
+    fn received(&self) -> Vec<Received> {
+        self.state.lock().unwrap().received.clone()
+    }
+}
+
+async fn receive(
+    State(state): State<Arc<Mutex<ReceiverState>>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string()
+    };
+    let mut state = state.lock().unwrap();
+    state.received.push(Received {
+        timestamp: header(TIMESTAMP_HEADER).parse().unwrap_or_default(),
+        signature: header(SIGNATURE_HEADER),
+        body: body.to_vec(),
+    });
+    state.script.pop_front().unwrap_or(StatusCode::OK)
+}
+
+/// Retries fast enough for tests
+fn quick_retries(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts,
+        initial_backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(40),
+        timeout: Duration::from_secs(2),
+    }
+}
+
+fn thaw_denied() -> GateEvent {
+    GateEvent::ThawDenied {
+        mint: Pubkey::new_unique().to_string(),
+        owner: Some(Pubkey::new_unique().to_string()),
+        reason: "blocked".to_string(),
+    }
+}
+
+/// An address nothing listens on
+fn unreachable_url() -> String {
+    let addr: SocketAddr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    format!("http://{}/hook", addr)
+}
+
+/// Test 1: Every webhook gets the notification signed with its own secret
+#[tokio::test]
+async fn test_signed_delivery() {
+    let report = run_signed_delivery_test().await;
+    assert!(
+        report.passed,
+        "Signed delivery test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_signed_delivery_test() -> TestResultReport {
+    let test_name = "Signed Delivery";
+    let mut assertions = 0;
+
+    let receivers = [Receiver::start(&[]), Receiver::start(&[])];
+    let secrets: [&[u8]; 2] = [b"first secret", b"second secret"];
+    let notifier = WebhookNotifier::new(
+        receivers
+            .iter()
+            .zip(secrets)
+            .map(|(receiver, secret)| Webhook::new(receiver.url.clone(), secret))
+            .collect(),
+        quick_retries(3),
+    );
+    let notification = Notification::new(thaw_denied());
+    let reports = notifier.deliver(&notification).await;
+
+    // Assertion 1: Both deliveries succeeded at the first attempt
+    assertions += 1;
+    if reports.len() != 2
+        || !reports
+            .iter()
+            .all(|report| report.outcome == DeliveryOutcome::Delivered(200) && report.attempts == 1)
+    {
+        return TestResultReport::failure(test_name, format!("Unexpected reports: {:?}", reports));
+    }
+
+    for (i, receiver) in receivers.iter().enumerate() {
+        let received = receiver.received();
+        let Some(request) = received.first().filter(|_| received.len() == 1) else {
+            return TestResultReport::failure(
+                test_name,
+                format!("Receiver {} got {} requests", i, received.len()),
+            );
+        };
+
+        // Assertion 2: The body is the notification sent
+        assertions += 1;
+        if request.notification() != notification {
+            return TestResultReport::failure(
+                test_name,
+                format!("Receiver {} got {:?}", i, request.notification()),
+            );
+        }
+
+        // Assertion 3: It verifies under the webhook's secret and no other
+        assertions += 1;
+        let verifies = |secret: &[u8]| {
+            verify_signature(secret, request.timestamp, &request.body, &request.signature)
+        };
+        if !verifies(secrets[i]) || verifies(secrets[1 - i]) {
+            return TestResultReport::failure(
+                test_name,
+                format!("Receiver {}'s signature checked out wrongly", i),
+            );
+        }
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: Server errors are retried with the same notification
+#[tokio::test]
+async fn test_retries_transient_failures() {
+    let report = run_retry_test().await;
+    assert!(report.passed, "Retry test failed: {:?}", report.error);
+}
+
+async fn run_retry_test() -> TestResultReport {
+    let test_name = "Retries Transient Failures";
+    let mut assertions = 0;
+
+    let receiver = Receiver::start(&[
+        StatusCode::SERVICE_UNAVAILABLE,
+        StatusCode::TOO_MANY_REQUESTS,
+    ]);
+    let notifier = WebhookNotifier::new(
+        vec![Webhook::new(receiver.url.clone(), "secret")],
+        quick_retries(5),
+    );
+    let reports = notifier.deliver(&Notification::new(thaw_denied())).await;
+
+    // Assertion 1: The third attempt got through
+    assertions += 1;
+    if !matches!(reports.as_slice(), [report] if report.attempts == 3
+        && report.outcome == DeliveryOutcome::Delivered(200))
+    {
+        return TestResultReport::failure(test_name, format!("Unexpected reports: {:?}", reports));
+    }
+
+    // Assertion 2: Every attempt carried the same notification id
+    assertions += 1;
+    let ids: Vec<String> = receiver
+        .received()
+        .iter()
+        .map(|request| request.notification().id)
+        .collect();
+    if ids.len() != 3 || ids.iter().any(|id| *id != reports[0].notification_id) {
+        return TestResultReport::failure(test_name, format!("Attempts carried ids {:?}", ids));
+    }
+
+    // Assertion 3: Every attempt was signed
+    assertions += 1;
+    if !receiver.received().iter().all(|request| {
+        verify_signature(
+            b"secret",
+            request.timestamp,
+            &request.body,
+            &request.signature,
+        )
+    }) {
+        return TestResultReport::failure(test_name, "A retry was not signed".to_string());
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: Client errors are final and dead hosts are given up on
+#[tokio::test]
+async fn test_final_failures() {
+    let report = run_final_failures_test().await;
+    assert!(
+        report.passed,
+        "Final failures test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_final_failures_test() -> TestResultReport {
+    let test_name = "Final Failures";
+    let mut assertions = 0;
+
+    let rejecting = Receiver::start(&[StatusCode::BAD_REQUEST]);
+    let notifier = WebhookNotifier::new(
+        vec![
+            Webhook::new(rejecting.url.clone(), "secret"),
+            Webhook::new(unreachable_url(), "secret"),
+        ],
+        quick_retries(3),
+    );
+    let reports = notifier.deliver(&Notification::new(thaw_denied())).await;
+    if reports.len() != 2 {
+        return TestResultReport::failure(test_name, format!("Unexpected reports: {:?}", reports));
+    }
+
+    // Assertion 1: A 400 is not retried
+    assertions += 1;
+    if reports[0].outcome != DeliveryOutcome::Rejected(400)
+        || reports[0].attempts != 1
+        || rejecting.received().len() != 1
+    {
+        return TestResultReport::failure(
+            test_name,
+            format!("Rejection was handled as {:?}", reports[0]),
+        );
+    }
+
+    // Assertion 2: An unreachable webhook is tried max_attempts times
+    assertions += 1;
+    if !matches!(reports[1].outcome, DeliveryOutcome::GaveUp(_)) || reports[1].attempts != 3 {
+        return TestResultReport::failure(
+            test_name,
+            format!("Unreachable webhook was handled as {:?}", reports[1]),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Feed the service the current state of `address`
+async fn feed(context: &mut ProgramTestContext, service: &QueryService, address: Pubkey) {
+    let slot = context.banks_client.get_root_slot().await.unwrap() + 1;
+    context.warp_to_slot(slot).unwrap();
+    let account = context
+        .banks_client
+        .get_account(address)
+        .await
+        .unwrap()
+        .unwrap();
+    service.apply_token_account(&AccountUpdate::from_account(address, slot, &account));
+}
+
+/// Test 4: The query service raises alerts for denied thaws and freezes
+#[tokio::test]
+async fn test_service_raises_alerts() {
+    let report = run_service_alerts_test().await;
+    assert!(
+        report.passed,
+        "Service alerts test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_service_alerts_test() -> TestResultReport {
+    let test_name = "Service Raises Alerts";
+    let mut assertions = 0;
+
+    let allow_list = Pubkey::new_unique();
+    let issuer = Keypair::new();
+    let mint = Pubkey::new_unique();
+    // Bob holds a thawed account, Dave one frozen by default
+    let (bob, bob_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (dave, dave_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    let mut program_test = ProgramTest::new(
+        "production_allow_list",
+        allow_list,
+        processor!(production_allow_list::process_instruction),
+    );
+    program_test.add_account(mint, mint_account(&issuer.pubkey(), 2_000));
+    program_test.add_account(
+        bob_account,
+        token_account(&mint, &bob, 1_000, AccountState::Initialized),
+    );
+    program_test.add_account(
+        dave_account,
+        token_account(&mint, &dave, 1_000, AccountState::Frozen),
+    );
+    let mut context = program_test.start_with_context().await;
+
+    let db = sled::Config::new().temporary(true).open().unwrap();
+    let gates = MirroredGates {
+        allow_list_program: Some(allow_list),
+        block_list_program: None,
+    };
+    let service = QueryService::new();
+    service.serve_mint(ListMirror::open(&db, mint, gates).unwrap());
+    let receiver = Receiver::start(&[]);
+    let notifier = WebhookNotifier::new(
+        vec![Webhook::new(receiver.url.clone(), "secret")],
+        quick_retries(3),
+    )
+    .spawn();
+    service.notify(notifier.clone());
+
+    // Both accounts are first seen as they are: nothing to report
+    feed(&mut context, &service, bob_account).await;
+    feed(&mut context, &service, dave_account).await;
+
+    // Dave asks for a thaw the allow list refuses
+    let payer = context.payer.pubkey();
+    let initialize = Instruction::new_with_bytes(
+        allow_list,
+        &[INITIALIZE],
+        vec![
+            AccountMeta::new(config_address(&mint, &allow_list).address(), false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(issuer.pubkey(), true),
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    let check = interface::can_thaw_permissionless(
+        &allow_list,
+        payer,
+        dave_account,
+        mint,
+        interface::find_thaw_extra_account_metas_address(&mint, &allow_list).0,
+        &[
+            dave.into(),
+            gate_view::allow_list_address(&allow_list, &mint, &dave)
+                .address()
+                .into(),
+            interface::find_missing_record_policy_address(&mint, &allow_list)
+                .0
+                .into(),
+        ],
+    );
+    // The issuer freezes Bob
+    let freeze = token_instruction::freeze_account(
+        &spl_token_2022::id(),
+        &bob_account,
+        &mint,
+        &issuer.pubkey(),
+        &[],
+    )
+    .unwrap();
+
+    let mut outcomes = Vec::new();
+    for (instructions, issuer_signs) in [(vec![initialize, freeze], true), (vec![check], false)] {
+        let blockhash = context.get_new_latest_blockhash().await.unwrap();
+        let mut signers = vec![&context.payer];
+        if issuer_signs {
+            signers.push(&issuer);
+        }
+        let transaction =
+            Transaction::new_signed_with_payer(&instructions, Some(&payer), &signers, blockhash);
+        outcomes.push(
+            context
+                .banks_client
+                .process_transaction_with_metadata(transaction)
+                .await
+                .unwrap(),
+        );
+    }
+    if let Err(e) = &outcomes[0].result {
+        return TestResultReport::failure(test_name, format!("Issuer setup failed: {}", e));
+    }
+    let denied = &outcomes[1];
+    let logs = denied
+        .metadata
+        .as_ref()
+        .map(|metadata| metadata.log_messages.clone())
+        .unwrap_or_default();
+    service.ingest_logs(&mint, GateOperation::Thaw, &logs, denied.result.is_ok());
+    feed(&mut context, &service, bob_account).await;
+
+    let reports = notifier.flush().await;
+    let events: Vec<GateEvent> = receiver
+        .received()
+        .iter()
+        .map(|request| request.notification().event)
+        .collect();
+
+    // Assertion 1: Two alerts were raised and delivered
+    assertions += 1;
+    if reports.len() != 2
+        || !reports
+            .iter()
+            .all(|report| report.outcome == DeliveryOutcome::Delivered(200))
+    {
+        return TestResultReport::failure(test_name, format!("Unexpected reports: {:?}", reports));
+    }
+
+    // Assertion 2: Dave's thaw was reported denied as unlisted
+    assertions += 1;
+    let expected_denial = GateEvent::ThawDenied {
+        mint: mint.to_string(),
+        owner: Some(dave.to_string()),
+        reason: "not_listed".to_string(),
+    };
+    if !events.contains(&expected_denial) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Denied thaw not reported: {:?}", events),
+        );
+    }
+
+    // Assertion 3: Bob's freeze was reported, and Dave's default freeze not
+    assertions += 1;
+    let frozen: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            GateEvent::AccountFrozen {
+                token_account,
+                owner,
+                ..
+            } => Some((token_account.clone(), owner.clone())),
+            _ => None,
+        })
+        .collect();
+    if frozen != [(bob_account.to_string(), bob.to_string())] {
+        return TestResultReport::failure(
+            test_name,
+            format!("Unexpected freeze alerts: {:?}", frozen),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate the webhook notification test report
+#[tokio::test]
+async fn generate_webhooks_report() {
+    let results = vec![
+        run_signed_delivery_test().await,
+        run_retry_test().await,
+        run_final_failures_test().await,
+        run_service_alerts_test().await,
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Webhook Notification Test Results",
+        "../../tests/reports/webhooks.md",
+    ) {
+        panic!("Failed to generate webhooks report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} webhook tests failed", failed);
+}
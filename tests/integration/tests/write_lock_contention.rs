@@ -0,0 +1,438 @@
+//! Write-lock contention tests
+//!
+//! These tests record the accounts each gate operation write-locks and check
+//! that no in-repo gate funnels independent operations through a shared
+//! writable account.
+
+use production_allow_list::{
+    pending_authority_address, RemoveMode, ACCEPT_AUTHORITY, ADD_TO_ALLOW_LIST, ALLOW_LIST_SEED,
+    CONFIG_SEED, INITIALIZE, REMOVE_FROM_ALLOW_LIST, UPDATE_AUTHORITY,
+};
+use solana_program_test::{processor, ProgramTest};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::Transaction,
+};
+use token_acl_interface::find_missing_record_policy_address;
+
+use token_acl_integration_tests::{
+    contention::ContentionAnalyzer, fixtures::test_data, reporting, TestResultReport,
+};
+
+const MINTS: usize = 3;
+const USERS_PER_MINT: usize = 8;
+
+fn config_pda(gate: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[CONFIG_SEED, mint.as_ref()], gate).0
+}
+
+fn record_pda(gate: &Pubkey, seed: &[u8], mint: &Pubkey, user: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[seed, mint.as_ref(), user.as_ref()], gate).0
+}
+
+/// sRFC 37 permissionless check with a single list record as extra account,
+/// followed by the missing-record policy PDA for thaw checks
+fn gate_check(
+    gate: Pubkey,
+    discriminator: [u8; 8],
+    extra_metas_seed: &[u8],
+    list_seed: &[u8],
+    mint: &Pubkey,
+    user: &Pubkey,
+) -> Instruction {
+    let (extra_metas, _) = Pubkey::find_program_address(&[extra_metas_seed, mint.as_ref()], &gate);
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*user, false),
+        AccountMeta::new_readonly(Pubkey::new_unique(), false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new_readonly(extra_metas, false),
+        AccountMeta::new_readonly(*user, false),
+        AccountMeta::new_readonly(record_pda(&gate, list_seed, mint, user), false),
+    ];
+    if discriminator == test_data::THAW_DISCRIMINATOR {
+        let (policy, _) = find_missing_record_policy_address(mint, &gate);
+        accounts.push(AccountMeta::new_readonly(policy, false));
+    }
+    Instruction::new_with_bytes(gate, &discriminator, accounts)
+}
+
+/// Test 1: Production allow list operations share no writable account
+#[tokio::test]
+async fn test_production_gate_has_no_hot_accounts() {
+    let report = run_production_gate_contention_test().await;
+    assert!(
+        report.passed,
+        "Production gate contention test failed: {:?}",
+        report.error
+    );
+}
+
+async fn run_production_gate_contention_test() -> TestResultReport {
+    let test_name = "Production Gate Write-Lock Contention";
+    let mut assertions = 0;
+
+    let gate = Pubkey::new_unique();
+    let (mut banks_client, payer, blockhash) = ProgramTest::new(
+        "production_allow_list",
+        gate,
+        processor!(production_allow_list::process_instruction),
+    )
+    .start()
+    .await;
+
+    let mut admin = ContentionAnalyzer::new();
+    let mut checks = ContentionAnalyzer::new();
+    let mut landed = Vec::new();
+
+    for _ in 0..MINTS {
+        let mint = Pubkey::new_unique();
+        let authority = Keypair::new();
+        let config = config_pda(&gate, &mint);
+        admin.label(config, "config");
+
+        let mut operations = vec![(
+            "initialize",
+            Instruction::new_with_bytes(
+                gate,
+                &[INITIALIZE],
+                vec![
+                    AccountMeta::new(config, false),
+                    AccountMeta::new_readonly(mint, false),
+                    AccountMeta::new_readonly(authority.pubkey(), true),
+                    AccountMeta::new(payer.pubkey(), true),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            ),
+        )];
+        for _ in 0..USERS_PER_MINT {
+            let user = Pubkey::new_unique();
+            let record = record_pda(&gate, ALLOW_LIST_SEED, &mint, &user);
+            operations.push((
+                "add",
+                Instruction::new_with_bytes(
+                    gate,
+                    &[ADD_TO_ALLOW_LIST],
+                    vec![
+                        AccountMeta::new_readonly(config, false),
+                        AccountMeta::new(record, false),
+                        AccountMeta::new_readonly(mint, false),
+                        AccountMeta::new_readonly(user, false),
+                        AccountMeta::new_readonly(authority.pubkey(), true),
+                        AccountMeta::new(payer.pubkey(), true),
+                        AccountMeta::new_readonly(system_program::id(), false),
+                    ],
+                ),
+            ));
+            operations.push((
+                "can_thaw",
+                gate_check(
+                    gate,
+                    test_data::THAW_DISCRIMINATOR,
+                    test_data::THAW_EXTRA_ACCOUNT_METAS_SEED,
+                    ALLOW_LIST_SEED,
+                    &mint,
+                    &user,
+                ),
+            ));
+            operations.push((
+                "remove",
+                Instruction::new_with_bytes(
+                    gate,
+                    &[REMOVE_FROM_ALLOW_LIST, RemoveMode::Deactivate as u8],
+                    vec![
+                        AccountMeta::new_readonly(config, false),
+                        AccountMeta::new(record, false),
+                        AccountMeta::new_readonly(authority.pubkey(), true),
+                    ],
+                ),
+            ));
+        }
+        let successor = Keypair::new();
+        let pending = pending_authority_address(&mint, &gate).address();
+        admin.label(pending, "pending authority");
+        operations.push((
+            "update_authority",
+            Instruction::new_with_bytes(
+                gate,
+                &[UPDATE_AUTHORITY],
+                vec![
+                    AccountMeta::new_readonly(config, false),
+                    AccountMeta::new_readonly(authority.pubkey(), true),
+                    AccountMeta::new_readonly(successor.pubkey(), false),
+                    AccountMeta::new(pending, false),
+                    AccountMeta::new(payer.pubkey(), true),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            ),
+        ));
+        operations.push((
+            "accept_authority",
+            Instruction::new_with_bytes(
+                gate,
+                &[ACCEPT_AUTHORITY],
+                vec![
+                    AccountMeta::new(config, false),
+                    AccountMeta::new(pending, false),
+                    AccountMeta::new_readonly(successor.pubkey(), true),
+                    AccountMeta::new(payer.pubkey(), false),
+                ],
+            ),
+        ));
+
+        // Only declared locks of landed transactions count: the runtime
+        // rejects any write to an account the transaction marked read-only
+        for (name, instruction) in operations {
+            let mut signers = vec![&payer];
+            match name {
+                "can_thaw" => {}
+                "accept_authority" => signers.push(&successor),
+                _ => signers.push(&authority),
+            }
+            let transaction = Transaction::new_signed_with_payer(
+                &[instruction],
+                Some(&payer.pubkey()),
+                &signers,
+                blockhash,
+            );
+            let analyzer = if name == "can_thaw" {
+                &mut checks
+            } else {
+                &mut admin
+            };
+            analyzer.record_transaction(name, &transaction);
+            landed.push((name, banks_client.process_transaction(transaction).await));
+        }
+    }
+
+    // Assertion 1: Every recorded operation landed
+    assertions += 1;
+    if let Some((name, Err(e))) = landed.iter().find(|(_, result)| result.is_err()) {
+        return TestResultReport::failure(test_name, format!("{} failed: {}", name, e));
+    }
+
+    // Assertion 2: Thaw checks write-lock nothing beyond their fee payer
+    assertions += 1;
+    if let Some(locks) = checks.operations().iter().find(|l| !l.writable.is_empty()) {
+        return TestResultReport::failure(
+            test_name,
+            format!("Thaw check write-locks {:?}", locks.writable),
+        );
+    }
+
+    // Assertion 3: Admin operations only write per-mint or per-user state,
+    // each touched at most twice (config: initialize and accept_authority;
+    // pending authority: update_authority and accept_authority; record: add
+    // and remove)
+    assertions += 1;
+    let admin = admin.with_max_writers(2);
+    let hot = admin.hot_accounts();
+    if !hot.is_empty() {
+        return TestResultReport::failure(
+            test_name,
+            format!("Hot accounts found:\n{}", admin.to_markdown()),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 2: Example gates only read accounts during permissionless checks
+#[test]
+fn test_example_gates_have_no_hot_accounts() {
+    let report = run_example_gates_contention_test();
+    assert!(
+        report.passed,
+        "Example gates contention test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_example_gates_contention_test() -> TestResultReport {
+    let test_name = "Example Gates Write-Lock Contention";
+    let mut assertions = 0;
+
+    // The example processors take every account read-only, so their
+    // extra-account-metas resolve to read-only list records
+    let (allow_gate, block_gate) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut analyzer = ContentionAnalyzer::new();
+    for _ in 0..MINTS {
+        let mint = Pubkey::new_unique();
+        for _ in 0..USERS_PER_MINT {
+            let user = Pubkey::new_unique();
+            let checks = [
+                (
+                    "allow_list::can_thaw",
+                    gate_check(
+                        allow_gate,
+                        test_data::THAW_DISCRIMINATOR,
+                        test_data::THAW_EXTRA_ACCOUNT_METAS_SEED,
+                        test_data::ALLOW_LIST_SEED,
+                        &mint,
+                        &user,
+                    ),
+                ),
+                (
+                    "block_list::can_thaw",
+                    gate_check(
+                        block_gate,
+                        test_data::THAW_DISCRIMINATOR,
+                        test_data::THAW_EXTRA_ACCOUNT_METAS_SEED,
+                        test_data::BLOCK_LIST_SEED,
+                        &mint,
+                        &user,
+                    ),
+                ),
+                (
+                    "block_list::can_freeze",
+                    gate_check(
+                        block_gate,
+                        test_data::FREEZE_DISCRIMINATOR,
+                        test_data::FREEZE_EXTRA_ACCOUNT_METAS_SEED,
+                        test_data::BLOCK_LIST_SEED,
+                        &mint,
+                        &user,
+                    ),
+                ),
+            ];
+            for (name, instruction) in checks {
+                analyzer.record_instructions(name, &[instruction]);
+            }
+        }
+    }
+
+    // Assertion 1: Every check was recorded
+    assertions += 1;
+    if analyzer.operations().len() != MINTS * USERS_PER_MINT * 3 {
+        return TestResultReport::failure(
+            test_name,
+            "Not every gate check was recorded".to_string(),
+        );
+    }
+
+    // Assertion 2: No account is written by more than one check
+    assertions += 1;
+    if !analyzer.hot_accounts().is_empty() {
+        return TestResultReport::failure(
+            test_name,
+            format!("Hot accounts found:\n{}", analyzer.to_markdown()),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Test 3: A gate writing a global counter on every check is flagged
+#[test]
+fn test_global_counter_flagged_as_hot() {
+    let report = run_global_counter_test();
+    assert!(
+        report.passed,
+        "Global counter test failed: {:?}",
+        report.error
+    );
+}
+
+fn run_global_counter_test() -> TestResultReport {
+    let test_name = "Global Counter Flagged As Hot";
+    let mut assertions = 0;
+
+    let gate = Pubkey::new_unique();
+    let (counter, _) = Pubkey::find_program_address(&[b"counter"], &gate);
+    let sponsor = Pubkey::new_unique();
+    let check_count = MINTS * USERS_PER_MINT;
+
+    let counting_checks: Vec<Instruction> = (0..check_count)
+        .map(|_| {
+            let user = Pubkey::new_unique();
+            let mut check = gate_check(
+                gate,
+                test_data::THAW_DISCRIMINATOR,
+                test_data::THAW_EXTRA_ACCOUNT_METAS_SEED,
+                test_data::ALLOW_LIST_SEED,
+                &Pubkey::new_unique(),
+                &user,
+            );
+            check.accounts.push(AccountMeta::new(counter, false));
+            check.accounts.push(AccountMeta::new(sponsor, true));
+            check
+        })
+        .collect();
+
+    let mut analyzer = ContentionAnalyzer::new();
+    analyzer.label(counter, "thaw counter");
+    for check in &counting_checks {
+        analyzer.record_instructions("counting::can_thaw", std::slice::from_ref(check));
+    }
+
+    // Assertion 1: The counter is the only hot account, written by every check
+    assertions += 1;
+    let hot = analyzer.hot_accounts();
+    match hot.as_slice() {
+        [account] if account.address == counter && account.write_count == check_count => {}
+        _ => {
+            return TestResultReport::failure(
+                test_name,
+                format!("Expected only the counter to be hot, got {:?}", hot),
+            )
+        }
+    }
+
+    // Assertion 2: The report names the hot account
+    assertions += 1;
+    if !analyzer.to_markdown().contains("thaw counter") {
+        return TestResultReport::failure(
+            test_name,
+            "Contention report does not label the counter".to_string(),
+        );
+    }
+
+    // Assertion 3: Counting fee payers surfaces the shared sponsor too
+    assertions += 1;
+    let mut with_payers = ContentionAnalyzer::new().include_fee_payers();
+    for check in &counting_checks {
+        with_payers.record_instructions("counting::can_thaw", std::slice::from_ref(check));
+    }
+    if with_payers.hot_accounts().len() != 2 {
+        return TestResultReport::failure(
+            test_name,
+            "Shared sponsor should be flagged when fee payers are counted".to_string(),
+        );
+    }
+
+    // Assertion 4: Ignored accounts and raised thresholds suppress flags
+    assertions += 1;
+    with_payers.ignore(sponsor);
+    let tolerant = analyzer.clone().with_max_writers(check_count);
+    if with_payers.hot_accounts().len() != 1 || !tolerant.hot_accounts().is_empty() {
+        return TestResultReport::failure(
+            test_name,
+            "Ignore list or writer threshold not applied".to_string(),
+        );
+    }
+
+    TestResultReport::success(test_name, assertions)
+}
+
+/// Generate write-lock contention report
+#[tokio::test]
+async fn generate_write_lock_contention_report() {
+    let results = vec![
+        run_production_gate_contention_test().await,
+        run_example_gates_contention_test(),
+        run_global_counter_test(),
+    ];
+
+    if let Err(e) = reporting::generate_test_report(
+        &results,
+        "Write-Lock Contention Test Results",
+        "../../tests/reports/write_lock_contention.md",
+    ) {
+        panic!("Failed to generate write-lock contention report: {}", e);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    assert_eq!(failed, 0, "{} write-lock contention tests failed", failed);
+}